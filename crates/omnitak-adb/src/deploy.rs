@@ -0,0 +1,147 @@
+//! Pushing configuration and certificates to ATAK devices
+//!
+//! ATAK imports data packages from `atak/tools/datapackage` and preference
+//! files from `atak/config/prefs` when it starts. Files are pushed there and
+//! a running ATAK is then asked to import them straight away with a
+//! broadcast, so a device that misses the broadcast still picks them up on
+//! its next start.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tracing::{debug, info, warn};
+
+use crate::{parser, AdbDevice, AtakPackage};
+
+/// Directory ATAK imports data packages from
+pub const DATA_PACKAGE_DIR: &str = "/sdcard/atak/tools/datapackage";
+
+/// Directory ATAK imports preference files from
+pub const PREFERENCES_DIR: &str = "/sdcard/atak/config/prefs";
+
+/// Directory preference files reference certificates in, as `cert/<name>`
+pub const CERT_DIR: &str = "/sdcard/atak/cert";
+
+/// Broadcast asking a running ATAK to import a file
+pub const IMPORT_ACTION: &str = "com.atakmap.android.importfiles.IMPORT_FILE";
+
+/// What a pushed file is, which decides where it goes on the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeployKind {
+    /// Data package (.zip)
+    DataPackage,
+    /// Preference file (.pref)
+    Preferences,
+    /// Certificate referenced by a preference file
+    Certificate,
+}
+
+impl DeployKind {
+    /// Directory on the device the file is pushed to
+    pub fn remote_dir(&self) -> &'static str {
+        match self {
+            Self::DataPackage => DATA_PACKAGE_DIR,
+            Self::Preferences => PREFERENCES_DIR,
+            Self::Certificate => CERT_DIR,
+        }
+    }
+}
+
+/// File to push to a device
+#[derive(Debug, Clone)]
+pub struct DeployFile {
+    /// File name on the device
+    pub name: String,
+    pub kind: DeployKind,
+    pub data: Vec<u8>,
+}
+
+/// Result of deploying files to a device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeployOutcome {
+    /// Paths the files were pushed to
+    pub remote_paths: Vec<String>,
+    /// Whether the import broadcast was sent for every file; either way the
+    /// files are imported when ATAK next starts
+    pub imported: bool,
+}
+
+impl AdbDevice {
+    /// Push files to the device and ask ATAK to import them
+    pub fn deploy(&self, files: &[DeployFile], package: &AtakPackage) -> Result<DeployOutcome> {
+        info!("Deploying {} file(s) to device {}...", files.len(), self.serial);
+
+        let mut remote_paths = Vec::new();
+        for file in files {
+            let remote_dir = file.kind.remote_dir();
+            self.adb_client
+                .shell(&self.serial, &["mkdir", "-p", remote_dir])
+                .with_context(|| format!("Failed to create {}", remote_dir))?;
+
+            let remote_path = format!("{}/{}", remote_dir, file.name);
+            let temp_file = std::env::temp_dir().join(format!(
+                "omnitak-deploy-{}-{}",
+                self.serial.replace([':', '/', '.'], "_"),
+                file.name
+            ));
+            fs::write(&temp_file, &file.data).context("Failed to write temporary file")?;
+            let pushed = self.adb_client.push(&self.serial, &temp_file, &remote_path);
+            let _ = fs::remove_file(&temp_file);
+            pushed?;
+
+            info!("  ✓ Pushed: {}", remote_path);
+            remote_paths.push(remote_path);
+        }
+
+        // Certificates are read by ATAK when it imports the preference file
+        // that references them, so only the other files are announced
+        let mut imported = true;
+        for (file, remote_path) in files.iter().zip(&remote_paths) {
+            if file.kind == DeployKind::Certificate {
+                continue;
+            }
+            if !self.broadcast_import(remote_path, package) {
+                imported = false;
+            }
+        }
+        if !imported {
+            warn!(
+                "Could not notify ATAK on {}; files will be imported on its next start",
+                self.serial
+            );
+        }
+
+        Ok(DeployOutcome {
+            remote_paths,
+            imported,
+        })
+    }
+
+    /// Ask ATAK to import a pushed file, returning whether the broadcast was sent
+    fn broadcast_import(&self, remote_path: &str, package: &AtakPackage) -> bool {
+        let result = self.adb_client.shell(
+            &self.serial,
+            &[
+                "am",
+                "broadcast",
+                "-a",
+                IMPORT_ACTION,
+                "-p",
+                package.package_name(),
+                "--es",
+                "filepath",
+                remote_path,
+            ],
+        );
+
+        match result {
+            Ok(output) => parser::parse_broadcast_output(&output),
+            Err(e) => {
+                debug!("Import broadcast failed: {}", e);
+                false
+            }
+        }
+    }
+}
+
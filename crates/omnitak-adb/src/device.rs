@@ -8,6 +8,7 @@ use std::process::Command;
 use tracing::{debug, info, warn};
 
 use crate::{AdbClient, AtakPackage, CertificateFile, CertificateType, TakCertificateBundle};
+use omnitak_datapackage::PreferenceProfile;
 
 /// Information about a connected device
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +25,15 @@ pub struct DeviceInfo {
     pub transport_id: Option<String>,
 }
 
+impl DeviceInfo {
+    /// Whether the device is connected over Wi-Fi rather than USB
+    pub fn is_wireless(&self) -> bool {
+        // `adb connect` serials are host:port; devices ADB connected to by
+        // itself via mDNS use their `_adb-tls-connect` service name
+        self.serial.contains(':') || self.serial.contains("._adb-tls-connect.")
+    }
+}
+
 /// Represents a connected ADB device
 #[derive(Debug, Clone)]
 pub struct AdbDevice {
@@ -38,7 +48,7 @@ impl AdbDevice {
     pub fn pull_tak_certificates(
         &self,
         output_dir: &Path,
-        package: AtakPackage,
+        package: &AtakPackage,
     ) -> Result<TakCertificateBundle> {
         info!("Pulling TAK certificates from device {}...", self.serial);
 
@@ -89,26 +99,13 @@ impl AdbDevice {
         }
 
         // Pull server configuration
-        let server_config = self.pull_server_config(package)?;
-
-        Ok(TakCertificateBundle {
-            server_address: server_config.get("address").cloned(),
-            server_host: server_config.get("host").cloned(),
-            server_port: server_config
-                .get("port")
-                .and_then(|p| p.parse().ok())
-                .unwrap_or(8089),
-            certificates,
-            server_name: server_config
-                .get("name")
-                .cloned()
-                .unwrap_or_else(|| "tak-server-from-device".to_string()),
-            protocol: "tls".to_string(),
-        })
+        let preferences = self.pull_preferences(package);
+
+        Ok(TakCertificateBundle::new(certificates, preferences))
     }
 
     /// Get certificate paths to check
-    fn get_cert_paths(&self, package: AtakPackage) -> Vec<String> {
+    fn get_cert_paths(&self, package: &AtakPackage) -> Vec<String> {
         vec![
             "/sdcard/atak/cert".to_string(),
             "/sdcard/atak/certs".to_string(),
@@ -160,7 +157,7 @@ impl AdbDevice {
                     "find",
                     base_path,
                     "-name",
-                    "*.p12",
+                    "'*.p12'",
                     "-type",
                     "f",
                     "2>/dev/null",
@@ -196,57 +193,59 @@ impl AdbDevice {
         Ok(certificates)
     }
 
-    /// Pull TAK server configuration from device
-    fn pull_server_config(
-        &self,
-        package: AtakPackage,
-    ) -> Result<std::collections::HashMap<String, String>> {
+    /// Pull ATAK's connection and certificate preferences from the device
+    ///
+    /// Reading them needs root on most devices; an empty profile is
+    /// returned when none can be pulled.
+    fn pull_preferences(&self, package: &AtakPackage) -> PreferenceProfile {
         info!("Pulling TAK server configuration...");
 
-        let pref_paths = vec![
+        let package_name = package.package_name();
+        let pref_paths = [
+            format!("/data/data/{}/shared_prefs/cot_streams.xml", package_name),
             format!(
                 "/data/data/{}/shared_prefs/{}_preferences.xml",
-                package.package_name(),
-                package.package_name()
+                package_name, package_name
             ),
             format!(
                 "/data/data/{}/shared_prefs/com.atakmap.app_preferences.xml",
-                package.package_name()
+                package_name
             ),
         ];
 
-        for pref_path in pref_paths {
+        let mut files = Vec::new();
+        for (i, pref_path) in pref_paths.iter().enumerate() {
             debug!("Checking preferences: {}", pref_path);
 
-            let temp_file = std::env::temp_dir().join("atak_prefs.xml");
-
-            if self
-                .adb_client
-                .pull(&self.serial, &pref_path, &temp_file)
-                .is_ok()
-            {
+            let temp_file = std::env::temp_dir().join(format!(
+                "omnitak-prefs-{}-{}.xml",
+                self.serial.replace([':', '/', '.'], "_"),
+                i
+            ));
+            if self.adb_client.pull(&self.serial, pref_path, &temp_file).is_ok() {
                 info!("  ✓ Pulled preferences from: {}", pref_path);
-
-                // Parse preferences
-                if let Ok(content) = fs::read_to_string(&temp_file) {
-                    let config = crate::parser::parse_tak_preferences(&content);
-
-                    // Clean up
-                    let _ = fs::remove_file(temp_file);
-
-                    return Ok(config);
+                match fs::read_to_string(&temp_file) {
+                    Ok(content) => files.push(content),
+                    Err(e) => warn!("Failed to read {}: {}", pref_path, e),
                 }
+                let _ = fs::remove_file(&temp_file);
             }
         }
 
-        warn!("Could not access preferences (may require root access)");
-
-        // Return default config
-        let mut config = std::collections::HashMap::new();
-        config.insert("name".to_string(), "tak-server-from-device".to_string());
-        config.insert("host".to_string(), "takserver.example.com".to_string());
-        config.insert("port".to_string(), "8089".to_string());
+        if files.is_empty() {
+            warn!("Could not access preferences (may require root access)");
+            return PreferenceProfile::default();
+        }
 
-        Ok(config)
+        match PreferenceProfile::parse_files(files.iter().map(String::as_str)) {
+            Ok(profile) => {
+                info!("Found {} server connection(s)", profile.streams.len());
+                profile
+            }
+            Err(e) => {
+                warn!("Failed to parse preferences: {}", e);
+                PreferenceProfile::default()
+            }
+        }
     }
 }
@@ -2,19 +2,26 @@
 //!
 //! This crate provides Android Debug Bridge (ADB) integration for pulling
 //! TAK server certificates and configuration from connected Android devices
-//! running ATAK.
+//! running ATAK, and for pushing configuration back to them.
 
 use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use omnitak_datapackage::{PreferenceProfile, StreamProtocol};
+
+pub mod deploy;
 pub mod device;
 pub mod monitor;
 pub mod parser;
 
+pub use deploy::{DeployFile, DeployKind, DeployOutcome};
 pub use device::{AdbDevice, DeviceInfo};
-pub use monitor::DeviceMonitor;
+pub use monitor::{DeviceEvent, DeviceMonitor, DeviceMonitorBuilder};
+
+/// Port `adb tcpip` puts a device's ADB daemon on
+pub const DEFAULT_TCPIP_PORT: u16 = 5555;
 
 /// ADB client for interacting with Android devices
 #[derive(Debug, Clone)]
@@ -114,12 +121,13 @@ impl AdbClient {
     }
 
     /// Execute ADB shell command
+    ///
+    /// Each element of `command` is passed as one argument; they are quoted
+    /// for the device shell, so none is interpreted by it.
     pub fn shell(&self, device: &str, command: &[&str]) -> Result<String> {
-        let mut args = vec!["-s", device, "shell"];
-        args.extend_from_slice(command);
-
         let output = Command::new(&self.adb_path)
-            .args(&args)
+            .args(["-s", device, "shell"])
+            .args(command.iter().map(|arg| shell_quote(arg)))
             .output()
             .context("Failed to execute ADB shell command")?;
 
@@ -151,10 +159,102 @@ impl AdbClient {
         Ok(())
     }
 
+    /// Push file to device
+    pub fn push(&self, device: &str, local_path: &Path, remote_path: &str) -> Result<()> {
+        let output = Command::new(&self.adb_path)
+            .args([
+                "-s",
+                device,
+                "push",
+                local_path.to_str().unwrap(),
+                remote_path,
+            ])
+            .output()
+            .context("Failed to push file")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("Failed to push {}: {}", remote_path, stderr.trim()));
+        }
+
+        Ok(())
+    }
+
+    /// Pair with a device over Wi-Fi
+    ///
+    /// `address` and `code` are shown on the device under Developer options >
+    /// Wireless debugging > Pair device with pairing code. The pairing port
+    /// differs from the port used to connect afterwards.
+    pub fn pair(&self, address: &str, code: &str) -> Result<()> {
+        let address = parser::parse_network_address(address, None)
+            .ok_or_else(|| anyhow!("Invalid pairing address: {}", address))?;
+
+        let output = Command::new(&self.adb_path)
+            .args(["pair", &address, code])
+            .output()
+            .context("Failed to execute ADB pair")?;
+
+        // adb exits 0 on some failures, so the output decides
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        parser::parse_pair_output(&format!("{}{}", stdout, stderr))
+    }
+
+    /// Connect to a device over Wi-Fi, returning its serial
+    ///
+    /// The port defaults to 5555, the `adb tcpip` port; devices paired over
+    /// Wireless debugging show theirs under Wireless debugging > IP address
+    /// & Port.
+    pub fn connect(&self, address: &str) -> Result<String> {
+        let address = parser::parse_network_address(address, Some(DEFAULT_TCPIP_PORT))
+            .ok_or_else(|| anyhow!("Invalid device address: {}", address))?;
+
+        let output = Command::new(&self.adb_path)
+            .args(["connect", &address])
+            .output()
+            .context("Failed to execute ADB connect")?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        parser::parse_connect_output(&format!("{}{}", stdout, stderr))?;
+
+        Ok(address)
+    }
+
+    /// Disconnect a device connected over Wi-Fi
+    pub fn disconnect(&self, address: &str) -> Result<()> {
+        let output = Command::new(&self.adb_path)
+            .args(["disconnect", address])
+            .output()
+            .context("Failed to execute ADB disconnect")?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() || stdout.contains("error") {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!(
+                "Failed to disconnect {}: {}",
+                address,
+                format!("{}{}", stdout, stderr).trim()
+            ));
+        }
+
+        Ok(())
+    }
+
     /// Check if a path exists on device
     pub fn path_exists(&self, device: &str, path: &str) -> bool {
         let result = Command::new(&self.adb_path)
-            .args(["-s", device, "shell", "test", "-e", path, "&&", "echo", "exists"])
+            .args([
+                "-s",
+                device,
+                "shell",
+                "test",
+                "-e",
+                &shell_quote(path),
+                "&&",
+                "echo",
+                "exists",
+            ])
             .output();
 
         if let Ok(output) = result {
@@ -181,6 +281,36 @@ pub struct TakCertificateBundle {
     pub server_name: String,
     /// Protocol (tcp, tls, udp, ws)
     pub protocol: String,
+    /// Connections and certificate settings from the device's ATAK preferences
+    pub preferences: PreferenceProfile,
+}
+
+impl TakCertificateBundle {
+    /// Bundle of pulled certificates, pointing at the primary connection
+    /// of the device's preferences
+    pub fn new(certificates: Vec<CertificateFile>, preferences: PreferenceProfile) -> Self {
+        let stream = preferences.primary_stream();
+        let server_port = stream.map_or(8089, |s| s.port);
+        let server_host = stream.map(|s| s.host.clone());
+
+        Self {
+            server_address: server_host.as_ref().map(|h| format!("{}:{}", h, server_port)),
+            server_name: stream
+                .map(|s| s.description.clone())
+                .unwrap_or_else(|| "tak-server-from-device".to_string()),
+            protocol: match stream.map(|s| s.protocol) {
+                Some(StreamProtocol::Tcp) => "tcp",
+                Some(StreamProtocol::Udp) => "udp",
+                Some(StreamProtocol::Quic) => "quic",
+                Some(StreamProtocol::Ssl) | None => "tls",
+            }
+            .to_string(),
+            server_host,
+            server_port,
+            certificates,
+            preferences,
+        }
+    }
 }
 
 /// Certificate file information
@@ -235,8 +365,16 @@ impl CertificateType {
     }
 }
 
+/// Quote an argument for the device shell
+///
+/// `adb shell` joins its arguments into one command line for `sh` on the
+/// device, so anything not quoted is open to expansion and injection.
+pub fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
 /// ATAK package variants
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AtakPackage {
     /// Civilian ATAK
     Civilian,
@@ -245,10 +383,27 @@ pub enum AtakPackage {
     /// WinTAK
     WinTak,
     /// Custom package
-    Custom(&'static str),
+    Custom(String),
 }
 
 impl AtakPackage {
+    /// Package from its name, which must be a valid Android package name
+    /// (letters, digits, underscores and dots)
+    pub fn from_name(name: &str) -> Result<Self> {
+        let known = Self::all().into_iter().find(|p| p.package_name() == name);
+        if let Some(package) = known {
+            return Ok(package);
+        }
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+        if !valid {
+            return Err(anyhow!("Invalid package name: {:?}", name));
+        }
+        Ok(Self::Custom(name.to_string()))
+    }
+
     /// Get package name
     pub fn package_name(&self) -> &str {
         match self {
@@ -281,5 +436,44 @@ mod tests {
     fn test_atak_package_names() {
         assert_eq!(AtakPackage::Civilian.package_name(), "com.atakmap.app.civ");
         assert_eq!(AtakPackage::Military.package_name(), "com.atakmap.app.mil");
+        assert_eq!(
+            AtakPackage::from_name("com.atakmap.app.civ").unwrap(),
+            AtakPackage::Civilian
+        );
+        assert_eq!(
+            AtakPackage::from_name("org.example.tak_fork").unwrap(),
+            AtakPackage::Custom("org.example.tak_fork".to_string())
+        );
+        assert!(AtakPackage::from_name("com.x; rm -rf /sdcard").is_err());
+        assert!(AtakPackage::from_name("").is_err());
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/sdcard/atak"), "'/sdcard/atak'");
+        assert_eq!(shell_quote("a'; reboot"), r"'a'\''; reboot'");
+    }
+
+    #[test]
+    fn test_bundle_from_preferences() {
+        let preferences = PreferenceProfile::parse(
+            r#"<map>
+    <string name="connectString0">10.0.0.5:8087:tcp</string>
+    <boolean name="enabled0" value="false" />
+    <string name="description1">Ops</string>
+    <string name="connectString1">tak.example.com:8443:ssl</string>
+</map>"#,
+        )
+        .unwrap();
+
+        let bundle = TakCertificateBundle::new(Vec::new(), preferences);
+        assert_eq!(bundle.server_address.as_deref(), Some("tak.example.com:8443"));
+        assert_eq!(bundle.server_name, "Ops");
+        assert_eq!(bundle.protocol, "tls");
+
+        let empty = TakCertificateBundle::new(Vec::new(), PreferenceProfile::default());
+        assert_eq!(empty.server_address, None);
+        assert_eq!(empty.server_port, 8089);
+        assert_eq!(empty.server_name, "tak-server-from-device");
     }
 }
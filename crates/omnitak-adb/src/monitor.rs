@@ -1,20 +1,23 @@
 //! USB device monitoring service
 
 use anyhow::Result;
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{broadcast, RwLock};
 use tokio::time::{interval, Duration};
 use tracing::{debug, info, warn};
 
-use crate::AdbClient;
+use crate::{AdbClient, DeviceInfo};
+
+/// Events buffered for slow subscribers before they start missing some
+const EVENT_CAPACITY: usize = 64;
 
 /// Device event types
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub enum DeviceEvent {
-    /// Device connected
-    Connected(String),
-    /// Device disconnected
+    /// Device attached and authorized
+    Connected(DeviceInfo),
+    /// Device detached, by serial
     Disconnected(String),
 }
 
@@ -22,12 +25,10 @@ pub enum DeviceEvent {
 pub struct DeviceMonitor {
     /// ADB client
     adb_client: AdbClient,
-    /// Currently tracked device serials
-    tracked_devices: Arc<RwLock<HashSet<String>>>,
+    /// Currently tracked devices by serial
+    tracked_devices: Arc<RwLock<HashMap<String, DeviceInfo>>>,
     /// Event sender
-    event_tx: mpsc::UnboundedSender<DeviceEvent>,
-    /// Event receiver
-    event_rx: Arc<RwLock<mpsc::UnboundedReceiver<DeviceEvent>>>,
+    event_tx: broadcast::Sender<DeviceEvent>,
     /// Poll interval
     poll_interval: Duration,
 }
@@ -35,13 +36,12 @@ pub struct DeviceMonitor {
 impl DeviceMonitor {
     /// Create a new device monitor
     pub fn new(poll_interval: Duration) -> Self {
-        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let (event_tx, _) = broadcast::channel(EVENT_CAPACITY);
 
         Self {
             adb_client: AdbClient::new(),
-            tracked_devices: Arc::new(RwLock::new(HashSet::new())),
+            tracked_devices: Arc::new(RwLock::new(HashMap::new())),
             event_tx,
-            event_rx: Arc::new(RwLock::new(event_rx)),
             poll_interval,
         }
     }
@@ -66,20 +66,28 @@ impl DeviceMonitor {
 
     /// Check for device changes
     async fn check_devices(&self) -> Result<()> {
-        // Check if ADB is available
-        if !self.adb_client.is_available() {
-            debug!("ADB not available, skipping device check");
-            return Ok(());
-        }
+        // ADB is run as a blocking child process
+        let adb_client = self.adb_client.clone();
+        let listed = tokio::task::spawn_blocking(move || {
+            if !adb_client.is_available() {
+                return None;
+            }
+            Some(adb_client.list_devices())
+        })
+        .await?;
 
         // Get current devices
-        let current_devices = match self.adb_client.list_devices() {
-            Ok(devices) => devices
+        let current_devices = match listed {
+            None => {
+                debug!("ADB not available, skipping device check");
+                return Ok(());
+            }
+            Some(Ok(devices)) => devices
                 .into_iter()
                 .filter(|d| d.state == "device")
-                .map(|d| d.serial)
-                .collect::<HashSet<_>>(),
-            Err(e) => {
+                .map(|d| (d.serial.clone(), d))
+                .collect::<HashMap<_, _>>(),
+            Some(Err(e)) => {
                 warn!("Failed to list devices: {}", e);
                 return Ok(());
             }
@@ -88,15 +96,19 @@ impl DeviceMonitor {
         let mut tracked = self.tracked_devices.write().await;
 
         // Find new devices (connected)
-        for serial in current_devices.difference(&*tracked) {
-            info!("Device connected: {}", serial);
-            let _ = self.event_tx.send(DeviceEvent::Connected(serial.clone()));
+        for (serial, device) in &current_devices {
+            if !tracked.contains_key(serial) {
+                info!("Device connected: {}", serial);
+                let _ = self.event_tx.send(DeviceEvent::Connected(device.clone()));
+            }
         }
 
         // Find removed devices (disconnected)
-        for serial in tracked.difference(&current_devices) {
-            info!("Device disconnected: {}", serial);
-            let _ = self.event_tx.send(DeviceEvent::Disconnected(serial.clone()));
+        for serial in tracked.keys() {
+            if !current_devices.contains_key(serial) {
+                info!("Device disconnected: {}", serial);
+                let _ = self.event_tx.send(DeviceEvent::Disconnected(serial.clone()));
+            }
         }
 
         // Update tracked devices
@@ -106,27 +118,21 @@ impl DeviceMonitor {
     }
 
     /// Get an event receiver
-    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<DeviceEvent> {
-        let (tx, rx) = mpsc::unbounded_channel();
-
-        // Clone events to new subscriber
-        let event_tx = self.event_tx.clone();
-        tokio::spawn(async move {
-            // Forward events to new subscriber
-            // Note: This is a simple implementation; for production, consider using tokio::sync::broadcast
-        });
-
-        rx
+    ///
+    /// Only events after the call are received; devices already attached
+    /// are listed by [`Self::get_devices`].
+    pub fn subscribe(&self) -> broadcast::Receiver<DeviceEvent> {
+        self.event_tx.subscribe()
     }
 
     /// Get currently connected devices
-    pub async fn get_devices(&self) -> Vec<String> {
-        self.tracked_devices.read().await.iter().cloned().collect()
+    pub async fn get_devices(&self) -> Vec<DeviceInfo> {
+        self.tracked_devices.read().await.values().cloned().collect()
     }
 
     /// Check if a specific device is connected
     pub async fn is_device_connected(&self, serial: &str) -> bool {
-        self.tracked_devices.read().await.contains(serial)
+        self.tracked_devices.read().await.contains_key(serial)
     }
 }
 
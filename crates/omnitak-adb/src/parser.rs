@@ -1,8 +1,6 @@
 //! Parsing utilities for ADB output and ATAK configuration
 
 use anyhow::Result;
-use std::collections::HashMap;
-use tracing::debug;
 
 use crate::DeviceInfo;
 
@@ -54,91 +52,59 @@ pub fn parse_device_list(output: &str) -> Result<Vec<DeviceInfo>> {
     Ok(devices)
 }
 
-/// Parse ATAK preferences XML for TAK server configuration
-pub fn parse_tak_preferences(content: &str) -> HashMap<String, String> {
-    let mut config = HashMap::new();
-
-    debug!("Parsing TAK preferences XML...");
-
-    for line in content.lines() {
-        // Look for server address
-        if line.contains("caLocation") || line.contains("serverConnectString") {
-            if let Some(value) = extract_xml_string_value(line) {
-                debug!("Found server reference: {}", value);
-
-                // Parse server:port format
-                if let Some((host, port)) = parse_server_address(&value) {
-                    config.insert("host".to_string(), host);
-                    config.insert("port".to_string(), port.to_string());
-                    config.insert("address".to_string(), format!("{}:{}", config["host"], port));
-                }
-            }
-        }
+/// Normalize a device address to `host:port`, using `default_port` when
+/// none is given. `None` if the address is malformed or needs a port
+pub fn parse_network_address(address: &str, default_port: Option<u16>) -> Option<String> {
+    let address = address.trim();
+    if address.is_empty() || address.contains(char::is_whitespace) {
+        return None;
+    }
 
-        // Look for certificate password
-        if line.contains("certificatePassword") || line.contains("clientPassword") {
-            if let Some(value) = extract_xml_string_value(line) {
-                config.insert("cert_password".to_string(), value);
-            }
+    match address.rsplit_once(':') {
+        // Bare IPv6 address
+        Some((host, _)) if host.contains(':') && !host.ends_with(']') => {
+            default_port.map(|port| format!("[{}]:{}", address, port))
         }
-
-        // Look for server description/name
-        if line.contains("serverDescription") || line.contains("connectString") {
-            if let Some(value) = extract_xml_string_value(line) {
-                if !value.contains(':') {
-                    // Not an address, probably a name
-                    config.insert("name".to_string(), value);
-                }
-            }
+        Some((host, port)) => {
+            let port: u16 = port.parse().ok().filter(|&p| p > 0)?;
+            (!host.is_empty()).then(|| format!("{}:{}", host, port))
         }
+        None => default_port.map(|port| format!("{}:{}", address, port)),
     }
+}
 
-    // Set default name if not found
-    if !config.contains_key("name") {
-        config.insert(
-            "name".to_string(),
-            "tak-server-from-device".to_string(),
-        );
+/// Check the output of `adb pair`
+pub fn parse_pair_output(output: &str) -> Result<()> {
+    if output.contains("Successfully paired") {
+        return Ok(());
     }
 
-    config
+    let reason = output.trim().trim_start_matches("Failed:").trim();
+    if reason.is_empty() {
+        anyhow::bail!("Pairing failed");
+    }
+    anyhow::bail!("Pairing failed: {}", reason)
 }
 
-/// Extract string value from XML line
-fn extract_xml_string_value(line: &str) -> Option<String> {
-    // Look for: <string name="key">value</string>
-    if let Some(start) = line.find('>') {
-        if let Some(end) = line.rfind('<') {
-            if start < end {
-                let value = &line[start + 1..end];
-                if !value.is_empty() {
-                    return Some(value.to_string());
-                }
-            }
-        }
+/// Check the output of `adb connect`
+pub fn parse_connect_output(output: &str) -> Result<()> {
+    let output = output.trim();
+    // "connected to ..." and "already connected to ..."
+    if output.contains("connected to") && !output.contains("failed") && !output.contains("cannot") {
+        return Ok(());
     }
-    None
-}
 
-/// Parse server address in format "host:port" or just "host"
-fn parse_server_address(addr: &str) -> Option<(String, u16)> {
-    let addr = addr.trim();
-
-    // Remove any protocol prefixes
-    let addr = addr
-        .trim_start_matches("ssl://")
-        .trim_start_matches("tcp://")
-        .trim_start_matches("https://")
-        .trim_start_matches("http://");
-
-    if let Some(colon) = addr.rfind(':') {
-        let host = addr[..colon].to_string();
-        let port = addr[colon + 1..].parse().unwrap_or(8089);
-        Some((host, port))
-    } else {
-        // Default port 8089 for TLS
-        Some((addr.to_string(), 8089))
+    if output.is_empty() {
+        anyhow::bail!("Connection failed");
     }
+    anyhow::bail!("Connection failed: {}", output)
+}
+
+/// Whether `am broadcast` sent the intent
+pub fn parse_broadcast_output(output: &str) -> bool {
+    output
+        .lines()
+        .any(|line| line.trim().starts_with("Broadcast completed"))
 }
 
 #[cfg(test)]
@@ -146,35 +112,64 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_parse_server_address() {
+    fn test_parse_device_list() {
+        let output = r#"List of devices attached
+abc123    device product:model model:Phone transport_id:1
+"#;
+        let devices = parse_device_list(output).unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].serial, "abc123");
+        assert_eq!(devices[0].state, "device");
+    }
+
+    #[test]
+    fn test_parse_network_address() {
         assert_eq!(
-            parse_server_address("example.com:8089"),
-            Some(("example.com".to_string(), 8089))
+            parse_network_address("192.168.1.20", Some(5555)),
+            Some("192.168.1.20:5555".to_string())
         );
         assert_eq!(
-            parse_server_address("ssl://example.com:8089"),
-            Some(("example.com".to_string(), 8089))
+            parse_network_address(" 192.168.1.20:37123 ", None),
+            Some("192.168.1.20:37123".to_string())
         );
         assert_eq!(
-            parse_server_address("example.com"),
-            Some(("example.com".to_string(), 8089))
+            parse_network_address("fe80::1", Some(5555)),
+            Some("[fe80::1]:5555".to_string())
         );
+        assert_eq!(parse_network_address("192.168.1.20", None), None);
+        assert_eq!(parse_network_address("192.168.1.20:abc", None), None);
+        assert_eq!(parse_network_address(":5555", None), None);
     }
 
     #[test]
-    fn test_extract_xml_value() {
-        let xml = r#"<string name="test">value123</string>"#;
-        assert_eq!(extract_xml_string_value(xml), Some("value123".to_string()));
+    fn test_parse_wireless_output() {
+        assert!(parse_pair_output(
+            "Successfully paired to 192.168.1.20:37123 [guid=adb-R58N123-abcDEF]"
+        )
+        .is_ok());
+        let err =
+            parse_pair_output("Failed: Wrong password or connection was dropped.").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Pairing failed: Wrong password or connection was dropped."
+        );
+
+        assert!(parse_connect_output("connected to 192.168.1.20:5555").is_ok());
+        assert!(parse_connect_output("already connected to 192.168.1.20:5555").is_ok());
+        assert!(parse_connect_output(
+            "failed to connect to '192.168.1.20:5555': Connection refused"
+        )
+        .is_err());
+        assert!(
+            parse_connect_output("cannot connect to 192.168.1.20:5555: No route to host").is_err()
+        );
     }
 
     #[test]
-    fn test_parse_device_list() {
-        let output = r#"List of devices attached
-abc123    device product:model model:Phone transport_id:1
-"#;
-        let devices = parse_device_list(output).unwrap();
-        assert_eq!(devices.len(), 1);
-        assert_eq!(devices[0].serial, "abc123");
-        assert_eq!(devices[0].state, "device");
+    fn test_parse_broadcast_output() {
+        assert!(parse_broadcast_output(
+            "Broadcasting: Intent { act=com.atakmap.android.importfiles.IMPORT_FILE }\nBroadcast completed: result=0\n"
+        ));
+        assert!(!parse_broadcast_output("Error: Unknown option: --es\n"));
     }
 }
@@ -0,0 +1,542 @@
+//! Generates the client from `openapi.json`
+//!
+//! Emits `types.rs` with a Rust type for every component schema and
+//! `client.rs` with one `Client` method per operation. Only the subset of
+//! OpenAPI the server's utoipa document uses is understood; anything else
+//! becomes `serde_json::Value`.
+
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+const SPEC: &str = "openapi.json";
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use",
+    "where", "while", "yield",
+];
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", SPEC);
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let spec: Value =
+        serde_json::from_str(&std::fs::read_to_string(SPEC).expect("openapi.json is readable"))
+            .expect("openapi.json is valid JSON");
+
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    let out_dir = Path::new(&out_dir);
+    std::fs::write(out_dir.join("types.rs"), types(&spec)).unwrap();
+    std::fs::write(out_dir.join("client.rs"), client(&spec)).unwrap();
+}
+
+// ============================================================================
+// Types
+// ============================================================================
+
+fn types(spec: &Value) -> String {
+    let mut out = String::new();
+    let schemas = spec["components"]["schemas"]
+        .as_object()
+        .cloned()
+        .unwrap_or_default();
+    for (name, schema) in &schemas {
+        out.push_str(&type_def(name, schema));
+        out.push('\n');
+    }
+    out
+}
+
+fn type_def(name: &str, schema: &Value) -> String {
+    let mut out = doc(schema["description"].as_str(), "");
+
+    if let Some(values) = schema["enum"].as_array() {
+        out.push_str(
+            "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]\n",
+        );
+        writeln!(out, "pub enum {} {{", name).unwrap();
+        for value in values.iter().filter_map(Value::as_str) {
+            writeln!(out, "    #[serde(rename = {:?})]", value).unwrap();
+            writeln!(out, "    {},", camel(value)).unwrap();
+        }
+        out.push_str("}\n");
+        return out;
+    }
+
+    if let Some(variants) = schema["oneOf"].as_array() {
+        if let Some(tag) = tag_property(variants) {
+            out.push_str(
+                "#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]\n",
+            );
+            writeln!(out, "#[serde(tag = {:?})]", tag).unwrap();
+            writeln!(out, "pub enum {} {{", name).unwrap();
+            for variant in variants {
+                out.push_str(&tagged_variant(variant, &tag));
+            }
+            out.push_str("}\n");
+            return out;
+        }
+    }
+
+    if let Some(parts) = schema["allOf"].as_array() {
+        out.push_str("#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]\n");
+        writeln!(out, "pub struct {} {{", name).unwrap();
+        for part in parts {
+            if let Some(reference) = ref_name(part) {
+                writeln!(out, "    #[serde(flatten)]").unwrap();
+                writeln!(out, "    pub {}: {},", snake(reference), reference).unwrap();
+            } else {
+                out.push_str(&fields(part, "    pub ", None));
+            }
+        }
+        out.push_str("}\n");
+        return out;
+    }
+
+    if schema["properties"].is_object() {
+        // Every field is an `Option` when none are required
+        let derive_default = schema["required"].as_array().is_none_or(Vec::is_empty);
+        writeln!(
+            out,
+            "#[derive(Debug, Clone, PartialEq, {}serde::Serialize, serde::Deserialize)]",
+            if derive_default { "Default, " } else { "" }
+        )
+        .unwrap();
+        writeln!(out, "pub struct {} {{", name).unwrap();
+        out.push_str(&fields(schema, "    pub ", None));
+        out.push_str("}\n");
+        return out;
+    }
+
+    writeln!(out, "pub type {} = {};", name, rust_type(schema, "")).unwrap();
+    out
+}
+
+/// Property every variant requires and pins to a single string, as serde's
+/// internally tagged enums are documented
+fn tag_property(variants: &[Value]) -> Option<String> {
+    let first = variant_object(variants.first()?)?;
+    let candidates = first["properties"].as_object()?.keys().cloned();
+    candidates.into_iter().find(|key| {
+        variants.iter().all(|v| {
+            variant_object(v)
+                .and_then(|o| o["properties"][key.as_str()]["enum"].as_array().cloned())
+                .is_some_and(|values| values.len() == 1)
+        })
+    })
+}
+
+/// The inline object of a variant: itself, or the inline part of an `allOf`
+fn variant_object(variant: &Value) -> Option<&Value> {
+    match variant["allOf"].as_array() {
+        Some(parts) => parts.iter().find(|p| ref_name(p).is_none()),
+        None => variant["properties"].is_object().then_some(variant),
+    }
+}
+
+fn tagged_variant(variant: &Value, tag: &str) -> String {
+    let object = variant_object(variant).unwrap();
+    let value = object["properties"][tag]["enum"][0].as_str().unwrap();
+    let mut out = doc(variant["description"].as_str(), "    ");
+    writeln!(out, "    #[serde(rename = {:?})]", value).unwrap();
+
+    let wrapped = variant["allOf"]
+        .as_array()
+        .and_then(|parts| parts.iter().find_map(ref_name));
+    if let Some(reference) = wrapped {
+        writeln!(out, "    {}({}),", camel(value), reference).unwrap();
+    } else if object["properties"]
+        .as_object()
+        .is_some_and(|p| p.len() > 1)
+    {
+        writeln!(out, "    {} {{", camel(value)).unwrap();
+        out.push_str(&fields(object, "        ", Some(tag)));
+        out.push_str("    },\n");
+    } else {
+        writeln!(out, "    {},", camel(value)).unwrap();
+    }
+    out
+}
+
+/// Fields of an object, each line started with `lead` (indent and visibility)
+fn fields(object: &Value, lead: &str, skip: Option<&str>) -> String {
+    let indent = lead.trim_end_matches("pub ");
+    let empty = Map::new();
+    let properties = object["properties"].as_object().unwrap_or(&empty);
+    let required: Vec<&str> = object["required"]
+        .as_array()
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let mut out = String::new();
+    for (property, schema) in properties {
+        if Some(property.as_str()) == skip {
+            continue;
+        }
+        out.push_str(&doc(schema["description"].as_str(), indent));
+
+        let ident = field_ident(property);
+        let mut serde = Vec::new();
+        if ident.trim_start_matches("r#") != property {
+            serde.push(format!("rename = {:?}", property));
+        }
+
+        let mut ty = rust_type(schema, "");
+        if ty.starts_with("Option<") {
+            serde.push("default".to_string());
+            serde.push("skip_serializing_if = \"Option::is_none\"".to_string());
+        } else if !required.contains(&property.as_str()) {
+            ty = format!("Option<{}>", ty);
+            serde.push("default".to_string());
+            serde.push("skip_serializing_if = \"Option::is_none\"".to_string());
+        } else if ty.starts_with("Vec<")
+            || ty.starts_with("std::collections::HashMap<")
+            || ty == "serde_json::Value"
+        {
+            serde.push("default".to_string());
+        }
+
+        if !serde.is_empty() {
+            writeln!(out, "{}#[serde({})]", indent, serde.join(", ")).unwrap();
+        }
+        writeln!(out, "{}{}: {},", lead, ident, ty).unwrap();
+    }
+    out
+}
+
+/// Rust type of a schema, naming component types through `prefix`
+fn rust_type(schema: &Value, prefix: &str) -> String {
+    if let Some(reference) = ref_name(schema) {
+        return format!("{}{}", prefix, reference);
+    }
+
+    if let Some(variants) = schema["oneOf"].as_array() {
+        let (nulls, others): (Vec<&Value>, Vec<&Value>) =
+            variants.iter().partition(|v| v["type"] == "null");
+        return match (nulls.len(), others.as_slice()) {
+            (1, [inner]) => format!("Option<{}>", rust_type(inner, prefix)),
+            _ => "serde_json::Value".to_string(),
+        };
+    }
+
+    let (ty, nullable) = match &schema["type"] {
+        Value::String(ty) => (ty.as_str(), false),
+        Value::Array(types) => {
+            let types: Vec<&str> = types.iter().filter_map(Value::as_str).collect();
+            match types.as_slice() {
+                [ty, "null"] | ["null", ty] => (*ty, true),
+                _ => ("", false),
+            }
+        }
+        _ => ("", false),
+    };
+
+    let unsigned = schema["minimum"].as_f64().is_some_and(|m| m >= 0.0);
+    let base = match (ty, schema["format"].as_str()) {
+        ("string", Some("date-time")) => "chrono::DateTime<chrono::Utc>".to_string(),
+        ("string", Some("uuid")) => "uuid::Uuid".to_string(),
+        ("string", Some("binary")) => "Vec<u8>".to_string(),
+        ("string", _) => "String".to_string(),
+        ("integer", Some("int32")) if unsigned => "u32".to_string(),
+        ("integer", Some("int32")) => "i32".to_string(),
+        ("integer", _) if unsigned => "u64".to_string(),
+        ("integer", _) => "i64".to_string(),
+        ("number", Some("float")) => "f32".to_string(),
+        ("number", _) => "f64".to_string(),
+        ("boolean", _) => "bool".to_string(),
+        ("array", _) => format!("Vec<{}>", rust_type(&schema["items"], prefix)),
+        ("object", _) => {
+            let values = match &schema["additionalProperties"] {
+                Value::Object(values) => rust_type(&Value::Object(values.clone()), prefix),
+                _ => "serde_json::Value".to_string(),
+            };
+            format!("std::collections::HashMap<String, {}>", values)
+        }
+        _ => "serde_json::Value".to_string(),
+    };
+
+    if nullable {
+        format!("Option<{}>", base)
+    } else {
+        base
+    }
+}
+
+fn ref_name(schema: &Value) -> Option<&str> {
+    schema["$ref"]
+        .as_str()
+        .and_then(|r| r.strip_prefix("#/components/schemas/"))
+}
+
+// ============================================================================
+// Operations
+// ============================================================================
+
+/// How a request body or response is carried
+enum Body {
+    None,
+    Json(String),
+    Text,
+    Bytes(String),
+    /// `multipart/form-data`, only ever a request body
+    Form,
+}
+
+fn client(spec: &Value) -> String {
+    let mut operations = BTreeMap::new();
+    for (path, items) in spec["paths"].as_object().cloned().unwrap_or_default() {
+        for (method, operation) in items.as_object().cloned().unwrap_or_default() {
+            let id = operation["operationId"].as_str().unwrap().to_string();
+            operations.insert(id, (path.clone(), method, operation));
+        }
+    }
+
+    let mut out = String::from("impl Client {\n");
+    for (id, (path, method, operation)) in &operations {
+        if let Some(method) = operation_method(id, path, method, operation) {
+            out.push_str(&method);
+            out.push('\n');
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn operation_method(id: &str, path: &str, method: &str, operation: &Value) -> Option<String> {
+    let responses = operation["responses"].as_object()?;
+    // WebSocket upgrades can't be made with a plain HTTP client
+    if responses.contains_key("101") {
+        return None;
+    }
+
+    let request_content = operation["requestBody"]["content"].as_object();
+    let body = match request_content.and_then(|c| c.iter().next()) {
+        None => Body::None,
+        Some((content_type, media)) => match content_type.as_str() {
+            "application/json" => Body::Json(rust_type(&media["schema"], "types::")),
+            t if t.starts_with("multipart/") => Body::Form,
+            t if t.starts_with("text/") || t.ends_with("yaml") => Body::Text,
+            t => Body::Bytes(t.to_string()),
+        },
+    };
+
+    let success = responses
+        .iter()
+        .find(|(status, _)| status.starts_with('2'))
+        .map(|(_, response)| response);
+    let response = match success.and_then(|r| r["content"].as_object()?.iter().next()) {
+        None => Body::None,
+        Some((content_type, media)) => match content_type.as_str() {
+            "application/json" => Body::Json(rust_type(&media["schema"], "types::")),
+            t if t.starts_with("text/") || t.ends_with("yaml") => Body::Text,
+            t => Body::Bytes(t.to_string()),
+        },
+    };
+
+    let mut args = Vec::new();
+    let mut prelude = String::new();
+    let mut query = String::new();
+    let parameters = operation["parameters"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    for parameter in &parameters {
+        let name = parameter["name"].as_str()?;
+        let ident = field_ident(name);
+        let required = parameter["required"].as_bool().unwrap_or(false);
+        let ty = rust_type(&parameter["schema"], "types::");
+        match parameter["in"].as_str()? {
+            "path" if ty == "String" => args.push(format!("{}: &str", ident)),
+            "path" => {
+                args.push(format!("{}: {}", ident, ty));
+                writeln!(prelude, "        let {0} = {0}.to_string();", ident).unwrap();
+            }
+            "query" => {
+                let ty = ty
+                    .strip_prefix("Option<")
+                    .and_then(|t| t.strip_suffix('>'))
+                    .unwrap_or(&ty);
+                let ty = if ty == "String" { "&str" } else { ty };
+                if required {
+                    args.push(format!("{}: {}", ident, ty));
+                    writeln!(
+                        query,
+                        "        query.push(({:?}, query_value(&{})));",
+                        name, ident
+                    )
+                    .unwrap();
+                } else {
+                    args.push(format!("{}: Option<{}>", ident, ty));
+                    writeln!(
+                        query,
+                        "        if let Some(value) = &{} {{\n            query.push(({:?}, query_value(value)));\n        }}",
+                        ident, name
+                    )
+                    .unwrap();
+                }
+            }
+            _ => {}
+        }
+    }
+    match &body {
+        Body::None => {}
+        Body::Json(ty) => args.push(format!("body: &{}", ty)),
+        Body::Text => args.push("body: String".to_string()),
+        Body::Bytes(_) => args.push("body: Vec<u8>".to_string()),
+        Body::Form => args.push("form: reqwest::multipart::Form".to_string()),
+    }
+
+    let returns = match &response {
+        Body::None => "()".to_string(),
+        Body::Json(ty) => ty.clone(),
+        Body::Text => "String".to_string(),
+        Body::Bytes(_) => "Vec<u8>".to_string(),
+        Body::Form => unreachable!("responses are never forms"),
+    };
+
+    let segments: Vec<String> = path
+        .trim_start_matches('/')
+        .split('/')
+        .map(
+            |segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                Some(param) => {
+                    let ident = field_ident(param);
+                    if prelude.contains(&format!("let {} =", ident)) {
+                        format!("&{}", ident)
+                    } else {
+                        ident
+                    }
+                }
+                None => format!("{:?}", segment),
+            },
+        )
+        .collect();
+
+    let summary = operation["summary"].as_str().unwrap_or_default();
+    let mut out = doc(Some(summary), "    ");
+    if let Some(description) = operation["description"].as_str() {
+        out.push_str("    ///\n");
+        out.push_str(&doc(Some(description), "    "));
+    }
+    // Summaries usually start with the route already
+    if !summary.contains(path) {
+        if !out.is_empty() {
+            out.push_str("    ///\n");
+        }
+        writeln!(out, "    /// `{} {}`", method.to_uppercase(), path).unwrap();
+    }
+    writeln!(
+        out,
+        "    pub async fn {}(&self{}) -> Result<{}, Error> {{",
+        snake(id),
+        args.iter().map(|a| format!(", {}", a)).collect::<String>(),
+        returns
+    )
+    .unwrap();
+    out.push_str(&prelude);
+    writeln!(
+        out,
+        "        let request = self.request(reqwest::Method::{}, &[{}]);",
+        method.to_uppercase(),
+        segments.join(", ")
+    )
+    .unwrap();
+    if !query.is_empty() {
+        out.push_str("        let mut query: Vec<(&str, String)> = Vec::new();\n");
+        out.push_str(&query);
+        out.push_str("        let request = request.query(&query);\n");
+    }
+    match &body {
+        Body::None => {}
+        Body::Json(_) => out.push_str("        let request = request.json(body);\n"),
+        Body::Text => out.push_str(
+            "        let request = request\n            .header(reqwest::header::CONTENT_TYPE, \"application/yaml\")\n            .body(body);\n",
+        ),
+        Body::Bytes(content_type) => writeln!(
+            out,
+            "        let request = request\n            .header(reqwest::header::CONTENT_TYPE, {:?})\n            .body(body);",
+            content_type
+        )
+        .unwrap(),
+        Body::Form => out.push_str("        let request = request.multipart(form);\n"),
+    }
+    out.push_str("        let response = self.execute(request).await?;\n");
+    match &response {
+        Body::None => out.push_str("        drop(response);\n        Ok(())\n"),
+        Body::Json(_) => out.push_str("        Ok(response.json().await?)\n"),
+        Body::Text => out.push_str("        Ok(response.text().await?)\n"),
+        Body::Bytes(_) => out.push_str("        Ok(response.bytes().await?.to_vec())\n"),
+        Body::Form => unreachable!("responses are never forms"),
+    }
+    out.push_str("    }\n");
+    Some(out)
+}
+
+// ============================================================================
+// Names
+// ============================================================================
+
+fn doc(text: Option<&str>, indent: &str) -> String {
+    let Some(text) = text.filter(|t| !t.trim().is_empty()) else {
+        return String::new();
+    };
+    text.lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                format!("{}///\n", indent)
+            } else {
+                format!("{}/// {}\n", indent, line.trim_end())
+            }
+        })
+        .collect()
+}
+
+fn field_ident(name: &str) -> String {
+    let ident = snake(name);
+    if KEYWORDS.contains(&ident.as_str()) {
+        format!("r#{}", ident)
+    } else {
+        ident
+    }
+}
+
+/// `MIMEType` -> `mime_type`, `adoptAs` -> `adopt_as`
+fn snake(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_ascii_alphanumeric() {
+            if !out.ends_with('_') && !out.is_empty() {
+                out.push('_');
+            }
+            continue;
+        }
+        if c.is_ascii_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_ascii_lowercase());
+            let boundary = previous.is_ascii_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_ascii_uppercase() && next_lower);
+            if boundary && !out.ends_with('_') {
+                out.push('_');
+            }
+        }
+        out.push(c.to_ascii_lowercase());
+    }
+    out.trim_end_matches('_').to_string()
+}
+
+/// `rolled_back` -> `RolledBack`, `connections:write` -> `ConnectionsWrite`
+fn camel(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            std::iter::once(first).chain(chars).collect::<String>()
+        })
+        .collect()
+}
@@ -0,0 +1,286 @@
+//! Typed client for the OmniTAK REST API
+//!
+//! The types and one [`Client`] method per operation are generated at build
+//! time from `openapi.json`, the document the server serves at
+//! `/api-docs/openapi.json`. The omnitak-api tests fail when the committed
+//! copy no longer matches the server; rerun them with `UPDATE_OPENAPI=1` to
+//! refresh it.
+//!
+//! ```no_run
+//! use omnitak_api_client::{Auth, Client, types::LoginRequest};
+//!
+//! # async fn example() -> Result<(), omnitak_api_client::Error> {
+//! let mut client = Client::new("https://omnitak.example.com:8443")?;
+//! let login = client
+//!     .login(&LoginRequest {
+//!         username: "admin".to_string(),
+//!         password: "secret".to_string(),
+//!     })
+//!     .await?;
+//! client.set_auth(Some(Auth::Bearer(login.access_token)));
+//!
+//! let list = client.list_connections(None, None, None, None, None, None).await?;
+//! for connection in list.connections {
+//!     println!("{} {:?}", connection.name, connection.status);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! WebSocket routes are not generated; [`Client::events_url`] gives the
+//! address of the system events stream.
+
+use reqwest::{Method, RequestBuilder, Response, StatusCode, Url};
+use serde::Serialize;
+use std::time::Duration;
+
+/// Types of the API's request and response bodies
+#[allow(clippy::large_enum_variant)]
+pub mod types {
+    include!(concat!(env!("OUT_DIR"), "/types.rs"));
+}
+
+/// One `Client` method per operation
+#[allow(clippy::too_many_arguments, clippy::vec_init_then_push)]
+mod operations {
+    use super::{Client, Error, query_value, types};
+
+    include!(concat!(env!("OUT_DIR"), "/client.rs"));
+}
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Client errors
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid base URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("Request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("Server returned {status}: {}", message(.body))]
+    Api { status: StatusCode, body: String },
+}
+
+impl Error {
+    /// Status of an error response from the server
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Api { status, .. } => Some(*status),
+            Error::Request(e) => e.status(),
+            Error::InvalidUrl(_) => None,
+        }
+    }
+
+    /// The server's error body, when it sent the usual JSON error
+    pub fn error_response(&self) -> Option<types::ErrorResponse> {
+        match self {
+            Error::Api { body, .. } => serde_json::from_str(body).ok(),
+            _ => None,
+        }
+    }
+}
+
+fn message(body: &str) -> String {
+    match serde_json::from_str::<types::ErrorResponse>(body) {
+        Ok(error) => error.message,
+        Err(_) => body.to_string(),
+    }
+}
+
+/// Credentials sent with every request
+#[derive(Debug, Clone)]
+pub enum Auth {
+    /// JWT access token from `login`
+    Bearer(String),
+    /// API key, sent as `X-API-Key`
+    ApiKey(String),
+}
+
+/// OmniTAK REST API client
+#[derive(Debug, Clone)]
+pub struct Client {
+    base_url: Url,
+    http: reqwest::Client,
+    auth: Option<Auth>,
+}
+
+impl Client {
+    /// Client for the server at `base_url`, e.g. `https://host:8443`
+    pub fn new(base_url: &str) -> Result<Self, Error> {
+        let http = reqwest::Client::builder()
+            .timeout(DEFAULT_TIMEOUT)
+            .build()?;
+        Self::with_client(base_url, http)
+    }
+
+    /// Client sending requests through a configured reqwest client, e.g. one
+    /// trusting a private CA
+    pub fn with_client(base_url: &str, http: reqwest::Client) -> Result<Self, Error> {
+        let base_url = Url::parse(base_url).map_err(|e| Error::InvalidUrl(e.to_string()))?;
+        if base_url.cannot_be_a_base() {
+            return Err(Error::InvalidUrl(base_url.to_string()));
+        }
+        Ok(Self {
+            base_url,
+            http,
+            auth: None,
+        })
+    }
+
+    /// Send these credentials with every request
+    pub fn with_auth(mut self, auth: Auth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Change the credentials sent with every request
+    pub fn set_auth(&mut self, auth: Option<Auth>) {
+        self.auth = auth;
+    }
+
+    pub fn auth(&self) -> Option<&Auth> {
+        self.auth.as_ref()
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// URL of the system events WebSocket
+    pub fn events_url(&self) -> Url {
+        let mut url = self.url(&["api", "v1", "events"]);
+        let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+        // http(s) -> ws(s) is always allowed
+        let _ = url.set_scheme(scheme);
+        url
+    }
+
+    /// Base URL with the path segments appended, each percent-encoded
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.base_url.clone();
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.pop_if_empty().extend(segments);
+        }
+        url
+    }
+
+    /// Request to the route at `segments` carrying the client's credentials,
+    /// for what the generated methods don't expose, such as response headers
+    pub fn request(&self, method: Method, segments: &[&str]) -> RequestBuilder {
+        let request = self.http.request(method, self.url(segments));
+        match &self.auth {
+            Some(Auth::Bearer(token)) => request.bearer_auth(token),
+            Some(Auth::ApiKey(key)) => request.header("X-API-Key", key),
+            None => request,
+        }
+    }
+
+    /// Send a request, turning non-success statuses into [`Error::Api`]
+    pub async fn execute(&self, request: RequestBuilder) -> Result<Response, Error> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        Err(Error::Api { status, body })
+    }
+}
+
+/// Query string form of a parameter: strings and enums unquoted
+fn query_value<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(s)) => s,
+        Ok(other) => other.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Json, Router, extract::Path, http::HeaderMap, routing::get};
+
+    async fn serve(router: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn test_url_keeps_base_path_and_encodes_segments() {
+        let client = Client::new("https://example.com/omnitak/").unwrap();
+        assert_eq!(
+            client
+                .url(&["api", "v1", "adb", "approved", "10.0.0.5:5555 a/b"])
+                .as_str(),
+            "https://example.com/omnitak/api/v1/adb/approved/10.0.0.5:5555%20a%2Fb"
+        );
+        assert_eq!(
+            client.events_url().as_str(),
+            "wss://example.com/omnitak/api/v1/events"
+        );
+    }
+
+    #[test]
+    fn test_query_values_are_unquoted() {
+        assert_eq!(query_value(&types::ServiceStatus::Active), "active");
+        assert_eq!(query_value(&"a b"), "a b");
+        assert_eq!(query_value(&25u64), "25");
+        assert_eq!(query_value(&true), "true");
+    }
+
+    #[tokio::test]
+    async fn test_operation_sends_auth_and_decodes_response() {
+        let router = Router::new().route(
+            "/api/v1/health",
+            get(|headers: HeaderMap| async move {
+                let status = if headers.get("x-api-key").is_some_and(|k| k == "key") {
+                    "healthy"
+                } else {
+                    "no key"
+                };
+                Json(serde_json::json!({
+                    "status": status,
+                    "timestamp": "2026-01-01T00:00:00Z",
+                }))
+            }),
+        );
+        let client = Client::new(&serve(router).await)
+            .unwrap()
+            .with_auth(Auth::ApiKey("key".to_string()));
+
+        let health = client.health_check().await.unwrap();
+        assert_eq!(health.status, "healthy");
+    }
+
+    #[tokio::test]
+    async fn test_error_responses_carry_status_and_message() {
+        let router = Router::new().route(
+            "/api/v1/filters/{id}",
+            get(|Path(id): Path<String>| async move {
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(serde_json::json!({
+                        "error": "not_found",
+                        "message": format!("Filter {} not found", id),
+                        "timestamp": "2026-01-01T00:00:00Z",
+                    })),
+                )
+            }),
+        );
+        let client = Client::new(&serve(router).await).unwrap();
+
+        let id = uuid::Uuid::nil();
+        let error = client.get_filter(id).await.unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::NOT_FOUND));
+        assert_eq!(error.error_response().unwrap().error, "not_found");
+        assert_eq!(
+            error.to_string(),
+            format!("Server returned 404 Not Found: Filter {} not found", id)
+        );
+    }
+}
@@ -8,7 +8,6 @@ async fn main() -> anyhow::Result<()> {
     let config = ServerConfig {
         bind_addr: "0.0.0.0:8443".parse().unwrap(),
         enable_tls: false, // Disable TLS for development
-        rate_limit_rps: 100,
         enable_swagger: true,
         enable_static_files: true,
         ..Default::default()
@@ -1,31 +1,137 @@
-//! ADB integration endpoints for pulling certificates from connected Android devices
+//! ADB integration endpoints for pulling certificates from connected Android
+//! devices and pushing connection settings back to them
 
-use crate::auth::AuthUser;
+use crate::auth::{AuthUser, RequireDevicesWrite};
+use crate::middleware::ClientIp;
 use axum::{
-    extract::State,
+    extract::{Path, State},
     Json,
 };
-use omnitak_adb::{AdbClient, AtakPackage, TakCertificateBundle};
+use omnitak_adb::{
+    AdbClient, AtakPackage, DeployFile, DeployKind, DeviceMonitor, DeviceMonitorBuilder,
+    TakCertificateBundle,
+};
 use omnitak_client::tls::{TlsClient, TlsClientConfig, TlsCertConfig, TlsCertSource, FramingMode};
+use omnitak_cert::CertificateBundle;
 use omnitak_client::ClientConfig;
 use omnitak_core::ConnectionId;
+use omnitak_datapackage::{ContentType, DataPackageBuilder, StreamPreference, StreamProtocol};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use tracing::{error, info, warn};
+use utoipa::ToSchema;
 use validator::Validate;
 
 use super::rest::{ApiState, ApiError};
-use super::types::ConnectionInfo;
+use super::types::{ConnectionInfo, ErrorResponse};
+
+// ============================================================================
+// Device Monitor
+// ============================================================================
+
+/// Background watch for devices being attached and detached
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdbMonitorConfig {
+    /// How often `adb devices` is polled
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+
+    /// Serials of devices whose certificates are pulled as soon as they are
+    /// attached; more can be approved through the API
+    #[serde(default)]
+    pub approved_devices: Vec<String>,
+
+    /// ATAK package certificates are pulled from
+    #[serde(default = "default_package")]
+    pub package: String,
+
+    /// Directory pulled certificates are written to
+    #[serde(default = "default_cert_dir")]
+    pub cert_dir: String,
+
+    /// Create a connection from certificates pulled automatically
+    #[serde(default = "default_auto_connect")]
+    pub auto_connect: bool,
+}
+
+fn default_poll_interval_secs() -> u64 {
+    2
+}
+
+impl Default for AdbMonitorConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_secs: default_poll_interval_secs(),
+            approved_devices: Vec::new(),
+            package: default_package(),
+            cert_dir: default_cert_dir(),
+            auto_connect: default_auto_connect(),
+        }
+    }
+}
+
+/// Running device monitor and the devices approved for automatic pulls
+pub struct AdbMonitorState {
+    pub monitor: Arc<DeviceMonitor>,
+    pub config: AdbMonitorConfig,
+    approved: RwLock<HashSet<String>>,
+}
+
+impl AdbMonitorState {
+    pub fn new(config: AdbMonitorConfig) -> Self {
+        let monitor = DeviceMonitorBuilder::new()
+            .poll_interval(Duration::from_secs(config.poll_interval_secs.max(1)))
+            .build();
+        let approved = config.approved_devices.iter().cloned().collect();
+
+        Self {
+            monitor,
+            config,
+            approved: RwLock::new(approved),
+        }
+    }
+
+    /// Whether certificates are pulled from the device when it is attached
+    pub fn is_approved(&self, serial: &str) -> bool {
+        self.approved.read().unwrap().contains(serial)
+    }
+
+    /// Approved serials, sorted
+    pub fn approved(&self) -> Vec<String> {
+        let mut approved: Vec<_> = self.approved.read().unwrap().iter().cloned().collect();
+        approved.sort();
+        approved
+    }
+
+    /// Approve a device, returning whether it was newly approved
+    pub fn approve(&self, serial: &str) -> bool {
+        self.approved.write().unwrap().insert(serial.to_string())
+    }
+
+    /// Withdraw a device's approval, returning whether it was approved
+    pub fn revoke(&self, serial: &str) -> bool {
+        self.approved.write().unwrap().remove(serial)
+    }
+}
 
 // ============================================================================
 // Types
 // ============================================================================
 
 /// Request to pull certificates from device
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct PullCertsRequest {
     /// Device serial number (optional, auto-detect if single device)
     pub device_serial: Option<String>,
+    /// Pull from each of these devices concurrently instead of one
+    #[serde(default)]
+    pub device_serials: Vec<String>,
+    /// Pull from every online device concurrently
+    #[serde(default)]
+    pub all_devices: bool,
     /// ATAK package name (defaults to civilian ATAK)
     #[serde(default = "default_package")]
     pub package: String,
@@ -50,7 +156,7 @@ fn default_auto_connect() -> bool {
 }
 
 /// Response after pulling certificates
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct PullCertsResponse {
     /// Success flag
     pub success: bool,
@@ -60,10 +166,32 @@ pub struct PullCertsResponse {
     pub bundle: Option<CertificateBundleInfo>,
     /// Connection ID if auto-connect was enabled
     pub connection_id: Option<String>,
+    /// Certificate store ID if a client certificate was loaded
+    pub certificate_id: Option<uuid::Uuid>,
+    /// Result for each device when pulling from several
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub results: Vec<DevicePullResult>,
+}
+
+/// Outcome of pulling certificates from one of several devices
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DevicePullResult {
+    /// Device serial number
+    pub serial: String,
+    /// Success flag
+    pub success: bool,
+    /// Message, or the error if the pull failed
+    pub message: String,
+    /// Certificate bundle information
+    pub bundle: Option<CertificateBundleInfo>,
+    /// Connection ID if auto-connect was enabled
+    pub connection_id: Option<String>,
+    /// Certificate store ID if a client certificate was loaded
+    pub certificate_id: Option<uuid::Uuid>,
 }
 
 /// Certificate bundle information
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct CertificateBundleInfo {
     /// Server address
     pub server_address: Option<String>,
@@ -75,15 +203,96 @@ pub struct CertificateBundleInfo {
     pub cert_files: Vec<String>,
 }
 
+/// Request to push a server connection to devices
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct DeployRequest {
+    /// Devices to deploy to; every online device when empty
+    #[serde(default)]
+    pub device_serials: Vec<String>,
+    /// ATAK package name (defaults to civilian ATAK)
+    #[serde(default = "default_package")]
+    pub package: String,
+    /// Server hostname or IP address
+    #[validate(length(min = 1, max = 255))]
+    pub host: String,
+    /// Server streaming port
+    #[validate(range(min = 1, max = 65535))]
+    pub port: u16,
+    /// Connection protocol: tcp, ssl, udp or quic (defaults to ssl)
+    #[serde(default = "default_deploy_protocol")]
+    #[schema(value_type = String, example = "ssl")]
+    pub protocol: StreamProtocol,
+    /// Connection name shown in ATAK
+    #[validate(length(max = 255))]
+    pub description: Option<String>,
+    /// Certificate store entry installed as the client certificate and
+    /// trust store
+    pub certificate_id: Option<uuid::Uuid>,
+    /// Whether to push a data package or a bare preference file
+    #[serde(default)]
+    pub format: DeployFormat,
+}
+
+fn default_deploy_protocol() -> StreamProtocol {
+    StreamProtocol::Ssl
+}
+
+/// How a deployed connection is packaged
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeployFormat {
+    /// Data package holding the preference file and certificates
+    #[default]
+    DataPackage,
+    /// Preference file, with certificates pushed alongside it
+    Preferences,
+}
+
+/// Response after deploying to devices
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeployResponse {
+    /// Whether every device was deployed to
+    pub success: bool,
+    /// Message
+    pub message: String,
+    /// Result for each device
+    pub results: Vec<DeviceDeployResult>,
+}
+
+/// Outcome of deploying to one device
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeviceDeployResult {
+    /// Device serial number
+    pub serial: String,
+    /// Deployment status
+    pub status: DeployStatus,
+    /// Paths the files were pushed to
+    pub remote_paths: Vec<String>,
+    /// Error if the deployment failed
+    pub error: Option<String>,
+}
+
+/// Deployment status of a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeployStatus {
+    /// Pushed and ATAK asked to import
+    Imported,
+    /// Pushed; ATAK imports the files on its next start
+    Pushed,
+    /// Nothing usable was pushed
+    Failed,
+}
+
 /// List of connected ADB devices
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct DeviceListResponse {
     /// Connected devices
     pub devices: Vec<DeviceInfoResponse>,
 }
 
 /// Device information
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct DeviceInfoResponse {
     /// Device serial number
     pub serial: String,
@@ -93,6 +302,53 @@ pub struct DeviceInfoResponse {
     pub model: Option<String>,
     /// Device product
     pub product: Option<String>,
+    /// Connected over Wi-Fi rather than USB
+    pub wireless: bool,
+    /// Certificates are pulled automatically when the device is attached
+    pub approved: bool,
+}
+
+/// Devices approved for automatic certificate pulls
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApprovedDevicesResponse {
+    /// Whether the device monitor is running
+    pub monitor_enabled: bool,
+    /// Approved device serials
+    pub devices: Vec<String>,
+}
+
+/// Request to pair with a device over Wi-Fi
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct PairDeviceRequest {
+    /// Pairing address shown on the device (host:port)
+    #[validate(length(min = 1, max = 255))]
+    pub address: String,
+    /// Six-digit pairing code shown on the device
+    #[validate(length(equal = 6))]
+    pub pairing_code: String,
+    /// Address to connect to once paired (host:port), shown on the device
+    /// under Wireless debugging > IP address & Port
+    #[validate(length(min = 1, max = 255))]
+    pub connect_address: Option<String>,
+}
+
+/// Request to connect to or disconnect from a device over Wi-Fi
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct WirelessDeviceRequest {
+    /// Device address (host or host:port, port defaults to 5555)
+    #[validate(length(min = 1, max = 255))]
+    pub address: String,
+}
+
+/// Response after a wireless ADB operation
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WirelessDeviceResponse {
+    /// Success flag
+    pub success: bool,
+    /// Message
+    pub message: String,
+    /// Serial of the connected device, usable as `device_serial`
+    pub serial: Option<String>,
 }
 
 // ============================================================================
@@ -100,7 +356,22 @@ pub struct DeviceInfoResponse {
 // ============================================================================
 
 /// GET /api/v1/adb/devices - List connected Android devices
+#[utoipa::path(
+    get,
+    path = "/api/v1/adb/devices",
+    responses(
+        (status = 200, description = "Connected devices", body = DeviceListResponse),
+        (status = 400, description = "ADB is not available", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 500, description = "Failed to list devices", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
 pub async fn list_devices(
+    State(state): State<ApiState>,
     _user: AuthUser,
 ) -> Result<Json<DeviceListResponse>, ApiError> {
     let adb = AdbClient::new();
@@ -115,6 +386,11 @@ pub async fn list_devices(
     let devices = devices
         .into_iter()
         .map(|d| DeviceInfoResponse {
+            wireless: d.is_wireless(),
+            approved: state
+                .adb_monitor
+                .as_ref()
+                .is_some_and(|m| m.is_approved(&d.serial)),
             serial: d.serial,
             state: d.state,
             model: d.model,
@@ -125,7 +401,168 @@ pub async fn list_devices(
     Ok(Json(DeviceListResponse { devices }))
 }
 
-/// POST /api/v1/adb/pull-certs - Pull certificates from connected device
+/// POST /api/v1/adb/pair - Pair with a device over Wi-Fi, then optionally connect to it
+#[utoipa::path(
+    post,
+    path = "/api/v1/adb/pair",
+    request_body = PairDeviceRequest,
+    responses(
+        (status = 200, description = "Paired, and connected if a connect address was given", body = WirelessDeviceResponse),
+        (status = 400, description = "Invalid pairing code, ADB not available or pairing failed", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires devices:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["devices:write"]),
+        ("api_key" = ["devices:write"])
+    )
+)]
+pub async fn pair_device(
+    State(state): State<ApiState>,
+    RequireDevicesWrite(user): RequireDevicesWrite,
+    ClientIp(client_ip): ClientIp,
+    Json(req): Json<PairDeviceRequest>,
+) -> Result<Json<WirelessDeviceResponse>, ApiError> {
+    req.validate().map_err(|e| ApiError::BadRequest(format!("Invalid request: {}", e)))?;
+    if !req.pairing_code.chars().all(|c| c.is_ascii_digit()) {
+        return Err(ApiError::BadRequest("Pairing code must be 6 digits".to_string()));
+    }
+
+    let adb = available_adb()?;
+
+    info!("Pairing with device at {}", req.address);
+    let pair_address = req.address.clone();
+    let code = req.pairing_code.clone();
+    let connect_address = req.connect_address.clone();
+    let serial = run_blocking(move || {
+        adb.pair(&pair_address, &code)?;
+        connect_address.map(|address| adb.connect(&address)).transpose()
+    })
+    .await?;
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "adb_pair".to_string(),
+        "/api/v1/adb/pair".to_string(),
+        serde_json::json!({
+            "address": req.address,
+            "connect_address": req.connect_address,
+        }),
+        client_ip.to_string(),
+        true,
+    );
+
+    let message = match &serial {
+        Some(serial) => format!("Paired and connected to {}", serial),
+        None => format!("Paired with {}", req.address),
+    };
+    info!("{}", message);
+
+    Ok(Json(WirelessDeviceResponse {
+        success: true,
+        message,
+        serial,
+    }))
+}
+
+/// POST /api/v1/adb/connect - Connect to a device over Wi-Fi
+#[utoipa::path(
+    post,
+    path = "/api/v1/adb/connect",
+    request_body = WirelessDeviceRequest,
+    responses(
+        (status = 200, description = "Connected", body = WirelessDeviceResponse),
+        (status = 400, description = "ADB is not available or the command failed", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires devices:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["devices:write"]),
+        ("api_key" = ["devices:write"])
+    )
+)]
+pub async fn connect_device(
+    State(state): State<ApiState>,
+    RequireDevicesWrite(user): RequireDevicesWrite,
+    ClientIp(client_ip): ClientIp,
+    Json(req): Json<WirelessDeviceRequest>,
+) -> Result<Json<WirelessDeviceResponse>, ApiError> {
+    req.validate().map_err(|e| ApiError::BadRequest(format!("Invalid request: {}", e)))?;
+
+    let adb = available_adb()?;
+
+    let address = req.address.clone();
+    let serial = run_blocking(move || adb.connect(&address)).await?;
+    info!("Connected to device {}", serial);
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "adb_connect".to_string(),
+        "/api/v1/adb/connect".to_string(),
+        serde_json::json!({ "address": serial }),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(Json(WirelessDeviceResponse {
+        success: true,
+        message: format!("Connected to {}", serial),
+        serial: Some(serial),
+    }))
+}
+
+/// POST /api/v1/adb/disconnect - Disconnect a device connected over Wi-Fi
+#[utoipa::path(
+    post,
+    path = "/api/v1/adb/disconnect",
+    request_body = WirelessDeviceRequest,
+    responses(
+        (status = 200, description = "Disconnected", body = WirelessDeviceResponse),
+        (status = 400, description = "ADB is not available or the command failed", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires devices:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["devices:write"]),
+        ("api_key" = ["devices:write"])
+    )
+)]
+pub async fn disconnect_device(
+    RequireDevicesWrite(_user): RequireDevicesWrite,
+    Json(req): Json<WirelessDeviceRequest>,
+) -> Result<Json<WirelessDeviceResponse>, ApiError> {
+    req.validate().map_err(|e| ApiError::BadRequest(format!("Invalid request: {}", e)))?;
+
+    let adb = available_adb()?;
+
+    let address = req.address.clone();
+    run_blocking(move || adb.disconnect(&address)).await?;
+    info!("Disconnected device {}", req.address);
+
+    Ok(Json(WirelessDeviceResponse {
+        success: true,
+        message: format!("Disconnected {}", req.address),
+        serial: None,
+    }))
+}
+
+/// POST /api/v1/adb/pull-certs - Pull certificates from one or several connected devices
+#[utoipa::path(
+    post,
+    path = "/api/v1/adb/pull-certs",
+    request_body = PullCertsRequest,
+    responses(
+        (status = 200, description = "Certificates pulled", body = PullCertsResponse),
+        (status = 400, description = "ADB not available, no device or no certificates", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
 pub async fn pull_certificates(
     State(state): State<ApiState>,
     _user: AuthUser,
@@ -133,42 +570,316 @@ pub async fn pull_certificates(
 ) -> Result<Json<PullCertsResponse>, ApiError> {
     req.validate().map_err(|e| ApiError::BadRequest(format!("Invalid request: {}", e)))?;
 
-    let adb = AdbClient::new();
+    if req.all_devices || !req.device_serials.is_empty() {
+        return pull_from_devices(state, &req).await.map(Json);
+    }
 
-    // Check if ADB is available
-    if !adb.is_available() {
-        return Err(ApiError::BadRequest("ADB is not available. Make sure Android SDK Platform-Tools is installed.".to_string()));
+    pull_from_device(
+        state,
+        req.device_serial,
+        &req.package,
+        &req.cert_dir,
+        req.auto_connect,
+    )
+    .await
+    .map(Json)
+}
+
+/// GET /api/v1/adb/approved - List devices approved for automatic certificate pulls
+#[utoipa::path(
+    get,
+    path = "/api/v1/adb/approved",
+    responses(
+        (status = 200, description = "Approved devices", body = ApprovedDevicesResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+pub async fn list_approved_devices(
+    State(state): State<ApiState>,
+    _user: AuthUser,
+) -> Json<ApprovedDevicesResponse> {
+    Json(ApprovedDevicesResponse {
+        monitor_enabled: state.adb_monitor.is_some(),
+        devices: state
+            .adb_monitor
+            .as_ref()
+            .map(|m| m.approved())
+            .unwrap_or_default(),
+    })
+}
+
+/// PUT /api/v1/adb/approved/{serial} - Pull certificates from a device whenever it is attached
+#[utoipa::path(
+    put,
+    path = "/api/v1/adb/approved/{serial}",
+    params(
+        ("serial" = String, Path, description = "Device serial number")
+    ),
+    responses(
+        (status = 200, description = "Device approved", body = ApprovedDevicesResponse),
+        (status = 400, description = "Device monitor not enabled", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires devices:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["devices:write"]),
+        ("api_key" = ["devices:write"])
+    )
+)]
+pub async fn approve_device(
+    State(state): State<ApiState>,
+    RequireDevicesWrite(user): RequireDevicesWrite,
+    ClientIp(client_ip): ClientIp,
+    Path(serial): Path<String>,
+) -> Result<Json<ApprovedDevicesResponse>, ApiError> {
+    let monitor = device_monitor(&state)?;
+    if monitor.approve(&serial) {
+        info!("Approved device {} for automatic certificate pulls", serial);
+        state.audit_logger.log(
+            user.user_id.unwrap_or_else(|| "api_key".to_string()),
+            user.role,
+            "adb_approve_device".to_string(),
+            format!("/api/v1/adb/approved/{}", serial),
+            serde_json::json!({ "serial": serial }),
+            client_ip.to_string(),
+            true,
+        );
     }
 
-    // Get device
-    let device = if let Some(serial) = req.device_serial {
-        adb.get_device(&serial).map_err(|e| ApiError::NotFound(format!("Device not found: {}", e)))?
+    Ok(Json(ApprovedDevicesResponse {
+        monitor_enabled: true,
+        devices: monitor.approved(),
+    }))
+}
+
+/// DELETE /api/v1/adb/approved/{serial} - Stop pulling certificates from a device when attached
+#[utoipa::path(
+    delete,
+    path = "/api/v1/adb/approved/{serial}",
+    params(
+        ("serial" = String, Path, description = "Device serial number")
+    ),
+    responses(
+        (status = 200, description = "Approval revoked", body = ApprovedDevicesResponse),
+        (status = 400, description = "Device monitor not enabled", body = ErrorResponse),
+        (status = 404, description = "Device is not approved", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires devices:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["devices:write"]),
+        ("api_key" = ["devices:write"])
+    )
+)]
+pub async fn revoke_device(
+    State(state): State<ApiState>,
+    RequireDevicesWrite(user): RequireDevicesWrite,
+    ClientIp(client_ip): ClientIp,
+    Path(serial): Path<String>,
+) -> Result<Json<ApprovedDevicesResponse>, ApiError> {
+    let monitor = device_monitor(&state)?;
+    if !monitor.revoke(&serial) {
+        return Err(ApiError::NotFound(format!("Device {} is not approved", serial)));
+    }
+
+    info!("Revoked automatic certificate pulls for device {}", serial);
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "adb_revoke_device".to_string(),
+        format!("/api/v1/adb/approved/{}", serial),
+        serde_json::json!({ "serial": serial }),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(Json(ApprovedDevicesResponse {
+        monitor_enabled: true,
+        devices: monitor.approved(),
+    }))
+}
+
+/// POST /api/v1/adb/deploy - Push a server connection and certificates to devices
+#[utoipa::path(
+    post,
+    path = "/api/v1/adb/deploy",
+    request_body = DeployRequest,
+    responses(
+        (status = 200, description = "Deployment finished; results give each device's outcome", body = DeployResponse),
+        (status = 400, description = "Invalid request, ADB not available or no devices", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires devices:write", body = ErrorResponse),
+        (status = 404, description = "Certificate not found", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["devices:write"]),
+        ("api_key" = ["devices:write"])
+    )
+)]
+pub async fn deploy_connection(
+    State(state): State<ApiState>,
+    RequireDevicesWrite(user): RequireDevicesWrite,
+    ClientIp(client_ip): ClientIp,
+    Json(req): Json<DeployRequest>,
+) -> Result<Json<DeployResponse>, ApiError> {
+    req.validate().map_err(|e| ApiError::BadRequest(format!("Invalid request: {}", e)))?;
+    if !is_valid_host(&req.host) {
+        return Err(ApiError::BadRequest(format!("Invalid host: {:?}", req.host)));
+    }
+    let package = atak_package(&req.package)?;
+
+    let adb = available_adb()?;
+
+    let certificate = match req.certificate_id {
+        Some(id) => Some(
+            state
+                .certificates
+                .get_bundle(&id)
+                .ok_or_else(|| ApiError::NotFound(format!("Certificate {} not found", id)))?,
+        ),
+        None => None,
+    };
+    let files = build_deploy_files(&req, certificate.as_ref())
+        .map_err(|e| ApiError::BadRequest(format!("Failed to build deployment: {}", e)))?;
+
+    let serials: Vec<String> = if req.device_serials.is_empty() {
+        let list_adb = adb.clone();
+        run_blocking(move || list_adb.list_devices())
+            .await?
+            .into_iter()
+            .filter(|d| d.state == "device")
+            .map(|d| d.serial)
+            .collect()
     } else {
-        adb.auto_detect_device().map_err(|e| ApiError::BadRequest(format!("Failed to detect device: {}", e)))?
+        req.device_serials.clone()
     };
+    if serials.is_empty() {
+        return Err(ApiError::BadRequest("No devices connected".to_string()));
+    }
+
+    info!(
+        "Deploying {}:{} to {} device(s)",
+        req.host,
+        req.port,
+        serials.len()
+    );
+
+    let files = std::sync::Arc::new(files);
+    let deployments = serials.into_iter().map(|serial| {
+        let adb = adb.clone();
+        let files = files.clone();
+        let package = package.clone();
+        async move {
+            let result = tokio::task::spawn_blocking({
+                let serial = serial.clone();
+                move || adb.get_device(&serial)?.deploy(&files, &package)
+            })
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|r| r);
 
-    info!("Pulling certificates from device: {}", device.serial);
+            match result {
+                Ok(outcome) => DeviceDeployResult {
+                    serial,
+                    status: if outcome.imported {
+                        DeployStatus::Imported
+                    } else {
+                        DeployStatus::Pushed
+                    },
+                    remote_paths: outcome.remote_paths,
+                    error: None,
+                },
+                Err(e) => {
+                    warn!("Failed to deploy to {}: {}", serial, e);
+                    DeviceDeployResult {
+                        serial,
+                        status: DeployStatus::Failed,
+                        remote_paths: Vec::new(),
+                        error: Some(e.to_string()),
+                    }
+                }
+            }
+        }
+    });
+    let results = futures::future::join_all(deployments).await;
+
+    let failed = results
+        .iter()
+        .filter(|r| r.status == DeployStatus::Failed)
+        .count();
 
-    // Save device serial for later use
-    let device_serial = device.serial.clone();
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "adb_deploy".to_string(),
+        "/api/v1/adb/deploy".to_string(),
+        serde_json::json!({
+            "server": format!("{}:{}:{}", req.host, req.port, req.protocol.as_str()),
+            "certificate_id": req.certificate_id,
+            "devices": results.iter().map(|r| &r.serial).collect::<Vec<_>>(),
+            "failed": failed,
+        }),
+        client_ip.to_string(),
+        failed == 0,
+    );
+
+    Ok(Json(DeployResponse {
+        success: failed == 0,
+        message: format!(
+            "Deployed to {} of {} device(s)",
+            results.len() - failed,
+            results.len()
+        ),
+        results,
+    }))
+}
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+/// Pull certificates from a device, or the only attached device, storing
+/// PEM bundles and optionally creating a connection from them
+pub async fn pull_from_device(
+    state: ApiState,
+    device_serial: Option<String>,
+    package: &str,
+    cert_dir: &str,
+    auto_connect: bool,
+) -> Result<PullCertsResponse, ApiError> {
+    let adb = available_adb()?;
 
     // Determine ATAK package
-    let package = match req.package.as_str() {
-        "com.atakmap.app.civ" => AtakPackage::Civilian,
-        "com.atakmap.app.mil" => AtakPackage::Military,
-        _ => AtakPackage::Custom(Box::leak(req.package.clone().into_boxed_str())),
-    };
+    let package = atak_package(package)?;
 
     // Create output directory
-    let cert_dir = PathBuf::from(&req.cert_dir);
+    let cert_dir = PathBuf::from(cert_dir);
     tokio::fs::create_dir_all(&cert_dir)
         .await
         .map_err(|e| ApiError::InternalError(format!("Failed to create certificate directory: {}", e)))?;
 
-    // Pull certificates
-    let bundle = device
-        .pull_tak_certificates(&cert_dir, package)
-        .map_err(|e| ApiError::InternalError(format!("Failed to pull certificates: {}", e)))?;
+    // Find the device and pull certificates off the async runtime, so pulls
+    // from several devices run side by side
+    let (device_serial, bundle) = tokio::task::spawn_blocking(move || {
+        let device = if let Some(serial) = device_serial {
+            adb.get_device(&serial).map_err(|e| ApiError::NotFound(format!("Device not found: {}", e)))?
+        } else {
+            adb.auto_detect_device().map_err(|e| ApiError::BadRequest(format!("Failed to detect device: {}", e)))?
+        };
+
+        info!("Pulling certificates from device: {}", device.serial);
+
+        let bundle = device
+            .pull_tak_certificates(&cert_dir, &package)
+            .map_err(|e| ApiError::InternalError(format!("Failed to pull certificates: {}", e)))?;
+        Ok::<_, ApiError>((device.serial, bundle))
+    })
+    .await
+    .map_err(|e| ApiError::InternalError(format!("ADB task failed: {}", e)))??;
 
     info!(
         "Successfully pulled {} certificate files",
@@ -187,9 +898,27 @@ pub async fn pull_certificates(
             .collect(),
     };
 
+    // Keep the client certificate in the certificate store so connections can use it by ID
+    let certificate_id = match load_pulled_bundle(&bundle) {
+        Ok(Some(cert_bundle)) => {
+            let id = state.certificates.insert(
+                format!("ADB-{}", device_serial),
+                "adb",
+                cert_bundle,
+            );
+            info!(certificate_id = %id, "Stored pulled certificates");
+            Some(id)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Failed to load pulled certificates into store: {}", e);
+            None
+        }
+    };
+
     // Auto-connect if requested
-    let connection_id = if req.auto_connect {
-        match create_connection_from_bundle(state, bundle, device_serial).await {
+    let connection_id = if auto_connect {
+        match create_connection_from_bundle(state, bundle, device_serial, certificate_id).await {
             Ok(id) => {
                 info!("Successfully created connection: {}", id);
                 Some(id)
@@ -203,7 +932,7 @@ pub async fn pull_certificates(
         None
     };
 
-    Ok(Json(PullCertsResponse {
+    Ok(PullCertsResponse {
         success: true,
         message: format!(
             "Successfully pulled {} certificate files from device",
@@ -211,18 +940,272 @@ pub async fn pull_certificates(
         ),
         bundle: Some(bundle_info),
         connection_id,
-    }))
+        certificate_id,
+        results: Vec::new(),
+    })
 }
 
-// ============================================================================
-// Helper Functions
-// ============================================================================
+/// Pull certificates from several devices at once, each into its own
+/// subdirectory of the requested certificate directory
+async fn pull_from_devices(state: ApiState, req: &PullCertsRequest) -> Result<PullCertsResponse, ApiError> {
+    let serials: Vec<String> = if req.all_devices {
+        let adb = available_adb()?;
+        run_blocking(move || adb.list_devices())
+            .await?
+            .into_iter()
+            .filter(|d| d.state == "device")
+            .map(|d| d.serial)
+            .collect()
+    } else {
+        req.device_serials.clone()
+    };
+    if serials.is_empty() {
+        return Err(ApiError::BadRequest("No devices connected".to_string()));
+    }
+
+    info!("Pulling certificates from {} device(s)", serials.len());
+
+    let pulls = serials.into_iter().map(|serial| {
+        // Wireless serials are host:port, which is not a valid path component everywhere
+        let cert_dir = PathBuf::from(&req.cert_dir).join(serial.replace([':', '/', '\\'], "_"));
+        let state = state.clone();
+        async move {
+            let result = pull_from_device(
+                state,
+                Some(serial.clone()),
+                &req.package,
+                &cert_dir.to_string_lossy(),
+                req.auto_connect,
+            )
+            .await;
+
+            match result {
+                Ok(response) => DevicePullResult {
+                    serial,
+                    success: response.success,
+                    message: response.message,
+                    bundle: response.bundle,
+                    connection_id: response.connection_id,
+                    certificate_id: response.certificate_id,
+                },
+                Err(e) => {
+                    let message = e.to_string();
+                    warn!("Failed to pull certificates from {}: {}", serial, message);
+                    DevicePullResult {
+                        serial,
+                        success: false,
+                        message,
+                        bundle: None,
+                        connection_id: None,
+                        certificate_id: None,
+                    }
+                }
+            }
+        }
+    });
+    let results = futures::future::join_all(pulls).await;
+
+    let pulled = results.iter().filter(|r| r.success).count();
+    Ok(PullCertsResponse {
+        success: pulled == results.len(),
+        message: format!(
+            "Pulled certificates from {} of {} device(s)",
+            pulled,
+            results.len()
+        ),
+        bundle: None,
+        connection_id: None,
+        certificate_id: None,
+        results,
+    })
+}
+
+/// Device monitor, or an error saying it is not enabled
+fn device_monitor(state: &ApiState) -> Result<&Arc<AdbMonitorState>, ApiError> {
+    state
+        .adb_monitor
+        .as_ref()
+        .ok_or_else(|| ApiError::BadRequest("ADB device monitor is not enabled".to_string()))
+}
+
+/// Standard ATAK default password for bundled PKCS#12 files
+const P12_PASSWORD: &str = "atakatak";
+
+/// ATAK package from its name
+fn atak_package(name: &str) -> Result<AtakPackage, ApiError> {
+    AtakPackage::from_name(name).map_err(|e| ApiError::BadRequest(e.to_string()))
+}
+
+/// Whether `host` is an IP address or a hostname, the only server
+/// addresses written to a device
+fn is_valid_host(host: &str) -> bool {
+    host.parse::<std::net::IpAddr>().is_ok()
+        || host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Files that configure the requested connection on a device
+fn build_deploy_files(
+    req: &DeployRequest,
+    certificate: Option<&CertificateBundle>,
+) -> anyhow::Result<Vec<DeployFile>> {
+    let name = format!("omnitak-{}", req.host.replace([':', '/'], "_"));
+
+    let mut certificates = Vec::new();
+    let mut ca_location = None;
+    let mut certificate_location = None;
+    if let Some(bundle) = certificate {
+        if let Some(truststore) = bundle.truststore_pkcs12(P12_PASSWORD, "OmniTAK-CA")? {
+            let file = format!("{}-truststore.p12", name);
+            ca_location = Some(format!("cert/{}", file));
+            certificates.push((file, truststore));
+        }
+        let file = format!("{}.p12", name);
+        certificate_location = Some(format!("cert/{}", file));
+        certificates.push((file, bundle.to_pkcs12(P12_PASSWORD, &name)?));
+    }
+
+    let stream = StreamPreference {
+        description: req.description.clone().unwrap_or_else(|| req.host.clone()),
+        host: req.host.clone(),
+        port: req.port,
+        protocol: req.protocol,
+        enabled: true,
+        ca_password: ca_location.as_ref().map(|_| P12_PASSWORD.to_string()),
+        ca_location,
+        client_password: certificate_location.as_ref().map(|_| P12_PASSWORD.to_string()),
+        certificate_location,
+        use_auth: false,
+        enroll_for_certificate: false,
+    };
+    let prefs = omnitak_datapackage::prefs::write_stream_preferences(&[stream]).into_bytes();
+
+    let files = match req.format {
+        DeployFormat::DataPackage => {
+            let mut builder = DataPackageBuilder::new(&name);
+            for (file, data) in certificates {
+                builder = builder.add_bytes(&file, data, ContentType::Certificate)?;
+            }
+            let package = builder
+                .add_bytes(&format!("{}.pref", name), prefs, ContentType::Configuration)?
+                .on_receive_delete(false)
+                .build_to_memory()?;
+            vec![DeployFile {
+                name: format!("{}.zip", name),
+                kind: DeployKind::DataPackage,
+                data: package,
+            }]
+        }
+        DeployFormat::Preferences => {
+            let mut files: Vec<_> = certificates
+                .into_iter()
+                .map(|(file, data)| DeployFile {
+                    name: file,
+                    kind: DeployKind::Certificate,
+                    data,
+                })
+                .collect();
+            files.push(DeployFile {
+                name: format!("{}.pref", name),
+                kind: DeployKind::Preferences,
+                data: prefs,
+            });
+            files
+        }
+    };
+
+    Ok(files)
+}
+
+/// ADB client, or an error telling the user to install it
+fn available_adb() -> Result<AdbClient, ApiError> {
+    let adb = AdbClient::new();
+    if !adb.is_available() {
+        return Err(ApiError::BadRequest("ADB is not available. Make sure Android SDK Platform-Tools is installed.".to_string()));
+    }
+    Ok(adb)
+}
+
+/// Run a network ADB command off the async runtime; pairing and connecting
+/// wait on the device and can take several seconds
+async fn run_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> anyhow::Result<T> + Send + 'static,
+) -> Result<T, ApiError> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("ADB task failed: {}", e)))?
+        .map_err(|e| ApiError::BadRequest(e.to_string()))
+}
+
+/// Parse pulled certificates into an in-memory bundle, preferring the client
+/// PKCS#12 over PEM files
+///
+/// PKCS#12 files are opened with the passwords from the device's
+/// preferences, or ATAK's default password when it has none.
+fn load_pulled_bundle(bundle: &TakCertificateBundle) -> anyhow::Result<Option<CertificateBundle>> {
+    let files: Vec<PathBuf> = bundle.certificates.iter().map(|c| c.local_path.clone()).collect();
+    let classified = omnitak_cert::classify_certificate_files(&files, std::path::Path::new(""))?;
+
+    let preferences = &bundle.preferences;
+    let (client_password, ca_password) = match preferences.primary_stream() {
+        Some(stream) => (stream.client_password(), stream.ca_password()),
+        None => (
+            preferences.client_password.as_deref().unwrap_or(P12_PASSWORD),
+            preferences.ca_password.as_deref().unwrap_or(P12_PASSWORD),
+        ),
+    };
+
+    if let Some(p12_path) = classified.p12_path.as_ref().filter(|p| !is_truststore(p)) {
+        let mut cert_bundle =
+            CertificateBundle::from_pkcs12(&std::fs::read(p12_path)?, Some(client_password))?;
+        if cert_bundle.ca_certs.is_none() {
+            if let Some(truststore) = files.iter().find(|p| is_truststore(p)) {
+                cert_bundle.ca_certs = Some(omnitak_cert::load_truststore(
+                    &std::fs::read(truststore)?,
+                    Some(ca_password),
+                )?);
+            }
+        }
+        return Ok(Some(cert_bundle));
+    }
+
+    let find = |cert_type: omnitak_adb::CertificateType| {
+        bundle.certificates.iter().find(|c| c.cert_type == cert_type)
+    };
+
+    let (Some(cert), Some(key)) = (
+        find(omnitak_adb::CertificateType::Client),
+        find(omnitak_adb::CertificateType::Key),
+    ) else {
+        return Ok(None);
+    };
+
+    let cert_pem = std::fs::read(&cert.local_path)?;
+    let key_pem = std::fs::read(&key.local_path)?;
+    let ca_pem = match find(omnitak_adb::CertificateType::CA) {
+        Some(ca) => Some(std::fs::read(&ca.local_path)?),
+        None => None,
+    };
+
+    CertificateBundle::from_pem(&cert_pem, &key_pem, ca_pem.as_deref()).map(Some)
+}
+
+/// Whether a pulled PKCS#12 file is a trust store rather than a client certificate
+fn is_truststore(path: &std::path::Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.to_lowercase().contains("truststore"))
+}
 
 /// Create a TAK connection from certificate bundle
 async fn create_connection_from_bundle(
     state: ApiState,
     bundle: TakCertificateBundle,
     device_serial: String,
+    certificate_id: Option<uuid::Uuid>,
 ) -> anyhow::Result<String> {
     info!("Creating TAK connection from certificate bundle...");
 
@@ -248,7 +1231,11 @@ async fn create_connection_from_bundle(
         .find(|c| c.cert_type == omnitak_adb::CertificateType::CA);
 
     // Determine certificate source
-    let cert_source = if let Some(p12) = p12_file {
+    let stored_bundle = certificate_id.and_then(|id| state.certificates.get_bundle(&id));
+    let cert_source = if let Some(cert_bundle) = stored_bundle {
+        info!("Using stored certificate bundle");
+        TlsCertSource::Bundle(cert_bundle)
+    } else if let Some(p12) = p12_file {
         info!("Using PKCS#12 certificate: {}", p12.local_path.display());
 
         // Note: PKCS#12 requires password, which we don't have
@@ -344,9 +1331,61 @@ async fn create_connection_from_bundle(
         connected_at: Some(chrono::Utc::now()),
         last_activity: Some(chrono::Utc::now()),
         error: None,
+        tenant: None,
     };
 
     state.connections.write().await.push(conn_info);
 
     Ok(connection_id.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deploy_request(format: DeployFormat) -> DeployRequest {
+        DeployRequest {
+            device_serials: Vec::new(),
+            package: default_package(),
+            host: "tak.example.com".to_string(),
+            port: 8089,
+            protocol: StreamProtocol::Ssl,
+            description: Some("Ops".to_string()),
+            certificate_id: None,
+            format,
+        }
+    }
+
+    #[test]
+    fn test_build_deploy_files() {
+        let files = build_deploy_files(&deploy_request(DeployFormat::Preferences), None).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "omnitak-tak.example.com.pref");
+        assert_eq!(files[0].kind, DeployKind::Preferences);
+
+        let prefs = String::from_utf8(files[0].data.clone()).unwrap();
+        let streams = omnitak_datapackage::prefs::parse_stream_preferences(&prefs).unwrap();
+        assert_eq!(streams.len(), 1);
+        assert_eq!(streams[0].description, "Ops");
+        assert_eq!((streams[0].host.as_str(), streams[0].port), ("tak.example.com", 8089));
+        assert_eq!(streams[0].certificate_location, None);
+
+        let files = build_deploy_files(&deploy_request(DeployFormat::DataPackage), None).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "omnitak-tak.example.com.zip");
+        assert_eq!(files[0].kind, DeployKind::DataPackage);
+    }
+
+    #[test]
+    fn test_deploy_inputs_validated() {
+        assert!(is_valid_host("tak.example.com"));
+        assert!(is_valid_host("10.0.0.5"));
+        assert!(is_valid_host("fd00::1"));
+        assert!(!is_valid_host("tak.example.com;reboot"));
+        assert!(!is_valid_host("$(id)"));
+        assert!(!is_valid_host(""));
+
+        assert!(atak_package("com.atakmap.app.civ").is_ok());
+        assert!(atak_package("com.atakmap.app.civ' -e x 'y").is_err());
+    }
+}
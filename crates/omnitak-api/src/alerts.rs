@@ -0,0 +1,616 @@
+//! Alert notification channels
+//!
+//! Delivers alerts to operators over email (SMTP), Slack incoming webhooks
+//! and Matrix rooms. Each channel has a minimum severity; alerts below it
+//! are not sent to that channel. Channels are loaded from configuration and
+//! can be managed at runtime through `/api/v1/alerts/channels`.
+
+use chrono::{DateTime, Utc};
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::warn;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Placeholder returned in place of secrets when listing channels
+pub const REDACTED: &str = "********";
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest one channel may take to deliver an alert, including connecting
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+// ============================================================================
+// Alerts
+// ============================================================================
+
+/// Alert severity, ordered from least to most severe
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertSeverity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+impl AlertSeverity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AlertSeverity::Info => "INFO",
+            AlertSeverity::Warning => "WARNING",
+            AlertSeverity::Critical => "CRITICAL",
+        }
+    }
+}
+
+/// An alert to deliver to operators
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Alert {
+    pub severity: AlertSeverity,
+    pub title: String,
+    pub message: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Alert {
+    pub fn new(
+        severity: AlertSeverity,
+        title: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            severity,
+            title: title.into(),
+            message: message.into(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// One-line subject, e.g. `[CRITICAL] Certificate expired`
+    pub fn subject(&self) -> String {
+        format!("[{}] {}", self.severity.label(), self.title)
+    }
+
+    /// Plain-text body used by all channels
+    pub fn text(&self) -> String {
+        format!(
+            "{}\n{}\n{}",
+            self.subject(),
+            self.message,
+            self.timestamp.format("%Y-%m-%d %H:%M:%S UTC")
+        )
+    }
+}
+
+// ============================================================================
+// Channel Configuration
+// ============================================================================
+
+/// Alerting configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlertsConfig {
+    #[serde(default)]
+    pub channels: Vec<AlertChannel>,
+}
+
+/// A named alert destination
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AlertChannel {
+    /// Unique channel name
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Alerts below this severity are not sent to the channel
+    #[serde(default)]
+    pub min_severity: AlertSeverity,
+    #[serde(flatten)]
+    pub kind: AlertChannelKind,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Delivery mechanism for a channel
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AlertChannelKind {
+    Email(EmailChannel),
+    Slack(SlackChannel),
+    Matrix(MatrixChannel),
+}
+
+/// SMTP email settings
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EmailChannel {
+    pub smtp_host: String,
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+/// SMTP connection security
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Plaintext connection (local relays only)
+    None,
+    /// Upgrade with STARTTLS (usually port 587)
+    #[default]
+    Starttls,
+    /// Implicit TLS (usually port 465)
+    Tls,
+}
+
+/// Slack incoming webhook settings
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SlackChannel {
+    pub webhook_url: String,
+    /// Override the webhook's default channel
+    #[serde(default)]
+    pub channel: Option<String>,
+}
+
+/// Matrix room settings
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MatrixChannel {
+    /// Homeserver base URL, e.g. `https://matrix.example.org`
+    pub homeserver: String,
+    /// Room ID, e.g. `!abcdef:example.org`
+    pub room_id: String,
+    pub access_token: String,
+}
+
+impl AlertChannel {
+    /// Whether an alert of `severity` should go to this channel
+    pub fn accepts(&self, severity: AlertSeverity) -> bool {
+        self.enabled && severity >= self.min_severity
+    }
+
+    /// Copy with passwords, tokens and webhook URLs replaced by [`REDACTED`]
+    pub fn redacted(&self) -> Self {
+        let mut channel = self.clone();
+        match &mut channel.kind {
+            AlertChannelKind::Email(email) => {
+                if email.password.is_some() {
+                    email.password = Some(REDACTED.to_string());
+                }
+            }
+            AlertChannelKind::Slack(slack) => slack.webhook_url = REDACTED.to_string(),
+            AlertChannelKind::Matrix(matrix) => matrix.access_token = REDACTED.to_string(),
+        }
+        channel
+    }
+
+    /// Keep secrets from `existing` where this channel still has the
+    /// [`REDACTED`] placeholder, so a listed channel can be edited and
+    /// submitted back unchanged
+    pub fn restore_secrets(&mut self, existing: &AlertChannel) {
+        match (&mut self.kind, &existing.kind) {
+            (AlertChannelKind::Email(new), AlertChannelKind::Email(old)) => {
+                if new.password.as_deref() == Some(REDACTED) {
+                    new.password = old.password.clone();
+                }
+            }
+            (AlertChannelKind::Slack(new), AlertChannelKind::Slack(old)) => {
+                if new.webhook_url == REDACTED {
+                    new.webhook_url = old.webhook_url.clone();
+                }
+            }
+            (AlertChannelKind::Matrix(new), AlertChannelKind::Matrix(old)) => {
+                if new.access_token == REDACTED {
+                    new.access_token = old.access_token.clone();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// ============================================================================
+// Errors
+// ============================================================================
+
+#[derive(Debug, thiserror::Error)]
+pub enum AlertError {
+    #[error("Channel {0} already exists")]
+    Exists(String),
+
+    #[error("Channel {0} not found")]
+    NotFound(String),
+
+    #[error("Invalid channel: {0}")]
+    Invalid(String),
+
+    #[error("Delivery failed: {0}")]
+    Delivery(String),
+}
+
+// ============================================================================
+// Alert Manager
+// ============================================================================
+
+/// Outcome of delivering one alert to one channel
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AlertDelivery {
+    pub channel: String,
+    pub delivered: bool,
+    pub error: Option<String>,
+}
+
+/// Holds the configured channels and delivers alerts to them
+pub struct AlertManager {
+    channels: RwLock<Vec<AlertChannel>>,
+    http: reqwest::Client,
+}
+
+impl AlertManager {
+    pub fn new(config: AlertsConfig) -> Self {
+        Self {
+            channels: RwLock::new(config.channels),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// All channels with secrets redacted
+    pub async fn list(&self) -> Vec<AlertChannel> {
+        self.channels
+            .read()
+            .await
+            .iter()
+            .map(AlertChannel::redacted)
+            .collect()
+    }
+
+    /// Add a new channel
+    pub async fn add(&self, channel: AlertChannel) -> Result<(), AlertError> {
+        validate(&channel)?;
+        let mut channels = self.channels.write().await;
+        if channels.iter().any(|c| c.name == channel.name) {
+            return Err(AlertError::Exists(channel.name));
+        }
+        channels.push(channel);
+        Ok(())
+    }
+
+    /// Replace the channel called `name`
+    pub async fn update(&self, name: &str, mut channel: AlertChannel) -> Result<(), AlertError> {
+        let mut channels = self.channels.write().await;
+        let index = channels
+            .iter()
+            .position(|c| c.name == name)
+            .ok_or_else(|| AlertError::NotFound(name.to_string()))?;
+        if channel.name != name && channels.iter().any(|c| c.name == channel.name) {
+            return Err(AlertError::Exists(channel.name));
+        }
+        channel.restore_secrets(&channels[index]);
+        validate(&channel)?;
+        channels[index] = channel;
+        Ok(())
+    }
+
+    /// Remove the channel called `name`
+    pub async fn remove(&self, name: &str) -> Result<(), AlertError> {
+        let mut channels = self.channels.write().await;
+        let before = channels.len();
+        channels.retain(|c| c.name != name);
+        if channels.len() == before {
+            return Err(AlertError::NotFound(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Deliver an alert to every channel whose threshold it meets
+    pub async fn dispatch(&self, alert: &Alert) -> Vec<AlertDelivery> {
+        let channels: Vec<_> = self
+            .channels
+            .read()
+            .await
+            .iter()
+            .filter(|c| c.accepts(alert.severity))
+            .cloned()
+            .collect();
+        self.deliver_all(&channels, alert).await
+    }
+
+    /// Deliver an alert to one channel regardless of its threshold
+    pub async fn send_to(&self, name: &str, alert: &Alert) -> Result<AlertDelivery, AlertError> {
+        let channel = self
+            .channels
+            .read()
+            .await
+            .iter()
+            .find(|c| c.name == name)
+            .cloned()
+            .ok_or_else(|| AlertError::NotFound(name.to_string()))?;
+        Ok(self.deliver_all(&[channel], alert).await.remove(0))
+    }
+
+    async fn deliver_all(&self, channels: &[AlertChannel], alert: &Alert) -> Vec<AlertDelivery> {
+        let deliveries = channels.iter().map(|channel| async move {
+            let result = tokio::time::timeout(DELIVERY_TIMEOUT, self.deliver(channel, alert))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(AlertError::Delivery(format!(
+                        "timed out after {}s",
+                        DELIVERY_TIMEOUT.as_secs()
+                    )))
+                });
+            if let Err(e) = &result {
+                warn!(channel = %channel.name, error = %e, "Alert delivery failed");
+            }
+            AlertDelivery {
+                channel: channel.name.clone(),
+                delivered: result.is_ok(),
+                error: result.err().map(|e| e.to_string()),
+            }
+        });
+        futures::future::join_all(deliveries).await
+    }
+
+    async fn deliver(&self, channel: &AlertChannel, alert: &Alert) -> Result<(), AlertError> {
+        match &channel.kind {
+            AlertChannelKind::Email(email) => {
+                send_email(email, alert.subject(), alert.text()).await
+            }
+            AlertChannelKind::Slack(slack) => self.send_slack(slack, alert).await,
+            AlertChannelKind::Matrix(matrix) => self.send_matrix(matrix, alert).await,
+        }
+    }
+
+    async fn send_slack(&self, slack: &SlackChannel, alert: &Alert) -> Result<(), AlertError> {
+        let mut body = serde_json::json!({ "text": alert.text() });
+        if let Some(channel) = &slack.channel {
+            body["channel"] = serde_json::Value::String(channel.clone());
+        }
+        let response = self
+            .http
+            .post(&slack.webhook_url)
+            .timeout(HTTP_TIMEOUT)
+            .json(&body)
+            .send()
+            .await;
+        check_response(response)
+    }
+
+    async fn send_matrix(&self, matrix: &MatrixChannel, alert: &Alert) -> Result<(), AlertError> {
+        let url = matrix_send_url(
+            &matrix.homeserver,
+            &matrix.room_id,
+            &Uuid::new_v4().to_string(),
+        )?;
+        let body = serde_json::json!({
+            "msgtype": "m.text",
+            "body": alert.text(),
+        });
+        let response = self
+            .http
+            .put(url)
+            .timeout(HTTP_TIMEOUT)
+            .bearer_auth(&matrix.access_token)
+            .json(&body)
+            .send()
+            .await;
+        check_response(response)
+    }
+}
+
+fn validate(channel: &AlertChannel) -> Result<(), AlertError> {
+    if channel.name.trim().is_empty() || channel.name.len() > 64 {
+        return Err(AlertError::Invalid(
+            "name must be 1-64 characters".to_string(),
+        ));
+    }
+    match &channel.kind {
+        AlertChannelKind::Email(email) => {
+            email_message(email, String::new(), String::new())?;
+        }
+        AlertChannelKind::Slack(slack) => {
+            reqwest::Url::parse(&slack.webhook_url)
+                .map_err(|e| AlertError::Invalid(format!("webhook_url: {}", e)))?;
+        }
+        AlertChannelKind::Matrix(matrix) => {
+            matrix_send_url(&matrix.homeserver, &matrix.room_id, "0")?;
+        }
+    }
+    Ok(())
+}
+
+fn check_response(response: reqwest::Result<reqwest::Response>) -> Result<(), AlertError> {
+    let response = response.map_err(|e| AlertError::Delivery(e.to_string()))?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(AlertError::Delivery(format!("HTTP {}", response.status())))
+    }
+}
+
+/// `PUT` URL for sending a message event to a Matrix room
+fn matrix_send_url(
+    homeserver: &str,
+    room_id: &str,
+    txn_id: &str,
+) -> Result<reqwest::Url, AlertError> {
+    let mut url = reqwest::Url::parse(homeserver)
+        .map_err(|e| AlertError::Invalid(format!("homeserver: {}", e)))?;
+    url.path_segments_mut()
+        .map_err(|_| AlertError::Invalid("homeserver cannot be a base URL".to_string()))?
+        .pop_if_empty()
+        .extend([
+            "_matrix",
+            "client",
+            "v3",
+            "rooms",
+            room_id,
+            "send",
+            "m.room.message",
+            txn_id,
+        ]);
+    Ok(url)
+}
+
+fn email_message(
+    email: &EmailChannel,
+    subject: String,
+    body: String,
+) -> Result<Message, AlertError> {
+    let mailbox = |address: &str| {
+        address
+            .parse::<Mailbox>()
+            .map_err(|e| AlertError::Invalid(format!("email address {}: {}", address, e)))
+    };
+
+    if email.to.is_empty() {
+        return Err(AlertError::Invalid(
+            "email needs at least one recipient".to_string(),
+        ));
+    }
+    let mut builder = Message::builder()
+        .from(mailbox(&email.from)?)
+        .subject(subject);
+    for to in &email.to {
+        builder = builder.to(mailbox(to)?);
+    }
+    builder
+        .body(body)
+        .map_err(|e| AlertError::Invalid(e.to_string()))
+}
+
+/// Send a plain-text email through the channel's SMTP server
+pub async fn send_email(
+    email: &EmailChannel,
+    subject: String,
+    body: String,
+) -> Result<(), AlertError> {
+    let message = email_message(email, subject, body)?;
+
+    let builder = match email.tls {
+        SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&email.smtp_host),
+        SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&email.smtp_host)
+            .map_err(|e| AlertError::Delivery(e.to_string()))?,
+        SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&email.smtp_host)
+            .map_err(|e| AlertError::Delivery(e.to_string()))?,
+    };
+    let mut builder = builder.port(email.smtp_port).timeout(Some(HTTP_TIMEOUT));
+    if let (Some(username), Some(password)) = (&email.username, &email.password) {
+        builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+
+    builder
+        .build()
+        .send(message)
+        .await
+        .map(|_| ())
+        .map_err(|e| AlertError::Delivery(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(json: serde_json::Value) -> AlertChannel {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn slack(name: &str, min_severity: &str) -> AlertChannel {
+        channel(serde_json::json!({
+            "name": name,
+            "type": "slack",
+            "min_severity": min_severity,
+            "webhook_url": "https://hooks.slack.com/services/T000/B000/XXXX",
+        }))
+    }
+
+    #[test]
+    fn test_severity_threshold() {
+        let ops = slack("ops", "warning");
+        assert!(!ops.accepts(AlertSeverity::Info));
+        assert!(ops.accepts(AlertSeverity::Warning));
+        assert!(ops.accepts(AlertSeverity::Critical));
+
+        let mut disabled = slack("pager", "info");
+        disabled.enabled = false;
+        assert!(!disabled.accepts(AlertSeverity::Critical));
+    }
+
+    #[test]
+    fn test_channel_config_parsing() {
+        let email = channel(serde_json::json!({
+            "name": "oncall",
+            "type": "email",
+            "smtp_host": "smtp.example.com",
+            "from": "omnitak@example.com",
+            "to": ["oncall@example.com"],
+            "password": "hunter2",
+        }));
+        assert_eq!(email.min_severity, AlertSeverity::Warning);
+        assert!(email.enabled);
+        match &email.kind {
+            AlertChannelKind::Email(e) => {
+                assert_eq!(e.smtp_port, 587);
+                assert_eq!(e.tls, SmtpTls::Starttls);
+            }
+            other => panic!("unexpected kind {:?}", other),
+        }
+        assert!(validate(&email).is_ok());
+
+        let listed = serde_json::to_value(email.redacted()).unwrap();
+        assert_eq!(listed["type"], "email");
+        assert_eq!(listed["password"], REDACTED);
+    }
+
+    #[test]
+    fn test_matrix_send_url() {
+        let url =
+            matrix_send_url("https://matrix.example.org/", "!room:example.org", "42").unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://matrix.example.org/_matrix/client/v3/rooms/!room:example.org/send/m.room.message/42"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_manage_channels() {
+        let manager = AlertManager::new(AlertsConfig {
+            channels: vec![slack("ops", "warning")],
+        });
+
+        assert!(matches!(
+            manager.add(slack("ops", "info")).await,
+            Err(AlertError::Exists(_))
+        ));
+
+        // A listed (redacted) channel can be submitted back without losing its secret
+        let mut edited = manager.list().await.remove(0);
+        edited.min_severity = AlertSeverity::Critical;
+        manager.update("ops", edited).await.unwrap();
+        let channels = manager.channels.read().await;
+        match &channels[0].kind {
+            AlertChannelKind::Slack(s) => assert!(s.webhook_url.starts_with("https://")),
+            other => panic!("unexpected kind {:?}", other),
+        }
+        assert_eq!(channels[0].min_severity, AlertSeverity::Critical);
+        drop(channels);
+
+        manager.remove("ops").await.unwrap();
+        assert!(manager.list().await.is_empty());
+    }
+}
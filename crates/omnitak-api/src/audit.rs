@@ -0,0 +1,579 @@
+//! Audit log storage
+//!
+//! Entries are kept in SQLite: in a database file when one is configured,
+//! so they survive restarts, and in memory otherwise. Retention applies to
+//! both. Entries older than `retention_days` are deleted, and past
+//! `max_entries` the oldest go first. Freed pages are returned to the file
+//! system after each prune.
+//!
+//! Entries are written by a dedicated thread, in batches, so logging never
+//! waits on the database. Queries and pruning first wait for the entries
+//! logged before them to be written.
+
+use crate::pagination::SortOrder;
+use crate::types::{AuditLogEntry, AuditLogPage, AuditQuery, AuditSort, UserRole};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::Mutex;
+use rusqlite::types::Value;
+use rusqlite::{Connection, params, params_from_iter};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+/// Entries waiting to be written before new ones are dropped
+const WRITE_QUEUE_CAPACITY: usize = 8192;
+
+/// Entries written per transaction at most
+const WRITE_BATCH_SIZE: usize = 512;
+
+/// Page size when a query does not ask for one
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Largest page a query can ask for
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// Entries a query returns at most
+pub fn page_size(query: &AuditQuery) -> usize {
+    query.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE)
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS audit_log (
+        id TEXT PRIMARY KEY,
+        timestamp INTEGER NOT NULL,
+        user TEXT NOT NULL,
+        role TEXT,
+        action TEXT NOT NULL,
+        resource TEXT NOT NULL,
+        details TEXT NOT NULL,
+        source_ip TEXT NOT NULL,
+        success INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS audit_log_timestamp ON audit_log (timestamp);
+    CREATE INDEX IF NOT EXISTS audit_log_user ON audit_log (user, timestamp);
+    CREATE INDEX IF NOT EXISTS audit_log_action ON audit_log (action, timestamp);
+";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditConfig {
+    /// SQLite database file; entries are kept in memory when unset
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+
+    /// Delete entries older than this many days (0 = keep them)
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u32,
+
+    /// Keep at most this many entries, deleting the oldest (0 = no limit)
+    #[serde(default = "default_max_entries")]
+    pub max_entries: u64,
+}
+
+fn default_retention_days() -> u32 {
+    90
+}
+
+fn default_max_entries() -> u64 {
+    1_000_000
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            retention_days: default_retention_days(),
+            max_entries: default_max_entries(),
+        }
+    }
+}
+
+pub struct AuditStore {
+    conn: Arc<Mutex<Connection>>,
+    writer: SyncSender<WriteCommand>,
+    retention_days: u32,
+    max_entries: u64,
+}
+
+enum WriteCommand {
+    Insert(AuditLogEntry),
+    /// Acknowledged once everything queued before it is written
+    Flush(mpsc::Sender<()>),
+}
+
+impl AuditStore {
+    /// Open the configured database, creating it if needed
+    pub fn open(config: &AuditConfig) -> Result<Self> {
+        let conn = match &config.path {
+            Some(path) => {
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    std::fs::create_dir_all(parent)
+                        .with_context(|| format!("Failed to create {}", parent.display()))?;
+                }
+                let conn = Connection::open(path)
+                    .with_context(|| format!("Failed to open {}", path.display()))?;
+                // Must precede the first table to take effect
+                conn.pragma_update(None, "auto_vacuum", "INCREMENTAL")?;
+                conn.pragma_update(None, "journal_mode", "WAL")?;
+                conn.pragma_update(None, "synchronous", "NORMAL")?;
+                conn
+            }
+            None => Connection::open_in_memory()?,
+        };
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create the audit log table")?;
+
+        let conn = Arc::new(Mutex::new(conn));
+        let (writer, commands) = mpsc::sync_channel(WRITE_QUEUE_CAPACITY);
+        let writer_conn = Arc::clone(&conn);
+        std::thread::Builder::new()
+            .name("audit-writer".to_string())
+            .spawn(move || write_entries(&writer_conn, commands))
+            .context("Failed to start the audit log writer")?;
+
+        Ok(Self {
+            conn,
+            writer,
+            retention_days: config.retention_days,
+            max_entries: config.max_entries,
+        })
+    }
+
+    /// Store with the default retention that keeps entries in memory
+    pub fn in_memory() -> Self {
+        Self::open(&AuditConfig::default()).expect("in-memory SQLite database")
+    }
+
+    /// Queue an entry for writing. Fails without waiting when the queue is
+    /// full.
+    pub fn insert(&self, entry: &AuditLogEntry) -> Result<()> {
+        match self.writer.try_send(WriteCommand::Insert(entry.clone())) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => anyhow::bail!("audit log write queue is full"),
+            Err(TrySendError::Disconnected(_)) => anyhow::bail!("audit log writer stopped"),
+        }
+    }
+
+    /// Wait until the entries queued so far are written
+    pub fn flush(&self) {
+        let (done, wait) = mpsc::channel();
+        if self.writer.send(WriteCommand::Flush(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+
+    /// Entries matching the query, newest first
+    pub fn query(&self, query: &AuditQuery) -> Result<AuditLogPage> {
+        self.flush();
+
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        if let Some(since) = query.since {
+            conditions.push("timestamp >= ?");
+            values.push(Value::Integer(since.timestamp_micros()));
+        }
+        if let Some(until) = query.until {
+            conditions.push("timestamp < ?");
+            values.push(Value::Integer(until.timestamp_micros()));
+        }
+        if let Some(user) = &query.user {
+            conditions.push("user = ?");
+            values.push(Value::Text(user.clone()));
+        }
+        let users_condition;
+        if let Some(users) = &query.users {
+            users_condition = if users.is_empty() {
+                "0".to_string()
+            } else {
+                format!("user IN ({})", vec!["?"; users.len()].join(", "))
+            };
+            conditions.push(users_condition.as_str());
+            values.extend(users.iter().cloned().map(Value::Text));
+        }
+        if let Some(action) = &query.action {
+            conditions.push("action = ?");
+            values.push(Value::Text(action.clone()));
+        }
+        if let Some(success) = query.success {
+            conditions.push("success = ?");
+            values.push(Value::Integer(success.into()));
+        }
+        if let Some(search) = query
+            .search
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+        {
+            conditions.push(
+                "(instr(lower(user), ?) > 0 OR instr(lower(action), ?) > 0 \
+                 OR instr(lower(resource), ?) > 0)",
+            );
+            let search = Value::Text(search.to_lowercase());
+            values.extend([search.clone(), search.clone(), search]);
+        }
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+
+        let conn = self.conn.lock();
+        let total: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM audit_log {}", filter),
+            params_from_iter(values.iter()),
+            |row| row.get(0),
+        )?;
+
+        let limit = page_size(query);
+        values.push(Value::Integer(limit as i64));
+        values.push(Value::Integer(query.offset as i64));
+        // Ties are broken newest first, or in insertion order for timestamps
+        let order = query.order.unwrap_or(SortOrder::Desc).sql();
+        let order_by = match query.sort.unwrap_or_default() {
+            AuditSort::Timestamp => format!("timestamp {0}, rowid {0}", order),
+            AuditSort::User => format!("user {}, timestamp DESC, rowid DESC", order),
+            AuditSort::Action => format!("action {}, timestamp DESC, rowid DESC", order),
+        };
+        let mut statement = conn.prepare(&format!(
+            "SELECT id, timestamp, user, role, action, resource, details, source_ip, success
+             FROM audit_log {} ORDER BY {} LIMIT ? OFFSET ?",
+            filter, order_by
+        ))?;
+        let entries = statement
+            .query_map(params_from_iter(values.iter()), |row| {
+                Ok(AuditLogEntry {
+                    id: row
+                        .get::<_, String>(0)?
+                        .parse()
+                        .unwrap_or_else(|_| Uuid::nil()),
+                    timestamp: DateTime::from_timestamp_micros(row.get(1)?).unwrap_or_default(),
+                    user: row.get(2)?,
+                    role: row
+                        .get::<_, Option<String>>(3)?
+                        .as_deref()
+                        .and_then(parse_role),
+                    action: row.get(4)?,
+                    resource: row.get(5)?,
+                    details: serde_json::from_str(&row.get::<_, String>(6)?)
+                        .unwrap_or(serde_json::Value::Null),
+                    source_ip: row.get(7)?,
+                    success: row.get(8)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(AuditLogPage {
+            entries,
+            total: total as usize,
+        })
+    }
+
+    /// Apply the retention policy, returning how many entries were deleted
+    pub fn prune(&self) -> Result<usize> {
+        self.flush();
+        let conn = self.conn.lock();
+        let mut deleted = 0;
+        if self.retention_days > 0 {
+            let cutoff = Utc::now() - Duration::days(self.retention_days.into());
+            deleted += conn.execute(
+                "DELETE FROM audit_log WHERE timestamp < ?1",
+                params![cutoff.timestamp_micros()],
+            )?;
+        }
+        if self.max_entries > 0 {
+            deleted += conn.execute(
+                "DELETE FROM audit_log WHERE rowid IN (
+                     SELECT rowid FROM audit_log
+                     ORDER BY timestamp DESC, rowid DESC LIMIT -1 OFFSET ?1
+                 )",
+                params![self.max_entries as i64],
+            )?;
+        }
+        if deleted > 0 {
+            conn.execute_batch("PRAGMA incremental_vacuum")?;
+        }
+        Ok(deleted)
+    }
+}
+
+impl Drop for AuditStore {
+    fn drop(&mut self) {
+        // The writer stops once its queue is empty and the sender is gone
+        self.flush();
+    }
+}
+
+/// Write queued entries until the store is dropped, batching whatever has
+/// queued up into one transaction
+fn write_entries(conn: &Mutex<Connection>, commands: Receiver<WriteCommand>) {
+    while let Ok(first) = commands.recv() {
+        let mut entries = Vec::new();
+        let mut flushes = Vec::new();
+        let mut next = Some(first);
+        while let Some(command) = next.take() {
+            match command {
+                WriteCommand::Insert(entry) => entries.push(entry),
+                WriteCommand::Flush(done) => flushes.push(done),
+            }
+            if entries.len() < WRITE_BATCH_SIZE {
+                next = commands.try_recv().ok();
+            }
+        }
+
+        if !entries.is_empty() {
+            if let Err(e) = insert_batch(&mut conn.lock(), &entries) {
+                error!(
+                    error = %e,
+                    entries = entries.len(),
+                    "Failed to store audit log entries"
+                );
+            }
+        }
+        for done in flushes {
+            let _ = done.send(());
+        }
+    }
+}
+
+fn insert_batch(conn: &mut Connection, entries: &[AuditLogEntry]) -> rusqlite::Result<()> {
+    let transaction = conn.transaction()?;
+    {
+        let mut statement = transaction.prepare_cached(
+            "INSERT OR REPLACE INTO audit_log
+                 (id, timestamp, user, role, action, resource, details, source_ip, success)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        )?;
+        for entry in entries {
+            statement.execute(params![
+                entry.id.to_string(),
+                entry.timestamp.timestamp_micros(),
+                entry.user,
+                entry.role.map(role_name),
+                entry.action,
+                entry.resource,
+                entry.details.to_string(),
+                entry.source_ip,
+                entry.success,
+            ])?;
+        }
+    }
+    transaction.commit()
+}
+
+fn role_name(role: UserRole) -> &'static str {
+    match role {
+        UserRole::Admin => "admin",
+        UserRole::Operator => "operator",
+        UserRole::ReadOnly => "readonly",
+    }
+}
+
+fn parse_role(name: &str) -> Option<UserRole> {
+    match name {
+        "admin" => Some(UserRole::Admin),
+        "operator" => Some(UserRole::Operator),
+        "readonly" => Some(UserRole::ReadOnly),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(user: &str, action: &str, success: bool, age: Duration) -> AuditLogEntry {
+        AuditLogEntry {
+            id: Uuid::new_v4(),
+            user: user.to_string(),
+            role: Some(UserRole::Operator),
+            action: action.to_string(),
+            resource: "/api/v1/connections".to_string(),
+            details: serde_json::json!({"connection_id": "123"}),
+            source_ip: "192.0.2.7".to_string(),
+            timestamp: Utc::now() - age,
+            success,
+        }
+    }
+
+    #[test]
+    fn test_query_filters() {
+        let store = AuditStore::in_memory();
+        store
+            .insert(&entry(
+                "alice",
+                "create_connection",
+                true,
+                Duration::hours(3),
+            ))
+            .unwrap();
+        store
+            .insert(&entry(
+                "alice",
+                "delete_connection",
+                false,
+                Duration::hours(2),
+            ))
+            .unwrap();
+        store
+            .insert(&entry("bob", "create_connection", true, Duration::hours(1)))
+            .unwrap();
+
+        let all = store.query(&AuditQuery::default()).unwrap();
+        assert_eq!(all.total, 3);
+        assert_eq!(all.entries[0].user, "bob");
+        assert_eq!(all.entries[0].role, Some(UserRole::Operator));
+        assert_eq!(all.entries[0].details["connection_id"], "123");
+
+        let alice = store
+            .query(&AuditQuery {
+                user: Some("alice".to_string()),
+                success: Some(true),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(alice.total, 1);
+        assert_eq!(alice.entries[0].action, "create_connection");
+
+        let recent = store
+            .query(&AuditQuery {
+                since: Some(Utc::now() - Duration::minutes(150)),
+                action: Some("create_connection".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(recent.total, 1);
+        assert_eq!(recent.entries[0].user, "bob");
+
+        let page = store
+            .query(&AuditQuery {
+                offset: 1,
+                limit: Some(1),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.entries.len(), 1);
+        assert_eq!(page.entries[0].action, "delete_connection");
+
+        let deletes = store
+            .query(&AuditQuery {
+                search: Some("DELETE".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(deletes.total, 1);
+        assert_eq!(deletes.entries[0].user, "alice");
+
+        let tenant = store
+            .query(&AuditQuery {
+                users: Some(vec!["bob".to_string(), "carol".to_string()]),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(tenant.total, 1);
+        assert_eq!(tenant.entries[0].user, "bob");
+        let nobody = store
+            .query(&AuditQuery {
+                users: Some(Vec::new()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(nobody.total, 0);
+
+        let by_user = store
+            .query(&AuditQuery {
+                sort: Some(AuditSort::User),
+                order: Some(SortOrder::Asc),
+                ..Default::default()
+            })
+            .unwrap();
+        let order: Vec<_> = by_user
+            .entries
+            .iter()
+            .map(|e| (e.user.as_str(), e.action.as_str()))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                ("alice", "delete_connection"),
+                ("alice", "create_connection"),
+                ("bob", "create_connection"),
+            ]
+        );
+
+        let oldest_first = store
+            .query(&AuditQuery {
+                order: Some(SortOrder::Asc),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(oldest_first.entries[0].action, "create_connection");
+        assert_eq!(oldest_first.entries[0].user, "alice");
+    }
+
+    #[test]
+    fn test_retention() {
+        let store = AuditStore::open(&AuditConfig {
+            path: None,
+            retention_days: 30,
+            max_entries: 2,
+        })
+        .unwrap();
+        store
+            .insert(&entry("alice", "login", true, Duration::days(31)))
+            .unwrap();
+        for hours in 1..=3 {
+            store
+                .insert(&entry("bob", "login", true, Duration::hours(hours)))
+                .unwrap();
+        }
+
+        assert_eq!(store.prune().unwrap(), 2);
+        let left = store.query(&AuditQuery::default()).unwrap();
+        assert_eq!(left.total, 2);
+        assert!(left.entries.iter().all(|e| e.user == "bob"));
+    }
+
+    #[test]
+    fn test_batched_writes() {
+        let store = AuditStore::in_memory();
+        let count = WRITE_BATCH_SIZE * 2 + 1;
+        for _ in 0..count {
+            store
+                .insert(&entry("alice", "login", true, Duration::zero()))
+                .unwrap();
+        }
+        assert_eq!(store.query(&AuditQuery::default()).unwrap().total, count);
+    }
+
+    #[test]
+    fn test_persistence() {
+        let path = std::env::temp_dir().join(format!("omnitak-audit-{}.db", Uuid::new_v4()));
+        let config = AuditConfig {
+            path: Some(path.clone()),
+            ..Default::default()
+        };
+
+        let logged = entry("alice", "restore_backup", true, Duration::zero());
+        AuditStore::open(&config).unwrap().insert(&logged).unwrap();
+
+        let reopened = AuditStore::open(&config).unwrap();
+        let page = reopened.query(&AuditQuery::default()).unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.entries[0].id, logged.id);
+        assert_eq!(
+            page.entries[0].timestamp.timestamp_micros(),
+            logged.timestamp.timestamp_micros()
+        );
+
+        drop(reopened);
+        for suffix in ["", "-wal", "-shm"] {
+            let mut file = path.clone().into_os_string();
+            file.push(suffix);
+            let _ = std::fs::remove_file(file);
+        }
+    }
+}
@@ -1,5 +1,12 @@
 //! Authentication and authorization with JWT and API keys
-
+//!
+//! Revoked access tokens, per-user revocations and refresh tokens are kept
+//! in memory, and also in a [`TokenStore`] when one is opened, so that they
+//! survive restarts.
+
+use crate::middleware::{AuditLogger, resolve_client_ip};
+use crate::rbac::{Permission, Policy, RoleDefinition};
+use crate::token_store::TokenStore;
 use crate::types::{ErrorResponse, UserRole};
 use anyhow::{Context, Result, anyhow};
 use argon2::{
@@ -8,8 +15,8 @@ use argon2::{
 };
 use axum::{
     Json, RequestPartsExt,
-    extract::FromRequestParts,
-    http::{StatusCode, request::Parts},
+    extract::{FromRequestParts, Query},
+    http::{StatusCode, header, request::Parts},
     response::{IntoResponse, Response},
 };
 use axum_extra::{
@@ -20,7 +27,11 @@ use chrono::{DateTime, Duration, Utc};
 use dashmap::DashMap;
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use tracing::error;
 use uuid::Uuid;
 
 // ============================================================================
@@ -35,11 +46,21 @@ pub struct AuthConfig {
     /// JWT token expiration duration
     pub jwt_expiration: Duration,
 
+    /// Refresh token lifetime; each refresh issues a new one
+    pub refresh_expiration: Duration,
+
     /// Enable API key authentication
     pub enable_api_keys: bool,
 
     /// Require authentication for all endpoints
     pub require_auth: bool,
+
+    /// Custom roles users and API keys can be given
+    pub roles: Vec<RoleDefinition>,
+
+    /// SQLite database keeping revocations and refresh tokens across
+    /// restarts; they are kept in memory only when unset
+    pub token_store: Option<PathBuf>,
 }
 
 impl Default for AuthConfig {
@@ -47,8 +68,11 @@ impl Default for AuthConfig {
         Self {
             jwt_secret: "change-me-in-production".to_string(),
             jwt_expiration: Duration::hours(24),
+            refresh_expiration: Duration::days(7),
             enable_api_keys: true,
             require_auth: true,
+            roles: Vec::new(),
+            token_store: None,
         }
     }
 }
@@ -73,6 +97,19 @@ pub struct Claims {
 
     /// JWT ID
     pub jti: String,
+
+    /// The user's token generation when issued; revoking all of a user's
+    /// tokens moves the user to the next generation
+    #[serde(default)]
+    pub generation: u64,
+
+    /// Tenant namespace, absent for users that see every namespace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+
+    /// Custom role replacing the permissions of `role`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_role: Option<String>,
 }
 
 impl Claims {
@@ -84,6 +121,9 @@ impl Claims {
             iat: now.timestamp(),
             exp: (now + expiration).timestamp(),
             jti: Uuid::new_v4().to_string(),
+            generation: 0,
+            tenant: None,
+            custom_role: None,
         }
     }
 }
@@ -98,6 +138,9 @@ pub struct User {
     pub username: String,
     pub password_hash: String,
     pub role: UserRole,
+    /// Custom role whose permissions replace those of `role`
+    pub custom_role: Option<String>,
+    pub tenant: Option<String>,
     pub enabled: bool,
     pub created_at: DateTime<Utc>,
 }
@@ -108,12 +151,34 @@ pub struct ApiKey {
     pub key_hash: String,
     pub name: String,
     pub role: UserRole,
+    /// Custom role whose permissions replace those of `role`
+    pub custom_role: Option<String>,
+    pub tenant: Option<String>,
     pub enabled: bool,
     pub created_at: DateTime<Utc>,
     pub expires_at: Option<DateTime<Utc>>,
     pub last_used: Option<DateTime<Utc>>,
 }
 
+/// Refresh token as held by the server; the token itself is not kept
+#[derive(Debug, Clone)]
+pub struct RefreshToken {
+    pub username: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Access token together with the refresh token that renews it
+#[derive(Debug, Clone)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub expires_at: DateTime<Utc>,
+    pub refresh_token: String,
+    pub refresh_expires_at: DateTime<Utc>,
+    pub role: UserRole,
+    pub custom_role: Option<String>,
+    pub permissions: BTreeSet<Permission>,
+}
+
 // ============================================================================
 // Authentication Service
 // ============================================================================
@@ -122,6 +187,14 @@ pub struct AuthService {
     config: AuthConfig,
     pub users: Arc<DashMap<String, User>>,
     api_keys: Arc<DashMap<String, ApiKey>>,
+    /// Refresh tokens by SHA-256 of the token
+    refresh_tokens: Arc<DashMap<String, RefreshToken>>,
+    /// Denylisted access token IDs and when each token expires
+    revoked_tokens: Arc<DashMap<String, i64>>,
+    /// Token generation by user ID; older access tokens are revoked
+    token_generations: Arc<DashMap<String, u64>>,
+    token_store: OnceLock<TokenStore>,
+    policy: Policy,
     encoding_key: EncodingKey,
     decoding_key: DecodingKey,
 }
@@ -130,16 +203,48 @@ impl AuthService {
     pub fn new(config: AuthConfig) -> Self {
         let encoding_key = EncodingKey::from_secret(config.jwt_secret.as_bytes());
         let decoding_key = DecodingKey::from_secret(config.jwt_secret.as_bytes());
+        let policy = Policy::new(&config.roles);
 
         Self {
             config,
             users: Arc::new(DashMap::new()),
             api_keys: Arc::new(DashMap::new()),
+            refresh_tokens: Arc::new(DashMap::new()),
+            revoked_tokens: Arc::new(DashMap::new()),
+            token_generations: Arc::new(DashMap::new()),
+            token_store: OnceLock::new(),
+            policy,
             encoding_key,
             decoding_key,
         }
     }
 
+    /// Keep revocations and refresh tokens in a database, restoring those
+    /// it already holds
+    pub fn open_token_store(&self, path: &Path) -> Result<()> {
+        let (store, state) = TokenStore::open(path)?;
+        for (digest, token) in state.refresh_tokens {
+            self.refresh_tokens.insert(digest, token);
+        }
+        for (jti, expires_at) in state.revoked_tokens {
+            self.revoked_tokens.insert(jti, expires_at);
+        }
+        for (user_id, generation) in state.generations {
+            self.token_generations.insert(user_id, generation);
+        }
+        self.token_store
+            .set(store)
+            .map_err(|_| anyhow!("Token store already open"))
+    }
+
+    /// Apply a change to the token store, if one is open
+    fn persist(&self, change: impl FnOnce(&TokenStore) -> Result<()>) -> Result<()> {
+        match self.token_store.get() {
+            Some(store) => change(store).context("Failed to update the token store"),
+            None => Ok(()),
+        }
+    }
+
     /// Hash a password using Argon2
     pub fn hash_password(&self, password: &str) -> Result<String> {
         let salt = SaltString::generate(&mut OsRng);
@@ -163,16 +268,46 @@ impl AuthService {
 
     /// Create a new user
     pub fn create_user(&self, username: String, password: &str, role: UserRole) -> Result<Uuid> {
+        self.create_tenant_user(username, password, role, None)
+    }
+
+    /// Create a new user confined to a tenant namespace, or to none
+    pub fn create_tenant_user(
+        &self,
+        username: String,
+        password: &str,
+        role: UserRole,
+        tenant: Option<String>,
+    ) -> Result<Uuid> {
         if self.users.contains_key(&username) {
             return Err(anyhow!("User already exists"));
         }
 
         let password_hash = self.hash_password(password)?;
+        self.create_user_with_hash(username, password_hash, role, tenant)
+    }
+
+    /// Create a user from an Argon2 hash, as stored in configuration files
+    pub fn create_user_with_hash(
+        &self,
+        username: String,
+        password_hash: String,
+        role: UserRole,
+        tenant: Option<String>,
+    ) -> Result<Uuid> {
+        if self.users.contains_key(&username) {
+            return Err(anyhow!("User already exists"));
+        }
+        PasswordHash::new(&password_hash)
+            .map_err(|e| anyhow!("Invalid password hash for {}: {}", username, e))?;
+
         let user = User {
-            id: Uuid::new_v4(),
+            id: user_id(&username),
             username: username.clone(),
             password_hash,
             role,
+            custom_role: None,
+            tenant,
             enabled: true,
             created_at: Utc::now(),
         };
@@ -197,7 +332,53 @@ impl AuthService {
             return Err(anyhow!("Invalid credentials"));
         }
 
-        let claims = Claims::new(user.id.to_string(), user.role, self.config.jwt_expiration);
+        self.access_token(&user)
+    }
+
+    /// Authenticate user and issue an access token and a refresh token
+    pub fn login_with_refresh(&self, username: &str, password: &str) -> Result<TokenPair> {
+        let (access_token, expires_at) = self.login(username, password)?;
+        self.token_pair(username, access_token, expires_at)
+    }
+
+    /// Exchange a refresh token for a new access token and refresh token.
+    /// The refresh token is used up either way.
+    pub fn refresh(&self, refresh_token: &str) -> Result<TokenPair> {
+        let digest = token_digest(refresh_token);
+        let (_, record) = self
+            .refresh_tokens
+            .remove(&digest)
+            .ok_or_else(|| anyhow!("Invalid refresh token"))?;
+        self.persist(|store| store.remove_refresh_token(&digest))?;
+        if record.expires_at <= Utc::now() {
+            return Err(anyhow!("Refresh token expired"));
+        }
+
+        let (access_token, expires_at) = {
+            let user = self
+                .users
+                .get(&record.username)
+                .ok_or_else(|| anyhow!("User no longer exists"))?;
+            if !user.enabled {
+                return Err(anyhow!("User account is disabled"));
+            }
+            self.access_token(&user)?
+        };
+        self.token_pair(&record.username, access_token, expires_at)
+    }
+
+    /// Sign an access token for a user
+    fn access_token(&self, user: &User) -> Result<(String, DateTime<Utc>)> {
+        let user_id = user.id.to_string();
+        let claims = Claims {
+            generation: self
+                .token_generations
+                .get(&user_id)
+                .map_or(0, |generation| *generation),
+            tenant: user.tenant.clone(),
+            custom_role: user.custom_role.clone(),
+            ..Claims::new(user_id, user.role, self.config.jwt_expiration)
+        };
 
         let expires_at = DateTime::from_timestamp(claims.exp, 0)
             .ok_or_else(|| anyhow!("Invalid expiration timestamp"))?;
@@ -208,12 +389,115 @@ impl AuthService {
         Ok((token, expires_at))
     }
 
-    /// Verify and decode JWT token
+    /// Pair an access token with a new refresh token for the user
+    fn token_pair(
+        &self,
+        username: &str,
+        access_token: String,
+        expires_at: DateTime<Utc>,
+    ) -> Result<TokenPair> {
+        let (role, custom_role) = self
+            .users
+            .get(username)
+            .map(|user| (user.role, user.custom_role.clone()))
+            .ok_or_else(|| anyhow!("Invalid credentials"))?;
+
+        let now = Utc::now();
+        self.refresh_tokens.retain(|_, t| t.expires_at > now);
+
+        let refresh_token = format!(
+            "omni_rt_{}{}",
+            Uuid::new_v4().simple(),
+            Uuid::new_v4().simple()
+        );
+        let refresh_expires_at = now + self.config.refresh_expiration;
+        let digest = token_digest(&refresh_token);
+        let record = RefreshToken {
+            username: username.to_string(),
+            expires_at: refresh_expires_at,
+        };
+        self.persist(|store| store.insert_refresh_token(&digest, &record))?;
+        self.refresh_tokens.insert(digest, record);
+
+        Ok(TokenPair {
+            access_token,
+            expires_at,
+            refresh_token,
+            refresh_expires_at,
+            role,
+            permissions: self.policy.permissions(role, custom_role.as_deref()),
+            custom_role,
+        })
+    }
+
+    /// Verify and decode JWT token, rejecting revoked tokens
     pub fn verify_token(&self, token: &str) -> Result<Claims> {
         let token_data = decode::<Claims>(token, &self.decoding_key, &Validation::default())
             .context("Invalid JWT token")?;
+        let claims = token_data.claims;
+
+        if self.revoked_tokens.contains_key(&claims.jti) {
+            return Err(anyhow!("Token has been revoked"));
+        }
+        if let Some(generation) = self.token_generations.get(&claims.sub) {
+            if claims.generation < *generation {
+                return Err(anyhow!("Token has been revoked"));
+            }
+        }
+
+        Ok(claims)
+    }
+
+    /// Denylist an access token until it expires
+    pub fn revoke_token(&self, claims: &Claims) {
+        let now = Utc::now().timestamp();
+        self.revoked_tokens.retain(|_, exp| *exp > now);
+        self.revoked_tokens.insert(claims.jti.clone(), claims.exp);
+        if let Err(e) = self.persist(|store| store.revoke_token(&claims.jti, claims.exp)) {
+            error!(error = %e, "Revoked token will be valid again after a restart");
+        }
+    }
+
+    /// Refresh token record, if the token is live
+    pub fn refresh_token_info(&self, refresh_token: &str) -> Option<RefreshToken> {
+        self.refresh_tokens
+            .get(&token_digest(refresh_token))
+            .map(|t| t.value().clone())
+            .filter(|t| t.expires_at > Utc::now())
+    }
+
+    /// Invalidate a refresh token. Returns whether it existed.
+    pub fn revoke_refresh_token(&self, refresh_token: &str) -> bool {
+        let digest = token_digest(refresh_token);
+        if let Err(e) = self.persist(|store| store.remove_refresh_token(&digest)) {
+            error!(error = %e, "Revoked refresh token will be valid again after a restart");
+        }
+        self.refresh_tokens.remove(&digest).is_some()
+    }
 
-        Ok(token_data.claims)
+    /// Revoke every access and refresh token issued to a user so far.
+    /// Returns the number of refresh tokens removed.
+    pub fn revoke_user_tokens(&self, username: &str) -> Result<usize> {
+        let user_id = self
+            .users
+            .get(username)
+            .map(|user| user.id.to_string())
+            .ok_or_else(|| anyhow!("User not found"))?;
+
+        // Tokens issued from here on carry the next generation
+        let generation = {
+            let mut generation = self.token_generations.entry(user_id.clone()).or_insert(0);
+            *generation += 1;
+            *generation
+        };
+        self.persist(|store| {
+            store.set_generation(&user_id, generation)?;
+            store.remove_user_refresh_tokens(username)
+        })?;
+
+        let before = self.refresh_tokens.len();
+        self.refresh_tokens.retain(|_, t| t.username != username);
+        Ok(before - self.refresh_tokens.len())
     }
 
     /// Generate a new API key
@@ -221,6 +505,7 @@ impl AuthService {
         &self,
         name: String,
         role: UserRole,
+        tenant: Option<String>,
         expires_at: Option<DateTime<Utc>>,
     ) -> Result<(String, Uuid)> {
         // Generate a random API key (64 hex characters)
@@ -232,6 +517,8 @@ impl AuthService {
             key_hash: key_hash.clone(),
             name,
             role,
+            custom_role: None,
+            tenant,
             enabled: true,
             created_at: Utc::now(),
             expires_at,
@@ -244,9 +531,17 @@ impl AuthService {
         Ok((api_key, key_id))
     }
 
-    /// Verify API key and return user role
-    pub fn verify_api_key(&self, api_key: &str) -> Result<UserRole> {
-        // Try to find matching API key by verifying hash
+    /// Verify API key and return its role and tenant
+    pub fn verify_api_key(&self, api_key: &str) -> Result<(UserRole, Option<String>)> {
+        let key_record = self.authenticate_api_key(api_key)?;
+        Ok((key_record.role, key_record.tenant))
+    }
+
+    /// Verify API key and return its record
+    pub fn authenticate_api_key(&self, api_key: &str) -> Result<ApiKey> {
+        // Verify against each key under a read lock only: Argon2 is slow,
+        // and a write lock would stall every other request on the shard
+        let mut found = None;
         for entry in self.api_keys.iter() {
             let key_record = entry.value();
 
@@ -263,14 +558,17 @@ impl AuthService {
 
             // Verify the key
             if self.verify_password(api_key, &key_record.key_hash)? {
-                // Update last used timestamp
-                let key_hash = key_record.key_hash.clone();
-                let role = key_record.role;
-                drop(entry);
-                if let Some(mut key) = self.api_keys.get_mut(&key_hash) {
-                    key.last_used = Some(Utc::now());
-                }
-                return Ok(role);
+                found = Some(key_record.key_hash.clone());
+                break;
+            }
+        }
+
+        // Update the last used timestamp once the iterator has released its
+        // shard; looking up while it holds one would deadlock
+        if let Some(key_hash) = found {
+            if let Some(mut key_record) = self.api_keys.get_mut(&key_hash) {
+                key_record.last_used = Some(Utc::now());
+                return Ok(key_record.clone());
             }
         }
 
@@ -288,6 +586,68 @@ impl AuthService {
         Err(anyhow!("API key not found"))
     }
 
+    /// Every API key record, for backups
+    pub fn api_key_records(&self) -> Vec<ApiKey> {
+        self.api_keys
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    /// Add a user from a backup, replacing any user of the same name
+    pub fn restore_user(&self, user: User) -> Result<()> {
+        PasswordHash::new(&user.password_hash)
+            .map_err(|e| anyhow!("Invalid password hash for {}: {}", user.username, e))?;
+        self.users.insert(user.username.clone(), user);
+        Ok(())
+    }
+
+    /// Add an API key from a backup, replacing any key with the same ID
+    pub fn restore_api_key(&self, key: ApiKey) -> Result<()> {
+        PasswordHash::new(&key.key_hash)
+            .map_err(|e| anyhow!("Invalid hash for API key {}: {}", key.name, e))?;
+        self.api_keys.retain(|_, existing| existing.id != key.id);
+        self.api_keys.insert(key.key_hash.clone(), key);
+        Ok(())
+    }
+
+    /// Role to permission mapping, including custom roles
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    /// Give a user a custom role, or take it away
+    pub fn set_custom_role(&self, username: &str, custom_role: Option<String>) -> Result<()> {
+        self.check_custom_role(custom_role.as_deref())?;
+        let mut user = self
+            .users
+            .get_mut(username)
+            .ok_or_else(|| anyhow!("User not found"))?;
+        user.custom_role = custom_role;
+        Ok(())
+    }
+
+    /// Give an API key a custom role, or take it away
+    pub fn set_api_key_custom_role(&self, key_id: Uuid, custom_role: Option<String>) -> Result<()> {
+        self.check_custom_role(custom_role.as_deref())?;
+        let mut key = self
+            .api_keys
+            .iter_mut()
+            .find(|entry| entry.value().id == key_id)
+            .ok_or_else(|| anyhow!("API key not found"))?;
+        key.custom_role = custom_role;
+        Ok(())
+    }
+
+    fn check_custom_role(&self, custom_role: Option<&str>) -> Result<()> {
+        match custom_role {
+            Some(name) if !self.policy.has_custom_role(name) => {
+                Err(anyhow!("Role '{}' is not defined", name))
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Check if user has required role
     pub fn check_role(&self, user_role: UserRole, required_role: UserRole) -> bool {
         match required_role {
@@ -298,6 +658,22 @@ impl AuthService {
     }
 }
 
+/// ID of the user with the given name, the same in every process so that
+/// revocations recorded in the token store keep applying after a restart
+fn user_id(username: &str) -> Uuid {
+    let mut bytes = [0; 16];
+    bytes.copy_from_slice(&Sha256::digest(username.as_bytes())[..16]);
+    uuid::Builder::from_custom_bytes(bytes).into_uuid()
+}
+
+/// Key a refresh token is stored under; a fast hash suffices for random tokens
+fn token_digest(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
 // ============================================================================
 // Request Extractors
 // ============================================================================
@@ -307,6 +683,10 @@ impl AuthService {
 pub struct AuthUser {
     pub user_id: Option<String>,
     pub role: UserRole,
+    /// Tenant namespace the user is confined to, `None` for all namespaces
+    pub tenant: Option<String>,
+    /// What the user's role, or custom role, allows
+    pub permissions: BTreeSet<Permission>,
 }
 
 impl AuthUser {
@@ -317,6 +697,16 @@ impl AuthUser {
             UserRole::ReadOnly => true,
         }
     }
+
+    /// Whether the user holds a permission
+    pub fn permits(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
+
+    /// Whether the user may see resources in `tenant`
+    pub fn can_access(&self, tenant: Option<&str>) -> bool {
+        self.tenant.is_none() || self.tenant.as_deref() == tenant
+    }
 }
 
 impl<S> FromRequestParts<S> for AuthUser
@@ -325,94 +715,225 @@ where
 {
     type Rejection = AuthError;
 
-    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        // Try JWT token first
-        if let Ok(TypedHeader(Authorization(bearer))) =
-            parts.extract::<TypedHeader<Authorization<Bearer>>>().await
-        {
-            // Extract auth service from state extensions
-            let auth_service = parts
-                .extensions
-                .get::<Arc<AuthService>>()
-                .ok_or(AuthError::InternalError)?;
-
-            let claims = auth_service
-                .verify_token(bearer.token())
-                .map_err(|_| AuthError::InvalidToken)?;
-
-            return Ok(AuthUser {
-                user_id: Some(claims.sub),
-                role: claims.role,
-            });
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let result = authenticate(parts).await;
+        if let Err(e) = &result {
+            audit_rejection(parts, None, e);
         }
-
-        // Try API key header
-        if let Some(api_key) = parts.headers.get("X-API-Key") {
-            let api_key = api_key.to_str().map_err(|_| AuthError::InvalidApiKey)?;
-
-            let auth_service = parts
-                .extensions
-                .get::<Arc<AuthService>>()
-                .ok_or(AuthError::InternalError)?;
-
-            let role = auth_service
-                .verify_api_key(api_key)
-                .map_err(|_| AuthError::InvalidApiKey)?;
-
-            return Ok(AuthUser {
-                user_id: None,
-                role,
-            });
-        }
-
-        Err(AuthError::MissingCredentials)
+        result
     }
 }
 
-// ============================================================================
-// Role-based access control extractors
-// ============================================================================
+async fn authenticate(parts: &mut Parts) -> Result<AuthUser, AuthError> {
+    // Try JWT token first
+    if let Ok(TypedHeader(Authorization(bearer))) =
+        parts.extract::<TypedHeader<Authorization<Bearer>>>().await
+    {
+        return token_user(parts, bearer.token());
+    }
 
-/// Require admin role
-pub struct RequireAdmin(pub AuthUser);
+    // Try API key header
+    if let Some(api_key) = parts.headers.get("X-API-Key") {
+        let api_key = api_key.to_str().map_err(|_| AuthError::InvalidApiKey)?;
+
+        let auth_service = parts
+            .extensions
+            .get::<Arc<AuthService>>()
+            .ok_or(AuthError::InternalError)?;
+
+        let key = auth_service
+            .authenticate_api_key(api_key)
+            .map_err(|_| AuthError::InvalidApiKey)?;
+
+        return Ok(AuthUser {
+            user_id: None,
+            role: key.role,
+            permissions: auth_service
+                .policy()
+                .permissions(key.role, key.custom_role.as_deref()),
+            tenant: key.tenant,
+        });
+    }
 
-impl<S> FromRequestParts<S> for RequireAdmin
-where
-    S: Send + Sync,
-{
-    type Rejection = AuthError;
+    // Browsers cannot set headers on WebSocket handshakes, so those may
+    // carry the JWT in the query instead
+    if let Some(token) = websocket_token(parts).await {
+        return token_user(parts, &token);
+    }
 
-    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let user = AuthUser::from_request_parts(parts, state).await?;
+    Err(AuthError::MissingCredentials)
+}
 
-        if !user.has_role(UserRole::Admin) {
-            return Err(AuthError::InsufficientPermissions);
-        }
+fn token_user(parts: &Parts, token: &str) -> Result<AuthUser, AuthError> {
+    // Extract auth service from state extensions
+    let auth_service = parts
+        .extensions
+        .get::<Arc<AuthService>>()
+        .ok_or(AuthError::InternalError)?;
+
+    let claims = auth_service
+        .verify_token(token)
+        .map_err(|_| AuthError::InvalidToken)?;
+
+    Ok(AuthUser {
+        permissions: auth_service
+            .policy()
+            .permissions(claims.role, claims.custom_role.as_deref()),
+        user_id: Some(claims.sub),
+        role: claims.role,
+        tenant: claims.tenant,
+    })
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    access_token: Option<String>,
+}
 
-        Ok(RequireAdmin(user))
+/// The `access_token` query parameter of a WebSocket handshake
+async fn websocket_token(parts: &mut Parts) -> Option<String> {
+    let upgrade = parts.headers.get(header::UPGRADE)?.to_str().ok()?;
+    if !upgrade.eq_ignore_ascii_case("websocket") {
+        return None;
     }
+    let Query(query) = parts.extract::<Query<TokenQuery>>().await.ok()?;
+    query.access_token
 }
 
-/// Require operator role or higher
-pub struct RequireOperator(pub AuthUser);
+/// Report a rejected request to the audit log, when the router provides one.
+/// Requests without any credentials are not recorded; clients routinely
+/// probe before logging in.
+fn audit_rejection(parts: &Parts, user: Option<&AuthUser>, error: &AuthError) {
+    let action = match error {
+        AuthError::InvalidToken | AuthError::InvalidApiKey => "auth_failed",
+        AuthError::InsufficientPermissions | AuthError::MissingPermission(_) => "access_denied",
+        AuthError::MissingCredentials | AuthError::InternalError => return,
+    };
+    let Some(audit_logger) = parts.extensions.get::<Arc<AuditLogger>>() else {
+        return;
+    };
+    audit_logger.log_auth_failure(
+        user.map(|u| u.user_id.as_deref().unwrap_or("api_key")),
+        user.map(|u| u.role),
+        action,
+        parts.uri.path(),
+        &error.to_string(),
+        Some(resolve_client_ip(&parts.extensions, &parts.headers)),
+    );
+}
 
-impl<S> FromRequestParts<S> for RequireOperator
-where
-    S: Send + Sync,
-{
-    type Rejection = AuthError;
+// ============================================================================
+// Permission extractors
+// ============================================================================
 
-    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let user = AuthUser::from_request_parts(parts, state).await?;
+/// Define an extractor that authenticates the caller and requires a
+/// permission of them
+macro_rules! permission_extractor {
+    ($(#[$doc:meta])* $name:ident => $permission:expr) => {
+        $(#[$doc])*
+        pub struct $name(pub AuthUser);
 
-        if !user.has_role(UserRole::Operator) {
-            return Err(AuthError::InsufficientPermissions);
-        }
+        impl<S> FromRequestParts<S> for $name
+        where
+            S: Send + Sync,
+        {
+            type Rejection = AuthError;
+
+            async fn from_request_parts(
+                parts: &mut Parts,
+                state: &S,
+            ) -> Result<Self, Self::Rejection> {
+                let user = AuthUser::from_request_parts(parts, state).await?;
+
+                if !user.permits($permission) {
+                    let error = AuthError::MissingPermission($permission);
+                    audit_rejection(parts, Some(&user), &error);
+                    return Err(error);
+                }
 
-        Ok(RequireOperator(user))
-    }
+                Ok($name(user))
+            }
+        }
+    };
 }
 
+permission_extractor!(
+    /// Require `connections:write`
+    RequireConnectionsWrite => Permission::ConnectionsWrite
+);
+permission_extractor!(
+    /// Require `filters:write`
+    RequireFiltersWrite => Permission::FiltersWrite
+);
+permission_extractor!(
+    /// Require `cot:send`
+    RequireCotSend => Permission::CotSend
+);
+permission_extractor!(
+    /// Require `audit:read`
+    RequireAuditRead => Permission::AuditRead
+);
+permission_extractor!(
+    /// Require `certificates:write`
+    RequireCertificatesWrite => Permission::CertificatesWrite
+);
+permission_extractor!(
+    /// Require `packages:write`
+    RequirePackagesWrite => Permission::PackagesWrite
+);
+permission_extractor!(
+    /// Require `emergencies:write`
+    RequireEmergenciesWrite => Permission::EmergenciesWrite
+);
+permission_extractor!(
+    /// Require `fts:write`
+    RequireFtsWrite => Permission::FtsWrite
+);
+permission_extractor!(
+    /// Require `devices:write`
+    RequireDevicesWrite => Permission::DevicesWrite
+);
+permission_extractor!(
+    /// Require `discovery:write`
+    RequireDiscoveryWrite => Permission::DiscoveryWrite
+);
+permission_extractor!(
+    /// Require `plugins:configure`
+    RequirePluginsConfigure => Permission::PluginsConfigure
+);
+permission_extractor!(
+    /// Require `plugins:manage`
+    RequirePluginsManage => Permission::PluginsManage
+);
+permission_extractor!(
+    /// Require `alerts:manage`
+    RequireAlertsManage => Permission::AlertsManage
+);
+permission_extractor!(
+    /// Require `logging:manage`
+    RequireLoggingManage => Permission::LoggingManage
+);
+permission_extractor!(
+    /// Require `enrollment:manage`
+    RequireEnrollmentManage => Permission::EnrollmentManage
+);
+permission_extractor!(
+    /// Require `users:manage`
+    RequireUsersManage => Permission::UsersManage
+);
+permission_extractor!(
+    /// Require `system:manage`
+    RequireSystemManage => Permission::SystemManage
+);
+permission_extractor!(
+    /// Require `traffic:tap`
+    RequireTrafficTap => Permission::TrafficTap
+);
+permission_extractor!(
+    /// Require `client_access:manage`
+    RequireClientAccessManage => Permission::ClientAccessManage
+);
+
 // ============================================================================
 // Error Handling
 // ============================================================================
@@ -431,12 +952,21 @@ pub enum AuthError {
     #[error("Insufficient permissions")]
     InsufficientPermissions,
 
+    #[error("Missing permission {0}")]
+    MissingPermission(Permission),
+
     #[error("Internal authentication error")]
     InternalError,
 }
 
 impl IntoResponse for AuthError {
     fn into_response(self) -> Response {
+        if let AuthError::MissingPermission(permission) = self {
+            let message = format!("This operation requires the {} permission", permission);
+            let body = Json(ErrorResponse::new("insufficient_permissions", message));
+            return (StatusCode::FORBIDDEN, body).into_response();
+        }
+
         let (status, error_code, message) = match self {
             AuthError::MissingCredentials => (
                 StatusCode::UNAUTHORIZED,
@@ -453,7 +983,7 @@ impl IntoResponse for AuthError {
                 "invalid_api_key",
                 "Invalid or expired API key",
             ),
-            AuthError::InsufficientPermissions => (
+            AuthError::InsufficientPermissions | AuthError::MissingPermission(_) => (
                 StatusCode::FORBIDDEN,
                 "insufficient_permissions",
                 "Insufficient permissions for this operation",
@@ -512,6 +1042,205 @@ mod tests {
         assert_eq!(claims.role, UserRole::Admin);
     }
 
+    #[test]
+    fn test_refresh_rotation() {
+        let auth = AuthService::new(AuthConfig::default());
+        auth.create_user("testuser".to_string(), "password123", UserRole::Operator)
+            .unwrap();
+
+        let pair = auth.login_with_refresh("testuser", "password123").unwrap();
+        assert_eq!(pair.role, UserRole::Operator);
+        assert!(auth.refresh_token_info(&pair.refresh_token).is_some());
+
+        let renewed = auth.refresh(&pair.refresh_token).unwrap();
+        assert_ne!(renewed.refresh_token, pair.refresh_token);
+        assert!(auth.verify_token(&renewed.access_token).is_ok());
+
+        // Each refresh token works once
+        assert!(auth.refresh(&pair.refresh_token).is_err());
+        assert!(auth.revoke_refresh_token(&renewed.refresh_token));
+        assert!(auth.refresh(&renewed.refresh_token).is_err());
+    }
+
+    #[test]
+    fn test_token_revocation() {
+        let auth = AuthService::new(AuthConfig::default());
+        auth.create_user("testuser".to_string(), "password123", UserRole::Admin)
+            .unwrap();
+
+        let first = auth.login_with_refresh("testuser", "password123").unwrap();
+        let second = auth.login_with_refresh("testuser", "password123").unwrap();
+
+        let claims = auth.verify_token(&first.access_token).unwrap();
+        auth.revoke_token(&claims);
+        assert!(auth.verify_token(&first.access_token).is_err());
+        assert!(auth.verify_token(&second.access_token).is_ok());
+
+        assert_eq!(auth.revoke_user_tokens("testuser").unwrap(), 2);
+        assert!(auth.verify_token(&second.access_token).is_err());
+        assert!(auth.refresh(&second.refresh_token).is_err());
+        assert!(auth.revoke_user_tokens("nobody").is_err());
+
+        // Tokens issued right after a revocation are valid, even within the
+        // same second
+        let third = auth.login_with_refresh("testuser", "password123").unwrap();
+        assert!(auth.verify_token(&third.access_token).is_ok());
+        assert!(auth.refresh(&third.refresh_token).is_ok());
+    }
+
+    #[test]
+    fn test_token_store() {
+        let path = std::env::temp_dir().join(format!("omnitak-tokens-{}.db", Uuid::new_v4()));
+        let service = || {
+            let auth = AuthService::new(AuthConfig::default());
+            auth.open_token_store(&path).unwrap();
+            auth.create_user("testuser".to_string(), "password123", UserRole::Admin)
+                .unwrap();
+            auth
+        };
+
+        let auth = service();
+        let revoked = auth.login_with_refresh("testuser", "password123").unwrap();
+        let kept = auth.login_with_refresh("testuser", "password123").unwrap();
+        auth.revoke_token(&auth.verify_token(&revoked.access_token).unwrap());
+        assert!(auth.revoke_refresh_token(&revoked.refresh_token));
+        drop(auth);
+
+        let restarted = service();
+        assert!(restarted.verify_token(&revoked.access_token).is_err());
+        assert!(restarted.refresh(&revoked.refresh_token).is_err());
+        assert!(restarted.verify_token(&kept.access_token).is_ok());
+        let renewed = restarted.refresh(&kept.refresh_token).unwrap();
+
+        // Revoking a user outlasts the restart too
+        restarted.revoke_user_tokens("testuser").unwrap();
+        drop(restarted);
+        let restarted = service();
+        assert!(restarted.verify_token(&kept.access_token).is_err());
+        assert!(restarted.refresh(&renewed.refresh_token).is_err());
+
+        drop(restarted);
+        for suffix in ["", "-wal", "-shm"] {
+            let mut file = path.clone().into_os_string();
+            file.push(suffix);
+            let _ = std::fs::remove_file(file);
+        }
+    }
+
+    #[test]
+    fn test_custom_role() {
+        let auth = AuthService::new(AuthConfig {
+            roles: vec![RoleDefinition {
+                name: "dispatcher".to_string(),
+                permissions: vec![Permission::CotSend, Permission::EmergenciesWrite],
+            }],
+            ..Default::default()
+        });
+        auth.create_user("dispatch1".to_string(), "password123", UserRole::Operator)
+            .unwrap();
+        assert!(
+            auth.set_custom_role("dispatch1", Some("supervisor".to_string()))
+                .is_err()
+        );
+        auth.set_custom_role("dispatch1", Some("dispatcher".to_string()))
+            .unwrap();
+
+        let pair = auth.login_with_refresh("dispatch1", "password123").unwrap();
+        assert_eq!(pair.role, UserRole::Operator);
+        assert_eq!(pair.custom_role.as_deref(), Some("dispatcher"));
+        assert!(pair.permissions.contains(&Permission::CotSend));
+        assert!(!pair.permissions.contains(&Permission::ConnectionsWrite));
+        assert_eq!(
+            auth.verify_token(&pair.access_token)
+                .unwrap()
+                .custom_role
+                .as_deref(),
+            Some("dispatcher")
+        );
+
+        let (key, id) = auth
+            .create_api_key("dispatch".to_string(), UserRole::ReadOnly, None, None)
+            .unwrap();
+        auth.set_api_key_custom_role(id, Some("dispatcher".to_string()))
+            .unwrap();
+        let record = auth.authenticate_api_key(&key).unwrap();
+        assert_eq!(record.custom_role.as_deref(), Some("dispatcher"));
+    }
+
+    #[test]
+    fn test_tenant_credentials() {
+        let auth = AuthService::new(AuthConfig::default());
+
+        auth.create_tenant_user(
+            "red-op".to_string(),
+            "password123",
+            UserRole::Operator,
+            Some("red".to_string()),
+        )
+        .unwrap();
+        let (token, _expires) = auth.login("red-op", "password123").unwrap();
+        assert_eq!(
+            auth.verify_token(&token).unwrap().tenant.as_deref(),
+            Some("red")
+        );
+
+        let (key, _id) = auth
+            .create_api_key(
+                "blue".to_string(),
+                UserRole::ReadOnly,
+                Some("blue".to_string()),
+                None,
+            )
+            .unwrap();
+        let (role, tenant) = auth.verify_api_key(&key).unwrap();
+        assert_eq!(role, UserRole::ReadOnly);
+        assert_eq!(tenant.as_deref(), Some("blue"));
+
+        let user = AuthUser {
+            user_id: None,
+            role,
+            tenant,
+            permissions: BTreeSet::new(),
+        };
+        assert!(user.can_access(Some("blue")));
+        assert!(!user.can_access(Some("red")));
+        assert!(!user.can_access(None));
+    }
+
+    #[tokio::test]
+    async fn test_websocket_query_token() {
+        let auth = Arc::new(AuthService::new(AuthConfig::default()));
+        auth.create_user("viewer".to_string(), "password123", UserRole::ReadOnly)
+            .unwrap();
+        let (token, _expires) = auth.login("viewer", "password123").unwrap();
+
+        let parts = |upgrade: bool, query: &str| {
+            let mut request =
+                axum::http::Request::builder().uri(format!("/api/v1/tracks/stream?{}", query));
+            if upgrade {
+                request = request.header(header::UPGRADE, "websocket");
+            }
+            let (mut parts, ()) = request.body(()).unwrap().into_parts();
+            parts.extensions.insert(auth.clone());
+            parts
+        };
+
+        let user = authenticate(&mut parts(true, &format!("access_token={}", token)))
+            .await
+            .unwrap();
+        assert_eq!(user.role, UserRole::ReadOnly);
+
+        // Only handshakes may carry it, and it must be valid
+        assert!(matches!(
+            authenticate(&mut parts(false, &format!("access_token={}", token))).await,
+            Err(AuthError::MissingCredentials)
+        ));
+        assert!(matches!(
+            authenticate(&mut parts(true, "access_token=bogus")).await,
+            Err(AuthError::InvalidToken)
+        ));
+    }
+
     #[test]
     fn test_role_checking() {
         let auth = AuthService::new(AuthConfig::default());
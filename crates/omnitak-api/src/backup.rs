@@ -0,0 +1,435 @@
+//! Backup archives of system state
+//!
+//! A backup is a ZIP archive holding `manifest.json` and one file per kind
+//! of state. The manifest names the format version and the SHA-256 of every
+//! other file; an archive is only read if each listed file is present with
+//! that hash and nothing else is in it.
+//!
+//! Password and API key hashes are included so accounts and keys keep
+//! working after a restore, and the configuration file is stored as
+//! written, so backups must be kept as carefully as the configuration.
+//! Certificate private keys are never included; only certificate metadata
+//! is, so the certificates can be imported again on the new instance.
+
+use crate::auth::{ApiKey, User};
+use crate::types::{CreateConnectionRequest, StoredCertificateInfo, UserRole};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read, Write};
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+/// Identifies a backup manifest
+pub const FORMAT: &str = "omnitak-backup";
+
+/// Newest format version this build writes and reads
+pub const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const CONFIG: &str = "config.yaml";
+const USERS: &str = "users.json";
+const API_KEYS: &str = "api_keys.json";
+const CONNECTIONS: &str = "connections.json";
+const CERTIFICATES: &str = "certificates.json";
+const PLUGINS: &str = "plugins.json";
+
+/// Largest file read from an archive
+const MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    #[error("Not a backup archive: {0}")]
+    NotABackup(String),
+
+    #[error("Backup format version {0} is newer than this server supports ({FORMAT_VERSION})")]
+    UnsupportedVersion(u32),
+
+    #[error("Backup is missing {0}")]
+    MissingFile(String),
+
+    #[error("Checksum mismatch for {0}; the backup is corrupt or was modified")]
+    ChecksumMismatch(String),
+
+    #[error("Backup contains {0}, which is not in its manifest")]
+    UnexpectedFile(String),
+
+    #[error("Invalid {file}: {reason}")]
+    Invalid { file: String, reason: String },
+
+    #[error("Archive error: {0}")]
+    Archive(String),
+}
+
+impl From<zip::result::ZipError> for BackupError {
+    fn from(e: zip::result::ZipError) -> Self {
+        Self::Archive(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for BackupError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Archive(e.to_string())
+    }
+}
+
+/// `manifest.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Always [`FORMAT`]
+    pub format: String,
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    /// Version of the server that wrote the backup
+    pub server_version: String,
+    /// SHA-256 (lowercase hex) of every other file, by name
+    pub files: BTreeMap<String, String>,
+}
+
+/// A user account with its password hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserRecord {
+    pub username: String,
+    pub password_hash: String,
+    pub role: UserRole,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_role: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An API key with the hash it is checked against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    pub id: Uuid,
+    pub name: String,
+    pub key_hash: String,
+    pub role: UserRole,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_role: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Everything a backup holds
+#[derive(Debug, Clone, Default)]
+pub struct BackupContents {
+    /// Configuration file as written, with references unresolved
+    pub config: Option<String>,
+    pub users: Vec<UserRecord>,
+    pub api_keys: Vec<ApiKeyRecord>,
+    /// Connections created through the API
+    pub connections: Vec<CreateConnectionRequest>,
+    /// Metadata of stored certificates; the certificates themselves are not
+    /// backed up
+    pub certificates: Vec<StoredCertificateInfo>,
+    /// Settings of loaded plugins, by plugin ID
+    pub plugins: BTreeMap<String, serde_json::Map<String, serde_json::Value>>,
+}
+
+impl BackupContents {
+    /// Write the contents and their manifest as a ZIP archive
+    pub fn to_archive(&self) -> Result<Vec<u8>, BackupError> {
+        let mut files: Vec<(&str, Vec<u8>)> = vec![
+            (USERS, to_json(&self.users)?),
+            (API_KEYS, to_json(&self.api_keys)?),
+            (CONNECTIONS, to_json(&self.connections)?),
+            (CERTIFICATES, to_json(&self.certificates)?),
+            (PLUGINS, to_json(&self.plugins)?),
+        ];
+        if let Some(config) = &self.config {
+            files.insert(0, (CONFIG, config.clone().into_bytes()));
+        }
+
+        let manifest = BackupManifest {
+            format: FORMAT.to_string(),
+            version: FORMAT_VERSION,
+            created_at: Utc::now(),
+            server_version: env!("CARGO_PKG_VERSION").to_string(),
+            files: files
+                .iter()
+                .map(|(name, data)| (name.to_string(), sha256_hex(data)))
+                .collect(),
+        };
+
+        let mut buffer = Cursor::new(Vec::new());
+        {
+            let mut zip = ZipWriter::new(&mut buffer);
+            let options = SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated)
+                .unix_permissions(0o600);
+
+            zip.start_file(MANIFEST, options)?;
+            zip.write_all(&to_json(&manifest)?)?;
+            for (name, data) in &files {
+                zip.start_file(*name, options)?;
+                zip.write_all(data)?;
+            }
+            zip.finish()?;
+        }
+        Ok(buffer.into_inner())
+    }
+
+    /// Read an archive, checking its format version and the checksum of
+    /// every file before parsing any of them
+    pub fn from_archive(data: &[u8]) -> Result<(BackupManifest, Self), BackupError> {
+        let mut archive = ZipArchive::new(Cursor::new(data))
+            .map_err(|e| BackupError::NotABackup(e.to_string()))?;
+
+        let manifest: BackupManifest = parse(MANIFEST, &read_file(&mut archive, MANIFEST)?)?;
+        if manifest.format != FORMAT {
+            return Err(BackupError::NotABackup(format!(
+                "manifest format is '{}'",
+                manifest.format
+            )));
+        }
+        if manifest.version > FORMAT_VERSION {
+            return Err(BackupError::UnsupportedVersion(manifest.version));
+        }
+
+        if let Some(name) = archive
+            .file_names()
+            .find(|name| *name != MANIFEST && !manifest.files.contains_key(*name))
+        {
+            return Err(BackupError::UnexpectedFile(name.to_string()));
+        }
+
+        let mut files = BTreeMap::new();
+        for (name, expected) in &manifest.files {
+            let data = read_file(&mut archive, name)?;
+            if !sha256_hex(&data).eq_ignore_ascii_case(expected) {
+                return Err(BackupError::ChecksumMismatch(name.clone()));
+            }
+            files.insert(name.as_str(), data);
+        }
+
+        let config = files
+            .get(CONFIG)
+            .map(|data| {
+                String::from_utf8(data.clone()).map_err(|e| BackupError::Invalid {
+                    file: CONFIG.to_string(),
+                    reason: e.to_string(),
+                })
+            })
+            .transpose()?;
+        let contents = Self {
+            config,
+            users: parse_optional(&files, USERS)?,
+            api_keys: parse_optional(&files, API_KEYS)?,
+            connections: parse_optional(&files, CONNECTIONS)?,
+            certificates: parse_optional(&files, CERTIFICATES)?,
+            plugins: parse_optional(&files, PLUGINS)?,
+        };
+        Ok((manifest, contents))
+    }
+}
+
+impl From<&User> for UserRecord {
+    fn from(user: &User) -> Self {
+        Self {
+            username: user.username.clone(),
+            password_hash: user.password_hash.clone(),
+            role: user.role,
+            custom_role: user.custom_role.clone(),
+            tenant: user.tenant.clone(),
+            enabled: user.enabled,
+            created_at: user.created_at,
+        }
+    }
+}
+
+impl From<UserRecord> for User {
+    fn from(record: UserRecord) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            username: record.username,
+            password_hash: record.password_hash,
+            role: record.role,
+            custom_role: record.custom_role,
+            tenant: record.tenant,
+            enabled: record.enabled,
+            created_at: record.created_at,
+        }
+    }
+}
+
+impl From<&ApiKey> for ApiKeyRecord {
+    fn from(key: &ApiKey) -> Self {
+        Self {
+            id: key.id,
+            name: key.name.clone(),
+            key_hash: key.key_hash.clone(),
+            role: key.role,
+            custom_role: key.custom_role.clone(),
+            tenant: key.tenant.clone(),
+            enabled: key.enabled,
+            created_at: key.created_at,
+            expires_at: key.expires_at,
+        }
+    }
+}
+
+impl From<ApiKeyRecord> for ApiKey {
+    fn from(record: ApiKeyRecord) -> Self {
+        Self {
+            id: record.id,
+            key_hash: record.key_hash,
+            name: record.name,
+            role: record.role,
+            custom_role: record.custom_role,
+            tenant: record.tenant,
+            enabled: record.enabled,
+            created_at: record.created_at,
+            expires_at: record.expires_at,
+            last_used: None,
+        }
+    }
+}
+
+/// SHA-256 of `data`, lowercase hex
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Vec<u8>, BackupError> {
+    serde_json::to_vec_pretty(value).map_err(|e| BackupError::Archive(e.to_string()))
+}
+
+fn read_file<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<Vec<u8>, BackupError> {
+    let file = archive
+        .by_name(name)
+        .map_err(|_| BackupError::MissingFile(name.to_string()))?;
+    if file.size() > MAX_FILE_SIZE {
+        return Err(BackupError::Invalid {
+            file: name.to_string(),
+            reason: format!("larger than {} bytes", MAX_FILE_SIZE),
+        });
+    }
+    let mut data = Vec::new();
+    file.take(MAX_FILE_SIZE).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn parse<T: DeserializeOwned>(name: &str, data: &[u8]) -> Result<T, BackupError> {
+    serde_json::from_slice(data).map_err(|e| BackupError::Invalid {
+        file: name.to_string(),
+        reason: e.to_string(),
+    })
+}
+
+/// Files may be left out of a backup; what they hold is then empty
+fn parse_optional<T: DeserializeOwned + Default>(
+    files: &BTreeMap<&str, Vec<u8>>,
+    name: &str,
+) -> Result<T, BackupError> {
+    files
+        .get(name)
+        .map_or_else(|| Ok(T::default()), |data| parse(name, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents() -> BackupContents {
+        BackupContents {
+            config: Some("api:\n  jwt_secret: \"${JWT_SECRET}\"\n".to_string()),
+            users: vec![UserRecord {
+                username: "ops".to_string(),
+                password_hash: "$argon2id$v=19$m=19456,t=2,p=1$c2FsdHNhbHQ$aGFzaA".to_string(),
+                role: UserRole::Operator,
+                custom_role: None,
+                tenant: Some("blue".to_string()),
+                enabled: true,
+                created_at: Utc::now(),
+            }],
+            plugins: [(
+                "keyword-filter".to_string(),
+                serde_json::json!({"keywords": ["hostile"]})
+                    .as_object()
+                    .unwrap()
+                    .clone(),
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Rewrite an archive, changing one file's data and keeping its manifest
+    fn tamper(archive: &[u8], name: &str, data: &[u8]) -> Vec<u8> {
+        let mut source = ZipArchive::new(Cursor::new(archive)).unwrap();
+        let mut buffer = Cursor::new(Vec::new());
+        {
+            let mut zip = ZipWriter::new(&mut buffer);
+            for i in 0..source.len() {
+                let mut file = source.by_index(i).unwrap();
+                let file_name = file.name().to_string();
+                let mut original = Vec::new();
+                file.read_to_end(&mut original).unwrap();
+                zip.start_file(file_name.as_str(), SimpleFileOptions::default())
+                    .unwrap();
+                zip.write_all(if file_name == name { data } else { &original })
+                    .unwrap();
+            }
+            zip.finish().unwrap();
+        }
+        buffer.into_inner()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let archive = contents().to_archive().unwrap();
+        let (manifest, restored) = BackupContents::from_archive(&archive).unwrap();
+
+        assert_eq!(manifest.format, FORMAT);
+        assert_eq!(manifest.version, FORMAT_VERSION);
+        assert_eq!(manifest.files.len(), 6);
+        assert_eq!(restored.config, contents().config);
+        assert_eq!(restored.users[0].username, "ops");
+        assert_eq!(restored.users[0].tenant.as_deref(), Some("blue"));
+        assert_eq!(restored.plugins["keyword-filter"]["keywords"][0], "hostile");
+        assert!(restored.api_keys.is_empty());
+    }
+
+    #[test]
+    fn test_detects_modification() {
+        let archive = contents().to_archive().unwrap();
+
+        let modified = tamper(&archive, USERS, b"[]");
+        assert!(matches!(
+            BackupContents::from_archive(&modified),
+            Err(BackupError::ChecksumMismatch(name)) if name == USERS
+        ));
+
+        assert!(matches!(
+            BackupContents::from_archive(b"not a zip"),
+            Err(BackupError::NotABackup(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_newer_version() {
+        let archive = contents().to_archive().unwrap();
+        let (mut manifest, _) = BackupContents::from_archive(&archive).unwrap();
+        manifest.version = FORMAT_VERSION + 1;
+
+        let newer = tamper(&archive, MANIFEST, &serde_json::to_vec(&manifest).unwrap());
+        assert!(matches!(
+            BackupContents::from_archive(&newer),
+            Err(BackupError::UnsupportedVersion(v)) if v == FORMAT_VERSION + 1
+        ));
+    }
+}
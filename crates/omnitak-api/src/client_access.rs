@@ -0,0 +1,417 @@
+//! Client certificate authorization
+//!
+//! Decides what a TAK client connecting to a TLS listener with a client
+//! certificate may do. Rules match the certificate's subject CN (with `*`
+//! wildcards), its organizational units and the CN of the CA that issued
+//! it; the first matching rule gives the client's group and access:
+//!
+//! - `publish`: receive the feed and send CoT (the default)
+//! - `read_only`: receive the feed; CoT sent by the client is dropped
+//! - `deny`: the connection is closed after the handshake
+//!
+//! Clients no rule matches, and clients that present no certificate, get
+//! `default_access`. Rules are loaded from
+//! configuration and can be managed at runtime through
+//! `/api/v1/client-access/rules`; changes apply to new connections.
+
+use metrics::{counter, describe_counter};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// What a client may do on its connection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientAccess {
+    /// Close the connection
+    Deny,
+    /// Receive the feed only
+    ReadOnly,
+    /// Receive the feed and send CoT
+    #[default]
+    Publish,
+}
+
+impl ClientAccess {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClientAccess::Deny => "deny",
+            ClientAccess::ReadOnly => "read_only",
+            ClientAccess::Publish => "publish",
+        }
+    }
+
+    /// Whether CoT sent by the client is accepted
+    pub fn can_publish(&self) -> bool {
+        *self == ClientAccess::Publish
+    }
+}
+
+/// Rule matching client certificates
+///
+/// All conditions given must hold; a rule without conditions matches every
+/// certificate.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ClientAccessRule {
+    /// Unique name, used in logs and the API
+    pub name: String,
+    /// Subject CN pattern; `*` matches any run of characters (`ATAK-*`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cn: Option<String>,
+    /// Organizational unit the subject must include
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ou: Option<String>,
+    /// CN of the issuing CA; `*` wildcards as for `cn`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    /// Group the matched clients belong to, reported in logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    pub access: ClientAccess,
+}
+
+impl ClientAccessRule {
+    pub fn matches(&self, identity: &ClientIdentity) -> bool {
+        self.cn
+            .as_deref()
+            .is_none_or(|pattern| wildcard_match(pattern, &identity.cn))
+            && self
+                .ou
+                .as_deref()
+                .is_none_or(|ou| identity.ous.iter().any(|o| o == ou))
+            && self
+                .issuer
+                .as_deref()
+                .is_none_or(|pattern| wildcard_match(pattern, &identity.issuer))
+    }
+}
+
+/// Client certificate authorization settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClientAccessConfig {
+    /// Rules in the order they are tried
+    #[serde(default)]
+    pub rules: Vec<ClientAccessRule>,
+    /// Access of clients no rule matches or without a certificate
+    #[serde(default)]
+    pub default_access: ClientAccess,
+}
+
+impl ClientAccessConfig {
+    /// Check every rule and that rule names are unique
+    pub fn validate(&self) -> Result<(), ClientAccessError> {
+        let mut names = std::collections::HashSet::new();
+        for rule in &self.rules {
+            validate(rule)?;
+            if !names.insert(rule.name.as_str()) {
+                return Err(ClientAccessError::Exists(rule.name.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Evaluation
+// ============================================================================
+
+/// Certificate attributes rules match against
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ClientIdentity {
+    /// Subject common name
+    pub cn: String,
+    /// Subject organizational units
+    #[serde(default)]
+    pub ous: Vec<String>,
+    /// Common name of the issuing CA
+    #[serde(default)]
+    pub issuer: String,
+}
+
+impl ClientIdentity {
+    /// Read the identity from a DER-encoded client certificate
+    pub fn from_der(der: &[u8]) -> anyhow::Result<Self> {
+        let info = omnitak_cert::CertificateInfo::from_der(der)?;
+        Ok(Self {
+            cn: info.subject_cn,
+            ous: info.subject_ou,
+            issuer: info.issuer_cn,
+        })
+    }
+}
+
+/// Outcome of evaluating a client against the rules
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ClientAuthorization {
+    pub access: ClientAccess,
+    /// Rule that matched; none when the default access applied
+    pub rule: Option<String>,
+    /// Group of the matched rule
+    pub group: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientAccessError {
+    #[error("Rule {0} already exists")]
+    Exists(String),
+
+    #[error("Rule {0} not found")]
+    NotFound(String),
+
+    #[error("Invalid rule: {0}")]
+    Invalid(String),
+}
+
+/// Client access rules shared between the TLS listeners and the API
+pub struct ClientAccessPolicy {
+    rules: RwLock<Vec<ClientAccessRule>>,
+    default_access: ClientAccess,
+}
+
+impl ClientAccessPolicy {
+    pub fn new(config: ClientAccessConfig) -> Self {
+        describe();
+
+        Self {
+            rules: RwLock::new(config.rules),
+            default_access: config.default_access,
+        }
+    }
+
+    pub fn default_access(&self) -> ClientAccess {
+        self.default_access
+    }
+
+    pub fn list(&self) -> Vec<ClientAccessRule> {
+        self.rules.read().clone()
+    }
+
+    /// Append a rule, to be tried after the existing ones
+    pub fn add(&self, rule: ClientAccessRule) -> Result<(), ClientAccessError> {
+        validate(&rule)?;
+        let mut rules = self.rules.write();
+        if rules.iter().any(|r| r.name == rule.name) {
+            return Err(ClientAccessError::Exists(rule.name));
+        }
+        rules.push(rule);
+        Ok(())
+    }
+
+    /// Replace the rule called `name`, keeping its position
+    pub fn update(&self, name: &str, rule: ClientAccessRule) -> Result<(), ClientAccessError> {
+        validate(&rule)?;
+        let mut rules = self.rules.write();
+        let index = rules
+            .iter()
+            .position(|r| r.name == name)
+            .ok_or_else(|| ClientAccessError::NotFound(name.to_string()))?;
+        if rule.name != name && rules.iter().any(|r| r.name == rule.name) {
+            return Err(ClientAccessError::Exists(rule.name));
+        }
+        rules[index] = rule;
+        Ok(())
+    }
+
+    /// Remove the rule called `name`
+    pub fn remove(&self, name: &str) -> Result<(), ClientAccessError> {
+        let mut rules = self.rules.write();
+        let before = rules.len();
+        rules.retain(|r| r.name != name);
+        if rules.len() == before {
+            return Err(ClientAccessError::NotFound(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Evaluate a client without counting the decision
+    pub fn evaluate(&self, identity: &ClientIdentity) -> ClientAuthorization {
+        match self.rules.read().iter().find(|r| r.matches(identity)) {
+            Some(rule) => ClientAuthorization {
+                access: rule.access,
+                rule: Some(rule.name.clone()),
+                group: rule.group.clone(),
+            },
+            None => ClientAuthorization {
+                access: self.default_access,
+                rule: None,
+                group: None,
+            },
+        }
+    }
+
+    /// Evaluate a connecting client
+    pub fn authorize(&self, identity: &ClientIdentity) -> ClientAuthorization {
+        let authorization = self.evaluate(identity);
+        counter!("client_access_decisions_total", "access" => authorization.access.as_str())
+            .increment(1);
+        authorization
+    }
+
+    /// Evaluate a connecting client that presented no certificate. No rule
+    /// can match it, so it gets the default access
+    pub fn authorize_anonymous(&self) -> ClientAuthorization {
+        let authorization = ClientAuthorization {
+            access: self.default_access,
+            rule: None,
+            group: None,
+        };
+        counter!("client_access_decisions_total", "access" => authorization.access.as_str())
+            .increment(1);
+        authorization
+    }
+}
+
+fn validate(rule: &ClientAccessRule) -> Result<(), ClientAccessError> {
+    if rule.name.trim().is_empty() {
+        return Err(ClientAccessError::Invalid(
+            "name must not be empty".to_string(),
+        ));
+    }
+    for (field, value) in [("cn", &rule.cn), ("ou", &rule.ou), ("issuer", &rule.issuer)] {
+        if value.as_deref().is_some_and(|v| v.is_empty()) {
+            return Err(ClientAccessError::Invalid(format!(
+                "{} must not be empty; leave it out to match any",
+                field
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Match `text` against `pattern`, where `*` matches any run of characters
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No wildcard
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+fn describe() {
+    describe_counter!(
+        "client_access_decisions_total",
+        "Client certificate authorization decisions for TLS connections"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, access: ClientAccess) -> ClientAccessRule {
+        ClientAccessRule {
+            name: name.to_string(),
+            cn: None,
+            ou: None,
+            issuer: None,
+            group: None,
+            access,
+        }
+    }
+
+    fn identity(cn: &str, ous: &[&str], issuer: &str) -> ClientIdentity {
+        ClientIdentity {
+            cn: cn.to_string(),
+            ous: ous.iter().map(|ou| ou.to_string()).collect(),
+            issuer: issuer.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("ATAK-*", "ATAK-001"));
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("*-obs-*", "team1-obs-7"));
+        assert!(wildcard_match("exact", "exact"));
+        assert!(!wildcard_match("exact", "exactly"));
+        assert!(!wildcard_match("ATAK-*", "WinTAK-1"));
+        assert!(!wildcard_match("a*b*c", "acb"));
+    }
+
+    #[test]
+    fn test_first_match_wins() {
+        let policy = ClientAccessPolicy::new(ClientAccessConfig {
+            rules: vec![
+                ClientAccessRule {
+                    cn: Some("*-observer".to_string()),
+                    group: Some("observers".to_string()),
+                    ..rule("observers", ClientAccess::ReadOnly)
+                },
+                ClientAccessRule {
+                    ou: Some("Operations".to_string()),
+                    issuer: Some("Unit CA*".to_string()),
+                    ..rule("operators", ClientAccess::Publish)
+                },
+            ],
+            default_access: ClientAccess::Deny,
+        });
+
+        let observer = policy.evaluate(&identity("ops-observer", &["Operations"], "Unit CA 1"));
+        assert_eq!(observer.access, ClientAccess::ReadOnly);
+        assert_eq!(observer.group.as_deref(), Some("observers"));
+
+        let operator = policy.evaluate(&identity("ATAK-1", &["Operations"], "Unit CA 1"));
+        assert_eq!(operator.access, ClientAccess::Publish);
+        assert_eq!(operator.rule.as_deref(), Some("operators"));
+
+        // Right OU, different CA
+        let outsider = policy.evaluate(&identity("ATAK-2", &["Operations"], "Other CA"));
+        assert_eq!(outsider.access, ClientAccess::Deny);
+        assert_eq!(outsider.rule, None);
+
+        // No certificate at all
+        assert_eq!(policy.authorize_anonymous().access, ClientAccess::Deny);
+    }
+
+    #[test]
+    fn test_manage_rules() {
+        let policy = ClientAccessPolicy::new(ClientAccessConfig::default());
+
+        policy.add(rule("a", ClientAccess::Deny)).unwrap();
+        policy.add(rule("b", ClientAccess::ReadOnly)).unwrap();
+        assert!(matches!(
+            policy.add(rule("a", ClientAccess::Deny)),
+            Err(ClientAccessError::Exists(_))
+        ));
+        assert!(matches!(
+            policy.add(ClientAccessRule {
+                cn: Some(String::new()),
+                ..rule("c", ClientAccess::Deny)
+            }),
+            Err(ClientAccessError::Invalid(_))
+        ));
+
+        policy
+            .update("a", rule("a2", ClientAccess::Publish))
+            .unwrap();
+        let names: Vec<_> = policy.list().into_iter().map(|r| r.name).collect();
+        assert_eq!(names, ["a2", "b"]);
+
+        policy.remove("b").unwrap();
+        assert!(matches!(
+            policy.remove("b"),
+            Err(ClientAccessError::NotFound(_))
+        ));
+        assert_eq!(
+            policy.evaluate(&ClientIdentity::default()).access,
+            ClientAccess::Publish
+        );
+    }
+}
@@ -1,19 +1,24 @@
 //! Discovery REST API endpoints for managing mDNS service discovery
 
-use crate::auth::{AuthUser, RequireOperator};
-use crate::rest::{ApiState, ApiError};
-use crate::types::ErrorResponse;
+use crate::auth::{AuthUser, RequireDiscoveryWrite};
+use crate::middleware::ClientIp;
+use crate::pagination::{Paginated, SortOrder, matches_search};
+use crate::rest::{ApiState, ApiError, open_connection};
+use crate::types::{ConnectionType, CreateConnectionRequest, ErrorResponse, ReconnectPolicy};
 use axum::{
-    extract::{Path, Query, State},
+    extract::{OriginalUri, Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     Json,
 };
 use chrono::{DateTime, Utc};
-use omnitak_discovery::{DiscoveredService, DiscoveryService, ServiceStatus, ServiceType};
+use omnitak_discovery::{
+    AnnouncedService, DiscoveredService, DiscoveryService, ServiceStatus, ServiceType,
+};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::{info, warn};
+use uuid::Uuid;
 use validator::Validate;
 
 // ============================================================================
@@ -25,7 +30,7 @@ use validator::Validate;
 #[serde(rename_all = "camelCase")]
 #[derive(utoipa::ToSchema)]
 pub struct DiscoveredServicesList {
-    /// Total number of services
+    /// Number of matching services across all pages
     pub total: usize,
 
     /// List of discovered services
@@ -81,6 +86,9 @@ pub struct DiscoveredServiceResponse {
 
     /// Age in seconds since last seen
     pub age_seconds: i64,
+
+    /// Connection type adopting this service creates, if it can be adopted
+    pub adopt_as: Option<ConnectionType>,
 }
 
 impl From<DiscoveredService> for DiscoveredServiceResponse {
@@ -100,6 +108,7 @@ impl From<DiscoveredService> for DiscoveredServiceResponse {
             last_seen_at: service.last_seen_at,
             seen_count: service.seen_count,
             age_seconds: service.age_seconds(),
+            adopt_as: adoption_type(&service),
         }
     }
 }
@@ -125,6 +134,85 @@ pub struct DiscoveryStatusResponse {
     pub timestamp: DateTime<Utc>,
 }
 
+/// Announcement status response
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(utoipa::ToSchema)]
+pub struct AnnouncementStatusResponse {
+    /// Whether this instance is announced via mDNS
+    pub announcing: bool,
+
+    /// Announced services with their TXT records
+    pub services: Vec<AnnouncedService>,
+
+    /// Response timestamp
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Request to start or stop announcing this instance
+#[derive(Debug, Serialize, Deserialize)]
+#[derive(utoipa::ToSchema)]
+pub struct SetAnnouncementRequest {
+    /// Announce (true) or withdraw the announcement (false)
+    pub enabled: bool,
+}
+
+/// Request to add a discovered service as a connection
+///
+/// Everything is inferred from the service's mDNS record when left out.
+/// TLS services need a client certificate: a stored one or key/cert paths.
+#[derive(Debug, Default, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+#[derive(utoipa::ToSchema)]
+pub struct AdoptServiceRequest {
+    /// Connection name (default: the service's instance name)
+    #[validate(length(min = 1, max = 100))]
+    pub name: Option<String>,
+
+    /// Connection type (default: inferred from TXT metadata and port)
+    pub connection_type: Option<ConnectionType>,
+
+    /// Port to connect to (default: the advertised port)
+    #[validate(range(min = 1, max = 65535))]
+    pub port: Option<u16>,
+
+    /// Stored certificate ID for TLS
+    pub certificate_id: Option<Uuid>,
+
+    /// TLS certificate path
+    pub tls_cert_path: Option<String>,
+
+    /// TLS key path
+    pub tls_key_path: Option<String>,
+
+    /// Validate the server's certificate (default: true)
+    pub validate_certs: Option<bool>,
+}
+
+/// Connection created from a discovered service
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(utoipa::ToSchema)]
+pub struct AdoptServiceResponse {
+    /// New connection ID
+    pub id: Uuid,
+
+    /// Connection name
+    pub name: String,
+
+    /// Connection type
+    pub connection_type: ConnectionType,
+
+    /// Address connected to
+    pub address: String,
+
+    /// Port connected to
+    pub port: u16,
+
+    /// Success message
+    pub message: String,
+}
+
 /// Request to manually trigger discovery refresh
 #[derive(Debug, Serialize, Deserialize, Validate)]
 #[derive(utoipa::ToSchema)]
@@ -137,7 +225,9 @@ pub struct RefreshRequest {
 // Query Parameters
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+/// Query of `GET /api/v1/discovery/services`
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct DiscoveryQuery {
     /// Filter by service type
     pub service_type: Option<String>,
@@ -148,6 +238,42 @@ pub struct DiscoveryQuery {
     /// Only show services with TLS support
     #[serde(default)]
     pub tls_only: bool,
+
+    /// Only services whose instance name or hostname contains this,
+    /// ignoring case
+    pub search: Option<String>,
+
+    /// Sort key (default name)
+    #[serde(default)]
+    pub sort: DiscoverySort,
+
+    /// Sort direction (default asc)
+    #[serde(default)]
+    pub order: SortOrder,
+
+    /// Services to skip
+    #[serde(default)]
+    pub offset: usize,
+
+    /// Services to return (default 100)
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+/// Sort keys of `GET /api/v1/discovery/services`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscoverySort {
+    /// Instance name
+    #[default]
+    Name,
+    Hostname,
+    Port,
+    LastSeen,
+}
+
+fn default_limit() -> usize {
+    100
 }
 
 // ============================================================================
@@ -201,13 +327,13 @@ pub async fn get_discovery_status(
 #[utoipa::path(
     get,
     path = "/api/v1/discovery/services",
-    params(
-        ("service_type" = Option<String>, Query, description = "Filter by service type"),
-        ("status" = Option<ServiceStatus>, Query, description = "Filter by status"),
-        ("tls_only" = Option<bool>, Query, description = "Only show TLS-enabled services")
-    ),
+    params(DiscoveryQuery),
     responses(
-        (status = 200, description = "Discovered services list", body = DiscoveredServicesList),
+        (status = 200, description = "Discovered services list", body = DiscoveredServicesList,
+            headers(
+                ("X-Total-Count" = usize, description = "Matching services across all pages"),
+                ("Link" = String, description = "first, prev, next and last pages")
+            )),
         (status = 401, description = "Unauthorized", body = ErrorResponse)
     ),
     security(
@@ -218,8 +344,9 @@ pub async fn get_discovery_status(
 pub async fn list_discovered_services(
     State(state): State<ApiState>,
     Query(query): Query<DiscoveryQuery>,
+    OriginalUri(uri): OriginalUri,
     _user: AuthUser,
-) -> Result<Json<DiscoveredServicesList>, ApiError> {
+) -> Result<Paginated<DiscoveredServicesList>, ApiError> {
     let discovery = state.discovery.as_ref()
         .ok_or_else(|| ApiError::NotFound("Discovery service not enabled".to_string()))?;
 
@@ -239,16 +366,36 @@ pub async fn list_discovered_services(
         services.retain(|s| s.supports_tls());
     }
 
-    let response_services: Vec<DiscoveredServiceResponse> =
-        services.into_iter().map(|s| s.into()).collect();
+    services.retain(|s| matches_search(query.search.as_deref(), &[&s.instance_name, &s.hostname]));
+    let total = services.len();
+
+    services.sort_by(|a, b| {
+        let ordering = match query.sort {
+            DiscoverySort::Name => a
+                .instance_name
+                .to_lowercase()
+                .cmp(&b.instance_name.to_lowercase()),
+            DiscoverySort::Hostname => a.hostname.to_lowercase().cmp(&b.hostname.to_lowercase()),
+            DiscoverySort::Port => a.port.cmp(&b.port),
+            DiscoverySort::LastSeen => a.last_seen_at.cmp(&b.last_seen_at),
+        };
+        query.order.apply(ordering)
+    });
+
+    let response_services: Vec<DiscoveredServiceResponse> = services
+        .into_iter()
+        .skip(query.offset)
+        .take(query.limit)
+        .map(|s| s.into())
+        .collect();
 
     let list = DiscoveredServicesList {
-        total: response_services.len(),
+        total,
         services: response_services,
         timestamp: Utc::now(),
     };
 
-    Ok(Json(list))
+    Ok(Paginated::new(list, &uri, query.offset, query.limit, total))
 }
 
 /// GET /api/v1/discovery/services/{id} - Get a specific discovered service
@@ -292,16 +439,16 @@ pub async fn get_discovered_service(
     responses(
         (status = 200, description = "Refresh triggered successfully"),
         (status = 401, description = "Unauthorized", body = ErrorResponse),
-        (status = 403, description = "Forbidden - requires operator role", body = ErrorResponse)
+        (status = 403, description = "Forbidden - requires discovery:write", body = ErrorResponse)
     ),
     security(
-        ("bearer_auth" = []),
-        ("api_key" = [])
+        ("bearer_auth" = ["discovery:write"]),
+        ("api_key" = ["discovery:write"])
     )
 )]
 pub async fn refresh_discovery(
     State(state): State<ApiState>,
-    _operator: RequireOperator,
+    _user: RequireDiscoveryWrite,
     Json(_request): Json<RefreshRequest>,
 ) -> Result<impl IntoResponse, ApiError> {
     let discovery = state.discovery.as_ref()
@@ -394,3 +541,217 @@ pub async fn list_atak_devices(
 
     Ok(Json(list))
 }
+
+/// POST /api/v1/discovery/services/{id}/adopt - Add a discovered service as a connection
+#[utoipa::path(
+    post,
+    path = "/api/v1/discovery/services/{id}/adopt",
+    params(
+        ("id" = String, Path, description = "Service ID")
+    ),
+    request_body = AdoptServiceRequest,
+    responses(
+        (status = 201, description = "Connection created", body = AdoptServiceResponse),
+        (status = 400, description = "Service cannot be adopted, or TLS certificate missing", body = ErrorResponse),
+        (status = 404, description = "Service not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires discovery:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["discovery:write"]),
+        ("api_key" = ["discovery:write"])
+    )
+)]
+pub async fn adopt_service(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    RequireDiscoveryWrite(user): RequireDiscoveryWrite,
+    ClientIp(client_ip): ClientIp,
+    Json(request): Json<AdoptServiceRequest>,
+) -> Result<(StatusCode, Json<AdoptServiceResponse>), ApiError> {
+    request.validate()?;
+
+    let discovery = state.discovery.as_ref()
+        .ok_or_else(|| ApiError::NotFound("Discovery service not enabled".to_string()))?;
+
+    let service = discovery
+        .get_service(&id)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("Service not found: {}", id)))?;
+
+    let connection_type = request
+        .connection_type
+        .or_else(|| adoption_type(&service))
+        .ok_or_else(|| {
+            ApiError::BadRequest(format!(
+                "{} does not advertise a CoT stream; specify a connection type",
+                service.instance_name
+            ))
+        })?;
+
+    // Checked before touching the pool so the caller can prompt and retry
+    if connection_type == ConnectionType::TlsClient
+        && request.certificate_id.is_none()
+        && (request.tls_cert_path.is_none() || request.tls_key_path.is_none())
+    {
+        return Err(ApiError::BadRequest(format!(
+            "{} requires TLS: a client certificate (certificate_id or cert/key paths) is required",
+            service.instance_name
+        )));
+    }
+
+    let address = service
+        .primary_address()
+        .map(|a| a.to_string())
+        .unwrap_or_else(|| service.hostname.trim_end_matches('.').to_string());
+
+    let create = CreateConnectionRequest {
+        name: request.name.unwrap_or_else(|| connection_name(&service)),
+        connection_type,
+        address,
+        port: request.port.unwrap_or(service.port),
+        auto_reconnect: true,
+        tls_cert_path: request.tls_cert_path,
+        tls_key_path: request.tls_key_path,
+        certificate_id: request.certificate_id,
+        validate_certs: request.validate_certs.unwrap_or(true),
+        reconnect: ReconnectPolicy::default(),
+    };
+
+    let connection_id = open_connection(&state, &user, &create).await?;
+
+    info!(
+        service = %service.id,
+        connection = %connection_id,
+        "Adopted discovered service"
+    );
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "adopt_discovered_service".to_string(),
+        format!("/api/v1/discovery/services/{}/adopt", service.id),
+        serde_json::json!({
+            "connection": connection_id,
+            "request": create,
+        }),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(AdoptServiceResponse {
+            id: connection_id,
+            name: create.name,
+            connection_type: create.connection_type,
+            address: create.address,
+            port: create.port,
+            message: "Connection created from discovered service".to_string(),
+        }),
+    ))
+}
+
+/// Connection type for a discovered service
+///
+/// The TXT `protocol` key decides when present; services advertising
+/// anything but a raw CoT stream (e.g. an aggregator's `http` API) cannot
+/// be adopted. Otherwise the standard TAK ports decide, then the `tls` key.
+fn adoption_type(service: &DiscoveredService) -> Option<ConnectionType> {
+    if service.service_type == ServiceType::AtakDevice {
+        return None;
+    }
+
+    let tls = if service.supports_tls() {
+        ConnectionType::TlsClient
+    } else {
+        ConnectionType::TcpClient
+    };
+    match service.properties.get("protocol").map(|p| p.to_ascii_lowercase()) {
+        Some(protocol) => match protocol.as_str() {
+            "tls" | "ssl" => Some(ConnectionType::TlsClient),
+            "tcp" | "stcp" => Some(tls),
+            _ => None,
+        },
+        None => match service.port {
+            8089 | 8443 => Some(ConnectionType::TlsClient),
+            8087 => Some(ConnectionType::TcpClient),
+            _ => Some(tls),
+        },
+    }
+}
+
+/// Connection name for a discovered service: its instance name without the
+/// service type suffix
+fn connection_name(service: &DiscoveredService) -> String {
+    let name = service
+        .instance_name
+        .split("._")
+        .next()
+        .filter(|n| !n.is_empty())
+        .unwrap_or(&service.hostname);
+    name.chars().take(100).collect()
+}
+
+fn announcement_status(discovery: &DiscoveryService) -> AnnouncementStatusResponse {
+    AnnouncementStatusResponse {
+        announcing: discovery.is_announcing(),
+        services: discovery.announced_services(),
+        timestamp: Utc::now(),
+    }
+}
+
+/// GET /api/v1/discovery/announcement - Get this instance's mDNS announcement
+#[utoipa::path(
+    get,
+    path = "/api/v1/discovery/announcement",
+    responses(
+        (status = 200, description = "Announcement status", body = AnnouncementStatusResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+pub async fn get_announcement(
+    State(state): State<ApiState>,
+    _user: AuthUser,
+) -> Result<Json<AnnouncementStatusResponse>, ApiError> {
+    let discovery = state.discovery.as_ref()
+        .ok_or_else(|| ApiError::NotFound("Discovery service not enabled".to_string()))?;
+
+    Ok(Json(announcement_status(discovery)))
+}
+
+/// PUT /api/v1/discovery/announcement - Start or stop announcing this instance
+#[utoipa::path(
+    put,
+    path = "/api/v1/discovery/announcement",
+    request_body = SetAnnouncementRequest,
+    responses(
+        (status = 200, description = "Announcement updated", body = AnnouncementStatusResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires discovery:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["discovery:write"]),
+        ("api_key" = ["discovery:write"])
+    )
+)]
+pub async fn set_announcement(
+    State(state): State<ApiState>,
+    _user: RequireDiscoveryWrite,
+    Json(request): Json<SetAnnouncementRequest>,
+) -> Result<Json<AnnouncementStatusResponse>, ApiError> {
+    let discovery = state.discovery.as_ref()
+        .ok_or_else(|| ApiError::NotFound("Discovery service not enabled".to_string()))?;
+
+    info!(enabled = request.enabled, "Setting mDNS announcement");
+
+    discovery
+        .set_announcing(request.enabled)
+        .map_err(|e| ApiError::InternalError(format!("Failed to update announcement: {}", e)))?;
+
+    Ok(Json(announcement_status(discovery)))
+}
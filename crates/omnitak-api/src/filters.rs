@@ -0,0 +1,285 @@
+//! Filter rules managed through the API
+//!
+//! Each rule belongs to the tenant namespace of the user who created it. Users
+//! confined to a tenant only see and change that tenant's rules, and rules
+//! only apply to messages sent to connections in their own namespace.
+//!
+//! For each connection, enabled rules are evaluated by descending priority
+//! and the first match decides: `deny` drops the message, `allow` and
+//! `modify` send it. Messages no rule matches are sent. The distributor does
+//! not know which address a message arrived from, so rules with a
+//! `source_address` never match.
+//!
+//! Rules set in the configuration file (`filters:`) are operator policy and
+//! apply to all traffic before it is distributed.
+
+use crate::auth::AuthUser;
+use crate::types::{FilterAction, FilterRule};
+use omnitak_cot::parse_cot_borrowed;
+use omnitak_pool::{ConnectionPool, FilterRule as PoolFilterRule, MessageDistributor};
+use parking_lot::RwLock;
+use regex::Regex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// A rule with its patterns compiled
+struct CompiledRule {
+    rule: FilterRule,
+    event_type: Option<Regex>,
+    uid: Option<Regex>,
+    callsign: Option<Regex>,
+    match_count: AtomicU64,
+}
+
+impl CompiledRule {
+    fn new(rule: FilterRule) -> Result<Self, regex::Error> {
+        let compile = |pattern: &Option<String>| pattern.as_deref().map(Regex::new).transpose();
+        Ok(Self {
+            event_type: compile(&rule.event_type)?,
+            uid: compile(&rule.uid_pattern)?,
+            callsign: compile(&rule.callsign_pattern)?,
+            match_count: AtomicU64::new(rule.match_count),
+            rule,
+        })
+    }
+
+    /// Current state of the rule, with its match count
+    fn snapshot(&self) -> FilterRule {
+        FilterRule {
+            match_count: self.match_count.load(Ordering::Relaxed),
+            ..self.rule.clone()
+        }
+    }
+
+    /// Whether a message sent to a connection at `destination` matches
+    fn matches(&self, message: &[u8], destination: &str) -> bool {
+        if self.rule.source_address.is_some() {
+            return false;
+        }
+        if self
+            .rule
+            .destination_address
+            .as_deref()
+            .is_some_and(|address| address != destination)
+        {
+            return false;
+        }
+        if self.event_type.is_none()
+            && self.uid.is_none()
+            && self.callsign.is_none()
+            && self.rule.geo_bounds.is_none()
+        {
+            return true;
+        }
+
+        let Some(event) = std::str::from_utf8(message)
+            .ok()
+            .and_then(|xml| parse_cot_borrowed(xml).ok())
+        else {
+            return false;
+        };
+        let matches = |regex: &Option<Regex>, value: Option<&str>| match regex {
+            Some(regex) => value.is_some_and(|value| regex.is_match(value)),
+            None => true,
+        };
+        matches(&self.event_type, Some(event.event_type))
+            && matches(&self.uid, Some(event.uid))
+            && matches(&self.callsign, event.callsign())
+            && self.rule.geo_bounds.as_ref().is_none_or(|bounds| {
+                (bounds.min_lat..=bounds.max_lat).contains(&event.point.lat)
+                    && (bounds.min_lon..=bounds.max_lon).contains(&event.point.lon)
+            })
+    }
+}
+
+/// Filter rules created through the API
+#[derive(Default)]
+pub struct FilterStore {
+    rules: RwLock<Vec<Arc<CompiledRule>>>,
+}
+
+impl FilterStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rules the user may see, highest priority first
+    pub fn list(&self, user: &AuthUser) -> Vec<FilterRule> {
+        self.rules
+            .read()
+            .iter()
+            .filter(|r| user.can_access(r.rule.tenant.as_deref()))
+            .map(|r| r.snapshot())
+            .collect()
+    }
+
+    /// The rule `id`, if the user may see it
+    pub fn get(&self, id: Uuid, user: &AuthUser) -> Option<FilterRule> {
+        self.rules
+            .read()
+            .iter()
+            .find(|r| r.rule.id == id && user.can_access(r.rule.tenant.as_deref()))
+            .map(|r| r.snapshot())
+    }
+
+    /// Add a rule, rejecting invalid patterns
+    pub fn insert(&self, rule: FilterRule) -> Result<(), regex::Error> {
+        let compiled = Arc::new(CompiledRule::new(rule)?);
+        let mut rules = self.rules.write();
+        rules.push(compiled);
+        rules.sort_by_key(|r| std::cmp::Reverse(r.rule.priority));
+        Ok(())
+    }
+
+    /// Remove the rule `id`, if the user may see it
+    pub fn remove(&self, id: Uuid, user: &AuthUser) -> Option<FilterRule> {
+        let mut rules = self.rules.write();
+        let index = rules
+            .iter()
+            .position(|r| r.rule.id == id && user.can_access(r.rule.tenant.as_deref()))?;
+        Some(rules.remove(index).snapshot())
+    }
+
+    /// Number of enabled rules
+    pub fn active(&self) -> usize {
+        self.rules.read().iter().filter(|r| r.rule.enabled).count()
+    }
+
+    /// Distributor rules for a connection at `destination` in `tenant`
+    pub fn connection_rules(&self, tenant: Option<&str>, destination: &str) -> Vec<PoolFilterRule> {
+        let rules: Vec<_> = self
+            .rules
+            .read()
+            .iter()
+            .filter(|r| r.rule.enabled && r.rule.tenant.as_deref() == tenant)
+            .cloned()
+            .collect();
+        if rules.is_empty() {
+            return vec![PoolFilterRule::AlwaysSend];
+        }
+
+        let destination = destination.to_string();
+        let denied = move |message: &[u8]| {
+            let rule = rules.iter().find(|r| r.matches(message, &destination));
+            rule.is_some_and(|r| {
+                r.match_count.fetch_add(1, Ordering::Relaxed);
+                r.rule.action == FilterAction::Deny
+            })
+        };
+        vec![
+            PoolFilterRule::Block(Arc::new(denied)),
+            PoolFilterRule::AlwaysSend,
+        ]
+    }
+
+    /// Install the current rules on every connection of `pool`
+    pub fn apply(&self, pool: &ConnectionPool, distributor: &MessageDistributor) {
+        for connection in pool.get_active_connections() {
+            let tenant = pool.tenant_of(&connection.id);
+            distributor.set_filters(
+                connection.id.clone(),
+                self.connection_rules(tenant.as_deref(), &connection.address),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{GeoBounds, UserRole};
+    use chrono::Utc;
+
+    const COT: &str = r#"<event version="2.0" uid="ANDROID-1" type="a-f-G-U-C" time="2024-01-15T10:30:00Z" start="2024-01-15T10:30:00Z" stale="2024-01-15T10:35:00Z" how="m-g"><point lat="37.7" lon="-122.4"/><detail><contact callsign="Alpha-1"/></detail></event>"#;
+
+    fn user(tenant: Option<&str>) -> AuthUser {
+        AuthUser {
+            user_id: Some("user".to_string()),
+            role: UserRole::Operator,
+            tenant: tenant.map(str::to_string),
+            permissions: Default::default(),
+        }
+    }
+
+    fn rule(tenant: Option<&str>, priority: i32, action: FilterAction) -> FilterRule {
+        FilterRule {
+            id: Uuid::new_v4(),
+            name: "rule".to_string(),
+            priority,
+            action,
+            event_type: None,
+            uid_pattern: None,
+            callsign_pattern: None,
+            source_address: None,
+            destination_address: None,
+            geo_bounds: None,
+            enabled: true,
+            match_count: 0,
+            tenant: tenant.map(str::to_string),
+            created_at: Utc::now(),
+            modified_at: Utc::now(),
+        }
+    }
+
+    fn blocks(rules: &[PoolFilterRule]) -> bool {
+        match rules {
+            [PoolFilterRule::Block(_), PoolFilterRule::AlwaysSend] => {
+                rules[0].matches(COT.as_bytes())
+            }
+            [PoolFilterRule::AlwaysSend] => false,
+            _ => panic!("unexpected rules {:?}", rules),
+        }
+    }
+
+    #[test]
+    fn test_tenant_isolation() {
+        let store = FilterStore::new();
+        let red = rule(Some("red"), 0, FilterAction::Deny);
+        let blue = rule(Some("blue"), 0, FilterAction::Deny);
+        store.insert(red.clone()).unwrap();
+        store.insert(blue.clone()).unwrap();
+
+        let red_user = user(Some("red"));
+        let ids = |rules: Vec<FilterRule>| rules.into_iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(ids(store.list(&red_user)), vec![red.id]);
+        assert!(store.get(blue.id, &red_user).is_none());
+        assert!(store.remove(blue.id, &red_user).is_none());
+        assert_eq!(store.list(&user(None)).len(), 2);
+
+        // Rules only apply to their own tenant's connections
+        assert!(blocks(&store.connection_rules(Some("blue"), "tak:8087")));
+        assert!(!blocks(&store.connection_rules(None, "tak:8087")));
+
+        assert!(store.remove(red.id, &red_user).is_some());
+        assert!(!blocks(&store.connection_rules(Some("red"), "tak:8087")));
+        assert!(blocks(&store.connection_rules(Some("blue"), "tak:8087")));
+    }
+
+    #[test]
+    fn test_priority_and_patterns() {
+        let store = FilterStore::new();
+        let mut allow = rule(None, 10, FilterAction::Allow);
+        allow.callsign_pattern = Some("^Alpha-".to_string());
+        let deny = rule(None, 0, FilterAction::Deny);
+        store.insert(deny).unwrap();
+        store.insert(allow.clone()).unwrap();
+        assert!(!blocks(&store.connection_rules(None, "tak:8087")));
+        assert_eq!(store.get(allow.id, &user(None)).unwrap().match_count, 1);
+
+        let store = FilterStore::new();
+        let mut deny = rule(None, 0, FilterAction::Deny);
+        deny.geo_bounds = Some(GeoBounds {
+            min_lat: 0.0,
+            max_lat: 10.0,
+            min_lon: 0.0,
+            max_lon: 10.0,
+        });
+        store.insert(deny).unwrap();
+        assert!(!blocks(&store.connection_rules(None, "tak:8087")));
+
+        let mut invalid = rule(None, 0, FilterAction::Deny);
+        invalid.uid_pattern = Some("(".to_string());
+        assert!(store.insert(invalid).is_err());
+    }
+}
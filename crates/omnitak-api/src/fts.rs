@@ -0,0 +1,382 @@
+//! FreeTAKServer integration
+//!
+//! FreeTAKServer (FTS) exposes a REST API next to its CoT port. For
+//! connections configured as FTS servers this module polls the system
+//! status and emergency list, so connection health shows whether the FTS
+//! services behind a socket are actually up, and forwards a few FTS-only
+//! controls (deleting emergencies, broadcasting chat) through
+//! `/api/v1/fts`.
+//!
+//! FTS responses vary between releases, so parsing is tolerant: unknown
+//! fields are ignored and malformed entries are skipped.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{debug, warn};
+use utoipa::ToSchema;
+
+const STATUS_PATH: &str = "/SystemStatus/getStatus";
+const EMERGENCY_LIST_PATH: &str = "/ManageEmergency/getEmergency";
+const EMERGENCY_DELETE_PATH: &str = "/ManageEmergency/deleteEmergency";
+const CHAT_PATH: &str = "/ManageChat/postChatToAll";
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// FreeTAKServer REST endpoints, keyed by the connection they belong to
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FtsConfig {
+    #[serde(default)]
+    pub servers: Vec<FtsServerConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FtsServerConfig {
+    /// ID of the TAK connection to this FTS instance
+    pub connection_id: String,
+    /// REST API base URL, e.g. `http://fts.example.com:19023`
+    pub base_url: String,
+    /// FTS API token, sent as a bearer token
+    #[serde(default)]
+    pub api_token: Option<String>,
+    /// How often to poll status and emergencies
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+}
+
+fn default_poll_interval_secs() -> u64 {
+    30
+}
+
+// ============================================================================
+// Status
+// ============================================================================
+
+/// Emergency as reported by FTS
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FtsEmergency {
+    pub uid: String,
+    /// Callsign of the reporting unit
+    #[serde(default, alias = "callsign")]
+    pub name: Option<String>,
+    #[serde(default, alias = "emergencyType", alias = "type")]
+    pub emergency_type: Option<String>,
+    #[serde(default, alias = "latitude")]
+    pub lat: Option<f64>,
+    #[serde(default, alias = "longitude")]
+    pub lon: Option<f64>,
+}
+
+/// Last known state of an FTS instance
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FtsStatus {
+    /// TAK connection the FTS instance serves
+    pub connection_id: String,
+    pub base_url: String,
+    /// Whether the last poll reached the REST API
+    pub reachable: bool,
+    /// FTS service name to status, e.g. `"TCP CoT service": "on"`
+    pub services: BTreeMap<String, String>,
+    /// Emergencies FTS currently holds
+    pub emergencies: Vec<FtsEmergency>,
+    /// When the last poll finished (None until the first poll)
+    pub last_checked: Option<DateTime<Utc>>,
+    /// Error from the last poll, if it failed
+    pub last_error: Option<String>,
+}
+
+impl FtsStatus {
+    fn new(server: &FtsServerConfig) -> Self {
+        Self {
+            connection_id: server.connection_id.clone(),
+            base_url: server.base_url.clone(),
+            reachable: false,
+            services: BTreeMap::new(),
+            emergencies: Vec::new(),
+            last_checked: None,
+            last_error: None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum FtsError {
+    #[error("No FreeTAKServer configured for connection '{0}'")]
+    NotFound(String),
+    #[error("FreeTAKServer request failed: {0}")]
+    Request(String),
+}
+
+// ============================================================================
+// Manager
+// ============================================================================
+
+/// Polls configured FTS instances and forwards controls to them
+pub struct FtsManager {
+    servers: Vec<FtsServerConfig>,
+    status: DashMap<String, FtsStatus>,
+    http: reqwest::Client,
+}
+
+impl FtsManager {
+    pub fn new(config: FtsConfig) -> Self {
+        let status = config
+            .servers
+            .iter()
+            .map(|server| (server.connection_id.clone(), FtsStatus::new(server)))
+            .collect();
+        Self {
+            servers: config.servers,
+            status,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Start polling every configured server in the background
+    pub fn start(self: Arc<Self>) {
+        for server in &self.servers {
+            let manager = Arc::clone(&self);
+            let connection_id = server.connection_id.clone();
+            let period = Duration::from_secs(server.poll_interval_secs.max(1));
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(period);
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = manager.refresh(&connection_id).await {
+                        debug!(connection = %connection_id, "FTS poll failed: {}", e);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Status of the FTS instance behind a connection
+    pub fn status(&self, connection_id: &str) -> Option<FtsStatus> {
+        self.status.get(connection_id).map(|s| s.clone())
+    }
+
+    /// Status of every configured FTS instance
+    pub fn list(&self) -> Vec<FtsStatus> {
+        let mut servers: Vec<_> = self.status.iter().map(|s| s.clone()).collect();
+        servers.sort_by(|a, b| a.connection_id.cmp(&b.connection_id));
+        servers
+    }
+
+    /// Poll one server now and record the result
+    pub async fn refresh(&self, connection_id: &str) -> Result<FtsStatus, FtsError> {
+        let server = self.server(connection_id)?;
+        let result = self.poll(server).await;
+
+        let mut status = self
+            .status
+            .entry(connection_id.to_string())
+            .or_insert_with(|| FtsStatus::new(server));
+        status.last_checked = Some(Utc::now());
+        match &result {
+            Ok((services, emergencies)) => {
+                if !status.reachable {
+                    debug!(connection = %connection_id, "FTS REST API reachable");
+                }
+                status.reachable = true;
+                status.services = services.clone();
+                status.emergencies = emergencies.clone();
+                status.last_error = None;
+            }
+            Err(e) => {
+                if status.reachable {
+                    warn!(connection = %connection_id, "FTS REST API unreachable: {}", e);
+                }
+                status.reachable = false;
+                status.last_error = Some(e.to_string());
+            }
+        }
+        let snapshot = status.clone();
+        drop(status);
+
+        result.map(|_| snapshot)
+    }
+
+    /// Delete an emergency on the FTS instance
+    pub async fn delete_emergency(&self, connection_id: &str, uid: &str) -> Result<(), FtsError> {
+        let server = self.server(connection_id)?;
+        let response = self
+            .request(server, reqwest::Method::DELETE, EMERGENCY_DELETE_PATH)
+            .json(&serde_json::json!({ "uid": uid }))
+            .send()
+            .await;
+        check_response(response).await?;
+
+        if let Some(mut status) = self.status.get_mut(connection_id) {
+            status.emergencies.retain(|e| e.uid != uid);
+        }
+        Ok(())
+    }
+
+    /// Send a chat message to every user on the FTS instance
+    pub async fn send_chat(
+        &self,
+        connection_id: &str,
+        sender: &str,
+        message: &str,
+    ) -> Result<(), FtsError> {
+        let server = self.server(connection_id)?;
+        let response = self
+            .request(server, reqwest::Method::POST, CHAT_PATH)
+            .json(&serde_json::json!({ "sender": sender, "message": message }))
+            .send()
+            .await;
+        check_response(response).await.map(|_| ())
+    }
+
+    fn server(&self, connection_id: &str) -> Result<&FtsServerConfig, FtsError> {
+        self.servers
+            .iter()
+            .find(|s| s.connection_id == connection_id)
+            .ok_or_else(|| FtsError::NotFound(connection_id.to_string()))
+    }
+
+    fn request(
+        &self,
+        server: &FtsServerConfig,
+        method: reqwest::Method,
+        path: &str,
+    ) -> reqwest::RequestBuilder {
+        let url = format!("{}{}", server.base_url.trim_end_matches('/'), path);
+        let mut request = self.http.request(method, url).timeout(HTTP_TIMEOUT);
+        if let Some(token) = &server.api_token {
+            request = request.bearer_auth(token);
+        }
+        request
+    }
+
+    async fn poll(
+        &self,
+        server: &FtsServerConfig,
+    ) -> Result<(BTreeMap<String, String>, Vec<FtsEmergency>), FtsError> {
+        let status = self
+            .request(server, reqwest::Method::GET, STATUS_PATH)
+            .send()
+            .await;
+        let services = parse_services(&check_response(status).await?);
+
+        let emergencies = self
+            .request(server, reqwest::Method::GET, EMERGENCY_LIST_PATH)
+            .send()
+            .await;
+        let emergencies = parse_emergencies(&check_response(emergencies).await?);
+
+        Ok((services, emergencies))
+    }
+}
+
+/// Error on transport failure or a non-2xx status, otherwise the JSON body
+/// (`Null` if the body is empty or not JSON)
+async fn check_response(
+    response: reqwest::Result<reqwest::Response>,
+) -> Result<serde_json::Value, FtsError> {
+    let response = response.map_err(|e| FtsError::Request(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(FtsError::Request(format!("HTTP {}", response.status())));
+    }
+    Ok(response.json().await.unwrap_or(serde_json::Value::Null))
+}
+
+/// Service statuses from a `getStatus` body; each value is either a status
+/// string or an object with a `status` field
+fn parse_services(body: &serde_json::Value) -> BTreeMap<String, String> {
+    let Some(object) = body.as_object() else {
+        return BTreeMap::new();
+    };
+    object
+        .iter()
+        .filter_map(|(name, value)| {
+            let status = match value {
+                serde_json::Value::String(s) => s.clone(),
+                serde_json::Value::Bool(b) => if *b { "on" } else { "off" }.to_string(),
+                serde_json::Value::Object(o) => o.get("status")?.as_str()?.to_string(),
+                _ => return None,
+            };
+            Some((name.clone(), status))
+        })
+        .collect()
+}
+
+/// Emergencies from a `getEmergency` body, either a bare array or wrapped
+/// in `json_list`
+fn parse_emergencies(body: &serde_json::Value) -> Vec<FtsEmergency> {
+    let list = match body {
+        serde_json::Value::Array(list) => list,
+        serde_json::Value::Object(o) => match o.get("json_list").or_else(|| o.get("emergencies")) {
+            Some(serde_json::Value::Array(list)) => list,
+            _ => return Vec::new(),
+        },
+        _ => return Vec::new(),
+    };
+    list.iter()
+        .filter_map(|entry| serde_json::from_value(entry.clone()).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_services() {
+        let body = serde_json::json!({
+            "TCP CoT service": {"status": "on", "port": 8087},
+            "SSL CoT service": "off",
+            "REST API service": true,
+            "version": 2,
+        });
+        let services = parse_services(&body);
+        assert_eq!(services.len(), 3);
+        assert_eq!(services["TCP CoT service"], "on");
+        assert_eq!(services["SSL CoT service"], "off");
+        assert_eq!(services["REST API service"], "on");
+    }
+
+    #[test]
+    fn test_parse_emergencies() {
+        let body = serde_json::json!({
+            "json_list": [
+                {"uid": "ANDROID-1-9-1-1", "callsign": "VIPER-1", "emergencyType": "911 Alert",
+                 "latitude": 34.1, "longitude": -117.2},
+                {"uid": "bad", "latitude": "not a number"},
+                {"name": "no uid"},
+            ]
+        });
+        let emergencies = parse_emergencies(&body);
+        assert_eq!(
+            emergencies,
+            vec![FtsEmergency {
+                uid: "ANDROID-1-9-1-1".to_string(),
+                name: Some("VIPER-1".to_string()),
+                emergency_type: Some("911 Alert".to_string()),
+                lat: Some(34.1),
+                lon: Some(-117.2),
+            }]
+        );
+
+        assert!(parse_emergencies(&serde_json::json!([])).is_empty());
+        assert!(parse_emergencies(&serde_json::Value::Null).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_connection() {
+        let manager = FtsManager::new(FtsConfig::default());
+        assert!(matches!(
+            manager.refresh("tak-main").await,
+            Err(FtsError::NotFound(_))
+        ));
+        assert!(manager.list().is_empty());
+    }
+}
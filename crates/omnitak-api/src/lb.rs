@@ -0,0 +1,172 @@
+//! Load balancer hints
+//!
+//! Reports how much more work this node can take, for deployments that
+//! spread clients across several OmniTAK nodes behind a load balancer. The
+//! weight is the smaller of the free connection capacity and the free
+//! space in the fuller of the aggregator and distributor queues:
+//!
+//! - `GET /api/v1/lb` returns the report as JSON, with status 503 while
+//!   draining so plain HTTP health checks take the node out of rotation.
+//! - An optional TCP responder answers HAProxy `agent-check` probes with
+//!   `up 73%` or `drain`.
+
+use crate::types::{LoadReport, LoadState};
+use chrono::Utc;
+use omnitak_pool::{ConnectionPool, MessageAggregator, MessageDistributor};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tracing::{debug, info, warn};
+
+/// Queue fill at which the node asks to be drained
+const DRAIN_PRESSURE: f64 = 0.9;
+
+/// Computes load reports from the pool and message queues
+pub struct LoadMonitor {
+    pool: Arc<ConnectionPool>,
+    aggregator: Arc<MessageAggregator>,
+    distributor: Arc<MessageDistributor>,
+    draining: AtomicBool,
+}
+
+impl LoadMonitor {
+    pub fn new(
+        pool: Arc<ConnectionPool>,
+        aggregator: Arc<MessageAggregator>,
+        distributor: Arc<MessageDistributor>,
+    ) -> Self {
+        Self {
+            pool,
+            aggregator,
+            distributor,
+            draining: AtomicBool::new(false),
+        }
+    }
+
+    /// Ask load balancers to stop sending new clients, e.g. before a
+    /// shutdown or upgrade
+    pub fn set_draining(&self, draining: bool) {
+        self.draining.store(draining, Ordering::Relaxed);
+    }
+
+    /// Current load
+    pub fn report(&self) -> LoadReport {
+        let active_connections = self.pool.stats().active_connections;
+        let max_connections = self.pool.max_connections();
+        let aggregator_queue = fill(
+            self.aggregator.pending_count(),
+            self.aggregator.queue_capacity(),
+        );
+        let distributor_queue = fill(
+            self.distributor.pending_count(),
+            self.distributor.queue_capacity(),
+        );
+        let connection_headroom = 1.0 - fill(active_connections, max_connections);
+        let (state, weight) = weigh(
+            connection_headroom,
+            aggregator_queue.max(distributor_queue),
+            self.draining.load(Ordering::Relaxed),
+        );
+
+        LoadReport {
+            state,
+            weight,
+            active_connections,
+            max_connections,
+            connection_headroom,
+            aggregator_queue,
+            distributor_queue,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// Answer HAProxy agent checks on `listener` until the task is dropped
+    pub async fn serve_agent(self: Arc<Self>, listener: TcpListener) {
+        if let Ok(addr) = listener.local_addr() {
+            info!(address = %addr, "Load balancer agent listening");
+        }
+        loop {
+            let (mut stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!(error = %e, "Load balancer agent accept failed");
+                    continue;
+                }
+            };
+            let response = agent_response(&self.report());
+            debug!(peer = %peer, response = response.trim_end(), "Load balancer agent check");
+            tokio::spawn(async move {
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            });
+        }
+    }
+}
+
+/// Fraction of `capacity` in use, 0.0-1.0
+fn fill(used: usize, capacity: usize) -> f64 {
+    if capacity == 0 {
+        return 1.0;
+    }
+    (used as f64 / capacity as f64).clamp(0.0, 1.0)
+}
+
+/// State and weight (0-100) from connection headroom and queue pressure
+fn weigh(connection_headroom: f64, queue_pressure: f64, draining: bool) -> (LoadState, u8) {
+    if draining || connection_headroom <= 0.0 || queue_pressure >= DRAIN_PRESSURE {
+        return (LoadState::Drain, 0);
+    }
+    let free = connection_headroom.min(1.0 - queue_pressure);
+    // A node that can take work never reports weight 0, which HAProxy
+    // treats like drain
+    (LoadState::Up, ((free * 100.0).round() as u8).max(1))
+}
+
+/// HAProxy agent-check reply
+pub fn agent_response(report: &LoadReport) -> String {
+    match report.state {
+        LoadState::Up => format!("up {}%\n", report.weight),
+        LoadState::Drain => "drain\n".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weigh() {
+        assert_eq!(weigh(1.0, 0.0, false), (LoadState::Up, 100));
+        // The tighter of connections and queues wins
+        assert_eq!(weigh(0.8, 0.5, false), (LoadState::Up, 50));
+        assert_eq!(weigh(0.25, 0.1, false), (LoadState::Up, 25));
+        assert_eq!(weigh(0.001, 0.0, false), (LoadState::Up, 1));
+
+        assert_eq!(weigh(1.0, 0.95, false), (LoadState::Drain, 0));
+        assert_eq!(weigh(0.0, 0.0, false), (LoadState::Drain, 0));
+        assert_eq!(weigh(1.0, 0.0, true), (LoadState::Drain, 0));
+
+        assert_eq!(fill(5, 10), 0.5);
+        assert_eq!(fill(1, 0), 1.0);
+    }
+
+    #[test]
+    fn test_agent_response() {
+        let mut report = LoadReport {
+            state: LoadState::Up,
+            weight: 73,
+            active_connections: 27,
+            max_connections: 100,
+            connection_headroom: 0.73,
+            aggregator_queue: 0.0,
+            distributor_queue: 0.1,
+            timestamp: Utc::now(),
+        };
+        assert_eq!(agent_response(&report), "up 73%\n");
+
+        report.state = LoadState::Drain;
+        report.weight = 0;
+        assert_eq!(agent_response(&report), "drain\n");
+    }
+}
@@ -8,7 +8,7 @@
 //! - REST API for system management
 //! - WebSocket streaming for real-time CoT messages
 //! - JWT and API key authentication
-//! - Role-based access control (RBAC)
+//! - Permission-based access control with custom roles
 //! - Rate limiting and DoS protection
 //! - Comprehensive audit logging
 //! - Prometheus metrics
@@ -33,24 +33,58 @@
 //! ```
 
 pub mod adb;
+pub mod alerts;
+pub mod audit;
+pub mod fts;
 pub mod auth;
+pub mod backup;
+pub mod client_access;
 pub mod discovery;
+pub mod filters;
+pub mod lb;
+pub mod logging;
 pub mod middleware;
+pub mod pagination;
+pub mod rbac;
+pub mod resources;
 pub mod rest;
+pub mod siem;
 pub mod static_files;
+pub mod time_sync;
+pub mod token_store;
+pub mod tracks;
 pub mod types;
+pub mod upload;
 pub mod websocket;
 
+pub use adb::AdbMonitorConfig;
+pub use alerts::{AlertManager, AlertsConfig};
+pub use audit::AuditConfig;
+pub use client_access::{ClientAccessConfig, ClientAccessPolicy};
+pub use fts::{FtsConfig, FtsManager};
+pub use lb::LoadMonitor;
+pub use logging::LogLevelControl;
+pub use middleware::RateLimitConfig;
+pub use rbac::{Permission, RoleDefinition};
+pub use rest::enrollment::ListenerEndpoint;
+pub use rest::marti::MartiConfig;
+pub use resources::ResourceMonitor;
+pub use siem::SiemConfig;
+pub use tracks::TrackStore;
 use auth::{AuthConfig, AuthService};
 use middleware::{
-    RateLimitState, ReadinessState, cors_layer, logging_middleware, rate_limit_middleware,
-    request_id_middleware, security_headers_middleware, timeout_middleware,
+    RateLimitState, ReadinessState, TrustedProxies, cors_layer, logging_middleware,
+    rate_limit_middleware, request_id_middleware, security_headers_middleware, timeout_middleware,
 };
 use omnitak_cert::generator::CaConfig;
-use omnitak_plugin_api::PluginManager;
+use omnitak_core::{AppConfig, GpsClock, TimeSyncConfig};
+use omnitak_discovery::{DiscoveryConfig, DiscoveryService};
+use omnitak_filter::FieldRuleFilter;
+use omnitak_plugin_api::{PluginManager, PluginManagerConfig};
 use omnitak_pool::{
-    AggregatorConfig, ConnectionPool, DistributionStrategy, DistributorConfig,
-    MessageAggregator, MessageDistributor, PoolConfig,
+    AggregatorConfig, ConnectionPool, DistributorConfig, Emergency, EmergencyTracker,
+    MessageAggregator, MessageDistributor, MessageTap, MetricsRegistry, PoolConfig,
+    SinkBatchConfig, TransformConfig, TransformPipeline,
 };
 use rest::ApiState;
 use rest::enrollment::EnrollmentState;
@@ -87,8 +121,12 @@ pub struct ServerConfig {
     /// Authentication configuration
     pub auth_config: AuthConfig,
 
-    /// Rate limit (requests per second)
-    pub rate_limit_rps: u32,
+    /// Per-client rate limits
+    pub rate_limit: RateLimitConfig,
+
+    /// Reverse proxies, as addresses or CIDR ranges, whose `Forwarded` and
+    /// `X-Forwarded-For` headers give the client address
+    pub trusted_proxies: Vec<String>,
 
     /// Enable Swagger UI
     pub enable_swagger: bool,
@@ -107,6 +145,12 @@ pub struct ServerConfig {
 
     /// Server connection config for enrollment data packages
     pub enrollment_server_config: Option<ServerConnectionConfig>,
+
+    /// System clock synchronization monitoring
+    pub time_sync: TimeSyncConfig,
+
+    /// Serve the TAK Marti sync endpoints for package sharing
+    pub marti: Option<MartiConfig>,
 }
 
 impl Default for ServerConfig {
@@ -117,13 +161,16 @@ impl Default for ServerConfig {
             tls_cert_path: None,
             tls_key_path: None,
             auth_config: AuthConfig::default(),
-            rate_limit_rps: 100,
+            rate_limit: RateLimitConfig::default(),
+            trusted_proxies: Vec::new(),
             enable_swagger: true,
             enable_static_files: true,
             enable_enrollment: true,
             enrollment_ca_cert_path: None,
             enrollment_ca_key_path: None,
             enrollment_server_config: None,
+            time_sync: TimeSyncConfig::default(),
+            marti: None,
         }
     }
 }
@@ -148,63 +195,282 @@ impl Default for ServerConfig {
     ),
     paths(
         rest::get_system_status,
+        rest::get_system_resources,
+        rest::health_check,
+        rest::lb::get_load,
         rest::list_connections,
         rest::get_connection,
+        rest::get_connection_stats,
+        rest::get_all_connection_stats,
         rest::create_connection,
+        rest::bulk::create_connections,
+        rest::probe::test_connection,
         rest::delete_connection,
+        rest::certificates::list_certificates,
+        rest::certificates::import_certificate,
+        rest::certificates::delete_certificate,
+        rest::certificates::upload_certificate,
+        rest::packages::upload_package,
+        rest::datapackages::preview_connections,
+        rest::datapackages::import_connections,
+        rest::datapackages::validate_package,
+        rest::duplicates::get_duplicates,
+        rest::quarantine::get_quarantine,
+        rest::quarantine::set_strict_validation,
+        rest::quarantine::clear_quarantine,
+        rest::emergencies::list_emergencies,
+        rest::emergencies::acknowledge_emergency,
+        rest::emergencies::clear_emergency,
         rest::list_filters,
         rest::create_filter,
+        rest::get_filter,
+        rest::delete_filter,
+        rest::send_cot_message,
         rest::get_metrics,
+        rest::tap::tap_traffic,
         rest::login,
+        rest::refresh_token,
+        rest::logout,
+        rest::revoke_token,
         rest::create_api_key,
         rest::get_audit_logs,
+        rest::alerts::list_channels,
+        rest::alerts::create_channel,
+        rest::alerts::update_channel,
+        rest::alerts::delete_channel,
+        rest::alerts::send_test_alert,
+        rest::client_access::list_rules,
+        rest::client_access::create_rule,
+        rest::client_access::update_rule,
+        rest::client_access::delete_rule,
+        rest::client_access::evaluate,
+        rest::logging::get_log_levels,
+        rest::logging::set_log_levels,
+        rest::logging::reset_log_levels,
+        rest::config::export_config,
+        rest::backup::create_backup,
+        rest::backup::restore_backup,
+        rest::fts::list_fts_servers,
+        rest::fts::get_fts_server,
+        rest::fts::refresh_fts_server,
+        rest::fts::delete_fts_emergency,
+        rest::fts::send_fts_chat,
+        rest::tracks::list_tracks,
         rest::plugins::list_plugins,
         rest::plugins::load_plugin,
         rest::plugins::get_plugin_details,
         rest::plugins::unload_plugin,
+        rest::plugins::get_plugin_config,
         rest::plugins::update_plugin_config,
         rest::plugins::toggle_plugin,
         rest::plugins::get_plugin_metrics,
         rest::plugins::get_plugin_health,
         rest::plugins::reload_plugin,
         rest::plugins::reload_all_plugins,
+        rest::enrollment::download_datapackage,
+        rest::enrollment::get_enrollment_status,
+        rest::enrollment::list_tokens,
+        rest::enrollment::create_token,
+        rest::enrollment::delete_token,
+        rest::enrollment::get_server_config,
+        rest::enrollment::update_server_config,
+        rest::enrollment::list_listeners,
+        rest::enrollment::download_listener_package,
+        rest::marti::upload,
+        rest::marti::mission_upload,
+        rest::marti::mission_query,
+        rest::marti::content,
+        rest::marti::search,
+        adb::list_devices,
+        adb::pull_certificates,
+        adb::pair_device,
+        adb::connect_device,
+        adb::disconnect_device,
+        adb::deploy_connection,
+        adb::list_approved_devices,
+        adb::approve_device,
+        adb::revoke_device,
+        discovery::get_discovery_status,
+        discovery::list_discovered_services,
+        discovery::get_discovered_service,
+        discovery::adopt_service,
+        discovery::refresh_discovery,
+        discovery::list_tak_servers,
+        discovery::list_atak_devices,
+        discovery::get_announcement,
+        discovery::set_announcement,
+        websocket::ws_stream_handler,
+        websocket::ws_events_handler,
+        websocket::ws_tracks_handler,
     ),
     components(
         schemas(
+            types::HealthResponse,
             types::SystemStatus,
+            types::TimeSyncInfo,
+            types::ResourceUsage,
+            types::LoadReport,
+            types::LoadState,
             types::ConnectionInfo,
             types::ConnectionList,
+            types::ConnectionStats,
+            types::ConnectionStatsSample,
+            types::ConnectionStatsSnapshot,
             types::ConnectionStatus,
             types::ConnectionType,
             types::CreateConnectionRequest,
+            types::ReconnectPolicy,
             types::CreateConnectionResponse,
             types::DeleteConnectionResponse,
+            types::BulkConnectionRequest,
+            types::BulkConnectionStatus,
+            types::BulkConnectionResult,
+            types::BulkConnectionResponse,
+            types::TestConnectionRequest,
+            types::ConnectionTestStage,
+            types::ConnectionTestStep,
+            types::ServerCertificateInfo,
+            types::TestConnectionResponse,
+            types::CertificateFormat,
+            types::ImportCertificateRequest,
+            types::StoredCertificateInfo,
+            types::CertificateList,
+            types::CertificateUploadResponse,
+            types::UploadedCertificateFile,
+            omnitak_cert::CertificateFileKind,
+            types::PackageKind,
+            types::PackageUploadResponse,
+            types::PackageConnection,
+            types::PackageConnectionList,
+            types::ImportedConnection,
+            types::SkippedConnection,
+            types::ImportConnectionsResponse,
+            types::ValidationSeverity,
+            types::PackageValidationIssue,
+            types::PackageValidationReport,
+            types::DuplicateOrigin,
+            types::DuplicateReport,
+            types::SourceDuplicateInfo,
+            types::QuarantinedMessageInfo,
+            types::SourceQuarantineInfo,
+            types::QuarantineReport,
+            types::SetStrictValidationRequest,
+            types::EmergencyInfo,
+            types::EmergencyList,
+            types::EmergencyStatus,
+            types::AlertChannelList,
+            types::TestAlertRequest,
+            types::TestAlertResponse,
+            alerts::Alert,
+            alerts::AlertSeverity,
+            alerts::AlertChannel,
+            alerts::AlertChannelKind,
+            alerts::EmailChannel,
+            alerts::SmtpTls,
+            alerts::SlackChannel,
+            alerts::MatrixChannel,
+            alerts::AlertDelivery,
+            types::ClientAccessRuleList,
+            client_access::ClientAccess,
+            client_access::ClientAccessRule,
+            client_access::ClientIdentity,
+            client_access::ClientAuthorization,
+            types::LogLevels,
+            types::LogLevelStatus,
+            types::RestoreReport,
+            types::FtsStatusList,
+            types::FtsChatRequest,
+            fts::FtsStatus,
+            fts::FtsEmergency,
+            types::TrackCollection,
+            types::TrackFeature,
+            types::PointGeometry,
+            types::TrackProperties,
             types::FilterRule,
             types::FilterList,
             types::FilterAction,
             types::CreateFilterRequest,
             types::CreateFilterResponse,
             types::GeoBounds,
+            types::SendCotRequest,
+            types::SendCotResponse,
             types::MetricsSnapshot,
             types::LoginRequest,
             types::LoginResponse,
+            types::RefreshRequest,
+            types::LogoutRequest,
+            types::RevokeTokenRequest,
+            rbac::Permission,
             types::ApiKeyRequest,
             types::ApiKeyResponse,
             types::UserRole,
             types::ErrorResponse,
             types::AuditLogEntry,
+            types::AuditLogPage,
+            types::AuditSort,
+            types::ConnectionSort,
+            pagination::SortOrder,
             types::WsClientMessage,
             types::WsServerMessage,
+            types::CreateEnrollmentTokenRequest,
+            types::CreateEnrollmentTokenResponse,
+            types::EnrollmentTokenInfo,
+            types::EnrollmentTokenList,
+            types::ServerConnectionConfig,
+            types::EnrollmentStatus,
+            types::CaInfo,
+            types::ListenerEndpointInfo,
+            adb::PullCertsRequest,
+            adb::PullCertsResponse,
+            adb::DevicePullResult,
+            adb::CertificateBundleInfo,
+            adb::DeployRequest,
+            adb::DeployFormat,
+            adb::DeployResponse,
+            adb::DeviceDeployResult,
+            adb::DeployStatus,
+            adb::DeviceListResponse,
+            adb::DeviceInfoResponse,
+            adb::ApprovedDevicesResponse,
+            adb::PairDeviceRequest,
+            adb::WirelessDeviceRequest,
+            adb::WirelessDeviceResponse,
+            discovery::DiscoveredServicesList,
+            discovery::DiscoverySort,
+            discovery::DiscoveredServiceResponse,
+            discovery::DiscoveryStatusResponse,
+            discovery::AnnouncementStatusResponse,
+            discovery::SetAnnouncementRequest,
+            discovery::AdoptServiceRequest,
+            discovery::AdoptServiceResponse,
+            discovery::RefreshRequest,
         )
     ),
     tags(
         (name = "system", description = "System status and health"),
         (name = "connections", description = "Connection management"),
+        (name = "certificates", description = "Client certificate store"),
+        (name = "datapackages", description = "Data package import"),
+        (name = "packages", description = "File and data package uploads"),
+        (name = "emergencies", description = "Emergency beacons"),
         (name = "filters", description = "Filter management"),
         (name = "metrics", description = "Prometheus metrics"),
         (name = "auth", description = "Authentication"),
         (name = "audit", description = "Audit logs"),
+        (name = "alerts", description = "Alert channels"),
+        (name = "client-access", description = "Client certificate authorization"),
+        (name = "logging", description = "Runtime log levels"),
+        (name = "config", description = "Configuration export"),
+        (name = "backup", description = "Backup and restore"),
+        (name = "fts", description = "FreeTAKServer integration"),
+        (name = "tracks", description = "GeoJSON track table"),
         (name = "plugins", description = "Plugin management"),
+        (name = "cot", description = "CoT message injection"),
+        (name = "enrollment", description = "Certificate enrollment and data packages"),
+        (name = "marti", description = "TAK server data sync"),
+        (name = "adb", description = "Android device provisioning over ADB"),
+        (name = "discovery", description = "mDNS service discovery"),
+        (name = "streaming", description = "WebSocket and Server-Sent Event streams"),
     ),
     modifiers(&SecurityAddon)
 )]
@@ -235,26 +501,283 @@ impl utoipa::Modify for SecurityAddon {
     }
 }
 
+/// OpenAPI document describing every route the server can mount
+///
+/// Routes behind optional features (enrollment, Marti sync) are included
+/// whether or not they are enabled. The committed copy used to generate
+/// `omnitak-api-client` is checked against this in the tests.
+pub fn openapi() -> utoipa::openapi::OpenApi {
+    ApiDoc::openapi()
+}
+
+// ============================================================================
+// Upgrade Handoff
+// ============================================================================
+
+/// Connection created through the API, handed to an upgraded process so it
+/// can reopen it
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HandoffConnection {
+    pub request: types::CreateConnectionRequest,
+    /// Tenant namespace of the user who created it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+}
+
+/// Connections the API server has open, shared with the process running it
+#[derive(Clone, Default)]
+pub struct ApiConnections {
+    connections: Arc<RwLock<Vec<types::ConnectionInfo>>>,
+    requests: Arc<RwLock<std::collections::HashMap<uuid::Uuid, types::CreateConnectionRequest>>>,
+}
+
+impl ApiConnections {
+    /// Connections open right now, with the requests that created them
+    pub async fn snapshot(&self) -> Vec<HandoffConnection> {
+        let connections = self.connections.read().await;
+        let requests = self.requests.read().await;
+        connections
+            .iter()
+            .filter_map(|c| {
+                Some(HandoffConnection {
+                    request: requests.get(&c.id)?.clone(),
+                    tenant: c.tenant.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
 // ============================================================================
 // Server Builder
 // ============================================================================
 
 pub struct ServerBuilder {
     config: ServerConfig,
+    app: AppConfig,
     auth_service: Option<Arc<AuthService>>,
+    listener: Option<std::net::TcpListener>,
+    alerts: Option<Arc<AlertManager>>,
+    client_access: Option<Arc<ClientAccessPolicy>>,
+    emergencies: Option<Arc<EmergencyTracker>>,
+    fts: Option<Arc<FtsManager>>,
+    tracks: Option<Arc<TrackStore>>,
+    tap: Option<Arc<MessageTap>>,
+    gps_clock: Option<Arc<GpsClock>>,
+    load: Option<Arc<LoadMonitor>>,
+    transformers: Option<Arc<TransformPipeline>>,
+    metrics: Option<Arc<MetricsRegistry>>,
+    plugin_config: PluginManagerConfig,
+    listener_endpoints: Vec<ListenerEndpoint>,
+    discovery_config: Option<DiscoveryConfig>,
+    adb_monitor_config: Option<AdbMonitorConfig>,
+    log_control: Option<Arc<LogLevelControl>>,
+    siem_config: Option<SiemConfig>,
+    audit_config: Option<AuditConfig>,
+    config_file: Option<PathBuf>,
+    connections: ApiConnections,
+    restored_connections: Vec<HandoffConnection>,
+    pipeline_runtime: Option<tokio::runtime::Handle>,
+    traffic_pool: Option<Arc<ConnectionPool>>,
 }
 
 impl ServerBuilder {
     pub fn new(config: ServerConfig) -> Self {
         Self {
             config,
+            app: AppConfig::default(),
             auth_service: None,
+            listener: None,
+            alerts: None,
+            client_access: None,
+            emergencies: None,
+            fts: None,
+            tracks: None,
+            tap: None,
+            gps_clock: None,
+            load: None,
+            transformers: None,
+            metrics: None,
+            plugin_config: PluginManagerConfig::default(),
+            listener_endpoints: Vec::new(),
+            discovery_config: None,
+            adb_monitor_config: None,
+            log_control: None,
+            siem_config: None,
+            audit_config: None,
+            config_file: None,
+            connections: ApiConnections::default(),
+            restored_connections: Vec::new(),
+            pipeline_runtime: None,
+            traffic_pool: None,
         }
     }
 
+    /// Size the server's connection pool, distributor and aggregator, filter
+    /// the traffic injected through it and configure discovery and metrics
+    /// from the application configuration
+    pub fn with_app_config(mut self, app: AppConfig) -> Self {
+        self.discovery_config = Some(app.discovery.clone());
+        self.app = app;
+        self
+    }
+
+    /// Share an alert manager so its channels can be managed through the API
+    pub fn with_alert_manager(mut self, alerts: Arc<AlertManager>) -> Self {
+        self.alerts = Some(alerts);
+        self
+    }
+
+    /// Share the client access policy of the TLS listeners so its rules can
+    /// be managed through the API
+    pub fn with_client_access_policy(mut self, client_access: Arc<ClientAccessPolicy>) -> Self {
+        self.client_access = Some(client_access);
+        self
+    }
+
+    /// Serve on an already-bound socket instead of binding `bind_addr`
+    pub fn with_listener(mut self, listener: std::net::TcpListener) -> Self {
+        self.listener = Some(listener);
+        self
+    }
+
+    /// Serve emergencies from an existing tracker instead of the server's
+    /// own aggregator
+    pub fn with_emergency_tracker(mut self, emergencies: Arc<EmergencyTracker>) -> Self {
+        self.emergencies = Some(emergencies);
+        self
+    }
+
+    /// Expose FreeTAKServer status and controls from an existing manager
+    pub fn with_fts_manager(mut self, fts: Arc<FtsManager>) -> Self {
+        self.fts = Some(fts);
+        self
+    }
+
+    /// Serve the track stream from an existing track table instead of the
+    /// server's own distributor
+    pub fn with_track_store(mut self, tracks: Arc<TrackStore>) -> Self {
+        self.tracks = Some(tracks);
+        self
+    }
+
+    /// Serve the traffic tap from an existing distributor's tap instead of
+    /// the server's own distributor
+    pub fn with_traffic_tap(mut self, tap: Arc<MessageTap>) -> Self {
+        self.tap = Some(tap);
+        self
+    }
+
+    /// Also report per-connection stats of an existing pool, e.g. the one
+    /// carrying the main traffic
+    pub fn with_traffic_pool(mut self, pool: Arc<ConnectionPool>) -> Self {
+        self.traffic_pool = Some(pool);
+        self
+    }
+
+    /// Check the system clock against GPS fix times when no time daemon
+    /// reports an offset
+    pub fn with_gps_clock(mut self, gps_clock: Arc<GpsClock>) -> Self {
+        self.gps_clock = Some(gps_clock);
+        self
+    }
+
+    /// Report load balancer hints from an existing monitor instead of the
+    /// server's own pool and queues
+    pub fn with_load_monitor(mut self, load: Arc<LoadMonitor>) -> Self {
+        self.load = Some(load);
+        self
+    }
+
+    /// Load transformer plugins into an existing pipeline, e.g. one the
+    /// main aggregator runs, instead of a pipeline of the server's own
+    pub fn with_transform_pipeline(mut self, transformers: Arc<TransformPipeline>) -> Self {
+        self.transformers = Some(transformers);
+        self
+    }
+
+    /// Render `/api/v1/metrics` from an existing registry instead of the
+    /// process-wide one
+    pub fn with_metrics_registry(mut self, metrics: Arc<MetricsRegistry>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Configure the plugin manager: plugin directory and hot reload,
+    /// storage, and the sandbox plugins run in
+    pub fn with_plugin_config(mut self, config: PluginManagerConfig) -> Self {
+        self.plugin_config = config;
+        self
+    }
+
+    /// Offer onboarding data packages for these inbound listeners
+    pub fn with_listener_endpoints(mut self, endpoints: Vec<ListenerEndpoint>) -> Self {
+        self.listener_endpoints = endpoints;
+        self
+    }
+
+    /// Run mDNS discovery and announce this instance, managed through the
+    /// discovery endpoints
+    pub fn with_discovery_config(mut self, config: DiscoveryConfig) -> Self {
+        self.discovery_config = Some(config);
+        self
+    }
+
+    /// Watch for Android devices being attached over ADB, pushing events to
+    /// WebSocket clients and pulling certificates from approved devices
+    pub fn with_adb_monitor(mut self, config: AdbMonitorConfig) -> Self {
+        self.adb_monitor_config = Some(config);
+        self
+    }
+
+    /// Let admins change log levels at runtime through `/api/v1/logging`
+    pub fn with_log_control(mut self, log_control: Arc<LogLevelControl>) -> Self {
+        self.log_control = Some(log_control);
+        self
+    }
+
+    /// Forward audit entries and authentication failures to a SIEM
+    pub fn with_siem_export(mut self, config: SiemConfig) -> Self {
+        self.siem_config = Some(config);
+        self
+    }
+
+    /// Store the audit log in SQLite with a retention policy; without it
+    /// entries are kept in memory
+    pub fn with_audit_log(mut self, config: AuditConfig) -> Self {
+        self.audit_config = Some(config);
+        self
+    }
+
+    /// Configuration file the server was started from, used as the base of
+    /// `/api/v1/config/export`
+    pub fn with_config_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_file = Some(path.into());
+        self
+    }
+
+    /// Run the distribution and aggregation workers of the server's own
+    /// pipeline on this runtime instead of the one serving the API
+    pub fn with_pipeline_runtime(mut self, runtime: tokio::runtime::Handle) -> Self {
+        self.pipeline_runtime = Some(runtime);
+        self
+    }
+
+    /// Reopen connections a previous process created through the API
+    pub fn with_restored_connections(mut self, connections: Vec<HandoffConnection>) -> Self {
+        self.restored_connections = connections;
+        self
+    }
+
+    /// Handle on the connections the server will have open, for handing
+    /// them to an upgraded process
+    pub fn connections(&self) -> ApiConnections {
+        self.connections.clone()
+    }
+
     /// Add a default admin user
     pub fn with_default_user(mut self, username: &str, password: &str) -> Self {
-        let auth_service = Arc::new(AuthService::new(self.config.auth_config.clone()));
+        let auth_service = self.auth_service();
 
         if let Err(e) = auth_service.create_user(username.to_string(), password, UserRole::Admin) {
             error!(error = %e, "Failed to create default user");
@@ -262,19 +785,133 @@ impl ServerBuilder {
             info!(username = username, "Created default admin user");
         }
 
-        self.auth_service = Some(auth_service);
         self
     }
 
+    /// Add a user confined to a tenant namespace
+    pub fn with_tenant_user(
+        mut self,
+        username: &str,
+        password: &str,
+        role: UserRole,
+        tenant: &str,
+    ) -> Self {
+        let auth_service = self.auth_service();
+
+        if let Err(e) = auth_service.create_tenant_user(
+            username.to_string(),
+            password,
+            role,
+            Some(tenant.to_string()),
+        ) {
+            error!(error = %e, username = username, "Failed to create tenant user");
+        } else {
+            info!(username = username, tenant = tenant, "Created tenant user");
+        }
+
+        self
+    }
+
+    /// Add a user whose password is already an Argon2 hash
+    pub fn with_hashed_user(
+        mut self,
+        username: &str,
+        password_hash: &str,
+        role: UserRole,
+        tenant: Option<&str>,
+    ) -> Self {
+        let auth_service = self.auth_service();
+
+        if let Err(e) = auth_service.create_user_with_hash(
+            username.to_string(),
+            password_hash.to_string(),
+            role,
+            tenant.map(str::to_string),
+        ) {
+            error!(error = %e, username = username, "Failed to create user");
+        } else {
+            info!(username = username, tenant = ?tenant, "Created user");
+        }
+
+        self
+    }
+
+    /// Give a user added earlier a custom role from the auth configuration
+    pub fn with_custom_role(mut self, username: &str, role: &str) -> Self {
+        let auth_service = self.auth_service();
+
+        if let Err(e) = auth_service.set_custom_role(username, Some(role.to_string())) {
+            error!(error = %e, username = username, role = role, "Failed to assign role");
+        }
+
+        self
+    }
+
+    fn auth_service(&mut self) -> Arc<AuthService> {
+        let config = &self.config.auth_config;
+        Arc::clone(
+            self.auth_service
+                .get_or_insert_with(|| Arc::new(AuthService::new(config.clone()))),
+        )
+    }
+
     /// Build the server
     pub fn build(self) -> anyhow::Result<Server> {
+        rbac::validate_roles(&self.config.auth_config.roles).map_err(anyhow::Error::msg)?;
+        let trusted_proxies = Arc::new(
+            TrustedProxies::parse(&self.config.trusted_proxies).map_err(anyhow::Error::msg)?,
+        );
+
         let auth_service = self
             .auth_service
             .unwrap_or_else(|| Arc::new(AuthService::new(self.config.auth_config.clone())));
+        if let Some(path) = &self.config.auth_config.token_store {
+            auth_service.open_token_store(path)?;
+        }
+
+        let alerts = self
+            .alerts
+            .unwrap_or_else(|| Arc::new(AlertManager::new(AlertsConfig::default())));
+
+        let client_access = self.client_access.unwrap_or_else(|| {
+            Arc::new(ClientAccessPolicy::new(ClientAccessConfig::default()))
+        });
+
+        let fts = self
+            .fts
+            .unwrap_or_else(|| Arc::new(FtsManager::new(FtsConfig::default())));
+
+        let filter = FieldRuleFilter::new(&self.app.filters)?;
 
         Ok(Server {
             config: self.config,
+            app: self.app,
+            filter,
             auth_service,
+            trusted_proxies,
+            listener: self.listener,
+            alerts,
+            client_access,
+            emergencies: self.emergencies,
+            fts,
+            tracks: self.tracks,
+            tap: self.tap,
+            gps_clock: self.gps_clock,
+            load: self.load,
+            transformers: self.transformers,
+            metrics: self.metrics,
+            plugin_config: self.plugin_config,
+            listener_endpoints: self.listener_endpoints,
+            discovery_config: self.discovery_config,
+            adb_monitor_config: self.adb_monitor_config,
+            log_control: self.log_control,
+            siem_config: self.siem_config,
+            audit_config: self.audit_config,
+            config_file: self.config_file,
+            connections: self.connections,
+            restored_connections: self.restored_connections,
+            pipeline_runtime: self.pipeline_runtime,
+            traffic_pool: self.traffic_pool,
         })
     }
 }
@@ -285,7 +922,33 @@ impl ServerBuilder {
 
 pub struct Server {
     config: ServerConfig,
+    app: AppConfig,
+    filter: FieldRuleFilter,
     auth_service: Arc<AuthService>,
+    trusted_proxies: Arc<TrustedProxies>,
+    listener: Option<std::net::TcpListener>,
+    alerts: Arc<AlertManager>,
+    client_access: Arc<ClientAccessPolicy>,
+    emergencies: Option<Arc<EmergencyTracker>>,
+    fts: Arc<FtsManager>,
+    tracks: Option<Arc<TrackStore>>,
+    tap: Option<Arc<MessageTap>>,
+    gps_clock: Option<Arc<GpsClock>>,
+    load: Option<Arc<LoadMonitor>>,
+    transformers: Option<Arc<TransformPipeline>>,
+    metrics: Option<Arc<MetricsRegistry>>,
+    plugin_config: PluginManagerConfig,
+    listener_endpoints: Vec<ListenerEndpoint>,
+    discovery_config: Option<DiscoveryConfig>,
+    adb_monitor_config: Option<AdbMonitorConfig>,
+    log_control: Option<Arc<LogLevelControl>>,
+    siem_config: Option<SiemConfig>,
+    audit_config: Option<AuditConfig>,
+    config_file: Option<PathBuf>,
+    connections: ApiConnections,
+    restored_connections: Vec<HandoffConnection>,
+    pipeline_runtime: Option<tokio::runtime::Handle>,
+    traffic_pool: Option<Arc<ConnectionPool>>,
 }
 
 impl Server {
@@ -304,58 +967,103 @@ impl Server {
         // Print embedded files in debug mode
         static_files::print_embedded_files();
 
+        // Install the recorder before anything records into it
+        let metrics = self
+            .app
+            .metrics
+            .enabled
+            .then(|| self.metrics.clone().unwrap_or_else(MetricsRegistry::global));
+
         // Initialize connection pool
         info!("Initializing connection pool");
-        let pool_config = PoolConfig {
-            max_connections: 1000,
-            channel_capacity: 1024,
-            health_check_interval: Duration::from_secs(30),
-            inactive_timeout: Duration::from_secs(300),
-            auto_reconnect: true,
-        };
-        let pool = Arc::new(ConnectionPool::new(pool_config));
+        let pool = Arc::new(ConnectionPool::new(PoolConfig::from(&self.app.pool)));
 
         // Initialize message distributor
         info!("Initializing message distributor");
-        let distributor_config = DistributorConfig {
-            strategy: DistributionStrategy::DropOnFull,
-            channel_capacity: 1024,
-            max_workers: 4,
-            batch_size: 10,
-            flush_interval: Duration::from_millis(100),
-        };
+        let distributor_config = DistributorConfig::from(&self.app.distributor);
         let distributor = Arc::new(MessageDistributor::new(pool.clone(), distributor_config));
 
-        // Initialize message aggregator (for future use)
-        let aggregator_config = AggregatorConfig {
-            dedup_window: Duration::from_secs(60),
-            max_cache_entries: 10000,
-            cleanup_interval: Duration::from_secs(30),
-            channel_capacity: 1024,
-            worker_count: 4,
+        // Initialize message aggregator; injected CoT goes through it so
+        // deduplication, emergency detection and filters apply
+        let aggregator_config = AggregatorConfig::from(&self.app.aggregator);
+        let mut aggregator = MessageAggregator::new(distributor.clone(), aggregator_config);
+        if !self.filter.passes_everything() {
+            aggregator = aggregator.with_filter(Arc::new(self.filter.clone()));
+        }
+        if let Some(emergencies) = self.emergencies.clone() {
+            aggregator = aggregator.with_emergency_tracker(emergencies);
+        }
+        let transformers = self
+            .transformers
+            .clone()
+            .unwrap_or_else(|| Arc::new(TransformPipeline::new(TransformConfig::default())));
+        aggregator = aggregator.with_transformers(transformers.clone());
+        let aggregator = Arc::new(aggregator);
+        let emergencies = aggregator.emergencies();
+
+        // Feed the track stream from this distributor unless one was shared
+        let tracks = match self.tracks.clone() {
+            Some(tracks) => tracks,
+            None => {
+                let tracks = Arc::new(TrackStore::new());
+                distributor.add_sink(tracks.clone(), SinkBatchConfig::default());
+                tracks.clone().start();
+                tracks
+            }
         };
-        let _aggregator = Arc::new(MessageAggregator::new(
-            distributor.clone(),
-            aggregator_config,
-        ));
 
-        // Create application state
-        let audit_logger = Arc::new(middleware::AuditLogger::new());
-        let api_state = ApiState {
-            auth_service: self.auth_service.clone(),
-            audit_logger: audit_logger.clone(),
-            pool: pool.clone(),
-            distributor: distributor.clone(),
-            connections: Arc::new(RwLock::new(Vec::new())),
-            start_time: std::time::Instant::now(),
-            discovery: None, // TODO: Initialize discovery service if enabled in config
-        };
+        let tap = self.tap.clone().unwrap_or_else(|| distributor.tap());
+
+        let load = self.load.clone().unwrap_or_else(|| {
+            Arc::new(LoadMonitor::new(
+                pool.clone(),
+                aggregator.clone(),
+                distributor.clone(),
+            ))
+        });
+
+        match &self.pipeline_runtime {
+            Some(runtime) => {
+                let (distributor, aggregator) = (distributor.clone(), aggregator.clone());
+                runtime
+                    .spawn(async move {
+                        distributor.start().await;
+                        aggregator.start().await;
+                    })
+                    .await?;
+            }
+            None => {
+                distributor.start().await;
+                aggregator.start().await;
+            }
+        }
+
+        // Start clock synchronization monitoring
+        let mut time_monitor = time_sync::TimeMonitor::new(self.config.time_sync.clone());
+        if let Some(gps_clock) = self.gps_clock.clone() {
+            time_monitor = time_monitor.with_gps_clock(gps_clock);
+        }
+        let time_monitor = Arc::new(time_monitor);
+        if self.config.time_sync.enabled {
+            time_monitor.clone().start();
+        }
+
+        let audit_logger = Arc::new(match &self.audit_config {
+            Some(config) => middleware::AuditLogger::open(config)?,
+            None => middleware::AuditLogger::new(),
+        });
+        tokio::spawn(prune_audit_log(audit_logger.clone()));
+        if let Some(siem_config) = self.siem_config.clone() {
+            siem::spawn(siem_config, &audit_logger)?;
+        }
 
         // Initialize plugin manager in a blocking task to avoid blocking the async runtime
         // (wasmtime Engine creation with Cranelift JIT compilation is CPU-intensive)
         info!("Initializing plugin manager");
-        let plugin_manager = tokio::task::spawn_blocking(|| {
-            match PluginManager::new(omnitak_plugin_api::PluginManagerConfig::default()) {
+        let plugin_config = self.plugin_config.clone();
+        let hot_reload = plugin_config.hot_reload;
+        let plugin_manager = tokio::task::spawn_blocking(move || {
+            match PluginManager::new(plugin_config) {
                 Ok(manager) => Arc::new(RwLock::new(manager)),
                 Err(e) => {
                     warn!("Failed to initialize plugin manager: {}, plugins will be disabled", e);
@@ -368,13 +1076,135 @@ impl Server {
                 }
             }
         }).await.expect("Plugin manager spawn_blocking failed");
+        plugin_manager
+            .read()
+            .await
+            .set_cot_sink(Arc::new(rest::plugins::AggregatorCotSink(aggregator.clone())));
+        tokio::spawn(rest::plugins::sync_transformers(
+            plugin_manager.clone(),
+            transformers.clone(),
+        ));
+        if hot_reload {
+            tokio::spawn(rest::plugins::hot_reload(plugin_manager.clone()));
+        }
+        let plugin_metrics = plugin_manager.read().await.metrics();
         let plugin_state = rest::plugins::PluginApiState {
             plugin_manager,
             audit_logger: audit_logger.clone(),
+            transformers,
+        };
+
+        // Start mDNS discovery and announcement
+        let discovery = match self.discovery_config.clone().filter(|c| c.enabled) {
+            Some(config) => match DiscoveryService::new(config) {
+                Ok(service) => {
+                    let service = Arc::new(service);
+                    match service.start().await {
+                        Ok(()) => Some(service),
+                        Err(e) => {
+                            warn!("Failed to start discovery service: {}", e);
+                            None
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to create discovery service: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        // Watch for Android devices attached over ADB
+        let adb_monitor = self.adb_monitor_config.clone().map(|config| {
+            let state = Arc::new(adb::AdbMonitorState::new(config));
+            tokio::spawn(state.monitor.clone().start());
+            state
+        });
+
+        // Package store, shared by the Marti sync routes and package uploads
+        let packages = match &self.config.marti {
+            Some(marti) => Some(Arc::new(
+                omnitak_datapackage::PackageStore::open(&marti.storage_dir)?
+                    .with_quota(marti.max_storage_mb * 1024 * 1024),
+            )),
+            None => None,
+        };
+
+        // Create application state
+        let api_state = ApiState {
+            auth_service: self.auth_service.clone(),
+            audit_logger: audit_logger.clone(),
+            pool: pool.clone(),
+            traffic_pool: self.traffic_pool.clone(),
+            filters: Arc::new(filters::FilterStore::new()),
+            distributor: distributor.clone(),
+            aggregator: aggregator.clone(),
+            connections: self.connections.connections.clone(),
+            connection_requests: self.connections.requests.clone(),
+            start_time: std::time::Instant::now(),
+            discovery,
+            certificates: Arc::new(rest::certificates::CertificateStore::new()),
+            packages: packages.clone(),
+            max_package_bytes: self
+                .config
+                .marti
+                .as_ref()
+                .map_or(0, |marti| marti.max_upload_mb as u64 * 1024 * 1024),
+            time_sync: time_monitor,
+            alerts: self.alerts.clone(),
+            client_access: self.client_access.clone(),
+            emergencies: emergencies.clone(),
+            fts: self.fts.clone(),
+            tracks: tracks.clone(),
+            tap,
+            load,
+            resources: Arc::new(ResourceMonitor::new()),
+            metrics,
+            plugin_metrics,
+            plugin_manager: plugin_state.plugin_manager.clone(),
+            adb_monitor,
+            log_control: self.log_control.clone(),
+            config_file: self.config_file.clone(),
         };
 
-        let ws_state = websocket::WsState::new(self.auth_service.clone());
-        let rate_limit_state = Arc::new(RateLimitState::new(self.config.rate_limit_rps));
+        for connection in &self.restored_connections {
+            let owner = auth::AuthUser {
+                user_id: None,
+                role: UserRole::Admin,
+                tenant: connection.tenant.clone(),
+                permissions: Default::default(),
+            };
+            if let Err(e) = rest::open_connection(&api_state, &owner, &connection.request).await {
+                warn!(name = connection.request.name, error = %e, "Failed to reopen connection");
+            }
+        }
+
+        let ws_state = websocket::WsState::new(self.auth_service.clone())
+            .with_tracks(tracks)
+            .with_pool(api_state.pool.clone());
+        tokio::spawn(forward_emergencies(
+            emergencies.subscribe(),
+            api_state.pool.clone(),
+            ws_state.clone(),
+        ));
+        tokio::spawn(forward_connection_states(
+            api_state.connections.clone(),
+            ws_state.clone(),
+        ));
+        tokio::spawn(forward_health(api_state.clone(), ws_state.clone()));
+        if let Some(discovery) = &api_state.discovery {
+            tokio::spawn(forward_discovery_events(discovery.clone(), ws_state.clone()));
+        }
+        if let Some(adb_monitor) = &api_state.adb_monitor {
+            tokio::spawn(forward_device_events(
+                adb_monitor.clone(),
+                api_state.clone(),
+                ws_state.clone(),
+            ));
+        }
+        let rate_limit_state = Arc::new(RateLimitState::new(&self.config.rate_limit));
+        tokio::spawn(prune_rate_limits(rate_limit_state.clone()));
         let readiness_state = Arc::new(ReadinessState::new());
 
         // Build the router
@@ -397,6 +1227,7 @@ impl Server {
             // Set CA paths if configured
             enrollment_state.ca_cert_path = self.config.enrollment_ca_cert_path.clone();
             enrollment_state.ca_key_path = self.config.enrollment_ca_key_path.clone();
+            enrollment_state.listeners = Arc::new(self.listener_endpoints.clone());
 
             // Initialize CA
             if let Err(e) = enrollment_state.initialize_ca(None).await {
@@ -427,9 +1258,25 @@ impl Server {
             }
         }
 
+        // Add Marti sync routes if enabled
+        if let (Some(marti), Some(store)) = (&self.config.marti, packages) {
+            let marti_state = rest::marti::MartiState {
+                store,
+                tls: self.config.enable_tls,
+            };
+            app = app.merge(rest::marti::create_marti_router(
+                marti_state,
+                marti.max_upload_mb * 1024 * 1024,
+            ));
+            info!(
+                storage_dir = %marti.storage_dir.display(),
+                "Marti sync enabled at /Marti/sync/*"
+            );
+        }
+
         // Add OpenAPI JSON endpoint if enabled
         if self.config.enable_swagger {
-            let openapi = ApiDoc::openapi();
+            let openapi = openapi();
             app = app.route(
                 "/api-docs/openapi.json",
                 axum::routing::get(|| async move { axum::Json(openapi) }),
@@ -455,7 +1302,16 @@ impl Server {
         // Add middleware layers
         app = app.layer(
             ServiceBuilder::new()
-                .layer(TraceLayer::new_for_http())
+                .layer(TraceLayer::new_for_http().make_span_with(
+                    |request: &axum::http::Request<axum::body::Body>| {
+                        tracing::debug_span!(
+                            "request",
+                            method = %request.method(),
+                            uri = %middleware::loggable_uri(request.uri()),
+                            version = ?request.version(),
+                        )
+                    },
+                ))
                 .layer(axum::middleware::from_fn(request_id_middleware))
                 .layer(axum::middleware::from_fn(security_headers_middleware))
                 .layer(axum::middleware::from_fn(timeout_middleware))
@@ -468,14 +1324,24 @@ impl Server {
                 .layer(cors_layer()),
         );
 
-        // Add auth service to extensions
-        app = app.layer(axum::Extension(self.auth_service.clone()));
+        // Add auth service to extensions, the audit log for rejected
+        // credentials, and the proxies trusted to report client addresses
+        app = app
+            .layer(axum::Extension(self.auth_service.clone()))
+            .layer(axum::Extension(audit_logger.clone()))
+            .layer(axum::Extension(self.trusted_proxies.clone()));
 
         // Mark server as ready
         readiness_state.set_ready(true);
 
         // Start server
-        let listener = tokio::net::TcpListener::bind(self.config.bind_addr).await?;
+        let listener = match self.listener {
+            Some(listener) => {
+                listener.set_nonblocking(true)?;
+                tokio::net::TcpListener::from_std(listener)?
+            }
+            None => tokio::net::TcpListener::bind(self.config.bind_addr).await?,
+        };
         let local_addr = listener.local_addr()?;
 
         info!(
@@ -503,6 +1369,300 @@ impl Server {
     }
 }
 
+// ============================================================================
+// Emergency Events
+// ============================================================================
+
+/// Forget rate limit buckets of clients that have gone quiet
+async fn prune_rate_limits(rate_limit_state: Arc<RateLimitState>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(60));
+    loop {
+        interval.tick().await;
+        rate_limit_state.retain_recent();
+    }
+}
+
+/// Apply the audit log retention policy
+async fn prune_audit_log(audit_logger: Arc<middleware::AuditLogger>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(3600));
+    loop {
+        interval.tick().await;
+        let logger = audit_logger.clone();
+        match tokio::task::spawn_blocking(move || logger.prune()).await {
+            Ok(Ok(0)) => {}
+            Ok(Ok(deleted)) => info!(deleted, "Pruned audit log"),
+            Ok(Err(e)) => warn!(error = %e, "Failed to prune audit log"),
+            Err(e) => warn!(error = %e, "Audit log pruning panicked"),
+        }
+    }
+}
+
+/// Push emergency state changes to WebSocket clients as system events,
+/// to those that may see the tenant of the connection the beacon came from
+async fn forward_emergencies(
+    mut events: tokio::sync::broadcast::Receiver<Emergency>,
+    pool: Arc<ConnectionPool>,
+    ws_state: websocket::WsState,
+) {
+    use tokio::sync::broadcast::error::RecvError;
+
+    loop {
+        match events.recv().await {
+            Ok(emergency) => {
+                let tenant = pool.tenant_of(&emergency.source);
+                let info = types::EmergencyInfo::from(emergency);
+                ws_state.broadcast_tenant_event(
+                    tenant,
+                    types::WsServerMessage::SystemEvent {
+                        event: "emergency".to_string(),
+                        details: serde_json::to_value(&info).unwrap_or_default(),
+                        timestamp: chrono::Utc::now(),
+                    },
+                );
+            }
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Dropped {} emergency events for WebSocket clients", skipped);
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+// ============================================================================
+// Connection State and Health Events
+// ============================================================================
+
+/// How often connection statuses are compared
+const CONNECTION_STATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often a health summary is sent
+const HEALTH_EVENT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Push connection status changes to event clients that may see the
+/// connection's tenant as `connection_state` system events; a deleted
+/// connection is reported with status `removed`
+async fn forward_connection_states(
+    connections: Arc<RwLock<Vec<types::ConnectionInfo>>>,
+    ws_state: websocket::WsState,
+) {
+    type Known = (String, Option<String>, types::ConnectionStatus);
+    let mut known: std::collections::HashMap<uuid::Uuid, Known> = std::collections::HashMap::new();
+    let mut interval = tokio::time::interval(CONNECTION_STATE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let connections = connections.read().await;
+
+        for info in connections.iter() {
+            let previous = known
+                .insert(
+                    info.id,
+                    (info.name.clone(), info.tenant.clone(), info.status),
+                )
+                .map(|(_, _, status)| status);
+            if previous == Some(info.status) {
+                continue;
+            }
+            ws_state.broadcast_tenant_event(
+                info.tenant.clone(),
+                types::WsServerMessage::SystemEvent {
+                    event: "connection_state".to_string(),
+                    details: serde_json::json!({
+                        "id": info.id,
+                        "name": info.name,
+                        "address": format!("{}:{}", info.address, info.port),
+                        "status": info.status,
+                        "previous": previous,
+                        "error": info.error,
+                    }),
+                    timestamp: chrono::Utc::now(),
+                },
+            );
+        }
+
+        known.retain(|id, (name, tenant, previous)| {
+            if connections.iter().any(|c| c.id == *id) {
+                return true;
+            }
+            ws_state.broadcast_tenant_event(
+                tenant.take(),
+                types::WsServerMessage::SystemEvent {
+                    event: "connection_state".to_string(),
+                    details: serde_json::json!({
+                        "id": id,
+                        "name": name,
+                        "status": "removed",
+                        "previous": previous,
+                    }),
+                    timestamp: chrono::Utc::now(),
+                },
+            );
+            false
+        });
+    }
+}
+
+/// Send event clients a `health` summary every [`HEALTH_EVENT_INTERVAL`],
+/// so dashboards notice both problems and the server going quiet
+async fn forward_health(api_state: ApiState, ws_state: websocket::WsState) {
+    let mut interval = tokio::time::interval(HEALTH_EVENT_INTERVAL);
+    loop {
+        interval.tick().await;
+        let load = api_state.load.report();
+        let (connections, connected) = {
+            let connections = api_state.connections.read().await;
+            let connected = connections
+                .iter()
+                .filter(|c| c.status == types::ConnectionStatus::Connected)
+                .count();
+            (connections.len(), connected)
+        };
+        ws_state.broadcast_event(types::WsServerMessage::SystemEvent {
+            event: "health".to_string(),
+            details: serde_json::json!({
+                "status": match load.state {
+                    types::LoadState::Up => "healthy",
+                    types::LoadState::Drain => "draining",
+                },
+                "uptime_seconds": api_state.start_time.elapsed().as_secs(),
+                "connections": connections,
+                "connected": connected,
+                "load": load,
+            }),
+            timestamp: chrono::Utc::now(),
+        });
+    }
+}
+
+// ============================================================================
+// Discovery Events
+// ============================================================================
+
+/// Push discovered services appearing, changing and disappearing to
+/// WebSocket clients as `service_appeared`, `service_updated` and
+/// `service_lost` system events. A service going stale is an update.
+async fn forward_discovery_events(discovery: Arc<DiscoveryService>, ws_state: websocket::WsState) {
+    use omnitak_discovery::ServiceEventType;
+
+    let events = discovery.event_receiver();
+    while let Ok(event) = events.recv().await {
+        let name = match event.event_type {
+            ServiceEventType::Discovered => "service_appeared",
+            ServiceEventType::Updated | ServiceEventType::Stale => "service_updated",
+            ServiceEventType::Lost => "service_lost",
+        };
+        let service = discovery::DiscoveredServiceResponse::from(event.service);
+        ws_state.broadcast_tenant_event(
+            None,
+            types::WsServerMessage::SystemEvent {
+                event: name.to_string(),
+                details: serde_json::to_value(&service).unwrap_or_default(),
+                timestamp: event.timestamp,
+            },
+        );
+    }
+}
+
+// ============================================================================
+// ADB Device Events
+// ============================================================================
+
+/// Push devices being attached and detached to WebSocket clients as
+/// `device_attached` and `device_detached` system events, and pull
+/// certificates from approved devices as they are attached, reporting the
+/// outcome as `device_certificates_pulled`
+async fn forward_device_events(
+    adb_monitor: Arc<adb::AdbMonitorState>,
+    api_state: ApiState,
+    ws_state: websocket::WsState,
+) {
+    use omnitak_adb::DeviceEvent;
+    use tokio::sync::broadcast::error::RecvError;
+
+    let mut events = adb_monitor.monitor.subscribe();
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Dropped {} ADB device events for WebSocket clients", skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+
+        let (name, details) = match &event {
+            DeviceEvent::Connected(device) => (
+                "device_attached",
+                serde_json::json!({
+                    "serial": device.serial,
+                    "model": device.model,
+                    "product": device.product,
+                    "wireless": device.is_wireless(),
+                    "approved": adb_monitor.is_approved(&device.serial),
+                }),
+            ),
+            DeviceEvent::Disconnected(serial) => {
+                ("device_detached", serde_json::json!({ "serial": serial }))
+            }
+        };
+        ws_state.broadcast_tenant_event(
+            None,
+            types::WsServerMessage::SystemEvent {
+                event: name.to_string(),
+                details,
+                timestamp: chrono::Utc::now(),
+            },
+        );
+
+        let DeviceEvent::Connected(device) = event else {
+            continue;
+        };
+        if !adb_monitor.is_approved(&device.serial) {
+            continue;
+        }
+
+        info!("Pulling certificates from approved device {}", device.serial);
+        let config = adb_monitor.config.clone();
+        let api_state = api_state.clone();
+        let ws_state = ws_state.clone();
+        tokio::spawn(async move {
+            let result = adb::pull_from_device(
+                api_state,
+                Some(device.serial.clone()),
+                &config.package,
+                &config.cert_dir,
+                config.auto_connect,
+            )
+            .await;
+
+            let details = match result {
+                Ok(response) => serde_json::json!({
+                    "serial": device.serial,
+                    "success": true,
+                    "message": response.message,
+                    "certificate_id": response.certificate_id,
+                    "connection_id": response.connection_id,
+                }),
+                Err(e) => {
+                    warn!("Automatic certificate pull from {} failed: {}", device.serial, e);
+                    serde_json::json!({
+                        "serial": device.serial,
+                        "success": false,
+                        "message": e.to_string(),
+                    })
+                }
+            };
+            ws_state.broadcast_tenant_event(
+                None,
+                types::WsServerMessage::SystemEvent {
+                    event: "device_certificates_pulled".to_string(),
+                    details,
+                    timestamp: chrono::Utc::now(),
+                },
+            );
+        });
+    }
+}
+
 // ============================================================================
 // Graceful Shutdown
 // ============================================================================
@@ -556,4 +1716,54 @@ mod tests {
             .build();
         assert!(server.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_connections_snapshot() {
+        let connections = ServerBuilder::new(ServerConfig::default()).connections();
+        let request: types::CreateConnectionRequest = serde_json::from_value(serde_json::json!({
+            "name": "blue-feed",
+            "connection_type": "tcpclient",
+            "address": "10.0.0.5",
+            "port": 8087,
+        }))
+        .unwrap();
+        let info = |name: &str, tenant: Option<&str>| types::ConnectionInfo {
+            id: uuid::Uuid::new_v4(),
+            name: name.to_string(),
+            connection_type: types::ConnectionType::TcpClient,
+            status: types::ConnectionStatus::Connected,
+            address: "10.0.0.5".to_string(),
+            port: 8087,
+            messages_received: 0,
+            messages_sent: 0,
+            bytes_received: 0,
+            bytes_sent: 0,
+            connected_at: None,
+            last_activity: None,
+            error: None,
+            tenant: tenant.map(str::to_string),
+        };
+
+        // Only connections with the request that created them are handed over
+        let created = info("blue-feed", Some("blue"));
+        connections
+            .requests
+            .write()
+            .await
+            .insert(created.id, request);
+        connections
+            .connections
+            .write()
+            .await
+            .extend([created, info("other", None)]);
+
+        let snapshot = connections.snapshot().await;
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].request.name, "blue-feed");
+        assert_eq!(snapshot[0].tenant.as_deref(), Some("blue"));
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let restored: Vec<HandoffConnection> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored[0].request.port, 8087);
+    }
 }
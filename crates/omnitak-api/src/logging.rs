@@ -0,0 +1,172 @@
+//! Runtime log level control
+//!
+//! The binary owns the tracing subscriber; the API only keeps the current
+//! levels and hands changes to a callback that swaps the subscriber's
+//! filter. Changes last until the next restart, or until they are reset to
+//! the levels from the configuration file.
+
+use crate::types::{LogLevelStatus, LogLevels};
+use parking_lot::RwLock;
+use std::str::FromStr;
+use tracing::level_filters::LevelFilter;
+
+type ApplyFn = dyn Fn(&LogLevels) -> Result<(), String> + Send + Sync;
+
+/// Current log levels and the hook that applies new ones
+pub struct LogLevelControl {
+    configured: LogLevels,
+    current: RwLock<LogLevels>,
+    apply: Box<ApplyFn>,
+}
+
+impl LogLevelControl {
+    /// `configured` are the levels the subscriber was started with; `apply`
+    /// installs a filter built from new levels
+    pub fn new<F>(configured: LogLevels, apply: F) -> Self
+    where
+        F: Fn(&LogLevels) -> Result<(), String> + Send + Sync + 'static,
+    {
+        Self {
+            current: RwLock::new(configured.clone()),
+            configured,
+            apply: Box::new(apply),
+        }
+    }
+
+    pub fn status(&self) -> LogLevelStatus {
+        LogLevelStatus {
+            current: self.current.read().clone(),
+            configured: self.configured.clone(),
+        }
+    }
+
+    /// Validate and apply new levels
+    pub fn set(&self, levels: LogLevels) -> Result<LogLevelStatus, String> {
+        levels.validate_levels()?;
+        let mut current = self.current.write();
+        (self.apply)(&levels)?;
+        *current = levels;
+        drop(current);
+        Ok(self.status())
+    }
+
+    /// Go back to the levels from the configuration file
+    pub fn reset(&self) -> Result<LogLevelStatus, String> {
+        self.set(self.configured.clone())
+    }
+}
+
+impl LogLevels {
+    /// `EnvFilter` directives, e.g. `info,omnitak_pool=debug`
+    pub fn directives(&self) -> String {
+        std::iter::once(self.level.clone())
+            .chain(
+                self.levels
+                    .iter()
+                    .map(|(target, level)| format!("{}={}", target, level)),
+            )
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Check level names and targets
+    pub fn validate_levels(&self) -> Result<(), String> {
+        check_level(&self.level)?;
+        for (target, level) in &self.levels {
+            if target.is_empty()
+                || target
+                    .chars()
+                    .any(|c| !(c.is_ascii_alphanumeric() || c == '_' || c == ':' || c == '-'))
+            {
+                return Err(format!("Invalid log target '{}'", target));
+            }
+            check_level(level)?;
+        }
+        Ok(())
+    }
+}
+
+fn check_level(level: &str) -> Result<(), String> {
+    LevelFilter::from_str(level).map(|_| ()).map_err(|_| {
+        format!(
+            "Invalid log level '{}' (expected trace, debug, info, warn, error or off)",
+            level
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    fn levels(level: &str, targets: &[(&str, &str)]) -> LogLevels {
+        LogLevels {
+            level: level.to_string(),
+            levels: targets
+                .iter()
+                .map(|(t, l)| (t.to_string(), l.to_string()))
+                .collect::<BTreeMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn test_directives() {
+        assert_eq!(levels("warn", &[]).directives(), "warn");
+        assert_eq!(
+            levels(
+                "info",
+                &[("omnitak_pool", "debug"), ("omnitak_api::rest", "trace")]
+            )
+            .directives(),
+            "info,omnitak_api::rest=trace,omnitak_pool=debug"
+        );
+    }
+
+    #[test]
+    fn test_set_and_reset() {
+        let applied = Arc::new(Mutex::new(Vec::new()));
+        let sink = applied.clone();
+        let control = LogLevelControl::new(levels("info", &[]), move |l: &LogLevels| {
+            sink.lock().unwrap().push(l.directives());
+            Ok(())
+        });
+
+        let status = control
+            .set(levels("info", &[("omnitak_pool", "debug")]))
+            .unwrap();
+        assert_eq!(status.current.directives(), "info,omnitak_pool=debug");
+        assert_eq!(status.configured.directives(), "info");
+
+        let status = control.reset().unwrap();
+        assert_eq!(status.current.directives(), "info");
+        assert_eq!(
+            *applied.lock().unwrap(),
+            vec!["info,omnitak_pool=debug", "info"]
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_levels() {
+        let control = LogLevelControl::new(levels("info", &[]), |_: &LogLevels| Ok(()));
+        assert!(control.set(levels("loud", &[])).is_err());
+        assert!(control
+            .set(levels("info", &[("omnitak_pool", "verbose")]))
+            .is_err());
+        assert!(control.set(levels("info", &[("a=b,c", "debug")])).is_err());
+        assert_eq!(control.status().current.directives(), "info");
+    }
+
+    #[test]
+    fn test_apply_failure_keeps_levels() {
+        let control = LogLevelControl::new(levels("info", &[]), |_: &LogLevels| {
+            Err("filter rejected".to_string())
+        });
+        assert_eq!(
+            control.set(levels("debug", &[])).unwrap_err(),
+            "filter rejected"
+        );
+        assert_eq!(control.status().current.level, "info");
+    }
+}
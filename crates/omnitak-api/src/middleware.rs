@@ -1,25 +1,28 @@
 //! Middleware for logging, CORS, rate limiting, and security headers
 
+use crate::audit::{AuditConfig, AuditStore};
 use crate::auth::AuthService;
-use crate::types::{AuditLogEntry, ErrorResponse, UserRole};
+use crate::types::{AuditLogEntry, AuditLogPage, AuditQuery, ErrorResponse, UserRole};
 use axum::{
     Json,
     body::Body,
-    extract::{ConnectInfo, Request, State},
-    http::{HeaderValue, StatusCode},
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{Extensions, HeaderMap, HeaderValue, Method, StatusCode, Uri, header, request::Parts},
     middleware::Next,
     response::{IntoResponse, Response},
 };
-use dashmap::DashMap;
 use governor::{
-    Quota, RateLimiter,
-    clock::DefaultClock,
-    state::{InMemoryState, direct::NotKeyed},
+    Quota, RateLimiter, clock::Clock, clock::DefaultClock, state::keyed::DefaultKeyedStateStore,
 };
-use std::net::SocketAddr;
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::num::NonZeroU32;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::broadcast;
 use tower_http::cors::{Any, CorsLayer};
 use tracing::{error, info, warn};
 use uuid::Uuid;
@@ -28,14 +31,29 @@ use uuid::Uuid;
 // Audit Logging
 // ============================================================================
 
+/// Entries buffered for subscribers that fall behind
+const AUDIT_FEED_CAPACITY: usize = 1024;
+
 pub struct AuditLogger {
-    logs: Arc<DashMap<Uuid, AuditLogEntry>>,
+    store: AuditStore,
+    feed: broadcast::Sender<AuditLogEntry>,
 }
 
 impl AuditLogger {
+    /// Logger keeping entries in memory
     pub fn new() -> Self {
+        Self::with_store(AuditStore::in_memory())
+    }
+
+    /// Logger with the configured storage and retention
+    pub fn open(config: &AuditConfig) -> anyhow::Result<Self> {
+        Ok(Self::with_store(AuditStore::open(config)?))
+    }
+
+    fn with_store(store: AuditStore) -> Self {
         Self {
-            logs: Arc::new(DashMap::new()),
+            store,
+            feed: broadcast::channel(AUDIT_FEED_CAPACITY).0,
         }
     }
 
@@ -49,32 +67,85 @@ impl AuditLogger {
         source_ip: String,
         success: bool,
     ) {
-        let entry = AuditLogEntry {
+        self.record(AuditLogEntry {
             id: Uuid::new_v4(),
             user,
-            role,
+            role: Some(role),
             action,
             resource,
             details,
             source_ip,
             timestamp: chrono::Utc::now(),
             success,
-        };
+        });
+    }
 
-        info!(
-            audit = true,
-            user = entry.user,
-            action = entry.action,
-            resource = entry.resource,
-            success = entry.success,
-            "Audit log"
-        );
+    /// Record a rejected login or request. `user` is the claimed username,
+    /// if any; `role` is set when an authenticated user lacked permission.
+    pub fn log_auth_failure(
+        &self,
+        user: Option<&str>,
+        role: Option<UserRole>,
+        action: &str,
+        resource: &str,
+        reason: &str,
+        source_ip: Option<IpAddr>,
+    ) {
+        self.record(AuditLogEntry {
+            id: Uuid::new_v4(),
+            user: user.unwrap_or("anonymous").to_string(),
+            role,
+            action: action.to_string(),
+            resource: resource.to_string(),
+            details: serde_json::json!({"reason": reason}),
+            source_ip: source_ip.map(|ip| ip.to_string()).unwrap_or_default(),
+            timestamp: chrono::Utc::now(),
+            success: false,
+        });
+    }
 
-        self.logs.insert(entry.id, entry);
+    fn record(&self, entry: AuditLogEntry) {
+        if entry.success {
+            info!(
+                audit = true,
+                user = entry.user,
+                action = entry.action,
+                resource = entry.resource,
+                success = entry.success,
+                "Audit log"
+            );
+        } else {
+            warn!(
+                audit = true,
+                user = entry.user,
+                action = entry.action,
+                resource = entry.resource,
+                source_ip = entry.source_ip,
+                success = entry.success,
+                "Audit log"
+            );
+        }
+
+        if let Err(e) = self.store.insert(&entry) {
+            error!(error = %e, action = entry.action, "Failed to store audit log entry");
+        }
+        // No subscribers is fine
+        let _ = self.feed.send(entry);
     }
 
-    pub fn get_logs(&self) -> Vec<AuditLogEntry> {
-        self.logs.iter().map(|e| e.value().clone()).collect()
+    /// Receive entries as they are logged, e.g. to forward them to a SIEM
+    pub fn subscribe(&self) -> broadcast::Receiver<AuditLogEntry> {
+        self.feed.subscribe()
+    }
+
+    /// Stored entries matching the query, newest first
+    pub fn query(&self, query: &AuditQuery) -> anyhow::Result<AuditLogPage> {
+        self.store.query(query)
+    }
+
+    /// Delete entries past the retention policy
+    pub fn prune(&self) -> anyhow::Result<usize> {
+        self.store.prune()
     }
 }
 
@@ -84,24 +155,146 @@ impl Default for AuditLogger {
     }
 }
 
+// ============================================================================
+// Client Address
+// ============================================================================
+
+/// Reverse proxies whose `Forwarded` and `X-Forwarded-For` headers are
+/// believed. Without any, the client address is the peer of the connection.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Vec<IpNet>);
+
+impl TrustedProxies {
+    /// Parse addresses and CIDR ranges, e.g. `10.0.0.0/8` or `::1`
+    pub fn parse(entries: &[String]) -> Result<Self, String> {
+        entries
+            .iter()
+            .map(|entry| {
+                let entry = entry.trim();
+                entry
+                    .parse::<IpNet>()
+                    .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| format!("Invalid trusted proxy address: {}", entry))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.0.iter().any(|net| net.contains(&ip))
+    }
+
+    /// Address of the client behind the proxies: the last hop a trusted
+    /// proxy reports that is not itself a trusted proxy. `Forwarded` is
+    /// preferred over `X-Forwarded-For`; a hop that cannot be parsed ends the
+    /// walk at the proxy that reported it.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.contains(peer) {
+            return peer;
+        }
+
+        let forwarded = header_list(headers, header::FORWARDED);
+        let hops: Vec<Option<IpAddr>> = if forwarded.is_empty() {
+            header_list(headers, "x-forwarded-for")
+                .iter()
+                .map(|hop| parse_hop(hop))
+                .collect()
+        } else {
+            forwarded
+                .iter()
+                .map(|element| {
+                    element
+                        .split(';')
+                        .filter_map(|pair| pair.trim().split_once('='))
+                        .find(|(name, _)| name.eq_ignore_ascii_case("for"))
+                        .and_then(|(_, value)| parse_hop(value))
+                })
+                .collect()
+        };
+
+        let mut client = peer;
+        for hop in hops.into_iter().rev() {
+            if !self.contains(client) {
+                break;
+            }
+            match hop {
+                Some(ip) => client = ip,
+                None => break,
+            }
+        }
+        client
+    }
+}
+
+/// Comma-separated elements of every instance of a header
+fn header_list(headers: &HeaderMap, name: impl header::AsHeaderName) -> Vec<&str> {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|element| !element.is_empty())
+        .collect()
+}
+
+/// Address of a forwarding hop: `192.0.2.60`, `"192.0.2.60:4711"` or
+/// `"[2001:db8::1]:4711"`. Obfuscated identifiers and `unknown` give `None`.
+fn parse_hop(hop: &str) -> Option<IpAddr> {
+    let hop = hop.trim().trim_matches('"');
+    if let Some(rest) = hop.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+    hop.parse().ok().or_else(|| {
+        let (host, port) = hop.rsplit_once(':')?;
+        port.parse::<u16>().ok()?;
+        host.parse().ok()
+    })
+}
+
+/// Address of the client that sent a request, resolved through trusted
+/// proxies; unspecified when the server did not record the peer address
+pub fn resolve_client_ip(extensions: &Extensions, headers: &HeaderMap) -> IpAddr {
+    let Some(ConnectInfo(peer)) = extensions.get::<ConnectInfo<SocketAddr>>() else {
+        return Ipv4Addr::UNSPECIFIED.into();
+    };
+    match extensions.get::<Arc<TrustedProxies>>() {
+        Some(proxies) => proxies.client_ip(peer.ip(), headers),
+        None => peer.ip(),
+    }
+}
+
+/// Extractor for the client address recorded in audit entries
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub IpAddr);
+
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(resolve_client_ip(&parts.extensions, &parts.headers)))
+    }
+}
+
 // ============================================================================
 // Request Logging Middleware
 // ============================================================================
 
-pub async fn logging_middleware(
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
-    request: Request,
-    next: Next,
-) -> Response {
+pub async fn logging_middleware(request: Request, next: Next) -> Response {
     let method = request.method().clone();
-    let uri = request.uri().clone();
+    let uri = loggable_uri(request.uri());
     let version = request.version();
+    let client_ip = resolve_client_ip(request.extensions(), request.headers());
 
     info!(
         method = %method,
         uri = %uri,
         version = ?version,
-        remote_addr = %addr,
+        remote_addr = %client_ip,
         "Incoming request"
     );
 
@@ -120,50 +313,281 @@ pub async fn logging_middleware(
     response
 }
 
+/// `uri` with the value of an `access_token` query parameter, which
+/// WebSocket handshakes may carry, masked
+pub fn loggable_uri(uri: &Uri) -> String {
+    match uri.query() {
+        Some(query) if query.contains("access_token=") => {
+            let query: Vec<&str> = query
+                .split('&')
+                .map(|pair| {
+                    if pair.starts_with("access_token=") {
+                        "access_token=REDACTED"
+                    } else {
+                        pair
+                    }
+                })
+                .collect();
+            format!("{}?{}", uri.path(), query.join("&"))
+        }
+        _ => uri.to_string(),
+    }
+}
+
 // ============================================================================
 // Rate Limiting
 // ============================================================================
 
+/// Per-client rate limits. Clients are told apart by API key, then by the
+/// user in a valid bearer token, then by source address. Reads (GET, HEAD,
+/// OPTIONS) and mutating requests draw from separate buckets. A rate of 0
+/// turns that limit off; a burst of 0 is the same as the rate.
+///
+/// Every request is also charged to its source address under the higher
+/// `ip_*` limits, which bound what one address can send in total while
+/// leaving room for several clients behind the same NAT or proxy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// Sustained reads per second for each client
+    #[serde(default = "default_read_rps")]
+    pub read_rps: u32,
+
+    /// Reads a client may make at once before the sustained rate applies
+    #[serde(default = "default_read_burst")]
+    pub read_burst: u32,
+
+    /// Sustained mutating requests per second for each client
+    #[serde(default = "default_write_rps")]
+    pub write_rps: u32,
+
+    /// Mutating requests a client may make at once
+    #[serde(default = "default_write_burst")]
+    pub write_burst: u32,
+
+    /// Sustained reads per second from each source address
+    #[serde(default = "default_ip_read_rps")]
+    pub ip_read_rps: u32,
+
+    /// Reads a source address may make at once
+    #[serde(default = "default_ip_read_burst")]
+    pub ip_read_burst: u32,
+
+    /// Sustained mutating requests per second from each source address
+    #[serde(default = "default_ip_write_rps")]
+    pub ip_write_rps: u32,
+
+    /// Mutating requests a source address may make at once
+    #[serde(default = "default_ip_write_burst")]
+    pub ip_write_burst: u32,
+}
+
+fn default_read_rps() -> u32 {
+    100
+}
+
+fn default_read_burst() -> u32 {
+    200
+}
+
+fn default_write_rps() -> u32 {
+    20
+}
+
+fn default_write_burst() -> u32 {
+    40
+}
+
+fn default_ip_read_rps() -> u32 {
+    1000
+}
+
+fn default_ip_read_burst() -> u32 {
+    2000
+}
+
+fn default_ip_write_rps() -> u32 {
+    200
+}
+
+fn default_ip_write_burst() -> u32 {
+    400
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            read_rps: default_read_rps(),
+            read_burst: default_read_burst(),
+            write_rps: default_write_rps(),
+            write_burst: default_write_burst(),
+            ip_read_rps: default_ip_read_rps(),
+            ip_read_burst: default_ip_read_burst(),
+            ip_write_rps: default_ip_write_rps(),
+            ip_write_burst: default_ip_write_burst(),
+        }
+    }
+}
+
+/// Who a request is counted against
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    /// SHA-256 of a valid X-API-Key header; the key itself is not kept
+    ApiKey([u8; 32]),
+    /// Subject of a valid bearer token
+    User(String),
+    /// Source address of a request without valid credentials
+    Ip(IpAddr),
+}
+
+impl RateLimitKey {
+    /// Identify the client by its credentials, if they verify. Unverified
+    /// credentials get no bucket of their own, so made-up keys or tokens
+    /// cannot be used to spread requests over fresh buckets
+    fn from_credentials(request: &Request) -> Option<Self> {
+        let headers = request.headers();
+        let auth_service = request.extensions().get::<Arc<AuthService>>()?;
+        if let Some(api_key) = headers.get("X-API-Key") {
+            let valid = api_key
+                .to_str()
+                .is_ok_and(|key| auth_service.verify_api_key(key).is_ok());
+            return valid.then(|| Self::ApiKey(Sha256::digest(api_key.as_bytes()).into()));
+        }
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))?;
+        let claims = auth_service.verify_token(token.trim()).ok()?;
+        Some(Self::User(claims.sub))
+    }
+}
+
+type KeyedLimiter = RateLimiter<RateLimitKey, DefaultKeyedStateStore<RateLimitKey>, DefaultClock>;
+
+type AddressLimiter = RateLimiter<IpAddr, DefaultKeyedStateStore<IpAddr>, DefaultClock>;
+
 pub struct RateLimitState {
-    limiter: Arc<RateLimiter<NotKeyed, InMemoryState, DefaultClock>>,
+    read: Option<KeyedLimiter>,
+    write: Option<KeyedLimiter>,
+    ip_read: Option<AddressLimiter>,
+    ip_write: Option<AddressLimiter>,
 }
 
 impl RateLimitState {
-    pub fn new(requests_per_second: u32) -> Self {
-        let quota = Quota::per_second(NonZeroU32::new(requests_per_second).unwrap());
+    pub fn new(config: &RateLimitConfig) -> Self {
         Self {
-            limiter: Arc::new(RateLimiter::direct(quota)),
+            read: limiter(config.read_rps, config.read_burst),
+            write: limiter(config.write_rps, config.write_burst),
+            ip_read: limiter(config.ip_read_rps, config.ip_read_burst),
+            ip_write: limiter(config.ip_write_rps, config.ip_write_burst),
         }
     }
 
-    pub fn new_per_minute(requests_per_minute: u32) -> Self {
-        let quota = Quota::per_minute(NonZeroU32::new(requests_per_minute).unwrap());
-        Self {
-            limiter: Arc::new(RateLimiter::direct(quota)),
+    /// Count a request; on refusal, how long until the client may retry
+    pub fn check(&self, key: &RateLimitKey, method: &Method) -> Result<(), Duration> {
+        let limiter = if is_read(method) {
+            &self.read
+        } else {
+            &self.write
+        };
+        check_key(limiter, key)
+    }
+
+    /// Count a request against its source address
+    pub fn check_address(&self, address: IpAddr, method: &Method) -> Result<(), Duration> {
+        let limiter = if is_read(method) {
+            &self.ip_read
+        } else {
+            &self.ip_write
+        };
+        check_key(limiter, &address)
+    }
+
+    /// Drop buckets of clients that have refilled, so idle clients do not
+    /// accumulate
+    pub fn retain_recent(&self) {
+        for limiter in self.read.iter().chain(self.write.iter()) {
+            limiter.retain_recent();
+            limiter.shrink_to_fit();
+        }
+        for limiter in self.ip_read.iter().chain(self.ip_write.iter()) {
+            limiter.retain_recent();
+            limiter.shrink_to_fit();
         }
     }
 }
 
+fn check_key<K>(
+    limiter: &Option<RateLimiter<K, DefaultKeyedStateStore<K>, DefaultClock>>,
+    key: &K,
+) -> Result<(), Duration>
+where
+    K: Clone + Eq + std::hash::Hash,
+{
+    let Some(limiter) = limiter else {
+        return Ok(());
+    };
+    limiter
+        .check_key(key)
+        .map_err(|not_until| not_until.wait_time_from(limiter.clock().now()))
+}
+
+fn limiter<K: Clone + Eq + std::hash::Hash>(
+    rps: u32,
+    burst: u32,
+) -> Option<RateLimiter<K, DefaultKeyedStateStore<K>, DefaultClock>> {
+    let rps = NonZeroU32::new(rps)?;
+    let burst = NonZeroU32::new(burst).unwrap_or(rps);
+    Some(RateLimiter::keyed(
+        Quota::per_second(rps).allow_burst(burst),
+    ))
+}
+
+fn is_read(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
 pub async fn rate_limit_middleware(
     State(rate_limiter): State<Arc<RateLimitState>>,
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     request: Request,
     next: Next,
 ) -> Response {
-    match rate_limiter.limiter.check() {
-        Ok(_) => next.run(request).await,
-        Err(_) => {
+    let client_ip = resolve_client_ip(request.extensions(), request.headers());
+    // The source address is always charged under its own limits; then the
+    // client is charged by its credentials, or by address without them.
+    // Credentials are only verified once the address is within its limit
+    let checked = rate_limiter
+        .check_address(client_ip, request.method())
+        .and_then(|()| {
+            let key =
+                RateLimitKey::from_credentials(&request).unwrap_or(RateLimitKey::Ip(client_ip));
+            rate_limiter.check(&key, request.method())
+        });
+    match checked {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            // Whole seconds, rounded up so a client that honours it succeeds
+            let retry_after = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+            let retry_after = retry_after.max(1);
+
             warn!(
-                remote_addr = %addr,
+                remote_addr = %client_ip,
+                method = %request.method(),
+                path = %request.uri().path(),
+                retry_after,
                 "Rate limit exceeded"
             );
 
             let error = ErrorResponse::new(
                 "rate_limit_exceeded",
-                "Too many requests. Please try again later.",
+                format!("Too many requests. Retry in {} s.", retry_after),
             );
 
-            (StatusCode::TOO_MANY_REQUESTS, Json(error)).into_response()
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                Json(error),
+            )
+                .into_response()
         }
     }
 }
@@ -355,6 +779,19 @@ pub async fn readiness_check(State(state): State<Arc<ReadinessState>>) -> impl I
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_loggable_uri() {
+        let uri: Uri = "/api/v1/tracks/stream?access_token=secret&x=1"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            loggable_uri(&uri),
+            "/api/v1/tracks/stream?access_token=REDACTED&x=1"
+        );
+        let uri: Uri = "/api/v1/audit?limit=5".parse().unwrap();
+        assert_eq!(loggable_uri(&uri), "/api/v1/audit?limit=5");
+    }
+
     #[test]
     fn test_audit_logger() {
         let logger = AuditLogger::new();
@@ -369,12 +806,157 @@ mod tests {
             true,
         );
 
-        let logs = logger.get_logs();
+        let logs = logger.query(&AuditQuery::default()).unwrap().entries;
         assert_eq!(logs.len(), 1);
         assert_eq!(logs[0].user, "testuser");
         assert_eq!(logs[0].action, "create_connection");
     }
 
+    #[test]
+    fn test_audit_feed_includes_auth_failures() {
+        let logger = AuditLogger::new();
+        let mut feed = logger.subscribe();
+
+        logger.log_auth_failure(
+            Some("mallory"),
+            None,
+            "login_failed",
+            "/api/v1/auth/login",
+            "Invalid credentials",
+            Some("192.0.2.7".parse().unwrap()),
+        );
+
+        let entry = feed.try_recv().unwrap();
+        assert_eq!(entry.user, "mallory");
+        assert_eq!(entry.role, None);
+        assert_eq!(entry.source_ip, "192.0.2.7");
+        assert!(!entry.success);
+        assert_eq!(logger.query(&AuditQuery::default()).unwrap().total, 1);
+    }
+
+    #[test]
+    fn test_client_ip_behind_trusted_proxies() {
+        let proxies =
+            TrustedProxies::parse(&["10.0.0.0/8".to_string(), "::1".to_string()]).unwrap();
+        let proxy: IpAddr = "10.1.2.3".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "198.51.100.9, 203.0.113.5, 10.0.0.7".parse().unwrap(),
+        );
+
+        // The rightmost hop a trusted proxy did not add is the client
+        assert_eq!(
+            proxies.client_ip(proxy, &headers),
+            "203.0.113.5".parse::<IpAddr>().unwrap()
+        );
+
+        // Headers from untrusted peers are ignored
+        let stranger: IpAddr = "192.0.2.1".parse().unwrap();
+        assert_eq!(proxies.client_ip(stranger, &headers), stranger);
+
+        // Forwarded wins over X-Forwarded-For, with quoted IPv6 and ports
+        headers.insert(
+            header::FORWARDED,
+            "for=192.0.2.60;proto=https, for=\"[2001:db8::1]:4711\""
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(
+            proxies.client_ip("::1".parse().unwrap(), &headers),
+            "2001:db8::1".parse::<IpAddr>().unwrap()
+        );
+
+        // An obfuscated hop stops at the proxy that reported it
+        headers.insert(header::FORWARDED, "for=_hidden".parse().unwrap());
+        assert_eq!(proxies.client_ip(proxy, &headers), proxy);
+
+        assert!(TrustedProxies::parse(&["10.0.0.0/33".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_rate_limit_buckets() {
+        let state = RateLimitState::new(&RateLimitConfig {
+            read_rps: 1,
+            read_burst: 2,
+            write_rps: 1,
+            write_burst: 1,
+            ..Default::default()
+        });
+        let alice = RateLimitKey::User("alice".to_string());
+        let bob = RateLimitKey::Ip("192.0.2.7".parse().unwrap());
+
+        assert!(state.check(&alice, &Method::GET).is_ok());
+        assert!(state.check(&alice, &Method::HEAD).is_ok());
+        let wait = state.check(&alice, &Method::GET).unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_secs(1));
+
+        // Mutating requests and other clients have their own buckets
+        assert!(state.check(&alice, &Method::POST).is_ok());
+        assert!(state.check(&alice, &Method::DELETE).is_err());
+        assert!(state.check(&bob, &Method::GET).is_ok());
+        assert!(state.check(&bob, &Method::PUT).is_ok());
+    }
+
+    #[test]
+    fn test_rate_limit_address_quota() {
+        let state = RateLimitState::new(&RateLimitConfig {
+            read_rps: 1,
+            read_burst: 1,
+            ip_read_rps: 1,
+            ip_read_burst: 3,
+            ..Default::default()
+        });
+        let address: IpAddr = "192.0.2.7".parse().unwrap();
+
+        // Clients behind one address each get their own bucket, up to the
+        // address's higher quota
+        for user in ["alice", "bob", "carol"] {
+            assert!(state.check_address(address, &Method::GET).is_ok());
+            assert!(state
+                .check(&RateLimitKey::User(user.to_string()), &Method::GET)
+                .is_ok());
+        }
+        assert!(state.check_address(address, &Method::GET).is_err());
+        assert!(state
+            .check_address("192.0.2.8".parse().unwrap(), &Method::GET)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_rate_limit_disabled() {
+        let state = RateLimitState::new(&RateLimitConfig {
+            read_rps: 0,
+            ..Default::default()
+        });
+        let key = RateLimitKey::ApiKey([0; 32]);
+        for _ in 0..1000 {
+            assert!(state.check(&key, &Method::GET).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_rate_limit_key_needs_valid_credentials() {
+        let auth = Arc::new(AuthService::new(crate::auth::AuthConfig::default()));
+        let (api_key, _) = auth
+            .create_api_key("ops".to_string(), UserRole::Operator, None, None)
+            .unwrap();
+        let request = |key: &str| {
+            let mut request = Request::builder()
+                .header("X-API-Key", key)
+                .body(Body::empty())
+                .unwrap();
+            request.extensions_mut().insert(auth.clone());
+            request
+        };
+
+        assert!(matches!(
+            RateLimitKey::from_credentials(&request(&api_key)),
+            Some(RateLimitKey::ApiKey(_))
+        ));
+        assert_eq!(RateLimitKey::from_credentials(&request("omni_made_up")), None);
+    }
+
     #[test]
     fn test_readiness_state() {
         let state = ReadinessState::new();
@@ -0,0 +1,171 @@
+//! Paging, sorting and search shared by the list endpoints
+//!
+//! List endpoints take `offset` and `limit`, a `sort` key with an `order`
+//! and usually a `search` string. They answer with the page in the body and
+//! two headers: `X-Total-Count`, the number of matching items across all
+//! pages, and an RFC 8288 `Link` header pointing at the `first`, `prev`,
+//! `next` and `last` pages with the other query parameters kept.
+
+use axum::Json;
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Uri, header};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use utoipa::ToSchema;
+
+/// Header carrying the number of matching items across all pages
+pub const TOTAL_COUNT: HeaderName = HeaderName::from_static("x-total-count");
+
+/// Direction of a sorted list
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    /// Ordering of two items compared in ascending order
+    pub fn apply(self, ordering: Ordering) -> Ordering {
+        match self {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    }
+
+    /// SQL keyword of the direction
+    pub fn sql(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+/// Whether any of `fields` contains `search`, ignoring case. No search
+/// matches everything.
+pub fn matches_search(search: Option<&str>, fields: &[&str]) -> bool {
+    let Some(search) = search.map(str::trim).filter(|s| !s.is_empty()) else {
+        return true;
+    };
+    let search = search.to_lowercase();
+    fields
+        .iter()
+        .any(|field| field.to_lowercase().contains(&search))
+}
+
+/// One page of a list response with its `X-Total-Count` and `Link` headers
+#[derive(Debug)]
+pub struct Paginated<T> {
+    pub body: T,
+    headers: HeaderMap,
+}
+
+impl<T> Paginated<T> {
+    /// Page of `limit` items from `offset` out of `total` matching items,
+    /// served at `uri`
+    pub fn new(body: T, uri: &Uri, offset: usize, limit: usize, total: usize) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(TOTAL_COUNT, HeaderValue::from(total));
+        if let Ok(link) = HeaderValue::try_from(link_header(uri, offset, limit, total)) {
+            headers.insert(header::LINK, link);
+        }
+        Self { body, headers }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Paginated<T> {
+    fn into_response(self) -> Response {
+        (self.headers, Json(self.body)).into_response()
+    }
+}
+
+/// `Link` header value for the pages around the one at `offset`
+fn link_header(uri: &Uri, offset: usize, limit: usize, total: usize) -> String {
+    if limit == 0 {
+        return String::new();
+    }
+    let last = total.saturating_sub(1) / limit * limit;
+
+    let mut links = vec![(0, "first")];
+    if offset > 0 {
+        links.push((offset.saturating_sub(limit), "prev"));
+    }
+    if offset + limit < total {
+        links.push((offset + limit, "next"));
+    }
+    links.push((last, "last"));
+
+    links
+        .into_iter()
+        .map(|(page, rel)| format!("<{}>; rel=\"{}\"", page_uri(uri, page, limit), rel))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `uri` with its `offset` and `limit` parameters replaced
+fn page_uri(uri: &Uri, offset: usize, limit: usize) -> String {
+    let mut params: Vec<&str> = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| {
+            let key = param.split('=').next().unwrap_or_default();
+            !param.is_empty() && key != "offset" && key != "limit"
+        })
+        .collect();
+    let paging = format!("offset={}&limit={}", offset, limit);
+    params.push(&paging);
+    format!("{}?{}", uri.path(), params.join("&"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_header_keeps_other_parameters() {
+        let uri: Uri = "/api/v1/connections?status=connected&offset=10&limit=10&sort=name"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            link_header(&uri, 10, 10, 35),
+            "</api/v1/connections?status=connected&sort=name&offset=0&limit=10>; rel=\"first\", \
+             </api/v1/connections?status=connected&sort=name&offset=0&limit=10>; rel=\"prev\", \
+             </api/v1/connections?status=connected&sort=name&offset=20&limit=10>; rel=\"next\", \
+             </api/v1/connections?status=connected&sort=name&offset=30&limit=10>; rel=\"last\""
+        );
+
+        let uri: Uri = "/api/v1/audit".parse().unwrap();
+        assert_eq!(
+            link_header(&uri, 0, 100, 0),
+            "</api/v1/audit?offset=0&limit=100>; rel=\"first\", \
+             </api/v1/audit?offset=0&limit=100>; rel=\"last\""
+        );
+    }
+
+    #[test]
+    fn test_paginated_headers() {
+        let uri: Uri = "/api/v1/discovery/services?limit=2".parse().unwrap();
+        let page = Paginated::new(vec![1, 2], &uri, 0, 2, 5);
+        assert_eq!(page.headers[&TOTAL_COUNT], "5");
+        let link = page.headers[header::LINK].to_str().unwrap();
+        assert!(link.contains("</api/v1/discovery/services?offset=2&limit=2>; rel=\"next\""));
+        assert!(link.contains("</api/v1/discovery/services?offset=4&limit=2>; rel=\"last\""));
+    }
+
+    #[test]
+    fn test_search_and_order() {
+        assert!(matches_search(None, &["anything"]));
+        assert!(matches_search(Some("  "), &["anything"]));
+        assert!(matches_search(Some("TAK"), &["Primary", "tak.example.com"]));
+        assert!(!matches_search(
+            Some("mesh"),
+            &["Primary", "tak.example.com"]
+        ));
+
+        assert_eq!(SortOrder::Desc.apply(1.cmp(&2)), Ordering::Greater);
+        assert_eq!(SortOrder::default().apply(1.cmp(&2)), Ordering::Less);
+    }
+}
@@ -0,0 +1,311 @@
+//! Permissions and the roles that grant them
+//!
+//! Endpoints that change state, or expose something sensitive, require a
+//! permission; everything else needs only a valid token or API key. The
+//! built-in roles grant fixed sets: `admin` every permission, `operator`
+//! what running connections and the feed takes, and `readonly` none.
+//! Custom roles are named permission lists from the configuration file. A
+//! user or API key given a custom role has exactly its permissions; the
+//! built-in role is then only what audit logs and login responses report.
+
+use crate::types::UserRole;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use tracing::warn;
+use utoipa::ToSchema;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema,
+)]
+pub enum Permission {
+    /// Open and close connections, import them from data packages
+    #[serde(rename = "connections:write")]
+    ConnectionsWrite,
+
+    /// Create and delete filters
+    #[serde(rename = "filters:write")]
+    FiltersWrite,
+
+    /// Inject CoT messages
+    #[serde(rename = "cot:send")]
+    CotSend,
+
+    /// Inject CoT messages past the filters
+    #[serde(rename = "cot:unfiltered")]
+    CotUnfiltered,
+
+    /// Read the audit log
+    #[serde(rename = "audit:read")]
+    AuditRead,
+
+    /// Import and delete client certificates
+    #[serde(rename = "certificates:write")]
+    CertificatesWrite,
+
+    /// Upload files and data packages shared with TAK clients
+    #[serde(rename = "packages:write")]
+    PackagesWrite,
+
+    /// Acknowledge and clear emergency beacons
+    #[serde(rename = "emergencies:write")]
+    EmergenciesWrite,
+
+    /// Act on FreeTAKServer connections
+    #[serde(rename = "fts:write")]
+    FtsWrite,
+
+    /// Pair, approve and provision Android devices over ADB
+    #[serde(rename = "devices:write")]
+    DevicesWrite,
+
+    /// Rescan, adopt discovered services and change the announcement
+    #[serde(rename = "discovery:write")]
+    DiscoveryWrite,
+
+    /// Change plugin settings and enable or disable plugins
+    #[serde(rename = "plugins:configure")]
+    PluginsConfigure,
+
+    /// Load, unload and reload plugins
+    #[serde(rename = "plugins:manage")]
+    PluginsManage,
+
+    /// Manage alert channels
+    #[serde(rename = "alerts:manage")]
+    AlertsManage,
+
+    /// Read and change runtime log levels
+    #[serde(rename = "logging:manage")]
+    LoggingManage,
+
+    /// Manage enrollment tokens and onboarding packages
+    #[serde(rename = "enrollment:manage")]
+    EnrollmentManage,
+
+    /// Create API keys and revoke tokens
+    #[serde(rename = "users:manage")]
+    UsersManage,
+
+    /// Export the configuration, back up and restore
+    #[serde(rename = "system:manage")]
+    SystemManage,
+
+    /// Watch live traffic and the routing decisions made for it
+    #[serde(rename = "traffic:tap")]
+    TrafficTap,
+
+    /// Manage the rules authorizing client certificates on TLS listeners
+    #[serde(rename = "client_access:manage")]
+    ClientAccessManage,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 20] = [
+        Permission::ConnectionsWrite,
+        Permission::FiltersWrite,
+        Permission::CotSend,
+        Permission::CotUnfiltered,
+        Permission::AuditRead,
+        Permission::CertificatesWrite,
+        Permission::PackagesWrite,
+        Permission::EmergenciesWrite,
+        Permission::FtsWrite,
+        Permission::DevicesWrite,
+        Permission::DiscoveryWrite,
+        Permission::PluginsConfigure,
+        Permission::PluginsManage,
+        Permission::AlertsManage,
+        Permission::LoggingManage,
+        Permission::EnrollmentManage,
+        Permission::UsersManage,
+        Permission::SystemManage,
+        Permission::TrafficTap,
+        Permission::ClientAccessManage,
+    ];
+
+    /// Permissions of the operator role
+    pub const OPERATOR: [Permission; 11] = [
+        Permission::ConnectionsWrite,
+        Permission::FiltersWrite,
+        Permission::CotSend,
+        Permission::CotUnfiltered,
+        Permission::CertificatesWrite,
+        Permission::PackagesWrite,
+        Permission::EmergenciesWrite,
+        Permission::FtsWrite,
+        Permission::DevicesWrite,
+        Permission::DiscoveryWrite,
+        Permission::PluginsConfigure,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Permission::ConnectionsWrite => "connections:write",
+            Permission::FiltersWrite => "filters:write",
+            Permission::CotSend => "cot:send",
+            Permission::CotUnfiltered => "cot:unfiltered",
+            Permission::AuditRead => "audit:read",
+            Permission::CertificatesWrite => "certificates:write",
+            Permission::PackagesWrite => "packages:write",
+            Permission::EmergenciesWrite => "emergencies:write",
+            Permission::FtsWrite => "fts:write",
+            Permission::DevicesWrite => "devices:write",
+            Permission::DiscoveryWrite => "discovery:write",
+            Permission::PluginsConfigure => "plugins:configure",
+            Permission::PluginsManage => "plugins:manage",
+            Permission::AlertsManage => "alerts:manage",
+            Permission::LoggingManage => "logging:manage",
+            Permission::EnrollmentManage => "enrollment:manage",
+            Permission::UsersManage => "users:manage",
+            Permission::SystemManage => "system:manage",
+            Permission::TrafficTap => "traffic:tap",
+            Permission::ClientAccessManage => "client_access:manage",
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Custom role from the configuration file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleDefinition {
+    /// Name users and API keys refer to it by
+    pub name: String,
+
+    /// Everything the role may do
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+}
+
+/// Check custom role definitions: names must be unique and must not be
+/// those of the built-in roles
+pub fn validate_roles(roles: &[RoleDefinition]) -> Result<(), String> {
+    let mut names = BTreeSet::new();
+    for role in roles {
+        if role.name.trim().is_empty() {
+            return Err("Custom role with an empty name".to_string());
+        }
+        if builtin_role(&role.name).is_some() {
+            return Err(format!(
+                "Custom role '{}' has the name of a built-in role",
+                role.name
+            ));
+        }
+        if !names.insert(role.name.as_str()) {
+            return Err(format!("Custom role '{}' is defined twice", role.name));
+        }
+    }
+    Ok(())
+}
+
+fn builtin_role(name: &str) -> Option<UserRole> {
+    match name {
+        "admin" => Some(UserRole::Admin),
+        "operator" => Some(UserRole::Operator),
+        "readonly" => Some(UserRole::ReadOnly),
+        _ => None,
+    }
+}
+
+/// Resolves roles to the permissions they grant
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    custom: HashMap<String, BTreeSet<Permission>>,
+}
+
+impl Policy {
+    /// Policy with the given custom roles. Definitions that
+    /// [`validate_roles`] rejects are skipped.
+    pub fn new(roles: &[RoleDefinition]) -> Self {
+        let mut custom = HashMap::new();
+        for role in roles {
+            if builtin_role(&role.name).is_some() || custom.contains_key(&role.name) {
+                warn!(role = %role.name, "Ignoring role definition that shadows another role");
+                continue;
+            }
+            custom.insert(
+                role.name.clone(),
+                role.permissions.iter().copied().collect(),
+            );
+        }
+        Self { custom }
+    }
+
+    /// Whether a custom role of this name is defined
+    pub fn has_custom_role(&self, name: &str) -> bool {
+        self.custom.contains_key(name)
+    }
+
+    /// Permissions of a built-in role, or of the custom role replacing it.
+    /// A custom role that is no longer defined grants nothing.
+    pub fn permissions(&self, role: UserRole, custom_role: Option<&str>) -> BTreeSet<Permission> {
+        match custom_role {
+            Some(name) => self.custom.get(name).cloned().unwrap_or_default(),
+            None => match role {
+                UserRole::Admin => Permission::ALL.into_iter().collect(),
+                UserRole::Operator => Permission::OPERATOR.into_iter().collect(),
+                UserRole::ReadOnly => BTreeSet::new(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_roles() {
+        let policy = Policy::default();
+
+        let admin = policy.permissions(UserRole::Admin, None);
+        assert_eq!(admin.len(), Permission::ALL.len());
+
+        let operator = policy.permissions(UserRole::Operator, None);
+        assert!(operator.contains(&Permission::ConnectionsWrite));
+        assert!(operator.contains(&Permission::CotSend));
+        // Operators could always send past the filters
+        assert!(operator.contains(&Permission::CotUnfiltered));
+        assert!(!operator.contains(&Permission::AuditRead));
+
+        assert!(policy.permissions(UserRole::ReadOnly, None).is_empty());
+    }
+
+    #[test]
+    fn test_custom_roles() {
+        let roles: Vec<RoleDefinition> = serde_yaml::from_str(
+            "- name: dispatcher\n  permissions: [\"cot:send\", \"emergencies:write\"]\n\
+             - name: auditor\n  permissions: [\"audit:read\"]\n",
+        )
+        .unwrap();
+        assert!(validate_roles(&roles).is_ok());
+
+        let policy = Policy::new(&roles);
+        assert!(policy.has_custom_role("dispatcher"));
+
+        // A custom role replaces the built-in role's permissions
+        let dispatcher = policy.permissions(UserRole::Operator, Some("dispatcher"));
+        assert_eq!(
+            dispatcher.into_iter().collect::<Vec<_>>(),
+            vec![Permission::CotSend, Permission::EmergenciesWrite]
+        );
+        assert!(policy.permissions(UserRole::Admin, Some("gone")).is_empty());
+
+        let shadowing = vec![RoleDefinition {
+            name: "admin".to_string(),
+            permissions: vec![],
+        }];
+        assert!(validate_roles(&shadowing).is_err());
+        assert!(
+            serde_yaml::from_str::<Vec<RoleDefinition>>(
+                "- name: x\n  permissions: [\"connections:delete\"]\n"
+            )
+            .is_err()
+        );
+    }
+}
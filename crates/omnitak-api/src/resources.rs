@@ -0,0 +1,127 @@
+//! Process resource usage
+//!
+//! CPU and memory come from `sysinfo` so they work on every platform the
+//! server runs on. Open file descriptors and sockets are counted from
+//! `/proc/self/fd` on Linux and `/dev/fd` on macOS; elsewhere they are
+//! reported as unknown. Task counts come from the tokio runtime that serves
+//! the request.
+
+use crate::types::ResourceUsage;
+use chrono::Utc;
+use parking_lot::Mutex;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+/// Samples resource usage of the current process
+pub struct ResourceMonitor {
+    pid: Option<Pid>,
+    system: Mutex<System>,
+}
+
+impl ResourceMonitor {
+    pub fn new() -> Self {
+        let monitor = Self {
+            pid: sysinfo::get_current_pid().ok(),
+            system: Mutex::new(System::new()),
+        };
+        // CPU usage is measured between refreshes, so prime the first one
+        monitor.refresh();
+        monitor
+    }
+
+    fn refresh(&self) -> (f32, u64, u64) {
+        let Some(pid) = self.pid else {
+            return (0.0, 0, 0);
+        };
+        let mut system = self.system.lock();
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            true,
+            ProcessRefreshKind::nothing().with_cpu().with_memory(),
+        );
+        system
+            .process(pid)
+            .map(|p| (p.cpu_usage(), p.memory(), p.virtual_memory()))
+            .unwrap_or_default()
+    }
+
+    /// Current usage. CPU is averaged since the previous sample and may
+    /// exceed 100% when several cores are busy.
+    pub fn sample(&self) -> ResourceUsage {
+        let (cpu_percent, memory_rss_bytes, memory_virtual_bytes) = self.refresh();
+        let (open_fds, open_sockets) = match count_fds() {
+            Some((fds, sockets)) => (Some(fds), sockets),
+            None => (None, None),
+        };
+        let runtime = tokio::runtime::Handle::try_current()
+            .ok()
+            .map(|h| h.metrics());
+
+        ResourceUsage {
+            cpu_percent,
+            cpu_count: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            memory_rss_bytes,
+            memory_virtual_bytes,
+            open_fds,
+            open_sockets,
+            tokio_workers: runtime.as_ref().map_or(0, |m| m.num_workers()),
+            tokio_tasks: runtime.as_ref().map_or(0, |m| m.num_alive_tasks()),
+            tokio_queue_depth: runtime.as_ref().map_or(0, |m| m.global_queue_depth()),
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+impl Default for ResourceMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Open descriptors and, where the platform says, how many are sockets
+#[cfg(target_os = "linux")]
+fn count_fds() -> Option<(u64, Option<u64>)> {
+    let mut fds = 0;
+    let mut sockets = 0;
+    for entry in std::fs::read_dir("/proc/self/fd").ok()?.flatten() {
+        fds += 1;
+        let is_socket = std::fs::read_link(entry.path())
+            .is_ok_and(|target| target.to_string_lossy().starts_with("socket:"));
+        if is_socket {
+            sockets += 1;
+        }
+    }
+    Some((fds, Some(sockets)))
+}
+
+#[cfg(target_os = "macos")]
+fn count_fds() -> Option<(u64, Option<u64>)> {
+    let fds = std::fs::read_dir("/dev/fd").ok()?.count() as u64;
+    Some((fds, None))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn count_fds() -> Option<(u64, Option<u64>)> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_sample() {
+        let monitor = ResourceMonitor::new();
+        let _listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let usage = monitor.sample();
+
+        assert!(usage.memory_rss_bytes > 0);
+        assert!(usage.cpu_count >= 1);
+        assert_eq!(usage.tokio_workers, 2);
+        assert!(usage.tokio_tasks <= 1);
+        #[cfg(target_os = "linux")]
+        {
+            assert!(usage.open_fds.unwrap() >= 3);
+            assert!(usage.open_sockets.unwrap() >= 1);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Alert channel management endpoints
+//!
+//! Channels changed here take effect immediately but are not written back
+//! to the configuration file.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use tracing::info;
+use validator::Validate;
+
+use crate::alerts::{Alert, AlertChannel, AlertError};
+use crate::auth::RequireAlertsManage;
+use crate::middleware::ClientIp;
+use crate::rest::{ApiError, ApiState};
+use crate::types::*;
+
+impl From<AlertError> for ApiError {
+    fn from(e: AlertError) -> Self {
+        match e {
+            AlertError::NotFound(_) => ApiError::NotFound(e.to_string()),
+            AlertError::Exists(_) | AlertError::Invalid(_) => ApiError::BadRequest(e.to_string()),
+            AlertError::Delivery(_) => ApiError::InternalError(e.to_string()),
+        }
+    }
+}
+
+/// GET /api/v1/alerts/channels - List alert channels (requires alerts:manage)
+#[utoipa::path(
+    get,
+    path = "/api/v1/alerts/channels",
+    responses(
+        (status = 200, description = "Alert channels with secrets redacted", body = AlertChannelList),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires alerts:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["alerts:manage"]),
+        ("api_key" = ["alerts:manage"])
+    )
+)]
+pub async fn list_channels(
+    State(state): State<ApiState>,
+    RequireAlertsManage(_user): RequireAlertsManage,
+) -> Result<Json<AlertChannelList>, ApiError> {
+    let channels = state.alerts.list().await;
+    Ok(Json(AlertChannelList {
+        total: channels.len(),
+        channels,
+    }))
+}
+
+/// POST /api/v1/alerts/channels - Add an alert channel (requires alerts:manage)
+#[utoipa::path(
+    post,
+    path = "/api/v1/alerts/channels",
+    request_body = AlertChannel,
+    responses(
+        (status = 201, description = "Channel added"),
+        (status = 400, description = "Invalid channel or name already in use", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires alerts:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["alerts:manage"]),
+        ("api_key" = ["alerts:manage"])
+    )
+)]
+pub async fn create_channel(
+    State(state): State<ApiState>,
+    RequireAlertsManage(user): RequireAlertsManage,
+    ClientIp(client_ip): ClientIp,
+    Json(channel): Json<AlertChannel>,
+) -> Result<StatusCode, ApiError> {
+    let name = channel.name.clone();
+    state.alerts.add(channel).await?;
+
+    info!(channel = %name, "Added alert channel");
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "create_alert_channel".to_string(),
+        "/api/v1/alerts/channels".to_string(),
+        serde_json::json!({"name": name}),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(StatusCode::CREATED)
+}
+
+/// PUT /api/v1/alerts/channels/:name - Replace an alert channel (requires alerts:manage)
+///
+/// Secrets left as the redacted placeholder keep their current value.
+#[utoipa::path(
+    put,
+    path = "/api/v1/alerts/channels/{name}",
+    params(
+        ("name" = String, Path, description = "Channel name")
+    ),
+    request_body = AlertChannel,
+    responses(
+        (status = 204, description = "Channel updated"),
+        (status = 400, description = "Invalid channel", body = ErrorResponse),
+        (status = 404, description = "Channel not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires alerts:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["alerts:manage"]),
+        ("api_key" = ["alerts:manage"])
+    )
+)]
+pub async fn update_channel(
+    State(state): State<ApiState>,
+    Path(name): Path<String>,
+    RequireAlertsManage(user): RequireAlertsManage,
+    ClientIp(client_ip): ClientIp,
+    Json(channel): Json<AlertChannel>,
+) -> Result<StatusCode, ApiError> {
+    let min_severity = channel.min_severity;
+    state.alerts.update(&name, channel).await?;
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "update_alert_channel".to_string(),
+        format!("/api/v1/alerts/channels/{}", name),
+        serde_json::json!({"name": name, "min_severity": min_severity}),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /api/v1/alerts/channels/:name - Remove an alert channel (requires alerts:manage)
+#[utoipa::path(
+    delete,
+    path = "/api/v1/alerts/channels/{name}",
+    params(
+        ("name" = String, Path, description = "Channel name")
+    ),
+    responses(
+        (status = 204, description = "Channel removed"),
+        (status = 404, description = "Channel not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires alerts:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["alerts:manage"]),
+        ("api_key" = ["alerts:manage"])
+    )
+)]
+pub async fn delete_channel(
+    State(state): State<ApiState>,
+    Path(name): Path<String>,
+    RequireAlertsManage(user): RequireAlertsManage,
+    ClientIp(client_ip): ClientIp,
+) -> Result<StatusCode, ApiError> {
+    state.alerts.remove(&name).await?;
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "delete_alert_channel".to_string(),
+        format!("/api/v1/alerts/channels/{}", name),
+        serde_json::json!({"name": name}),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/v1/alerts/test - Send a test alert (requires alerts:manage)
+#[utoipa::path(
+    post,
+    path = "/api/v1/alerts/test",
+    request_body = TestAlertRequest,
+    responses(
+        (status = 200, description = "Per-channel delivery results", body = TestAlertResponse),
+        (status = 404, description = "Channel not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires alerts:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["alerts:manage"]),
+        ("api_key" = ["alerts:manage"])
+    )
+)]
+pub async fn send_test_alert(
+    State(state): State<ApiState>,
+    RequireAlertsManage(_user): RequireAlertsManage,
+    Json(request): Json<TestAlertRequest>,
+) -> Result<Json<TestAlertResponse>, ApiError> {
+    request.validate()?;
+
+    let alert = Alert::new(request.severity, request.title, request.message);
+    let deliveries = match &request.channel {
+        Some(name) => vec![state.alerts.send_to(name, &alert).await?],
+        None => state.alerts.dispatch(&alert).await,
+    };
+
+    Ok(Json(TestAlertResponse { deliveries }))
+}
@@ -0,0 +1,302 @@
+//! Backup and restore endpoints
+//!
+//! See [`crate::backup`] for what an archive holds. Restoring adds to what
+//! the instance already has: users, API keys and plugin settings in the
+//! backup replace those of the same name, and connections are opened unless
+//! one of the same name exists.
+
+use axum::{
+    Json,
+    body::Bytes,
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use tracing::{info, warn};
+
+use crate::auth::{AuthUser, RequireSystemManage};
+use crate::backup::{ApiKeyRecord, BackupContents, UserRecord};
+use crate::middleware::ClientIp;
+use crate::rest::{ApiError, ApiState, open_connection};
+use crate::types::*;
+
+/// Backups hold every tenant's users and keys
+fn require_unscoped(user: &AuthUser) -> Result<(), ApiError> {
+    if user.tenant.is_some() {
+        return Err(ApiError::Forbidden(
+            "Backup and restore require a user outside any tenant".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// GET /api/v1/backup - Download a backup archive (requires system:manage)
+#[utoipa::path(
+    get,
+    path = "/api/v1/backup",
+    responses(
+        (status = 200, description = "Backup archive", content_type = "application/zip", body = Vec<u8>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires system:manage outside any tenant", body = ErrorResponse),
+        (status = 500, description = "Configuration file unreadable", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["system:manage"]),
+        ("api_key" = ["system:manage"])
+    )
+)]
+pub async fn create_backup(
+    State(state): State<ApiState>,
+    RequireSystemManage(user): RequireSystemManage,
+    ClientIp(client_ip): ClientIp,
+) -> Result<Response, ApiError> {
+    require_unscoped(&user)?;
+
+    let config = match &state.config_file {
+        Some(path) => Some(tokio::fs::read_to_string(path).await.map_err(|e| {
+            ApiError::InternalError(format!("Failed to read {}: {}", path.display(), e))
+        })?),
+        None => None,
+    };
+
+    let connections = {
+        let connections = state.connections.read().await;
+        let requests = state.connection_requests.read().await;
+        connections
+            .iter()
+            .filter_map(|c| requests.get(&c.id).cloned())
+            .collect()
+    };
+
+    let plugins = {
+        let manager = state.plugin_manager.read().await;
+        manager
+            .list_plugins()
+            .into_iter()
+            .filter_map(|plugin| {
+                let settings = manager.plugin_settings(&plugin.id).ok()?;
+                (!settings.values.is_empty()).then_some((plugin.id, settings.values))
+            })
+            .collect()
+    };
+
+    let contents = BackupContents {
+        config,
+        users: state
+            .auth_service
+            .users
+            .iter()
+            .map(|entry| UserRecord::from(entry.value()))
+            .collect(),
+        api_keys: state
+            .auth_service
+            .api_key_records()
+            .iter()
+            .map(ApiKeyRecord::from)
+            .collect(),
+        connections,
+        certificates: state.certificates.list(),
+        plugins,
+    };
+    let archive = contents
+        .to_archive()
+        .map_err(|e| ApiError::InternalError(format!("Failed to write backup: {}", e)))?;
+
+    info!(
+        users = contents.users.len(),
+        api_keys = contents.api_keys.len(),
+        connections = contents.connections.len(),
+        bytes = archive.len(),
+        "Created backup"
+    );
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "create_backup".to_string(),
+        "/api/v1/backup".to_string(),
+        serde_json::json!({
+            "users": contents.users.len(),
+            "api_keys": contents.api_keys.len(),
+            "connections": contents.connections.len(),
+            "certificates": contents.certificates.len(),
+            "plugins": contents.plugins.len(),
+        }),
+        client_ip.to_string(),
+        true,
+    );
+
+    let filename = format!("omnitak-backup-{}.zip", Utc::now().format("%Y%m%dT%H%M%SZ"));
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        archive,
+    )
+        .into_response())
+}
+
+/// POST /api/v1/backup/restore - Restore a backup archive (requires system:manage)
+#[utoipa::path(
+    post,
+    path = "/api/v1/backup/restore",
+    request_body(content = Vec<u8>, content_type = "application/zip", description = "Archive from GET /api/v1/backup"),
+    responses(
+        (status = 200, description = "Backup restored; anything left out is listed in warnings", body = RestoreReport),
+        (status = 400, description = "Not a backup, unsupported version or failed integrity check", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires system:manage outside any tenant", body = ErrorResponse),
+        (status = 500, description = "Configuration file could not be written", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["system:manage"]),
+        ("api_key" = ["system:manage"])
+    )
+)]
+pub async fn restore_backup(
+    State(state): State<ApiState>,
+    RequireSystemManage(user): RequireSystemManage,
+    ClientIp(client_ip): ClientIp,
+    body: Bytes,
+) -> Result<Json<RestoreReport>, ApiError> {
+    require_unscoped(&user)?;
+
+    let (manifest, contents) =
+        BackupContents::from_archive(&body).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let mut warnings = Vec::new();
+
+    info!(
+        version = manifest.version,
+        created_at = %manifest.created_at,
+        server_version = %manifest.server_version,
+        "Restoring backup"
+    );
+
+    // Configuration file, kept as <file>.bak
+    let config_restored = match (&contents.config, &state.config_file) {
+        (Some(config), Some(path)) => {
+            serde_yaml::from_str::<serde_yaml::Value>(config).map_err(|e| {
+                ApiError::BadRequest(format!("Configuration in backup is not valid YAML: {}", e))
+            })?;
+            let mut backup_path = path.clone().into_os_string();
+            backup_path.push(".bak");
+            if tokio::fs::try_exists(path).await.unwrap_or(false) {
+                tokio::fs::copy(path, &backup_path).await.map_err(|e| {
+                    ApiError::InternalError(format!("Failed to keep {}: {}", path.display(), e))
+                })?;
+            }
+            tokio::fs::write(path, config).await.map_err(|e| {
+                ApiError::InternalError(format!("Failed to write {}: {}", path.display(), e))
+            })?;
+            true
+        }
+        (Some(_), None) => {
+            warnings.push(
+                "The server was not started from a configuration file; config.yaml was not restored"
+                    .to_string(),
+            );
+            false
+        }
+        (None, _) => false,
+    };
+
+    let mut users_restored = 0;
+    for record in contents.users {
+        let username = record.username.clone();
+        match state.auth_service.restore_user(record.into()) {
+            Ok(()) => users_restored += 1,
+            Err(e) => warnings.push(format!("User '{}' was not restored: {}", username, e)),
+        }
+    }
+
+    let mut api_keys_restored = 0;
+    for record in contents.api_keys {
+        let name = record.name.clone();
+        match state.auth_service.restore_api_key(record.into()) {
+            Ok(()) => api_keys_restored += 1,
+            Err(e) => warnings.push(format!("API key '{}' was not restored: {}", name, e)),
+        }
+    }
+
+    // Certificates are not in backups; connections using them fail below
+    for certificate in &contents.certificates {
+        if state.certificates.info(&certificate.id).is_none() {
+            warnings.push(format!(
+                "Certificate '{}' ({}) is not in the certificate store and must be imported again",
+                certificate.name, certificate.id
+            ));
+        }
+    }
+
+    let mut connections_restored = 0;
+    for request in &contents.connections {
+        let exists = state
+            .connections
+            .read()
+            .await
+            .iter()
+            .any(|c| c.name == request.name);
+        if exists {
+            warnings.push(format!(
+                "Connection '{}' already exists and was not restored",
+                request.name
+            ));
+            continue;
+        }
+        match open_connection(&state, &user, request).await {
+            Ok(_) => connections_restored += 1,
+            Err(e) => warnings.push(format!(
+                "Connection '{}' was not restored: {}",
+                request.name, e
+            )),
+        }
+    }
+
+    let mut plugins_restored = 0;
+    {
+        let manager = state.plugin_manager.read().await;
+        for (id, values) in &contents.plugins {
+            match manager.update_plugin_settings(id, values) {
+                Ok(()) => plugins_restored += 1,
+                Err(e) => warnings.push(format!(
+                    "Settings of plugin '{}' were not restored: {}",
+                    id, e
+                )),
+            }
+        }
+    }
+
+    for warning in &warnings {
+        warn!("Restore: {}", warning);
+    }
+
+    let report = RestoreReport {
+        version: manifest.version,
+        created_at: manifest.created_at,
+        server_version: manifest.server_version,
+        config_restored,
+        users_restored,
+        api_keys_restored,
+        connections_restored,
+        plugins_restored,
+        warnings,
+    };
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "restore_backup".to_string(),
+        "/api/v1/backup/restore".to_string(),
+        serde_json::to_value(&report).unwrap_or_default(),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(Json(report))
+}
@@ -0,0 +1,240 @@
+//! Bulk connection provisioning
+//!
+//! Creates a batch of connections as one change: every item is checked
+//! before any is opened, and if one fails to open, those opened before it
+//! are closed again. The batch is a JSON list of connection requests, or a
+//! configuration file whose `servers` are turned into requests the way
+//! [`crate::rest::config`] exports them.
+
+use axum::{
+    Json,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+};
+use std::collections::HashSet;
+use tracing::{info, warn};
+use validator::Validate;
+
+use crate::auth::RequireConnectionsWrite;
+use crate::middleware::ClientIp;
+use crate::rest::config::connection_requests;
+use crate::rest::{ApiError, ApiState, close_connection, open_connection};
+use crate::types::*;
+
+/// Most connections one request may create
+const MAX_BATCH: usize = 500;
+
+/// Content types read as a configuration file rather than JSON
+const YAML_CONTENT_TYPES: [&str; 3] = ["application/yaml", "application/x-yaml", "text/yaml"];
+
+/// POST /api/v1/connections/bulk - Create connections all or nothing (requires connections:write)
+#[utoipa::path(
+    post,
+    path = "/api/v1/connections/bulk",
+    request_body(
+        description = "Connections to create, or a configuration file whose servers are created",
+        content(
+            (BulkConnectionRequest = "application/json"),
+            (String = "application/yaml")
+        )
+    ),
+    responses(
+        (status = 201, description = "Every connection was created", body = BulkConnectionResponse),
+        (status = 400, description = "Nothing was created; the results say which connections failed and why", body = BulkConnectionResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires connections:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["connections:write"]),
+        ("api_key" = ["connections:write"])
+    )
+)]
+pub async fn create_connections(
+    State(state): State<ApiState>,
+    RequireConnectionsWrite(user): RequireConnectionsWrite,
+    ClientIp(client_ip): ClientIp,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<BulkConnectionResponse>), ApiError> {
+    let items = parse_items(&headers, &body)?;
+    if items.is_empty() {
+        return Err(ApiError::BadRequest("No connections to create".to_string()));
+    }
+    if items.len() > MAX_BATCH {
+        return Err(ApiError::BadRequest(format!(
+            "At most {} connections can be created at once, got {}",
+            MAX_BATCH,
+            items.len()
+        )));
+    }
+
+    let existing: HashSet<String> = state
+        .connections
+        .read()
+        .await
+        .iter()
+        .map(|c| c.name.clone())
+        .collect();
+    let mut seen = HashSet::new();
+    let problems: Vec<Option<String>> = items
+        .iter()
+        .map(|(name, request)| {
+            let problem = check(&state, &existing, request.as_ref()).err();
+            if seen.insert(name.as_str()) {
+                problem
+            } else {
+                problem.or_else(|| Some(format!("'{}' appears more than once", name)))
+            }
+        })
+        .collect();
+
+    let mut results: Vec<BulkConnectionResult> = items
+        .iter()
+        .enumerate()
+        .map(|(index, (name, _))| BulkConnectionResult {
+            index,
+            name: name.clone(),
+            status: BulkConnectionStatus::Skipped,
+            id: None,
+            error: None,
+        })
+        .collect();
+
+    let committed = if problems.iter().any(Option::is_some) {
+        for (result, problem) in results.iter_mut().zip(problems) {
+            if let Some(problem) = problem {
+                result.status = BulkConnectionStatus::Failed;
+                result.error = Some(problem);
+            }
+        }
+        false
+    } else {
+        let requests = items
+            .iter()
+            .filter_map(|(_, request)| request.as_ref().ok());
+        let mut failed = false;
+        for (request, result) in requests.zip(results.iter_mut()) {
+            match open_connection(&state, &user, request).await {
+                Ok(id) => {
+                    result.status = BulkConnectionStatus::Created;
+                    result.id = Some(id);
+                }
+                Err(e) => {
+                    result.status = BulkConnectionStatus::Failed;
+                    result.error = Some(e.to_string());
+                    failed = true;
+                    break;
+                }
+            }
+        }
+
+        if failed {
+            for result in &mut results {
+                let (BulkConnectionStatus::Created, Some(id)) = (result.status, result.id) else {
+                    continue;
+                };
+                if let Err(e) = close_connection(&state, id).await {
+                    warn!(connection_id = %id, error = %e, "Failed to roll back bulk-created connection");
+                }
+                result.status = BulkConnectionStatus::RolledBack;
+            }
+        }
+        !failed
+    };
+
+    let failed = results
+        .iter()
+        .filter(|r| r.status == BulkConnectionStatus::Failed)
+        .count();
+    if committed {
+        info!(created = results.len(), "Created connections in bulk");
+    } else {
+        warn!(
+            requested = results.len(),
+            failed, "Bulk connection creation rolled back"
+        );
+    }
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "bulk_create_connections".to_string(),
+        "/api/v1/connections/bulk".to_string(),
+        serde_json::json!({
+            "requested": results.len(),
+            "failed": failed,
+            "ids": results.iter().filter_map(|r| r.id).collect::<Vec<_>>(),
+        }),
+        client_ip.to_string(),
+        committed,
+    );
+
+    let status = if committed {
+        StatusCode::CREATED
+    } else {
+        StatusCode::BAD_REQUEST
+    };
+    Ok((status, Json(BulkConnectionResponse { committed, results })))
+}
+
+/// Name and request, or why there is none, for each item of the body
+fn parse_items(
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<Vec<(String, Result<CreateConnectionRequest, String>)>, ApiError> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    if YAML_CONTENT_TYPES.contains(&content_type.as_str()) {
+        let yaml = std::str::from_utf8(body).map_err(|_| {
+            ApiError::BadRequest("Configuration file is not valid UTF-8".to_string())
+        })?;
+        return connection_requests(yaml).map_err(ApiError::BadRequest);
+    }
+
+    let request: BulkConnectionRequest = serde_json::from_slice(body)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid bulk connection request: {}", e)))?;
+    Ok(request
+        .connections
+        .into_iter()
+        .map(|request| (request.name.clone(), Ok(request)))
+        .collect())
+}
+
+/// Whether a request can be opened, short of actually connecting
+fn check(
+    state: &ApiState,
+    existing: &HashSet<String>,
+    request: Result<&CreateConnectionRequest, &String>,
+) -> Result<(), String> {
+    let request = request.map_err(|reason| reason.clone())?;
+    request
+        .validate()
+        .map_err(|e| format!("Validation failed: {}", e))?;
+    if existing.contains(&request.name) {
+        return Err(format!("Connection '{}' already exists", request.name));
+    }
+    match request.connection_type {
+        ConnectionType::TcpClient => {}
+        ConnectionType::TlsClient => match request.certificate_id {
+            Some(id) if state.certificates.info(&id).is_none() => {
+                return Err(format!("Certificate {} not found", id));
+            }
+            Some(_) => {}
+            None if request.tls_cert_path.is_none() || request.tls_key_path.is_none() => {
+                return Err(
+                    "TLS connections need a certificate_id or tls_cert_path and tls_key_path"
+                        .to_string(),
+                );
+            }
+            None => {}
+        },
+        other => return Err(format!("Connection type {:?} not yet supported", other)),
+    }
+    Ok(())
+}
@@ -0,0 +1,394 @@
+//! Certificate store endpoints
+//!
+//! Keeps imported client certificates in memory as parsed bundles so TLS
+//! connections can reference them by ID instead of key files on disk.
+
+use axum::{
+    Json,
+    extract::{Multipart, Path, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use omnitak_cert::{CertificateBundle, CertificateChainInfo, CertificateData};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::auth::{AuthUser, RequireCertificatesWrite};
+use crate::middleware::ClientIp;
+use crate::rest::{ApiError, ApiState};
+use crate::types::*;
+use crate::upload::Upload;
+
+/// Largest certificate upload accepted, ZIP archives included
+const MAX_UPLOAD_BYTES: u64 = 10 * 1024 * 1024;
+
+// ============================================================================
+// Certificate Store
+// ============================================================================
+
+/// A certificate bundle held in memory
+#[derive(Clone)]
+struct StoredCertificate {
+    name: String,
+    source: String,
+    bundle: CertificateBundle,
+    created_at: DateTime<Utc>,
+}
+
+/// In-memory store of client certificate bundles keyed by ID
+#[derive(Default)]
+pub struct CertificateStore {
+    certificates: DashMap<Uuid, StoredCertificate>,
+}
+
+impl CertificateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store a bundle and return its ID
+    pub fn insert(
+        &self,
+        name: impl Into<String>,
+        source: impl Into<String>,
+        bundle: CertificateBundle,
+    ) -> Uuid {
+        let id = Uuid::new_v4();
+        self.certificates.insert(
+            id,
+            StoredCertificate {
+                name: name.into(),
+                source: source.into(),
+                bundle,
+                created_at: Utc::now(),
+            },
+        );
+        id
+    }
+
+    /// Get a copy of a stored bundle
+    pub fn get_bundle(&self, id: &Uuid) -> Option<CertificateBundle> {
+        self.certificates.get(id).map(|c| c.bundle.clone())
+    }
+
+    /// Get summary information for a stored certificate
+    pub fn info(&self, id: &Uuid) -> Option<StoredCertificateInfo> {
+        self.certificates.get(id).map(|c| Self::to_info(*id, &c))
+    }
+
+    /// List summary information for all stored certificates, oldest first
+    pub fn list(&self) -> Vec<StoredCertificateInfo> {
+        let mut list: Vec<_> = self
+            .certificates
+            .iter()
+            .map(|entry| Self::to_info(*entry.key(), entry.value()))
+            .collect();
+        list.sort_by_key(|c| c.created_at);
+        list
+    }
+
+    /// Remove a stored certificate, returning whether it existed
+    pub fn remove(&self, id: &Uuid) -> bool {
+        self.certificates.remove(id).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.certificates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.certificates.is_empty()
+    }
+
+    fn to_info(id: Uuid, cert: &StoredCertificate) -> StoredCertificateInfo {
+        let chain = CertificateChainInfo::from_bundle(&cert.bundle);
+        StoredCertificateInfo {
+            id,
+            name: cert.name.clone(),
+            source: cert.source.clone(),
+            subject_cn: chain.client_cert.as_ref().map(|c| c.subject_cn.clone()),
+            not_after: chain.client_cert.map(|c| c.not_after),
+            has_ca: cert.bundle.ca_certs.is_some(),
+            created_at: cert.created_at,
+        }
+    }
+}
+
+// ============================================================================
+// Endpoints
+// ============================================================================
+
+/// GET /api/v1/certificates - List stored certificates
+#[utoipa::path(
+    get,
+    path = "/api/v1/certificates",
+    responses(
+        (status = 200, description = "Certificates retrieved successfully", body = CertificateList),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+pub async fn list_certificates(
+    State(state): State<ApiState>,
+    _user: AuthUser,
+) -> Result<Json<CertificateList>, ApiError> {
+    let certificates = state.certificates.list();
+    Ok(Json(CertificateList {
+        total: certificates.len(),
+        certificates,
+    }))
+}
+
+/// POST /api/v1/certificates - Import a certificate into the store
+#[utoipa::path(
+    post,
+    path = "/api/v1/certificates",
+    request_body = ImportCertificateRequest,
+    responses(
+        (status = 201, description = "Certificate imported successfully", body = StoredCertificateInfo),
+        (status = 400, description = "Invalid certificate data", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires certificates:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["certificates:write"]),
+        ("api_key" = ["certificates:write"])
+    )
+)]
+pub async fn import_certificate(
+    State(state): State<ApiState>,
+    RequireCertificatesWrite(user): RequireCertificatesWrite,
+    ClientIp(client_ip): ClientIp,
+    Json(request): Json<ImportCertificateRequest>,
+) -> Result<(StatusCode, Json<StoredCertificateInfo>), ApiError> {
+    request.validate()?;
+
+    let bundle = parse_bundle(&request)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid certificate: {}", e)))?;
+
+    let id = state.certificates.insert(request.name.clone(), "upload", bundle);
+    let info = state
+        .certificates
+        .info(&id)
+        .ok_or_else(|| ApiError::InternalError("Certificate missing after import".to_string()))?;
+
+    info!(certificate_id = %id, name = %request.name, "Imported certificate");
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "import_certificate".to_string(),
+        format!("/api/v1/certificates/{}", id),
+        serde_json::json!({"name": request.name, "format": request.format}),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok((StatusCode::CREATED, Json(info)))
+}
+
+/// POST /api/v1/certificates/upload - Upload certificate files into the store
+///
+/// Files are told apart by their contents, so they may be named anything:
+/// PEM certificates and keys, PKCS#12 files and ZIP data packages, with
+/// their ATAK preference files supplying PKCS#12 passwords.
+#[utoipa::path(
+    post,
+    path = "/api/v1/certificates/upload",
+    request_body(content = Vec<u8>, content_type = "multipart/form-data", description = "Certificate files in any file fields, with optional text fields name and password"),
+    responses(
+        (status = 201, description = "Certificate uploaded", body = CertificateUploadResponse),
+        (status = 400, description = "No usable certificate in the upload", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires certificates:write", body = ErrorResponse),
+        (status = 413, description = "Upload larger than 10 MiB", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["certificates:write"]),
+        ("api_key" = ["certificates:write"])
+    )
+)]
+pub async fn upload_certificate(
+    State(state): State<ApiState>,
+    RequireCertificatesWrite(user): RequireCertificatesWrite,
+    ClientIp(client_ip): ClientIp,
+    multipart: Multipart,
+) -> Result<(StatusCode, Json<CertificateUploadResponse>), ApiError> {
+    let upload = Upload::receive(multipart, MAX_UPLOAD_BYTES).await?;
+    let Some(first) = upload.files.first() else {
+        return Err(ApiError::BadRequest("No files uploaded".to_string()));
+    };
+    let name = upload.field("name").unwrap_or(&first.file_name).to_string();
+
+    let (upload, classified) = tokio::task::spawn_blocking(move || {
+        let paths: Vec<_> = upload.files.iter().map(|f| f.path.clone()).collect();
+        let classified = omnitak_cert::load_bundle_by_contents(
+            &paths,
+            &upload.dir().join("extracted"),
+            upload.field("password"),
+        );
+        (upload, classified)
+    })
+    .await
+    .map_err(|e| ApiError::InternalError(e.to_string()))?;
+    let classified =
+        classified.map_err(|e| ApiError::BadRequest(format!("Invalid certificate: {}", e)))?;
+
+    // Extracted files are named by their path in the archive
+    let extracted = upload.dir().join("extracted");
+    let files: Vec<UploadedCertificateFile> = classified
+        .files
+        .iter()
+        .map(|file| {
+            let name = match file.path.strip_prefix(&extracted) {
+                Ok(path) => path.components().skip(1).collect::<std::path::PathBuf>(),
+                Err(_) => upload
+                    .files
+                    .iter()
+                    .find(|f| f.path == file.path)
+                    .map(|f| f.file_name.clone().into())
+                    .unwrap_or_default(),
+            };
+            UploadedCertificateFile {
+                name: name.to_string_lossy().into_owned(),
+                kind: file.kind,
+            }
+        })
+        .collect();
+
+    let id = state
+        .certificates
+        .insert(name.clone(), "upload", classified.bundle);
+    let certificate = state
+        .certificates
+        .info(&id)
+        .ok_or_else(|| ApiError::InternalError("Certificate missing after upload".to_string()))?;
+
+    info!(certificate_id = %id, name = %name, files = files.len(), "Uploaded certificate");
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "upload_certificate".to_string(),
+        format!("/api/v1/certificates/{}", id),
+        serde_json::json!({
+            "name": name,
+            "files": files.iter().map(|f| &f.name).collect::<Vec<_>>(),
+        }),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CertificateUploadResponse { certificate, files }),
+    ))
+}
+
+/// DELETE /api/v1/certificates/:id - Remove a stored certificate
+#[utoipa::path(
+    delete,
+    path = "/api/v1/certificates/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Certificate ID")
+    ),
+    responses(
+        (status = 200, description = "Certificate deleted successfully", body = DeleteConnectionResponse),
+        (status = 404, description = "Certificate not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires certificates:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["certificates:write"]),
+        ("api_key" = ["certificates:write"])
+    )
+)]
+pub async fn delete_certificate(
+    State(state): State<ApiState>,
+    Path(id): Path<Uuid>,
+    RequireCertificatesWrite(user): RequireCertificatesWrite,
+    ClientIp(client_ip): ClientIp,
+) -> Result<Json<DeleteConnectionResponse>, ApiError> {
+    if !state.certificates.remove(&id) {
+        return Err(ApiError::NotFound(format!("Certificate {} not found", id)));
+    }
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "delete_certificate".to_string(),
+        format!("/api/v1/certificates/{}", id),
+        serde_json::json!({"certificate_id": id}),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(Json(DeleteConnectionResponse {
+        message: "Certificate deleted successfully".to_string(),
+    }))
+}
+
+/// Decode an import request into a certificate bundle
+fn parse_bundle(request: &ImportCertificateRequest) -> anyhow::Result<CertificateBundle> {
+    let data = |name: &str, data: &str| CertificateData {
+        name: name.to_string(),
+        data: data.to_string(),
+        size: data.len() as u64,
+    };
+
+    let cert_name = match request.format {
+        CertificateFormat::Pem => "certificate.pem",
+        CertificateFormat::Pkcs12 => "certificate.p12",
+    };
+    let cert = data(cert_name, &request.certificate);
+    let key = request.private_key.as_deref().map(|k| data("key.pem", k));
+    let ca = request.ca_certificate.as_deref().map(|c| data("ca.pem", c));
+
+    CertificateBundle::from_certificate_data(
+        &cert,
+        key.as_ref(),
+        ca.as_ref(),
+        request.password.as_deref(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use omnitak_cert::generator::{CaConfig, ClientCertConfig, GeneratedCa};
+
+    fn test_bundle() -> CertificateBundle {
+        let ca = GeneratedCa::generate(&CaConfig::default()).unwrap();
+        let client = ca.issue_client_cert(&ClientCertConfig::new("alpha-1")).unwrap();
+        CertificateBundle::from_pem(
+            client.cert_pem.as_bytes(),
+            client.key_pem.as_bytes(),
+            Some(client.ca_cert_pem.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_store_insert_get_remove() {
+        let store = CertificateStore::new();
+        assert!(store.is_empty());
+
+        let id = store.insert("alpha", "upload", test_bundle());
+        assert_eq!(store.len(), 1);
+        assert!(store.get_bundle(&id).is_some());
+
+        let info = store.info(&id).unwrap();
+        assert_eq!(info.name, "alpha");
+        assert_eq!(info.subject_cn.as_deref(), Some("alpha-1"));
+        assert!(info.has_ca);
+
+        assert!(store.remove(&id));
+        assert!(!store.remove(&id));
+        assert!(store.get_bundle(&id).is_none());
+    }
+}
@@ -0,0 +1,201 @@
+//! Client certificate authorization rule endpoints
+//!
+//! Rules changed here apply to connections made afterwards and are not
+//! written back to the configuration file.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use tracing::info;
+
+use crate::auth::RequireClientAccessManage;
+use crate::client_access::{
+    ClientAccessError, ClientAccessRule, ClientAuthorization, ClientIdentity,
+};
+use crate::middleware::ClientIp;
+use crate::rest::{ApiError, ApiState};
+use crate::types::*;
+
+impl From<ClientAccessError> for ApiError {
+    fn from(e: ClientAccessError) -> Self {
+        match e {
+            ClientAccessError::NotFound(_) => ApiError::NotFound(e.to_string()),
+            ClientAccessError::Exists(_) | ClientAccessError::Invalid(_) => {
+                ApiError::BadRequest(e.to_string())
+            }
+        }
+    }
+}
+
+/// GET /api/v1/client-access/rules - List client access rules (requires client_access:manage)
+#[utoipa::path(
+    get,
+    path = "/api/v1/client-access/rules",
+    responses(
+        (status = 200, description = "Rules in the order they are tried", body = ClientAccessRuleList),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires client_access:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["client_access:manage"]),
+        ("api_key" = ["client_access:manage"])
+    )
+)]
+pub async fn list_rules(
+    State(state): State<ApiState>,
+    RequireClientAccessManage(_user): RequireClientAccessManage,
+) -> Result<Json<ClientAccessRuleList>, ApiError> {
+    let rules = state.client_access.list();
+    Ok(Json(ClientAccessRuleList {
+        total: rules.len(),
+        rules,
+        default_access: state.client_access.default_access(),
+    }))
+}
+
+/// POST /api/v1/client-access/rules - Add a rule after the existing ones (requires client_access:manage)
+#[utoipa::path(
+    post,
+    path = "/api/v1/client-access/rules",
+    request_body = ClientAccessRule,
+    responses(
+        (status = 201, description = "Rule added"),
+        (status = 400, description = "Invalid rule or name already in use", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires client_access:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["client_access:manage"]),
+        ("api_key" = ["client_access:manage"])
+    )
+)]
+pub async fn create_rule(
+    State(state): State<ApiState>,
+    RequireClientAccessManage(user): RequireClientAccessManage,
+    ClientIp(client_ip): ClientIp,
+    Json(rule): Json<ClientAccessRule>,
+) -> Result<StatusCode, ApiError> {
+    let name = rule.name.clone();
+    let access = rule.access;
+    state.client_access.add(rule)?;
+
+    info!(rule = %name, access = access.as_str(), "Added client access rule");
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "create_client_access_rule".to_string(),
+        "/api/v1/client-access/rules".to_string(),
+        serde_json::json!({"name": name, "access": access}),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(StatusCode::CREATED)
+}
+
+/// PUT /api/v1/client-access/rules/:name - Replace a rule in place (requires client_access:manage)
+#[utoipa::path(
+    put,
+    path = "/api/v1/client-access/rules/{name}",
+    params(
+        ("name" = String, Path, description = "Rule name")
+    ),
+    request_body = ClientAccessRule,
+    responses(
+        (status = 204, description = "Rule updated"),
+        (status = 400, description = "Invalid rule", body = ErrorResponse),
+        (status = 404, description = "Rule not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires client_access:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["client_access:manage"]),
+        ("api_key" = ["client_access:manage"])
+    )
+)]
+pub async fn update_rule(
+    State(state): State<ApiState>,
+    Path(name): Path<String>,
+    RequireClientAccessManage(user): RequireClientAccessManage,
+    ClientIp(client_ip): ClientIp,
+    Json(rule): Json<ClientAccessRule>,
+) -> Result<StatusCode, ApiError> {
+    let access = rule.access;
+    state.client_access.update(&name, rule)?;
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "update_client_access_rule".to_string(),
+        format!("/api/v1/client-access/rules/{}", name),
+        serde_json::json!({"name": name, "access": access}),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /api/v1/client-access/rules/:name - Remove a rule (requires client_access:manage)
+#[utoipa::path(
+    delete,
+    path = "/api/v1/client-access/rules/{name}",
+    params(
+        ("name" = String, Path, description = "Rule name")
+    ),
+    responses(
+        (status = 204, description = "Rule removed"),
+        (status = 404, description = "Rule not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires client_access:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["client_access:manage"]),
+        ("api_key" = ["client_access:manage"])
+    )
+)]
+pub async fn delete_rule(
+    State(state): State<ApiState>,
+    Path(name): Path<String>,
+    RequireClientAccessManage(user): RequireClientAccessManage,
+    ClientIp(client_ip): ClientIp,
+) -> Result<StatusCode, ApiError> {
+    state.client_access.remove(&name)?;
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "delete_client_access_rule".to_string(),
+        format!("/api/v1/client-access/rules/{}", name),
+        serde_json::json!({"name": name}),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/v1/client-access/evaluate - Access a certificate would get (requires client_access:manage)
+#[utoipa::path(
+    post,
+    path = "/api/v1/client-access/evaluate",
+    request_body = ClientIdentity,
+    responses(
+        (status = 200, description = "Matching rule and resulting access", body = ClientAuthorization),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires client_access:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["client_access:manage"]),
+        ("api_key" = ["client_access:manage"])
+    )
+)]
+pub async fn evaluate(
+    State(state): State<ApiState>,
+    RequireClientAccessManage(_user): RequireClientAccessManage,
+    Json(identity): Json<ClientIdentity>,
+) -> Result<Json<ClientAuthorization>, ApiError> {
+    Ok(Json(state.client_access.evaluate(&identity)))
+}
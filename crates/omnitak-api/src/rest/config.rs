@@ -0,0 +1,468 @@
+//! Configuration export
+//!
+//! Serializes the running system back into a config.yaml. The file the
+//! server was started from is the base, so users, listeners, filters and
+//! `${VAR}`/`secret://` references come out as written rather than
+//! resolved; connections added through the API and the current log levels
+//! are layered on top. Comments in the original file are not kept.
+
+use axum::{
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{SecondsFormat, Utc};
+use serde_yaml::{Mapping, Value};
+use tracing::{info, warn};
+
+use crate::auth::RequireSystemManage;
+use crate::middleware::ClientIp;
+use crate::rest::{ApiError, ApiState};
+use crate::types::*;
+
+/// Header carrying the number of connections or settings left out
+const WARNINGS_HEADER: &str = "x-export-warnings";
+
+/// GET /api/v1/config/export - Running configuration as config.yaml (requires system:manage)
+#[utoipa::path(
+    get,
+    path = "/api/v1/config/export",
+    responses(
+        (status = 200, description = "Configuration file; anything that could not be exported is listed in comments at the top", content_type = "application/yaml", body = String),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires system:manage outside any tenant", body = ErrorResponse),
+        (status = 500, description = "Configuration file unreadable", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["system:manage"]),
+        ("api_key" = ["system:manage"])
+    )
+)]
+pub async fn export_config(
+    State(state): State<ApiState>,
+    RequireSystemManage(user): RequireSystemManage,
+    ClientIp(client_ip): ClientIp,
+) -> Result<Response, ApiError> {
+    // The file holds every tenant's users and connections
+    if user.tenant.is_some() {
+        return Err(ApiError::Forbidden(
+            "Exporting the configuration requires a user outside any tenant".to_string(),
+        ));
+    }
+
+    let base = match &state.config_file {
+        Some(path) => Some(tokio::fs::read_to_string(path).await.map_err(|e| {
+            ApiError::InternalError(format!("Failed to read {}: {}", path.display(), e))
+        })?),
+        None => None,
+    };
+
+    let connections = {
+        let connections = state.connections.read().await;
+        let requests = state.connection_requests.read().await;
+        connections
+            .iter()
+            .map(|c| (c.name.clone(), requests.get(&c.id).cloned()))
+            .collect::<Vec<_>>()
+    };
+    let levels = state.log_control.as_ref().map(|c| c.status().current);
+
+    let (document, warnings) = build_export(base.as_deref(), &connections, levels.as_ref())
+        .map_err(ApiError::InternalError)?;
+    let yaml = render(&document, &warnings)?;
+
+    for warning in &warnings {
+        warn!("Config export: {}", warning);
+    }
+    info!(warnings = warnings.len(), "Exported configuration");
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "export_config".to_string(),
+        "/api/v1/config/export".to_string(),
+        serde_json::json!({"warnings": warnings}),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/yaml".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"config.yaml\"".to_string(),
+            ),
+            (
+                header::HeaderName::from_static(WARNINGS_HEADER),
+                warnings.len().to_string(),
+            ),
+        ],
+        yaml,
+    )
+        .into_response())
+}
+
+/// Layer API connections and log levels over the configuration file.
+/// Returns the document and what could not be exported.
+pub(crate) fn build_export(
+    base: Option<&str>,
+    connections: &[(String, Option<CreateConnectionRequest>)],
+    levels: Option<&LogLevels>,
+) -> Result<(Mapping, Vec<String>), String> {
+    let mut document = match base.map(serde_yaml::from_str::<Value>).transpose() {
+        Ok(None | Some(Value::Null)) => Mapping::new(),
+        Ok(Some(Value::Mapping(mapping))) => mapping,
+        Ok(Some(_)) => return Err("Configuration file is not a mapping".to_string()),
+        Err(e) => return Err(format!("Configuration file is not valid YAML: {}", e)),
+    };
+    let mut warnings = Vec::new();
+
+    let servers = document
+        .entry(Value::from("servers"))
+        .or_insert_with(|| Value::Sequence(Vec::new()));
+    if servers.is_null() {
+        *servers = Value::Sequence(Vec::new());
+    }
+    let servers = servers
+        .as_sequence_mut()
+        .ok_or_else(|| "'servers' in the configuration file is not a list".to_string())?;
+
+    for (name, request) in connections {
+        let Some(request) = request else {
+            warnings.push(format!(
+                "Connection '{}' was not created through the connections API and was not exported",
+                name
+            ));
+            continue;
+        };
+        if servers
+            .iter()
+            .any(|s| s.get("id").and_then(Value::as_str) == Some(name.as_str()))
+        {
+            warnings.push(format!(
+                "Connection '{}' has the same name as a configured server and was not exported",
+                name
+            ));
+            continue;
+        }
+        match server_entry(request) {
+            Ok(entry) => servers.push(entry),
+            Err(reason) => warnings.push(format!(
+                "Connection '{}' was not exported: {}",
+                name, reason
+            )),
+        }
+    }
+
+    if let Some(levels) = levels {
+        let logging = document
+            .entry(Value::from("logging"))
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+        if logging.is_null() {
+            *logging = Value::Mapping(Mapping::new());
+        }
+        let logging = logging
+            .as_mapping_mut()
+            .ok_or_else(|| "'logging' in the configuration file is not a mapping".to_string())?;
+        logging.insert(Value::from("level"), Value::from(levels.level.clone()));
+        if levels.levels.is_empty() {
+            logging.remove("levels");
+        } else {
+            logging.insert(
+                Value::from("levels"),
+                Value::Mapping(
+                    levels
+                        .levels
+                        .iter()
+                        .map(|(target, level)| {
+                            (Value::from(target.clone()), Value::from(level.clone()))
+                        })
+                        .collect(),
+                ),
+            );
+        }
+    }
+
+    Ok((document, warnings))
+}
+
+/// `servers` entry for a connection created through the API
+fn server_entry(request: &CreateConnectionRequest) -> Result<Value, String> {
+    let protocol = match request.connection_type {
+        ConnectionType::TcpClient => "tcp",
+        ConnectionType::TlsClient => "tls",
+        other => {
+            return Err(format!(
+                "{:?} connections cannot be configured in the file",
+                other
+            ));
+        }
+    };
+    let address = if request.address.contains(':') && !request.address.starts_with('[') {
+        format!("[{}]:{}", request.address, request.port)
+    } else {
+        format!("{}:{}", request.address, request.port)
+    };
+
+    let mut entry = Mapping::new();
+    entry.insert(Value::from("id"), Value::from(request.name.clone()));
+    entry.insert(Value::from("address"), Value::from(address));
+    entry.insert(Value::from("protocol"), Value::from(protocol));
+
+    if request.connection_type == ConnectionType::TlsClient {
+        if request.certificate_id.is_some() {
+            return Err(
+                "it uses a certificate from the certificate store; save it to files and add the connection with cert_path and key_path"
+                    .to_string(),
+            );
+        }
+        let (Some(cert_path), Some(key_path)) = (&request.tls_cert_path, &request.tls_key_path)
+        else {
+            return Err("it has no certificate and key paths".to_string());
+        };
+        let mut tls = Mapping::new();
+        tls.insert(Value::from("cert_path"), Value::from(cert_path.clone()));
+        tls.insert(Value::from("key_path"), Value::from(key_path.clone()));
+        tls.insert(
+            Value::from("verify_server"),
+            Value::from(request.validate_certs),
+        );
+        entry.insert(Value::from("tls"), Value::Mapping(tls));
+    }
+
+    Ok(Value::Mapping(entry))
+}
+
+/// Connection requests for the `servers` of a configuration file, in
+/// order: each entry's `id` (or its position when it has none) and the
+/// request, or why the entry cannot be one
+pub(crate) fn connection_requests(
+    yaml: &str,
+) -> Result<Vec<(String, Result<CreateConnectionRequest, String>)>, String> {
+    let document: Value = serde_yaml::from_str(yaml)
+        .map_err(|e| format!("Configuration file is not valid YAML: {}", e))?;
+    let servers = match document.get("servers") {
+        None | Some(Value::Null) => return Ok(Vec::new()),
+        Some(Value::Sequence(servers)) => servers,
+        Some(_) => return Err("'servers' in the configuration file is not a list".to_string()),
+    };
+    Ok(servers
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let name = entry
+                .get("id")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("servers[{}]", i));
+            (name, connection_request(entry))
+        })
+        .collect())
+}
+
+/// Connection request for a `servers` entry, the reverse of
+/// [`server_entry`]. A `tls.ca_path` is not carried over; API connections
+/// verify servers against the system roots.
+fn connection_request(entry: &Value) -> Result<CreateConnectionRequest, String> {
+    let field = |key: &str| entry.get(key).and_then(Value::as_str);
+    let name = field("id").ok_or("it has no id")?;
+    let address = field("address").ok_or("it has no address")?;
+    let (host, port) =
+        split_address(address).ok_or_else(|| format!("address '{}' is not host:port", address))?;
+    let connection_type = match field("protocol").ok_or("it has no protocol")? {
+        protocol if protocol.eq_ignore_ascii_case("tcp") => ConnectionType::TcpClient,
+        protocol if protocol.eq_ignore_ascii_case("tls") => ConnectionType::TlsClient,
+        other => return Err(format!("protocol '{}' is not supported", other)),
+    };
+
+    let tls = entry.get("tls").filter(|tls| !tls.is_null());
+    if connection_type == ConnectionType::TlsClient && tls.is_none() {
+        return Err("it uses TLS but has no tls section".to_string());
+    }
+    let tls_path = |key: &str| {
+        tls.and_then(|tls| tls.get(key))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+
+    Ok(CreateConnectionRequest {
+        name: name.to_string(),
+        connection_type,
+        address: host.to_string(),
+        port,
+        auto_reconnect: true,
+        tls_cert_path: tls_path("cert_path"),
+        tls_key_path: tls_path("key_path"),
+        certificate_id: None,
+        validate_certs: tls
+            .and_then(|tls| tls.get("verify_server"))
+            .and_then(Value::as_bool)
+            .unwrap_or(true),
+        reconnect: ReconnectPolicy::default(),
+    })
+}
+
+/// Split `host:port`, where the host may be a bracketed IPv6 address
+fn split_address(address: &str) -> Option<(&str, u16)> {
+    let (host, port) = match address.strip_prefix('[') {
+        Some(rest) => {
+            let (host, port) = rest.split_once(']')?;
+            (host, port.strip_prefix(':')?)
+        }
+        None => address.rsplit_once(':')?,
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some((host, port.parse().ok()?))
+}
+
+/// YAML text with a header naming the export time and anything left out
+fn render(document: &Mapping, warnings: &[String]) -> Result<String, ApiError> {
+    let body = serde_yaml::to_string(document).map_err(|e| {
+        ApiError::InternalError(format!("Failed to serialize configuration: {}", e))
+    })?;
+
+    let mut yaml = format!(
+        "# OmniTAK configuration exported {}\n",
+        Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+    );
+    for warning in warnings {
+        yaml.push_str(&format!("# Not exported: {}\n", warning.replace('\n', " ")));
+    }
+    yaml.push_str(&body);
+    Ok(yaml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(name: &str, connection_type: ConnectionType) -> CreateConnectionRequest {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "connection_type": connection_type,
+            "address": "tak.example.com",
+            "port": 8089,
+            "tls_cert_path": "/etc/omnitak/client.pem",
+            "tls_key_path": "/etc/omnitak/client.key",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_export_layers_connections_over_file() {
+        let base = "servers:\n  - id: hq\n    address: \"hq:8087\"\n    protocol: tcp\n\
+                    api:\n  jwt_secret: \"${JWT_SECRET}\"\n";
+        let connections = vec![
+            (
+                "field".to_string(),
+                Some(request("field", ConnectionType::TlsClient)),
+            ),
+            (
+                "hq".to_string(),
+                Some(request("hq", ConnectionType::TcpClient)),
+            ),
+            ("ADB-123".to_string(), None),
+        ];
+        let levels = LogLevels {
+            level: "debug".to_string(),
+            levels: [("omnitak_pool".to_string(), "trace".to_string())]
+                .into_iter()
+                .collect(),
+        };
+
+        let (document, warnings) = build_export(Some(base), &connections, Some(&levels)).unwrap();
+
+        let servers = document["servers"].as_sequence().unwrap();
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[1]["id"].as_str(), Some("field"));
+        assert_eq!(servers[1]["address"].as_str(), Some("tak.example.com:8089"));
+        assert_eq!(servers[1]["protocol"].as_str(), Some("tls"));
+        assert_eq!(servers[1]["tls"]["verify_server"].as_bool(), Some(true));
+        assert_eq!(
+            document["api"]["jwt_secret"].as_str(),
+            Some("${JWT_SECRET}")
+        );
+        assert_eq!(document["logging"]["level"].as_str(), Some("debug"));
+        assert_eq!(
+            document["logging"]["levels"]["omnitak_pool"].as_str(),
+            Some("trace")
+        );
+
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("'hq'"));
+        assert!(warnings[1].contains("'ADB-123'"));
+    }
+
+    #[test]
+    fn test_export_without_file() {
+        let mut stored = request("store", ConnectionType::TlsClient);
+        stored.certificate_id = Some(uuid::Uuid::new_v4());
+        let connections = vec![
+            ("store".to_string(), Some(stored)),
+            ("udp".to_string(), Some(request("udp", ConnectionType::Udp))),
+            (
+                "v6".to_string(),
+                Some({
+                    let mut r = request("v6", ConnectionType::TcpClient);
+                    r.address = "fd00::1".to_string();
+                    r
+                }),
+            ),
+        ];
+
+        let (document, warnings) = build_export(None, &connections, None).unwrap();
+
+        let servers = document["servers"].as_sequence().unwrap();
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0]["address"].as_str(), Some("[fd00::1]:8089"));
+        assert!(servers[0].get("tls").is_none());
+        assert!(document.get("logging").is_none());
+        assert_eq!(warnings.len(), 2);
+
+        assert!(build_export(Some("- not a mapping"), &[], None).is_err());
+    }
+
+    #[test]
+    fn test_connection_requests_from_file() {
+        let mut servers = vec![server_entry(&request("field", ConnectionType::TlsClient)).unwrap()];
+        servers.extend(
+            serde_yaml::from_str::<Vec<Value>>(
+                "- id: v6\n  address: \"[fd00::1]:8087\"\n  protocol: tcp\n\
+                 - id: bad\n  address: tak.example.com\n  protocol: tcp\n\
+                 - address: tak.example.com:8087\n  protocol: udp\n",
+            )
+            .unwrap(),
+        );
+        let mut document = Mapping::new();
+        document.insert(Value::from("servers"), Value::Sequence(servers));
+        let yaml = serde_yaml::to_string(&document).unwrap();
+
+        let requests = connection_requests(&yaml).unwrap();
+        assert_eq!(requests.len(), 4);
+
+        // Exported entries come back as the requests they were made from
+        let field = requests[0].1.as_ref().unwrap();
+        assert_eq!(field.connection_type, ConnectionType::TlsClient);
+        assert_eq!(field.address, "tak.example.com");
+        assert_eq!(field.port, 8089);
+        assert_eq!(
+            field.tls_cert_path.as_deref(),
+            Some("/etc/omnitak/client.pem")
+        );
+
+        let v6 = requests[1].1.as_ref().unwrap();
+        assert_eq!((v6.address.as_str(), v6.port), ("fd00::1", 8087));
+
+        assert_eq!(requests[2].0, "bad");
+        assert!(requests[2].1.as_ref().unwrap_err().contains("host:port"));
+        assert_eq!(requests[3].0, "servers[3]");
+        assert!(requests[3].1.is_err());
+
+        assert!(connection_requests("servers: 5").is_err());
+        assert!(connection_requests("api: {}").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,426 @@
+//! Data package import endpoints
+//!
+//! Server connection packages (as handed out by TAK servers, or by OmniTAK's
+//! own onboarding) describe their streams in ATAK `.pref` files next to a
+//! client certificate and trust store. These endpoints list those streams
+//! for review, then create the selected ones as pool connections, loading
+//! the certificates into the certificate store. Any package can also be
+//! linted before it is handed to devices.
+
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use omnitak_cert::CertificateBundle;
+use omnitak_datapackage::{
+    DataPackageError, DataPackageReader, Severity, StreamPreference, StreamProtocol,
+    ValidationIssue, ValidationReport,
+};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::auth::{AuthUser, RequireConnectionsWrite};
+use crate::middleware::ClientIp;
+use crate::rest::{ApiError, ApiState, open_connection};
+use crate::types::*;
+
+#[derive(Debug, Deserialize)]
+pub struct ImportConnectionsQuery {
+    /// Comma-separated stream indexes; all enabled streams when absent
+    #[serde(default)]
+    pub streams: Option<String>,
+}
+
+/// A stream from the package, with its certificates loaded
+struct PackageStream {
+    info: PackageConnection,
+    bundle: Option<CertificateBundle>,
+}
+
+/// POST /api/v1/datapackages/connections/preview - List a package's connections
+#[utoipa::path(
+    post,
+    path = "/api/v1/datapackages/connections/preview",
+    request_body(content = Vec<u8>, description = "Data package (.zip)", content_type = "application/zip"),
+    responses(
+        (status = 200, description = "Connections found in the package", body = PackageConnectionList),
+        (status = 400, description = "Invalid data package", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+pub async fn preview_connections(
+    _user: AuthUser,
+    body: Bytes,
+) -> Result<Json<PackageConnectionList>, ApiError> {
+    let (package_name, streams) = read_package(body).await?;
+    Ok(Json(PackageConnectionList {
+        package_name,
+        connections: streams.into_iter().map(|s| s.info).collect(),
+    }))
+}
+
+/// POST /api/v1/datapackages/connections - Create a package's connections
+#[utoipa::path(
+    post,
+    path = "/api/v1/datapackages/connections",
+    params(
+        ("streams" = Option<String>, Query, description = "Comma-separated stream indexes to create (default: all enabled streams)")
+    ),
+    request_body(content = Vec<u8>, description = "Data package (.zip)", content_type = "application/zip"),
+    responses(
+        (status = 201, description = "Connections created", body = ImportConnectionsResponse),
+        (status = 400, description = "Invalid data package or stream selection", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires connections:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["connections:write"]),
+        ("api_key" = ["connections:write"])
+    )
+)]
+pub async fn import_connections(
+    State(state): State<ApiState>,
+    Query(query): Query<ImportConnectionsQuery>,
+    RequireConnectionsWrite(user): RequireConnectionsWrite,
+    ClientIp(client_ip): ClientIp,
+    body: Bytes,
+) -> Result<(StatusCode, Json<ImportConnectionsResponse>), ApiError> {
+    let selected = parse_selection(query.streams.as_deref())?;
+    let (package_name, streams) = read_package(body).await?;
+    if let Some(index) = selected.iter().flatten().find(|&&i| i >= streams.len()) {
+        return Err(ApiError::BadRequest(format!(
+            "Package has no stream {}",
+            index
+        )));
+    }
+
+    let mut response = ImportConnectionsResponse {
+        created: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for stream in streams {
+        let info = stream.info;
+        let wanted = match &selected {
+            Some(indexes) => indexes.contains(&info.index),
+            None => info.enabled,
+        };
+        if !wanted {
+            continue;
+        }
+        let skip = |reason: String| SkippedConnection {
+            index: info.index,
+            name: info.name.clone(),
+            reason,
+        };
+
+        let (Some(connection_type), None) = (info.connection_type, &info.unsupported_reason) else {
+            let reason = info.unsupported_reason.clone().unwrap_or_default();
+            response.skipped.push(skip(reason));
+            continue;
+        };
+
+        let certificate_id = stream.bundle.map(|bundle| {
+            state
+                .certificates
+                .insert(info.name.clone(), "datapackage", bundle)
+        });
+        let request = CreateConnectionRequest {
+            name: info.name.clone(),
+            connection_type,
+            address: info.address.clone(),
+            port: info.port,
+            auto_reconnect: true,
+            tls_cert_path: None,
+            tls_key_path: None,
+            certificate_id,
+            validate_certs: true,
+            reconnect: ReconnectPolicy::default(),
+        };
+
+        match open_connection(&state, &user, &request).await {
+            Ok(id) => response.created.push(ImportedConnection {
+                index: info.index,
+                id,
+                name: info.name.clone(),
+                certificate_id,
+            }),
+            Err(e) => {
+                if let Some(certificate_id) = certificate_id {
+                    state.certificates.remove(&certificate_id);
+                }
+                warn!(name = %info.name, error = %e, "Failed to create connection from data package");
+                response.skipped.push(skip(e.to_string()));
+            }
+        }
+    }
+
+    info!(
+        package = package_name.as_deref().unwrap_or("unnamed"),
+        created = response.created.len(),
+        skipped = response.skipped.len(),
+        "Imported connections from data package"
+    );
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "import_datapackage_connections".to_string(),
+        "/api/v1/datapackages/connections".to_string(),
+        serde_json::json!({
+            "package": package_name,
+            "connections": response.created.iter().map(|c| c.id).collect::<Vec<_>>(),
+        }),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// POST /api/v1/datapackages/validate - Lint a data package
+#[utoipa::path(
+    post,
+    path = "/api/v1/datapackages/validate",
+    request_body(content = Vec<u8>, description = "Data package (.zip)", content_type = "application/zip"),
+    responses(
+        (status = 200, description = "Validation report; packages that cannot be opened are reported as invalid", body = PackageValidationReport),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+pub async fn validate_package(
+    _user: AuthUser,
+    body: Bytes,
+) -> Result<Json<PackageValidationReport>, ApiError> {
+    let (package_name, report) = tokio::task::spawn_blocking(move || {
+        let reader = DataPackageReader::from_bytes(body.to_vec())?;
+        Ok::<_, DataPackageError>((reader.name().map(str::to_string), reader.lint()?))
+    })
+    .await
+    .map_err(|e| ApiError::InternalError(e.to_string()))?
+    .unwrap_or_else(|e| {
+        let mut report = ValidationReport::default();
+        report.issues.push(ValidationIssue {
+            severity: Severity::Error,
+            path: None,
+            message: format!("Cannot open package: {}", e),
+        });
+        (None, report)
+    });
+
+    Ok(Json(PackageValidationReport {
+        package_name,
+        valid: report.is_valid(),
+        issues: report
+            .issues
+            .into_iter()
+            .map(|issue| PackageValidationIssue {
+                severity: match issue.severity {
+                    Severity::Error => ValidationSeverity::Error,
+                    Severity::Warning => ValidationSeverity::Warning,
+                },
+                path: issue.path,
+                message: issue.message,
+            })
+            .collect(),
+    }))
+}
+
+/// Parse `0,2,3` into stream indexes
+fn parse_selection(streams: Option<&str>) -> Result<Option<Vec<usize>>, ApiError> {
+    streams
+        .map(|s| {
+            s.split(',')
+                .map(|i| {
+                    i.trim()
+                        .parse()
+                        .map_err(|_| ApiError::BadRequest(format!("Invalid stream index: {}", i)))
+                })
+                .collect()
+        })
+        .transpose()
+}
+
+async fn read_package(body: Bytes) -> Result<(Option<String>, Vec<PackageStream>), ApiError> {
+    tokio::task::spawn_blocking(move || {
+        let reader = DataPackageReader::from_bytes(body.to_vec())
+            .map_err(|e| ApiError::BadRequest(format!("Invalid data package: {}", e)))?;
+        let streams = reader
+            .stream_preferences()
+            .map_err(|e| ApiError::BadRequest(format!("Invalid data package: {}", e)))?;
+        let streams = streams
+            .iter()
+            .enumerate()
+            .map(|(index, pref)| package_stream(&reader, index, pref))
+            .collect();
+        Ok((reader.name().map(str::to_string), streams))
+    })
+    .await
+    .map_err(|e| ApiError::InternalError(e.to_string()))?
+}
+
+/// Map a preference stream onto a connection, loading its certificates
+fn package_stream(
+    reader: &DataPackageReader,
+    index: usize,
+    pref: &StreamPreference,
+) -> PackageStream {
+    let certificate = pref
+        .certificate_location
+        .as_deref()
+        .and_then(|l| reader.find_file(l));
+    let truststore = pref
+        .ca_location
+        .as_deref()
+        .and_then(|l| reader.find_file(l));
+
+    let mut info = PackageConnection {
+        index,
+        name: pref.description.clone(),
+        address: pref.host.clone(),
+        port: pref.port,
+        connection_type: None,
+        enabled: pref.enabled,
+        certificate: certificate.map(str::to_string),
+        truststore: truststore.map(str::to_string),
+        unsupported_reason: None,
+    };
+    let mut bundle = None;
+
+    match pref.protocol {
+        StreamProtocol::Tcp => info.connection_type = Some(ConnectionType::TcpClient),
+        StreamProtocol::Ssl => {
+            info.connection_type = Some(ConnectionType::TlsClient);
+            match load_bundle(reader, pref, certificate, truststore) {
+                Ok(b) => bundle = Some(b),
+                Err(reason) => info.unsupported_reason = Some(reason),
+            }
+        }
+        StreamProtocol::Udp | StreamProtocol::Quic => {
+            info.unsupported_reason =
+                Some(format!("{:?} streams are not supported", pref.protocol));
+        }
+    }
+
+    PackageStream { info, bundle }
+}
+
+/// Client certificate and trust store for a TLS stream
+fn load_bundle(
+    reader: &DataPackageReader,
+    pref: &StreamPreference,
+    certificate: Option<&str>,
+    truststore: Option<&str>,
+) -> Result<CertificateBundle, String> {
+    let Some(certificate) = certificate else {
+        return Err(if pref.enroll_for_certificate {
+            "Server issues client certificates by enrollment; enroll first".to_string()
+        } else {
+            "No client certificate in package".to_string()
+        });
+    };
+
+    let data = reader.read_file(certificate).map_err(|e| e.to_string())?;
+    let bundle = CertificateBundle::from_pkcs12(&data, Some(pref.client_password()))
+        .map_err(|e| format!("Failed to load {}: {}", certificate, e))?;
+
+    match truststore {
+        Some(truststore) => {
+            let data = reader.read_file(truststore).map_err(|e| e.to_string())?;
+            bundle
+                .with_truststore(&data, Some(pref.ca_password()))
+                .map_err(|e| format!("Failed to load {}: {}", truststore, e))
+        }
+        None => Ok(bundle),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use omnitak_cert::generator::{CaConfig, ClientCertConfig, GeneratedCa};
+    use omnitak_datapackage::{ContentType, DataPackageBuilder};
+
+    const PREFS: &str = r#"<preferences>
+  <preference version="1" name="cot_streams">
+    <entry key="count" class="class java.lang.Integer">3</entry>
+    <entry key="description0" class="class java.lang.String">Primary</entry>
+    <entry key="connectString0" class="class java.lang.String">tak.example.com:8089:ssl</entry>
+    <entry key="caLocation0" class="class java.lang.String">cert/truststore.p12</entry>
+    <entry key="certificateLocation0" class="class java.lang.String">cert/viper.p12</entry>
+    <entry key="connectString1" class="class java.lang.String">10.0.0.5:8087:tcp</entry>
+    <entry key="connectString2" class="class java.lang.String">239.2.3.1:6969:udp</entry>
+  </preference>
+</preferences>"#;
+
+    #[test]
+    fn test_package_streams() {
+        let ca = GeneratedCa::generate(&CaConfig::default()).unwrap();
+        let client = ca
+            .issue_client_cert(&ClientCertConfig::new("viper"))
+            .unwrap();
+        let ca_der = rustls_pemfile::certs(&mut ca.cert_pem.as_bytes())
+            .next()
+            .unwrap()
+            .unwrap();
+        let truststore = p12::PFX::new(&ca_der, &[], None, "atakatak", "ca")
+            .unwrap()
+            .to_der();
+        let package = DataPackageBuilder::new("server.zip")
+            .add_bytes("truststore.p12", truststore, ContentType::Certificate)
+            .unwrap()
+            .add_bytes(
+                "viper.p12",
+                client.to_pkcs12("atakatak").unwrap(),
+                ContentType::Certificate,
+            )
+            .unwrap()
+            .add_bytes(
+                "server.pref",
+                PREFS.as_bytes().to_vec(),
+                ContentType::Preferences,
+            )
+            .unwrap()
+            .build_to_memory()
+            .unwrap();
+
+        let reader = DataPackageReader::from_bytes(package).unwrap();
+        let streams: Vec<_> = reader
+            .stream_preferences()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .map(|(i, pref)| package_stream(&reader, i, pref))
+            .collect();
+
+        let tls = &streams[0];
+        assert_eq!(tls.info.connection_type, Some(ConnectionType::TlsClient));
+        assert_eq!(tls.info.unsupported_reason, None);
+        assert_eq!(tls.info.certificate.as_deref(), Some("viper.p12"));
+        let bundle = tls.bundle.as_ref().unwrap();
+        assert_eq!(bundle.ca_certs.as_ref().unwrap()[0], ca_der);
+
+        assert_eq!(
+            streams[1].info.connection_type,
+            Some(ConnectionType::TcpClient)
+        );
+        assert!(streams[1].bundle.is_none());
+        assert!(streams[2].info.unsupported_reason.is_some());
+    }
+
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!(parse_selection(None).unwrap(), None);
+        assert_eq!(parse_selection(Some("0, 2")).unwrap(), Some(vec![0, 2]));
+        assert!(parse_selection(Some("0,x")).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+//! Duplicate statistics endpoint
+//!
+//! Shows, per source connection, how many of its messages the aggregator
+//! dropped as duplicates and which sources delivered them first. Sources
+//! with a high ratio are usually redundant feeds of the same picture.
+
+use axum::{Json, extract::State};
+use omnitak_pool::SourceDuplicates;
+
+use crate::auth::AuthUser;
+use crate::rest::{ApiError, ApiState};
+use crate::types::*;
+
+/// GET /api/v1/duplicates - Duplicate statistics per source connection
+#[utoipa::path(
+    get,
+    path = "/api/v1/duplicates",
+    responses(
+        (status = 200, description = "Duplicate statistics retrieved successfully", body = DuplicateReport),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+pub async fn get_duplicates(
+    State(state): State<ApiState>,
+    user: AuthUser,
+) -> Result<Json<DuplicateReport>, ApiError> {
+    let name = |source: &String| state.pool.get_connection(source).map(|c| c.name.clone());
+
+    let mut sources: Vec<SourceDuplicateInfo> = state
+        .aggregator
+        .duplicate_stats()
+        .into_iter()
+        // Tenant users only see their own namespace's connections
+        .filter(|stats| user.can_access(state.pool.tenant_of(&stats.source).as_deref()))
+        .map(|stats| {
+            let duplicate_ratio = stats.duplicate_ratio();
+            let SourceDuplicates {
+                source,
+                unique,
+                duplicates,
+                repeats,
+                first_seen_from,
+            } = stats;
+
+            let mut first_seen_from: Vec<DuplicateOrigin> = first_seen_from
+                .into_iter()
+                .map(|(origin, count)| DuplicateOrigin {
+                    name: name(&origin),
+                    source: origin,
+                    count,
+                })
+                .collect();
+            first_seen_from.sort_by(|a, b| b.count.cmp(&a.count).then(a.source.cmp(&b.source)));
+
+            SourceDuplicateInfo {
+                name: name(&source),
+                source,
+                unique,
+                duplicates,
+                repeats,
+                duplicate_ratio,
+                first_seen_from,
+            }
+        })
+        .collect();
+    sources.sort_by(|a, b| b.duplicate_ratio.total_cmp(&a.duplicate_ratio));
+
+    Ok(Json(DuplicateReport {
+        total: sources.len(),
+        sources,
+    }))
+}
@@ -0,0 +1,185 @@
+//! Emergency beacon endpoints
+//!
+//! Lists beacons detected by the aggregator and lets operators acknowledge
+//! and clear them. State changes are also pushed to WebSocket clients as
+//! `emergency` system events. A beacon belongs to the tenant of the
+//! connection it was received from.
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use omnitak_pool::{Emergency, EmergencyError, EmergencyState};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::auth::{AuthUser, RequireEmergenciesWrite};
+use crate::middleware::ClientIp;
+use crate::rest::{ApiError, ApiState};
+use crate::types::*;
+
+impl From<EmergencyError> for ApiError {
+    fn from(e: EmergencyError) -> Self {
+        match e {
+            EmergencyError::NotFound(_) => ApiError::NotFound(e.to_string()),
+            EmergencyError::Closed(_) => ApiError::BadRequest(e.to_string()),
+        }
+    }
+}
+
+/// Whether `user` may see a beacon
+fn can_see(state: &ApiState, user: &AuthUser, emergency: &Emergency) -> bool {
+    user.can_access(state.pool.tenant_of(&emergency.source).as_deref())
+}
+
+/// Fail with not found unless `user` may see the beacon, as for beacons that
+/// don't exist
+fn check_access(state: &ApiState, user: &AuthUser, uid: &str) -> Result<(), ApiError> {
+    match state.emergencies.get(uid) {
+        Some(emergency) if can_see(state, user, &emergency) => Ok(()),
+        _ => Err(EmergencyError::NotFound(uid.to_string()).into()),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmergencyQuery {
+    /// Only list active and acknowledged beacons
+    #[serde(default)]
+    pub open_only: bool,
+}
+
+/// GET /api/v1/emergencies - List emergency beacons
+#[utoipa::path(
+    get,
+    path = "/api/v1/emergencies",
+    params(
+        ("open_only" = Option<bool>, Query, description = "Only list active and acknowledged beacons")
+    ),
+    responses(
+        (status = 200, description = "Emergencies retrieved successfully", body = EmergencyList),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+pub async fn list_emergencies(
+    State(state): State<ApiState>,
+    Query(query): Query<EmergencyQuery>,
+    user: AuthUser,
+) -> Result<Json<EmergencyList>, ApiError> {
+    let visible: Vec<Emergency> = state
+        .emergencies
+        .list()
+        .into_iter()
+        .filter(|e| can_see(&state, &user, e))
+        .collect();
+    let active = visible
+        .iter()
+        .filter(|e| e.state == EmergencyState::Active)
+        .count();
+    let emergencies: Vec<EmergencyInfo> = visible
+        .into_iter()
+        .filter(|e| !query.open_only || e.state.is_open())
+        .map(EmergencyInfo::from)
+        .collect();
+
+    Ok(Json(EmergencyList {
+        active,
+        total: emergencies.len(),
+        emergencies,
+    }))
+}
+
+/// POST /api/v1/emergencies/:uid/acknowledge - Acknowledge a beacon
+#[utoipa::path(
+    post,
+    path = "/api/v1/emergencies/{uid}/acknowledge",
+    params(
+        ("uid" = String, Path, description = "Beacon UID")
+    ),
+    responses(
+        (status = 200, description = "Emergency acknowledged", body = EmergencyInfo),
+        (status = 400, description = "Emergency already closed", body = ErrorResponse),
+        (status = 404, description = "Emergency not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires emergencies:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["emergencies:write"]),
+        ("api_key" = ["emergencies:write"])
+    )
+)]
+pub async fn acknowledge_emergency(
+    State(state): State<ApiState>,
+    Path(uid): Path<String>,
+    RequireEmergenciesWrite(user): RequireEmergenciesWrite,
+    ClientIp(client_ip): ClientIp,
+) -> Result<Json<EmergencyInfo>, ApiError> {
+    let operator = user
+        .user_id
+        .clone()
+        .unwrap_or_else(|| "api_key".to_string());
+    check_access(&state, &user, &uid)?;
+    let emergency = state.emergencies.acknowledge(&uid, &operator)?;
+
+    info!(uid = %uid, operator = %operator, "Emergency acknowledged");
+    state.audit_logger.log(
+        operator.clone(),
+        user.role,
+        "acknowledge_emergency".to_string(),
+        format!("/api/v1/emergencies/{}/acknowledge", uid),
+        serde_json::json!({"uid": uid}),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(Json(emergency.into()))
+}
+
+/// POST /api/v1/emergencies/:uid/clear - Clear a beacon
+#[utoipa::path(
+    post,
+    path = "/api/v1/emergencies/{uid}/clear",
+    params(
+        ("uid" = String, Path, description = "Beacon UID")
+    ),
+    responses(
+        (status = 200, description = "Emergency cleared", body = EmergencyInfo),
+        (status = 400, description = "Emergency already closed", body = ErrorResponse),
+        (status = 404, description = "Emergency not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires emergencies:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["emergencies:write"]),
+        ("api_key" = ["emergencies:write"])
+    )
+)]
+pub async fn clear_emergency(
+    State(state): State<ApiState>,
+    Path(uid): Path<String>,
+    RequireEmergenciesWrite(user): RequireEmergenciesWrite,
+    ClientIp(client_ip): ClientIp,
+) -> Result<Json<EmergencyInfo>, ApiError> {
+    let operator = user
+        .user_id
+        .clone()
+        .unwrap_or_else(|| "api_key".to_string());
+    check_access(&state, &user, &uid)?;
+    let emergency = state.emergencies.clear(&uid, &operator)?;
+
+    info!(uid = %uid, operator = %operator, "Emergency cleared");
+    state.audit_logger.log(
+        operator.clone(),
+        user.role,
+        "clear_emergency".to_string(),
+        format!("/api/v1/emergencies/{}/clear", uid),
+        serde_json::json!({"uid": uid}),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(Json(emergency.into()))
+}
@@ -4,6 +4,9 @@
 //! 1. Admin creates enrollment tokens
 //! 2. Users download data packages using tokens
 //! 3. Data packages contain certificates + server configuration
+//!
+//! Admins can also download a ready-made package for any of OmniTAK's
+//! inbound listeners, to onboard a device with a single file.
 
 use axum::{
     Json, Router,
@@ -24,8 +27,8 @@ use tokio::sync::RwLock;
 use tracing::{info, warn, error};
 use validator::Validate;
 
-use crate::auth::RequireAdmin;
-use crate::middleware::AuditLogger;
+use crate::auth::RequireEnrollmentManage;
+use crate::middleware::{AuditLogger, ClientIp};
 use crate::rest::ApiError;
 use crate::types::*;
 
@@ -49,6 +52,21 @@ pub struct EnrollmentState {
     /// CA storage path (for persistence)
     pub ca_cert_path: Option<std::path::PathBuf>,
     pub ca_key_path: Option<std::path::PathBuf>,
+    /// Inbound listeners onboarding packages can be built for
+    pub listeners: Arc<Vec<ListenerEndpoint>>,
+}
+
+/// An OmniTAK inbound listener
+#[derive(Debug, Clone)]
+pub struct ListenerEndpoint {
+    pub id: String,
+    pub port: u16,
+    pub tls: bool,
+    pub client_auth: bool,
+    /// Server certificate (PEM) of a TLS listener. The last certificate in
+    /// the file, the CA when a chain is bundled, goes in the trust store;
+    /// without it the enrollment CA is trusted.
+    pub cert_path: Option<std::path::PathBuf>,
 }
 
 impl EnrollmentState {
@@ -62,6 +80,7 @@ impl EnrollmentState {
             audit_logger,
             ca_cert_path: None,
             ca_key_path: None,
+            listeners: Arc::new(Vec::new()),
         }
     }
 
@@ -121,6 +140,11 @@ pub fn create_enrollment_router(state: EnrollmentState) -> Router {
         .route("/api/v1/enrollment/tokens/{id}", delete(delete_token))
         .route("/api/v1/enrollment/config", get(get_server_config))
         .route("/api/v1/enrollment/config", post(update_server_config))
+        .route("/api/v1/enrollment/listeners", get(list_listeners))
+        .route(
+            "/api/v1/enrollment/listeners/{id}/datapackage",
+            get(download_listener_package),
+        )
         .with_state(state)
 }
 
@@ -128,18 +152,39 @@ pub fn create_enrollment_router(state: EnrollmentState) -> Router {
 // Query Parameters
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
 pub struct DatapackageQuery {
     /// Enrollment token
     pub token: String,
 }
 
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct ListenerPackageQuery {
+    /// Callsign and client certificate name (default: omnitak-<listener>)
+    pub username: Option<String>,
+    /// Host the device connects to (default: the enrollment server host)
+    pub host: Option<String>,
+    /// Include a freshly issued client certificate (default: when the
+    /// listener requires one)
+    pub client_cert: Option<bool>,
+}
+
 // ============================================================================
 // Endpoints
 // ============================================================================
 
 /// GET /api/v1/enrollment/datapackage?token=xxx
 /// Download a data package using an enrollment token
+#[utoipa::path(
+    get,
+    path = "/api/v1/enrollment/datapackage",
+    params(DatapackageQuery),
+    responses(
+        (status = 200, description = "Data package with a new client certificate", content_type = "application/zip", body = Vec<u8>),
+        (status = 401, description = "Invalid, expired or used up token", body = ErrorResponse),
+        (status = 500, description = "Enrollment CA not initialized or package build failed", body = ErrorResponse)
+    )
+)]
 async fn download_datapackage(
     State(state): State<EnrollmentState>,
     Query(query): Query<DatapackageQuery>,
@@ -209,10 +254,23 @@ async fn download_datapackage(
 }
 
 /// GET /api/v1/enrollment/status
-/// Get enrollment service status (admin only)
+/// Get enrollment service status (requires enrollment:manage)
+#[utoipa::path(
+    get,
+    path = "/api/v1/enrollment/status",
+    responses(
+        (status = 200, description = "Enrollment status", body = EnrollmentStatus),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires enrollment:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["enrollment:manage"]),
+        ("api_key" = ["enrollment:manage"])
+    )
+)]
 async fn get_enrollment_status(
     State(state): State<EnrollmentState>,
-    RequireAdmin(_user): RequireAdmin,
+    RequireEnrollmentManage(_user): RequireEnrollmentManage,
 ) -> Result<Json<EnrollmentStatus>, ApiError> {
     let ca_lock = state.ca.read().await;
     let ca_info = if let Some(ca) = ca_lock.as_ref() {
@@ -258,10 +316,23 @@ fn parse_ca_info(cert_pem: &str) -> Result<CaInfo, anyhow::Error> {
 }
 
 /// GET /api/v1/enrollment/tokens
-/// List all enrollment tokens (admin only)
+/// List all enrollment tokens (requires enrollment:manage)
+#[utoipa::path(
+    get,
+    path = "/api/v1/enrollment/tokens",
+    responses(
+        (status = 200, description = "Enrollment tokens", body = EnrollmentTokenList),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires enrollment:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["enrollment:manage"]),
+        ("api_key" = ["enrollment:manage"])
+    )
+)]
 async fn list_tokens(
     State(state): State<EnrollmentState>,
-    RequireAdmin(_user): RequireAdmin,
+    RequireEnrollmentManage(_user): RequireEnrollmentManage,
 ) -> Result<Json<EnrollmentTokenList>, ApiError> {
     let tokens: Vec<EnrollmentTokenInfo> = state.tokens
         .iter()
@@ -286,10 +357,26 @@ async fn list_tokens(
 }
 
 /// POST /api/v1/enrollment/tokens
-/// Create a new enrollment token (admin only)
+/// Create a new enrollment token (requires enrollment:manage)
+#[utoipa::path(
+    post,
+    path = "/api/v1/enrollment/tokens",
+    request_body = CreateEnrollmentTokenRequest,
+    responses(
+        (status = 201, description = "Token created", body = CreateEnrollmentTokenResponse),
+        (status = 400, description = "Invalid request or enrollment CA not initialized", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires enrollment:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["enrollment:manage"]),
+        ("api_key" = ["enrollment:manage"])
+    )
+)]
 async fn create_token(
     State(state): State<EnrollmentState>,
-    RequireAdmin(user): RequireAdmin,
+    RequireEnrollmentManage(user): RequireEnrollmentManage,
+    ClientIp(client_ip): ClientIp,
     Json(request): Json<CreateEnrollmentTokenRequest>,
 ) -> Result<(StatusCode, Json<CreateEnrollmentTokenResponse>), ApiError> {
     request.validate()?;
@@ -338,7 +425,7 @@ async fn create_token(
             "validity_hours": request.validity_hours,
             "max_uses": request.max_uses,
         }),
-        "0.0.0.0".to_string(),
+        client_ip.to_string(),
         true,
     );
 
@@ -356,11 +443,29 @@ async fn create_token(
 }
 
 /// DELETE /api/v1/enrollment/tokens/{id}
-/// Delete an enrollment token (admin only)
+/// Delete an enrollment token (requires enrollment:manage)
+#[utoipa::path(
+    delete,
+    path = "/api/v1/enrollment/tokens/{id}",
+    params(
+        ("id" = String, Path, description = "Token ID")
+    ),
+    responses(
+        (status = 200, description = "Token deleted", body = DeleteConnectionResponse),
+        (status = 404, description = "Token not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires enrollment:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["enrollment:manage"]),
+        ("api_key" = ["enrollment:manage"])
+    )
+)]
 async fn delete_token(
     State(state): State<EnrollmentState>,
     Path(id): Path<String>,
-    RequireAdmin(user): RequireAdmin,
+    RequireEnrollmentManage(user): RequireEnrollmentManage,
+    ClientIp(client_ip): ClientIp,
 ) -> Result<Json<DeleteConnectionResponse>, ApiError> {
     // Find token by ID
     let token_key = state.tokens
@@ -379,7 +484,7 @@ async fn delete_token(
                 "delete_enrollment_token".to_string(),
                 format!("/api/v1/enrollment/tokens/{}", id),
                 serde_json::json!({"token_id": id}),
-                "0.0.0.0".to_string(),
+                client_ip.to_string(),
                 true,
             );
 
@@ -392,20 +497,48 @@ async fn delete_token(
 }
 
 /// GET /api/v1/enrollment/config
-/// Get server connection configuration (admin only)
+/// Get server connection configuration (requires enrollment:manage)
+#[utoipa::path(
+    get,
+    path = "/api/v1/enrollment/config",
+    responses(
+        (status = 200, description = "Server connection put in data packages", body = ServerConnectionConfig),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires enrollment:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["enrollment:manage"]),
+        ("api_key" = ["enrollment:manage"])
+    )
+)]
 async fn get_server_config(
     State(state): State<EnrollmentState>,
-    RequireAdmin(_user): RequireAdmin,
+    RequireEnrollmentManage(_user): RequireEnrollmentManage,
 ) -> Result<Json<ServerConnectionConfig>, ApiError> {
     let config = state.server_config.read().await.clone();
     Ok(Json(config))
 }
 
 /// POST /api/v1/enrollment/config
-/// Update server connection configuration (admin only)
+/// Update server connection configuration (requires enrollment:manage)
+#[utoipa::path(
+    post,
+    path = "/api/v1/enrollment/config",
+    request_body = ServerConnectionConfig,
+    responses(
+        (status = 200, description = "Configuration updated", body = ServerConnectionConfig),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires enrollment:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["enrollment:manage"]),
+        ("api_key" = ["enrollment:manage"])
+    )
+)]
 async fn update_server_config(
     State(state): State<EnrollmentState>,
-    RequireAdmin(user): RequireAdmin,
+    RequireEnrollmentManage(user): RequireEnrollmentManage,
+    ClientIp(client_ip): ClientIp,
     Json(config): Json<ServerConnectionConfig>,
 ) -> Result<Json<ServerConnectionConfig>, ApiError> {
     state.set_server_config(config.clone()).await;
@@ -418,17 +551,167 @@ async fn update_server_config(
         "update_enrollment_config".to_string(),
         "/api/v1/enrollment/config".to_string(),
         serde_json::to_value(&config).unwrap(),
-        "0.0.0.0".to_string(),
+        client_ip.to_string(),
         true,
     );
 
     Ok(Json(config))
 }
 
+/// GET /api/v1/enrollment/listeners
+/// List inbound listeners onboarding packages can be built for (requires enrollment:manage)
+#[utoipa::path(
+    get,
+    path = "/api/v1/enrollment/listeners",
+    responses(
+        (status = 200, description = "Inbound listeners", body = Vec<ListenerEndpointInfo>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires enrollment:manage", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["enrollment:manage"]),
+        ("api_key" = ["enrollment:manage"])
+    )
+)]
+async fn list_listeners(
+    State(state): State<EnrollmentState>,
+    RequireEnrollmentManage(_user): RequireEnrollmentManage,
+) -> Json<Vec<ListenerEndpointInfo>> {
+    Json(
+        state
+            .listeners
+            .iter()
+            .map(|l| ListenerEndpointInfo {
+                id: l.id.clone(),
+                port: l.port,
+                tls: l.tls,
+                client_auth: l.client_auth,
+            })
+            .collect(),
+    )
+}
+
+/// GET /api/v1/enrollment/listeners/{id}/datapackage
+/// Download a data package that connects a device to an inbound listener
+/// (requires enrollment:manage). The client certificate is issued by the enrollment CA, so
+/// the listener must trust that CA for client authentication.
+#[utoipa::path(
+    get,
+    path = "/api/v1/enrollment/listeners/{id}/datapackage",
+    params(
+        ("id" = String, Path, description = "Listener ID"),
+        ListenerPackageQuery
+    ),
+    responses(
+        (status = 200, description = "Onboarding data package", content_type = "application/zip", body = Vec<u8>),
+        (status = 404, description = "Listener not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires enrollment:manage", body = ErrorResponse),
+        (status = 500, description = "Enrollment CA not initialized or package build failed", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["enrollment:manage"]),
+        ("api_key" = ["enrollment:manage"])
+    )
+)]
+async fn download_listener_package(
+    State(state): State<EnrollmentState>,
+    RequireEnrollmentManage(user): RequireEnrollmentManage,
+    ClientIp(client_ip): ClientIp,
+    Path(id): Path<String>,
+    Query(query): Query<ListenerPackageQuery>,
+) -> Result<Response, ApiError> {
+    let listener = state
+        .listeners
+        .iter()
+        .find(|l| l.id == id)
+        .cloned()
+        .ok_or_else(|| ApiError::NotFound(format!("Listener {} not found", id)))?;
+
+    let server_config = state.server_config.read().await.clone();
+    let username = query
+        .username
+        .unwrap_or_else(|| format!("omnitak-{}", listener.id));
+    let connection = ServerConnectionConfig {
+        host: query.host.unwrap_or(server_config.host),
+        streaming_port: listener.port,
+        api_port: server_config.api_port,
+        description: Some(format!("OmniTAK {}", listener.id)),
+        use_tls: listener.tls,
+    };
+
+    let ca_missing = || ApiError::InternalError("Enrollment CA not initialized".to_string());
+    let (truststore, client_cert) = if listener.tls {
+        let ca_lock = state.ca.read().await;
+        let trusted_pem = match &listener.cert_path {
+            Some(path) => tokio::fs::read_to_string(path).await.map_err(|e| {
+                ApiError::InternalError(format!("Failed to read {}: {}", path.display(), e))
+            })?,
+            None => ca_lock
+                .as_ref()
+                .map(|ca| ca.cert_pem.clone())
+                .ok_or_else(ca_missing)?,
+        };
+        let truststore = create_truststore(&trusted_pem, P12_PASSWORD)
+            .map_err(|e| ApiError::InternalError(format!("Failed to build trust store: {}", e)))?;
+
+        let client_cert = if query.client_cert.unwrap_or(listener.client_auth) {
+            let ca = ca_lock.as_ref().ok_or_else(ca_missing)?;
+            let config = ClientCertConfig::new(&username).with_validity(365);
+            Some(ca.issue_client_cert(&config).map_err(|e| {
+                error!("Failed to issue client certificate: {}", e);
+                ApiError::InternalError("Failed to generate certificate".to_string())
+            })?)
+        } else {
+            None
+        };
+        (Some(truststore), client_cert)
+    } else {
+        (None, None)
+    };
+
+    let client_cert = client_cert.as_ref();
+    let data_package = build_listener_package(&connection, &username, truststore, client_cert)
+        .map_err(|e| {
+            error!("Failed to build data package: {}", e);
+            ApiError::InternalError("Failed to build data package".to_string())
+        })?;
+
+    info!(
+        "Onboarding package for listener {} generated for: {}",
+        listener.id, username
+    );
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "admin".to_string()),
+        user.role,
+        "download_listener_package".to_string(),
+        format!("/api/v1/enrollment/listeners/{}/datapackage", listener.id),
+        serde_json::json!({
+            "username": username,
+            "client_cert": client_cert.is_some(),
+        }),
+        client_ip.to_string(),
+        true,
+    );
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"omnitak-{}.zip\"", listener.id),
+        )
+        .body(Body::from(data_package))
+        .map_err(|e| ApiError::InternalError(format!("Response build error: {}", e)))
+}
+
 // ============================================================================
 // Data Package Building
 // ============================================================================
 
+/// Standard ATAK default password for bundled PKCS#12 files
+const P12_PASSWORD: &str = "atakatak";
+
 /// Build a TAK data package with client certificate and server configuration
 fn build_data_package(
     client_cert: &GeneratedClientCert,
@@ -436,11 +719,11 @@ fn build_data_package(
     username: &str,
 ) -> Result<Vec<u8>, anyhow::Error> {
     // Generate PKCS#12 with password
-    let p12_password = "atakatak"; // Standard ATAK default password
+    let p12_password = P12_PASSWORD;
     let p12_data = client_cert.to_pkcs12(p12_password)?;
 
     // Generate CA truststore (also as P12)
-    let ca_truststore = create_ca_truststore(&client_cert.ca_cert_pem, p12_password)?;
+    let ca_truststore = create_truststore(&client_cert.ca_cert_pem, p12_password)?;
 
     // Generate server preferences XML
     let prefs_xml = generate_atak_preferences(server_config, username);
@@ -468,30 +751,90 @@ fn build_data_package(
     Ok(package)
 }
 
-/// Create a CA truststore in PKCS#12 format
-fn create_ca_truststore(ca_cert_pem: &str, password: &str) -> Result<Vec<u8>, anyhow::Error> {
-    use p12::PFX;
-
-    // Parse CA certificate
-    let ca_cert_der = {
-        let mut reader = std::io::BufReader::new(ca_cert_pem.as_bytes());
-        let certs: Vec<_> = rustls_pemfile::certs(&mut reader)
-            .collect::<Result<Vec<_>, _>>()?;
-        certs.first()
-            .ok_or_else(|| anyhow::anyhow!("No CA certificate found"))?
-            .to_vec()
-    };
+/// Build a data package connecting to an inbound listener
+fn build_listener_package(
+    connection: &ServerConnectionConfig,
+    username: &str,
+    truststore: Option<Vec<u8>>,
+    client_cert: Option<&GeneratedClientCert>,
+) -> Result<Vec<u8>, anyhow::Error> {
+    let prefs_xml = generate_stream_preferences(
+        connection,
+        username,
+        truststore.is_some(),
+        client_cert.is_some(),
+    );
 
-    // Create truststore (certificate only, no private key)
-    // Note: For truststores, we create a special P12 with just the CA cert
-    let pfx = PFX::new(&ca_cert_der, &[], None, password, "OmniTAK-CA")
-        .ok_or_else(|| anyhow::anyhow!("Failed to create truststore PKCS#12"))?;
+    let mut builder = DataPackageBuilder::new(&format!("omnitak-{}", username));
+    if let Some(truststore) = truststore {
+        builder = builder.add_bytes(
+            "truststore-omnitak-ca.p12",
+            truststore,
+            ContentType::Certificate,
+        )?;
+    }
+    if let Some(client_cert) = client_cert {
+        builder = builder.add_bytes(
+            &format!("{}.p12", username),
+            client_cert.to_pkcs12(P12_PASSWORD)?,
+            ContentType::Certificate,
+        )?;
+    }
+
+    Ok(builder
+        .add_bytes(
+            "omnitak-server.pref",
+            prefs_xml.into_bytes(),
+            ContentType::Configuration,
+        )?
+        .on_receive_delete(false)
+        .build_to_memory()?)
+}
+
+/// Truststore holding the last certificate of a PEM file (the root of a
+/// bundled chain)
+fn create_truststore(pem: &str, password: &str) -> Result<Vec<u8>, anyhow::Error> {
+    let mut reader = std::io::BufReader::new(pem.as_bytes());
+    let certs: Vec<_> = rustls_pemfile::certs(&mut reader).collect::<Result<Vec<_>, _>>()?;
+    let cert_der = certs
+        .last()
+        .ok_or_else(|| anyhow::anyhow!("No certificate found"))?;
 
+    let pfx = p12::PFX::new(cert_der, &[], None, password, "OmniTAK-CA")
+        .ok_or_else(|| anyhow::anyhow!("Failed to create truststore PKCS#12"))?;
     Ok(pfx.to_der())
 }
 
 /// Generate ATAK preferences XML for server connection
 fn generate_atak_preferences(server_config: &ServerConnectionConfig, username: &str) -> String {
+    generate_stream_preferences(server_config, username, true, true)
+}
+
+/// ATAK preferences for one streaming connection, referencing the bundled
+/// trust store and client certificate when there are any
+fn generate_stream_preferences(
+    server_config: &ServerConnectionConfig,
+    username: &str,
+    truststore: bool,
+    client_cert: bool,
+) -> String {
+    let mut cert_entries = String::new();
+    if truststore {
+        cert_entries.push_str(
+            r#"
+        <entry key="caLocation0" class="class java.lang.String">cert/truststore-omnitak-ca.p12</entry>
+        <entry key="caPassword0" class="class java.lang.String">atakatak</entry>"#,
+        );
+    }
+    if client_cert {
+        cert_entries.push_str(&format!(
+            r#"
+        <entry key="certificateLocation0" class="class java.lang.String">cert/{username}.p12</entry>
+        <entry key="clientPassword0" class="class java.lang.String">atakatak</entry>"#,
+            username = username,
+        ));
+    }
+
     let protocol = if server_config.use_tls { "ssl" } else { "tcp" };
     let connect_string = format!(
         "{}:{}:{}",
@@ -506,12 +849,8 @@ fn generate_atak_preferences(server_config: &ServerConnectionConfig, username: &
         <entry key="count" class="class java.lang.Integer">1</entry>
         <entry key="description0" class="class java.lang.String">{description}</entry>
         <entry key="enabled0" class="class java.lang.Boolean">true</entry>
-        <entry key="connectString0" class="class java.lang.String">{connect_string}</entry>
-        <entry key="caLocation0" class="class java.lang.String">cert/truststore-omnitak-ca.p12</entry>
-        <entry key="caPassword0" class="class java.lang.String">atakatak</entry>
-        <entry key="certificateLocation0" class="class java.lang.String">cert/{username}.p12</entry>
-        <entry key="clientPassword0" class="class java.lang.String">atakatak</entry>
-        <entry key="useAuth0" class="class java.lang.Boolean">true</entry>
+        <entry key="connectString0" class="class java.lang.String">{connect_string}</entry>{cert_entries}
+        <entry key="useAuth0" class="class java.lang.Boolean">{client_cert}</entry>
         <entry key="enrollForCertificateWithTrust0" class="class java.lang.Boolean">false</entry>
     </preference>
     <preference version="1" name="com.atakmap.app_preferences">
@@ -521,6 +860,8 @@ fn generate_atak_preferences(server_config: &ServerConnectionConfig, username: &
 "#,
         description = server_config.description.as_deref().unwrap_or("OmniTAK Server"),
         connect_string = connect_string,
+        cert_entries = cert_entries,
+        client_cert = client_cert,
         username = username,
     )
 }
@@ -543,5 +884,25 @@ mod tests {
         assert!(prefs.contains("tak.example.com:8089:ssl"));
         assert!(prefs.contains("testuser"));
         assert!(prefs.contains("Test Server"));
+        assert!(prefs.contains("cert/testuser.p12"));
+    }
+
+    #[test]
+    fn test_listener_package_preferences() {
+        let config = ServerConnectionConfig {
+            host: "10.0.0.5".to_string(),
+            streaming_port: 8087,
+            api_port: 8443,
+            description: None,
+            use_tls: false,
+        };
+
+        let prefs = generate_stream_preferences(&config, "eud-1", false, false);
+        assert!(prefs.contains("10.0.0.5:8087:tcp"));
+        assert!(!prefs.contains("caLocation0"));
+        assert!(!prefs.contains("certificateLocation0"));
+
+        let package = build_listener_package(&config, "eud-1", None, None).unwrap();
+        assert!(!package.is_empty());
     }
 }
@@ -0,0 +1,200 @@
+//! FreeTAKServer endpoints
+//!
+//! Status and controls for connections configured as FreeTAKServer
+//! instances. Controls are forwarded to the FTS REST API as-is.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use tracing::info;
+use validator::Validate;
+
+use crate::auth::{AuthUser, RequireFtsWrite};
+use crate::fts::{FtsError, FtsStatus};
+use crate::middleware::ClientIp;
+use crate::rest::{ApiError, ApiState};
+use crate::types::*;
+
+impl From<FtsError> for ApiError {
+    fn from(e: FtsError) -> Self {
+        match e {
+            FtsError::NotFound(_) => ApiError::NotFound(e.to_string()),
+            FtsError::Request(_) => ApiError::InternalError(e.to_string()),
+        }
+    }
+}
+
+/// GET /api/v1/fts - Status of all FreeTAKServer connections
+#[utoipa::path(
+    get,
+    path = "/api/v1/fts",
+    responses(
+        (status = 200, description = "FreeTAKServer status", body = FtsStatusList),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+pub async fn list_fts_servers(
+    State(state): State<ApiState>,
+    _user: AuthUser,
+) -> Result<Json<FtsStatusList>, ApiError> {
+    let servers = state.fts.list();
+    Ok(Json(FtsStatusList {
+        total: servers.len(),
+        servers,
+    }))
+}
+
+/// GET /api/v1/fts/:connection_id - Status of one FreeTAKServer connection
+#[utoipa::path(
+    get,
+    path = "/api/v1/fts/{connection_id}",
+    params(
+        ("connection_id" = String, Path, description = "Connection ID")
+    ),
+    responses(
+        (status = 200, description = "FreeTAKServer status", body = FtsStatus),
+        (status = 404, description = "Not an FTS connection", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+pub async fn get_fts_server(
+    State(state): State<ApiState>,
+    Path(connection_id): Path<String>,
+    _user: AuthUser,
+) -> Result<Json<FtsStatus>, ApiError> {
+    state
+        .fts
+        .status(&connection_id)
+        .map(Json)
+        .ok_or_else(|| FtsError::NotFound(connection_id).into())
+}
+
+/// POST /api/v1/fts/:connection_id/refresh - Poll a FreeTAKServer now
+#[utoipa::path(
+    post,
+    path = "/api/v1/fts/{connection_id}/refresh",
+    params(
+        ("connection_id" = String, Path, description = "Connection ID")
+    ),
+    responses(
+        (status = 200, description = "Fresh FreeTAKServer status", body = FtsStatus),
+        (status = 404, description = "Not an FTS connection", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires fts:write", body = ErrorResponse),
+        (status = 500, description = "FTS REST API unreachable", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["fts:write"]),
+        ("api_key" = ["fts:write"])
+    )
+)]
+pub async fn refresh_fts_server(
+    State(state): State<ApiState>,
+    Path(connection_id): Path<String>,
+    RequireFtsWrite(_user): RequireFtsWrite,
+) -> Result<Json<FtsStatus>, ApiError> {
+    Ok(Json(state.fts.refresh(&connection_id).await?))
+}
+
+/// DELETE /api/v1/fts/:connection_id/emergencies/:uid - Delete an emergency on FTS
+#[utoipa::path(
+    delete,
+    path = "/api/v1/fts/{connection_id}/emergencies/{uid}",
+    params(
+        ("connection_id" = String, Path, description = "Connection ID"),
+        ("uid" = String, Path, description = "Emergency UID")
+    ),
+    responses(
+        (status = 204, description = "Emergency deleted"),
+        (status = 404, description = "Not an FTS connection", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires fts:write", body = ErrorResponse),
+        (status = 500, description = "FTS rejected the request", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["fts:write"]),
+        ("api_key" = ["fts:write"])
+    )
+)]
+pub async fn delete_fts_emergency(
+    State(state): State<ApiState>,
+    Path((connection_id, uid)): Path<(String, String)>,
+    RequireFtsWrite(user): RequireFtsWrite,
+    ClientIp(client_ip): ClientIp,
+) -> Result<StatusCode, ApiError> {
+    state.fts.delete_emergency(&connection_id, &uid).await?;
+
+    info!(connection = %connection_id, uid = %uid, "Deleted FTS emergency");
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "delete_fts_emergency".to_string(),
+        format!("/api/v1/fts/{}/emergencies/{}", connection_id, uid),
+        serde_json::json!({"connection_id": connection_id, "uid": uid}),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/v1/fts/:connection_id/chat - Broadcast chat to all FTS users
+#[utoipa::path(
+    post,
+    path = "/api/v1/fts/{connection_id}/chat",
+    params(
+        ("connection_id" = String, Path, description = "Connection ID")
+    ),
+    request_body = FtsChatRequest,
+    responses(
+        (status = 204, description = "Chat sent"),
+        (status = 400, description = "Invalid message", body = ErrorResponse),
+        (status = 404, description = "Not an FTS connection", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires fts:write", body = ErrorResponse),
+        (status = 500, description = "FTS rejected the request", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["fts:write"]),
+        ("api_key" = ["fts:write"])
+    )
+)]
+pub async fn send_fts_chat(
+    State(state): State<ApiState>,
+    Path(connection_id): Path<String>,
+    RequireFtsWrite(user): RequireFtsWrite,
+    ClientIp(client_ip): ClientIp,
+    Json(request): Json<FtsChatRequest>,
+) -> Result<StatusCode, ApiError> {
+    request.validate()?;
+    state
+        .fts
+        .send_chat(&connection_id, &request.sender, &request.message)
+        .await?;
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "send_fts_chat".to_string(),
+        format!("/api/v1/fts/{}/chat", connection_id),
+        serde_json::json!({
+            "connection_id": connection_id,
+            "sender": request.sender,
+            "message_length": request.message.len(),
+        }),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,27 @@
+//! Load balancer hint endpoint
+//!
+//! Unauthenticated like `/api/v1/health`, since load balancers probe it
+//! without credentials.
+
+use axum::{Json, extract::State, http::StatusCode};
+
+use crate::rest::ApiState;
+use crate::types::*;
+
+/// GET /api/v1/lb - Weighted health for load balancers
+#[utoipa::path(
+    get,
+    path = "/api/v1/lb",
+    responses(
+        (status = 200, description = "Node accepts new clients", body = LoadReport),
+        (status = 503, description = "Node is draining", body = LoadReport)
+    )
+)]
+pub async fn get_load(State(state): State<ApiState>) -> (StatusCode, Json<LoadReport>) {
+    let report = state.load.report();
+    let status = match report.state {
+        LoadState::Up => StatusCode::OK,
+        LoadState::Drain => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status, Json(report))
+}
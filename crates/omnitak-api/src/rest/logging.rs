@@ -0,0 +1,115 @@
+//! Log level endpoints
+//!
+//! Level changes take effect immediately and last until restart; they are
+//! not written back to the configuration file.
+
+use axum::{Json, extract::State};
+use std::net::IpAddr;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::auth::{AuthUser, RequireLoggingManage};
+use crate::logging::LogLevelControl;
+use crate::middleware::ClientIp;
+use crate::rest::{ApiError, ApiState};
+use crate::types::*;
+
+fn control(state: &ApiState) -> Result<&Arc<LogLevelControl>, ApiError> {
+    state
+        .log_control
+        .as_ref()
+        .ok_or_else(|| ApiError::NotFound("Runtime log level control not enabled".to_string()))
+}
+
+fn audit(state: &ApiState, user: AuthUser, action: &str, levels: &LogLevels, client_ip: IpAddr) {
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        action.to_string(),
+        "/api/v1/logging".to_string(),
+        serde_json::json!({"filter": levels.directives()}),
+        client_ip.to_string(),
+        true,
+    );
+}
+
+/// GET /api/v1/logging - Current and configured log levels (requires logging:manage)
+#[utoipa::path(
+    get,
+    path = "/api/v1/logging",
+    responses(
+        (status = 200, description = "Log levels", body = LogLevelStatus),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires logging:manage", body = ErrorResponse),
+        (status = 404, description = "Runtime log level control not enabled", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["logging:manage"]),
+        ("api_key" = ["logging:manage"])
+    )
+)]
+pub async fn get_log_levels(
+    State(state): State<ApiState>,
+    RequireLoggingManage(_user): RequireLoggingManage,
+) -> Result<Json<LogLevelStatus>, ApiError> {
+    Ok(Json(control(&state)?.status()))
+}
+
+/// PUT /api/v1/logging - Replace the log levels (requires logging:manage)
+#[utoipa::path(
+    put,
+    path = "/api/v1/logging",
+    request_body = LogLevels,
+    responses(
+        (status = 200, description = "Levels applied", body = LogLevelStatus),
+        (status = 400, description = "Invalid level or target", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires logging:manage", body = ErrorResponse),
+        (status = 404, description = "Runtime log level control not enabled", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["logging:manage"]),
+        ("api_key" = ["logging:manage"])
+    )
+)]
+pub async fn set_log_levels(
+    State(state): State<ApiState>,
+    RequireLoggingManage(user): RequireLoggingManage,
+    ClientIp(client_ip): ClientIp,
+    Json(levels): Json<LogLevels>,
+) -> Result<Json<LogLevelStatus>, ApiError> {
+    let status = control(&state)?.set(levels).map_err(ApiError::BadRequest)?;
+
+    info!(filter = %status.current.directives(), "Changed log levels");
+    audit(&state, user, "set_log_levels", &status.current, client_ip);
+
+    Ok(Json(status))
+}
+
+/// DELETE /api/v1/logging - Go back to the configured log levels (requires logging:manage)
+#[utoipa::path(
+    delete,
+    path = "/api/v1/logging",
+    responses(
+        (status = 200, description = "Configured levels restored", body = LogLevelStatus),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires logging:manage", body = ErrorResponse),
+        (status = 404, description = "Runtime log level control not enabled", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["logging:manage"]),
+        ("api_key" = ["logging:manage"])
+    )
+)]
+pub async fn reset_log_levels(
+    State(state): State<ApiState>,
+    RequireLoggingManage(user): RequireLoggingManage,
+    ClientIp(client_ip): ClientIp,
+) -> Result<Json<LogLevelStatus>, ApiError> {
+    let status = control(&state)?.reset().map_err(ApiError::InternalError)?;
+
+    info!(filter = %status.current.directives(), "Reset log levels");
+    audit(&state, user, "reset_log_levels", &status.current, client_ip);
+
+    Ok(Json(status))
+}
@@ -0,0 +1,480 @@
+//! TAK "Marti" data sync endpoints
+//!
+//! The subset of the TAK server sync API that ATAK and WinTAK use to share
+//! files and mission packages: upload, mission package query/upload, content
+//! download and keyword search. Packages are stored by content hash in an
+//! omnitak-datapackage [`PackageStore`].
+//!
+//! Every route takes API credentials (a bearer token or `X-API-Key`), and
+//! the uploads require `packages:write`. Uploads are limited to
+//! `max_upload_mb` each, and refused once the store holds `max_storage_mb`.
+
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::{DefaultBodyLimit, Multipart, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use omnitak_datapackage::{DataPackageError, PackageStore, PackageUpload, StoredPackage};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::auth::{AuthUser, RequirePackagesWrite};
+use crate::rest::ApiError;
+use crate::types::ErrorResponse;
+
+/// Marti sync configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MartiConfig {
+    /// Directory packages are stored in
+    #[serde(default = "default_storage_dir")]
+    pub storage_dir: PathBuf,
+
+    /// Largest accepted upload in megabytes
+    #[serde(default = "default_max_upload_mb")]
+    pub max_upload_mb: usize,
+
+    /// Most megabytes the store holds
+    #[serde(default = "default_max_storage_mb")]
+    pub max_storage_mb: u64,
+}
+
+fn default_storage_dir() -> PathBuf {
+    PathBuf::from("data/packages")
+}
+
+fn default_max_upload_mb() -> usize {
+    100
+}
+
+fn default_max_storage_mb() -> u64 {
+    10 * 1024
+}
+
+impl Default for MartiConfig {
+    fn default() -> Self {
+        Self {
+            storage_dir: default_storage_dir(),
+            max_upload_mb: default_max_upload_mb(),
+            max_storage_mb: default_max_storage_mb(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MartiState {
+    pub store: Arc<PackageStore>,
+    /// Whether content URLs handed to clients use https
+    pub tls: bool,
+}
+
+/// Keyword TAK clients tag mission packages with
+pub(crate) const MISSION_PACKAGE_KEYWORD: &str = "missionpackage";
+
+pub fn create_marti_router(state: MartiState, max_upload_bytes: usize) -> Router {
+    Router::new()
+        .route("/Marti/sync/upload", post(upload))
+        .route("/Marti/sync/missionupload", post(mission_upload))
+        .route("/Marti/sync/missionquery", get(mission_query))
+        .route("/Marti/sync/content", get(content))
+        .route("/Marti/sync/search", get(search))
+        .layer(DefaultBodyLimit::max(max_upload_bytes))
+        .with_state(state)
+}
+
+impl From<DataPackageError> for ApiError {
+    fn from(e: DataPackageError) -> Self {
+        match e {
+            DataPackageError::MissingFile(hash) => {
+                ApiError::NotFound(format!("No package with hash {}", hash))
+            }
+            e @ DataPackageError::QuotaExceeded { .. } => ApiError::PayloadTooLarge(e.to_string()),
+            e => ApiError::InternalError(e.to_string()),
+        }
+    }
+}
+
+// ============================================================================
+// Query Parameters
+// ============================================================================
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query, rename_all = "camelCase")]
+struct UploadQuery {
+    name: String,
+    #[serde(default)]
+    keywords: Option<String>,
+    #[serde(default)]
+    creator_uid: Option<String>,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query, rename_all = "camelCase")]
+struct MissionUploadQuery {
+    hash: String,
+    filename: String,
+    #[serde(default)]
+    creator_uid: Option<String>,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+struct HashQuery {
+    hash: String,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SearchQuery {
+    #[serde(default)]
+    keywords: Option<String>,
+}
+
+// ============================================================================
+// Responses
+// ============================================================================
+
+/// Package metadata as the TAK server reports it
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "PascalCase")]
+struct MartiPackage {
+    #[serde(rename = "UID")]
+    uid: String,
+    name: String,
+    hash: String,
+    primary_key: String,
+    submission_date_time: String,
+    submission_user: String,
+    creator_uid: String,
+    keywords: Vec<String>,
+    #[serde(rename = "MIMEType")]
+    mime_type: String,
+    size: u64,
+}
+
+impl From<StoredPackage> for MartiPackage {
+    fn from(p: StoredPackage) -> Self {
+        Self {
+            uid: p.hash.clone(),
+            primary_key: p.hash.clone(),
+            name: p.name,
+            hash: p.hash,
+            submission_date_time: p.submitted_at.to_rfc3339(),
+            submission_user: "anonymous".to_string(),
+            creator_uid: p.creator_uid.unwrap_or_default(),
+            keywords: p.keywords,
+            mime_type: p.mime_type,
+            size: p.size,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+struct SearchResults {
+    #[serde(rename = "resultCount")]
+    result_count: usize,
+    results: Vec<MartiPackage>,
+}
+
+// ============================================================================
+// Endpoints
+// ============================================================================
+
+/// POST /Marti/sync/upload?name=&keywords=&creatorUid=
+/// Store the request body as a file
+#[utoipa::path(
+    post,
+    path = "/Marti/sync/upload",
+    params(UploadQuery),
+    request_body(content = Vec<u8>, content_type = "application/octet-stream", description = "File contents"),
+    responses(
+        (status = 200, description = "File stored", body = MartiPackage),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires packages:write", body = ErrorResponse),
+        (status = 413, description = "Upload larger than max_upload_mb, or the store is full", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["packages:write"]),
+        ("api_key" = ["packages:write"])
+    )
+)]
+async fn upload(
+    State(state): State<MartiState>,
+    _user: RequirePackagesWrite,
+    Query(query): Query<UploadQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<MartiPackage>, ApiError> {
+    let mime_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+    let upload = PackageUpload {
+        name: query.name,
+        mime_type,
+        keywords: split_keywords(query.keywords.as_deref()),
+        creator_uid: query.creator_uid,
+    };
+
+    let package = put(&state, body, upload).await?;
+    Ok(Json(package.into()))
+}
+
+/// POST /Marti/sync/missionupload?hash=&filename=&creatorUid=
+/// Store a mission package sent as the multipart field `assetfile`
+#[utoipa::path(
+    post,
+    path = "/Marti/sync/missionupload",
+    params(MissionUploadQuery),
+    request_body(content = Vec<u8>, content_type = "multipart/form-data", description = "Mission package in the field assetfile"),
+    responses(
+        (status = 200, description = "Content URL of the stored package", content_type = "text/plain", body = String),
+        (status = 400, description = "Missing assetfile or contents do not match hash", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires packages:write", body = ErrorResponse),
+        (status = 413, description = "Upload larger than max_upload_mb, or the store is full", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["packages:write"]),
+        ("api_key" = ["packages:write"])
+    )
+)]
+async fn mission_upload(
+    State(state): State<MartiState>,
+    _user: RequirePackagesWrite,
+    Query(query): Query<MissionUploadQuery>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<String, ApiError> {
+    let mut data = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?
+    {
+        if field.name() == Some("assetfile") {
+            data = Some(
+                field
+                    .bytes()
+                    .await
+                    .map_err(|e| ApiError::BadRequest(e.to_string()))?,
+            );
+            break;
+        }
+    }
+    let data = data.ok_or_else(|| ApiError::BadRequest("Missing assetfile".to_string()))?;
+
+    let hash = omnitak_datapackage::store::hash_hex(&data);
+    if !hash.eq_ignore_ascii_case(&query.hash) {
+        warn!(expected = %query.hash, actual = %hash, "Mission package hash mismatch");
+        return Err(ApiError::BadRequest(
+            "Package contents do not match hash".to_string(),
+        ));
+    }
+
+    let upload = PackageUpload {
+        name: query.filename,
+        mime_type: "application/x-zip-compressed".to_string(),
+        keywords: vec![MISSION_PACKAGE_KEYWORD.to_string()],
+        creator_uid: query.creator_uid,
+    };
+    let package = put(&state, data, upload).await?;
+    Ok(content_url(&headers, state.tls, &package.hash))
+}
+
+/// GET /Marti/sync/missionquery?hash=
+/// Content URL of a stored package, so clients can skip uploading it again
+#[utoipa::path(
+    get,
+    path = "/Marti/sync/missionquery",
+    params(HashQuery),
+    responses(
+        (status = 200, description = "Content URL of the stored package", content_type = "text/plain", body = String),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "No package with this hash", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+async fn mission_query(
+    State(state): State<MartiState>,
+    _user: AuthUser,
+    Query(query): Query<HashQuery>,
+    headers: HeaderMap,
+) -> Result<String, ApiError> {
+    let package = state
+        .store
+        .get(&query.hash)
+        .ok_or_else(|| ApiError::NotFound(format!("No package with hash {}", query.hash)))?;
+    Ok(content_url(&headers, state.tls, &package.hash))
+}
+
+/// GET /Marti/sync/content?hash=
+/// Download a stored package
+#[utoipa::path(
+    get,
+    path = "/Marti/sync/content",
+    params(HashQuery),
+    responses(
+        (status = 200, description = "Package contents", content_type = "application/octet-stream", body = Vec<u8>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "No package with this hash", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+async fn content(
+    State(state): State<MartiState>,
+    _user: AuthUser,
+    Query(query): Query<HashQuery>,
+) -> Result<Response, ApiError> {
+    let store = state.store.clone();
+    let hash = query.hash.clone();
+    let data = tokio::task::spawn_blocking(move || store.read(&hash))
+        .await
+        .map_err(|e| ApiError::InternalError(e.to_string()))??;
+    let package = state
+        .store
+        .get(&query.hash)
+        .ok_or_else(|| ApiError::NotFound(format!("No package with hash {}", query.hash)))?;
+
+    let disposition = format!(
+        "attachment; filename=\"{}\"",
+        package.name.replace(['"', '\\', '\r', '\n'], "_")
+    );
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, package.mime_type),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        data,
+    )
+        .into_response())
+}
+
+/// GET /Marti/sync/search?keywords=&tool=
+/// Packages carrying all the given keywords
+#[utoipa::path(
+    get,
+    path = "/Marti/sync/search",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Packages carrying every keyword", body = SearchResults),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+async fn search(
+    State(state): State<MartiState>,
+    _user: AuthUser,
+    Query(query): Query<SearchQuery>,
+) -> Json<SearchResults> {
+    let keywords = split_keywords(query.keywords.as_deref());
+    let keywords: Vec<&str> = keywords.iter().map(String::as_str).collect();
+    let results: Vec<MartiPackage> = state
+        .store
+        .search(&keywords)
+        .into_iter()
+        .map(MartiPackage::from)
+        .collect();
+
+    Json(SearchResults {
+        result_count: results.len(),
+        results,
+    })
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+async fn put(
+    state: &MartiState,
+    data: Bytes,
+    upload: PackageUpload,
+) -> Result<StoredPackage, ApiError> {
+    let store = state.store.clone();
+    let package = tokio::task::spawn_blocking(move || store.put(&data, upload))
+        .await
+        .map_err(|e| ApiError::InternalError(e.to_string()))??;
+    info!(
+        hash = %package.hash,
+        name = %package.name,
+        size = package.size,
+        "Stored package"
+    );
+    Ok(package)
+}
+
+pub(crate) fn split_keywords(keywords: Option<&str>) -> Vec<String> {
+    keywords
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Download URL for `hash` on the host the client connected to
+fn content_url(headers: &HeaderMap, tls: bool, hash: &str) -> String {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("localhost");
+    let scheme = if tls { "https" } else { "http" };
+    format!("{}://{}/Marti/sync/content?hash={}", scheme, host, hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_search_results_format() {
+        let package = StoredPackage {
+            hash: "ab".repeat(32),
+            name: "route.zip".to_string(),
+            mime_type: "application/x-zip-compressed".to_string(),
+            size: 42,
+            keywords: split_keywords(Some("missionpackage, ")),
+            creator_uid: Some("ANDROID-1".to_string()),
+            submitted_at: Utc::now(),
+        };
+        let json = serde_json::to_value(SearchResults {
+            result_count: 1,
+            results: vec![package.into()],
+        })
+        .unwrap();
+
+        assert_eq!(json["resultCount"], 1);
+        let result = &json["results"][0];
+        assert_eq!(result["Hash"], "ab".repeat(32));
+        assert_eq!(result["MIMEType"], "application/x-zip-compressed");
+        assert_eq!(result["CreatorUid"], "ANDROID-1");
+        assert_eq!(result["Keywords"], serde_json::json!(["missionpackage"]));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, "10.0.0.5:8443".parse().unwrap());
+        assert_eq!(
+            content_url(&headers, true, "ab"),
+            "https://10.0.0.5:8443/Marti/sync/content?hash=ab"
+        );
+    }
+}
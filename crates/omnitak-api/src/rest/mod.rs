@@ -2,16 +2,43 @@
 
 pub mod plugins;
 pub mod enrollment;
-
-use crate::auth::{AuthService, AuthUser, RequireAdmin, RequireOperator};
-use crate::middleware::AuditLogger;
+pub mod certificates;
+pub mod config;
+pub mod datapackages;
+pub mod alerts;
+pub mod backup;
+pub mod bulk;
+pub mod client_access;
+pub mod duplicates;
+pub mod emergencies;
+pub mod fts;
+pub mod lb;
+pub mod logging;
+pub mod marti;
+pub mod packages;
+pub mod probe;
+pub mod quarantine;
+pub mod tap;
+pub mod tracks;
+
+use crate::auth::{
+    AuthService, AuthUser, RequireAuditRead, RequireConnectionsWrite, RequireCotSend,
+    RequireFiltersWrite, RequireUsersManage, TokenPair,
+};
+use crate::middleware::{AuditLogger, ClientIp};
+use crate::pagination::{Paginated, SortOrder, matches_search};
+use crate::rbac::Permission;
 use crate::types::*;
 use axum::{
     Json, Router,
-    extract::{ConnectInfo, Path, Query, State},
+    extract::{DefaultBodyLimit, OriginalUri, Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
 };
 use chrono::Utc;
 use omnitak_client::{
@@ -19,10 +46,12 @@ use omnitak_client::{
     tcp::{FramingMode, TcpClient, TcpClientConfig},
     tls::{TlsClient, TlsClientConfig},
 };
-use omnitak_pool::{ConnectionPool, FilterRule as PoolFilterRule, MessageDistributor, PoolMessage};
+use omnitak_pool::{
+    Connection, ConnectionPool, MessageAggregator, MessageDistributor, PoolMessage,
+    STATS_SAMPLE_INTERVAL,
+};
 use quick_xml;
 use serde::Deserialize;
-use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
@@ -30,6 +59,9 @@ use tracing::{error, info, warn};
 use uuid::Uuid;
 use validator::Validate;
 
+/// Source ID recorded for CoT injected through the API
+const INJECTED_SOURCE: &str = "api";
+
 // ============================================================================
 // Application State
 // ============================================================================
@@ -39,10 +71,39 @@ pub struct ApiState {
     pub auth_service: Arc<AuthService>,
     pub audit_logger: Arc<AuditLogger>,
     pub pool: Arc<ConnectionPool>,
+    /// Pool carrying the main traffic, when it is not `pool`
+    pub traffic_pool: Option<Arc<ConnectionPool>>,
+    /// Filter rules created through the API, applied to `pool`
+    pub filters: Arc<crate::filters::FilterStore>,
     pub distributor: Arc<MessageDistributor>,
+    pub aggregator: Arc<MessageAggregator>,
     pub connections: Arc<RwLock<Vec<ConnectionInfo>>>,
+    /// Requests behind the connections created through the API, for export
+    pub connection_requests: Arc<RwLock<std::collections::HashMap<Uuid, CreateConnectionRequest>>>,
     pub start_time: std::time::Instant,
     pub discovery: Option<Arc<omnitak_discovery::DiscoveryService>>,
+    pub certificates: Arc<certificates::CertificateStore>,
+    /// Package store the Marti sync routes serve, when they are enabled
+    pub packages: Option<Arc<omnitak_datapackage::PackageStore>>,
+    /// Largest package upload accepted, in bytes
+    pub max_package_bytes: u64,
+    pub time_sync: Arc<crate::time_sync::TimeMonitor>,
+    pub alerts: Arc<crate::alerts::AlertManager>,
+    pub client_access: Arc<crate::client_access::ClientAccessPolicy>,
+    pub emergencies: Arc<omnitak_pool::EmergencyTracker>,
+    pub fts: Arc<crate::fts::FtsManager>,
+    pub tracks: Arc<crate::tracks::TrackStore>,
+    /// Routing decisions of the distributor carrying the live traffic
+    pub tap: Arc<omnitak_pool::MessageTap>,
+    pub load: Arc<crate::lb::LoadMonitor>,
+    pub resources: Arc<crate::resources::ResourceMonitor>,
+    /// Prometheus registry, unless metrics are disabled
+    pub metrics: Option<Arc<omnitak_pool::MetricsRegistry>>,
+    pub plugin_metrics: Arc<omnitak_plugin_api::PluginMetrics>,
+    pub plugin_manager: Arc<RwLock<omnitak_plugin_api::PluginManager>>,
+    pub adb_monitor: Option<Arc<crate::adb::AdbMonitorState>>,
+    pub log_control: Option<Arc<crate::logging::LogLevelControl>>,
+    pub config_file: Option<std::path::PathBuf>,
 }
 
 // ============================================================================
@@ -53,36 +114,119 @@ pub fn create_rest_router(state: ApiState) -> Router {
     Router::new()
         // System endpoints
         .route("/api/v1/status", get(get_system_status))
+        .route("/api/v1/system/resources", get(get_system_resources))
         .route("/api/v1/health", get(health_check))
+        .route("/api/v1/lb", get(lb::get_load))
         // Connection management
         .route("/api/v1/connections", get(list_connections))
         .route("/api/v1/connections", post(create_connection))
+        .route("/api/v1/connections/bulk", post(bulk::create_connections))
+        .route("/api/v1/connections/test", post(probe::test_connection))
         .route("/api/v1/connections/{id}", get(get_connection))
         .route("/api/v1/connections/{id}", delete(delete_connection))
+        .route("/api/v1/connections/stats", get(get_all_connection_stats))
+        .route("/api/v1/connections/{id}/stats", get(get_connection_stats))
+        // Certificate store
+        .route("/api/v1/certificates", get(certificates::list_certificates))
+        .route("/api/v1/certificates", post(certificates::import_certificate))
+        .route("/api/v1/certificates/{id}", delete(certificates::delete_certificate))
+        // Uploads enforce their own size limits while streaming to disk
+        .route(
+            "/api/v1/certificates/upload",
+            post(certificates::upload_certificate).layer(DefaultBodyLimit::disable()),
+        )
+        .route(
+            "/api/v1/packages/upload",
+            post(packages::upload_package).layer(DefaultBodyLimit::disable()),
+        )
+        // Data package import
+        .route(
+            "/api/v1/datapackages/connections/preview",
+            post(datapackages::preview_connections),
+        )
+        .route("/api/v1/datapackages/connections", post(datapackages::import_connections))
+        .route("/api/v1/datapackages/validate", post(datapackages::validate_package))
         // Filter management
         .route("/api/v1/filters", get(list_filters))
         .route("/api/v1/filters", post(create_filter))
         .route("/api/v1/filters/{id}", get(get_filter))
         .route("/api/v1/filters/{id}", delete(delete_filter))
+        // Duplicate statistics
+        .route("/api/v1/duplicates", get(duplicates::get_duplicates))
+        // Strict validation quarantine
+        .route("/api/v1/quarantine", get(quarantine::get_quarantine))
+        .route("/api/v1/quarantine/{connection_id}", put(quarantine::set_strict_validation))
+        .route("/api/v1/quarantine/{connection_id}", delete(quarantine::clear_quarantine))
+        // Emergency beacons
+        .route("/api/v1/emergencies", get(emergencies::list_emergencies))
+        .route("/api/v1/emergencies/{uid}/acknowledge", post(emergencies::acknowledge_emergency))
+        .route("/api/v1/emergencies/{uid}/clear", post(emergencies::clear_emergency))
+        // FreeTAKServer integration
+        .route("/api/v1/fts", get(fts::list_fts_servers))
+        .route("/api/v1/fts/{connection_id}", get(fts::get_fts_server))
+        .route("/api/v1/fts/{connection_id}/refresh", post(fts::refresh_fts_server))
+        .route(
+            "/api/v1/fts/{connection_id}/emergencies/{uid}",
+            delete(fts::delete_fts_emergency),
+        )
+        .route("/api/v1/fts/{connection_id}/chat", post(fts::send_fts_chat))
+        // Track snapshot (deltas are on the WebSocket stream)
+        .route("/api/v1/tracks", get(tracks::list_tracks))
         // CoT message injection
         .route("/api/v1/cot/send", post(send_cot_message))
         // Metrics
         .route("/api/v1/metrics", get(get_metrics))
+        // Live traffic with routing decisions (traffic:tap)
+        .route("/api/v1/debug/tap", get(tap::tap_traffic))
         // Authentication
         .route("/api/v1/auth/login", post(login))
+        .route("/api/v1/auth/refresh", post(refresh_token))
+        .route("/api/v1/auth/logout", post(logout))
+        .route("/api/v1/auth/revoke", post(revoke_token))
         .route("/api/v1/auth/api-keys", post(create_api_key))
-        // Audit logs (admin only)
+        // Audit logs (audit:read)
         .route("/api/v1/audit", get(get_audit_logs))
-        // ADB integration (requires operator role)
+        // Alert channels (alerts:manage)
+        .route("/api/v1/alerts/channels", get(alerts::list_channels))
+        .route("/api/v1/alerts/channels", post(alerts::create_channel))
+        .route("/api/v1/alerts/channels/{name}", put(alerts::update_channel))
+        .route("/api/v1/alerts/channels/{name}", delete(alerts::delete_channel))
+        .route("/api/v1/alerts/test", post(alerts::send_test_alert))
+        // Client certificate authorization (client_access:manage)
+        .route("/api/v1/client-access/rules", get(client_access::list_rules))
+        .route("/api/v1/client-access/rules", post(client_access::create_rule))
+        .route("/api/v1/client-access/rules/{name}", put(client_access::update_rule))
+        .route("/api/v1/client-access/rules/{name}", delete(client_access::delete_rule))
+        .route("/api/v1/client-access/evaluate", post(client_access::evaluate))
+        // Runtime log levels (logging:manage)
+        .route("/api/v1/logging", get(logging::get_log_levels))
+        .route("/api/v1/logging", put(logging::set_log_levels))
+        .route("/api/v1/logging", delete(logging::reset_log_levels))
+        // Configuration export (system:manage)
+        .route("/api/v1/config/export", get(config::export_config))
+        // Backup and restore (system:manage)
+        .route("/api/v1/backup", get(backup::create_backup))
+        .route("/api/v1/backup/restore", post(backup::restore_backup))
+        // ADB integration (devices:write to change anything)
         .route("/api/v1/adb/devices", get(crate::adb::list_devices))
         .route("/api/v1/adb/pull-certs", post(crate::adb::pull_certificates))
+        .route("/api/v1/adb/pair", post(crate::adb::pair_device))
+        .route("/api/v1/adb/connect", post(crate::adb::connect_device))
+        .route("/api/v1/adb/disconnect", post(crate::adb::disconnect_device))
+        .route("/api/v1/adb/deploy", post(crate::adb::deploy_connection))
+        .route("/api/v1/adb/approved", get(crate::adb::list_approved_devices))
+        .route("/api/v1/adb/approved/{serial}", put(crate::adb::approve_device))
+        .route("/api/v1/adb/approved/{serial}", delete(crate::adb::revoke_device))
         // Discovery service routes (if enabled)
         .route("/api/v1/discovery/status", get(crate::discovery::get_discovery_status))
         .route("/api/v1/discovery/services", get(crate::discovery::list_discovered_services))
         .route("/api/v1/discovery/services/{id}", get(crate::discovery::get_discovered_service))
+        .route("/api/v1/discovery/services/{id}/adopt", post(crate::discovery::adopt_service))
         .route("/api/v1/discovery/refresh", post(crate::discovery::refresh_discovery))
         .route("/api/v1/discovery/tak-servers", get(crate::discovery::list_tak_servers))
         .route("/api/v1/discovery/atak-devices", get(crate::discovery::list_atak_devices))
+        .route("/api/v1/discovery/announcement", get(crate::discovery::get_announcement))
+        .route("/api/v1/discovery/announcement", put(crate::discovery::set_announcement))
         .with_state(state)
 }
 
@@ -123,7 +267,7 @@ async fn get_system_status(
     };
 
     // Get memory usage (current process)
-    let memory_usage_bytes = get_memory_usage();
+    let memory_usage_bytes = state.resources.sample().memory_rss_bytes;
 
     let status = SystemStatus {
         uptime_seconds,
@@ -131,51 +275,78 @@ async fn get_system_status(
         messages_processed,
         messages_per_second,
         memory_usage_bytes,
-        active_filters: 0, // TODO: Get from filter engine when available
+        active_filters: state.filters.active(),
         version: env!("CARGO_PKG_VERSION").to_string(),
         timestamp: Utc::now(),
+        time_sync: state.time_sync.info(),
     };
 
     Ok(Json(status))
 }
 
-// Helper function to get memory usage
-fn get_memory_usage() -> u64 {
-    // Read from /proc/self/statm for Linux
-    #[cfg(target_os = "linux")]
-    {
-        if let Ok(statm) = std::fs::read_to_string("/proc/self/statm") {
-            if let Some(pages) = statm.split_whitespace().nth(1) {
-                if let Ok(rss_pages) = pages.parse::<u64>() {
-                    // Convert pages to bytes (page size is typically 4096)
-                    return rss_pages * 4096;
-                }
-            }
-        }
-    }
-
-    // Fallback for non-Linux or if reading failed
-    0
+/// GET /api/v1/system/resources - Process CPU, memory, descriptor and task usage
+#[utoipa::path(
+    get,
+    path = "/api/v1/system/resources",
+    responses(
+        (status = 200, description = "Resource usage retrieved successfully", body = ResourceUsage),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+async fn get_system_resources(
+    State(state): State<ApiState>,
+    _user: AuthUser,
+) -> Result<Json<ResourceUsage>, ApiError> {
+    Ok(Json(state.resources.sample()))
 }
 
 /// GET /api/v1/health - Health check endpoint (no auth required)
-async fn health_check() -> impl IntoResponse {
-    Json(serde_json::json!({
-        "status": "healthy",
-        "timestamp": Utc::now(),
-    }))
+#[utoipa::path(
+    get,
+    path = "/api/v1/health",
+    responses(
+        (status = 200, description = "Server is up", body = HealthResponse)
+    )
+)]
+async fn health_check() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "healthy".to_string(),
+        timestamp: Utc::now(),
+    })
 }
 
 // ============================================================================
 // Connection Management Endpoints
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
-struct ListQuery {
+/// Query of `GET /api/v1/connections`
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ConnectionListQuery {
+    /// Connections to skip
     #[serde(default)]
     offset: usize,
+
+    /// Connections to return (default 100)
     #[serde(default = "default_limit")]
     limit: usize,
+
+    /// Sort key; unsorted connections keep the order they were added in
+    sort: Option<ConnectionSort>,
+
+    /// Sort direction (default asc)
+    #[serde(default)]
+    order: SortOrder,
+
+    /// Only connections with this status
+    status: Option<ConnectionStatus>,
+
+    /// Only connections whose name contains this, ignoring case
+    search: Option<String>,
 }
 
 fn default_limit() -> usize {
@@ -186,12 +357,13 @@ fn default_limit() -> usize {
 #[utoipa::path(
     get,
     path = "/api/v1/connections",
-    params(
-        ("offset" = Option<usize>, Query, description = "Pagination offset"),
-        ("limit" = Option<usize>, Query, description = "Pagination limit")
-    ),
+    params(ConnectionListQuery),
     responses(
-        (status = 200, description = "Connections list retrieved successfully", body = ConnectionList),
+        (status = 200, description = "Connections list retrieved successfully", body = ConnectionList,
+            headers(
+                ("X-Total-Count" = usize, description = "Matching connections across all pages"),
+                ("Link" = String, description = "first, prev, next and last pages")
+            )),
         (status = 401, description = "Unauthorized", body = ErrorResponse)
     ),
     security(
@@ -201,22 +373,48 @@ fn default_limit() -> usize {
 )]
 async fn list_connections(
     State(state): State<ApiState>,
-    Query(query): Query<ListQuery>,
-    _user: AuthUser,
-) -> Result<Json<ConnectionList>, ApiError> {
-    // Get connections from state
+    Query(query): Query<ConnectionListQuery>,
+    OriginalUri(uri): OriginalUri,
+    user: AuthUser,
+) -> Result<Paginated<ConnectionList>, ApiError> {
+    // Get the connections in the user's namespace
     let all_connections = state.connections.read().await;
-    let total = all_connections.len();
+    let mut visible: Vec<&ConnectionInfo> = all_connections
+        .iter()
+        .filter(|c| user.can_access(c.tenant.as_deref()))
+        .filter(|c| query.status.is_none_or(|status| c.status == status))
+        .filter(|c| matches_search(query.search.as_deref(), &[&c.name]))
+        .collect();
+    let total = visible.len();
+
+    if let Some(sort) = query.sort {
+        visible.sort_by(|a, b| {
+            let ordering = match sort {
+                ConnectionSort::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                ConnectionSort::Status => a.status.cmp(&b.status),
+                ConnectionSort::Traffic => {
+                    (a.bytes_sent + a.bytes_received).cmp(&(b.bytes_sent + b.bytes_received))
+                }
+            };
+            query.order.apply(ordering)
+        });
+    }
 
     // Apply pagination
-    let connections: Vec<ConnectionInfo> = all_connections
-        .iter()
+    let connections: Vec<ConnectionInfo> = visible
+        .into_iter()
         .skip(query.offset)
         .take(query.limit)
         .cloned()
         .collect();
 
-    Ok(Json(ConnectionList { total, connections }))
+    Ok(Paginated::new(
+        ConnectionList { total, connections },
+        &uri,
+        query.offset,
+        query.limit,
+        total,
+    ))
 }
 
 /// GET /api/v1/connections/:id - Get specific connection details
@@ -239,19 +437,106 @@ async fn list_connections(
 async fn get_connection(
     State(state): State<ApiState>,
     Path(id): Path<Uuid>,
-    _user: AuthUser,
+    user: AuthUser,
 ) -> Result<Json<ConnectionInfo>, ApiError> {
     // Get connection from state by ID
     let connections = state.connections.read().await;
     let connection = connections
         .iter()
-        .find(|c| c.id == id)
+        .find(|c| c.id == id && user.can_access(c.tenant.as_deref()))
         .cloned()
         .ok_or_else(|| ApiError::NotFound(format!("Connection {} not found", id)))?;
 
     Ok(Json(connection))
 }
 
+/// GET /api/v1/connections/:id/stats - Recent throughput and write latency
+#[utoipa::path(
+    get,
+    path = "/api/v1/connections/{id}/stats",
+    params(
+        ("id" = Uuid, Path, description = "Connection ID")
+    ),
+    responses(
+        (status = 200, description = "Connection stats retrieved successfully", body = ConnectionStats),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+async fn get_connection_stats(
+    State(state): State<ApiState>,
+    Path(id): Path<Uuid>,
+    user: AuthUser,
+) -> Result<Json<ConnectionStats>, ApiError> {
+    let connection = state
+        .pool
+        .get_connection(&id.to_string())
+        .filter(|c| user.can_access(state.pool.tenant_of(&c.id).as_deref()))
+        .ok_or_else(|| ApiError::NotFound(format!("Connection {} not found", id)))?;
+
+    Ok(Json(ConnectionStats {
+        id,
+        sample_interval_secs: STATS_SAMPLE_INTERVAL.as_secs(),
+        samples: stats_samples(&connection),
+        fts: state.fts.status(&id.to_string()),
+    }))
+}
+
+/// GET /api/v1/connections/stats - Recent samples of every connection
+#[utoipa::path(
+    get,
+    path = "/api/v1/connections/stats",
+    responses(
+        (status = 200, description = "Connection stats retrieved successfully", body = ConnectionStatsSnapshot),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+async fn get_all_connection_stats(
+    State(state): State<ApiState>,
+    user: AuthUser,
+) -> Result<Json<ConnectionStatsSnapshot>, ApiError> {
+    let mut connections = std::collections::BTreeMap::new();
+    for pool in state.traffic_pool.iter().chain([&state.pool]) {
+        for connection in pool.get_active_connections() {
+            if user.can_access(pool.tenant_of(&connection.id).as_deref()) {
+                connections
+                    .entry(connection.name.clone())
+                    .or_insert_with(|| stats_samples(&connection));
+            }
+        }
+    }
+
+    Ok(Json(ConnectionStatsSnapshot {
+        sample_interval_secs: STATS_SAMPLE_INTERVAL.as_secs(),
+        connections,
+    }))
+}
+
+/// Recent samples of a connection, oldest first
+fn stats_samples(connection: &Connection) -> Vec<ConnectionStatsSample> {
+    connection
+        .state
+        .history()
+        .into_iter()
+        .map(|sample| ConnectionStatsSample {
+            timestamp: chrono::DateTime::from_timestamp_millis(sample.timestamp_ms as i64)
+                .unwrap_or_else(Utc::now),
+            messages_sent_per_sec: sample.messages_sent_per_sec,
+            messages_received_per_sec: sample.messages_received_per_sec,
+            avg_write_latency_ms: sample.avg_write_latency_ms,
+            max_write_latency_ms: sample.max_write_latency_ms,
+        })
+        .collect()
+}
+
 /// POST /api/v1/connections - Create new connection
 #[utoipa::path(
     post,
@@ -261,20 +546,47 @@ async fn get_connection(
         (status = 201, description = "Connection created successfully", body = CreateConnectionResponse),
         (status = 400, description = "Invalid request", body = ErrorResponse),
         (status = 401, description = "Unauthorized", body = ErrorResponse),
-        (status = 403, description = "Forbidden - requires operator role", body = ErrorResponse)
+        (status = 403, description = "Forbidden - requires connections:write", body = ErrorResponse)
     ),
     security(
-        ("bearer_auth" = []),
-        ("api_key" = [])
+        ("bearer_auth" = ["connections:write"]),
+        ("api_key" = ["connections:write"])
     )
 )]
 async fn create_connection(
     State(state): State<ApiState>,
-    RequireOperator(user): RequireOperator,
-    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    RequireConnectionsWrite(user): RequireConnectionsWrite,
+    ClientIp(client_ip): ClientIp,
     Json(request): Json<CreateConnectionRequest>,
 ) -> Result<(StatusCode, Json<CreateConnectionResponse>), ApiError> {
-    // Validate request
+    let connection_id = open_connection(&state, &user, &request).await?;
+
+    // Audit log with actual client IP
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "create_connection".to_string(),
+        format!("/api/v1/connections/{}", connection_id),
+        serde_json::to_value(&request).unwrap(),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateConnectionResponse {
+            id: connection_id,
+            message: "Connection created successfully".to_string(),
+        }),
+    ))
+}
+
+/// Validate a connection request, add it to the pool and start its client
+pub(crate) async fn open_connection(
+    state: &ApiState,
+    user: &AuthUser,
+    request: &CreateConnectionRequest,
+) -> Result<Uuid, ApiError> {
     request.validate()?;
 
     let connection_id = Uuid::new_v4();
@@ -311,17 +623,28 @@ async fn create_connection(
 
     let pool_tx = connection.tx.clone();
     let pool_rx = connection.rx.clone();
+    let pool_state = Arc::clone(&connection.state);
 
-    // Add filter for this connection
-    state
-        .distributor
-        .add_filter(id_str.clone(), PoolFilterRule::AlwaysSend);
+    // Connections live in their creator's namespace
+    state.pool.set_tenant(id_str.clone(), user.tenant.clone());
+
+    // Apply the tenant's filters to this connection
+    state.distributor.set_filters(
+        id_str.clone(),
+        state
+            .filters
+            .connection_rules(user.tenant.as_deref(), &address_with_port),
+    );
 
     // Spawn client task based on connection type
     let address_clone = address_with_port.clone();
     let id_clone = id_str.clone();
     let auto_reconnect = request.auto_reconnect;
 
+    // Held until the info is stored so a client task can't report its
+    // status before there is an entry to update
+    let mut infos = state.connections.write().await;
+
     match request.connection_type {
         ConnectionType::TcpClient => {
             info!(id = %connection_id, "Creating TCP client");
@@ -333,13 +656,8 @@ async fn create_connection(
                     read_timeout: Duration::from_secs(30),
                     write_timeout: Duration::from_secs(10),
                     recv_buffer_size: 65536,
-                    reconnect: ReconnectConfig {
-                        enabled: auto_reconnect,
-                        max_attempts: Some(5),
-                        initial_backoff: Duration::from_secs(1),
-                        max_backoff: Duration::from_secs(60),
-                        backoff_multiplier: 2.0,
-                    },
+                    reconnect: reconnect_config(auto_reconnect, &request.reconnect),
+                    ..Default::default()
                 },
                 framing: FramingMode::Xml,
                 keepalive: true,
@@ -350,15 +668,30 @@ async fn create_connection(
             let mut client = TcpClient::new(config);
 
             // Spawn client task
+            let connections = Arc::clone(&state.connections);
             tokio::spawn(async move {
                 info!(id = %id_clone, "Connecting TCP client");
 
                 if let Err(e) = client.connect_only().await {
                     error!(id = %id_clone, error = %e, "Failed to connect TCP client");
+                    set_connection_status(
+                        &connections,
+                        connection_id,
+                        ConnectionStatus::Error,
+                        Some(e.to_string()),
+                    )
+                    .await;
                     return;
                 }
 
                 info!(id = %id_clone, address = %address_clone, "TCP client connected");
+                set_connection_status(
+                    &connections,
+                    connection_id,
+                    ConnectionStatus::Connected,
+                    None,
+                )
+                .await;
 
                 let client_arc = Arc::new(tokio::sync::Mutex::new(client));
                 let client_read = Arc::clone(&client_arc);
@@ -421,10 +754,12 @@ async fn create_connection(
                         match pool_rx.recv_async().await {
                             Ok(PoolMessage::Cot(data)) => {
                                 let mut client = client_write.lock().await;
+                                let started = std::time::Instant::now();
                                 if let Err(e) = client.write_frame_direct(&data).await {
                                     error!(id = %id_write, error = %e, "Failed to send to TAK server");
                                     break;
                                 }
+                                pool_state.record_write_latency(started.elapsed());
                             }
                             Ok(PoolMessage::Shutdown) => {
                                 info!(id = %id_write, "Shutdown signal received");
@@ -444,35 +779,30 @@ async fn create_connection(
                     _ = read_task => {}
                     _ = write_task => {}
                 }
+                set_connection_status(
+                    &connections,
+                    connection_id,
+                    ConnectionStatus::Disconnected,
+                    None,
+                )
+                .await;
             });
         }
         ConnectionType::TlsClient => {
             info!(id = %connection_id, "Creating TLS client");
 
-            // Validate TLS cert paths are provided
-            let cert_path = request.tls_cert_path.clone().ok_or_else(|| {
-                ApiError::BadRequest("TLS certificate path required for TLS connection".to_string())
-            })?;
-            let key_path = request.tls_key_path.clone().ok_or_else(|| {
-                ApiError::BadRequest("TLS key path required for TLS connection".to_string())
-            })?;
-
-            let mut client_config = TlsClientConfig::new(
-                std::path::PathBuf::from(cert_path),
-                std::path::PathBuf::from(key_path),
-            );
+            let mut client_config = tls_client_config(
+                state,
+                request.certificate_id,
+                request.tls_cert_path.as_deref(),
+                request.tls_key_path.as_deref(),
+            )?;
             client_config.base.server_addr = address_with_port.clone();
             client_config.base.connect_timeout = Duration::from_secs(10);
             client_config.base.read_timeout = Duration::from_secs(30);
             client_config.base.write_timeout = Duration::from_secs(10);
             client_config.base.recv_buffer_size = 65536;
-            client_config.base.reconnect = ReconnectConfig {
-                enabled: auto_reconnect,
-                max_attempts: Some(5),
-                initial_backoff: Duration::from_secs(1),
-                max_backoff: Duration::from_secs(60),
-                backoff_multiplier: 2.0,
-            };
+            client_config.base.reconnect = reconnect_config(auto_reconnect, &request.reconnect);
             client_config.verify_server = request.validate_certs;
 
             let mut client = TlsClient::new(client_config).map_err(|e| {
@@ -480,15 +810,30 @@ async fn create_connection(
             })?;
 
             // Spawn client task (similar pattern to TCP)
+            let connections = Arc::clone(&state.connections);
             tokio::spawn(async move {
                 info!(id = %id_clone, "Connecting TLS client");
 
                 if let Err(e) = client.connect_only().await {
                     error!(id = %id_clone, error = %e, "Failed to connect TLS client");
+                    set_connection_status(
+                        &connections,
+                        connection_id,
+                        ConnectionStatus::Error,
+                        Some(e.to_string()),
+                    )
+                    .await;
                     return;
                 }
 
                 info!(id = %id_clone, address = %address_clone, "TLS client connected");
+                set_connection_status(
+                    &connections,
+                    connection_id,
+                    ConnectionStatus::Connected,
+                    None,
+                )
+                .await;
 
                 let client_arc = Arc::new(tokio::sync::Mutex::new(client));
                 let client_read = Arc::clone(&client_arc);
@@ -553,10 +898,12 @@ async fn create_connection(
                         match pool_rx.recv_async().await {
                             Ok(PoolMessage::Cot(data)) => {
                                 let mut client = client_write.lock().await;
+                                let started = std::time::Instant::now();
                                 if let Err(e) = client.write_frame_direct(&data).await {
                                     error!(id = %id_write, error = %e, "Failed to send to TLS TAK server");
                                     break;
                                 }
+                                pool_state.record_write_latency(started.elapsed());
                             }
                             Ok(PoolMessage::Shutdown) => {
                                 info!(id = %id_write, "Shutdown signal received");
@@ -576,6 +923,13 @@ async fn create_connection(
                     _ = read_task => {}
                     _ = write_task => {}
                 }
+                set_connection_status(
+                    &connections,
+                    connection_id,
+                    ConnectionStatus::Disconnected,
+                    None,
+                )
+                .await;
             });
         }
         _ => {
@@ -601,30 +955,103 @@ async fn create_connection(
         connected_at: None,
         last_activity: None,
         error: None,
+        tenant: user.tenant.clone(),
     };
 
+    state
+        .connection_requests
+        .write()
+        .await
+        .insert(connection_id, request.clone());
+    infos.push(conn_info);
+
+    Ok(connection_id)
+}
+
+/// Record what a connection's client task is doing
+async fn set_connection_status(
+    connections: &RwLock<Vec<ConnectionInfo>>,
+    id: Uuid,
+    status: ConnectionStatus,
+    error: Option<String>,
+) {
+    let mut connections = connections.write().await;
+    let Some(info) = connections.iter_mut().find(|c| c.id == id) else {
+        // Deleted in the meantime
+        return;
+    };
+    if status == ConnectionStatus::Connected {
+        info.connected_at = Some(Utc::now());
+    }
+    info.status = status;
+    info.error = error;
+}
+
+/// TLS client settings for a stored certificate or certificate and key
+/// paths, preferring the stored certificate
+pub(crate) fn tls_client_config(
+    state: &ApiState,
+    certificate_id: Option<Uuid>,
+    cert_path: Option<&str>,
+    key_path: Option<&str>,
+) -> Result<TlsClientConfig, ApiError> {
+    if let Some(certificate_id) = certificate_id {
+        let bundle = state.certificates.get_bundle(&certificate_id).ok_or_else(|| {
+            ApiError::BadRequest(format!("Certificate {} not found", certificate_id))
+        })?;
+        return Ok(TlsClientConfig::from_bundle(bundle));
+    }
+
+    let cert_path = cert_path.ok_or_else(|| {
+        ApiError::BadRequest(
+            "TLS certificate path or certificate_id required for TLS connection".to_string(),
+        )
+    })?;
+    let key_path = key_path.ok_or_else(|| {
+        ApiError::BadRequest("TLS key path required for TLS connection".to_string())
+    })?;
+    Ok(TlsClientConfig::new(
+        std::path::PathBuf::from(cert_path),
+        std::path::PathBuf::from(key_path),
+    ))
+}
+
+/// Stop a connection and remove it from the pool, the distributor and the
+/// connection list
+pub(crate) async fn close_connection(state: &ApiState, id: Uuid) -> Result<(), ApiError> {
+    let id_str = id.to_string();
+
+    // Remove from connection pool
+    state.pool.remove_connection(&id_str).await.map_err(|e| {
+        error!(connection_id = %id, error = %e, "Failed to remove connection from pool");
+        ApiError::InternalError(format!("Failed to remove connection: {}", e))
+    })?;
+
+    // Remove from state tracking
     let mut connections = state.connections.write().await;
-    connections.push(conn_info);
+    let initial_len = connections.len();
+    connections.retain(|c| c.id != id);
+
+    if connections.len() == initial_len {
+        return Err(ApiError::NotFound(format!("Connection {} not found", id)));
+    }
     drop(connections);
+    state.connection_requests.write().await.remove(&id);
 
-    // Audit log with actual client IP
-    state.audit_logger.log(
-        user.user_id.unwrap_or_else(|| "api_key".to_string()),
-        user.role,
-        "create_connection".to_string(),
-        format!("/api/v1/connections/{}", connection_id),
-        serde_json::to_value(&request).unwrap(),
-        client_addr.ip().to_string(),
-        true,
-    );
+    // Remove filters
+    state.distributor.remove_filters(&id_str);
+    Ok(())
+}
 
-    Ok((
-        StatusCode::CREATED,
-        Json(CreateConnectionResponse {
-            id: connection_id,
-            message: "Connection created successfully".to_string(),
-        }),
-    ))
+/// Client reconnect settings for a connection request's backoff policy
+fn reconnect_config(enabled: bool, policy: &ReconnectPolicy) -> ReconnectConfig {
+    ReconnectConfig {
+        enabled,
+        max_attempts: policy.max_attempts,
+        initial_backoff: Duration::from_millis(policy.initial_backoff_ms),
+        max_backoff: Duration::from_secs(policy.max_backoff_secs),
+        backoff_multiplier: policy.backoff_multiplier,
+    }
 }
 
 /// DELETE /api/v1/connections/:id - Remove connection
@@ -638,40 +1065,30 @@ async fn create_connection(
         (status = 200, description = "Connection deleted successfully", body = DeleteConnectionResponse),
         (status = 404, description = "Connection not found", body = ErrorResponse),
         (status = 401, description = "Unauthorized", body = ErrorResponse),
-        (status = 403, description = "Forbidden - requires operator role", body = ErrorResponse)
+        (status = 403, description = "Forbidden - requires connections:write", body = ErrorResponse)
     ),
     security(
-        ("bearer_auth" = []),
-        ("api_key" = [])
+        ("bearer_auth" = ["connections:write"]),
+        ("api_key" = ["connections:write"])
     )
 )]
 async fn delete_connection(
     State(state): State<ApiState>,
     Path(id): Path<Uuid>,
-    RequireOperator(user): RequireOperator,
-    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    RequireConnectionsWrite(user): RequireConnectionsWrite,
+    ClientIp(client_ip): ClientIp,
 ) -> Result<Json<DeleteConnectionResponse>, ApiError> {
     let id_str = id.to_string();
     info!(connection_id = %id, "Deleting connection");
 
-    // Remove from connection pool
-    state.pool.remove_connection(&id_str).await.map_err(|e| {
-        error!(connection_id = %id, error = %e, "Failed to remove connection from pool");
-        ApiError::InternalError(format!("Failed to remove connection: {}", e))
-    })?;
-
-    // Remove from state tracking
-    let mut connections = state.connections.write().await;
-    let initial_len = connections.len();
-    connections.retain(|c| c.id != id);
-
-    if connections.len() == initial_len {
+    // Connections in other namespaces look the same as missing ones
+    if state.pool.get_connection(&id_str).is_some()
+        && !user.can_access(state.pool.tenant_of(&id_str).as_deref())
+    {
         return Err(ApiError::NotFound(format!("Connection {} not found", id)));
     }
-    drop(connections);
 
-    // Remove filters
-    state.distributor.remove_filters(&id_str);
+    close_connection(&state, id).await?;
 
     info!(connection_id = %id, "Connection deleted successfully");
 
@@ -682,7 +1099,7 @@ async fn delete_connection(
         "delete_connection".to_string(),
         format!("/api/v1/connections/{}", id),
         serde_json::json!({"connection_id": id}),
-        client_addr.ip().to_string(),
+        client_ip.to_string(),
         true,
     );
 
@@ -704,26 +1121,33 @@ async fn delete_connection(
         (status = 200, description = "Message sent successfully", body = SendCotResponse),
         (status = 400, description = "Invalid message format", body = ErrorResponse),
         (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires cot:send, and cot:unfiltered to bypass filters", body = ErrorResponse),
         (status = 500, description = "Internal error", body = ErrorResponse)
     ),
     security(
-        ("bearer_auth" = []),
-        ("api_key" = [])
+        ("bearer_auth" = ["cot:send"]),
+        ("api_key" = ["cot:send"])
     )
 )]
 async fn send_cot_message(
     State(state): State<ApiState>,
-    RequireOperator(user): RequireOperator,
-    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    RequireCotSend(user): RequireCotSend,
+    ClientIp(client_ip): ClientIp,
     Json(request): Json<SendCotRequest>,
 ) -> Result<Json<SendCotResponse>, ApiError> {
-    use omnitak_pool::DistributionMessage;
+    use omnitak_pool::{DistributionMessage, InboundMessage, TraceId};
 
     // Validate the request
     request
         .validate()
         .map_err(|e| ApiError::BadRequest(format!("Validation failed: {}", e)))?;
 
+    if !request.apply_filters && !user.permits(Permission::CotUnfiltered) {
+        return Err(ApiError::Forbidden(
+            "Bypassing filters requires cot:unfiltered".to_string(),
+        ));
+    }
+
     let message_id = Uuid::new_v4();
     let mut warnings = Vec::new();
 
@@ -731,6 +1155,7 @@ async fn send_cot_message(
         message_id = %message_id,
         user = %user.user_id.as_ref().unwrap_or(&"api_key".to_string()),
         target_count = request.target_connections.as_ref().map(|t| t.len()).unwrap_or(0),
+        apply_filters = request.apply_filters,
         "Injecting CoT message"
     );
 
@@ -756,19 +1181,47 @@ async fn send_cot_message(
         }
     }
 
-    // Create distribution message
-    let dist_message = DistributionMessage {
-        data: message_str.as_bytes().to_vec(),
-        source: None, // Injected messages have no source connection
-        timestamp: std::time::Instant::now(),
+    // Tenant users inject into their own namespace only
+    let source = match &user.tenant {
+        Some(tenant) => {
+            let source = format!("{}:{}", INJECTED_SOURCE, tenant);
+            state.pool.set_tenant(source.clone(), Some(tenant.clone()));
+            source
+        }
+        None => INJECTED_SOURCE.to_string(),
     };
 
-    // Send to distributor
-    let sender = state.distributor.sender();
-    sender.send_async(dist_message).await.map_err(|e| {
-        error!(message_id = %message_id, error = %e, "Failed to send message to distributor");
-        ApiError::InternalError(format!("Failed to queue message: {}", e))
-    })?;
+    let data = message_str.as_bytes().to_vec();
+    let trace_id = TraceId::new();
+    if request.apply_filters {
+        // Same pipeline as traffic from TAK servers: dedup, emergency
+        // detection, then per-connection filters in the distributor
+        let inbound = InboundMessage {
+            data,
+            source: source.clone(),
+            timestamp: std::time::Instant::now(),
+            trace_id,
+        };
+        state.aggregator.sender().send_async(inbound).await.map_err(|e| {
+            error!(message_id = %message_id, error = %e, "Failed to send message to aggregator");
+            ApiError::InternalError(format!("Failed to queue message: {}", e))
+        })?;
+    } else {
+        // Admin override: skip dedup and filters but still raise emergencies
+        state.emergencies.inspect(&data, &source);
+        let dist_message = DistributionMessage {
+            data,
+            // Injected messages have no source connection, only a namespace
+            source: user.tenant.is_some().then(|| source.clone()),
+            timestamp: std::time::Instant::now(),
+            bypass_filters: true,
+            trace_id,
+        };
+        state.distributor.sender().send_async(dist_message).await.map_err(|e| {
+            error!(message_id = %message_id, error = %e, "Failed to send message to distributor");
+            ApiError::InternalError(format!("Failed to queue message: {}", e))
+        })?;
+    }
 
     // Determine which connections received it
     // For now, we report based on request (actual delivery is async)
@@ -779,7 +1232,7 @@ async fn send_cot_message(
         let connections = state.connections.read().await;
         let all_ids: Vec<Uuid> = connections
             .iter()
-            .filter(|c| c.status == ConnectionStatus::Connected)
+            .filter(|c| c.status == ConnectionStatus::Connected && c.tenant == user.tenant)
             .map(|c| c.id)
             .collect();
         let count = all_ids.len();
@@ -788,6 +1241,7 @@ async fn send_cot_message(
 
     info!(
         message_id = %message_id,
+        trace_id = %trace_id,
         sent_to_count = sent_to_count,
         "CoT message queued for distribution"
     );
@@ -801,9 +1255,10 @@ async fn send_cot_message(
         serde_json::json!({
             "message_id": message_id,
             "sent_to_count": sent_to_count,
+            "apply_filters": request.apply_filters,
             "message_length": message_str.len(),
         }),
-        client_addr.ip().to_string(),
+        client_ip.to_string(),
         true,
     );
 
@@ -834,11 +1289,10 @@ async fn send_cot_message(
     )
 )]
 async fn list_filters(
-    State(_state): State<ApiState>,
-    _user: AuthUser,
+    State(state): State<ApiState>,
+    user: AuthUser,
 ) -> Result<Json<FilterList>, ApiError> {
-    // TODO: Get actual filters from filter engine
-    let filters = vec![];
+    let filters = state.filters.list(&user);
 
     Ok(Json(FilterList {
         total: filters.len(),
@@ -847,13 +1301,32 @@ async fn list_filters(
 }
 
 /// GET /api/v1/filters/:id - Get specific filter
+#[utoipa::path(
+    get,
+    path = "/api/v1/filters/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Filter ID")
+    ),
+    responses(
+        (status = 200, description = "Filter retrieved successfully", body = FilterRule),
+        (status = 404, description = "Filter not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
 async fn get_filter(
-    State(_state): State<ApiState>,
+    State(state): State<ApiState>,
     Path(id): Path<Uuid>,
-    _user: AuthUser,
+    user: AuthUser,
 ) -> Result<Json<crate::types::FilterRule>, ApiError> {
-    // TODO: Get actual filter from filter engine
-    Err(ApiError::NotFound(format!("Filter {} not found", id)))
+    state
+        .filters
+        .get(id, &user)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Filter {} not found", id)))
 }
 
 /// POST /api/v1/filters - Create new filter
@@ -865,16 +1338,17 @@ async fn get_filter(
         (status = 201, description = "Filter created successfully", body = CreateFilterResponse),
         (status = 400, description = "Invalid request", body = ErrorResponse),
         (status = 401, description = "Unauthorized", body = ErrorResponse),
-        (status = 403, description = "Forbidden - requires operator role", body = ErrorResponse)
+        (status = 403, description = "Forbidden - requires filters:write", body = ErrorResponse)
     ),
     security(
-        ("bearer_auth" = []),
-        ("api_key" = [])
+        ("bearer_auth" = ["filters:write"]),
+        ("api_key" = ["filters:write"])
     )
 )]
 async fn create_filter(
     State(state): State<ApiState>,
-    RequireOperator(user): RequireOperator,
+    RequireFiltersWrite(user): RequireFiltersWrite,
+    ClientIp(client_ip): ClientIp,
     Json(request): Json<CreateFilterRequest>,
 ) -> Result<(StatusCode, Json<CreateFilterResponse>), ApiError> {
     // Validate request
@@ -887,8 +1361,30 @@ async fn create_filter(
         "Creating filter"
     );
 
-    // TODO: Actually create filter in filter engine
+    // Filters live in their creator's namespace
     let filter_id = Uuid::new_v4();
+    let now = Utc::now();
+    state
+        .filters
+        .insert(crate::types::FilterRule {
+            id: filter_id,
+            name: request.name.clone(),
+            priority: request.priority,
+            action: request.action,
+            event_type: request.event_type.clone(),
+            uid_pattern: request.uid_pattern.clone(),
+            callsign_pattern: request.callsign_pattern.clone(),
+            source_address: request.source_address.clone(),
+            destination_address: request.destination_address.clone(),
+            geo_bounds: request.geo_bounds.clone(),
+            enabled: request.enabled,
+            match_count: 0,
+            tenant: user.tenant.clone(),
+            created_at: now,
+            modified_at: now,
+        })
+        .map_err(|e| ApiError::BadRequest(format!("Invalid pattern: {}", e)))?;
+    state.filters.apply(&state.pool, &state.distributor);
 
     // Audit log
     state.audit_logger.log(
@@ -897,7 +1393,7 @@ async fn create_filter(
         "create_filter".to_string(),
         format!("/api/v1/filters/{}", filter_id),
         serde_json::to_value(&request).unwrap(),
-        "0.0.0.0".to_string(), // TODO: Get actual IP
+        client_ip.to_string(),
         true,
     );
 
@@ -911,14 +1407,36 @@ async fn create_filter(
 }
 
 /// DELETE /api/v1/filters/:id - Remove filter
+#[utoipa::path(
+    delete,
+    path = "/api/v1/filters/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Filter ID")
+    ),
+    responses(
+        (status = 200, description = "Filter deleted successfully", body = DeleteConnectionResponse),
+        (status = 404, description = "Filter not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires filters:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["filters:write"]),
+        ("api_key" = ["filters:write"])
+    )
+)]
 async fn delete_filter(
     State(state): State<ApiState>,
     Path(id): Path<Uuid>,
-    RequireOperator(user): RequireOperator,
+    RequireFiltersWrite(user): RequireFiltersWrite,
+    ClientIp(client_ip): ClientIp,
 ) -> Result<Json<DeleteConnectionResponse>, ApiError> {
     info!(filter_id = %id, "Deleting filter");
 
-    // TODO: Actually delete filter from filter engine
+    state
+        .filters
+        .remove(id, &user)
+        .ok_or_else(|| ApiError::NotFound(format!("Filter {} not found", id)))?;
+    state.filters.apply(&state.pool, &state.distributor);
 
     // Audit log
     state.audit_logger.log(
@@ -927,7 +1445,7 @@ async fn delete_filter(
         "delete_filter".to_string(),
         format!("/api/v1/filters/{}", id),
         serde_json::json!({"filter_id": id}),
-        "0.0.0.0".to_string(), // TODO: Get actual IP
+        client_ip.to_string(),
         true,
     );
 
@@ -946,7 +1464,8 @@ async fn delete_filter(
     path = "/api/v1/metrics",
     responses(
         (status = 200, description = "Metrics retrieved successfully", content_type = "text/plain"),
-        (status = 401, description = "Unauthorized", body = ErrorResponse)
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Metrics are disabled", body = ErrorResponse)
     ),
     security(
         ("bearer_auth" = []),
@@ -954,42 +1473,32 @@ async fn delete_filter(
     )
 )]
 async fn get_metrics(State(state): State<ApiState>, _user: AuthUser) -> Result<String, ApiError> {
-    // Get real metrics from pool
-    let pool_stats = state.pool.stats();
-    let pool_metrics = state.pool.metrics();
-
-    // Format as Prometheus metrics
-    Ok(format!(
-        "# HELP omnitak_connections_total Total number of connections\n\
-         # TYPE omnitak_connections_total gauge\n\
-         omnitak_connections_total {}\n\
-         \n\
-         # HELP omnitak_connections_active Active connections\n\
-         # TYPE omnitak_connections_active gauge\n\
-         omnitak_connections_active {}\n\
-         \n\
-         # HELP omnitak_messages_received_total Total messages received\n\
-         # TYPE omnitak_messages_received_total counter\n\
-         omnitak_messages_received_total {}\n\
-         \n\
-         # HELP omnitak_messages_sent_total Total messages sent\n\
-         # TYPE omnitak_messages_sent_total counter\n\
-         omnitak_messages_sent_total {}\n\
-         \n\
-         # HELP omnitak_messages_processed_total Total messages processed\n\
-         # TYPE omnitak_messages_processed_total counter\n\
-         omnitak_messages_processed_total {}\n\
-         \n\
-         # HELP omnitak_uptime_seconds System uptime in seconds\n\
-         # TYPE omnitak_uptime_seconds gauge\n\
-         omnitak_uptime_seconds {}\n",
-        pool_stats.total_connections,
-        pool_stats.active_connections,
-        pool_stats.total_messages_received,
-        pool_stats.total_messages_sent,
-        pool_stats.total_messages_received + pool_stats.total_messages_sent,
-        state.start_time.elapsed().as_secs(),
-    ))
+    let registry = state
+        .metrics
+        .as_ref()
+        .ok_or_else(|| ApiError::NotFound("Metrics are disabled".to_string()))?;
+    let usage = state.resources.sample();
+    registry.update_gauges(&state.pool);
+    registry.update_process(&omnitak_pool::ProcessStats {
+        cpu_percent: usage.cpu_percent as f64,
+        resident_memory_bytes: usage.memory_rss_bytes,
+        virtual_memory_bytes: usage.memory_virtual_bytes,
+        open_fds: usage.open_fds,
+        open_sockets: usage.open_sockets,
+        uptime: state.start_time.elapsed(),
+    });
+    registry.update_runtime();
+    registry.update_channels(&state.distributor, &state.aggregator);
+    registry.update_duplicates(&state.aggregator);
+
+    let mut metrics = registry
+        .render()
+        .ok_or_else(|| ApiError::InternalError("Metrics exporter is not installed".to_string()))?;
+
+    // Per-plugin invocations, timings and memory
+    metrics.push_str(&state.plugin_metrics.render_prometheus());
+
+    Ok(metrics)
 }
 
 // ============================================================================
@@ -1009,36 +1518,236 @@ async fn get_metrics(State(state): State<ApiState>, _user: AuthUser) -> Result<S
 )]
 async fn login(
     State(state): State<ApiState>,
+    ClientIp(client_ip): ClientIp,
     Json(request): Json<LoginRequest>,
 ) -> Result<Json<LoginResponse>, ApiError> {
     // Validate request
     request.validate()?;
 
     // Attempt login
-    let (access_token, expires_at) = state
+    let tokens = state
+        .auth_service
+        .login_with_refresh(&request.username, &request.password)
+        .map_err(|_| {
+            state.audit_logger.log_auth_failure(
+                Some(&request.username),
+                None,
+                "login_failed",
+                "/api/v1/auth/login",
+                "Invalid credentials",
+                Some(client_ip),
+            );
+            ApiError::Unauthorized("Invalid credentials".to_string())
+        })?;
+
+    info!(username = request.username, "User logged in successfully");
+
+    Ok(Json(login_response(tokens)))
+}
+
+fn login_response(tokens: TokenPair) -> LoginResponse {
+    LoginResponse {
+        access_token: tokens.access_token,
+        expires_at: tokens.expires_at,
+        refresh_token: tokens.refresh_token,
+        refresh_expires_at: tokens.refresh_expires_at,
+        role: tokens.role,
+        custom_role: tokens.custom_role,
+        permissions: tokens.permissions.into_iter().collect(),
+    }
+}
+
+/// POST /api/v1/auth/refresh - Exchange a refresh token for new tokens
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/refresh",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "New access and refresh tokens; the old refresh token no longer works", body = LoginResponse),
+        (status = 401, description = "Refresh token invalid, expired, revoked or already used", body = ErrorResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse)
+    )
+)]
+async fn refresh_token(
+    State(state): State<ApiState>,
+    ClientIp(client_ip): ClientIp,
+    Json(request): Json<RefreshRequest>,
+) -> Result<Json<LoginResponse>, ApiError> {
+    request.validate()?;
+
+    let tokens = state
         .auth_service
-        .login(&request.username, &request.password)
-        .map_err(|_| ApiError::Unauthorized("Invalid credentials".to_string()))?;
+        .refresh(&request.refresh_token)
+        .map_err(|e| {
+            state.audit_logger.log_auth_failure(
+                None,
+                None,
+                "refresh_failed",
+                "/api/v1/auth/refresh",
+                &e.to_string(),
+                Some(client_ip),
+            );
+            ApiError::Unauthorized("Invalid or expired refresh token".to_string())
+        })?;
+
+    Ok(Json(login_response(tokens)))
+}
 
-    // Get user role
-    let user = state
+/// POST /api/v1/auth/logout - Revoke the calling access token and, if given,
+/// its refresh token
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/logout",
+    request_body(content = Option<LogoutRequest>, description = "Refresh token to revoke as well"),
+    responses(
+        (status = 204, description = "Logged out"),
+        (status = 401, description = "Missing, invalid or already revoked access token", body = ErrorResponse),
+        (status = 403, description = "The refresh token belongs to another user", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+async fn logout(
+    State(state): State<ApiState>,
+    ClientIp(client_ip): ClientIp,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    request: Option<Json<LogoutRequest>>,
+) -> Result<StatusCode, ApiError> {
+    let claims = state
         .auth_service
-        .users
-        .get(&request.username)
-        .ok_or_else(|| ApiError::Unauthorized("Invalid credentials".to_string()))?;
+        .verify_token(bearer.token())
+        .map_err(|_| ApiError::Unauthorized("Invalid or expired JWT token".to_string()))?;
+    let request = request.map(|Json(r)| r).unwrap_or_default();
+
+    if let Some(refresh_token) = &request.refresh_token {
+        if let Some(record) = state.auth_service.refresh_token_info(refresh_token) {
+            let owner = state
+                .auth_service
+                .users
+                .get(&record.username)
+                .map(|u| u.id.to_string());
+            if owner.as_deref() != Some(claims.sub.as_str()) {
+                return Err(ApiError::Forbidden(
+                    "Refresh token belongs to another user".to_string(),
+                ));
+            }
+            state.auth_service.revoke_refresh_token(refresh_token);
+        }
+    }
+    state.auth_service.revoke_token(&claims);
 
-    let role = user.role;
+    info!(user_id = %claims.sub, "User logged out");
 
-    info!(username = request.username, "User logged in successfully");
+    state.audit_logger.log(
+        claims.sub.clone(),
+        claims.role,
+        "logout".to_string(),
+        "/api/v1/auth/logout".to_string(),
+        serde_json::json!({"refresh_token_revoked": request.refresh_token.is_some()}),
+        client_ip.to_string(),
+        true,
+    );
 
-    Ok(Json(LoginResponse {
-        access_token,
-        expires_at,
-        role,
-    }))
+    Ok(StatusCode::NO_CONTENT)
 }
 
-/// POST /api/v1/auth/api-keys - Create API key (admin only)
+/// POST /api/v1/auth/revoke - Revoke a token, or every token of a user (requires users:manage)
+///
+/// Revocations outlast a restart only when the server has a token store
+/// (`api.token_store`).
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/revoke",
+    request_body = RevokeTokenRequest,
+    responses(
+        (status = 204, description = "Revoked"),
+        (status = 400, description = "Neither token nor username given", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires users:manage, or the token or user is in another tenant", body = ErrorResponse),
+        (status = 404, description = "Token already invalid or user not found", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["users:manage"]),
+        ("api_key" = ["users:manage"])
+    )
+)]
+async fn revoke_token(
+    State(state): State<ApiState>,
+    RequireUsersManage(user): RequireUsersManage,
+    ClientIp(client_ip): ClientIp,
+    Json(request): Json<RevokeTokenRequest>,
+) -> Result<StatusCode, ApiError> {
+    request.validate()?;
+
+    let auth = &state.auth_service;
+    let tenant_of = |username: &str| auth.users.get(username).map(|u| u.tenant.clone());
+    let mut details = serde_json::Map::new();
+
+    match (&request.token, &request.username) {
+        (None, None) => {
+            return Err(ApiError::BadRequest(
+                "Give a token or a username to revoke".to_string(),
+            ));
+        }
+        (Some(token), _) if token.starts_with("omni_rt_") => {
+            let record = auth
+                .refresh_token_info(token)
+                .ok_or_else(|| ApiError::NotFound("Refresh token not found".to_string()))?;
+            if !user.can_access(tenant_of(&record.username).flatten().as_deref()) {
+                return Err(ApiError::Forbidden(
+                    "Refresh token belongs to another tenant".to_string(),
+                ));
+            }
+            auth.revoke_refresh_token(token);
+            details.insert("refresh_token_of".to_string(), record.username.into());
+        }
+        (Some(token), _) => {
+            let claims = auth
+                .verify_token(token)
+                .map_err(|_| ApiError::NotFound("Token is not a live access token".to_string()))?;
+            if !user.can_access(claims.tenant.as_deref()) {
+                return Err(ApiError::Forbidden(
+                    "Token belongs to another tenant".to_string(),
+                ));
+            }
+            auth.revoke_token(&claims);
+            details.insert("access_token".to_string(), claims.jti.into());
+        }
+        (None, Some(_)) => {}
+    }
+
+    if let Some(username) = &request.username {
+        let tenant = tenant_of(username)
+            .ok_or_else(|| ApiError::NotFound(format!("User {} not found", username)))?;
+        if !user.can_access(tenant.as_deref()) {
+            return Err(ApiError::Forbidden(
+                "User belongs to another tenant".to_string(),
+            ));
+        }
+        let refresh_tokens = auth
+            .revoke_user_tokens(username)
+            .map_err(|e| ApiError::NotFound(e.to_string()))?;
+        details.insert("user".to_string(), username.clone().into());
+        details.insert("refresh_tokens".to_string(), refresh_tokens.into());
+    }
+
+    info!(details = ?details, "Tokens revoked");
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "revoke_token".to_string(),
+        "/api/v1/auth/revoke".to_string(),
+        serde_json::Value::Object(details),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/v1/auth/api-keys - Create API key (requires users:manage)
 #[utoipa::path(
     post,
     path = "/api/v1/auth/api-keys",
@@ -1047,26 +1756,65 @@ async fn login(
         (status = 201, description = "API key created successfully", body = ApiKeyResponse),
         (status = 400, description = "Invalid request", body = ErrorResponse),
         (status = 401, description = "Unauthorized", body = ErrorResponse),
-        (status = 403, description = "Forbidden - requires admin role", body = ErrorResponse)
+        (status = 403, description = "Forbidden - requires users:manage", body = ErrorResponse)
     ),
     security(
-        ("bearer_auth" = []),
-        ("api_key" = [])
+        ("bearer_auth" = ["users:manage"]),
+        ("api_key" = ["users:manage"])
     )
 )]
 async fn create_api_key(
     State(state): State<ApiState>,
-    RequireAdmin(user): RequireAdmin,
+    RequireUsersManage(user): RequireUsersManage,
+    ClientIp(client_ip): ClientIp,
     Json(request): Json<ApiKeyRequest>,
 ) -> Result<(StatusCode, Json<ApiKeyResponse>), ApiError> {
     // Validate request
     request.validate()?;
 
+    // Tenant admins can only issue keys for their own tenant
+    if user.tenant.is_some() && request.tenant.is_some() && request.tenant != user.tenant {
+        return Err(ApiError::Forbidden(
+            "Cannot create API keys for another tenant".to_string(),
+        ));
+    }
+    let tenant = user.tenant.clone().or_else(|| request.tenant.clone());
+
+    // Keys can do no more than their creator
+    let policy = state.auth_service.policy();
+    if let Some(custom_role) = &request.custom_role {
+        if !policy.has_custom_role(custom_role) {
+            return Err(ApiError::BadRequest(format!(
+                "Role '{}' is not defined",
+                custom_role
+            )));
+        }
+    }
+    if !policy
+        .permissions(request.role, request.custom_role.as_deref())
+        .is_subset(&user.permissions)
+    {
+        return Err(ApiError::Forbidden(
+            "Cannot create API keys with permissions you do not have".to_string(),
+        ));
+    }
+
     // Create API key
     let (api_key, key_id) = state
         .auth_service
-        .create_api_key(request.name.clone(), request.role, request.expires_at)
+        .create_api_key(
+            request.name.clone(),
+            request.role,
+            tenant.clone(),
+            request.expires_at,
+        )
         .map_err(|e| ApiError::InternalError(e.to_string()))?;
+    if request.custom_role.is_some() {
+        state
+            .auth_service
+            .set_api_key_custom_role(key_id, request.custom_role.clone())
+            .map_err(|e| ApiError::InternalError(e.to_string()))?;
+    }
 
     info!(
         key_name = request.name,
@@ -1081,7 +1829,7 @@ async fn create_api_key(
         "create_api_key".to_string(),
         format!("/api/v1/auth/api-keys/{}", key_id),
         serde_json::to_value(&request).unwrap(),
-        "0.0.0.0".to_string(), // TODO: Get actual IP
+        client_ip.to_string(),
         true,
     );
 
@@ -1091,6 +1839,7 @@ async fn create_api_key(
             api_key,
             id: key_id,
             name: request.name,
+            tenant,
             created_at: Utc::now(),
         }),
     ))
@@ -1100,26 +1849,52 @@ async fn create_api_key(
 // Audit Log Endpoints
 // ============================================================================
 
-/// GET /api/v1/audit - Get audit logs (admin only)
+/// GET /api/v1/audit - Query the audit log (requires audit:read)
 #[utoipa::path(
     get,
     path = "/api/v1/audit",
+    params(AuditQuery),
     responses(
-        (status = 200, description = "Audit logs retrieved successfully"),
+        (status = 200, description = "Matching audit log entries, newest first", body = AuditLogPage,
+            headers(
+                ("X-Total-Count" = usize, description = "Matching entries across all pages"),
+                ("Link" = String, description = "first, prev, next and last pages")
+            )),
         (status = 401, description = "Unauthorized", body = ErrorResponse),
-        (status = 403, description = "Forbidden - requires admin role", body = ErrorResponse)
+        (status = 403, description = "Forbidden - requires audit:read", body = ErrorResponse)
     ),
     security(
-        ("bearer_auth" = []),
-        ("api_key" = [])
+        ("bearer_auth" = ["audit:read"]),
+        ("api_key" = ["audit:read"])
     )
 )]
 async fn get_audit_logs(
     State(state): State<ApiState>,
-    RequireAdmin(_user): RequireAdmin,
-) -> Result<Json<Vec<AuditLogEntry>>, ApiError> {
-    let logs = state.audit_logger.get_logs();
-    Ok(Json(logs))
+    Query(query): Query<AuditQuery>,
+    OriginalUri(uri): OriginalUri,
+    RequireAuditRead(user): RequireAuditRead,
+) -> Result<Paginated<AuditLogPage>, ApiError> {
+    let mut query = query;
+    // Tenant users only see what the users of their tenant did
+    if let Some(tenant) = &user.tenant {
+        query.users = Some(
+            state
+                .auth_service
+                .users
+                .iter()
+                .filter(|u| u.tenant.as_ref() == Some(tenant))
+                .map(|u| u.key().clone())
+                .collect(),
+        );
+    }
+    let (offset, limit) = (query.offset, crate::audit::page_size(&query));
+    let audit_logger = state.audit_logger.clone();
+    let page = tokio::task::spawn_blocking(move || audit_logger.query(&query))
+        .await
+        .map_err(|e| ApiError::InternalError(e.to_string()))?
+        .map_err(|e| ApiError::InternalError(format!("Failed to query audit log: {}", e)))?;
+    let total = page.total;
+    Ok(Paginated::new(page, &uri, offset, limit, total))
 }
 
 // ============================================================================
@@ -1134,9 +1909,15 @@ pub enum ApiError {
     #[error("Unauthorized: {0}")]
     Unauthorized(String),
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
     #[error("Internal error: {0}")]
     InternalError(String),
 
@@ -1149,7 +1930,11 @@ impl IntoResponse for ApiError {
         let (status, error_code, message) = match self {
             ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, "bad_request", msg),
             ApiError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, "unauthorized", msg),
+            ApiError::Forbidden(msg) => (StatusCode::FORBIDDEN, "forbidden", msg),
             ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, "not_found", msg),
+            ApiError::PayloadTooLarge(msg) => {
+                (StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large", msg)
+            }
             ApiError::InternalError(msg) => {
                 error!(error = %msg, "Internal API error");
                 (
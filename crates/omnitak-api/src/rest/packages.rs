@@ -0,0 +1,150 @@
+//! Package upload endpoint
+//!
+//! Puts files and data packages into the store the Marti sync routes serve
+//! TAK clients from, streamed from a multipart body rather than read from a
+//! path on the server. ZIP archives are looked into with omnitak-cert, so
+//! packages carrying certificates or server connections are reported as
+//! connection packages, ready for `/api/v1/datapackages/connections`.
+
+use axum::{
+    Json,
+    extract::{Multipart, State},
+    http::StatusCode,
+};
+use omnitak_cert::CertificateFileKind;
+use omnitak_datapackage::PackageUpload;
+use tracing::info;
+
+use crate::auth::RequirePackagesWrite;
+use crate::middleware::ClientIp;
+use crate::rest::marti::{MISSION_PACKAGE_KEYWORD, split_keywords};
+use crate::rest::{ApiError, ApiState};
+use crate::types::*;
+use crate::upload::{self, Upload};
+
+/// POST /api/v1/packages/upload - Upload a file or data package
+#[utoipa::path(
+    post,
+    path = "/api/v1/packages/upload",
+    request_body(content = Vec<u8>, content_type = "multipart/form-data", description = "One file field, with optional text fields name, keywords (comma-separated) and creator_uid"),
+    responses(
+        (status = 201, description = "Package stored", body = PackageUploadResponse),
+        (status = 400, description = "Not exactly one file, or a damaged ZIP archive", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires packages:write", body = ErrorResponse),
+        (status = 404, description = "Package store not enabled", body = ErrorResponse),
+        (status = 413, description = "Upload larger than the Marti max_upload_mb, or the store is full", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["packages:write"]),
+        ("api_key" = ["packages:write"])
+    )
+)]
+pub async fn upload_package(
+    State(state): State<ApiState>,
+    RequirePackagesWrite(user): RequirePackagesWrite,
+    ClientIp(client_ip): ClientIp,
+    multipart: Multipart,
+) -> Result<(StatusCode, Json<PackageUploadResponse>), ApiError> {
+    let store = state
+        .packages
+        .clone()
+        .ok_or_else(|| ApiError::NotFound("Package store not enabled".to_string()))?;
+
+    let upload = Upload::receive(multipart, state.max_package_bytes).await?;
+    let [file] = upload.files.as_slice() else {
+        return Err(ApiError::BadRequest("Upload exactly one file".to_string()));
+    };
+
+    let is_zip = CertificateFileKind::detect(&upload::sniff(&file.path).await?)
+        == Some(CertificateFileKind::Zip);
+    let certificates = if is_zip {
+        let path = file.path.clone();
+        tokio::task::spawn_blocking(move || omnitak_cert::classify_zip_entries(&path))
+            .await
+            .map_err(|e| ApiError::InternalError(e.to_string()))?
+            .map_err(|e| ApiError::BadRequest(format!("Invalid ZIP archive: {}", e)))?
+    } else {
+        Vec::new()
+    };
+
+    let kind = if !is_zip {
+        PackageKind::File
+    } else if certificates.iter().any(|(_, kind)| {
+        matches!(
+            kind,
+            CertificateFileKind::Pkcs12
+                | CertificateFileKind::CertificateWithKey
+                | CertificateFileKind::Preferences
+        )
+    }) {
+        PackageKind::ConnectionPackage
+    } else {
+        PackageKind::MissionPackage
+    };
+
+    let mut keywords = split_keywords(upload.field("keywords"));
+    if kind == PackageKind::MissionPackage
+        && !keywords
+            .iter()
+            .any(|k| k.eq_ignore_ascii_case(MISSION_PACKAGE_KEYWORD))
+    {
+        keywords.push(MISSION_PACKAGE_KEYWORD.to_string());
+    }
+    let mime_type = if is_zip {
+        "application/x-zip-compressed".to_string()
+    } else {
+        file.content_type
+            .clone()
+            .unwrap_or_else(|| "application/octet-stream".to_string())
+    };
+    let metadata = PackageUpload {
+        name: upload.field("name").unwrap_or(&file.file_name).to_string(),
+        mime_type,
+        keywords,
+        creator_uid: upload.field("creator_uid").map(str::to_string),
+    };
+
+    // The upload is dropped, removing what is left of it, once stored
+    let package = tokio::task::spawn_blocking(move || {
+        let file = &upload.files[0];
+        store.put_file(&file.path, metadata)
+    })
+    .await
+    .map_err(|e| ApiError::InternalError(e.to_string()))??;
+
+    info!(
+        hash = %package.hash,
+        name = %package.name,
+        size = package.size,
+        kind = ?kind,
+        "Uploaded package"
+    );
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "upload_package".to_string(),
+        "/api/v1/packages/upload".to_string(),
+        serde_json::json!({"hash": package.hash, "name": package.name, "size": package.size}),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(PackageUploadResponse {
+            content_path: format!("/Marti/sync/content?hash={}", package.hash),
+            hash: package.hash,
+            name: package.name,
+            mime_type: package.mime_type,
+            size: package.size,
+            keywords: package.keywords,
+            kind,
+            certificates: certificates
+                .into_iter()
+                .map(|(name, kind)| UploadedCertificateFile { name, kind })
+                .collect(),
+        }),
+    ))
+}
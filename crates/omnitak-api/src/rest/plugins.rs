@@ -1,7 +1,7 @@
 //! Plugin management REST API endpoints
 
-use crate::auth::{AuthUser, RequireAdmin, RequireOperator};
-use crate::middleware::AuditLogger;
+use crate::auth::{AuthUser, RequirePluginsConfigure, RequirePluginsManage};
+use crate::middleware::{AuditLogger, ClientIp};
 use crate::types::ErrorResponse;
 use super::ApiError;
 use axum::{
@@ -13,11 +13,17 @@ use axum::{
 };
 use omnitak_plugin_api::{
     PluginManager, PluginInfo, PluginCapability, FilterMetadata, TransformerMetadata,
-    ResourceLimits, SandboxPolicy,
+    ScheduledTaskMetadata, PluginSettingsSnapshot,
+    ResourceLimits, SandboxPolicy, WasmTransformerPlugin, CotSink, PluginError, PluginEvent,
+    watch_plugin_dir,
+};
+use omnitak_pool::{
+    InboundMessage, MessageAggregator, MessageTransformer, TraceId, TransformPipeline,
+    TransformerStats,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, broadcast};
 use tracing::{error, info, warn};
 use utoipa::ToSchema;
 use validator::Validate;
@@ -30,6 +36,136 @@ use validator::Validate;
 pub struct PluginApiState {
     pub plugin_manager: Arc<RwLock<PluginManager>>,
     pub audit_logger: Arc<AuditLogger>,
+    /// Transformer stages run on live traffic by the aggregator
+    pub transformers: Arc<TransformPipeline>,
+}
+
+/// Runs a loaded WASM transformer as an aggregator pipeline stage
+pub struct WasmTransformerStage(pub Arc<WasmTransformerPlugin>);
+
+#[async_trait::async_trait]
+impl MessageTransformer for WasmTransformerStage {
+    fn id(&self) -> &str {
+        &self.0.metadata().id
+    }
+
+    fn handles(&self, cot_type: &str) -> bool {
+        self.0.can_transform(cot_type)
+    }
+
+    async fn transform(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        Ok(self.0.transform(data).await?)
+    }
+}
+
+/// Queues messages plugins emit into the aggregator, so they are
+/// deduplicated and filtered like traffic from TAK servers
+pub struct AggregatorCotSink(pub Arc<MessageAggregator>);
+
+impl CotSink for AggregatorCotSink {
+    fn submit(&self, plugin_id: &str, xml: String) -> Result<(), String> {
+        self.0
+            .sender()
+            .try_send(InboundMessage {
+                data: xml.into_bytes(),
+                source: format!("plugin:{}", plugin_id),
+                timestamp: std::time::Instant::now(),
+                trace_id: TraceId::new(),
+            })
+            .map_err(|e| format!("message queue unavailable: {}", e))
+    }
+}
+
+/// Keep `transformers` in step with the transformer plugins `manager` has
+/// loaded, however they were loaded or replaced
+pub async fn sync_transformers(
+    manager: Arc<RwLock<PluginManager>>,
+    transformers: Arc<TransformPipeline>,
+) {
+    let mut events = manager.read().await.subscribe();
+    // Plugins loaded before subscribing
+    resync_transformers(&*manager.read().await, &transformers);
+    loop {
+        match events.recv().await {
+            Ok(PluginEvent::Loaded(id)) => {
+                match manager.read().await.get_transformer_plugin(&id) {
+                    Some(plugin) => transformers.add(Arc::new(WasmTransformerStage(plugin))),
+                    // Replaced by a filter with the same ID
+                    None => {
+                        transformers.remove(&id);
+                    }
+                }
+            }
+            Ok(PluginEvent::Unloaded(id)) => {
+                transformers.remove(&id);
+            }
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                warn!("Missed {} plugin events, resyncing transformer pipeline", missed);
+                resync_transformers(&*manager.read().await, &transformers);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+fn resync_transformers(manager: &PluginManager, transformers: &TransformPipeline) {
+    for stage in transformers.stats() {
+        if manager.get_transformer_plugin(&stage.id).is_none() {
+            transformers.remove(&stage.id);
+        }
+    }
+    for info in manager.list_plugins() {
+        if let Some(plugin) = manager.get_transformer_plugin(&info.id) {
+            transformers.add(Arc::new(WasmTransformerStage(plugin)));
+        }
+    }
+}
+
+/// Load the plugins in the plugin directory and reload them as their files
+/// change. A change that fails to load keeps the running version
+pub async fn hot_reload(manager: Arc<RwLock<PluginManager>>) {
+    let (dir, debounce) = {
+        let manager = manager.read().await;
+        let config = manager.config();
+        (
+            std::path::PathBuf::from(&config.plugin_dir),
+            std::time::Duration::from_millis(config.hot_reload_debounce_ms),
+        )
+    };
+
+    if let Err(e) = manager.read().await.load_all_plugins().await {
+        warn!("Failed to load plugins from {}: {}", dir.display(), e);
+    }
+    let (_watcher, mut changes) = match watch_plugin_dir(&dir, debounce) {
+        Ok(watch) => watch,
+        Err(e) => {
+            error!("Plugin hot reload disabled, cannot watch {}: {}", dir.display(), e);
+            return;
+        }
+    };
+    info!("Watching {} for plugin changes", dir.display());
+
+    while let Some(path) = changes.recv().await {
+        if let Err(e) = manager.read().await.sync_plugin_file(&path).await {
+            warn!("Failed to reload {}, keeping the loaded version: {}", path.display(), e);
+        }
+    }
+}
+
+/// Transformers have pipeline stats; other plugins only the counters the
+/// runtime keeps
+fn plugin_stats(state: &PluginApiState, manager: &PluginManager, id: &str) -> TransformerStats {
+    state.transformers.stage_stats(id).unwrap_or_else(|| {
+        let runs = manager.metrics().snapshot(id).unwrap_or_default();
+        TransformerStats {
+            enabled: true,
+            executions: runs.invocations,
+            errors: runs.errors,
+            timeouts: runs.timeouts,
+            avg_time_us: runs.avg_time_us,
+            ..Default::default()
+        }
+    })
 }
 
 // ============================================================================
@@ -77,6 +213,8 @@ pub struct LoadPluginRequest {
 pub enum PluginType {
     Filter,
     Transformer,
+    /// Runs periodically on the schedule in its metadata
+    Scheduled,
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
@@ -150,6 +288,7 @@ pub fn create_plugin_router(state: PluginApiState) -> Router {
         .route("/api/v1/plugins/{id}", delete(unload_plugin))
 
         // Plugin configuration
+        .route("/api/v1/plugins/{id}/config", get(get_plugin_config))
         .route("/api/v1/plugins/{id}/config", put(update_plugin_config))
         .route("/api/v1/plugins/{id}/toggle", post(toggle_plugin))
 
@@ -202,6 +341,7 @@ async fn list_plugins(
             p.capabilities.iter().any(|cap| match plugin_type.as_str() {
                 "filter" => matches!(cap, PluginCapability::Filter),
                 "transformer" => matches!(cap, PluginCapability::Transform),
+                "scheduled" => matches!(cap, PluginCapability::Schedule),
                 _ => false,
             })
         });
@@ -224,16 +364,17 @@ async fn list_plugins(
         (status = 201, description = "Plugin loaded successfully", body = PluginInfo),
         (status = 400, description = "Invalid request", body = ErrorResponse),
         (status = 401, description = "Unauthorized", body = ErrorResponse),
-        (status = 403, description = "Forbidden - Admin required", body = ErrorResponse)
+        (status = 403, description = "Forbidden - requires plugins:manage", body = ErrorResponse)
     ),
     security(
-        ("bearer_auth" = ["admin"]),
-        ("api_key" = ["admin"])
+        ("bearer_auth" = ["plugins:manage"]),
+        ("api_key" = ["plugins:manage"])
     )
 )]
 async fn load_plugin(
     State(state): State<PluginApiState>,
-    _admin: RequireAdmin,
+    _user: RequirePluginsManage,
+    ClientIp(client_ip): ClientIp,
     Json(req): Json<LoadPluginRequest>,
 ) -> Result<(StatusCode, Json<PluginInfo>), ApiError> {
     req.validate()?;
@@ -262,7 +403,7 @@ async fn load_plugin(
             let metadata: TransformerMetadata = serde_json::from_value(req.config.clone())
                 .map_err(|e| ApiError::BadRequest(format!("Invalid transformer metadata: {}", e)))?;
 
-            let plugin = manager.load_transformer_plugin(&req.path, metadata)
+            manager.load_transformer_plugin(&req.path, metadata)
                 .map_err(|e| ApiError::InternalError(format!("Failed to load plugin: {}", e)))?;
 
             manager.list_plugins()
@@ -270,6 +411,21 @@ async fn load_plugin(
                 .find(|p| p.id == req.id)
                 .ok_or_else(|| ApiError::InternalError("Plugin loaded but not found in list".to_string()))?
         }
+        PluginType::Scheduled => {
+            let metadata: ScheduledTaskMetadata = serde_json::from_value(req.config.clone())
+                .map_err(|e| ApiError::BadRequest(format!("Invalid scheduled task metadata: {}", e)))?;
+
+            manager.load_scheduled_plugin(&req.path, metadata)
+                .map_err(|e| match e {
+                    PluginError::InvalidMetadata(msg) => ApiError::BadRequest(msg),
+                    e => ApiError::InternalError(format!("Failed to load plugin: {}", e)),
+                })?;
+
+            manager.list_plugins()
+                .into_iter()
+                .find(|p| p.id == req.id)
+                .ok_or_else(|| ApiError::InternalError("Plugin loaded but not found in list".to_string()))?
+        }
     };
 
     info!("Plugin loaded successfully: {}", req.id);
@@ -281,7 +437,7 @@ async fn load_plugin(
         "load_plugin".to_string(),
         format!("/api/v1/plugins"),
         serde_json::json!({"plugin_id": req.id, "path": req.path}),
-        "0.0.0.0".to_string(), // TODO: Get actual IP from request
+        client_ip.to_string(),
         true,
     );
 
@@ -316,14 +472,14 @@ async fn get_plugin_details(
         .find(|p| p.id == id)
         .ok_or(ApiError::NotFound(format!("Plugin not found: {}", id)))?;
 
-    // TODO: Get actual metrics from plugin manager
+    let stats = plugin_stats(&state, &manager, &id);
     let details = PluginDetailsResponse {
         info: plugin_info,
-        enabled: true,
+        enabled: stats.enabled,
         loaded_at: Some(chrono::Utc::now().to_rfc3339()),
-        execution_count: 0,
-        error_count: 0,
-        avg_execution_time_ms: 0.0,
+        execution_count: stats.executions,
+        error_count: stats.errors + stats.timeouts,
+        avg_execution_time_ms: stats.avg_time_us / 1000.0,
     };
 
     Ok(Json(details))
@@ -339,17 +495,18 @@ async fn get_plugin_details(
     responses(
         (status = 204, description = "Plugin unloaded successfully"),
         (status = 404, description = "Plugin not found", body = ErrorResponse),
-        (status = 403, description = "Forbidden - Admin required", body = ErrorResponse)
+        (status = 403, description = "Forbidden - requires plugins:manage", body = ErrorResponse)
     ),
     security(
-        ("bearer_auth" = ["admin"]),
-        ("api_key" = ["admin"])
+        ("bearer_auth" = ["plugins:manage"]),
+        ("api_key" = ["plugins:manage"])
     )
 )]
 async fn unload_plugin(
     State(state): State<PluginApiState>,
     Path(id): Path<String>,
-    _admin: RequireAdmin,
+    _user: RequirePluginsManage,
+    ClientIp(client_ip): ClientIp,
 ) -> Result<StatusCode, ApiError> {
     info!("Unloading plugin: {}", id);
 
@@ -366,13 +523,43 @@ async fn unload_plugin(
         "unload_plugin".to_string(),
         format!("/api/v1/plugins/{}", id),
         serde_json::json!({"plugin_id": id}),
-        "0.0.0.0".to_string(),
+        client_ip.to_string(),
         true,
     );
 
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// GET /api/v1/plugins/{id}/config - Get plugin settings and their schema
+#[utoipa::path(
+    get,
+    path = "/api/v1/plugins/{id}/config",
+    params(
+        ("id" = String, Path, description = "Plugin ID")
+    ),
+    responses(
+        (status = 200, description = "Plugin settings retrieved", body = PluginSettingsSnapshot),
+        (status = 404, description = "Plugin not found", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+async fn get_plugin_config(
+    State(state): State<PluginApiState>,
+    Path(id): Path<String>,
+    _user: AuthUser,
+) -> Result<Json<PluginSettingsSnapshot>, ApiError> {
+    let manager = state.plugin_manager.read().await;
+    let settings = manager.plugin_settings(&id).map_err(|e| match e {
+        PluginError::NotFound(_) => ApiError::NotFound(format!("Plugin not found: {}", id)),
+        e => ApiError::InternalError(format!("Failed to read plugin settings: {}", e)),
+    })?;
+
+    Ok(Json(settings))
+}
+
 /// PUT /api/v1/plugins/{id}/config - Update plugin configuration
 #[utoipa::path(
     put,
@@ -383,29 +570,34 @@ async fn unload_plugin(
     request_body = UpdatePluginConfigRequest,
     responses(
         (status = 200, description = "Configuration updated successfully"),
+        (status = 400, description = "Settings do not match the plugin's config schema", body = ErrorResponse),
         (status = 404, description = "Plugin not found", body = ErrorResponse),
-        (status = 403, description = "Forbidden - Operator required", body = ErrorResponse)
+        (status = 403, description = "Forbidden - requires plugins:configure", body = ErrorResponse)
     ),
     security(
-        ("bearer_auth" = ["operator"]),
-        ("api_key" = ["operator"])
+        ("bearer_auth" = ["plugins:configure"]),
+        ("api_key" = ["plugins:configure"])
     )
 )]
 async fn update_plugin_config(
     State(state): State<PluginApiState>,
     Path(id): Path<String>,
-    _operator: RequireOperator,
+    _user: RequirePluginsConfigure,
+    ClientIp(client_ip): ClientIp,
     Json(req): Json<UpdatePluginConfigRequest>,
 ) -> Result<StatusCode, ApiError> {
     info!("Updating config for plugin: {}", id);
 
-    // TODO: Implement config update in plugin manager
-    // For now, just validate the plugin exists
+    let values = req.config.as_object().ok_or_else(|| {
+        ApiError::BadRequest("config must be an object of setting names to values".to_string())
+    })?;
+
     let manager = state.plugin_manager.read().await;
-    let _ = manager.list_plugins()
-        .into_iter()
-        .find(|p| p.id == id)
-        .ok_or(ApiError::NotFound(format!("Plugin not found: {}", id)))?;
+    manager.update_plugin_settings(&id, values).map_err(|e| match e {
+        PluginError::NotFound(_) => ApiError::NotFound(format!("Plugin not found: {}", id)),
+        PluginError::InvalidConfig(msg) => ApiError::BadRequest(msg),
+        e => ApiError::InternalError(format!("Failed to update plugin settings: {}", e)),
+    })?;
 
     state.audit_logger.log(
         "operator".to_string(),
@@ -413,7 +605,7 @@ async fn update_plugin_config(
         "update_plugin_config".to_string(),
         format!("/api/v1/plugins/{}/config", id),
         serde_json::json!({"plugin_id": id, "config": req.config}),
-        "0.0.0.0".to_string(),
+        client_ip.to_string(),
         true,
     );
 
@@ -431,22 +623,32 @@ async fn update_plugin_config(
     responses(
         (status = 200, description = "Plugin toggled successfully"),
         (status = 404, description = "Plugin not found", body = ErrorResponse),
-        (status = 403, description = "Forbidden - Operator required", body = ErrorResponse)
+        (status = 403, description = "Forbidden - requires plugins:configure", body = ErrorResponse)
     ),
     security(
-        ("bearer_auth" = ["operator"]),
-        ("api_key" = ["operator"])
+        ("bearer_auth" = ["plugins:configure"]),
+        ("api_key" = ["plugins:configure"])
     )
 )]
 async fn toggle_plugin(
     State(state): State<PluginApiState>,
     Path(id): Path<String>,
-    _operator: RequireOperator,
+    _user: RequirePluginsConfigure,
+    ClientIp(client_ip): ClientIp,
     Json(req): Json<TogglePluginRequest>,
 ) -> Result<StatusCode, ApiError> {
     info!("Toggling plugin {} to enabled={}", id, req.enabled);
 
-    // TODO: Implement toggle in plugin manager
+    let manager = state.plugin_manager.read().await;
+    let plugin = manager.list_plugins()
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or(ApiError::NotFound(format!("Plugin not found: {}", id)))?;
+
+    // TODO: Toggle filter plugins once they run in the filter chain
+    if plugin.capabilities.contains(&PluginCapability::Transform) {
+        state.transformers.set_enabled(&id, req.enabled);
+    }
 
     state.audit_logger.log(
         "operator".to_string(),
@@ -454,7 +656,7 @@ async fn toggle_plugin(
         "toggle_plugin".to_string(),
         format!("/api/v1/plugins/{}/toggle", id),
         serde_json::json!({"plugin_id": id, "enabled": req.enabled}),
-        "0.0.0.0".to_string(),
+        client_ip.to_string(),
         true,
     );
 
@@ -489,18 +691,18 @@ async fn get_plugin_metrics(
         .find(|p| p.id == id)
         .ok_or(ApiError::NotFound(format!("Plugin not found: {}", id)))?;
 
-    // TODO: Get actual metrics from plugin manager
+    let stats = plugin_stats(&state, &manager, &id);
     let metrics = PluginMetricsResponse {
         plugin_id: id,
-        execution_count: 0,
-        error_count: 0,
-        timeout_count: 0,
-        avg_execution_time_ms: 0.0,
-        p50_execution_time_ms: 0.0,
-        p95_execution_time_ms: 0.0,
-        p99_execution_time_ms: 0.0,
-        last_execution: None,
-        last_error: None,
+        execution_count: stats.executions,
+        error_count: stats.errors,
+        timeout_count: stats.timeouts,
+        avg_execution_time_ms: stats.avg_time_us / 1000.0,
+        p50_execution_time_ms: stats.p50_time_us as f64 / 1000.0,
+        p95_execution_time_ms: stats.p95_time_us as f64 / 1000.0,
+        p99_execution_time_ms: stats.p99_time_us as f64 / 1000.0,
+        last_execution: stats.last_execution.map(|t| t.to_rfc3339()),
+        last_error: stats.last_error,
     };
 
     Ok(Json(metrics))
@@ -556,21 +758,26 @@ async fn get_plugin_health(
     responses(
         (status = 200, description = "Plugin reloaded successfully"),
         (status = 404, description = "Plugin not found", body = ErrorResponse),
-        (status = 403, description = "Forbidden - Admin required", body = ErrorResponse)
+        (status = 403, description = "Forbidden - requires plugins:manage", body = ErrorResponse)
     ),
     security(
-        ("bearer_auth" = ["admin"]),
-        ("api_key" = ["admin"])
+        ("bearer_auth" = ["plugins:manage"]),
+        ("api_key" = ["plugins:manage"])
     )
 )]
 async fn reload_plugin(
     State(state): State<PluginApiState>,
     Path(id): Path<String>,
-    _admin: RequireAdmin,
+    _user: RequirePluginsManage,
+    ClientIp(client_ip): ClientIp,
 ) -> Result<StatusCode, ApiError> {
     info!("Reloading plugin: {}", id);
 
-    // TODO: Implement reload (unload + load)
+    let manager = state.plugin_manager.read().await;
+    manager.reload_plugin(&id).await.map_err(|e| match e {
+        PluginError::NotFound(_) => ApiError::NotFound(format!("Plugin not found: {}", id)),
+        e => ApiError::InternalError(format!("Failed to reload plugin: {}", e)),
+    })?;
 
     state.audit_logger.log(
         "admin".to_string(),
@@ -578,7 +785,7 @@ async fn reload_plugin(
         "reload_plugin".to_string(),
         format!("/api/v1/plugins/{}/reload", id),
         serde_json::json!({"plugin_id": id}),
-        "0.0.0.0".to_string(),
+        client_ip.to_string(),
         true,
     );
 
@@ -591,16 +798,17 @@ async fn reload_plugin(
     path = "/api/v1/plugins/reload-all",
     responses(
         (status = 200, description = "All plugins reloaded successfully"),
-        (status = 403, description = "Forbidden - Admin required", body = ErrorResponse)
+        (status = 403, description = "Forbidden - requires plugins:manage", body = ErrorResponse)
     ),
     security(
-        ("bearer_auth" = ["admin"]),
-        ("api_key" = ["admin"])
+        ("bearer_auth" = ["plugins:manage"]),
+        ("api_key" = ["plugins:manage"])
     )
 )]
 async fn reload_all_plugins(
     State(state): State<PluginApiState>,
-    _admin: RequireAdmin,
+    _user: RequirePluginsManage,
+    ClientIp(client_ip): ClientIp,
 ) -> Result<StatusCode, ApiError> {
     info!("Reloading all plugins");
 
@@ -616,7 +824,7 @@ async fn reload_all_plugins(
         "reload_all_plugins".to_string(),
         "/api/v1/plugins/reload-all".to_string(),
         serde_json::json!({"count": result}),
-        "0.0.0.0".to_string(),
+        client_ip.to_string(),
         true,
     );
 
@@ -0,0 +1,129 @@
+//! Connection test endpoint
+//!
+//! Tries connection settings the way a new connection would use them,
+//! without adding anything to the pool, and reports each step so the
+//! server dialog can show where a connection would fail.
+
+use axum::{Json, extract::State};
+use omnitak_cert::CertificateInfo;
+use omnitak_client::{ProbeConfig, ProbeStage, probe};
+use std::time::Duration;
+use tracing::info;
+use validator::Validate;
+
+use crate::auth::RequireConnectionsWrite;
+use crate::middleware::ClientIp;
+use crate::rest::{ApiError, ApiState, tls_client_config};
+use crate::types::*;
+
+/// POST /api/v1/connections/test - Try connection settings (requires connections:write)
+#[utoipa::path(
+    post,
+    path = "/api/v1/connections/test",
+    request_body = TestConnectionRequest,
+    responses(
+        (status = 200, description = "Test finished; success says whether every step passed", body = TestConnectionResponse),
+        (status = 400, description = "Invalid request or unknown certificate", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires connections:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["connections:write"]),
+        ("api_key" = ["connections:write"])
+    )
+)]
+pub async fn test_connection(
+    State(state): State<ApiState>,
+    RequireConnectionsWrite(user): RequireConnectionsWrite,
+    ClientIp(client_ip): ClientIp,
+    Json(request): Json<TestConnectionRequest>,
+) -> Result<Json<TestConnectionResponse>, ApiError> {
+    request.validate()?;
+
+    let tls = match request.connection_type {
+        ConnectionType::TcpClient => None,
+        ConnectionType::TlsClient => {
+            let mut config = tls_client_config(
+                &state,
+                request.certificate_id,
+                request.tls_cert_path.as_deref(),
+                request.tls_key_path.as_deref(),
+            )?;
+            config.verify_server = request.validate_certs;
+            Some(config)
+        }
+        other => {
+            return Err(ApiError::BadRequest(format!(
+                "Connection type {:?} not yet supported",
+                other
+            )));
+        }
+    };
+
+    let address = format!("{}:{}", request.address, request.port);
+    let report = probe(&ProbeConfig {
+        server_addr: address.clone(),
+        tls,
+        timeout: Duration::from_secs(request.timeout_secs),
+        tak_ping: request.tak_ping,
+    })
+    .await;
+
+    let response = TestConnectionResponse {
+        success: report.success(),
+        steps: report
+            .steps
+            .iter()
+            .map(|step| ConnectionTestStep {
+                stage: match step.stage {
+                    ProbeStage::ClientCertificate => ConnectionTestStage::ClientCertificate,
+                    ProbeStage::Resolve => ConnectionTestStage::Resolve,
+                    ProbeStage::Connect => ConnectionTestStage::Connect,
+                    ProbeStage::TlsHandshake => ConnectionTestStage::TlsHandshake,
+                    ProbeStage::TakPing => ConnectionTestStage::TakPing,
+                },
+                success: step.success,
+                duration_ms: step.duration.as_millis() as u64,
+                message: step.message.clone(),
+            })
+            .collect(),
+        resolved_addresses: report.resolved.iter().map(|a| a.ip().to_string()).collect(),
+        connected_address: report.connected.map(|a| a.to_string()),
+        server_certificate: report
+            .server_certificate
+            .as_deref()
+            .and_then(|der| CertificateInfo::from_der(der).ok())
+            .map(|cert| ServerCertificateInfo {
+                subject: cert.subject_dn,
+                issuer: cert.issuer_dn,
+                not_before: cert.not_before,
+                not_after: cert.not_after,
+                fingerprint: cert.fingerprint,
+                expired: cert.is_expired,
+                trusted: report.certificate_trusted,
+            }),
+    };
+
+    info!(
+        address = %address,
+        success = response.success,
+        steps = response.steps.len(),
+        "Tested connection settings"
+    );
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "test_connection".to_string(),
+        "/api/v1/connections/test".to_string(),
+        serde_json::json!({
+            "address": address,
+            "connection_type": request.connection_type,
+            "failed_step": response.steps.iter().find(|s| !s.success).map(|s| s.stage),
+        }),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(Json(response))
+}
@@ -0,0 +1,177 @@
+//! Strict validation quarantine endpoints
+//!
+//! Messages from sources in strict mode that fail CoT schema validation are
+//! kept here instead of being forwarded. Strict mode set through the API
+//! lasts until the connection is closed and is not written back to the
+//! configuration file.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use omnitak_pool::SourceQuarantine;
+use tracing::info;
+
+use crate::auth::{AuthUser, RequireConnectionsWrite};
+use crate::middleware::ClientIp;
+use crate::rest::{ApiError, ApiState};
+use crate::types::*;
+
+/// GET /api/v1/quarantine - Quarantined messages per source connection
+#[utoipa::path(
+    get,
+    path = "/api/v1/quarantine",
+    responses(
+        (status = 200, description = "Strict sources and their quarantined messages", body = QuarantineReport),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+pub async fn get_quarantine(
+    State(state): State<ApiState>,
+    user: AuthUser,
+) -> Result<Json<QuarantineReport>, ApiError> {
+    let sources: Vec<SourceQuarantineInfo> = state
+        .aggregator
+        .quarantine()
+        .sources()
+        .into_iter()
+        // Tenant users only see their own namespace's connections
+        .filter(|source| user.can_access(state.pool.tenant_of(&source.source).as_deref()))
+        .map(|source| {
+            let SourceQuarantine {
+                source,
+                strict,
+                total,
+                messages,
+            } = source;
+
+            SourceQuarantineInfo {
+                name: state.pool.get_connection(&source).map(|c| c.name.clone()),
+                source,
+                strict,
+                total,
+                messages: messages
+                    .into_iter()
+                    .map(|message| QuarantinedMessageInfo {
+                        received_at: message.received_at,
+                        trace_id: message.trace_id.to_string(),
+                        uid: message.uid,
+                        reason: message.reason,
+                        data: String::from_utf8_lossy(&message.data).into_owned(),
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    Ok(Json(QuarantineReport {
+        total: sources.len(),
+        sources,
+    }))
+}
+
+/// PUT /api/v1/quarantine/:connection_id - Turn strict validation on or off (requires connections:write)
+#[utoipa::path(
+    put,
+    path = "/api/v1/quarantine/{connection_id}",
+    params(
+        ("connection_id" = String, Path, description = "Source connection ID")
+    ),
+    request_body = SetStrictValidationRequest,
+    responses(
+        (status = 204, description = "Validation mode changed"),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires connections:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["connections:write"]),
+        ("api_key" = ["connections:write"])
+    )
+)]
+pub async fn set_strict_validation(
+    State(state): State<ApiState>,
+    Path(connection_id): Path<String>,
+    RequireConnectionsWrite(user): RequireConnectionsWrite,
+    ClientIp(client_ip): ClientIp,
+    Json(request): Json<SetStrictValidationRequest>,
+) -> Result<StatusCode, ApiError> {
+    // Connections in other namespaces look the same as missing ones
+    if state.pool.get_connection(&connection_id).is_none()
+        || !user.can_access(state.pool.tenant_of(&connection_id).as_deref())
+    {
+        return Err(ApiError::NotFound(format!(
+            "Connection {} not found",
+            connection_id
+        )));
+    }
+
+    state
+        .aggregator
+        .quarantine()
+        .set_strict(connection_id.clone(), request.strict);
+
+    info!(connection_id = %connection_id, strict = request.strict, "Changed validation mode");
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "set_strict_validation".to_string(),
+        format!("/api/v1/quarantine/{}", connection_id),
+        serde_json::json!({"connection_id": connection_id, "strict": request.strict}),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /api/v1/quarantine/:connection_id - Drop a source's quarantined messages (requires connections:write)
+#[utoipa::path(
+    delete,
+    path = "/api/v1/quarantine/{connection_id}",
+    params(
+        ("connection_id" = String, Path, description = "Source connection ID")
+    ),
+    responses(
+        (status = 204, description = "Quarantined messages dropped"),
+        (status = 404, description = "No quarantined messages from this source", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires connections:write", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["connections:write"]),
+        ("api_key" = ["connections:write"])
+    )
+)]
+pub async fn clear_quarantine(
+    State(state): State<ApiState>,
+    Path(connection_id): Path<String>,
+    RequireConnectionsWrite(user): RequireConnectionsWrite,
+    ClientIp(client_ip): ClientIp,
+) -> Result<StatusCode, ApiError> {
+    let not_found =
+        || ApiError::NotFound(format!("No quarantined messages from {}", connection_id));
+    if !user.can_access(state.pool.tenant_of(&connection_id).as_deref()) {
+        return Err(not_found());
+    }
+    if !state.aggregator.quarantine().clear(&connection_id) {
+        return Err(not_found());
+    }
+
+    state.audit_logger.log(
+        user.user_id.unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "clear_quarantine".to_string(),
+        format!("/api/v1/quarantine/{}", connection_id),
+        serde_json::json!({"connection_id": connection_id}),
+        client_ip.to_string(),
+        true,
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,240 @@
+//! Traffic tap endpoint
+//!
+//! Mirrors live traffic to a WebSocket together with the routing decisions
+//! the distributor made for it: which filter rule or route matched for each
+//! connection and which connections received, filtered or dropped the
+//! message. Meant for debugging routing, so it is sampled and filtered on
+//! the server and the payload is only included when asked for.
+
+use axum::{
+    extract::{
+        Query, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    response::{IntoResponse, Response},
+};
+use futures::{SinkExt, StreamExt};
+use omnitak_pool::TapRecord;
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::auth::{AuthUser, RequireTrafficTap};
+use crate::middleware::ClientIp;
+use crate::rest::{ApiError, ApiState};
+use crate::types::ErrorResponse;
+
+/// Which messages a tap client sees
+#[derive(Debug, Clone, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TapQuery {
+    /// Fraction of matching messages to send, from 0 to 1
+    #[serde(default = "default_sample")]
+    pub sample: f64,
+
+    /// Only messages from this source connection
+    #[serde(default)]
+    pub source: Option<String>,
+
+    /// Only messages the distributor considered for this connection
+    #[serde(default)]
+    pub destination: Option<String>,
+
+    /// Only messages with this UID
+    #[serde(default)]
+    pub uid: Option<String>,
+
+    /// Only messages whose CoT type starts with this, e.g. `a-f`
+    #[serde(default, rename = "type")]
+    pub cot_type: Option<String>,
+
+    /// Include the message itself
+    #[serde(default)]
+    pub payload: bool,
+}
+
+fn default_sample() -> f64 {
+    1.0
+}
+
+impl TapQuery {
+    fn matches(&self, record: &TapRecord) -> bool {
+        let source = self.source.as_deref();
+        let uid = self.uid.as_deref();
+        (source.is_none() || record.source.as_deref() == source)
+            && (uid.is_none() || record.uid.as_deref() == uid)
+            && self.cot_type.as_ref().is_none_or(|prefix| {
+                record
+                    .cot_type
+                    .as_ref()
+                    .is_some_and(|t| t.starts_with(prefix.as_str()))
+            })
+            && self.destination.as_ref().is_none_or(|destination| {
+                record
+                    .destinations
+                    .iter()
+                    .any(|d| &d.connection == destination)
+            })
+    }
+}
+
+/// GET /api/v1/debug/tap - Live traffic with routing decisions (requires traffic:tap)
+#[utoipa::path(
+    get,
+    path = "/api/v1/debug/tap",
+    params(TapQuery),
+    responses(
+        (status = 101, description = "WebSocket upgrade; each text frame is one routed message with the outcome at every connection"),
+        (status = 400, description = "Sample rate out of range", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden - requires traffic:tap", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = ["traffic:tap"]),
+        ("api_key" = ["traffic:tap"])
+    )
+)]
+pub async fn tap_traffic(
+    State(state): State<ApiState>,
+    RequireTrafficTap(user): RequireTrafficTap,
+    ClientIp(client_ip): ClientIp,
+    Query(query): Query<TapQuery>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    if !(0.0..=1.0).contains(&query.sample) {
+        return Err(ApiError::BadRequest("sample must be between 0 and 1".to_string()));
+    }
+    audit(&state, &user, &query, client_ip);
+
+    let records = state.tap.subscribe();
+    Ok(ws
+        .on_upgrade(move |socket| stream_tap(socket, records, query, user, state))
+        .into_response())
+}
+
+fn audit(state: &ApiState, user: &AuthUser, query: &TapQuery, client_ip: IpAddr) {
+    state.audit_logger.log(
+        user.user_id.clone().unwrap_or_else(|| "api_key".to_string()),
+        user.role,
+        "traffic_tap".to_string(),
+        "/api/v1/debug/tap".to_string(),
+        serde_json::json!({
+            "sample": query.sample,
+            "source": query.source,
+            "destination": query.destination,
+            "uid": query.uid,
+            "type": query.cot_type,
+            "payload": query.payload,
+        }),
+        client_ip.to_string(),
+        true,
+    );
+}
+
+async fn stream_tap(
+    socket: WebSocket,
+    mut records: broadcast::Receiver<Arc<TapRecord>>,
+    query: TapQuery,
+    user: AuthUser,
+    state: ApiState,
+) {
+    let client_id = Uuid::new_v4();
+    info!(client_id = %client_id, "Traffic tap opened");
+
+    let (mut sender, mut receiver) = socket.split();
+
+    loop {
+        tokio::select! {
+            record = records.recv() => {
+                let record = match record {
+                    Ok(record) => record,
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        debug!(client_id = %client_id, missed, "Traffic tap lagged");
+                        let notice = serde_json::json!({ "lagged": missed }).to_string();
+                        if sender.send(Message::Text(notice.into())).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+
+                // Tenant users only see their own namespace's traffic
+                let tenant = record.source.as_ref().and_then(|s| state.pool.tenant_of(s));
+                if !user.can_access(tenant.as_deref()) || !query.matches(&record) {
+                    continue;
+                }
+                if query.sample < 1.0 && rand::random::<f64>() >= query.sample {
+                    continue;
+                }
+
+                let json = if query.payload {
+                    serde_json::to_string(&*record)
+                } else {
+                    serde_json::to_string(&TapRecord {
+                        payload: String::new(),
+                        ..(*record).clone()
+                    })
+                };
+                let Ok(json) = json else {
+                    continue;
+                };
+                if sender.send(Message::Text(json.into())).await.is_err() {
+                    break;
+                }
+            }
+            message = receiver.next() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    info!(client_id = %client_id, "Traffic tap closed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use omnitak_pool::{TapDestination, TapOutcome};
+
+    fn record() -> TapRecord {
+        TapRecord {
+            trace_id: "0000000000000001".to_string(),
+            timestamp_ms: 0,
+            source: Some("tak-1".to_string()),
+            uid: Some("ANDROID-1".to_string()),
+            cot_type: Some("a-f-G-U-C".to_string()),
+            bypass_filters: false,
+            matched_routes: Vec::new(),
+            destinations: vec![TapDestination {
+                connection: "tak-2".to_string(),
+                outcome: TapOutcome::Delivered,
+                matched_rule: None,
+                smoothed: false,
+                plugins: Vec::new(),
+            }],
+            latency_us: 40,
+            payload: String::new(),
+        }
+    }
+
+    fn query(params: &str) -> TapQuery {
+        let uri = format!("/api/v1/debug/tap?{}", params).parse().unwrap();
+        Query::<TapQuery>::try_from_uri(&uri).unwrap().0
+    }
+
+    #[test]
+    fn test_query_matches() {
+        let record = record();
+        assert!(query("").matches(&record));
+        assert!(query("source=tak-1&type=a-f").matches(&record));
+        assert!(query("destination=tak-2&uid=ANDROID-1").matches(&record));
+        assert!(!query("type=a-h").matches(&record));
+        assert!(!query("destination=tak-1").matches(&record));
+        assert_eq!(query("").sample, 1.0);
+    }
+}
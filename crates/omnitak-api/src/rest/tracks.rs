@@ -0,0 +1,34 @@
+//! Track endpoints
+//!
+//! Full snapshot of the track table behind the `/api/v1/tracks/stream`
+//! delta stream, for clients that poll instead of holding a WebSocket.
+//! Tenant users only get tracks received from their tenant's connections.
+
+use axum::{Json, extract::State};
+
+use crate::auth::AuthUser;
+use crate::rest::{ApiError, ApiState};
+use crate::types::*;
+
+/// GET /api/v1/tracks - Current tracks as a GeoJSON FeatureCollection
+#[utoipa::path(
+    get,
+    path = "/api/v1/tracks",
+    responses(
+        (status = 200, description = "Current tracks", body = TrackCollection),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+pub async fn list_tracks(
+    State(state): State<ApiState>,
+    user: AuthUser,
+) -> Result<Json<TrackCollection>, ApiError> {
+    Ok(Json(state.tracks.snapshot_for(|source| {
+        let tenant = source.and_then(|source| state.pool.tenant_of(&source.to_string()));
+        user.can_access(tenant.as_deref())
+    })))
+}
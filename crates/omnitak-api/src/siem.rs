@@ -0,0 +1,573 @@
+//! SIEM export of audit and security events
+//!
+//! Forwards every audit log entry, including failed logins and requests
+//! rejected for bad credentials or missing permissions, to a collector over
+//! TCP or TLS. Entries are sent as RFC 5424 syslog messages with the entry
+//! fields as structured data, or as CEF records behind a syslog header for
+//! SIEMs that parse ArcSight's format. While the collector is unreachable
+//! entries queue in memory, dropping the oldest once the queue is full.
+
+use crate::middleware::AuditLogger;
+use crate::types::AuditLogEntry;
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::SecondsFormat;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::Instant;
+use tokio_rustls::TlsConnector;
+use tracing::{info, warn};
+
+/// SD-ID of the structured data element carrying entry fields. 32473 is the
+/// private enterprise number RFC 5424 reserves for documentation and
+/// examples; collectors match on the whole SD-ID.
+const SD_ID: &str = "audit@32473";
+const APP_NAME: &str = "omnitak";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiemConfig {
+    /// Collector address, host:port
+    pub address: String,
+    #[serde(default)]
+    pub transport: SiemTransport,
+    #[serde(default)]
+    pub format: SiemFormat,
+    /// Message framing on the stream (default: octet counting for syslog,
+    /// newline for CEF)
+    #[serde(default)]
+    pub framing: Option<SiemFraming>,
+    /// CA bundle (PEM) to verify the collector; public web roots if unset
+    #[serde(default)]
+    pub ca_path: Option<PathBuf>,
+    /// Client certificate (PEM), for collectors that require mutual TLS
+    #[serde(default)]
+    pub cert_path: Option<PathBuf>,
+    /// Client private key (PEM)
+    #[serde(default)]
+    pub key_path: Option<PathBuf>,
+    /// Name to verify the collector certificate against (default: the host
+    /// part of `address`)
+    #[serde(default)]
+    pub server_name: Option<String>,
+    /// Syslog facility, 0-23 (default 13, log audit)
+    #[serde(default = "default_facility")]
+    pub facility: u8,
+    /// HOSTNAME field of the syslog header (default: this machine's name)
+    #[serde(default)]
+    pub hostname: Option<String>,
+    /// Entries held while the collector is unreachable
+    #[serde(default = "default_queue_size")]
+    pub queue_size: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SiemTransport {
+    #[default]
+    Tcp,
+    Tls,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SiemFormat {
+    /// RFC 5424 with the entry as structured data
+    #[default]
+    Syslog,
+    /// ArcSight Common Event Format
+    Cef,
+}
+
+/// How messages are delimited on the stream (RFC 6587)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SiemFraming {
+    /// Length-prefixed, as RFC 5425 requires over TLS
+    OctetCounting,
+    /// Each message ends with a newline
+    Newline,
+}
+
+fn default_facility() -> u8 {
+    13
+}
+
+fn default_queue_size() -> usize {
+    10_000
+}
+
+impl SiemConfig {
+    /// Check the settings that do not need the filesystem or network
+    pub fn validate(&self) -> Result<()> {
+        let (host, port) = self
+            .address
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow!("SIEM address must be host:port: {}", self.address))?;
+        if host.is_empty() || port.parse::<u16>().is_err() {
+            bail!("Invalid SIEM address: {}", self.address);
+        }
+        if self.facility > 23 {
+            bail!("SIEM syslog facility must be 0-23, got {}", self.facility);
+        }
+        if self.cert_path.is_some() != self.key_path.is_some() {
+            bail!("SIEM cert_path and key_path must be set together");
+        }
+        if self.transport == SiemTransport::Tcp
+            && (self.ca_path.is_some() || self.cert_path.is_some())
+        {
+            warn!("SIEM TLS settings are ignored with the tcp transport");
+        }
+        Ok(())
+    }
+
+    fn framing(&self) -> SiemFraming {
+        self.framing.unwrap_or(match self.format {
+            SiemFormat::Syslog => SiemFraming::OctetCounting,
+            SiemFormat::Cef => SiemFraming::Newline,
+        })
+    }
+
+    fn host(&self) -> &str {
+        let host = self
+            .address
+            .rsplit_once(':')
+            .map_or(self.address.as_str(), |(host, _)| host);
+        host.trim_start_matches('[').trim_end_matches(']')
+    }
+}
+
+/// Start forwarding entries from `audit_logger` to the collector
+pub fn spawn(config: SiemConfig, audit_logger: &AuditLogger) -> Result<()> {
+    let exporter = SiemExporter::new(config)?;
+    info!(
+        address = %exporter.config.address,
+        transport = ?exporter.config.transport,
+        format = ?exporter.config.format,
+        "Exporting audit events to SIEM"
+    );
+    tokio::spawn(exporter.run(audit_logger.subscribe()));
+    Ok(())
+}
+
+type Connection = Box<dyn AsyncWrite + Send + Unpin>;
+
+struct SiemExporter {
+    config: SiemConfig,
+    tls: Option<(TlsConnector, ServerName<'static>)>,
+    hostname: String,
+}
+
+impl SiemExporter {
+    fn new(config: SiemConfig) -> Result<Self> {
+        config.validate()?;
+        let tls = match config.transport {
+            SiemTransport::Tcp => None,
+            SiemTransport::Tls => {
+                let name = config
+                    .server_name
+                    .clone()
+                    .unwrap_or_else(|| config.host().to_string());
+                let server_name = ServerName::try_from(name.clone())
+                    .with_context(|| format!("Invalid SIEM server name: {}", name))?;
+                Some((tls_connector(&config)?, server_name))
+            }
+        };
+        let hostname = config
+            .hostname
+            .clone()
+            .or_else(sysinfo::System::host_name)
+            .unwrap_or_else(|| "-".to_string());
+        Ok(Self {
+            config,
+            tls,
+            hostname,
+        })
+    }
+
+    async fn run(self, mut feed: broadcast::Receiver<AuditLogEntry>) {
+        let mut queue: VecDeque<Vec<u8>> = VecDeque::new();
+        let mut dropped = 0u64;
+        let mut connection: Option<Connection> = None;
+        let mut backoff = INITIAL_BACKOFF;
+        let mut retry_at = Instant::now();
+
+        loop {
+            tokio::select! {
+                entry = feed.recv() => match entry {
+                    Ok(entry) => {
+                        if queue.len() >= self.config.queue_size.max(1) {
+                            queue.pop_front();
+                            dropped += 1;
+                        }
+                        queue.push_back(self.encode(&entry));
+                    }
+                    Err(RecvError::Lagged(n)) => dropped += n,
+                    Err(RecvError::Closed) => return,
+                },
+                _ = tokio::time::sleep_until(retry_at),
+                    if connection.is_none() && !queue.is_empty() => {}
+            }
+
+            if dropped > 0 {
+                warn!(dropped, "Dropped audit entries bound for the SIEM");
+                dropped = 0;
+            }
+
+            if connection.is_none() {
+                if Instant::now() < retry_at {
+                    continue;
+                }
+                match self.connect().await {
+                    Ok(stream) => {
+                        info!(address = %self.config.address, "Connected to SIEM collector");
+                        connection = Some(stream);
+                        backoff = INITIAL_BACKOFF;
+                    }
+                    Err(e) => {
+                        warn!(
+                            address = %self.config.address,
+                            error = %e,
+                            retry_in = ?backoff,
+                            "Failed to connect to SIEM collector"
+                        );
+                        retry_at = Instant::now() + backoff;
+                        backoff = (backoff * 2).min(MAX_BACKOFF);
+                        continue;
+                    }
+                }
+            }
+
+            if let Some(stream) = connection.as_mut() {
+                if let Err(e) = send_queued(stream, &mut queue).await {
+                    warn!(address = %self.config.address, error = %e, "Lost SIEM connection");
+                    connection = None;
+                    retry_at = Instant::now();
+                }
+            }
+        }
+    }
+
+    async fn connect(&self) -> Result<Connection> {
+        let tcp = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(&self.config.address))
+            .await
+            .context("Connection timed out")??;
+        tcp.set_nodelay(true)?;
+        Ok(match &self.tls {
+            None => Box::new(tcp),
+            Some((connector, server_name)) => Box::new(
+                tokio::time::timeout(CONNECT_TIMEOUT, connector.connect(server_name.clone(), tcp))
+                    .await
+                    .context("TLS handshake timed out")??,
+            ),
+        })
+    }
+
+    /// One framed message
+    fn encode(&self, entry: &AuditLogEntry) -> Vec<u8> {
+        let message = match self.config.format {
+            SiemFormat::Syslog => syslog_message(entry, self.config.facility, &self.hostname),
+            SiemFormat::Cef => cef_message(entry, self.config.facility, &self.hostname),
+        };
+        match self.config.framing() {
+            SiemFraming::OctetCounting => format!("{} {}", message.len(), message).into_bytes(),
+            SiemFraming::Newline => format!("{}\n", message.replace('\n', " ")).into_bytes(),
+        }
+    }
+}
+
+/// Write queued messages, keeping the one that failed at the front
+async fn send_queued(
+    stream: &mut Connection,
+    queue: &mut VecDeque<Vec<u8>>,
+) -> std::io::Result<()> {
+    while let Some(frame) = queue.front() {
+        stream.write_all(frame).await?;
+        queue.pop_front();
+    }
+    stream.flush().await
+}
+
+fn tls_connector(config: &SiemConfig) -> Result<TlsConnector> {
+    let mut roots = rustls::RootCertStore::empty();
+    match &config.ca_path {
+        Some(path) => {
+            for cert in load_certs(path)? {
+                roots
+                    .add(cert)
+                    .context("Failed to add SIEM CA certificate")?;
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+    let builder = rustls::ClientConfig::builder().with_root_certificates(roots);
+    let client_config = match (&config.cert_path, &config.key_path) {
+        (Some(cert_path), Some(key_path)) => builder
+            .with_client_auth_cert(load_certs(cert_path)?, load_key(key_path)?)
+            .context("Invalid SIEM client certificate or key")?,
+        _ => builder.with_no_client_auth(),
+    };
+    Ok(TlsConnector::from(Arc::new(client_config)))
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    rustls_pemfile::certs(&mut std::io::BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to read certificates from {}", path.display()))
+}
+
+fn load_key(path: &Path) -> Result<PrivateKeyDer<'static>> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    rustls_pemfile::private_key(&mut std::io::BufReader::new(file))
+        .with_context(|| format!("Failed to read private key from {}", path.display()))?
+        .ok_or_else(|| anyhow!("No private key found in {}", path.display()))
+}
+
+/// `<PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID`
+fn syslog_header(entry: &AuditLogEntry, facility: u8, hostname: &str) -> String {
+    // Notice for completed actions, warning for failures and rejections
+    let severity = if entry.success { 5 } else { 4 };
+    format!(
+        "<{}>1 {} {} {} {} {}",
+        u16::from(facility) * 8 + severity,
+        entry.timestamp.to_rfc3339_opts(SecondsFormat::Micros, true),
+        header_field(hostname, 255),
+        APP_NAME,
+        std::process::id(),
+        header_field(&entry.action, 32),
+    )
+}
+
+/// RFC 5424 message with the entry fields as structured data and the
+/// request details as JSON in MSG
+pub fn syslog_message(entry: &AuditLogEntry, facility: u8, hostname: &str) -> String {
+    let role = entry.role.map(role_name).unwrap_or("none");
+    format!(
+        "{} [{} user=\"{}\" role=\"{}\" resource=\"{}\" src=\"{}\" outcome=\"{}\" id=\"{}\"] {}",
+        syslog_header(entry, facility, hostname),
+        SD_ID,
+        sd_escape(&entry.user),
+        role,
+        sd_escape(&entry.resource),
+        sd_escape(&entry.source_ip),
+        outcome(entry),
+        entry.id,
+        entry.details,
+    )
+}
+
+/// CEF record behind a syslog header with no structured data
+pub fn cef_message(entry: &AuditLogEntry, facility: u8, hostname: &str) -> String {
+    let mut extension = vec![
+        format!("rt={}", entry.timestamp.timestamp_millis()),
+        format!("suser={}", cef_value(&entry.user)),
+        format!("request={}", cef_value(&entry.resource)),
+        format!("outcome={}", outcome(entry)),
+        format!("externalId={}", entry.id),
+    ];
+    if let Some(role) = entry.role {
+        extension.push(format!("spriv={}", role_name(role)));
+    }
+    if entry.source_ip.parse::<IpAddr>().is_ok() {
+        extension.push(format!("src={}", entry.source_ip));
+    }
+    if !entry.details.is_null() {
+        extension.push("cs1Label=details".to_string());
+        extension.push(format!("cs1={}", cef_value(&entry.details.to_string())));
+    }
+
+    format!(
+        "{} - CEF:0|OmniTAK|OmniTAK|{}|{}|{}|{}|{}",
+        syslog_header(entry, facility, hostname),
+        env!("CARGO_PKG_VERSION"),
+        cef_header(&entry.action),
+        cef_header(&format!("{} {}", entry.action, outcome(entry))),
+        if entry.success { 3 } else { 7 },
+        extension.join(" "),
+    )
+}
+
+fn outcome(entry: &AuditLogEntry) -> &'static str {
+    if entry.success { "success" } else { "failure" }
+}
+
+fn role_name(role: crate::types::UserRole) -> &'static str {
+    match role {
+        crate::types::UserRole::Admin => "admin",
+        crate::types::UserRole::Operator => "operator",
+        crate::types::UserRole::ReadOnly => "readonly",
+    }
+}
+
+/// Header fields are printable ASCII without spaces; `-` when empty
+fn header_field(value: &str, max_len: usize) -> String {
+    let field: String = value
+        .chars()
+        .filter(|c| c.is_ascii_graphic())
+        .take(max_len)
+        .collect();
+    if field.is_empty() {
+        "-".to_string()
+    } else {
+        field
+    }
+}
+
+fn sd_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(']', "\\]")
+}
+
+fn cef_header(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+fn cef_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace("\r\n", "\\n")
+        .replace(['\r', '\n'], "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::UserRole;
+    use chrono::TimeZone;
+    use tokio::io::AsyncReadExt;
+    use uuid::Uuid;
+
+    fn entry(success: bool) -> AuditLogEntry {
+        AuditLogEntry {
+            id: Uuid::nil(),
+            user: "alice".to_string(),
+            role: Some(UserRole::Admin),
+            action: "delete_connection".to_string(),
+            resource: "/api/v1/connections/a=b".to_string(),
+            details: serde_json::json!({"note": "x|y]"}),
+            source_ip: "192.0.2.10".to_string(),
+            timestamp: chrono::Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap(),
+            success,
+        }
+    }
+
+    fn config(address: &str, format: SiemFormat) -> SiemConfig {
+        serde_json::from_value(serde_json::json!({
+            "address": address,
+            "format": format,
+            "hostname": "tak1",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_syslog_message() {
+        let message = syslog_message(&entry(true), 13, "tak1");
+        assert!(message.starts_with(&format!(
+            "<109>1 2025-03-01T12:00:00.000000Z tak1 omnitak {} delete_connection [audit@32473 ",
+            std::process::id()
+        )));
+        assert!(message.contains(r#"user="alice" role="admin""#));
+        assert!(message.contains(r#"outcome="success""#));
+        assert!(message.ends_with(r#"] {"note":"x|y]"}"#));
+
+        // Failures are warnings, unauthenticated entries have no role
+        let mut failed = entry(false);
+        failed.role = None;
+        failed.user = "bad\"user]".to_string();
+        let message = syslog_message(&failed, 13, "tak1");
+        assert!(message.starts_with("<108>1 "));
+        assert!(message.contains(r#"user="bad\"user\]" role="none""#));
+    }
+
+    #[test]
+    fn test_cef_message() {
+        let message = cef_message(&entry(false), 13, "tak1");
+        let cef = message.split_once(" - ").unwrap().1;
+        assert_eq!(
+            cef,
+            format!(
+                "CEF:0|OmniTAK|OmniTAK|{}|delete_connection|delete_connection failure|7|\
+                 rt=1740830400000 suser=alice request=/api/v1/connections/a\\=b \
+                 outcome=failure externalId={} spriv=admin src=192.0.2.10 \
+                 cs1Label=details cs1={{\"note\":\"x|y]\"}}",
+                env!("CARGO_PKG_VERSION"),
+                Uuid::nil()
+            )
+        );
+    }
+
+    #[test]
+    fn test_config_validation_and_framing() {
+        let syslog = config("siem.example.com:6514", SiemFormat::Syslog);
+        assert!(syslog.validate().is_ok());
+        assert_eq!(syslog.framing(), SiemFraming::OctetCounting);
+        assert_eq!(syslog.facility, 13);
+        assert_eq!(config("[::1]:514", SiemFormat::Cef).host(), "::1");
+        assert_eq!(
+            config("x:514", SiemFormat::Cef).framing(),
+            SiemFraming::Newline
+        );
+
+        assert!(
+            config("siem.example.com", SiemFormat::Syslog)
+                .validate()
+                .is_err()
+        );
+        let mut bad = syslog.clone();
+        bad.facility = 24;
+        assert!(bad.validate().is_err());
+        let mut bad = syslog;
+        bad.cert_path = Some(PathBuf::from("client.pem"));
+        assert!(bad.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_forwards_audit_entries() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let logger = AuditLogger::new();
+        spawn(config(&address, SiemFormat::Syslog), &logger).unwrap();
+
+        logger.log_auth_failure(
+            None,
+            None,
+            "auth_failed",
+            "/api/v1/status",
+            "Invalid API key",
+            None,
+        );
+        let (mut socket, _) = listener.accept().await.unwrap();
+
+        let mut received = Vec::new();
+        while !received.ends_with(b"}") {
+            let mut buf = [0u8; 1024];
+            let n = tokio::time::timeout(Duration::from_secs(5), socket.read(&mut buf))
+                .await
+                .unwrap()
+                .unwrap();
+            received.extend_from_slice(&buf[..n]);
+        }
+        let received = String::from_utf8(received).unwrap();
+        let (length, message) = received.split_once(' ').unwrap();
+        assert_eq!(length.parse::<usize>().unwrap(), message.len());
+        assert!(message.contains("auth_failed"));
+        assert!(message.contains(r#"user="anonymous""#));
+        assert!(message.ends_with(r#"{"reason":"Invalid API key"}"#));
+    }
+}
@@ -0,0 +1,117 @@
+//! Background clock synchronization monitor
+//!
+//! Periodically runs [`omnitak_core::time_sync::check`] and keeps the latest
+//! result for the status endpoint, logging a warning whenever the clock is
+//! unsynchronized or drifts beyond the configured threshold. When the
+//! platform reports no offset, recent GPS fix times are used instead.
+
+use crate::types::TimeSyncInfo;
+use omnitak_core::time_sync::{self, GpsClock, TimeSource, TimeSyncConfig, TimeSyncStatus};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// GPS fixes older than this are not used as a time reference
+const GPS_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// Holds the most recent clock check result
+pub struct TimeMonitor {
+    config: TimeSyncConfig,
+    status: RwLock<Option<TimeSyncStatus>>,
+    gps: Option<Arc<GpsClock>>,
+}
+
+impl TimeMonitor {
+    pub fn new(config: TimeSyncConfig) -> Self {
+        Self {
+            config,
+            status: RwLock::new(None),
+            gps: None,
+        }
+    }
+
+    /// Fall back to the time of GPS fixes recorded in `gps`
+    pub fn with_gps_clock(mut self, gps: Arc<GpsClock>) -> Self {
+        self.gps = Some(gps);
+        self
+    }
+
+    /// Start checking the clock in the background
+    pub fn start(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.config.check_interval());
+            let mut warned = false;
+
+            loop {
+                interval.tick().await;
+
+                let config = self.config.clone();
+                let mut status =
+                    match tokio::task::spawn_blocking(move || time_sync::check(&config)).await {
+                        Ok(status) => status,
+                        Err(e) => {
+                            warn!(error = %e, "Clock check task failed");
+                            continue;
+                        }
+                    };
+                if let Some(offset_ms) =
+                    self.gps.as_ref().and_then(|gps| gps.offset_ms(GPS_MAX_AGE))
+                {
+                    status.apply_gps_offset(offset_ms, self.config.drift_warning_ms);
+                }
+
+                let attention = status.needs_attention(self.config.drift_warning_ms);
+                if attention {
+                    warn!(
+                        synchronized = ?status.synchronized,
+                        offset_ms = ?status.offset_ms,
+                        threshold_ms = self.config.drift_warning_ms,
+                        "System clock is not synchronized; CoT timestamps may be rejected as stale"
+                    );
+                } else if warned {
+                    info!(offset_ms = ?status.offset_ms, "System clock synchronization restored");
+                } else {
+                    debug!(
+                        source = ?status.source,
+                        synchronized = ?status.synchronized,
+                        offset_ms = ?status.offset_ms,
+                        "Clock check completed"
+                    );
+                }
+                warned = attention;
+
+                *self.status.write().unwrap() = Some(status);
+            }
+        });
+    }
+
+    /// Latest check result for API responses
+    pub fn info(&self) -> Option<TimeSyncInfo> {
+        let status = self.status.read().unwrap();
+        status
+            .as_ref()
+            .map(|s| to_info(s, self.config.drift_warning_ms))
+    }
+}
+
+fn to_info(status: &TimeSyncStatus, drift_warning_ms: u64) -> TimeSyncInfo {
+    let source = match status.source {
+        TimeSource::Chrony => "chrony",
+        TimeSource::Timedatectl => "timedatectl",
+        TimeSource::W32Time => "w32time",
+        TimeSource::Ntp => "ntp",
+        TimeSource::Gps => "gps",
+        TimeSource::None => "none",
+    };
+
+    TimeSyncInfo {
+        source: source.to_string(),
+        synchronized: status.synchronized,
+        offset_ms: status.offset_ms,
+        reference: status.reference.clone(),
+        warning: status.needs_attention(drift_warning_ms),
+        drift_warning_ms,
+        checked_at: status.checked_at,
+        error: status.error.clone(),
+    }
+}
@@ -0,0 +1,151 @@
+//! Token state kept across restarts
+//!
+//! Holds what [`AuthService`](crate::auth::AuthService) needs to honour
+//! revocations and refresh tokens after a restart: digests of live refresh
+//! tokens, denylisted access token IDs, and the per-user token generation
+//! that revoking all of a user's tokens advances. Expired rows are dropped
+//! when the store is opened.
+
+use crate::auth::RefreshToken;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use rusqlite::{Connection, params};
+use std::path::Path;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS refresh_tokens (
+        digest TEXT PRIMARY KEY,
+        username TEXT NOT NULL,
+        expires_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS revoked_tokens (
+        jti TEXT PRIMARY KEY,
+        expires_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS token_generations (
+        user_id TEXT PRIMARY KEY,
+        generation INTEGER NOT NULL
+    );
+";
+
+/// Persisted state, as loaded when the store is opened
+#[derive(Debug, Default)]
+pub struct TokenState {
+    /// Refresh tokens by digest
+    pub refresh_tokens: Vec<(String, RefreshToken)>,
+    /// Denylisted access token IDs and when each token expires
+    pub revoked_tokens: Vec<(String, i64)>,
+    /// Token generation by user ID
+    pub generations: Vec<(String, u64)>,
+}
+
+pub struct TokenStore {
+    conn: Mutex<Connection>,
+}
+
+impl TokenStore {
+    /// Open the database, creating it if needed, and load its live state
+    pub fn open(path: &Path) -> Result<(Self, TokenState)> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let conn =
+            Connection::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create the token tables")?;
+
+        let now = Utc::now().timestamp();
+        conn.execute(
+            "DELETE FROM refresh_tokens WHERE expires_at <= ?1",
+            params![now],
+        )?;
+        conn.execute(
+            "DELETE FROM revoked_tokens WHERE expires_at <= ?1",
+            params![now],
+        )?;
+
+        let refresh_tokens = conn
+            .prepare("SELECT digest, username, expires_at FROM refresh_tokens")?
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    RefreshToken {
+                        username: row.get(1)?,
+                        expires_at: DateTime::from_timestamp(row.get(2)?, 0).unwrap_or_default(),
+                    },
+                ))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        let revoked_tokens = conn
+            .prepare("SELECT jti, expires_at FROM revoked_tokens")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        let generations = conn
+            .prepare("SELECT user_id, generation FROM token_generations")?
+            .query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as u64)))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let state = TokenState {
+            refresh_tokens,
+            revoked_tokens,
+            generations,
+        };
+        Ok((
+            Self {
+                conn: Mutex::new(conn),
+            },
+            state,
+        ))
+    }
+
+    pub fn insert_refresh_token(&self, digest: &str, token: &RefreshToken) -> Result<()> {
+        self.conn.lock().execute(
+            "INSERT OR REPLACE INTO refresh_tokens (digest, username, expires_at)
+             VALUES (?1, ?2, ?3)",
+            params![digest, token.username, token.expires_at.timestamp()],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_refresh_token(&self, digest: &str) -> Result<()> {
+        self.conn.lock().execute(
+            "DELETE FROM refresh_tokens WHERE digest = ?1",
+            params![digest],
+        )?;
+        Ok(())
+    }
+
+    /// Remove every refresh token of a user
+    pub fn remove_user_refresh_tokens(&self, username: &str) -> Result<()> {
+        self.conn.lock().execute(
+            "DELETE FROM refresh_tokens WHERE username = ?1",
+            params![username],
+        )?;
+        Ok(())
+    }
+
+    /// Denylist an access token ID until `expires_at`
+    pub fn revoke_token(&self, jti: &str, expires_at: i64) -> Result<()> {
+        let conn = self.conn.lock();
+        conn.execute(
+            "DELETE FROM revoked_tokens WHERE expires_at <= ?1",
+            params![Utc::now().timestamp()],
+        )?;
+        conn.execute(
+            "INSERT OR REPLACE INTO revoked_tokens (jti, expires_at) VALUES (?1, ?2)",
+            params![jti, expires_at],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_generation(&self, user_id: &str, generation: u64) -> Result<()> {
+        self.conn.lock().execute(
+            "INSERT OR REPLACE INTO token_generations (user_id, generation) VALUES (?1, ?2)",
+            params![user_id, generation as i64],
+        )?;
+        Ok(())
+    }
+}
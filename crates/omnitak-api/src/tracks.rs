@@ -0,0 +1,449 @@
+//! Track table for the delta stream
+//!
+//! Keeps the latest position of every track the distributor sends out and
+//! publishes changes as numbered GeoJSON deltas (`added`, `updated`,
+//! `removed`), so the web dashboard and external web maps can hold
+//! thousands of tracks without re-fetching full snapshots. Clients apply
+//! deltas in sequence and ask for a resync when they see a gap.
+//!
+//! Tracks are removed when they go stale or when a `t-x-d-d` delete event
+//! for their UID arrives.
+//!
+//! Each track remembers the connection its latest position came from, so
+//! snapshots and deltas can be narrowed to the tracks a client may see.
+
+use async_trait::async_trait;
+use chrono::Utc;
+use omnitak_cot::Event;
+use omnitak_pool::{ConnectionId, EventSink, SinkError, SinkRecord};
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::types::{
+    PointGeometry, TrackCollection, TrackFeature, TrackProperties, WsServerMessage,
+};
+
+/// CoT type that deletes the linked UID
+const DELETE_TYPE: &str = "t-x-d-d";
+
+/// How often stale tracks are removed
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Deltas buffered per subscriber before it must resync
+const DELTA_CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Default)]
+struct TrackState {
+    seq: u64,
+    tracks: HashMap<String, SourcedTrack>,
+}
+
+/// A track and the connection its latest position came from
+#[derive(Debug, Clone)]
+pub struct SourcedTrack {
+    pub feature: TrackFeature,
+    pub source: Option<ConnectionId>,
+}
+
+/// Changes accumulated while applying a batch
+#[derive(Default)]
+struct Changes {
+    added: HashMap<String, SourcedTrack>,
+    updated: HashMap<String, SourcedTrack>,
+    removed: HashSet<String>,
+}
+
+impl Changes {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// The changes of one batch, before they are narrowed to a client
+#[derive(Debug, Clone)]
+pub struct TrackChanges {
+    pub seq: u64,
+    pub added: Vec<SourcedTrack>,
+    pub updated: Vec<SourcedTrack>,
+    /// UIDs of removed tracks
+    pub removed: Vec<String>,
+}
+
+impl TrackChanges {
+    /// The `TrackDelta` for a client that may see tracks from the sources
+    /// `visible` accepts. `shown` holds the UIDs the client was sent and is
+    /// kept current: a track that moves to a source the client may not see
+    /// is removed for it, and removals of tracks it never saw are left out.
+    /// The delta is sent even when empty so the sequence has no gaps.
+    pub fn delta_for(
+        &self,
+        visible: impl Fn(Option<&str>) -> bool,
+        shown: &mut HashSet<String>,
+    ) -> WsServerMessage {
+        let mut added = Vec::new();
+        let mut updated = Vec::new();
+        let mut removed = Vec::new();
+        for track in self.added.iter().chain(&self.updated) {
+            let id = &track.feature.id;
+            if visible(track.source.as_deref()) {
+                if shown.insert(id.clone()) {
+                    added.push(track.feature.clone());
+                } else {
+                    updated.push(track.feature.clone());
+                }
+            } else if shown.remove(id) {
+                removed.push(id.clone());
+            }
+        }
+        for uid in &self.removed {
+            if shown.remove(uid) {
+                removed.push(uid.clone());
+            }
+        }
+        WsServerMessage::TrackDelta {
+            seq: self.seq,
+            added,
+            updated,
+            removed,
+        }
+    }
+}
+
+/// Current tracks and the delta broadcast
+pub struct TrackStore {
+    state: Mutex<TrackState>,
+    tx: broadcast::Sender<TrackChanges>,
+}
+
+impl TrackStore {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(TrackState::default()),
+            tx: broadcast::channel(DELTA_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// Receive the changes behind `TrackDelta` messages; subscribe before
+    /// taking the snapshot the deltas are applied to
+    pub fn subscribe(&self) -> broadcast::Receiver<TrackChanges> {
+        self.tx.subscribe()
+    }
+
+    /// All current tracks
+    pub fn snapshot(&self) -> TrackCollection {
+        self.snapshot_for(|_| true)
+    }
+
+    /// Current tracks from the sources `visible` accepts
+    pub fn snapshot_for(&self, visible: impl Fn(Option<&str>) -> bool) -> TrackCollection {
+        let state = self.state.lock();
+        TrackCollection {
+            kind: "FeatureCollection".to_string(),
+            seq: state.seq,
+            features: state
+                .tracks
+                .values()
+                .filter(|t| visible(t.source.as_deref()))
+                .map(|t| t.feature.clone())
+                .collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().tracks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Apply a batch of CoT messages and publish one delta for it
+    pub fn apply<'a>(&self, messages: impl IntoIterator<Item = &'a [u8]>) {
+        self.apply_from(messages.into_iter().map(|data| (data, None)));
+    }
+
+    /// Apply a batch of CoT messages, each with the connection it came
+    /// from, and publish one delta for it
+    pub fn apply_from<'a>(&self, messages: impl IntoIterator<Item = (&'a [u8], Option<&'a str>)>) {
+        let events: Vec<(Event, &[u8], Option<&str>)> = messages
+            .into_iter()
+            .filter_map(|(data, source)| {
+                Some((omnitak_cot::parser::parse_any(data).ok()?, data, source))
+            })
+            .collect();
+        if events.is_empty() {
+            return;
+        }
+
+        let now = Utc::now();
+        let mut state = self.state.lock();
+        let mut changes = Changes::default();
+        for (event, data, source) in events {
+            if event.event_type == DELETE_TYPE {
+                for target in linked_uids(data) {
+                    remove(&mut state, &mut changes, &target);
+                }
+                continue;
+            }
+            // Taskings and pings are not map objects
+            if event.event_type.starts_with("t-") || event.stale <= now {
+                continue;
+            }
+
+            let track = SourcedTrack {
+                feature: to_feature(&event),
+                source: source.map(str::to_string),
+            };
+            let id = track.feature.id.clone();
+            changes.removed.remove(&id);
+            if changes.added.contains_key(&id) {
+                changes.added.insert(id.clone(), track.clone());
+            } else if state.tracks.contains_key(&id) {
+                changes.updated.insert(id.clone(), track.clone());
+            } else {
+                changes.added.insert(id.clone(), track.clone());
+            }
+            state.tracks.insert(id, track);
+        }
+        self.publish(&mut state, changes);
+    }
+
+    /// Remove tracks past their stale time
+    pub fn expire(&self) {
+        let now = Utc::now();
+        let mut state = self.state.lock();
+        let stale: Vec<String> = state
+            .tracks
+            .values()
+            .filter(|t| t.feature.properties.stale <= now)
+            .map(|t| t.feature.id.clone())
+            .collect();
+        let mut changes = Changes::default();
+        for uid in stale {
+            remove(&mut state, &mut changes, &uid);
+        }
+        self.publish(&mut state, changes);
+    }
+
+    /// Start removing stale tracks in the background
+    pub fn start(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+            loop {
+                interval.tick().await;
+                self.expire();
+            }
+        });
+    }
+
+    /// Bump the sequence and broadcast, while still holding the state lock
+    /// so deltas go out in sequence order
+    fn publish(&self, state: &mut TrackState, changes: Changes) {
+        if changes.is_empty() {
+            return;
+        }
+        state.seq += 1;
+        // No subscribers is fine
+        let _ = self.tx.send(TrackChanges {
+            seq: state.seq,
+            added: changes.added.into_values().collect(),
+            updated: changes.updated.into_values().collect(),
+            removed: changes.removed.into_iter().collect(),
+        });
+    }
+}
+
+impl Default for TrackStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl EventSink for TrackStore {
+    fn name(&self) -> &str {
+        "track-stream"
+    }
+
+    async fn send_batch(&self, records: &[SinkRecord]) -> Result<(), SinkError> {
+        self.apply_from(
+            records
+                .iter()
+                .map(|r| (r.payload.as_slice(), r.source.as_deref())),
+        );
+        Ok(())
+    }
+}
+
+fn remove(state: &mut TrackState, changes: &mut Changes, uid: &str) {
+    changes.updated.remove(uid);
+    let added_this_batch = changes.added.remove(uid).is_some();
+    if state.tracks.remove(uid).is_some() && !added_this_batch {
+        changes.removed.insert(uid.to_string());
+    }
+}
+
+/// UIDs of the `<link>` elements in a raw XML message; the CoT parser
+/// does not keep links
+fn linked_uids(data: &[u8]) -> Vec<String> {
+    let text = String::from_utf8_lossy(data);
+    let mut uids = Vec::new();
+    let mut rest = text.as_ref();
+    while let Some(start) = rest.find("<link ") {
+        rest = &rest[start + 6..];
+        let element = &rest[..rest.find('>').unwrap_or(rest.len())];
+        if let Some(uid) = element
+            .split_once("uid=\"")
+            .and_then(|(_, v)| v.split_once('"'))
+            .map(|(uid, _)| uid)
+        {
+            uids.push(uid.to_string());
+        }
+    }
+    uids
+}
+
+fn to_feature(event: &Event) -> TrackFeature {
+    TrackFeature {
+        kind: "Feature".to_string(),
+        id: event.uid.clone(),
+        geometry: PointGeometry {
+            kind: "Point".to_string(),
+            coordinates: [event.point.lon, event.point.lat, event.point.hae],
+        },
+        properties: TrackProperties {
+            cot_type: event.event_type.clone(),
+            callsign: event.callsign().map(str::to_string),
+            time: event.time,
+            stale: event.stale,
+            speed: event.speed(),
+            course: event.course(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cot(uid: &str, cot_type: &str, lat: f64, stale: &str, link: &str) -> Vec<u8> {
+        format!(
+            r#"<event version="2.0" uid="{uid}" type="{cot_type}" time="2026-01-01T00:00:00Z" start="2026-01-01T00:00:00Z" stale="{stale}" how="m-g"><point lat="{lat}" lon="-117.2" hae="0" ce="10" le="10"/><detail><contact callsign="{uid}"/>{link}</detail></event>"#
+        )
+        .into_bytes()
+    }
+
+    fn delta(rx: &mut broadcast::Receiver<TrackChanges>) -> (u64, usize, usize, Vec<String>) {
+        // As seen by a client that was sent every track so far
+        let mut shown = HashSet::new();
+        let changes = rx.try_recv().unwrap();
+        shown.extend(changes.removed.iter().cloned());
+        shown.extend(changes.updated.iter().map(|t| t.feature.id.clone()));
+        match changes.delta_for(|_| true, &mut shown) {
+            WsServerMessage::TrackDelta {
+                seq,
+                added,
+                updated,
+                removed,
+            } => (seq, added.len(), updated.len(), removed),
+            other => panic!("expected delta, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_deltas() {
+        let store = TrackStore::new();
+        let mut rx = store.subscribe();
+        let live = "2099-01-01T00:00:00Z";
+
+        let first = [
+            cot("A", "a-f-G", 34.1, live, ""),
+            cot("B", "a-h-G", 34.2, live, ""),
+        ];
+        store.apply(first.iter().map(Vec::as_slice));
+        assert_eq!(delta(&mut rx), (1, 2, 0, vec![]));
+
+        // A moves, C appears, B is deleted
+        let second = [
+            cot("A", "a-f-G", 34.15, live, ""),
+            cot("C", "a-n-G", 34.3, live, ""),
+            cot(
+                "DEL",
+                "t-x-d-d",
+                0.0,
+                live,
+                r#"<link uid="B" relation="none" type="a-h-G"/>"#,
+            ),
+        ];
+        store.apply(second.iter().map(Vec::as_slice));
+        assert_eq!(delta(&mut rx), (2, 1, 1, vec!["B".to_string()]));
+
+        let snapshot = store.snapshot();
+        assert_eq!(snapshot.seq, 2);
+        assert_eq!(snapshot.features.len(), 2);
+
+        // Already-stale events are not tracks and publish nothing
+        store.apply(
+            [cot("D", "a-f-G", 34.4, "2020-01-01T00:00:00Z", "")]
+                .iter()
+                .map(Vec::as_slice),
+        );
+        assert!(rx.try_recv().is_err());
+        assert_eq!(store.len(), 2);
+    }
+
+    #[test]
+    fn test_deltas_narrowed_to_visible_sources() {
+        let store = TrackStore::new();
+        let mut rx = store.subscribe();
+        let live = "2099-01-01T00:00:00Z";
+        let blue = |source: Option<&str>| source == Some("blue-conn");
+        let mut shown = HashSet::new();
+
+        let (a, b) = (
+            cot("A", "a-f-G", 34.1, live, ""),
+            cot("B", "a-h-G", 34.2, live, ""),
+        );
+        store.apply_from([
+            (a.as_slice(), Some("blue-conn")),
+            (b.as_slice(), Some("red-conn")),
+        ]);
+        let snapshot = store.snapshot_for(blue);
+        assert_eq!(snapshot.features.len(), 1);
+        assert_eq!(snapshot.features[0].id, "A");
+
+        let WsServerMessage::TrackDelta { added, .. } =
+            rx.try_recv().unwrap().delta_for(blue, &mut shown)
+        else {
+            panic!("expected delta");
+        };
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[0].id, "A");
+
+        // A moves to a connection the client can't see, B is deleted
+        let deleted = cot(
+            "DEL",
+            "t-x-d-d",
+            0.0,
+            live,
+            r#"<link uid="B" relation="none" type="a-h-G"/>"#,
+        );
+        store.apply_from([(a.as_slice(), Some("red-conn")), (deleted.as_slice(), None)]);
+        let WsServerMessage::TrackDelta {
+            seq,
+            added,
+            updated,
+            removed,
+        } = rx.try_recv().unwrap().delta_for(blue, &mut shown)
+        else {
+            panic!("expected delta");
+        };
+        assert_eq!(seq, 2);
+        assert!(added.is_empty() && updated.is_empty());
+        assert_eq!(removed, ["A"]);
+        assert!(shown.is_empty());
+    }
+}
@@ -10,7 +10,8 @@ use validator::Validate;
 // Common Types
 // ============================================================================
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+/// Connection state; sorted in this order
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "lowercase")]
 pub enum ConnectionStatus {
     Connected,
@@ -50,6 +51,15 @@ pub enum FilterAction {
 // System Status
 // ============================================================================
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct HealthResponse {
+    /// Always `healthy` when the server answers
+    pub status: String,
+
+    /// Current timestamp
+    pub timestamp: DateTime<Utc>,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct SystemStatus {
     /// System uptime in seconds
@@ -75,6 +85,108 @@ pub struct SystemStatus {
 
     /// Current timestamp
     pub timestamp: DateTime<Utc>,
+
+    /// System clock synchronization (None until the first check completes)
+    pub time_sync: Option<TimeSyncInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TimeSyncInfo {
+    /// Where the status came from (chrony, timedatectl, w32time, ntp, gps, none)
+    pub source: String,
+
+    /// Whether the system reports its clock as synchronized (null if unknown)
+    pub synchronized: Option<bool>,
+
+    /// Local clock minus reference time in milliseconds (null if unknown)
+    pub offset_ms: Option<f64>,
+
+    /// Reference server or clock
+    pub reference: Option<String>,
+
+    /// Whether the clock is unsynchronized or drifting beyond the threshold
+    pub warning: bool,
+
+    /// Drift warning threshold in milliseconds
+    pub drift_warning_ms: u64,
+
+    /// When the check ran
+    pub checked_at: DateTime<Utc>,
+
+    /// Error from the last failed query
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ResourceUsage {
+    /// Process CPU usage since the previous sample (100 = one full core)
+    pub cpu_percent: f32,
+
+    /// Logical CPUs available to the process
+    pub cpu_count: usize,
+
+    /// Resident memory in bytes
+    pub memory_rss_bytes: u64,
+
+    /// Virtual memory in bytes
+    pub memory_virtual_bytes: u64,
+
+    /// Open file descriptors (null where the platform does not expose them)
+    pub open_fds: Option<u64>,
+
+    /// Open sockets, a subset of `open_fds` (null if unknown)
+    pub open_sockets: Option<u64>,
+
+    /// Tokio worker threads
+    pub tokio_workers: usize,
+
+    /// Tokio tasks alive
+    pub tokio_tasks: usize,
+
+    /// Tasks waiting in the tokio global queue
+    pub tokio_queue_depth: usize,
+
+    /// When the sample was taken
+    pub timestamp: DateTime<Utc>,
+}
+
+// ============================================================================
+// Load Balancer Hints
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LoadState {
+    /// Accepting new clients
+    Up,
+    /// Keep existing clients but send no new ones
+    Drain,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LoadReport {
+    pub state: LoadState,
+
+    /// Relative capacity for new clients, 0-100 (0 while draining)
+    pub weight: u8,
+
+    /// Active pool connections
+    pub active_connections: usize,
+
+    /// Pool connection limit
+    pub max_connections: usize,
+
+    /// Free fraction of the connection limit (0.0-1.0)
+    pub connection_headroom: f64,
+
+    /// Fill of the aggregator queue (0.0-1.0)
+    pub aggregator_queue: f64,
+
+    /// Fill of the distributor queue (0.0-1.0)
+    pub distributor_queue: f64,
+
+    /// When the report was taken
+    pub timestamp: DateTime<Utc>,
 }
 
 // ============================================================================
@@ -121,6 +233,10 @@ pub struct ConnectionInfo {
 
     /// Error message if status is Error
     pub error: Option<String>,
+
+    /// Tenant namespace, absent for the default namespace
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -129,7 +245,61 @@ pub struct ConnectionList {
     pub total: usize,
 }
 
-#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+/// Sort keys of `GET /api/v1/connections`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionSort {
+    Name,
+    Status,
+    /// Bytes sent and received
+    Traffic,
+}
+
+/// One window of per-connection throughput and write latency
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ConnectionStatsSample {
+    /// End of the sample window
+    pub timestamp: DateTime<Utc>,
+
+    /// Messages queued to the connection per second
+    pub messages_sent_per_sec: f64,
+
+    /// Messages received from the connection per second
+    pub messages_received_per_sec: f64,
+
+    /// Mean write latency in milliseconds (null if nothing was written)
+    pub avg_write_latency_ms: Option<f64>,
+
+    /// Slowest write in the window in milliseconds
+    pub max_write_latency_ms: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ConnectionStats {
+    /// Connection identifier
+    pub id: Uuid,
+
+    /// Seconds covered by each sample
+    pub sample_interval_secs: u64,
+
+    /// Recent samples, oldest first
+    pub samples: Vec<ConnectionStatsSample>,
+
+    /// FreeTAKServer status, if the connection is configured as FTS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fts: Option<crate::fts::FtsStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ConnectionStatsSnapshot {
+    /// Seconds covered by each sample
+    pub sample_interval_secs: u64,
+
+    /// Recent samples per connection name, oldest first
+    pub connections: std::collections::BTreeMap<String, Vec<ConnectionStatsSample>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
 pub struct CreateConnectionRequest {
     /// Connection name/label
     #[validate(length(min = 1, max = 100))]
@@ -158,9 +328,72 @@ pub struct CreateConnectionRequest {
     #[validate(length(max = 500))]
     pub tls_key_path: Option<String>,
 
+    /// Stored certificate ID (for TLS connections, takes precedence over paths)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate_id: Option<Uuid>,
+
     /// Validate TLS certificates
     #[serde(default = "default_validate_certs")]
     pub validate_certs: bool,
+
+    /// Backoff policy used when auto-reconnect is enabled
+    #[serde(default)]
+    #[validate(nested)]
+    pub reconnect: ReconnectPolicy,
+}
+
+/// Auto-reconnect backoff policy
+///
+/// The delay before attempt `n` is `initial_backoff_ms * backoff_multiplier^(n-1)`,
+/// capped at `max_backoff_secs`.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+pub struct ReconnectPolicy {
+    /// Maximum reconnection attempts (null = unlimited)
+    #[serde(default = "default_reconnect_max_attempts")]
+    #[validate(range(min = 1, max = 10000))]
+    pub max_attempts: Option<u32>,
+
+    /// Delay before the first reconnection attempt in milliseconds
+    #[serde(default = "default_initial_backoff_ms")]
+    #[validate(range(min = 100, max = 3600000))]
+    pub initial_backoff_ms: u64,
+
+    /// Upper bound on the delay between attempts in seconds
+    #[serde(default = "default_max_backoff_secs")]
+    #[validate(range(min = 1, max = 86400))]
+    pub max_backoff_secs: u64,
+
+    /// Growth factor applied to the delay after each attempt
+    #[serde(default = "default_backoff_multiplier")]
+    #[validate(range(min = 1.0, max = 10.0))]
+    pub backoff_multiplier: f64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_reconnect_max_attempts(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_secs: default_max_backoff_secs(),
+            backoff_multiplier: default_backoff_multiplier(),
+        }
+    }
+}
+
+fn default_reconnect_max_attempts() -> Option<u32> {
+    Some(5)
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_max_backoff_secs() -> u64 {
+    60
+}
+
+fn default_backoff_multiplier() -> f64 {
+    2.0
 }
 
 fn default_auto_reconnect() -> bool {
@@ -182,6 +415,616 @@ pub struct DeleteConnectionResponse {
     pub message: String,
 }
 
+/// Connections to create together; if any cannot be created, none are
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BulkConnectionRequest {
+    pub connections: Vec<CreateConnectionRequest>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkConnectionStatus {
+    /// The connection was created
+    Created,
+    /// The connection is invalid or could not be created
+    Failed,
+    /// The connection was created, then removed because another failed
+    RolledBack,
+    /// The connection was not attempted because another failed
+    Skipped,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BulkConnectionResult {
+    /// Position in the request
+    pub index: usize,
+
+    pub name: String,
+
+    pub status: BulkConnectionStatus,
+
+    /// Connection ID, once created
+    pub id: Option<Uuid>,
+
+    /// Why the connection failed
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BulkConnectionResponse {
+    /// Whether every connection was created
+    pub committed: bool,
+
+    pub results: Vec<BulkConnectionResult>,
+}
+
+/// Connection settings to try without adding a connection
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+pub struct TestConnectionRequest {
+    /// Connection type (tcpclient or tlsclient)
+    pub connection_type: ConnectionType,
+
+    /// Remote address (hostname or IP)
+    #[validate(length(min = 1, max = 255))]
+    pub address: String,
+
+    /// Remote port (1-65535)
+    #[validate(range(min = 1, max = 65535))]
+    pub port: u16,
+
+    /// TLS certificate path (for TLS connections)
+    #[validate(length(max = 500))]
+    pub tls_cert_path: Option<String>,
+
+    /// TLS key path (for TLS connections)
+    #[validate(length(max = 500))]
+    pub tls_key_path: Option<String>,
+
+    /// Stored certificate ID (for TLS connections, takes precedence over paths)
+    #[serde(default)]
+    pub certificate_id: Option<Uuid>,
+
+    /// Validate TLS certificates
+    #[serde(default = "default_validate_certs")]
+    pub validate_certs: bool,
+
+    /// Send a TAK ping once connected and wait for the pong
+    #[serde(default)]
+    pub tak_ping: bool,
+
+    /// Time limit for each step in seconds
+    #[serde(default = "default_test_timeout_secs")]
+    #[validate(range(min = 1, max = 60))]
+    pub timeout_secs: u64,
+}
+
+fn default_test_timeout_secs() -> u64 {
+    10
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionTestStage {
+    /// Load the client certificate and key
+    ClientCertificate,
+    /// Resolve the server name
+    Resolve,
+    /// Open the TCP connection
+    Connect,
+    /// Complete the TLS handshake
+    TlsHandshake,
+    /// Send a TAK ping and wait for the pong
+    TakPing,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ConnectionTestStep {
+    pub stage: ConnectionTestStage,
+
+    pub success: bool,
+
+    pub duration_ms: u64,
+
+    /// What the step found, or why it failed
+    pub message: String,
+}
+
+/// Certificate the server presented
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ServerCertificateInfo {
+    pub subject: String,
+
+    pub issuer: String,
+
+    pub not_before: String,
+
+    pub not_after: String,
+
+    /// SHA-256 fingerprint
+    pub fingerprint: String,
+
+    pub expired: bool,
+
+    /// Whether the certificate verified; unknown when validation is off
+    pub trusted: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TestConnectionResponse {
+    /// Whether every step succeeded
+    pub success: bool,
+
+    /// Steps in the order they ran; testing stops at the first failure
+    pub steps: Vec<ConnectionTestStep>,
+
+    /// Addresses the server name resolved to
+    pub resolved_addresses: Vec<String>,
+
+    /// Address the connection was made to
+    pub connected_address: Option<String>,
+
+    pub server_certificate: Option<ServerCertificateInfo>,
+}
+
+// ============================================================================
+// Certificate Store
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CertificateFormat {
+    Pem,
+    Pkcs12,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ImportCertificateRequest {
+    /// Certificate name/label
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+
+    /// Encoding of the uploaded data
+    pub format: CertificateFormat,
+
+    /// Base64-encoded client certificate (PEM) or PKCS#12 archive
+    #[validate(length(min = 1))]
+    pub certificate: String,
+
+    /// Base64-encoded private key (PEM format only)
+    pub private_key: Option<String>,
+
+    /// Base64-encoded CA certificate (PEM format only)
+    pub ca_certificate: Option<String>,
+
+    /// PKCS#12 password
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StoredCertificateInfo {
+    /// Certificate ID, usable as `certificate_id` when creating connections
+    pub id: Uuid,
+
+    /// Certificate name/label
+    pub name: String,
+
+    /// Where the certificate came from (upload, adb, ...)
+    pub source: String,
+
+    /// Client certificate subject common name
+    pub subject_cn: Option<String>,
+
+    /// Client certificate expiry
+    pub not_after: Option<String>,
+
+    /// Whether CA certificates are included
+    pub has_ca: bool,
+
+    /// Import timestamp
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CertificateList {
+    pub certificates: Vec<StoredCertificateInfo>,
+    pub total: usize,
+}
+
+/// File found in an uploaded certificate or package
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UploadedCertificateFile {
+    /// File name as uploaded, or the path inside its ZIP archive
+    pub name: String,
+
+    /// What the file holds, told from its contents
+    pub kind: omnitak_cert::CertificateFileKind,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CertificateUploadResponse {
+    /// The certificate added to the store
+    pub certificate: StoredCertificateInfo,
+
+    /// Files the certificate was loaded from
+    pub files: Vec<UploadedCertificateFile>,
+}
+
+// ============================================================================
+// Package Uploads
+// ============================================================================
+
+/// What an uploaded package is, told from its contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageKind {
+    /// ZIP archive carrying certificates or server connections
+    ConnectionPackage,
+    /// Any other ZIP archive
+    MissionPackage,
+    /// Anything else
+    File,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PackageUploadResponse {
+    /// SHA-256 of the contents, which TAK clients refer to the package by
+    pub hash: String,
+
+    pub name: String,
+
+    /// Content type, sniffed for ZIP archives
+    pub mime_type: String,
+
+    /// Size in bytes
+    pub size: u64,
+
+    /// Search keywords
+    pub keywords: Vec<String>,
+
+    pub kind: PackageKind,
+
+    /// Certificate-related files inside the package
+    pub certificates: Vec<UploadedCertificateFile>,
+
+    /// Where TAK clients download the package
+    pub content_path: String,
+}
+
+// ============================================================================
+// Data Package Import
+// ============================================================================
+
+/// Streaming connection found in a data package's preference files
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PackageConnection {
+    /// Position of the stream in the package, used to select it for import
+    pub index: usize,
+
+    /// Stream description
+    pub name: String,
+
+    /// Remote address (hostname or IP)
+    pub address: String,
+
+    /// Remote port
+    pub port: u16,
+
+    /// Connection type the stream maps to, if it is supported
+    pub connection_type: Option<ConnectionType>,
+
+    /// Whether the stream is enabled on the device
+    pub enabled: bool,
+
+    /// Client certificate file in the package
+    pub certificate: Option<String>,
+
+    /// Trust store file in the package
+    pub truststore: Option<String>,
+
+    /// Why the connection cannot be created, if it cannot
+    pub unsupported_reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PackageConnectionList {
+    /// Package name from the manifest
+    pub package_name: Option<String>,
+    pub connections: Vec<PackageConnection>,
+}
+
+/// Connection created from a data package
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ImportedConnection {
+    /// Stream index in the package
+    pub index: usize,
+
+    /// New connection ID
+    pub id: Uuid,
+
+    pub name: String,
+
+    /// Stored certificate created for the connection
+    pub certificate_id: Option<Uuid>,
+}
+
+/// Stream that was selected but could not be created
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SkippedConnection {
+    pub index: usize,
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ImportConnectionsResponse {
+    pub created: Vec<ImportedConnection>,
+    pub skipped: Vec<SkippedConnection>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationSeverity {
+    /// The package will not import as intended
+    Error,
+    /// The package imports, but probably not as intended
+    Warning,
+}
+
+/// Problem found in a data package
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PackageValidationIssue {
+    pub severity: ValidationSeverity,
+
+    /// Archive entry the issue concerns
+    pub path: Option<String>,
+
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PackageValidationReport {
+    /// Package name from the manifest
+    pub package_name: Option<String>,
+
+    /// Whether the package has no errors (warnings are allowed)
+    pub valid: bool,
+
+    pub issues: Vec<PackageValidationIssue>,
+}
+
+// ============================================================================
+// Alerting
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AlertChannelList {
+    pub channels: Vec<crate::alerts::AlertChannel>,
+    pub total: usize,
+}
+
+/// Send a test alert, either to one channel or through normal routing
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct TestAlertRequest {
+    /// Deliver only to this channel, ignoring its severity threshold
+    #[serde(default)]
+    pub channel: Option<String>,
+    #[serde(default)]
+    pub severity: crate::alerts::AlertSeverity,
+    #[serde(default = "default_test_alert_title")]
+    #[validate(length(min = 1, max = 200))]
+    pub title: String,
+    #[serde(default = "default_test_alert_message")]
+    #[validate(length(max = 4000))]
+    pub message: String,
+}
+
+fn default_test_alert_title() -> String {
+    "Test alert".to_string()
+}
+
+fn default_test_alert_message() -> String {
+    "This is a test alert from OmniTAK.".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TestAlertResponse {
+    pub deliveries: Vec<crate::alerts::AlertDelivery>,
+}
+
+// ============================================================================
+// Client Access
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ClientAccessRuleList {
+    /// Rules in the order they are tried
+    pub rules: Vec<crate::client_access::ClientAccessRule>,
+    /// Access of clients no rule matches
+    pub default_access: crate::client_access::ClientAccess,
+    pub total: usize,
+}
+
+// ============================================================================
+// Emergencies
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmergencyStatus {
+    Active,
+    Acknowledged,
+    Cleared,
+    Cancelled,
+}
+
+impl From<omnitak_pool::EmergencyState> for EmergencyStatus {
+    fn from(state: omnitak_pool::EmergencyState) -> Self {
+        match state {
+            omnitak_pool::EmergencyState::Active => Self::Active,
+            omnitak_pool::EmergencyState::Acknowledged => Self::Acknowledged,
+            omnitak_pool::EmergencyState::Cleared => Self::Cleared,
+            omnitak_pool::EmergencyState::Cancelled => Self::Cancelled,
+        }
+    }
+}
+
+/// An emergency beacon and its handling state
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EmergencyInfo {
+    /// Beacon UID (CoT event UID)
+    pub uid: String,
+    /// Emergency kind, e.g. "911 Alert"
+    pub kind: String,
+    pub cot_type: String,
+    pub callsign: Option<String>,
+    /// Connection the beacon was received from
+    pub source_connection: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub status: EmergencyStatus,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub acknowledged_by: Option<String>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    pub cleared_by: Option<String>,
+    pub cleared_at: Option<DateTime<Utc>>,
+}
+
+impl From<omnitak_pool::Emergency> for EmergencyInfo {
+    fn from(e: omnitak_pool::Emergency) -> Self {
+        let time = |ms: u64| DateTime::from_timestamp_millis(ms as i64).unwrap_or_default();
+        Self {
+            uid: e.uid,
+            kind: e.kind.label().to_string(),
+            cot_type: e.cot_type,
+            callsign: e.callsign,
+            source_connection: e.source,
+            lat: e.lat,
+            lon: e.lon,
+            status: e.state.into(),
+            first_seen: time(e.first_seen_ms),
+            last_seen: time(e.last_seen_ms),
+            acknowledged_by: e.acknowledged_by,
+            acknowledged_at: e.acknowledged_at_ms.map(time),
+            cleared_by: e.cleared_by,
+            cleared_at: e.cleared_at_ms.map(time),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct EmergencyList {
+    pub emergencies: Vec<EmergencyInfo>,
+    /// Beacons not yet acknowledged
+    pub active: usize,
+    pub total: usize,
+}
+
+// ============================================================================
+// Duplicates
+// ============================================================================
+
+/// Deduplication counts for one source connection
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SourceDuplicateInfo {
+    pub source: String,
+    /// Connection name, if the source is a configured connection
+    pub name: Option<String>,
+    /// Messages this source delivered first
+    pub unique: u64,
+    /// Messages another source had already delivered
+    pub duplicates: u64,
+    /// Messages this source had already delivered itself
+    pub repeats: u64,
+    /// Share of this source's messages another source delivered first
+    pub duplicate_ratio: f64,
+    /// Sources that delivered this source's duplicates first, most first
+    pub first_seen_from: Vec<DuplicateOrigin>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DuplicateOrigin {
+    pub source: String,
+    pub name: Option<String>,
+    /// Duplicates first delivered by this source
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DuplicateReport {
+    /// Sources with the highest duplicate ratio first
+    pub sources: Vec<SourceDuplicateInfo>,
+    pub total: usize,
+}
+
+// ============================================================================
+// Quarantine
+// ============================================================================
+
+/// A message from a strict source that failed validation
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct QuarantinedMessageInfo {
+    pub received_at: DateTime<Utc>,
+    pub trace_id: String,
+    /// UID of the event, if one could be read
+    pub uid: Option<String>,
+    /// Why the message was rejected
+    pub reason: String,
+    /// The message as received, with invalid UTF-8 replaced
+    pub data: String,
+}
+
+/// Quarantined messages of one source connection
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SourceQuarantineInfo {
+    pub source: String,
+    /// Connection name, if the source is connected
+    pub name: Option<String>,
+    /// Whether messages from this source are validated
+    pub strict: bool,
+    /// Messages quarantined since startup, including those no longer kept
+    pub total: u64,
+    /// Most recent quarantined messages, oldest first
+    pub messages: Vec<QuarantinedMessageInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct QuarantineReport {
+    pub sources: Vec<SourceQuarantineInfo>,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SetStrictValidationRequest {
+    /// Quarantine messages that fail CoT schema validation instead of
+    /// forwarding them
+    pub strict: bool,
+}
+
+// ============================================================================
+// FreeTAKServer
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct FtsStatusList {
+    pub servers: Vec<crate::fts::FtsStatus>,
+    pub total: usize,
+}
+
+/// Chat broadcast to every user on a FreeTAKServer
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct FtsChatRequest {
+    #[serde(default = "default_fts_chat_sender")]
+    #[validate(length(min = 1, max = 100))]
+    pub sender: String,
+    #[validate(length(min = 1, max = 4000))]
+    pub message: String,
+}
+
+fn default_fts_chat_sender() -> String {
+    "OmniTAK".to_string()
+}
+
 // ============================================================================
 // Filter Management
 // ============================================================================
@@ -224,6 +1067,10 @@ pub struct FilterRule {
     /// Number of messages matched
     pub match_count: u64,
 
+    /// Tenant namespace, absent for the default namespace
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+
     /// Created timestamp
     pub created_at: DateTime<Utc>,
 
@@ -338,8 +1185,50 @@ pub struct LoginResponse {
     /// Token expiration time
     pub expires_at: DateTime<Utc>,
 
+    /// Token for POST /api/v1/auth/refresh; each one works once
+    pub refresh_token: String,
+
+    /// Refresh token expiration time
+    pub refresh_expires_at: DateTime<Utc>,
+
     /// User role
     pub role: UserRole,
+
+    /// Custom role whose permissions replace those of `role`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_role: Option<String>,
+
+    /// What the user may do
+    #[serde(default)]
+    pub permissions: Vec<crate::rbac::Permission>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct RefreshRequest {
+    /// Refresh token from login or the previous refresh
+    #[validate(length(min = 1))]
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct LogoutRequest {
+    /// Refresh token to invalidate along with the access token
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+}
+
+/// Give either a token or a username; a username revokes every token the
+/// user holds
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct RevokeTokenRequest {
+    /// Access token or refresh token
+    #[serde(default)]
+    pub token: Option<String>,
+
+    /// User whose access and refresh tokens are all revoked
+    #[serde(default)]
+    #[validate(length(min = 1, max = 100))]
+    pub username: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
@@ -351,8 +1240,19 @@ pub struct ApiKeyRequest {
     /// API key role
     pub role: UserRole,
 
+    /// Custom role from the configuration whose permissions the key gets
+    /// instead of those of `role`
+    #[serde(default)]
+    #[validate(length(min = 1, max = 100))]
+    pub custom_role: Option<String>,
+
     /// Expiration time (optional)
     pub expires_at: Option<DateTime<Utc>>,
+
+    /// Tenant namespace to confine the key to. Keys created by a tenant
+    /// user always belong to that user's tenant.
+    #[validate(length(min = 1, max = 100))]
+    pub tenant: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -366,6 +1266,10 @@ pub struct ApiKeyResponse {
     /// Key name
     pub name: String,
 
+    /// Tenant namespace the key is confined to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+
     /// Created timestamp
     pub created_at: DateTime<Utc>,
 }
@@ -401,6 +1305,9 @@ pub enum WsClientMessage {
     /// Unsubscribe from system events
     UnsubscribeEvents,
 
+    /// Request a fresh track snapshot after a delta sequence gap
+    Resync,
+
     /// Ping to keep connection alive
     Ping,
 }
@@ -456,10 +1363,141 @@ pub enum WsServerMessage {
         message_type: String,
     },
 
+    /// Every current track; deltas continue from `seq + 1`
+    TrackSnapshot {
+        seq: u64,
+        features: Vec<TrackFeature>,
+    },
+
+    /// Track changes; apply only if `seq` is one more than the last seen,
+    /// otherwise send `resync`. `added` and `updated` are both upserts.
+    TrackDelta {
+        seq: u64,
+        added: Vec<TrackFeature>,
+        updated: Vec<TrackFeature>,
+        /// UIDs of removed tracks
+        removed: Vec<String>,
+    },
+
     /// Pong response
     Pong,
 }
 
+// ============================================================================
+// Track Stream (GeoJSON)
+// ============================================================================
+
+/// GeoJSON point geometry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PointGeometry {
+    /// Always `Point`
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// `[lon, lat, hae]`
+    pub coordinates: [f64; 3],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TrackProperties {
+    /// CoT type
+    pub cot_type: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callsign: Option<String>,
+
+    pub time: DateTime<Utc>,
+
+    pub stale: DateTime<Utc>,
+
+    /// Speed in meters per second
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f64>,
+
+    /// Course in degrees
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub course: Option<f64>,
+}
+
+/// A track as a GeoJSON feature; `id` is the CoT UID
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TrackFeature {
+    /// Always `Feature`
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    pub id: String,
+
+    pub geometry: PointGeometry,
+
+    pub properties: TrackProperties,
+}
+
+/// GeoJSON feature collection of current tracks
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TrackCollection {
+    /// Always `FeatureCollection`
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// Delta sequence number the snapshot is current to
+    pub seq: u64,
+
+    pub features: Vec<TrackFeature>,
+}
+
+// ============================================================================
+// Logging
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct LogLevels {
+    /// Level for everything without a more specific entry: trace, debug,
+    /// info, warn, error or off
+    pub level: String,
+
+    /// Levels for subsystems by tracing target, e.g. `omnitak_pool`
+    #[serde(default)]
+    pub levels: std::collections::BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LogLevelStatus {
+    /// Levels in effect
+    pub current: LogLevels,
+
+    /// Levels from the configuration file
+    pub configured: LogLevels,
+}
+
+// ============================================================================
+// Backup and Restore
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RestoreReport {
+    /// Format version of the restored backup
+    pub version: u32,
+
+    /// When the backup was created
+    pub created_at: DateTime<Utc>,
+
+    /// Version of the server that created the backup
+    pub server_version: String,
+
+    /// Whether the configuration file was replaced; the previous one is kept
+    /// with a `.bak` suffix and the new one takes effect on restart
+    pub config_restored: bool,
+
+    pub users_restored: usize,
+    pub api_keys_restored: usize,
+    pub connections_restored: usize,
+    pub plugins_restored: usize,
+
+    /// Parts of the backup that were not restored, and why
+    pub warnings: Vec<String>,
+}
+
 // ============================================================================
 // Error Responses
 // ============================================================================
@@ -508,8 +1546,8 @@ pub struct AuditLogEntry {
     /// User who performed the action
     pub user: String,
 
-    /// User role at time of action
-    pub role: UserRole,
+    /// User role at time of action (null if the request was not authenticated)
+    pub role: Option<UserRole>,
 
     /// Action performed
     pub action: String,
@@ -530,6 +1568,76 @@ pub struct AuditLogEntry {
     pub success: bool,
 }
 
+/// Filters, order and page of `GET /api/v1/audit`. Entries come newest
+/// first unless sorted otherwise.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditQuery {
+    /// Only entries at or after this time (RFC 3339)
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only entries before this time (RFC 3339)
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+
+    /// Only entries of this user
+    #[serde(default)]
+    pub user: Option<String>,
+
+    /// Only entries of these users. Set by the server, not the client, to
+    /// confine tenant users to their own tenant's entries.
+    #[serde(skip)]
+    pub users: Option<Vec<String>>,
+
+    /// Only entries of this action, e.g. `login_failed`
+    #[serde(default)]
+    pub action: Option<String>,
+
+    /// Only succeeded (true) or failed (false) actions
+    #[serde(default)]
+    pub success: Option<bool>,
+
+    /// Only entries whose user, action or resource contains this, ignoring
+    /// case
+    #[serde(default)]
+    pub search: Option<String>,
+
+    /// Sort key (default timestamp)
+    #[serde(default)]
+    pub sort: Option<AuditSort>,
+
+    /// Sort direction (default desc)
+    #[serde(default)]
+    pub order: Option<crate::pagination::SortOrder>,
+
+    /// Entries to skip
+    #[serde(default)]
+    pub offset: usize,
+
+    /// Entries to return (default 100, at most 1000)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Sort keys of `GET /api/v1/audit`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSort {
+    #[default]
+    Timestamp,
+    User,
+    Action,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AuditLogPage {
+    pub entries: Vec<AuditLogEntry>,
+
+    /// Entries matching the filters, across all pages
+    pub total: usize,
+}
+
 // ============================================================================
 // CoT Message Injection
 // ============================================================================
@@ -546,8 +1654,8 @@ pub struct SendCotRequest {
     pub target_connections: Option<Vec<Uuid>>,
 
     /// Optional: Apply filters before sending
-    /// If true, message goes through normal filter rules
-    /// If false, bypasses filters and sends directly
+    /// If true, message goes through the aggregator and normal filter rules
+    /// If false, bypasses filters and sends directly (requires cot:unfiltered)
     #[serde(default = "default_apply_filters")]
     pub apply_filters: bool,
 
@@ -727,3 +1835,19 @@ pub struct CaInfo {
     /// Days until expiration
     pub days_until_expiry: i64,
 }
+
+/// Inbound listener a device can be onboarded to
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ListenerEndpointInfo {
+    /// Listener ID
+    pub id: String,
+
+    /// Port the listener accepts connections on
+    pub port: u16,
+
+    /// Whether connections use TLS
+    pub tls: bool,
+
+    /// Whether the listener requires client certificates
+    pub client_auth: bool,
+}
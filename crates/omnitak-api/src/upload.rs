@@ -0,0 +1,226 @@
+//! Streamed multipart uploads
+//!
+//! File parts are written to a temporary directory chunk by chunk as they
+//! arrive, so an upload is never held in memory whole, and the request is
+//! refused as soon as it passes its size limit. The files are deleted when
+//! the [`Upload`] is dropped, unless they have been moved elsewhere.
+
+use axum::extract::Multipart;
+use axum::extract::multipart::MultipartError;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+use crate::rest::ApiError;
+
+/// A file part written to disk
+#[derive(Debug)]
+pub struct UploadedFile {
+    /// Name of the multipart field
+    pub field: String,
+    /// Name the client gave the file, without any directories
+    pub file_name: String,
+    /// Content type the client declared
+    pub content_type: Option<String>,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Parts of a multipart body
+#[derive(Debug)]
+pub struct Upload {
+    pub files: Vec<UploadedFile>,
+    /// Text parts by field name
+    pub fields: HashMap<String, String>,
+    dir: tempfile::TempDir,
+}
+
+impl Upload {
+    /// Read a multipart body of at most `max_bytes`
+    pub async fn receive(mut multipart: Multipart, max_bytes: u64) -> Result<Self, ApiError> {
+        let dir = tempfile::tempdir().map_err(staging_error)?;
+        let mut upload = Self {
+            files: Vec::new(),
+            fields: HashMap::new(),
+            dir,
+        };
+        let mut received = 0;
+
+        while let Some(mut field) = multipart.next_field().await.map_err(bad_part)? {
+            let name = field.name().unwrap_or_default().to_string();
+            let Some(file_name) = field.file_name().map(sanitize_file_name) else {
+                let mut value = Vec::new();
+                while let Some(chunk) = field.chunk().await.map_err(bad_part)? {
+                    received += chunk.len() as u64;
+                    check_size(received, max_bytes)?;
+                    value.extend_from_slice(&chunk);
+                }
+                let value = String::from_utf8(value)
+                    .map_err(|_| ApiError::BadRequest(format!("Field {} is not text", name)))?;
+                upload.fields.insert(name, value);
+                continue;
+            };
+
+            // A directory per part keeps the client's file name, which
+            // several parts may share
+            let dir = upload.dir.path().join(upload.files.len().to_string());
+            tokio::fs::create_dir(&dir).await.map_err(staging_error)?;
+            let path = dir.join(&file_name);
+            let mut file = tokio::fs::File::create(&path)
+                .await
+                .map_err(staging_error)?;
+
+            let content_type = field.content_type().map(str::to_string);
+            let mut size = 0;
+            while let Some(chunk) = field.chunk().await.map_err(bad_part)? {
+                size += chunk.len() as u64;
+                received += chunk.len() as u64;
+                check_size(received, max_bytes)?;
+                file.write_all(&chunk).await.map_err(staging_error)?;
+            }
+            file.flush().await.map_err(staging_error)?;
+
+            upload.files.push(UploadedFile {
+                field: name,
+                file_name,
+                content_type,
+                path,
+                size,
+            });
+        }
+
+        Ok(upload)
+    }
+
+    /// Text part `name`, unless missing or blank
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .get(name)
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Scratch directory the files are staged in, removed with the upload
+    pub fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+/// Read the first bytes of a staged file, enough to tell its format
+pub async fn sniff(path: &Path) -> Result<Vec<u8>, ApiError> {
+    use tokio::io::AsyncReadExt;
+
+    let mut head = Vec::with_capacity(512);
+    tokio::fs::File::open(path)
+        .await
+        .map_err(staging_error)?
+        .take(512)
+        .read_to_end(&mut head)
+        .await
+        .map_err(staging_error)?;
+    Ok(head)
+}
+
+fn check_size(received: u64, max_bytes: u64) -> Result<(), ApiError> {
+    if received > max_bytes {
+        return Err(ApiError::PayloadTooLarge(format!(
+            "Upload is larger than {} bytes",
+            max_bytes
+        )));
+    }
+    Ok(())
+}
+
+/// Last path component of a client-supplied file name, so it cannot
+/// escape the staging directory
+fn sanitize_file_name(name: &str) -> String {
+    let name = name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .replace(|c: char| c.is_control(), "_");
+    match name.as_str() {
+        "" | "." | ".." => "upload".to_string(),
+        _ => name,
+    }
+}
+
+fn bad_part(e: MultipartError) -> ApiError {
+    if e.status() == axum::http::StatusCode::PAYLOAD_TOO_LARGE {
+        return ApiError::PayloadTooLarge(e.body_text());
+    }
+    ApiError::BadRequest(format!("Invalid multipart body: {}", e.body_text()))
+}
+
+fn staging_error(e: std::io::Error) -> ApiError {
+    ApiError::InternalError(format!("Failed to stage upload: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::body::Body;
+    use axum::extract::{FromRequest, Request};
+
+    fn multipart(body: &str) -> Request {
+        Request::builder()
+            .method("POST")
+            .header("content-type", "multipart/form-data; boundary=X")
+            .body(Body::from(body.replace('\n', "\r\n")))
+            .unwrap()
+    }
+
+    const BODY: &str = "--X
+Content-Disposition: form-data; name=\"name\"
+
+ Alpha 
+--X
+Content-Disposition: form-data; name=\"file\"; filename=\"../alpha.pem\"
+Content-Type: application/x-pem-file
+
+-----BEGIN CERTIFICATE-----
+--X--
+";
+
+    #[tokio::test]
+    async fn test_receive_streams_files_to_disk() {
+        let multipart = Multipart::from_request(multipart(BODY), &()).await.unwrap();
+        let upload = Upload::receive(multipart, 1024).await.unwrap();
+
+        assert_eq!(upload.field("name"), Some("Alpha"));
+        assert_eq!(upload.field("password"), None);
+        let [file] = upload.files.as_slice() else {
+            panic!("expected one file");
+        };
+        assert_eq!(file.field, "file");
+        assert_eq!(file.file_name, "alpha.pem");
+        assert_eq!(file.content_type.as_deref(), Some("application/x-pem-file"));
+        assert!(file.path.starts_with(upload.dir()));
+        assert_eq!(
+            std::fs::read_to_string(&file.path).unwrap(),
+            "-----BEGIN CERTIFICATE-----"
+        );
+        assert_eq!(file.size, 27);
+
+        let dir = upload.dir().to_path_buf();
+        drop(upload);
+        assert!(!dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_receive_enforces_size_limit() {
+        let multipart = Multipart::from_request(multipart(BODY), &()).await.unwrap();
+        let err = Upload::receive(multipart, 16).await.unwrap_err();
+        assert!(matches!(err, ApiError::PayloadTooLarge(_)));
+    }
+
+    #[test]
+    fn test_sanitize_file_name() {
+        assert_eq!(sanitize_file_name("alpha.p12"), "alpha.p12");
+        assert_eq!(sanitize_file_name("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_file_name("C:\\certs\\ca.pem"), "ca.pem");
+        assert_eq!(sanitize_file_name("certs/"), "upload");
+        assert_eq!(sanitize_file_name(".."), "upload");
+    }
+}
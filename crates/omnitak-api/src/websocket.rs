@@ -1,20 +1,33 @@
 //! WebSocket API for real-time CoT message streaming and system events
-
-use crate::auth::AuthService;
-use crate::types::{WsClientMessage, WsServerMessage};
+//!
+//! System events are also served as Server-Sent Events on
+//! `/api/v1/events` to clients that don't ask for a WebSocket upgrade.
+//!
+//! Events and tracks are only sent to clients that may see the tenant
+//! namespace they belong to.
+
+use crate::auth::{AuthService, AuthUser};
+use crate::tracks::TrackStore;
+use crate::types::{ErrorResponse, WsClientMessage, WsServerMessage};
 use axum::{
     Router,
     extract::{
         State,
-        ws::{Message, WebSocket, WebSocketUpgrade},
+        ws::{Message, WebSocket, WebSocketUpgrade, rejection::WebSocketUpgradeRejection},
+    },
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
     },
-    response::IntoResponse,
     routing::get,
 };
 use futures::{
     sink::SinkExt,
-    stream::{SplitSink, SplitStream, StreamExt},
+    stream::{self, SplitSink, SplitStream, Stream, StreamExt},
 };
+use omnitak_pool::ConnectionPool;
+use std::collections::HashSet;
+use std::convert::Infallible;
 use std::sync::Arc;
 use tokio::sync::{broadcast, mpsc};
 use tracing::{debug, error, info, warn};
@@ -24,15 +37,38 @@ use uuid::Uuid;
 // WebSocket State
 // ============================================================================
 
+/// Who receives a system event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventScope {
+    /// Every client, e.g. health summaries
+    All,
+    /// Clients that may see this tenant namespace; `None` is the default
+    /// namespace, which tenant users can't see
+    Tenant(Option<String>),
+}
+
+impl EventScope {
+    fn includes(&self, user: &AuthUser) -> bool {
+        match self {
+            Self::All => true,
+            Self::Tenant(tenant) => user.can_access(tenant.as_deref()),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct WsState {
     /// Broadcast channel for CoT messages
     cot_tx: broadcast::Sender<WsServerMessage>,
     /// Broadcast channel for system events
-    event_tx: broadcast::Sender<WsServerMessage>,
+    event_tx: broadcast::Sender<(EventScope, WsServerMessage)>,
     /// Authentication service (kept for future auth integration)
     #[allow(dead_code)]
     auth_service: Arc<AuthService>,
+    /// Track table behind the GeoJSON delta stream
+    tracks: Arc<TrackStore>,
+    /// Pool whose connection tenants decide which tracks a client sees
+    pool: Option<Arc<ConnectionPool>>,
 }
 
 impl WsState {
@@ -44,9 +80,33 @@ impl WsState {
             cot_tx,
             event_tx,
             auth_service,
+            tracks: Arc::new(TrackStore::new()),
+            pool: None,
         }
     }
 
+    /// Serve the delta stream from a shared track table
+    pub fn with_tracks(mut self, tracks: Arc<TrackStore>) -> Self {
+        self.tracks = tracks;
+        self
+    }
+
+    /// Look up the tenants of track sources in this pool. Without one,
+    /// tracks belong to the default namespace.
+    pub fn with_pool(mut self, pool: Arc<ConnectionPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Whether `user` may see what arrived from connection `source`
+    fn can_see_source(&self, user: &AuthUser, source: Option<&str>) -> bool {
+        let tenant = match (&self.pool, source) {
+            (Some(pool), Some(source)) => pool.tenant_of(&source.to_string()),
+            _ => None,
+        };
+        user.can_access(tenant.as_deref())
+    }
+
     /// Broadcast a CoT message to all subscribers
     pub fn broadcast_cot_message(&self, message: WsServerMessage) {
         if let Err(e) = self.cot_tx.send(message) {
@@ -56,7 +116,16 @@ impl WsState {
 
     /// Broadcast a system event to all subscribers
     pub fn broadcast_event(&self, message: WsServerMessage) {
-        if let Err(e) = self.event_tx.send(message) {
+        self.broadcast_scoped_event(EventScope::All, message);
+    }
+
+    /// Broadcast a system event to the subscribers that may see `tenant`
+    pub fn broadcast_tenant_event(&self, tenant: Option<String>, message: WsServerMessage) {
+        self.broadcast_scoped_event(EventScope::Tenant(tenant), message);
+    }
+
+    fn broadcast_scoped_event(&self, scope: EventScope, message: WsServerMessage) {
+        if let Err(e) = self.event_tx.send((scope, message)) {
             debug!("No event subscribers: {}", e);
         }
     }
@@ -70,6 +139,7 @@ pub fn create_ws_router(state: WsState) -> Router {
     Router::new()
         .route("/api/v1/stream", get(ws_stream_handler))
         .route("/api/v1/events", get(ws_events_handler))
+        .route("/api/v1/tracks/stream", get(ws_tracks_handler))
         .with_state(state)
 }
 
@@ -78,6 +148,13 @@ pub fn create_ws_router(state: WsState) -> Router {
 // ============================================================================
 
 /// WS /api/v1/stream - Real-time CoT message stream
+#[utoipa::path(
+    get,
+    path = "/api/v1/stream",
+    responses(
+        (status = 101, description = "WebSocket upgrade; clients send WsClientMessage and receive WsServerMessage", body = WsServerMessage)
+    )
+)]
 async fn ws_stream_handler(
     ws: WebSocketUpgrade,
     State(state): State<WsState>,
@@ -86,11 +163,58 @@ async fn ws_stream_handler(
 }
 
 /// WS /api/v1/events - System events stream
+///
+/// Requests without a WebSocket upgrade get the same events as
+/// Server-Sent Events instead. Browsers, which cannot set headers on the
+/// handshake, may pass the JWT in the `access_token` query parameter.
+#[utoipa::path(
+    get,
+    path = "/api/v1/events",
+    responses(
+        (status = 101, description = "WebSocket upgrade; system events arrive as WsServerMessage", body = WsServerMessage),
+        (status = 200, description = "Server-Sent Events named after the event type", content_type = "text/event-stream", body = WsServerMessage),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
 async fn ws_events_handler(
+    ws: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+    State(state): State<WsState>,
+    user: AuthUser,
+) -> Response {
+    match ws {
+        Ok(ws) => ws
+            .on_upgrade(move |socket| handle_events_socket(socket, state, user))
+            .into_response(),
+        Err(_) => sse_events(state, user).into_response(),
+    }
+}
+
+/// WS /api/v1/tracks/stream - GeoJSON track snapshot followed by deltas
+///
+/// Browsers, which cannot set headers on the handshake, may pass the JWT
+/// in the `access_token` query parameter.
+#[utoipa::path(
+    get,
+    path = "/api/v1/tracks/stream",
+    responses(
+        (status = 101, description = "WebSocket upgrade; a track snapshot followed by TrackDelta messages", body = WsServerMessage),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("api_key" = [])
+    )
+)]
+async fn ws_tracks_handler(
     ws: WebSocketUpgrade,
     State(state): State<WsState>,
+    user: AuthUser,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_events_socket(socket, state))
+    ws.on_upgrade(move |socket| handle_tracks_socket(socket, state, user))
 }
 
 // ============================================================================
@@ -134,7 +258,7 @@ async fn handle_stream_socket(socket: WebSocket, state: WsState) {
 // Events Socket Handler
 // ============================================================================
 
-async fn handle_events_socket(socket: WebSocket, state: WsState) {
+async fn handle_events_socket(socket: WebSocket, state: WsState, user: AuthUser) {
     let client_id = Uuid::new_v4();
     info!(client_id = %client_id, "New WebSocket events connection");
 
@@ -150,7 +274,10 @@ async fn handle_events_socket(socket: WebSocket, state: WsState) {
     tokio::spawn({
         let client_tx = client_tx.clone();
         async move {
-            while let Ok(message) = event_rx.recv().await {
+            while let Ok((scope, message)) = event_rx.recv().await {
+                if !scope.includes(&user) {
+                    continue;
+                }
                 if client_tx.send(message).is_err() {
                     break;
                 }
@@ -179,6 +306,147 @@ async fn handle_events_socket(socket: WebSocket, state: WsState) {
     info!(client_id = %client_id, "WebSocket events connection closed");
 }
 
+// ============================================================================
+// Events SSE Stream
+// ============================================================================
+
+/// System events as an SSE stream, each named after its event type so
+/// browsers can listen for the ones they want
+fn sse_events(
+    state: WsState,
+    user: AuthUser,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let client_id = Uuid::new_v4();
+    info!(client_id = %client_id, "New SSE events connection");
+
+    let user = Arc::new(user);
+    let events = stream::unfold(state.event_tx.subscribe(), move |mut event_rx| {
+        let user = user.clone();
+        async move {
+            loop {
+                match event_rx.recv().await {
+                    Ok((scope, message)) => {
+                        if !scope.includes(&user) {
+                            continue;
+                        }
+                        if let Some(event) = sse_event(&message) {
+                            return Some((Ok(event), event_rx));
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        debug!(client_id = %client_id, missed, "SSE events client lagged");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        }
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+fn sse_event(message: &WsServerMessage) -> Option<Event> {
+    let WsServerMessage::SystemEvent { event, .. } = message else {
+        return None;
+    };
+    Event::default().event(event).json_data(message).ok()
+}
+
+// ============================================================================
+// Tracks Socket Handler
+// ============================================================================
+
+async fn handle_tracks_socket(socket: WebSocket, state: WsState, user: AuthUser) {
+    let client_id = Uuid::new_v4();
+    info!(client_id = %client_id, "New WebSocket track stream connection");
+
+    let (sender, receiver) = socket.split();
+
+    // Create channels for communication
+    let (client_tx, client_rx) = mpsc::unbounded_channel();
+    let (resync_tx, mut resync_rx) = mpsc::unbounded_channel();
+
+    // Subscribe before the first snapshot so no delta is missed
+    let mut delta_rx = state.tracks.subscribe();
+
+    // Spawn delta forwarding task
+    let forward_task = tokio::spawn({
+        let client_tx = client_tx.clone();
+        async move {
+            let visible = |source: Option<&str>| state.can_see_source(&user, source);
+            // UIDs of the tracks this client was sent
+            let mut shown = HashSet::new();
+            let mut last_seq = send_track_snapshot(&state.tracks, visible, &mut shown, &client_tx);
+            loop {
+                tokio::select! {
+                    delta = delta_rx.recv() => match delta {
+                        Ok(changes) if changes.seq <= last_seq => {
+                            // Already part of the last snapshot
+                        }
+                        Ok(changes) => {
+                            last_seq = changes.seq;
+                            if client_tx.send(changes.delta_for(visible, &mut shown)).is_err() {
+                                break;
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
+                            debug!(client_id = %client_id, missed, "Track stream lagged, resyncing");
+                            last_seq = send_track_snapshot(&state.tracks, visible, &mut shown, &client_tx);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    resync = resync_rx.recv() => match resync {
+                        Some(()) => {
+                            last_seq = send_track_snapshot(&state.tracks, visible, &mut shown, &client_tx);
+                        }
+                        None => break,
+                    },
+                }
+            }
+        }
+    });
+
+    // Spawn sender task
+    let send_task = tokio::spawn(handle_send_messages(sender, client_rx, client_id));
+
+    // Spawn receiver task (for resync and ping/pong)
+    let recv_task = tokio::spawn(handle_receive_tracks_messages(
+        receiver, client_tx, resync_tx, client_id,
+    ));
+
+    // Wait for either task to complete
+    tokio::select! {
+        _ = send_task => {
+            debug!(client_id = %client_id, "Send task completed");
+        }
+        _ = recv_task => {
+            debug!(client_id = %client_id, "Receive task completed");
+        }
+    }
+    forward_task.abort();
+
+    info!(client_id = %client_id, "WebSocket track stream connection closed");
+}
+
+/// Send the tracks the client may see, record them in `shown` and return
+/// the snapshot's sequence number
+fn send_track_snapshot(
+    tracks: &TrackStore,
+    visible: impl Fn(Option<&str>) -> bool,
+    shown: &mut HashSet<String>,
+    client_tx: &mpsc::UnboundedSender<WsServerMessage>,
+) -> u64 {
+    let snapshot = tracks.snapshot_for(visible);
+    let seq = snapshot.seq;
+    shown.clear();
+    shown.extend(snapshot.features.iter().map(|f| f.id.clone()));
+    let _ = client_tx.send(WsServerMessage::TrackSnapshot {
+        seq,
+        features: snapshot.features,
+    });
+    seq
+}
+
 // ============================================================================
 // Message Handling
 // ============================================================================
@@ -277,6 +545,12 @@ async fn handle_receive_stream_messages(
                             WsClientMessage::UnsubscribeEvents => {
                                 // No-op
                             }
+                            WsClientMessage::Resync => {
+                                let _ = client_tx.send(WsServerMessage::Error {
+                                    code: "invalid_endpoint".to_string(),
+                                    message: "Use /api/v1/tracks/stream for track deltas".to_string(),
+                                });
+                            }
                             WsClientMessage::Ping => {
                                 let _ = client_tx.send(WsServerMessage::Pong);
                             }
@@ -364,6 +638,44 @@ async fn handle_receive_events_messages(
     }
 }
 
+async fn handle_receive_tracks_messages(
+    mut receiver: SplitStream<WebSocket>,
+    client_tx: mpsc::UnboundedSender<WsServerMessage>,
+    resync_tx: mpsc::UnboundedSender<()>,
+    client_id: Uuid,
+) {
+    while let Some(result) = receiver.next().await {
+        match result {
+            Ok(Message::Text(text)) => match serde_json::from_str::<WsClientMessage>(&text) {
+                Ok(WsClientMessage::Resync) => {
+                    debug!(client_id = %client_id, "Client requested track resync");
+                    if resync_tx.send(()).is_err() {
+                        break;
+                    }
+                }
+                Ok(WsClientMessage::Ping) => {
+                    let _ = client_tx.send(WsServerMessage::Pong);
+                }
+                Ok(_) => {
+                    // Track stream has no subscriptions
+                }
+                Err(e) => {
+                    warn!(client_id = %client_id, error = %e, "Failed to parse client message");
+                }
+            },
+            Ok(Message::Close(reason)) => {
+                info!(client_id = %client_id, reason = ?reason, "Client closed connection");
+                break;
+            }
+            Err(e) => {
+                error!(client_id = %client_id, error = %e, "WebSocket error");
+                break;
+            }
+            _ => {}
+        }
+    }
+}
+
 // ============================================================================
 // Utility Functions
 // ============================================================================
@@ -431,4 +743,64 @@ mod tests {
         let received = rx.recv().await.unwrap();
         assert!(matches!(received, WsServerMessage::CotMessage { .. }));
     }
+
+    #[tokio::test]
+    async fn test_events_served_as_sse_without_upgrade() {
+        use axum::body::Body;
+        use axum::http::{Request, header};
+        use http_body_util::BodyExt;
+        use tower::ServiceExt;
+
+        let auth_service = Arc::new(AuthService::new(AuthConfig::default()));
+        auth_service
+            .create_tenant_user(
+                "blue-op".to_string(),
+                "password123",
+                crate::types::UserRole::ReadOnly,
+                Some("blue".to_string()),
+            )
+            .unwrap();
+        let (token, _) = auth_service.login("blue-op", "password123").unwrap();
+        let state = WsState::new(auth_service.clone());
+        let app = create_ws_router(state.clone()).layer(axum::Extension(auth_service));
+
+        let request = |token: Option<&str>| {
+            let mut request = Request::builder()
+                .uri("/api/v1/events")
+                .header(header::ACCEPT, "text/event-stream");
+            if let Some(token) = token {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            request.body(Body::empty()).unwrap()
+        };
+        let response = app.clone().oneshot(request(None)).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::UNAUTHORIZED);
+
+        let response = app.oneshot(request(Some(&token))).await.unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+
+        // Events of other namespaces are not sent to tenant users
+        state.broadcast_tenant_event(
+            None,
+            WsState::create_test_system_event(
+                "connection_state",
+                serde_json::json!({"status": "disconnected"}),
+            ),
+        );
+        state.broadcast_tenant_event(
+            Some("blue".to_string()),
+            WsState::create_test_system_event(
+                "connection_state",
+                serde_json::json!({"status": "connected"}),
+            ),
+        );
+        let mut body = response.into_body();
+        let frame = body.frame().await.unwrap().unwrap();
+        let text = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+        assert!(text.starts_with("event: connection_state\n"));
+        assert!(text.contains("\"status\":\"connected\""));
+    }
 }
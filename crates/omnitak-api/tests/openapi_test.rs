@@ -0,0 +1,53 @@
+//! Checks the OpenAPI document `omnitak-api-client` is generated from
+//!
+//! Run with `UPDATE_OPENAPI=1` to rewrite the committed copy after changing
+//! a route or one of its types.
+
+use std::path::PathBuf;
+
+fn committed_spec_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../omnitak-api-client/openapi.json")
+}
+
+#[test]
+fn test_client_spec_is_current() {
+    let spec = omnitak_api::openapi()
+        .to_pretty_json()
+        .expect("OpenAPI document serializes")
+        + "\n";
+    let path = committed_spec_path();
+
+    if std::env::var_os("UPDATE_OPENAPI").is_some() {
+        std::fs::write(&path, &spec).unwrap();
+        return;
+    }
+
+    let committed = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        committed == spec,
+        "{} is out of date; rerun this test with UPDATE_OPENAPI=1",
+        path.display()
+    );
+}
+
+#[test]
+fn test_every_route_is_documented() {
+    let spec = omnitak_api::openapi();
+    for path in [
+        "/api/v1/health",
+        "/api/v1/cot/send",
+        "/api/v1/filters/{id}",
+        "/api/v1/adb/devices",
+        "/api/v1/adb/approved/{serial}",
+        "/api/v1/discovery/services/{id}/adopt",
+        "/api/v1/enrollment/tokens/{id}",
+        "/api/v1/events",
+        "/Marti/sync/content",
+    ] {
+        assert!(
+            spec.paths.paths.contains_key(path),
+            "{} is not documented",
+            path
+        );
+    }
+}
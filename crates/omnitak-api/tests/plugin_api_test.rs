@@ -27,6 +27,7 @@ use omnitak_api::{
     types::UserRole,
 };
 use omnitak_plugin_api::{PluginManager, PluginManagerConfig};
+use omnitak_pool::{TransformConfig, TransformPipeline};
 use serde_json::json;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -49,6 +50,7 @@ async fn create_test_state() -> PluginApiState {
     PluginApiState {
         plugin_manager: Arc::new(RwLock::new(plugin_manager)),
         audit_logger: Arc::new(AuditLogger::new()),
+        transformers: Arc::new(TransformPipeline::new(TransformConfig::default())),
     }
 }
 
@@ -380,8 +382,7 @@ async fn test_toggle_plugin_not_found() {
         .unwrap();
 
     let response = app.oneshot(request).await.unwrap();
-    // Current implementation returns OK even if plugin doesn't exist (TODO)
-    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
 // ============================================================================
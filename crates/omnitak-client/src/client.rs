@@ -1,6 +1,7 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
+use crate::compression::Compression;
 use std::time::Duration;
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::{error, info, warn};
@@ -71,6 +72,8 @@ pub struct ClientConfig {
     pub reconnect: ReconnectConfig,
     /// Buffer size for receiving messages
     pub recv_buffer_size: usize,
+    /// Frame compression (requires length-prefixed framing on both ends)
+    pub compression: Compression,
 }
 
 impl Default for ClientConfig {
@@ -82,6 +85,7 @@ impl Default for ClientConfig {
             write_timeout: Duration::from_secs(10),
             reconnect: ReconnectConfig::default(),
             recv_buffer_size: 1024,
+            compression: Compression::None,
         }
     }
 }
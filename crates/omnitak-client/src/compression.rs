@@ -0,0 +1,95 @@
+//! Frame-level compression for links between OmniTAK instances
+//!
+//! When enabled, each frame payload is compressed independently before it is
+//! written and decompressed after it is read, so framing and reconnect logic
+//! are unaffected. Compressed payloads are binary, so compression requires
+//! length-prefixed framing and both ends of the link must enable it.
+
+use anyhow::{Context, Result, anyhow};
+use bytes::Bytes;
+
+/// Maximum decompressed frame size (matches the framing limit)
+const MAX_DECOMPRESSED_SIZE: usize = 10 * 1024 * 1024;
+
+/// Default zstd compression level (fast, good ratio on CoT XML)
+pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+/// Per-connection frame compression mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// Frames are sent as-is
+    #[default]
+    None,
+    /// Frames are zstd-compressed at the given level
+    Zstd {
+        /// Compression level (1-22)
+        level: i32,
+    },
+}
+
+impl Compression {
+    /// zstd compression at the default level
+    pub fn zstd() -> Self {
+        Self::Zstd {
+            level: DEFAULT_ZSTD_LEVEL,
+        }
+    }
+
+    /// Whether frames are transformed at all
+    pub fn is_enabled(&self) -> bool {
+        !matches!(self, Self::None)
+    }
+
+    /// Compress an outgoing frame payload
+    pub fn compress(&self, data: &[u8]) -> Result<Bytes> {
+        match self {
+            Self::None => Ok(Bytes::copy_from_slice(data)),
+            Self::Zstd { level } => zstd::bulk::compress(data, *level)
+                .map(Bytes::from)
+                .context("zstd compression failed"),
+        }
+    }
+
+    /// Decompress an incoming frame payload
+    pub fn decompress(&self, data: Bytes) -> Result<Bytes> {
+        match self {
+            Self::None => Ok(data),
+            Self::Zstd { .. } => zstd::bulk::decompress(&data, MAX_DECOMPRESSED_SIZE)
+                .map(Bytes::from)
+                .map_err(|e| anyhow!("zstd decompression failed: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[u8] = br#"<event version="2.0" uid="ANDROID-1" type="a-f-G-U-C" time="2024-01-01T00:00:00Z" start="2024-01-01T00:00:00Z" stale="2024-01-01T00:05:00Z" how="m-g"><point lat="37.7749" lon="-122.4194" hae="10.0" ce="9999999.0" le="9999999.0"/><detail><contact callsign="ALPHA-1"/></detail></event>"#;
+
+    #[test]
+    fn test_none_passthrough() {
+        let c = Compression::None;
+        assert!(!c.is_enabled());
+        assert_eq!(&c.compress(SAMPLE).unwrap()[..], SAMPLE);
+        assert_eq!(&c.decompress(Bytes::from_static(SAMPLE)).unwrap()[..], SAMPLE);
+    }
+
+    #[test]
+    fn test_zstd_round_trip() {
+        let c = Compression::zstd();
+        assert!(c.is_enabled());
+
+        let compressed = c.compress(SAMPLE).unwrap();
+        assert_ne!(&compressed[..], SAMPLE);
+
+        let restored = c.decompress(compressed).unwrap();
+        assert_eq!(&restored[..], SAMPLE);
+    }
+
+    #[test]
+    fn test_zstd_rejects_garbage() {
+        let c = Compression::zstd();
+        assert!(c.decompress(Bytes::from_static(b"<event/>")).is_err());
+    }
+}
@@ -17,6 +17,7 @@
 //! - Connection state tracking and metrics
 //! - Comprehensive error handling
 //! - Configurable timeouts and backpressure handling
+//! - Optional zstd frame compression for links between OmniTAK instances
 //! - Distributed tracing support
 //!
 //! ## Example
@@ -40,6 +41,8 @@
 //! ```
 
 pub mod client;
+pub mod compression;
+pub mod probe;
 pub mod state;
 pub mod tcp;
 pub mod tls;
@@ -51,6 +54,8 @@ pub use client::{
     ClientConfig, CotMessage, HealthCheck, HealthStatus, MessageMetadata, ReconnectConfig,
     TakClient,
 };
+pub use compression::Compression;
+pub use probe::{ProbeConfig, ProbeReport, ProbeStage, ProbeStep, probe};
 pub use state::{ConnectionMetrics, ConnectionState, ConnectionStatus, MetricsSnapshot};
 
 // Re-export FramingMode from both tcp and tls for convenience
@@ -0,0 +1,424 @@
+//! One-off connection checks
+//!
+//! [`probe`] walks the steps a client takes to reach a TAK server: loading
+//! the client certificate, resolving the name, connecting, the TLS
+//! handshake and optionally a TAK ping. It stops at the first step that
+//! fails and times each one. Nothing is kept open afterwards, and unlike
+//! the clients a probe never retries.
+
+use crate::tls::{TlsClient, TlsClientConfig};
+use chrono::{SecondsFormat, Utc};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpStream, lookup_host};
+use tokio::time::timeout;
+use tokio_native_tls::TlsConnector;
+
+/// CoT type of a TAK ping; servers answer with [`PONG_TYPE`]
+const PING_TYPE: &str = "t-x-c-t";
+
+const PONG_TYPE: &str = "t-x-c-t-r";
+
+/// Bytes kept while looking for the pong
+const MAX_PING_BUFFER: usize = 1024 * 1024;
+
+/// Step of a probe, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeStage {
+    /// Load the client certificate and key (TLS only)
+    ClientCertificate,
+    /// Resolve the server name
+    Resolve,
+    /// Open the TCP connection
+    Connect,
+    /// Complete the TLS handshake (TLS only)
+    TlsHandshake,
+    /// Send a TAK ping and wait for the pong
+    TakPing,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProbeStep {
+    pub stage: ProbeStage,
+    pub success: bool,
+    pub duration: Duration,
+    /// What the step found, or why it failed
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProbeConfig {
+    /// Server address (host:port)
+    pub server_addr: String,
+
+    /// TLS settings; plain TCP when unset. Only the certificates,
+    /// verification and server name are used.
+    pub tls: Option<TlsClientConfig>,
+
+    /// Time limit for each step
+    pub timeout: Duration,
+
+    /// Send a TAK ping once connected and wait for the pong
+    pub tak_ping: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProbeReport {
+    /// Steps that ran; the last one failed unless all succeeded
+    pub steps: Vec<ProbeStep>,
+
+    /// Addresses the server name resolved to
+    pub resolved: Vec<SocketAddr>,
+
+    /// Address the connection was made to
+    pub connected: Option<SocketAddr>,
+
+    /// Server certificate (DER), when the handshake got far enough to see it
+    pub server_certificate: Option<Vec<u8>>,
+
+    /// Whether the server certificate verified; unknown when verification
+    /// is disabled or the handshake failed for another reason
+    pub certificate_trusted: Option<bool>,
+}
+
+impl ProbeReport {
+    /// Whether every step succeeded
+    pub fn success(&self) -> bool {
+        !self.steps.is_empty() && self.steps.iter().all(|s| s.success)
+    }
+
+    fn record(
+        &mut self,
+        stage: ProbeStage,
+        started: Instant,
+        result: Result<String, String>,
+    ) -> bool {
+        let success = result.is_ok();
+        self.steps.push(ProbeStep {
+            stage,
+            success,
+            duration: started.elapsed(),
+            message: result.unwrap_or_else(|e| e),
+        });
+        success
+    }
+}
+
+/// Check that a server can be reached with the given settings
+pub async fn probe(config: &ProbeConfig) -> ProbeReport {
+    let mut report = ProbeReport::default();
+    let limit = config.timeout;
+
+    let tls = config.tls.clone().map(|mut tls| {
+        tls.base.server_addr = config.server_addr.clone();
+        tls
+    });
+    let tls = match &tls {
+        Some(tls) => {
+            let started = Instant::now();
+            match TlsClient::build_tls_config(tls) {
+                Ok(connector) => {
+                    report.record(
+                        ProbeStage::ClientCertificate,
+                        started,
+                        Ok("Client certificate loaded".to_string()),
+                    );
+                    Some((tls, connector))
+                }
+                Err(e) => {
+                    report.record(
+                        ProbeStage::ClientCertificate,
+                        started,
+                        Err(format!("{:#}", e)),
+                    );
+                    return report;
+                }
+            }
+        }
+        None => None,
+    };
+
+    let started = Instant::now();
+    let resolved = match timeout(limit, lookup_host(&config.server_addr)).await {
+        Ok(Ok(addrs)) => {
+            report.resolved = addrs.collect();
+            if report.resolved.is_empty() {
+                Err(format!("{} resolved to no addresses", config.server_addr))
+            } else {
+                Ok(format!(
+                    "Resolved to {}",
+                    report
+                        .resolved
+                        .iter()
+                        .map(|a| a.ip().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            }
+        }
+        Ok(Err(e)) => Err(format!("Failed to resolve {}: {}", config.server_addr, e)),
+        Err(_) => Err(format!("Name resolution timed out after {:?}", limit)),
+    };
+    if !report.record(ProbeStage::Resolve, started, resolved) {
+        return report;
+    }
+
+    let started = Instant::now();
+    let mut stream = match connect(&report.resolved, limit).await {
+        Ok((stream, addr)) => {
+            report.connected = Some(addr);
+            report.record(
+                ProbeStage::Connect,
+                started,
+                Ok(format!("Connected to {}", addr)),
+            );
+            stream
+        }
+        Err(e) => {
+            report.record(ProbeStage::Connect, started, Err(e));
+            return report;
+        }
+    };
+
+    let Some((tls, connector)) = tls else {
+        if config.tak_ping {
+            ping_step(&mut report, &mut stream, limit).await;
+        }
+        return report;
+    };
+
+    let started = Instant::now();
+    let server_name = tls.sni_name();
+    let handshake = timeout(
+        limit,
+        TlsConnector::from(connector).connect(&server_name, stream),
+    )
+    .await;
+    let mut stream = match handshake {
+        Ok(Ok(stream)) => {
+            report.server_certificate = peer_certificate(&stream);
+            report.certificate_trusted = tls.verify_server.then_some(true);
+            report.record(
+                ProbeStage::TlsHandshake,
+                started,
+                Ok(format!("Handshake completed with {}", server_name)),
+            );
+            stream
+        }
+        Ok(Err(e)) => {
+            let mut message = format!("TLS handshake failed: {}", e);
+            if tls.verify_server && unverified_handshake(&mut report, tls, limit).await {
+                report.certificate_trusted = Some(false);
+                message.push_str(
+                    "; the handshake succeeds without verification, so the server certificate is not trusted",
+                );
+            }
+            report.record(ProbeStage::TlsHandshake, started, Err(message));
+            return report;
+        }
+        Err(_) => {
+            report.record(
+                ProbeStage::TlsHandshake,
+                started,
+                Err(format!("TLS handshake timed out after {:?}", limit)),
+            );
+            return report;
+        }
+    };
+
+    if config.tak_ping {
+        ping_step(&mut report, &mut stream, limit).await;
+    }
+    report
+}
+
+/// Connect to the first address that accepts
+async fn connect(addrs: &[SocketAddr], limit: Duration) -> Result<(TcpStream, SocketAddr), String> {
+    let mut errors = Vec::new();
+    for &addr in addrs {
+        match timeout(limit, TcpStream::connect(addr)).await {
+            Ok(Ok(stream)) => return Ok((stream, addr)),
+            Ok(Err(e)) => errors.push(format!("{}: {}", addr, e)),
+            Err(_) => errors.push(format!("{}: timed out after {:?}", addr, limit)),
+        }
+    }
+    Err(format!("Failed to connect ({})", errors.join("; ")))
+}
+
+/// Repeat the handshake without verifying the server, to tell a certificate
+/// the client does not trust from other failures and to see that certificate
+async fn unverified_handshake(
+    report: &mut ProbeReport,
+    tls: &TlsClientConfig,
+    limit: Duration,
+) -> bool {
+    let Some(addr) = report.connected else {
+        return false;
+    };
+    let mut unverified = tls.clone();
+    unverified.verify_server = false;
+    let Ok(connector) = TlsClient::build_tls_config(&unverified) else {
+        return false;
+    };
+    let Ok(Ok(stream)) = timeout(limit, TcpStream::connect(addr)).await else {
+        return false;
+    };
+    match timeout(
+        limit,
+        TlsConnector::from(connector).connect(&tls.sni_name(), stream),
+    )
+    .await
+    {
+        Ok(Ok(stream)) => {
+            report.server_certificate = peer_certificate(&stream);
+            true
+        }
+        _ => false,
+    }
+}
+
+fn peer_certificate(stream: &tokio_native_tls::TlsStream<TcpStream>) -> Option<Vec<u8>> {
+    let certificate = stream.get_ref().peer_certificate().ok()??;
+    certificate.to_der().ok()
+}
+
+async fn ping_step<S>(report: &mut ProbeReport, stream: &mut S, limit: Duration)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let started = Instant::now();
+    let result = ping(stream, limit).await;
+    report.record(ProbeStage::TakPing, started, result);
+}
+
+/// Send a TAK ping and read until the pong arrives
+async fn ping<S>(stream: &mut S, limit: Duration) -> Result<String, String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let started = Instant::now();
+    stream
+        .write_all(ping_event().as_bytes())
+        .await
+        .map_err(|e| format!("Failed to send ping: {}", e))?;
+    stream
+        .flush()
+        .await
+        .map_err(|e| format!("Failed to send ping: {}", e))?;
+
+    let pong = format!("type=\"{}\"", PONG_TYPE);
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let mut received = 0;
+    let deadline = tokio::time::Instant::now() + limit;
+    loop {
+        let read = match tokio::time::timeout_at(deadline, stream.read(&mut chunk)).await {
+            Ok(Ok(0)) => {
+                return Err(
+                    "The server closed the connection without answering the ping".to_string(),
+                );
+            }
+            Ok(Ok(read)) => read,
+            Ok(Err(e)) => return Err(format!("Failed to read the pong: {}", e)),
+            Err(_) => {
+                return Err(format!(
+                    "No pong within {:?} ({} bytes received)",
+                    limit, received
+                ));
+            }
+        };
+        received += read;
+        buffer.extend_from_slice(&chunk[..read]);
+        if String::from_utf8_lossy(&buffer).contains(&pong) {
+            return Ok(format!(
+                "Pong received after {} ms",
+                started.elapsed().as_millis()
+            ));
+        }
+        if buffer.len() > MAX_PING_BUFFER {
+            buffer.drain(..buffer.len() - pong.len());
+        }
+    }
+}
+
+fn ping_event() -> String {
+    let now = Utc::now();
+    let time = now.to_rfc3339_opts(SecondsFormat::Millis, true);
+    let stale = (now + chrono::Duration::seconds(20)).to_rfc3339_opts(SecondsFormat::Millis, true);
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <event version=\"2.0\" uid=\"omnitak-probe-ping\" type=\"{}\" how=\"h-g-i-g-o\" \
+         time=\"{}\" start=\"{}\" stale=\"{}\">\
+         <point lat=\"0.0\" lon=\"0.0\" hae=\"0.0\" ce=\"9999999.0\" le=\"9999999.0\"/>\
+         <detail/></event>",
+        PING_TYPE, time, time, stale
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn config(server_addr: String, tak_ping: bool) -> ProbeConfig {
+        ProbeConfig {
+            server_addr,
+            tls: None,
+            timeout: Duration::from_secs(2),
+            tak_ping,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_probe_tcp_with_ping() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buffer = vec![0u8; 4096];
+            let read = socket.read(&mut buffer).await.unwrap();
+            assert!(String::from_utf8_lossy(&buffer[..read]).contains("type=\"t-x-c-t\""));
+            socket
+                .write_all(b"<event version=\"2.0\" type=\"t-x-c-t-r\"><detail/></event>")
+                .await
+                .unwrap();
+        });
+
+        let report = probe(&config(addr.to_string(), true)).await;
+        assert!(report.success(), "{:?}", report.steps);
+        let stages: Vec<_> = report.steps.iter().map(|s| s.stage).collect();
+        assert_eq!(
+            stages,
+            vec![
+                ProbeStage::Resolve,
+                ProbeStage::Connect,
+                ProbeStage::TakPing
+            ]
+        );
+        assert_eq!(report.connected, Some(addr));
+    }
+
+    #[tokio::test]
+    async fn test_probe_stops_at_first_failure() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let report = probe(&config(addr.to_string(), true)).await;
+        assert!(!report.success());
+        assert_eq!(report.steps.len(), 2);
+        assert_eq!(report.steps[1].stage, ProbeStage::Connect);
+        assert!(!report.steps[1].success);
+
+        let mut tls = config(addr.to_string(), false);
+        tls.tls = Some(TlsClientConfig::new(
+            "/nonexistent/client.pem".into(),
+            "/nonexistent/client.key".into(),
+        ));
+        let report = probe(&tls).await;
+        assert_eq!(report.steps.len(), 1);
+        assert_eq!(report.steps[0].stage, ProbeStage::ClientCertificate);
+        assert!(!report.steps[0].success);
+    }
+}
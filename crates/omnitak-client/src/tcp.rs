@@ -7,8 +7,9 @@ use anyhow::{Context, Result, anyhow};
 use async_trait::async_trait;
 use bytes::{Buf, BytesMut};
 use std::sync::Arc;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
+use tokio::net::tcp::OwnedWriteHalf;
 use tokio::sync::mpsc::{self, Receiver, Sender};
 use tokio::time::timeout;
 use tokio_stream::wrappers::ReceiverStream;
@@ -63,6 +64,8 @@ pub struct TcpClient {
     config: TcpClientConfig,
     status: Arc<ConnectionStatus>,
     stream: Option<TcpStream>,
+    /// Write half of the stream once the receive task owns the read half
+    writer: Option<OwnedWriteHalf>,
     recv_tx: Option<Sender<Result<CotMessage>>>,
     recv_rx: Option<Receiver<Result<CotMessage>>>,
     shutdown_tx: Option<Sender<()>>,
@@ -77,6 +80,7 @@ impl TcpClient {
             config,
             status: Arc::new(ConnectionStatus::new()),
             stream: None,
+            writer: None,
             recv_tx: Some(recv_tx),
             recv_rx: Some(recv_rx),
             shutdown_tx: None,
@@ -116,6 +120,13 @@ impl TcpClient {
     /// Establish TCP connection
     #[instrument(skip(self))]
     async fn establish_connection(&mut self) -> Result<()> {
+        // Compressed frames are binary and cannot be delimited by newline or </event>
+        if self.config.base.compression.is_enabled()
+            && self.config.framing != FramingMode::LengthPrefixed
+        {
+            return Err(anyhow!("Frame compression requires length-prefixed framing"));
+        }
+
         self.status.set_state(ConnectionState::Connecting);
 
         info!("Connecting to {}", self.config.base.server_addr);
@@ -371,10 +382,12 @@ impl TcpClient {
 
     /// Write a frame to the stream
     async fn write_frame(&mut self, data: &[u8]) -> Result<()> {
-        let stream = self
-            .stream
-            .as_mut()
-            .ok_or_else(|| anyhow!("Not connected"))?;
+        let stream: &mut (dyn AsyncWrite + Send + Unpin) =
+            match (self.stream.as_mut(), self.writer.as_mut()) {
+                (Some(stream), _) => stream,
+                (None, Some(writer)) => writer,
+                (None, None) => return Err(anyhow!("Not connected")),
+            };
 
         match self.config.framing {
             FramingMode::Newline => {
@@ -431,9 +444,12 @@ impl TcpClient {
         let (shutdown_tx, mut shutdown_rx) = mpsc::channel(1);
         self.shutdown_tx = Some(shutdown_tx);
         let framing = self.config.framing;
+        let compression = self.config.base.compression;
 
-        // Move the stream out for the task
-        if let Some(mut stream) = self.stream.take() {
+        // Move the read half out for the task, keeping the write half for send_cot
+        if let Some(stream) = self.stream.take() {
+            let (mut stream, writer) = stream.into_split();
+            self.writer = Some(writer);
             tokio::spawn(async move {
                 loop {
                     tokio::select! {
@@ -444,6 +460,16 @@ impl TcpClient {
                         result = Self::read_frame_static(&mut stream, &mut buffer, &status, framing) => {
                             match result {
                                 Ok(Some(frame)) => {
+                                    let frame = match compression.decompress(frame) {
+                                        Ok(frame) => frame,
+                                        Err(e) => {
+                                            error!(error = %e, "Failed to decompress frame");
+                                            status.metrics().record_error();
+                                            let _ = tx.send(Err(e)).await;
+                                            break;
+                                        }
+                                    };
+
                                     status.metrics().record_message_received();
                                     let message = CotMessage {
                                         data: frame,
@@ -478,8 +504,8 @@ impl TcpClient {
     }
 
     /// Static helper for reading frames (used in async task)
-    pub async fn read_frame_static(
-        stream: &mut TcpStream,
+    pub async fn read_frame_static<R: AsyncRead + Unpin>(
+        stream: &mut R,
         buffer: &mut BytesMut,
         status: &ConnectionStatus,
         framing: FramingMode,
@@ -678,6 +704,12 @@ impl TakClient for TcpClient {
                 .await
                 .context("Failed to shutdown stream")?;
         }
+        if let Some(mut writer) = self.writer.take() {
+            writer
+                .shutdown()
+                .await
+                .context("Failed to shutdown stream")?;
+        }
 
         self.status.set_state(ConnectionState::Disconnected);
         self.status.metrics().mark_disconnected();
@@ -692,7 +724,8 @@ impl TakClient for TcpClient {
 
         debug!(size = message.data.len(), "Sending CoT message");
 
-        self.write_frame(&message.data).await?;
+        let payload = self.config.base.compression.compress(&message.data)?;
+        self.write_frame(&payload).await?;
         self.status.metrics().record_message_sent();
 
         Ok(())
@@ -752,4 +785,103 @@ mod tests {
         assert_eq!(FramingMode::Newline, FramingMode::Newline);
         assert_ne!(FramingMode::Newline, FramingMode::LengthPrefixed);
     }
+
+    #[tokio::test]
+    async fn test_compression_requires_length_prefixed() {
+        let mut config = TcpClientConfig::default();
+        config.base.server_addr = "127.0.0.1:1".to_string();
+        config.base.reconnect.enabled = false;
+        config.base.compression = crate::Compression::zstd();
+        config.framing = FramingMode::Xml;
+
+        let mut client = TcpClient::new(config);
+        let err = client.connect().await.unwrap_err();
+        assert!(err.to_string().contains("length-prefixed"));
+    }
+
+    #[tokio::test]
+    async fn test_compressed_frames() {
+        use tokio_stream::StreamExt;
+
+        let xml = b"<event uid=\"test\"><point lat=\"1\" lon=\"2\"/></event>".to_vec();
+        let compression = crate::Compression::zstd();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (sent_tx, sent_rx) = tokio::sync::oneshot::channel();
+
+        let expected = xml.clone();
+        tokio::spawn(async move {
+            // First connection: read the client's compressed frame
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let len = socket.read_u32().await.unwrap();
+            let mut frame = vec![0u8; len as usize];
+            socket.read_exact(&mut frame).await.unwrap();
+            let _ = sent_tx.send(frame);
+
+            // Second connection: push a compressed frame to the client
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let frame = compression.compress(&expected).unwrap();
+            socket.write_u32(frame.len() as u32).await.unwrap();
+            socket.write_all(&frame).await.unwrap();
+        });
+
+        let mut config = TcpClientConfig::default();
+        config.base.server_addr = addr.to_string();
+        config.base.reconnect.enabled = false;
+        config.base.compression = compression;
+        config.framing = FramingMode::LengthPrefixed;
+
+        // Send path: payload on the wire is compressed
+        let mut client = TcpClient::new(config.clone());
+        client.connect_only().await.unwrap();
+        client
+            .send_cot(CotMessage {
+                data: bytes::Bytes::from(xml.clone()),
+                metadata: None,
+            })
+            .await
+            .unwrap();
+        let on_wire = sent_rx.await.unwrap();
+        assert_ne!(on_wire, xml);
+        assert_eq!(&compression.decompress(on_wire.into()).unwrap()[..], &xml[..]);
+
+        // Receive path: frames are decompressed transparently
+        let mut client = TcpClient::new(config);
+        client.connect().await.unwrap();
+        let received = client.receive_cot().next().await.unwrap().unwrap();
+        assert_eq!(&received.data[..], &xml[..]);
+    }
+
+    #[tokio::test]
+    async fn test_send_after_connect() {
+        use tokio::io::AsyncBufReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut line = String::new();
+            tokio::io::BufReader::new(&mut socket)
+                .read_line(&mut line)
+                .await
+                .unwrap();
+            line
+        });
+
+        let mut config = TcpClientConfig::default();
+        config.base.server_addr = addr.to_string();
+        config.base.reconnect.enabled = false;
+
+        // The receive task owns the read half; sending still works
+        let mut client = TcpClient::new(config);
+        client.connect().await.unwrap();
+        client
+            .send_cot(CotMessage {
+                data: bytes::Bytes::from_static(b"<event uid=\"a\"/>"),
+                metadata: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(server.await.unwrap(), "<event uid=\"a\"/>\n");
+    }
 }
@@ -164,6 +164,19 @@ impl TlsClientConfig {
         self.server_name = Some(server_name);
         self
     }
+
+    /// Server name sent for SNI and checked against the certificate
+    pub(crate) fn sni_name(&self) -> String {
+        self.server_name.clone().unwrap_or_else(|| {
+            // Extract hostname from server_addr
+            self.base
+                .server_addr
+                .split(':')
+                .next()
+                .unwrap_or("localhost")
+                .to_string()
+        })
+    }
 }
 
 /// TLS client for secure TAK server connections
@@ -200,7 +213,7 @@ impl TlsClient {
     }
 
     /// Build native-tls configuration
-    fn build_tls_config(config: &TlsClientConfig) -> Result<NativeTlsConnector> {
+    pub(crate) fn build_tls_config(config: &TlsClientConfig) -> Result<NativeTlsConnector> {
         info!("Building TLS configuration with native-tls");
 
         let mut builder = NativeTlsConnector::builder();
@@ -334,8 +347,35 @@ impl TlsClient {
                     }
                 }
             }
-            TlsCertSource::Bundle(_bundle) => {
-                return Err(anyhow!("Bundle source not supported with native-tls, use Files or Memory"));
+            TlsCertSource::Bundle(bundle) => {
+                info!("Loading certificates from in-memory bundle");
+
+                // native-tls only accepts PKCS#8 keys for PEM identities
+                if !matches!(bundle.private_key, rustls::pki_types::PrivateKeyDer::Pkcs8(_)) {
+                    return Err(anyhow!(
+                        "Bundle private key must be PKCS#8 encoded (convert with 'openssl pkcs8 -topk8 -nocrypt')"
+                    ));
+                }
+
+                // Re-encode as PEM in memory - the key never touches the filesystem
+                let cert_pem = bundle.certs_pem();
+                let key_pem = bundle.private_key_pem();
+                let identity = Identity::from_pkcs8(cert_pem.as_bytes(), key_pem.as_bytes())
+                    .context("Failed to create identity from certificate bundle")?;
+                builder.identity(identity);
+
+                if let Some(ca_certs) = &bundle.ca_certs {
+                    if config.verify_server {
+                        for ca in ca_certs {
+                            let ca_cert = Certificate::from_der(ca.as_ref())
+                                .context("Failed to load CA certificate from bundle")?;
+                            builder.add_root_certificate(ca_cert);
+                        }
+                        info!("Loaded {} CA certificate(s) from bundle", ca_certs.len());
+                    } else {
+                        info!("Skipping CA certificate loading because server verification is disabled");
+                    }
+                }
             }
         }
 
@@ -357,25 +397,19 @@ impl TlsClient {
 
     /// Extract server name from address for SNI
     fn get_server_name(&self) -> String {
-        self.config
-            .server_name
-            .as_ref()
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| {
-                // Extract hostname from server_addr
-                self.config
-                    .base
-                    .server_addr
-                    .split(':')
-                    .next()
-                    .unwrap_or("localhost")
-                    .to_string()
-            })
+        self.config.sni_name()
     }
 
     /// Establish TLS connection
     #[instrument(skip(self))]
     async fn establish_connection(&mut self) -> Result<()> {
+        // Compressed frames are binary and cannot be delimited by newline or </event>
+        if self.config.base.compression.is_enabled()
+            && self.config.framing != FramingMode::LengthPrefixed
+        {
+            return Err(anyhow!("Frame compression requires length-prefixed framing"));
+        }
+
         self.status.set_state(ConnectionState::Connecting);
 
         info!("Connecting to {} with TLS", self.config.base.server_addr);
@@ -719,6 +753,7 @@ impl TlsClient {
         let (shutdown_tx, mut shutdown_rx) = mpsc::channel(1);
         self.shutdown_tx = Some(shutdown_tx);
         let framing = self.config.framing;
+        let compression = self.config.base.compression;
 
         // Move the stream out for the task
         if let Some(mut stream) = self.stream.take() {
@@ -732,6 +767,16 @@ impl TlsClient {
                         result = Self::read_frame_static(&mut stream, &mut buffer, &status, framing) => {
                             match result {
                                 Ok(Some(frame)) => {
+                                    let frame = match compression.decompress(frame) {
+                                        Ok(frame) => frame,
+                                        Err(e) => {
+                                            error!(error = %e, "Failed to decompress frame");
+                                            status.metrics().record_error();
+                                            let _ = tx.send(Err(e)).await;
+                                            break;
+                                        }
+                                    };
+
                                     status.metrics().record_message_received();
                                     let message = CotMessage {
                                         data: frame,
@@ -980,7 +1025,8 @@ impl TakClient for TlsClient {
 
         debug!(size = message.data.len(), "Sending CoT message over TLS");
 
-        self.write_frame(&message.data).await?;
+        let payload = self.config.base.compression.compress(&message.data)?;
+        self.write_frame(&payload).await?;
         self.status.metrics().record_message_sent();
 
         Ok(())
@@ -3,8 +3,10 @@
 //! This module provides a comprehensive configuration system that supports:
 //! - Loading from YAML files
 //! - Environment variable overrides
+//! - `${VAR}` and `secret://` references in values
 //! - Validation of all settings
 //! - Server definitions, filter rules, TLS settings, and logging configuration
+//! - Connection pool, distributor and aggregator tuning
 
 use crate::error::{ConfigError, Result};
 use crate::types::{ServerConfig, TlsConfig};
@@ -48,6 +50,18 @@ pub struct AppConfig {
     #[serde(default)]
     pub filters: FilterConfig,
 
+    /// Connection pool settings
+    #[serde(default)]
+    pub pool: PoolConfig,
+
+    /// Message distributor settings
+    #[serde(default)]
+    pub distributor: DistributorConfig,
+
+    /// Message aggregator (deduplication) settings
+    #[serde(default)]
+    pub aggregator: AggregatorConfig,
+
     /// Logging configuration
     #[serde(default)]
     pub logging: LoggingConfig,
@@ -94,13 +108,16 @@ impl AppConfig {
         Self::from_yaml(&contents)
     }
 
-    /// Loads configuration from a YAML string.
+    /// Loads configuration from a YAML string, resolving `${VAR}` and
+    /// `secret://` references (see [`crate::secrets`]).
     ///
     /// # Errors
     ///
-    /// Returns an error if the YAML cannot be parsed.
+    /// Returns an error if the YAML cannot be parsed or a reference cannot
+    /// be resolved.
     pub fn from_yaml(yaml: &str) -> Result<Self> {
-        serde_yaml::from_str(yaml).map_err(|e| {
+        let value = crate::secrets::resolve_yaml(yaml)?;
+        serde_yaml::from_value(value).map_err(|e| {
             ConfigError::InvalidFormat {
                 reason: e.to_string(),
             }
@@ -187,9 +204,17 @@ impl AppConfig {
         // Validate filter rules
         self.filters.validate()?;
 
+        // Validate routing core settings
+        self.pool.validate()?;
+        self.distributor.validate()?;
+        self.aggregator.validate()?;
+
         // Validate API configuration
         self.api.validate()?;
 
+        // Validate metrics configuration
+        self.metrics.validate()?;
+
         // Validate plugin configuration
         self.plugins.validate()?;
 
@@ -249,6 +274,9 @@ impl AppConfig {
         self.app = other.app;
         self.servers.extend(other.servers);
         self.filters = other.filters;
+        self.pool = other.pool;
+        self.distributor = other.distributor;
+        self.aggregator = other.aggregator;
         self.logging = other.logging;
         self.api = other.api;
         self.metrics = other.metrics;
@@ -471,6 +499,335 @@ pub enum FilterOperator {
     EndsWith,
 }
 
+/// Checks that a size or count setting is not zero.
+fn require_nonzero(field: &str, value: u64) -> Result<()> {
+    if value == 0 {
+        return Err(ConfigError::InvalidValue {
+            field: field.to_string(),
+            reason: "Must be greater than 0".to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Connection pool configuration.
+///
+/// The defaults are the values the server has always run with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolConfig {
+    /// Maximum number of pooled connections
+    #[serde(default = "default_max_connections")]
+    pub max_connections: usize,
+
+    /// Outbound message queue per connection
+    #[serde(default = "default_pool_channel_capacity")]
+    pub channel_capacity: usize,
+
+    /// Health check interval in seconds
+    #[serde(default = "default_health_check_interval")]
+    pub health_check_interval_secs: u64,
+
+    /// Seconds without traffic before a connection is considered inactive
+    #[serde(default = "default_inactive_timeout")]
+    pub inactive_timeout_secs: u64,
+
+    /// Whether dropped connections are re-established
+    #[serde(default = "default_true")]
+    pub auto_reconnect: bool,
+}
+
+fn default_pool_channel_capacity() -> usize {
+    1000
+}
+
+fn default_health_check_interval() -> u64 {
+    30
+}
+
+fn default_inactive_timeout() -> u64 {
+    300
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: default_max_connections(),
+            channel_capacity: default_pool_channel_capacity(),
+            health_check_interval_secs: default_health_check_interval(),
+            inactive_timeout_secs: default_inactive_timeout(),
+            auto_reconnect: true,
+        }
+    }
+}
+
+impl PoolConfig {
+    /// Validates the pool configuration.
+    pub fn validate(&self) -> Result<()> {
+        require_nonzero("pool.max_connections", self.max_connections as u64)?;
+        require_nonzero("pool.channel_capacity", self.channel_capacity as u64)?;
+        require_nonzero(
+            "pool.health_check_interval_secs",
+            self.health_check_interval_secs,
+        )?;
+        require_nonzero("pool.inactive_timeout_secs", self.inactive_timeout_secs)
+    }
+
+    /// Returns the health check interval as a Duration.
+    pub fn health_check_interval(&self) -> Duration {
+        Duration::from_secs(self.health_check_interval_secs)
+    }
+
+    /// Returns the inactive timeout as a Duration.
+    pub fn inactive_timeout(&self) -> Duration {
+        Duration::from_secs(self.inactive_timeout_secs)
+    }
+}
+
+/// Message distributor configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DistributorConfig {
+    /// Inbound message queue
+    #[serde(default = "default_distributor_channel_capacity")]
+    pub channel_capacity: usize,
+
+    /// What to do when a connection's queue is full
+    #[serde(default)]
+    pub strategy: DistributionStrategy,
+
+    /// Wait time in milliseconds for the `try_for_timeout` strategy
+    #[serde(default = "default_send_timeout")]
+    pub send_timeout_ms: u64,
+
+    /// Maximum concurrent distribution workers
+    #[serde(default = "default_max_workers")]
+    pub max_workers: usize,
+
+    /// Messages distributed per batch
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+
+    /// Batch flush interval in milliseconds
+    #[serde(default = "default_flush_interval")]
+    pub flush_interval_ms: u64,
+}
+
+fn default_distributor_channel_capacity() -> usize {
+    10_000
+}
+
+fn default_send_timeout() -> u64 {
+    100
+}
+
+fn default_max_workers() -> usize {
+    16
+}
+
+fn default_batch_size() -> usize {
+    100
+}
+
+fn default_flush_interval() -> u64 {
+    10
+}
+
+impl Default for DistributorConfig {
+    fn default() -> Self {
+        Self {
+            channel_capacity: default_distributor_channel_capacity(),
+            strategy: DistributionStrategy::default(),
+            send_timeout_ms: default_send_timeout(),
+            max_workers: default_max_workers(),
+            batch_size: default_batch_size(),
+            flush_interval_ms: default_flush_interval(),
+        }
+    }
+}
+
+impl DistributorConfig {
+    /// Validates the distributor configuration.
+    pub fn validate(&self) -> Result<()> {
+        require_nonzero("distributor.channel_capacity", self.channel_capacity as u64)?;
+        require_nonzero("distributor.max_workers", self.max_workers as u64)?;
+        require_nonzero("distributor.batch_size", self.batch_size as u64)?;
+        if self.strategy == DistributionStrategy::TryForTimeout {
+            require_nonzero("distributor.send_timeout_ms", self.send_timeout_ms)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the `try_for_timeout` wait time as a Duration.
+    pub fn send_timeout(&self) -> Duration {
+        Duration::from_millis(self.send_timeout_ms)
+    }
+
+    /// Returns the batch flush interval as a Duration.
+    pub fn flush_interval(&self) -> Duration {
+        Duration::from_millis(self.flush_interval_ms)
+    }
+}
+
+/// Behaviour when a destination connection's queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DistributionStrategy {
+    /// Drop the message for that connection
+    #[default]
+    DropOnFull,
+    /// Wait for space (a slow connection delays the others)
+    BlockOnFull,
+    /// Wait up to `send_timeout_ms`, then drop
+    TryForTimeout,
+}
+
+/// Message aggregator configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregatorConfig {
+    /// Seconds a message UID is remembered for deduplication
+    #[serde(default = "default_dedup_window")]
+    pub dedup_window_secs: u64,
+
+    /// Maximum entries in the deduplication cache
+    #[serde(default = "default_max_cache_entries")]
+    pub max_cache_entries: usize,
+
+    /// Expired cache entry cleanup interval in seconds
+    #[serde(default = "default_cleanup_interval")]
+    pub cleanup_interval_secs: u64,
+
+    /// Inbound message queue
+    #[serde(default = "default_aggregator_channel_capacity")]
+    pub channel_capacity: usize,
+
+    /// Number of aggregator workers
+    #[serde(default = "default_worker_count")]
+    pub worker_count: usize,
+
+    /// What makes two messages duplicates within the window
+    #[serde(default)]
+    pub dedup_key: DedupKey,
+
+    /// Dedup keys for CoT types; the longest matching prefix wins
+    #[serde(default = "default_dedup_overrides")]
+    pub dedup_overrides: Vec<DedupOverride>,
+}
+
+/// What makes two messages duplicates within the dedup window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupKey {
+    /// Same UID
+    #[default]
+    Uid,
+    /// Same UID and event `time`
+    UidTime,
+    /// Same UID and position
+    UidPoint,
+    /// Identical message
+    Content,
+    /// Never dropped as a duplicate
+    None,
+}
+
+/// Dedup key for CoT types starting with a prefix.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DedupOverride {
+    /// CoT type prefix, e.g. `b-t-f`
+    pub type_prefix: String,
+    pub key: DedupKey,
+}
+
+impl DedupOverride {
+    pub fn new(type_prefix: impl Into<String>, key: DedupKey) -> Self {
+        Self {
+            type_prefix: type_prefix.into(),
+            key,
+        }
+    }
+}
+
+fn default_dedup_window() -> u64 {
+    60
+}
+
+fn default_max_cache_entries() -> usize {
+    100_000
+}
+
+fn default_cleanup_interval() -> u64 {
+    10
+}
+
+fn default_aggregator_channel_capacity() -> usize {
+    10_000
+}
+
+fn default_worker_count() -> usize {
+    4
+}
+
+/// Chat is never deduplicated: every message is new even when the text and
+/// UID repeat. File transfer requests for different files share a UID.
+fn default_dedup_overrides() -> Vec<DedupOverride> {
+    vec![
+        DedupOverride::new("b-t-f", DedupKey::None),
+        DedupOverride::new("b-f-t", DedupKey::Content),
+    ]
+}
+
+impl Default for AggregatorConfig {
+    fn default() -> Self {
+        Self {
+            dedup_window_secs: default_dedup_window(),
+            max_cache_entries: default_max_cache_entries(),
+            cleanup_interval_secs: default_cleanup_interval(),
+            channel_capacity: default_aggregator_channel_capacity(),
+            worker_count: default_worker_count(),
+            dedup_key: DedupKey::default(),
+            dedup_overrides: default_dedup_overrides(),
+        }
+    }
+}
+
+impl AggregatorConfig {
+    /// Validates the aggregator configuration.
+    pub fn validate(&self) -> Result<()> {
+        require_nonzero(
+            "aggregator.max_cache_entries",
+            self.max_cache_entries as u64,
+        )?;
+        require_nonzero(
+            "aggregator.cleanup_interval_secs",
+            self.cleanup_interval_secs,
+        )?;
+        require_nonzero("aggregator.channel_capacity", self.channel_capacity as u64)?;
+        require_nonzero("aggregator.worker_count", self.worker_count as u64)?;
+        if self
+            .dedup_overrides
+            .iter()
+            .any(|o| o.type_prefix.is_empty())
+        {
+            return Err(ConfigError::InvalidValue {
+                field: "aggregator.dedup_overrides".to_string(),
+                reason: "type_prefix must not be empty; use dedup_key for every type".to_string(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Returns the deduplication window as a Duration.
+    pub fn dedup_window(&self) -> Duration {
+        Duration::from_secs(self.dedup_window_secs)
+    }
+
+    /// Returns the cache cleanup interval as a Duration.
+    pub fn cleanup_interval(&self) -> Duration {
+        Duration::from_secs(self.cleanup_interval_secs)
+    }
+}
+
 /// Logging configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoggingConfig {
@@ -649,7 +1006,7 @@ fn default_metrics_path() -> String {
 }
 
 fn default_metrics_interval() -> u64 {
-    60
+    30
 }
 
 impl Default for MetricsConfig {
@@ -663,6 +1020,11 @@ impl Default for MetricsConfig {
 }
 
 impl MetricsConfig {
+    /// Validates the metrics configuration.
+    pub fn validate(&self) -> Result<()> {
+        require_nonzero("metrics.interval_secs", self.interval_secs)
+    }
+
     /// Returns the metrics collection interval as a Duration.
     pub fn interval(&self) -> Duration {
         Duration::from_secs(self.interval_secs)
@@ -1218,6 +1580,79 @@ api:
         assert!(invalid.parse_level().is_err());
     }
 
+    #[test]
+    fn test_routing_core_defaults() {
+        let config = AppConfig::from_yaml("{}").unwrap();
+        assert_eq!(config.pool.max_connections, 1000);
+        assert_eq!(config.pool.health_check_interval(), Duration::from_secs(30));
+        assert_eq!(
+            config.distributor.strategy,
+            DistributionStrategy::DropOnFull
+        );
+        assert_eq!(
+            config.distributor.flush_interval(),
+            Duration::from_millis(10)
+        );
+        assert_eq!(config.aggregator.dedup_window(), Duration::from_secs(60));
+        assert_eq!(config.aggregator.worker_count, 4);
+        assert_eq!(config.aggregator.dedup_key, DedupKey::Uid);
+        assert_eq!(
+            config.aggregator.dedup_overrides[0],
+            DedupOverride::new("b-t-f", DedupKey::None)
+        );
+    }
+
+    #[test]
+    fn test_routing_core_from_yaml() {
+        let yaml = r#"
+pool:
+  max_connections: 5000
+  auto_reconnect: false
+distributor:
+  max_workers: 32
+  strategy: try_for_timeout
+  send_timeout_ms: 50
+aggregator:
+  dedup_window_secs: 0
+  worker_count: 8
+  dedup_key: uid_point
+  dedup_overrides:
+    - type_prefix: "b-t-f"
+      key: uid_time
+"#;
+        let config = AppConfig::from_yaml(yaml).unwrap();
+        assert_eq!(config.pool.max_connections, 5000);
+        assert_eq!(config.pool.channel_capacity, 1000);
+        assert!(!config.pool.auto_reconnect);
+        assert_eq!(config.distributor.max_workers, 32);
+        assert_eq!(
+            config.distributor.strategy,
+            DistributionStrategy::TryForTimeout
+        );
+        assert_eq!(config.distributor.send_timeout(), Duration::from_millis(50));
+        assert_eq!(config.aggregator.dedup_window_secs, 0);
+        assert_eq!(config.aggregator.dedup_key, DedupKey::UidPoint);
+        assert_eq!(
+            config.aggregator.dedup_overrides,
+            vec![DedupOverride::new("b-t-f", DedupKey::UidTime)]
+        );
+        assert!(config.pool.validate().is_ok());
+        assert!(config.distributor.validate().is_ok());
+        assert!(config.aggregator.validate().is_ok());
+
+        let invalid = DistributorConfig {
+            max_workers: 0,
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+
+        let invalid = AggregatorConfig {
+            dedup_overrides: vec![DedupOverride::new("", DedupKey::None)],
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+
     #[test]
     fn test_metrics_interval() {
         let metrics = MetricsConfig {
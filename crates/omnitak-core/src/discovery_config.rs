@@ -1,8 +1,16 @@
 //! Configuration types for service discovery
 
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::net::{Ipv4Addr, SocketAddrV4};
 use std::time::Duration;
 
+/// Longest TXT record entry (`key=value`) mDNS allows
+const MAX_TXT_ENTRY_LEN: usize = 255;
+
+/// Most addresses a scan may cover, a /16
+const MAX_SCAN_HOSTS: usize = 65536;
+
 /// Configuration for the mDNS discovery service
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiscoveryConfig {
@@ -26,6 +34,19 @@ pub struct DiscoveryConfig {
     #[serde(default)]
     pub instance_name: Option<String>,
 
+    /// Services to announce. If empty, the REST API is announced as
+    /// `_tak-aggregator._tcp` on `announce_port`
+    #[serde(default)]
+    pub announcements: Vec<AnnouncementConfig>,
+
+    /// Active scanning for TAK servers that don't announce themselves
+    #[serde(default)]
+    pub scan: ScanConfig,
+
+    /// Passive discovery of ATAK devices from their multicast SA
+    #[serde(default)]
+    pub multicast_sa: MulticastSaConfig,
+
     /// How often to check for stale services (seconds)
     #[serde(default = "default_cleanup_interval")]
     pub cleanup_interval_secs: u64,
@@ -51,6 +72,9 @@ impl Default for DiscoveryConfig {
             service_types: default_service_types(),
             announce_port: default_announce_port(),
             instance_name: None,
+            announcements: Vec::new(),
+            scan: ScanConfig::default(),
+            multicast_sa: MulticastSaConfig::default(),
             cleanup_interval_secs: default_cleanup_interval(),
             stale_timeout_secs: default_stale_timeout(),
             auto_connect: default_auto_connect(),
@@ -88,10 +112,302 @@ impl DiscoveryConfig {
             return Err("at least one service type must be configured".to_string());
         }
 
+        for announcement in &self.announcements {
+            announcement.validate()?;
+        }
+
+        self.scan.validate()?;
+        self.multicast_sa.validate()?;
+        if self.scan.enabled && self.scan.interval_secs >= self.stale_timeout_secs {
+            return Err(
+                "scan interval_secs must be shorter than stale_timeout_secs".to_string(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The services to announce, with the default REST API announcement if
+    /// none are configured
+    pub fn effective_announcements(&self) -> Vec<AnnouncementConfig> {
+        if !self.announcements.is_empty() {
+            return self.announcements.clone();
+        }
+        let mut properties = HashMap::new();
+        properties.insert("description".to_string(), "OmniTAK Server Aggregator".to_string());
+        properties.insert("api".to_string(), "rest+websocket".to_string());
+        vec![AnnouncementConfig {
+            service_type: "_tak-aggregator._tcp".to_string(),
+            port: self.announce_port,
+            protocol: "http".to_string(),
+            tls: false,
+            properties,
+        }]
+    }
+}
+
+/// A service this instance announces via mDNS
+///
+/// The TXT record carries `protocol`, `port`, `tls` and `version`, plus any
+/// extra `properties`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnouncementConfig {
+    /// mDNS service type, e.g. `_takserver._tcp` or `_omnitak._tcp`
+    pub service_type: String,
+
+    /// Port the service listens on
+    pub port: u16,
+
+    /// Protocol clients should use, e.g. `tcp`, `tls`, `udp` or `https`
+    #[serde(default = "default_protocol")]
+    pub protocol: String,
+
+    /// Whether clients must connect with TLS
+    #[serde(default)]
+    pub tls: bool,
+
+    /// Extra TXT record properties
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+}
+
+impl AnnouncementConfig {
+    /// Returns the fully qualified mDNS service type string
+    pub fn to_service_string(&self) -> String {
+        let service_type = self.service_type.trim_end_matches('.');
+        if service_type.ends_with(".local") {
+            format!("{}.", service_type)
+        } else {
+            format!("{}.local.", service_type)
+        }
+    }
+
+    /// TXT record properties: the configured ones, then the standard keys,
+    /// which take precedence
+    pub fn txt_properties(&self, version: &str) -> HashMap<String, String> {
+        let mut properties = self.properties.clone();
+        properties.insert("protocol".to_string(), self.protocol.clone());
+        properties.insert("port".to_string(), self.port.to_string());
+        properties.insert("tls".to_string(), self.tls.to_string());
+        properties.insert("version".to_string(), version.to_string());
+        properties
+    }
+
+    /// Validates the announcement
+    pub fn validate(&self) -> Result<(), String> {
+        let name = self.to_service_string();
+        let labels: Vec<&str> = name.trim_end_matches('.').split('.').collect();
+        let valid = matches!(
+            labels.as_slice(),
+            [service, "_tcp" | "_udp", "local"]
+                if service.len() > 1 && service.len() <= 16 && service.starts_with('_')
+        );
+        if !valid {
+            return Err(format!(
+                "invalid announcement service type '{}', expected e.g. _omnitak._tcp",
+                self.service_type
+            ));
+        }
+
+        if self.port == 0 {
+            return Err(format!("announcement '{}' port cannot be 0", self.service_type));
+        }
+
+        for (key, value) in &self.properties {
+            if key.is_empty() || key.contains('=') {
+                return Err(format!("invalid TXT property key '{}'", key));
+            }
+            if key.len() + 1 + value.len() > MAX_TXT_ENTRY_LEN {
+                return Err(format!(
+                    "TXT property '{}' is longer than {} bytes",
+                    key, MAX_TXT_ENTRY_LEN
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Active subnet scanning
+///
+/// Opt-in complement to mDNS: every `interval_secs`, each address in
+/// `ranges` is probed on each of `ports`, at most `max_probes_per_sec`
+/// connection attempts per second. Servers found are fingerprinted as plain
+/// TCP or TLS and added to the discovery registry as TAK servers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanConfig {
+    /// Enable scanning
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// IPv4 ranges to scan in CIDR notation, e.g. `192.168.1.0/24`; a bare
+    /// address scans just that host
+    #[serde(default)]
+    pub ranges: Vec<String>,
+
+    /// Ports to probe
+    #[serde(default = "default_scan_ports")]
+    pub ports: Vec<u16>,
+
+    /// Time between sweeps (seconds); must be shorter than the stale
+    /// timeout or scanned servers go stale between sweeps
+    #[serde(default = "default_scan_interval")]
+    pub interval_secs: u64,
+
+    /// Connection attempts per second
+    #[serde(default = "default_scan_rate")]
+    pub max_probes_per_sec: u32,
+
+    /// Timeout for connecting and for each fingerprint read (milliseconds)
+    #[serde(default = "default_probe_timeout")]
+    pub probe_timeout_ms: u64,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ranges: Vec::new(),
+            ports: default_scan_ports(),
+            interval_secs: default_scan_interval(),
+            max_probes_per_sec: default_scan_rate(),
+            probe_timeout_ms: default_probe_timeout(),
+        }
+    }
+}
+
+impl ScanConfig {
+    /// Returns the sweep interval as a Duration
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+
+    /// Returns the probe timeout as a Duration
+    pub fn probe_timeout(&self) -> Duration {
+        Duration::from_millis(self.probe_timeout_ms)
+    }
+
+    /// Every address to probe, in order and without duplicates. Network and
+    /// broadcast addresses are left out of ranges larger than a /31
+    pub fn targets(&self) -> Result<Vec<Ipv4Addr>, String> {
+        let mut targets = BTreeSet::new();
+        for range in &self.ranges {
+            let (network, prefix) = parse_cidr(range)?;
+            let size = 1u64 << (32 - prefix);
+            if targets.len() as u64 + size > MAX_SCAN_HOSTS as u64 {
+                return Err(format!(
+                    "scan ranges cover more than {} addresses",
+                    MAX_SCAN_HOSTS
+                ));
+            }
+            let first = u32::from(network) as u64;
+            let (start, end) = if prefix < 31 {
+                (first + 1, first + size - 1)
+            } else {
+                (first, first + size)
+            };
+            targets.extend((start..end).map(|ip| Ipv4Addr::from(ip as u32)));
+        }
+        Ok(targets.into_iter().collect())
+    }
+
+    /// Validates the scan settings; ranges are checked even when disabled
+    pub fn validate(&self) -> Result<(), String> {
+        let targets = self.targets()?;
+        if !self.enabled {
+            return Ok(());
+        }
+
+        if targets.is_empty() {
+            return Err("scan is enabled but no ranges are configured".to_string());
+        }
+        if self.ports.is_empty() || self.ports.contains(&0) {
+            return Err("scan ports must be non-empty and cannot be 0".to_string());
+        }
+        if self.interval_secs == 0 {
+            return Err("scan interval_secs cannot be 0".to_string());
+        }
+        if self.max_probes_per_sec == 0 {
+            return Err("scan max_probes_per_sec cannot be 0".to_string());
+        }
+        if self.probe_timeout_ms == 0 {
+            return Err("scan probe_timeout_ms cannot be 0".to_string());
+        }
+
         Ok(())
     }
 }
 
+/// Passive device discovery from the ATAK mesh SA multicast group
+///
+/// ATAK devices broadcast their own position (self-SA) to the mesh group
+/// even when they don't advertise via mDNS. Listening there registers each
+/// device, by UID, with its callsign and address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MulticastSaConfig {
+    /// Enable listening
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Multicast group and port, `239.2.3.1:6969` for the ATAK default mesh
+    #[serde(default = "default_sa_group")]
+    pub group: String,
+
+    /// Local interface address to join the group on (all by default)
+    #[serde(default)]
+    pub interface: Option<Ipv4Addr>,
+}
+
+impl Default for MulticastSaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            group: default_sa_group(),
+            interface: None,
+        }
+    }
+}
+
+impl MulticastSaConfig {
+    /// The multicast group address
+    pub fn group_addr(&self) -> Result<SocketAddrV4, String> {
+        let addr: SocketAddrV4 = self.group.parse().map_err(|_| {
+            format!(
+                "invalid multicast SA group '{}', expected e.g. 239.2.3.1:6969",
+                self.group
+            )
+        })?;
+        if !addr.ip().is_multicast() {
+            return Err(format!(
+                "multicast SA group {} is not a multicast address",
+                addr.ip()
+            ));
+        }
+        Ok(addr)
+    }
+
+    /// Validates the settings
+    pub fn validate(&self) -> Result<(), String> {
+        self.group_addr().map(|_| ())
+    }
+}
+
+/// Parses `a.b.c.d/n` or a bare address into the network address and prefix
+fn parse_cidr(range: &str) -> Result<(Ipv4Addr, u32), String> {
+    let invalid = || format!("invalid scan range '{}', expected e.g. 192.168.1.0/24", range);
+    let (addr, prefix) = match range.trim().split_once('/') {
+        Some((addr, prefix)) => (addr, prefix.parse::<u32>().map_err(|_| invalid())?),
+        None => (range.trim(), 32),
+    };
+    let addr: Ipv4Addr = addr.parse().map_err(|_| invalid())?;
+    if prefix > 32 {
+        return Err(invalid());
+    }
+    let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+    Ok((Ipv4Addr::from(u32::from(addr) & mask), prefix))
+}
+
 /// Types of services to discover
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -172,6 +488,10 @@ fn default_announce_port() -> u16 {
     8080
 }
 
+fn default_protocol() -> String {
+    "tcp".to_string()
+}
+
 fn default_cleanup_interval() -> u64 {
     30 // Check every 30 seconds
 }
@@ -180,6 +500,26 @@ fn default_stale_timeout() -> u64 {
     300 // 5 minutes
 }
 
+fn default_scan_ports() -> Vec<u16> {
+    vec![8087, 8089, 8443]
+}
+
+fn default_scan_interval() -> u64 {
+    240 // 4 minutes, so found servers are re-seen before going stale
+}
+
+fn default_scan_rate() -> u32 {
+    20
+}
+
+fn default_probe_timeout() -> u64 {
+    1000
+}
+
+fn default_sa_group() -> String {
+    "239.2.3.1:6969".to_string()
+}
+
 fn default_auto_connect() -> bool {
     false // Require manual approval by default
 }
@@ -187,3 +527,116 @@ fn default_auto_connect() -> bool {
 fn default_require_tls() -> bool {
     true // Require TLS for auto-connect
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn announcement(service_type: &str) -> AnnouncementConfig {
+        AnnouncementConfig {
+            service_type: service_type.to_string(),
+            port: 8089,
+            protocol: "tls".to_string(),
+            tls: true,
+            properties: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_announcement_txt_properties() {
+        let mut takserver = announcement("_takserver._tcp");
+        takserver.properties.insert("tls".to_string(), "false".to_string());
+        takserver.properties.insert("site".to_string(), "alpha".to_string());
+        assert_eq!(takserver.to_service_string(), "_takserver._tcp.local.");
+
+        let txt = takserver.txt_properties("0.2.0");
+        assert_eq!(txt["protocol"], "tls");
+        assert_eq!(txt["port"], "8089");
+        assert_eq!(txt["tls"], "true");
+        assert_eq!(txt["version"], "0.2.0");
+        assert_eq!(txt["site"], "alpha");
+    }
+
+    #[test]
+    fn test_announcement_validation() {
+        assert!(announcement("_omnitak._tcp").validate().is_ok());
+        assert!(announcement("_omnitak._udp.local.").validate().is_ok());
+        assert!(announcement("omnitak._tcp").validate().is_err());
+        assert!(announcement("_omnitak").validate().is_err());
+        assert!(announcement("_a-very-long-service-name._tcp").validate().is_err());
+
+        let mut bad_port = announcement("_omnitak._tcp");
+        bad_port.port = 0;
+        assert!(bad_port.validate().is_err());
+
+        let mut long_txt = announcement("_omnitak._tcp");
+        long_txt.properties.insert("notes".to_string(), "x".repeat(255));
+        assert!(long_txt.validate().is_err());
+    }
+
+    #[test]
+    fn test_scan_targets() {
+        let scan = ScanConfig {
+            enabled: true,
+            ranges: vec![
+                "192.168.1.7/30".to_string(),
+                "10.0.0.1".to_string(),
+                "192.168.1.5".to_string(),
+            ],
+            ..Default::default()
+        };
+        let targets = scan.targets().unwrap();
+        assert_eq!(
+            targets,
+            vec![
+                Ipv4Addr::new(10, 0, 0, 1),
+                Ipv4Addr::new(192, 168, 1, 5),
+                Ipv4Addr::new(192, 168, 1, 6),
+            ]
+        );
+        assert!(scan.validate().is_ok());
+
+        let too_large = ScanConfig {
+            ranges: vec!["10.0.0.0/15".to_string()],
+            ..Default::default()
+        };
+        assert!(too_large.validate().is_err());
+
+        let invalid = ScanConfig {
+            ranges: vec!["10.0.0.0/33".to_string()],
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+
+        let no_ranges = ScanConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        assert!(no_ranges.validate().is_err());
+        assert!(ScanConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_multicast_sa_group() {
+        let config = MulticastSaConfig::default();
+        assert_eq!(
+            config.group_addr().unwrap(),
+            "239.2.3.1:6969".parse().unwrap()
+        );
+
+        let unicast = MulticastSaConfig {
+            group: "192.168.1.1:6969".to_string(),
+            ..Default::default()
+        };
+        assert!(unicast.validate().is_err());
+    }
+
+    #[test]
+    fn test_default_announcement() {
+        let config = DiscoveryConfig::default();
+        let announcements = config.effective_announcements();
+        assert_eq!(announcements.len(), 1);
+        assert_eq!(announcements[0].to_service_string(), "_tak-aggregator._tcp.local.");
+        assert_eq!(announcements[0].port, config.announce_port);
+    }
+}
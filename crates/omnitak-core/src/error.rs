@@ -409,6 +409,10 @@ pub enum ConfigError {
     #[error("Environment variable error: {details}")]
     EnvironmentVariableError { details: String },
 
+    /// Secret reference could not be read
+    #[error("Secret {reference} unavailable: {reason}")]
+    SecretUnavailable { reference: String, reason: String },
+
     /// Configuration merge conflict
     #[error("Configuration merge conflict: {details}")]
     MergeConflict { details: String },
@@ -41,9 +41,12 @@ pub mod config;
 pub mod discovery_config;
 pub mod error;
 pub mod plugins;
+pub mod secrets;
+pub mod time_sync;
 pub mod types;
 
 // Re-export commonly used types for convenience
 pub use config::AppConfig;
 pub use error::{OmniTAKError, Result};
-pub use types::{ConnectionId, Protocol, ServerConfig, ServerStatus};
+pub use time_sync::{GpsClock, TimeSyncConfig, TimeSyncStatus};
+pub use types::{ConnectionId, Protocol, ServerConfig, ServerStatus, TraceId};
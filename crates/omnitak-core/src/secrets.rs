@@ -0,0 +1,270 @@
+//! Environment variable and secret references in configuration values.
+//!
+//! String values in a YAML configuration may refer to values kept outside
+//! the file, so that passwords, API keys and tokens need not be stored in
+//! plaintext:
+//!
+//! - `${VAR}` is replaced by the environment variable `VAR`, and
+//!   `${VAR:-default}` falls back to `default` when `VAR` is unset or empty.
+//!   `$${` stands for a literal `${`.
+//! - A value of the form `secret://keyring/<service>/<account>` is replaced by
+//!   the password stored in the operating system's credential store (macOS
+//!   Keychain, Windows Credential Manager, Linux kernel keyring).
+//! - A value of the form `secret://file/<path>` is replaced by the contents of
+//!   the file without its trailing newline, as with Docker and Kubernetes
+//!   secrets. The path is absolute: `secret://file/run/secrets/admin` reads
+//!   `/run/secrets/admin` (`secret://file/C:/secrets/admin` on Windows).
+//!
+//! References are resolved after the YAML is parsed, so a resolved value
+//! cannot change the structure of the document. A value that is exactly one
+//! `${VAR}` reference takes the type of what the variable holds, as if it
+//! had been written in its place: `port: ${API_PORT}` with `API_PORT=8443`
+//! is a number.
+//!
+//! # Examples
+//!
+//! ```
+//! use omnitak_core::secrets::resolve_str;
+//!
+//! std::env::set_var("OMNITAK_DOC_HOST", "tak.example.com");
+//! assert_eq!(
+//!     resolve_str("${OMNITAK_DOC_HOST}:${OMNITAK_DOC_PORT:-8089}").unwrap(),
+//!     "tak.example.com:8089"
+//! );
+//! ```
+
+use crate::error::{ConfigError, Result};
+use serde_yaml::Value;
+
+/// Scheme prefix of secret references
+const SECRET_SCHEME: &str = "secret://";
+
+/// Parses a YAML document and resolves every reference in its string values.
+///
+/// # Errors
+///
+/// Returns an error if the YAML cannot be parsed or a reference cannot be
+/// resolved.
+pub fn resolve_yaml(yaml: &str) -> Result<Value> {
+    let mut value: Value = serde_yaml::from_str(yaml).map_err(|e| ConfigError::InvalidFormat {
+        reason: e.to_string(),
+    })?;
+    resolve_value(&mut value)?;
+    Ok(value)
+}
+
+/// Resolves every reference in the string values of a parsed document.
+///
+/// # Errors
+///
+/// Returns an error naming the first reference that cannot be resolved.
+pub fn resolve_value(value: &mut Value) -> Result<()> {
+    match value {
+        Value::String(s) => {
+            if let Some(resolved) = resolve_scalar(s)? {
+                *value = resolved;
+            }
+        }
+        Value::Sequence(items) => {
+            for item in items {
+                resolve_value(item)?;
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (_, item) in mapping.iter_mut() {
+                resolve_value(item)?;
+            }
+        }
+        Value::Tagged(tagged) => resolve_value(&mut tagged.value)?,
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+    Ok(())
+}
+
+/// Resolves the references in a single string value.
+///
+/// # Errors
+///
+/// Returns an error if a variable is unset and has no default, if a
+/// reference is malformed, or if a secret cannot be read.
+pub fn resolve_str(s: &str) -> Result<String> {
+    if let Some(reference) = s.strip_prefix(SECRET_SCHEME) {
+        return read_secret(reference);
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        if let Some(after) = tail.strip_prefix("$${") {
+            out.push_str("${");
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix("${") {
+            let end = after
+                .find('}')
+                .ok_or_else(|| ConfigError::EnvironmentVariableError {
+                    details: format!("Unterminated reference in '{}'", s),
+                })?;
+            out.push_str(&env_var(&after[..end])?);
+            rest = &after[end + 1..];
+        } else {
+            out.push('$');
+            rest = &tail[1..];
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Resolved replacement for a string value, or `None` if it has no references
+fn resolve_scalar(s: &str) -> Result<Option<Value>> {
+    if !s.starts_with(SECRET_SCHEME) && !s.contains('$') {
+        return Ok(None);
+    }
+    let resolved = resolve_str(s)?;
+
+    let whole_reference = s.starts_with("${") && s.ends_with('}') && s.matches("${").count() == 1;
+    if whole_reference {
+        if let Ok(typed @ (Value::Bool(_) | Value::Number(_))) =
+            serde_yaml::from_str::<Value>(&resolved)
+        {
+            return Ok(Some(typed));
+        }
+    }
+    Ok(Some(Value::String(resolved)))
+}
+
+/// Value of `NAME` or `NAME:-default`
+fn env_var(expr: &str) -> Result<String> {
+    let (name, default) = match expr.split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (expr, None),
+    };
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(ConfigError::EnvironmentVariableError {
+            details: format!("Invalid variable name in '${{{}}}'", expr),
+        }
+        .into());
+    }
+
+    match (std::env::var(name), default) {
+        (Ok(value), Some(default)) if value.is_empty() => Ok(default.to_string()),
+        (Ok(value), _) => Ok(value),
+        (Err(_), Some(default)) => Ok(default.to_string()),
+        (Err(std::env::VarError::NotPresent), None) => Err(ConfigError::EnvironmentVariableError {
+            details: format!("{} is not set", name),
+        }
+        .into()),
+        (Err(std::env::VarError::NotUnicode(_)), None) => {
+            Err(ConfigError::EnvironmentVariableError {
+                details: format!("{} is not valid UTF-8", name),
+            }
+            .into())
+        }
+    }
+}
+
+/// Reads a `secret://` reference (without the scheme)
+fn read_secret(reference: &str) -> Result<String> {
+    let unavailable = |reason: String| ConfigError::SecretUnavailable {
+        reference: format!("{}{}", SECRET_SCHEME, reference),
+        reason,
+    };
+
+    match reference.split_once('/') {
+        Some(("keyring", entry)) => {
+            let (service, account) = entry
+                .split_once('/')
+                .filter(|(service, account)| !service.is_empty() && !account.is_empty())
+                .ok_or_else(|| {
+                    unavailable("expected secret://keyring/<service>/<account>".to_string())
+                })?;
+            keyring::Entry::new(service, account)
+                .and_then(|entry| entry.get_password())
+                .map_err(|e| unavailable(e.to_string()).into())
+        }
+        Some(("file", path)) if !path.is_empty() => {
+            // Drive letter paths stand on their own, others are from the root
+            let path = if path.as_bytes().get(1) == Some(&b':') {
+                path.to_string()
+            } else {
+                format!("/{}", path)
+            };
+            let content = std::fs::read_to_string(path).map_err(|e| unavailable(e.to_string()))?;
+            Ok(content.trim_end_matches(['\r', '\n']).to_string())
+        }
+        _ => Err(unavailable(
+            "expected secret://keyring/<service>/<account> or secret://file/<path>".to_string(),
+        )
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_substitution() {
+        std::env::set_var("OMNITAK_TEST_SECRETS_USER", "ops");
+        std::env::set_var("OMNITAK_TEST_SECRETS_EMPTY", "");
+
+        assert_eq!(
+            resolve_str("${OMNITAK_TEST_SECRETS_USER}@tak").unwrap(),
+            "ops@tak"
+        );
+        assert_eq!(
+            resolve_str("${OMNITAK_TEST_SECRETS_UNSET:-fallback}").unwrap(),
+            "fallback"
+        );
+        assert_eq!(
+            resolve_str("${OMNITAK_TEST_SECRETS_EMPTY:-fallback}").unwrap(),
+            "fallback"
+        );
+        assert_eq!(
+            resolve_str("cost: $5, $${HOME}").unwrap(),
+            "cost: $5, ${HOME}"
+        );
+
+        assert!(resolve_str("${OMNITAK_TEST_SECRETS_UNSET}").is_err());
+        assert!(resolve_str("${OMNITAK_TEST_SECRETS_USER").is_err());
+        assert!(resolve_str("${not a name}").is_err());
+    }
+
+    #[test]
+    fn test_resolve_yaml_keeps_structure() {
+        std::env::set_var("OMNITAK_TEST_SECRETS_PORT", "8443");
+        std::env::set_var("OMNITAK_TEST_SECRETS_TOKEN", "a: b\n- c");
+
+        let value = resolve_yaml(
+            "port: ${OMNITAK_TEST_SECRETS_PORT}\n\
+             bind: \"0.0.0.0:${OMNITAK_TEST_SECRETS_PORT}\"\n\
+             tokens: [\"${OMNITAK_TEST_SECRETS_TOKEN}\"]\n",
+        )
+        .unwrap();
+
+        assert_eq!(value["port"].as_u64(), Some(8443));
+        assert_eq!(value["bind"].as_str(), Some("0.0.0.0:8443"));
+        assert_eq!(value["tokens"][0].as_str(), Some("a: b\n- c"));
+    }
+
+    #[test]
+    fn test_file_secret() {
+        let path =
+            std::env::temp_dir().join(format!("omnitak-secret-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "s3cret\n").unwrap();
+        let reference = format!(
+            "secret://file/{}",
+            path.display().to_string().trim_start_matches('/')
+        );
+
+        let value = resolve_yaml(&format!("password: {}\n", reference)).unwrap();
+        assert_eq!(value["password"].as_str(), Some("s3cret"));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(resolve_str(&reference).is_err());
+        assert!(resolve_str("secret://vault/omnitak/admin").is_err());
+        assert!(resolve_str("secret://keyring/omnitak").is_err());
+    }
+}
@@ -0,0 +1,479 @@
+//! System clock synchronization checks
+//!
+//! CoT timestamps (`time`, `start`, `stale`) are only meaningful if the
+//! aggregator's clock is right: a clock running a few minutes slow makes
+//! every outgoing event look stale to end devices. This module asks the
+//! platform's time daemon (chrony, systemd-timesyncd via `timedatectl`, or
+//! Windows Time) whether the clock is synchronized and, when the daemon does
+//! not report an offset, can measure one directly with an SNTP query.
+//! Gateways with a GPS receiver but no network (e.g. vehicle-mounted) can
+//! fall back to the time of recent GPS fixes, recorded in a [`GpsClock`].
+//!
+//! Offsets are reported as local clock minus reference time, so a positive
+//! offset means the local clock is ahead.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Seconds between the NTP epoch (1900) and the Unix epoch (1970)
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// Configuration for clock synchronization monitoring
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeSyncConfig {
+    /// Enable periodic clock checks
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    /// NTP server to query when the system daemon does not report an offset
+    /// (e.g. "pool.ntp.org" or "10.0.0.1:123")
+    #[serde(default)]
+    pub ntp_server: Option<String>,
+
+    /// How often to check the clock (seconds)
+    #[serde(default = "default_check_interval")]
+    pub check_interval_secs: u64,
+
+    /// Offset beyond which a drift warning is raised (milliseconds)
+    #[serde(default = "default_drift_warning")]
+    pub drift_warning_ms: u64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_check_interval() -> u64 {
+    300
+}
+
+fn default_drift_warning() -> u64 {
+    500
+}
+
+impl Default for TimeSyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            ntp_server: None,
+            check_interval_secs: default_check_interval(),
+            drift_warning_ms: default_drift_warning(),
+        }
+    }
+}
+
+impl TimeSyncConfig {
+    /// Returns the check interval as a Duration
+    pub fn check_interval(&self) -> Duration {
+        Duration::from_secs(self.check_interval_secs.max(1))
+    }
+}
+
+/// Where the synchronization status came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeSource {
+    /// `chronyc tracking`
+    Chrony,
+    /// `timedatectl show` (systemd-timesyncd)
+    Timedatectl,
+    /// `w32tm /query /status` (Windows Time service)
+    W32Time,
+    /// Direct SNTP query to the configured server
+    Ntp,
+    /// Time of recent GPS fixes
+    Gps,
+    /// No source could be queried
+    None,
+}
+
+/// Result of a clock synchronization check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeSyncStatus {
+    /// Source of the status
+    pub source: TimeSource,
+    /// Whether the system reports its clock as synchronized (None if unknown)
+    pub synchronized: Option<bool>,
+    /// Local clock minus reference time in milliseconds (None if unknown)
+    pub offset_ms: Option<f64>,
+    /// Reference server or clock, if reported
+    pub reference: Option<String>,
+    /// When this check ran
+    pub checked_at: DateTime<Utc>,
+    /// Error from the last failed query, if any
+    pub error: Option<String>,
+}
+
+impl TimeSyncStatus {
+    fn unknown() -> Self {
+        Self {
+            source: TimeSource::None,
+            synchronized: None,
+            offset_ms: None,
+            reference: None,
+            checked_at: Utc::now(),
+            error: None,
+        }
+    }
+
+    /// Whether the measured offset exceeds the threshold
+    pub fn drift_exceeds(&self, threshold_ms: u64) -> bool {
+        self.offset_ms
+            .map(|offset| offset.abs() > threshold_ms as f64)
+            .unwrap_or(false)
+    }
+
+    /// Whether the clock is known to be unsynchronized or drifting
+    pub fn needs_attention(&self, threshold_ms: u64) -> bool {
+        self.synchronized == Some(false) || self.drift_exceeds(threshold_ms)
+    }
+
+    /// Use a GPS-measured offset when no other source reported one
+    ///
+    /// If the platform could not say whether the clock is synchronized, the
+    /// GPS offset decides it.
+    pub fn apply_gps_offset(&mut self, offset_ms: f64, threshold_ms: u64) {
+        if self.offset_ms.is_some() {
+            return;
+        }
+        self.offset_ms = Some(offset_ms);
+        if self.source == TimeSource::None {
+            self.source = TimeSource::Gps;
+        }
+        self.reference.get_or_insert_with(|| "GPS".to_string());
+        if self.synchronized.is_none() {
+            self.synchronized = Some(!self.drift_exceeds(threshold_ms));
+        }
+    }
+}
+
+/// Local clock offset measured against GPS fix times
+///
+/// Position readers call [`GpsClock::record`] with the UTC time of each fix
+/// as it arrives. The measurement includes the receiver's output latency
+/// (typically well under a second), so it is a sanity check rather than a
+/// precise reference.
+#[derive(Debug, Default)]
+pub struct GpsClock {
+    latest: Mutex<Option<(f64, Instant)>>,
+}
+
+impl GpsClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the UTC time of a fix that has just been received
+    pub fn record(&self, gps_time: DateTime<Utc>) {
+        let offset_ms = (Utc::now() - gps_time)
+            .num_microseconds()
+            .unwrap_or(i64::MAX) as f64
+            / 1000.0;
+        *self.latest.lock().unwrap() = Some((offset_ms, Instant::now()));
+    }
+
+    /// Local clock minus GPS time in milliseconds, if a fix was recorded
+    /// within `max_age`
+    pub fn offset_ms(&self, max_age: Duration) -> Option<f64> {
+        self.latest
+            .lock()
+            .unwrap()
+            .filter(|(_, at)| at.elapsed() <= max_age)
+            .map(|(offset, _)| offset)
+    }
+}
+
+/// Check clock synchronization using whatever the platform offers
+///
+/// This blocks on subprocesses and network I/O; call it from a blocking
+/// context.
+pub fn check(config: &TimeSyncConfig) -> TimeSyncStatus {
+    let mut status = query_system().unwrap_or_else(TimeSyncStatus::unknown);
+
+    if status.offset_ms.is_none() {
+        if let Some(server) = &config.ntp_server {
+            match sntp_offset(server, Duration::from_secs(3)) {
+                Ok(offset_ms) => {
+                    status.offset_ms = Some(offset_ms);
+                    if status.source == TimeSource::None {
+                        status.source = TimeSource::Ntp;
+                    }
+                    status.reference.get_or_insert_with(|| server.clone());
+                }
+                Err(e) => status.error = Some(format!("NTP query to {} failed: {}", server, e)),
+            }
+        }
+    }
+
+    status
+}
+
+/// Query the platform time daemon
+fn query_system() -> Option<TimeSyncStatus> {
+    if cfg!(windows) {
+        return run("w32tm", &["/query", "/status"]).map(|out| parse_w32tm_status(&out));
+    }
+
+    run("chronyc", &["tracking"])
+        .map(|out| parse_chrony_tracking(&out))
+        .or_else(|| run("timedatectl", &["show"]).map(|out| parse_timedatectl(&out)))
+}
+
+/// Run a command and return its stdout if it succeeded
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Split `Key : Value` / `Key: Value` lines
+fn fields(output: &str) -> impl Iterator<Item = (&str, &str)> {
+    output.lines().filter_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        Some((key.trim(), value.trim()))
+    })
+}
+
+/// Parse `chronyc tracking` output
+fn parse_chrony_tracking(output: &str) -> TimeSyncStatus {
+    let mut status = TimeSyncStatus::unknown();
+    status.source = TimeSource::Chrony;
+
+    for (key, value) in fields(output) {
+        match key {
+            // "0.000012345 seconds fast of NTP time"
+            "System time" => {
+                let mut parts = value.split_whitespace();
+                let seconds = parts.next().and_then(|s| s.parse::<f64>().ok());
+                let direction = parts.nth(1);
+                if let Some(seconds) = seconds {
+                    let sign = if direction == Some("slow") { -1.0 } else { 1.0 };
+                    status.offset_ms = Some(sign * seconds * 1000.0);
+                }
+            }
+            // "A29FC87B (time.cloudflare.com)"
+            "Reference ID" => {
+                status.reference = value
+                    .split_once('(')
+                    .map(|(_, name)| name.trim_end_matches(')').to_string())
+                    .filter(|name| !name.is_empty());
+            }
+            "Leap status" => {
+                status.synchronized = Some(value != "Not synchronised");
+            }
+            _ => {}
+        }
+    }
+
+    status
+}
+
+/// Parse `timedatectl show` output
+fn parse_timedatectl(output: &str) -> TimeSyncStatus {
+    let mut status = TimeSyncStatus::unknown();
+    status.source = TimeSource::Timedatectl;
+
+    for line in output.lines() {
+        if let Some(value) = line.strip_prefix("NTPSynchronized=") {
+            status.synchronized = Some(value.trim() == "yes");
+        }
+    }
+
+    status
+}
+
+/// Parse `w32tm /query /status` output
+fn parse_w32tm_status(output: &str) -> TimeSyncStatus {
+    let mut status = TimeSyncStatus::unknown();
+    status.source = TimeSource::W32Time;
+
+    for (key, value) in fields(output) {
+        match key {
+            // "0(no warning)" ... "3(not synchronized)"
+            "Leap Indicator" => {
+                status.synchronized = Some(!value.starts_with('3'));
+            }
+            // "time.windows.com,0x8" or "Local CMOS Clock"
+            "Source" => {
+                let source = value.split(',').next().unwrap_or(value).trim();
+                if source == "Local CMOS Clock" || source == "Free-running System Clock" {
+                    status.synchronized = Some(false);
+                }
+                status.reference = Some(source.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    status
+}
+
+/// Measure the local clock offset against an NTP server, in milliseconds
+pub fn sntp_offset(server: &str, timeout: Duration) -> std::io::Result<f64> {
+    let addr = if server.contains(':') {
+        server.to_string()
+    } else {
+        format!("{}:123", server)
+    };
+    let addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "server did not resolve")
+    })?;
+
+    let socket = UdpSocket::bind(if addr.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    })?;
+    socket.set_read_timeout(Some(timeout))?;
+
+    // LI=0, VN=4, Mode=3 (client)
+    let mut request = [0u8; 48];
+    request[0] = 0x23;
+    let t1 = ntp_now();
+    request[40..48].copy_from_slice(&to_ntp_timestamp(t1));
+    socket.send_to(&request, addr)?;
+
+    let mut response = [0u8; 48];
+    let (len, _) = socket.recv_from(&mut response)?;
+    let t4 = ntp_now();
+    if len < 48 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "short NTP response",
+        ));
+    }
+
+    let t2 = from_ntp_timestamp(&response[32..40]);
+    let t3 = from_ntp_timestamp(&response[40..48]);
+    Ok(sntp_local_offset(t1, t2, t3, t4) * 1000.0)
+}
+
+/// Local-minus-server offset in seconds from the four SNTP timestamps
+fn sntp_local_offset(t1: f64, t2: f64, t3: f64, t4: f64) -> f64 {
+    -((t2 - t1) + (t3 - t4)) / 2.0
+}
+
+/// Current time in seconds since the NTP epoch
+fn ntp_now() -> f64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    (now.as_secs() + NTP_UNIX_OFFSET) as f64 + now.subsec_nanos() as f64 / 1e9
+}
+
+fn to_ntp_timestamp(seconds: f64) -> [u8; 8] {
+    let secs = seconds.trunc() as u32;
+    let frac = (seconds.fract() * 4_294_967_296.0) as u32;
+    let mut bytes = [0u8; 8];
+    bytes[..4].copy_from_slice(&secs.to_be_bytes());
+    bytes[4..].copy_from_slice(&frac.to_be_bytes());
+    bytes
+}
+
+fn from_ntp_timestamp(bytes: &[u8]) -> f64 {
+    let secs = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let frac = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    secs as f64 + frac as f64 / 4_294_967_296.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chrony_tracking() {
+        let output = "\
+Reference ID    : A29FC87B (time.cloudflare.com)
+Stratum         : 4
+Ref time (UTC)  : Mon Jan 15 10:30:00 2024
+System time     : 0.000250000 seconds slow of NTP time
+Last offset     : -0.000012345 seconds
+Leap status     : Normal
+";
+        let status = parse_chrony_tracking(output);
+        assert_eq!(status.source, TimeSource::Chrony);
+        assert_eq!(status.synchronized, Some(true));
+        assert_eq!(status.reference.as_deref(), Some("time.cloudflare.com"));
+        assert!((status.offset_ms.unwrap() + 0.25).abs() < 1e-9);
+
+        let status = parse_chrony_tracking(
+            "System time     : 1.5 seconds fast of NTP time\nLeap status     : Not synchronised\n",
+        );
+        assert_eq!(status.synchronized, Some(false));
+        assert_eq!(status.offset_ms, Some(1500.0));
+        assert!(status.needs_attention(500));
+    }
+
+    #[test]
+    fn test_parse_timedatectl() {
+        let status = parse_timedatectl("Timezone=UTC\nNTP=yes\nNTPSynchronized=no\n");
+        assert_eq!(status.source, TimeSource::Timedatectl);
+        assert_eq!(status.synchronized, Some(false));
+        assert_eq!(status.offset_ms, None);
+    }
+
+    #[test]
+    fn test_parse_w32tm_status() {
+        let output = "\
+Leap Indicator: 0(no warning)
+Stratum: 4 (secondary reference - syncd by (S)NTP)
+Source: time.windows.com,0x8
+";
+        let status = parse_w32tm_status(output);
+        assert_eq!(status.synchronized, Some(true));
+        assert_eq!(status.reference.as_deref(), Some("time.windows.com"));
+
+        let status =
+            parse_w32tm_status("Leap Indicator: 3(not synchronized)\nSource: Local CMOS Clock\n");
+        assert_eq!(status.synchronized, Some(false));
+    }
+
+    #[test]
+    fn test_sntp_offset_math() {
+        // Server is 2s ahead of us with a 100ms symmetric round trip
+        let offset = sntp_local_offset(100.0, 102.05, 102.05, 100.1);
+        assert!((offset + 2.0).abs() < 1e-9);
+
+        let ts = 3_913_056_000.25;
+        assert!((from_ntp_timestamp(&to_ntp_timestamp(ts)) - ts).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_gps_offset() {
+        let clock = GpsClock::new();
+        assert_eq!(clock.offset_ms(Duration::from_secs(60)), None);
+
+        // A fix stamped two seconds ago means the local clock is ahead
+        clock.record(Utc::now() - chrono::Duration::seconds(2));
+        let offset = clock.offset_ms(Duration::from_secs(60)).unwrap();
+        assert!((offset - 2000.0).abs() < 500.0);
+
+        let mut status = TimeSyncStatus::unknown();
+        status.apply_gps_offset(offset, 500);
+        assert_eq!(status.source, TimeSource::Gps);
+        assert_eq!(status.synchronized, Some(false));
+        assert!(status.needs_attention(500));
+
+        // A daemon-reported offset wins
+        let mut status =
+            parse_chrony_tracking("System time     : 0.001 seconds fast of NTP time\n");
+        status.apply_gps_offset(offset, 500);
+        assert_eq!(status.source, TimeSource::Chrony);
+        assert_eq!(status.offset_ms, Some(1.0));
+    }
+
+    #[test]
+    fn test_drift_threshold() {
+        let mut status = TimeSyncStatus::unknown();
+        assert!(!status.needs_attention(500));
+        status.offset_ms = Some(-750.0);
+        assert!(status.drift_exceeds(500));
+        assert!(!status.drift_exceeds(1000));
+    }
+}
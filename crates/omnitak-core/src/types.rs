@@ -8,6 +8,8 @@ use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
 use std::time::Duration;
 use uuid::Uuid;
 
@@ -64,6 +66,56 @@ impl From<ConnectionId> for Uuid {
     }
 }
 
+/// Identifier following a single message through the system.
+///
+/// Assigned when a message is ingested and carried through aggregation,
+/// filtering, plugin execution and distribution, so every debug log line
+/// about that message can be found by one ID. IDs are unique within a
+/// process and start from a random offset so restarts don't reuse them.
+///
+/// # Examples
+///
+/// ```
+/// use omnitak_core::types::TraceId;
+///
+/// let id = TraceId::new();
+/// assert_eq!(id.to_string().len(), 16);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TraceId(u64);
+
+impl TraceId {
+    /// Allocates the next trace identifier.
+    pub fn new() -> Self {
+        static NEXT: OnceLock<AtomicU64> = OnceLock::new();
+        let next = NEXT.get_or_init(|| AtomicU64::new(Uuid::new_v4().as_u64_pair().0));
+        Self(next.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Returns the raw identifier.
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for TraceId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl From<u64> for TraceId {
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+
 /// Network protocol supported by the TAK server aggregator.
 ///
 /// Defines the available transport protocols for connecting to TAK servers.
@@ -283,6 +335,27 @@ impl Default for ReconnectConfig {
     }
 }
 
+impl ReconnectConfig {
+    /// Returns the delay before reconnection attempt `attempt` (1-based).
+    ///
+    /// Grows by `backoff_multiplier` per attempt and is capped at `max_delay`.
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let secs = self.initial_delay.as_secs_f64() * self.backoff_multiplier.powi(exponent);
+        Duration::from_secs_f64(secs.min(self.max_delay.as_secs_f64()).max(0.0))
+    }
+
+    /// Returns the delays before each of the first `limit` attempts,
+    /// stopping early at `max_attempts`.
+    pub fn schedule(&self, limit: usize) -> Vec<Duration> {
+        let count = match self.max_attempts {
+            Some(max) => limit.min(max as usize),
+            None => limit,
+        };
+        (1..=count as u32).map(|attempt| self.delay_for_attempt(attempt)).collect()
+    }
+}
+
 /// Configuration for a TAK server connection.
 ///
 /// Contains all parameters needed to establish and maintain a connection
@@ -639,6 +712,14 @@ mod tests {
         assert_ne!(id1, id2);
     }
 
+    #[test]
+    fn test_trace_id_sequence() {
+        let first = TraceId::new();
+        let second = TraceId::new();
+        assert_ne!(first, second);
+        assert_eq!(TraceId::from(0xab).to_string(), "00000000000000ab");
+    }
+
     #[test]
     fn test_protocol_security() {
         assert!(!Protocol::Tcp.is_secure());
@@ -726,4 +807,32 @@ mod tests {
         assert!(config.verify_cert);
         assert_eq!(config.server_name, Some("example.com".to_string()));
     }
+
+    #[test]
+    fn test_reconnect_schedule() {
+        let config = ReconnectConfig {
+            auto_reconnect: true,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+            backoff_multiplier: 2.0,
+            max_attempts: Some(4),
+        };
+
+        assert_eq!(
+            config.schedule(10),
+            vec![
+                Duration::from_secs(1),
+                Duration::from_secs(2),
+                Duration::from_secs(4),
+                Duration::from_secs(5),
+            ]
+        );
+
+        let unlimited = ReconnectConfig {
+            max_attempts: None,
+            ..config
+        };
+        assert_eq!(unlimited.schedule(6).len(), 6);
+        assert_eq!(unlimited.delay_for_attempt(u32::MAX), Duration::from_secs(5));
+    }
 }
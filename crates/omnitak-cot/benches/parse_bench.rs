@@ -1,6 +1,7 @@
 use chrono::{TimeZone, Utc};
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 use omnitak_cot::event::{Contact, Detail, Event, Point};
+use omnitak_cot::parse_cot_borrowed;
 use omnitak_cot::parser::parse_cot;
 use omnitak_cot::proto::{decode_event, encode_event};
 
@@ -124,5 +125,31 @@ fn bench_comparison(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_xml_parsing, bench_protobuf, bench_comparison);
+fn bench_owned_vs_borrowed(c: &mut Criterion) {
+    let mut group = c.benchmark_group("owned_vs_borrowed");
+
+    for (name, xml) in [
+        ("simple", SIMPLE_COT),
+        ("with_detail", COT_WITH_DETAIL),
+        ("complex", COMPLEX_COT),
+    ] {
+        group.bench_with_input(BenchmarkId::new("owned", name), &xml, |b, xml| {
+            b.iter(|| parse_cot(black_box(xml)))
+        });
+
+        group.bench_with_input(BenchmarkId::new("borrowed", name), &xml, |b, xml| {
+            b.iter(|| parse_cot_borrowed(black_box(*xml)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_xml_parsing,
+    bench_protobuf,
+    bench_comparison,
+    bench_owned_vs_borrowed
+);
 criterion_main!(benches);
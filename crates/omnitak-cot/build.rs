@@ -12,6 +12,7 @@ fn main() -> Result<()> {
             "proto/status.proto",
             "proto/takv.proto",
             "proto/precisionlocation.proto",
+            "proto/federation.proto",
         ],
         &["proto/"],
     )?;
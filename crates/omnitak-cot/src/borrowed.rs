@@ -0,0 +1,304 @@
+//! Borrowed CoT events for the message hot path
+//!
+//! [`parse_cot_borrowed`] reads the fields routing, filtering and track
+//! correlation look at without allocating: every string in a borrowed
+//! [`Event`] is a slice of the message it was parsed from. As with
+//! [`parse_cot`], attribute values are returned as they appear in the XML.
+//!
+//! Only contact, group and track are read from the detail section; anything
+//! else in it is skipped. Use [`parse_cot`] when the whole event is needed,
+//! e.g. to re-serialize or validate it.
+//!
+//! [`parse_cot`]: crate::parser::parse_cot
+
+use crate::event::{datetime_to_millis, Affiliation, Point, Track};
+use crate::parser::{parse_datetime, parse_f64, ParseError};
+use chrono::{DateTime, Utc};
+use quick_xml::events::{BytesStart, Event as XmlEvent};
+use quick_xml::Reader;
+
+/// CoT event borrowing its strings from the XML it was parsed from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event<'a> {
+    /// CoT version (typically "2.0")
+    pub version: &'a str,
+    /// Unique identifier for this event
+    pub uid: &'a str,
+    /// CoT type (e.g., "a-f-G" for atom-friendly-ground)
+    pub event_type: &'a str,
+    /// Event timestamp
+    pub time: DateTime<Utc>,
+    /// Event start time
+    pub start: DateTime<Utc>,
+    /// Event stale time (when the event becomes invalid)
+    pub stale: DateTime<Utc>,
+    /// How the event was generated (e.g., "h-e" for human-entered)
+    pub how: &'a str,
+    /// Geographic location and accuracy
+    pub point: Point,
+    /// Structured fields of the detail section, if it has one
+    pub detail: Option<Detail<'a>>,
+}
+
+/// Structured fields of the detail section
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Detail<'a> {
+    /// Contact information
+    pub contact: Option<Contact<'a>>,
+    /// Group information
+    pub group: Option<Group<'a>>,
+    /// Track information
+    pub track: Option<Track>,
+}
+
+/// Contact information
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Contact<'a> {
+    /// Optional endpoint for communication
+    pub endpoint: Option<&'a str>,
+    /// Callsign for display
+    pub callsign: &'a str,
+}
+
+/// Group information
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Group<'a> {
+    /// Group name
+    pub name: &'a str,
+    /// Group role
+    pub role: &'a str,
+}
+
+impl<'a> Event<'a> {
+    /// Get the affiliation from the event type
+    pub fn affiliation(&self) -> Option<Affiliation> {
+        Affiliation::from_cot_type(self.event_type)
+    }
+
+    /// Get the callsign from the contact detail, if present
+    pub fn callsign(&self) -> Option<&'a str> {
+        self.detail.as_ref()?.contact.map(|c| c.callsign)
+    }
+
+    /// Get the group name from the group detail, if present
+    pub fn group_name(&self) -> Option<&'a str> {
+        self.detail.as_ref()?.group.map(|g| g.name)
+    }
+
+    /// Convert event time to milliseconds since epoch (TAK Protocol Version 1 format)
+    pub fn time_millis(&self) -> u64 {
+        datetime_to_millis(&self.time)
+    }
+}
+
+/// Parse a CoT message from XML without copying its strings
+pub fn parse_cot_borrowed(xml: &str) -> Result<Event<'_>, ParseError> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut header = [None; 7];
+    let mut point = None;
+    let mut detail = None;
+
+    loop {
+        match reader.read_event()? {
+            XmlEvent::Empty(e) if e.name().as_ref() == b"detail" => {
+                detail = Some(Detail::default());
+            }
+            XmlEvent::Start(e) | XmlEvent::Empty(e) => match e.name().as_ref() {
+                b"event" => {
+                    header = attributes(
+                        xml,
+                        &e,
+                        ["version", "uid", "type", "time", "start", "stale", "how"],
+                    )?;
+                }
+                b"point" => {
+                    let [lat, lon, hae, ce, le] =
+                        attributes(xml, &e, ["lat", "lon", "hae", "ce", "le"])?;
+                    let number =
+                        |value: Option<&str>, default: f64| value.map_or(Ok(default), parse_f64);
+                    point = Some(Point {
+                        lat: parse_f64(required(lat, "lat")?)?,
+                        lon: parse_f64(required(lon, "lon")?)?,
+                        hae: number(hae, 0.0)?,
+                        ce: number(ce, 9999999.0)?,
+                        le: number(le, 9999999.0)?,
+                    });
+                }
+                b"detail" => {
+                    detail = Some(parse_detail(&mut reader, xml)?);
+                }
+                _ => {}
+            },
+            XmlEvent::Eof => break,
+            _ => {}
+        }
+    }
+
+    let [version, uid, event_type, time, start, stale, how] = header;
+    Ok(Event {
+        version: required(version, "version")?,
+        uid: required(uid, "uid")?,
+        event_type: required(event_type, "type")?,
+        time: parse_datetime(required(time, "time")?)?,
+        start: parse_datetime(required(start, "start")?)?,
+        stale: parse_datetime(required(stale, "stale")?)?,
+        how: required(how, "how")?,
+        point: point.ok_or_else(|| ParseError::MissingField("point".into()))?,
+        detail,
+    })
+}
+
+/// Read the children of `<detail>`. Structured elements that fail to parse
+/// are ignored, as are elements with children.
+fn parse_detail<'a>(reader: &mut Reader<&'a [u8]>, xml: &'a str) -> Result<Detail<'a>, ParseError> {
+    let mut detail = Detail::default();
+
+    loop {
+        match reader.read_event()? {
+            XmlEvent::Start(e) => {
+                reader.read_to_end(e.name())?;
+            }
+            XmlEvent::Empty(e) => match e.name().as_ref() {
+                b"contact" => {
+                    let [callsign, endpoint] = attributes(xml, &e, ["callsign", "endpoint"])?;
+                    if let Some(callsign) = callsign {
+                        detail.contact = Some(Contact { endpoint, callsign });
+                    }
+                }
+                b"__group" => {
+                    if let [Some(name), Some(role)] = attributes(xml, &e, ["name", "role"])? {
+                        detail.group = Some(Group { name, role });
+                    }
+                }
+                b"track" => {
+                    if let [Some(speed), Some(course)] = attributes(xml, &e, ["speed", "course"])? {
+                        if let (Ok(speed), Ok(course)) = (parse_f64(speed), parse_f64(course)) {
+                            detail.track = Some(Track { speed, course });
+                        }
+                    }
+                }
+                _ => {}
+            },
+            // </detail>
+            XmlEvent::End(_) | XmlEvent::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(detail)
+}
+
+/// Values of the attributes `names` of an element, in the same order
+fn attributes<'a, const N: usize>(
+    xml: &'a str,
+    element: &BytesStart,
+    names: [&str; N],
+) -> Result<[Option<&'a str>; N], ParseError> {
+    let mut values = [None; N];
+    for attr in element.attributes() {
+        let attr = attr.map_err(|e| ParseError::XmlError(quick_xml::Error::InvalidAttr(e)))?;
+        if let Some(i) = names
+            .iter()
+            .position(|name| name.as_bytes() == attr.key.as_ref())
+        {
+            values[i] = Some(subslice(xml, &attr.value)?);
+        }
+    }
+    Ok(values)
+}
+
+/// The part of `xml` that `part`, read from it without unescaping, spans
+fn subslice<'a>(xml: &'a str, part: &[u8]) -> Result<&'a str, ParseError> {
+    (part.as_ptr() as usize)
+        .checked_sub(xml.as_ptr() as usize)
+        .and_then(|start| xml.get(start..)?.get(..part.len()))
+        .ok_or_else(|| ParseError::InvalidStructure("attribute value outside the message".into()))
+}
+
+fn required<'a>(value: Option<&'a str>, name: &str) -> Result<&'a str, ParseError> {
+    value.ok_or_else(|| ParseError::MissingField(name.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_cot;
+
+    const EXAMPLE_COT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<event version="2.0" uid="ANDROID-12345678" type="a-f-G-U-C" time="2024-01-15T10:30:00Z" start="2024-01-15T10:30:00Z" stale="2024-01-15T10:35:00Z" how="m-g">
+    <point lat="37.7749" lon="-122.4194" hae="100.0" ce="10.0" le="5.0"/>
+    <detail>
+        <contact callsign="Alpha-1" endpoint="192.168.1.10:4242:tcp"/>
+        <__group name="Cyan" role="Team Member"/>
+        <track speed="12.5" course="270.0"/>
+        <link uid="PARENT-1" relation="p-p"><extra/></link>
+        <remarks>Test &amp; CoT message</remarks>
+        <status battery="85"/>
+    </detail>
+</event>"#;
+
+    #[test]
+    fn test_matches_owned_parser() {
+        let event = parse_cot_borrowed(EXAMPLE_COT).unwrap();
+        let owned = parse_cot(EXAMPLE_COT).unwrap();
+
+        assert_eq!(event.version, owned.version);
+        assert_eq!(event.uid, owned.uid);
+        assert_eq!(event.event_type, owned.event_type);
+        assert_eq!(event.how, owned.how);
+        assert_eq!(
+            (event.time, event.start, event.stale),
+            (owned.time, owned.start, owned.stale)
+        );
+        assert_eq!(event.point, owned.point);
+        assert_eq!(event.callsign(), owned.callsign());
+        assert_eq!(event.group_name(), owned.group_name());
+        assert_eq!(event.affiliation(), Some(Affiliation::Friend));
+        assert_eq!(event.time_millis(), owned.time_millis());
+
+        let detail = event.detail.unwrap();
+        assert_eq!(
+            detail.contact.unwrap().endpoint,
+            Some("192.168.1.10:4242:tcp")
+        );
+        assert_eq!(detail.group.unwrap().role, "Team Member");
+        assert_eq!(
+            detail.track,
+            Some(Track {
+                speed: 12.5,
+                course: 270.0
+            })
+        );
+    }
+
+    #[test]
+    fn test_strings_borrowed_from_input() {
+        let event = parse_cot_borrowed(EXAMPLE_COT).unwrap();
+        let input = EXAMPLE_COT.as_bytes().as_ptr_range();
+        for value in [event.uid, event.event_type, event.callsign().unwrap()] {
+            assert!(input.contains(&value.as_ptr()));
+        }
+    }
+
+    #[test]
+    fn test_defaults_and_errors() {
+        let event = parse_cot_borrowed(
+            r#"<event version="2.0" uid="U-1" type="a-f-G" time="2024-01-15T10:30:00Z" start="2024-01-15T10:30:00Z" stale="2024-01-15T10:35:00Z" how="h-e"><point lat="1.5" lon="2.5"/><detail/></event>"#,
+        )
+        .unwrap();
+        assert_eq!(event.point.hae, 0.0);
+        assert_eq!(event.point.ce, 9999999.0);
+        assert_eq!(event.detail, Some(Detail::default()));
+
+        assert!(matches!(
+            parse_cot_borrowed(r#"<event version="2.0" type="a-f-G"/>"#),
+            Err(ParseError::MissingField(field)) if field == "uid"
+        ));
+        assert!(matches!(
+            parse_cot_borrowed(&EXAMPLE_COT.replace("37.7749", "north")),
+            Err(ParseError::InvalidNumber(_))
+        ));
+    }
+}
@@ -326,7 +326,7 @@ impl Event {
 }
 
 /// Convert DateTime to milliseconds since epoch
-fn datetime_to_millis(dt: &DateTime<Utc>) -> u64 {
+pub(crate) fn datetime_to_millis(dt: &DateTime<Utc>) -> u64 {
     (dt.timestamp() * 1000 + dt.timestamp_subsec_millis() as i64) as u64
 }
 
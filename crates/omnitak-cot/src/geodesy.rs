@@ -0,0 +1,243 @@
+//! Spherical geodesy and dead reckoning
+//!
+//! Great-circle math on a spherical Earth model, which is accurate to well
+//! under 0.5% — plenty for map display and for deciding whether a position
+//! update carries new information. All angles are in degrees and distances
+//! in meters.
+
+use crate::event::Event;
+use chrono::{DateTime, Utc};
+
+/// Mean Earth radius in meters
+pub const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+/// Great-circle (haversine) distance between two points
+pub fn distance_m(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let dlat = (lat2 - lat1).to_radians();
+    let dlon = (lon2 - lon1).to_radians();
+    let a = (dlat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * a.sqrt().atan2((1.0 - a).sqrt())
+}
+
+/// Initial bearing from the first point to the second (0-360, clockwise from north)
+pub fn bearing_deg(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let dlon = (lon2 - lon1).to_radians();
+    let y = dlon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+    normalize_bearing(y.atan2(x).to_degrees())
+}
+
+/// Point reached by travelling `distance_m` along a great circle from a start
+/// point on the given initial bearing
+pub fn destination(lat: f64, lon: f64, bearing_deg: f64, distance_m: f64) -> (f64, f64) {
+    let delta = distance_m / EARTH_RADIUS_M;
+    let theta = bearing_deg.to_radians();
+    let phi1 = lat.to_radians();
+    let lambda1 = lon.to_radians();
+
+    let phi2 = (phi1.sin() * delta.cos() + phi1.cos() * delta.sin() * theta.cos()).asin();
+    let lambda2 = lambda1
+        + (theta.sin() * delta.sin() * phi1.cos()).atan2(delta.cos() - phi1.sin() * phi2.sin());
+
+    (phi2.to_degrees(), normalize_lon(lambda2.to_degrees()))
+}
+
+/// Point at `fraction` (0.0-1.0) of the way along the great circle between
+/// two points
+pub fn interpolate(lat1: f64, lon1: f64, lat2: f64, lon2: f64, fraction: f64) -> (f64, f64) {
+    let delta = distance_m(lat1, lon1, lat2, lon2) / EARTH_RADIUS_M;
+    if delta < 1e-12 {
+        return (lat1, lon1);
+    }
+
+    let (phi1, lambda1) = (lat1.to_radians(), lon1.to_radians());
+    let (phi2, lambda2) = (lat2.to_radians(), lon2.to_radians());
+
+    let a = ((1.0 - fraction) * delta).sin() / delta.sin();
+    let b = (fraction * delta).sin() / delta.sin();
+
+    let x = a * phi1.cos() * lambda1.cos() + b * phi2.cos() * lambda2.cos();
+    let y = a * phi1.cos() * lambda1.sin() + b * phi2.cos() * lambda2.sin();
+    let z = a * phi1.sin() + b * phi2.sin();
+
+    let phi = z.atan2((x * x + y * y).sqrt());
+    let lambda = y.atan2(x);
+    (phi.to_degrees(), normalize_lon(lambda.to_degrees()))
+}
+
+/// Project a position forward along its course at constant speed
+pub fn dead_reckon(
+    lat: f64,
+    lon: f64,
+    course_deg: f64,
+    speed_mps: f64,
+    elapsed_secs: f64,
+) -> (f64, f64) {
+    if speed_mps <= 0.0 || elapsed_secs <= 0.0 {
+        return (lat, lon);
+    }
+    destination(lat, lon, course_deg, speed_mps * elapsed_secs)
+}
+
+/// Normalize a bearing to 0-360
+pub fn normalize_bearing(bearing: f64) -> f64 {
+    bearing.rem_euclid(360.0)
+}
+
+/// Normalize a longitude to -180..180
+pub fn normalize_lon(lon: f64) -> f64 {
+    (lon + 540.0).rem_euclid(360.0) - 180.0
+}
+
+/// Last known position and motion of a track, for predicting where it is now
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackState {
+    /// Latitude at `time`
+    pub lat: f64,
+    /// Longitude at `time`
+    pub lon: f64,
+    /// Course over ground in degrees
+    pub course: f64,
+    /// Speed over ground in meters per second
+    pub speed: f64,
+    /// When the position was valid
+    pub time: DateTime<Utc>,
+}
+
+impl TrackState {
+    /// Build from an event carrying a `<track>` element
+    pub fn from_event(event: &Event) -> Option<Self> {
+        let track = event.detail.as_ref()?.track.as_ref()?;
+        if !track.speed.is_finite() || !track.course.is_finite() {
+            return None;
+        }
+        Some(Self {
+            lat: event.point.lat,
+            lon: event.point.lon,
+            course: track.course,
+            speed: track.speed,
+            time: event.time,
+        })
+    }
+
+    /// Predicted position at a given time
+    pub fn predict(&self, at: DateTime<Utc>) -> (f64, f64) {
+        let elapsed = (at - self.time).num_milliseconds() as f64 / 1000.0;
+        dead_reckon(self.lat, self.lon, self.course, self.speed, elapsed)
+    }
+
+    /// Distance between the predicted position and the one reported by `event`
+    pub fn prediction_error_m(&self, event: &Event) -> f64 {
+        let (lat, lon) = self.predict(event.time);
+        distance_m(lat, lon, event.point.lat, event.point.lon)
+    }
+
+    /// Whether `event` is within `tolerance_m` of where this track was
+    /// predicted to be, i.e. carries no new position information
+    pub fn is_predicted(&self, event: &Event, tolerance_m: f64) -> bool {
+        self.prediction_error_m(event) <= tolerance_m
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{Detail, Point, Track};
+    use chrono::Duration;
+
+    fn close(a: f64, b: f64, eps: f64) -> bool {
+        (a - b).abs() < eps
+    }
+
+    #[test]
+    fn test_distance_and_bearing() {
+        // One degree of latitude is ~111.2 km
+        assert!(close(distance_m(0.0, 0.0, 1.0, 0.0), 111_195.0, 1.0));
+        assert!(close(bearing_deg(0.0, 0.0, 1.0, 0.0), 0.0, 1e-9));
+        assert!(close(bearing_deg(0.0, 0.0, 0.0, 1.0), 90.0, 1e-9));
+        assert!(close(bearing_deg(0.0, 0.0, 0.0, -1.0), 270.0, 1e-9));
+    }
+
+    #[test]
+    fn test_destination_round_trip() {
+        let (lat, lon) = destination(37.7749, -122.4194, 45.0, 10_000.0);
+        assert!(close(
+            distance_m(37.7749, -122.4194, lat, lon),
+            10_000.0,
+            0.01
+        ));
+        assert!(close(bearing_deg(37.7749, -122.4194, lat, lon), 45.0, 0.1));
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let (lat, lon) = interpolate(0.0, 0.0, 0.0, 10.0, 0.5);
+        assert!(close(lat, 0.0, 1e-9));
+        assert!(close(lon, 5.0, 1e-9));
+
+        assert_eq!(interpolate(10.0, 20.0, 10.0, 20.0, 0.5), (10.0, 20.0));
+        let (lat, lon) = interpolate(10.0, 20.0, 30.0, 40.0, 1.0);
+        assert!(close(lat, 30.0, 1e-9) && close(lon, 40.0, 1e-9));
+
+        // Crossing the antimeridian takes the short way round
+        let (_, lon) = interpolate(0.0, 179.0, 0.0, -179.0, 0.5);
+        assert!(close(lon.abs(), 180.0, 1e-9));
+    }
+
+    #[test]
+    fn test_dead_reckon() {
+        // 10 m/s due east for 100 s
+        let (lat, lon) = dead_reckon(0.0, 0.0, 90.0, 10.0, 100.0);
+        assert!(close(lat, 0.0, 1e-9));
+        assert!(close(distance_m(0.0, 0.0, lat, lon), 1000.0, 0.01));
+
+        assert_eq!(dead_reckon(1.0, 2.0, 90.0, 0.0, 100.0), (1.0, 2.0));
+        assert_eq!(dead_reckon(1.0, 2.0, 90.0, 10.0, -5.0), (1.0, 2.0));
+    }
+
+    #[test]
+    fn test_track_state_prediction() {
+        let t0 = Utc::now();
+        let mut event = Event {
+            version: "2.0".to_string(),
+            uid: "TRK-1".to_string(),
+            event_type: "a-f-G".to_string(),
+            time: t0,
+            start: t0,
+            stale: t0 + Duration::minutes(5),
+            how: "m-g".to_string(),
+            point: Point {
+                lat: 0.0,
+                lon: 0.0,
+                hae: 0.0,
+                ce: 10.0,
+                le: 10.0,
+            },
+            detail: Some(Detail {
+                track: Some(Track {
+                    speed: 10.0,
+                    course: 0.0,
+                }),
+                ..Default::default()
+            }),
+        };
+
+        let state = TrackState::from_event(&event).unwrap();
+
+        // 60 s later the track reports exactly where dead reckoning puts it
+        let (lat, lon) = destination(0.0, 0.0, 0.0, 600.0);
+        event.time = t0 + Duration::seconds(60);
+        event.point.lat = lat;
+        event.point.lon = lon;
+        assert!(state.is_predicted(&event, 5.0));
+
+        // ...or somewhere else entirely
+        event.point.lon = 0.01;
+        assert!(!state.is_predicted(&event, 5.0));
+
+        event.detail = None;
+        assert!(TrackState::from_event(&event).is_none());
+    }
+}
@@ -5,10 +5,12 @@
 //!
 //! # Features
 //!
-//! - Zero-copy XML parsing using quick-xml
+//! - Zero-copy XML parsing using quick-xml, with a borrowed event type for hot paths
 //! - Protobuf support for binary serialization
 //! - MIL-STD-2525 affiliation parsing
 //! - Comprehensive validation
+//! - Great-circle geodesy and dead reckoning for track prediction
+//! - MGRS and UTM coordinate conversion
 //! - High performance (<1μs per message for typical payloads)
 //!
 //! # Example
@@ -29,7 +31,10 @@
 //! assert_eq!(event.point.lat, 37.7749);
 //! ```
 
+pub mod borrowed;
 pub mod event;
+pub mod geodesy;
+pub mod mgrs;
 pub mod parser;
 pub mod proto;
 pub mod serializer;
@@ -38,7 +43,8 @@ pub mod validate;
 pub use event::{
     Affiliation, Contact, Detail, Event, Group, Point, PrecisionLocation, Status, Takv, Track,
 };
+pub use borrowed::parse_cot_borrowed;
 pub use parser::{parse_cot, parse_cot_bytes, ParseError};
 pub use proto::{decode_event, encode_event, ProtoError};
-pub use serializer::serialize_event;
+pub use serializer::{escape_xml, serialize_event, unescape_xml};
 pub use validate::{validate_event, validate_event_strict, validate_point, ValidationError};
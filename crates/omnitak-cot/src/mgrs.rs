@@ -0,0 +1,521 @@
+//! MGRS and UTM coordinates
+//!
+//! Conversion between WGS84 latitude/longitude, UTM (e.g.
+//! `11S 512345 4067890`) and the Military Grid Reference System (e.g.
+//! `11S MS 12345 67890`). Covers the UTM latitude bands (80°S to 84°N)
+//! including the Norway and Svalbard zone exceptions; the polar UPS regions
+//! are not supported.
+//!
+//! [`GridSquare`] is an MGRS square at any precision, for areas given as
+//! `18SUJ` (100 km) or `18SUJ2306` (1 km).
+
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// MGRS and UTM conversion errors
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MgrsError {
+    #[error("Latitude out of MGRS range (80°S to 84°N)")]
+    OutOfRange,
+
+    #[error("Malformed MGRS string: {0}")]
+    Malformed(&'static str),
+
+    #[error("Malformed UTM string: {0}")]
+    MalformedUtm(&'static str),
+}
+
+// WGS84 ellipsoid
+const A: f64 = 6_378_137.0;
+const F: f64 = 1.0 / 298.257_223_563;
+const K0: f64 = 0.9996;
+const FALSE_EASTING: f64 = 500_000.0;
+const FALSE_NORTHING_SOUTH: f64 = 10_000_000.0;
+
+/// Latitude band letters, 8° each from 80°S (X is 12°)
+const BANDS: &[u8] = b"CDEFGHJKLMNPQRSTUVWX";
+/// 100 km column letters, repeating every three zones
+const COLUMN_SETS: [&[u8]; 3] = [b"ABCDEFGH", b"JKLMNPQR", b"STUVWXYZ"];
+/// 100 km row letters, offset by five rows in even zones
+const ROW_LETTERS: &[u8] = b"ABCDEFGHJKLMNPQRSTUV";
+
+fn e2() -> f64 {
+    F * (2.0 - F)
+}
+
+/// UTM zone for a position, honouring the Norway and Svalbard exceptions
+fn utm_zone(lat: f64, lon: f64) -> u8 {
+    let lon = if lon >= 180.0 { lon - 360.0 } else { lon };
+    if (56.0..64.0).contains(&lat) && (3.0..12.0).contains(&lon) {
+        return 32;
+    }
+    if (72.0..84.0).contains(&lat) {
+        match lon {
+            l if (0.0..9.0).contains(&l) => return 31,
+            l if (9.0..21.0).contains(&l) => return 33,
+            l if (21.0..33.0).contains(&l) => return 35,
+            l if (33.0..42.0).contains(&l) => return 37,
+            _ => {}
+        }
+    }
+    (((lon + 180.0) / 6.0).floor() as i32).clamp(0, 59) as u8 + 1
+}
+
+fn central_meridian(zone: u8) -> f64 {
+    zone as f64 * 6.0 - 183.0
+}
+
+/// Latitude/longitude to UTM easting and northing (northing is negative in
+/// the southern hemisphere, before the false northing is applied)
+fn utm_forward(lat: f64, lon: f64, zone: u8) -> (f64, f64) {
+    let e2 = e2();
+    let ep2 = e2 / (1.0 - e2);
+    let phi = lat.to_radians();
+    let lambda = (lon - central_meridian(zone)).to_radians();
+
+    let n = A / (1.0 - e2 * phi.sin().powi(2)).sqrt();
+    let t = phi.tan().powi(2);
+    let c = ep2 * phi.cos().powi(2);
+    let a = phi.cos() * lambda;
+    let m = meridian_arc(phi);
+
+    let easting = K0
+        * n
+        * (a + (1.0 - t + c) * a.powi(3) / 6.0
+            + (5.0 - 18.0 * t + t * t + 72.0 * c - 58.0 * ep2) * a.powi(5) / 120.0)
+        + FALSE_EASTING;
+    let northing = K0
+        * (m + n
+            * phi.tan()
+            * (a * a / 2.0
+                + (5.0 - t + 9.0 * c + 4.0 * c * c) * a.powi(4) / 24.0
+                + (61.0 - 58.0 * t + t * t + 600.0 * c - 330.0 * ep2) * a.powi(6) / 720.0));
+    (easting, northing)
+}
+
+/// Distance along the meridian from the equator to latitude `phi` (radians)
+fn meridian_arc(phi: f64) -> f64 {
+    let e2 = e2();
+    let e4 = e2 * e2;
+    let e6 = e4 * e2;
+    A * ((1.0 - e2 / 4.0 - 3.0 * e4 / 64.0 - 5.0 * e6 / 256.0) * phi
+        - (3.0 * e2 / 8.0 + 3.0 * e4 / 32.0 + 45.0 * e6 / 1024.0) * (2.0 * phi).sin()
+        + (15.0 * e4 / 256.0 + 45.0 * e6 / 1024.0) * (4.0 * phi).sin()
+        - (35.0 * e6 / 3072.0) * (6.0 * phi).sin())
+}
+
+/// UTM easting and signed northing to latitude/longitude
+fn utm_inverse(easting: f64, northing: f64, zone: u8) -> (f64, f64) {
+    let e2 = e2();
+    let ep2 = e2 / (1.0 - e2);
+    let e1 = (1.0 - (1.0 - e2).sqrt()) / (1.0 + (1.0 - e2).sqrt());
+
+    let x = easting - FALSE_EASTING;
+    let m = northing / K0;
+    let mu = m / (A * (1.0 - e2 / 4.0 - 3.0 * e2 * e2 / 64.0 - 5.0 * e2.powi(3) / 256.0));
+    let phi1 = mu
+        + (3.0 * e1 / 2.0 - 27.0 * e1.powi(3) / 32.0) * (2.0 * mu).sin()
+        + (21.0 * e1 * e1 / 16.0 - 55.0 * e1.powi(4) / 32.0) * (4.0 * mu).sin()
+        + (151.0 * e1.powi(3) / 96.0) * (6.0 * mu).sin()
+        + (1097.0 * e1.powi(4) / 512.0) * (8.0 * mu).sin();
+
+    let n1 = A / (1.0 - e2 * phi1.sin().powi(2)).sqrt();
+    let t1 = phi1.tan().powi(2);
+    let c1 = ep2 * phi1.cos().powi(2);
+    let r1 = A * (1.0 - e2) / (1.0 - e2 * phi1.sin().powi(2)).powf(1.5);
+    let d = x / (n1 * K0);
+
+    let lat = phi1
+        - (n1 * phi1.tan() / r1)
+            * (d * d / 2.0
+                - (5.0 + 3.0 * t1 + 10.0 * c1 - 4.0 * c1 * c1 - 9.0 * ep2) * d.powi(4) / 24.0
+                + (61.0 + 90.0 * t1 + 298.0 * c1 + 45.0 * t1 * t1 - 252.0 * ep2 - 3.0 * c1 * c1)
+                    * d.powi(6)
+                    / 720.0);
+    let lon = (d - (1.0 + 2.0 * t1 + c1) * d.powi(3) / 6.0
+        + (5.0 - 2.0 * c1 + 28.0 * t1 - 3.0 * c1 * c1 + 8.0 * ep2 + 24.0 * t1 * t1) * d.powi(5)
+            / 120.0)
+        / phi1.cos();
+
+    (lat.to_degrees(), central_meridian(zone) + lon.to_degrees())
+}
+
+fn band_letter(lat: f64) -> u8 {
+    let index = (((lat + 80.0) / 8.0).floor() as usize).min(BANDS.len() - 1);
+    BANDS[index]
+}
+
+/// Convert latitude/longitude to an MGRS string with `digits` digits per
+/// coordinate (1 = 10 km … 5 = 1 m), e.g. `11SMS1234567890`
+pub fn to_mgrs(lat: f64, lon: f64, digits: usize) -> Result<String, MgrsError> {
+    if !(-80.0..=84.0).contains(&lat) {
+        return Err(MgrsError::OutOfRange);
+    }
+    let digits = digits.clamp(1, 5);
+    let zone = utm_zone(lat, lon);
+    let (easting, northing) = utm_forward(lat, lon, zone);
+    let northing = if northing < 0.0 {
+        northing + FALSE_NORTHING_SOUTH
+    } else {
+        northing
+    };
+
+    let set = (zone as usize - 1) % 3;
+    let column = COLUMN_SETS[set][((easting / 100_000.0).floor() as usize).clamp(1, 8) - 1];
+    let row_offset = if zone.is_multiple_of(2) { 5 } else { 0 };
+    let row = ROW_LETTERS[((northing / 100_000.0).floor() as usize + row_offset) % 20];
+
+    let scale = 10f64.powi(5 - digits as i32);
+    let e = ((easting % 100_000.0) / scale).floor() as u64;
+    let n = ((northing % 100_000.0) / scale).floor() as u64;
+
+    Ok(format!(
+        "{:02}{}{}{}{:0width$}{:0width$}",
+        zone,
+        band_letter(lat) as char,
+        column as char,
+        row as char,
+        e,
+        n,
+        width = digits
+    ))
+}
+
+/// Parse an MGRS string (spaces optional) to the latitude/longitude of the
+/// centre of the square it names
+pub fn from_mgrs(mgrs: &str) -> Result<(f64, f64), MgrsError> {
+    let s: String = mgrs
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_uppercase();
+    let bytes = s.as_bytes();
+
+    let zone_len = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+    if zone_len == 0 || zone_len > 2 {
+        return Err(MgrsError::Malformed("expected a 1-2 digit zone"));
+    }
+    let zone: u8 = s[..zone_len].parse().unwrap_or(0);
+    if !(1..=60).contains(&zone) {
+        return Err(MgrsError::Malformed("zone must be 1-60"));
+    }
+
+    let letters = &bytes[zone_len..];
+    if letters.len() < 3 {
+        return Err(MgrsError::Malformed(
+            "expected band and 100 km square letters",
+        ));
+    }
+    let band = BANDS
+        .iter()
+        .position(|&b| b == letters[0])
+        .ok_or(MgrsError::Malformed("invalid latitude band"))?;
+    let set = (zone as usize - 1) % 3;
+    let column = COLUMN_SETS[set]
+        .iter()
+        .position(|&b| b == letters[1])
+        .ok_or(MgrsError::Malformed("invalid 100 km column letter"))?;
+    let row_offset = if zone.is_multiple_of(2) { 5 } else { 0 };
+    let row = ROW_LETTERS
+        .iter()
+        .position(|&b| b == letters[2])
+        .ok_or(MgrsError::Malformed("invalid 100 km row letter"))?;
+
+    let numbers = &s[zone_len + 3..];
+    if !numbers.len().is_multiple_of(2) || numbers.len() > 10 || !numbers.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(MgrsError::Malformed("expected an even number of digits"));
+    }
+    let digits = numbers.len() / 2;
+    let scale = 10f64.powi(5 - digits as i32);
+    let parse = |n: &str| n.parse::<f64>().unwrap_or(0.0) * scale;
+    // Centre of the named square
+    let half = scale / 2.0;
+    let (e, n) = if digits == 0 {
+        (50_000.0, 50_000.0)
+    } else {
+        (
+            parse(&numbers[..digits]) + half,
+            parse(&numbers[digits..]) + half,
+        )
+    };
+
+    let easting = (column + 1) as f64 * 100_000.0 + e;
+
+    // The row letter only fixes northing modulo 2000 km; pick the cycle that
+    // lands inside the latitude band
+    let band_south = -80.0 + band as f64 * 8.0;
+    let band_lat = band_south + 4.0;
+    let (_, band_northing) = utm_forward(band_lat, central_meridian(zone), zone);
+    let row_northing = ((row + 20 - row_offset) % 20) as f64 * 100_000.0 + n;
+    let band_northing = if band_northing < 0.0 {
+        band_northing + FALSE_NORTHING_SOUTH
+    } else {
+        band_northing
+    };
+    let cycle = ((band_northing - row_northing) / 2_000_000.0).round();
+    let mut northing = row_northing + cycle * 2_000_000.0;
+    if band_south < 0.0 {
+        northing -= FALSE_NORTHING_SOUTH;
+    }
+
+    Ok(utm_inverse(easting, northing, zone))
+}
+
+/// A UTM coordinate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Utm {
+    pub zone: u8,
+    /// Latitude band letter, `C` to `X`; `N` and later are north of the
+    /// equator
+    pub band: char,
+    pub easting: f64,
+    /// Northing, including the 10 000 km false northing south of the equator
+    pub northing: f64,
+}
+
+impl Utm {
+    /// UTM coordinate of a latitude/longitude
+    pub fn from_lat_lon(lat: f64, lon: f64) -> Result<Self, MgrsError> {
+        if !(-80.0..=84.0).contains(&lat) {
+            return Err(MgrsError::OutOfRange);
+        }
+        let zone = utm_zone(lat, lon);
+        let (easting, northing) = utm_forward(lat, lon, zone);
+        Ok(Self {
+            zone,
+            band: band_letter(lat) as char,
+            easting,
+            northing: if northing < 0.0 {
+                northing + FALSE_NORTHING_SOUTH
+            } else {
+                northing
+            },
+        })
+    }
+
+    /// Whether the coordinate is north of the equator
+    pub fn is_north(&self) -> bool {
+        self.band >= 'N'
+    }
+
+    /// Latitude/longitude of the coordinate
+    pub fn to_lat_lon(&self) -> (f64, f64) {
+        let northing = if self.is_north() {
+            self.northing
+        } else {
+            self.northing - FALSE_NORTHING_SOUTH
+        };
+        utm_inverse(self.easting, northing, self.zone)
+    }
+}
+
+/// Formats as `11S 512345 4067890`, truncated to the metre like MGRS
+impl fmt::Display for Utm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}{} {:.0} {:.0}",
+            self.zone,
+            self.band,
+            self.easting.floor(),
+            self.northing.floor()
+        )
+    }
+}
+
+/// Parses `11S 512345 4067890`; the band may be separated from the zone
+impl FromStr for Utm {
+    type Err = MgrsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_uppercase();
+        let zone_len = s.bytes().take_while(|b| b.is_ascii_digit()).count();
+        if zone_len == 0 || zone_len > 2 {
+            return Err(MgrsError::MalformedUtm("expected a 1-2 digit zone"));
+        }
+        let zone: u8 = s[..zone_len].parse().unwrap_or(0);
+        if !(1..=60).contains(&zone) {
+            return Err(MgrsError::MalformedUtm("zone must be 1-60"));
+        }
+
+        let rest = s[zone_len..].trim_start();
+        let band = rest
+            .chars()
+            .next()
+            .filter(|c| c.is_ascii() && BANDS.contains(&(*c as u8)))
+            .ok_or(MgrsError::MalformedUtm("invalid latitude band"))?;
+        let numbers: Vec<f64> = rest[1..]
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|_| MgrsError::MalformedUtm("expected easting and northing"))?;
+        let &[easting, northing] = numbers.as_slice() else {
+            return Err(MgrsError::MalformedUtm("expected easting and northing"));
+        };
+        if !(100_000.0..1_000_000.0).contains(&easting)
+            || !(0.0..=FALSE_NORTHING_SOUTH).contains(&northing)
+        {
+            return Err(MgrsError::MalformedUtm("easting or northing out of range"));
+        }
+
+        Ok(Self {
+            zone,
+            band,
+            easting,
+            northing,
+        })
+    }
+}
+
+/// An MGRS grid square at any precision, e.g. `18SUJ` (100 km) or
+/// `18SUJ2306` (1 km)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridSquare {
+    /// Zone, band and 100 km square letters, e.g. `18SUJ`
+    square: String,
+    /// Easting digits followed by as many northing digits
+    digits: String,
+}
+
+impl GridSquare {
+    /// Whether a position lies inside the square
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        let precision = self.digits.len() / 2;
+        let Ok(mgrs) = to_mgrs(lat, lon, precision.max(1)) else {
+            return false;
+        };
+        let (square, numbers) = mgrs.split_at(5);
+        let (easting, northing) = numbers.split_at(numbers.len() / 2);
+        square == self.square
+            && easting[..precision] == self.digits[..precision]
+            && northing[..precision] == self.digits[precision..]
+    }
+}
+
+impl fmt::Display for GridSquare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.square, self.digits)
+    }
+}
+
+impl FromStr for GridSquare {
+    type Err = MgrsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Checks the zone, letters and digits
+        from_mgrs(s)?;
+
+        let s: String = s
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_ascii_uppercase();
+        let zone_len = s.bytes().take_while(|b| b.is_ascii_digit()).count();
+        Ok(Self {
+            square: format!("{:0>2}{}", &s[..zone_len], &s[zone_len..zone_len + 3]),
+            digits: s[zone_len + 3..].to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geodesy::distance_m;
+
+    #[test]
+    fn test_to_mgrs_known_points() {
+        // GeographicLib GeoConvert reference point
+        assert_eq!(to_mgrs(33.3, 44.4, 2).unwrap(), "38SMB4484");
+        assert_eq!(to_mgrs(85.0, 0.0, 5), Err(MgrsError::OutOfRange));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for &(lat, lon) in &[
+            (37.8199, -122.4783),
+            (-33.8568, 151.2153),
+            (0.5, 0.5),
+            (60.0, 5.0),
+            (78.2, 15.6),
+            (-79.5, -70.0),
+            (51.4778, -0.0015),
+        ] {
+            let mgrs = to_mgrs(lat, lon, 5).unwrap();
+            let (lat2, lon2) = from_mgrs(&mgrs).unwrap();
+            assert!(
+                distance_m(lat, lon, lat2, lon2) < 2.0,
+                "{} -> {} -> {}, {}",
+                lat,
+                mgrs,
+                lat2,
+                lon2
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_mgrs_formats() {
+        let exact = to_mgrs(37.8199, -122.4783, 5).unwrap();
+        let spaced = format!(
+            "{} {} {} {}",
+            &exact[..3],
+            &exact[3..5],
+            &exact[5..10],
+            &exact[10..]
+        );
+        let (lat, lon) = from_mgrs(&spaced).unwrap();
+        assert!(distance_m(lat, lon, 37.8199, -122.4783) < 2.0);
+
+        // Lower precision returns the centre of the square
+        let (lat, lon) = from_mgrs("38smb4484").unwrap();
+        assert!(distance_m(lat, lon, 33.3, 44.4) < 1_000.0);
+
+        assert!(from_mgrs("10S").is_err());
+        assert!(from_mgrs("61SEG1234").is_err());
+        assert!(from_mgrs("10SEG123").is_err());
+        assert!(from_mgrs("10SIG1234").is_err());
+    }
+
+    #[test]
+    fn test_utm() {
+        // GeographicLib GeoConvert reference point: 38n 444140 3684706
+        let utm = Utm::from_lat_lon(33.3, 44.4).unwrap();
+        assert_eq!((utm.zone, utm.band), (38, 'S'));
+        assert_eq!(utm.to_string(), "38S 444140 3684706");
+
+        for &(lat, lon) in &[(37.8199, -122.4783), (-33.8568, 151.2153), (60.0, 5.0)] {
+            let utm = Utm::from_lat_lon(lat, lon).unwrap();
+            let parsed: Utm = utm.to_string().parse().unwrap();
+            let (lat2, lon2) = parsed.to_lat_lon();
+            assert!(distance_m(lat, lon, lat2, lon2) < 2.0, "{} -> {}", lat, utm);
+        }
+
+        assert_eq!("38 s 444140 3684706".parse::<Utm>().unwrap().band, 'S');
+        assert!("38S 444140".parse::<Utm>().is_err());
+        assert!("38I 444140 3684706".parse::<Utm>().is_err());
+        assert!("61S 444140 3684706".parse::<Utm>().is_err());
+        assert!("38S 44414 3684706".parse::<Utm>().is_err());
+    }
+
+    #[test]
+    fn test_grid_square() {
+        // 38SMB 44140 84706
+        for square in ["38SMB", "38SMB48", "38 S MB 441 847", "38smb4414084706"] {
+            let square: GridSquare = square.parse().unwrap();
+            assert!(square.contains(33.3, 44.4), "{}", square);
+        }
+        for square in ["38TMB", "38SMC", "38SMB49", "38SMB4485"] {
+            let square: GridSquare = square.parse().unwrap();
+            assert!(!square.contains(33.3, 44.4), "{}", square);
+        }
+
+        assert_eq!(
+            "4QFJ12".parse::<GridSquare>().unwrap().to_string(),
+            "04QFJ12"
+        );
+        assert!("38SMB123".parse::<GridSquare>().is_err());
+    }
+}
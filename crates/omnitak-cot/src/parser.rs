@@ -5,6 +5,7 @@ use crate::proto::pb;
 use chrono::{DateTime, Utc};
 use prost::Message;
 use quick_xml::events::Event as XmlEvent;
+use quick_xml::name::QName;
 use quick_xml::Reader;
 use std::io::{Cursor, Read};
 use thiserror::Error;
@@ -286,6 +287,9 @@ pub fn parse_cot_bytes(xml: &[u8]) -> Result<Event, ParseError> {
 
     loop {
         match reader.read_event_into(&mut buf) {
+            Ok(XmlEvent::Empty(e)) if e.name().as_ref() == b"detail" => {
+                detail = Some(Detail::default());
+            }
             Ok(XmlEvent::Start(e)) | Ok(XmlEvent::Empty(e)) => {
                 match e.name().as_ref() {
                     b"event" => {
@@ -377,8 +381,7 @@ pub fn parse_cot_bytes(xml: &[u8]) -> Result<Event, ParseError> {
                         });
                     }
                     b"detail" => {
-                        // Parse detail section (simplified as HashMap for now)
-                        detail = Some(parse_detail(&mut reader, &mut buf)?);
+                        detail = Some(parse_detail(&mut reader, xml, &mut buf)?);
                     }
                     _ => {}
                 }
@@ -404,85 +407,74 @@ pub fn parse_cot_bytes(xml: &[u8]) -> Result<Event, ParseError> {
     })
 }
 
-fn parse_datetime(s: &str) -> Result<DateTime<Utc>, ParseError> {
+pub(crate) fn parse_datetime(s: &str) -> Result<DateTime<Utc>, ParseError> {
     s.parse::<DateTime<Utc>>()
         .map_err(|_| ParseError::InvalidDateTime(s.to_string()))
 }
 
-fn parse_f64(s: &str) -> Result<f64, ParseError> {
+pub(crate) fn parse_f64(s: &str) -> Result<f64, ParseError> {
     s.parse::<f64>()
         .map_err(|_| ParseError::InvalidNumber(s.to_string()))
 }
 
-fn parse_detail(reader: &mut Reader<&[u8]>, buf: &mut Vec<u8>) -> Result<Detail, ParseError> {
+/// Parse the children of `<detail>`. Elements with a structured field are
+/// parsed into it; everything else, including known elements that fail to
+/// parse or have children, is kept verbatim in `xml_detail` so it survives
+/// re-serialization.
+fn parse_detail(
+    reader: &mut Reader<&[u8]>,
+    xml: &[u8],
+    buf: &mut Vec<u8>,
+) -> Result<Detail, ParseError> {
     let mut detail = Detail::default();
     let mut xml_fragments = Vec::new();
-    let mut depth = 1;
 
     loop {
+        let element_start = reader.buffer_position() as usize;
         match reader.read_event_into(buf) {
             Ok(XmlEvent::Start(e)) => {
-                depth += 1;
-                let tag_name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
-
-                // For Start events with unknown tags, save to xml_detail
-                if !matches!(
-                    tag_name.as_str(),
-                    "contact" | "__group" | "track" | "status" | "takv" | "precisionlocation"
-                ) {
-                    xml_fragments.push(tag_name);
-                }
+                let name = e.name().as_ref().to_vec();
+                reader.read_to_end_into(QName(&name), &mut Vec::new())?;
+                xml_fragments.push(raw_element(xml, element_start, reader));
             }
             Ok(XmlEvent::Empty(e)) => {
-                let tag_name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
-
-                match tag_name.as_str() {
-                    "contact" => {
-                        detail.contact = Some(parse_contact(&e)?);
-                    }
-                    "__group" => {
-                        detail.group = Some(parse_group(&e)?);
-                    }
-                    "track" => {
-                        detail.track = Some(parse_track(&e)?);
-                    }
-                    "status" => {
-                        detail.status = Some(parse_status(&e)?);
-                    }
-                    "takv" => {
-                        detail.takv = Some(parse_takv(&e)?);
+                let parsed = match e.name().as_ref() {
+                    b"contact" => parse_contact(&e).map(|c| detail.contact = Some(c)),
+                    b"__group" => parse_group(&e).map(|g| detail.group = Some(g)),
+                    b"track" => parse_track(&e).map(|t| detail.track = Some(t)),
+                    b"status" => parse_status(&e).map(|s| detail.status = Some(s)),
+                    b"takv" => parse_takv(&e).map(|t| detail.takv = Some(t)),
+                    b"precisionlocation" => {
+                        parse_precision_location(&e).map(|p| detail.precision_location = Some(p))
                     }
-                    "precisionlocation" => {
-                        detail.precision_location = Some(parse_precision_location(&e)?);
-                    }
-                    _ => {
-                        // Unknown element - save to xml_detail
-                        let element_str = std::str::from_utf8(buf).unwrap_or("").to_string();
-                        xml_fragments.push(element_str);
-                    }
-                }
-            }
-            Ok(XmlEvent::End(_)) => {
-                depth -= 1;
-                if depth == 0 {
-                    break;
+                    _ => Err(ParseError::InvalidStructure("unstructured".into())),
+                };
+                if parsed.is_err() {
+                    xml_fragments.push(raw_element(xml, element_start, reader));
                 }
             }
-            Ok(XmlEvent::Eof) => break,
+            // </detail>
+            Ok(XmlEvent::End(_)) | Ok(XmlEvent::Eof) => break,
             Err(e) => return Err(ParseError::XmlError(e)),
             _ => {}
         }
         buf.clear();
     }
 
-    // Combine xml fragments if any
     if !xml_fragments.is_empty() {
-        detail.xml_detail = Some(xml_fragments.join("\n"));
+        detail.xml_detail = Some(xml_fragments.join(""));
     }
 
     Ok(detail)
 }
 
+/// Source text from `start` to the reader's position, without the
+/// whitespace before the element
+fn raw_element(xml: &[u8], start: usize, reader: &Reader<&[u8]>) -> String {
+    let end = reader.buffer_position() as usize;
+    String::from_utf8_lossy(&xml[start..end]).trim().to_string()
+}
+
 fn parse_contact(element: &quick_xml::events::BytesStart) -> Result<Contact, ParseError> {
     let mut endpoint = None;
     let mut callsign = None;
@@ -1,6 +1,7 @@
 //! Protobuf support for CoT messages with TAK Protocol Version 1
 
 use crate::event::{Contact, Detail, Event, Group, Point, PrecisionLocation, Status, Takv, Track};
+use crate::serializer::serialize_unstructured_detail;
 use chrono::{DateTime, Utc};
 use prost::Message;
 use std::io::Write;
@@ -14,6 +15,12 @@ pub mod pb {
 /// TAK Protocol headers
 const MESH_HEADER: &[u8] = &[0xBF, 0x01, 0xBF];
 
+/// Magic byte in front of every federation frame
+const FEDERATION_MAGIC: u8 = 0xBF;
+
+/// Magic byte in front of every TAK server streaming frame
+const STREAM_MAGIC: u8 = 0xBF;
+
 #[derive(Error, Debug)]
 pub enum ProtoError {
     #[error("Protobuf encoding error: {0}")]
@@ -30,6 +37,9 @@ pub enum ProtoError {
 
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
+
+    #[error("Invalid frame: {0}")]
+    InvalidFrame(String),
 }
 
 /// Convert DateTime to milliseconds since epoch
@@ -63,7 +73,12 @@ impl From<&Event> for pb::CotEvent {
                 le: event.point.le,
             }),
             detail: event.detail.as_ref().map(|d| pb::Detail {
-                xml_detail: d.xml_detail.clone().unwrap_or_default(),
+                // Links, shapes and colors are carried as XML, as ATAK does
+                xml_detail: {
+                    let mut xml = String::new();
+                    serialize_unstructured_detail(&mut xml, d);
+                    xml
+                },
                 contact: d.contact.as_ref().map(|c| pb::Contact {
                     endpoint: c.endpoint.clone().unwrap_or_default(),
                     callsign: c.callsign.clone(),
@@ -248,6 +263,78 @@ pub fn encode_stream(event: &Event) -> Result<Vec<u8>, ProtoError> {
     Ok(buf)
 }
 
+/// Encode an Event as a TAK server streaming frame: 0xBF, varint length,
+/// TakMessage
+///
+/// This is what TAK servers read on a streaming connection that speaks
+/// protobuf; [`encode_stream`] leaves out the magic byte.
+pub fn encode_stream_frame(event: &Event) -> Result<Vec<u8>, ProtoError> {
+    let proto_buf = pb::TakMessage::from(event).encode_to_vec();
+    let mut buf = Vec::with_capacity(proto_buf.len() + 6);
+    buf.push(STREAM_MAGIC);
+    write_varint(&mut buf, proto_buf.len())?;
+    buf.extend_from_slice(&proto_buf);
+    Ok(buf)
+}
+
+/// Encode a federation envelope as a frame: 0xBF, varint length, protobuf
+pub fn encode_federated(message: &pb::FederatedMessage) -> Result<Vec<u8>, ProtoError> {
+    let proto_buf = message.encode_to_vec();
+    let mut buf = Vec::with_capacity(proto_buf.len() + 6);
+    buf.push(FEDERATION_MAGIC);
+    write_varint(&mut buf, proto_buf.len())?;
+    buf.extend_from_slice(&proto_buf);
+    Ok(buf)
+}
+
+/// Decode the federation frame at the start of `buf`
+///
+/// Returns the envelope and the number of bytes consumed, or `None` if the
+/// frame is not complete yet. Frames longer than `max_len` are rejected.
+pub fn decode_federated(
+    buf: &[u8],
+    max_len: usize,
+) -> Result<Option<(pb::FederatedMessage, usize)>, ProtoError> {
+    let Some(&magic) = buf.first() else {
+        return Ok(None);
+    };
+    if magic != FEDERATION_MAGIC {
+        return Err(ProtoError::InvalidFrame(format!(
+            "expected magic 0xBF, got {:#04x}",
+            magic
+        )));
+    }
+
+    // Varint length, at most 10 bytes
+    let mut len: usize = 0;
+    let mut header = 1;
+    loop {
+        let Some(&byte) = buf.get(header) else {
+            return Ok(None);
+        };
+        if header > 10 {
+            return Err(ProtoError::InvalidFrame("length varint too long".to_string()));
+        }
+        len |= ((byte & 0x7F) as usize) << (7 * (header - 1));
+        header += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    if len > max_len {
+        return Err(ProtoError::InvalidFrame(format!(
+            "frame length {} exceeds maximum {}",
+            len, max_len
+        )));
+    }
+    if buf.len() < header + len {
+        return Ok(None);
+    }
+
+    let message = pb::FederatedMessage::decode(&buf[header..header + len])?;
+    Ok(Some((message, header + len)))
+}
+
 /// Write a varint to a buffer
 fn write_varint<W: Write>(writer: &mut W, mut value: usize) -> Result<(), ProtoError> {
     loop {
@@ -300,6 +387,7 @@ mod tests {
                 status: None,
                 takv: None,
                 track: None,
+                ..Default::default()
             }),
         }
     }
@@ -327,4 +415,40 @@ mod tests {
         assert_eq!(proto.r#type, "a-f-G");
         assert!(proto.point.is_some());
     }
+
+    #[test]
+    fn test_stream_frame() {
+        let event = create_test_event();
+        let frame = encode_stream_frame(&event).unwrap();
+        assert_eq!(frame[0], 0xBF);
+
+        let decoded = crate::parser::parse_stream(&frame[1..]).unwrap();
+        assert_eq!(decoded.uid, event.uid);
+        assert_eq!(decoded.point.lat, event.point.lat);
+    }
+
+    #[test]
+    fn test_federated_framing() {
+        let message = pb::FederatedMessage {
+            origin: "site-a".to_string(),
+            path: vec!["site-a".to_string(), "site-b".to_string()],
+            hops: 1,
+            payload: vec![b'x'; 300],
+        };
+        let frame = encode_federated(&message).unwrap();
+        assert_eq!(frame[0], 0xBF);
+
+        // Incomplete frames wait for more data
+        assert!(decode_federated(&frame[..1], 1024).unwrap().is_none());
+        assert!(decode_federated(&frame[..frame.len() - 1], 1024).unwrap().is_none());
+
+        let mut stream = frame.clone();
+        stream.extend_from_slice(&frame);
+        let (decoded, used) = decode_federated(&stream, 1024).unwrap().unwrap();
+        assert_eq!(decoded, message);
+        assert_eq!(used, frame.len());
+
+        assert!(decode_federated(&frame, 100).is_err());
+        assert!(decode_federated(b"<event", 1024).is_err());
+    }
 }
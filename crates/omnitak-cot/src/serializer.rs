@@ -3,8 +3,24 @@
 use crate::event::{
     Contact, Detail, Event, Group, Link, PrecisionLocation, Shape, Status, Takv, Track,
 };
+use std::borrow::Cow;
 use std::fmt::Write;
 
+/// Escape plain text for an attribute or element
+///
+/// Strings in an [`Event`] are written as they are, the way the parser
+/// returns them, so text from anywhere else (user input, names from other
+/// protocols) must go through this before it is put in an event.
+pub fn escape_xml(text: &str) -> String {
+    quick_xml::escape::escape(text).into_owned()
+}
+
+/// Plain text of a value read from CoT XML; the reverse of [`escape_xml`].
+/// Values with malformed references are returned unchanged.
+pub fn unescape_xml(text: &str) -> Cow<'_, str> {
+    quick_xml::escape::unescape(text).unwrap_or(Cow::Borrowed(text))
+}
+
 /// Serialize an Event to XML string
 pub fn serialize_event(event: &Event) -> String {
     let mut xml = String::new();
@@ -72,6 +88,12 @@ fn serialize_detail(xml: &mut String, detail: &Detail) {
         serialize_precision_location(xml, precision_location);
     }
 
+    serialize_unstructured_detail(xml, detail);
+}
+
+/// Serialize the detail content that TAK Protocol protobuf has no field for,
+/// which travels in its `xmlDetail` string
+pub(crate) fn serialize_unstructured_detail(xml: &mut String, detail: &Detail) {
     // Serialize links
     for link in &detail.link {
         serialize_link(xml, link);
@@ -400,4 +422,13 @@ mod tests {
         assert!(xml.contains(r#"<link uid="waypoint-2" relation="c" type="b-m-p-s-p-loc"/>"#));
         assert!(xml.contains(r#"<color value="-256"/>"#));
     }
+
+    #[test]
+    fn test_escape_xml() {
+        let text = r#"Tom & "Jerry" <1>'s"#;
+        let escaped = escape_xml(text);
+        assert_eq!(escaped, "Tom &amp; &quot;Jerry&quot; &lt;1&gt;&apos;s");
+        assert_eq!(unescape_xml(&escaped), text);
+        assert_eq!(unescape_xml("A &bogus; B"), "A &bogus; B");
+    }
 }
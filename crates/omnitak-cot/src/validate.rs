@@ -31,6 +31,15 @@ pub enum ValidationError {
 
     #[error("Empty version")]
     EmptyVersion,
+
+    #[error("Unsupported version: {0} (must be 2.0)")]
+    UnsupportedVersion(String),
+
+    #[error("Invalid how format: {0}")]
+    InvalidHow(String),
+
+    #[error("Non-finite point value: {0}")]
+    NonFinitePoint(&'static str),
 }
 
 /// Validates a CoT Event
@@ -89,18 +98,61 @@ pub fn validate_point(point: &Point) -> Result<(), ValidationError> {
     Ok(())
 }
 
-/// Strict validation that enforces additional constraints
+/// Strict validation against the constraints of the CoT event schema
+/// (`Event.xsd`)
+///
+/// On top of [`validate_event`]: the version must be 2.0, `type` must match
+/// `\w+(-\w+)*(;[^;]*)?`, `how` must be present and match `\w(-\w)*`, and
+/// every point value must be a finite number.
 pub fn validate_event_strict(event: &Event) -> Result<(), ValidationError> {
     // Run standard validation first
     validate_event(event)?;
 
-    // Additional strict checks could go here
-    // For example: checking if version is exactly "2.0"
-    // or validating specific CoT type patterns
+    if event.version != "2.0" {
+        return Err(ValidationError::UnsupportedVersion(event.version.clone()));
+    }
+
+    // Everything after a ';' is free-form
+    let base_type = event.event_type.split(';').next().unwrap_or_default();
+    if !base_type
+        .split('-')
+        .all(|part| !part.is_empty() && part.chars().all(is_word_char))
+    {
+        return Err(ValidationError::InvalidCotType(event.event_type.clone()));
+    }
+
+    if event.how.is_empty() {
+        return Err(ValidationError::MissingField("how".to_string()));
+    }
+    if !event.how.split('-').all(|part| {
+        let mut chars = part.chars();
+        matches!((chars.next(), chars.next()), (Some(c), None) if is_word_char(c))
+    }) {
+        return Err(ValidationError::InvalidHow(event.how.clone()));
+    }
+
+    // Range checks pass NaN, the schema's decimals don't
+    let point = &event.point;
+    for (name, value) in [
+        ("lat", point.lat),
+        ("lon", point.lon),
+        ("hae", point.hae),
+        ("ce", point.ce),
+        ("le", point.le),
+    ] {
+        if !value.is_finite() {
+            return Err(ValidationError::NonFinitePoint(name));
+        }
+    }
 
     Ok(())
 }
 
+/// Characters XML Schema's `\w` matches, as far as CoT uses them
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -193,6 +245,44 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_strict_validation() {
+        let event = create_valid_event();
+        assert!(validate_event_strict(&event).is_ok());
+
+        let mut event = create_valid_event();
+        event.event_type = "a-f-G-U-C;extension".to_string();
+        assert!(validate_event_strict(&event).is_ok());
+
+        let mut event = create_valid_event();
+        event.event_type = "a--f".to_string();
+        assert!(matches!(
+            validate_event_strict(&event),
+            Err(ValidationError::InvalidCotType(_))
+        ));
+
+        let mut event = create_valid_event();
+        event.version = "1.0".to_string();
+        assert!(matches!(
+            validate_event_strict(&event),
+            Err(ValidationError::UnsupportedVersion(_))
+        ));
+
+        let mut event = create_valid_event();
+        event.how = "machine".to_string();
+        assert!(matches!(
+            validate_event_strict(&event),
+            Err(ValidationError::InvalidHow(_))
+        ));
+
+        let mut event = create_valid_event();
+        event.point.lat = f64::NAN;
+        assert!(matches!(
+            validate_event_strict(&event),
+            Err(ValidationError::NonFinitePoint("lat"))
+        ));
+    }
+
     #[test]
     fn test_point_validation() {
         let valid_point = Point {
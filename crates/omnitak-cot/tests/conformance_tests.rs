@@ -0,0 +1,137 @@
+//! Conformance tests against CoT captured from ATAK and WinTAK
+//!
+//! Each `fixtures/conformance/<name>.xml` is a message as sent by a TAK
+//! client. Serializing the parsed event must match `<name>.golden.xml`, and
+//! the event must come back unchanged from XML and protobuf round trips.
+//!
+//! After an intentional serializer change, regenerate the golden files with
+//! `UPDATE_GOLDEN=1 cargo test -p omnitak-cot --test conformance_tests` and
+//! review the diff.
+
+use omnitak_cot::parser::{parse_mesh, parse_stream};
+use omnitak_cot::proto::{encode_mesh, encode_stream};
+use omnitak_cot::{decode_event, encode_event, parse_cot, serialize_event, Event};
+use std::path::PathBuf;
+
+/// EUD position report, GeoChat, drawn shape, 9-line MEDEVAC, video feed
+/// and route
+const SAMPLES: &[&str] = &["eud_position", "chat", "shape", "medevac", "video", "route"];
+
+fn fixture(file: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/conformance")
+        .join(file)
+}
+
+fn load(name: &str) -> Event {
+    let xml = std::fs::read_to_string(fixture(&format!("{}.xml", name))).unwrap();
+    parse_cot(&xml).unwrap_or_else(|e| panic!("{}: {}", name, e))
+}
+
+fn xml_detail(event: &Event) -> &str {
+    event
+        .detail
+        .as_ref()
+        .and_then(|d| d.xml_detail.as_deref())
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_golden_serialization() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    for name in SAMPLES {
+        let serialized = serialize_event(&load(name));
+        let golden = fixture(&format!("{}.golden.xml", name));
+        if update {
+            std::fs::write(&golden, &serialized).unwrap();
+            continue;
+        }
+        let expected = std::fs::read_to_string(&golden)
+            .unwrap_or_else(|e| panic!("{}: {} (run with UPDATE_GOLDEN=1)", golden.display(), e));
+        assert_eq!(serialized, expected, "{} serialization changed", name);
+    }
+}
+
+#[test]
+fn test_xml_roundtrip() {
+    for name in SAMPLES {
+        let event = load(name);
+        let reparsed = parse_cot(&serialize_event(&event)).unwrap();
+        assert_eq!(reparsed, event, "{} changed in XML round trip", name);
+    }
+}
+
+#[test]
+fn test_protobuf_roundtrip() {
+    for name in SAMPLES {
+        let event = load(name);
+
+        let decoded = decode_event(&encode_event(&event).unwrap()).unwrap();
+        assert_eq!(decoded, event, "{} changed in protobuf round trip", name);
+
+        let mesh = parse_mesh(&encode_mesh(&event).unwrap()).unwrap();
+        assert_eq!(mesh, event, "{} changed in mesh round trip", name);
+
+        let stream = parse_stream(&encode_stream(&event).unwrap()).unwrap();
+        assert_eq!(stream, event, "{} changed in stream round trip", name);
+    }
+}
+
+#[test]
+fn test_eud_position() {
+    let event = load("eud_position");
+    assert_eq!(event.event_type, "a-f-G-U-C");
+    assert_eq!(event.time.timestamp_subsec_millis(), 123);
+    assert_eq!(event.point.ce, 4.9);
+    assert_eq!(event.callsign(), Some("VIPER"));
+    assert_eq!(event.group_name(), Some("Cyan"));
+    assert_eq!(event.course(), Some(134.52));
+
+    let detail = event.detail.as_ref().unwrap();
+    assert_eq!(
+        detail.contact.as_ref().unwrap().endpoint.as_deref(),
+        Some("*:-1:stcp")
+    );
+    assert_eq!(detail.status.as_ref().unwrap().battery, 87);
+    assert_eq!(detail.takv.as_ref().unwrap().platform, "ATAK-CIV");
+    assert_eq!(
+        detail.precision_location.as_ref().unwrap().geopointsrc,
+        "GPS"
+    );
+    assert_eq!(xml_detail(&event), r#"<uid Droid="VIPER"/>"#);
+}
+
+#[test]
+fn test_unstructured_detail_preserved() {
+    // GeoChat: nested elements and text content
+    let chat = load("chat");
+    assert!(xml_detail(&chat).contains(r#"<chatgrp uid0="ANDROID-589520ccfcd20f01""#));
+    assert!(xml_detail(&chat).contains(">Rally at checkpoint 2 in 10 mikes</remarks>"));
+
+    // Shape: vertices, styling, and a precisionlocation without geopointsrc
+    let shape = load("shape");
+    assert_eq!(xml_detail(&shape).matches("<link point=").count(), 5);
+    assert!(xml_detail(&shape).contains(r#"<fillColor value="1442775040" />"#));
+    assert!(xml_detail(&shape).contains(r#"<precisionlocation altsrc="???" />"#));
+    assert!(shape.detail.as_ref().unwrap().precision_location.is_none());
+
+    // MEDEVAC: the 9-line fields and a status without battery
+    let medevac = load("medevac");
+    assert!(xml_detail(&medevac).contains("medline_remarks=\"GSW left leg, tourniquet applied\""));
+    assert!(xml_detail(&medevac).contains(r#"<status readiness="false"/>"#));
+    assert_eq!(medevac.callsign(), Some("MED.141422"));
+
+    // Video: connection details nested in __video
+    let video = load("video");
+    assert!(xml_detail(&video).contains(r#"address="10.0.0.5" port="554""#));
+    assert_eq!(video.speed(), Some(22.5));
+
+    // Route: waypoints in order, and nested route info
+    let route = load("route");
+    let detail = xml_detail(&route);
+    let sp = detail.find(r#"callsign="SP""#).unwrap();
+    let cp1 = detail.find(r#"callsign="CP1""#).unwrap();
+    let vdo = detail.find(r#"callsign="VDO""#).unwrap();
+    assert!(sp < cp1 && cp1 < vdo);
+    assert!(detail.contains("<__routeinfo><__navcues/></__routeinfo>"));
+}
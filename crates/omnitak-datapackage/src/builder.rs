@@ -151,6 +151,24 @@ impl DataPackageBuilder {
         self.add_bytes(&zip_entry, xml.as_bytes().to_vec(), ContentType::CotEvent)
     }
 
+    /// Add the CoT event of a map item as `<uid>/<uid>.cot`, the layout
+    /// ATAK uses, with the UID recorded in the manifest
+    pub fn add_map_item(mut self, uid: &str, xml: &str) -> Result<Self> {
+        // The UID names a directory, so it must be a single path component
+        if uid.is_empty() || uid.contains(['/', '\\']) || uid.contains("..") {
+            return Err(DataPackageError::PathTraversal(uid.to_string()));
+        }
+        let zip_entry = format!("{}/{}.cot", uid, uid);
+
+        let data = xml.as_bytes().to_vec();
+        let content = PackageContent::from_bytes(&zip_entry, &data, ContentType::CotEvent);
+        self.contents.push(content);
+        self.content_data.push((zip_entry.clone(), data));
+        self.manifest.add_cot_content(&zip_entry, uid);
+
+        Ok(self)
+    }
+
     /// Get package summary
     pub fn summary(&self) -> PackageSummary {
         let mut summary = PackageSummary::default();
@@ -312,4 +330,24 @@ mod tests {
         // Verify it's a valid ZIP (starts with PK)
         assert_eq!(&data[0..2], b"PK");
     }
+
+    #[test]
+    fn test_add_map_item() {
+        let cot_xml = r#"<event version="2.0" uid="shape-1" type="u-d-f" />"#;
+        let dir = TempDir::new().unwrap();
+        let path = DataPackageBuilder::new("test.zip")
+            .add_map_item("shape-1", cot_xml)
+            .unwrap()
+            .build(dir.path().join("test.zip"))
+            .unwrap();
+
+        let reader = crate::DataPackageReader::open(path).unwrap();
+        let content = &reader.manifest().contents[0];
+        assert_eq!(content.zip_entry, "shape-1/shape-1.cot");
+        assert_eq!(content.uid(), Some("shape-1"));
+
+        assert!(DataPackageBuilder::new("test.zip")
+            .add_map_item("../shape", cot_xml)
+            .is_err());
+    }
 }
@@ -24,6 +24,10 @@ pub enum DataPackageError {
     #[error("Invalid manifest: {0}")]
     InvalidManifest(String),
 
+    /// Malformed ATAK preference file
+    #[error("Invalid preferences: {0}")]
+    InvalidPreferences(String),
+
     /// Missing required file in package
     #[error("Missing required file: {0}")]
     MissingFile(String),
@@ -48,6 +52,10 @@ pub enum DataPackageError {
     #[error("Package size {size} exceeds maximum {max_size}")]
     PackageTooLarge { size: u64, max_size: u64 },
 
+    /// Package store full
+    #[error("Package store quota exceeded: {current} bytes, limit {limit}")]
+    QuotaExceeded { current: u64, limit: u64 },
+
     /// Invalid UTF-8 in manifest
     #[error("Invalid UTF-8: {0}")]
     InvalidUtf8(#[from] std::string::FromUtf8Error),
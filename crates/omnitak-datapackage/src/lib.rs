@@ -11,12 +11,18 @@ pub mod manifest;
 pub mod builder;
 pub mod reader;
 pub mod content;
+pub mod prefs;
+pub mod store;
+pub mod validate;
 
 pub use error::{DataPackageError, Result};
 pub use manifest::{Manifest, ManifestParameter, ManifestContent};
 pub use builder::DataPackageBuilder;
 pub use reader::DataPackageReader;
 pub use content::{ContentType, PackageContent, PackageSummary};
+pub use prefs::{PreferenceProfile, StreamPreference, StreamProtocol};
+pub use store::{PackageStore, PackageUpload, StoredPackage};
+pub use validate::{Severity, ValidationIssue, ValidationReport};
 
 /// TAK Data Package version
 pub const MANIFEST_VERSION: &str = "2";
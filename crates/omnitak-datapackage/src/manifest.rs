@@ -37,6 +37,19 @@ pub struct ManifestContent {
     pub ignore: bool,
     /// Path within the ZIP archive
     pub zip_entry: String,
+    /// Per-entry parameters, e.g. the `uid` of a CoT event
+    #[serde(default)]
+    pub parameters: Vec<ManifestParameter>,
+}
+
+impl ManifestContent {
+    /// UID of the map item this entry holds, if any
+    pub fn uid(&self) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|p| p.name == "uid")
+            .map(|p| p.value.as_str())
+    }
 }
 
 impl Manifest {
@@ -87,9 +100,22 @@ impl Manifest {
         self.contents.push(ManifestContent {
             ignore,
             zip_entry: zip_entry.to_string(),
+            parameters: Vec::new(),
         });
     }
 
+    /// Add a CoT event entry tagged with the UID of its map item, so
+    /// receivers replace the item rather than duplicate it
+    pub fn add_cot_content(&mut self, zip_entry: &str, uid: &str) {
+        self.add_content(zip_entry, false);
+        if let Some(content) = self.contents.last_mut() {
+            content.parameters.push(ManifestParameter {
+                name: "uid".to_string(),
+                value: uid.to_string(),
+            });
+        }
+    }
+
     /// Get package UID
     pub fn uid(&self) -> Option<&str> {
         self.get_parameter("uid")
@@ -124,6 +150,8 @@ impl Manifest {
 
         let mut in_configuration = false;
         let mut in_contents = false;
+        // Content element whose child parameters are being read
+        let mut open_content: Option<ManifestContent> = None;
 
         loop {
             match xml_reader.read_event_into(&mut buf) {
@@ -140,51 +168,33 @@ impl Manifest {
                     }
                     b"Configuration" => in_configuration = true,
                     b"Contents" => in_contents = true,
+                    b"Content" if in_contents => open_content = Some(parse_content(e)?),
                     _ => {}
                 },
                 Ok(Event::Empty(ref e)) => {
-                    if e.name().as_ref() == b"Parameter" && in_configuration {
-                        let mut name = String::new();
-                        let mut value = String::new();
-                        for attr in e.attributes() {
-                            let attr = attr.map_err(|e| {
-                                DataPackageError::InvalidManifest(format!("Invalid attribute: {}", e))
-                            })?;
-                            match attr.key.as_ref() {
-                                b"name" => name = String::from_utf8(attr.value.to_vec())?,
-                                b"value" => value = String::from_utf8(attr.value.to_vec())?,
-                                _ => {}
-                            }
-                        }
-                        if !name.is_empty() {
-                            configuration.push(ManifestParameter { name, value });
+                    if e.name().as_ref() == b"Parameter" {
+                        if let Some(content) = open_content.as_mut() {
+                            content.parameters.extend(parse_parameter(e)?);
+                        } else if in_configuration {
+                            configuration.extend(parse_parameter(e)?);
                         }
                     } else if e.name().as_ref() == b"Content" && in_contents {
-                        let mut ignore = false;
-                        let mut zip_entry = String::new();
-                        for attr in e.attributes() {
-                            let attr = attr.map_err(|e| {
-                                DataPackageError::InvalidManifest(format!("Invalid attribute: {}", e))
-                            })?;
-                            match attr.key.as_ref() {
-                                b"ignore" => {
-                                    let val = String::from_utf8(attr.value.to_vec())?;
-                                    ignore = val.eq_ignore_ascii_case("true");
-                                }
-                                b"zipEntry" => {
-                                    zip_entry = String::from_utf8(attr.value.to_vec())?;
-                                }
-                                _ => {}
-                            }
-                        }
-                        if !zip_entry.is_empty() {
-                            contents.push(ManifestContent { ignore, zip_entry });
+                        let content = parse_content(e)?;
+                        if !content.zip_entry.is_empty() {
+                            contents.push(content);
                         }
                     }
                 }
                 Ok(Event::End(ref e)) => match e.name().as_ref() {
                     b"Configuration" => in_configuration = false,
                     b"Contents" => in_contents = false,
+                    b"Content" => {
+                        if let Some(content) = open_content.take() {
+                            if !content.zip_entry.is_empty() {
+                                contents.push(content);
+                            }
+                        }
+                    }
                     _ => {}
                 },
                 Ok(Event::Eof) => break,
@@ -229,7 +239,18 @@ impl Manifest {
             let mut elem = BytesStart::new("Content");
             elem.push_attribute(("ignore", if content.ignore { "true" } else { "false" }));
             elem.push_attribute(("zipEntry", content.zip_entry.as_str()));
-            writer.write_event(Event::Empty(elem))?;
+            if content.parameters.is_empty() {
+                writer.write_event(Event::Empty(elem))?;
+                continue;
+            }
+            writer.write_event(Event::Start(elem))?;
+            for param in &content.parameters {
+                let mut elem = BytesStart::new("Parameter");
+                elem.push_attribute(("name", param.name.as_str()));
+                elem.push_attribute(("value", param.value.as_str()));
+                writer.write_event(Event::Empty(elem))?;
+            }
+            writer.write_event(Event::End(BytesEnd::new("Content")))?;
         }
         writer.write_event(Event::End(BytesEnd::new("Contents")))?;
 
@@ -241,6 +262,47 @@ impl Manifest {
     }
 }
 
+/// Parse a `<Parameter name=".." value=".."/>` element; unnamed ones are dropped
+fn parse_parameter(e: &BytesStart) -> Result<Option<ManifestParameter>> {
+    let mut name = String::new();
+    let mut value = String::new();
+    for attr in e.attributes() {
+        let attr = attr
+            .map_err(|e| DataPackageError::InvalidManifest(format!("Invalid attribute: {}", e)))?;
+        match attr.key.as_ref() {
+            b"name" => name = String::from_utf8(attr.value.to_vec())?,
+            b"value" => value = String::from_utf8(attr.value.to_vec())?,
+            _ => {}
+        }
+    }
+    Ok((!name.is_empty()).then_some(ManifestParameter { name, value }))
+}
+
+/// Parse the attributes of a `<Content>` element
+fn parse_content(e: &BytesStart) -> Result<ManifestContent> {
+    let mut ignore = false;
+    let mut zip_entry = String::new();
+    for attr in e.attributes() {
+        let attr = attr
+            .map_err(|e| DataPackageError::InvalidManifest(format!("Invalid attribute: {}", e)))?;
+        match attr.key.as_ref() {
+            b"ignore" => {
+                let val = String::from_utf8(attr.value.to_vec())?;
+                ignore = val.eq_ignore_ascii_case("true");
+            }
+            b"zipEntry" => {
+                zip_entry = String::from_utf8(attr.value.to_vec())?;
+            }
+            _ => {}
+        }
+    }
+    Ok(ManifestContent {
+        ignore,
+        zip_entry,
+        parameters: Vec::new(),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -297,6 +359,7 @@ mod tests {
         original.set_parameter("onReceiveDelete", "true");
         original.add_content("file1.cot", false);
         original.add_content("file2.kml", true);
+        original.add_cot_content("marker-1/marker-1.cot", "marker-1");
 
         let xml = original.to_xml().unwrap();
         let parsed = Manifest::from_xml(&xml).unwrap();
@@ -305,5 +368,9 @@ mod tests {
         assert_eq!(parsed.name(), original.name());
         assert_eq!(parsed.on_receive_delete(), original.on_receive_delete());
         assert_eq!(parsed.contents.len(), original.contents.len());
+        assert_eq!(parsed.contents[0].uid(), None);
+        assert_eq!(parsed.contents[2].uid(), Some("marker-1"));
+        // Entry parameters stay out of the package configuration
+        assert_eq!(parsed.configuration.len(), original.configuration.len());
     }
 }
@@ -0,0 +1,550 @@
+//! ATAK preference (.pref) files
+//!
+//! Server connection packages carry a `.pref` file whose `cot_streams`
+//! preference lists the streaming connections to set up, along with the
+//! locations and passwords of the bundled certificates:
+//!
+//! ```xml
+//! <preferences>
+//!   <preference version="1" name="cot_streams">
+//!     <entry key="count" class="class java.lang.Integer">1</entry>
+//!     <entry key="description0" class="class java.lang.String">TAK Server</entry>
+//!     <entry key="connectString0" class="class java.lang.String">tak.example.com:8089:ssl</entry>
+//!     <entry key="caLocation0" class="class java.lang.String">cert/truststore.p12</entry>
+//!   </preference>
+//! </preferences>
+//! ```
+//!
+//! ATAK keeps the same settings on the device as Android shared preference
+//! files (`<map>` of `<string name="...">` elements), which
+//! [`PreferenceProfile::parse_files`] reads as well.
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::{DataPackageError, Result};
+
+/// Password ATAK uses for certificates when a preference file names none
+pub const DEFAULT_CERTIFICATE_PASSWORD: &str = "atakatak";
+
+/// Transport of a streaming connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamProtocol {
+    Tcp,
+    Ssl,
+    Udp,
+    Quic,
+}
+
+impl StreamProtocol {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "tcp" => Some(Self::Tcp),
+            "ssl" | "tls" => Some(Self::Ssl),
+            "udp" => Some(Self::Udp),
+            "quic" => Some(Self::Quic),
+            _ => None,
+        }
+    }
+
+    /// Name used in a `connectString`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tcp => "tcp",
+            Self::Ssl => "ssl",
+            Self::Udp => "udp",
+            Self::Quic => "quic",
+        }
+    }
+}
+
+/// One connection from a `cot_streams` preference
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamPreference {
+    pub description: String,
+    pub host: String,
+    pub port: u16,
+    pub protocol: StreamProtocol,
+    pub enabled: bool,
+    /// Trust store location as written on the device, e.g. `cert/truststore.p12`
+    pub ca_location: Option<String>,
+    pub ca_password: Option<String>,
+    /// Client certificate location as written on the device
+    pub certificate_location: Option<String>,
+    pub client_password: Option<String>,
+    /// Whether the server expects a username and password
+    pub use_auth: bool,
+    /// Whether the client should enroll for a certificate on first connect
+    pub enroll_for_certificate: bool,
+}
+
+impl StreamPreference {
+    /// Trust store password, falling back to the ATAK default
+    pub fn ca_password(&self) -> &str {
+        self.ca_password
+            .as_deref()
+            .unwrap_or(DEFAULT_CERTIFICATE_PASSWORD)
+    }
+
+    /// Client certificate password, falling back to the ATAK default
+    pub fn client_password(&self) -> &str {
+        self.client_password
+            .as_deref()
+            .unwrap_or(DEFAULT_CERTIFICATE_PASSWORD)
+    }
+}
+
+/// Connection settings of an ATAK preference file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PreferenceProfile {
+    /// Streaming connections, in the order ATAK lists them
+    pub streams: Vec<StreamPreference>,
+    /// Trust store location used by streams that name none
+    pub ca_location: Option<String>,
+    pub ca_password: Option<String>,
+    /// Client certificate location used by streams that name none
+    pub certificate_location: Option<String>,
+    pub client_password: Option<String>,
+}
+
+impl PreferenceProfile {
+    /// Parse a `.pref` file or a shared preference file pulled from a device
+    pub fn parse(xml: &str) -> Result<Self> {
+        Self::parse_files([xml])
+    }
+
+    /// Parse several preference files as one
+    ///
+    /// On a device the connections (`cot_streams.xml`) and the global
+    /// certificate settings (the app's own preferences) live in separate
+    /// files; streams only pick up the global settings when both are read
+    /// together.
+    pub fn parse_files<'a>(files: impl IntoIterator<Item = &'a str>) -> Result<Self> {
+        let mut preferences: HashMap<String, HashMap<String, String>> = HashMap::new();
+        for xml in files {
+            for (name, entries) in parse_entries(xml)? {
+                preferences.entry(name).or_default().extend(entries);
+            }
+        }
+
+        let empty = HashMap::new();
+        let app = preferences
+            .get("com.atakmap.app_preferences")
+            .unwrap_or(&empty);
+        let global = |key: &str| app.get(key).filter(|v| !v.is_empty()).cloned();
+
+        Ok(Self {
+            streams: streams_from_entries(&preferences),
+            ca_location: global("caLocation"),
+            ca_password: global("caPassword"),
+            certificate_location: global("certificateLocation"),
+            client_password: global("clientPassword"),
+        })
+    }
+
+    /// Connection to use when only one can be: the first enabled stream,
+    /// or the first stream if all are disabled
+    pub fn primary_stream(&self) -> Option<&StreamPreference> {
+        self.streams
+            .iter()
+            .find(|s| s.enabled)
+            .or_else(|| self.streams.first())
+    }
+}
+
+/// Parse the streaming connections out of an ATAK preference file
+///
+/// Certificate settings missing from a stream are taken from the global
+/// `com.atakmap.app_preferences` entries, as ATAK does. Streams with an
+/// unparseable `connectString` are skipped.
+pub fn parse_stream_preferences(xml: &str) -> Result<Vec<StreamPreference>> {
+    PreferenceProfile::parse(xml).map(|profile| profile.streams)
+}
+
+fn streams_from_entries(
+    preferences: &HashMap<String, HashMap<String, String>>,
+) -> Vec<StreamPreference> {
+    let empty = HashMap::new();
+    let streams = preferences.get("cot_streams").unwrap_or(&empty);
+    let app = preferences
+        .get("com.atakmap.app_preferences")
+        .unwrap_or(&empty);
+
+    let count = streams
+        .get("count")
+        .and_then(|c| c.parse().ok())
+        .unwrap_or_else(|| {
+            (0..)
+                .take_while(|i| streams.contains_key(&format!("connectString{}", i)))
+                .count()
+        });
+
+    let mut result = Vec::new();
+    for i in 0..count {
+        let entry = |key: &str| {
+            streams
+                .get(&format!("{}{}", key, i))
+                .filter(|v| !v.is_empty())
+                .cloned()
+        };
+        let setting =
+            |key: &str| entry(key).or_else(|| app.get(key).filter(|v| !v.is_empty()).cloned());
+        let flag = |key: &str, default: bool| {
+            entry(key)
+                .map(|v| v.eq_ignore_ascii_case("true"))
+                .unwrap_or(default)
+        };
+
+        let Some(connect_string) = entry("connectString") else {
+            continue;
+        };
+        let Some((host, port, protocol)) = parse_connect_string(&connect_string) else {
+            tracing::warn!(
+                "Skipping stream with invalid connectString: {}",
+                connect_string
+            );
+            continue;
+        };
+
+        result.push(StreamPreference {
+            description: entry("description").unwrap_or_else(|| host.clone()),
+            host,
+            port,
+            protocol,
+            enabled: flag("enabled", true),
+            ca_location: setting("caLocation"),
+            ca_password: setting("caPassword"),
+            certificate_location: setting("certificateLocation"),
+            client_password: setting("clientPassword"),
+            use_auth: flag("useAuth", false),
+            enroll_for_certificate: flag("enrollForCertificateWithTrust", false),
+        });
+    }
+
+    result
+}
+
+/// Write streaming connections as an ATAK preference file
+///
+/// The inverse of [`parse_stream_preferences`]; certificate settings are
+/// written per stream, so nothing is set globally.
+pub fn write_stream_preferences(streams: &[StreamPreference]) -> String {
+    let mut entries = String::new();
+    let mut entry = |key: &str, index: usize, class: &str, value: &str| {
+        entries.push_str(&format!(
+            "\n    <entry key=\"{}{}\" class=\"class java.lang.{}\">{}</entry>",
+            key,
+            index,
+            class,
+            quick_xml::escape::escape(value)
+        ));
+    };
+
+    for (i, stream) in streams.iter().enumerate() {
+        let host = if stream.host.contains(':') {
+            format!("[{}]", stream.host)
+        } else {
+            stream.host.clone()
+        };
+        let connect_string = format!("{}:{}:{}", host, stream.port, stream.protocol.as_str());
+
+        entry("description", i, "String", &stream.description);
+        entry("enabled", i, "Boolean", &stream.enabled.to_string());
+        entry("connectString", i, "String", &connect_string);
+        let certificates = [
+            ("caLocation", &stream.ca_location),
+            ("caPassword", &stream.ca_password),
+            ("certificateLocation", &stream.certificate_location),
+            ("clientPassword", &stream.client_password),
+        ];
+        for (key, value) in certificates {
+            if let Some(value) = value {
+                entry(key, i, "String", value);
+            }
+        }
+        entry("useAuth", i, "Boolean", &stream.use_auth.to_string());
+        entry(
+            "enrollForCertificateWithTrust",
+            i,
+            "Boolean",
+            &stream.enroll_for_certificate.to_string(),
+        );
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<preferences>
+  <preference version="1" name="cot_streams">
+    <entry key="count" class="class java.lang.Integer">{}</entry>{}
+  </preference>
+</preferences>
+"#,
+        streams.len(),
+        entries
+    )
+}
+
+/// Problems with the `cot_streams` entries that [`parse_stream_preferences`]
+/// would silently skip
+pub(crate) fn stream_problems(xml: &str) -> Result<Vec<String>> {
+    let preferences = parse_entries(xml)?;
+    let Some(streams) = preferences.get("cot_streams") else {
+        return Ok(Vec::new());
+    };
+
+    let mut problems = Vec::new();
+    let count = match streams.get("count") {
+        Some(count) => match count.parse::<usize>() {
+            Ok(count) => count,
+            Err(_) => {
+                problems.push(format!("cot_streams count '{}' is not a number", count));
+                return Ok(problems);
+            }
+        },
+        None => return Ok(problems),
+    };
+
+    for i in 0..count {
+        match streams.get(&format!("connectString{}", i)) {
+            None => problems.push(format!(
+                "stream {} is counted but has no connectString{}",
+                i, i
+            )),
+            Some(s) if parse_connect_string(s).is_none() => problems.push(format!(
+                "stream {} has invalid connectString '{}' (expected host:port:protocol)",
+                i, s
+            )),
+            Some(_) => {}
+        }
+    }
+    if streams.contains_key(&format!("connectString{}", count)) {
+        problems.push(format!(
+            "cot_streams count is {} but more streams follow; they will be ignored",
+            count
+        ));
+    }
+
+    Ok(problems)
+}
+
+/// Split `host:port:protocol`, where the host may itself contain colons
+fn parse_connect_string(s: &str) -> Option<(String, u16, StreamProtocol)> {
+    let mut parts = s.trim().rsplitn(3, ':');
+    let protocol = StreamProtocol::parse(parts.next()?)?;
+    let port = parts.next()?.parse().ok()?;
+    let host = parts.next()?.trim_matches(['[', ']']);
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_string(), port, protocol))
+}
+
+/// Entries of each `<preference>` element, keyed by preference name
+///
+/// A shared preference file holds a single unnamed `<map>`. Its entries are
+/// filed under `cot_streams` when it lists connections and under
+/// `com.atakmap.app_preferences` otherwise.
+fn parse_entries(xml: &str) -> Result<HashMap<String, HashMap<String, String>>> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut preferences: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut shared = HashMap::new();
+    let mut preference = None;
+    let mut key = None;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => match e.name().as_ref() {
+                b"preference" => preference = attribute(&e, b"name")?,
+                b"entry" => key = attribute(&e, b"key")?,
+                b"string" if preference.is_none() => key = attribute(&e, b"name")?,
+                _ => {}
+            },
+            // Shared preferences keep non-string values in an attribute:
+            // <boolean name="useAuth0" value="true" />
+            Event::Empty(e) if preference.is_none() => {
+                if matches!(e.name().as_ref(), b"boolean" | b"int" | b"long" | b"float") {
+                    if let (Some(name), Some(value)) =
+                        (attribute(&e, b"name")?, attribute(&e, b"value")?)
+                    {
+                        shared.insert(name, value);
+                    }
+                }
+            }
+            Event::Text(text) => {
+                if let Some(key) = key.take() {
+                    let value = text
+                        .unescape()
+                        .map_err(|e| DataPackageError::InvalidPreferences(e.to_string()))?
+                        .into_owned();
+                    match &preference {
+                        Some(preference) => {
+                            preferences
+                                .entry(preference.clone())
+                                .or_default()
+                                .insert(key, value);
+                        }
+                        None => {
+                            shared.insert(key, value);
+                        }
+                    }
+                }
+            }
+            Event::End(e) => match e.name().as_ref() {
+                b"preference" => preference = None,
+                b"entry" | b"string" => key = None,
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !shared.is_empty() {
+        let name = if shared.contains_key("connectString0") {
+            "cot_streams"
+        } else {
+            "com.atakmap.app_preferences"
+        };
+        preferences.entry(name.to_string()).or_default().extend(shared);
+    }
+
+    Ok(preferences)
+}
+
+fn attribute(e: &quick_xml::events::BytesStart, name: &[u8]) -> Result<Option<String>> {
+    for attr in e.attributes() {
+        let attr = attr.map_err(|e| DataPackageError::InvalidPreferences(e.to_string()))?;
+        if attr.key.as_ref() == name {
+            return Ok(Some(String::from_utf8(attr.value.to_vec())?));
+        }
+    }
+    Ok(None)
+}
+
+/// Whether a package entry is a preference file
+pub fn is_preference_file(zip_entry: &str) -> bool {
+    Path::new(zip_entry)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("pref"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PREFS: &str = r#"<?xml version='1.0' standalone='yes'?>
+<preferences>
+  <preference version="1" name="cot_streams">
+    <entry key="count" class="class java.lang.Integer">2</entry>
+    <entry key="description0" class="class java.lang.String">Ops &amp; Intel</entry>
+    <entry key="enabled0" class="class java.lang.Boolean">true</entry>
+    <entry key="connectString0" class="class java.lang.String">tak.example.com:8089:ssl</entry>
+    <entry key="caLocation0" class="class java.lang.String">/sdcard/atak/cert/truststore.p12</entry>
+    <entry key="useAuth0" class="class java.lang.Boolean">true</entry>
+    <entry key="enabled1" class="class java.lang.Boolean">false</entry>
+    <entry key="connectString1" class="class java.lang.String">10.0.0.5:8087:tcp</entry>
+  </preference>
+  <preference version="1" name="com.atakmap.app_preferences">
+    <entry key="certificateLocation" class="class java.lang.String">cert/viper.p12</entry>
+    <entry key="clientPassword" class="class java.lang.String">s3cret</entry>
+  </preference>
+</preferences>"#;
+
+    #[test]
+    fn test_parse_stream_preferences() {
+        let streams = parse_stream_preferences(PREFS).unwrap();
+        assert_eq!(streams.len(), 2);
+
+        let tls = &streams[0];
+        assert_eq!(tls.description, "Ops & Intel");
+        assert_eq!((tls.host.as_str(), tls.port), ("tak.example.com", 8089));
+        assert_eq!(tls.protocol, StreamProtocol::Ssl);
+        assert!(tls.enabled && tls.use_auth);
+        assert_eq!(
+            tls.ca_location.as_deref(),
+            Some("/sdcard/atak/cert/truststore.p12")
+        );
+        assert_eq!(tls.ca_password(), DEFAULT_CERTIFICATE_PASSWORD);
+        // Falls back to the global certificate settings
+        assert_eq!(tls.certificate_location.as_deref(), Some("cert/viper.p12"));
+        assert_eq!(tls.client_password(), "s3cret");
+
+        let tcp = &streams[1];
+        assert_eq!(tcp.description, "10.0.0.5");
+        assert_eq!(tcp.protocol, StreamProtocol::Tcp);
+        assert!(!tcp.enabled);
+    }
+
+    #[test]
+    fn test_parse_shared_preferences() {
+        let streams = r#"<?xml version='1.0' encoding='utf-8' standalone='yes' ?>
+<map>
+    <int name="count" value="1" />
+    <string name="description0">Field Server</string>
+    <string name="connectString0">192.168.50.2:8089:ssl</string>
+    <boolean name="enabled0" value="true" />
+    <boolean name="useAuth0" value="true" />
+</map>"#;
+        let app = r#"<?xml version='1.0' encoding='utf-8' standalone='yes' ?>
+<map>
+    <string name="caLocation">/sdcard/atak/cert/truststore-field.p12</string>
+    <string name="clientPassword">field</string>
+    <boolean name="displayServerConnectionWidget" value="true" />
+</map>"#;
+
+        let profile = PreferenceProfile::parse_files([streams, app]).unwrap();
+        assert_eq!(
+            profile.ca_location.as_deref(),
+            Some("/sdcard/atak/cert/truststore-field.p12")
+        );
+        assert_eq!(profile.certificate_location, None);
+
+        let stream = profile.primary_stream().unwrap();
+        assert_eq!(stream.description, "Field Server");
+        assert_eq!((stream.host.as_str(), stream.port), ("192.168.50.2", 8089));
+        assert!(stream.use_auth);
+        assert_eq!(stream.ca_location, profile.ca_location);
+        assert_eq!(stream.client_password(), "field");
+    }
+
+    #[test]
+    fn test_primary_stream_skips_disabled() {
+        let mut profile = PreferenceProfile::parse(PREFS).unwrap();
+        assert_eq!(profile.client_password.as_deref(), Some("s3cret"));
+        profile.streams.swap(0, 1);
+        assert_eq!(profile.primary_stream().unwrap().host, "tak.example.com");
+
+        profile.streams.retain(|s| !s.enabled);
+        assert_eq!(profile.primary_stream().unwrap().host, "10.0.0.5");
+        assert!(PreferenceProfile::default().primary_stream().is_none());
+    }
+
+    #[test]
+    fn test_write_stream_preferences_round_trip() {
+        let mut streams = parse_stream_preferences(PREFS).unwrap();
+        streams[1].host = "fd00::5".to_string();
+
+        let xml = write_stream_preferences(&streams);
+        assert!(xml.contains("Ops &amp; Intel"));
+        assert!(xml.contains("[fd00::5]:8087:tcp"));
+        assert_eq!(parse_stream_preferences(&xml).unwrap(), streams);
+    }
+
+    #[test]
+    fn test_parse_connect_string() {
+        assert_eq!(
+            parse_connect_string("[fd00::1]:8087:TCP"),
+            Some(("fd00::1".to_string(), 8087, StreamProtocol::Tcp))
+        );
+        assert_eq!(parse_connect_string("host:notaport:ssl"), None);
+        assert_eq!(parse_connect_string("host:8089:carrier-pigeon"), None);
+        assert_eq!(parse_connect_string(":8089:ssl"), None);
+    }
+}
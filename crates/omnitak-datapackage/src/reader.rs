@@ -1,15 +1,18 @@
 //! TAK Data Package reader for importing .dpk/.zip files
 
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{Cursor, Read, Seek, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use zip::ZipArchive;
 
 use crate::content::{ContentType, PackageContent, PackageSummary};
 use crate::error::{DataPackageError, Result};
 use crate::manifest::Manifest;
-use crate::{TakFileType, MANIFEST_PATH};
+use crate::prefs::{self, StreamPreference, StreamProtocol};
+use crate::validate::{is_unsafe_path, sha256_hex, ValidationReport};
+use crate::{TakFileType, MANIFEST_PATH, MANIFEST_VERSION};
 
 /// Reader for TAK Data Packages
 pub struct DataPackageReader {
@@ -17,16 +20,33 @@ pub struct DataPackageReader {
     manifest: Manifest,
     /// Content metadata
     contents: Vec<PackageContent>,
-    /// Source path of the package
-    source_path: PathBuf,
+    /// Where the package is read from
+    source: Source,
 }
 
+/// Package archive location
+enum Source {
+    File(PathBuf),
+    Memory(Arc<[u8]>),
+}
+
+trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
 impl DataPackageReader {
     /// Open a data package from file
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let path_ref = path.as_ref();
-        let file = File::open(path_ref)?;
-        let mut archive = ZipArchive::new(file)?;
+        Self::from_source(Source::File(path.as_ref().to_path_buf()))
+    }
+
+    /// Open a data package held in memory, such as an upload
+    pub fn from_bytes(data: impl Into<Arc<[u8]>>) -> Result<Self> {
+        Self::from_source(Source::Memory(data.into()))
+    }
+
+    fn from_source(source: Source) -> Result<Self> {
+        let mut archive = Self::open_archive(&source)?;
 
         // Read manifest
         let manifest = Self::read_manifest(&mut archive)?;
@@ -68,10 +88,22 @@ impl DataPackageReader {
         Ok(Self {
             manifest,
             contents,
-            source_path: path_ref.to_path_buf(),
+            source,
         })
     }
 
+    fn open_archive(source: &Source) -> Result<ZipArchive<Box<dyn ReadSeek>>> {
+        let reader: Box<dyn ReadSeek> = match source {
+            Source::File(path) => Box::new(File::open(path)?),
+            Source::Memory(data) => Box::new(Cursor::new(Arc::clone(data))),
+        };
+        Ok(ZipArchive::new(reader)?)
+    }
+
+    fn archive(&self) -> Result<ZipArchive<Box<dyn ReadSeek>>> {
+        Self::open_archive(&self.source)
+    }
+
     /// Read manifest from archive
     fn read_manifest<R: Read + std::io::Seek>(archive: &mut ZipArchive<R>) -> Result<Manifest> {
         let mut manifest_file = archive.by_name(MANIFEST_PATH).map_err(|_| {
@@ -123,8 +155,7 @@ impl DataPackageReader {
 
     /// Read a specific file from the package
     pub fn read_file(&self, zip_entry: &str) -> Result<Vec<u8>> {
-        let file = File::open(&self.source_path)?;
-        let mut archive = ZipArchive::new(file)?;
+        let mut archive = self.archive()?;
 
         let mut entry = archive.by_name(zip_entry).map_err(|_| {
             DataPackageError::MissingFile(zip_entry.to_string())
@@ -142,13 +173,44 @@ impl DataPackageReader {
         Ok(String::from_utf8(bytes)?)
     }
 
+    /// Resolve a file location from a preference file to a package entry
+    ///
+    /// Preferences name files by their path on the device (for example
+    /// `cert/truststore.p12` or `/sdcard/atak/cert/truststore.p12`), which
+    /// rarely matches the layout inside the package, so entries are also
+    /// matched by file name.
+    pub fn find_file(&self, location: &str) -> Option<&str> {
+        let file_name = |p: &str| p.rsplit(['/', '\\']).next().map(str::to_ascii_lowercase);
+        let wanted = file_name(location)?;
+        self.contents
+            .iter()
+            .find(|c| c.path == location)
+            .or_else(|| {
+                self.contents
+                    .iter()
+                    .find(|c| file_name(&c.path).as_deref() == Some(wanted.as_str()))
+            })
+            .map(|c| c.path.as_str())
+    }
+
+    /// Streaming connections described by the package's preference files
+    pub fn stream_preferences(&self) -> Result<Vec<StreamPreference>> {
+        let mut streams = Vec::new();
+        for content in &self.contents {
+            if prefs::is_preference_file(&content.path) {
+                let xml = self.read_file_string(&content.path)?;
+                streams.extend(prefs::parse_stream_preferences(&xml)?);
+            }
+        }
+        Ok(streams)
+    }
+
     /// Extract all files to a directory
     pub fn extract_all<P: AsRef<Path>>(&self, output_dir: P) -> Result<Vec<PathBuf>> {
         let output = output_dir.as_ref();
         std::fs::create_dir_all(output)?;
 
-        let file = File::open(&self.source_path)?;
-        let mut archive = ZipArchive::new(file)?;
+        let mut archive = self.archive()?;
 
         let mut extracted = Vec::new();
 
@@ -234,39 +296,165 @@ impl DataPackageReader {
         Ok(events)
     }
 
-    /// Validate package integrity
+    /// Validate package integrity, failing on the first error [`lint`](Self::lint) finds
     pub fn validate(&self) -> Result<()> {
         // Check manifest version
-        if self.manifest.version != "2" {
+        if self.manifest.version != MANIFEST_VERSION {
             return Err(DataPackageError::UnsupportedVersion(
                 self.manifest.version.clone(),
             ));
         }
 
-        // Check that manifest contents match actual files
-        let file = File::open(&self.source_path)?;
-        let mut archive = ZipArchive::new(file)?;
+        match self.lint()?.errors().next() {
+            Some(issue) => Err(DataPackageError::ValidationFailed(issue.to_string())),
+            None => Ok(()),
+        }
+    }
 
-        let mut actual_files: HashMap<String, bool> = HashMap::new();
-        for i in 0..archive.len() {
-            let entry = archive.by_index_raw(i)?;
-            let name = entry.name().to_string();
-            if !name.starts_with("MANIFEST/") {
-                actual_files.insert(name, true);
+    /// Check the package for everything that would stop it working on a device
+    ///
+    /// Returns every problem found rather than stopping at the first; only
+    /// failures to read the archive itself are returned as errors.
+    pub fn lint(&self) -> Result<ValidationReport> {
+        let mut report = ValidationReport::default();
+
+        if self.manifest.version != MANIFEST_VERSION {
+            report.error(
+                Some(MANIFEST_PATH),
+                format!(
+                    "unsupported manifest version '{}' (expected {})",
+                    self.manifest.version, MANIFEST_VERSION
+                ),
+            );
+        }
+        for parameter in ["uid", "name"] {
+            if self
+                .manifest
+                .get_parameter(parameter)
+                .is_none_or(|v| v.trim().is_empty())
+            {
+                report.error(
+                    Some(MANIFEST_PATH),
+                    format!("missing required parameter '{}'", parameter),
+                );
             }
         }
 
-        // Verify all manifest entries exist
+        // Declared contents exist and match their hashes
+        let mut archive = self.archive()?;
+        let mut declared = HashSet::new();
         for content in &self.manifest.contents {
-            if !actual_files.contains_key(&content.zip_entry) {
-                return Err(DataPackageError::ValidationFailed(format!(
-                    "Manifest entry '{}' not found in archive",
-                    content.zip_entry
-                )));
+            let entry = content.zip_entry.as_str();
+            if !declared.insert(entry) {
+                report.warning(Some(entry), "listed more than once in the manifest");
+                continue;
+            }
+            if is_unsafe_path(entry) {
+                report.error(Some(entry), "path points outside the package");
+                continue;
+            }
+            let Ok(mut file) = archive.by_name(entry) else {
+                report.error(
+                    Some(entry),
+                    "listed in the manifest but missing from the archive",
+                );
+                continue;
+            };
+            if let Some(expected) = content.parameters.iter().find(|p| p.name == "sha256") {
+                let mut data = Vec::new();
+                file.read_to_end(&mut data)?;
+                let actual = sha256_hex(&data);
+                if !actual.eq_ignore_ascii_case(expected.value.trim()) {
+                    report.error(
+                        Some(entry),
+                        format!(
+                            "sha256 is {} but the manifest says {}",
+                            actual, expected.value
+                        ),
+                    );
+                }
             }
         }
 
-        Ok(())
+        // Devices only import what the manifest lists
+        for content in &self.contents {
+            let path = content.path.as_str();
+            if declared.contains(path) || path.ends_with('/') {
+                continue;
+            }
+            if is_unsafe_path(path) {
+                report.error(Some(path), "path points outside the package");
+            } else {
+                report.warning(
+                    Some(path),
+                    "not listed in the manifest, so devices will not import it",
+                );
+            }
+        }
+
+        for content in &self.contents {
+            if prefs::is_preference_file(&content.path) {
+                self.lint_preferences(&content.path, &mut report);
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Check that a preference file parses and its streams can connect
+    fn lint_preferences(&self, path: &str, report: &mut ValidationReport) {
+        let parsed = self.read_file_string(path).and_then(|xml| {
+            let problems = prefs::stream_problems(&xml)?;
+            Ok((problems, prefs::parse_stream_preferences(&xml)?))
+        });
+        let (problems, streams) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                report.error(Some(path), format!("unreadable preference file: {}", e));
+                return;
+            }
+        };
+        for problem in problems {
+            report.error(Some(path), problem);
+        }
+
+        for stream in &streams {
+            let files = [
+                ("trust store", &stream.ca_location),
+                ("client certificate", &stream.certificate_location),
+            ];
+            for (kind, location) in files {
+                if let Some(location) = location {
+                    if self.find_file(location).is_none() {
+                        report.warning(
+                            Some(path),
+                            format!(
+                                "{}: {} '{}' is not in the package",
+                                stream.description, kind, location
+                            ),
+                        );
+                    }
+                }
+            }
+
+            if stream.protocol == StreamProtocol::Ssl {
+                if stream.ca_location.is_none() {
+                    report.warning(
+                        Some(path),
+                        format!("{}: SSL stream without a trust store", stream.description),
+                    );
+                }
+                if stream.certificate_location.is_none() && !stream.enroll_for_certificate {
+                    report.warning(
+                        Some(path),
+                        format!(
+                            "{}: SSL stream without a client certificate or certificate enrollment",
+                            stream.description
+                        ),
+                    );
+                }
+            }
+        }
     }
 }
 
@@ -337,4 +525,95 @@ mod tests {
         let reader = DataPackageReader::open(&pkg_path).unwrap();
         assert!(reader.validate().is_ok());
     }
+
+    #[test]
+    fn test_stream_preferences_from_bytes() {
+        let prefs = r#"<preferences>
+  <preference version="1" name="cot_streams">
+    <entry key="count" class="class java.lang.Integer">1</entry>
+    <entry key="connectString0" class="class java.lang.String">tak.example.com:8089:ssl</entry>
+    <entry key="caLocation0" class="class java.lang.String">cert/truststore.p12</entry>
+  </preference>
+</preferences>"#;
+        let data = DataPackageBuilder::new("server.zip")
+            .add_bytes("certs/TrustStore.p12", vec![0; 4], ContentType::Certificate)
+            .unwrap()
+            .add_bytes(
+                "server.pref",
+                prefs.as_bytes().to_vec(),
+                ContentType::Preferences,
+            )
+            .unwrap()
+            .build_to_memory()
+            .unwrap();
+
+        let reader = DataPackageReader::from_bytes(data).unwrap();
+        let streams = reader.stream_preferences().unwrap();
+        assert_eq!(streams.len(), 1);
+        assert_eq!(streams[0].host, "tak.example.com");
+
+        let ca = streams[0].ca_location.as_deref().unwrap();
+        assert_eq!(reader.find_file(ca), Some("certs/TrustStore.p12"));
+        assert_eq!(reader.read_file(ca).ok(), None);
+        assert_eq!(
+            reader.read_file("certs/TrustStore.p12").unwrap(),
+            vec![0; 4]
+        );
+        assert_eq!(reader.find_file("cert/missing.p12"), None);
+    }
+
+    #[test]
+    fn test_lint_broken_package() {
+        let manifest = r#"<MissionPackageManifest version="2">
+  <Configuration>
+    <Parameter name="uid" value="broken"/>
+  </Configuration>
+  <Contents>
+    <Content ignore="false" zipEntry="event.cot">
+      <Parameter name="sha256" value="0000"/>
+    </Content>
+    <Content ignore="false" zipEntry="missing.kml"/>
+    <Content ignore="false" zipEntry="server.pref"/>
+  </Contents>
+</MissionPackageManifest>"#;
+        let prefs = r#"<preferences>
+  <preference version="1" name="cot_streams">
+    <entry key="count" class="class java.lang.Integer">2</entry>
+    <entry key="connectString0" class="class java.lang.String">tak.example.com:8089:ssl</entry>
+    <entry key="caLocation0" class="class java.lang.String">cert/truststore.p12</entry>
+    <entry key="connectString1" class="class java.lang.String">tak.example.com</entry>
+  </preference>
+</preferences>"#;
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in [
+            (MANIFEST_PATH, manifest),
+            ("event.cot", "<event/>"),
+            ("server.pref", prefs),
+            ("notes.txt", "unlisted"),
+        ] {
+            zip.start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        let data = zip.finish().unwrap().into_inner();
+
+        let reader = DataPackageReader::from_bytes(data).unwrap();
+        let report = reader.lint().unwrap();
+        let issues: Vec<String> = report.issues.iter().map(|i| i.to_string()).collect();
+        let has = |text: &str| issues.iter().any(|i| i.contains(text));
+
+        assert!(!report.is_valid());
+        assert!(has("missing required parameter 'name'"));
+        assert!(has("event.cot: sha256 is"));
+        assert!(has("missing.kml: listed in the manifest but missing"));
+        assert!(has("notes.txt: not listed in the manifest"));
+        assert!(has("stream 1 has invalid connectString 'tak.example.com'"));
+        assert!(has("trust store 'cert/truststore.p12' is not in"));
+        assert_eq!(report.errors().count(), 4);
+        assert!(matches!(
+            reader.validate(),
+            Err(DataPackageError::ValidationFailed(_))
+        ));
+    }
 }
@@ -0,0 +1,338 @@
+//! Content-addressed package storage
+//!
+//! Stores uploaded files and data packages under the SHA-256 hash of their
+//! contents, which is how TAK clients refer to them, with a JSON sidecar per
+//! file holding the upload metadata. The index is rebuilt from the sidecars
+//! when the store is opened. A quota, if set, caps the bytes stored.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use tracing::{debug, warn};
+
+use crate::error::{DataPackageError, Result};
+
+/// Metadata for a stored file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredPackage {
+    /// SHA-256 of the contents, lowercase hex
+    pub hash: String,
+    /// File name given by the uploader
+    pub name: String,
+    /// MIME type given by the uploader
+    pub mime_type: String,
+    /// Size in bytes
+    pub size: u64,
+    /// Search keywords
+    pub keywords: Vec<String>,
+    /// UID of the uploading device
+    pub creator_uid: Option<String>,
+    /// When the file was first stored
+    pub submitted_at: DateTime<Utc>,
+}
+
+/// Metadata supplied with an upload
+#[derive(Debug, Clone, Default)]
+pub struct PackageUpload {
+    pub name: String,
+    pub mime_type: String,
+    pub keywords: Vec<String>,
+    pub creator_uid: Option<String>,
+}
+
+/// Directory-backed package store
+pub struct PackageStore {
+    root: PathBuf,
+    index: RwLock<HashMap<String, StoredPackage>>,
+    /// Bytes the store may hold
+    quota_bytes: Option<u64>,
+    /// Bytes stored, including uploads being written
+    used_bytes: Mutex<u64>,
+}
+
+impl PackageStore {
+    /// Open (creating if needed) a store in `root`
+    pub fn open<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)?;
+
+        let mut index = HashMap::new();
+        for entry in fs::read_dir(&root)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let package: StoredPackage = match fs::read(&path)
+                .map_err(DataPackageError::from)
+                .and_then(|data| {
+                    serde_json::from_slice(&data)
+                        .map_err(|e| DataPackageError::ValidationFailed(e.to_string()))
+                }) {
+                Ok(package) => package,
+                Err(e) => {
+                    warn!(path = %path.display(), error = %e, "Skipping unreadable package metadata");
+                    continue;
+                }
+            };
+            if is_hash(&package.hash) && root.join(&package.hash).is_file() {
+                index.insert(package.hash.clone(), package);
+            }
+        }
+        let used_bytes = index.values().map(|p| p.size).sum();
+        debug!(root = %root.display(), packages = index.len(), used_bytes, "Opened package store");
+
+        Ok(Self {
+            root,
+            index: RwLock::new(index),
+            quota_bytes: None,
+            used_bytes: Mutex::new(used_bytes),
+        })
+    }
+
+    /// Refuse uploads that would take the store past `bytes`
+    pub fn with_quota(mut self, bytes: u64) -> Self {
+        self.quota_bytes = Some(bytes);
+        self
+    }
+
+    /// Bytes stored
+    pub fn used_bytes(&self) -> u64 {
+        *self.used_bytes.lock().unwrap()
+    }
+
+    /// Store `data`. Uploading contents that are already stored keeps the
+    /// original metadata.
+    pub fn put(&self, data: &[u8], upload: PackageUpload) -> Result<StoredPackage> {
+        let hash = hash_hex(data);
+        self.insert(hash, data.len() as u64, upload, |path| {
+            write_atomic(path, data)
+        })
+    }
+
+    /// Store the file at `path`, moving it into the store, without reading
+    /// it into memory. As with [`put`](Self::put), contents that are
+    /// already stored keep the original metadata; `path` is then left as is.
+    pub fn put_file(&self, path: &Path, upload: PackageUpload) -> Result<StoredPackage> {
+        let mut hasher = Sha256::new();
+        let size = std::io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+        let hash = hex(&hasher.finalize());
+        self.insert(hash, size, upload, |target| {
+            let tmp = target.with_extension("tmp");
+            // Uploads are usually staged on another filesystem
+            if fs::rename(path, &tmp).is_err() {
+                fs::copy(path, &tmp)?;
+            }
+            fs::rename(&tmp, target)?;
+            Ok(())
+        })
+    }
+
+    fn insert(
+        &self,
+        hash: String,
+        size: u64,
+        upload: PackageUpload,
+        write_contents: impl FnOnce(&Path) -> Result<()>,
+    ) -> Result<StoredPackage> {
+        if let Some(existing) = self.get(&hash) {
+            return Ok(existing);
+        }
+
+        let package = StoredPackage {
+            hash: hash.clone(),
+            name: upload.name,
+            mime_type: upload.mime_type,
+            size,
+            keywords: upload.keywords,
+            creator_uid: upload.creator_uid,
+            submitted_at: Utc::now(),
+        };
+        let metadata = serde_json::to_vec_pretty(&package)
+            .map_err(|e| DataPackageError::ValidationFailed(e.to_string()))?;
+
+        // Reserve the space before writing, so concurrent uploads can't
+        // overrun the quota together
+        {
+            let mut used = self.used_bytes.lock().unwrap();
+            if let Some(limit) = self.quota_bytes.filter(|&limit| *used + size > limit) {
+                return Err(DataPackageError::QuotaExceeded {
+                    current: *used + size,
+                    limit,
+                });
+            }
+            *used += size;
+        }
+
+        // Contents first, so a sidecar never points at a missing file
+        let written = write_contents(&self.root.join(&hash))
+            .and_then(|()| write_atomic(&self.root.join(format!("{}.json", hash)), &metadata));
+        if let Err(e) = written {
+            *self.used_bytes.lock().unwrap() -= size;
+            return Err(e);
+        }
+
+        // The same contents stored concurrently are only counted once
+        if self
+            .index
+            .write()
+            .unwrap()
+            .insert(hash, package.clone())
+            .is_some()
+        {
+            *self.used_bytes.lock().unwrap() -= size;
+        }
+        Ok(package)
+    }
+
+    /// Metadata for `hash`
+    pub fn get(&self, hash: &str) -> Option<StoredPackage> {
+        self.index
+            .read()
+            .unwrap()
+            .get(&hash.to_lowercase())
+            .cloned()
+    }
+
+    /// Contents of `hash`
+    pub fn read(&self, hash: &str) -> Result<Vec<u8>> {
+        let hash = hash.to_lowercase();
+        if !is_hash(&hash) || self.get(&hash).is_none() {
+            return Err(DataPackageError::MissingFile(hash));
+        }
+        Ok(fs::read(self.root.join(hash))?)
+    }
+
+    /// Packages carrying every keyword (case-insensitive), newest first
+    pub fn search(&self, keywords: &[&str]) -> Vec<StoredPackage> {
+        let mut results: Vec<StoredPackage> = self
+            .index
+            .read()
+            .unwrap()
+            .values()
+            .filter(|p| {
+                keywords
+                    .iter()
+                    .all(|k| p.keywords.iter().any(|pk| pk.eq_ignore_ascii_case(k)))
+            })
+            .cloned()
+            .collect();
+        results.sort_by_key(|p| std::cmp::Reverse(p.submitted_at));
+        results
+    }
+}
+
+/// SHA-256 of `data` as lowercase hex
+pub fn hash_hex(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Whether `s` looks like a hash this store issues (also keeps request
+/// parameters from escaping the store directory)
+fn is_hash(s: &str) -> bool {
+    s.len() == 64 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, data)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PackageStore::open(dir.path()).unwrap();
+
+        let package = store
+            .put(
+                b"package contents",
+                PackageUpload {
+                    name: "route.zip".to_string(),
+                    mime_type: "application/x-zip-compressed".to_string(),
+                    keywords: vec!["missionpackage".to_string()],
+                    creator_uid: Some("ANDROID-1".to_string()),
+                },
+            )
+            .unwrap();
+        assert_eq!(package.hash, hash_hex(b"package contents"));
+        assert_eq!(package.size, 16);
+
+        // Same contents again keep the first upload's metadata
+        let again = store
+            .put(b"package contents", PackageUpload::default())
+            .unwrap();
+        assert_eq!(again.name, "route.zip");
+
+        assert_eq!(store.search(&["MissionPackage"]).len(), 1);
+        assert!(store.search(&["missionpackage", "other"]).is_empty());
+        assert!(store.read("../../etc/passwd").is_err());
+
+        // Survives a restart
+        let store = PackageStore::open(dir.path()).unwrap();
+        assert_eq!(
+            store.read(&package.hash.to_uppercase()).unwrap(),
+            b"package contents"
+        );
+        assert_eq!(store.get(&package.hash).unwrap().name, "route.zip");
+    }
+
+    #[test]
+    fn test_put_file_moves_upload() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PackageStore::open(dir.path().join("store")).unwrap();
+        let upload = dir.path().join("upload");
+        fs::write(&upload, b"package contents").unwrap();
+
+        let package = store
+            .put_file(
+                &upload,
+                PackageUpload {
+                    name: "route.zip".to_string(),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(package.hash, hash_hex(b"package contents"));
+        assert_eq!(package.size, 16);
+        assert!(!upload.exists());
+        assert_eq!(store.read(&package.hash).unwrap(), b"package contents");
+    }
+
+    #[test]
+    fn test_quota() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PackageStore::open(dir.path()).unwrap().with_quota(20);
+
+        store.put(b"0123456789", PackageUpload::default()).unwrap();
+        // Stored contents don't count twice
+        store.put(b"0123456789", PackageUpload::default()).unwrap();
+        assert_eq!(store.used_bytes(), 10);
+
+        assert!(matches!(
+            store.put(b"too much for the rest", PackageUpload::default()),
+            Err(DataPackageError::QuotaExceeded {
+                current: 31,
+                limit: 20
+            })
+        ));
+        store.put(b"abcdefghij", PackageUpload::default()).unwrap();
+        assert_eq!(store.used_bytes(), 20);
+
+        // Usage is counted again on open
+        let store = PackageStore::open(dir.path()).unwrap();
+        assert_eq!(store.used_bytes(), 20);
+    }
+}
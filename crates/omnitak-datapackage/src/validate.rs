@@ -0,0 +1,105 @@
+//! Data package linting
+//!
+//! ATAK imports a broken package without complaint and simply leaves out
+//! what it cannot use, so problems are only noticed in the field. The
+//! checks here find them beforehand: manifest entries that point nowhere,
+//! content whose `sha256` parameter does not match, missing package
+//! parameters, and preference files that will not configure a connection.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// How serious a validation issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The package will not import as intended
+    Error,
+    /// The package imports, but probably not as the author meant
+    Warning,
+}
+
+/// One problem found in a package
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    /// Archive entry the issue concerns, if any
+    pub path: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}: {}", path, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Result of linting a package
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Whether the package has no errors (warnings are allowed)
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|i| i.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == Severity::Warning)
+    }
+
+    pub(crate) fn error(&mut self, path: Option<&str>, message: impl Into<String>) {
+        self.push(Severity::Error, path, message);
+    }
+
+    pub(crate) fn warning(&mut self, path: Option<&str>, message: impl Into<String>) {
+        self.push(Severity::Warning, path, message);
+    }
+
+    fn push(&mut self, severity: Severity, path: Option<&str>, message: impl Into<String>) {
+        self.issues.push(ValidationIssue {
+            severity,
+            path: path.map(str::to_string),
+            message: message.into(),
+        });
+    }
+}
+
+/// Whether an archive entry name could escape the extraction directory
+pub(crate) fn is_unsafe_path(zip_entry: &str) -> bool {
+    zip_entry.starts_with(['/', '\\'])
+        || zip_entry.contains(':')
+        || zip_entry.split(['/', '\\']).any(|part| part == "..")
+}
+
+/// SHA-256 of `data`, lowercase hex
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsafe_paths() {
+        assert!(is_unsafe_path("../etc/passwd"));
+        assert!(is_unsafe_path("cert/../../x.p12"));
+        assert!(is_unsafe_path("/sdcard/atak/x.cot"));
+        assert!(is_unsafe_path("C:\\temp\\x.cot"));
+        assert!(!is_unsafe_path("cert/truststore.p12"));
+        assert!(!is_unsafe_path("a..b/event.cot"));
+    }
+}
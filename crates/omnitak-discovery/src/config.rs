@@ -2,4 +2,6 @@
 //!
 //! Re-exports configuration from omnitak-core to avoid circular dependencies
 
-pub use omnitak_core::discovery_config::{DiscoveryConfig, ServiceType};
+pub use omnitak_core::discovery_config::{
+    AnnouncementConfig, DiscoveryConfig, MulticastSaConfig, ScanConfig, ServiceType,
+};
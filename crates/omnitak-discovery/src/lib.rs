@@ -3,7 +3,11 @@
 //! This crate provides automatic network discovery capabilities for TAK infrastructure:
 //! - Discover TAK servers advertising via mDNS
 //! - Discover ATAK devices on the local network
-//! - Announce the OmniTAK aggregator as a discoverable service
+//! - Optionally scan configured subnets for TAK servers that don't use mDNS
+//! - Optionally find ATAK devices from the self-SA they send to the mesh
+//!   multicast group
+//! - Announce the OmniTAK aggregator as a discoverable service, with protocol,
+//!   port, TLS requirement and version in its TXT records
 //! - Support for RFC 6762 (Multicast DNS) and RFC 6763 (DNS-SD)
 //!
 //! # Architecture
@@ -42,10 +46,16 @@
 
 pub mod config;
 pub mod error;
+pub mod scanner;
 pub mod service;
+pub mod sniffer;
 pub mod types;
 
-pub use config::{DiscoveryConfig, ServiceType};
+pub use config::{
+    AnnouncementConfig, DiscoveryConfig, MulticastSaConfig, ScanConfig, ServiceType,
+};
 pub use error::{DiscoveryError, Result};
 pub use service::DiscoveryService;
-pub use types::{DiscoveredService, ServiceStatus};
+pub use types::{
+    AnnouncedService, DiscoveredService, ServiceEvent, ServiceEventType, ServiceStatus,
+};
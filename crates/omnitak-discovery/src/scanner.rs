@@ -0,0 +1,259 @@
+//! Active subnet scanning for TAK servers
+//!
+//! Finds servers that don't announce themselves via mDNS by connecting to
+//! the configured ports on every address in the configured ranges. A port
+//! that accepts the connection is fingerprinted:
+//!
+//! 1. Anything the server sends first is read; CoT servers may open with a
+//!    TAK protocol negotiation event (`<?xml` / `<event`)
+//! 2. Otherwise a TLS ClientHello is sent; a TLS handshake or alert record
+//!    in reply means TLS, an `HTTP/` reply means plain HTTP, and silence or
+//!    a closed connection means a plain TCP stream
+//!
+//! Probes are paced to `max_probes_per_sec` so a sweep never floods the
+//! network.
+
+use crate::config::{ScanConfig, ServiceType};
+use crate::types::DiscoveredService;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, MissedTickBehavior};
+use tracing::debug;
+
+/// Probes allowed in flight at once, whatever the rate
+const MAX_CONCURRENT_PROBES: usize = 64;
+
+/// Transport a scanned port speaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fingerprint {
+    /// Plain TCP; `cot` if the server opened with CoT XML
+    Tcp { cot: bool },
+    /// TLS
+    Tls,
+    /// Plain HTTP
+    Http,
+}
+
+impl Fingerprint {
+    /// Value of the `protocol` property of the registry entry
+    pub fn protocol(&self) -> &'static str {
+        match self {
+            Fingerprint::Tcp { .. } => "tcp",
+            Fingerprint::Tls => "tls",
+            Fingerprint::Http => "http",
+        }
+    }
+}
+
+/// An open port found by a sweep
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanHit {
+    pub addr: SocketAddr,
+    pub fingerprint: Fingerprint,
+}
+
+impl ScanHit {
+    /// Registry ID, stable across sweeps
+    pub fn key(&self) -> String {
+        format!("scan:{}", self.addr)
+    }
+
+    /// Registry entry for the hit, recognisable by its `source=scan`
+    /// property
+    pub fn to_service(&self) -> DiscoveredService {
+        let mut properties = HashMap::new();
+        properties.insert("source".to_string(), "scan".to_string());
+        properties.insert(
+            "protocol".to_string(),
+            self.fingerprint.protocol().to_string(),
+        );
+        properties.insert(
+            "tls".to_string(),
+            (self.fingerprint == Fingerprint::Tls).to_string(),
+        );
+        if let Fingerprint::Tcp { cot: true } = self.fingerprint {
+            properties.insert("cot".to_string(), "true".to_string());
+        }
+
+        DiscoveredService::new(
+            ServiceType::TakServer,
+            self.key(),
+            self.addr.ip().to_string(),
+            vec![self.addr.ip()],
+            self.addr.port(),
+            properties,
+        )
+    }
+}
+
+/// Probes every target of `config` once, returning the open ports found
+pub async fn sweep(config: &ScanConfig) -> Vec<ScanHit> {
+    let targets = match config.targets() {
+        Ok(targets) => targets,
+        Err(e) => {
+            debug!(error = %e, "Skipping scan");
+            return Vec::new();
+        }
+    };
+
+    let probe_timeout = config.probe_timeout();
+    let mut pacing = tokio::time::interval(Duration::from_secs_f64(
+        1.0 / config.max_probes_per_sec.max(1) as f64,
+    ));
+    pacing.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let permits = std::sync::Arc::new(tokio::sync::Semaphore::new(MAX_CONCURRENT_PROBES));
+
+    let mut probes = tokio::task::JoinSet::new();
+    for ip in targets {
+        for &port in &config.ports {
+            pacing.tick().await;
+            let Ok(permit) = permits.clone().acquire_owned().await else {
+                break;
+            };
+            let addr = SocketAddr::new(IpAddr::V4(ip), port);
+            probes.spawn(async move {
+                let fingerprint = probe(addr, probe_timeout).await;
+                drop(permit);
+                fingerprint.map(|fingerprint| ScanHit { addr, fingerprint })
+            });
+        }
+    }
+
+    let mut hits = Vec::new();
+    while let Some(result) = probes.join_next().await {
+        if let Ok(Some(hit)) = result {
+            hits.push(hit);
+        }
+    }
+    hits.sort_by_key(|hit| hit.addr);
+    hits
+}
+
+/// Connects to `addr` and fingerprints it; `None` if nothing listens
+pub async fn probe(addr: SocketAddr, probe_timeout: Duration) -> Option<Fingerprint> {
+    let mut stream = timeout(probe_timeout, TcpStream::connect(addr))
+        .await
+        .ok()?
+        .ok()?;
+
+    let mut buf = [0u8; 16];
+    // Server speaks first: only plain-text protocols do that
+    if let Ok(Ok(n)) = timeout(probe_timeout, stream.read(&mut buf)).await {
+        if n == 0 {
+            return Some(Fingerprint::Tcp { cot: false });
+        }
+        return Some(classify(&buf[..n]).unwrap_or(Fingerprint::Tcp { cot: false }));
+    }
+
+    if stream.write_all(&client_hello()).await.is_err() {
+        return Some(Fingerprint::Tcp { cot: false });
+    }
+    let fingerprint = match timeout(probe_timeout, stream.read(&mut buf)).await {
+        Ok(Ok(n)) if n > 0 => classify(&buf[..n]).unwrap_or(Fingerprint::Tcp { cot: false }),
+        _ => Fingerprint::Tcp { cot: false },
+    };
+    let _ = stream.shutdown().await;
+    Some(fingerprint)
+}
+
+/// Recognises the first bytes a server sent
+fn classify(data: &[u8]) -> Option<Fingerprint> {
+    match data {
+        // TLS record header: handshake (22) or alert (21), version 3.x
+        [0x16 | 0x15, 0x03, ..] => Some(Fingerprint::Tls),
+        _ if data.starts_with(b"HTTP/") => Some(Fingerprint::Http),
+        _ if data.trim_ascii_start().starts_with(b"<") => Some(Fingerprint::Tcp { cot: true }),
+        _ => None,
+    }
+}
+
+/// A minimal TLS 1.2 ClientHello. Enough for any TLS server to answer with
+/// a ServerHello or an alert
+fn client_hello() -> Vec<u8> {
+    const CIPHER_SUITES: [u16; 6] = [0xc02f, 0xc02b, 0xc030, 0xc02c, 0x009c, 0x002f];
+
+    let mut body = Vec::with_capacity(64);
+    body.extend_from_slice(&[0x03, 0x03]); // TLS 1.2
+    body.extend_from_slice(&[0x4f; 32]); // client random
+    body.push(0); // no session ID
+    body.extend_from_slice(&((CIPHER_SUITES.len() * 2) as u16).to_be_bytes());
+    for suite in CIPHER_SUITES {
+        body.extend_from_slice(&suite.to_be_bytes());
+    }
+    body.extend_from_slice(&[0x01, 0x00]); // null compression
+    body.extend_from_slice(&[0x00, 0x00]); // no extensions
+
+    let mut handshake = vec![0x01]; // ClientHello
+    handshake.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+    handshake.extend_from_slice(&body);
+
+    let mut record = vec![0x16, 0x03, 0x01];
+    record.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+    record.extend_from_slice(&handshake);
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    const TIMEOUT: Duration = Duration::from_millis(300);
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(&[0x16, 0x03, 0x03, 0x00]), Some(Fingerprint::Tls));
+        assert_eq!(classify(&[0x15, 0x03, 0x01, 0x00]), Some(Fingerprint::Tls));
+        assert_eq!(classify(b"HTTP/1.1 400"), Some(Fingerprint::Http));
+        assert_eq!(
+            classify(b"\n<?xml vers"),
+            Some(Fingerprint::Tcp { cot: true })
+        );
+        assert_eq!(classify(b"SSH-2.0"), None);
+
+        let hello = client_hello();
+        assert_eq!(hello[..3], [0x16, 0x03, 0x01]);
+        assert_eq!(
+            u16::from_be_bytes([hello[3], hello[4]]) as usize,
+            hello.len() - 5
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe() {
+        // A silent server that answers the ClientHello with an alert
+        let tls = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let tls_addr = tls.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = tls.accept().await.unwrap();
+            let mut buf = [0u8; 512];
+            let _ = socket.read(&mut buf).await;
+            let _ = socket
+                .write_all(&[0x15, 0x03, 0x03, 0x00, 0x02, 0x02, 0x28])
+                .await;
+        });
+
+        // A server that opens with CoT
+        let cot = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let cot_addr = cot.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = cot.accept().await.unwrap();
+            let _ = socket.write_all(b"<?xml version=\"1.0\"?><event/>").await;
+            tokio::time::sleep(TIMEOUT).await;
+        });
+
+        assert_eq!(probe(tls_addr, TIMEOUT).await, Some(Fingerprint::Tls));
+        assert_eq!(
+            probe(cot_addr, TIMEOUT).await,
+            Some(Fingerprint::Tcp { cot: true })
+        );
+
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed_addr = closed.local_addr().unwrap();
+        drop(closed);
+        assert_eq!(probe(closed_addr, TIMEOUT).await, None);
+    }
+}
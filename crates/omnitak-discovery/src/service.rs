@@ -2,14 +2,19 @@
 
 use crate::config::{DiscoveryConfig, ServiceType};
 use crate::error::{DiscoveryError, Result};
-use crate::types::{DiscoveredService, ServiceEvent, ServiceEventType, ServiceStatus};
+use crate::scanner;
+use crate::sniffer;
+use crate::types::{
+    AnnouncedService, DiscoveredService, ServiceEvent, ServiceEventType, ServiceStatus,
+};
 use async_channel::{Receiver, Sender};
 use dashmap::DashMap;
 use mdns_sd::{ServiceDaemon, ServiceEvent as MdnsEvent, ServiceInfo};
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
 use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 
@@ -38,6 +43,12 @@ pub struct DiscoveryService {
 
     /// Background task handles
     tasks: Arc<DashMap<String, JoinHandle<()>>>,
+
+    /// Services currently announced via mDNS
+    announced: Mutex<Vec<AnnouncedService>>,
+
+    /// Wakes the subnet scanner for an immediate sweep
+    scan_trigger: Arc<Notify>,
 }
 
 impl DiscoveryService {
@@ -70,6 +81,8 @@ impl DiscoveryService {
             event_rx,
             running: Arc::new(AtomicBool::new(false)),
             tasks: Arc::new(DashMap::new()),
+            announced: Mutex::new(Vec::new()),
+            scan_trigger: Arc::new(Notify::new()),
         })
     }
 
@@ -94,7 +107,17 @@ impl DiscoveryService {
 
         // Start announcement if enabled
         if self.config.announce_enabled {
-            self.start_announcement().await?;
+            self.start_announcement()?;
+        }
+
+        // Start subnet scanning if enabled
+        if self.config.scan.enabled {
+            self.start_scanner();
+        }
+
+        // Start listening for multicast SA if enabled
+        if self.config.multicast_sa.enabled {
+            self.start_sa_listener()?;
         }
 
         // Start cleanup task
@@ -121,6 +144,9 @@ impl DiscoveryService {
             }
         }
 
+        // Say goodbye so peers drop us right away
+        self.stop_announcement();
+
         // Shutdown mDNS daemon
         self.mdns.shutdown().map_err(|e| {
             DiscoveryError::Internal(format!("Failed to shutdown mDNS daemon: {}", e))
@@ -160,6 +186,32 @@ impl DiscoveryService {
             .map(|entry| entry.value().clone())
     }
 
+    /// Returns whether this instance is being announced
+    pub fn is_announcing(&self) -> bool {
+        !self.announced.lock().unwrap().is_empty()
+    }
+
+    /// Gets the services this instance announces
+    pub fn announced_services(&self) -> Vec<AnnouncedService> {
+        self.announced.lock().unwrap().clone()
+    }
+
+    /// Starts or stops announcing this instance while the service runs
+    pub fn set_announcing(&self, enabled: bool) -> Result<()> {
+        if !self.running.load(Ordering::SeqCst) {
+            return Err(DiscoveryError::NotStarted);
+        }
+
+        if enabled {
+            if !self.is_announcing() {
+                self.start_announcement()?;
+            }
+        } else {
+            self.stop_announcement();
+        }
+        Ok(())
+    }
+
     /// Returns the event receiver for external consumers
     pub fn event_receiver(&self) -> Receiver<ServiceEvent> {
         self.event_rx.clone()
@@ -173,8 +225,11 @@ impl DiscoveryService {
 
         info!("Manually refreshing service discovery");
 
-        // The mdns-sd library handles ongoing browsing automatically
-        // This is a no-op but could be extended to force re-query
+        // The mdns-sd library handles ongoing browsing automatically; only
+        // the subnet scanner needs a nudge
+        if self.config.scan.enabled {
+            self.scan_trigger.notify_one();
+        }
         Ok(())
     }
 
@@ -302,51 +357,206 @@ impl DiscoveryService {
         )
     }
 
+    /// Instance name to announce under
+    fn instance_name(&self) -> String {
+        self.config.instance_name.clone().unwrap_or_else(|| {
+            let host = hostname::get()
+                .map(|h| h.to_string_lossy().into_owned())
+                .unwrap_or_else(|_| "unknown".to_string());
+            format!("OmniTAK-{}", host)
+        })
+    }
+
     /// Starts announcing this aggregator as a discoverable service
-    async fn start_announcement(&self) -> Result<()> {
-        let instance_name = self
-            .config
-            .instance_name
-            .clone()
-            .unwrap_or_else(|| format!("OmniTAK-{}", hostname::get().unwrap().to_string_lossy()));
+    fn start_announcement(&self) -> Result<()> {
+        let instance_name = self.instance_name();
+        let host_name = match hostname::get() {
+            Ok(host) => format!("{}.local.", host.to_string_lossy()),
+            Err(_) => format!("{}.local.", instance_name.replace(' ', "-")),
+        };
+
+        let mut announced = self.announced.lock().unwrap();
+        for announcement in self.config.effective_announcements() {
+            let service_type = announcement.to_service_string();
+            let properties = announcement.txt_properties(env!("CARGO_PKG_VERSION"));
+
+            info!(
+                instance = instance_name,
+                service_type = service_type,
+                port = announcement.port,
+                "Starting service announcement"
+            );
+
+            let register_failed = |e: mdns_sd::Error| DiscoveryError::RegisterFailed {
+                service_name: format!("{}.{}", instance_name, service_type),
+                reason: e.to_string(),
+            };
+            let service_info = ServiceInfo::new(
+                &service_type,
+                &instance_name,
+                &host_name,
+                "",
+                announcement.port,
+                properties.clone(),
+            )
+            .map_err(register_failed)?
+            .enable_addr_auto();
+            let fullname = service_info.get_fullname().to_string();
+
+            // Register the service
+            self.mdns.register(service_info).map_err(register_failed)?;
+
+            announced.push(AnnouncedService {
+                fullname,
+                service_type,
+                instance_name: instance_name.clone(),
+                port: announcement.port,
+                properties,
+            });
+        }
+
+        info!(count = announced.len(), "Service announcement registered successfully");
+        Ok(())
+    }
+
+    /// Withdraws all announcements
+    fn stop_announcement(&self) {
+        let mut announced = self.announced.lock().unwrap();
+        for service in announced.drain(..) {
+            match self.mdns.unregister(&service.fullname) {
+                Ok(_) => info!(service = service.fullname, "Service announcement withdrawn"),
+                Err(e) => warn!(service = service.fullname, error = %e, "Failed to withdraw announcement"),
+            }
+        }
+    }
 
-        let service_type = "_tak-aggregator._tcp.local.";
-        let port = self.config.announce_port;
+    /// Starts the subnet scanner, sweeping every scan interval or on
+    /// refresh and adding what it finds to the registry
+    fn start_scanner(&self) {
+        let scan = self.config.scan.clone();
+        let services = self.services.clone();
+        let event_tx = self.event_tx.clone();
+        let running = self.running.clone();
+        let trigger = self.scan_trigger.clone();
 
         info!(
-            instance = instance_name,
-            port = port,
-            "Starting service announcement"
+            ranges = ?scan.ranges,
+            ports = ?scan.ports,
+            rate = scan.max_probes_per_sec,
+            "Starting subnet scanner"
         );
 
-        // Create service info with metadata
-        let mut properties = HashMap::new();
-        properties.insert("version".to_string(), env!("CARGO_PKG_VERSION").to_string());
-        properties.insert("description".to_string(), "OmniTAK Server Aggregator".to_string());
-        properties.insert("api".to_string(), "rest+websocket".to_string());
+        let task = tokio::spawn(async move {
+            while running.load(Ordering::SeqCst) {
+                let hits = scanner::sweep(&scan).await;
+                debug!(found = hits.len(), "Subnet sweep finished");
+
+                for hit in hits {
+                    let key = hit.key();
+                    let found = hit.to_service();
+                    let (event_type, service) =
+                        if let Some(mut existing) = services.get_mut(&key) {
+                            existing.properties = found.properties;
+                            existing.mark_seen();
+                            (ServiceEventType::Updated, existing.clone())
+                        } else {
+                            info!(
+                                address = %hit.addr,
+                                protocol = hit.fingerprint.protocol(),
+                                "Found TAK server by scanning"
+                            );
+                            services.insert(key, found.clone());
+                            (ServiceEventType::Discovered, found)
+                        };
+
+                    if let Err(e) = event_tx.send(ServiceEvent::new(event_type, service)).await {
+                        warn!(error = %e, "Failed to send service event");
+                    }
+                }
 
-        let service_info = ServiceInfo::new(
-            service_type,
-            &instance_name,
-            &instance_name,
-            "",
-            port,
-            properties,
-        )
-        .map_err(|e| DiscoveryError::RegisterFailed {
-            service_name: instance_name.clone(),
-            reason: e.to_string(),
+                tokio::select! {
+                    _ = tokio::time::sleep(scan.interval()) => {}
+                    _ = trigger.notified() => debug!("Subnet sweep requested"),
+                }
+            }
+
+            debug!("Scanner task stopped");
+        });
+
+        self.tasks.insert("scanner".to_string(), task);
+    }
+
+    /// Starts listening on the mesh SA multicast group, registering every
+    /// device heard sending its self-SA
+    fn start_sa_listener(&self) -> Result<()> {
+        let socket = sniffer::bind(&self.config.multicast_sa).map_err(|e| {
+            DiscoveryError::Internal(format!(
+                "Failed to join multicast SA group {}: {}",
+                self.config.multicast_sa.group, e
+            ))
         })?;
 
-        // Register the service
-        self.mdns.register(service_info).map_err(|e| {
-            DiscoveryError::RegisterFailed {
-                service_name: instance_name.clone(),
-                reason: e.to_string(),
+        info!(
+            group = self.config.multicast_sa.group,
+            "Listening for multicast SA"
+        );
+
+        let services = self.services.clone();
+        let event_tx = self.event_tx.clone();
+        let running = self.running.clone();
+
+        let task = tokio::spawn(async move {
+            let mut buf = vec![0u8; sniffer::MAX_DATAGRAM_SIZE];
+            while running.load(Ordering::SeqCst) {
+                let (len, source) = match socket.recv_from(&mut buf).await {
+                    Ok(received) => received,
+                    Err(e) => {
+                        warn!(error = %e, "Error receiving multicast SA");
+                        continue;
+                    }
+                };
+                let Some(sa) = sniffer::parse_self_sa(&buf[..len]) else {
+                    continue;
+                };
+
+                let found = sa.to_service(source.ip());
+                let event = if let Some(mut existing) = services.get_mut(&sa.key()) {
+                    let changed = existing.instance_name != found.instance_name
+                        || existing.addresses != found.addresses
+                        || existing.port != found.port
+                        || existing.properties != found.properties;
+                    let was_alive = existing.is_alive();
+                    existing.instance_name = found.instance_name;
+                    existing.hostname = found.hostname;
+                    existing.addresses = found.addresses;
+                    existing.port = found.port;
+                    existing.properties = found.properties;
+                    existing.mark_seen();
+                    // Devices report every few seconds; only changes are news
+                    (changed || !was_alive)
+                        .then(|| ServiceEvent::new(ServiceEventType::Updated, existing.clone()))
+                } else {
+                    info!(
+                        uid = sa.uid,
+                        callsign = sa.callsign,
+                        address = %source.ip(),
+                        "Found ATAK device by its SA"
+                    );
+                    services.insert(sa.key(), found.clone());
+                    Some(ServiceEvent::new(ServiceEventType::Discovered, found))
+                };
+
+                if let Some(event) = event {
+                    if let Err(e) = event_tx.send(event).await {
+                        warn!(error = %e, "Failed to send service event");
+                    }
+                }
             }
-        })?;
 
-        info!("Service announcement registered successfully");
+            debug!("Multicast SA listener stopped");
+        });
+
+        self.tasks.insert("multicast_sa".to_string(), task);
         Ok(())
     }
 
@@ -0,0 +1,216 @@
+//! Passive ATAK device discovery from multicast SA
+//!
+//! ATAK devices on a mesh network broadcast their own position report
+//! (self-SA) to a multicast group, `239.2.3.1:6969` by default, whether or
+//! not they advertise via mDNS. Listening on the group finds them: every
+//! self-SA carries the device UID and callsign, and the packet's source
+//! address is the device's IP.
+//!
+//! Only XML CoT is understood; devices sending TAK protocol (protobuf) mesh
+//! traffic are not picked up.
+
+use crate::config::{MulticastSaConfig, ServiceType};
+use crate::error::{DiscoveryError, Result};
+use crate::types::DiscoveredService;
+use quick_xml::events::{BytesStart, Event as XmlEvent};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use tokio::net::UdpSocket;
+
+/// Largest datagram read from the group
+pub const MAX_DATAGRAM_SIZE: usize = 65536;
+
+/// A device's self-SA
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfSa {
+    pub uid: String,
+    pub callsign: String,
+    pub cot_type: String,
+    /// Where the device accepts direct connections, e.g.
+    /// `192.168.1.20:4242:tcp`
+    pub endpoint: Option<String>,
+    /// Team color
+    pub team: Option<String>,
+    pub role: Option<String>,
+    /// Client software, e.g. "ATAK-CIV"
+    pub platform: Option<String>,
+    pub version: Option<String>,
+}
+
+impl SelfSa {
+    /// Registry ID, stable across reports
+    pub fn key(&self) -> String {
+        format!("sa:{}", self.uid)
+    }
+
+    /// Registry entry for a device heard from `source`
+    pub fn to_service(&self, source: IpAddr) -> DiscoveredService {
+        let mut properties = HashMap::new();
+        properties.insert("source".to_string(), "multicast-sa".to_string());
+        properties.insert("uid".to_string(), self.uid.clone());
+        properties.insert("callsign".to_string(), self.callsign.clone());
+        properties.insert("cotType".to_string(), self.cot_type.clone());
+        let optional = [
+            ("endpoint", &self.endpoint),
+            ("team", &self.team),
+            ("role", &self.role),
+            ("platform", &self.platform),
+            ("version", &self.version),
+        ];
+        for (key, value) in optional {
+            if let Some(value) = value {
+                properties.insert(key.to_string(), value.clone());
+            }
+        }
+
+        DiscoveredService::new(
+            ServiceType::AtakDevice,
+            self.callsign.clone(),
+            source.to_string(),
+            vec![source],
+            self.endpoint_port().unwrap_or(0),
+            properties,
+        )
+    }
+
+    /// Port of the contact endpoint, if the device listens on one
+    fn endpoint_port(&self) -> Option<u16> {
+        self.endpoint
+            .as_deref()?
+            .split(':')
+            .nth(1)?
+            .parse()
+            .ok()
+            .filter(|&port| port > 0)
+    }
+}
+
+/// Parses a datagram as a self-SA: an atom (`a-...`) event with a contact
+/// callsign and the `takv` or `__group` detail only clients add to their
+/// own position. Anything else, including markers placed by a user, is
+/// `None`
+pub fn parse_self_sa(data: &[u8]) -> Option<SelfSa> {
+    if !data.trim_ascii_start().starts_with(b"<") {
+        return None;
+    }
+
+    let mut sa = SelfSa {
+        uid: String::new(),
+        callsign: String::new(),
+        cot_type: String::new(),
+        endpoint: None,
+        team: None,
+        role: None,
+        platform: None,
+        version: None,
+    };
+    let mut is_client = false;
+
+    let mut reader = Reader::from_reader(data);
+    let mut buf = Vec::new();
+    loop {
+        let element = match reader.read_event_into(&mut buf).ok()? {
+            XmlEvent::Start(e) | XmlEvent::Empty(e) => e,
+            XmlEvent::Eof => break,
+            _ => continue,
+        };
+        match element.name().as_ref() {
+            b"event" => {
+                sa.uid = attr(&element, "uid")?;
+                sa.cot_type = attr(&element, "type")?;
+            }
+            b"contact" => {
+                sa.callsign = attr(&element, "callsign").unwrap_or_default();
+                sa.endpoint = attr(&element, "endpoint");
+            }
+            b"__group" => {
+                is_client = true;
+                sa.team = attr(&element, "name");
+                sa.role = attr(&element, "role");
+            }
+            b"takv" => {
+                is_client = true;
+                sa.platform = attr(&element, "platform");
+                sa.version = attr(&element, "version");
+            }
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    let valid = is_client
+        && sa.cot_type.starts_with("a-")
+        && !sa.uid.is_empty()
+        && !sa.callsign.is_empty();
+    valid.then_some(sa)
+}
+
+fn attr(element: &BytesStart<'_>, name: &str) -> Option<String> {
+    element
+        .try_get_attribute(name)
+        .ok()
+        .flatten()
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.into_owned())
+}
+
+/// Binds a socket to the SA group. The port is shared so OmniTAK's own
+/// multicast connections and other listeners on the host keep working
+pub fn bind(config: &MulticastSaConfig) -> Result<UdpSocket> {
+    let group = config.group_addr().map_err(DiscoveryError::InvalidConfig)?;
+    let interface = config.interface.unwrap_or(Ipv4Addr::UNSPECIFIED);
+
+    let socket = socket2::Socket::new(
+        socket2::Domain::IPV4,
+        socket2::Type::DGRAM,
+        Some(socket2::Protocol::UDP),
+    )?;
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), group.port()).into())?;
+    socket.join_multicast_v4(group.ip(), &interface)?;
+
+    Ok(UdpSocket::from_std(socket.into())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SELF_SA: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<event version="2.0" uid="ANDROID-589520ccfcd20f01" type="a-f-G-U-C" time="2024-05-01T12:00:00Z" start="2024-05-01T12:00:00Z" stale="2024-05-01T12:06:00Z" how="h-e">
+  <point lat="38.8977" lon="-77.0365" hae="21.3" ce="9.9" le="9999999.0"/>
+  <detail>
+    <takv os="34" version="5.1.0.12" device="SAMSUNG SM-G991U" platform="ATAK-CIV"/>
+    <contact endpoint="192.168.1.20:4242:tcp" callsign="VIPER"/>
+    <__group role="Team Member" name="Cyan"/>
+  </detail>
+</event>"#;
+
+    const MARKER: &str = r#"<event version="2.0" uid="marker-1" type="a-h-G" time="2024-05-01T12:00:00Z" start="2024-05-01T12:00:00Z" stale="2024-05-02T12:00:00Z" how="h-g-i-g-o">
+  <point lat="38.9" lon="-77.0" hae="0" ce="9999999.0" le="9999999.0"/>
+  <detail><contact callsign="H.1"/></detail>
+</event>"#;
+
+    #[test]
+    fn test_parse_self_sa() {
+        let sa = parse_self_sa(SELF_SA.as_bytes()).unwrap();
+        assert_eq!(sa.uid, "ANDROID-589520ccfcd20f01");
+        assert_eq!(sa.callsign, "VIPER");
+        assert_eq!(sa.team.as_deref(), Some("Cyan"));
+        assert_eq!(sa.platform.as_deref(), Some("ATAK-CIV"));
+
+        let service = sa.to_service("192.168.1.20".parse().unwrap());
+        assert_eq!(service.service_type, ServiceType::AtakDevice);
+        assert_eq!(service.instance_name, "VIPER");
+        assert_eq!(service.port, 4242);
+        assert_eq!(service.properties["uid"], sa.uid);
+
+        assert_eq!(parse_self_sa(MARKER.as_bytes()), None);
+        assert_eq!(parse_self_sa(&[0xbf, 0x01, 0xbf, 0x12]), None);
+        assert_eq!(parse_self_sa(b"<event uid=\"x\""), None);
+    }
+}
@@ -161,6 +161,27 @@ impl ServiceStatus {
     }
 }
 
+/// A service this instance announces via mDNS
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(utoipa::ToSchema)]
+pub struct AnnouncedService {
+    /// Full mDNS name (instance and service type)
+    pub fullname: String,
+
+    /// mDNS service type (e.g., "_omnitak._tcp.local.")
+    pub service_type: String,
+
+    /// Instance name
+    pub instance_name: String,
+
+    /// Announced port
+    pub port: u16,
+
+    /// TXT record properties
+    pub properties: HashMap<String, String>,
+}
+
 /// Event emitted when a service changes state
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServiceEvent {
@@ -6,11 +6,13 @@
 // Affiliation and Dimension types available for future use
 use crate::router::{Route, RouteStrategy, RouteTable, RouteTableBuilder};
 use crate::rules::{
-    AffiliationFilter, DimensionFilter, GeoBoundingBoxFilter, GroupFilter, TeamFilter, UidFilter,
+    AffiliationFilter, DimensionFilter, GeoBoundingBoxFilter, GroupFilter, MgrsAreaFilter,
+    TeamFilter, UidFilter,
 };
+use crate::transform::TransformProfile;
 use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 use std::sync::Arc;
@@ -51,6 +53,11 @@ pub enum FilterConfig {
         /// Maximum longitude
         max_lon: f64,
     },
+    /// Filter by MGRS grid squares at any precision
+    Mgrs {
+        /// Allowed squares, e.g. "18SUJ" (100 km) or "18SUJ2306" (1 km)
+        squares: Vec<String>,
+    },
     /// Filter by specific UIDs
     Uid {
         /// Allowed UIDs
@@ -109,6 +116,10 @@ impl FilterConfig {
                 let filter = GeoBoundingBoxFilter::new(min_lat, max_lat, min_lon, max_lon);
                 Ok(Arc::new(filter))
             }
+            FilterConfig::Mgrs { squares } => {
+                let filter = MgrsAreaFilter::new(&squares).context("Invalid MGRS square")?;
+                Ok(Arc::new(filter))
+            }
             FilterConfig::Uid { uids } => {
                 let filter = UidFilter {
                     uids: uids.into_iter().collect(),
@@ -196,6 +207,13 @@ impl FilterConfig {
                 }
                 Ok(())
             }
+            FilterConfig::Mgrs { squares } => {
+                if squares.is_empty() {
+                    return Err(anyhow!("MGRS filter must have at least one square"));
+                }
+                MgrsAreaFilter::new(squares).context("Invalid MGRS square")?;
+                Ok(())
+            }
             FilterConfig::Uid { uids } => {
                 if uids.is_empty() {
                     return Err(anyhow!("UID filter must have at least one UID"));
@@ -242,6 +260,17 @@ pub struct RouteConfig {
     /// Whether this route is enabled
     #[serde(default = "default_enabled")]
     pub enabled: bool,
+    /// Name of the transform profile applied to this route's destinations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<String>,
+    /// IDs of transformer plugins run on messages this route sends to its
+    /// destinations, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plugins: Vec<String>,
+    /// Send smoothed positions to this route's destinations (see the
+    /// top-level `smoothing` settings)
+    #[serde(default)]
+    pub smoothing: bool,
 }
 
 fn default_priority() -> i32 {
@@ -254,6 +283,9 @@ fn default_enabled() -> bool {
 
 impl RouteConfig {
     /// Convert to a Route
+    ///
+    /// The transform profile is not resolved here; see
+    /// [`RoutingConfig::into_route_table`].
     pub fn into_route(self) -> Result<Route> {
         self.filter.validate()?;
         let filter = self.filter.into_filter_rule()?;
@@ -264,7 +296,9 @@ impl RouteConfig {
             filter,
             self.destinations,
             self.priority,
-        ))
+        )
+        .with_plugins(self.plugins)
+        .with_smoothing(self.smoothing))
     }
 
     /// Validate the route configuration
@@ -275,6 +309,9 @@ impl RouteConfig {
         if self.destinations.is_empty() {
             return Err(anyhow!("Route must have at least one destination"));
         }
+        if self.plugins.iter().any(|id| id.is_empty()) {
+            return Err(anyhow!("Route {} has an empty plugin ID", self.id));
+        }
         self.filter.validate()?;
         Ok(())
     }
@@ -288,6 +325,9 @@ pub struct RoutingConfig {
     pub strategy: String,
     /// Default destination for unmatched messages
     pub default_destination: Option<String>,
+    /// Named transform profiles referenced by routes
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub profiles: HashMap<String, TransformProfile>,
     /// List of routes
     pub routes: Vec<RouteConfig>,
 }
@@ -318,6 +358,24 @@ impl RoutingConfig {
         Ok(config)
     }
 
+    /// Look up a transform profile by name, falling back to built-in profiles
+    pub fn profile(&self, name: &str) -> Option<TransformProfile> {
+        self.profiles
+            .get(name)
+            .cloned()
+            .or_else(|| TransformProfile::builtin(name))
+    }
+
+    /// IDs of the plugins enabled routes run. These run only on the
+    /// destinations of the routes naming them, not on all traffic
+    pub fn route_plugins(&self) -> HashSet<String> {
+        self.routes
+            .iter()
+            .filter(|route| route.enabled)
+            .flat_map(|route| route.plugins.iter().cloned())
+            .collect()
+    }
+
     /// Convert to a RouteTable
     pub fn into_route_table(self) -> Result<RouteTable> {
         let strategy = match self.strategy.to_lowercase().as_str() {
@@ -336,19 +394,44 @@ impl RoutingConfig {
             RouteStrategy::FirstMatch => RouteTableBuilder::unicast(),
         };
 
-        if let Some(default) = self.default_destination {
-            builder = builder.default_destination(default);
+        if let Some(default) = &self.default_destination {
+            builder = builder.default_destination(default.clone());
         }
 
-        for route_config in self.routes {
+        // Share one instance of each profile between the routes using it
+        let mut profiles: HashMap<String, Arc<TransformProfile>> = HashMap::new();
+
+        for route_config in &self.routes {
             if !route_config.enabled {
                 info!(route_id = %route_config.id, "Skipping disabled route");
                 continue;
             }
 
-            let route = route_config
+            let transform = match &route_config.transform {
+                Some(name) => match profiles.get(name) {
+                    Some(profile) => Some(profile.clone()),
+                    None => {
+                        let profile = Arc::new(self.profile(name).ok_or_else(|| {
+                            anyhow!(
+                                "Route {} uses unknown transform profile: {}",
+                                route_config.id,
+                                name
+                            )
+                        })?);
+                        profiles.insert(name.clone(), profile.clone());
+                        Some(profile)
+                    }
+                },
+                None => None,
+            };
+
+            let mut route = route_config
+                .clone()
                 .into_route()
                 .with_context(|| "Failed to create route")?;
+            if let Some(transform) = transform {
+                route = route.with_transform(transform);
+            }
             builder = builder.add_route(route);
         }
 
@@ -375,6 +458,15 @@ impl RoutingConfig {
                 return Err(anyhow!("Duplicate route ID: {}", route.id));
             }
             route.validate()?;
+            if let Some(name) = &route.transform {
+                if self.profile(name).is_none() {
+                    return Err(anyhow!(
+                        "Route {} uses unknown transform profile: {}",
+                        route.id,
+                        name
+                    ));
+                }
+            }
         }
 
         Ok(())
@@ -401,6 +493,7 @@ impl RoutingConfig {
         RoutingConfig {
             strategy: "all".to_string(),
             default_destination: Some("default-server".to_string()),
+            profiles: HashMap::new(),
             routes: vec![
                 RouteConfig {
                     id: "friendly-ground".to_string(),
@@ -418,6 +511,9 @@ impl RoutingConfig {
                     destinations: vec!["blue-ground-server".to_string()],
                     priority: 100,
                     enabled: true,
+                    transform: None,
+                    plugins: Vec::new(),
+                    smoothing: false,
                 },
                 RouteConfig {
                     id: "hostile-air".to_string(),
@@ -435,6 +531,9 @@ impl RoutingConfig {
                     destinations: vec!["air-defense-server".to_string()],
                     priority: 90,
                     enabled: true,
+                    transform: None,
+                    plugins: Vec::new(),
+                    smoothing: false,
                 },
                 RouteConfig {
                     id: "team-alpha".to_string(),
@@ -445,6 +544,9 @@ impl RoutingConfig {
                     destinations: vec!["team-alpha-server".to_string()],
                     priority: 80,
                     enabled: true,
+                    transform: None,
+                    plugins: Vec::new(),
+                    smoothing: false,
                 },
                 RouteConfig {
                     id: "aor-northeast".to_string(),
@@ -458,6 +560,9 @@ impl RoutingConfig {
                     destinations: vec!["northeast-server".to_string()],
                     priority: 50,
                     enabled: true,
+                    transform: None,
+                    plugins: Vec::new(),
+                    smoothing: false,
                 },
             ],
         }
@@ -508,6 +613,22 @@ mod tests {
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_mgrs_validation() {
+        let config: FilterConfig =
+            serde_yaml::from_str("type: mgrs\nsquares: [\"18SUJ\", \"18S UJ 23 06\"]").unwrap();
+        assert!(config.validate().is_ok());
+        assert!(config.into_filter_rule().is_ok());
+
+        let config = FilterConfig::Mgrs { squares: vec![] };
+        assert!(config.validate().is_err());
+
+        let config = FilterConfig::Mgrs {
+            squares: vec!["18SUJ230".to_string()],
+        };
+        assert!(config.validate().is_err());
+    }
+
     #[test]
     fn test_route_config_validation() {
         let config = RouteConfig {
@@ -519,6 +640,9 @@ mod tests {
             destinations: vec!["dest1".to_string()],
             priority: 100,
             enabled: true,
+            transform: None,
+            plugins: Vec::new(),
+            smoothing: false,
         };
         assert!(config.validate().is_ok());
 
@@ -532,6 +656,9 @@ mod tests {
             destinations: vec![],
             priority: 100,
             enabled: true,
+            transform: None,
+            plugins: Vec::new(),
+            smoothing: false,
         };
         assert!(config.validate().is_err());
     }
@@ -541,6 +668,7 @@ mod tests {
         let config = RoutingConfig {
             strategy: "all".to_string(),
             default_destination: None,
+            profiles: HashMap::new(),
             routes: vec![
                 RouteConfig {
                     id: "test".to_string(),
@@ -551,6 +679,9 @@ mod tests {
                     destinations: vec!["dest1".to_string()],
                     priority: 100,
                     enabled: true,
+                    transform: None,
+                    plugins: Vec::new(),
+                    smoothing: false,
                 },
                 RouteConfig {
                     id: "test".to_string(),
@@ -561,6 +692,9 @@ mod tests {
                     destinations: vec!["dest2".to_string()],
                     priority: 90,
                     enabled: true,
+                    transform: None,
+                    plugins: Vec::new(),
+                    smoothing: false,
                 },
             ],
         };
@@ -573,4 +707,80 @@ mod tests {
         assert!(config.validate().is_ok());
         assert_eq!(config.routes.len(), 4);
     }
+
+    #[test]
+    fn test_route_transform_profiles() {
+        let yaml = r#"
+profiles:
+  coarse:
+    coordinate_decimals: 3
+routes:
+  - id: radio
+    description: Radio-linked EUDs
+    filter:
+      type: affiliation
+      allow: [friend]
+    destinations: [radio-gateway]
+    transform: lite
+  - id: overview
+    description: Coarse overview feed
+    filter:
+      type: team
+      teams: [Alpha]
+    destinations: [overview-server]
+    transform: coarse
+"#;
+        let config: RoutingConfig = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.profile("lite"), Some(TransformProfile::lite()));
+        assert_eq!(
+            config.profile("coarse").unwrap().coordinate_decimals,
+            Some(3)
+        );
+
+        let table = config.clone().into_route_table().unwrap();
+        let route = table.get_route("radio").unwrap();
+        assert_eq!(route.transform.as_deref(), Some(&TransformProfile::lite()));
+
+        let mut config = config;
+        config.routes[1].transform = Some("missing".to_string());
+        assert!(config.validate().is_err());
+        assert!(config.into_route_table().is_err());
+    }
+
+    #[test]
+    fn test_route_plugins() {
+        let yaml = r#"
+routes:
+  - id: partner
+    description: Partner nation server
+    filter:
+      type: affiliation
+      allow: [friend]
+    destinations: [tak-server-partner]
+    plugins: [sanitizer]
+  - id: archive
+    description: Disabled archive feed
+    filter:
+      type: affiliation
+      allow: [friend]
+    destinations: [archive]
+    enabled: false
+    plugins: [compressor]
+"#;
+        let config: RoutingConfig = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.route_plugins(),
+            HashSet::from(["sanitizer".to_string()])
+        );
+
+        let table = config.clone().into_route_table().unwrap();
+        let route = table.get_route("partner").unwrap();
+        assert_eq!(route.plugins, vec!["sanitizer".to_string()]);
+
+        let mut config = config;
+        config.routes[0].plugins.push(String::new());
+        assert!(config.validate().is_err());
+    }
 }
@@ -7,6 +7,8 @@
 //! - Multiple filter types (affiliation, geographic, team, group, UID)
 //! - High-performance routing engine with multicast/unicast support
 //! - YAML configuration with hot-reload capability
+//! - Named per-route transform profiles for low-bandwidth destinations
+//! - Per-route transformer plugin assignment
 //! - Lock-free data structures for concurrent access
 //! - Optimized fast-path operations with SIMD acceleration
 //!
@@ -97,6 +99,7 @@ pub mod config;
 pub mod fast_path;
 pub mod router;
 pub mod rules;
+pub mod transform;
 
 // Re-export commonly used types
 pub use affiliation::{Affiliation, CotType, Dimension};
@@ -105,9 +108,10 @@ pub use router::{
     DestinationId, Route, RouteStrategy, RouteTable, RouteTableBuilder, RoutingResult,
 };
 pub use rules::{
-    AffiliationFilter, CotMessage, DimensionFilter, FilterResult, FilterRule, FilterStats,
-    GeoBoundingBoxFilter, GroupFilter, TeamFilter, UidFilter,
+    AffiliationFilter, CotMessage, DimensionFilter, FieldRuleFilter, FilterResult, FilterRule,
+    FilterStats, GeoBoundingBoxFilter, GroupFilter, MgrsAreaFilter, TeamFilter, UidFilter,
 };
+pub use transform::{DetailElement, TransformProfile};
 
 #[cfg(test)]
 mod tests {
@@ -4,8 +4,10 @@
 //! Uses lock-free data structures for concurrent access.
 
 use crate::rules::{CotMessage, FilterResult, FilterRule, FilterStats};
+use crate::transform::TransformProfile;
 use dashmap::DashMap;
 use parking_lot::RwLock;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::{debug, trace};
 
@@ -25,6 +27,13 @@ pub struct Route {
     pub destinations: Vec<DestinationId>,
     /// Priority (higher priority routes evaluated first)
     pub priority: i32,
+    /// Transform applied to messages sent to this route's destinations
+    pub transform: Option<Arc<TransformProfile>>,
+    /// IDs of transformer plugins run on messages sent to this route's
+    /// destinations
+    pub plugins: Vec<String>,
+    /// Whether this route's destinations receive smoothed positions
+    pub smoothing: bool,
     /// Statistics for this route
     stats: Arc<RwLock<FilterStats>>,
 }
@@ -44,10 +53,32 @@ impl Route {
             filter,
             destinations,
             priority,
+            transform: None,
+            plugins: Vec::new(),
+            smoothing: false,
             stats: Arc::new(RwLock::new(FilterStats::new())),
         }
     }
 
+    /// Attach a transform profile to this route
+    pub fn with_transform(mut self, transform: Arc<TransformProfile>) -> Self {
+        self.transform = Some(transform);
+        self
+    }
+
+    /// Run these transformer plugins on messages sent to this route's
+    /// destinations
+    pub fn with_plugins(mut self, plugins: Vec<String>) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    /// Send smoothed positions to this route's destinations
+    pub fn with_smoothing(mut self, smoothing: bool) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
     /// Evaluate this route against a message
     #[inline]
     pub fn evaluate(&self, msg: &CotMessage) -> FilterResult {
@@ -81,6 +112,12 @@ pub struct RoutingResult {
     pub destinations: Vec<DestinationId>,
     /// Routes that matched (for audit trail)
     pub matched_routes: Vec<String>,
+    /// Transforms to apply per destination (set by the first route reaching it)
+    pub transforms: HashMap<DestinationId, Arc<TransformProfile>>,
+    /// Plugins to run per destination, from every matched route reaching it
+    pub plugins: HashMap<DestinationId, Vec<String>>,
+    /// Destinations a matched smoothing route reaches
+    pub smoothed: Vec<DestinationId>,
 }
 
 impl RoutingResult {
@@ -89,6 +126,9 @@ impl RoutingResult {
         Self {
             destinations: Vec::new(),
             matched_routes: Vec::new(),
+            transforms: HashMap::new(),
+            plugins: HashMap::new(),
+            smoothed: Vec::new(),
         }
     }
 
@@ -97,6 +137,21 @@ impl RoutingResult {
     pub fn has_destinations(&self) -> bool {
         !self.destinations.is_empty()
     }
+
+    /// Get the transform to apply for a destination, if any
+    pub fn transform_for(&self, dest: &str) -> Option<&TransformProfile> {
+        self.transforms.get(dest).map(|t| t.as_ref())
+    }
+
+    /// Get the plugins to run for a destination, in route priority order
+    pub fn plugins_for(&self, dest: &str) -> &[String] {
+        self.plugins.get(dest).map_or(&[], |p| p.as_slice())
+    }
+
+    /// Check if a destination receives smoothed positions
+    pub fn smooths(&self, dest: &str) -> bool {
+        self.smoothed.iter().any(|d| d == dest)
+    }
 }
 
 /// Route evaluation strategy
@@ -209,6 +264,20 @@ impl RouteTable {
                     for dest in &route.destinations {
                         if !result.destinations.contains(dest) {
                             result.destinations.push(dest.clone());
+                            if let Some(transform) = &route.transform {
+                                result.transforms.insert(dest.clone(), transform.clone());
+                            }
+                        }
+                        if !route.plugins.is_empty() {
+                            let plugins = result.plugins.entry(dest.clone()).or_default();
+                            for plugin in &route.plugins {
+                                if !plugins.contains(plugin) {
+                                    plugins.push(plugin.clone());
+                                }
+                            }
+                        }
+                        if route.smoothing && !result.smoothed.contains(dest) {
+                            result.smoothed.push(dest.clone());
                         }
                     }
                     result.matched_routes.push(route_id.clone());
@@ -516,4 +585,78 @@ mod tests {
         assert_eq!(table.route_count(), 1);
         assert_eq!(table.default_destination, Some("default".to_string()));
     }
+
+    #[test]
+    fn test_route_transform_per_destination() {
+        let lite = Arc::new(TransformProfile::lite());
+        let table = RouteTableBuilder::multicast()
+            .add_route(
+                Route::new(
+                    "eud".to_string(),
+                    "Radio-linked EUDs".to_string(),
+                    Arc::new(AffiliationFilter::friendly_only()),
+                    vec!["radio".to_string(), "shared".to_string()],
+                    50,
+                )
+                .with_transform(lite.clone()),
+            )
+            .add_route(Route::new(
+                "full".to_string(),
+                "Full fidelity".to_string(),
+                Arc::new(AffiliationFilter::friendly_only()),
+                vec!["shared".to_string(), "server".to_string()],
+                100,
+            ))
+            .build();
+
+        let result = table.route(&create_test_message());
+        assert_eq!(result.destinations.len(), 3);
+        assert_eq!(result.transform_for("radio"), Some(lite.as_ref()));
+        // Higher-priority route without a transform reached "shared" first
+        assert!(result.transform_for("shared").is_none());
+        assert!(result.transform_for("server").is_none());
+    }
+
+    #[test]
+    fn test_route_plugins_per_destination() {
+        let table = RouteTableBuilder::multicast()
+            .add_route(
+                Route::new(
+                    "partner".to_string(),
+                    "Partner nation feed".to_string(),
+                    Arc::new(AffiliationFilter::friendly_only()),
+                    vec!["partner".to_string()],
+                    100,
+                )
+                .with_plugins(vec!["sanitizer".to_string()]),
+            )
+            .add_route(
+                Route::new(
+                    "nyc".to_string(),
+                    "NYC area".to_string(),
+                    Arc::new(GeoBoundingBoxFilter::new(40.0, 41.0, -75.0, -73.0)),
+                    vec!["partner".to_string(), "local".to_string()],
+                    90,
+                )
+                .with_plugins(vec!["geotag".to_string(), "sanitizer".to_string()])
+                .with_smoothing(true),
+            )
+            .add_route(Route::new(
+                "hostile".to_string(),
+                "Hostile units".to_string(),
+                Arc::new(AffiliationFilter::hostile_only()),
+                vec!["intel".to_string()],
+                80,
+            ))
+            .build();
+
+        let result = table.route(&create_test_message());
+        // Every matched route reaching a destination adds its plugins, once
+        assert_eq!(result.plugins_for("partner"), ["sanitizer", "geotag"]);
+        assert_eq!(result.plugins_for("local"), ["geotag", "sanitizer"]);
+        assert!(result.plugins_for("intel").is_empty());
+        // Only the smoothing route's destinations get smoothed positions
+        assert!(result.smooths("partner") && result.smooths("local"));
+        assert!(!result.smooths("intel"));
+    }
 }
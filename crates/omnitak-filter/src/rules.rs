@@ -3,6 +3,9 @@
 //! Provides various filter implementations that can be composed together.
 
 use crate::affiliation::{Affiliation, CotType, Dimension};
+use anyhow::Context;
+use omnitak_core::config::{FilterAction, FilterMode, FilterOperator};
+use omnitak_cot::mgrs::{GridSquare, MgrsError};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -62,6 +65,38 @@ pub struct CotMessage<'a> {
     pub hae: Option<f64>,
 }
 
+// TAK clients put their team color in the group name, so it is used as both
+// group and team
+impl<'a> From<&omnitak_cot::borrowed::Event<'a>> for CotMessage<'a> {
+    fn from(event: &omnitak_cot::borrowed::Event<'a>) -> Self {
+        Self {
+            cot_type: event.event_type,
+            uid: event.uid,
+            callsign: event.callsign(),
+            group: event.group_name(),
+            team: event.group_name(),
+            lat: event.point.lat,
+            lon: event.point.lon,
+            hae: Some(event.point.hae),
+        }
+    }
+}
+
+impl<'a> From<&'a omnitak_cot::Event> for CotMessage<'a> {
+    fn from(event: &'a omnitak_cot::Event) -> Self {
+        Self {
+            cot_type: &event.event_type,
+            uid: &event.uid,
+            callsign: event.callsign(),
+            group: event.group_name(),
+            team: event.group_name(),
+            lat: event.point.lat,
+            lon: event.point.lon,
+            hae: Some(event.point.hae),
+        }
+    }
+}
+
 /// Filter by affiliation (friendly, hostile, neutral, etc.)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AffiliationFilter {
@@ -243,6 +278,46 @@ impl FilterRule for GeoBoundingBoxFilter {
     }
 }
 
+/// Filter by MGRS grid squares, e.g. `18SUJ` or `18SUJ2306`
+///
+/// Passes positions inside any of the squares. Unlike a latitude/longitude
+/// box, a square follows the grid exactly, as it is drawn on a map sheet.
+#[derive(Debug, Clone)]
+pub struct MgrsAreaFilter {
+    /// Squares to allow
+    pub squares: Vec<GridSquare>,
+}
+
+impl MgrsAreaFilter {
+    /// Create a new MGRS area filter from square references
+    pub fn new<S: AsRef<str>>(squares: &[S]) -> Result<Self, MgrsError> {
+        let squares = squares
+            .iter()
+            .map(|square| square.as_ref().parse())
+            .collect::<Result<_, _>>()?;
+        Ok(Self { squares })
+    }
+}
+
+impl FilterRule for MgrsAreaFilter {
+    fn evaluate(&self, msg: &CotMessage) -> FilterResult {
+        if self
+            .squares
+            .iter()
+            .any(|square| square.contains(msg.lat, msg.lon))
+        {
+            FilterResult::Pass
+        } else {
+            FilterResult::Block
+        }
+    }
+
+    fn describe(&self) -> String {
+        let squares: Vec<String> = self.squares.iter().map(|s| s.to_string()).collect();
+        format!("MgrsAreaFilter(squares: {:?})", squares)
+    }
+}
+
 /// Filter by specific UIDs
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UidFilter {
@@ -322,6 +397,135 @@ impl FilterRule for DimensionFilter {
     }
 }
 
+/// Field rules from the `filters` section of the application configuration
+///
+/// The first enabled rule matching a message decides: `accept` and `modify`
+/// pass it, `reject` blocks it. A message no rule matches is blocked in
+/// whitelist mode and gets `default_action` in blacklist mode. Rules match
+/// the `type`, `uid`, `callsign`, `group` and `team` fields; a field the
+/// message lacks only matches `not_equals` and `not_contains`.
+#[derive(Debug, Clone)]
+pub struct FieldRuleFilter {
+    enabled: bool,
+    mode: FilterMode,
+    default_action: FilterAction,
+    rules: Vec<FieldRule>,
+}
+
+#[derive(Debug, Clone)]
+struct FieldRule {
+    name: String,
+    field: String,
+    matcher: FieldMatcher,
+    action: FilterAction,
+}
+
+#[derive(Debug, Clone)]
+enum FieldMatcher {
+    Equals(String),
+    NotEquals(String),
+    Contains(String),
+    NotContains(String),
+    Regex(Regex),
+    StartsWith(String),
+    EndsWith(String),
+}
+
+impl FieldRuleFilter {
+    /// Compile the enabled rules of a configuration
+    pub fn new(config: &omnitak_core::config::FilterConfig) -> anyhow::Result<Self> {
+        let mut rules = Vec::new();
+        for rule in config.rules.iter().filter(|rule| rule.enabled) {
+            if !matches!(
+                rule.field.as_str(),
+                "type" | "uid" | "callsign" | "group" | "team"
+            ) {
+                anyhow::bail!("Filter rule {}: unknown field {:?}", rule.name, rule.field);
+            }
+            let value = rule.value.clone();
+            let matcher = match rule.operator {
+                FilterOperator::Equals => FieldMatcher::Equals(value),
+                FilterOperator::NotEquals => FieldMatcher::NotEquals(value),
+                FilterOperator::Contains => FieldMatcher::Contains(value),
+                FilterOperator::NotContains => FieldMatcher::NotContains(value),
+                FilterOperator::Regex => FieldMatcher::Regex(
+                    Regex::new(&value)
+                        .with_context(|| format!("Filter rule {}: invalid regex", rule.name))?,
+                ),
+                FilterOperator::StartsWith => FieldMatcher::StartsWith(value),
+                FilterOperator::EndsWith => FieldMatcher::EndsWith(value),
+            };
+            rules.push(FieldRule {
+                name: rule.name.clone(),
+                field: rule.field.clone(),
+                matcher,
+                action: rule.action,
+            });
+        }
+
+        Ok(Self {
+            enabled: config.enabled,
+            mode: config.mode,
+            default_action: config.default_action,
+            rules,
+        })
+    }
+
+    /// Whether every message passes, so the filter need not be applied
+    pub fn passes_everything(&self) -> bool {
+        !self.enabled
+            || (self.rules.is_empty()
+                && self.mode == FilterMode::Blacklist
+                && self.default_action != FilterAction::Reject)
+    }
+}
+
+impl FieldRule {
+    fn matches(&self, msg: &CotMessage) -> bool {
+        let value = match self.field.as_str() {
+            "type" => Some(msg.cot_type),
+            "uid" => Some(msg.uid),
+            "callsign" => msg.callsign,
+            "group" => msg.group,
+            _ => msg.team,
+        };
+        match (&self.matcher, value) {
+            (FieldMatcher::NotEquals(expected), value) => value != Some(expected.as_str()),
+            (FieldMatcher::NotContains(part), value) => {
+                !value.is_some_and(|v| v.contains(part.as_str()))
+            }
+            (_, None) => false,
+            (FieldMatcher::Equals(expected), Some(value)) => value == expected,
+            (FieldMatcher::Contains(part), Some(value)) => value.contains(part.as_str()),
+            (FieldMatcher::Regex(pattern), Some(value)) => pattern.is_match(value),
+            (FieldMatcher::StartsWith(prefix), Some(value)) => value.starts_with(prefix.as_str()),
+            (FieldMatcher::EndsWith(suffix), Some(value)) => value.ends_with(suffix.as_str()),
+        }
+    }
+}
+
+impl FilterRule for FieldRuleFilter {
+    fn evaluate(&self, msg: &CotMessage) -> FilterResult {
+        if !self.enabled {
+            return FilterResult::Pass;
+        }
+        let action = match self.rules.iter().find(|rule| rule.matches(msg)) {
+            Some(rule) => rule.action,
+            None if self.mode == FilterMode::Whitelist => FilterAction::Reject,
+            None => self.default_action,
+        };
+        match action {
+            FilterAction::Reject => FilterResult::Block,
+            FilterAction::Accept | FilterAction::Modify => FilterResult::Pass,
+        }
+    }
+
+    fn describe(&self) -> String {
+        let rules: Vec<&str> = self.rules.iter().map(|rule| rule.name.as_str()).collect();
+        format!("FieldRuleFilter(mode: {:?}, rules: {:?})", self.mode, rules)
+    }
+}
+
 /// Composite filter that combines multiple filters with AND/OR logic
 ///
 /// Note: Cannot derive Clone because it contains trait objects
@@ -505,6 +709,37 @@ mod tests {
         assert_eq!(result, FilterResult::Block);
     }
 
+    #[test]
+    fn test_mgrs_area() {
+        // The test message is at 40.7128, -74.0060: 18T WL 83 07
+        let filter = MgrsAreaFilter::new(&["18TWL", "33UUP"]).unwrap();
+        let msg = create_test_message();
+        assert_eq!(filter.evaluate(&msg), FilterResult::Pass);
+
+        let filter = MgrsAreaFilter::new(&["18TWL84"]).unwrap();
+        assert_eq!(filter.evaluate(&msg), FilterResult::Block);
+
+        assert!(MgrsAreaFilter::new(&["18TWL1"]).is_err());
+    }
+
+    #[test]
+    fn test_from_borrowed_event() {
+        let xml = r#"<event version="2.0" uid="TEST-001" type="a-f-G-E-V-C" time="2024-01-15T10:30:00Z" start="2024-01-15T10:30:00Z" stale="2024-01-15T10:35:00Z" how="m-g"><point lat="40.7128" lon="-74.006" hae="100" ce="5" le="5"/><detail><contact callsign="ALPHA-1"/><__group name="Cyan" role="Team Member"/></detail></event>"#;
+        let event = omnitak_cot::parse_cot_borrowed(xml).unwrap();
+        let msg = CotMessage::from(&event);
+        assert_eq!(msg.uid, "TEST-001");
+        assert_eq!(msg.callsign, Some("ALPHA-1"));
+        assert_eq!(msg.team, Some("Cyan"));
+
+        let owned = omnitak_cot::parse_cot(xml).unwrap();
+        let expected = CotMessage::from(&owned);
+        assert_eq!(format!("{:?}", msg), format!("{:?}", expected));
+        assert_eq!(
+            AffiliationFilter::friendly_only().evaluate(&msg),
+            FilterResult::Pass
+        );
+    }
+
     #[test]
     fn test_uid_filter() {
         let filter = UidFilter::new(vec!["TEST-001".to_string()]);
@@ -514,6 +749,62 @@ mod tests {
         assert_eq!(result, FilterResult::Pass);
     }
 
+    #[test]
+    fn test_field_rule_filter() {
+        let config: omnitak_core::config::FilterConfig = serde_yaml::from_str(
+            r#"
+mode: blacklist
+rules:
+  - name: drop-test-tracks
+    field: callsign
+    operator: starts_with
+    value: "TEST-"
+    action: reject
+  - name: drop-hostile
+    field: type
+    operator: regex
+    value: "^a-h-"
+    action: reject
+"#,
+        )
+        .unwrap();
+        let filter = FieldRuleFilter::new(&config).unwrap();
+        assert!(!filter.passes_everything());
+
+        let mut msg = create_test_message();
+        assert_eq!(filter.evaluate(&msg), FilterResult::Pass);
+        msg.callsign = Some("TEST-7");
+        assert_eq!(filter.evaluate(&msg), FilterResult::Block);
+        msg.callsign = None;
+        msg.cot_type = "a-h-G";
+        assert_eq!(filter.evaluate(&msg), FilterResult::Block);
+
+        // Whitelist mode drops what no rule accepts
+        let config = omnitak_core::config::FilterConfig {
+            mode: FilterMode::Whitelist,
+            rules: vec![omnitak_core::config::FilterRule {
+                name: "blue".to_string(),
+                description: String::new(),
+                enabled: true,
+                field: "group".to_string(),
+                operator: FilterOperator::Equals,
+                value: "Blue Force".to_string(),
+                action: FilterAction::Accept,
+            }],
+            ..Default::default()
+        };
+        let filter = FieldRuleFilter::new(&config).unwrap();
+        let mut msg = create_test_message();
+        assert_eq!(filter.evaluate(&msg), FilterResult::Pass);
+        msg.group = Some("Red");
+        assert_eq!(filter.evaluate(&msg), FilterResult::Block);
+
+        let mut config = config;
+        config.rules[0].field = "lat".to_string();
+        assert!(FieldRuleFilter::new(&config).is_err());
+        assert!(FieldRuleFilter::new(&Default::default()).unwrap().passes_everything());
+    }
+
     #[test]
     fn test_dimension_filter() {
         let filter = DimensionFilter::ground_only();
@@ -0,0 +1,267 @@
+//! Per-route message transforms
+//!
+//! A transform profile thins out CoT messages before they are sent to a
+//! destination, for example low-bandwidth end devices that only need a
+//! position, callsign and team. Profiles are defined once by name in the
+//! routing configuration and referenced from any number of routes.
+//!
+//! ```yaml
+//! profiles:
+//!   radio:
+//!     strip: [takv, precision_location, status, style, unparsed]
+//!     coordinate_decimals: 5
+//!     value_decimals: 0
+//! routes:
+//!   - id: handhelds
+//!     description: Thin traffic for radio-linked EUDs
+//!     filter:
+//!       type: affiliation
+//!       allow: [friend]
+//!     destinations: [radio-gateway]
+//!     transform: radio
+//! ```
+//!
+//! The built-in `lite` profile can be referenced without defining it.
+
+use anyhow::{Context, Result};
+use omnitak_cot::event::Shape;
+use omnitak_cot::{parse_cot_bytes, serialize_event, Event, Point};
+use serde::{Deserialize, Serialize};
+
+/// Name of the built-in low-bandwidth profile
+pub const LITE_PROFILE: &str = "lite";
+
+/// Optional detail elements a profile can strip
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DetailElement {
+    /// `<contact>` (callsign and endpoint)
+    Contact,
+    /// `<__group>` (team and role)
+    Group,
+    /// `<track>` (speed and course)
+    Track,
+    /// `<status>` (battery)
+    Status,
+    /// `<takv>` (device and software version)
+    Takv,
+    /// `<precisionlocation>` (position sources)
+    PrecisionLocation,
+    /// `<link>` elements
+    Link,
+    /// Shape geometry
+    Shape,
+    /// Color, stroke and label attributes
+    Style,
+    /// Detail content not parsed into structured fields (remarks, icons, etc.)
+    Unparsed,
+}
+
+/// A named set of reductions applied to messages on a route
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransformProfile {
+    /// Detail elements removed from the message
+    #[serde(default)]
+    pub strip: Vec<DetailElement>,
+    /// Decimal places kept for latitude and longitude
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coordinate_decimals: Option<u32>,
+    /// Decimal places kept for other measurements (hae, ce, le, track, shape)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_decimals: Option<u32>,
+}
+
+impl TransformProfile {
+    /// Low-bandwidth profile keeping position, identity, team, track and geometry
+    ///
+    /// Coordinates are rounded to 5 decimal places (about 1 m) and other
+    /// measurements to whole units.
+    pub fn lite() -> Self {
+        Self {
+            strip: vec![
+                DetailElement::Status,
+                DetailElement::Takv,
+                DetailElement::PrecisionLocation,
+                DetailElement::Style,
+                DetailElement::Unparsed,
+            ],
+            coordinate_decimals: Some(5),
+            value_decimals: Some(0),
+        }
+    }
+
+    /// Look up a built-in profile by name
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            LITE_PROFILE => Some(Self::lite()),
+            _ => None,
+        }
+    }
+
+    /// Whether applying this profile changes anything
+    pub fn is_noop(&self) -> bool {
+        self.strip.is_empty() && self.coordinate_decimals.is_none() && self.value_decimals.is_none()
+    }
+
+    /// Apply the profile to a parsed event
+    pub fn apply(&self, event: &mut Event) {
+        if let Some(decimals) = self.coordinate_decimals {
+            event.point.lat = round(event.point.lat, decimals);
+            event.point.lon = round(event.point.lon, decimals);
+        }
+        if let Some(decimals) = self.value_decimals {
+            event.point.hae = round(event.point.hae, decimals);
+            event.point.ce = round(event.point.ce, decimals);
+            event.point.le = round(event.point.le, decimals);
+        }
+
+        let Some(detail) = event.detail.as_mut() else {
+            return;
+        };
+
+        for element in &self.strip {
+            match element {
+                DetailElement::Contact => detail.contact = None,
+                DetailElement::Group => detail.group = None,
+                DetailElement::Track => detail.track = None,
+                DetailElement::Status => detail.status = None,
+                DetailElement::Takv => detail.takv = None,
+                DetailElement::PrecisionLocation => detail.precision_location = None,
+                DetailElement::Link => detail.link.clear(),
+                DetailElement::Shape => detail.shape = None,
+                DetailElement::Style => {
+                    detail.color = None;
+                    detail.fill_color = None;
+                    detail.stroke_color = None;
+                    detail.stroke_weight = None;
+                    detail.labels_on = None;
+                }
+                DetailElement::Unparsed => detail.xml_detail = None,
+            }
+        }
+
+        if let Some(decimals) = self.value_decimals {
+            if let Some(track) = detail.track.as_mut() {
+                track.speed = round(track.speed, decimals);
+                track.course = round(track.course, decimals);
+            }
+            match detail.shape.as_mut() {
+                Some(Shape::Ellipse {
+                    major,
+                    minor,
+                    angle,
+                }) => {
+                    *major = round(*major, decimals);
+                    *minor = round(*minor, decimals);
+                    *angle = round(*angle, decimals);
+                }
+                Some(Shape::Polyline { vertices, .. }) => {
+                    for vertex in vertices {
+                        self.round_point(vertex, decimals);
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Apply the profile to a serialized CoT XML message
+    pub fn apply_xml(&self, data: &[u8]) -> Result<Vec<u8>> {
+        if self.is_noop() {
+            return Ok(data.to_vec());
+        }
+
+        let mut event = parse_cot_bytes(data).context("Failed to parse CoT for transform")?;
+        self.apply(&mut event);
+        Ok(serialize_event(&event).into_bytes())
+    }
+
+    fn round_point(&self, point: &mut Point, value_decimals: u32) {
+        if let Some(decimals) = self.coordinate_decimals {
+            point.lat = round(point.lat, decimals);
+            point.lon = round(point.lon, decimals);
+        }
+        point.hae = round(point.hae, value_decimals);
+    }
+}
+
+/// Round to a fixed number of decimal places
+fn round(value: f64, decimals: u32) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    (value * factor).round() / factor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"<?xml version="1.0"?>
+<event version="2.0" uid="ANDROID-1" type="a-f-G-U-C" time="2024-01-15T10:30:00Z" start="2024-01-15T10:30:00Z" stale="2024-01-15T10:35:00Z" how="m-g">
+    <point lat="37.774912345" lon="-122.419412345" hae="12.3456" ce="4.87" le="9999999.0"/>
+    <detail>
+        <contact callsign="ALPHA-1" endpoint="*:-1:stcp"/>
+        <__group name="Cyan" role="Team Member"/>
+        <status battery="87"/>
+        <takv device="Pixel" platform="ATAK-CIV" os="34" version="5.2.0"/>
+        <track speed="1.2345" course="271.88"/>
+        <remarks>long free text that EUDs on a radio link do not need</remarks>
+    </detail>
+</event>"#;
+
+    #[test]
+    fn test_lite_strips_and_rounds() {
+        let profile = TransformProfile::lite();
+        let mut event = omnitak_cot::parse_cot(SAMPLE).unwrap();
+        profile.apply(&mut event);
+
+        assert_eq!(event.point.lat, 37.77491);
+        assert_eq!(event.point.lon, -122.41941);
+        assert_eq!(event.point.hae, 12.0);
+        assert_eq!(event.point.ce, 5.0);
+
+        let detail = event.detail.unwrap();
+        assert_eq!(detail.contact.unwrap().callsign, "ALPHA-1");
+        assert_eq!(detail.group.unwrap().name, "Cyan");
+        let track = detail.track.unwrap();
+        assert_eq!((track.speed, track.course), (1.0, 272.0));
+        assert!(detail.status.is_none());
+        assert!(detail.takv.is_none());
+        assert!(detail.xml_detail.is_none());
+    }
+
+    #[test]
+    fn test_apply_xml_shrinks_message() {
+        let profile = TransformProfile::lite();
+        let thinned = profile.apply_xml(SAMPLE.as_bytes()).unwrap();
+        let xml = String::from_utf8(thinned).unwrap();
+
+        assert!(xml.len() < SAMPLE.len());
+        assert!(xml.contains(r#"callsign="ALPHA-1""#));
+        assert!(!xml.contains("takv"));
+        assert!(!xml.contains("remarks"));
+    }
+
+    #[test]
+    fn test_noop_profile_passes_through() {
+        let profile = TransformProfile::default();
+        assert!(profile.is_noop());
+        assert_eq!(profile.apply_xml(b"not xml").unwrap(), b"not xml");
+        assert!(TransformProfile::lite().apply_xml(b"not xml").is_err());
+    }
+
+    #[test]
+    fn test_profile_from_yaml() {
+        let profile: TransformProfile =
+            serde_yaml::from_str("strip: [takv, unparsed]\ncoordinate_decimals: 4\n").unwrap();
+        assert_eq!(
+            profile.strip,
+            vec![DetailElement::Takv, DetailElement::Unparsed]
+        );
+        assert_eq!(profile.coordinate_decimals, Some(4));
+        assert_eq!(profile.value_decimals, None);
+        assert_eq!(
+            TransformProfile::builtin(LITE_PROFILE),
+            Some(TransformProfile::lite())
+        );
+    }
+}
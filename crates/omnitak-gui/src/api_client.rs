@@ -1,170 +1,51 @@
 //! REST API client for OmniTAK GUI
 //!
-//! Handles all communication with the main OmniTAK server via REST API
+//! Handles all communication with the main OmniTAK server via REST API,
+//! through the client generated from the server's OpenAPI document
 
-use anyhow::{Context, Result};
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, Context, Result};
+use omnitak_api_client::{Auth, Client};
+use std::path::PathBuf;
 use std::time::Duration;
+use uuid::Uuid;
+
+pub use omnitak_api_client::types::{
+    AdoptServiceRequest, AdoptServiceResponse, CertificateUploadResponse, ConfigField,
+    ConfigFieldType, ConnectionInfo, ConnectionStats, ConnectionStatsSample,
+    ConnectionStatsSnapshot, ConnectionStatus, ConnectionTestStage, ConnectionTestStep,
+    ConnectionType, CreateConnectionRequest, DeviceInfoResponse, DiscoveredServiceResponse,
+    EmergencyInfo, EmergencyStatus, ImportConnectionsResponse, ImportedConnection,
+    ListenerEndpointInfo, LoadPluginRequest, PairDeviceRequest, PluginCapability,
+    PluginDetailsResponse, PluginHealthResponse, PluginInfo, PluginMetricsResponse,
+    PluginSettingsSnapshot, PluginType, PullCertsResponse, ReconnectPolicy, ServerCertificateInfo,
+    ServiceStatus, SkippedConnection, SystemStatus, TestConnectionRequest, TestConnectionResponse,
+    TimeSyncInfo, WirelessDeviceResponse,
+};
+use omnitak_api_client::types::{
+    LoginRequest, PullCertsRequest, TogglePluginRequest, UpdatePluginConfigRequest,
+    WirelessDeviceRequest,
+};
 
 const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
 
 #[derive(Debug, Clone)]
 pub struct ApiClient {
-    base_url: String,
     client: Client,
-    auth_token: Option<String>,
 }
 
-// ============================================================================
-// Request/Response Types
-// ============================================================================
-
-#[derive(Debug, Serialize)]
-struct LoginRequest {
-    username: String,
-    password: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct LoginResponse {
-    access_token: String,
-    #[allow(dead_code)]
-    expires_at: String,
-    #[allow(dead_code)]
-    role: String,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct SystemStatus {
-    pub uptime_seconds: u64,
-    pub active_connections: usize,
-    pub messages_processed: u64,
-    pub messages_per_second: f64,
-    pub memory_usage_bytes: u64,
-    pub active_filters: usize,
-    pub version: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct ConnectionListResponse {
-    connections: Vec<ConnectionInfo>,
-    #[allow(dead_code)]
-    total: usize,
-}
-
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum ConnectionType {
-    TcpClient,
-    TcpServer,
-    TlsClient,
-    TlsServer,
-    Multicast,
-    Udp,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ConnectionInfo {
-    pub id: String,
-    pub name: String,
-    pub connection_type: ConnectionType,
-    pub status: String,
-    pub address: String,
-    pub port: u16,
-    pub messages_received: u64,
-    pub messages_sent: u64,
-}
-
-#[derive(Debug, Serialize)]
-pub struct CreateConnectionRequest {
-    pub name: String,
-    pub connection_type: ConnectionType,
-    pub address: String,
-    pub port: u16,
-    pub priority: Option<u8>,
-}
-
-#[derive(Debug, Deserialize)]
-struct CreateConnectionResponse {
-    id: String,
-    #[allow(dead_code)]
-    message: String,
-}
-
-// ============================================================================
-// Plugin API Types
-// ============================================================================
-
-#[derive(Debug, Deserialize)]
-pub struct PluginListResponse {
-    pub plugins: Vec<omnitak_plugin_api::PluginInfo>,
-    pub total: usize,
-}
-
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct LoadPluginRequest {
-    pub id: String,
-    pub path: String,
-    pub enabled: bool,
-    pub plugin_type: PluginApiType,
-    pub config: serde_json::Value,
-}
-
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "lowercase")]
-pub enum PluginApiType {
-    Filter,
-    Transformer,
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct PluginDetailsResponse {
-    pub info: omnitak_plugin_api::PluginInfo,
-    pub enabled: bool,
-    pub loaded_at: Option<String>,
-    pub execution_count: u64,
-    pub error_count: u64,
-    pub avg_execution_time_ms: f64,
-}
-
-#[derive(Debug, Clone, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct PluginMetricsResponse {
-    pub plugin_id: String,
-    pub execution_count: u64,
-    pub error_count: u64,
-    pub timeout_count: u64,
-    pub avg_execution_time_ms: f64,
-    pub p50_execution_time_ms: f64,
-    pub p95_execution_time_ms: f64,
-    pub p99_execution_time_ms: f64,
-    pub last_execution: Option<String>,
-    pub last_error: Option<String>,
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct PluginHealthResponse {
-    pub plugin_id: String,
-    pub status: String,
-    pub health_check_time: String,
-    pub uptime_seconds: u64,
-    pub issues: Vec<String>,
-}
-
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct UpdatePluginConfigRequest {
-    pub config: serde_json::Value,
+/// Auto-reconnect backoff policy sent with a new connection
+pub fn reconnect_policy(config: &omnitak_core::types::ReconnectConfig) -> ReconnectPolicy {
+    ReconnectPolicy {
+        max_attempts: config.max_attempts,
+        initial_backoff_ms: Some(config.initial_delay.as_millis() as u64),
+        max_backoff_secs: Some(config.max_delay.as_secs().max(1)),
+        backoff_multiplier: Some(config.backoff_multiplier),
+    }
 }
 
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct TogglePluginRequest {
-    pub enabled: bool,
+/// Error for a failed request, keeping the server's message
+fn failed(action: &'static str) -> impl FnOnce(omnitak_api_client::Error) -> anyhow::Error {
+    move |e| anyhow!("{} failed: {}", action, e)
 }
 
 // ============================================================================
@@ -174,179 +55,185 @@ pub struct TogglePluginRequest {
 impl ApiClient {
     /// Create a new API client
     pub fn new(base_url: impl Into<String>) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(DEFAULT_TIMEOUT)
-            .build()
-            .context("Failed to create HTTP client")?;
+        let client = Client::new(&base_url.into()).context("Failed to create HTTP client")?;
 
-        Ok(Self {
-            base_url: base_url.into(),
-            client,
-            auth_token: None,
-        })
+        Ok(Self { client })
+    }
+
+    /// URL of the server's system events WebSocket
+    pub fn events_url(&self) -> String {
+        self.client.events_url().to_string()
+    }
+
+    /// Header carrying the client's credentials, for WebSocket handshakes
+    pub fn auth_header(&self) -> Option<(&'static str, String)> {
+        match self.client.auth()? {
+            Auth::Bearer(token) => Some(("Authorization", format!("Bearer {}", token))),
+            Auth::ApiKey(key) => Some(("X-API-Key", key.clone())),
+        }
     }
 
     /// Login and get authentication token
     pub async fn login(&mut self, username: &str, password: &str) -> Result<()> {
-        let url = format!("{}/api/v1/auth/login", self.base_url);
-
         let request = LoginRequest {
             username: username.to_string(),
             password: password.to_string(),
         };
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&request)
-            .send()
-            .await
-            .context("Failed to send login request")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Login failed ({}): {}", status, error_text);
-        }
+        let login_response = self.client.login(&request).await.map_err(failed("Login"))?;
 
-        let login_response: LoginResponse = response
-            .json()
-            .await
-            .context("Failed to parse login response")?;
-
-        self.auth_token = Some(login_response.access_token);
+        self.client
+            .set_auth(Some(Auth::Bearer(login_response.access_token)));
 
         Ok(())
     }
 
     /// Check if authenticated
     pub fn is_authenticated(&self) -> bool {
-        self.auth_token.is_some()
+        self.client.auth().is_some()
     }
 
     /// Get system status
     pub async fn get_status(&self) -> Result<SystemStatus> {
-        let url = format!("{}/api/v1/status", self.base_url);
-
-        let mut request = self.client.get(&url);
-
-        if let Some(token) = &self.auth_token {
-            request = request.bearer_auth(token);
-        }
+        self.client
+            .get_system_status()
+            .await
+            .map_err(failed("Status request"))
+    }
 
-        let response = request
-            .send()
+    /// List all connections
+    pub async fn list_connections(&self) -> Result<Vec<ConnectionInfo>> {
+        let list = self
+            .client
+            .list_connections(None, None, None, None, None, None)
             .await
-            .context("Failed to get system status")?;
+            .map_err(failed("List connections"))?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Status request failed ({}): {}", status, error_text);
-        }
+        Ok(list.connections)
+    }
 
-        let status = response
-            .json()
+    /// List emergency beacons that are still open
+    pub async fn get_emergencies(&self) -> Result<Vec<EmergencyInfo>> {
+        let list = self
+            .client
+            .list_emergencies(Some(true))
             .await
-            .context("Failed to parse status response")?;
+            .map_err(failed("Get emergencies"))?;
 
-        Ok(status)
+        Ok(list.emergencies)
     }
 
-    /// List all connections
-    pub async fn list_connections(&self) -> Result<Vec<ConnectionInfo>> {
-        let url = format!("{}/api/v1/connections", self.base_url);
-
-        let mut request = self.client.get(&url);
+    /// Acknowledge (`action = "acknowledge"`) or clear (`"clear"`) a beacon
+    pub async fn update_emergency(&self, uid: &str, action: &str) -> Result<()> {
+        match action {
+            "acknowledge" => self
+                .client
+                .acknowledge_emergency(uid)
+                .await
+                .map_err(failed("Emergency acknowledge"))?,
+            "clear" => self
+                .client
+                .clear_emergency(uid)
+                .await
+                .map_err(failed("Emergency clear"))?,
+            other => anyhow::bail!("Unknown emergency action: {}", other),
+        };
 
-        if let Some(token) = &self.auth_token {
-            request = request.bearer_auth(token);
-        }
+        Ok(())
+    }
 
-        let response = request
-            .send()
+    /// Get recent throughput and write latency samples for a connection
+    pub async fn get_connection_stats(&self, id: Uuid) -> Result<ConnectionStats> {
+        self.client
+            .get_connection_stats(id)
             .await
-            .context("Failed to list connections")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("List connections failed ({}): {}", status, error_text);
-        }
+            .map_err(failed("Get connection stats"))
+    }
 
-        let list_response: ConnectionListResponse = response
-            .json()
+    /// Get recent samples of every connection, keyed by connection name
+    pub async fn get_all_connection_stats(&self) -> Result<ConnectionStatsSnapshot> {
+        self.client
+            .get_all_connection_stats()
             .await
-            .context("Failed to parse connections response")?;
-
-        Ok(list_response.connections)
+            .map_err(failed("Get connection stats"))
     }
 
     /// Create a new connection
-    pub async fn create_connection(&self, request: CreateConnectionRequest) -> Result<String> {
-        let url = format!("{}/api/v1/connections", self.base_url);
+    pub async fn create_connection(&self, request: CreateConnectionRequest) -> Result<Uuid> {
+        let create_response = self
+            .client
+            .create_connection(&request)
+            .await
+            .map_err(failed("Create connection"))?;
 
-        let mut req = self.client.post(&url).json(&request);
+        Ok(create_response.id)
+    }
 
-        if let Some(token) = &self.auth_token {
-            req = req.bearer_auth(token);
+    /// Upload certificate, key and CA files from this machine to the
+    /// server's certificate store, whatever their names or formats
+    pub async fn upload_certificate(
+        &self,
+        name: &str,
+        files: &[PathBuf],
+    ) -> Result<CertificateUploadResponse> {
+        let mut form = reqwest::multipart::Form::new().text("name", name.to_string());
+        for path in files {
+            let contents = tokio::fs::read(path)
+                .await
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let file_name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            form = form.part(
+                "file",
+                reqwest::multipart::Part::bytes(contents).file_name(file_name),
+            );
         }
 
-        let response = req
-            .send()
+        self.client
+            .upload_certificate(form)
             .await
-            .context("Failed to create connection")?;
+            .map_err(failed("Certificate upload"))
+    }
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Create connection failed ({}): {}", status, error_text);
-        }
+    /// Try connection settings on the server without adding a connection
+    pub async fn test_connection(
+        &self,
+        request: &TestConnectionRequest,
+    ) -> Result<TestConnectionResponse> {
+        // Each step may take up to timeout_secs
+        let timeout = Duration::from_secs(request.timeout_secs.unwrap_or(10) * 6);
+        let http = reqwest::Client::builder()
+            .timeout(timeout.max(DEFAULT_TIMEOUT))
+            .build()
+            .context("Failed to create HTTP client")?;
+        let mut client = Client::with_client(self.client.base_url().as_str(), http)?;
+        client.set_auth(self.client.auth().cloned());
 
-        let create_response: CreateConnectionResponse = response
-            .json()
+        client
+            .test_connection(request)
             .await
-            .context("Failed to parse create connection response")?;
-
-        Ok(create_response.id)
+            .map_err(failed("Connection test"))
     }
 
     /// Delete a connection
-    pub async fn delete_connection(&self, id: &str) -> Result<()> {
-        let url = format!("{}/api/v1/connections/{}", self.base_url, id);
-
-        let mut request = self.client.delete(&url);
-
-        if let Some(token) = &self.auth_token {
-            request = request.bearer_auth(token);
-        }
-
-        let response = request
-            .send()
+    pub async fn delete_connection(&self, id: Uuid) -> Result<()> {
+        self.client
+            .delete_connection(id)
             .await
-            .context("Failed to delete connection")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Delete connection failed ({}): {}", status, error_text);
-        }
+            .map_err(failed("Delete connection"))?;
 
         Ok(())
     }
 
     /// Health check (no auth required)
     pub async fn health_check(&self) -> Result<bool> {
-        let url = format!("{}/api/v1/health", self.base_url);
-
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .context("Failed to check health")?;
-
-        Ok(response.status().is_success())
+        match self.client.health_check().await {
+            Ok(_) => Ok(true),
+            Err(omnitak_api_client::Error::Api { .. }) => Ok(false),
+            Err(e) => Err(e).context("Failed to check health"),
+        }
     }
 
     // ============================================================================
@@ -354,272 +241,269 @@ impl ApiClient {
     // ============================================================================
 
     /// List all plugins
-    pub async fn list_plugins(&self) -> Result<Vec<omnitak_plugin_api::PluginInfo>> {
-        let url = format!("{}/api/v1/plugins", self.base_url);
-
-        let mut request = self.client.get(&url);
-
-        if let Some(token) = &self.auth_token {
-            request = request.bearer_auth(token);
-        }
-
-        let response = request
-            .send()
-            .await
-            .context("Failed to list plugins")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("List plugins failed ({}): {}", status, error_text);
-        }
-
-        let list_response: PluginListResponse = response
-            .json()
+    pub async fn list_plugins(&self) -> Result<Vec<PluginInfo>> {
+        let list = self
+            .client
+            .list_plugins(None, None)
             .await
-            .context("Failed to parse plugins response")?;
+            .map_err(failed("List plugins"))?;
 
-        Ok(list_response.plugins)
+        Ok(list.plugins)
     }
 
     /// Load a new plugin
-    pub async fn load_plugin(&self, request: LoadPluginRequest) -> Result<omnitak_plugin_api::PluginInfo> {
-        let url = format!("{}/api/v1/plugins", self.base_url);
-
-        let mut req = self.client.post(&url).json(&request);
-
-        if let Some(token) = &self.auth_token {
-            req = req.bearer_auth(token);
-        }
-
-        let response = req
-            .send()
+    pub async fn load_plugin(&self, request: LoadPluginRequest) -> Result<PluginInfo> {
+        self.client
+            .load_plugin(&request)
             .await
-            .context("Failed to load plugin")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Load plugin failed ({}): {}", status, error_text);
-        }
-
-        let plugin_info = response
-            .json()
-            .await
-            .context("Failed to parse load plugin response")?;
-
-        Ok(plugin_info)
+            .map_err(failed("Load plugin"))
     }
 
     /// Get plugin details
     pub async fn get_plugin_details(&self, id: &str) -> Result<PluginDetailsResponse> {
-        let url = format!("{}/api/v1/plugins/{}", self.base_url, id);
-
-        let mut request = self.client.get(&url);
-
-        if let Some(token) = &self.auth_token {
-            request = request.bearer_auth(token);
-        }
-
-        let response = request
-            .send()
+        self.client
+            .get_plugin_details(id)
             .await
-            .context("Failed to get plugin details")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Get plugin details failed ({}): {}", status, error_text);
-        }
-
-        let details = response
-            .json()
-            .await
-            .context("Failed to parse plugin details response")?;
-
-        Ok(details)
+            .map_err(failed("Get plugin details"))
     }
 
     /// Unload a plugin
     pub async fn unload_plugin(&self, id: &str) -> Result<()> {
-        let url = format!("{}/api/v1/plugins/{}", self.base_url, id);
-
-        let mut request = self.client.delete(&url);
-
-        if let Some(token) = &self.auth_token {
-            request = request.bearer_auth(token);
-        }
-
-        let response = request
-            .send()
+        self.client
+            .unload_plugin(id)
             .await
-            .context("Failed to unload plugin")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Unload plugin failed ({}): {}", status, error_text);
-        }
+            .map_err(failed("Unload plugin"))
+    }
 
-        Ok(())
+    /// Get plugin settings and the schema they follow
+    pub async fn get_plugin_config(&self, id: &str) -> Result<PluginSettingsSnapshot> {
+        self.client
+            .get_plugin_config(id)
+            .await
+            .map_err(failed("Get plugin config"))
     }
 
     /// Update plugin configuration
     pub async fn update_plugin_config(&self, id: &str, config: serde_json::Value) -> Result<()> {
-        let url = format!("{}/api/v1/plugins/{}/config", self.base_url, id);
-
-        let request_body = UpdatePluginConfigRequest { config };
-
-        let mut req = self.client.put(&url).json(&request_body);
-
-        if let Some(token) = &self.auth_token {
-            req = req.bearer_auth(token);
-        }
-
-        let response = req
-            .send()
+        self.client
+            .update_plugin_config(id, &UpdatePluginConfigRequest { config })
             .await
-            .context("Failed to update plugin config")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Update plugin config failed ({}): {}", status, error_text);
-        }
-
-        Ok(())
+            .map_err(failed("Update plugin config"))
     }
 
     /// Toggle plugin enabled/disabled
     pub async fn toggle_plugin(&self, id: &str, enabled: bool) -> Result<()> {
-        let url = format!("{}/api/v1/plugins/{}/toggle", self.base_url, id);
-
-        let request_body = TogglePluginRequest { enabled };
-
-        let mut req = self.client.post(&url).json(&request_body);
+        self.client
+            .toggle_plugin(id, &TogglePluginRequest { enabled })
+            .await
+            .map_err(failed("Toggle plugin"))
+    }
 
-        if let Some(token) = &self.auth_token {
-            req = req.bearer_auth(token);
-        }
+    /// Get plugin metrics
+    pub async fn get_plugin_metrics(&self, id: &str) -> Result<PluginMetricsResponse> {
+        self.client
+            .get_plugin_metrics(id)
+            .await
+            .map_err(failed("Get plugin metrics"))
+    }
 
-        let response = req
-            .send()
+    /// Get plugin health
+    pub async fn get_plugin_health(&self, id: &str) -> Result<PluginHealthResponse> {
+        self.client
+            .get_plugin_health(id)
             .await
-            .context("Failed to toggle plugin")?;
+            .map_err(failed("Get plugin health"))
+    }
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Toggle plugin failed ({}): {}", status, error_text);
-        }
+    /// Reload a plugin
+    pub async fn reload_plugin(&self, id: &str) -> Result<()> {
+        self.client
+            .reload_plugin(id)
+            .await
+            .map_err(failed("Reload plugin"))
+    }
 
-        Ok(())
+    /// Reload all plugins
+    pub async fn reload_all_plugins(&self) -> Result<()> {
+        self.client
+            .reload_all_plugins()
+            .await
+            .map_err(failed("Reload all plugins"))
     }
 
-    /// Get plugin metrics
-    pub async fn get_plugin_metrics(&self, id: &str) -> Result<PluginMetricsResponse> {
-        let url = format!("{}/api/v1/plugins/{}/metrics", self.base_url, id);
+    /// List inbound listeners that onboarding packages can be built for
+    pub async fn list_listener_endpoints(&self) -> Result<Vec<ListenerEndpointInfo>> {
+        self.client
+            .list_listeners()
+            .await
+            .map_err(failed("List listeners"))
+    }
 
-        let mut request = self.client.get(&url);
+    /// Create connections for the given streams of a data package's
+    /// preference files
+    pub async fn import_package_connections(
+        &self,
+        package: Vec<u8>,
+        streams: &[usize],
+    ) -> Result<ImportConnectionsResponse> {
+        let streams = streams
+            .iter()
+            .map(usize::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+
+        self.client
+            .import_connections(Some(&streams), package)
+            .await
+            .map_err(failed("Connection import"))
+    }
 
-        if let Some(token) = &self.auth_token {
-            request = request.bearer_auth(token);
-        }
+    /// Download a data package that connects a device to an inbound listener
+    pub async fn download_listener_package(
+        &self,
+        id: &str,
+        username: Option<&str>,
+    ) -> Result<Vec<u8>> {
+        self.client
+            .download_listener_package(id, username, None, None)
+            .await
+            .map_err(failed("Data package download"))
+    }
 
-        let response = request
-            .send()
+    /// Running server configuration as config.yaml, with the number of
+    /// connections or settings that could not be exported (admin only)
+    pub async fn export_server_config(&self) -> Result<(String, usize)> {
+        // The generated export_config drops the warnings header
+        let request = self
+            .client
+            .request(reqwest::Method::GET, &["api", "v1", "config", "export"]);
+        let response = self
+            .client
+            .execute(request)
             .await
-            .context("Failed to get plugin metrics")?;
+            .map_err(failed("Config export"))?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Get plugin metrics failed ({}): {}", status, error_text);
-        }
+        let warnings = response
+            .headers()
+            .get("x-export-warnings")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
 
-        let metrics = response
-            .json()
+        let yaml = response
+            .text()
             .await
-            .context("Failed to parse plugin metrics response")?;
+            .context("Failed to read exported configuration")?;
 
-        Ok(metrics)
+        Ok((yaml, warnings))
     }
 
-    /// Get plugin health
-    pub async fn get_plugin_health(&self, id: &str) -> Result<PluginHealthResponse> {
-        let url = format!("{}/api/v1/plugins/{}/health", self.base_url, id);
+    /// List services found by mDNS discovery
+    pub async fn list_discovered_services(&self) -> Result<Vec<DiscoveredServiceResponse>> {
+        let list = self
+            .client
+            .list_discovered_services(None, None, None, None, None, None, None, None)
+            .await
+            .map_err(failed("List discovered services"))?;
 
-        let mut request = self.client.get(&url);
+        Ok(list.services)
+    }
 
-        if let Some(token) = &self.auth_token {
-            request = request.bearer_auth(token);
-        }
+    /// Create a connection to a discovered service
+    pub async fn adopt_service(
+        &self,
+        id: &str,
+        request: &AdoptServiceRequest,
+    ) -> Result<AdoptServiceResponse> {
+        self.client
+            .adopt_service(id, request)
+            .await
+            .map_err(failed("Adopt service"))
+    }
 
-        let response = request
-            .send()
+    /// List Android devices attached to the server over ADB
+    pub async fn list_adb_devices(&self) -> Result<Vec<DeviceInfoResponse>> {
+        let list = self
+            .client
+            .list_devices()
             .await
-            .context("Failed to get plugin health")?;
+            .map_err(failed("List ADB devices"))?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Get plugin health failed ({}): {}", status, error_text);
-        }
+        Ok(list.devices)
+    }
 
-        let health = response
-            .json()
+    /// Pair the server with a device over Wi-Fi
+    pub async fn pair_adb_device(
+        &self,
+        request: &PairDeviceRequest,
+    ) -> Result<WirelessDeviceResponse> {
+        self.client
+            .pair_device(request)
             .await
-            .context("Failed to parse plugin health response")?;
-
-        Ok(health)
+            .map_err(failed("ADB pair"))
     }
 
-    /// Reload a plugin
-    pub async fn reload_plugin(&self, id: &str) -> Result<()> {
-        let url = format!("{}/api/v1/plugins/{}/reload", self.base_url, id);
+    /// Connect the server to a device over Wi-Fi
+    pub async fn connect_adb_device(&self, address: &str) -> Result<WirelessDeviceResponse> {
+        let request = WirelessDeviceRequest {
+            address: address.to_string(),
+        };
 
-        let mut request = self.client.post(&url);
+        self.client
+            .connect_device(&request)
+            .await
+            .map_err(failed("ADB connect"))
+    }
 
-        if let Some(token) = &self.auth_token {
-            request = request.bearer_auth(token);
-        }
+    /// Disconnect a device connected over Wi-Fi
+    pub async fn disconnect_adb_device(&self, address: &str) -> Result<WirelessDeviceResponse> {
+        let request = WirelessDeviceRequest {
+            address: address.to_string(),
+        };
 
-        let response = request
-            .send()
+        self.client
+            .disconnect_device(&request)
             .await
-            .context("Failed to reload plugin")?;
+            .map_err(failed("ADB disconnect"))
+    }
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Reload plugin failed ({}): {}", status, error_text);
+    /// Approve or stop pulling certificates from a device whenever it is attached
+    pub async fn set_adb_device_approved(&self, serial: &str, approved: bool) -> Result<()> {
+        if approved {
+            self.client.approve_device(serial).await
+        } else {
+            self.client.revoke_device(serial).await
         }
+        .map_err(failed("Update device approval"))?;
 
         Ok(())
     }
 
-    /// Reload all plugins
-    pub async fn reload_all_plugins(&self) -> Result<()> {
-        let url = format!("{}/api/v1/plugins/reload-all", self.base_url);
-
-        let mut request = self.client.post(&url);
+    /// Pull TAK certificates from a device and create a connection with them
+    pub async fn pull_adb_certificates(&self, serial: &str) -> Result<PullCertsResponse> {
+        self.pull_certificates(PullCertsRequest {
+            device_serial: Some(serial.to_string()),
+            auto_connect: Some(true),
+            ..Default::default()
+        })
+        .await
+    }
 
-        if let Some(token) = &self.auth_token {
-            request = request.bearer_auth(token);
-        }
+    /// Pull certificates from every online device at once
+    pub async fn pull_all_adb_certificates(&self) -> Result<PullCertsResponse> {
+        self.pull_certificates(PullCertsRequest {
+            all_devices: Some(true),
+            auto_connect: Some(true),
+            ..Default::default()
+        })
+        .await
+    }
 
-        let response = request
-            .send()
+    async fn pull_certificates(&self, request: PullCertsRequest) -> Result<PullCertsResponse> {
+        self.client
+            .pull_certificates(&request)
             .await
-            .context("Failed to reload all plugins")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Reload all plugins failed ({}): {}", status, error_text);
-        }
-
-        Ok(())
+            .map_err(failed("Pull certificates"))
     }
 }
 
@@ -0,0 +1,121 @@
+//! Live system events from the server
+//!
+//! Keeps a WebSocket open to `/api/v1/events` in the background,
+//! reconnecting when it drops, and queues each system event for the UI to
+//! pick up on its next frame.
+
+use futures_util::StreamExt;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Delay before reconnecting after the connection drops
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// A system event pushed by the server
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerEvent {
+    /// Event name, e.g. `service_appeared`
+    pub event: String,
+    pub details: serde_json::Value,
+}
+
+/// Message on the events socket; anything but a system event is ignored
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WsMessage {
+    SystemEvent(ServerEvent),
+    #[serde(other)]
+    Other,
+}
+
+/// Background subscription to the server's events socket
+pub struct EventStream {
+    events: async_channel::Receiver<ServerEvent>,
+    /// Set whenever the socket (re)connects: events may have been missed
+    reconnected: async_channel::Receiver<()>,
+    task: JoinHandle<()>,
+}
+
+impl EventStream {
+    /// Starts listening on `url` (`ws://` or `wss://`), sending the `auth`
+    /// header, and wakes the UI through `ctx` whenever an event arrives
+    pub fn start(
+        runtime: &tokio::runtime::Handle,
+        url: String,
+        auth: Option<(&'static str, String)>,
+        ctx: egui::Context,
+    ) -> Self {
+        let (events_tx, events) = async_channel::unbounded();
+        let (reconnected_tx, reconnected) = async_channel::unbounded();
+
+        let task = runtime.spawn(async move {
+            loop {
+                let connected = match handshake(&url, auth.as_ref()) {
+                    Ok(request) => tokio_tungstenite::connect_async(request).await,
+                    Err(e) => Err(e),
+                };
+                match connected {
+                    Ok((mut socket, _)) => {
+                        tracing::debug!("Connected to event stream {}", url);
+                        let _ = reconnected_tx.try_send(());
+                        while let Some(Ok(message)) = socket.next().await {
+                            let Message::Text(text) = message else {
+                                continue;
+                            };
+                            if let Ok(WsMessage::SystemEvent(event)) = serde_json::from_str(&text) {
+                                if events_tx.send(event).await.is_err() {
+                                    return;
+                                }
+                                ctx.request_repaint();
+                            }
+                        }
+                        tracing::debug!("Event stream closed, reconnecting");
+                    }
+                    Err(e) => tracing::debug!("Event stream connection failed: {}", e),
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+
+        Self {
+            events,
+            reconnected,
+            task,
+        }
+    }
+
+    /// Events received since the last call
+    pub fn drain(&self) -> Vec<ServerEvent> {
+        std::iter::from_fn(|| self.events.try_recv().ok()).collect()
+    }
+
+    /// Whether the socket reconnected since the last call, so state kept up
+    /// to date by events should be reloaded
+    pub fn reconnected(&self) -> bool {
+        std::iter::from_fn(|| self.reconnected.try_recv().ok()).count() > 0
+    }
+}
+
+/// Handshake request for `url` carrying the `auth` header
+fn handshake(
+    url: &str,
+    auth: Option<&(&'static str, String)>,
+) -> tokio_tungstenite::tungstenite::Result<Request> {
+    let mut request = url.into_client_request()?;
+    if let Some((name, value)) = auth {
+        if let Ok(value) = value.parse() {
+            request.headers_mut().insert(*name, value);
+        }
+    }
+    Ok(request)
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -8,23 +8,29 @@ use omnitak_core::types::{
     ConnectionId, ConnectionMetadata, Protocol, ServerConfig, ServerStatus, TlsConfig,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::process::{Child, Command};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 mod ui;
+pub use ui::map::{CameraPosition, FollowMode, MapPanelState};
 
 pub mod backend;
 use backend::BackendService;
 
 pub mod api_client;
-pub use api_client::{ApiClient, ConnectionInfo, ConnectionType, CreateConnectionRequest};
+pub use api_client::{
+    ApiClient, ConnectionInfo, ConnectionStatus, ConnectionType, CreateConnectionRequest,
+    EmergencyStatus,
+};
 
 pub mod config_io;
 pub use config_io::{export_config, import_config, ConfigFile};
 
+pub mod events;
+
 /// Main application state for the OmniTAK GUI.
 pub struct OmniTakApp {
     /// Configuration state
@@ -74,6 +80,49 @@ pub struct OmniTakApp {
 
     /// Flag to track if theme has been initialized
     pub theme_initialized: bool,
+
+    /// Server clock synchronization status (from the last API refresh)
+    pub time_sync: Option<api_client::TimeSyncInfo>,
+
+    /// Recent throughput/latency samples per connection, keyed by server name
+    pub connection_stats: ConnectionStatsMap,
+
+    /// Connection stats being fetched in the background
+    pub connection_stats_promise: Option<poll_promise::Promise<Result<ConnectionStatsMap, String>>>,
+
+    /// Open emergency beacons (from the last API refresh)
+    pub emergencies: Vec<api_client::EmergencyInfo>,
+
+    /// Beacons the alert sound has already been played for
+    pub announced_emergencies: HashSet<String>,
+
+    /// Live system events from the server, once logged in
+    pub event_stream: Option<events::EventStream>,
+}
+
+/// Recent samples per connection name
+type ConnectionStatsMap = HashMap<String, Vec<api_client::ConnectionStatsSample>>;
+
+/// Fetch the stats of every connection on a background thread
+fn spawn_connection_stats(
+    client: ApiClient,
+) -> poll_promise::Promise<Result<ConnectionStatsMap, String>> {
+    poll_promise::Promise::spawn_thread("connection_stats", move || {
+        let rt = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+        rt.block_on(client.get_all_connection_stats())
+            .map(|snapshot| snapshot.connections.into_iter().collect())
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// Short description of a clock synchronization problem
+fn clock_warning_text(time_sync: &api_client::TimeSyncInfo) -> String {
+    match time_sync.offset_ms {
+        Some(offset) if offset.abs() > time_sync.drift_warning_ms as f64 => {
+            format!("Clock drift {:+.0} ms", offset)
+        }
+        _ => "Clock not synchronized".to_string(),
+    }
 }
 
 /// Status message level
@@ -113,6 +162,12 @@ impl Default for OmniTakApp {
             auto_start_done: false,
             command_palette: ui::command_palette::CommandPaletteState::default(),
             theme_initialized: false,
+            time_sync: None,
+            connection_stats: HashMap::new(),
+            connection_stats_promise: None,
+            emergencies: Vec::new(),
+            announced_emergencies: HashSet::new(),
+            event_stream: None,
         }
     }
 }
@@ -276,6 +331,10 @@ pub struct UiState {
     /// File dialog promise for import
     pub import_promise: Option<poll_promise::Promise<Option<std::path::PathBuf>>>,
 
+    /// Running server configuration export (saved path and warning count)
+    pub server_export_promise:
+        Option<poll_promise::Promise<Result<Option<(std::path::PathBuf, usize)>, String>>>,
+
     /// Inline server form state (replaces modal dialog)
     pub inline_server_form: Option<ServerDialogState>,
 
@@ -292,6 +351,12 @@ pub struct UiState {
 
     /// Data Package panel state
     pub datapackage_panel: ui::datapackage::DataPackagePanelState,
+
+    /// Network discovery panel state
+    pub discovery_panel: ui::discovery::DiscoveryPanelState,
+
+    /// Android devices panel state
+    pub devices_panel: ui::devices::DevicesPanelState,
 }
 
 impl Default for UiState {
@@ -309,6 +374,7 @@ impl Default for UiState {
             map_panel: ui::map::MapPanelState::default(),
             export_promise: None,
             import_promise: None,
+            server_export_promise: None,
             inline_server_form: None,
             cert_ca_promise: None,
             cert_client_promise: None,
@@ -316,6 +382,8 @@ impl Default for UiState {
             quick_connect: None,
             certificate_manager: ui::certificates::CertificateManagerState::default(),
             datapackage_panel: ui::datapackage::DataPackagePanelState::default(),
+            discovery_panel: ui::discovery::DiscoveryPanelState::default(),
+            devices_panel: ui::devices::DevicesPanelState::default(),
         }
     }
 }
@@ -339,6 +407,8 @@ pub enum AffiliationFilter {
 pub enum Tab {
     Dashboard,
     Connections,
+    Discovery,
+    Devices,
     Messages,
     Map,
     Plugins,
@@ -367,6 +437,13 @@ pub struct ServerDialogState {
     pub client_key_path: String,
     pub verify_cert: bool,
     pub server_name: String,
+
+    /// Connection test in progress
+    pub test_promise:
+        Option<poll_promise::Promise<Result<api_client::TestConnectionResponse, String>>>,
+
+    /// Outcome of the last connection test
+    pub test_result: Option<Result<api_client::TestConnectionResponse, String>>,
 }
 
 impl ServerDialogState {
@@ -387,6 +464,8 @@ impl ServerDialogState {
             client_key_path: String::new(),
             verify_cert: true,
             server_name: String::new(),
+            test_promise: None,
+            test_result: None,
         }
     }
 
@@ -434,9 +513,37 @@ impl ServerDialogState {
             client_key_path,
             verify_cert,
             server_name,
+            test_promise: None,
+            test_result: None,
         }
     }
 
+    /// Connection test for the settings in the dialog, if the protocol
+    /// can be tested
+    pub fn test_request(&self) -> Option<api_client::TestConnectionRequest> {
+        let path = |p: &str| (!p.is_empty()).then(|| p.to_string());
+        let (connection_type, tls_cert_path, tls_key_path) = match self.config.protocol {
+            Protocol::Tcp => (ConnectionType::Tcpclient, None, None),
+            Protocol::Tls => (
+                ConnectionType::Tlsclient,
+                path(&self.client_cert_path),
+                path(&self.client_key_path),
+            ),
+            Protocol::Udp | Protocol::WebSocket => return None,
+        };
+        Some(api_client::TestConnectionRequest {
+            connection_type,
+            address: self.config.host.clone(),
+            port: self.config.port.into(),
+            certificate_id: None,
+            tls_cert_path,
+            tls_key_path,
+            validate_certs: Some(self.verify_cert),
+            tak_ping: Some(true),
+            timeout_secs: Some(10),
+        })
+    }
+
     /// Builds the final server config from the dialog state.
     pub fn build(&self) -> ServerConfig {
         let mut config = self.config.clone();
@@ -558,10 +665,17 @@ impl OmniTakApp {
             }
         };
 
+        // Restore map settings and camera position
+        let map_panel = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, ui::map::STORAGE_KEY))
+            .unwrap_or_default();
+
         Self {
             state: Arc::new(Mutex::new(state)),
             ui_state: UiState {
                 auto_scroll: true,
+                map_panel,
                 ..Default::default()
             },
             backend: None, // Deprecated - using API client now
@@ -580,6 +694,45 @@ impl OmniTakApp {
             auto_start_done: false,
             command_palette: ui::command_palette::CommandPaletteState::default(),
             theme_initialized: false,
+            time_sync: None,
+            connection_stats: HashMap::new(),
+            connection_stats_promise: None,
+            emergencies: Vec::new(),
+            announced_emergencies: HashSet::new(),
+            event_stream: None,
+        }
+    }
+
+    /// Subscribes to the server's event stream and routes what arrived
+    /// since the last frame to the panels that show it
+    fn handle_server_events(&mut self, ctx: &egui::Context) {
+        if self.event_stream.is_none() {
+            if let Some(client) = &self.api_client {
+                self.event_stream = Some(events::EventStream::start(
+                    self.runtime.handle(),
+                    client.events_url(),
+                    client.auth_header(),
+                    ctx.clone(),
+                ));
+            }
+        }
+        let Some(stream) = &self.event_stream else {
+            return;
+        };
+
+        if stream.reconnected() {
+            self.ui_state.discovery_panel.reload();
+            self.ui_state.devices_panel.reload();
+        }
+        for event in stream.drain() {
+            let notice = if event.event.starts_with("device_") {
+                ui::devices::apply_event(&mut self.ui_state.devices_panel, &event)
+            } else {
+                ui::discovery::apply_event(&mut self.ui_state.discovery_panel, &event)
+            };
+            if let Some((message, level)) = notice {
+                self.show_status(message, level, 5);
+            }
         }
     }
 
@@ -651,18 +804,51 @@ impl OmniTakApp {
         };
 
         let connection_type = match config.protocol {
-            Protocol::Tcp => ConnectionType::TcpClient,
+            Protocol::Tcp => ConnectionType::Tcpclient,
             Protocol::Udp => ConnectionType::Udp,
-            Protocol::Tls => ConnectionType::TlsClient,
-            Protocol::WebSocket => ConnectionType::TcpClient, // WebSocket uses TCP
+            Protocol::Tls => ConnectionType::Tlsclient,
+            Protocol::WebSocket => ConnectionType::Tcpclient, // WebSocket uses TCP
         };
 
+        // The server may not share this machine's filesystem, so client
+        // certificates are uploaded rather than passed as paths
+        let tls = config
+            .tls
+            .as_ref()
+            .filter(|_| config.protocol == Protocol::Tls);
+        let mut certificate_id = None;
+        if let Some(tls) = tls {
+            if let (Some(cert), Some(key)) = (&tls.client_cert_path, &tls.client_key_path) {
+                let files = [cert.clone(), key.clone(), tls.ca_cert_path.clone()];
+                match self
+                    .runtime
+                    .block_on(api_client.upload_certificate(&config.name, &files))
+                {
+                    Ok(upload) => certificate_id = Some(upload.certificate.id),
+                    Err(e) => {
+                        tracing::error!("Failed to upload certificate: {}", e);
+                        self.show_status(
+                            format!("Failed to connect to {}: {}", config.name, e),
+                            StatusLevel::Error,
+                            5,
+                        );
+                        return;
+                    }
+                }
+            }
+        }
+
         let request = crate::api_client::CreateConnectionRequest {
             name: config.name.clone(),
             connection_type,
             address: config.host.clone(),
-            port: config.port,
-            priority: None,
+            port: config.port.into(),
+            certificate_id,
+            auto_reconnect: Some(config.reconnect.auto_reconnect),
+            reconnect: Some(api_client::reconnect_policy(&config.reconnect)),
+            tls_cert_path: None,
+            tls_key_path: None,
+            validate_certs: tls.map(|tls| tls.verify_cert),
         };
 
         match self.runtime.block_on(api_client.create_connection(request)) {
@@ -717,10 +903,10 @@ impl OmniTakApp {
         let connection_id = connections
             .iter()
             .find(|c| c.name == server_name)
-            .map(|c| c.id.clone());
+            .map(|c| c.id);
 
         if let Some(id) = connection_id {
-            match self.runtime.block_on(api_client.delete_connection(&id)) {
+            match self.runtime.block_on(api_client.delete_connection(id)) {
                 Ok(()) => {
                     tracing::info!("Deleted connection: {}", server_name);
                     self.show_status(
@@ -932,6 +1118,8 @@ impl OmniTakApp {
                 self.login_error = None;
                 self.login_password.clear(); // Clear password from memory
                 tracing::info!("Successfully logged in as {}", username);
+                // Resubscribe to events with the new token
+                self.event_stream = None;
 
                 // Initial data refresh
                 self.refresh_from_api();
@@ -950,12 +1138,28 @@ impl OmniTakApp {
             None => return,
         };
 
+        let mut clock_warning = None;
+
         // Get system status
         if let Ok(status) = self.runtime.block_on(api_client.get_status()) {
             let mut state = self.state.lock().unwrap();
-            state.metrics.active_connections = status.active_connections;
+            state.metrics.active_connections = status.active_connections as usize;
             state.metrics.total_messages_received = status.messages_processed;
             // Update other metrics as needed
+            drop(state);
+
+            // Announce a clock problem once when it first appears
+            if let Some(time_sync) = &status.time_sync {
+                if time_sync.warning && !self.time_sync.as_ref().is_some_and(|t| t.warning) {
+                    clock_warning = Some(clock_warning_text(time_sync));
+                }
+            }
+            self.time_sync = status.time_sync;
+        }
+
+        // Sparkline history for each connection, picked up by `update`
+        if self.connection_stats_promise.is_none() {
+            self.connection_stats_promise = Some(spawn_connection_stats(api_client.clone()));
         }
 
         // Get connections
@@ -967,7 +1171,7 @@ impl OmniTakApp {
                 let metadata = ConnectionMetadata {
                     connection_id: ConnectionId::new(),
                     server_name: conn.name.clone(),
-                    status: if conn.status == "connected" {
+                    status: if conn.status == ConnectionStatus::Connected {
                         ServerStatus::Connected
                     } else {
                         ServerStatus::Disconnected
@@ -986,16 +1190,16 @@ impl OmniTakApp {
                 // Also add to servers list if not already there
                 if !state.servers.iter().any(|s| s.name == conn.name) {
                     let protocol = match conn.connection_type {
-                        ConnectionType::TcpClient | ConnectionType::TcpServer => Protocol::Tcp,
+                        ConnectionType::Tcpclient | ConnectionType::Tcpserver => Protocol::Tcp,
                         ConnectionType::Udp => Protocol::Udp,
-                        ConnectionType::TlsClient | ConnectionType::TlsServer => Protocol::Tls,
+                        ConnectionType::Tlsclient | ConnectionType::Tlsserver => Protocol::Tls,
                         ConnectionType::Multicast => Protocol::Udp,
                     };
 
                     let server_config = ServerConfig {
                         name: conn.name.clone(),
                         host: conn.address.clone(),
-                        port: conn.port,
+                        port: conn.port as u16,
                         protocol,
                         tls: None,
                         reconnect: omnitak_core::types::ReconnectConfig::default(),
@@ -1008,6 +1212,26 @@ impl OmniTakApp {
                 }
             }
         }
+
+        // Get open emergencies; sound the alarm once per new beacon
+        if let Ok(emergencies) = self.runtime.block_on(api_client.get_emergencies()) {
+            let new_beacon = emergencies.iter().any(|e| {
+                e.status == EmergencyStatus::Active && !self.announced_emergencies.contains(&e.uid)
+            });
+            if new_beacon {
+                ui::emergency::play_alert_sound();
+            }
+            self.announced_emergencies = emergencies
+                .iter()
+                .filter(|e| e.status == EmergencyStatus::Active)
+                .map(|e| e.uid.clone())
+                .collect();
+            self.emergencies = emergencies;
+        }
+
+        if let Some(message) = clock_warning {
+            self.show_status(message, StatusLevel::Warning, 15);
+        }
     }
 }
 
@@ -1060,6 +1284,23 @@ impl eframe::App for OmniTakApp {
             self.auto_start_done = true;
         }
 
+        // Apply events pushed by the server
+        self.handle_server_events(ctx);
+
+        // Data packages dropped onto the window open in the Data Packages tab
+        if ui::datapackage::handle_dropped_files(ctx, &mut self.ui_state.datapackage_panel) {
+            self.ui_state.selected_tab = Tab::DataPackages;
+        }
+
+        // Connection stats fetched in the background
+        if let Some(promise) = self.connection_stats_promise.take() {
+            match promise.try_take() {
+                Ok(Ok(stats)) => self.connection_stats = stats,
+                Ok(Err(e)) => tracing::debug!("Failed to get connection stats: {}", e),
+                Err(promise) => self.connection_stats_promise = Some(promise),
+            }
+        }
+
         // Refresh data from API every 5 seconds
         if self.last_refresh.elapsed() > Duration::from_secs(5) {
             self.refresh_from_api();
@@ -1090,6 +1331,20 @@ impl eframe::App for OmniTakApp {
                     self.ui_state.selected_tab = Tab::Connections;
                 }
 
+                if ui
+                    .selectable_label(self.ui_state.selected_tab == Tab::Discovery, "📡 Discovery")
+                    .clicked()
+                {
+                    self.ui_state.selected_tab = Tab::Discovery;
+                }
+
+                if ui
+                    .selectable_label(self.ui_state.selected_tab == Tab::Devices, "📱 Devices")
+                    .clicked()
+                {
+                    self.ui_state.selected_tab = Tab::Devices;
+                }
+
                 if ui
                     .selectable_label(self.ui_state.selected_tab == Tab::Messages, "💬 Messages")
                     .clicked()
@@ -1135,14 +1390,57 @@ impl eframe::App for OmniTakApp {
                             .small()
                             .color(egui::Color32::GRAY),
                     );
+
+                    if let Some(time_sync) = self.time_sync.as_ref().filter(|t| t.warning) {
+                        ui.separator();
+                        ui.label(
+                            egui::RichText::new(format!("⚠ {}", clock_warning_text(time_sync)))
+                                .strong()
+                                .color(egui::Color32::from_rgb(255, 165, 0)),
+                        )
+                        .on_hover_text(format!(
+                            "Source: {}{}\nCoT timestamps from this server may be rejected as stale \
+                             by end devices when the clock is off by more than {} ms.",
+                            time_sync.source,
+                            time_sync
+                                .reference
+                                .as_ref()
+                                .map(|r| format!(" ({})", r))
+                                .unwrap_or_default(),
+                            time_sync.drift_warning_ms
+                        ));
+                    }
                 });
             });
         });
 
+        // Emergency banner below the tabs, above everything else
+        ui::emergency::show_banner(ctx, self);
+
         // Main content
         egui::CentralPanel::default().show(ctx, |ui| match self.ui_state.selected_tab {
             Tab::Dashboard => ui::dashboard::show(ui, self),
             Tab::Connections => ui::connections::show(ui, self),
+            Tab::Discovery => {
+                if let Some((message, level)) = ui::discovery::render_discovery_panel(
+                    ui,
+                    &self.state,
+                    &mut self.ui_state.discovery_panel,
+                    self.api_client.as_ref(),
+                ) {
+                    self.show_status(message, level, 5);
+                }
+            }
+            Tab::Devices => {
+                if let Some((message, level)) = ui::devices::render_devices_panel(
+                    ui,
+                    &self.state,
+                    &mut self.ui_state.devices_panel,
+                    self.api_client.as_ref(),
+                ) {
+                    self.show_status(message, level, 5);
+                }
+            }
             Tab::Messages => ui::messages::show(ui, &self.state, &mut self.ui_state),
             Tab::Map => ui::map::show(ui, &self.state, &mut self.ui_state.map_panel),
             Tab::Plugins => {
@@ -1160,6 +1458,7 @@ impl eframe::App for OmniTakApp {
                     ui,
                     &self.state,
                     &mut self.ui_state.datapackage_panel,
+                    self.api_client.as_ref(),
                 ) {
                     self.show_status(message, level, 5);
                 }
@@ -1197,6 +1496,7 @@ impl eframe::App for OmniTakApp {
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
         let state = self.state.lock().unwrap();
         eframe::set_value(storage, eframe::APP_KEY, &*state);
+        eframe::set_value(storage, ui::map::STORAGE_KEY, &self.ui_state.map_panel);
     }
 }
 
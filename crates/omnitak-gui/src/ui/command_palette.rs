@@ -78,6 +78,20 @@ pub fn get_all_commands() -> Vec<Command> {
             shortcut: Some("Ctrl+2".to_string()),
             category: CommandCategory::Navigation,
         },
+        Command {
+            id: "nav.discovery".to_string(),
+            name: "Go to Discovery".to_string(),
+            description: "Show TAK servers found on the local network".to_string(),
+            shortcut: None,
+            category: CommandCategory::Navigation,
+        },
+        Command {
+            id: "nav.devices".to_string(),
+            name: "Go to Devices".to_string(),
+            description: "Pair Android devices and pull ATAK certificates".to_string(),
+            shortcut: None,
+            category: CommandCategory::Navigation,
+        },
         Command {
             id: "nav.messages".to_string(),
             name: "Go to Messages".to_string(),
@@ -92,6 +106,13 @@ pub fn get_all_commands() -> Vec<Command> {
             shortcut: Some("Ctrl+4".to_string()),
             category: CommandCategory::Navigation,
         },
+        Command {
+            id: "map.search".to_string(),
+            name: "Search Map".to_string(),
+            description: "Find a unit by callsign, UID, MGRS or lat/lon".to_string(),
+            shortcut: Some("Ctrl+F".to_string()),
+            category: CommandCategory::Navigation,
+        },
         Command {
             id: "nav.plugins".to_string(),
             name: "Go to Plugins".to_string(),
@@ -481,9 +502,15 @@ pub fn execute_command(app: &mut OmniTakApp, command_id: &str, ctx: &egui::Conte
         // Navigation
         "nav.dashboard" => app.ui_state.selected_tab = Tab::Dashboard,
         "nav.connections" => app.ui_state.selected_tab = Tab::Connections,
+        "nav.discovery" => app.ui_state.selected_tab = Tab::Discovery,
+        "nav.devices" => app.ui_state.selected_tab = Tab::Devices,
         "nav.messages" => app.ui_state.selected_tab = Tab::Messages,
         "nav.map" => app.ui_state.selected_tab = Tab::Map,
         "nav.plugins" => app.ui_state.selected_tab = Tab::Plugins,
+        "map.search" => {
+            app.ui_state.selected_tab = Tab::Map;
+            app.ui_state.map_panel.search_focus_requested = true;
+        }
         "nav.settings" => app.ui_state.selected_tab = Tab::Settings,
 
         // View
@@ -636,6 +663,13 @@ pub fn handle_keyboard_shortcuts(ctx: &egui::Context, app: &mut OmniTakApp) -> b
                 }
             }
 
+            // Map search: Ctrl + F
+            if i.modifiers.command && i.key_pressed(Key::F) {
+                app.ui_state.selected_tab = Tab::Map;
+                app.ui_state.map_panel.search_focus_requested = true;
+                handled = true;
+            }
+
             // Settings: Ctrl + ,
             if i.modifiers.command && i.key_pressed(Key::Comma) {
                 app.ui_state.selected_tab = Tab::Settings;
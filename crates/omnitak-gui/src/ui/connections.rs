@@ -1,9 +1,13 @@
 //! Connections view for managing server connections.
 
+use crate::api_client::{
+    ConnectionStatsSample, ConnectionTestStage, TestConnectionRequest, TestConnectionResponse,
+};
 use crate::{format_bytes, OmniTakApp, ServerDialogState};
 use eframe::egui;
-use omnitak_core::types::{Protocol, ServerStatus};
+use omnitak_core::types::{Protocol, ReconnectConfig, ServerStatus};
 use std::path::PathBuf;
+use std::time::Duration;
 
 /// Result of certificate scanning
 #[derive(Debug)]
@@ -101,7 +105,318 @@ fn scan_for_certificates() -> Option<CertificateScanResult> {
     }
 }
 
+/// Sparkline size in points
+const SPARKLINE_SIZE: egui::Vec2 = egui::vec2(140.0, 28.0);
+
+/// Average write latency above which a link is shown as degraded
+const LATENCY_WARN_MS: f64 = 50.0;
+
+/// Average write latency above which a link is shown as failing
+const LATENCY_CRITICAL_MS: f64 = 250.0;
+
+/// Line color for a write latency value
+fn latency_color(latency_ms: f64) -> egui::Color32 {
+    if latency_ms >= LATENCY_CRITICAL_MS {
+        egui::Color32::from_rgb(230, 80, 70)
+    } else if latency_ms >= LATENCY_WARN_MS {
+        egui::Color32::from_rgb(230, 180, 60)
+    } else {
+        egui::Color32::from_rgb(90, 200, 120)
+    }
+}
+
+/// Maps a series onto a rect, scaled so the largest value touches the top.
+/// Missing values (no writes in the window) are drawn at zero.
+fn sparkline_points(values: &[Option<f64>], rect: egui::Rect) -> Vec<egui::Pos2> {
+    let max = values.iter().flatten().cloned().fold(0.0_f64, f64::max);
+    let step = if values.len() > 1 {
+        rect.width() / (values.len() - 1) as f32
+    } else {
+        0.0
+    };
+
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let fraction = if max > 0.0 {
+                (value.unwrap_or(0.0) / max) as f32
+            } else {
+                0.0
+            };
+            egui::pos2(
+                rect.left() + step * i as f32,
+                rect.bottom() - fraction * rect.height(),
+            )
+        })
+        .collect()
+}
+
+/// Draws write latency (colored by severity) over message rate (gray)
+fn show_sparkline(ui: &mut egui::Ui, samples: &[ConnectionStatsSample]) {
+    let (rect, response) = ui.allocate_exact_size(SPARKLINE_SIZE, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, egui::Color32::from_gray(25));
+
+    if samples.len() < 2 {
+        painter.text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            "collecting…",
+            egui::FontId::proportional(10.0),
+            egui::Color32::GRAY,
+        );
+        return;
+    }
+
+    let plot = rect.shrink(2.0);
+    let rates: Vec<Option<f64>> = samples
+        .iter()
+        .map(|s| Some(s.messages_sent_per_sec + s.messages_received_per_sec))
+        .collect();
+    let latencies: Vec<Option<f64>> = samples.iter().map(|s| s.avg_write_latency_ms).collect();
+
+    let latest = samples.last().unwrap();
+    let latest_latency = latest.avg_write_latency_ms.unwrap_or(0.0);
+
+    painter.add(egui::Shape::line(
+        sparkline_points(&rates, plot),
+        egui::Stroke::new(1.0, egui::Color32::from_gray(110)),
+    ));
+    painter.add(egui::Shape::line(
+        sparkline_points(&latencies, plot),
+        egui::Stroke::new(1.5, latency_color(latest_latency)),
+    ));
+
+    response.on_hover_ui(|ui| {
+        let peak = samples
+            .iter()
+            .filter_map(|s| s.max_write_latency_ms)
+            .fold(0.0_f64, f64::max);
+        match latest.avg_write_latency_ms {
+            Some(latency) => ui.label(format!("Write latency: {:.1} ms", latency)),
+            None => ui.label("Write latency: no writes"),
+        };
+        ui.label(format!("Peak write latency: {:.1} ms", peak));
+        ui.label(format!(
+            "Rate: ↑ {:.1}/s  ↓ {:.1}/s",
+            latest.messages_sent_per_sec, latest.messages_received_per_sec
+        ));
+    });
+}
+
+/// Attempts shown in the backoff preview
+const BACKOFF_PREVIEW_ATTEMPTS: usize = 10;
+
+/// Formats a reconnect delay compactly (e.g. "1.5s", "2m 30s")
+fn format_delay(delay: Duration) -> String {
+    let secs = delay.as_secs_f64();
+    if secs < 60.0 {
+        format!("{:.1}s", secs)
+    } else if secs < 3600.0 {
+        format!("{}m {}s", delay.as_secs() / 60, delay.as_secs() % 60)
+    } else {
+        format!("{}h {}m", delay.as_secs() / 3600, (delay.as_secs() % 3600) / 60)
+    }
+}
+
+/// Editor for a server's auto-reconnect policy with a preview of the
+/// resulting backoff schedule
+fn show_reconnect_editor(ui: &mut egui::Ui, reconnect: &mut ReconnectConfig) {
+    ui.checkbox(&mut reconnect.auto_reconnect, "Reconnect automatically");
+    if !reconnect.auto_reconnect {
+        return;
+    }
+
+    egui::Grid::new("reconnect_grid")
+        .num_columns(2)
+        .spacing([10.0, 4.0])
+        .show(ui, |ui| {
+            ui.label("Max attempts:");
+            ui.horizontal(|ui| {
+                let mut unlimited = reconnect.max_attempts.is_none();
+                if ui.checkbox(&mut unlimited, "Unlimited").changed() {
+                    reconnect.max_attempts = if unlimited { None } else { Some(5) };
+                }
+                if let Some(attempts) = &mut reconnect.max_attempts {
+                    ui.add(egui::DragValue::new(attempts).range(1..=10_000));
+                }
+            });
+            ui.end_row();
+
+            ui.label("Initial delay:");
+            let mut initial = reconnect.initial_delay.as_secs_f64();
+            if ui
+                .add(
+                    egui::DragValue::new(&mut initial)
+                        .range(0.1..=3600.0)
+                        .speed(0.1)
+                        .suffix(" s"),
+                )
+                .changed()
+            {
+                reconnect.initial_delay = Duration::from_secs_f64(initial);
+            }
+            ui.end_row();
+
+            ui.label("Max delay:");
+            let mut max = reconnect.max_delay.as_secs();
+            if ui
+                .add(egui::DragValue::new(&mut max).range(1..=86_400).suffix(" s"))
+                .changed()
+            {
+                reconnect.max_delay = Duration::from_secs(max);
+            }
+            ui.end_row();
+
+            ui.label("Backoff multiplier:");
+            ui.add(
+                egui::DragValue::new(&mut reconnect.backoff_multiplier)
+                    .range(1.0..=10.0)
+                    .speed(0.05)
+                    .suffix("×"),
+            );
+            ui.end_row();
+        });
+
+    ui.add_space(5.0);
+    show_backoff_preview(ui, reconnect);
+}
+
+/// Bar chart of the delay before each reconnection attempt
+fn show_backoff_preview(ui: &mut egui::Ui, reconnect: &ReconnectConfig) {
+    let schedule = reconnect.schedule(BACKOFF_PREVIEW_ATTEMPTS);
+    if schedule.is_empty() {
+        return;
+    }
+
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(320.0, 70.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, egui::Color32::from_gray(25));
+
+    let plot = rect.shrink2(egui::vec2(4.0, 12.0));
+    let longest = schedule.iter().max().unwrap().as_secs_f64().max(f64::EPSILON);
+    let slot = plot.width() / BACKOFF_PREVIEW_ATTEMPTS as f32;
+    let font = egui::FontId::proportional(9.0);
+
+    for (i, delay) in schedule.iter().enumerate() {
+        let height = (delay.as_secs_f64() / longest) as f32 * plot.height();
+        let left = plot.left() + slot * i as f32;
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(left + 2.0, plot.bottom() - height.max(1.0)),
+            egui::pos2(left + slot - 2.0, plot.bottom()),
+        );
+        let capped = *delay >= reconnect.max_delay;
+        let color = if capped {
+            egui::Color32::from_rgb(200, 150, 60)
+        } else {
+            egui::Color32::from_rgb(80, 140, 220)
+        };
+        painter.rect_filled(bar, 1.0, color);
+        painter.text(
+            egui::pos2(bar.center().x, bar.top() - 1.0),
+            egui::Align2::CENTER_BOTTOM,
+            format_delay(*delay),
+            font.clone(),
+            egui::Color32::LIGHT_GRAY,
+        );
+        painter.text(
+            egui::pos2(bar.center().x, rect.bottom() - 1.0),
+            egui::Align2::CENTER_BOTTOM,
+            format!("#{}", i + 1),
+            font.clone(),
+            egui::Color32::GRAY,
+        );
+    }
+
+    let summary = match reconnect.max_attempts {
+        Some(max) => {
+            let total: Duration = reconnect.schedule(max as usize).iter().sum();
+            format!("Gives up after {} attempts ({} total)", max, format_delay(total))
+        }
+        None => "Retries forever; amber bars have reached the max delay".to_string(),
+    };
+    ui.label(egui::RichText::new(summary).small().color(egui::Color32::GRAY));
+}
+
 /// Shows the connections view.
+/// Run a connection test on the server in the background
+fn spawn_connection_test(
+    client: crate::ApiClient,
+    request: TestConnectionRequest,
+) -> poll_promise::Promise<Result<TestConnectionResponse, String>> {
+    poll_promise::Promise::spawn_thread("connection_test", move || {
+        let rt = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+        rt.block_on(client.test_connection(&request))
+            .map_err(|e| e.to_string())
+    })
+}
+
+fn test_stage_label(stage: ConnectionTestStage) -> &'static str {
+    match stage {
+        ConnectionTestStage::ClientCertificate => "Client certificate",
+        ConnectionTestStage::Resolve => "DNS lookup",
+        ConnectionTestStage::Connect => "TCP connect",
+        ConnectionTestStage::TlsHandshake => "TLS handshake",
+        ConnectionTestStage::TakPing => "TAK ping",
+    }
+}
+
+/// Steps of a connection test, then the certificate the server presented
+fn show_test_result(ui: &mut egui::Ui, result: &Result<TestConnectionResponse, String>) {
+    let response = match result {
+        Ok(response) => response,
+        Err(e) => {
+            ui.colored_label(egui::Color32::from_rgb(230, 80, 70), format!("✖ {}", e));
+            return;
+        }
+    };
+
+    for step in &response.steps {
+        let (icon, color) = if step.success {
+            ("✔", egui::Color32::from_rgb(90, 200, 120))
+        } else {
+            ("✖", egui::Color32::from_rgb(230, 80, 70))
+        };
+        ui.horizontal(|ui| {
+            ui.colored_label(color, icon);
+            ui.label(egui::RichText::new(test_stage_label(step.stage)).strong());
+            ui.label(
+                egui::RichText::new(format!("{} ms", step.duration_ms))
+                    .small()
+                    .color(egui::Color32::GRAY),
+            );
+        });
+        ui.label(egui::RichText::new(&step.message).small());
+    }
+
+    if let Some(cert) = &response.server_certificate {
+        ui.add_space(5.0);
+        ui.label(egui::RichText::new("Server Certificate").strong());
+        ui.label(egui::RichText::new(format!("Subject: {}", cert.subject)).small());
+        ui.label(egui::RichText::new(format!("Issuer: {}", cert.issuer)).small());
+        let expiry = format!("Expires: {}", cert.not_after);
+        if cert.expired {
+            ui.colored_label(egui::Color32::from_rgb(230, 80, 70), format!("{} (expired)", expiry));
+        } else {
+            ui.label(egui::RichText::new(expiry).small());
+        }
+        match cert.trusted {
+            Some(true) => ui.colored_label(egui::Color32::from_rgb(90, 200, 120), "Trusted"),
+            Some(false) => ui.colored_label(
+                egui::Color32::from_rgb(230, 180, 60),
+                "Not trusted by the server's CA store; import the CA or disable verification",
+            ),
+            None => ui.label(egui::RichText::new("Not verified").small().color(egui::Color32::GRAY)),
+        };
+        ui.label(
+            egui::RichText::new(format!("SHA-256: {}", cert.fingerprint))
+                .small()
+                .color(egui::Color32::GRAY),
+        );
+    }
+}
+
 pub fn show(ui: &mut egui::Ui, app: &mut OmniTakApp) {
     // Check if Quick Connect wizard is open
     if app.ui_state.quick_connect.is_some() {
@@ -175,6 +490,19 @@ pub fn show(ui: &mut egui::Ui, app: &mut OmniTakApp) {
         }
     }
 
+    // Handle a finished connection test
+    if let Some(dialog_state) = &mut app.ui_state.inline_server_form {
+        let finished = dialog_state
+            .test_promise
+            .as_ref()
+            .and_then(|promise| promise.ready())
+            .cloned();
+        if let Some(result) = finished {
+            dialog_state.test_result = Some(result);
+            dialog_state.test_promise = None;
+        }
+    }
+
     // Inline Add/Edit Form
     let mut form_closed = false;
     let mut form_saved = false;
@@ -301,6 +629,15 @@ pub fn show(ui: &mut egui::Ui, app: &mut OmniTakApp) {
 
                         ui.add_space(10.0);
 
+                        // Auto-reconnect policy
+                        egui::CollapsingHeader::new("Auto-Reconnect")
+                            .default_open(false)
+                            .show(ui, |ui| {
+                                show_reconnect_editor(ui, &mut dialog_state.config.reconnect);
+                            });
+
+                        ui.add_space(10.0);
+
                         // Enabled checkbox
                         ui.checkbox(&mut dialog_state.config.enabled, "Enable auto-connect");
 
@@ -315,7 +652,30 @@ pub fn show(ui: &mut egui::Ui, app: &mut OmniTakApp) {
                             if ui.button("✖ Cancel").clicked() {
                                 form_closed = true;
                             }
+
+                            let test_request = dialog_state.test_request();
+                            let can_test = test_request.is_some()
+                                && app.api_client.is_some()
+                                && dialog_state.test_promise.is_none();
+                            if ui
+                                .add_enabled(can_test, egui::Button::new("🔌 Test Connection"))
+                                .on_disabled_hover_text("Only TCP and TLS connections can be tested")
+                                .clicked()
+                            {
+                                if let (Some(client), Some(request)) = (app.api_client.clone(), test_request) {
+                                    dialog_state.test_result = None;
+                                    dialog_state.test_promise = Some(spawn_connection_test(client, request));
+                                }
+                            }
+                            if dialog_state.test_promise.is_some() {
+                                ui.spinner();
+                            }
                         });
+
+                        if let Some(result) = &dialog_state.test_result {
+                            ui.add_space(10.0);
+                            show_test_result(ui, result);
+                        }
                     });
                 });
         });
@@ -352,6 +712,7 @@ pub fn show(ui: &mut egui::Ui, app: &mut OmniTakApp) {
     let servers_clone = state.servers.clone();
     let connections_clone = state.connections.clone();
     drop(state);
+    let stats_clone = app.connection_stats.clone();
 
     if servers_clone.is_empty() {
         ui.vertical_centered(|ui| {
@@ -423,6 +784,10 @@ pub fn show(ui: &mut egui::Ui, app: &mut OmniTakApp) {
                                     ));
                                 });
 
+                                if let Some(samples) = stats_clone.get(&server.name) {
+                                    show_sparkline(ui, samples);
+                                }
+
                                 if metadata.reconnect_attempts > 0 {
                                     ui.label(format!(
                                         "Reconnect attempts: {}",
@@ -538,3 +903,37 @@ pub fn show(ui: &mut egui::Ui, app: &mut OmniTakApp) {
         app.disconnect_server(server_name);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline_points() {
+        let rect = egui::Rect::from_min_size(egui::pos2(0.0, 0.0), egui::vec2(100.0, 10.0));
+
+        let points = sparkline_points(&[Some(0.0), None, Some(5.0), Some(10.0)], rect);
+        assert_eq!(points.len(), 4);
+        assert_eq!(points[0], egui::pos2(0.0, 10.0));
+        assert_eq!(points[1].y, 10.0);
+        assert_eq!(points[2].y, 5.0);
+        assert_eq!(points[3], egui::pos2(100.0, 0.0));
+
+        // An idle series stays flat on the baseline
+        let idle = sparkline_points(&[None, None], rect);
+        assert!(idle.iter().all(|p| p.y == 10.0));
+    }
+
+    #[test]
+    fn test_format_delay() {
+        assert_eq!(format_delay(Duration::from_millis(1500)), "1.5s");
+        assert_eq!(format_delay(Duration::from_secs(150)), "2m 30s");
+        assert_eq!(format_delay(Duration::from_secs(5400)), "1h 30m");
+    }
+
+    #[test]
+    fn test_latency_color() {
+        assert_ne!(latency_color(5.0), latency_color(LATENCY_WARN_MS));
+        assert_ne!(latency_color(LATENCY_WARN_MS), latency_color(LATENCY_CRITICAL_MS));
+    }
+}
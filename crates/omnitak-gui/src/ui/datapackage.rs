@@ -2,13 +2,15 @@
 
 use egui::{Button, Color32, ScrollArea, Ui};
 use omnitak_datapackage::{
-    ContentType, DataPackageBuilder, DataPackageReader, PackageContent, PackageSummary,
+    ContentType, DataPackageBuilder, DataPackageReader, PackageContent, PackageSummary, Severity,
+    StreamPreference, ValidationReport,
 };
 use poll_promise::Promise;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
-use crate::{AppState, StatusLevel as CrateStatusLevel};
+use crate::api_client::{ImportConnectionsResponse, ListenerEndpointInfo};
+use crate::{ApiClient, AppState, StatusLevel as CrateStatusLevel};
 
 /// State for the Data Package panel
 #[derive(Default)]
@@ -23,6 +25,25 @@ pub struct DataPackagePanelState {
     pub export_promise: Option<Promise<Result<PathBuf, String>>>,
     /// Status message
     pub status_message: Option<(String, StatusLevel)>,
+    /// Listener list for the onboarding dialog
+    pub listeners_promise: Option<Promise<Result<Vec<ListenerEndpointInfo>, String>>>,
+    /// Device onboarding dialog
+    pub onboarding: Option<OnboardingDialog>,
+    /// Onboarding package download in progress
+    pub onboarding_promise: Option<Promise<Result<Option<PathBuf>, String>>>,
+    /// Which of the loaded package's streams to create connections for
+    pub stream_selection: Vec<bool>,
+    /// Connection import in progress
+    pub connections_promise: Option<Promise<Result<ImportConnectionsResponse, String>>>,
+}
+
+/// Onboarding dialog state: packages that connect a device to one of
+/// OmniTAK's inbound listeners
+#[derive(Default)]
+pub struct OnboardingDialog {
+    pub listeners: Vec<ListenerEndpointInfo>,
+    /// Callsign and client certificate name (server default when empty)
+    pub username: String,
 }
 
 /// Loaded package information
@@ -33,6 +54,10 @@ pub struct LoadedPackage {
     pub name: String,
     pub summary: PackageSummary,
     pub contents: Vec<PackageContent>,
+    /// Server connections from the package's preference files
+    pub streams: Vec<StreamPreference>,
+    /// Problems that would stop the package working on a device
+    pub validation: ValidationReport,
 }
 
 /// Package creation dialog state
@@ -69,6 +94,7 @@ pub fn render_datapackage_panel(
     ui: &mut Ui,
     _state: &Arc<Mutex<AppState>>,
     panel_state: &mut DataPackagePanelState,
+    api_client: Option<&ApiClient>,
 ) -> Option<(String, CrateStatusLevel)> {
     let mut status_to_return = None;
 
@@ -78,34 +104,27 @@ pub fn render_datapackage_panel(
     // Check for import completion
     if let Some(promise) = &panel_state.import_promise {
         if let Some(result) = promise.ready() {
-            if let Some(path) = result {
-                match DataPackageReader::open(path) {
-                    Ok(reader) => {
-                        let loaded = LoadedPackage {
-                            path: path.clone(),
-                            uid: reader.uid().unwrap_or("unknown").to_string(),
-                            name: reader.name().unwrap_or("unknown").to_string(),
-                            summary: reader.summary(),
-                            contents: reader.contents().to_vec(),
-                        };
-                        panel_state.loaded_package = Some(loaded);
-                        panel_state.status_message = Some((
-                            format!("Loaded package: {}", path.display()),
-                            StatusLevel::Success,
-                        ));
-                    }
-                    Err(e) => {
-                        panel_state.status_message = Some((
-                            format!("Failed to load package: {}", e),
-                            StatusLevel::Error,
-                        ));
-                    }
-                }
+            if let Some(path) = result.clone() {
+                load_package(panel_state, &path);
             }
             panel_state.import_promise = None;
         }
     }
 
+    // Check for connection import completion
+    if let Some(promise) = &panel_state.connections_promise {
+        if let Some(result) = promise.ready() {
+            panel_state.status_message = Some(match result {
+                Ok(response) => import_summary(response),
+                Err(e) => (
+                    format!("Failed to create connections: {}", e),
+                    StatusLevel::Error,
+                ),
+            });
+            panel_state.connections_promise = None;
+        }
+    }
+
     // Check for export completion
     if let Some(promise) = &panel_state.export_promise {
         if let Some(result) = promise.ready() {
@@ -127,6 +146,55 @@ pub fn render_datapackage_panel(
         }
     }
 
+    // Check for listener list completion
+    if let Some(promise) = &panel_state.listeners_promise {
+        if let Some(result) = promise.ready() {
+            match result {
+                Ok(listeners) if listeners.is_empty() => {
+                    panel_state.status_message = Some((
+                        "No inbound listeners are configured".to_string(),
+                        StatusLevel::Warning,
+                    ));
+                }
+                Ok(listeners) => {
+                    panel_state.onboarding = Some(OnboardingDialog {
+                        listeners: listeners.clone(),
+                        username: String::new(),
+                    });
+                }
+                Err(e) => {
+                    panel_state.status_message = Some((
+                        format!("Failed to list listeners: {}", e),
+                        StatusLevel::Error,
+                    ));
+                }
+            }
+            panel_state.listeners_promise = None;
+        }
+    }
+
+    // Check for onboarding package completion
+    if let Some(promise) = &panel_state.onboarding_promise {
+        if let Some(result) = promise.ready() {
+            match result {
+                Ok(Some(path)) => {
+                    panel_state.status_message = Some((
+                        format!("Onboarding package saved: {}", path.display()),
+                        StatusLevel::Success,
+                    ));
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    panel_state.status_message = Some((
+                        format!("Onboarding package failed: {}", e),
+                        StatusLevel::Error,
+                    ));
+                }
+            }
+            panel_state.onboarding_promise = None;
+        }
+    }
+
     // Show status message
     if let Some((msg, level)) = &panel_state.status_message {
         ui.colored_label(level.color(), msg);
@@ -143,6 +211,17 @@ pub fn render_datapackage_panel(
             panel_state.create_dialog = Some(CreatePackageDialog::default());
         }
 
+        let can_onboard = api_client.is_some() && panel_state.listeners_promise.is_none();
+        if ui
+            .add_enabled(can_onboard, Button::new("📱 Onboard Device"))
+            .on_disabled_hover_text("Requires a connection to the OmniTAK server")
+            .clicked()
+        {
+            if let Some(client) = api_client {
+                panel_state.listeners_promise = Some(spawn_list_listeners(client.clone()));
+            }
+        }
+
         if panel_state.loaded_package.is_some() {
             if ui.button("🗑 Close Package").clicked() {
                 panel_state.loaded_package = None;
@@ -160,11 +239,13 @@ pub fn render_datapackage_panel(
     if has_loaded_package {
         // Clone the loaded package to avoid borrow conflicts
         let pkg = panel_state.loaded_package.as_ref().unwrap().clone();
-        render_package_details(ui, &pkg, panel_state);
+        render_package_details(ui, &pkg, panel_state, api_client);
     } else if has_create_dialog {
         render_create_dialog(ui, panel_state);
+    } else if panel_state.onboarding.is_some() {
+        render_onboarding_dialog(ui, panel_state, api_client);
     } else {
-        ui.label("No package loaded. Import a .dpk/.zip file or create a new package.");
+        ui.label("No package loaded. Import or drop a .dpk/.zip file, or create a new package.");
     }
 
     // Convert status message for return
@@ -181,10 +262,99 @@ pub fn render_datapackage_panel(
     status_to_return
 }
 
+/// Open the package at `path` as the loaded package
+fn load_package(panel_state: &mut DataPackagePanelState, path: &Path) {
+    let loaded = DataPackageReader::open(path).and_then(|reader| {
+        // Malformed preferences show up in the validation report
+        Ok(LoadedPackage {
+            path: path.to_path_buf(),
+            uid: reader.uid().unwrap_or("unknown").to_string(),
+            name: reader.name().unwrap_or("unknown").to_string(),
+            summary: reader.summary(),
+            contents: reader.contents().to_vec(),
+            streams: reader.stream_preferences().unwrap_or_default(),
+            validation: reader.lint()?,
+        })
+    });
+
+    match loaded {
+        Ok(loaded) => {
+            panel_state.stream_selection = loaded.streams.iter().map(|s| s.enabled).collect();
+            let mut message = match loaded.streams.len() {
+                0 => format!("Loaded package: {}", path.display()),
+                n => format!(
+                    "Loaded package: {} ({} server connection(s) found)",
+                    path.display(),
+                    n
+                ),
+            };
+            let level = match loaded.validation.errors().count() {
+                0 => StatusLevel::Success,
+                n => {
+                    message.push_str(&format!(" - {} problem(s) found", n));
+                    StatusLevel::Warning
+                }
+            };
+            panel_state.loaded_package = Some(loaded);
+            panel_state.status_message = Some((message, level));
+        }
+        Err(e) => {
+            panel_state.status_message =
+                Some((format!("Failed to load package: {}", e), StatusLevel::Error));
+        }
+    }
+}
+
+/// Load a data package dropped onto the window, returning whether one was
+pub fn handle_dropped_files(ctx: &egui::Context, panel_state: &mut DataPackagePanelState) -> bool {
+    let dropped = ctx.input(|i| {
+        i.raw
+            .dropped_files
+            .iter()
+            .filter_map(|f| f.path.clone())
+            .find(|p| is_package_file(p))
+    });
+
+    match dropped {
+        Some(path) => {
+            panel_state.create_dialog = None;
+            panel_state.onboarding = None;
+            load_package(panel_state, &path);
+            true
+        }
+        None => false,
+    }
+}
+
+fn is_package_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("zip") || e.eq_ignore_ascii_case("dpk"))
+}
+
+/// Status line for a finished connection import
+fn import_summary(response: &ImportConnectionsResponse) -> (String, StatusLevel) {
+    let created = format!("Created {} connection(s)", response.created.len());
+    if response.skipped.is_empty() {
+        return (created, StatusLevel::Success);
+    }
+
+    let skipped: Vec<String> = response
+        .skipped
+        .iter()
+        .map(|s| format!("{}: {}", s.name, s.reason))
+        .collect();
+    (
+        format!("{}; skipped {}", created, skipped.join("; ")),
+        StatusLevel::Warning,
+    )
+}
+
 fn render_package_details(
     ui: &mut Ui,
     pkg: &LoadedPackage,
     panel_state: &mut DataPackagePanelState,
+    api_client: Option<&ApiClient>,
 ) {
     ui.heading("Package Details");
 
@@ -252,6 +422,16 @@ fn render_package_details(
             }
         });
 
+    if !pkg.validation.issues.is_empty() {
+        ui.add_space(8.0);
+        render_validation(ui, &pkg.validation);
+    }
+
+    if !pkg.streams.is_empty() {
+        ui.add_space(8.0);
+        render_stream_connections(ui, pkg, panel_state, api_client);
+    }
+
     ui.add_space(8.0);
     ui.heading("Files");
 
@@ -301,6 +481,98 @@ fn render_package_details(
     });
 }
 
+/// Problems found when linting the package
+fn render_validation(ui: &mut Ui, report: &ValidationReport) {
+    ui.heading("Validation");
+    if report.is_valid() {
+        ui.colored_label(
+            StatusLevel::Warning.color(),
+            "Package will import, but check these warnings:",
+        );
+    } else {
+        ui.colored_label(
+            StatusLevel::Error.color(),
+            "Package is broken and should not be sent to devices:",
+        );
+    }
+
+    for issue in &report.issues {
+        let (icon, level) = match issue.severity {
+            Severity::Error => ("❌", StatusLevel::Error),
+            Severity::Warning => ("⚠", StatusLevel::Warning),
+        };
+        ui.horizontal_wrapped(|ui| {
+            ui.colored_label(level.color(), icon);
+            if let Some(path) = &issue.path {
+                ui.monospace(path);
+            }
+            ui.label(&issue.message);
+        });
+    }
+}
+
+/// Server connections from the package, with the option to create them
+fn render_stream_connections(
+    ui: &mut Ui,
+    pkg: &LoadedPackage,
+    panel_state: &mut DataPackagePanelState,
+    api_client: Option<&ApiClient>,
+) {
+    ui.heading("Server Connections");
+    panel_state
+        .stream_selection
+        .resize(pkg.streams.len(), false);
+
+    egui::Grid::new("package_streams")
+        .num_columns(4)
+        .spacing([12.0, 4.0])
+        .show(ui, |ui| {
+            for (stream, selected) in pkg.streams.iter().zip(&mut panel_state.stream_selection) {
+                ui.checkbox(selected, &stream.description);
+                ui.monospace(format!("{}:{}", stream.host, stream.port));
+                ui.label(format!("{:?}", stream.protocol).to_uppercase());
+                if stream.certificate_location.is_some() {
+                    ui.label("🔐 client certificate");
+                } else if stream.enroll_for_certificate {
+                    ui.label("requires enrollment");
+                } else {
+                    ui.label("");
+                }
+                ui.end_row();
+            }
+        });
+
+    let streams: Vec<usize> = panel_state
+        .stream_selection
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &selected)| selected.then_some(i))
+        .collect();
+    let busy = panel_state.connections_promise.is_some();
+
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(
+                api_client.is_some() && !streams.is_empty() && !busy,
+                Button::new("🔌 Create Connections"),
+            )
+            .on_disabled_hover_text("Requires a connection to the OmniTAK server")
+            .clicked()
+        {
+            if let Some(client) = api_client {
+                panel_state.connections_promise = Some(spawn_import_connections(
+                    client.clone(),
+                    pkg.path.clone(),
+                    streams,
+                ));
+            }
+        }
+        if busy {
+            ui.spinner();
+        }
+    });
+}
+
 fn render_create_dialog(ui: &mut Ui, panel_state: &mut DataPackagePanelState) {
     // Extract values we need before the borrow
     let (name, delete_on_receive, files_to_add, cot_events) = {
@@ -427,6 +699,76 @@ fn render_create_dialog(ui: &mut Ui, panel_state: &mut DataPackagePanelState) {
     }
 }
 
+fn render_onboarding_dialog(
+    ui: &mut Ui,
+    panel_state: &mut DataPackagePanelState,
+    api_client: Option<&ApiClient>,
+) {
+    let busy = panel_state.onboarding_promise.is_some();
+    let mut download = None;
+    let mut close = false;
+    let dialog = panel_state.onboarding.as_mut().unwrap();
+
+    ui.heading("Onboard a Device");
+    ui.label(
+        "Save a data package for a listener and import it on the device (ATAK: \
+         Import → Local SD). It holds the connection settings, the trust store and, \
+         when the listener requires one, a new client certificate.",
+    );
+    ui.add_space(8.0);
+
+    ui.horizontal(|ui| {
+        ui.label("Callsign:");
+        ui.add(
+            egui::TextEdit::singleline(&mut dialog.username)
+                .hint_text("omnitak-<listener>")
+                .desired_width(200.0),
+        );
+    });
+    ui.add_space(8.0);
+
+    egui::Grid::new("onboarding_listeners")
+        .num_columns(4)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            for listener in &dialog.listeners {
+                ui.monospace(&listener.id);
+                ui.label(format!("port {}", listener.port));
+                ui.label(match (listener.tls, listener.client_auth) {
+                    (false, _) => "TCP",
+                    (true, false) => "TLS",
+                    (true, true) => "TLS + client cert",
+                });
+                if ui
+                    .add_enabled(!busy, Button::new("💾 Save Package"))
+                    .clicked()
+                {
+                    download = Some(listener.id.clone());
+                }
+                ui.end_row();
+            }
+        });
+
+    ui.add_space(8.0);
+    ui.horizontal(|ui| {
+        if ui.button("Close").clicked() {
+            close = true;
+        }
+        if busy {
+            ui.spinner();
+        }
+    });
+
+    if let (Some(id), Some(client)) = (download, api_client) {
+        let username = Some(dialog.username.trim().to_string()).filter(|u| !u.is_empty());
+        panel_state.onboarding_promise =
+            Some(spawn_save_onboarding_package(client.clone(), id, username));
+    }
+    if close {
+        panel_state.onboarding = None;
+    }
+}
+
 fn format_size(bytes: u64) -> String {
     if bytes < 1024 {
         format!("{} B", bytes)
@@ -446,3 +788,84 @@ fn spawn_file_picker() -> Promise<Option<PathBuf>> {
             .pick_file()
     })
 }
+
+fn spawn_list_listeners(client: ApiClient) -> Promise<Result<Vec<ListenerEndpointInfo>, String>> {
+    Promise::spawn_thread("list_listeners", move || {
+        let rt = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+        rt.block_on(client.list_listener_endpoints())
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// Upload the package so the server creates connections for `streams`
+fn spawn_import_connections(
+    client: ApiClient,
+    path: PathBuf,
+    streams: Vec<usize>,
+) -> Promise<Result<ImportConnectionsResponse, String>> {
+    Promise::spawn_thread("import_connections", move || {
+        let package = std::fs::read(&path).map_err(|e| e.to_string())?;
+        let rt = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+        rt.block_on(client.import_package_connections(package, &streams))
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// Ask where to save, then download the package for listener `id`
+fn spawn_save_onboarding_package(
+    client: ApiClient,
+    id: String,
+    username: Option<String>,
+) -> Promise<Result<Option<PathBuf>, String>> {
+    Promise::spawn_thread("onboarding_package", move || {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("TAK Data Package", &["zip"])
+            .set_file_name(format!("omnitak-{}.zip", id))
+            .set_title("Save Onboarding Package")
+            .save_file()
+        else {
+            return Ok(None);
+        };
+
+        let rt = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+        let data = rt
+            .block_on(client.download_listener_package(&id, username.as_deref()))
+            .map_err(|e| e.to_string())?;
+        std::fs::write(&path, data).map_err(|e| e.to_string())?;
+        Ok(Some(path))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_client::{ImportedConnection, SkippedConnection};
+
+    #[test]
+    fn test_import_summary() {
+        let mut response = ImportConnectionsResponse {
+            created: vec![ImportedConnection {
+                index: 0,
+                id: uuid::Uuid::new_v4(),
+                name: "Primary".to_string(),
+                certificate_id: None,
+            }],
+            skipped: Vec::new(),
+        };
+        let (message, level) = import_summary(&response);
+        assert_eq!(message, "Created 1 connection(s)");
+        assert!(level == StatusLevel::Success);
+
+        response.skipped.push(SkippedConnection {
+            index: 1,
+            name: "Mesh".to_string(),
+            reason: "Udp streams are not supported".to_string(),
+        });
+        let (message, level) = import_summary(&response);
+        assert!(message.ends_with("skipped Mesh: Udp streams are not supported"));
+        assert!(level == StatusLevel::Warning);
+
+        assert!(is_package_file(Path::new("/tmp/Server.DPK")));
+        assert!(!is_package_file(Path::new("/tmp/server.pref")));
+    }
+}
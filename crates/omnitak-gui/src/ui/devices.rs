@@ -0,0 +1,425 @@
+//! Android devices panel
+//!
+//! Lists the devices the server reaches over ADB and pulls ATAK
+//! certificates from them. Devices without a cable are paired and connected
+//! over Wi-Fi using the code shown under Developer options > Wireless
+//! debugging. The list follows devices being attached and detached through
+//! server events when the server runs its device monitor.
+
+use eframe::egui;
+use poll_promise::Promise;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::api_client::{DeviceInfoResponse, PairDeviceRequest};
+use crate::events::ServerEvent;
+use crate::{ApiClient, AppState, StatusLevel};
+
+/// How often the device list is reloaded while the panel is shown
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// State for the Devices panel
+#[derive(Default)]
+pub struct DevicesPanelState {
+    /// Devices from the last refresh
+    pub devices: Vec<DeviceInfoResponse>,
+    /// Error from the last refresh, e.g. ADB not installed on the server
+    pub list_error: Option<String>,
+    /// Device list request in flight
+    pub list_promise: Option<Promise<Result<Vec<DeviceInfoResponse>, String>>>,
+    /// When the list was last requested
+    pub last_refresh: Option<Instant>,
+    /// Pair, connect, disconnect or pull in flight; resolves to a message
+    pub action_promise: Option<Promise<Result<String, String>>>,
+    /// Address typed into the connect field
+    pub connect_address: String,
+    /// Wi-Fi pairing dialog
+    pub pair_dialog: Option<PairDialog>,
+}
+
+impl DevicesPanelState {
+    /// Reloads the list the next time the panel is shown
+    pub fn reload(&mut self) {
+        self.last_refresh = None;
+    }
+}
+
+/// Applies a device event pushed by the server, returning a notice for the
+/// status bar
+pub fn apply_event(
+    panel_state: &mut DevicesPanelState,
+    event: &ServerEvent,
+) -> Option<(String, StatusLevel)> {
+    let serial = event.details.get("serial")?.as_str()?;
+    match event.event.as_str() {
+        "device_attached" => {
+            panel_state.reload();
+            let model = event.details.get("model").and_then(|m| m.as_str());
+            Some((
+                format!("Android device attached: {}", model.unwrap_or(serial)),
+                StatusLevel::Info,
+            ))
+        }
+        "device_detached" => {
+            panel_state.devices.retain(|d| d.serial != serial);
+            None
+        }
+        "device_certificates_pulled" => {
+            let message = event.details.get("message").and_then(|m| m.as_str());
+            let success = event
+                .details
+                .get("success")
+                .and_then(|s| s.as_bool())
+                .unwrap_or(false);
+            Some(if success {
+                (
+                    format!("Pulled certificates from {}", serial),
+                    StatusLevel::Success,
+                )
+            } else {
+                (
+                    format!(
+                        "Certificate pull from {} failed: {}",
+                        serial,
+                        message.unwrap_or("unknown error")
+                    ),
+                    StatusLevel::Error,
+                )
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Wi-Fi pairing dialog
+#[derive(Default)]
+pub struct PairDialog {
+    /// Pairing address (host:port) shown next to the code
+    pub address: String,
+    pub code: String,
+    /// Address under Wireless debugging > IP address & Port
+    pub connect_address: String,
+}
+
+/// Render the devices panel
+pub fn render_devices_panel(
+    ui: &mut egui::Ui,
+    _state: &Arc<Mutex<AppState>>,
+    panel_state: &mut DevicesPanelState,
+    api_client: Option<&ApiClient>,
+) -> Option<(String, StatusLevel)> {
+    let mut status_message = None;
+
+    if let Some(promise) = &panel_state.list_promise {
+        if let Some(result) = promise.ready() {
+            match result {
+                Ok(devices) => {
+                    panel_state.devices = devices.clone();
+                    panel_state.list_error = None;
+                }
+                Err(e) => panel_state.list_error = Some(e.clone()),
+            }
+            panel_state.list_promise = None;
+        }
+    }
+
+    if let Some(promise) = &panel_state.action_promise {
+        if let Some(result) = promise.ready() {
+            status_message = Some(match result {
+                Ok(message) => (message.clone(), StatusLevel::Success),
+                Err(e) => (e.clone(), StatusLevel::Error),
+            });
+            panel_state.action_promise = None;
+            panel_state.reload();
+        }
+    }
+
+    let due = panel_state
+        .last_refresh
+        .is_none_or(|t| t.elapsed() > REFRESH_INTERVAL);
+    if due && panel_state.list_promise.is_none() {
+        if let Some(client) = api_client {
+            panel_state.list_promise = Some(spawn_list_devices(client.clone()));
+            panel_state.last_refresh = Some(Instant::now());
+        }
+    }
+
+    ui.heading("Android Devices");
+    ui.add_space(10.0);
+
+    if api_client.is_none() {
+        ui.colored_label(
+            egui::Color32::YELLOW,
+            "Not connected to server. Connect to see ADB devices.",
+        );
+        ui.add_space(10.0);
+    }
+
+    let busy = panel_state.action_promise.is_some();
+    let mut connect = None;
+    let mut pull_all = false;
+    ui.add_enabled_ui(api_client.is_some(), |ui| {
+        ui.horizontal(|ui| {
+            if ui.button("🔄 Refresh").clicked() {
+                panel_state.reload();
+            }
+            if ui.button("📶 Pair over Wi-Fi...").clicked() && panel_state.pair_dialog.is_none() {
+                panel_state.pair_dialog = Some(PairDialog::default());
+            }
+            let online = panel_state.devices.iter().any(|d| d.state == "device");
+            if ui
+                .add_enabled(!busy && online, egui::Button::new("⬇ Pull from All"))
+                .on_hover_text("Pull ATAK certificates from every online device")
+                .clicked()
+            {
+                pull_all = true;
+            }
+            ui.separator();
+            ui.label("Connect:");
+            ui.add(
+                egui::TextEdit::singleline(&mut panel_state.connect_address)
+                    .hint_text("192.168.1.20:5555")
+                    .desired_width(160.0),
+            );
+            let address = panel_state.connect_address.trim();
+            if ui
+                .add_enabled(!busy && !address.is_empty(), egui::Button::new("Connect"))
+                .on_hover_text("Connect to a paired device or one in `adb tcpip` mode")
+                .clicked()
+            {
+                connect = Some(address.to_string());
+            }
+            if panel_state.list_promise.is_some() || busy {
+                ui.spinner();
+            }
+        });
+    });
+
+    if let Some(error) = &panel_state.list_error {
+        ui.colored_label(egui::Color32::LIGHT_RED, error);
+    }
+
+    ui.separator();
+
+    let mut pull = None;
+    let mut disconnect = None;
+    let mut approve = None;
+    egui::ScrollArea::vertical()
+        .auto_shrink([false; 2])
+        .show(ui, |ui| {
+            if panel_state.devices.is_empty() {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(50.0);
+                    ui.label("No devices attached. Plug one in over USB or pair over Wi-Fi.");
+                });
+                return;
+            }
+
+            egui::Grid::new("adb_devices")
+                .num_columns(6)
+                .striped(true)
+                .spacing([16.0, 6.0])
+                .show(ui, |ui| {
+                    ui.strong("Serial");
+                    ui.strong("Model");
+                    ui.strong("Link");
+                    ui.strong("State");
+                    ui.strong("Auto-pull");
+                    ui.label("");
+                    ui.end_row();
+
+                    for device in &panel_state.devices {
+                        ui.label(&device.serial);
+                        ui.label(device.model.as_deref().unwrap_or("-"))
+                            .on_hover_text(device.product.as_deref().unwrap_or_default());
+                        ui.label(if device.wireless {
+                            "📶 Wi-Fi"
+                        } else {
+                            "🔌 USB"
+                        });
+                        ui.colored_label(state_color(&device.state), &device.state);
+
+                        let mut approved = device.approved;
+                        if ui
+                            .add_enabled(
+                                api_client.is_some() && !busy,
+                                egui::Checkbox::without_text(&mut approved),
+                            )
+                            .on_hover_text("Pull certificates whenever this device is attached")
+                            .changed()
+                        {
+                            approve = Some((device.serial.clone(), approved));
+                        }
+
+                        ui.horizontal(|ui| {
+                            let ready = api_client.is_some() && !busy && device.state == "device";
+                            if ui
+                                .add_enabled(ready, egui::Button::new("⬇ Pull Certificates"))
+                                .on_hover_text("Pull ATAK certificates and add a connection")
+                                .on_disabled_hover_text("Device must be online and authorized")
+                                .clicked()
+                            {
+                                pull = Some(device.serial.clone());
+                            }
+                            if device.wireless
+                                && ui
+                                    .add_enabled(!busy, egui::Button::new("Disconnect"))
+                                    .clicked()
+                            {
+                                disconnect = Some(device.serial.clone());
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+        });
+
+    let action = connect
+        .map(DeviceAction::Connect)
+        .or(disconnect.map(DeviceAction::Disconnect))
+        .or(pull.map(DeviceAction::PullCertificates))
+        .or(pull_all.then_some(DeviceAction::PullAll))
+        .or(approve.map(|(serial, approved)| DeviceAction::SetApproved(serial, approved)));
+    if let (Some(action), Some(client)) = (action, api_client) {
+        panel_state.action_promise = Some(spawn_action(client.clone(), action));
+    }
+
+    render_pair_dialog(ui, panel_state, api_client);
+
+    status_message
+}
+
+fn render_pair_dialog(
+    ui: &mut egui::Ui,
+    panel_state: &mut DevicesPanelState,
+    api_client: Option<&ApiClient>,
+) {
+    let busy = panel_state.action_promise.is_some();
+    let Some(dialog) = &mut panel_state.pair_dialog else {
+        return;
+    };
+
+    let mut open = true;
+    let mut submit = false;
+    egui::Window::new("Pair Device over Wi-Fi")
+        .open(&mut open)
+        .resizable(false)
+        .collapsible(false)
+        .show(ui.ctx(), |ui| {
+            ui.label(
+                "On the device, open Developer options > Wireless debugging > \
+                 Pair device with pairing code, and enter what it shows.",
+            );
+            ui.add_space(8.0);
+
+            egui::Grid::new("adb_pair_grid")
+                .num_columns(2)
+                .spacing([8.0, 6.0])
+                .show(ui, |ui| {
+                    ui.label("Pairing address:");
+                    ui.add(egui::TextEdit::singleline(&mut dialog.address).hint_text("192.168.1.20:37123"));
+                    ui.end_row();
+
+                    ui.label("Pairing code:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut dialog.code)
+                            .hint_text("123456")
+                            .char_limit(6),
+                    );
+                    ui.end_row();
+
+                    ui.label("Connect address:");
+                    ui.add(egui::TextEdit::singleline(&mut dialog.connect_address).hint_text("192.168.1.20:41567"))
+                        .on_hover_text("IP address & Port on the Wireless debugging screen. Leave empty to pair only.");
+                    ui.end_row();
+                });
+
+            ui.add_space(8.0);
+            let code_valid = dialog.code.len() == 6 && dialog.code.chars().all(|c| c.is_ascii_digit());
+            let ready = !busy && code_valid && !dialog.address.trim().is_empty();
+            if ui.add_enabled(ready, egui::Button::new("Pair")).clicked() {
+                submit = true;
+            }
+        });
+
+    if submit {
+        if let Some(client) = api_client {
+            let connect_address = dialog.connect_address.trim();
+            let request = PairDeviceRequest {
+                address: dialog.address.trim().to_string(),
+                pairing_code: dialog.code.clone(),
+                connect_address: (!connect_address.is_empty()).then(|| connect_address.to_string()),
+            };
+            panel_state.action_promise =
+                Some(spawn_action(client.clone(), DeviceAction::Pair(request)));
+        }
+    }
+    if submit || !open {
+        panel_state.pair_dialog = None;
+    }
+}
+
+fn state_color(state: &str) -> egui::Color32 {
+    match state {
+        "device" => egui::Color32::GREEN,
+        "unauthorized" => egui::Color32::YELLOW,
+        _ => egui::Color32::GRAY,
+    }
+}
+
+/// Operation started from the panel
+enum DeviceAction {
+    Pair(PairDeviceRequest),
+    Connect(String),
+    Disconnect(String),
+    PullCertificates(String),
+    PullAll,
+    SetApproved(String, bool),
+}
+
+fn spawn_action(client: ApiClient, action: DeviceAction) -> Promise<Result<String, String>> {
+    Promise::spawn_thread("adb_action", move || {
+        let rt = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+        rt.block_on(async {
+            match action {
+                DeviceAction::Pair(request) => {
+                    client.pair_adb_device(&request).await.map(|r| r.message)
+                }
+                DeviceAction::Connect(address) => {
+                    client.connect_adb_device(&address).await.map(|r| r.message)
+                }
+                DeviceAction::Disconnect(serial) => client
+                    .disconnect_adb_device(&serial)
+                    .await
+                    .map(|r| r.message),
+                DeviceAction::SetApproved(serial, approved) => client
+                    .set_adb_device_approved(&serial, approved)
+                    .await
+                    .map(|()| {
+                        if approved {
+                            format!("Certificates will be pulled whenever {} is attached", serial)
+                        } else {
+                            format!("Stopped automatic certificate pulls from {}", serial)
+                        }
+                    }),
+                DeviceAction::PullCertificates(serial) => client
+                    .pull_adb_certificates(&serial)
+                    .await
+                    .map(|r| r.message),
+                DeviceAction::PullAll => client
+                    .pull_all_adb_certificates()
+                    .await
+                    .map(|r| r.message),
+            }
+        })
+        .map_err(|e| e.to_string())
+    })
+}
+
+fn spawn_list_devices(client: ApiClient) -> Promise<Result<Vec<DeviceInfoResponse>, String>> {
+    Promise::spawn_thread("list_adb_devices", move || {
+        let rt = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+        rt.block_on(client.list_adb_devices())
+            .map_err(|e| e.to_string())
+    })
+}
@@ -0,0 +1,388 @@
+//! Network discovery panel
+//!
+//! Lists TAK servers and devices found on the local network over mDNS and
+//! adds a server as a connection with one click. Servers that need TLS open
+//! a prompt for the client certificate first. The list is loaded once and
+//! kept current by the server's `service_*` events.
+
+use eframe::egui;
+use poll_promise::Promise;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::api_client::{
+    AdoptServiceRequest, AdoptServiceResponse, ConnectionType, DiscoveredServiceResponse,
+    ServiceStatus,
+};
+use crate::events::ServerEvent;
+use crate::{ApiClient, AppState, StatusLevel};
+
+/// How often the whole list is reloaded while the panel is shown, in case
+/// an event was missed
+const REFRESH_INTERVAL: Duration = Duration::from_secs(120);
+
+/// State for the Discovery panel
+#[derive(Default)]
+pub struct DiscoveryPanelState {
+    /// Services from the last refresh
+    pub services: Vec<DiscoveredServiceResponse>,
+    /// Error from the last refresh, e.g. discovery disabled on the server
+    pub list_error: Option<String>,
+    /// Service list request in flight
+    pub list_promise: Option<Promise<Result<Vec<DiscoveredServiceResponse>, String>>>,
+    /// When the list was last requested
+    pub last_refresh: Option<Instant>,
+    /// Adoption in flight
+    pub adopt_promise: Option<Promise<Result<AdoptServiceResponse, String>>>,
+    /// Client certificate prompt for a TLS server
+    pub cert_prompt: Option<CertificatePrompt>,
+}
+
+impl DiscoveryPanelState {
+    /// Reloads the list the next time the panel is shown
+    pub fn reload(&mut self) {
+        self.last_refresh = None;
+    }
+}
+
+/// Applies a `service_appeared`, `service_updated` or `service_lost` event
+/// to the list, returning a notice when a server that can be added appears
+pub fn apply_event(
+    panel_state: &mut DiscoveryPanelState,
+    event: &ServerEvent,
+) -> Option<(String, StatusLevel)> {
+    if !event.event.starts_with("service_") {
+        return None;
+    }
+    let service: DiscoveredServiceResponse = serde_json::from_value(event.details.clone()).ok()?;
+    let existing = panel_state.services.iter().position(|s| s.id == service.id);
+
+    match (event.event.as_str(), existing) {
+        ("service_lost", Some(index)) => {
+            panel_state.services.remove(index);
+            None
+        }
+        ("service_appeared" | "service_updated", Some(index)) => {
+            panel_state.services[index] = service;
+            None
+        }
+        ("service_appeared" | "service_updated", None) => {
+            let notice = (event.event == "service_appeared" && service.adopt_as.is_some())
+                .then(|| {
+                    (
+                        format!(
+                            "Discovered TAK server {} at {}",
+                            display_name(&service),
+                            service.connection_string
+                        ),
+                        StatusLevel::Info,
+                    )
+                });
+            panel_state.services.push(service);
+            notice
+        }
+        _ => None,
+    }
+}
+
+/// Client certificate prompt shown before adopting a TLS server
+pub struct CertificatePrompt {
+    pub service_id: String,
+    pub service_name: String,
+    pub cert_path: String,
+    pub key_path: String,
+    pub validate_certs: bool,
+    pub cert_picker: Option<Promise<Option<PathBuf>>>,
+    pub key_picker: Option<Promise<Option<PathBuf>>>,
+}
+
+impl CertificatePrompt {
+    fn new(service: &DiscoveredServiceResponse) -> Self {
+        Self {
+            service_id: service.id.clone(),
+            service_name: display_name(service).to_string(),
+            cert_path: String::new(),
+            key_path: String::new(),
+            validate_certs: true,
+            cert_picker: None,
+            key_picker: None,
+        }
+    }
+}
+
+/// Render the discovery panel
+pub fn render_discovery_panel(
+    ui: &mut egui::Ui,
+    _state: &Arc<Mutex<AppState>>,
+    panel_state: &mut DiscoveryPanelState,
+    api_client: Option<&ApiClient>,
+) -> Option<(String, StatusLevel)> {
+    let mut status_message = None;
+
+    if let Some(promise) = &panel_state.list_promise {
+        if let Some(result) = promise.ready() {
+            match result {
+                Ok(services) => {
+                    panel_state.services = services.clone();
+                    panel_state.list_error = None;
+                }
+                Err(e) => panel_state.list_error = Some(e.clone()),
+            }
+            panel_state.list_promise = None;
+        }
+    }
+
+    if let Some(promise) = &panel_state.adopt_promise {
+        if let Some(result) = promise.ready() {
+            status_message = Some(match result {
+                Ok(connection) => (
+                    format!(
+                        "Added connection {} ({}:{})",
+                        connection.name, connection.address, connection.port
+                    ),
+                    StatusLevel::Success,
+                ),
+                Err(e) => (format!("Failed to add server: {}", e), StatusLevel::Error),
+            });
+            panel_state.adopt_promise = None;
+        }
+    }
+
+    let due = panel_state
+        .last_refresh
+        .is_none_or(|t| t.elapsed() > REFRESH_INTERVAL);
+    if due && panel_state.list_promise.is_none() {
+        if let Some(client) = api_client {
+            panel_state.list_promise = Some(spawn_list_services(client.clone()));
+            panel_state.last_refresh = Some(Instant::now());
+        }
+    }
+
+    ui.heading("Network Discovery");
+    ui.add_space(10.0);
+
+    if api_client.is_none() {
+        ui.colored_label(
+            egui::Color32::YELLOW,
+            "Not connected to server. Connect to see discovered services.",
+        );
+        ui.add_space(10.0);
+    }
+
+    ui.horizontal(|ui| {
+        ui.add_enabled_ui(api_client.is_some(), |ui| {
+            if ui.button("🔄 Refresh").clicked() {
+                panel_state.reload();
+            }
+        });
+        if panel_state.list_promise.is_some() || panel_state.adopt_promise.is_some() {
+            ui.spinner();
+        }
+    });
+
+    if let Some(error) = &panel_state.list_error {
+        ui.colored_label(egui::Color32::LIGHT_RED, error);
+    }
+
+    ui.separator();
+
+    let mut adopt = None;
+    egui::ScrollArea::vertical()
+        .auto_shrink([false; 2])
+        .show(ui, |ui| {
+            if panel_state.services.is_empty() {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(50.0);
+                    ui.label("No services discovered yet");
+                });
+                return;
+            }
+
+            egui::Grid::new("discovered_services")
+                .num_columns(6)
+                .striped(true)
+                .spacing([16.0, 6.0])
+                .show(ui, |ui| {
+                    ui.strong("Name");
+                    ui.strong("Type");
+                    ui.strong("Address");
+                    ui.strong("Protocol");
+                    ui.strong("Status");
+                    ui.label("");
+                    ui.end_row();
+
+                    for service in &panel_state.services {
+                        ui.label(display_name(service))
+                            .on_hover_text(&service.instance_name);
+                        ui.label(&service.service_type);
+                        ui.label(&service.connection_string)
+                            .on_hover_text(&service.hostname);
+                        ui.label(match service.adopt_as {
+                            Some(ConnectionType::Tlsclient) => "🔒 TLS",
+                            Some(_) => "TCP",
+                            None => "-",
+                        });
+                        let (status, color) = status_label(service.status);
+                        ui.colored_label(color, status);
+
+                        let can_add = api_client.is_some()
+                            && service.adopt_as.is_some()
+                            && panel_state.adopt_promise.is_none();
+                        if ui
+                            .add_enabled(can_add, egui::Button::new("➕ Add"))
+                            .on_disabled_hover_text("Not a CoT stream that can be added")
+                            .clicked()
+                        {
+                            adopt = Some(service.clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+
+    if let (Some(service), Some(client)) = (adopt, api_client) {
+        if matches!(service.adopt_as, Some(ConnectionType::Tlsclient)) {
+            panel_state.cert_prompt = Some(CertificatePrompt::new(&service));
+        } else {
+            panel_state.adopt_promise = Some(spawn_adopt_service(
+                client.clone(),
+                service.id,
+                AdoptServiceRequest::default(),
+            ));
+        }
+    }
+
+    render_certificate_prompt(ui, panel_state, api_client);
+
+    status_message
+}
+
+fn render_certificate_prompt(
+    ui: &mut egui::Ui,
+    panel_state: &mut DiscoveryPanelState,
+    api_client: Option<&ApiClient>,
+) {
+    let Some(prompt) = &mut panel_state.cert_prompt else {
+        return;
+    };
+
+    for (picker, path) in [
+        (&mut prompt.cert_picker, &mut prompt.cert_path),
+        (&mut prompt.key_picker, &mut prompt.key_path),
+    ] {
+        if let Some(result) = picker.as_ref().and_then(|p| p.ready()).cloned() {
+            if let Some(picked) = result {
+                *path = picked.display().to_string();
+            }
+            *picker = None;
+        }
+    }
+
+    let mut open = true;
+    let mut submit = false;
+    egui::Window::new(format!("Add {}", prompt.service_name))
+        .open(&mut open)
+        .resizable(false)
+        .collapsible(false)
+        .show(ui.ctx(), |ui| {
+            ui.label("This server requires TLS. Select the client certificate to connect with.");
+            ui.add_space(8.0);
+
+            egui::Grid::new("adopt_cert_grid")
+                .num_columns(3)
+                .spacing([8.0, 6.0])
+                .show(ui, |ui| {
+                    ui.label("Certificate:");
+                    ui.text_edit_singleline(&mut prompt.cert_path);
+                    if ui.button("Browse...").clicked() && prompt.cert_picker.is_none() {
+                        prompt.cert_picker = Some(spawn_file_picker("Select Client Certificate"));
+                    }
+                    ui.end_row();
+
+                    ui.label("Private key:");
+                    ui.text_edit_singleline(&mut prompt.key_path);
+                    if ui.button("Browse...").clicked() && prompt.key_picker.is_none() {
+                        prompt.key_picker = Some(spawn_file_picker("Select Private Key"));
+                    }
+                    ui.end_row();
+                });
+
+            ui.checkbox(&mut prompt.validate_certs, "Verify server certificate");
+            ui.add_space(8.0);
+
+            let ready = !prompt.cert_path.trim().is_empty() && !prompt.key_path.trim().is_empty();
+            if ui.add_enabled(ready, egui::Button::new("Add Connection")).clicked() {
+                submit = true;
+            }
+        });
+
+    if submit {
+        if let Some(client) = api_client {
+            let request = AdoptServiceRequest {
+                tls_cert_path: Some(prompt.cert_path.trim().to_string()),
+                tls_key_path: Some(prompt.key_path.trim().to_string()),
+                validate_certs: Some(prompt.validate_certs),
+                ..Default::default()
+            };
+            panel_state.adopt_promise = Some(spawn_adopt_service(
+                client.clone(),
+                prompt.service_id.clone(),
+                request,
+            ));
+        }
+    }
+    if submit || !open {
+        panel_state.cert_prompt = None;
+    }
+}
+
+/// Instance name without the `._tak._tcp.local.` suffix
+fn display_name(service: &DiscoveredServiceResponse) -> &str {
+    service
+        .instance_name
+        .split("._")
+        .next()
+        .filter(|n| !n.is_empty())
+        .unwrap_or(&service.hostname)
+}
+
+fn status_label(status: ServiceStatus) -> (&'static str, egui::Color32) {
+    match status {
+        ServiceStatus::Active => ("active", egui::Color32::GREEN),
+        ServiceStatus::Stale => ("stale", egui::Color32::YELLOW),
+        ServiceStatus::Lost => ("lost", egui::Color32::GRAY),
+    }
+}
+
+fn spawn_list_services(
+    client: ApiClient,
+) -> Promise<Result<Vec<DiscoveredServiceResponse>, String>> {
+    Promise::spawn_thread("list_discovered_services", move || {
+        let rt = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+        rt.block_on(client.list_discovered_services())
+            .map_err(|e| e.to_string())
+    })
+}
+
+fn spawn_adopt_service(
+    client: ApiClient,
+    id: String,
+    request: AdoptServiceRequest,
+) -> Promise<Result<AdoptServiceResponse, String>> {
+    Promise::spawn_thread("adopt_service", move || {
+        let rt = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+        rt.block_on(client.adopt_service(&id, &request))
+            .map_err(|e| e.to_string())
+    })
+}
+
+fn spawn_file_picker(title: &'static str) -> Promise<Option<PathBuf>> {
+    Promise::spawn_thread("file_picker", move || {
+        rfd::FileDialog::new()
+            .add_filter("Certificates", &["pem", "crt", "cer", "key"])
+            .add_filter("All Files", &["*"])
+            .set_title(title)
+            .pick_file()
+    })
+}
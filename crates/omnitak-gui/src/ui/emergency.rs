@@ -0,0 +1,181 @@
+//! Emergency beacon banner.
+//!
+//! Shows open emergencies across the top of every tab, flashing until each
+//! is acknowledged, and plays an alert sound when a new one arrives.
+
+use crate::api_client::{EmergencyInfo, EmergencyStatus};
+use crate::ui::map::SearchTarget;
+use crate::{OmniTakApp, StatusLevel, Tab};
+use eframe::egui;
+use std::time::Duration;
+
+const BANNER_RED: egui::Color32 = egui::Color32::from_rgb(180, 20, 20);
+const BANNER_DARK_RED: egui::Color32 = egui::Color32::from_rgb(110, 10, 10);
+const BANNER_AMBER: egui::Color32 = egui::Color32::from_rgb(150, 90, 0);
+
+/// Shows the banner if any emergencies are open.
+pub fn show_banner(ctx: &egui::Context, app: &mut OmniTakApp) {
+    if app.emergencies.is_empty() {
+        return;
+    }
+
+    let any_active = app
+        .emergencies
+        .iter()
+        .any(|e| e.status == EmergencyStatus::Active);
+    let fill = if any_active {
+        // Flash once a second until everything is acknowledged
+        let phase = ctx.input(|i| i.time) % 1.0;
+        ctx.request_repaint_after(Duration::from_millis(100));
+        if phase < 0.5 {
+            BANNER_RED
+        } else {
+            BANNER_DARK_RED
+        }
+    } else {
+        BANNER_AMBER
+    };
+
+    let mut action = None;
+    egui::TopBottomPanel::top("emergency_banner")
+        .frame(egui::Frame::default().fill(fill).inner_margin(6.0))
+        .show(ctx, |ui| {
+            for emergency in &app.emergencies {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        egui::RichText::new(banner_text(emergency))
+                            .strong()
+                            .color(egui::Color32::WHITE),
+                    );
+                    if let Some(by) = &emergency.acknowledged_by {
+                        ui.label(
+                            egui::RichText::new(format!("acknowledged by {}", by))
+                                .color(egui::Color32::LIGHT_GRAY),
+                        );
+                    }
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("Clear").clicked() {
+                            action = Some(Action::Update(emergency.uid.clone(), "clear"));
+                        }
+                        if emergency.status == EmergencyStatus::Active
+                            && ui.button("Acknowledge").clicked()
+                        {
+                            action = Some(Action::Update(emergency.uid.clone(), "acknowledge"));
+                        }
+                        if ui.button("🗺 Show on map").clicked() {
+                            action = Some(Action::ShowOnMap(emergency.lat, emergency.lon));
+                        }
+                    });
+                });
+            }
+        });
+
+    match action {
+        Some(Action::Update(uid, verb)) => update(app, &uid, verb),
+        Some(Action::ShowOnMap(lat, lon)) => {
+            app.ui_state.selected_tab = Tab::Map;
+            app.ui_state
+                .map_panel
+                .go_to(SearchTarget::Location { lat, lon });
+        }
+        None => {}
+    }
+}
+
+enum Action {
+    Update(String, &'static str),
+    ShowOnMap(f64, f64),
+}
+
+fn update(app: &mut OmniTakApp, uid: &str, verb: &'static str) {
+    let Some(api_client) = &app.api_client else {
+        return;
+    };
+    match app.runtime.block_on(api_client.update_emergency(uid, verb)) {
+        Ok(()) => {
+            if verb == "clear" {
+                app.emergencies.retain(|e| e.uid != uid);
+            } else if let Some(e) = app.emergencies.iter_mut().find(|e| e.uid == uid) {
+                e.status = EmergencyStatus::Acknowledged;
+            }
+        }
+        Err(e) => app.show_status(
+            format!("Emergency update failed: {}", e),
+            StatusLevel::Error,
+            5,
+        ),
+    }
+}
+
+/// One-line description, e.g. `🚨 911 Alert: VIPER-1 at 34.10000, -117.20000 (via tak-main)`
+fn banner_text(emergency: &EmergencyInfo) -> String {
+    format!(
+        "🚨 {}: {} at {:.5}, {:.5} (via {})",
+        emergency.kind,
+        emergency.callsign.as_deref().unwrap_or(&emergency.uid),
+        emergency.lat,
+        emergency.lon,
+        emergency.source_connection
+    )
+}
+
+/// Plays the system alert sound without blocking the UI.
+///
+/// Uses the platform's stock sound player; does nothing if none is available.
+pub fn play_alert_sound() {
+    std::thread::spawn(|| {
+        #[cfg(target_os = "macos")]
+        let result = std::process::Command::new("afplay")
+            .arg("/System/Library/Sounds/Sosumi.aiff")
+            .status();
+        #[cfg(target_os = "windows")]
+        let result = std::process::Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                "[System.Media.SystemSounds]::Hand.Play()",
+            ])
+            .status();
+        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+        let result = std::process::Command::new("canberra-gtk-play")
+            .args(["--id", "dialog-warning"])
+            .status();
+
+        if result.is_err() {
+            tracing::debug!("No alert sound player available");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_banner_text() {
+        let mut emergency = EmergencyInfo {
+            uid: "ANDROID-1-9-1-1".to_string(),
+            kind: "911 Alert".to_string(),
+            callsign: Some("VIPER-1".to_string()),
+            source_connection: "tak-main".to_string(),
+            lat: 34.1,
+            lon: -117.2,
+            status: EmergencyStatus::Active,
+            cot_type: "b-a-o-tbl".to_string(),
+            first_seen: chrono::Utc::now(),
+            last_seen: chrono::Utc::now(),
+            acknowledged_at: None,
+            acknowledged_by: None,
+            cleared_at: None,
+            cleared_by: None,
+        };
+        assert_eq!(
+            banner_text(&emergency),
+            "🚨 911 Alert: VIPER-1 at 34.10000, -117.20000 (via tak-main)"
+        );
+
+        emergency.callsign = None;
+        assert!(banner_text(&emergency).contains("ANDROID-1-9-1-1"));
+    }
+}
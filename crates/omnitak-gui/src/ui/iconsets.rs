@@ -0,0 +1,353 @@
+//! ATAK user iconset support
+//!
+//! Loads ATAK iconset zips (an `iconset.xml` plus folders of images) so units
+//! using custom symbology see the same icons in OmniTAK as on their devices.
+//! A track is matched to an icon by:
+//! - its `<usericon iconsetpath="UID/Group/icon.png"/>`, exactly as ATAK does
+//! - otherwise the most specific icon whose `type2525b` is a prefix of the
+//!   track's CoT type
+
+use anyhow::{anyhow, Context, Result};
+use eframe::egui;
+use quick_xml::events::Event as XmlEvent;
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+
+/// Image file extensions loaded from an iconset
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg"];
+
+/// Largest icon edge kept in memory; bigger images are downscaled
+const MAX_ICON_SIZE: u32 = 64;
+
+/// A single icon within an iconset
+pub struct Icon {
+    /// Path within the iconset (`Group/icon.png`)
+    pub path: String,
+    /// CoT type this icon represents, if declared
+    pub type2525b: Option<String>,
+    image: egui::ColorImage,
+}
+
+/// A loaded ATAK iconset
+pub struct Iconset {
+    pub uid: String,
+    pub name: String,
+    /// Zip file the iconset was loaded from
+    pub source: PathBuf,
+    pub icons: Vec<Icon>,
+}
+
+impl Iconset {
+    /// Load an iconset zip
+    pub fn from_zip(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open iconset {}", path.display()))?;
+        Self::from_reader(file, path.to_path_buf())
+    }
+
+    fn from_reader<R: Read + Seek>(reader: R, source: PathBuf) -> Result<Self> {
+        let mut archive = zip::ZipArchive::new(reader).context("Not a zip archive")?;
+
+        // iconset.xml may sit at the root or inside a single top-level folder
+        let manifest_name = archive
+            .file_names()
+            .filter(|name| name.rsplit('/').next() == Some("iconset.xml"))
+            .min_by_key(|name| name.len())
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("No iconset.xml in archive"))?;
+        let root = manifest_name.trim_end_matches("iconset.xml").to_string();
+
+        let mut manifest = String::new();
+        archive
+            .by_name(&manifest_name)?
+            .read_to_string(&mut manifest)?;
+        let (uid, name, types) = parse_manifest(&manifest)?;
+
+        let mut icons = Vec::new();
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            let Some(path) = entry.name().strip_prefix(&root).map(str::to_string) else {
+                continue;
+            };
+            let is_image = path
+                .rsplit_once('.')
+                .is_some_and(|(_, ext)| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+            if entry.is_dir() || !is_image {
+                continue;
+            }
+
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            let image = match decode_icon(&data) {
+                Ok(image) => image,
+                Err(e) => {
+                    tracing::warn!("Skipping icon {} in {}: {}", path, source.display(), e);
+                    continue;
+                }
+            };
+
+            let file_name = path.rsplit('/').next().unwrap_or(&path);
+            icons.push(Icon {
+                type2525b: types.get(file_name).cloned(),
+                path,
+                image,
+            });
+        }
+
+        Ok(Self {
+            uid,
+            name,
+            source,
+            icons,
+        })
+    }
+
+    /// Icon at a path within this iconset
+    pub fn icon(&self, path: &str) -> Option<&Icon> {
+        self.icons.iter().find(|icon| icon.path == path)
+    }
+}
+
+/// Read the iconset UID, name and the CoT type declared for each icon file
+fn parse_manifest(xml: &str) -> Result<(String, String, HashMap<String, String>)> {
+    let mut reader = Reader::from_str(xml);
+    let mut uid = None;
+    let mut name = None;
+    let mut types = HashMap::new();
+
+    loop {
+        match reader.read_event()? {
+            XmlEvent::Start(e) | XmlEvent::Empty(e) => {
+                let attr = |key: &[u8]| -> Option<String> {
+                    e.attributes()
+                        .flatten()
+                        .find(|a| a.key.as_ref() == key)
+                        .and_then(|a| a.unescape_value().ok())
+                        .map(|v| v.into_owned())
+                };
+                match e.name().as_ref() {
+                    b"iconset" => {
+                        uid = attr(b"uid");
+                        name = attr(b"name");
+                    }
+                    b"icon" => {
+                        if let (Some(file), Some(cot_type)) = (attr(b"name"), attr(b"type2525b")) {
+                            if !cot_type.is_empty() {
+                                types.insert(file, cot_type);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            XmlEvent::Eof => break,
+            _ => {}
+        }
+    }
+
+    let uid = uid.ok_or_else(|| anyhow!("iconset.xml has no uid"))?;
+    let name = name.unwrap_or_else(|| uid.clone());
+    Ok((uid, name, types))
+}
+
+fn decode_icon(data: &[u8]) -> Result<egui::ColorImage> {
+    let mut image = image::load_from_memory(data)?;
+    if image.width() > MAX_ICON_SIZE || image.height() > MAX_ICON_SIZE {
+        image = image.thumbnail(MAX_ICON_SIZE, MAX_ICON_SIZE);
+    }
+    let rgba = image.to_rgba8();
+    Ok(egui::ColorImage::from_rgba_unmultiplied(
+        [rgba.width() as usize, rgba.height() as usize],
+        rgba.as_raw(),
+    ))
+}
+
+/// All loaded iconsets, with textures created on first use
+#[derive(Default)]
+pub struct IconLibrary {
+    pub iconsets: Vec<Iconset>,
+    textures: HashMap<String, egui::TextureHandle>,
+}
+
+impl IconLibrary {
+    /// Load an iconset zip, replacing any loaded iconset with the same UID
+    pub fn load(&mut self, path: &Path) -> Result<()> {
+        let iconset = Iconset::from_zip(path)?;
+        tracing::info!(
+            "Loaded iconset '{}' ({} icons) from {}",
+            iconset.name,
+            iconset.icons.len(),
+            path.display()
+        );
+        self.remove_uid(&iconset.uid);
+        self.iconsets.push(iconset);
+        Ok(())
+    }
+
+    /// Unload an iconset
+    pub fn remove(&mut self, index: usize) {
+        if index < self.iconsets.len() {
+            let uid = self.iconsets[index].uid.clone();
+            self.remove_uid(&uid);
+        }
+    }
+
+    fn remove_uid(&mut self, uid: &str) {
+        self.iconsets.retain(|set| set.uid != uid);
+        let prefix = format!("{}/", uid);
+        self.textures.retain(|key, _| !key.starts_with(&prefix));
+    }
+
+    /// Find the icon for a track
+    pub fn lookup(&self, cot_type: &str, iconset_path: Option<&str>) -> Option<(&Iconset, &Icon)> {
+        if let Some((uid, path)) = iconset_path.and_then(|p| p.split_once('/')) {
+            let found = self
+                .iconsets
+                .iter()
+                .find(|set| set.uid == uid)
+                .and_then(|set| Some((set, set.icon(path)?)));
+            if found.is_some() {
+                return found;
+            }
+        }
+
+        self.iconsets
+            .iter()
+            .flat_map(|set| set.icons.iter().map(move |icon| (set, icon)))
+            .filter(|(_, icon)| {
+                icon.type2525b.as_deref().is_some_and(|t| {
+                    cot_type == t
+                        || (cot_type.starts_with(t)
+                            && cot_type.as_bytes().get(t.len()) == Some(&b'-'))
+                })
+            })
+            .max_by_key(|(_, icon)| icon.type2525b.as_ref().map_or(0, |t| t.len()))
+    }
+
+    /// Texture for a track's icon, uploading it on first use
+    pub fn texture(
+        &mut self,
+        ctx: &egui::Context,
+        cot_type: &str,
+        iconset_path: Option<&str>,
+    ) -> Option<egui::TextureId> {
+        let (set, icon) = self.lookup(cot_type, iconset_path)?;
+        let key = format!("{}/{}", set.uid, icon.path);
+        if let Some(texture) = self.textures.get(&key) {
+            return Some(texture.id());
+        }
+        let texture = ctx.load_texture(&key, icon.image.clone(), egui::TextureOptions::LINEAR);
+        let id = texture.id();
+        self.textures.insert(key, texture);
+        Some(id)
+    }
+}
+
+/// Extract the `iconsetpath` of a `<usericon>` element from CoT detail XML
+pub fn usericon_path(detail_xml: &str) -> Option<String> {
+    let start = detail_xml.find("<usericon")?;
+    let element = &detail_xml[start..];
+    let element = &element[..element.find('>')?];
+    let value = element.split_once("iconsetpath=")?.1;
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = &value[1..];
+    Some(value[..value.find(quote)?].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    fn png() -> Vec<u8> {
+        let mut data = Vec::new();
+        image::RgbaImage::new(4, 4)
+            .write_to(&mut Cursor::new(&mut data), image::ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    fn iconset_zip() -> Vec<u8> {
+        let mut buffer = Cursor::new(Vec::new());
+        let mut zip = zip::ZipWriter::new(&mut buffer);
+        let options = zip::write::SimpleFileOptions::default();
+
+        zip.start_file("iconset.xml", options).unwrap();
+        zip.write_all(
+            br#"<iconset version="1" name="Team Icons" uid="6d781afb-89a6-4c07-b2b9-a89748b6a38f" skip_resize="false">
+                <icon name="medic.png" type2525b="a-f-G-U-C-I"/>
+                <icon name="ground.png" type2525b="a-f-G"/>
+                <icon name="plain.png" type2525b=""/>
+            </iconset>"#,
+        )
+        .unwrap();
+        for name in ["Units/medic.png", "Units/ground.png", "Misc/plain.png"] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(&png()).unwrap();
+        }
+        zip.start_file("README.txt", options).unwrap();
+        zip.write_all(b"not an icon").unwrap();
+        zip.finish().unwrap();
+
+        buffer.into_inner()
+    }
+
+    #[test]
+    fn test_load_iconset() {
+        let set = Iconset::from_reader(Cursor::new(iconset_zip()), PathBuf::from("t.zip")).unwrap();
+        assert_eq!(set.name, "Team Icons");
+        assert_eq!(set.icons.len(), 3);
+
+        let medic = set.icon("Units/medic.png").unwrap();
+        assert_eq!(medic.type2525b.as_deref(), Some("a-f-G-U-C-I"));
+        assert_eq!(medic.image.size, [4, 4]);
+        assert!(set.icon("Misc/plain.png").unwrap().type2525b.is_none());
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut library = IconLibrary::default();
+        library.iconsets.push(
+            Iconset::from_reader(Cursor::new(iconset_zip()), PathBuf::from("t.zip")).unwrap(),
+        );
+        let path = |cot_type, iconset_path| {
+            library
+                .lookup(cot_type, iconset_path)
+                .map(|(_, icon)| icon.path.clone())
+        };
+
+        // Most specific type wins; prefixes only match whole components
+        assert_eq!(
+            path("a-f-G-U-C-I", None).as_deref(),
+            Some("Units/medic.png")
+        );
+        assert_eq!(path("a-f-G-E-V", None).as_deref(), Some("Units/ground.png"));
+        assert_eq!(path("a-f-GX", None), None);
+        assert_eq!(path("a-h-G", None), None);
+
+        // An explicit usericon beats the type
+        let explicit = "6d781afb-89a6-4c07-b2b9-a89748b6a38f/Misc/plain.png";
+        assert_eq!(
+            path("a-f-G", Some(explicit)).as_deref(),
+            Some("Misc/plain.png")
+        );
+        assert_eq!(
+            path("a-f-G", Some("other-set/Misc/plain.png")).as_deref(),
+            Some("Units/ground.png")
+        );
+    }
+
+    #[test]
+    fn test_usericon_path() {
+        let xml = r#"<contact callsign="A"/><usericon iconsetpath="abc/Units/medic.png"/>"#;
+        assert_eq!(usericon_path(xml).as_deref(), Some("abc/Units/medic.png"));
+        assert_eq!(
+            usericon_path("<usericon iconsetpath='x/y.png' />").as_deref(),
+            Some("x/y.png")
+        );
+        assert_eq!(usericon_path("<contact/>"), None);
+    }
+}
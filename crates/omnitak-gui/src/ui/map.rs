@@ -1,8 +1,11 @@
 //! Map panel for visualizing TAK positions with altitude.
 
 use crate::{AppState, MessageLog};
+use crate::ui::iconsets::{self, IconLibrary};
+use crate::ui::map_package::{self, MapPackageDialog};
 use crate::ui::offline_maps::{OfflineMapManager, render_overlays};
 use eframe::egui;
+use omnitak_cot::{geodesy, mgrs};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
@@ -80,20 +83,29 @@ impl DrawingTool {
 }
 
 /// A drawn shape on the map
+///
+/// Each shape carries the CoT UID it is shared under, so sharing it again
+/// updates the copy on other devices instead of adding a duplicate.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum DrawnShape {
     Marker {
+        #[serde(default = "new_shape_uid")]
+        uid: String,
         lat: f64,
         lon: f64,
         label: String,
         color: [u8; 3],
     },
     Line {
+        #[serde(default = "new_shape_uid")]
+        uid: String,
         points: Vec<(f64, f64)>,
         color: [u8; 3],
         width: f32,
     },
     Circle {
+        #[serde(default = "new_shape_uid")]
+        uid: String,
         center_lat: f64,
         center_lon: f64,
         radius_m: f64,
@@ -101,11 +113,15 @@ pub enum DrawnShape {
         filled: bool,
     },
     Polygon {
+        #[serde(default = "new_shape_uid")]
+        uid: String,
         points: Vec<(f64, f64)>,
         color: [u8; 3],
         filled: bool,
     },
     RangeRing {
+        #[serde(default = "new_shape_uid")]
+        uid: String,
         center_lat: f64,
         center_lon: f64,
         rings: Vec<f64>, // radii in meters
@@ -113,6 +129,34 @@ pub enum DrawnShape {
     },
 }
 
+/// Fresh UID for a drawn shape
+pub fn new_shape_uid() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+impl DrawnShape {
+    pub fn uid(&self) -> &str {
+        match self {
+            Self::Marker { uid, .. }
+            | Self::Line { uid, .. }
+            | Self::Circle { uid, .. }
+            | Self::Polygon { uid, .. }
+            | Self::RangeRing { uid, .. } => uid,
+        }
+    }
+
+    /// Short description for lists
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Marker { label, .. } => format!("📍 {}", label),
+            Self::Line { points, .. } => format!("📏 Line ({} points)", points.len()),
+            Self::Circle { radius_m, .. } => format!("⭕ Circle ({:.0} m)", radius_m),
+            Self::Polygon { points, .. } => format!("⬡ Polygon ({} points)", points.len()),
+            Self::RangeRing { rings, .. } => format!("🎯 Range Rings ({})", rings.len()),
+        }
+    }
+}
+
 /// Track history entry for Blue Force Tracking
 #[derive(Clone, Debug)]
 pub struct TrackPoint {
@@ -124,14 +168,134 @@ pub struct TrackPoint {
     pub heading: Option<f64>,
 }
 
+/// eframe storage key for the persisted map panel state
+pub const STORAGE_KEY: &str = "map_panel";
+
+/// Camera position used before anything has been viewed
+const DEFAULT_CAMERA: CameraPosition = CameraPosition {
+    lat: 37.7749,
+    lon: -122.4194,
+    zoom: 16.0,
+};
+
+/// Closest zoom used when fitting tracks into view
+const MAX_FIT_ZOOM: f64 = 16.0;
+
+/// Zoom used when centering on a search result, unless already closer
+const SEARCH_ZOOM: f64 = 15.0;
+
+/// How long a search result stays highlighted
+const HIGHLIGHT_DURATION: Duration = Duration::from_secs(6);
+
+/// What a map search resolved to
+#[derive(Clone, Debug, PartialEq)]
+pub enum SearchTarget {
+    /// A track, by UID
+    Track(String),
+    /// A coordinate (lat/lon, MGRS or UTM)
+    Location { lat: f64, lon: f64 },
+}
+
+/// What the map camera keeps centered
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum FollowMode {
+    /// Camera stays where the user leaves it
+    Off,
+    /// Position of the most recent message
+    #[default]
+    Latest,
+    /// The selected track
+    SelectedTrack,
+    /// All tracks of a group (team), zooming out as needed to keep them in view
+    Group(String),
+}
+
+/// Camera center and zoom, remembered across restarts
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraPosition {
+    pub lat: f64,
+    pub lon: f64,
+    pub zoom: f64,
+}
+
+/// How coordinates are shown in the status bar
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum CoordinateFormat {
+    /// Decimal degrees
+    #[default]
+    LatLon,
+    /// MGRS, to one meter
+    Mgrs,
+    /// UTM zone, easting and northing
+    Utm,
+}
+
+impl CoordinateFormat {
+    /// The format after this one, for cycling through them
+    pub fn next(self) -> Self {
+        match self {
+            CoordinateFormat::LatLon => CoordinateFormat::Mgrs,
+            CoordinateFormat::Mgrs => CoordinateFormat::Utm,
+            CoordinateFormat::Utm => CoordinateFormat::LatLon,
+        }
+    }
+
+    /// Format a position; polar positions, which have no MGRS or UTM
+    /// coordinate, fall back to decimal degrees
+    pub fn format(self, lat: f64, lon: f64) -> String {
+        let grid = match self {
+            CoordinateFormat::LatLon => None,
+            CoordinateFormat::Mgrs => mgrs::to_mgrs(lat, lon, 5).ok(),
+            CoordinateFormat::Utm => mgrs::Utm::from_lat_lon(lat, lon)
+                .ok()
+                .map(|utm| utm.to_string()),
+        };
+        grid.unwrap_or_else(|| format!("{:.5}, {:.5}", lat, lon))
+    }
+}
+
+/// Longest time a track is dead-reckoned past its last report
+const MAX_PREDICTION: Duration = Duration::from_secs(30);
+
+/// Time taken to glide from the predicted position onto a fresh report
+const CORRECTION_BLEND: Duration = Duration::from_millis(750);
+
+/// Assumed prediction error as a fraction of distance travelled, until a
+/// track has reported twice
+const DEFAULT_ERROR_RATIO: f64 = 0.1;
+
+/// Where to draw a track right now
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DisplayPosition {
+    pub lat: f64,
+    pub lon: f64,
+    /// Position is extrapolated rather than reported
+    pub predicted: bool,
+    /// Estimated error of the extrapolated position in meters
+    pub accuracy_m: f64,
+}
+
 /// Blue Force Track with history
 #[derive(Clone, Debug)]
 pub struct BlueForceTack {
     pub uid: String,
     pub callsign: String,
     pub affiliation: String,
+    /// Group (team) name from `<__group>`
+    pub group: Option<String>,
+    /// CoT type of the latest message
+    pub cot_type: String,
+    /// `<usericon iconsetpath>` of the latest message
+    pub icon_path: Option<String>,
     pub history: VecDeque<TrackPoint>,
     pub max_history: usize,
+    /// Timestamp of the newest message applied to this track
+    pub last_message: Option<chrono::DateTime<chrono::Utc>>,
+    /// Last prediction error divided by the distance dead-reckoned
+    pub error_ratio: Option<f64>,
+    /// Displayed position when the latest report arrived, blended out over
+    /// [`CORRECTION_BLEND`]
+    correction_from: Option<(f64, f64, Instant)>,
 }
 
 impl BlueForceTack {
@@ -140,12 +304,32 @@ impl BlueForceTack {
             uid,
             callsign,
             affiliation,
+            group: None,
+            cot_type: String::new(),
+            icon_path: None,
             history: VecDeque::with_capacity(100),
             max_history: 100,
+            last_message: None,
+            error_ratio: None,
+            correction_from: None,
         }
     }
 
     pub fn add_point(&mut self, point: TrackPoint) {
+        // Score the previous prediction against the new report, and remember
+        // where the marker was so it glides rather than jumps
+        if let Some(shown) = self.display_position(point.timestamp, true) {
+            if shown.predicted {
+                let latest = self.latest().unwrap();
+                let travelled = geodesy::distance_m(latest.lat, latest.lon, shown.lat, shown.lon);
+                if travelled > 1.0 {
+                    let error = geodesy::distance_m(shown.lat, shown.lon, point.lat, point.lon);
+                    self.error_ratio = Some(error / travelled);
+                }
+                self.correction_from = Some((shown.lat, shown.lon, point.timestamp));
+            }
+        }
+
         if self.history.len() >= self.max_history {
             self.history.pop_front();
         }
@@ -169,33 +353,84 @@ impl BlueForceTack {
             return (None, None);
         }
 
-        // Haversine distance calculation
-        let lat1 = prev.lat.to_radians();
-        let lat2 = latest.lat.to_radians();
-        let dlat = (latest.lat - prev.lat).to_radians();
-        let dlon = (latest.lon - prev.lon).to_radians();
+        let distance = geodesy::distance_m(prev.lat, prev.lon, latest.lat, latest.lon);
+        let speed = distance / dt; // m/s
+        let heading = geodesy::bearing_deg(prev.lat, prev.lon, latest.lat, latest.lon);
 
-        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
-        let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
-        let distance = 6371000.0 * c; // Earth radius in meters
+        (Some(speed), Some(heading))
+    }
 
-        let speed = distance / dt; // m/s
+    /// Reported speed and heading, falling back to the last two positions
+    pub fn velocity(&self) -> Option<(f64, f64)> {
+        let latest = self.latest()?;
+        match (latest.speed, latest.heading) {
+            (Some(speed), Some(heading)) => Some((speed, heading)),
+            _ => match self.calculate_speed_heading() {
+                (Some(speed), Some(heading)) => Some((speed, heading)),
+                _ => None,
+            },
+        }
+    }
 
-        // Calculate heading (bearing)
-        let y = dlon.sin() * lat2.cos();
-        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
-        let heading = y.atan2(x).to_degrees();
-        let heading = (heading + 360.0) % 360.0;
+    /// Position to draw at `now`
+    ///
+    /// With `smooth` set, a moving track is dead-reckoned from its last
+    /// report (for at most [`MAX_PREDICTION`]) and eased onto each new report
+    /// instead of jumping to it.
+    pub fn display_position(&self, now: Instant, smooth: bool) -> Option<DisplayPosition> {
+        let latest = self.latest()?;
+        let reported = DisplayPosition {
+            lat: latest.lat,
+            lon: latest.lon,
+            predicted: false,
+            accuracy_m: 0.0,
+        };
+        if !smooth {
+            return Some(reported);
+        }
 
-        (Some(speed), Some(heading))
+        let elapsed = now
+            .saturating_duration_since(latest.timestamp)
+            .min(MAX_PREDICTION)
+            .as_secs_f64();
+        let Some((speed, heading)) = self.velocity().filter(|(speed, _)| *speed > 0.5) else {
+            return Some(reported);
+        };
+
+        let (mut lat, mut lon) =
+            geodesy::dead_reckon(latest.lat, latest.lon, heading, speed, elapsed);
+        let accuracy_m = speed * elapsed * self.error_ratio.unwrap_or(DEFAULT_ERROR_RATIO);
+
+        if let Some((from_lat, from_lon, since)) = self.correction_from {
+            let blend = now.saturating_duration_since(since).as_secs_f64()
+                / CORRECTION_BLEND.as_secs_f64();
+            if blend < 1.0 {
+                (lat, lon) = geodesy::interpolate(from_lat, from_lon, lat, lon, blend);
+            }
+        }
+
+        Some(DisplayPosition {
+            lat,
+            lon,
+            predicted: true,
+            accuracy_m,
+        })
     }
 }
 
 /// Map panel state (persisted)
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct MapPanelState {
-    /// Follow latest position
-    pub follow_mode: bool,
+    /// What the camera follows
+    pub follow_mode: FollowMode,
+
+    /// Last camera position
+    pub camera: Option<CameraPosition>,
+
+    /// Fit all tracks into view on the next frame
+    #[serde(skip)]
+    pub zoom_to_fit_requested: bool,
 
     /// Show altitude labels
     pub show_altitude: bool,
@@ -237,6 +472,12 @@ pub struct MapPanelState {
     /// Show speed/heading indicators
     pub show_vectors: bool,
 
+    /// Animate tracks between position updates
+    pub smooth_motion: bool,
+
+    /// Coordinate format of the mouse position readout
+    pub coordinate_format: CoordinateFormat,
+
     /// Selected track UID
     #[serde(skip)]
     pub selected_track: Option<String>,
@@ -272,12 +513,53 @@ pub struct MapPanelState {
     /// File picker promise for loading layers
     #[serde(skip)]
     pub layer_picker_promise: Option<poll_promise::Promise<Option<PathBuf>>>,
+
+    /// ATAK iconset zips to load on startup
+    pub iconset_paths: Vec<PathBuf>,
+
+    /// Loaded iconsets (not serialized - reloaded from `iconset_paths`)
+    #[serde(skip)]
+    pub icons: IconLibrary,
+
+    /// Whether `iconset_paths` have been loaded this session
+    #[serde(skip)]
+    iconsets_restored: bool,
+
+    /// Search box text
+    #[serde(skip)]
+    pub search_query: String,
+
+    /// Focus the search box on the next frame
+    #[serde(skip)]
+    pub search_focus_requested: bool,
+
+    /// Other tracks matching the last search
+    #[serde(skip)]
+    search_matches: Vec<String>,
+
+    /// Feedback for the last search
+    #[serde(skip)]
+    search_message: Option<String>,
+
+    /// Search result being highlighted, and since when
+    #[serde(skip)]
+    highlight: Option<(SearchTarget, Instant)>,
+
+    /// Center the camera here on the next frame
+    #[serde(skip)]
+    center_requested: Option<(f64, f64)>,
+
+    /// Open map data package dialog
+    #[serde(skip)]
+    pub package_dialog: Option<MapPackageDialog>,
 }
 
 impl Default for MapPanelState {
     fn default() -> Self {
         Self {
-            follow_mode: true,
+            follow_mode: FollowMode::Latest,
+            camera: None,
+            zoom_to_fit_requested: false,
             show_altitude: true,
             altitude_coloring: true,
             min_altitude: 0.0,
@@ -291,6 +573,8 @@ impl Default for MapPanelState {
             show_trails: true,
             trail_length: 50,
             show_vectors: true,
+            smooth_motion: true,
+            coordinate_format: CoordinateFormat::LatLon,
             selected_track: None,
             mouse_geo_pos: None,
             measurement_result: None,
@@ -300,6 +584,16 @@ impl Default for MapPanelState {
             tracks: HashMap::new(),
             offline_manager: OfflineMapManager::new(),
             layer_picker_promise: None,
+            iconset_paths: vec![],
+            icons: IconLibrary::default(),
+            iconsets_restored: false,
+            search_query: String::new(),
+            search_focus_requested: false,
+            search_matches: vec![],
+            search_message: None,
+            highlight: None,
+            center_requested: None,
+            package_dialog: None,
         }
     }
 }
@@ -309,6 +603,49 @@ impl MapPanelState {
         Self::default()
     }
 
+    /// Run the search box query and go to the best match
+    fn run_search(&mut self) {
+        let query = self.search_query.trim().to_string();
+        self.search_matches.clear();
+        self.search_message = None;
+        if query.is_empty() {
+            return;
+        }
+
+        let mut results = search(&query, &self.tracks).into_iter();
+        match results.next() {
+            Some(first) => {
+                self.search_matches = results
+                    .filter_map(|r| match r {
+                        SearchTarget::Track(uid) => Some(uid),
+                        SearchTarget::Location { .. } => None,
+                    })
+                    .collect();
+                self.go_to(first);
+            }
+            None => self.search_message = Some(format!("No track or coordinate matches \"{}\"", query)),
+        }
+    }
+
+    /// Center on and highlight a search result
+    pub fn go_to(&mut self, target: SearchTarget) {
+        let position = match &target {
+            SearchTarget::Track(uid) => {
+                self.selected_track = Some(uid.clone());
+                self.tracks
+                    .get(uid)
+                    .and_then(|t| t.display_position(Instant::now(), self.smooth_motion))
+                    .map(|p| (p.lat, p.lon))
+            }
+            SearchTarget::Location { lat, lon } => Some((*lat, *lon)),
+        };
+        if let Some(position) = position {
+            self.follow_mode = FollowMode::Off;
+            self.center_requested = Some(position);
+            self.highlight = Some((target, Instant::now()));
+        }
+    }
+
     /// Create HttpTiles for the selected provider
     fn create_tiles(&self, ctx: egui::Context) -> Option<HttpTiles> {
         match self.tile_provider {
@@ -367,6 +704,109 @@ impl MapPanelState {
 }
 
 
+/// Resolve a map search: an exact UID or callsign, then a lat/lon, MGRS or
+/// UTM coordinate, then every track whose callsign or UID contains the query
+pub fn search(query: &str, tracks: &HashMap<String, BlueForceTack>) -> Vec<SearchTarget> {
+    let query = query.trim();
+    if query.is_empty() {
+        return vec![];
+    }
+
+    if tracks.contains_key(query) {
+        return vec![SearchTarget::Track(query.to_string())];
+    }
+    if let Some(track) = tracks.values().find(|t| t.callsign.eq_ignore_ascii_case(query)) {
+        return vec![SearchTarget::Track(track.uid.clone())];
+    }
+    if let Some((lat, lon)) = parse_lat_lon(query)
+        .or_else(|| mgrs::from_mgrs(query).ok())
+        .or_else(|| query.parse::<mgrs::Utm>().ok().map(|utm| utm.to_lat_lon()))
+    {
+        return vec![SearchTarget::Location { lat, lon }];
+    }
+
+    let needle = query.to_lowercase();
+    let mut matches: Vec<&BlueForceTack> = tracks
+        .values()
+        .filter(|t| t.callsign.to_lowercase().contains(&needle) || t.uid.to_lowercase().contains(&needle))
+        .collect();
+    matches.sort_by(|a, b| a.callsign.cmp(&b.callsign));
+    matches.into_iter().map(|t| SearchTarget::Track(t.uid.clone())).collect()
+}
+
+/// Parse decimal degrees such as `37.77, -122.42` or `37.77N 122.42W`
+fn parse_lat_lon(query: &str) -> Option<(f64, f64)> {
+    let parts: Vec<&str> = query
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|p| !p.is_empty())
+        .collect();
+    let [lat, lon] = parts.as_slice() else {
+        return None;
+    };
+
+    let parse = |part: &str, positive: char, negative: char| -> Option<f64> {
+        let part = part.to_uppercase();
+        let part = part.trim_end_matches('°');
+        let (number, sign) = if let Some(n) = part.strip_suffix(positive) {
+            (n, 1.0)
+        } else if let Some(n) = part.strip_suffix(negative) {
+            (n, -1.0)
+        } else {
+            (part, 1.0)
+        };
+        let value: f64 = number.trim_end_matches('°').parse().ok()?;
+        Some(value * sign)
+    };
+
+    let lat = parse(lat, 'N', 'S')?;
+    let lon = parse(lon, 'E', 'W')?;
+    ((-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)).then_some((lat, lon))
+}
+
+/// Center and zoom that fit all `points` (lat, lon) into a view of `size`
+/// pixels, with some margin
+pub fn fit_view(points: &[(f64, f64)], size: egui::Vec2) -> Option<CameraPosition> {
+    const TILE_SIZE: f64 = 256.0;
+
+    fn mercator_y(lat: f64) -> f64 {
+        let lat = lat.clamp(-85.05, 85.05).to_radians();
+        (std::f64::consts::FRAC_PI_4 + lat / 2.0).tan().ln()
+    }
+
+    let (first, rest) = points.split_first()?;
+    let (mut min_lat, mut max_lat, mut min_lon, mut max_lon) = (first.0, first.0, first.1, first.1);
+    for &(lat, lon) in rest {
+        min_lat = min_lat.min(lat);
+        max_lat = max_lat.max(lat);
+        min_lon = min_lon.min(lon);
+        max_lon = max_lon.max(lon);
+    }
+
+    let (y_min, y_max) = (mercator_y(min_lat), mercator_y(max_lat));
+    let center_y = (y_min + y_max) / 2.0;
+    let lat = (2.0 * center_y.exp().atan() - std::f64::consts::FRAC_PI_2).to_degrees();
+    let lon = (min_lon + max_lon) / 2.0;
+
+    // Fraction of the world's width/height the points span
+    let span_x = (max_lon - min_lon) / 360.0;
+    let span_y = (y_max - y_min) / (2.0 * std::f64::consts::PI);
+    // Half a zoom level of margin around the outermost points
+    let zoom_for = |pixels: f32, span: f64| {
+        if span > 0.0 {
+            (pixels as f64 / (TILE_SIZE * span)).log2() - 0.5
+        } else {
+            MAX_FIT_ZOOM
+        }
+    };
+    let zoom = zoom_for(size.x, span_x).min(zoom_for(size.y, span_y));
+
+    Some(CameraPosition {
+        lat,
+        lon,
+        zoom: zoom.clamp(1.0, MAX_FIT_ZOOM),
+    })
+}
+
 /// Converts altitude to color (low = blue, mid = green, high = red)
 fn altitude_to_color(altitude: f64, min_alt: f64, max_alt: f64) -> egui::Color32 {
     let normalized = ((altitude - min_alt) / (max_alt - min_alt)).clamp(0.0, 1.0);
@@ -522,7 +962,10 @@ pub struct BlueForceTrackingPlugin {
     show_trails: bool,
     trail_length: usize,
     show_vectors: bool,
+    smooth_motion: bool,
     selected_track: Option<String>,
+    /// Iconset textures by track UID
+    icons: HashMap<String, egui::TextureId>,
 }
 
 impl BlueForceTrackingPlugin {
@@ -531,14 +974,18 @@ impl BlueForceTrackingPlugin {
         show_trails: bool,
         trail_length: usize,
         show_vectors: bool,
+        smooth_motion: bool,
         selected_track: Option<String>,
+        icons: HashMap<String, egui::TextureId>,
     ) -> Self {
         Self {
             tracks,
             show_trails,
             trail_length,
             show_vectors,
+            smooth_motion,
             selected_track,
+            icons,
         }
     }
 }
@@ -552,6 +999,7 @@ impl Plugin for BlueForceTrackingPlugin {
         _map_memory: &MapMemory,
     ) {
         let painter = ui.painter();
+        let now = Instant::now();
 
         for track in &self.tracks {
             if track.history.is_empty() {
@@ -596,8 +1044,10 @@ impl Plugin for BlueForceTrackingPlugin {
             }
 
             // Draw current position
-            if let Some(latest) = track.latest() {
-                let geo_pos = walkers::lat_lon(latest.lat, latest.lon);
+            if let (Some(latest), Some(shown)) =
+                (track.latest(), track.display_position(now, self.smooth_motion))
+            {
+                let geo_pos = walkers::lat_lon(shown.lat, shown.lon);
                 let screen_vec = projector.project(geo_pos);
                 let screen_pos = egui::pos2(screen_vec.x, screen_vec.y);
 
@@ -607,23 +1057,64 @@ impl Plugin for BlueForceTrackingPlugin {
 
                 let radius = if is_selected { 12.0 } else { 8.0 };
 
-                // Draw marker
-                painter.circle_filled(
-                    screen_pos + egui::vec2(2.0, 2.0),
-                    radius,
-                    egui::Color32::from_black_alpha(100),
-                );
-                painter.circle_filled(screen_pos, radius, base_color);
-                painter.circle_stroke(
-                    screen_pos,
-                    radius,
-                    egui::Stroke::new(if is_selected { 3.0 } else { 2.0 }, egui::Color32::WHITE),
-                );
+                // Extrapolated: link back to the last report and show the
+                // estimated error as a ring
+                if shown.predicted {
+                    let reported = projector.project(walkers::lat_lon(latest.lat, latest.lon));
+                    painter.add(egui::Shape::dashed_line(
+                        &[egui::pos2(reported.x, reported.y), screen_pos],
+                        egui::Stroke::new(1.0, egui::Color32::from_white_alpha(120)),
+                        4.0,
+                        4.0,
+                    ));
+
+                    let edge_lat = shown.lat + (shown.accuracy_m / 111320.0);
+                    let edge_screen = projector.project(walkers::lat_lon(edge_lat, shown.lon));
+                    let accuracy_radius = (edge_screen.y - screen_vec.y).abs();
+                    if accuracy_radius > radius {
+                        painter.circle(
+                            screen_pos,
+                            accuracy_radius,
+                            base_color.gamma_multiply(0.1),
+                            egui::Stroke::new(1.0, base_color.gamma_multiply(0.5)),
+                        );
+                    }
+                }
+
+                // Draw marker: the track's iconset icon, or an affiliation dot
+                if let Some(texture) = self.icons.get(&track.uid) {
+                    let rect = egui::Rect::from_center_size(screen_pos, egui::Vec2::splat(radius * 2.5));
+                    if is_selected {
+                        painter.rect_stroke(
+                            rect.expand(2.0),
+                            2.0,
+                            egui::Stroke::new(2.0, egui::Color32::WHITE),
+                            egui::StrokeKind::Outside,
+                        );
+                    }
+                    painter.image(
+                        *texture,
+                        rect,
+                        egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                        egui::Color32::WHITE,
+                    );
+                } else {
+                    painter.circle_filled(
+                        screen_pos + egui::vec2(2.0, 2.0),
+                        radius,
+                        egui::Color32::from_black_alpha(100),
+                    );
+                    painter.circle_filled(screen_pos, radius, base_color);
+                    painter.circle_stroke(
+                        screen_pos,
+                        radius,
+                        egui::Stroke::new(if is_selected { 3.0 } else { 2.0 }, egui::Color32::WHITE),
+                    );
+                }
 
                 // Draw speed/heading vector
                 if self.show_vectors {
-                    let (speed, heading) = track.calculate_speed_heading();
-                    if let (Some(spd), Some(hdg)) = (speed, heading) {
+                    if let Some((spd, hdg)) = track.velocity() {
                         if spd > 0.5 {
                             // Draw heading arrow
                             let arrow_len = (spd * 3.0).min(50.0).max(15.0) as f32;
@@ -672,6 +1163,44 @@ impl Plugin for BlueForceTrackingPlugin {
     }
 }
 
+/// Plugin for marking a search result with expanding rings
+pub struct SearchHighlightPlugin {
+    lat: f64,
+    lon: f64,
+    /// Time since the highlight started, as a fraction of its duration
+    progress: f32,
+}
+
+impl Plugin for SearchHighlightPlugin {
+    fn run(
+        self: Box<Self>,
+        ui: &mut egui::Ui,
+        _response: &egui::Response,
+        projector: &Projector,
+        _map_memory: &MapMemory,
+    ) {
+        let painter = ui.painter();
+        let screen = projector.project(walkers::lat_lon(self.lat, self.lon));
+        let center = egui::pos2(screen.x, screen.y);
+        let fade = 1.0 - self.progress;
+        let color = egui::Color32::from_rgb(255, 220, 0);
+
+        // Two rings pulsing outwards, fading as the highlight ends
+        for offset in [0.0, 0.5] {
+            let phase = (self.progress * 4.0 + offset) % 1.0;
+            painter.circle_stroke(
+                center,
+                14.0 + phase * 36.0,
+                egui::Stroke::new(3.0, color.gamma_multiply(fade * (1.0 - phase))),
+            );
+        }
+        let arm = 10.0;
+        let stroke = egui::Stroke::new(2.0, color.gamma_multiply(fade));
+        painter.line_segment([center - egui::vec2(arm, 0.0), center + egui::vec2(arm, 0.0)], stroke);
+        painter.line_segment([center - egui::vec2(0.0, arm), center + egui::vec2(0.0, arm)], stroke);
+    }
+}
+
 /// Plugin for rendering drawn shapes
 pub struct DrawnShapesPlugin {
     shapes: Vec<DrawnShape>,
@@ -702,7 +1231,7 @@ impl Plugin for DrawnShapesPlugin {
         // Draw completed shapes
         for shape in &self.shapes {
             match shape {
-                DrawnShape::Marker { lat, lon, label, color } => {
+                DrawnShape::Marker { lat, lon, label, color, .. } => {
                     let geo = walkers::lat_lon(*lat, *lon);
                     let screen = projector.project(geo);
                     let pos = egui::pos2(screen.x, screen.y);
@@ -721,7 +1250,7 @@ impl Plugin for DrawnShapesPlugin {
                         );
                     }
                 }
-                DrawnShape::Line { points, color, width } => {
+                DrawnShape::Line { points, color, width, .. } => {
                     if points.len() >= 2 {
                         let screen_points: Vec<egui::Pos2> = points.iter()
                             .map(|(lat, lon)| {
@@ -740,7 +1269,7 @@ impl Plugin for DrawnShapesPlugin {
                         }
                     }
                 }
-                DrawnShape::Circle { center_lat, center_lon, radius_m, color, filled } => {
+                DrawnShape::Circle { center_lat, center_lon, radius_m, color, filled, .. } => {
                     let center_geo = walkers::lat_lon(*center_lat, *center_lon);
                     let center_screen = projector.project(center_geo);
                     let center_pos = egui::pos2(center_screen.x, center_screen.y);
@@ -757,7 +1286,7 @@ impl Plugin for DrawnShapesPlugin {
                     }
                     painter.circle_stroke(center_pos, screen_radius, egui::Stroke::new(2.0, c));
                 }
-                DrawnShape::Polygon { points, color, filled } => {
+                DrawnShape::Polygon { points, color, filled, .. } => {
                     if points.len() >= 3 {
                         let screen_points: Vec<egui::Pos2> = points.iter()
                             .map(|(lat, lon)| {
@@ -785,7 +1314,7 @@ impl Plugin for DrawnShapesPlugin {
                         }
                     }
                 }
-                DrawnShape::RangeRing { center_lat, center_lon, rings, color } => {
+                DrawnShape::RangeRing { center_lat, center_lon, rings, color, .. } => {
                     let center_geo = walkers::lat_lon(*center_lat, *center_lon);
                     let center_screen = projector.project(center_geo);
                     let center_pos = egui::pos2(center_screen.x, center_screen.y);
@@ -954,21 +1483,51 @@ fn draw_marker(
     }
 }
 
-/// Calculate distance between two geographic points (Haversine)
-fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
-    let r = 6371000.0; // Earth radius in meters
-    let dlat = (lat2 - lat1).to_radians();
-    let dlon = (lon2 - lon1).to_radians();
-    let a = (dlat / 2.0).sin().powi(2)
-        + lat1.to_radians().cos() * lat2.to_radians().cos() * (dlon / 2.0).sin().powi(2);
-    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
-    r * c
-}
-
 /// Shows the map panel
 pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut MapPanelState) {
     ui.heading("Tactical Map");
 
+    // Search: callsign, UID, MGRS, UTM or lat/lon
+    ui.horizontal(|ui| {
+        ui.label("🔍");
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut map_state.search_query)
+                .hint_text("Callsign, UID, MGRS, UTM or lat, lon")
+                .desired_width(260.0),
+        );
+        if map_state.search_focus_requested {
+            map_state.search_focus_requested = false;
+            response.request_focus();
+        }
+        let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if submitted || ui.button("Go").clicked() {
+            map_state.run_search();
+        }
+
+        if let Some(message) = &map_state.search_message {
+            ui.colored_label(egui::Color32::LIGHT_RED, message);
+        }
+
+        // Further matches for an ambiguous search
+        if !map_state.search_matches.is_empty() {
+            ui.separator();
+            ui.label("Also:");
+            let mut chosen = None;
+            for uid in map_state.search_matches.iter().take(8) {
+                let label = map_state.tracks.get(uid).map_or(uid.as_str(), |t| t.callsign.as_str());
+                if ui.small_button(label).on_hover_text(uid).clicked() {
+                    chosen = Some(uid.clone());
+                }
+            }
+            if map_state.search_matches.len() > 8 {
+                ui.label(format!("+{} more", map_state.search_matches.len() - 8));
+            }
+            if let Some(uid) = chosen {
+                map_state.go_to(SearchTarget::Track(uid));
+            }
+        }
+    });
+
     // Drawing toolbar
     ui.horizontal(|ui| {
         ui.label("Tools:");
@@ -998,6 +1557,15 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
             }
         }
 
+        if ui
+            .button("📦 Share")
+            .on_hover_text("Save shapes, tracks and overlays as a data package")
+            .clicked()
+            && map_state.package_dialog.is_none()
+        {
+            map_state.package_dialog = Some(MapPackageDialog::new(map_state));
+        }
+
         if !map_state.drawing_points.is_empty() {
             if ui.button("✓ Finish").clicked() {
                 // Finalize current shape
@@ -1005,6 +1573,7 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
                     DrawingTool::Line => {
                         if map_state.drawing_points.len() >= 2 {
                             map_state.shapes.push(DrawnShape::Line {
+                                uid: new_shape_uid(),
                                 points: map_state.drawing_points.clone(),
                                 color: [255, 100, 100],
                                 width: 2.0,
@@ -1014,6 +1583,7 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
                     DrawingTool::Polygon => {
                         if map_state.drawing_points.len() >= 3 {
                             map_state.shapes.push(DrawnShape::Polygon {
+                                uid: new_shape_uid(),
                                 points: map_state.drawing_points.clone(),
                                 color: [100, 255, 100],
                                 filled: true,
@@ -1026,7 +1596,7 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
                         for i in 1..map_state.drawing_points.len() {
                             let (lat1, lon1) = map_state.drawing_points[i - 1];
                             let (lat2, lon2) = map_state.drawing_points[i];
-                            total_dist += haversine_distance(lat1, lon1, lat2, lon2);
+                            total_dist += geodesy::distance_m(lat1, lon1, lat2, lon2);
                         }
                         map_state.measurement_result = Some(if total_dist >= 1000.0 {
                             format!("Distance: {:.2} km", total_dist / 1000.0)
@@ -1044,6 +1614,8 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
         }
     });
 
+    map_package::show_dialog(ui.ctx(), map_state);
+
     // Blue Force Tracking controls
     ui.horizontal(|ui| {
         ui.checkbox(&mut map_state.show_trails, "Show Trails");
@@ -1052,8 +1624,32 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
         }
         ui.separator();
         ui.checkbox(&mut map_state.show_vectors, "Speed/Heading");
+        ui.checkbox(&mut map_state.smooth_motion, "Smooth Motion")
+            .on_hover_text("Dead-reckon tracks between position updates");
         ui.separator();
-        ui.checkbox(&mut map_state.follow_mode, "Follow Latest");
+        let mut groups: Vec<String> = map_state.tracks.values().filter_map(|t| t.group.clone()).collect();
+        groups.sort();
+        groups.dedup();
+        ui.label("Follow:");
+        egui::ComboBox::from_id_salt("follow_mode")
+            .selected_text(match &map_state.follow_mode {
+                FollowMode::Off => "Off".to_string(),
+                FollowMode::Latest => "Latest Message".to_string(),
+                FollowMode::SelectedTrack => "Selected Track".to_string(),
+                FollowMode::Group(name) => format!("Group: {}", name),
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut map_state.follow_mode, FollowMode::Off, "Off");
+                ui.selectable_value(&mut map_state.follow_mode, FollowMode::Latest, "Latest Message");
+                ui.selectable_value(&mut map_state.follow_mode, FollowMode::SelectedTrack, "Selected Track");
+                for group in groups {
+                    let label = format!("Group: {}", group);
+                    ui.selectable_value(&mut map_state.follow_mode, FollowMode::Group(group), label);
+                }
+            });
+        if ui.button("⛶ Zoom to Fit").on_hover_text("Show all tracks").clicked() {
+            map_state.zoom_to_fit_requested = true;
+        }
         ui.separator();
         ui.checkbox(&mut map_state.show_altitude, "Show Altitude");
     });
@@ -1164,6 +1760,16 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
             });
         });
 
+    // Reload iconsets from the last session
+    if !map_state.iconsets_restored {
+        map_state.iconsets_restored = true;
+        for path in &map_state.iconset_paths {
+            if let Err(e) = map_state.icons.load(path) {
+                tracing::warn!("Failed to reload iconset {}: {}", path.display(), e);
+            }
+        }
+    }
+
     // Handle layer file picker
     if let Some(promise) = &map_state.layer_picker_promise {
         if let Some(result) = promise.ready() {
@@ -1185,6 +1791,14 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
                             tracing::error!("Failed to load MBTiles: {}", e);
                         }
                     }
+                    "zip" => match map_state.icons.load(path) {
+                        Ok(()) => {
+                            if !map_state.iconset_paths.contains(path) {
+                                map_state.iconset_paths.push(path.clone());
+                            }
+                        }
+                        Err(e) => tracing::error!("Failed to load iconset: {}", e),
+                    },
                     _ => {}
                 }
             }
@@ -1194,9 +1808,10 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
 
     // Layers management panel
     egui::CollapsingHeader::new(format!(
-        "Layers ({} GeoJSON, {} KML)",
+        "Layers ({} GeoJSON, {} KML, {} Iconsets)",
         map_state.offline_manager.geojson_layers.len(),
-        map_state.offline_manager.kml_layers.len()
+        map_state.offline_manager.kml_layers.len(),
+        map_state.icons.iconsets.len()
     ))
         .default_open(false)
         .show(ui, |ui| {
@@ -1233,8 +1848,42 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
                         },
                     ));
                 }
+
+                if ui.button("📂 Load Iconset").on_hover_text("ATAK iconset zip").clicked()
+                    && map_state.layer_picker_promise.is_none()
+                {
+                    map_state.layer_picker_promise = Some(poll_promise::Promise::spawn_thread(
+                        "iconset_picker",
+                        || {
+                            rfd::FileDialog::new()
+                                .add_filter("ATAK Iconset", &["zip"])
+                                .pick_file()
+                        },
+                    ));
+                }
             });
 
+            // List iconsets
+            if !map_state.icons.iconsets.is_empty() {
+                ui.separator();
+                ui.label("Iconsets:");
+                let mut to_remove = None;
+                for (i, iconset) in map_state.icons.iconsets.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(&iconset.name);
+                        ui.label(format!("({} icons)", iconset.icons.len()));
+                        if ui.small_button("🗑️").clicked() {
+                            to_remove = Some(i);
+                        }
+                    });
+                }
+                if let Some(idx) = to_remove {
+                    let source = map_state.icons.iconsets[idx].source.clone();
+                    map_state.icons.remove(idx);
+                    map_state.iconset_paths.retain(|p| *p != source);
+                }
+            }
+
             // List GeoJSON layers
             if !map_state.offline_manager.geojson_layers.is_empty() {
                 ui.separator();
@@ -1330,32 +1979,65 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
             track.callsign = callsign;
             track.affiliation = affiliation;
 
+            // Only messages newer than the last one applied add a point
+            if track.last_message.is_some_and(|t| msg.timestamp <= t) {
+                continue;
+            }
+            track.last_message = Some(msg.timestamp);
+
+            // Reported speed/course and group, if the event carries them
+            let detail = msg
+                .raw_content
+                .as_deref()
+                .and_then(|raw| omnitak_cot::parse_cot(raw).ok())
+                .and_then(|event| event.detail);
+            if let Some(group) = detail.as_ref().and_then(|d| d.group.as_ref()) {
+                track.group = Some(group.name.clone());
+            }
+            track.cot_type = msg.msg_type.clone();
+            track.icon_path = detail
+                .as_ref()
+                .and_then(|d| d.xml_detail.as_deref())
+                .and_then(iconsets::usericon_path);
+            let reported = detail.and_then(|d| d.track);
+            let age = (chrono::Utc::now() - msg.timestamp).to_std().unwrap_or_default();
+
             // Add track point
             let point = TrackPoint {
                 lat,
                 lon,
                 altitude: msg.altitude,
-                timestamp: Instant::now(),
-                speed: None,
-                heading: None,
+                timestamp: Instant::now().checked_sub(age).unwrap_or_else(Instant::now),
+                speed: reported.as_ref().map(|t| t.speed),
+                heading: reported.as_ref().map(|t| t.course),
             };
             track.add_point(point);
         }
     }
 
-    // Determine map center
-    let center_pos = if map_state.follow_mode {
-        positions.last()
-            .and_then(|msg| {
-                if let (Some(lat), Some(lon)) = (msg.lat, msg.lon) {
-                    Some(walkers::lat_lon(lat, lon))
-                } else {
-                    None
-                }
-            })
-            .unwrap_or_else(|| walkers::lat_lon(37.7749, -122.4194))
-    } else {
-        walkers::lat_lon(37.7749, -122.4194)
+    // Where the camera should be: a followed position, plus for groups the
+    // widest zoom that keeps every member in view
+    let now = Instant::now();
+    let view_size = ui.available_size();
+    let follow_target: Option<(f64, f64, Option<f64>)> = match &map_state.follow_mode {
+        FollowMode::Off => None,
+        FollowMode::Latest => positions.last().and_then(|msg| Some((msg.lat?, msg.lon?, None))),
+        FollowMode::SelectedTrack => map_state
+            .selected_track
+            .as_ref()
+            .and_then(|uid| map_state.tracks.get(uid))
+            .and_then(|track| track.display_position(now, map_state.smooth_motion))
+            .map(|p| (p.lat, p.lon, None)),
+        FollowMode::Group(name) => {
+            let members: Vec<(f64, f64)> = map_state
+                .tracks
+                .values()
+                .filter(|t| t.group.as_ref() == Some(name))
+                .filter_map(|t| t.display_position(now, map_state.smooth_motion))
+                .map(|p| (p.lat, p.lon))
+                .collect();
+            fit_view(&members, view_size).map(|fit| (fit.lat, fit.lon, Some(fit.zoom)))
+        }
     };
 
     // Check if provider changed and reinitialize tiles
@@ -1365,8 +2047,13 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
         map_state.current_provider = Some(map_state.tile_provider);
     }
 
+    // Start where the map was last left rather than at a fixed location
     if map_state.map_memory.is_none() {
-        map_state.map_memory = Some(MapMemory::default());
+        let camera = map_state.camera.unwrap_or(DEFAULT_CAMERA);
+        let mut memory = MapMemory::default();
+        let _ = memory.set_zoom(camera.zoom);
+        memory.center_at(walkers::lat_lon(camera.lat, camera.lon));
+        map_state.map_memory = Some(memory);
     }
 
     let has_tiles = map_state.tiles.is_some();
@@ -1381,21 +2068,79 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
     let tiles: Option<&mut dyn Tiles> = map_state.tiles.as_mut().map(|t| t as &mut dyn Tiles);
     let memory = map_state.map_memory.as_mut().unwrap();
 
-    // Create plugins
+    if let Some((lat, lon)) = map_state.center_requested.take() {
+        memory.center_at(walkers::lat_lon(lat, lon));
+        if memory.zoom() < SEARCH_ZOOM {
+            let _ = memory.set_zoom(SEARCH_ZOOM);
+        }
+    } else if map_state.zoom_to_fit_requested {
+        map_state.zoom_to_fit_requested = false;
+        let all: Vec<(f64, f64)> = map_state
+            .tracks
+            .values()
+            .filter_map(|t| t.display_position(now, map_state.smooth_motion))
+            .map(|p| (p.lat, p.lon))
+            .collect();
+        if let Some(fit) = fit_view(&all, view_size) {
+            memory.center_at(walkers::lat_lon(fit.lat, fit.lon));
+            let _ = memory.set_zoom(fit.zoom);
+            map_state.follow_mode = FollowMode::Off;
+        }
+    } else if let Some((lat, lon, max_zoom)) = follow_target {
+        memory.center_at(walkers::lat_lon(lat, lon));
+        // Zoom out to keep a group in view, but never zoom in on the user
+        if let Some(zoom) = max_zoom.filter(|z| *z < memory.zoom()) {
+            let _ = memory.set_zoom(zoom);
+        }
+    }
+    let center_pos = memory.detached().unwrap_or_else(|| walkers::lat_lon(DEFAULT_CAMERA.lat, DEFAULT_CAMERA.lon));
+
+    // Keep animating while any track is being dead-reckoned
+    if map_state.smooth_motion {
+        let now = Instant::now();
+        if map_state
+            .tracks
+            .values()
+            .any(|t| t.display_position(now, true).is_some_and(|p| p.predicted))
+        {
+            ui.ctx().request_repaint_after(Duration::from_millis(50));
+        }
+    }
+
+    // Create plugins. With smooth motion, tracked UIDs are drawn only by the
+    // tracking layer so raw report markers don't jump underneath them.
     let markers_plugin = TacticalMarkersPlugin::new(
-        positions.clone(),
+        if map_state.smooth_motion {
+            positions.iter().filter(|msg| msg.uid.is_none()).cloned().collect()
+        } else {
+            positions.clone()
+        },
         map_state.show_altitude,
         map_state.altitude_coloring,
         map_state.min_altitude,
         map_state.max_altitude,
     );
 
+    // Resolve iconset icons for tracks
+    let mut track_icons = HashMap::new();
+    if !map_state.icons.iconsets.is_empty() {
+        for track in map_state.tracks.values() {
+            if let Some(texture) =
+                map_state.icons.texture(ui.ctx(), &track.cot_type, track.icon_path.as_deref())
+            {
+                track_icons.insert(track.uid.clone(), texture);
+            }
+        }
+    }
+
     let bft_plugin = BlueForceTrackingPlugin::new(
         map_state.tracks.values().cloned().collect(),
         map_state.show_trails,
         map_state.trail_length,
         map_state.show_vectors,
+        map_state.smooth_motion,
         map_state.selected_track.clone(),
+        track_icons,
     );
 
     let shapes_plugin = DrawnShapesPlugin::new(
@@ -1404,19 +2149,57 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
         map_state.drawing_tool,
     );
 
+    // Highlight the last search result, following it if it's a track
+    let highlight_plugin = map_state.highlight.as_ref().and_then(|(target, started)| {
+        let progress = started.elapsed().as_secs_f32() / HIGHLIGHT_DURATION.as_secs_f32();
+        if progress >= 1.0 {
+            return None;
+        }
+        let (lat, lon) = match target {
+            SearchTarget::Track(uid) => map_state
+                .tracks
+                .get(uid)
+                .and_then(|t| t.display_position(now, map_state.smooth_motion))
+                .map(|p| (p.lat, p.lon))?,
+            SearchTarget::Location { lat, lon } => (*lat, *lon),
+        };
+        Some(SearchHighlightPlugin { lat, lon, progress })
+    });
+    if highlight_plugin.is_some() {
+        ui.ctx().request_repaint();
+    } else {
+        map_state.highlight = None;
+    }
+
     let overlay_plugin = OverlayLayersPlugin::new(
         map_state.offline_manager.geojson_layers.clone(),
         map_state.offline_manager.kml_layers.clone(),
     );
 
     // Map widget with all plugins
-    let map_response = ui.add(
-        Map::new(tiles, memory, center_pos)
-            .with_plugin(overlay_plugin)
-            .with_plugin(shapes_plugin)
-            .with_plugin(bft_plugin)
-            .with_plugin(markers_plugin),
-    );
+    let mut map = Map::new(tiles, memory, center_pos)
+        .with_plugin(overlay_plugin)
+        .with_plugin(shapes_plugin)
+        .with_plugin(bft_plugin)
+        .with_plugin(markers_plugin);
+    if let Some(highlight) = highlight_plugin {
+        map = map.with_plugin(highlight);
+    }
+    let map_response = ui.add(map);
+
+    // Dragging the map takes the camera back from follow mode
+    if map_response.dragged() && map_state.follow_mode != FollowMode::Off {
+        map_state.follow_mode = FollowMode::Off;
+    }
+
+    // Remember the camera for the next start
+    if let Some(center) = memory.detached() {
+        map_state.camera = Some(CameraPosition {
+            lat: center.y(),
+            lon: center.x(),
+            zoom: memory.zoom(),
+        });
+    }
 
     // Handle map interactions for drawing
     if map_response.clicked() && map_state.drawing_tool != DrawingTool::Select {
@@ -1432,6 +2215,7 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
             match map_state.drawing_tool {
                 DrawingTool::Marker => {
                     map_state.shapes.push(DrawnShape::Marker {
+                        uid: new_shape_uid(),
                         lat: click_lat,
                         lon: click_lon,
                         label: format!("Marker {}", map_state.shapes.len() + 1),
@@ -1443,8 +2227,9 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
                         map_state.drawing_points.push((click_lat, click_lon));
                     } else {
                         let (center_lat, center_lon) = map_state.drawing_points[0];
-                        let radius = haversine_distance(center_lat, center_lon, click_lat, click_lon);
+                        let radius = geodesy::distance_m(center_lat, center_lon, click_lat, click_lon);
                         map_state.shapes.push(DrawnShape::Circle {
+                            uid: new_shape_uid(),
                             center_lat,
                             center_lon,
                             radius_m: radius,
@@ -1456,6 +2241,7 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
                 }
                 DrawingTool::RangeRing => {
                     map_state.shapes.push(DrawnShape::RangeRing {
+                        uid: new_shape_uid(),
                         center_lat: click_lat,
                         center_lon: click_lon,
                         rings: vec![500.0, 1000.0, 2000.0, 5000.0],
@@ -1481,7 +2267,16 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
 
         if let Some((lat, lon)) = map_state.mouse_geo_pos {
             ui.separator();
-            ui.label(format!("📌 {:.5}, {:.5}", lat, lon));
+            let format = map_state.coordinate_format;
+            let readout = ui
+                .add(
+                    egui::Label::new(format!("📌 {}", format.format(lat, lon)))
+                        .sense(egui::Sense::click()),
+                )
+                .on_hover_text("Click to switch between lat/lon, MGRS and UTM");
+            if readout.clicked() {
+                map_state.coordinate_format = format.next();
+            }
         }
     });
 
@@ -1497,7 +2292,7 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
 
                     for track in tracks {
                         let is_selected = map_state.selected_track.as_ref() == Some(&track.uid);
-                        let (speed, heading) = track.calculate_speed_heading();
+                        let (speed, heading) = track.velocity().unzip();
 
                         ui.horizontal(|ui| {
                             let color = match track.affiliation.as_str() {
@@ -1526,9 +2321,163 @@ pub fn show(ui: &mut egui::Ui, app_state: &Arc<Mutex<AppState>>, map_state: &mut
                                 if let Some(hdg) = heading {
                                     ui.label(format!("{}°", hdg as i32));
                                 }
+
+                                if let Some(shown) = track
+                                    .display_position(Instant::now(), map_state.smooth_motion)
+                                    .filter(|p| p.predicted)
+                                {
+                                    ui.colored_label(egui::Color32::GRAY, format!("±{:.0}m", shown.accuracy_m))
+                                        .on_hover_text("Estimated error of the animated position");
+                                }
                             }
                         });
                     }
                 });
         });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(lat: f64, timestamp: Instant) -> TrackPoint {
+        TrackPoint {
+            lat,
+            lon: 0.0,
+            altitude: None,
+            timestamp,
+            speed: None,
+            heading: None,
+        }
+    }
+
+    #[test]
+    fn test_display_position_dead_reckons() {
+        let t0 = Instant::now();
+        let mut track = BlueForceTack::new("T1".into(), "T1".into(), "f".into());
+        track.add_point(point(0.0, t0));
+        track.add_point(point(geodesy::destination(0.0, 0.0, 0.0, 100.0).0, t0 + Duration::from_secs(10)));
+
+        // 10 m/s north; 5 s after the last report the marker is 50 m further on
+        let latest = track.latest().unwrap().clone();
+        let shown = track.display_position(t0 + Duration::from_secs(15), true).unwrap();
+        assert!(shown.predicted);
+        assert!((geodesy::distance_m(latest.lat, latest.lon, shown.lat, shown.lon) - 50.0).abs() < 0.5);
+        assert!((shown.accuracy_m - 50.0 * DEFAULT_ERROR_RATIO).abs() < 0.5);
+
+        // Prediction stops after MAX_PREDICTION
+        let capped = track.display_position(t0 + Duration::from_secs(600), true).unwrap();
+        let distance = geodesy::distance_m(latest.lat, latest.lon, capped.lat, capped.lon);
+        assert!((distance - 10.0 * MAX_PREDICTION.as_secs_f64()).abs() < 1.0);
+
+        // Disabled: the raw report
+        let raw = track.display_position(t0 + Duration::from_secs(15), false).unwrap();
+        assert!(!raw.predicted);
+        assert_eq!((raw.lat, raw.lon), (latest.lat, latest.lon));
+    }
+
+    #[test]
+    fn test_fit_view() {
+        let size = egui::vec2(800.0, 600.0);
+        assert!(fit_view(&[], size).is_none());
+
+        // A single point gets the closest fit zoom
+        let single = fit_view(&[(10.0, 20.0)], size).unwrap();
+        assert!((single.lat - 10.0).abs() < 1e-9);
+        assert_eq!((single.lon, single.zoom), (20.0, MAX_FIT_ZOOM));
+
+        // One degree of longitude at 800 px: log2(800 * 360 / 256) - 0.5
+        let fit = fit_view(&[(0.0, 0.0), (0.0, 1.0)], size).unwrap();
+        assert!((fit.lon - 0.5).abs() < 1e-9);
+        assert!((fit.zoom - ((800.0 * 360.0 / 256.0f64).log2() - 0.5)).abs() < 1e-9);
+
+        // Both corners of the box end up inside the view
+        let fit = fit_view(&[(37.0, -123.0), (38.5, -121.0)], size).unwrap();
+        assert!(fit.lat > 37.0 && fit.lat < 38.5);
+        assert!(fit.zoom < 9.0 && fit.zoom > 6.0);
+    }
+
+    #[test]
+    fn test_search() {
+        let mut tracks = HashMap::new();
+        for (uid, callsign) in [("ANDROID-1", "Alpha-1"), ("ANDROID-2", "Alpha-2"), ("S-7", "Bravo")] {
+            tracks.insert(uid.to_string(), BlueForceTack::new(uid.into(), callsign.into(), "f".into()));
+        }
+        let track = |uid: &str| SearchTarget::Track(uid.to_string());
+
+        // Exact UID or callsign wins over partial matches
+        assert_eq!(search("S-7", &tracks), vec![track("S-7")]);
+        assert_eq!(search(" alpha-2 ", &tracks), vec![track("ANDROID-2")]);
+        assert_eq!(search("alpha", &tracks), vec![track("ANDROID-1"), track("ANDROID-2")]);
+        assert_eq!(search("android", &tracks).len(), 2);
+        assert!(search("charlie", &tracks).is_empty());
+        assert!(search("", &tracks).is_empty());
+
+        // Coordinates
+        assert_eq!(
+            search("37.77, -122.42", &tracks),
+            vec![SearchTarget::Location { lat: 37.77, lon: -122.42 }]
+        );
+        let Some(SearchTarget::Location { lat, lon }) = search("38SMB4484", &tracks).pop() else {
+            panic!("MGRS not resolved");
+        };
+        assert!(geodesy::distance_m(lat, lon, 33.3, 44.4) < 1_000.0);
+        let Some(SearchTarget::Location { lat, lon }) = search("38S 444140 3684706", &tracks).pop() else {
+            panic!("UTM not resolved");
+        };
+        assert!(geodesy::distance_m(lat, lon, 33.3, 44.4) < 10.0);
+    }
+
+    #[test]
+    fn test_coordinate_format() {
+        let baghdad = |format: CoordinateFormat| format.format(33.3, 44.4);
+        assert_eq!(baghdad(CoordinateFormat::LatLon), "33.30000, 44.40000");
+        assert_eq!(baghdad(CoordinateFormat::Mgrs), "38SMB4414084706");
+        assert_eq!(baghdad(CoordinateFormat::Utm), "38S 444140 3684706");
+
+        // No grid coordinate near the poles
+        let polar = CoordinateFormat::Mgrs.format(85.0, 10.0);
+        assert_eq!(polar, "85.00000, 10.00000");
+        assert_eq!(CoordinateFormat::Utm.next(), CoordinateFormat::LatLon);
+    }
+
+    #[test]
+    fn test_parse_lat_lon() {
+        assert_eq!(parse_lat_lon("37.77 -122.42"), Some((37.77, -122.42)));
+        assert_eq!(parse_lat_lon("33.9°S, 151.2°E"), Some((-33.9, 151.2)));
+        assert_eq!(parse_lat_lon("37.77N 122.42w"), Some((37.77, -122.42)));
+        assert_eq!(parse_lat_lon("91, 0"), None);
+        assert_eq!(parse_lat_lon("37.77"), None);
+        assert_eq!(parse_lat_lon("1 2 3"), None);
+    }
+
+    #[test]
+    fn test_new_report_scores_prediction_and_blends() {
+        let t0 = Instant::now();
+        let mut track = BlueForceTack::new("T1".into(), "T1".into(), "f".into());
+        track.add_point(TrackPoint {
+            speed: Some(10.0),
+            heading: Some(0.0),
+            ..point(0.0, t0)
+        });
+
+        // Reported 80 m north after 10 s, where 100 m was predicted
+        let t1 = t0 + Duration::from_secs(10);
+        track.add_point(TrackPoint {
+            speed: Some(10.0),
+            heading: Some(0.0),
+            ..point(geodesy::destination(0.0, 0.0, 0.0, 80.0).0, t1)
+        });
+        assert!((track.error_ratio.unwrap() - 0.2).abs() < 0.01);
+
+        // The marker starts where it was drawn, not at the new report
+        let shown = track.display_position(t1, true).unwrap();
+        let predicted = geodesy::destination(0.0, 0.0, 0.0, 100.0);
+        assert!(geodesy::distance_m(shown.lat, shown.lon, predicted.0, predicted.1) < 0.5);
+
+        // ...and has caught up once the blend is over
+        let later = track.display_position(t1 + CORRECTION_BLEND, true).unwrap();
+        let expected = geodesy::destination(0.0, 0.0, 0.0, 80.0 + 7.5);
+        assert!(geodesy::distance_m(later.lat, later.lon, expected.0, expected.1) < 0.5);
+    }
+}
@@ -0,0 +1,549 @@
+//! Data packages from live map content
+//!
+//! Packages shapes drawn on the map, tracks and overlay layers into a TAK
+//! data package. Shapes and tracks become CoT events stored under their UIDs,
+//! the layout ATAK and WinTAK import as map items; overlays are included as
+//! the files they were loaded from.
+
+use chrono::{DateTime, Utc};
+use eframe::egui;
+use omnitak_cot::event::{Contact, Detail, Event, Group, Point, Shape, Track};
+use omnitak_cot::serializer::serialize_event;
+use omnitak_datapackage::DataPackageBuilder;
+use poll_promise::Promise;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use crate::ui::map::{BlueForceTack, DrawnShape, MapPanelState};
+
+/// How long shared shapes stay on receiving maps
+const SHAPE_STALE: chrono::Duration = chrono::Duration::days(365);
+
+/// How long a shared track position stays on receiving maps
+const TRACK_STALE: chrono::Duration = chrono::Duration::hours(1);
+
+/// Height used when a position has no altitude, per the CoT convention
+const UNKNOWN_HAE: f64 = 9999999.0;
+
+/// Map package dialog state
+#[derive(Default)]
+pub struct MapPackageDialog {
+    pub name: String,
+    pub delete_on_receive: bool,
+    /// UIDs of the selected shapes and tracks
+    pub selected: HashSet<String>,
+    /// Source files of the selected overlay layers
+    pub overlays: HashSet<PathBuf>,
+    save_promise: Option<Promise<Result<Option<PathBuf>, String>>>,
+    /// Result of the last save, and whether it failed
+    status: Option<(String, bool)>,
+}
+
+impl MapPackageDialog {
+    /// Dialog with every drawn shape selected
+    pub fn new(map_state: &MapPanelState) -> Self {
+        Self {
+            name: "map-items".to_string(),
+            selected: map_state
+                .shapes
+                .iter()
+                .map(|s| s.uid().to_string())
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Show the map package dialog, if open
+pub fn show_dialog(ctx: &egui::Context, map_state: &mut MapPanelState) {
+    let Some(mut dialog) = map_state.package_dialog.take() else {
+        return;
+    };
+
+    if let Some(result) = dialog.save_promise.as_ref().and_then(|p| p.ready()) {
+        dialog.status = match result {
+            Ok(Some(path)) => Some((format!("Saved {}", path.display()), false)),
+            Ok(None) => None,
+            Err(e) => Some((format!("Failed to save package: {}", e), true)),
+        };
+        dialog.save_promise = None;
+    }
+
+    let mut open = true;
+    let mut close = false;
+    egui::Window::new("📦 Map Data Package")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(true)
+        .default_width(360.0)
+        .show(ctx, |ui| {
+            egui::Grid::new("map_package_options")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Package Name:");
+                    ui.text_edit_singleline(&mut dialog.name);
+                    ui.end_row();
+
+                    ui.label("Delete on Receive:");
+                    ui.checkbox(&mut dialog.delete_on_receive, "");
+                    ui.end_row();
+                });
+            ui.separator();
+
+            egui::ScrollArea::vertical()
+                .max_height(320.0)
+                .show(ui, |ui| {
+                    ui.strong(format!("Shapes ({})", map_state.shapes.len()));
+                    for shape in &map_state.shapes {
+                        toggle(
+                            ui,
+                            &mut dialog.selected,
+                            shape.uid().to_string(),
+                            shape.describe(),
+                        );
+                    }
+
+                    let mut tracks: Vec<&BlueForceTack> = map_state.tracks.values().collect();
+                    tracks.sort_by(|a, b| a.callsign.cmp(&b.callsign));
+                    ui.strong(format!("Tracks ({})", tracks.len()));
+                    for track in tracks {
+                        toggle(
+                            ui,
+                            &mut dialog.selected,
+                            track.uid.clone(),
+                            format!("👤 {}", track.callsign),
+                        );
+                    }
+
+                    let overlays = &map_state.offline_manager;
+                    ui.strong(format!(
+                        "Overlays ({})",
+                        overlays.geojson_layers.len() + overlays.kml_layers.len()
+                    ));
+                    let layers = overlays
+                        .geojson_layers
+                        .iter()
+                        .map(|l| (&l.source, &l.name))
+                        .chain(overlays.kml_layers.iter().map(|l| (&l.source, &l.name)));
+                    for (source, name) in layers {
+                        if source.as_os_str().is_empty() {
+                            continue;
+                        }
+                        toggle(
+                            ui,
+                            &mut dialog.overlays,
+                            source.clone(),
+                            format!("🗺 {}", name),
+                        );
+                    }
+                });
+            ui.separator();
+
+            if let Some((message, failed)) = &dialog.status {
+                let color = if *failed {
+                    egui::Color32::LIGHT_RED
+                } else {
+                    egui::Color32::LIGHT_GREEN
+                };
+                ui.colored_label(color, message);
+            }
+
+            ui.horizontal(|ui| {
+                let ready = !dialog.name.trim().is_empty()
+                    && (!dialog.selected.is_empty() || !dialog.overlays.is_empty())
+                    && dialog.save_promise.is_none();
+                if ui
+                    .add_enabled(ready, egui::Button::new("💾 Save Package"))
+                    .clicked()
+                {
+                    dialog.save_promise = Some(spawn_save_package(&dialog, map_state));
+                }
+                if dialog.save_promise.is_some() {
+                    ui.spinner();
+                }
+                if ui.button("Close").clicked() {
+                    close = true;
+                }
+            });
+        });
+
+    if open && !close {
+        map_state.package_dialog = Some(dialog);
+    }
+}
+
+fn toggle<T: std::hash::Hash + Eq>(ui: &mut egui::Ui, set: &mut HashSet<T>, key: T, label: String) {
+    let mut checked = set.contains(&key);
+    if ui.checkbox(&mut checked, label).changed() {
+        if checked {
+            set.insert(key);
+        } else {
+            set.remove(&key);
+        }
+    }
+}
+
+/// Snapshot the selected items and write the package off the UI thread
+fn spawn_save_package(
+    dialog: &MapPackageDialog,
+    map_state: &MapPanelState,
+) -> Promise<Result<Option<PathBuf>, String>> {
+    let now = Utc::now();
+    let mut events: Vec<Event> = map_state
+        .shapes
+        .iter()
+        .filter(|s| dialog.selected.contains(s.uid()))
+        .flat_map(|s| shape_events(s, now))
+        .collect();
+    events.extend(
+        map_state
+            .tracks
+            .values()
+            .filter(|t| dialog.selected.contains(&t.uid))
+            .filter_map(|t| track_event(t, now)),
+    );
+    let overlays: Vec<PathBuf> = dialog.overlays.iter().cloned().collect();
+
+    let name = package_file_name(dialog.name.trim());
+    let delete_on_receive = dialog.delete_on_receive;
+
+    Promise::spawn_thread("map_package", move || {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("TAK Data Package", &["zip"])
+            .set_file_name(&name)
+            .set_title("Save Map Data Package")
+            .save_file()
+        else {
+            return Ok(None);
+        };
+
+        build_package(&name, delete_on_receive, &events, &overlays)
+            .and_then(|builder| builder.build(&path))
+            .map(Some)
+            .map_err(|e| e.to_string())
+    })
+}
+
+fn package_file_name(name: &str) -> String {
+    if name.ends_with(".zip") || name.ends_with(".dpk") {
+        name.to_string()
+    } else {
+        format!("{}.zip", name)
+    }
+}
+
+/// Package map item events and overlay files
+pub fn build_package(
+    name: &str,
+    delete_on_receive: bool,
+    events: &[Event],
+    overlays: &[PathBuf],
+) -> omnitak_datapackage::Result<DataPackageBuilder> {
+    let mut builder = DataPackageBuilder::new(name).on_receive_delete(delete_on_receive);
+    for event in events {
+        builder = builder.add_map_item(&event.uid, &serialize_event(event))?;
+    }
+
+    let mut entries = HashSet::new();
+    for (i, path) in overlays.iter().enumerate() {
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| format!("overlay-{}", i));
+        // Layers loaded from different folders may share a file name
+        let mut entry = format!("overlays/{}", file_name);
+        if !entries.insert(entry.clone()) {
+            entry = format!("overlays/{}-{}", i, file_name);
+            entries.insert(entry.clone());
+        }
+        builder = builder.add_file(path, &entry)?;
+    }
+    Ok(builder)
+}
+
+/// CoT events for a drawn shape, in ATAK's drawing formats. Range rings
+/// become one circle per ring.
+pub fn shape_events(shape: &DrawnShape, now: DateTime<Utc>) -> Vec<Event> {
+    match shape {
+        DrawnShape::Marker {
+            uid,
+            lat,
+            lon,
+            label,
+            color,
+        } => {
+            let mut event = map_item(uid, "b-m-p-s-m", *lat, *lon, now);
+            let detail = event.detail.get_or_insert_with(Detail::new);
+            detail.contact = Some(contact(label));
+            detail.color = Some(argb(*color, 0xff));
+            vec![event]
+        }
+        DrawnShape::Line {
+            uid,
+            points,
+            color,
+            width,
+        } => {
+            let mut event = polyline(uid, "Line", points, now);
+            let detail = event.detail.get_or_insert_with(Detail::new);
+            detail.stroke_color = Some(argb(*color, 0xff));
+            detail.stroke_weight = Some(*width as f64);
+            vec![event]
+        }
+        DrawnShape::Polygon {
+            uid,
+            points,
+            color,
+            filled,
+        } => {
+            // ATAK closes a freehand shape whose last point repeats the first
+            let mut closed = points.clone();
+            closed.extend(points.first().copied());
+            let mut event = polyline(uid, "Polygon", &closed, now);
+            let detail = event.detail.get_or_insert_with(Detail::new);
+            detail.stroke_color = Some(argb(*color, 0xff));
+            detail.stroke_weight = Some(2.0);
+            if *filled {
+                detail.fill_color = Some(argb(*color, 0x4d));
+            }
+            vec![event]
+        }
+        DrawnShape::Circle {
+            uid,
+            center_lat,
+            center_lon,
+            radius_m,
+            color,
+            filled,
+        } => {
+            let fill = filled.then_some(*color);
+            vec![circle(
+                uid,
+                "Circle",
+                *center_lat,
+                *center_lon,
+                *radius_m,
+                *color,
+                fill,
+                now,
+            )]
+        }
+        DrawnShape::RangeRing {
+            uid,
+            center_lat,
+            center_lon,
+            rings,
+            color,
+        } => rings
+            .iter()
+            .enumerate()
+            .map(|(i, radius_m)| {
+                let ring_uid = format!("{}-{}", uid, i + 1);
+                let label = format!("Range Ring {:.0} m", radius_m);
+                circle(
+                    &ring_uid,
+                    &label,
+                    *center_lat,
+                    *center_lon,
+                    *radius_m,
+                    *color,
+                    None,
+                    now,
+                )
+            })
+            .collect(),
+    }
+}
+
+/// CoT event for the latest position of a track
+pub fn track_event(track: &BlueForceTack, now: DateTime<Utc>) -> Option<Event> {
+    let point = track.latest()?;
+    let cot_type = if track.cot_type.is_empty() {
+        "a-u-G"
+    } else {
+        &track.cot_type
+    };
+    let time = track.last_message.unwrap_or(now);
+
+    Some(Event {
+        version: "2.0".to_string(),
+        uid: track.uid.clone(),
+        event_type: cot_type.to_string(),
+        time,
+        start: time,
+        stale: now + TRACK_STALE,
+        how: "m-g".to_string(),
+        point: Point::new(point.lat, point.lon, point.altitude.unwrap_or(UNKNOWN_HAE)),
+        detail: Some(Detail {
+            contact: Some(contact(&track.callsign)),
+            group: track.group.as_ref().map(|name| Group {
+                name: name.clone(),
+                role: "Team Member".to_string(),
+            }),
+            track: point
+                .speed
+                .zip(point.heading)
+                .map(|(speed, course)| Track { speed, course }),
+            ..Default::default()
+        }),
+    })
+}
+
+fn map_item(uid: &str, cot_type: &str, lat: f64, lon: f64, now: DateTime<Utc>) -> Event {
+    Event {
+        version: "2.0".to_string(),
+        uid: uid.to_string(),
+        event_type: cot_type.to_string(),
+        time: now,
+        start: now,
+        stale: now + SHAPE_STALE,
+        how: "h-e".to_string(),
+        point: Point::new(lat, lon, UNKNOWN_HAE),
+        detail: None,
+    }
+}
+
+/// `u-d-f` freehand shape through `points`, listed as `<link point=..>`
+fn polyline(uid: &str, label: &str, points: &[(f64, f64)], now: DateTime<Utc>) -> Event {
+    let (lat, lon) = points.first().copied().unwrap_or_default();
+    let mut event = map_item(uid, "u-d-f", lat, lon, now);
+    event.detail = Some(Detail {
+        contact: Some(contact(label)),
+        labels_on: Some(false),
+        xml_detail: Some(
+            points
+                .iter()
+                .map(|(lat, lon)| format!(r#"<link point="{},{}"/>"#, lat, lon))
+                .collect(),
+        ),
+        ..Default::default()
+    });
+    event
+}
+
+#[allow(clippy::too_many_arguments)]
+fn circle(
+    uid: &str,
+    label: &str,
+    lat: f64,
+    lon: f64,
+    radius_m: f64,
+    color: [u8; 3],
+    fill: Option<[u8; 3]>,
+    now: DateTime<Utc>,
+) -> Event {
+    let mut event = map_item(uid, "u-d-c-c", lat, lon, now);
+    event.detail = Some(Detail {
+        contact: Some(contact(label)),
+        shape: Some(Shape::Ellipse {
+            major: radius_m,
+            minor: radius_m,
+            angle: 0.0,
+        }),
+        stroke_color: Some(argb(color, 0xff)),
+        stroke_weight: Some(2.0),
+        fill_color: fill.map(|c| argb(c, 0x4d)),
+        labels_on: Some(false),
+        ..Default::default()
+    });
+    event
+}
+
+fn contact(callsign: &str) -> Contact {
+    Contact {
+        endpoint: None,
+        callsign: escape_attr(callsign),
+    }
+}
+
+/// ATAK's signed ARGB color value
+fn argb(color: [u8; 3], alpha: u8) -> i32 {
+    i32::from_be_bytes([alpha, color[0], color[1], color[2]])
+}
+
+/// The CoT serializer writes attribute values as given
+fn escape_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shape_events() {
+        let now = Utc::now();
+        let marker = DrawnShape::Marker {
+            uid: "marker-1".to_string(),
+            lat: 1.0,
+            lon: 2.0,
+            label: "HLZ \"Alpha\"".to_string(),
+            color: [255, 0, 0],
+        };
+        let events = shape_events(&marker, now);
+        assert_eq!(events[0].uid, "marker-1");
+        assert_eq!(events[0].event_type, "b-m-p-s-m");
+        let xml = serialize_event(&events[0]);
+        assert!(xml.contains(r#"callsign="HLZ &quot;Alpha&quot;""#));
+        assert!(xml.contains(r#"<color value="-65536"/>"#));
+
+        let polygon = DrawnShape::Polygon {
+            uid: "poly-1".to_string(),
+            points: vec![(0.0, 0.0), (0.0, 1.0), (1.0, 1.0)],
+            color: [0, 255, 0],
+            filled: true,
+        };
+        let xml = serialize_event(&shape_events(&polygon, now)[0]);
+        assert_eq!(xml.matches("<link point=").count(), 4);
+        assert!(xml.contains(r#"type="u-d-f""#));
+
+        let rings = DrawnShape::RangeRing {
+            uid: "rings".to_string(),
+            center_lat: 1.0,
+            center_lon: 2.0,
+            rings: vec![500.0, 1000.0],
+            color: [255, 100, 100],
+        };
+        let uids: Vec<String> = shape_events(&rings, now)
+            .into_iter()
+            .map(|e| e.uid)
+            .collect();
+        assert_eq!(uids, ["rings-1", "rings-2"]);
+    }
+
+    #[test]
+    fn test_build_package() {
+        let dir = tempfile::tempdir().unwrap();
+        let overlay = dir.path().join("route.kml");
+        std::fs::write(&overlay, "<kml/>").unwrap();
+
+        let now = Utc::now();
+        let circle = DrawnShape::Circle {
+            uid: "circle-1".to_string(),
+            center_lat: 1.0,
+            center_lon: 2.0,
+            radius_m: 250.0,
+            color: [100, 200, 255],
+            filled: false,
+        };
+        let events = shape_events(&circle, now);
+        let path = build_package("map.zip", false, &events, &[overlay])
+            .unwrap()
+            .build(dir.path().join("map.zip"))
+            .unwrap();
+
+        let reader = omnitak_datapackage::DataPackageReader::open(path).unwrap();
+        let contents = &reader.manifest().contents;
+        assert_eq!(contents[0].zip_entry, "circle-1/circle-1.cot");
+        assert_eq!(contents[0].uid(), Some("circle-1"));
+        assert_eq!(contents[1].zip_entry, "overlays/route.kml");
+        assert!(reader
+            .read_file_string("circle-1/circle-1.cot")
+            .unwrap()
+            .contains(r#"<ellipse major="250" minor="250""#));
+    }
+}
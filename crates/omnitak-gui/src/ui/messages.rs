@@ -2,6 +2,7 @@
 
 use crate::{AffiliationFilter, AppState, MessageLog, UiState};
 use eframe::egui;
+use omnitak_cot::mgrs;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
@@ -469,6 +470,16 @@ fn show_message_details_dialog(ctx: &egui::Context, ui_state: &mut UiState) {
                                 ui.end_row();
                             }
 
+                            if let Some(grid) = msg
+                                .lat
+                                .zip(msg.lon)
+                                .and_then(|(lat, lon)| mgrs::to_mgrs(lat, lon, 5).ok())
+                            {
+                                ui.label(egui::RichText::new("MGRS:").strong());
+                                ui.label(grid);
+                                ui.end_row();
+                            }
+
                             if let Some(alt) = msg.altitude {
                                 ui.label(egui::RichText::new("Altitude:").strong());
                                 ui.label(format!("{:.2} m", alt));
@@ -5,8 +5,13 @@ pub mod command_palette;
 pub mod connections;
 pub mod dashboard;
 pub mod datapackage;
+pub mod devices;
+pub mod discovery;
+pub mod emergency;
 pub mod enrollment;
+pub mod iconsets;
 pub mod map;
+pub mod map_package;
 pub mod messages;
 pub mod offline_maps;
 pub mod plugins;
@@ -266,6 +266,9 @@ pub struct CacheStats {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeoJsonLayer {
     pub name: String,
+    /// File the layer was loaded from
+    #[serde(default)]
+    pub source: PathBuf,
     pub visible: bool,
     pub features: Vec<GeoFeature>,
     pub color: [u8; 3],
@@ -374,6 +377,7 @@ impl GeoJsonLayer {
 
         Ok(Self {
             name,
+            source: path.to_path_buf(),
             visible: true,
             features,
             color: [255, 165, 0], // Orange
@@ -386,6 +390,9 @@ impl GeoJsonLayer {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KmlLayer {
     pub name: String,
+    /// File the layer was loaded from
+    #[serde(default)]
+    pub source: PathBuf,
     pub visible: bool,
     pub placemarks: Vec<KmlPlacemark>,
     pub color: [u8; 3],
@@ -517,6 +524,7 @@ impl KmlLayer {
 
         Ok(Self {
             name,
+            source: path.to_path_buf(),
             visible: true,
             placemarks,
             color: [0, 255, 255], // Cyan
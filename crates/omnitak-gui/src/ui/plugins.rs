@@ -1,9 +1,11 @@
 //! Plugin management UI panel
 
 use crate::{ApiClient, AppState, StatusLevel};
-use crate::api_client::{LoadPluginRequest, PluginApiType, PluginMetricsResponse};
+use crate::api_client::{
+    ConfigField, ConfigFieldType, LoadPluginRequest, PluginCapability, PluginInfo,
+    PluginMetricsResponse, PluginSettingsSnapshot,
+};
 use eframe::egui;
-use omnitak_plugin_api::{PluginInfo, PluginCapability};
 use poll_promise::Promise;
 use std::sync::{Arc, Mutex};
 
@@ -73,6 +75,7 @@ pub enum PluginTypeFilter {
     All,
     Filter,
     Transformer,
+    Scheduled,
 }
 
 pub struct LoadPluginDialog {
@@ -97,10 +100,78 @@ impl Default for LoadPluginDialog {
 
 pub struct ConfigEditorDialog {
     pub plugin_id: String,
-    pub config_json: String,
+    /// One entry per setting in the plugin's config schema, once loaded
+    pub fields: Option<Vec<ConfigFieldEdit>>,
+    /// Promise for loading the current settings
+    pub load_promise: Option<Promise<Result<PluginSettingsSnapshot, String>>>,
+    /// Promise for saving the edited settings
+    pub save_promise: Option<Promise<Result<String, String>>>,
     pub error_message: Option<String>,
 }
 
+impl ConfigEditorDialog {
+    fn new(plugin_id: String, client: &ApiClient) -> Self {
+        Self {
+            load_promise: Some(spawn_get_config(client.clone(), plugin_id.clone())),
+            plugin_id,
+            fields: None,
+            save_promise: None,
+            error_message: None,
+        }
+    }
+}
+
+/// A setting being edited. Booleans use `checked`, everything else is
+/// edited as text and parsed on save
+pub struct ConfigFieldEdit {
+    pub field: ConfigField,
+    pub text: String,
+    pub checked: bool,
+}
+
+impl ConfigFieldEdit {
+    fn new(field: ConfigField, value: Option<&serde_json::Value>) -> Self {
+        let mut edit = Self {
+            field,
+            text: String::new(),
+            checked: false,
+        };
+        edit.set(value);
+        edit
+    }
+
+    fn set(&mut self, value: Option<&serde_json::Value>) {
+        match value {
+            Some(serde_json::Value::String(s)) => self.text = s.clone(),
+            Some(serde_json::Value::Bool(b)) => self.checked = *b,
+            Some(v) => self.text = v.to_string(),
+            None => {
+                self.text.clear();
+                self.checked = false;
+            }
+        }
+    }
+
+    /// The value to send. An empty number field resets the setting to its
+    /// default
+    fn value(&self) -> Result<serde_json::Value, String> {
+        let text = self.text.trim();
+        match self.field.r#type {
+            ConfigFieldType::String => Ok(serde_json::Value::String(self.text.clone())),
+            ConfigFieldType::Boolean => Ok(serde_json::Value::Bool(self.checked)),
+            _ if text.is_empty() => Ok(serde_json::Value::Null),
+            ConfigFieldType::Integer => text
+                .parse::<i64>()
+                .map(Into::into)
+                .map_err(|_| format!("{} must be a whole number", self.field.key)),
+            ConfigFieldType::Float => text
+                .parse::<f64>()
+                .map(Into::into)
+                .map_err(|_| format!("{} must be a number", self.field.key)),
+        }
+    }
+}
+
 /// Render the plugins panel
 pub fn render_plugins_panel(
     ui: &mut egui::Ui,
@@ -226,6 +297,7 @@ pub fn render_plugins_panel(
                 ui.selectable_value(&mut panel_state.type_filter, PluginTypeFilter::All, "All");
                 ui.selectable_value(&mut panel_state.type_filter, PluginTypeFilter::Filter, "Filter");
                 ui.selectable_value(&mut panel_state.type_filter, PluginTypeFilter::Transformer, "Transformer");
+                ui.selectable_value(&mut panel_state.type_filter, PluginTypeFilter::Scheduled, "Scheduled");
             });
     });
 
@@ -302,7 +374,7 @@ pub fn render_plugins_panel(
             .default_width(600.0)
             .default_height(400.0)
             .show(ui.ctx(), |ui| {
-                if let Some(msg) = render_config_editor_dialog(ui, dialog) {
+                if let Some(msg) = render_config_editor_dialog(ui, dialog, api_client) {
                     status_message = Some(msg);
                     close_config_editor = true;
                 }
@@ -337,6 +409,8 @@ fn render_plugin_card(
                     "🔍"
                 } else if plugin.capabilities.contains(&PluginCapability::Transform) {
                     "⚙️"
+                } else if plugin.capabilities.contains(&PluginCapability::Schedule) {
+                    "⏱"
                 } else {
                     "📦"
                 };
@@ -377,6 +451,7 @@ fn render_plugin_card(
                             let (text, color) = match cap {
                                 PluginCapability::Filter => ("Filter", egui::Color32::from_rgb(100, 149, 237)),
                                 PluginCapability::Transform => ("Transform", egui::Color32::from_rgb(144, 238, 144)),
+                                PluginCapability::Schedule => ("Scheduled", egui::Color32::from_rgb(186, 85, 211)),
                                 PluginCapability::NetworkAccess => ("Network", egui::Color32::from_rgb(255, 165, 0)),
                                 PluginCapability::FilesystemAccess => ("Filesystem", egui::Color32::from_rgb(255, 99, 71)),
                             };
@@ -395,11 +470,10 @@ fn render_plugin_card(
                     ui.horizontal(|ui| {
                         ui.add_enabled_ui(can_interact, |ui| {
                             if ui.button("Configure").clicked() {
-                                panel_state.config_editor = Some(ConfigEditorDialog {
-                                    plugin_id: plugin.id.clone(),
-                                    config_json: "{}".to_string(),
-                                    error_message: None,
-                                });
+                                if let Some(client) = api_client {
+                                    panel_state.config_editor =
+                                        Some(ConfigEditorDialog::new(plugin.id.clone(), client));
+                                }
                             }
 
                             if ui.button("Metrics").clicked() {
@@ -567,6 +641,7 @@ fn render_load_plugin_dialog(
                 .show_ui(ui, |ui| {
                     ui.selectable_value(&mut dialog.plugin_type, "filter".to_string(), "Filter");
                     ui.selectable_value(&mut dialog.plugin_type, "transformer".to_string(), "Transformer");
+                    ui.selectable_value(&mut dialog.plugin_type, "scheduled".to_string(), "Scheduled");
                 });
             ui.end_row();
 
@@ -608,23 +683,100 @@ fn render_load_plugin_dialog(
 fn render_config_editor_dialog(
     ui: &mut egui::Ui,
     dialog: &mut ConfigEditorDialog,
+    api_client: Option<&ApiClient>,
 ) -> Option<(String, StatusLevel)> {
     let mut status_message = None;
 
+    if let Some(promise) = &dialog.load_promise {
+        if let Some(result) = promise.ready() {
+            match result {
+                Ok(snapshot) => {
+                    dialog.fields = Some(
+                        snapshot
+                            .schema
+                            .iter()
+                            .map(|f| ConfigFieldEdit::new(f.clone(), snapshot.values.get(&f.key)))
+                            .collect(),
+                    );
+                }
+                Err(e) => {
+                    dialog.error_message = Some(format!("Failed to load configuration: {}", e));
+                }
+            }
+            dialog.load_promise = None;
+        }
+    }
+
+    if let Some(promise) = &dialog.save_promise {
+        if let Some(result) = promise.ready() {
+            match result {
+                Ok(msg) => status_message = Some((msg.clone(), StatusLevel::Success)),
+                Err(e) => dialog.error_message = Some(e.clone()),
+            }
+            dialog.save_promise = None;
+        }
+    }
+
     ui.add_space(10.0);
 
-    ui.label("Plugin Configuration (JSON):");
-    ui.add_space(5.0);
+    let Some(fields) = dialog.fields.as_mut() else {
+        if dialog.load_promise.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Loading configuration...");
+            });
+        }
+        if let Some(ref error) = dialog.error_message {
+            ui.label(egui::RichText::new(error).color(egui::Color32::RED));
+        }
+        return status_message;
+    };
+
+    if fields.is_empty() {
+        ui.label("This plugin has no configurable settings.");
+        ui.add_space(10.0);
+        if ui.button("Close").clicked() {
+            status_message = Some(("Cancelled".to_string(), StatusLevel::Info));
+        }
+        return status_message;
+    }
 
     egui::ScrollArea::vertical()
         .max_height(300.0)
         .show(ui, |ui| {
-            ui.add(
-                egui::TextEdit::multiline(&mut dialog.config_json)
-                    .font(egui::TextStyle::Monospace)
-                    .desired_width(f32::INFINITY)
-                    .desired_rows(15)
-            );
+            egui::Grid::new("plugin_config_grid")
+                .num_columns(2)
+                .spacing([10.0, 8.0])
+                .show(ui, |ui| {
+                    for edit in fields.iter_mut() {
+                        let description = edit.field.description.clone().unwrap_or_default();
+                        ui.label(&edit.field.key).on_hover_text(&description);
+                        ui.vertical(|ui| {
+                            match edit.field.r#type {
+                                ConfigFieldType::Boolean => {
+                                    ui.checkbox(&mut edit.checked, "");
+                                }
+                                _ => {
+                                    let hint = edit
+                                        .field
+                                        .default
+                                        .as_ref()
+                                        .map(|d| d.to_string())
+                                        .unwrap_or_default();
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut edit.text)
+                                            .hint_text(hint)
+                                            .desired_width(250.0),
+                                    );
+                                }
+                            }
+                            if !description.is_empty() {
+                                ui.label(egui::RichText::new(&description).small().weak());
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
         });
 
     ui.add_space(10.0);
@@ -635,20 +787,31 @@ fn render_config_editor_dialog(
     }
 
     ui.horizontal(|ui| {
-        if ui.button("Save").clicked() {
-            // Validate JSON
-            match serde_json::from_str::<serde_json::Value>(&dialog.config_json) {
-                Ok(_) => {
-                    // TODO: Call API to update config
-                    status_message = Some((
-                        format!("Configuration updated for plugin: {}", dialog.plugin_id),
-                        StatusLevel::Success,
-                    ));
-                }
-                Err(e) => {
-                    dialog.error_message = Some(format!("Invalid JSON: {}", e));
+        let saving = dialog.save_promise.is_some();
+        ui.add_enabled_ui(!saving && api_client.is_some(), |ui| {
+            if ui.button("Save").clicked() {
+                let values: Result<serde_json::Map<String, serde_json::Value>, String> = fields
+                    .iter()
+                    .map(|edit| Ok((edit.field.key.clone(), edit.value()?)))
+                    .collect();
+                match values {
+                    Ok(values) => {
+                        if let Some(client) = api_client {
+                            dialog.error_message = None;
+                            dialog.save_promise = Some(spawn_update_config(
+                                client.clone(),
+                                dialog.plugin_id.clone(),
+                                serde_json::Value::Object(values),
+                            ));
+                        }
+                    }
+                    Err(e) => dialog.error_message = Some(e),
                 }
             }
+        });
+
+        if saving {
+            ui.spinner();
         }
 
         if ui.button("Cancel").clicked() {
@@ -656,13 +819,11 @@ fn render_config_editor_dialog(
         }
 
         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-            if ui.button("Load Example").clicked() {
-                dialog.config_json = serde_json::to_string_pretty(&serde_json::json!({
-                    "center_lat": 35.0,
-                    "center_lon": -79.0,
-                    "radius_degrees": 2.0,
-                    "enabled": true
-                })).unwrap();
+            if ui.button("Reset to Defaults").clicked() {
+                for edit in fields.iter_mut() {
+                    let default = edit.field.default.clone();
+                    edit.set(default.as_ref());
+                }
             }
         });
     });
@@ -696,6 +857,7 @@ fn filter_plugins(plugins: &[PluginInfo], panel_state: &PluginPanelState) -> Vec
                 PluginTypeFilter::All => true,
                 PluginTypeFilter::Filter => p.capabilities.contains(&PluginCapability::Filter),
                 PluginTypeFilter::Transformer => p.capabilities.contains(&PluginCapability::Transform),
+                PluginTypeFilter::Scheduled => p.capabilities.contains(&PluginCapability::Schedule),
             }
         })
         .cloned()
@@ -786,6 +948,16 @@ fn spawn_get_metrics(client: ApiClient, plugin_id: String) -> Promise<Result<Plu
     })
 }
 
+/// Spawn async task to get plugin config
+fn spawn_get_config(client: ApiClient, plugin_id: String) -> Promise<Result<PluginSettingsSnapshot, String>> {
+    Promise::spawn_thread("get_plugin_config", move || {
+        let rt = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+        rt.block_on(async {
+            client.get_plugin_config(&plugin_id).await.map_err(|e| e.to_string())
+        })
+    })
+}
+
 /// Spawn async task to update plugin config
 fn spawn_update_config(client: ApiClient, plugin_id: String, config: serde_json::Value) -> Promise<Result<String, String>> {
     let id = plugin_id.clone();
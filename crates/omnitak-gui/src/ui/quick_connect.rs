@@ -10,6 +10,7 @@ use crate::{OmniTakApp, StatusLevel};
 use eframe::egui;
 use omnitak_cert::{ExtractedCertificates, extract_zip_certificates, scan_directory_for_certificates};
 use omnitak_core::types::{Protocol, ReconnectConfig, ServerConfig, TlsConfig};
+use omnitak_datapackage::StreamProtocol;
 use std::path::PathBuf;
 use std::time::Duration;
 
@@ -30,6 +31,9 @@ pub struct QuickConnectState {
     /// Extracted certificate information
     pub extracted_certs: Option<ExtractedCertificates>,
 
+    /// Server picked from the bundle's preference files
+    pub selected_stream: Option<usize>,
+
     /// Output directory for extracted certs
     pub cert_output_dir: PathBuf,
 
@@ -82,6 +86,7 @@ impl QuickConnectState {
             p12_password: String::new(),
             show_password: false,
             extracted_certs: None,
+            selected_stream: None,
             cert_output_dir,
             server_config: ServerConfig {
                 name: "My TAK Server".to_string(),
@@ -330,6 +335,7 @@ fn render_extract_certs_step(
 
     // Check if we need to extract
     if state.extracted_certs.is_none() {
+        state.selected_stream = None;
         let path = PathBuf::from(&state.cert_file_path);
         let ext = path.extension()
             .and_then(|e| e.to_str())
@@ -360,7 +366,7 @@ fn render_extract_certs_step(
                         client_cert_path: None,
                         client_key_path: None,
                         p12_path: Some(path.clone()),
-                        server_info: None,
+                        preferences: None,
                         all_files: vec![path],
                     });
                 }
@@ -395,7 +401,13 @@ fn render_extract_certs_step(
             .map(|p| p.file_name().unwrap_or_default().to_string_lossy().to_string());
         let key_name = extracted.client_key_path.as_ref()
             .map(|p| p.file_name().unwrap_or_default().to_string_lossy().to_string());
-        let server_info = extracted.server_info.clone();
+        let streams = extracted
+            .preferences
+            .as_ref()
+            .map(|p| p.streams.clone())
+            .unwrap_or_default();
+        let primary = streams.iter().position(|s| s.enabled).unwrap_or(0);
+        let mut selected = state.selected_stream.or((!streams.is_empty()).then_some(primary));
         let has_p12 = extracted.p12_path.is_some();
 
         // Clone paths for TLS config construction
@@ -440,25 +452,47 @@ fn render_extract_certs_step(
                     );
                 }
 
-                if let Some(info) = &server_info {
+                if !streams.is_empty() {
                     ui.add_space(5.0);
-                    ui.label(egui::RichText::new("Server Info Found:").strong());
-                    if let Some(host) = &info.host {
-                        ui.label(format!("  Host: {}", host));
-                    }
-                    if let Some(port) = info.port {
-                        ui.label(format!("  Port: {}", port));
+                    ui.label(egui::RichText::new("Servers Found:").strong());
+                    for (i, stream) in streams.iter().enumerate() {
+                        let mut label = format!(
+                            "{} ({}:{}:{})",
+                            stream.description,
+                            stream.host,
+                            stream.port,
+                            stream.protocol.as_str()
+                        );
+                        if !stream.enabled {
+                            label.push_str(" - disabled");
+                        }
+                        ui.radio_value(&mut selected, Some(i), label)
+                            .on_hover_text(if stream.use_auth {
+                                "Server expects a username and password"
+                            } else {
+                                "Server authenticates with the client certificate"
+                            });
                     }
                 }
             });
 
-        // Update server config from extracted info
-        if let Some(info) = &server_info {
-            if let Some(host) = &info.host {
-                state.server_config.host = host.clone();
-            }
-            if let Some(port) = info.port {
-                state.server_config.port = port;
+        // Fill in the server config from the picked server
+        if selected != state.selected_stream {
+            state.selected_stream = selected;
+            if let Some(stream) = selected.and_then(|i| streams.get(i)) {
+                state.server_config.name = stream.description.clone();
+                state.server_config.host = stream.host.clone();
+                state.server_config.port = stream.port;
+                match stream.protocol {
+                    StreamProtocol::Tcp => state.server_config.protocol = Protocol::Tcp,
+                    StreamProtocol::Udp => state.server_config.protocol = Protocol::Udp,
+                    StreamProtocol::Ssl => state.server_config.protocol = Protocol::Tls,
+                    // No QUIC client; leave the protocol for the user to pick
+                    StreamProtocol::Quic => {}
+                }
+                if state.p12_password.is_empty() {
+                    state.p12_password = stream.client_password().to_string();
+                }
             }
         }
 
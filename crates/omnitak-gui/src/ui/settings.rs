@@ -160,6 +160,51 @@ pub fn show(ui: &mut egui::Ui, app: &mut OmniTakApp) {
 
             ui.add_space(5.0);
             ui.label(egui::RichText::new("⚠ Importing will add to existing servers").color(egui::Color32::YELLOW));
+
+            ui.add_space(15.0);
+            ui.label("Snapshot the running server (connections, filters, settings) as a config.yaml");
+            ui.add_space(5.0);
+
+            ui.horizontal(|ui| {
+                // Handle ongoing server export
+                if let Some(promise) = &app.ui_state.server_export_promise {
+                    if let Some(result) = promise.ready() {
+                        match result {
+                            Ok(Some((path, 0))) => app.show_status(
+                                format!("Server configuration exported to {}", path.display()),
+                                crate::StatusLevel::Success,
+                                5,
+                            ),
+                            Ok(Some((path, warnings))) => app.show_status(
+                                format!(
+                                    "Server configuration exported to {}; {} item(s) could not be exported, see the comments at the top of the file",
+                                    path.display(),
+                                    warnings
+                                ),
+                                crate::StatusLevel::Warning,
+                                10,
+                            ),
+                            Ok(None) => {}
+                            Err(e) => app.show_status(
+                                format!("Server config export failed: {}", e),
+                                crate::StatusLevel::Error,
+                                10,
+                            ),
+                        }
+                        app.ui_state.server_export_promise = None;
+                    }
+                }
+
+                let enabled = app.api_client.is_some() && app.ui_state.server_export_promise.is_none();
+                if ui
+                    .add_enabled(enabled, egui::Button::new("🗄 Export Server Config"))
+                    .clicked()
+                {
+                    if let Some(client) = app.api_client.clone() {
+                        app.ui_state.server_export_promise = Some(spawn_server_export(client));
+                    }
+                }
+            });
         });
 
     ui.add_space(20.0);
@@ -427,3 +472,26 @@ pub fn show(ui: &mut egui::Ui, app: &mut OmniTakApp) {
 
     ui.add_space(20.0);
 }
+
+/// Ask where to save, then download the running server configuration
+fn spawn_server_export(
+    client: crate::ApiClient,
+) -> poll_promise::Promise<Result<Option<(std::path::PathBuf, usize)>, String>> {
+    poll_promise::Promise::spawn_thread("server_config_export", move || {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("YAML", &["yaml", "yml"])
+            .set_file_name("config.yaml")
+            .set_title("Export Server Configuration")
+            .save_file()
+        else {
+            return Ok(None);
+        };
+
+        let rt = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+        let (yaml, warnings) = rt
+            .block_on(client.export_server_config())
+            .map_err(|e| e.to_string())?;
+        std::fs::write(&path, yaml).map_err(|e| e.to_string())?;
+        Ok(Some((path, warnings)))
+    })
+}
@@ -0,0 +1,373 @@
+//! End-to-end tests for the GUI
+//!
+//! The app runs headless against a mock REST API. Widgets are found by the
+//! text egui paints for them each frame and clicked with synthetic pointer
+//! events.
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use eframe::App;
+use omnitak_core::types::{Protocol, ServerConfig};
+use omnitak_gui::{
+    AffiliationFilter, ApiClient, CameraPosition, FollowMode, MessageLog, OmniTakApp, Tab,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+const PASSWORD: &str = "omnitak123";
+
+/// Connections the mock server has been asked to create
+type Connections = Arc<Mutex<Vec<Value>>>;
+
+/// Serves the subset of the REST API the GUI polls, on a thread of its own
+fn spawn_mock_api() -> (String, Connections) {
+    let connections = Connections::default();
+
+    let app = Router::new()
+        .route("/api/v1/auth/login", post(login))
+        .route("/api/v1/status", get(status))
+        .route(
+            "/api/v1/connections",
+            get(list_connections).post(create_connection),
+        )
+        .route("/api/v1/connections/{id}", delete(delete_connection))
+        .route("/api/v1/connections/{id}/stats", get(connection_stats))
+        .route("/api/v1/emergencies", get(emergencies))
+        .with_state(connections.clone());
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async move {
+            let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+            axum::serve(listener, app).await.unwrap();
+        });
+    });
+
+    (url, connections)
+}
+
+async fn login(Json(body): Json<Value>) -> Result<Json<Value>, StatusCode> {
+    if body["password"] != PASSWORD {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(Json(json!({
+        "access_token": "token",
+        "expires_at": "2099-01-01T00:00:00Z",
+        "refresh_token": "refresh",
+        "refresh_expires_at": "2099-01-01T00:00:00Z",
+        "role": "admin",
+    })))
+}
+
+async fn status(State(connections): State<Connections>) -> Json<Value> {
+    Json(json!({
+        "uptime_seconds": 1,
+        "active_connections": connections.lock().unwrap().len(),
+        "messages_processed": 0,
+        "messages_per_second": 0.0,
+        "memory_usage_bytes": 0,
+        "active_filters": 0,
+        "version": "test",
+        "timestamp": "2026-01-01T00:00:00Z",
+    }))
+}
+
+async fn list_connections(State(connections): State<Connections>) -> Json<Value> {
+    let connections = connections.lock().unwrap();
+    Json(json!({ "connections": *connections, "total": connections.len() }))
+}
+
+async fn create_connection(
+    State(connections): State<Connections>,
+    Json(request): Json<Value>,
+) -> Json<Value> {
+    let mut connections = connections.lock().unwrap();
+    let id = uuid::Uuid::new_v4().to_string();
+    connections.push(json!({
+        "id": id,
+        "name": request["name"],
+        "connection_type": request["connection_type"],
+        "status": "connected",
+        "address": request["address"],
+        "port": request["port"],
+        "messages_received": 0,
+        "messages_sent": 0,
+        "bytes_received": 0,
+        "bytes_sent": 0,
+    }));
+    Json(json!({ "id": id, "message": "created" }))
+}
+
+async fn delete_connection(
+    State(connections): State<Connections>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    let mut connections = connections.lock().unwrap();
+    let before = connections.len();
+    connections.retain(|c| c["id"] != id);
+    if connections.len() < before {
+        Ok(Json(json!({ "message": "deleted" })))
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+async fn connection_stats(Path(id): Path<String>) -> Json<Value> {
+    Json(json!({ "id": id, "sample_interval_secs": 5, "samples": [] }))
+}
+
+async fn emergencies() -> Json<Value> {
+    Json(json!({ "emergencies": [], "total": 0, "active": 0 }))
+}
+
+/// Drives an [`OmniTakApp`] one frame at a time
+struct Harness {
+    ctx: egui::Context,
+    frame: eframe::Frame,
+    app: OmniTakApp,
+    events: Vec<egui::Event>,
+    /// Text painted in the last frame, with where it was painted
+    texts: Vec<(String, egui::Rect)>,
+}
+
+impl Harness {
+    fn new(app: OmniTakApp) -> Self {
+        let ctx = egui::Context::default();
+        let mut harness = Self {
+            ctx,
+            frame: eframe::Frame::_new_kittest(),
+            app,
+            events: Vec::new(),
+            texts: Vec::new(),
+        };
+        harness.run();
+        harness
+    }
+
+    /// Harness for an app talking to `api_url`
+    fn with_api(api_url: &str) -> Self {
+        let mut app = OmniTakApp::default();
+        app.api_client = Some(ApiClient::new(api_url).unwrap());
+        app.api_url = api_url.to_string();
+        Self::new(app)
+    }
+
+    /// Runs frames until the UI settles
+    fn run(&mut self) {
+        for _ in 0..3 {
+            self.step();
+        }
+    }
+
+    fn step(&mut self) {
+        let input = egui::RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(
+                egui::Pos2::ZERO,
+                egui::vec2(1280.0, 900.0),
+            )),
+            events: std::mem::take(&mut self.events),
+            ..Default::default()
+        };
+        let output = self
+            .ctx
+            .run(input, |ctx| self.app.update(ctx, &mut self.frame));
+        self.texts.clear();
+        for clipped in &output.shapes {
+            collect_texts(&clipped.shape, clipped.clip_rect, &mut self.texts);
+        }
+    }
+
+    fn find(&self, text: &str) -> Option<egui::Rect> {
+        self.texts
+            .iter()
+            .find(|(painted, _)| painted == text)
+            .map(|(_, rect)| *rect)
+    }
+
+    fn has(&self, text: &str) -> bool {
+        self.find(text).is_some()
+    }
+
+    /// Clicks the widget labelled `text` and lets the app react
+    fn click(&mut self, text: &str) {
+        let pos = self
+            .find(text)
+            .unwrap_or_else(|| panic!("no widget labelled {:?}", text))
+            .center();
+        self.events.push(egui::Event::PointerMoved(pos));
+        self.step();
+        for pressed in [true, false] {
+            self.events.push(egui::Event::PointerButton {
+                pos,
+                button: egui::PointerButton::Primary,
+                pressed,
+                modifiers: Default::default(),
+            });
+        }
+        self.run();
+    }
+}
+
+/// Visible text of a shape, in screen coordinates
+fn collect_texts(shape: &egui::Shape, clip: egui::Rect, texts: &mut Vec<(String, egui::Rect)>) {
+    match shape {
+        egui::Shape::Vec(shapes) => {
+            for shape in shapes {
+                collect_texts(shape, clip, texts);
+            }
+        }
+        egui::Shape::Text(text) => {
+            let rect = text.visual_bounding_rect();
+            if clip.intersects(rect) {
+                texts.push((text.galley.text().to_string(), rect));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn message(callsign: &str, affiliation: &str) -> MessageLog {
+    MessageLog {
+        timestamp: chrono::Utc::now(),
+        server: "ops".to_string(),
+        content: format!("<event uid=\"{}\"/>", callsign),
+        msg_type: "a-f-G-U-C".to_string(),
+        uid: Some(callsign.to_string()),
+        affiliation: Some(affiliation.to_string()),
+        callsign: Some(callsign.to_string()),
+        lat: Some(38.0),
+        lon: Some(-77.0),
+        altitude: None,
+        raw_content: None,
+    }
+}
+
+#[test]
+fn test_login_flow() {
+    let (url, _) = spawn_mock_api();
+    let mut harness = Harness::with_api(&url);
+    assert!(harness.has("Login"));
+
+    harness.app.login_password = "wrong".to_string();
+    harness.click("Login");
+    assert!(!harness.app.is_authenticated);
+    assert!(harness.app.login_error.as_deref().unwrap().contains("401"));
+
+    harness.app.login_password = PASSWORD.to_string();
+    harness.click("Login");
+    assert!(harness.app.is_authenticated);
+    assert!(harness.app.login_password.is_empty());
+    assert!(harness.has("📊 Dashboard"));
+    assert!(!harness.has("Login"));
+}
+
+#[test]
+fn test_connection_crud() {
+    let (url, connections) = spawn_mock_api();
+    let mut harness = Harness::with_api(&url);
+    harness.click("Login");
+
+    harness.app.add_server(
+        ServerConfig::builder()
+            .name("Alpha")
+            .host("tak.example.com")
+            .port(8087)
+            .protocol(Protocol::Tcp)
+            .build(),
+    );
+    harness.click("🔌 Connections");
+    assert!(harness.has("Alpha"));
+
+    harness.click("▶ Connect");
+    {
+        let connections = connections.lock().unwrap();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0]["address"], "tak.example.com");
+        assert_eq!(connections[0]["port"], 8087);
+    }
+    assert!(harness.has("⏸ Disconnect"));
+
+    harness.click("⏸ Disconnect");
+    assert!(connections.lock().unwrap().is_empty());
+    assert!(harness.has("▶ Connect"));
+
+    harness.click("🗑 Delete");
+    assert!(!harness.has("Alpha"));
+    assert!(harness.app.state.lock().unwrap().servers.is_empty());
+}
+
+#[test]
+fn test_message_filtering() {
+    let (url, _) = spawn_mock_api();
+    let mut harness = Harness::with_api(&url);
+    harness.click("Login");
+
+    for (callsign, affiliation) in [("VIPER", "friend"), ("BANDIT", "hostile")] {
+        harness.app.add_message_log(message(callsign, affiliation));
+    }
+    harness.app.ui_state.selected_tab = Tab::Messages;
+    harness.run();
+    assert!(harness.has("VIPER") && harness.has("BANDIT"));
+
+    harness.app.ui_state.message_filter = "vip".to_string();
+    harness.run();
+    assert!(harness.has("VIPER"));
+    assert!(!harness.has("BANDIT"));
+
+    harness.app.ui_state.message_filter.clear();
+    harness.app.ui_state.affiliation_filter = AffiliationFilter::Hostile;
+    harness.run();
+    assert!(!harness.has("VIPER"));
+    assert!(harness.has("BANDIT"));
+}
+
+/// [`eframe::Storage`] kept in memory
+#[derive(Default)]
+struct MemoryStorage(HashMap<String, String>);
+
+impl eframe::Storage for MemoryStorage {
+    fn get_string(&self, key: &str) -> Option<String> {
+        self.0.get(key).cloned()
+    }
+
+    fn set_string(&mut self, key: &str, value: String) {
+        self.0.insert(key.to_string(), value);
+    }
+
+    fn flush(&mut self) {}
+}
+
+#[test]
+fn test_map_state_persistence() {
+    let camera = CameraPosition {
+        lat: 38.8895,
+        lon: -77.0353,
+        zoom: 12.0,
+    };
+
+    let mut app = OmniTakApp::default();
+    app.ui_state.map_panel.camera = Some(camera);
+    app.ui_state.map_panel.follow_mode = FollowMode::Group("Cyan".to_string());
+    let mut storage = MemoryStorage::default();
+    app.save(&mut storage);
+
+    let ctx = egui::Context::default();
+    let mut cc = eframe::CreationContext::_new_kittest(ctx);
+    cc.storage = Some(&storage);
+    let restored = OmniTakApp::new(&cc, None);
+    assert_eq!(restored.ui_state.map_panel.camera, Some(camera));
+    assert_eq!(
+        restored.ui_state.map_panel.follow_mode,
+        FollowMode::Group("Cyan".to_string())
+    );
+}
@@ -0,0 +1,105 @@
+//! Messages injected by plugins through the `emit-cot` host function
+//!
+//! Emitted messages are handed to a [`CotSink`] set by the embedding
+//! application, normally the message aggregator, so they are deduplicated
+//! and filtered like any other traffic. Each plugin may emit at most
+//! [`ResourceLimits::max_emits_per_second`](crate::ResourceLimits)
+//! messages per second.
+
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Destination for messages emitted by plugins
+pub trait CotSink: Send + Sync {
+    /// Queue a message from `plugin_id`; the error is returned to the plugin
+    fn submit(&self, plugin_id: &str, xml: String) -> Result<(), String>;
+}
+
+/// Validates, rate limits and forwards emitted messages
+pub struct CotEmitter {
+    sink: RwLock<Option<Arc<dyn CotSink>>>,
+    max_per_second: u32,
+    /// Start of each plugin's current one-second window and messages in it
+    windows: DashMap<String, (Instant, u32)>,
+}
+
+impl CotEmitter {
+    pub fn new(max_per_second: u32) -> Self {
+        Self {
+            sink: RwLock::new(None),
+            max_per_second,
+            windows: DashMap::new(),
+        }
+    }
+
+    /// Set where emitted messages go; until then emitting fails
+    pub fn set_sink(&self, sink: Arc<dyn CotSink>) {
+        *self.sink.write() = Some(sink);
+    }
+
+    /// Forward a message emitted by `plugin_id`
+    pub fn emit(&self, plugin_id: &str, xml: String) -> Result<(), String> {
+        omnitak_cot::parse_cot(&xml).map_err(|e| format!("invalid CoT: {}", e))?;
+
+        {
+            let now = Instant::now();
+            let mut window = self
+                .windows
+                .entry(plugin_id.to_string())
+                .or_insert((now, 0));
+            if now.duration_since(window.0) >= Duration::from_secs(1) {
+                *window = (now, 0);
+            }
+            if window.1 >= self.max_per_second {
+                return Err(format!(
+                    "rate limit of {} messages per second exceeded",
+                    self.max_per_second
+                ));
+            }
+            window.1 += 1;
+        }
+
+        let sink = self.sink.read().clone();
+        match sink {
+            Some(sink) => sink.submit(plugin_id, xml),
+            None => Err("no message sink configured".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+
+    #[derive(Default)]
+    struct Collect(Mutex<Vec<(String, String)>>);
+
+    impl CotSink for Collect {
+        fn submit(&self, plugin_id: &str, xml: String) -> Result<(), String> {
+            self.0.lock().push((plugin_id.to_string(), xml));
+            Ok(())
+        }
+    }
+
+    const EVENT: &str = r#"<event version="2.0" uid="alert-1" type="b-a-o-tbl" time="2024-01-01T00:00:00Z" start="2024-01-01T00:00:00Z" stale="2024-01-01T00:05:00Z" how="m-g"><point lat="38.0" lon="-77.0" hae="0" ce="10" le="10"/></event>"#;
+
+    #[test]
+    fn test_emit_validates_and_rate_limits() {
+        let emitter = CotEmitter::new(2);
+        assert!(emitter.emit("geofence", EVENT.to_string()).is_err());
+
+        let sink = Arc::new(Collect::default());
+        emitter.set_sink(sink.clone());
+        assert!(emitter.emit("geofence", "<not-cot/>".to_string()).is_err());
+        assert!(emitter.emit("geofence", EVENT.to_string()).is_ok());
+        let err = emitter.emit("geofence", EVENT.to_string()).unwrap_err();
+        assert!(err.contains("rate limit"));
+
+        // Limits are per plugin
+        assert!(emitter.emit("enricher", EVENT.to_string()).is_ok());
+        assert_eq!(sink.0.lock().len(), 2);
+    }
+}
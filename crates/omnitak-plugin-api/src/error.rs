@@ -15,6 +15,15 @@ pub enum PluginError {
     #[error("Plugin memory limit exceeded: {current} > {limit} bytes")]
     MemoryLimitExceeded { current: u64, limit: u64 },
 
+    #[error("Plugin storage quota exceeded: {current} > {limit} bytes")]
+    StorageQuotaExceeded { current: u64, limit: u64 },
+
+    #[error("Plugin storage error: {0}")]
+    StorageError(#[from] sled::Error),
+
+    #[error("Plugin directory watch error: {0}")]
+    WatchError(#[from] notify::Error),
+
     #[error("Plugin execution error: {0}")]
     ExecutionError(String),
 
@@ -27,6 +36,9 @@ pub enum PluginError {
     #[error("Invalid plugin metadata: {0}")]
     InvalidMetadata(String),
 
+    #[error("Invalid plugin configuration: {0}")]
+    InvalidConfig(String),
+
     #[error("Security violation: {0}")]
     SecurityViolation(String),
 
@@ -0,0 +1,215 @@
+//! Outbound HTTP for plugins through the `http-fetch` host function
+//!
+//! A plugin may only reach the domains listed for it in
+//! [`SandboxPolicy::http_domains`](crate::SandboxPolicy). Requests time out
+//! after [`ResourceLimits::http_timeout_ms`](crate::ResourceLimits), or when
+//! a plugin on the message path runs out of execution budget, and responses
+//! larger than `max_http_response_bytes` are rejected. Redirects are not
+//! followed, so an allowed domain cannot forward a plugin elsewhere.
+
+use std::time::Duration;
+
+use crate::omnitak::plugin::host::{HttpRequest, HttpResponse};
+use crate::security::ResourceLimits;
+
+/// Performs `http-fetch` requests for all plugins of a runtime
+pub struct HttpFetcher {
+    client: reqwest::Client,
+    timeout: Duration,
+    max_response_bytes: u64,
+}
+
+impl HttpFetcher {
+    pub fn new(limits: &ResourceLimits) -> Self {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            timeout: Duration::from_millis(limits.http_timeout_ms),
+            max_response_bytes: limits.max_http_response_bytes,
+        }
+    }
+
+    /// Make `request` if its URL is on `allowed_domains`, giving up after
+    /// `timeout` or the configured timeout, whichever is shorter
+    pub async fn fetch(
+        &self,
+        allowed_domains: &[String],
+        request: HttpRequest,
+        timeout: Duration,
+    ) -> Result<HttpResponse, String> {
+        let timeout = timeout.min(self.timeout);
+        let url = reqwest::Url::parse(&request.url).map_err(|e| format!("invalid URL: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("unsupported scheme: {}", url.scheme()));
+        }
+        let host = url.host_str().unwrap_or_default();
+        if !domain_allowed(allowed_domains, host) {
+            return Err(format!("domain not allowed: {}", host));
+        }
+        let method = reqwest::Method::from_bytes(request.method.to_uppercase().as_bytes())
+            .map_err(|_| format!("invalid method: {}", request.method))?;
+
+        let mut builder = self.client.request(method, url).timeout(timeout);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+
+        let fetch = async {
+            let mut response = builder.send().await.map_err(|e| e.to_string())?;
+            if response
+                .content_length()
+                .is_some_and(|len| len > self.max_response_bytes)
+            {
+                return Err(self.too_large());
+            }
+
+            let status = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect();
+            let mut body = Vec::new();
+            while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+                if (body.len() + chunk.len()) as u64 > self.max_response_bytes {
+                    return Err(self.too_large());
+                }
+                body.extend_from_slice(&chunk);
+            }
+
+            Ok(HttpResponse {
+                status,
+                headers,
+                body,
+            })
+        };
+        // The request timeout does not cover reading the body
+        tokio::time::timeout(timeout, fetch)
+            .await
+            .map_err(|_| format!("timed out after {:?}", timeout))?
+    }
+
+    fn too_large(&self) -> String {
+        format!("response larger than {} bytes", self.max_response_bytes)
+    }
+}
+
+/// Whether `host` matches an allow-list entry: either the exact domain or,
+/// for entries like `*.example.com`, any subdomain of it
+pub fn domain_allowed(allowed_domains: &[String], host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    allowed_domains.iter().any(|entry| {
+        let entry = entry.to_ascii_lowercase();
+        match entry.strip_prefix("*.") {
+            Some(parent) => host
+                .strip_suffix(parent)
+                .is_some_and(|sub| sub.ends_with('.')),
+            None => host == entry,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn request(url: &str) -> HttpRequest {
+        HttpRequest {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    #[test]
+    fn test_domain_allow_list() {
+        let allowed = vec!["api.weather.gov".to_string(), "*.units.mil".to_string()];
+        assert!(domain_allowed(&allowed, "api.weather.gov"));
+        assert!(domain_allowed(&allowed, "API.Weather.gov."));
+        assert!(domain_allowed(&allowed, "registry.units.mil"));
+        assert!(!domain_allowed(&allowed, "units.mil"));
+        assert!(!domain_allowed(&allowed, "evilunits.mil"));
+        assert!(!domain_allowed(&allowed, "weather.gov"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_limits() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let n = socket.read(&mut buf).await.unwrap();
+                if buf[..n].starts_with(b"GET /slow") {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+                let body = if buf[..n].starts_with(b"GET /big") {
+                    "x".repeat(64)
+                } else {
+                    "ok".to_string()
+                };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let fetcher = HttpFetcher::new(&ResourceLimits {
+            max_http_response_bytes: 16,
+            ..Default::default()
+        });
+        let allowed = vec!["127.0.0.1".to_string()];
+        let timeout = Duration::from_secs(2);
+
+        let response = fetcher
+            .fetch(
+                &allowed,
+                request(&format!("http://{}/small", addr)),
+                timeout,
+            )
+            .await
+            .unwrap();
+        assert_eq!((response.status, response.body), (200, b"ok".to_vec()));
+
+        let err = fetcher
+            .fetch(&allowed, request(&format!("http://{}/big", addr)), timeout)
+            .await
+            .unwrap_err();
+        assert!(err.contains("larger than 16 bytes"));
+
+        let err = fetcher
+            .fetch(&[], request(&format!("http://{}/small", addr)), timeout)
+            .await
+            .unwrap_err();
+        assert!(err.contains("domain not allowed"));
+        assert!(fetcher
+            .fetch(&allowed, request("file:///etc/passwd"), timeout)
+            .await
+            .is_err());
+
+        // The caller's deadline applies when it is shorter
+        let start = std::time::Instant::now();
+        assert!(fetcher
+            .fetch(
+                &allowed,
+                request(&format!("http://{}/slow", addr)),
+                Duration::from_millis(50)
+            )
+            .await
+            .is_err());
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -1,13 +1,21 @@
 // OmniTAK Plugin API
 // WASM-based plugin system for extensible TAK message processing
 
+pub mod emit;
 pub mod error;
+pub mod http;
 pub mod manager;
 pub mod metadata;
 pub mod registry;
 pub mod runtime;
+pub mod schedule;
 pub mod security;
+pub mod settings;
+pub mod stats;
+pub mod storage;
+pub mod watcher;
 pub mod wasm_filter;
+pub mod wasm_scheduled;
 pub mod wasm_transformer;
 
 // Generate bindings from WIT file
@@ -17,13 +25,49 @@ wasmtime::component::bindgen!({
     async: true,
 });
 
+/// Bindings for the transformer-only world, sharing the host interface
+/// generated above
+pub mod transformer_bindings {
+    wasmtime::component::bindgen!({
+        path: "wit/plugin.wit",
+        world: "transformer-plugin",
+        async: true,
+        with: {
+            "omnitak:plugin/host": crate::omnitak::plugin::host,
+        },
+    });
+}
+
+/// Bindings for the scheduled-task world, sharing the host interface
+/// generated above
+pub mod scheduled_bindings {
+    wasmtime::component::bindgen!({
+        path: "wit/plugin.wit",
+        world: "scheduled-task-plugin",
+        async: true,
+        with: {
+            "omnitak:plugin/host": crate::omnitak::plugin::host,
+        },
+    });
+}
+
+pub use emit::{CotEmitter, CotSink};
 pub use error::{PluginError, PluginResult};
-pub use manager::{PluginManager, PluginManagerConfig};
-pub use metadata::{FilterMetadata, PluginCapability, PluginInfo, PluginMetadata, TransformerMetadata};
+pub use manager::{PluginEvent, PluginManager, PluginManagerConfig};
+pub use metadata::{
+    ConfigField, ConfigFieldType, FilterMetadata, PluginCapability, PluginInfo, PluginMetadata,
+    ScheduledTaskMetadata, TransformerMetadata,
+};
 pub use registry::{PluginCategory, RegistryClient, RegistryManifest, RegistryPlugin};
 pub use runtime::PluginRuntime;
+pub use schedule::Schedule;
 pub use security::{ResourceLimits, SandboxPolicy};
+pub use settings::{PluginSettings, PluginSettingsSnapshot};
+pub use stats::{PluginMetrics, PluginStatsSnapshot};
+pub use storage::PluginStore;
+pub use watcher::{watch_plugin_dir, PluginDirWatcher};
 pub use wasm_filter::WasmFilterPlugin;
+pub use wasm_scheduled::WasmScheduledPlugin;
 pub use wasm_transformer::WasmTransformerPlugin;
 
 // Re-export core types that plugins interact with
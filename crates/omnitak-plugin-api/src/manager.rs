@@ -1,17 +1,28 @@
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
 
+use crate::emit::CotSink;
 use crate::error::{PluginError, PluginResult};
-use crate::metadata::{FilterMetadata, PluginCapability, PluginInfo, TransformerMetadata};
+use crate::metadata::{
+    FilterMetadata, PluginCapability, PluginInfo, PluginMetadata, ScheduledTaskMetadata,
+    TransformerMetadata,
+};
 use crate::runtime::PluginRuntime;
 use crate::security::{ResourceLimits, SandboxPolicy};
+use crate::settings::{PluginSettings, PluginSettingsSnapshot};
+use crate::stats::PluginMetrics;
+use crate::storage::PluginStore;
 use crate::wasm_filter::WasmFilterPlugin;
+use crate::wasm_scheduled::WasmScheduledPlugin;
 use crate::wasm_transformer::WasmTransformerPlugin;
 
 /// Plugin manager configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PluginManagerConfig {
     /// Resource limits for plugins
     pub resource_limits: ResourceLimits,
@@ -19,8 +30,15 @@ pub struct PluginManagerConfig {
     pub sandbox_policy: SandboxPolicy,
     /// Plugin directory path
     pub plugin_dir: String,
-    /// Enable hot-reload
+    /// Load, replace and unload plugins as `.wasm` files (with `.toml`
+    /// metadata alongside) change in `plugin_dir`
     pub hot_reload: bool,
+    /// How long a changed plugin file must be left alone before it is
+    /// reloaded
+    pub hot_reload_debounce_ms: u64,
+    /// Directory for plugin key-value storage and settings; without one,
+    /// stored data only lasts until the manager is dropped
+    pub storage_dir: Option<String>,
 }
 
 impl Default for PluginManagerConfig {
@@ -30,36 +48,125 @@ impl Default for PluginManagerConfig {
             sandbox_policy: SandboxPolicy::strict(),
             plugin_dir: "plugins".to_string(),
             hot_reload: false,
+            hot_reload_debounce_ms: default_hot_reload_debounce_ms(),
+            storage_dir: None,
         }
     }
 }
 
+fn default_hot_reload_debounce_ms() -> u64 {
+    500
+}
+
+/// Change to the set of loaded plugins
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginEvent {
+    /// A plugin was loaded or replaced by a new version
+    Loaded(String),
+    Unloaded(String),
+}
+
+/// A scheduled-task plugin and the task running it; dropping it stops
+/// the schedule
+struct ScheduledTask {
+    plugin: Arc<WasmScheduledPlugin>,
+    handle: JoinHandle<()>,
+}
+
+impl ScheduledTask {
+    fn start(plugin: Arc<WasmScheduledPlugin>) -> Self {
+        let handle = tokio::spawn(Self::run_schedule(plugin.clone()));
+        Self { plugin, handle }
+    }
+
+    async fn run_schedule(plugin: Arc<WasmScheduledPlugin>) {
+        let id = plugin.metadata().id.clone();
+        let mut due = chrono::Utc::now();
+        loop {
+            let Some(next) = plugin.schedule().next_after(due) else {
+                tracing::info!("Scheduled plugin {} has no further runs", id);
+                return;
+            };
+            let wait = (next - chrono::Utc::now()).to_std().unwrap_or_default();
+            tokio::time::sleep(wait).await;
+
+            match plugin.run().await {
+                Ok(()) => tracing::debug!("Scheduled plugin {} ran", id),
+                Err(e) => tracing::warn!("Scheduled plugin {} failed: {}", id, e),
+            }
+            // Runs that fell due while this one was running are skipped
+            due = next.max(chrono::Utc::now());
+        }
+    }
+}
+
+impl Drop for ScheduledTask {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
 /// Central plugin manager
 pub struct PluginManager {
     runtime: Arc<PluginRuntime>,
     config: PluginManagerConfig,
+    settings: Arc<PluginSettings>,
     filter_plugins: DashMap<String, Arc<WasmFilterPlugin>>,
     transformer_plugins: DashMap<String, Arc<WasmTransformerPlugin>>,
+    scheduled_plugins: DashMap<String, ScheduledTask>,
     plugin_registry: DashMap<String, PluginInfo>,
+    /// Binary each plugin was loaded from, for reloading
+    plugin_paths: DashMap<String, PathBuf>,
+    events: broadcast::Sender<PluginEvent>,
 }
 
 impl PluginManager {
     /// Create a new plugin manager
     pub fn new(config: PluginManagerConfig) -> PluginResult<Self> {
-        let runtime = Arc::new(PluginRuntime::with_config(
-            config.resource_limits.clone(),
-            config.sandbox_policy.clone(),
-        )?);
+        let quota = config.resource_limits.max_storage_bytes;
+        let storage = match &config.storage_dir {
+            Some(dir) => PluginStore::open(dir, quota)?,
+            None => PluginStore::temporary(quota)?,
+        };
+        let storage = Arc::new(storage);
+        let settings = Arc::new(PluginSettings::new(storage.clone()));
+        let runtime = Arc::new(
+            PluginRuntime::with_config(
+                config.resource_limits.clone(),
+                config.sandbox_policy.clone(),
+            )?
+            .with_storage(storage)
+            .with_settings(settings.clone()),
+        );
 
         Ok(Self {
             runtime,
             config,
+            settings,
             filter_plugins: DashMap::new(),
             transformer_plugins: DashMap::new(),
+            scheduled_plugins: DashMap::new(),
             plugin_registry: DashMap::new(),
+            plugin_paths: DashMap::new(),
+            events: broadcast::channel(64).0,
         })
     }
 
+    /// Get the manager configuration
+    pub fn config(&self) -> &PluginManagerConfig {
+        &self.config
+    }
+
+    /// Get the execution metrics of the loaded plugins
+    pub fn metrics(&self) -> Arc<PluginMetrics> {
+        self.runtime.metrics().clone()
+    }
+
+    /// Subscribe to plugins being loaded, replaced and unloaded
+    pub fn subscribe(&self) -> broadcast::Receiver<PluginEvent> {
+        self.events.subscribe()
+    }
+
     /// Load a filter plugin from file
     pub fn load_filter_plugin(
         &self,
@@ -88,7 +195,14 @@ impl PluginManager {
         )?;
         let plugin = Arc::new(plugin);
 
-        // Register plugin
+        self.register_filter(Path::new(path), hash, plugin.clone());
+
+        tracing::info!("Successfully loaded filter plugin: {}", metadata.name);
+        Ok(plugin)
+    }
+
+    fn register_filter(&self, path: &Path, hash: String, plugin: Arc<WasmFilterPlugin>) {
+        let metadata = plugin.metadata();
         let plugin_id = metadata.id.clone();
         let plugin_info = PluginInfo {
             id: plugin_id.clone(),
@@ -100,11 +214,13 @@ impl PluginManager {
             binary_hash: hash,
         };
 
+        self.settings.register(&plugin_id, metadata.config_schema.clone());
         self.plugin_registry.insert(plugin_id.clone(), plugin_info);
-        self.filter_plugins.insert(plugin_id, plugin.clone());
-
-        tracing::info!("Successfully loaded filter plugin: {}", metadata.name);
-        Ok(plugin)
+        self.transformer_plugins.remove(&plugin_id);
+        self.scheduled_plugins.remove(&plugin_id);
+        self.filter_plugins.insert(plugin_id.clone(), plugin);
+        self.plugin_paths.insert(plugin_id.clone(), path.to_path_buf());
+        let _ = self.events.send(PluginEvent::Loaded(plugin_id));
     }
 
     /// Load a transformer plugin from file
@@ -125,6 +241,14 @@ impl PluginManager {
         )?;
         let plugin = Arc::new(plugin);
 
+        self.register_transformer(Path::new(path), hash, plugin.clone());
+
+        tracing::info!("Successfully loaded transformer plugin: {}", metadata.name);
+        Ok(plugin)
+    }
+
+    fn register_transformer(&self, path: &Path, hash: String, plugin: Arc<WasmTransformerPlugin>) {
+        let metadata = plugin.metadata();
         let plugin_id = metadata.id.clone();
         let plugin_info = PluginInfo {
             id: plugin_id.clone(),
@@ -136,21 +260,187 @@ impl PluginManager {
             binary_hash: hash,
         };
 
+        self.settings.register(&plugin_id, metadata.config_schema.clone());
         self.plugin_registry.insert(plugin_id.clone(), plugin_info);
-        self.transformer_plugins.insert(plugin_id, plugin.clone());
+        self.filter_plugins.remove(&plugin_id);
+        self.scheduled_plugins.remove(&plugin_id);
+        self.transformer_plugins.insert(plugin_id.clone(), plugin);
+        self.plugin_paths.insert(plugin_id.clone(), path.to_path_buf());
+        let _ = self.events.send(PluginEvent::Loaded(plugin_id));
+    }
 
-        tracing::info!("Successfully loaded transformer plugin: {}", metadata.name);
+    /// Load a scheduled-task plugin from file and start running it on its
+    /// schedule
+    pub fn load_scheduled_plugin(
+        &self,
+        path: &str,
+        metadata: ScheduledTaskMetadata,
+    ) -> PluginResult<Arc<WasmScheduledPlugin>> {
+        tracing::info!("Loading scheduled plugin: {} from {}", metadata.name, path);
+
+        let plugin_bytes = std::fs::read(path)?;
+        let hash = Self::calculate_hash(&plugin_bytes);
+
+        let plugin = WasmScheduledPlugin::from_bytes(
+            self.runtime.clone(),
+            &plugin_bytes,
+            metadata.clone(),
+        )?;
+        let plugin = Arc::new(plugin);
+
+        self.register_scheduled(Path::new(path), hash, plugin.clone());
+
+        tracing::info!("Successfully loaded scheduled plugin: {}", metadata.name);
         Ok(plugin)
     }
 
+    fn register_scheduled(&self, path: &Path, hash: String, plugin: Arc<WasmScheduledPlugin>) {
+        let metadata = plugin.metadata();
+        let plugin_id = metadata.id.clone();
+        let plugin_info = PluginInfo {
+            id: plugin_id.clone(),
+            name: metadata.name.clone(),
+            version: metadata.version.clone(),
+            author: metadata.author.clone(),
+            description: metadata.description.clone(),
+            capabilities: vec![PluginCapability::Schedule],
+            binary_hash: hash,
+        };
+        tracing::info!("Scheduled plugin {} runs on '{}'", plugin_id, plugin.schedule());
+
+        self.settings.register(&plugin_id, metadata.config_schema.clone());
+        self.plugin_registry.insert(plugin_id.clone(), plugin_info);
+        self.filter_plugins.remove(&plugin_id);
+        self.transformer_plugins.remove(&plugin_id);
+        // Replacing the entry stops the previous version's schedule
+        self.scheduled_plugins
+            .insert(plugin_id.clone(), ScheduledTask::start(plugin));
+        self.plugin_paths.insert(plugin_id.clone(), path.to_path_buf());
+        let _ = self.events.send(PluginEvent::Loaded(plugin_id));
+    }
+
+    /// Load the plugin at `wasm_path`, described by the `.toml` metadata
+    /// file next to it, and return its ID.
+    ///
+    /// The new plugin is instantiated once before it replaces a loaded
+    /// plugin with the same ID, so a build that fails to instantiate leaves
+    /// the running version in place.
+    pub async fn load_plugin_file(&self, wasm_path: &Path) -> PluginResult<String> {
+        let metadata_path = wasm_path.with_extension("toml");
+        let metadata: PluginMetadata = toml::from_str(&std::fs::read_to_string(&metadata_path)?)
+            .map_err(|e| {
+                PluginError::InvalidMetadata(format!("{}: {}", metadata_path.display(), e))
+            })?;
+        self.load_checked(wasm_path, metadata).await
+    }
+
+    async fn load_checked(&self, wasm_path: &Path, metadata: PluginMetadata) -> PluginResult<String> {
+        let plugin_bytes = std::fs::read(wasm_path)?;
+        let hash = Self::calculate_hash(&plugin_bytes);
+        let id = metadata.id().to_string();
+
+        match metadata {
+            PluginMetadata::Filter(metadata) => {
+                let plugin =
+                    WasmFilterPlugin::from_bytes(self.runtime.clone(), &plugin_bytes, metadata)?;
+                plugin.check().await?;
+                self.register_filter(wasm_path, hash, Arc::new(plugin));
+            }
+            PluginMetadata::Transformer(metadata) => {
+                let plugin = WasmTransformerPlugin::from_bytes(
+                    self.runtime.clone(),
+                    &plugin_bytes,
+                    metadata,
+                )?;
+                plugin.check().await?;
+                self.register_transformer(wasm_path, hash, Arc::new(plugin));
+            }
+            PluginMetadata::ScheduledTask(metadata) => {
+                let plugin = WasmScheduledPlugin::from_bytes(
+                    self.runtime.clone(),
+                    &plugin_bytes,
+                    metadata,
+                )?;
+                plugin.check().await?;
+                self.register_scheduled(wasm_path, hash, Arc::new(plugin));
+            }
+        }
+
+        tracing::info!("Loaded plugin {} from {}", id, wasm_path.display());
+        Ok(id)
+    }
+
+    /// Reload a plugin from the file it was loaded from, keeping the
+    /// current version if the new one fails to load
+    pub async fn reload_plugin(&self, id: &str) -> PluginResult<()> {
+        let path = self
+            .plugin_paths
+            .get(id)
+            .map(|p| p.value().clone())
+            .ok_or_else(|| PluginError::NotFound(id.to_string()))?;
+        if !path.with_extension("toml").exists() {
+            // Loaded through the API with inline metadata; keep using it
+            let metadata = if let Some(plugin) = self.get_filter_plugin(id) {
+                PluginMetadata::Filter(plugin.metadata().clone())
+            } else if let Some(plugin) = self.get_transformer_plugin(id) {
+                PluginMetadata::Transformer(plugin.metadata().clone())
+            } else if let Some(plugin) = self.get_scheduled_plugin(id) {
+                PluginMetadata::ScheduledTask(plugin.metadata().clone())
+            } else {
+                return Err(PluginError::NotFound(id.to_string()));
+            };
+            self.load_checked(&path, metadata).await?;
+            return Ok(());
+        }
+        let loaded = self.load_plugin_file(&path).await?;
+        if loaded != id {
+            // The metadata now names a different plugin
+            self.unload_plugin(id)?;
+        }
+        Ok(())
+    }
+
+    /// Bring the loaded plugins in line with `wasm_path` after it changed
+    /// on disk: load or replace its plugin, or unload it if the file is gone
+    pub async fn sync_plugin_file(&self, wasm_path: &Path) -> PluginResult<()> {
+        let previous: Vec<String> = self
+            .plugin_paths
+            .iter()
+            .filter(|entry| entry.value() == wasm_path)
+            .map(|entry| entry.key().clone())
+            .collect();
+
+        let loaded = if wasm_path.exists() {
+            Some(self.load_plugin_file(wasm_path).await?)
+        } else {
+            None
+        };
+        for id in previous {
+            if Some(&id) != loaded.as_ref() {
+                self.unload_plugin(&id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Send messages plugins emit with `emit-cot` to `sink`
+    pub fn set_cot_sink(&self, sink: Arc<dyn CotSink>) {
+        self.runtime.emitter().set_sink(sink);
+    }
+
     /// Get a filter plugin by ID
     pub fn get_filter_plugin(&self, id: &str) -> Option<Arc<WasmFilterPlugin>> {
-        self.filter_plugins.get(id).map(|p| p.clone())
+        self.filter_plugins.get(id).map(|p| p.value().clone())
     }
 
     /// Get a transformer plugin by ID
     pub fn get_transformer_plugin(&self, id: &str) -> Option<Arc<WasmTransformerPlugin>> {
-        self.transformer_plugins.get(id).map(|p| p.clone())
+        self.transformer_plugins.get(id).map(|p| p.value().clone())
+    }
+
+    /// Get a scheduled-task plugin by ID
+    pub fn get_scheduled_plugin(&self, id: &str) -> Option<Arc<WasmScheduledPlugin>> {
+        self.scheduled_plugins.get(id).map(|t| t.plugin.clone())
     }
 
     /// List all loaded plugins
@@ -161,14 +451,36 @@ impl PluginManager {
             .collect()
     }
 
+    /// Get a plugin's config schema and current settings
+    pub fn plugin_settings(&self, id: &str) -> PluginResult<PluginSettingsSnapshot> {
+        self.settings.snapshot(id)
+    }
+
+    /// Change a plugin's settings; see [`PluginSettings::update`]. Plugins
+    /// see the new values from their next call
+    pub fn update_plugin_settings(
+        &self,
+        id: &str,
+        values: &serde_json::Map<String, serde_json::Value>,
+    ) -> PluginResult<()> {
+        self.settings.update(id, values)?;
+        tracing::info!("Updated settings of plugin {}", id);
+        Ok(())
+    }
+
     /// Unload a plugin
     pub fn unload_plugin(&self, id: &str) -> PluginResult<()> {
         self.filter_plugins.remove(id);
         self.transformer_plugins.remove(id);
+        self.scheduled_plugins.remove(id);
+        self.plugin_paths.remove(id);
+        self.runtime.metrics().remove(id);
+        self.settings.unregister(id);
         self.plugin_registry
             .remove(id)
             .ok_or_else(|| PluginError::NotFound(id.to_string()))?;
 
+        let _ = self.events.send(PluginEvent::Unloaded(id.to_string()));
         tracing::info!("Unloaded plugin: {}", id);
         Ok(())
     }
@@ -188,9 +500,10 @@ impl PluginManager {
 
             if path.extension().and_then(|s| s.to_str()) == Some("wasm") {
                 tracing::info!("Found plugin: {:?}", path);
-                // TODO: Load plugin metadata from companion file (.toml)
-                // For now, skip automatic loading
-                count += 1;
+                match self.load_plugin_file(&path).await {
+                    Ok(_) => count += 1,
+                    Err(e) => tracing::warn!("Failed to load plugin {:?}: {}", path, e),
+                }
             }
         }
 
@@ -223,4 +536,70 @@ mod tests {
         let hash = PluginManager::calculate_hash(data);
         assert_eq!(hash.len(), 64); // SHA-256 hex string length
     }
+
+    #[tokio::test]
+    async fn test_failed_load_leaves_plugins_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = PluginManager::new(PluginManagerConfig::default()).unwrap();
+        let mut events = manager.subscribe();
+
+        let wasm = dir.path().join("enricher.wasm");
+        std::fs::write(&wasm, b"not a component").unwrap();
+        // No metadata file
+        assert!(manager.load_plugin_file(&wasm).await.is_err());
+
+        std::fs::write(
+            wasm.with_extension("toml"),
+            r#"
+type = "Transformer"
+id = "enricher"
+name = "Enricher"
+version = "0.1.0"
+author = "ops"
+description = "Adds remarks"
+supported_types = ["a-f-*"]
+"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            manager.load_plugin_file(&wasm).await,
+            Err(PluginError::LoadError(_))
+        ));
+
+        assert!(manager.list_plugins().is_empty());
+        assert!(events.try_recv().is_err());
+        assert!(matches!(
+            manager.reload_plugin("enricher").await,
+            Err(PluginError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_plugin_with_invalid_schedule_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = PluginManager::new(PluginManagerConfig::default()).unwrap();
+
+        let wasm = dir.path().join("heartbeat.wasm");
+        std::fs::write(&wasm, b"not a component").unwrap();
+        std::fs::write(
+            wasm.with_extension("toml"),
+            r#"
+type = "ScheduledTask"
+id = "heartbeat"
+name = "Heartbeat"
+version = "0.1.0"
+author = "ops"
+description = "Publishes a synthetic heartbeat"
+schedule = "every tuesday"
+max_execution_time_us = 50000
+"#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            manager.load_plugin_file(&wasm).await,
+            Err(PluginError::InvalidMetadata(_))
+        ));
+        assert!(manager.get_scheduled_plugin("heartbeat").is_none());
+    }
 }
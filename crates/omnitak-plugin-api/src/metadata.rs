@@ -11,12 +11,59 @@ pub enum PluginCapability {
     Filter,
     /// Can transform CoT messages
     Transform,
+    /// Runs periodically on a schedule
+    Schedule,
     /// Requires network access
     NetworkAccess,
     /// Requires filesystem access
     FilesystemAccess,
 }
 
+/// Type of a plugin setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigFieldType {
+    String,
+    Integer,
+    Float,
+    Boolean,
+}
+
+/// One setting a plugin declares in its config schema
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ConfigField {
+    pub key: String,
+    #[serde(rename = "type")]
+    pub field_type: ConfigFieldType,
+    /// Value used until an operator sets one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<serde_json::Value>,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl ConfigField {
+    /// Check that `value` has the field's type
+    pub fn check(&self, value: &serde_json::Value) -> Result<(), String> {
+        let valid = match self.field_type {
+            ConfigFieldType::String => value.is_string(),
+            ConfigFieldType::Integer => value.is_i64() || value.is_u64(),
+            ConfigFieldType::Float => value.is_number(),
+            ConfigFieldType::Boolean => value.is_boolean(),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!(
+                "setting '{}' must be of type {:?}, got {}",
+                self.key, self.field_type, value
+            ))
+        }
+    }
+}
+
 /// Complete plugin information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
@@ -42,6 +89,10 @@ pub struct FilterMetadata {
     pub description: String,
     /// Maximum expected execution time in microseconds
     pub max_execution_time_us: u64,
+    /// Settings operators can change, read with the `get-config` host
+    /// function
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub config_schema: Vec<ConfigField>,
 }
 
 /// Plugin metadata (transformer-specific)
@@ -55,6 +106,29 @@ pub struct TransformerMetadata {
     pub description: String,
     /// Supported CoT types (glob patterns)
     pub supported_types: Vec<String>,
+    /// Settings operators can change, read with the `get-config` host
+    /// function
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub config_schema: Vec<ConfigField>,
+}
+
+/// Plugin metadata (scheduled-task-specific)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ScheduledTaskMetadata {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub author: String,
+    pub description: String,
+    /// When to run: a cron expression or `@every <interval>`
+    pub schedule: String,
+    /// Maximum expected execution time per run in microseconds
+    pub max_execution_time_us: u64,
+    /// Settings operators can change, read with the `get-config` host
+    /// function
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub config_schema: Vec<ConfigField>,
 }
 
 /// Generic plugin metadata
@@ -63,6 +137,7 @@ pub struct TransformerMetadata {
 pub enum PluginMetadata {
     Filter(FilterMetadata),
     Transformer(TransformerMetadata),
+    ScheduledTask(ScheduledTaskMetadata),
 }
 
 impl PluginMetadata {
@@ -70,6 +145,7 @@ impl PluginMetadata {
         match self {
             PluginMetadata::Filter(m) => &m.id,
             PluginMetadata::Transformer(m) => &m.id,
+            PluginMetadata::ScheduledTask(m) => &m.id,
         }
     }
 
@@ -77,6 +153,7 @@ impl PluginMetadata {
         match self {
             PluginMetadata::Filter(m) => &m.name,
             PluginMetadata::Transformer(m) => &m.name,
+            PluginMetadata::ScheduledTask(m) => &m.name,
         }
     }
 
@@ -84,6 +161,15 @@ impl PluginMetadata {
         match self {
             PluginMetadata::Filter(m) => &m.version,
             PluginMetadata::Transformer(m) => &m.version,
+            PluginMetadata::ScheduledTask(m) => &m.version,
+        }
+    }
+
+    pub fn config_schema(&self) -> &[ConfigField] {
+        match self {
+            PluginMetadata::Filter(m) => &m.config_schema,
+            PluginMetadata::Transformer(m) => &m.config_schema,
+            PluginMetadata::ScheduledTask(m) => &m.config_schema,
         }
     }
 }
@@ -1,14 +1,34 @@
 use wasmtime::*;
 use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiView};
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::emit::CotEmitter;
 use crate::error::{PluginError, PluginResult};
+use crate::http::HttpFetcher;
 use crate::security::{ResourceLimits, SandboxPolicy};
+use crate::settings::PluginSettings;
+use crate::stats::PluginMetrics;
+use crate::storage::PluginStore;
+
+/// Interval of the epoch ticker that enforces execution budgets; budgets
+/// are enforced to within about one tick
+const EPOCH_TICK: Duration = Duration::from_micros(100);
 
 /// WASM plugin runtime environment
 pub struct PluginRuntime {
     engine: Engine,
     resource_limits: ResourceLimits,
     sandbox_policy: SandboxPolicy,
+    emitter: Arc<CotEmitter>,
+    storage: Option<Arc<PluginStore>>,
+    settings: Option<Arc<PluginSettings>>,
+    http: Arc<HttpFetcher>,
+    metrics: Arc<PluginMetrics>,
+    /// Stops the epoch ticker thread when the runtime is dropped
+    ticker_stop: Arc<AtomicBool>,
 }
 
 impl PluginRuntime {
@@ -36,13 +56,50 @@ impl PluginRuntime {
         let engine = Engine::new(&config)
             .map_err(|e| PluginError::CompilationError(e.to_string()))?;
 
+        let emitter = Arc::new(CotEmitter::new(resource_limits.max_emits_per_second));
+        let http = Arc::new(HttpFetcher::new(&resource_limits));
+
+        // Advance the engine's epoch so plugins can be interrupted when they
+        // run past their budget
+        let ticker_stop = Arc::new(AtomicBool::new(false));
+        {
+            let engine = engine.clone();
+            let stop = ticker_stop.clone();
+            std::thread::Builder::new()
+                .name("plugin-epoch".to_string())
+                .spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        std::thread::sleep(EPOCH_TICK);
+                        engine.increment_epoch();
+                    }
+                })?;
+        }
+
         Ok(Self {
             engine,
             resource_limits,
             sandbox_policy,
+            emitter,
+            storage: None,
+            settings: None,
+            http,
+            metrics: Arc::new(PluginMetrics::new()),
+            ticker_stop,
         })
     }
 
+    /// Give plugins key-value storage in `storage`
+    pub fn with_storage(mut self, storage: Arc<PluginStore>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Give plugins the settings in `settings` through `get-config`
+    pub fn with_settings(mut self, settings: Arc<PluginSettings>) -> Self {
+        self.settings = Some(settings);
+        self
+    }
+
     /// Get the WASM engine
     pub fn engine(&self) -> &Engine {
         &self.engine
@@ -58,21 +115,100 @@ impl PluginRuntime {
         &self.sandbox_policy
     }
 
-    /// Create a new store with configured limits
-    pub fn create_store(&self) -> Store<PluginState> {
+    /// Get the destination of messages emitted by plugins
+    pub fn emitter(&self) -> &Arc<CotEmitter> {
+        &self.emitter
+    }
+
+    /// Get the per-plugin execution metrics
+    pub fn metrics(&self) -> &Arc<PluginMetrics> {
+        &self.metrics
+    }
+
+    /// Execution budget for plugins on the message path: `declared_us`
+    /// if the plugin declares a lower one than the configured limit
+    pub fn message_path_budget(&self, declared_us: Option<u64>) -> Duration {
+        let limit = self.resource_limits.max_execution_time;
+        declared_us
+            .map(Duration::from_micros)
+            .map_or(limit, |declared| declared.min(limit))
+    }
+
+    /// Create a new store with configured limits for a plugin, which may
+    /// spend `budget` running its own code per call. Time spent waiting
+    /// in host functions such as `http-fetch` does not count
+    pub fn create_store(&self, plugin_id: &str, budget: Duration) -> Store<PluginState> {
+        self.store(plugin_id, budget, false)
+    }
+
+    /// Create a store for a plugin on the message path, which may take
+    /// `budget` per call in total: time waiting in `http-fetch` counts, and
+    /// a fetch is cut off when the budget runs out
+    pub fn create_message_path_store(
+        &self,
+        plugin_id: &str,
+        budget: Duration,
+    ) -> Store<PluginState> {
+        self.store(plugin_id, budget, true)
+    }
+
+    fn store(&self, plugin_id: &str, budget: Duration, waits_count: bool) -> Store<PluginState> {
         let wasi_ctx = self.create_wasi_context();
         let state = PluginState {
             wasi_ctx,
-            limits: self.resource_limits.clone(),
+            limiter: MemoryLimiter {
+                max_memory_bytes: self.resource_limits.max_memory_bytes,
+                memory_bytes: 0,
+            },
+            budget,
+            waits_count,
+            call_started: Instant::now(),
+            host_time: Duration::ZERO,
+            plugin_id: plugin_id.to_string(),
+            emitter: self
+                .sandbox_policy
+                .allow_emit_cot
+                .then(|| self.emitter.clone()),
+            storage: self.storage.clone(),
+            settings: self.settings.clone(),
+            http: self.http.clone(),
+            http_domains: self
+                .sandbox_policy
+                .http_domains
+                .get(plugin_id)
+                .cloned()
+                .unwrap_or_default(),
         };
         let mut store = Store::new(self.engine(), state);
 
         // Set memory limits
-        store.limiter(|state| &mut state.limits);
+        store.limiter(|state| &mut state.limiter);
+
+        // At the deadline, check how much of the budget went to the
+        // plugin's own code and either stop it or extend the deadline
+        store.epoch_deadline_callback(|ctx| {
+            let state = ctx.data();
+            let used = state.call_started.elapsed().saturating_sub(state.host_time);
+            if used >= state.budget {
+                return Err(Trap::Interrupt.into());
+            }
+            Ok(UpdateDeadline::Continue(epoch_ticks(state.budget - used)))
+        });
+        store.set_epoch_deadline(epoch_ticks(budget));
 
         store
     }
 
+    /// Restart the budget of `store` before calling into the plugin, so
+    /// instantiation does not count against the call
+    pub fn start_call(&self, store: &mut Store<PluginState>) {
+        let budget = store.data().budget;
+        let state = store.data_mut();
+        state.call_started = Instant::now();
+        state.host_time = Duration::ZERO;
+        store.set_epoch_deadline(epoch_ticks(budget));
+    }
+
     /// Create WASI context based on sandbox policy
     fn create_wasi_context(&self) -> WasiCtx {
         let builder = &mut WasiCtxBuilder::new();
@@ -125,6 +261,22 @@ impl PluginRuntime {
     }
 }
 
+impl Drop for PluginRuntime {
+    fn drop(&mut self) {
+        self.ticker_stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Epoch ticks covering `duration`, at least one
+fn epoch_ticks(duration: Duration) -> u64 {
+    (duration.as_micros() / EPOCH_TICK.as_micros()).max(1) as u64
+}
+
+/// Whether a plugin call failed by running past its execution budget
+pub fn is_budget_exceeded(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<Trap>() == Some(&Trap::Interrupt)
+}
+
 impl Default for PluginRuntime {
     fn default() -> Self {
         Self::new().expect("Failed to create default plugin runtime")
@@ -134,7 +286,30 @@ impl Default for PluginRuntime {
 /// Plugin execution state
 pub struct PluginState {
     wasi_ctx: WasiCtx,
-    limits: ResourceLimits,
+    limiter: MemoryLimiter,
+    /// Time the plugin's own code may run per call
+    budget: Duration,
+    /// Whether waiting in host functions counts against `budget`
+    waits_count: bool,
+    call_started: Instant,
+    /// Time spent waiting in host functions since `call_started`, when it
+    /// does not count
+    host_time: Duration,
+    plugin_id: String,
+    /// Set only if the sandbox policy allows `emit-cot`
+    emitter: Option<Arc<CotEmitter>>,
+    storage: Option<Arc<PluginStore>>,
+    settings: Option<Arc<PluginSettings>>,
+    http: Arc<HttpFetcher>,
+    /// Domains this plugin may fetch from
+    http_domains: Vec<String>,
+}
+
+impl PluginState {
+    /// Linear memory the plugin has grown to
+    pub fn memory_bytes(&self) -> u64 {
+        self.limiter.memory_bytes as u64
+    }
 }
 
 impl WasiView for PluginState {
@@ -147,8 +322,14 @@ impl WasiView for PluginState {
     }
 }
 
+/// Enforces the memory limit and keeps the largest memory size granted
+struct MemoryLimiter {
+    max_memory_bytes: u64,
+    memory_bytes: usize,
+}
+
 // Resource limiter implementation
-impl wasmtime::ResourceLimiter for ResourceLimits {
+impl wasmtime::ResourceLimiter for MemoryLimiter {
     fn memory_growing(
         &mut self,
         _current: usize,
@@ -158,6 +339,7 @@ impl wasmtime::ResourceLimiter for ResourceLimits {
         if desired as u64 > self.max_memory_bytes {
             Ok(false)
         } else {
+            self.memory_bytes = self.memory_bytes.max(desired);
             Ok(true)
         }
     }
@@ -197,4 +379,90 @@ impl crate::omnitak::plugin::host::Host for PluginState {
         // Elevation queries not implemented yet
         None
     }
+
+    async fn emit_cot(&mut self, xml: String) -> Result<(), String> {
+        let emitter = self
+            .emitter
+            .as_ref()
+            .ok_or_else(|| "emit-cot is not allowed by the sandbox policy".to_string())?;
+        emitter.emit(&self.plugin_id, xml).inspect_err(
+            |e| tracing::debug!(plugin = %self.plugin_id, error = %e, "Plugin emit-cot rejected"),
+        )
+    }
+
+    async fn kv_get(&mut self, key: String) -> Option<Vec<u8>> {
+        let storage = self.storage.as_ref()?;
+        storage
+            .get(&self.plugin_id, &key)
+            .inspect_err(
+                |e| tracing::warn!(plugin = %self.plugin_id, error = %e, "Plugin kv-get failed"),
+            )
+            .ok()
+            .flatten()
+    }
+
+    async fn kv_set(&mut self, key: String, value: Vec<u8>) -> Result<(), String> {
+        let storage = self
+            .storage
+            .as_ref()
+            .ok_or_else(|| "plugin storage is not available".to_string())?;
+        storage
+            .set(&self.plugin_id, &key, &value)
+            .map_err(|e| e.to_string())
+    }
+
+    async fn kv_delete(&mut self, key: String) -> bool {
+        let Some(storage) = self.storage.as_ref() else {
+            return false;
+        };
+        storage
+            .delete(&self.plugin_id, &key)
+            .inspect_err(
+                |e| tracing::warn!(plugin = %self.plugin_id, error = %e, "Plugin kv-delete failed"),
+            )
+            .unwrap_or(false)
+    }
+
+    async fn get_config(&mut self, key: String) -> Option<String> {
+        let settings = self.settings.as_ref()?;
+        settings
+            .get(&self.plugin_id, &key)
+            .inspect_err(
+                |e| tracing::warn!(plugin = %self.plugin_id, error = %e, "Plugin get-config failed"),
+            )
+            .ok()
+            .flatten()
+    }
+
+    async fn http_fetch(
+        &mut self,
+        request: crate::omnitak::plugin::host::HttpRequest,
+    ) -> Result<crate::omnitak::plugin::host::HttpResponse, String> {
+        if self.http_domains.is_empty() {
+            return Err("http-fetch is not allowed by the sandbox policy".to_string());
+        }
+        let timeout = if self.waits_count {
+            let left = self.budget.saturating_sub(self.call_started.elapsed());
+            if left.is_zero() {
+                return Err("no execution budget left for http-fetch".to_string());
+            }
+            left
+        } else {
+            Duration::MAX
+        };
+        let url = request.url.clone();
+        let start = Instant::now();
+        let response = self.http.fetch(&self.http_domains, request, timeout).await;
+        if !self.waits_count {
+            self.host_time += start.elapsed();
+        }
+        response.inspect_err(|e| {
+            tracing::debug!(
+                plugin = %self.plugin_id,
+                url = %url,
+                error = %e,
+                "Plugin http-fetch failed"
+            )
+        })
+    }
 }
@@ -0,0 +1,284 @@
+//! Schedules for scheduled-task plugins
+//!
+//! A schedule is either a fixed interval (`@every 30s`, `@every 5m`) or a
+//! five-field cron expression (`minute hour day-of-month month day-of-week`)
+//! evaluated in UTC. Cron fields accept `*`, single values, ranges (`1-5`),
+//! steps (`*/15`, `0-30/10`) and comma-separated lists. `@hourly`, `@daily`
+//! and `@weekly` are shorthands for the matching cron expressions.
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, TimeZone, Timelike, Utc};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::error::PluginError;
+
+/// When a scheduled-task plugin runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// Run repeatedly, `interval` after the previous run was due
+    Every(Duration),
+    /// Run at the times matching a cron expression
+    Cron(CronSpec),
+}
+
+impl Schedule {
+    /// The first time after `after` the task is due, if there is one
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Every(interval) => Some(after + ChronoDuration::from_std(*interval).ok()?),
+            Schedule::Cron(spec) => spec.next_after(after),
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = PluginError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let spec = spec.trim();
+        let invalid = |reason: &str| {
+            PluginError::InvalidMetadata(format!("invalid schedule '{}': {}", spec, reason))
+        };
+
+        if let Some(interval) = spec.strip_prefix("@every") {
+            let interval = parse_interval(interval.trim()).ok_or_else(|| {
+                invalid("expected an interval such as 30s, 5m, 1h or 1d")
+            })?;
+            if interval.is_zero() {
+                return Err(invalid("interval must be greater than zero"));
+            }
+            return Ok(Schedule::Every(interval));
+        }
+
+        let expr = match spec {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            _ => spec,
+        };
+        CronSpec::parse(expr).map(Schedule::Cron).map_err(|e| invalid(&e))
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Every(interval) => write!(f, "@every {}s", interval.as_secs()),
+            Schedule::Cron(spec) => f.write_str(&spec.expr),
+        }
+    }
+}
+
+/// `30s`, `5m`, `1h` or `1d`
+fn parse_interval(s: &str) -> Option<Duration> {
+    let split = s.find(|c: char| !c.is_ascii_digit())?;
+    let (value, unit) = s.split_at(split);
+    let value: u64 = value.parse().ok()?;
+    let secs = match unit {
+        "s" => value,
+        "m" => value.checked_mul(60)?,
+        "h" => value.checked_mul(3600)?,
+        "d" => value.checked_mul(86_400)?,
+        _ => return None,
+    };
+    Some(Duration::from_secs(secs))
+}
+
+/// Parsed five-field cron expression. Each field is a bit set of the
+/// values it matches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSpec {
+    expr: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Whether day-of-month and day-of-week were `*`. When both are
+    /// restricted, a day matching either runs the task, as in cron
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+/// Furthest ahead to look for a matching time; far enough for any
+/// expression that can match at all, e.g. February 29th on a Monday
+const MAX_SEARCH_YEARS: i32 = 30;
+
+impl CronSpec {
+    fn parse(expr: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, dom, month, dow] = fields.as_slice() else {
+            return Err(format!("expected 5 cron fields, got {}", fields.len()));
+        };
+
+        // Sunday may be written as 0 or 7
+        let mut days_of_week = parse_field(dow, 0, 7)?;
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            expr: fields.join(" "),
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days_of_month: parse_field(dom, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            days_of_week,
+            any_day_of_month: *dom == "*",
+            any_day_of_week: *dow == "*",
+        })
+    }
+
+    fn day_matches(&self, t: &DateTime<Utc>) -> bool {
+        let dom = self.days_of_month & (1 << t.day()) != 0;
+        let dow = self.days_of_week & (1 << t.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (false, false) => dom || dow,
+            _ => dom && dow,
+        }
+    }
+
+    fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut t = after.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        let limit = after.year() + MAX_SEARCH_YEARS;
+
+        while t.year() <= limit {
+            if self.months & (1 << t.month()) == 0 {
+                let (year, month) = match t.month() {
+                    12 => (t.year() + 1, 1),
+                    m => (t.year(), m + 1),
+                };
+                t = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()?;
+            } else if !self.day_matches(&t) {
+                t = t.date_naive().succ_opt()?.and_hms_opt(0, 0, 0)?.and_utc();
+            } else if self.hours & (1 << t.hour()) == 0 {
+                t = t.with_minute(0)? + ChronoDuration::hours(1);
+            } else if self.minutes & (1 << t.minute()) == 0 {
+                t += ChronoDuration::minutes(1);
+            } else {
+                return Some(t);
+            }
+        }
+        None
+    }
+}
+
+/// Parse one cron field into a bit set of the values in `min..=max` it
+/// matches
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("invalid step in '{}'", part))?;
+                if step == 0 {
+                    return Err(format!("step must be greater than zero in '{}'", part));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, part)?, parse_value(end, part)?)
+        } else {
+            let value = parse_value(range, part)?;
+            // `5/15` means from 5 to the end in steps of 15
+            (value, if step > 1 { max } else { value })
+        };
+        if start < min || end > max || start > end {
+            return Err(format!("'{}' is outside {}-{}", part, min, max));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+fn parse_value(value: &str, part: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value in '{}'", part))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn next(spec: &str, after: &str) -> DateTime<Utc> {
+        spec.parse::<Schedule>()
+            .unwrap()
+            .next_after(at(after))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_interval_schedules() {
+        assert_eq!(
+            "@every 30s".parse::<Schedule>().unwrap(),
+            Schedule::Every(Duration::from_secs(30))
+        );
+        assert_eq!(
+            next("@every 5m", "2025-01-01T00:00:10Z"),
+            at("2025-01-01T00:05:10Z")
+        );
+        assert!("@every 0s".parse::<Schedule>().is_err());
+        assert!("@every soon".parse::<Schedule>().is_err());
+    }
+
+    #[test]
+    fn test_cron_schedules() {
+        assert_eq!(
+            next("*/15 * * * *", "2025-01-01T00:07:30Z"),
+            at("2025-01-01T00:15:00Z")
+        );
+        // Always strictly after
+        assert_eq!(
+            next("*/15 * * * *", "2025-01-01T00:15:00Z"),
+            at("2025-01-01T00:30:00Z")
+        );
+        assert_eq!(
+            next("@daily", "2025-01-31T12:00:00Z"),
+            at("2025-02-01T00:00:00Z")
+        );
+        assert_eq!(
+            next("30 9 * * 1-5", "2025-01-03T10:00:00Z"), // Friday
+            at("2025-01-06T09:30:00Z")
+        );
+        // Sunday as 7
+        assert_eq!(
+            next("0 0 * * 7", "2025-01-01T00:00:00Z"),
+            at("2025-01-05T00:00:00Z")
+        );
+        // Day of month or day of week when both are given
+        assert_eq!(
+            next("0 0 13 * 5", "2025-01-01T00:00:00Z"),
+            at("2025-01-03T00:00:00Z")
+        );
+        assert_eq!(
+            next("0 0 29 2 *", "2025-01-01T00:00:00Z"),
+            at("2028-02-29T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_invalid_cron_schedules() {
+        for spec in ["* * * *", "60 * * * *", "* * 0 * *", "*/0 * * * *", "5-1 * * * *"] {
+            assert!(spec.parse::<Schedule>().is_err(), "{}", spec);
+        }
+        // Parses, but never matches
+        let never: Schedule = "0 0 31 2 *".parse().unwrap();
+        assert!(never.next_after(at("2025-01-01T00:00:00Z")).is_none());
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::Duration;
 
 use crate::DEFAULT_MAX_EXECUTION_TIME_US;
@@ -6,6 +7,7 @@ use crate::DEFAULT_MAX_MEMORY_BYTES;
 
 /// Resource limits for plugin execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ResourceLimits {
     /// Maximum execution time per call
     pub max_execution_time: Duration,
@@ -13,6 +15,31 @@ pub struct ResourceLimits {
     pub max_memory_bytes: u64,
     /// Maximum number of concurrent executions
     pub max_concurrent_executions: usize,
+    /// Maximum messages a plugin may inject per second with `emit-cot`
+    pub max_emits_per_second: u32,
+    /// Maximum bytes of keys and values a plugin may keep in storage
+    pub max_storage_bytes: u64,
+    /// Timeout for a whole `http-fetch` request, including the body. On the
+    /// message path the plugin's execution budget also bounds it
+    pub http_timeout_ms: u64,
+    /// Largest response body `http-fetch` returns
+    pub max_http_response_bytes: u64,
+}
+
+fn default_max_emits_per_second() -> u32 {
+    10
+}
+
+fn default_max_storage_bytes() -> u64 {
+    1024 * 1024
+}
+
+fn default_http_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_max_http_response_bytes() -> u64 {
+    256 * 1024
 }
 
 impl Default for ResourceLimits {
@@ -21,12 +48,17 @@ impl Default for ResourceLimits {
             max_execution_time: Duration::from_micros(DEFAULT_MAX_EXECUTION_TIME_US),
             max_memory_bytes: DEFAULT_MAX_MEMORY_BYTES,
             max_concurrent_executions: 100,
+            max_emits_per_second: default_max_emits_per_second(),
+            max_storage_bytes: default_max_storage_bytes(),
+            http_timeout_ms: default_http_timeout_ms(),
+            max_http_response_bytes: default_max_http_response_bytes(),
         }
     }
 }
 
 /// Sandbox security policy
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SandboxPolicy {
     /// Allow network access
     pub allow_network: bool,
@@ -38,6 +70,11 @@ pub struct SandboxPolicy {
     pub allow_env_vars: bool,
     /// Allowed filesystem paths (if filesystem access enabled)
     pub allowed_paths: Vec<String>,
+    /// Allow injecting new messages with `emit-cot`
+    pub allow_emit_cot: bool,
+    /// Domains each plugin may reach with `http-fetch`, by plugin ID.
+    /// `*.example.com` allows subdomains of example.com
+    pub http_domains: HashMap<String, Vec<String>>,
 }
 
 impl Default for SandboxPolicy {
@@ -48,6 +85,8 @@ impl Default for SandboxPolicy {
             allow_filesystem_write: false,
             allow_env_vars: false,
             allowed_paths: Vec::new(),
+            allow_emit_cot: false,
+            http_domains: HashMap::new(),
         }
     }
 }
@@ -66,6 +105,8 @@ impl SandboxPolicy {
             allow_filesystem_write: true,
             allow_env_vars: true,
             allowed_paths: vec!["/".to_string()],
+            allow_emit_cot: true,
+            http_domains: HashMap::new(),
         }
     }
 
@@ -77,6 +118,8 @@ impl SandboxPolicy {
             allow_filesystem_write: false,
             allow_env_vars: false,
             allowed_paths: paths,
+            allow_emit_cot: false,
+            http_domains: HashMap::new(),
         }
     }
 }
@@ -0,0 +1,177 @@
+//! Operator-set plugin settings
+//!
+//! Plugins declare the settings they read in the `config_schema` of their
+//! metadata. Operators change them through the API; plugins read them with
+//! the `get-config` host function, getting the schema default for settings
+//! nobody has set. Values are checked against the schema and kept in the
+//! plugin store, so they survive reloads and, with a storage directory,
+//! restarts.
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+use crate::error::{PluginError, PluginResult};
+use crate::metadata::ConfigField;
+use crate::storage::PluginStore;
+
+/// A plugin's settings and the schema they follow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct PluginSettingsSnapshot {
+    pub schema: Vec<ConfigField>,
+    /// Current value of every setting in the schema that has one
+    #[cfg_attr(feature = "openapi", schema(value_type = Object))]
+    pub values: Map<String, Value>,
+}
+
+/// Settings of all loaded plugins
+pub struct PluginSettings {
+    store: Arc<PluginStore>,
+    schemas: DashMap<String, Arc<Vec<ConfigField>>>,
+}
+
+impl PluginSettings {
+    pub fn new(store: Arc<PluginStore>) -> Self {
+        Self {
+            store,
+            schemas: DashMap::new(),
+        }
+    }
+
+    /// Use `schema` for a loaded plugin. Stored values are kept, so a new
+    /// version of a plugin sees the settings of the previous one
+    pub fn register(&self, plugin_id: &str, schema: Vec<ConfigField>) {
+        self.schemas.insert(plugin_id.to_string(), Arc::new(schema));
+    }
+
+    /// Forget the schema of an unloaded plugin
+    pub fn unregister(&self, plugin_id: &str) {
+        self.schemas.remove(plugin_id);
+    }
+
+    fn schema(&self, plugin_id: &str) -> PluginResult<Arc<Vec<ConfigField>>> {
+        self.schemas
+            .get(plugin_id)
+            .map(|s| s.value().clone())
+            .ok_or_else(|| PluginError::NotFound(plugin_id.to_string()))
+    }
+
+    /// A plugin's schema and current settings
+    pub fn snapshot(&self, plugin_id: &str) -> PluginResult<PluginSettingsSnapshot> {
+        let schema = self.schema(plugin_id)?;
+        let mut stored = self.store.settings(plugin_id)?;
+        let values = schema
+            .iter()
+            .filter_map(|field| {
+                let value = stored.remove(&field.key).or_else(|| field.default.clone())?;
+                Some((field.key.clone(), value))
+            })
+            .collect();
+        Ok(PluginSettingsSnapshot {
+            schema: schema.to_vec(),
+            values,
+        })
+    }
+
+    /// Change settings of a plugin. Each key must be in the plugin's
+    /// schema and each value of the declared type; `null` resets a setting
+    /// to its default. Nothing is changed if any value is invalid
+    pub fn update(&self, plugin_id: &str, values: &Map<String, Value>) -> PluginResult<()> {
+        let schema = self.schema(plugin_id)?;
+        for (key, value) in values {
+            let field = schema
+                .iter()
+                .find(|f| &f.key == key)
+                .ok_or_else(|| PluginError::InvalidConfig(format!("unknown setting '{}'", key)))?;
+            if !value.is_null() {
+                field.check(value).map_err(PluginError::InvalidConfig)?;
+            }
+        }
+        for (key, value) in values {
+            let value = (!value.is_null()).then_some(value);
+            self.store.set_setting(plugin_id, key, value)?;
+        }
+        Ok(())
+    }
+
+    /// A setting as plugins see it: the stored value, else the default.
+    /// Strings are returned as they are, other values in their JSON form
+    pub fn get(&self, plugin_id: &str, key: &str) -> PluginResult<Option<String>> {
+        let Some(field) = self
+            .schemas
+            .get(plugin_id)
+            .and_then(|schema| schema.iter().find(|f| f.key == key).cloned())
+        else {
+            return Ok(None);
+        };
+        let value = match self.store.setting(plugin_id, key)? {
+            Some(value) => Some(value),
+            None => field.default,
+        };
+        Ok(value.map(|value| match value {
+            Value::String(s) => s,
+            other => other.to_string(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::ConfigFieldType;
+    use serde_json::json;
+
+    fn field(key: &str, field_type: ConfigFieldType, default: Option<Value>) -> ConfigField {
+        ConfigField {
+            key: key.to_string(),
+            field_type,
+            default,
+            description: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_settings_follow_schema() {
+        let settings = PluginSettings::new(Arc::new(PluginStore::temporary(1024).unwrap()));
+        settings.register(
+            "geofence",
+            vec![
+                field("radius_km", ConfigFieldType::Float, Some(json!(5.0))),
+                field("zone", ConfigFieldType::String, None),
+                field("strict", ConfigFieldType::Boolean, Some(json!(false))),
+            ],
+        );
+
+        assert_eq!(settings.get("geofence", "radius_km").unwrap().as_deref(), Some("5.0"));
+        assert_eq!(settings.get("geofence", "zone").unwrap(), None);
+        assert_eq!(settings.get("geofence", "unknown").unwrap(), None);
+
+        let update = json!({"zone": "north", "strict": true});
+        settings.update("geofence", update.as_object().unwrap()).unwrap();
+        assert_eq!(settings.get("geofence", "zone").unwrap().as_deref(), Some("north"));
+        assert_eq!(settings.get("geofence", "strict").unwrap().as_deref(), Some("true"));
+
+        // Invalid updates change nothing
+        for update in [json!({"zone": "south", "strict": "yes"}), json!({"colour": "red"})] {
+            assert!(matches!(
+                settings.update("geofence", update.as_object().unwrap()),
+                Err(PluginError::InvalidConfig(_))
+            ));
+        }
+        assert_eq!(settings.get("geofence", "zone").unwrap().as_deref(), Some("north"));
+
+        // Null resets to the default; values survive a reload
+        let reset = json!({"strict": null});
+        settings.update("geofence", reset.as_object().unwrap()).unwrap();
+        settings.unregister("geofence");
+        assert!(settings.snapshot("geofence").is_err());
+        settings.register("geofence", vec![field("zone", ConfigFieldType::String, None)]);
+        let config = settings.snapshot("geofence").unwrap();
+        assert_eq!(config.values, json!({"zone": "north"}).as_object().unwrap().clone());
+    }
+}
@@ -0,0 +1,260 @@
+//! Per-plugin execution metrics
+//!
+//! Every plugin invocation is counted by outcome and timed, and the linear
+//! memory it grew to is kept. [`PluginMetrics::render_prometheus`] formats
+//! everything in the Prometheus text format for the metrics endpoint.
+
+use dashmap::DashMap;
+use serde::Serialize;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Upper bounds of the execution time histogram buckets, in seconds
+const DURATION_BUCKETS: [f64; 10] = [
+    0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.05, 0.25, 1.0,
+];
+
+/// How a plugin invocation ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Ran to completion; a filter passed the message
+    Completed,
+    /// A filter blocked the message
+    Rejected,
+    /// Returned an error or trapped
+    Failed,
+    /// Stopped for exceeding its execution budget
+    TimedOut,
+}
+
+/// Counters for one plugin
+#[derive(Debug, Default)]
+struct PluginStats {
+    kind: &'static str,
+    invocations: AtomicU64,
+    rejections: AtomicU64,
+    errors: AtomicU64,
+    timeouts: AtomicU64,
+    /// Non-cumulative counts per bucket; the last counts everything slower
+    buckets: [AtomicU64; DURATION_BUCKETS.len() + 1],
+    total_time_us: AtomicU64,
+    memory_bytes: AtomicU64,
+}
+
+/// Point-in-time copy of a plugin's counters
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PluginStatsSnapshot {
+    pub invocations: u64,
+    pub rejections: u64,
+    pub errors: u64,
+    pub timeouts: u64,
+    pub avg_time_us: f64,
+    /// Linear memory the most recent invocation grew to
+    pub memory_bytes: u64,
+}
+
+/// Execution metrics for all loaded plugins
+#[derive(Debug, Default)]
+pub struct PluginMetrics {
+    plugins: DashMap<String, Arc<PluginStats>>,
+}
+
+impl PluginMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one invocation of `plugin`, a plugin of type `kind`
+    pub fn record(
+        &self,
+        plugin: &str,
+        kind: &'static str,
+        elapsed: Duration,
+        outcome: Outcome,
+        memory_bytes: u64,
+    ) {
+        let stats = self
+            .plugins
+            .entry(plugin.to_string())
+            .or_insert_with(|| {
+                Arc::new(PluginStats {
+                    kind,
+                    ..Default::default()
+                })
+            })
+            .clone();
+
+        stats.invocations.fetch_add(1, Ordering::Relaxed);
+        match outcome {
+            Outcome::Completed => {}
+            Outcome::Rejected => {
+                stats.rejections.fetch_add(1, Ordering::Relaxed);
+            }
+            Outcome::Failed => {
+                stats.errors.fetch_add(1, Ordering::Relaxed);
+            }
+            Outcome::TimedOut => {
+                stats.timeouts.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let secs = elapsed.as_secs_f64();
+        let bucket = DURATION_BUCKETS
+            .iter()
+            .position(|&bound| secs <= bound)
+            .unwrap_or(DURATION_BUCKETS.len());
+        stats.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        stats
+            .total_time_us
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        stats.memory_bytes.store(memory_bytes, Ordering::Relaxed);
+    }
+
+    /// Counters for one plugin, if it has run
+    pub fn snapshot(&self, plugin: &str) -> Option<PluginStatsSnapshot> {
+        let stats = self.plugins.get(plugin)?;
+        let invocations = stats.invocations.load(Ordering::Relaxed);
+        Some(PluginStatsSnapshot {
+            invocations,
+            rejections: stats.rejections.load(Ordering::Relaxed),
+            errors: stats.errors.load(Ordering::Relaxed),
+            timeouts: stats.timeouts.load(Ordering::Relaxed),
+            avg_time_us: if invocations == 0 {
+                0.0
+            } else {
+                stats.total_time_us.load(Ordering::Relaxed) as f64 / invocations as f64
+            },
+            memory_bytes: stats.memory_bytes.load(Ordering::Relaxed),
+        })
+    }
+
+    /// Forget an unloaded plugin
+    pub fn remove(&self, plugin: &str) {
+        self.plugins.remove(plugin);
+    }
+
+    /// All plugins' metrics in the Prometheus text format
+    pub fn render_prometheus(&self) -> String {
+        let mut plugins: Vec<(String, Arc<PluginStats>)> = self
+            .plugins
+            .iter()
+            .map(|e| (e.key().clone(), e.value().clone()))
+            .collect();
+        plugins.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut out = String::new();
+        if plugins.is_empty() {
+            return out;
+        }
+
+        let counters: [(&str, &str, fn(&PluginStats) -> &AtomicU64); 4] = [
+            ("omnitak_plugin_invocations_total", "Plugin invocations", |s| &s.invocations),
+            (
+                "omnitak_plugin_rejections_total",
+                "Messages blocked by filter plugins",
+                |s| &s.rejections,
+            ),
+            ("omnitak_plugin_errors_total", "Plugin invocations that failed", |s| &s.errors),
+            (
+                "omnitak_plugin_timeouts_total",
+                "Plugin invocations stopped for exceeding their execution budget",
+                |s| &s.timeouts,
+            ),
+        ];
+        for (name, help, counter) in counters {
+            let _ = writeln!(out, "\n# HELP {name} {help}\n# TYPE {name} counter");
+            for (id, stats) in &plugins {
+                let _ = writeln!(
+                    out,
+                    "{name}{{{}}} {}",
+                    labels(id, stats),
+                    counter(stats).load(Ordering::Relaxed)
+                );
+            }
+        }
+
+        let name = "omnitak_plugin_execution_seconds";
+        let _ = writeln!(
+            out,
+            "\n# HELP {name} Time per plugin invocation, including instantiation\n# TYPE {name} histogram"
+        );
+        for (id, stats) in &plugins {
+            let labels = labels(id, stats);
+            let mut cumulative = 0;
+            for (i, bucket) in stats.buckets.iter().enumerate() {
+                cumulative += bucket.load(Ordering::Relaxed);
+                let le = DURATION_BUCKETS
+                    .get(i)
+                    .map_or("+Inf".to_string(), |b| b.to_string());
+                let _ = writeln!(out, "{name}_bucket{{{labels},le=\"{le}\"}} {cumulative}");
+            }
+            let sum = stats.total_time_us.load(Ordering::Relaxed) as f64 / 1e6;
+            let _ = writeln!(out, "{name}_sum{{{labels}}} {sum}");
+            let _ = writeln!(out, "{name}_count{{{labels}}} {cumulative}");
+        }
+
+        let name = "omnitak_plugin_memory_bytes";
+        let _ = writeln!(
+            out,
+            "\n# HELP {name} Linear memory of the plugin's most recent invocation\n# TYPE {name} gauge"
+        );
+        for (id, stats) in &plugins {
+            let _ = writeln!(
+                out,
+                "{name}{{{}}} {}",
+                labels(id, stats),
+                stats.memory_bytes.load(Ordering::Relaxed)
+            );
+        }
+
+        out
+    }
+}
+
+fn labels(id: &str, stats: &PluginStats) -> String {
+    let id = id
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("plugin=\"{}\",kind=\"{}\"", id, stats.kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_render() {
+        let metrics = PluginMetrics::new();
+        metrics.record("geofence", "filter", Duration::from_micros(80), Outcome::Completed, 65536);
+        metrics.record("geofence", "filter", Duration::from_micros(300), Outcome::Rejected, 65536);
+        metrics.record("geofence", "filter", Duration::from_millis(2), Outcome::TimedOut, 131072);
+
+        let snapshot = metrics.snapshot("geofence").unwrap();
+        assert_eq!(snapshot.invocations, 3);
+        assert_eq!(snapshot.rejections, 1);
+        assert_eq!(snapshot.timeouts, 1);
+        assert_eq!(snapshot.errors, 0);
+        assert_eq!(snapshot.memory_bytes, 131072);
+
+        let text = metrics.render_prometheus();
+        assert!(text.contains(
+            "omnitak_plugin_invocations_total{plugin=\"geofence\",kind=\"filter\"} 3"
+        ));
+        assert!(text.contains(
+            "omnitak_plugin_execution_seconds_bucket{plugin=\"geofence\",kind=\"filter\",le=\"0.0001\"} 1"
+        ));
+        assert!(text.contains(
+            "omnitak_plugin_execution_seconds_bucket{plugin=\"geofence\",kind=\"filter\",le=\"0.0005\"} 2"
+        ));
+        assert!(text.contains(
+            "omnitak_plugin_execution_seconds_bucket{plugin=\"geofence\",kind=\"filter\",le=\"+Inf\"} 3"
+        ));
+
+        metrics.remove("geofence");
+        assert!(metrics.snapshot("geofence").is_none());
+        assert!(metrics.render_prometheus().is_empty());
+    }
+}
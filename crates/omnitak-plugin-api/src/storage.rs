@@ -0,0 +1,204 @@
+//! Persistent key-value storage for plugins
+//!
+//! Each plugin gets its own namespace (a sled tree named after the plugin
+//! ID), reached through the `kv-get`/`kv-set`/`kv-delete` host functions.
+//! Data outlives plugin instances, so caches and counters survive reloads,
+//! and survives restarts when the store is opened on disk.
+//!
+//! Operator-set plugin settings are kept here too, in a separate namespace
+//! that plugins cannot write and that does not count against their quota.
+
+use dashmap::DashMap;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+use crate::error::{PluginError, PluginResult};
+
+/// Namespaced key-value store shared by all plugins
+pub struct PluginStore {
+    db: sled::Db,
+    /// Bytes of keys and values each plugin may store
+    quota_bytes: u64,
+    /// Bytes stored per plugin, computed on first use
+    usage: DashMap<String, u64>,
+}
+
+impl PluginStore {
+    /// Open (or create) a store in `path`
+    pub fn open(path: impl AsRef<Path>, quota_bytes: u64) -> PluginResult<Self> {
+        Ok(Self::with_db(sled::open(path)?, quota_bytes))
+    }
+
+    /// Store that is deleted when dropped
+    pub fn temporary(quota_bytes: u64) -> PluginResult<Self> {
+        let db = sled::Config::new().temporary(true).open()?;
+        Ok(Self::with_db(db, quota_bytes))
+    }
+
+    fn with_db(db: sled::Db, quota_bytes: u64) -> Self {
+        Self {
+            db,
+            quota_bytes,
+            usage: DashMap::new(),
+        }
+    }
+
+    fn tree(&self, plugin_id: &str) -> PluginResult<sled::Tree> {
+        Ok(self.db.open_tree(format!("plugin:{}", plugin_id))?)
+    }
+
+    pub fn get(&self, plugin_id: &str, key: &str) -> PluginResult<Option<Vec<u8>>> {
+        Ok(self.tree(plugin_id)?.get(key)?.map(|v| v.to_vec()))
+    }
+
+    /// Store a value, unless the plugin's quota would be exceeded
+    pub fn set(&self, plugin_id: &str, key: &str, value: &[u8]) -> PluginResult<()> {
+        let tree = self.tree(plugin_id)?;
+        // The entry guard serializes writes by instances of the same plugin
+        let mut usage = self
+            .usage
+            .entry(plugin_id.to_string())
+            .or_try_insert_with(|| stored_bytes(&tree))?;
+
+        let old = tree.get(key)?.map_or(0, |v| entry_size(key, &v));
+        let new_usage = *usage - old + entry_size(key, value);
+        if new_usage > self.quota_bytes {
+            return Err(PluginError::StorageQuotaExceeded {
+                current: new_usage,
+                limit: self.quota_bytes,
+            });
+        }
+
+        tree.insert(key, value)?;
+        *usage = new_usage;
+        Ok(())
+    }
+
+    /// Delete a value, returning whether it existed
+    pub fn delete(&self, plugin_id: &str, key: &str) -> PluginResult<bool> {
+        let tree = self.tree(plugin_id)?;
+        let mut usage = self
+            .usage
+            .entry(plugin_id.to_string())
+            .or_try_insert_with(|| stored_bytes(&tree))?;
+
+        match tree.remove(key)? {
+            Some(old) => {
+                *usage -= entry_size(key, &old);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Bytes a plugin currently stores
+    pub fn usage(&self, plugin_id: &str) -> PluginResult<u64> {
+        if let Some(usage) = self.usage.get(plugin_id) {
+            return Ok(*usage);
+        }
+        stored_bytes(&self.tree(plugin_id)?)
+    }
+
+    /// Delete everything a plugin has stored
+    pub fn clear(&self, plugin_id: &str) -> PluginResult<()> {
+        self.tree(plugin_id)?.clear()?;
+        self.usage.remove(plugin_id);
+        Ok(())
+    }
+
+    fn settings_tree(&self, plugin_id: &str) -> PluginResult<sled::Tree> {
+        Ok(self.db.open_tree(format!("settings:{}", plugin_id))?)
+    }
+
+    /// Settings an operator has set for a plugin
+    pub fn settings(&self, plugin_id: &str) -> PluginResult<Map<String, Value>> {
+        self.settings_tree(plugin_id)?
+            .iter()
+            .map(|entry| {
+                let (key, value) = entry?;
+                Ok((
+                    String::from_utf8_lossy(&key).into_owned(),
+                    serde_json::from_slice(&value)?,
+                ))
+            })
+            .collect()
+    }
+
+    /// One setting an operator has set for a plugin
+    pub fn setting(&self, plugin_id: &str, key: &str) -> PluginResult<Option<Value>> {
+        match self.settings_tree(plugin_id)?.get(key)? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Set a plugin setting, or clear it with `None`
+    pub fn set_setting(
+        &self,
+        plugin_id: &str,
+        key: &str,
+        value: Option<&Value>,
+    ) -> PluginResult<()> {
+        let tree = self.settings_tree(plugin_id)?;
+        match value {
+            Some(value) => tree.insert(key, serde_json::to_vec(value)?)?,
+            None => tree.remove(key)?,
+        };
+        tree.flush()?;
+        Ok(())
+    }
+}
+
+fn entry_size(key: &str, value: &[u8]) -> u64 {
+    (key.len() + value.len()) as u64
+}
+
+fn stored_bytes(tree: &sled::Tree) -> PluginResult<u64> {
+    tree.iter().try_fold(0, |total, entry| {
+        let (key, value) = entry?;
+        Ok(total + (key.len() + value.len()) as u64)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namespaces_and_quota() {
+        let store = PluginStore::temporary(16).unwrap();
+        store.set("dedup", "uid-1", b"seen").unwrap();
+        assert_eq!(store.get("dedup", "uid-1").unwrap(), Some(b"seen".to_vec()));
+        assert_eq!(store.get("counter", "uid-1").unwrap(), None);
+
+        // Overwriting only counts the difference
+        store.set("dedup", "uid-1", b"again").unwrap();
+        assert_eq!(store.usage("dedup").unwrap(), 10);
+        assert!(matches!(
+            store.set("dedup", "uid-2", b"too long"),
+            Err(PluginError::StorageQuotaExceeded {
+                current: 23,
+                limit: 16
+            })
+        ));
+
+        assert!(store.delete("dedup", "uid-1").unwrap());
+        assert!(!store.delete("dedup", "uid-1").unwrap());
+        store.set("dedup", "uid-2", b"too long").unwrap();
+    }
+
+    #[test]
+    fn test_data_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let store = PluginStore::open(dir.path(), 1024).unwrap();
+            store.set("counter", "count", &7u64.to_le_bytes()).unwrap();
+        }
+        let store = PluginStore::open(dir.path(), 1024).unwrap();
+        assert_eq!(
+            store.get("counter", "count").unwrap(),
+            Some(7u64.to_le_bytes().to_vec())
+        );
+        assert_eq!(store.usage("counter").unwrap(), 13);
+    }
+}
@@ -1,11 +1,12 @@
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use wasmtime::component::{Component, Linker};
 use wasmtime::Store;
 
 use crate::error::{PluginError, PluginResult};
 use crate::metadata::FilterMetadata;
-use crate::runtime::{PluginRuntime, PluginState};
+use crate::runtime::{is_budget_exceeded, PluginRuntime, PluginState};
+use crate::stats::Outcome;
 
 // Re-export filter types from omnitak-filter
 pub use omnitak_filter::rules::{CotMessage, FilterResult, FilterRule};
@@ -59,9 +60,17 @@ impl WasmFilterPlugin {
         &self.metadata
     }
 
+    /// Time the plugin may run per evaluation
+    fn budget(&self) -> Duration {
+        self.runtime
+            .message_path_budget(Some(self.metadata.max_execution_time_us))
+    }
+
     /// Create a new plugin instance
     async fn create_plugin_instance(&self) -> PluginResult<(Store<PluginState>, FilterPlugin)> {
-        let mut store = self.runtime.create_store();
+        let mut store = self
+            .runtime
+            .create_message_path_store(&self.metadata.id, self.budget());
         let mut linker = Linker::new(self.runtime.engine());
 
         // Add host functions (WASI + custom bindings)
@@ -75,6 +84,12 @@ impl WasmFilterPlugin {
         Ok((store, plugin))
     }
 
+    /// Instantiate the plugin once, to catch link errors before it is put
+    /// into service
+    pub async fn check(&self) -> PluginResult<()> {
+        self.create_plugin_instance().await.map(|_| ())
+    }
+
     /// Add host functions that plugins can call
     fn add_host_functions(linker: &mut Linker<PluginState>) -> PluginResult<()> {
         // Add WASI support
@@ -110,24 +125,36 @@ impl WasmFilterPlugin {
             xml_payload: None,    // Not available in CotMessage
         };
 
-        // Call the WASM exported evaluate function
+        // Call the WASM exported evaluate function; the store stops the
+        // plugin if it runs past its budget
+        self.runtime.start_call(&mut store);
         let result = plugin
             .omnitak_plugin_filter()
             .call_evaluate(&mut store, &wit_msg)
-            .await
-            .map_err(|e| PluginError::ExecutionError(e.to_string()))?;
-
-        // Check timeout
-        let elapsed = start.elapsed();
-        if elapsed.as_micros() > self.metadata.max_execution_time_us as u128 {
-            return Err(PluginError::Timeout(self.metadata.max_execution_time_us));
-        }
+            .await;
 
         // Convert WIT filter-result to FilterResult
-        match result {
-            crate::exports::omnitak::plugin::filter::FilterResult::Pass => Ok(FilterResult::Pass),
-            crate::exports::omnitak::plugin::filter::FilterResult::Block => Ok(FilterResult::Block),
-        }
+        let (result, outcome) = match result {
+            Ok(crate::exports::omnitak::plugin::filter::FilterResult::Pass) => {
+                (Ok(FilterResult::Pass), Outcome::Completed)
+            }
+            Ok(crate::exports::omnitak::plugin::filter::FilterResult::Block) => {
+                (Ok(FilterResult::Block), Outcome::Rejected)
+            }
+            Err(e) if is_budget_exceeded(&e) => (
+                Err(PluginError::Timeout(self.budget().as_micros() as u64)),
+                Outcome::TimedOut,
+            ),
+            Err(e) => (Err(PluginError::ExecutionError(e.to_string())), Outcome::Failed),
+        };
+        self.runtime.metrics().record(
+            &self.metadata.id,
+            "filter",
+            start.elapsed(),
+            outcome,
+            store.data().memory_bytes(),
+        );
+        result
     }
 }
 
@@ -169,6 +196,7 @@ mod tests {
             author: "Test Author".to_string(),
             description: "A test filter plugin".to_string(),
             max_execution_time_us: 1000,
+            config_schema: Vec::new(),
         };
 
         // Note: Can't create actual plugin without WASM binary
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use wasmtime::component::{Component, Linker};
+use wasmtime::Store;
+
+use crate::error::{PluginError, PluginResult};
+use crate::metadata::ScheduledTaskMetadata;
+use crate::runtime::{is_budget_exceeded, PluginRuntime, PluginState};
+use crate::schedule::Schedule;
+use crate::scheduled_bindings::ScheduledTaskPlugin;
+use crate::stats::Outcome;
+
+/// WASM-based plugin that runs periodically on a schedule
+pub struct WasmScheduledPlugin {
+    runtime: Arc<PluginRuntime>,
+    component: Component,
+    metadata: ScheduledTaskMetadata,
+    schedule: Schedule,
+}
+
+impl WasmScheduledPlugin {
+    /// Create a new WASM scheduled-task plugin. Fails if the schedule in
+    /// `metadata` cannot be parsed
+    pub fn new(
+        runtime: Arc<PluginRuntime>,
+        component: Component,
+        metadata: ScheduledTaskMetadata,
+    ) -> PluginResult<Self> {
+        let schedule = metadata.schedule.parse()?;
+        Ok(Self {
+            runtime,
+            component,
+            metadata,
+            schedule,
+        })
+    }
+
+    /// Load from bytes
+    pub fn from_bytes(
+        runtime: Arc<PluginRuntime>,
+        wasm_bytes: &[u8],
+        metadata: ScheduledTaskMetadata,
+    ) -> PluginResult<Self> {
+        // Check the schedule before spending time compiling
+        metadata.schedule.parse::<Schedule>()?;
+        let component = runtime.load_plugin(wasm_bytes)?;
+        Self::new(runtime, component, metadata)
+    }
+
+    /// Get plugin metadata
+    pub fn metadata(&self) -> &ScheduledTaskMetadata {
+        &self.metadata
+    }
+
+    /// When the plugin runs
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    /// Time the plugin may run per run. Scheduled tasks are off the
+    /// message path, so they get the budget they declare
+    fn budget(&self) -> Duration {
+        Duration::from_micros(self.metadata.max_execution_time_us)
+    }
+
+    /// Create a new plugin instance
+    async fn create_plugin_instance(
+        &self,
+    ) -> PluginResult<(Store<PluginState>, ScheduledTaskPlugin)> {
+        let mut store = self.runtime.create_store(&self.metadata.id, self.budget());
+        let mut linker = Linker::new(self.runtime.engine());
+
+        wasmtime_wasi::add_to_linker_async(&mut linker)
+            .map_err(|e| PluginError::InstantiationError(e.to_string()))?;
+        ScheduledTaskPlugin::add_to_linker(&mut linker, |state| state)
+            .map_err(|e| PluginError::InstantiationError(e.to_string()))?;
+
+        let plugin = ScheduledTaskPlugin::instantiate_async(&mut store, &self.component, &linker)
+            .await
+            .map_err(|e| PluginError::InstantiationError(e.to_string()))?;
+
+        Ok((store, plugin))
+    }
+
+    /// Instantiate the plugin once, to catch link errors before it is put
+    /// into service
+    pub async fn check(&self) -> PluginResult<()> {
+        self.create_plugin_instance().await.map(|_| ())
+    }
+
+    /// Run the task once
+    pub async fn run(&self) -> PluginResult<()> {
+        let start = Instant::now();
+
+        // Create new instance for this run
+        let (mut store, plugin) = self.create_plugin_instance().await?;
+
+        self.runtime.start_call(&mut store);
+        let result = plugin
+            .omnitak_plugin_scheduled_task()
+            .call_run(&mut store)
+            .await;
+
+        let (result, outcome) = match result {
+            Ok(Ok(())) => (Ok(()), Outcome::Completed),
+            Ok(Err(e)) => (Err(PluginError::ExecutionError(e)), Outcome::Failed),
+            Err(e) if is_budget_exceeded(&e) => (
+                Err(PluginError::Timeout(self.metadata.max_execution_time_us)),
+                Outcome::TimedOut,
+            ),
+            Err(e) => (Err(PluginError::ExecutionError(e.to_string())), Outcome::Failed),
+        };
+        self.runtime.metrics().record(
+            &self.metadata.id,
+            "scheduled",
+            start.elapsed(),
+            outcome,
+            store.data().memory_bytes(),
+        );
+        result
+    }
+}
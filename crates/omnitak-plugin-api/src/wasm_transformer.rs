@@ -1,9 +1,13 @@
 use std::sync::Arc;
-use wasmtime::component::Component;
+use std::time::Instant;
+use wasmtime::component::{Component, Linker};
+use wasmtime::Store;
 
-use crate::error::PluginResult;
+use crate::error::{PluginError, PluginResult};
 use crate::metadata::TransformerMetadata;
-use crate::runtime::PluginRuntime;
+use crate::runtime::{is_budget_exceeded, PluginRuntime, PluginState};
+use crate::stats::Outcome;
+use crate::transformer_bindings::TransformerPlugin;
 
 /// WASM-based message transformer plugin
 pub struct WasmTransformerPlugin {
@@ -51,11 +55,63 @@ impl WasmTransformerPlugin {
         &self.metadata
     }
 
+    /// Create a new plugin instance
+    async fn create_plugin_instance(
+        &self,
+    ) -> PluginResult<(Store<PluginState>, TransformerPlugin)> {
+        let mut store = self
+            .runtime
+            .create_message_path_store(&self.metadata.id, self.runtime.message_path_budget(None));
+        let mut linker = Linker::new(self.runtime.engine());
+
+        wasmtime_wasi::add_to_linker_async(&mut linker)
+            .map_err(|e| PluginError::InstantiationError(e.to_string()))?;
+        TransformerPlugin::add_to_linker(&mut linker, |state| state)
+            .map_err(|e| PluginError::InstantiationError(e.to_string()))?;
+
+        let plugin = TransformerPlugin::instantiate_async(&mut store, &self.component, &linker)
+            .await
+            .map_err(|e| PluginError::InstantiationError(e.to_string()))?;
+
+        Ok((store, plugin))
+    }
+
+    /// Instantiate the plugin once, to catch link errors before it is put
+    /// into service
+    pub async fn check(&self) -> PluginResult<()> {
+        self.create_plugin_instance().await.map(|_| ())
+    }
+
     /// Transform a message
     pub async fn transform(&self, data: &[u8]) -> PluginResult<Vec<u8>> {
-        // TODO: Implement WASM call
-        // For now, return unchanged data
-        Ok(data.to_vec())
+        let start = Instant::now();
+
+        // Create new instance for this message
+        let (mut store, plugin) = self.create_plugin_instance().await?;
+
+        self.runtime.start_call(&mut store);
+        let result = plugin
+            .omnitak_plugin_transformer()
+            .call_transform(&mut store, data)
+            .await;
+
+        let (result, outcome) = match result {
+            Ok(Ok(transformed)) => (Ok(transformed), Outcome::Completed),
+            Ok(Err(e)) => (Err(PluginError::ExecutionError(e)), Outcome::Failed),
+            Err(e) if is_budget_exceeded(&e) => {
+                let budget = self.runtime.message_path_budget(None);
+                (Err(PluginError::Timeout(budget.as_micros() as u64)), Outcome::TimedOut)
+            }
+            Err(e) => (Err(PluginError::ExecutionError(e.to_string())), Outcome::Failed),
+        };
+        self.runtime.metrics().record(
+            &self.metadata.id,
+            "transformer",
+            start.elapsed(),
+            outcome,
+            store.data().memory_bytes(),
+        );
+        result
     }
 
     /// Check if this transformer can handle a given CoT type
@@ -0,0 +1,106 @@
+//! Plugin directory watcher for hot reload
+//!
+//! Reports `.wasm` files in the plugin directory that were created,
+//! changed or removed, either directly or through their `.toml` metadata.
+//! Editors and build tools often write a file in several steps, so a path
+//! is only reported once it has been quiet for the debounce interval.
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use crate::error::PluginResult;
+
+/// Keeps the directory watch alive; dropping it stops change reports
+pub struct PluginDirWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+/// Watch `dir`, sending the path of each `.wasm` file that settled after a
+/// change. The file may no longer exist if the change was a removal
+pub fn watch_plugin_dir(
+    dir: &Path,
+    debounce: Duration,
+) -> PluginResult<(PluginDirWatcher, mpsc::Receiver<PathBuf>)> {
+    let (raw_tx, mut raw_rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            for path in event.paths {
+                let _ = raw_tx.send(path);
+            }
+        }
+    })?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    let (tx, rx) = mpsc::channel(64);
+    tokio::spawn(async move {
+        let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+        let mut tick = tokio::time::interval(debounce / 4);
+        loop {
+            tokio::select! {
+                path = raw_rx.recv() => {
+                    let Some(path) = path else { break };
+                    if let Some(wasm) = plugin_binary(&path) {
+                        pending.insert(wasm, Instant::now());
+                    }
+                }
+                _ = tick.tick() => {
+                    let settled: Vec<PathBuf> = pending
+                        .iter()
+                        .filter(|(_, changed)| changed.elapsed() >= debounce)
+                        .map(|(path, _)| path.clone())
+                        .collect();
+                    for path in settled {
+                        pending.remove(&path);
+                        if tx.send(path).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    });
+
+    Ok((PluginDirWatcher { _watcher: watcher }, rx))
+}
+
+/// The plugin binary a changed file belongs to
+fn plugin_binary(path: &Path) -> Option<PathBuf> {
+    match path.extension()?.to_str()? {
+        "wasm" => Some(path.to_path_buf()),
+        "toml" => Some(path.with_extension("wasm")),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_changes_are_debounced() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_watcher, mut changes) =
+            watch_plugin_dir(dir.path(), Duration::from_millis(200)).unwrap();
+
+        let wasm = dir.path().join("geofence.wasm");
+        for chunk in 0..3 {
+            std::fs::write(&wasm, vec![chunk; 16]).unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        std::fs::write(dir.path().join("geofence.toml"), "").unwrap();
+        std::fs::write(dir.path().join("README.md"), "").unwrap();
+
+        let changed = tokio::time::timeout(Duration::from_secs(5), changes.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(changed.file_name().unwrap(), "geofence.wasm");
+
+        // One report for the burst of writes
+        let again = tokio::time::timeout(Duration::from_millis(500), changes.recv()).await;
+        assert!(again.is_err());
+    }
+}
@@ -0,0 +1,168 @@
+//! Sample CoT messages
+//!
+//! Real-world messages as ATAK and WinTAK send them, for feeding plugin
+//! logic in tests. [`Sample::message`] turns one into the [`CotMessage`] a
+//! filter plugin receives for it.
+
+use quick_xml::events::{BytesStart, Event as XmlEvent};
+use quick_xml::Reader;
+
+use crate::filter::CotMessage;
+
+/// A sample CoT message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    pub name: &'static str,
+    pub xml: &'static str,
+}
+
+/// Position report from an ATAK end-user device, team Cyan
+pub const EUD_POSITION: Sample = Sample {
+    name: "eud_position",
+    xml: include_str!("../corpus/eud_position.xml"),
+};
+
+/// Hostile ground unit marked by an operator
+pub const HOSTILE_TRACK: Sample = Sample {
+    name: "hostile_track",
+    xml: include_str!("../corpus/hostile_track.xml"),
+};
+
+/// 911 emergency alert
+pub const EMERGENCY: Sample = Sample {
+    name: "emergency",
+    xml: include_str!("../corpus/emergency.xml"),
+};
+
+/// GeoChat message to All Chat Rooms
+pub const CHAT: Sample = Sample {
+    name: "chat",
+    xml: include_str!("../corpus/chat.xml"),
+};
+
+/// 9-line MEDEVAC request
+pub const MEDEVAC: Sample = Sample {
+    name: "medevac",
+    xml: include_str!("../corpus/medevac.xml"),
+};
+
+/// Route with three checkpoints
+pub const ROUTE: Sample = Sample {
+    name: "route",
+    xml: include_str!("../corpus/route.xml"),
+};
+
+/// Rectangle drawn on the map, pretty-printed as WinTAK sends it
+pub const SHAPE: Sample = Sample {
+    name: "shape",
+    xml: include_str!("../corpus/shape.xml"),
+};
+
+/// UAV video feed
+pub const VIDEO: Sample = Sample {
+    name: "video",
+    xml: include_str!("../corpus/video.xml"),
+};
+
+/// Every sample
+pub const ALL: &[Sample] = &[
+    EUD_POSITION,
+    HOSTILE_TRACK,
+    EMERGENCY,
+    CHAT,
+    MEDEVAC,
+    ROUTE,
+    SHAPE,
+    VIDEO,
+];
+
+/// Look up a sample by name
+pub fn get(name: &str) -> Option<Sample> {
+    ALL.iter().find(|s| s.name == name).copied()
+}
+
+impl Sample {
+    /// The message as OmniTAK hands it to filter plugins: the callsign from
+    /// `<contact>`, the `<__group>` name as both group and team, and no time
+    /// or XML payload
+    pub fn message(&self) -> CotMessage {
+        let mut msg = CotMessage {
+            cot_type: String::new(),
+            uid: String::new(),
+            callsign: None,
+            group: None,
+            team: None,
+            lat: 0.0,
+            lon: 0.0,
+            hae: None,
+            time: String::new(),
+            xml_payload: None,
+        };
+
+        let mut reader = Reader::from_str(self.xml);
+        loop {
+            let element = match reader.read_event() {
+                Ok(XmlEvent::Start(e)) | Ok(XmlEvent::Empty(e)) => e,
+                Ok(XmlEvent::Eof) => break,
+                Ok(_) => continue,
+                Err(e) => panic!("sample {} is not valid XML: {}", self.name, e),
+            };
+            match element.name().as_ref() {
+                b"event" => {
+                    msg.cot_type = attr(&element, "type").unwrap_or_default();
+                    msg.uid = attr(&element, "uid").unwrap_or_default();
+                }
+                b"point" => {
+                    let coord = |name| attr(&element, name).and_then(|v| v.parse().ok());
+                    msg.lat = coord("lat").unwrap_or_default();
+                    msg.lon = coord("lon").unwrap_or_default();
+                    // 9999999.0 means unknown
+                    msg.hae = coord("hae").filter(|hae: &f64| *hae < 9_999_999.0);
+                }
+                b"contact" => msg.callsign = attr(&element, "callsign"),
+                b"__group" => {
+                    msg.group = attr(&element, "name");
+                    msg.team = msg.group.clone();
+                }
+                _ => {}
+            }
+        }
+        msg
+    }
+}
+
+fn attr(element: &BytesStart<'_>, name: &str) -> Option<String> {
+    element
+        .try_get_attribute(name)
+        .ok()
+        .flatten()
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_messages() {
+        let msg = EUD_POSITION.message();
+        assert_eq!(msg.cot_type, "a-f-G-U-C");
+        assert_eq!(msg.uid, "ANDROID-589520ccfcd20f01");
+        assert_eq!(msg.callsign.as_deref(), Some("VIPER"));
+        assert_eq!(msg.team.as_deref(), Some("Cyan"));
+        assert_eq!(msg.lat, 38.8977);
+        assert_eq!(msg.hae, Some(21.3));
+
+        let route = ROUTE.message();
+        assert_eq!(route.cot_type, "b-m-r");
+        assert_eq!(route.hae, None);
+
+        for sample in ALL {
+            let msg = sample.message();
+            assert!(!msg.cot_type.is_empty(), "{}", sample.name);
+            assert!(!msg.uid.is_empty(), "{}", sample.name);
+        }
+        assert_eq!(get("medevac"), Some(MEDEVAC));
+    }
+}
@@ -0,0 +1,33 @@
+//! Records of the `omnitak:plugin/filter` interface
+//!
+//! Field for field the types `wit-bindgen` generates, so evaluation logic
+//! written against them compiles against the generated ones too.
+
+/// A CoT message as handed to a filter plugin's `evaluate`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CotMessage {
+    /// CoT event type (e.g., "a-f-G-E-V")
+    pub cot_type: String,
+    pub uid: String,
+    pub callsign: Option<String>,
+    pub group: Option<String>,
+    /// Team color (e.g., "Cyan")
+    pub team: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+    /// Height above ellipsoid in meters
+    pub hae: Option<f64>,
+    /// Message timestamp; OmniTAK currently passes it empty
+    pub time: String,
+    /// Full XML payload; OmniTAK currently does not pass it
+    pub xml_payload: Option<String>,
+}
+
+/// Result of filter evaluation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterResult {
+    /// Allow message to pass through
+    Pass,
+    /// Block message from being forwarded
+    Block,
+}
@@ -0,0 +1,94 @@
+//! Host functions of the `omnitak:plugin/host` interface
+//!
+//! Signatures match the ones `wit-bindgen` generates for plugins, so plugin
+//! code compiles against either. Every call goes to the [`MockHost`]
+//! installed on the calling thread and panics if there is none.
+
+use crate::mock::with_host;
+use crate::MockHost;
+
+/// Severity of a plugin log message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// Outbound HTTP request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+}
+
+/// Response to an [`HttpRequest`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// A 200 response with `body`
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: 200,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+}
+
+/// Log a message from the plugin
+pub fn log(level: LogLevel, message: &str) {
+    with_host(|host| host.log(level, message))
+}
+
+/// Current time in milliseconds since the epoch; see
+/// [`MockHost::with_time_ms`]
+pub fn get_current_time_ms() -> u64 {
+    with_host(MockHost::time_ms)
+}
+
+/// Elevation at a point, if the mock host has one
+pub fn query_elevation(lat: f64, lon: f64) -> Option<f64> {
+    with_host(|host| host.elevation(lat, lon))
+}
+
+/// Inject a CoT message. Fails like the real host if emitting is not
+/// allowed or `xml` is not a CoT event
+pub fn emit_cot(xml: &str) -> Result<(), String> {
+    with_host(|host| host.emit(xml))
+}
+
+/// Read a value from the plugin's key-value store
+pub fn kv_get(key: &str) -> Option<Vec<u8>> {
+    with_host(|host| host.kv_get(key))
+}
+
+/// Store a value; fails if the storage quota would be exceeded
+pub fn kv_set(key: &str, value: &[u8]) -> Result<(), String> {
+    with_host(|host| host.kv_set(key, value))
+}
+
+/// Delete a value, returning whether it existed
+pub fn kv_delete(key: &str) -> bool {
+    with_host(|host| host.kv_delete(key))
+}
+
+/// Read a setting; see [`MockHost::with_config`]
+pub fn get_config(key: &str) -> Option<String> {
+    with_host(|host| host.config(key))
+}
+
+/// Make an HTTP request, answered with a canned response; see
+/// [`MockHost::with_http_response`]
+pub fn http_fetch(request: &HttpRequest) -> Result<HttpResponse, String> {
+    with_host(|host| host.fetch(request))
+}
@@ -0,0 +1,47 @@
+//! Test harness for OmniTAK plugins
+//!
+//! Lets plugin authors unit-test filter, transformer and scheduled-task logic
+//! with `cargo test` on the build machine, without compiling to WASM or
+//! running OmniTAK:
+//!
+//! - [`host`] has the host functions of `wit/plugin.wit` with the signatures
+//!   `wit-bindgen` generates, backed by a [`MockHost`] instead of the runtime
+//! - [`MockHost`] captures logs, emitted CoT and HTTP requests, and serves
+//!   fake config, key-value storage, time, elevation and HTTP responses
+//! - [`filter`] mirrors the records of the `filter` interface
+//! - [`corpus`] is a set of sample CoT messages from ATAK and WinTAK
+//!
+//! Point the plugin's host import at [`host`] in tests:
+//!
+//! ```ignore
+//! #[cfg(not(test))]
+//! use crate::bindings::omnitak::plugin::host;
+//! #[cfg(test)]
+//! use omnitak_plugin_test::host;
+//! ```
+//!
+//! and install a mock host in each test:
+//!
+//! ```
+//! use omnitak_plugin_test::{corpus, host, MockHost};
+//!
+//! fn transform(xml: &str) -> Result<String, String> {
+//!     let tag = host::get_config("tag").unwrap_or_default();
+//!     host::log(host::LogLevel::Info, "tagging message");
+//!     Ok(xml.replace("<detail>", &format!("<detail><remarks>{}</remarks>", tag)))
+//! }
+//!
+//! let mock = MockHost::new().with_config("tag", "checked");
+//! let _guard = mock.install();
+//!
+//! let out = transform(corpus::EUD_POSITION.xml).unwrap();
+//! assert!(out.contains("<remarks>checked</remarks>"));
+//! assert!(mock.logged(host::LogLevel::Info, "tagging"));
+//! ```
+
+pub mod corpus;
+pub mod filter;
+pub mod host;
+mod mock;
+
+pub use mock::{LogRecord, MockHost, MockHostGuard};
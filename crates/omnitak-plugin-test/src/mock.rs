@@ -0,0 +1,412 @@
+use quick_xml::events::Event as XmlEvent;
+use quick_xml::Reader;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::host::{HttpRequest, HttpResponse, LogLevel};
+
+/// Storage quota the host gives plugins by default
+const DEFAULT_STORAGE_QUOTA_BYTES: u64 = 1024 * 1024;
+
+thread_local! {
+    static CURRENT: RefCell<Option<MockHost>> = const { RefCell::new(None) };
+}
+
+/// Run `f` on the mock host installed on this thread
+pub(crate) fn with_host<R>(f: impl FnOnce(&MockHost) -> R) -> R {
+    let host = CURRENT
+        .with(|current| current.borrow().clone())
+        .expect("no MockHost installed on this thread; call MockHost::install() first");
+    f(&host)
+}
+
+/// A message the plugin logged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    pub level: LogLevel,
+    pub message: String,
+}
+
+type Elevation = Box<dyn Fn(f64, f64) -> Option<f64> + Send>;
+
+struct State {
+    logs: Vec<LogRecord>,
+    config: HashMap<String, String>,
+    kv: BTreeMap<String, Vec<u8>>,
+    storage_quota_bytes: u64,
+    allow_emit: bool,
+    emitted: Vec<String>,
+    time_ms: Option<u64>,
+    elevation: Option<Elevation>,
+    /// Canned responses by URL
+    http: HashMap<String, Result<HttpResponse, String>>,
+    requests: Vec<HttpRequest>,
+}
+
+/// Stand-in for the OmniTAK host while testing a plugin natively
+///
+/// Configure it with the `with_*` methods, [`install`](Self::install) it on
+/// the test's thread, run the plugin code and check what it did with the
+/// inspection methods. Clones share state, so a test can keep its handle
+/// after installing. Defaults follow the real host: emitting CoT is allowed,
+/// storage is limited to 1 MiB, no settings are set and every HTTP request
+/// fails.
+#[derive(Clone)]
+pub struct MockHost {
+    state: Arc<Mutex<State>>,
+}
+
+impl Default for MockHost {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                logs: Vec::new(),
+                config: HashMap::new(),
+                kv: BTreeMap::new(),
+                storage_quota_bytes: DEFAULT_STORAGE_QUOTA_BYTES,
+                allow_emit: true,
+                emitted: Vec::new(),
+                time_ms: None,
+                elevation: None,
+                http: HashMap::new(),
+                requests: Vec::new(),
+            })),
+        }
+    }
+}
+
+impl MockHost {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // A panicking assertion in one test must not hide the state from
+        // the rest of it
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Make this the host for plugin calls on the current thread until the
+    /// guard is dropped
+    pub fn install(&self) -> MockHostGuard {
+        let previous = CURRENT.with(|current| current.replace(Some(self.clone())));
+        MockHostGuard {
+            previous,
+            _not_send: PhantomData,
+        }
+    }
+
+    // ------------------------------------------------------------------
+    // Configuration
+    // ------------------------------------------------------------------
+
+    /// Set a setting returned by `get-config`. As with the real host,
+    /// strings are returned as-is and other values as JSON text
+    pub fn with_config(self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        let value = match value.into() {
+            Value::String(s) => s,
+            other => other.to_string(),
+        };
+        self.state().config.insert(key.into(), value);
+        self
+    }
+
+    /// Put a value in the plugin's key-value store
+    pub fn with_kv(self, key: impl Into<String>, value: impl Into<Vec<u8>>) -> Self {
+        self.state().kv.insert(key.into(), value.into());
+        self
+    }
+
+    /// Limit keys and values together to `bytes`
+    pub fn with_storage_quota(self, bytes: u64) -> Self {
+        self.state().storage_quota_bytes = bytes;
+        self
+    }
+
+    /// Reject `emit-cot` calls, as a sandbox policy without
+    /// `allow_emit_cot` does
+    pub fn deny_emit(self) -> Self {
+        self.state().allow_emit = false;
+        self
+    }
+
+    /// Freeze the clock at `ms` since the epoch; otherwise the system time
+    /// is used
+    pub fn with_time_ms(self, ms: u64) -> Self {
+        self.set_time_ms(ms);
+        self
+    }
+
+    /// Answer elevation queries with `f`; otherwise there is no elevation
+    /// data
+    pub fn with_elevation(self, f: impl Fn(f64, f64) -> Option<f64> + Send + 'static) -> Self {
+        self.state().elevation = Some(Box::new(f));
+        self
+    }
+
+    /// Answer requests to `url` with `response`
+    pub fn with_http_response(self, url: impl Into<String>, response: HttpResponse) -> Self {
+        self.state().http.insert(url.into(), Ok(response));
+        self
+    }
+
+    /// Fail requests to `url` with `error`
+    pub fn with_http_error(self, url: impl Into<String>, error: impl Into<String>) -> Self {
+        self.state().http.insert(url.into(), Err(error.into()));
+        self
+    }
+
+    /// Move the frozen clock, e.g. between two calls of a scheduled task
+    pub fn set_time_ms(&self, ms: u64) {
+        self.state().time_ms = Some(ms);
+    }
+
+    // ------------------------------------------------------------------
+    // Inspection
+    // ------------------------------------------------------------------
+
+    /// Everything the plugin logged, oldest first
+    pub fn logs(&self) -> Vec<LogRecord> {
+        self.state().logs.clone()
+    }
+
+    /// Whether the plugin logged a message at `level` containing `text`
+    pub fn logged(&self, level: LogLevel, text: &str) -> bool {
+        self.state()
+            .logs
+            .iter()
+            .any(|r| r.level == level && r.message.contains(text))
+    }
+
+    /// CoT messages the plugin emitted, oldest first
+    pub fn emitted(&self) -> Vec<String> {
+        self.state().emitted.clone()
+    }
+
+    /// HTTP requests the plugin made, oldest first, including failed ones
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.state().requests.clone()
+    }
+
+    /// A value in the plugin's key-value store
+    pub fn stored(&self, key: &str) -> Option<Vec<u8>> {
+        self.state().kv.get(key).cloned()
+    }
+
+    /// Forget captured logs, emitted messages and requests
+    pub fn clear(&self) {
+        let mut state = self.state();
+        state.logs.clear();
+        state.emitted.clear();
+        state.requests.clear();
+    }
+
+    // ------------------------------------------------------------------
+    // Host functions
+    // ------------------------------------------------------------------
+
+    pub(crate) fn log(&self, level: LogLevel, message: &str) {
+        self.state().logs.push(LogRecord {
+            level,
+            message: message.to_string(),
+        });
+    }
+
+    pub(crate) fn time_ms(&self) -> u64 {
+        self.state().time_ms.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64
+        })
+    }
+
+    pub(crate) fn elevation(&self, lat: f64, lon: f64) -> Option<f64> {
+        self.state().elevation.as_ref().and_then(|f| f(lat, lon))
+    }
+
+    pub(crate) fn emit(&self, xml: &str) -> Result<(), String> {
+        let mut state = self.state();
+        if !state.allow_emit {
+            return Err("emit-cot is not allowed by the sandbox policy".to_string());
+        }
+        check_cot(xml).map_err(|e| format!("invalid CoT: {}", e))?;
+        state.emitted.push(xml.to_string());
+        Ok(())
+    }
+
+    pub(crate) fn kv_get(&self, key: &str) -> Option<Vec<u8>> {
+        self.stored(key)
+    }
+
+    pub(crate) fn kv_set(&self, key: &str, value: &[u8]) -> Result<(), String> {
+        let mut state = self.state();
+        let usage: u64 = state
+            .kv
+            .iter()
+            .filter(|(k, _)| k.as_str() != key)
+            .map(|(k, v)| entry_size(k, v))
+            .sum::<u64>()
+            + entry_size(key, value);
+        if usage > state.storage_quota_bytes {
+            return Err(format!(
+                "Plugin storage quota exceeded: {} > {} bytes",
+                usage, state.storage_quota_bytes
+            ));
+        }
+        state.kv.insert(key.to_string(), value.to_vec());
+        Ok(())
+    }
+
+    pub(crate) fn kv_delete(&self, key: &str) -> bool {
+        self.state().kv.remove(key).is_some()
+    }
+
+    pub(crate) fn config(&self, key: &str) -> Option<String> {
+        self.state().config.get(key).cloned()
+    }
+
+    pub(crate) fn fetch(&self, request: &HttpRequest) -> Result<HttpResponse, String> {
+        let mut state = self.state();
+        state.requests.push(request.clone());
+        state
+            .http
+            .get(&request.url)
+            .cloned()
+            .unwrap_or_else(|| Err(format!("no response configured for {}", request.url)))
+    }
+}
+
+/// Keeps a [`MockHost`] installed on the current thread; restores the
+/// previous one when dropped
+pub struct MockHostGuard {
+    previous: Option<MockHost>,
+    /// The host is per thread, so the guard must stay on this one
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for MockHostGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+fn entry_size(key: &str, value: &[u8]) -> u64 {
+    (key.len() + value.len()) as u64
+}
+
+/// Check that `xml` is a single well-formed `<event>` element
+fn check_cot(xml: &str) -> Result<(), String> {
+    let mut reader = Reader::from_str(xml);
+    let mut depth = 0usize;
+    let mut seen_event = false;
+    loop {
+        match reader.read_event().map_err(|e| e.to_string())? {
+            XmlEvent::Start(e) => {
+                if depth == 0 {
+                    if seen_event || e.name().as_ref() != b"event" {
+                        return Err("root element must be a single <event>".to_string());
+                    }
+                    seen_event = true;
+                }
+                depth += 1;
+            }
+            XmlEvent::Empty(e) if depth == 0 => {
+                if seen_event || e.name().as_ref() != b"event" {
+                    return Err("root element must be a single <event>".to_string());
+                }
+                seen_event = true;
+            }
+            XmlEvent::End(_) => depth = depth.saturating_sub(1),
+            XmlEvent::Eof => break,
+            _ => {}
+        }
+    }
+    if !seen_event {
+        return Err("no <event> element".to_string());
+    }
+    if depth != 0 {
+        return Err("unclosed element".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{corpus, host};
+
+    #[test]
+    fn test_mock_host_functions() {
+        let mock = MockHost::new()
+            .with_config("radius_km", 25.5)
+            .with_config("label", "north")
+            .with_time_ms(1_700_000_000_000)
+            .with_elevation(|_, _| Some(120.0))
+            .with_storage_quota(16)
+            .with_http_response("https://example.com/units", HttpResponse::ok("[]"));
+        let _guard = mock.install();
+
+        host::log(host::LogLevel::Warn, "radius is large");
+        assert!(mock.logged(host::LogLevel::Warn, "large"));
+        assert!(!mock.logged(host::LogLevel::Info, "large"));
+
+        assert_eq!(host::get_config("radius_km").as_deref(), Some("25.5"));
+        assert_eq!(host::get_config("label").as_deref(), Some("north"));
+        assert_eq!(host::get_config("missing"), None);
+        assert_eq!(host::get_current_time_ms(), 1_700_000_000_000);
+        assert_eq!(host::query_elevation(38.9, -77.0), Some(120.0));
+
+        assert!(host::kv_set("count", b"1").is_ok());
+        assert!(host::kv_set("too-long", b"0123456789").is_err());
+        assert_eq!(host::kv_get("count"), Some(b"1".to_vec()));
+        assert!(host::kv_delete("count"));
+        assert!(!host::kv_delete("count"));
+
+        let request = host::HttpRequest {
+            method: "GET".to_string(),
+            url: "https://example.com/units".to_string(),
+            headers: Vec::new(),
+            body: None,
+        };
+        assert_eq!(host::http_fetch(&request).unwrap().body, b"[]".to_vec());
+        let other = host::HttpRequest {
+            url: "https://example.com/other".to_string(),
+            ..request
+        };
+        assert!(host::http_fetch(&other).is_err());
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[test]
+    fn test_emit_cot() {
+        let mock = MockHost::new();
+        let _guard = mock.install();
+
+        assert!(host::emit_cot(corpus::EUD_POSITION.xml).is_ok());
+        assert!(host::emit_cot("<detail/>").is_err());
+        assert!(host::emit_cot("<event><point/>").is_err());
+        assert_eq!(mock.emitted().len(), 1);
+
+        let denied = MockHost::new().deny_emit();
+        let _guard = denied.install();
+        assert!(host::emit_cot(corpus::EUD_POSITION.xml).is_err());
+    }
+
+    #[test]
+    fn test_guard_restores_previous_host() {
+        let outer = MockHost::new().with_config("which", "outer");
+        let _outer = outer.install();
+        {
+            let inner = MockHost::new().with_config("which", "inner");
+            let _inner = inner.install();
+            assert_eq!(host::get_config("which").as_deref(), Some("inner"));
+        }
+        assert_eq!(host::get_config("which").as_deref(), Some("outer"));
+    }
+}
@@ -13,7 +13,7 @@
 use omnitak_pool::{
     AggregatorConfig, ConcurrencyConfig, ConcurrencyLimiter, ConnectionPool, DistributionStrategy,
     DistributorConfig, FilterRule, HealthConfig, HealthMonitor, InboundMessage, MessageAggregator,
-    MessageDistributor, MetricsConfig, MetricsRegistry, PoolConfig,
+    MessageDistributor, MetricsConfig, MetricsRegistry, PoolConfig, TraceId,
 };
 use std::sync::Arc;
 use std::time::Duration;
@@ -85,6 +85,7 @@ async fn main() -> anyhow::Result<()> {
             cleanup_interval: Duration::from_secs(10),
             channel_capacity: 10_000,
             worker_count: 4,
+            ..Default::default()
         },
     ));
 
@@ -215,6 +216,7 @@ async fn main() -> anyhow::Result<()> {
                     .into_bytes(),
                     source: "test-generator".to_string(),
                     timestamp: std::time::Instant::now(),
+                    trace_id: TraceId::new(),
                 };
 
                 if let Err(e) = aggregator_clone.sender().try_send(msg) {
@@ -232,6 +234,7 @@ async fn main() -> anyhow::Result<()> {
                     .into_bytes(),
                     source: "test-generator-2".to_string(),
                     timestamp: std::time::Instant::now(),
+                    trace_id: TraceId::new(),
                 };
 
                 let _ = aggregator_clone.sender().try_send(duplicate_msg);
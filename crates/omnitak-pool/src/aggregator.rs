@@ -1,21 +1,46 @@
 //! Message Aggregator
 //!
-//! Collects CoT messages from all sources, deduplicates by UID
-//! with time-based deduplication window, and forwards unique messages
-//! to the distributor.
+//! Collects CoT messages from all sources, deduplicates them within a
+//! time-based window, and forwards unique messages to the distributor.
+//! The dedup key is the UID by default; it can include the event time,
+//! the position or the whole message, and differ by CoT type (chat is
+//! never deduplicated by default). Unique messages are also checked for
+//! emergency beacons (see [`crate::emergency`]) and, when enabled, for
+//! impossible movement (see [`crate::anomaly`]), correlated across sources
+//! (see [`crate::fusion`]) and run through transformer plugins (see
+//! [`crate::transform`]). Under overload, low-priority traffic is shed
+//! before it is deduplicated (see [`crate::shedding`]), and with a hop
+//! limit on the distributor every message is stamped with this instance
+//! as a hop (see [`crate::hops`]). Position reports of fast-moving tracks
+//! can be coalesced to the newest per UID before distribution (see
+//! [`crate::coalescing`]). Messages from sources in strict mode that fail
+//! schema validation are quarantined instead of forwarded (see
+//! [`crate::quarantine`]).
 
 use anyhow::Result;
 use dashmap::DashMap;
 use flume::{Receiver, Sender};
-use std::collections::VecDeque;
+use omnitak_core::config::{DedupKey, DedupOverride};
+use omnitak_core::TraceId;
+use omnitak_filter::FilterRule;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::task::JoinHandle;
-use tracing::{debug, info, warn};
-
-use crate::distributor::{DistributionMessage, MessageDistributor};
+use tracing::{debug, debug_span, info, warn, Instrument};
+
+use crate::distributor::{with_cot_message, DistributionMessage, MessageDistributor};
+use crate::emergency::{EmergencyTracker, CLEARED_RETENTION};
+use crate::anomaly::AnomalyDetector;
+use crate::coalescing::PositionCoalescer;
+use crate::fusion::{FusionOutcome, TrackFusion};
+use crate::hops;
 use crate::metrics::AggregatorMetrics;
 use crate::pool::ConnectionId;
+use crate::quarantine::Quarantine;
+use crate::shedding::{self, LoadShedder};
+use crate::transform::{extract_type, TransformPipeline};
 
 /// Message unique identifier (extracted from CoT XML)
 pub type MessageUid = String;
@@ -46,6 +71,10 @@ pub struct AggregatorConfig {
     pub channel_capacity: usize,
     /// Number of aggregator workers
     pub worker_count: usize,
+    /// What makes two messages duplicates within the window
+    pub dedup_key: DedupKey,
+    /// Dedup keys for CoT types; the longest matching prefix wins
+    pub dedup_overrides: Vec<DedupOverride>,
 }
 
 impl Default for AggregatorConfig {
@@ -56,6 +85,39 @@ impl Default for AggregatorConfig {
             cleanup_interval: Duration::from_secs(10),
             channel_capacity: 10_000,
             worker_count: 4,
+            dedup_key: DedupKey::Uid,
+            dedup_overrides: vec![
+                DedupOverride::new("b-t-f", DedupKey::None),
+                DedupOverride::new("b-f-t", DedupKey::Content),
+            ],
+        }
+    }
+}
+
+impl AggregatorConfig {
+    /// Dedup key for a CoT type
+    pub fn dedup_key_for(&self, cot_type: Option<&str>) -> DedupKey {
+        cot_type
+            .and_then(|cot_type| {
+                self.dedup_overrides
+                    .iter()
+                    .filter(|o| cot_type.starts_with(o.type_prefix.as_str()))
+                    .max_by_key(|o| o.type_prefix.len())
+            })
+            .map_or(self.dedup_key, |o| o.key)
+    }
+}
+
+impl From<&omnitak_core::config::AggregatorConfig> for AggregatorConfig {
+    fn from(config: &omnitak_core::config::AggregatorConfig) -> Self {
+        Self {
+            dedup_window: config.dedup_window(),
+            max_cache_entries: config.max_cache_entries,
+            cleanup_interval: config.cleanup_interval(),
+            channel_capacity: config.channel_capacity,
+            worker_count: config.worker_count,
+            dedup_key: config.dedup_key,
+            dedup_overrides: config.dedup_overrides.clone(),
         }
     }
 }
@@ -69,6 +131,38 @@ pub struct InboundMessage {
     pub source: ConnectionId,
     /// Received timestamp
     pub timestamp: Instant,
+    /// Trace ID assigned at ingest, carried through to distribution
+    pub trace_id: TraceId,
+}
+
+/// Duplicate counts for one source connection
+///
+/// A source with a high [`duplicate_ratio`](Self::duplicate_ratio) mostly
+/// delivers what other sources already delivered: a redundant feed, or a
+/// federation peer echoing traffic.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SourceDuplicates {
+    pub source: ConnectionId,
+    /// Messages first seen from this source
+    pub unique: u64,
+    /// Messages another source delivered first
+    pub duplicates: u64,
+    /// Messages this source had already delivered itself
+    pub repeats: u64,
+    /// Duplicates by the source that delivered the message first
+    pub first_seen_from: HashMap<ConnectionId, u64>,
+}
+
+impl SourceDuplicates {
+    /// Fraction of this source's messages that another source delivered
+    /// first
+    pub fn duplicate_ratio(&self) -> f64 {
+        let total = self.unique + self.duplicates + self.repeats;
+        if total == 0 {
+            return 0.0;
+        }
+        self.duplicates as f64 / total as f64
+    }
 }
 
 /// Deduplication cache
@@ -77,6 +171,8 @@ pub struct InboundMessage {
 struct DeduplicationCache {
     /// Cache entries indexed by UID
     entries: DashMap<MessageUid, DeduplicationEntry>,
+    /// Duplicate counts by source
+    attribution: DashMap<ConnectionId, SourceDuplicates>,
     /// Entry queue for LRU eviction
     queue: Arc<parking_lot::Mutex<VecDeque<(MessageUid, Instant)>>>,
     /// Maximum cache size
@@ -89,6 +185,7 @@ impl DeduplicationCache {
     fn new(max_size: usize, window: Duration) -> Self {
         Self {
             entries: DashMap::new(),
+            attribution: DashMap::new(),
             queue: Arc::new(parking_lot::Mutex::new(VecDeque::new())),
             max_size,
             window,
@@ -106,9 +203,14 @@ impl DeduplicationCache {
             // Check if entry is still within deduplication window
             if entry.first_seen.elapsed() < self.window {
                 // Duplicate message - record source and return true
-                if !entry.sources.contains(&source) {
-                    entry.sources.push(source);
-                }
+                let first = if entry.sources.contains(&source) {
+                    source.clone()
+                } else {
+                    entry.sources.push(source.clone());
+                    entry.sources[0].clone()
+                };
+                drop(entry);
+                self.attribute(&source, Some(&first));
                 return true;
             } else {
                 // Entry expired - will be replaced
@@ -118,6 +220,7 @@ impl DeduplicationCache {
         }
 
         // Not a duplicate - add new entry
+        self.attribute(&source, None);
         let entry = DeduplicationEntry {
             uid: uid.clone(),
             first_seen: now,
@@ -141,6 +244,35 @@ impl DeduplicationCache {
         false
     }
 
+    /// Count a message from `source` that `first` delivered first (`source`
+    /// itself for a repeat), or that is new if there is no `first`
+    fn attribute(&self, source: &ConnectionId, first: Option<&ConnectionId>) {
+        let mut stats = match self.attribution.get_mut(source) {
+            Some(stats) => stats,
+            None => self
+                .attribution
+                .entry(source.clone())
+                .or_insert_with(|| SourceDuplicates {
+                    source: source.clone(),
+                    ..Default::default()
+                }),
+        };
+
+        match first {
+            None => stats.unique += 1,
+            Some(first) if first == source => stats.repeats += 1,
+            Some(first) => {
+                stats.duplicates += 1;
+                match stats.first_seen_from.get_mut(first) {
+                    Some(count) => *count += 1,
+                    None => {
+                        stats.first_seen_from.insert(first.clone(), 1);
+                    }
+                }
+            }
+        }
+    }
+
     /// Clean up expired entries
     fn cleanup(&self) {
         let now = Instant::now();
@@ -190,10 +322,30 @@ pub struct MessageAggregator {
     config: AggregatorConfig,
     /// Metrics
     metrics: Arc<AggregatorMetrics>,
+    /// Emergency beacon tracker
+    emergencies: Arc<EmergencyTracker>,
+    /// Strict validation of selected sources
+    quarantine: Arc<Quarantine>,
+    /// Cross-source track fusion, if enabled
+    fusion: Option<Arc<TrackFusion>>,
+    /// Impossible-movement detection, if enabled
+    anomalies: Option<Arc<AnomalyDetector>>,
+    /// Transformer plugins, if any
+    transformers: Option<Arc<TransformPipeline>>,
+    /// Messages must pass this to be forwarded, if set
+    filter: Option<Arc<dyn FilterRule>>,
+    /// Overload shedding, if enabled
+    shedder: Option<Arc<LoadShedder>>,
+    /// Position coalescing, if enabled
+    coalescer: Option<Arc<PositionCoalescer>>,
     /// Worker task handles
     workers: Arc<parking_lot::RwLock<Vec<JoinHandle<()>>>>,
     /// Cleanup task handle
     cleanup_task: Arc<parking_lot::RwLock<Option<JoinHandle<()>>>>,
+    /// Load sampling task handle
+    shedding_task: Arc<parking_lot::RwLock<Option<JoinHandle<()>>>>,
+    /// Coalesced position flush task handle
+    coalescing_task: Arc<parking_lot::RwLock<Option<JoinHandle<()>>>>,
 }
 
 impl MessageAggregator {
@@ -212,11 +364,71 @@ impl MessageAggregator {
             dedup_cache,
             config,
             metrics: Arc::new(AggregatorMetrics::new()),
+            emergencies: Arc::new(EmergencyTracker::new()),
+            quarantine: Arc::new(Quarantine::default()),
+            fusion: None,
+            anomalies: None,
+            transformers: None,
+            filter: None,
+            shedder: None,
+            coalescer: None,
             workers: Arc::new(parking_lot::RwLock::new(Vec::new())),
             cleanup_task: Arc::new(parking_lot::RwLock::new(None)),
+            shedding_task: Arc::new(parking_lot::RwLock::new(None)),
+            coalescing_task: Arc::new(parking_lot::RwLock::new(None)),
         }
     }
 
+    /// Share an emergency tracker with other aggregators, so beacons from
+    /// every pipeline are tracked in one place
+    pub fn with_emergency_tracker(mut self, emergencies: Arc<EmergencyTracker>) -> Self {
+        self.emergencies = emergencies;
+        self
+    }
+
+    /// Validate strict sources with this quarantine, for its buffer size
+    pub fn with_quarantine(mut self, quarantine: Arc<Quarantine>) -> Self {
+        self.quarantine = quarantine;
+        self
+    }
+
+    /// Correlate tracks reported by several sources into one fused track
+    pub fn with_fusion(mut self, fusion: Arc<TrackFusion>) -> Self {
+        self.fusion = Some(fusion);
+        self
+    }
+
+    /// Flag (and optionally quarantine) reports with impossible movement
+    pub fn with_anomaly_detector(mut self, anomalies: Arc<AnomalyDetector>) -> Self {
+        self.anomalies = Some(anomalies);
+        self
+    }
+
+    /// Run unique messages through transformer plugins before distribution
+    pub fn with_transformers(mut self, transformers: Arc<TransformPipeline>) -> Self {
+        self.transformers = Some(transformers);
+        self
+    }
+
+    /// Drop inbound messages the filter blocks, before deduplication.
+    /// Messages that are not CoT are not filtered
+    pub fn with_filter(mut self, filter: Arc<dyn FilterRule>) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Shed low-priority traffic when queues or CPU are overloaded
+    pub fn with_load_shedder(mut self, shedder: Arc<LoadShedder>) -> Self {
+        self.shedder = Some(shedder);
+        self
+    }
+
+    /// Forward only the newest position report per UID within a short window
+    pub fn with_coalescing(mut self, coalescer: Arc<PositionCoalescer>) -> Self {
+        self.coalescer = Some(coalescer);
+        self
+    }
+
     /// Get sender for submitting messages
     pub fn sender(&self) -> Sender<InboundMessage> {
         self.tx.clone()
@@ -225,7 +437,7 @@ impl MessageAggregator {
     /// Extract UID from CoT message
     ///
     /// Simplified extraction - real implementation would parse XML
-    fn extract_uid(data: &[u8]) -> Option<MessageUid> {
+    pub(crate) fn extract_uid(data: &[u8]) -> Option<MessageUid> {
         let msg_str = String::from_utf8_lossy(data);
 
         // Look for uid="..." in XML
@@ -249,21 +461,68 @@ impl MessageAggregator {
         hasher.finish()
     }
 
+    /// Dedup key and hash of a message, or `None` if its type is never
+    /// deduplicated. The hop stamp is left out, so copies that passed
+    /// through different numbers of aggregators match.
+    fn message_key(config: &AggregatorConfig, uid: &str, data: &[u8]) -> Option<(String, u64)> {
+        let data = hops::unstamped(data);
+        let hash = Self::calculate_hash(&data);
+        Some((Self::dedup_key(config, uid, &data, hash)?, hash))
+    }
+
+    /// Key a message is deduplicated by, or `None` if its type is never
+    /// deduplicated
+    fn dedup_key(config: &AggregatorConfig, uid: &str, data: &[u8], hash: u64) -> Option<String> {
+        let msg = String::from_utf8_lossy(data);
+        let key = match config.dedup_key_for(extract_type(data).as_deref()) {
+            DedupKey::Uid => uid.to_string(),
+            DedupKey::UidTime => {
+                let time = msg
+                    .find(" time=\"")
+                    .map(|start| &msg[start + 7..])
+                    .and_then(|rest| rest.find('"').map(|end| &rest[..end]))
+                    .unwrap_or_default();
+                format!("{}@{}", uid, time)
+            }
+            DedupKey::UidPoint => {
+                let point = msg
+                    .find("<point ")
+                    .map(|start| &msg[start..])
+                    .and_then(|rest| rest.find('>').map(|end| &rest[..end]))
+                    .unwrap_or_default();
+                format!("{}@{:016x}", uid, Self::calculate_hash(point.as_bytes()))
+            }
+            DedupKey::Content => format!("{}#{:016x}", uid, hash),
+            DedupKey::None => return None,
+        };
+        Some(key)
+    }
+
     /// Start the aggregator
     pub async fn start(&self) {
         info!("Starting message aggregator");
 
-        // Spawn worker tasks
-        let mut workers = self.workers.write();
+        // Spawn worker tasks, then store their handles
+        let mut handles = Vec::with_capacity(self.config.worker_count);
         for worker_id in 0..self.config.worker_count {
-            let handle = self.spawn_worker(worker_id).await;
-            workers.push(handle);
+            handles.push(self.spawn_worker(worker_id).await);
         }
+        self.workers.write().extend(handles);
 
         // Spawn cleanup task
         let cleanup_handle = self.spawn_cleanup_task().await;
         *self.cleanup_task.write() = Some(cleanup_handle);
 
+        if let Some(shedder) = &self.shedder {
+            let handle = self.spawn_shedding_task(Arc::clone(shedder));
+            *self.shedding_task.write() = Some(handle);
+        }
+
+        if let Some(coalescer) = &self.coalescer {
+            let handle = self.spawn_coalescing_task(Arc::clone(coalescer));
+            *self.coalescing_task.write() = Some(handle);
+        }
+
         info!(
             worker_count = self.config.worker_count,
             "Message aggregator started"
@@ -275,13 +534,58 @@ impl MessageAggregator {
         let rx = self.rx.clone();
         let distributor = Arc::clone(&self.distributor);
         let dedup_cache = Arc::clone(&self.dedup_cache);
+        let config = self.config.clone();
         let metrics = Arc::clone(&self.metrics);
+        let emergencies = Arc::clone(&self.emergencies);
+        let quarantine = Arc::clone(&self.quarantine);
+        let fusion = self.fusion.clone();
+        let anomalies = self.anomalies.clone();
+        let transformers = self.transformers.clone();
+        let filter = self.filter.clone();
+        let shedder = self.shedder.clone();
+        let coalescer = self.coalescer.clone();
+        let hop_limit = distributor.hop_limit();
 
         tokio::spawn(async move {
             debug!(worker_id, "Aggregator worker started");
 
             while let Ok(msg) = rx.recv_async().await {
                 metrics.record_message_received();
+                let trace_id = msg.trace_id;
+                debug!(worker_id, trace_id = %trace_id, source = %msg.source, "Message received");
+
+                if let Some(class) = shedder.as_ref().and_then(|s| s.shed(&msg.data)) {
+                    debug!(
+                        worker_id,
+                        trace_id = %trace_id,
+                        class = class.as_str(),
+                        "Message shed under overload",
+                    );
+                    continue;
+                }
+
+                if quarantine.inspect(&msg.data, &msg.source, trace_id) {
+                    continue;
+                }
+
+                let blocked = filter.as_ref().is_some_and(|filter| {
+                    with_cot_message(&msg.data, |cot| filter.evaluate(cot).is_block())
+                        .unwrap_or(false)
+                });
+                if blocked {
+                    metrics.record_filtered();
+                    debug!(worker_id, trace_id = %trace_id, "Message blocked by filters");
+                    continue;
+                }
+
+                // Count this instance as a hop before anything reads the data
+                let msg = match &hop_limit {
+                    Some(hop_limit) => InboundMessage {
+                        data: hop_limit.stamp(msg.data),
+                        ..msg
+                    },
+                    None => msg,
+                };
 
                 // Extract UID from message
                 let uid = match Self::extract_uid(&msg.data) {
@@ -290,6 +594,7 @@ impl MessageAggregator {
                         // No UID found - forward message anyway
                         debug!(
                             worker_id,
+                            trace_id = %trace_id,
                             "Message has no UID, forwarding without deduplication"
                         );
                         metrics.record_no_uid();
@@ -298,26 +603,35 @@ impl MessageAggregator {
                             data: msg.data,
                             source: Some(msg.source),
                             timestamp: msg.timestamp,
+                            bypass_filters: false,
+                            trace_id,
                         };
 
                         if let Err(e) = distributor.sender().send_async(dist_msg).await {
-                            warn!(worker_id, error = %e, "Failed to forward message to distributor");
+                            warn!(
+                                worker_id,
+                                trace_id = %trace_id,
+                                error = %e,
+                                "Failed to forward message to distributor",
+                            );
                         }
                         continue;
                     }
                 };
 
-                // Calculate message hash
-                let hash = Self::calculate_hash(&msg.data);
-
-                // Check for duplicate
-                let is_duplicate =
-                    dedup_cache.check_and_record(uid.clone(), msg.source.clone(), hash);
+                // Check for duplicate, unless the type is never deduplicated
+                let is_duplicate = match Self::message_key(&config, &uid, &msg.data) {
+                    Some((key, hash)) => {
+                        dedup_cache.check_and_record(key, msg.source.clone(), hash)
+                    }
+                    None => false,
+                };
 
                 if is_duplicate {
                     metrics.record_duplicate();
                     debug!(
                         worker_id,
+                        trace_id = %trace_id,
                         uid = %uid,
                         "Duplicate message detected, dropping"
                     );
@@ -326,17 +640,81 @@ impl MessageAggregator {
 
                 // Unique message - forward to distributor
                 metrics.record_unique();
+                if anomalies
+                    .as_ref()
+                    .is_some_and(|a| a.inspect(&msg.data, &msg.source))
+                {
+                    debug!(
+                        worker_id,
+                        trace_id = %trace_id,
+                        uid = %uid,
+                        "Anomalous report quarantined",
+                    );
+                    continue;
+                }
+                emergencies.inspect(&msg.data, &msg.source);
+
+                let data = match fusion.as_ref().map(|f| f.process(&msg.data, &msg.source)) {
+                    Some(FusionOutcome::Stale) => {
+                        debug!(
+                            worker_id,
+                            trace_id = %trace_id,
+                            uid = %uid,
+                            "Fused track already newer, dropping",
+                        );
+                        continue;
+                    }
+                    Some(FusionOutcome::Fused(data)) => data,
+                    _ => msg.data,
+                };
+                // Plugin logs carry the trace ID through the span
+                let data = match &transformers {
+                    Some(transformers) => {
+                        transformers
+                            .apply(data)
+                            .instrument(debug_span!("transform", trace_id = %trace_id))
+                            .await
+                    }
+                    None => data,
+                };
 
                 let dist_msg = DistributionMessage {
-                    data: msg.data,
+                    data,
                     source: Some(msg.source),
                     timestamp: msg.timestamp,
+                    bypass_filters: false,
+                    trace_id,
+                };
+                let dist_msg = match &coalescer {
+                    Some(coalescer) => match coalescer.offer(dist_msg) {
+                        Some(dist_msg) => dist_msg,
+                        None => {
+                            debug!(
+                                worker_id,
+                                trace_id = %trace_id,
+                                uid = %uid,
+                                "Position report held for coalescing",
+                            );
+                            continue;
+                        }
+                    },
+                    None => dist_msg,
                 };
 
                 if let Err(e) = distributor.sender().send_async(dist_msg).await {
-                    warn!(worker_id, error = %e, "Failed to forward message to distributor");
+                    warn!(
+                        worker_id,
+                        trace_id = %trace_id,
+                        error = %e,
+                        "Failed to forward message to distributor",
+                    );
                 } else {
-                    debug!(worker_id, uid = %uid, "Unique message forwarded to distributor");
+                    debug!(
+                        worker_id,
+                        trace_id = %trace_id,
+                        uid = %uid,
+                        "Unique message forwarded to distributor",
+                    );
                 }
             }
 
@@ -349,6 +727,9 @@ impl MessageAggregator {
         let dedup_cache = Arc::clone(&self.dedup_cache);
         let interval = self.config.cleanup_interval;
         let metrics = Arc::clone(&self.metrics);
+        let emergencies = Arc::clone(&self.emergencies);
+        let fusion = self.fusion.clone();
+        let anomalies = self.anomalies.clone();
 
         tokio::spawn(async move {
             debug!("Cleanup task started");
@@ -358,6 +739,13 @@ impl MessageAggregator {
 
                 let (before_entries, _) = dedup_cache.stats();
                 dedup_cache.cleanup();
+                emergencies.prune(CLEARED_RETENTION);
+                if let Some(fusion) = &fusion {
+                    fusion.prune();
+                }
+                if let Some(anomalies) = &anomalies {
+                    anomalies.prune();
+                }
                 let (after_entries, _) = dedup_cache.stats();
 
                 let cleaned = before_entries.saturating_sub(after_entries);
@@ -373,14 +761,70 @@ impl MessageAggregator {
         })
     }
 
+    /// Spawn the task that feeds queue and CPU pressure to the load shedder
+    fn spawn_shedding_task(&self, shedder: Arc<LoadShedder>) -> JoinHandle<()> {
+        let rx = self.rx.clone();
+        let distributor = Arc::clone(&self.distributor);
+        let capacity = self.queue_capacity();
+        let interval = shedder.config().sample_interval();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+
+                let queue_pressure = shedding::fill(rx.len(), capacity).max(shedding::fill(
+                    distributor.pending_count(),
+                    distributor.queue_capacity(),
+                ));
+                shedder.update(queue_pressure, shedder.sample_cpu());
+            }
+        })
+    }
+
+    /// Spawn the task that forwards coalesced position reports as their
+    /// windows end
+    fn spawn_coalescing_task(&self, coalescer: Arc<PositionCoalescer>) -> JoinHandle<()> {
+        let distributor = Arc::clone(&self.distributor);
+        let interval = coalescer.flush_interval();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+
+                for dist_msg in coalescer.flush() {
+                    let trace_id = dist_msg.trace_id;
+                    if let Err(e) = distributor.sender().send_async(dist_msg).await {
+                        warn!(
+                            trace_id = %trace_id,
+                            error = %e,
+                            "Failed to forward coalesced position to distributor",
+                        );
+                    }
+                }
+            }
+        })
+    }
+
     /// Stop the aggregator
     pub async fn stop(&self) {
         info!("Stopping message aggregator");
 
-        // Stop cleanup task
-        if let Some(task) = self.cleanup_task.write().take() {
-            task.abort();
-            let _ = task.await;
+        // Stop cleanup, load sampling and coalescing tasks
+        for task in [
+            &self.cleanup_task,
+            &self.shedding_task,
+            &self.coalescing_task,
+        ] {
+            if let Some(task) = task.write().take() {
+                task.abort();
+                let _ = task.await;
+            }
         }
 
         // Wait for all workers to finish
@@ -397,21 +841,74 @@ impl MessageAggregator {
         Arc::clone(&self.metrics)
     }
 
+    /// Get the track fusion stage, if enabled
+    pub fn fusion(&self) -> Option<Arc<TrackFusion>> {
+        self.fusion.clone()
+    }
+
+    /// Get the anomaly detector, if enabled
+    pub fn anomalies(&self) -> Option<Arc<AnomalyDetector>> {
+        self.anomalies.clone()
+    }
+
+    /// Get the transformer pipeline, if any
+    pub fn transformers(&self) -> Option<Arc<TransformPipeline>> {
+        self.transformers.clone()
+    }
+
+    /// Get the load shedder, if enabled
+    pub fn load_shedder(&self) -> Option<Arc<LoadShedder>> {
+        self.shedder.clone()
+    }
+
+    /// Get the position coalescing stage, if enabled
+    pub fn coalescer(&self) -> Option<Arc<PositionCoalescer>> {
+        self.coalescer.clone()
+    }
+
+    /// Get the emergency beacon tracker
+    pub fn emergencies(&self) -> Arc<EmergencyTracker> {
+        Arc::clone(&self.emergencies)
+    }
+
+    /// Get the strict validation quarantine
+    pub fn quarantine(&self) -> Arc<Quarantine> {
+        Arc::clone(&self.quarantine)
+    }
+
     /// Get pending message count
     pub fn pending_count(&self) -> usize {
         self.rx.len()
     }
 
+    /// Get the inbound queue capacity
+    pub fn queue_capacity(&self) -> usize {
+        self.rx.capacity().unwrap_or(usize::MAX)
+    }
+
     /// Get deduplication cache statistics
     pub fn cache_stats(&self) -> (usize, usize) {
         self.dedup_cache.stats()
     }
+
+    /// Duplicate counts per source connection since start
+    pub fn duplicate_stats(&self) -> Vec<SourceDuplicates> {
+        let mut stats: Vec<SourceDuplicates> = self
+            .dedup_cache
+            .attribution
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        stats.sort_by(|a, b| a.source.cmp(&b.source));
+        stats
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::distributor::DistributorConfig;
+    use crate::hops::{HopLimit, HopLimitConfig, HopStamp};
     use crate::pool::{ConnectionPool, PoolConfig};
 
     #[test]
@@ -464,6 +961,112 @@ mod tests {
         assert_eq!(entries, 2); // Only 2 entries due to LRU eviction
     }
 
+    #[test]
+    fn test_duplicate_attribution() {
+        let cache = DeduplicationCache::new(100, Duration::from_secs(60));
+
+        cache.check_and_record("uid-1".to_string(), "primary".to_string(), 1);
+        cache.check_and_record("uid-1".to_string(), "mirror".to_string(), 1);
+        cache.check_and_record("uid-2".to_string(), "primary".to_string(), 2);
+        cache.check_and_record("uid-2".to_string(), "mirror".to_string(), 2);
+        cache.check_and_record("uid-2".to_string(), "mirror".to_string(), 2);
+        cache.check_and_record("uid-3".to_string(), "mirror".to_string(), 3);
+
+        let mirror = cache.attribution.get("mirror").unwrap().clone();
+        assert_eq!(mirror.unique, 1);
+        assert_eq!(mirror.duplicates, 2);
+        assert_eq!(mirror.repeats, 1);
+        assert_eq!(mirror.first_seen_from["primary"], 2);
+        assert_eq!(mirror.duplicate_ratio(), 0.5);
+
+        let primary = cache.attribution.get("primary").unwrap().clone();
+        assert_eq!(primary.unique, 2);
+        assert_eq!(primary.duplicates, 0);
+    }
+
+    #[test]
+    fn test_dedup_key_strategies() {
+        let config = AggregatorConfig {
+            dedup_overrides: vec![
+                DedupOverride::new("a-", DedupKey::UidPoint),
+                DedupOverride::new("a-f-A", DedupKey::UidTime),
+                DedupOverride::new("b-t-f", DedupKey::None),
+            ],
+            ..Default::default()
+        };
+        let key = |data: &[u8]| {
+            let hash = MessageAggregator::calculate_hash(data);
+            MessageAggregator::dedup_key(&config, "U-1", data, hash)
+        };
+
+        // Longest prefix wins
+        assert_eq!(config.dedup_key_for(Some("a-f-G")), DedupKey::UidPoint);
+        assert_eq!(config.dedup_key_for(Some("a-f-A-M")), DedupKey::UidTime);
+        assert_eq!(config.dedup_key_for(Some("t-x-c")), DedupKey::Uid);
+        assert_eq!(config.dedup_key_for(None), DedupKey::Uid);
+
+        let here =
+            b"<event uid=\"U-1\" type=\"a-f-G\" time=\"t1\"><point lat=\"1\" lon=\"2\"/></event>";
+        let later =
+            b"<event uid=\"U-1\" type=\"a-f-G\" time=\"t2\"><point lat=\"1\" lon=\"2\"/></event>";
+        let moved =
+            b"<event uid=\"U-1\" type=\"a-f-G\" time=\"t2\"><point lat=\"1\" lon=\"3\"/></event>";
+        assert_eq!(key(here), key(later));
+        assert_ne!(key(here), key(moved));
+
+        let air =
+            b"<event uid=\"U-1\" type=\"a-f-A\" time=\"t1\"><point lat=\"1\" lon=\"2\"/></event>";
+        let air_later =
+            b"<event uid=\"U-1\" type=\"a-f-A\" time=\"t2\"><point lat=\"1\" lon=\"2\"/></event>";
+        assert_ne!(key(air), key(air_later));
+
+        assert_eq!(key(b"<event uid=\"U-1\" type=\"b-t-f\"/>"), None);
+
+        let content = AggregatorConfig {
+            dedup_key: DedupKey::Content,
+            dedup_overrides: Vec::new(),
+            ..Default::default()
+        };
+        let key = |data: &[u8]| {
+            let hash = MessageAggregator::calculate_hash(data);
+            MessageAggregator::dedup_key(&content, "U-1", data, hash)
+        };
+        assert_eq!(key(here), key(here));
+        assert_ne!(key(here), key(later));
+    }
+
+    #[test]
+    fn test_dedup_ignores_hop_count() {
+        let event = b"<event uid=\"U-1\" type=\"a-f-G\" time=\"t1\"><point lat=\"1\" lon=\"2\"/><detail/></event>";
+        let hop = |instance_id: &str| {
+            HopLimit::new(HopLimitConfig {
+                instance_id: Some(instance_id.to_string()),
+                max_hops: 8,
+            })
+        };
+        // The same event, stamped here after arriving directly and after
+        // passing through two other aggregators
+        let here = hop("here");
+        let direct = here.stamp(event.to_vec());
+        let relayed = here.stamp(hop("site-b").stamp(hop("site-a").stamp(event.to_vec())));
+        assert_eq!(HopStamp::read(&relayed).unwrap().count, 2);
+
+        for dedup_key in [DedupKey::Uid, DedupKey::Content] {
+            let config = AggregatorConfig {
+                dedup_key,
+                dedup_overrides: Vec::new(),
+                ..Default::default()
+            };
+            let cache = DeduplicationCache::new(100, Duration::from_secs(60));
+            let check = |data: &[u8], source: &str| {
+                let (key, hash) = MessageAggregator::message_key(&config, "U-1", data).unwrap();
+                cache.check_and_record(key, source.to_string(), hash)
+            };
+            assert!(!check(&direct, "peer-a"));
+            assert!(check(&relayed, "peer-b"));
+        }
+    }
+
     #[tokio::test]
     async fn test_aggregator_creation() {
         let pool = Arc::new(ConnectionPool::new(PoolConfig::default()));
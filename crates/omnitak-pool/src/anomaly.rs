@@ -0,0 +1,373 @@
+//! Track Anomaly Detection
+//!
+//! Flags position reports that no real unit could produce, which usually
+//! means a spoofed or corrupted feed:
+//!
+//! - **teleport**: the implied ground speed since the previous report is
+//!   above `max_speed_mps`
+//! - **altitude**: the height is outside `min_altitude_m..=max_altitude_m`,
+//!   or the implied climb or descent rate is above `max_climb_rate_mps`
+//!
+//! Every anomaly is broadcast so the event bus can raise warnings. With
+//! `quarantine` enabled the aggregator also drops the offending report.
+//! Anomalous reports do not move the reference position, so one bad fix
+//! does not also flag the next good one; after `RELOCATE_AFTER` anomalies
+//! in a row the newest report is taken as the unit's real position.
+//!
+//! Only atom (`a-*`) events are checked.
+
+use chrono::{DateTime, Utc};
+use omnitak_cot::geodesy::distance_m;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::pool::ConnectionId;
+
+/// Consecutive anomalies after which a track is treated as relocated
+const RELOCATE_AFTER: u32 = 3;
+
+/// CoT `hae` used for "unknown"
+const UNKNOWN_HAE: f64 = 9_999_999.0;
+
+/// Anomaly detection settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnomalyConfig {
+    /// Highest believable ground speed
+    #[serde(default = "default_max_speed_mps")]
+    pub max_speed_mps: f64,
+    /// Jumps shorter than this are never flagged (GPS noise)
+    #[serde(default = "default_min_distance_m")]
+    pub min_distance_m: f64,
+    /// Highest believable climb or descent rate
+    #[serde(default = "default_max_climb_rate_mps")]
+    pub max_climb_rate_mps: f64,
+    /// Height changes smaller than this are never flagged
+    #[serde(default = "default_min_altitude_change_m")]
+    pub min_altitude_change_m: f64,
+    /// Lowest believable height above the ellipsoid
+    #[serde(default = "default_min_altitude_m")]
+    pub min_altitude_m: f64,
+    /// Highest believable height above the ellipsoid
+    #[serde(default = "default_max_altitude_m")]
+    pub max_altitude_m: f64,
+    /// Drop anomalous reports instead of only warning
+    #[serde(default)]
+    pub quarantine: bool,
+    /// Forget tracks not updated for this long
+    #[serde(default = "default_track_ttl_secs")]
+    pub track_ttl_secs: u64,
+}
+
+fn default_max_speed_mps() -> f64 {
+    // About Mach 1.2; faster than any aircraft a TAK network tracks
+    400.0
+}
+
+fn default_min_distance_m() -> f64 {
+    100.0
+}
+
+fn default_max_climb_rate_mps() -> f64 {
+    150.0
+}
+
+fn default_min_altitude_change_m() -> f64 {
+    100.0
+}
+
+fn default_min_altitude_m() -> f64 {
+    -500.0
+}
+
+fn default_max_altitude_m() -> f64 {
+    30_000.0
+}
+
+fn default_track_ttl_secs() -> u64 {
+    300
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        Self {
+            max_speed_mps: default_max_speed_mps(),
+            min_distance_m: default_min_distance_m(),
+            max_climb_rate_mps: default_max_climb_rate_mps(),
+            min_altitude_change_m: default_min_altitude_change_m(),
+            min_altitude_m: default_min_altitude_m(),
+            max_altitude_m: default_max_altitude_m(),
+            quarantine: false,
+            track_ttl_secs: default_track_ttl_secs(),
+        }
+    }
+}
+
+/// What was wrong with a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    /// Implied ground speed too high
+    Teleport,
+    /// Height out of range
+    Altitude,
+    /// Implied climb or descent rate too high
+    ClimbRate,
+}
+
+impl AnomalyKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Teleport => "teleport",
+            Self::Altitude => "altitude",
+            Self::ClimbRate => "climb_rate",
+        }
+    }
+}
+
+/// A flagged report
+#[derive(Debug, Clone, Serialize)]
+pub struct Anomaly {
+    pub uid: String,
+    pub kind: AnomalyKind,
+    pub callsign: Option<String>,
+    /// Connection the report came from
+    pub source: ConnectionId,
+    pub lat: f64,
+    pub lon: f64,
+    /// Offending value: m/s for teleport and climb rate, meters for altitude
+    pub value: f64,
+    /// Configured limit the value broke
+    pub limit: f64,
+    /// Whether the report was dropped
+    pub quarantined: bool,
+}
+
+/// Last accepted fix of a track
+struct LastFix {
+    lat: f64,
+    lon: f64,
+    hae: Option<f64>,
+    time: DateTime<Utc>,
+    updated: Instant,
+    /// Anomalies in a row since the last accepted fix
+    consecutive: u32,
+}
+
+/// Checks position reports for physically impossible movement
+pub struct AnomalyDetector {
+    config: AnomalyConfig,
+    tracks: Mutex<HashMap<String, LastFix>>,
+    events: broadcast::Sender<Anomaly>,
+}
+
+impl AnomalyDetector {
+    pub fn new(config: AnomalyConfig) -> Self {
+        Self {
+            config,
+            tracks: Mutex::new(HashMap::new()),
+            events: broadcast::channel(256).0,
+        }
+    }
+
+    /// Receive every anomaly as it is detected
+    pub fn subscribe(&self) -> broadcast::Receiver<Anomaly> {
+        self.events.subscribe()
+    }
+
+    /// Check a report; returns `true` if it should be quarantined
+    pub fn inspect(&self, data: &[u8], source: &ConnectionId) -> bool {
+        if !data.windows(8).any(|w| w == b"type=\"a-") {
+            return false;
+        }
+        let Ok(event) = omnitak_cot::parser::parse_any(data) else {
+            return false;
+        };
+        if !event.event_type.starts_with("a-") {
+            return false;
+        }
+
+        let (lat, lon) = (event.point.lat, event.point.lon);
+        let hae = (event.point.hae.abs() < UNKNOWN_HAE).then_some(event.point.hae);
+        let ttl = Duration::from_secs(self.config.track_ttl_secs);
+
+        let mut tracks = self.tracks.lock();
+        let anomaly = match tracks.get(&event.uid) {
+            Some(last) if last.updated.elapsed() <= ttl => {
+                // Out-of-order reports carry no speed information
+                if event.time <= last.time {
+                    return false;
+                }
+                self.check(last, lat, lon, hae, event.time)
+            }
+            _ => hae.and_then(|hae| self.check_altitude(hae)),
+        };
+
+        let relocated = match tracks.get_mut(&event.uid) {
+            Some(last) if anomaly.is_some() => {
+                last.consecutive += 1;
+                last.consecutive >= RELOCATE_AFTER
+            }
+            _ => true,
+        };
+        if relocated || anomaly.is_none() {
+            tracks.insert(
+                event.uid.clone(),
+                LastFix {
+                    lat,
+                    lon,
+                    hae,
+                    time: event.time,
+                    updated: Instant::now(),
+                    consecutive: 0,
+                },
+            );
+        }
+        drop(tracks);
+
+        let Some((kind, value, limit)) = anomaly else {
+            return false;
+        };
+        let anomaly = Anomaly {
+            uid: event.uid.clone(),
+            kind,
+            callsign: event.callsign().map(str::to_string),
+            source: source.clone(),
+            lat,
+            lon,
+            value,
+            limit,
+            quarantined: self.config.quarantine,
+        };
+        warn!(
+            uid = %anomaly.uid,
+            source = %anomaly.source,
+            kind = anomaly.kind.label(),
+            value = anomaly.value,
+            limit = anomaly.limit,
+            quarantined = anomaly.quarantined,
+            "Track anomaly detected"
+        );
+        // No subscribers is fine
+        let _ = self.events.send(anomaly);
+        self.config.quarantine
+    }
+
+    /// Compare a report against the previous accepted fix
+    fn check(
+        &self,
+        last: &LastFix,
+        lat: f64,
+        lon: f64,
+        hae: Option<f64>,
+        time: DateTime<Utc>,
+    ) -> Option<(AnomalyKind, f64, f64)> {
+        let secs = ((time - last.time).num_milliseconds() as f64 / 1000.0).max(0.001);
+
+        let distance = distance_m(last.lat, last.lon, lat, lon);
+        let speed = distance / secs;
+        if distance > self.config.min_distance_m && speed > self.config.max_speed_mps {
+            return Some((AnomalyKind::Teleport, speed, self.config.max_speed_mps));
+        }
+
+        let hae = hae?;
+        if let Some(anomaly) = self.check_altitude(hae) {
+            return Some(anomaly);
+        }
+        let climb = (hae - last.hae?).abs();
+        let rate = climb / secs;
+        if climb > self.config.min_altitude_change_m && rate > self.config.max_climb_rate_mps {
+            return Some((AnomalyKind::ClimbRate, rate, self.config.max_climb_rate_mps));
+        }
+        None
+    }
+
+    fn check_altitude(&self, hae: f64) -> Option<(AnomalyKind, f64, f64)> {
+        if hae > self.config.max_altitude_m {
+            Some((AnomalyKind::Altitude, hae, self.config.max_altitude_m))
+        } else if hae < self.config.min_altitude_m {
+            Some((AnomalyKind::Altitude, hae, self.config.min_altitude_m))
+        } else {
+            None
+        }
+    }
+
+    /// Number of tracks being followed
+    pub fn track_count(&self) -> usize {
+        self.tracks.lock().len()
+    }
+
+    /// Forget tracks that have not reported within the TTL
+    pub fn prune(&self) {
+        let ttl = Duration::from_secs(self.config.track_ttl_secs);
+        self.tracks
+            .lock()
+            .retain(|_, last| last.updated.elapsed() <= ttl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(lat: f64, hae: f64, time: &str) -> Vec<u8> {
+        format!(
+            r#"<event version="2.0" uid="UNIT-1" type="a-f-A-M-F" time="{time}" start="{time}" stale="2030-01-01T00:00:00Z" how="m-g"><point lat="{lat}" lon="-117.2" hae="{hae}" ce="10" le="10"/><detail><contact callsign="VIPER-1"/></detail></event>"#
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_teleport_quarantine_and_recovery() {
+        let detector = AnomalyDetector::new(AnomalyConfig {
+            quarantine: true,
+            ..Default::default()
+        });
+        let mut events = detector.subscribe();
+        let source = "tak-main".to_string();
+
+        assert!(!detector.inspect(&report(34.0, 100.0, "2026-01-01T00:00:00Z"), &source));
+        // ~1.1 km in 10 s is a fast jet, not a teleport
+        assert!(!detector.inspect(&report(34.01, 100.0, "2026-01-01T00:00:10Z"), &source));
+
+        // ~111 km in 10 s is
+        assert!(detector.inspect(&report(35.01, 100.0, "2026-01-01T00:00:20Z"), &source));
+        let anomaly = events.try_recv().unwrap();
+        assert_eq!(anomaly.kind, AnomalyKind::Teleport);
+        assert_eq!(anomaly.callsign.as_deref(), Some("VIPER-1"));
+        assert!(anomaly.quarantined);
+
+        // The spoofed fix did not move the reference
+        assert!(!detector.inspect(&report(34.02, 100.0, "2026-01-01T00:00:30Z"), &source));
+
+        // A unit that really moved is accepted after repeated anomalies
+        for (i, time) in ["00:01:00", "00:01:10", "00:01:20"].iter().enumerate() {
+            let time = format!("2026-01-01T{}Z", time);
+            assert!(detector.inspect(&report(36.0 + i as f64 * 0.001, 100.0, &time), &source));
+        }
+        assert!(!detector.inspect(&report(36.003, 100.0, "2026-01-01T00:01:30Z"), &source));
+    }
+
+    #[test]
+    fn test_altitude() {
+        let detector = AnomalyDetector::new(AnomalyConfig::default());
+        let mut events = detector.subscribe();
+        let source = "tak-main".to_string();
+
+        // Out of range, but only warned about without quarantine
+        assert!(!detector.inspect(&report(34.0, 90_000.0, "2026-01-01T00:00:00Z"), &source));
+        assert_eq!(events.try_recv().unwrap().kind, AnomalyKind::Altitude);
+
+        // Unknown height is ignored
+        assert!(!detector.inspect(&report(34.0, 9_999_999.0, "2026-01-01T00:00:05Z"), &source));
+        assert!(events.try_recv().is_err());
+
+        assert!(!detector.inspect(&report(34.0, 1_000.0, "2026-01-01T00:00:10Z"), &source));
+        // 5 km climb in 5 s
+        assert!(!detector.inspect(&report(34.0, 6_000.0, "2026-01-01T00:00:15Z"), &source));
+        assert_eq!(events.try_recv().unwrap().kind, AnomalyKind::ClimbRate);
+    }
+}
@@ -0,0 +1,262 @@
+//! Position Coalescing
+//!
+//! Fast-moving tracks can report several times a second, and every report
+//! goes out to every connection. With coalescing enabled the aggregator
+//! forwards the first position report of a UID right away and holds later
+//! ones for `window_ms`: when the window ends, only the newest held report
+//! is forwarded and a new window starts. Tracks reporting less often than
+//! once per window are not delayed at all.
+//!
+//! Only position reports (`a-*` events that aren't emergencies) are
+//! coalesced; every other event type is forwarded immediately.
+
+use metrics::{counter, describe_counter, describe_gauge, gauge};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::aggregator::MessageAggregator;
+use crate::distributor::DistributionMessage;
+use crate::shedding::TrafficClass;
+
+/// Position coalescing settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoalescingConfig {
+    /// Forward at most one position report per UID this often
+    #[serde(default = "default_window_ms")]
+    pub window_ms: u64,
+}
+
+fn default_window_ms() -> u64 {
+    1000
+}
+
+impl Default for CoalescingConfig {
+    fn default() -> Self {
+        Self {
+            window_ms: default_window_ms(),
+        }
+    }
+}
+
+impl CoalescingConfig {
+    pub fn window(&self) -> Duration {
+        Duration::from_millis(self.window_ms)
+    }
+}
+
+/// Position reports handled since start
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CoalescingStats {
+    /// Reports forwarded, immediately or at the end of a window
+    pub forwarded: u64,
+    /// Reports replaced by a newer one before they were forwarded
+    pub coalesced: u64,
+    /// Tracks with an open window
+    pub tracks: usize,
+}
+
+/// Open window of one track
+struct Window {
+    until: Instant,
+    /// Newest report received during the window
+    pending: Option<DistributionMessage>,
+}
+
+/// Keeps only the newest position report per UID within a window
+pub struct PositionCoalescer {
+    config: CoalescingConfig,
+    windows: Mutex<HashMap<String, Window>>,
+    forwarded: AtomicU64,
+    coalesced: AtomicU64,
+}
+
+impl PositionCoalescer {
+    pub fn new(config: CoalescingConfig) -> Self {
+        describe();
+
+        Self {
+            config,
+            windows: Mutex::new(HashMap::new()),
+            forwarded: AtomicU64::new(0),
+            coalesced: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &CoalescingConfig {
+        &self.config
+    }
+
+    /// How often [`flush`](Self::flush) should run: a quarter of the window
+    pub fn flush_interval(&self) -> Duration {
+        (self.config.window() / 4).max(Duration::from_millis(10))
+    }
+
+    /// Offer a message on its way to the distributor
+    ///
+    /// Returns the message if it is to be forwarded now. Otherwise it is
+    /// held until its track's window ends, replacing any report held before.
+    pub fn offer(&self, msg: DistributionMessage) -> Option<DistributionMessage> {
+        if TrafficClass::of(&msg.data) != TrafficClass::Position {
+            return Some(msg);
+        }
+        let Some(uid) = MessageAggregator::extract_uid(&msg.data) else {
+            return Some(msg);
+        };
+
+        let now = Instant::now();
+        let mut windows = self.windows.lock();
+        match windows.get_mut(&uid) {
+            Some(window) if now < window.until => {
+                if window.pending.replace(msg).is_some() {
+                    self.coalesced.fetch_add(1, Ordering::Relaxed);
+                    counter!("coalesced_messages_total").increment(1);
+                }
+                None
+            }
+            _ => {
+                windows.insert(
+                    uid,
+                    Window {
+                        until: now + self.config.window(),
+                        pending: None,
+                    },
+                );
+                self.forwarded.fetch_add(1, Ordering::Relaxed);
+                Some(msg)
+            }
+        }
+    }
+
+    /// Take the held report of every track whose window ended
+    ///
+    /// Tracks that had a report held start a new window; tracks that went
+    /// quiet are forgotten.
+    pub fn flush(&self) -> Vec<DistributionMessage> {
+        let now = Instant::now();
+        let window = self.config.window();
+        let mut flushed = Vec::new();
+
+        let mut windows = self.windows.lock();
+        windows.retain(|_, track| {
+            if now < track.until {
+                return true;
+            }
+            match track.pending.take() {
+                Some(msg) => {
+                    flushed.push(msg);
+                    track.until = now + window;
+                    true
+                }
+                None => false,
+            }
+        });
+        gauge!("coalescing_tracks").set(windows.len() as f64);
+        drop(windows);
+
+        self.forwarded.fetch_add(flushed.len() as u64, Ordering::Relaxed);
+        flushed
+    }
+
+    pub fn stats(&self) -> CoalescingStats {
+        CoalescingStats {
+            forwarded: self.forwarded.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+            tracks: self.windows.lock().len(),
+        }
+    }
+}
+
+pub(crate) fn describe() {
+    describe_counter!(
+        "coalesced_messages_total",
+        "Position reports replaced by a newer report of the same UID before forwarding"
+    );
+    describe_gauge!(
+        "coalescing_tracks",
+        "Tracks with an open position coalescing window"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use omnitak_core::TraceId;
+
+    fn message(xml: &str) -> DistributionMessage {
+        DistributionMessage {
+            data: xml.as_bytes().to_vec(),
+            source: Some("tak-1".to_string()),
+            timestamp: Instant::now(),
+            bypass_filters: false,
+            trace_id: TraceId::new(),
+        }
+    }
+
+    fn position(uid: &str, lat: u32) -> DistributionMessage {
+        message(&format!(
+            "<event uid=\"{}\" type=\"a-f-A\"><point lat=\"{}\" lon=\"0\"/></event>",
+            uid, lat
+        ))
+    }
+
+    fn coalescer(window_ms: u64) -> PositionCoalescer {
+        PositionCoalescer::new(CoalescingConfig { window_ms })
+    }
+
+    #[test]
+    fn test_newest_report_kept() {
+        let coalescer = coalescer(60_000);
+
+        assert!(coalescer.offer(position("U-1", 1)).is_some());
+        assert!(coalescer.offer(position("U-1", 2)).is_none());
+        assert!(coalescer.offer(position("U-1", 3)).is_none());
+        // Other tracks have their own window
+        assert!(coalescer.offer(position("U-2", 1)).is_some());
+        // Window still open
+        assert!(coalescer.flush().is_empty());
+
+        let stats = coalescer.stats();
+        assert_eq!(stats.forwarded, 2);
+        assert_eq!(stats.coalesced, 1);
+        assert_eq!(stats.tracks, 2);
+    }
+
+    #[test]
+    fn test_flush_after_window() {
+        let coalescer = coalescer(20);
+
+        assert!(coalescer.offer(position("U-1", 1)).is_some());
+        assert!(coalescer.offer(position("U-1", 2)).is_none());
+        assert!(coalescer.offer(position("U-1", 3)).is_none());
+        std::thread::sleep(Duration::from_millis(30));
+
+        let flushed = coalescer.flush();
+        assert_eq!(flushed.len(), 1);
+        assert!(String::from_utf8_lossy(&flushed[0].data).contains("lat=\"3\""));
+
+        // A new window started with the flush; once it ends with nothing
+        // held, the track is forgotten
+        assert!(coalescer.offer(position("U-1", 4)).is_none());
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(coalescer.flush().len(), 1);
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(coalescer.flush().is_empty());
+        assert_eq!(coalescer.stats().tracks, 0);
+    }
+
+    #[test]
+    fn test_other_types_pass() {
+        let coalescer = coalescer(60_000);
+        let chat = "<event uid=\"U-1\" type=\"b-t-f\"><detail/></event>";
+        let emergency = "<event uid=\"U-1\" type=\"a-f-G\"><detail><emergency/></detail></event>";
+
+        for _ in 0..3 {
+            assert!(coalescer.offer(message(chat)).is_some());
+            assert!(coalescer.offer(message(emergency)).is_some());
+        }
+        assert_eq!(coalescer.stats().tracks, 0);
+    }
+}
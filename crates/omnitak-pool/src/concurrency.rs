@@ -2,10 +2,16 @@
 //!
 //! Limits max concurrent connections, implements connection queue,
 //! priority queue for critical connections, and semaphore-based rate limiting.
+//!
+//! Queued requests age: every `priority_aging` spent waiting raises a
+//! request's effective priority by one, so a steady stream of
+//! high-priority requests can delay a low-priority one but never starve
+//! it. Time spent queued is recorded in the
+//! `concurrency_queue_wait_seconds` histogram.
 
 use anyhow::{Context, Result};
+use metrics::{describe_histogram, histogram};
 use std::cmp::Reverse;
-use std::collections::BinaryHeap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::{Semaphore, SemaphorePermit};
@@ -42,6 +48,18 @@ impl ConnectionRequest {
             requested_at: Instant::now(),
         }
     }
+
+    /// Priority after aging: one more for every `aging` spent queued
+    pub fn effective_priority(&self, now: Instant, aging: Option<Duration>) -> u32 {
+        let aged = aging.filter(|a| !a.is_zero()).map_or(0, |aging| {
+            now.saturating_duration_since(self.requested_at).as_nanos() / aging.as_nanos()
+        });
+        u32::from(self.priority).saturating_add(aged.min(u32::MAX as u128) as u32)
+    }
+
+    fn is(&self, key: &(ConnectionId, Instant)) -> bool {
+        self.id == key.0 && self.requested_at == key.1
+    }
 }
 
 impl PartialEq for ConnectionRequest {
@@ -77,8 +95,12 @@ pub struct ConcurrencyConfig {
     pub max_queue_size: usize,
     /// Queue timeout (requests older than this are rejected)
     pub queue_timeout: Duration,
-    /// Queue processing interval
+    /// How often a waiting [`ConcurrencyLimiter::acquire_queued`] checks
+    /// whether it is its turn
     pub processing_interval: Duration,
+    /// Queue time that raises a request's priority by one; `None` keeps
+    /// strict priority order, where low priorities can starve
+    pub priority_aging: Option<Duration>,
     /// Enable rate limiting
     pub enable_rate_limit: bool,
     /// Rate limit: max operations per second
@@ -92,6 +114,7 @@ impl Default for ConcurrencyConfig {
             max_queue_size: 1_000,
             queue_timeout: Duration::from_secs(30),
             processing_interval: Duration::from_millis(100),
+            priority_aging: Some(Duration::from_secs(1)),
             enable_rate_limit: false,
             rate_limit_ops_per_sec: 1000,
         }
@@ -123,8 +146,8 @@ pub struct LimiterStats {
 pub struct ConcurrencyLimiter {
     /// Semaphore for connection slots
     semaphore: Arc<Semaphore>,
-    /// Priority queue for pending requests
-    queue: Arc<parking_lot::Mutex<BinaryHeap<ConnectionRequest>>>,
+    /// Pending requests; picked by aged priority, so kept unordered
+    queue: Arc<parking_lot::Mutex<Vec<ConnectionRequest>>>,
     /// Configuration
     config: ConcurrencyConfig,
     /// Rate limiter semaphore
@@ -156,6 +179,8 @@ impl LimiterStatistics {
 impl ConcurrencyLimiter {
     /// Create a new concurrency limiter
     pub fn new(config: ConcurrencyConfig) -> Self {
+        describe();
+
         let semaphore = Arc::new(Semaphore::new(config.max_concurrent));
 
         let rate_limiter = if config.enable_rate_limit {
@@ -168,7 +193,7 @@ impl ConcurrencyLimiter {
 
         Self {
             semaphore,
-            queue: Arc::new(parking_lot::Mutex::new(BinaryHeap::new())),
+            queue: Arc::new(parking_lot::Mutex::new(Vec::new())),
             config,
             rate_limiter,
             rate_limiter_task: Arc::new(parking_lot::RwLock::new(None)),
@@ -251,6 +276,50 @@ impl ConcurrencyLimiter {
             .context("Failed to acquire semaphore permit")
     }
 
+    /// Wait in the queue for a connection permit
+    ///
+    /// Unlike [`acquire`](Self::acquire), which takes whichever permit
+    /// frees up first, queued requests get permits in aged priority order:
+    /// a free permit goes to the request [`dequeue`](Self::dequeue) would
+    /// pick. Fails if the queue is full or the request waits longer than
+    /// `queue_timeout`. Dropping the future gives up the place in the
+    /// queue.
+    pub async fn acquire_queued(&self, request: ConnectionRequest) -> Result<SemaphorePermit<'_>> {
+        let slot = QueueSlot {
+            limiter: self,
+            key: (request.id.clone(), request.requested_at),
+        };
+        self.enqueue(request)?;
+
+        let mut ticker = tokio::time::interval(self.config.processing_interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+
+            let now = Instant::now();
+            let mut queue = self.queue.lock();
+            self.expire(&mut queue, now);
+            let Some(index) = queue.iter().position(|r| r.is(&slot.key)) else {
+                anyhow::bail!("Timeout waiting in connection queue");
+            };
+            if self.next_index(&queue, now) != Some(index) {
+                continue;
+            }
+            let Ok(permit) = self.semaphore.try_acquire() else {
+                continue;
+            };
+
+            let request = queue.swap_remove(index);
+            drop(queue);
+            record_wait(&request, now, "granted");
+            self.stats
+                .accepted
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            return Ok(permit);
+        }
+    }
+
     /// Acquire rate limit permit if enabled
     pub async fn acquire_rate_limit(&self) -> Result<Option<SemaphorePermit<'_>>> {
         if let Some(rate_limiter) = &self.rate_limiter {
@@ -292,31 +361,51 @@ impl ConcurrencyLimiter {
         Ok(())
     }
 
-    /// Dequeue the highest priority request
+    /// Dequeue the request with the highest aged priority, oldest first
+    /// among equals
     pub fn dequeue(&self) -> Option<ConnectionRequest> {
         let mut queue = self.queue.lock();
 
         // Remove expired requests
         let now = Instant::now();
-        let mut temp_queue = BinaryHeap::new();
+        self.expire(&mut queue, now);
 
-        while let Some(req) = queue.pop() {
-            if now.duration_since(req.requested_at) <= self.config.queue_timeout {
-                temp_queue.push(req);
-            } else {
-                self.stats
-                    .timeouts
-                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                debug!(
-                    connection_id = %req.id,
-                    "Connection request timed out in queue"
-                );
-            }
-        }
+        let request = queue.swap_remove(self.next_index(&queue, now)?);
+        record_wait(&request, now, "dequeued");
+        Some(request)
+    }
 
-        *queue = temp_queue;
+    /// Index of the request that goes next
+    fn next_index(&self, queue: &[ConnectionRequest], now: Instant) -> Option<usize> {
+        let aging = self.config.priority_aging;
+        queue
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, req)| {
+                (
+                    req.effective_priority(now, aging),
+                    Reverse(req.requested_at),
+                )
+            })
+            .map(|(index, _)| index)
+    }
 
-        queue.pop()
+    /// Drop requests queued longer than the queue timeout
+    fn expire(&self, queue: &mut Vec<ConnectionRequest>, now: Instant) {
+        queue.retain(|req| {
+            if now.duration_since(req.requested_at) <= self.config.queue_timeout {
+                return true;
+            }
+            self.stats
+                .timeouts
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            record_wait(req, now, "timed_out");
+            debug!(
+                connection_id = %req.id,
+                "Connection request timed out in queue"
+            );
+            false
+        });
     }
 
     /// Get the number of queued requests
@@ -357,6 +446,38 @@ impl ConcurrencyLimiter {
     }
 }
 
+/// Place of a [`ConcurrencyLimiter::acquire_queued`] caller in the queue,
+/// given up when the caller stops waiting
+struct QueueSlot<'a> {
+    limiter: &'a ConcurrencyLimiter,
+    key: (ConnectionId, Instant),
+}
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.limiter.queue.lock().retain(|req| !req.is(&self.key));
+    }
+}
+
+fn record_wait(request: &ConnectionRequest, now: Instant, outcome: &'static str) {
+    histogram!(
+        "concurrency_queue_wait_seconds",
+        "priority" => request.priority.to_string(),
+        "outcome" => outcome
+    )
+    .record(
+        now.saturating_duration_since(request.requested_at)
+            .as_secs_f64(),
+    );
+}
+
+pub(crate) fn describe() {
+    describe_histogram!(
+        "concurrency_queue_wait_seconds",
+        "Time connection requests spent queued, by requested priority and outcome"
+    );
+}
+
 /// Connection permit guard
 ///
 /// Wrapper that holds both connection and rate limit permits
@@ -507,4 +628,75 @@ mod tests {
         assert_eq!(stats.active_connections, 2);
         assert_eq!(stats.available_permits, 8);
     }
+
+    #[tokio::test]
+    async fn test_priority_aging() {
+        let high = ConnectionRequest::new(
+            "high".to_string(),
+            "High Priority".to_string(),
+            "localhost:8088".to_string(),
+            10,
+        );
+        let mut low = ConnectionRequest::new(
+            "low".to_string(),
+            "Low Priority".to_string(),
+            "localhost:8087".to_string(),
+            1,
+        );
+        low.requested_at = Instant::now() - Duration::from_secs(20);
+
+        // Twenty seconds of aging outweighs the priority gap
+        let limiter = ConcurrencyLimiter::new(ConcurrencyConfig::default());
+        limiter.enqueue(high.clone()).unwrap();
+        limiter.enqueue(low.clone()).unwrap();
+        assert_eq!(limiter.dequeue().unwrap().id, "low");
+
+        let limiter = ConcurrencyLimiter::new(ConcurrencyConfig {
+            priority_aging: None,
+            ..Default::default()
+        });
+        limiter.enqueue(high).unwrap();
+        limiter.enqueue(low).unwrap();
+        assert_eq!(limiter.dequeue().unwrap().id, "high");
+    }
+
+    #[tokio::test]
+    async fn test_acquire_queued() {
+        let limiter = ConcurrencyLimiter::new(ConcurrencyConfig {
+            max_concurrent: 1,
+            processing_interval: Duration::from_millis(10),
+            ..Default::default()
+        });
+        let request = |id: &str| {
+            ConnectionRequest::new(
+                id.to_string(),
+                "Queued".to_string(),
+                "localhost:8087".to_string(),
+                5,
+            )
+        };
+
+        let held = limiter.acquire().await.unwrap();
+        let waiter = limiter.acquire_queued(request("first"));
+        tokio::pin!(waiter);
+        assert!(tokio::time::timeout(Duration::from_millis(50), &mut waiter)
+            .await
+            .is_err());
+        assert_eq!(limiter.queue_len(), 1);
+
+        drop(held);
+        let permit = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(limiter.queue_len(), 0);
+
+        // Giving up leaves the queue
+        let waiter = limiter.acquire_queued(request("second"));
+        assert!(tokio::time::timeout(Duration::from_millis(50), waiter)
+            .await
+            .is_err());
+        assert_eq!(limiter.queue_len(), 0);
+        drop(permit);
+    }
 }
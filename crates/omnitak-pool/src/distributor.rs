@@ -3,17 +3,41 @@
 //! Receives CoT messages from any source, applies filters to determine
 //! destinations, and distributes to relevant connections with backpressure
 //! handling for slow consumers.
+//!
+//! With route plugins configured, each message is also evaluated against a
+//! [`RouteTable`]; connections a matching route names as destinations get
+//! the message after that route's transformer plugins have run on it.
+//!
+//! With a hop limit set, messages that looped back to this instance or
+//! passed through too many aggregators are dropped before distribution
+//! (see [`crate::hops`]).
+//!
+//! Connections that declared a wire format other than XML get each message
+//! converted to it (see [`crate::format`]).
 
 use anyhow::{Context, Result};
+use dashmap::DashMap;
 use flume::{Receiver, Sender};
+use omnitak_cot::parse_cot_borrowed;
+use omnitak_cot::parser::{detect_protocol, parse_any, Protocol};
+use omnitak_filter::{CotMessage, RouteTable, RoutingResult};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::task::JoinHandle;
-use tracing::{debug, error, info, warn};
+use omnitak_core::TraceId;
+use tracing::{debug, debug_span, error, info, warn, Instrument};
 
+use crate::aggregator::MessageAggregator;
+use crate::format::{self, WireFormat};
+use crate::hops::HopLimit;
 use crate::metrics::DistributorMetrics;
 use crate::pool::{ConnectionId, ConnectionPool, PoolMessage};
+use crate::sink::{EventSink, SinkBatchConfig, SinkHandle, SinkRecord, SinkStats};
+use crate::smoothing::TrackSmoother;
+use crate::tap::{MessageTap, TapDestination, TapOutcome, TapRecord};
+use crate::transform::TransformPipeline;
 
 /// Filter rule for message distribution
 #[derive(Clone)]
@@ -30,6 +54,8 @@ pub enum FilterRule {
     ByGeoBounds { lat: f64, lon: f64, radius_km: f64 },
     /// Custom filter function
     Custom(Arc<dyn Fn(&[u8]) -> bool + Send + Sync>),
+    /// Drop messages the function matches
+    Block(Arc<dyn Fn(&[u8]) -> bool + Send + Sync>),
 }
 
 impl std::fmt::Debug for FilterRule {
@@ -50,6 +76,7 @@ impl std::fmt::Debug for FilterRule {
                 .field("radius_km", radius_km)
                 .finish(),
             Self::Custom(_) => write!(f, "Custom(<function>)"),
+            Self::Block(_) => write!(f, "Block(<function>)"),
         }
     }
 }
@@ -74,7 +101,47 @@ impl FilterRule {
                 // Simplified for now
                 true
             }
-            FilterRule::Custom(func) => func(message),
+            FilterRule::Custom(func) | FilterRule::Block(func) => func(message),
+        }
+    }
+}
+
+/// Transformer plugins and transform profiles routes apply to the traffic
+/// to their destinations
+pub struct RoutePlugins {
+    routes: Arc<RouteTable>,
+    transformers: Arc<TransformPipeline>,
+}
+
+impl RoutePlugins {
+    /// Run the plugins `routes` assign from `transformers`, then the routes'
+    /// transform profiles. Destinations are connection IDs or names
+    pub fn new(routes: Arc<RouteTable>, transformers: Arc<TransformPipeline>) -> Self {
+        Self {
+            routes,
+            transformers,
+        }
+    }
+
+    /// Route a message, if it is CoT any route could match
+    fn route(&self, data: &[u8]) -> Option<RoutingResult> {
+        let result = with_cot_message(data, |msg| self.routes.route(msg))?;
+        (!result.plugins.is_empty() || !result.transforms.is_empty() || !result.smoothed.is_empty())
+            .then_some(result)
+    }
+}
+
+/// Run `f` on the filter view of a message, if it is CoT. XML is read
+/// without copying its strings
+pub(crate) fn with_cot_message<R>(data: &[u8], f: impl FnOnce(&CotMessage) -> R) -> Option<R> {
+    match detect_protocol(data).ok()? {
+        Protocol::Xml => {
+            let event = parse_cot_borrowed(std::str::from_utf8(data).ok()?).ok()?;
+            Some(f(&CotMessage::from(&event)))
+        }
+        Protocol::Mesh | Protocol::Stream => {
+            let event = parse_any(data).ok()?;
+            Some(f(&CotMessage::from(&event)))
         }
     }
 }
@@ -123,6 +190,25 @@ impl Default for DistributorConfig {
     }
 }
 
+impl From<&omnitak_core::config::DistributorConfig> for DistributorConfig {
+    fn from(config: &omnitak_core::config::DistributorConfig) -> Self {
+        use omnitak_core::config::DistributionStrategy as Strategy;
+        Self {
+            channel_capacity: config.channel_capacity,
+            strategy: match config.strategy {
+                Strategy::DropOnFull => DistributionStrategy::DropOnFull,
+                Strategy::BlockOnFull => DistributionStrategy::BlockOnFull,
+                Strategy::TryForTimeout => {
+                    DistributionStrategy::TryForTimeout(config.send_timeout())
+                }
+            },
+            max_workers: config.max_workers,
+            batch_size: config.batch_size,
+            flush_interval: config.flush_interval(),
+        }
+    }
+}
+
 /// Message to be distributed
 #[derive(Debug, Clone)]
 pub struct DistributionMessage {
@@ -132,6 +218,10 @@ pub struct DistributionMessage {
     pub source: Option<ConnectionId>,
     /// Timestamp when received
     pub timestamp: Instant,
+    /// Deliver to every connection regardless of filter rules
+    pub bypass_filters: bool,
+    /// Trace ID assigned at ingest
+    pub trace_id: TraceId,
 }
 
 /// Message Distributor
@@ -151,6 +241,19 @@ pub struct MessageDistributor {
     config: DistributorConfig,
     /// Metrics
     metrics: Arc<DistributorMetrics>,
+    /// Output sinks that receive every message
+    sinks: Arc<parking_lot::RwLock<Vec<Arc<SinkHandle>>>>,
+    /// Messages matched by a content filter, per connection, since last taken
+    filter_matches: Arc<DashMap<ConnectionId, AtomicU64>>,
+    /// Position smoothing for smoothing routes and selected connections, if
+    /// enabled
+    smoother: Option<Arc<TrackSmoother>>,
+    /// Per-route transformer plugins, if configured
+    route_plugins: Option<Arc<RoutePlugins>>,
+    /// Loop and hop count checks, if enabled
+    hop_limit: Option<Arc<HopLimit>>,
+    /// Live routing decisions for debugging
+    tap: Arc<MessageTap>,
     /// Worker task handles
     workers: Arc<parking_lot::RwLock<Vec<JoinHandle<()>>>>,
 }
@@ -167,10 +270,53 @@ impl MessageDistributor {
             tx,
             config,
             metrics: Arc::new(DistributorMetrics::new()),
+            sinks: Arc::new(parking_lot::RwLock::new(Vec::new())),
+            filter_matches: Arc::new(DashMap::new()),
+            smoother: None,
+            route_plugins: None,
+            hop_limit: None,
+            tap: Arc::new(MessageTap::new()),
             workers: Arc::new(parking_lot::RwLock::new(Vec::new())),
         }
     }
 
+    /// Send smoothed positions to the destinations of smoothing routes and
+    /// the connections the smoother selects
+    pub fn with_smoothing(mut self, smoother: Arc<TrackSmoother>) -> Self {
+        self.smoother = Some(smoother);
+        self
+    }
+
+    /// Run transformer plugins on the connections routes assign them to
+    pub fn with_route_plugins(mut self, route_plugins: RoutePlugins) -> Self {
+        self.route_plugins = Some(Arc::new(route_plugins));
+        self
+    }
+
+    /// Drop messages that looped back or passed through too many
+    /// aggregators. The aggregator feeding this distributor stamps
+    /// messages for it at ingest.
+    pub fn with_hop_limit(mut self, hop_limit: Arc<HopLimit>) -> Self {
+        self.hop_limit = Some(hop_limit);
+        self
+    }
+
+    /// Get the hop limit, if enabled
+    pub fn hop_limit(&self) -> Option<Arc<HopLimit>> {
+        self.hop_limit.clone()
+    }
+
+    /// Get the track smoothing stage, if enabled
+    pub fn smoother(&self) -> Option<Arc<TrackSmoother>> {
+        self.smoother.clone()
+    }
+
+    /// Get the traffic tap, which reports routing decisions to its
+    /// subscribers
+    pub fn tap(&self) -> Arc<MessageTap> {
+        Arc::clone(&self.tap)
+    }
+
     /// Get sender for submitting messages
     pub fn sender(&self) -> Sender<DistributionMessage> {
         self.tx.clone()
@@ -197,16 +343,42 @@ impl MessageDistributor {
         filters.insert(connection_id, rules);
     }
 
+    /// Add an output sink that receives every distributed message
+    pub fn add_sink(&self, sink: Arc<dyn EventSink>, config: SinkBatchConfig) {
+        info!(sink = sink.name(), "Output sink added");
+        self.sinks
+            .write()
+            .push(Arc::new(SinkHandle::spawn(sink, config)));
+    }
+
+    /// Delivery statistics for each output sink
+    pub fn sink_stats(&self) -> Vec<SinkStats> {
+        self.sinks.read().iter().map(|sink| sink.stats()).collect()
+    }
+
+    /// Take the number of messages each connection's content filters
+    /// (anything but `AlwaysSend`) matched since the previous call
+    pub fn take_filter_matches(&self) -> Vec<(ConnectionId, u64)> {
+        self.filter_matches
+            .iter()
+            .filter_map(|entry| {
+                let count = entry.value().swap(0, Ordering::Relaxed);
+                (count > 0).then(|| (entry.key().clone(), count))
+            })
+            .collect()
+    }
+
     /// Start the distributor
     pub async fn start(&self) {
         info!("Starting message distributor");
 
-        // Spawn worker tasks
-        let mut workers = self.workers.write();
+        // Spawn worker tasks. The lock is only taken afterwards: a guard
+        // held across the awaits would make this future !Send.
+        let mut handles = Vec::with_capacity(self.config.max_workers);
         for worker_id in 0..self.config.max_workers {
-            let handle = self.spawn_worker(worker_id).await;
-            workers.push(handle);
+            handles.push(self.spawn_worker(worker_id).await);
         }
+        self.workers.write().extend(handles);
 
         info!(
             worker_count = self.config.max_workers,
@@ -220,6 +392,12 @@ impl MessageDistributor {
         let pool = Arc::clone(&self.pool);
         let filters = Arc::clone(&self.filters);
         let metrics = Arc::clone(&self.metrics);
+        let sinks = Arc::clone(&self.sinks);
+        let filter_matches = Arc::clone(&self.filter_matches);
+        let smoother = self.smoother.clone();
+        let route_plugins = self.route_plugins.clone();
+        let hop_limit = self.hop_limit.clone();
+        let tap = Arc::clone(&self.tap);
         let config = self.config.clone();
 
         tokio::spawn(async move {
@@ -241,8 +419,20 @@ impl MessageDistributor {
                         if batch.len() >= config.batch_size
                             || last_flush.elapsed() >= config.flush_interval
                         {
-                            Self::distribute_batch(&pool, &filters, &metrics, &config, &mut batch)
-                                .await;
+                            Self::distribute_batch(
+                                &pool,
+                                &filters,
+                                &sinks,
+                                &filter_matches,
+                                smoother.as_deref(),
+                                route_plugins.as_deref(),
+                                hop_limit.as_deref(),
+                                &tap,
+                                &metrics,
+                                &config,
+                                &mut batch,
+                            )
+                            .await;
                             last_flush = Instant::now();
                         }
                     }
@@ -253,8 +443,20 @@ impl MessageDistributor {
                     Err(_elapsed) => {
                         // Timeout - flush any pending messages
                         if !batch.is_empty() {
-                            Self::distribute_batch(&pool, &filters, &metrics, &config, &mut batch)
-                                .await;
+                            Self::distribute_batch(
+                                &pool,
+                                &filters,
+                                &sinks,
+                                &filter_matches,
+                                smoother.as_deref(),
+                                route_plugins.as_deref(),
+                                hop_limit.as_deref(),
+                                &tap,
+                                &metrics,
+                                &config,
+                                &mut batch,
+                            )
+                            .await;
                             last_flush = Instant::now();
                         }
                     }
@@ -266,9 +468,16 @@ impl MessageDistributor {
     }
 
     /// Distribute a batch of messages
+    #[allow(clippy::too_many_arguments)]
     async fn distribute_batch(
         pool: &Arc<ConnectionPool>,
         filters: &Arc<parking_lot::RwLock<HashMap<ConnectionId, Vec<FilterRule>>>>,
+        sinks: &Arc<parking_lot::RwLock<Vec<Arc<SinkHandle>>>>,
+        filter_matches: &DashMap<ConnectionId, AtomicU64>,
+        smoother: Option<&TrackSmoother>,
+        route_plugins: Option<&RoutePlugins>,
+        hop_limit: Option<&HopLimit>,
+        tap: &MessageTap,
         metrics: &Arc<DistributorMetrics>,
         config: &DistributorConfig,
         batch: &mut Vec<DistributionMessage>,
@@ -288,11 +497,65 @@ impl MessageDistributor {
             let filter_map = filters.read();
             filter_map.clone()
         }; // filter_map guard is dropped here
+        let sinks: Vec<Arc<SinkHandle>> = sinks.read().clone();
 
         for msg in batch.drain(..) {
             metrics.record_message_received();
 
+            if let Some(Err(violation)) = hop_limit.map(|h| h.check(&msg.data)) {
+                metrics.record_drop();
+                debug!(
+                    trace_id = %msg.trace_id,
+                    reason = violation.as_str(),
+                    "Message dropped by hop limit"
+                );
+                continue;
+            }
+
+            // Sinks get every message, regardless of source or filters
+            if !sinks.is_empty() {
+                let key = MessageAggregator::extract_uid(&msg.data).unwrap_or_default();
+                for sink in &sinks {
+                    sink.offer(SinkRecord {
+                        key: key.clone(),
+                        payload: msg.data.clone(),
+                        source: msg.source.clone(),
+                        timestamp: msg.timestamp,
+                    });
+                }
+            }
+
+            let routing = route_plugins.and_then(|r| r.route(&msg.data));
+
+            // Destinations of smoothing routes, and connections the smoother
+            // selects, get the smoothed position instead. Tracks going to
+            // neither are not filtered
+            let smoothed = smoother.and_then(|s| {
+                let routed = routing.as_ref().map_or(&[][..], |r| r.smoothed.as_slice());
+                if routed.is_empty() && !s.has_connections() {
+                    return None;
+                }
+                s.process(&msg.data, routed)
+            });
+
+            // Connections with route plugins get the plugins' output, shared
+            // between connections running the same plugins on the same input
+            let mut plugged: HashMap<(bool, &[String]), Vec<u8>> = HashMap::new();
+            // Then the route's transform profile, shared between connections
+            // given the same profile on the same input. Routes naming a profile
+            // share one instance, so profiles are keyed by address
+            let mut transformed: HashMap<(bool, &[String], usize), Vec<u8>> = HashMap::new();
+            // Conversions for connections that don't take XML, shared the
+            // same way; `None` if the message could not be converted
+            let mut converted: HashMap<(bool, &[String], usize, WireFormat), Option<Vec<u8>>> =
+                HashMap::new();
+
+            // Traffic never crosses tenant namespaces, even with filters bypassed
+            let tenant = msg.source.as_ref().and_then(|source| pool.tenant_of(source));
+
             let mut distributed_count = 0;
+            let tapping = tap.is_active();
+            let mut destinations = Vec::new();
 
             for connection in &connections {
                 // Skip source connection to avoid loops
@@ -302,32 +565,153 @@ impl MessageDistributor {
                     }
                 }
 
+                if pool.tenant_of(&connection.id) != tenant {
+                    continue;
+                }
+
                 // Check filters
-                let should_send = if let Some(rules) = connection_filters.get(&connection.id) {
-                    rules.iter().any(|rule| rule.matches(&msg.data))
+                let mut matched_rule = None;
+                let should_send = if msg.bypass_filters {
+                    true
+                } else if let Some(rules) = connection_filters.get(&connection.id) {
+                    match rules.iter().find(|rule| rule.matches(&msg.data)) {
+                        Some(FilterRule::AlwaysSend) => true,
+                        Some(FilterRule::Block(_)) => false,
+                        Some(rule) => {
+                            filter_matches
+                                .entry(connection.id.clone())
+                                .or_default()
+                                .fetch_add(1, Ordering::Relaxed);
+                            matched_rule = Some(rule);
+                            true
+                        }
+                        None => false,
+                    }
                 } else {
                     // No filters = send to all (default behavior)
                     true
                 };
 
                 if !should_send {
+                    debug!(
+                        trace_id = %msg.trace_id,
+                        connection_id = %connection.id,
+                        "Message filtered out for connection"
+                    );
+                    if tapping {
+                        destinations.push(TapDestination {
+                            connection: connection.id.clone(),
+                            outcome: TapOutcome::Filtered,
+                            matched_rule: None,
+                            smoothed: false,
+                            plugins: Vec::new(),
+                        });
+                    }
                     continue;
                 }
 
+                let (mut payload, is_smoothed) = match &smoothed {
+                    Some(data)
+                        if smoother.is_some_and(|s| s.is_selected(&connection.id))
+                            || routing.as_ref().is_some_and(|r| {
+                                r.smooths(&connection.id) || r.smooths(&connection.name)
+                            }) =>
+                    {
+                        (data, true)
+                    }
+                    _ => (&msg.data, false),
+                };
+                let mut applied_plugins: &[String] = &[];
+                let mut applied_profile = 0;
+
+                if let (Some(routing), Some(route_plugins)) = (&routing, route_plugins) {
+                    let mut plugins = routing.plugins_for(&connection.id);
+                    if plugins.is_empty() {
+                        plugins = routing.plugins_for(&connection.name);
+                    }
+                    if !plugins.is_empty() {
+                        let key = (is_smoothed, plugins);
+                        if !plugged.contains_key(&key) {
+                            let data = route_plugins
+                                .transformers
+                                .apply_selected(plugins, payload.clone())
+                                .instrument(debug_span!("route", trace_id = %msg.trace_id))
+                                .await;
+                            plugged.insert(key, data);
+                        }
+                        payload = &plugged[&key];
+                        applied_plugins = plugins;
+                    }
+
+                    let profile = routing
+                        .transform_for(&connection.id)
+                        .or_else(|| routing.transform_for(&connection.name));
+                    if let Some(profile) = profile.filter(|p| !p.is_noop()) {
+                        applied_profile = profile as *const _ as usize;
+                        payload = transformed
+                            .entry((is_smoothed, applied_plugins, applied_profile))
+                            .or_insert_with(|| match profile.apply_xml(payload) {
+                                Ok(data) => data,
+                                Err(e) => {
+                                    debug!(
+                                        trace_id = %msg.trace_id,
+                                        error = %e,
+                                        "Transform profile not applied"
+                                    );
+                                    payload.clone()
+                                }
+                            });
+                    }
+                }
+
+                let wire_format = pool.wire_format_of(&connection.id);
+                if wire_format != WireFormat::Xml {
+                    let frame = converted
+                        .entry((is_smoothed, applied_plugins, applied_profile, wire_format))
+                        .or_insert_with(|| match format::convert(payload, wire_format) {
+                            Ok(frame) => Some(frame),
+                            Err(e) => {
+                                debug!(
+                                    trace_id = %msg.trace_id,
+                                    format = wire_format.as_str(),
+                                    error = %e,
+                                    "Message could not be converted"
+                                );
+                                None
+                            }
+                        });
+                    match frame {
+                        Some(frame) => payload = frame,
+                        None => {
+                            metrics.record_drop();
+                            if tapping {
+                                destinations.push(TapDestination {
+                                    connection: connection.id.clone(),
+                                    outcome: TapOutcome::Dropped,
+                                    matched_rule: matched_rule.map(|rule| format!("{:?}", rule)),
+                                    smoothed: is_smoothed,
+                                    plugins: applied_plugins.to_vec(),
+                                });
+                            }
+                            continue;
+                        }
+                    }
+                }
+
                 // Attempt to send based on strategy
                 let send_result: Result<(), String> = match config.strategy {
                     DistributionStrategy::DropOnFull => connection
                         .tx
-                        .try_send(PoolMessage::Cot(msg.data.clone()))
+                        .try_send(PoolMessage::Cot(payload.clone()))
                         .map_err(|e| e.to_string()),
                     DistributionStrategy::BlockOnFull => connection
                         .tx
-                        .send_async(PoolMessage::Cot(msg.data.clone()))
+                        .send_async(PoolMessage::Cot(payload.clone()))
                         .await
                         .map_err(|e| e.to_string()),
                     DistributionStrategy::TryForTimeout(timeout) => {
                         tokio::select! {
-                            result = connection.tx.send_async(PoolMessage::Cot(msg.data.clone())) => {
+                            result = connection.tx.send_async(PoolMessage::Cot(payload.clone())) => {
                                 result.map_err(|e| e.to_string())
                             }
                             _ = tokio::time::sleep(timeout) => {
@@ -337,6 +721,20 @@ impl MessageDistributor {
                     }
                 };
 
+                if tapping {
+                    destinations.push(TapDestination {
+                        connection: connection.id.clone(),
+                        outcome: if send_result.is_ok() {
+                            TapOutcome::Delivered
+                        } else {
+                            TapOutcome::Dropped
+                        },
+                        matched_rule: matched_rule.map(|rule| format!("{:?}", rule)),
+                        smoothed: is_smoothed,
+                        plugins: applied_plugins.to_vec(),
+                    });
+                }
+
                 match send_result {
                     Ok(_) => {
                         connection.state.record_sent();
@@ -347,6 +745,7 @@ impl MessageDistributor {
                         // Channel full or disconnected
                         metrics.record_drop();
                         debug!(
+                            trace_id = %msg.trace_id,
                             connection_id = %connection.id,
                             "Failed to send message (channel full or disconnected)"
                         );
@@ -358,8 +757,37 @@ impl MessageDistributor {
             let latency = msg.timestamp.elapsed();
             metrics.record_distribution_latency(latency);
 
+            if tapping {
+                let event = omnitak_cot::parser::parse_any(&msg.data).ok();
+                tap.publish(TapRecord {
+                    trace_id: msg.trace_id.to_string(),
+                    timestamp_ms: std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64,
+                    source: msg.source.clone(),
+                    uid: event.as_ref().map(|e| e.uid.clone()),
+                    cot_type: event.as_ref().map(|e| e.event_type.clone()),
+                    bypass_filters: msg.bypass_filters,
+                    matched_routes: routing
+                        .as_ref()
+                        .map(|r| r.matched_routes.clone())
+                        .unwrap_or_default(),
+                    destinations,
+                    latency_us: latency.as_micros() as u64,
+                    payload: String::from_utf8_lossy(&msg.data).into_owned(),
+                });
+            }
+
             if distributed_count == 0 {
-                debug!("Message not distributed to any connection");
+                debug!(trace_id = %msg.trace_id, "Message not distributed to any connection");
+            } else {
+                debug!(
+                    trace_id = %msg.trace_id,
+                    distributed_count,
+                    latency_us = latency.as_micros() as u64,
+                    "Message distributed"
+                );
             }
         }
 
@@ -371,6 +799,10 @@ impl MessageDistributor {
     pub async fn stop(&self) {
         info!("Stopping message distributor");
 
+        for sink in self.sinks.write().drain(..) {
+            sink.abort();
+        }
+
         // Wait for all workers to finish
         let mut workers = self.workers.write();
         for handle in workers.drain(..) {
@@ -389,6 +821,11 @@ impl MessageDistributor {
     pub fn pending_count(&self) -> usize {
         self.rx.len()
     }
+
+    /// Get the distribution queue capacity
+    pub fn queue_capacity(&self) -> usize {
+        self.rx.capacity().unwrap_or(usize::MAX)
+    }
 }
 
 #[cfg(test)]
@@ -396,6 +833,23 @@ mod tests {
     use super::*;
     use crate::pool::PoolConfig;
 
+    #[test]
+    fn test_config_from_core_settings() {
+        let settings = omnitak_core::config::DistributorConfig {
+            strategy: omnitak_core::config::DistributionStrategy::TryForTimeout,
+            send_timeout_ms: 50,
+            flush_interval_ms: 25,
+            ..Default::default()
+        };
+        let config = DistributorConfig::from(&settings);
+        assert!(matches!(
+            config.strategy,
+            DistributionStrategy::TryForTimeout(t) if t == Duration::from_millis(50)
+        ));
+        assert_eq!(config.flush_interval, Duration::from_millis(25));
+        assert_eq!(config.max_workers, 16);
+    }
+
     #[tokio::test]
     async fn test_filter_always_send() {
         let rule = FilterRule::AlwaysSend;
@@ -436,4 +890,481 @@ mod tests {
         assert!(filters.contains_key(&conn_id));
         assert_eq!(filters[&conn_id].len(), 1);
     }
+
+    #[tokio::test]
+    async fn test_filter_match_counts() {
+        let pool = Arc::new(ConnectionPool::new(PoolConfig::default()));
+        for id in ["typed", "all"] {
+            pool.add_connection(
+                id.to_string(),
+                id.to_string(),
+                "localhost:8087".to_string(),
+                5,
+            )
+            .await
+            .unwrap();
+        }
+        let distributor = MessageDistributor::new(Arc::clone(&pool), DistributorConfig::default());
+        distributor.add_filter(
+            "typed".to_string(),
+            FilterRule::ByType(vec!["a-f-G".to_string()]),
+        );
+        distributor.add_filter("all".to_string(), FilterRule::AlwaysSend);
+
+        let mut batch = [&b"<event type=\"a-f-G\">"[..], b"<event type=\"a-h-G\">"]
+            .iter()
+            .map(|data| DistributionMessage {
+                data: data.to_vec(),
+                source: None,
+                timestamp: Instant::now(),
+                bypass_filters: false,
+                trace_id: TraceId::new(),
+            })
+            .collect();
+        MessageDistributor::distribute_batch(
+            &pool,
+            &distributor.filters,
+            &distributor.sinks,
+            &distributor.filter_matches,
+            None,
+            None,
+            None,
+            &distributor.tap,
+            &distributor.metrics,
+            &distributor.config,
+            &mut batch,
+        )
+        .await;
+
+        // AlwaysSend is not a match worth reporting
+        assert_eq!(
+            distributor.take_filter_matches(),
+            vec![("typed".to_string(), 1)]
+        );
+        assert!(distributor.take_filter_matches().is_empty());
+
+        pool.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tap_records_routing() {
+        let pool = Arc::new(ConnectionPool::new(PoolConfig::default()));
+        for id in ["typed", "all"] {
+            pool.add_connection(
+                id.to_string(),
+                id.to_string(),
+                "localhost:8087".to_string(),
+                5,
+            )
+            .await
+            .unwrap();
+        }
+        let distributor = MessageDistributor::new(Arc::clone(&pool), DistributorConfig::default());
+        distributor.add_filter(
+            "typed".to_string(),
+            FilterRule::ByType(vec!["a-f-G".to_string()]),
+        );
+        let mut tap = distributor.tap().subscribe();
+
+        let trace_id = TraceId::new();
+        let mut batch = vec![DistributionMessage {
+            data: b"<event type=\"a-h-G\">".to_vec(),
+            source: None,
+            timestamp: Instant::now(),
+            bypass_filters: false,
+            trace_id,
+        }];
+        MessageDistributor::distribute_batch(
+            &pool,
+            &distributor.filters,
+            &distributor.sinks,
+            &distributor.filter_matches,
+            None,
+            None,
+            None,
+            &distributor.tap,
+            &distributor.metrics,
+            &distributor.config,
+            &mut batch,
+        )
+        .await;
+
+        let record = tap.try_recv().unwrap();
+        assert_eq!(record.trace_id, trace_id.to_string());
+        let outcome = |id: &str| {
+            record
+                .destinations
+                .iter()
+                .find(|d| d.connection == id)
+                .map(|d| d.outcome)
+        };
+        assert_eq!(outcome("typed"), Some(TapOutcome::Filtered));
+        assert_eq!(outcome("all"), Some(TapOutcome::Delivered));
+        assert_eq!(record.delivered().collect::<Vec<_>>(), vec!["all"]);
+
+        pool.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bypass_filters() {
+        let pool = Arc::new(ConnectionPool::new(PoolConfig::default()));
+        pool.add_connection(
+            "typed".to_string(),
+            "typed".to_string(),
+            "localhost:8087".to_string(),
+            5,
+        )
+        .await
+        .unwrap();
+        let distributor = MessageDistributor::new(Arc::clone(&pool), DistributorConfig::default());
+        distributor.add_filter(
+            "typed".to_string(),
+            FilterRule::ByType(vec!["a-f-G".to_string()]),
+        );
+
+        let mut batch = [false, true]
+            .iter()
+            .map(|&bypass_filters| DistributionMessage {
+                data: b"<event type=\"a-h-G\">".to_vec(),
+                source: None,
+                timestamp: Instant::now(),
+                bypass_filters,
+                trace_id: TraceId::new(),
+            })
+            .collect();
+        MessageDistributor::distribute_batch(
+            &pool,
+            &distributor.filters,
+            &distributor.sinks,
+            &distributor.filter_matches,
+            None,
+            None,
+            None,
+            &distributor.tap,
+            &distributor.metrics,
+            &distributor.config,
+            &mut batch,
+        )
+        .await;
+
+        // Only the override gets past the type filter
+        let connection = pool.get_connection(&"typed".to_string()).unwrap();
+        assert_eq!(connection.state.messages_sent.load(Ordering::Relaxed), 1);
+        assert!(distributor.take_filter_matches().is_empty());
+
+        pool.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tenant_isolation() {
+        let pool = Arc::new(ConnectionPool::new(PoolConfig::default()));
+        for id in ["red-1", "red-2", "blue", "default"] {
+            pool.add_connection(
+                id.to_string(),
+                id.to_string(),
+                "localhost:8087".to_string(),
+                5,
+            )
+            .await
+            .unwrap();
+        }
+        pool.set_tenant("red-1".to_string(), Some("red".to_string()));
+        pool.set_tenant("red-2".to_string(), Some("red".to_string()));
+        pool.set_tenant("blue".to_string(), Some("blue".to_string()));
+        let distributor = MessageDistributor::new(Arc::clone(&pool), DistributorConfig::default());
+
+        let mut batch = [Some("red-1"), None]
+            .iter()
+            .map(|source| DistributionMessage {
+                data: b"<event type=\"a-f-G\">".to_vec(),
+                source: source.map(str::to_string),
+                timestamp: Instant::now(),
+                bypass_filters: true,
+                trace_id: TraceId::new(),
+            })
+            .collect();
+        MessageDistributor::distribute_batch(
+            &pool,
+            &distributor.filters,
+            &distributor.sinks,
+            &distributor.filter_matches,
+            None,
+            None,
+            None,
+            &distributor.tap,
+            &distributor.metrics,
+            &distributor.config,
+            &mut batch,
+        )
+        .await;
+
+        let sent = |id: &str| {
+            let connection = pool.get_connection(&id.to_string()).unwrap();
+            connection.state.messages_sent.load(Ordering::Relaxed)
+        };
+        assert_eq!(sent("red-1"), 0);
+        assert_eq!(sent("red-2"), 1);
+        assert_eq!(sent("blue"), 0);
+        assert_eq!(sent("default"), 1);
+
+        pool.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_route_plugins() {
+        struct Redact;
+
+        #[async_trait::async_trait]
+        impl crate::transform::MessageTransformer for Redact {
+            fn id(&self) -> &str {
+                "sanitizer"
+            }
+
+            async fn transform(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+                Ok(String::from_utf8_lossy(data)
+                    .replace("ALPHA-1", "REDACTED")
+                    .into_bytes())
+            }
+        }
+
+        let pool = Arc::new(ConnectionPool::new(PoolConfig::default()));
+        for (id, name) in [("tak-server-partner", "partner"), ("local", "local")] {
+            pool.add_connection(
+                id.to_string(),
+                name.to_string(),
+                "localhost:8087".to_string(),
+                5,
+            )
+            .await
+            .unwrap();
+        }
+
+        let transformers = Arc::new(TransformPipeline::new(Default::default()));
+        transformers.add(Arc::new(Redact));
+        transformers.set_route_only(["sanitizer".to_string()].into());
+        let routes = omnitak_filter::RouteTableBuilder::multicast()
+            .add_route(
+                omnitak_filter::Route::new(
+                    "partner".to_string(),
+                    "Partner nation server".to_string(),
+                    Arc::new(omnitak_filter::AffiliationFilter::friendly_only()),
+                    vec!["partner".to_string()],
+                    100,
+                )
+                .with_plugins(vec!["sanitizer".to_string()]),
+            )
+            .build();
+        let distributor = MessageDistributor::new(Arc::clone(&pool), DistributorConfig::default())
+            .with_route_plugins(RoutePlugins::new(Arc::new(routes), transformers));
+
+        let event = br#"<event version="2.0" uid="UNIT-1" type="a-f-G-U-C" time="2026-01-01T00:00:00Z" start="2026-01-01T00:00:00Z" stale="2026-01-01T00:05:00Z" how="m-g"><point lat="40.7" lon="-74.0" hae="0" ce="10" le="10"/><detail><contact callsign="ALPHA-1"/></detail></event>"#;
+        let mut batch = vec![DistributionMessage {
+            data: event.to_vec(),
+            source: None,
+            timestamp: Instant::now(),
+            bypass_filters: false,
+            trace_id: TraceId::new(),
+        }];
+        MessageDistributor::distribute_batch(
+            &pool,
+            &distributor.filters,
+            &distributor.sinks,
+            &distributor.filter_matches,
+            None,
+            distributor.route_plugins.as_deref(),
+            None,
+            &distributor.tap,
+            &distributor.metrics,
+            &distributor.config,
+            &mut batch,
+        )
+        .await;
+
+        let mut received = HashMap::new();
+        for id in ["tak-server-partner", "local"] {
+            let connection = pool.get_connection(&id.to_string()).unwrap();
+            let message = tokio::time::timeout(Duration::from_secs(1), connection.rx.recv_async())
+                .await
+                .unwrap()
+                .unwrap();
+            let PoolMessage::Cot(data) = message else {
+                panic!("expected CoT");
+            };
+            received.insert(id, String::from_utf8(data).unwrap());
+        }
+        // Only the route's destination gets the plugin's output
+        assert!(received["tak-server-partner"].contains("callsign=\"REDACTED\""));
+        assert!(received["local"].contains("callsign=\"ALPHA-1\""));
+
+        pool.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_route_transforms() {
+        let pool = Arc::new(ConnectionPool::new(PoolConfig::default()));
+        for (id, name) in [("tak-server-satcom", "satcom"), ("local", "local")] {
+            pool.add_connection(
+                id.to_string(),
+                name.to_string(),
+                "localhost:8087".to_string(),
+                5,
+            )
+            .await
+            .unwrap();
+        }
+
+        let routes = omnitak_filter::RouteTableBuilder::multicast()
+            .add_route(
+                omnitak_filter::Route::new(
+                    "satcom".to_string(),
+                    "Low-bandwidth link".to_string(),
+                    Arc::new(omnitak_filter::AffiliationFilter::friendly_only()),
+                    vec!["satcom".to_string()],
+                    100,
+                )
+                .with_transform(Arc::new(omnitak_filter::TransformProfile::lite())),
+            )
+            .build();
+        let transformers = Arc::new(TransformPipeline::new(Default::default()));
+        let distributor = MessageDistributor::new(Arc::clone(&pool), DistributorConfig::default())
+            .with_route_plugins(RoutePlugins::new(Arc::new(routes), transformers));
+
+        let event = br#"<event version="2.0" uid="UNIT-1" type="a-f-G-U-C" time="2026-01-01T00:00:00Z" start="2026-01-01T00:00:00Z" stale="2026-01-01T00:05:00Z" how="m-g"><point lat="40.712345678" lon="-74.0" hae="0" ce="10" le="10"/><detail><contact callsign="ALPHA-1"/><takv device="Pixel" platform="ATAK-CIV" os="34" version="5.2.0"/></detail></event>"#;
+        let mut batch = vec![DistributionMessage {
+            data: event.to_vec(),
+            source: None,
+            timestamp: Instant::now(),
+            bypass_filters: false,
+            trace_id: TraceId::new(),
+        }];
+        MessageDistributor::distribute_batch(
+            &pool,
+            &distributor.filters,
+            &distributor.sinks,
+            &distributor.filter_matches,
+            None,
+            distributor.route_plugins.as_deref(),
+            None,
+            &distributor.tap,
+            &distributor.metrics,
+            &distributor.config,
+            &mut batch,
+        )
+        .await;
+
+        let mut received = HashMap::new();
+        for id in ["tak-server-satcom", "local"] {
+            let connection = pool.get_connection(&id.to_string()).unwrap();
+            let message = tokio::time::timeout(Duration::from_secs(1), connection.rx.recv_async())
+                .await
+                .unwrap()
+                .unwrap();
+            let PoolMessage::Cot(data) = message else {
+                panic!("expected CoT");
+            };
+            received.insert(id, data);
+        }
+        // Only the route's destination gets the reduced message
+        let lite = String::from_utf8(received["tak-server-satcom"].clone()).unwrap();
+        assert!(!lite.contains("takv"));
+        assert!(lite.contains("40.71235"));
+        assert!(lite.contains("ALPHA-1"));
+        assert_eq!(received["local"], event.to_vec());
+
+        pool.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_route_smoothing() {
+        let pool = Arc::new(ConnectionPool::new(PoolConfig::default()));
+        for (id, name) in [("web-map", "map"), ("local", "local")] {
+            pool.add_connection(
+                id.to_string(),
+                name.to_string(),
+                "localhost:8087".to_string(),
+                5,
+            )
+            .await
+            .unwrap();
+        }
+
+        let routes = omnitak_filter::RouteTableBuilder::multicast()
+            .add_route(
+                omnitak_filter::Route::new(
+                    "map".to_string(),
+                    "Smoothed map display".to_string(),
+                    Arc::new(omnitak_filter::AffiliationFilter::friendly_only()),
+                    vec!["map".to_string()],
+                    100,
+                )
+                .with_smoothing(true),
+            )
+            .build();
+        let transformers = Arc::new(TransformPipeline::new(Default::default()));
+        let distributor = MessageDistributor::new(Arc::clone(&pool), DistributorConfig::default())
+            .with_route_plugins(RoutePlugins::new(Arc::new(routes), transformers));
+        let smoother = TrackSmoother::new(Default::default());
+
+        let report = |lat: &str, time: &str| {
+            format!(
+                r#"<event version="2.0" uid="UNIT-1" type="a-f-G-U-C" time="{time}" start="{time}" stale="2030-01-01T00:00:00Z" how="m-g"><point lat="{lat}" lon="-74.0" hae="0" ce="10" le="10"/><detail/></event>"#
+            )
+            .into_bytes()
+        };
+        let mut batch = vec![
+            report("40.0", "2026-01-01T00:00:00Z"),
+            report("40.0009", "2026-01-01T00:00:01Z"),
+        ]
+        .into_iter()
+        .map(|data| DistributionMessage {
+            data,
+            source: None,
+            timestamp: Instant::now(),
+            bypass_filters: false,
+            trace_id: TraceId::new(),
+        })
+        .collect();
+        MessageDistributor::distribute_batch(
+            &pool,
+            &distributor.filters,
+            &distributor.sinks,
+            &distributor.filter_matches,
+            Some(&smoother),
+            distributor.route_plugins.as_deref(),
+            None,
+            &distributor.tap,
+            &distributor.metrics,
+            &distributor.config,
+            &mut batch,
+        )
+        .await;
+        assert_eq!(smoother.track_count(), 1);
+
+        let mut received = HashMap::new();
+        for id in ["web-map", "local"] {
+            let connection = pool.get_connection(&id.to_string()).unwrap();
+            let mut last = None;
+            for _ in 0..2 {
+                let message =
+                    tokio::time::timeout(Duration::from_secs(1), connection.rx.recv_async())
+                        .await
+                        .unwrap()
+                        .unwrap();
+                let PoolMessage::Cot(data) = message else {
+                    panic!("expected CoT");
+                };
+                last = Some(String::from_utf8(data).unwrap());
+            }
+            received.insert(id, last.unwrap());
+        }
+        // Only the smoothing route's destination gets the filtered position
+        assert!(received["web-map"].contains(r#"mode="smoothed""#));
+        assert!(!received["web-map"].contains(r#"lat="40.0009""#));
+        assert_eq!(
+            received["local"].as_bytes(),
+            report("40.0009", "2026-01-01T00:00:01Z")
+        );
+
+        pool.shutdown().await.unwrap();
+    }
 }
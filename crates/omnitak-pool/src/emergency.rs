@@ -0,0 +1,407 @@
+//! Emergency Beacon Tracking
+//!
+//! Detects emergency CoT (911 alerts, ring-the-bell, troops in contact,
+//! geo-fence breaches and `a-*-emergency` tracks) as messages pass through
+//! the aggregator, and tracks each beacon from raised through acknowledged
+//! to cleared. Every state change is broadcast so the API, alerting and
+//! webhooks can escalate it.
+//!
+//! Beacons are keyed by event UID; ATAK reuses the same UID
+//! (`<device>-9-1-1`) for repeats and for the `b-a-o-can` cancel message.
+
+use dashmap::DashMap;
+use omnitak_cot::Event;
+use serde::Serialize;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+use crate::pool::ConnectionId;
+
+/// CoT type ATAK sends to cancel an emergency
+pub const CANCEL_TYPE: &str = "b-a-o-can";
+
+/// How long cleared and cancelled beacons stay listed
+pub const CLEARED_RETENTION: Duration = Duration::from_secs(3600);
+
+/// Kind of emergency, from the CoT type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmergencyKind {
+    /// `b-a-o-tbl`
+    Alert911,
+    /// `b-a-o-pan`
+    RingTheBell,
+    /// `b-a-o-opn`
+    TroopsInContact,
+    /// `b-a-g`
+    GeoFenceBreached,
+    /// `b-a-o-c`
+    Custom,
+    /// Track types ending in `-emergency`, e.g. `a-u-emergency`
+    Distress,
+}
+
+impl EmergencyKind {
+    /// Classify a CoT type; `None` for non-emergency types and cancels
+    pub fn from_cot_type(cot_type: &str) -> Option<Self> {
+        match cot_type {
+            "b-a-o-tbl" => Some(Self::Alert911),
+            "b-a-o-pan" => Some(Self::RingTheBell),
+            "b-a-o-opn" => Some(Self::TroopsInContact),
+            "b-a-g" => Some(Self::GeoFenceBreached),
+            "b-a-o-c" => Some(Self::Custom),
+            t if t.starts_with("a-") && t.ends_with("-emergency") => Some(Self::Distress),
+            _ => None,
+        }
+    }
+
+    /// Name as shown in ATAK
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Alert911 => "911 Alert",
+            Self::RingTheBell => "Ring The Bell",
+            Self::TroopsInContact => "Troops In Contact",
+            Self::GeoFenceBreached => "Geo-fence Breached",
+            Self::Custom => "Custom Emergency",
+            Self::Distress => "Distress",
+        }
+    }
+}
+
+/// Where a beacon is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmergencyState {
+    /// Raised and not yet seen by an operator
+    Active,
+    /// An operator has acknowledged it; still ongoing
+    Acknowledged,
+    /// Resolved by an operator
+    Cleared,
+    /// Cancelled by the originating device
+    Cancelled,
+}
+
+impl EmergencyState {
+    /// Whether the emergency is still ongoing
+    pub fn is_open(&self) -> bool {
+        matches!(self, Self::Active | Self::Acknowledged)
+    }
+}
+
+/// A tracked emergency beacon
+#[derive(Debug, Clone, Serialize)]
+pub struct Emergency {
+    pub uid: String,
+    pub kind: EmergencyKind,
+    pub cot_type: String,
+    pub callsign: Option<String>,
+    /// Connection the beacon was first received from
+    pub source: ConnectionId,
+    pub lat: f64,
+    pub lon: f64,
+    pub state: EmergencyState,
+    pub first_seen_ms: u64,
+    pub last_seen_ms: u64,
+    pub acknowledged_by: Option<String>,
+    pub acknowledged_at_ms: Option<u64>,
+    pub cleared_by: Option<String>,
+    pub cleared_at_ms: Option<u64>,
+}
+
+/// Errors from operator actions on beacons
+#[derive(Debug, Error)]
+pub enum EmergencyError {
+    #[error("Emergency {0} not found")]
+    NotFound(String),
+
+    #[error("Emergency {0} is already closed")]
+    Closed(String),
+}
+
+/// Tracks emergency beacons and broadcasts their state changes
+pub struct EmergencyTracker {
+    beacons: DashMap<String, Emergency>,
+    events: broadcast::Sender<Emergency>,
+}
+
+impl EmergencyTracker {
+    pub fn new() -> Self {
+        Self {
+            beacons: DashMap::new(),
+            events: broadcast::channel(256).0,
+        }
+    }
+
+    /// Receive a snapshot of each beacon whenever its state changes
+    pub fn subscribe(&self) -> broadcast::Receiver<Emergency> {
+        self.events.subscribe()
+    }
+
+    /// Check a raw message (XML or TAK protobuf) for an emergency
+    pub fn inspect(&self, data: &[u8], source: &ConnectionId) {
+        // Cheap byte scan first; only candidates are parsed
+        if !contains(data, b"b-a-") && !contains(data, b"emergency") {
+            return;
+        }
+        let Ok(event) = omnitak_cot::parser::parse_any(data) else {
+            return;
+        };
+        let cancelled = contains(data, b"cancel=\"true\"");
+        self.observe(&event, source, cancelled);
+    }
+
+    /// Record a parsed event. `cancelled` marks an emergency detail with
+    /// `cancel="true"`, which some clients send instead of `b-a-o-can`.
+    pub fn observe(&self, event: &Event, source: &ConnectionId, cancelled: bool) {
+        if event.event_type == CANCEL_TYPE || cancelled {
+            self.cancel(&event.uid);
+            return;
+        }
+        let Some(kind) = EmergencyKind::from_cot_type(&event.event_type) else {
+            return;
+        };
+
+        let now = now_ms();
+        let callsign = event
+            .detail
+            .as_ref()
+            .and_then(|d| d.contact.as_ref())
+            .map(|c| c.callsign.clone());
+
+        let mut entry = self
+            .beacons
+            .entry(event.uid.clone())
+            .or_insert_with(|| Emergency {
+                uid: event.uid.clone(),
+                kind,
+                cot_type: event.event_type.clone(),
+                callsign: callsign.clone(),
+                source: source.clone(),
+                lat: event.point.lat,
+                lon: event.point.lon,
+                state: EmergencyState::Cleared,
+                first_seen_ms: now,
+                last_seen_ms: now,
+                acknowledged_by: None,
+                acknowledged_at_ms: None,
+                cleared_by: None,
+                cleared_at_ms: None,
+            });
+
+        let beacon = entry.value_mut();
+        beacon.lat = event.point.lat;
+        beacon.lon = event.point.lon;
+        beacon.last_seen_ms = now;
+        if callsign.is_some() {
+            beacon.callsign = callsign;
+        }
+
+        // Repeats of an open beacon only refresh position
+        if beacon.state.is_open() {
+            return;
+        }
+
+        // New beacon, or a closed one raised again
+        *beacon = Emergency {
+            kind,
+            cot_type: event.event_type.clone(),
+            source: source.clone(),
+            state: EmergencyState::Active,
+            first_seen_ms: now,
+            acknowledged_by: None,
+            acknowledged_at_ms: None,
+            cleared_by: None,
+            cleared_at_ms: None,
+            ..beacon.clone()
+        };
+        let snapshot = beacon.clone();
+        drop(entry);
+
+        warn!(
+            uid = %snapshot.uid,
+            callsign = snapshot.callsign.as_deref().unwrap_or("unknown"),
+            kind = snapshot.kind.label(),
+            source = %snapshot.source,
+            "Emergency beacon raised"
+        );
+        let _ = self.events.send(snapshot);
+    }
+
+    /// Mark a beacon as seen by `by`
+    pub fn acknowledge(&self, uid: &str, by: &str) -> Result<Emergency, EmergencyError> {
+        self.transition(uid, |beacon| {
+            if beacon.state != EmergencyState::Active {
+                return false;
+            }
+            beacon.state = EmergencyState::Acknowledged;
+            beacon.acknowledged_by = Some(by.to_string());
+            beacon.acknowledged_at_ms = Some(now_ms());
+            true
+        })
+    }
+
+    /// Resolve a beacon on behalf of `by`
+    pub fn clear(&self, uid: &str, by: &str) -> Result<Emergency, EmergencyError> {
+        self.transition(uid, |beacon| {
+            beacon.state = EmergencyState::Cleared;
+            beacon.cleared_by = Some(by.to_string());
+            beacon.cleared_at_ms = Some(now_ms());
+            true
+        })
+    }
+
+    fn cancel(&self, uid: &str) {
+        let result = self.transition(uid, |beacon| {
+            beacon.state = EmergencyState::Cancelled;
+            beacon.cleared_at_ms = Some(now_ms());
+            true
+        });
+        if result.is_ok() {
+            info!(uid, "Emergency beacon cancelled by originator");
+        }
+    }
+
+    /// Apply `change` to an open beacon and broadcast the result. `change`
+    /// returns false to leave the beacon as it was.
+    fn transition(
+        &self,
+        uid: &str,
+        change: impl FnOnce(&mut Emergency) -> bool,
+    ) -> Result<Emergency, EmergencyError> {
+        let mut beacon = self
+            .beacons
+            .get_mut(uid)
+            .ok_or_else(|| EmergencyError::NotFound(uid.to_string()))?;
+        if !beacon.state.is_open() {
+            return Err(EmergencyError::Closed(uid.to_string()));
+        }
+        if change(&mut beacon) {
+            let _ = self.events.send(beacon.clone());
+        }
+        Ok(beacon.clone())
+    }
+
+    pub fn get(&self, uid: &str) -> Option<Emergency> {
+        self.beacons.get(uid).map(|b| b.clone())
+    }
+
+    /// All beacons, open ones first, newest first within each group
+    pub fn list(&self) -> Vec<Emergency> {
+        let mut beacons: Vec<_> = self.beacons.iter().map(|b| b.clone()).collect();
+        beacons.sort_by_key(|b| (!b.state.is_open(), std::cmp::Reverse(b.first_seen_ms)));
+        beacons
+    }
+
+    /// Number of beacons not yet acknowledged or closed
+    pub fn active_count(&self) -> usize {
+        self.beacons
+            .iter()
+            .filter(|b| b.state == EmergencyState::Active)
+            .count()
+    }
+
+    /// Forget beacons closed longer than `retention` ago
+    pub fn prune(&self, retention: Duration) {
+        let cutoff = now_ms().saturating_sub(retention.as_millis() as u64);
+        self.beacons
+            .retain(|_, b| b.state.is_open() || b.cleared_at_ms.unwrap_or(u64::MAX) > cutoff);
+    }
+}
+
+impl Default for EmergencyTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn beacon_xml(uid: &str, cot_type: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<event version="2.0" uid="{uid}" type="{cot_type}" time="2026-01-01T00:00:00Z" start="2026-01-01T00:00:00Z" stale="2026-01-01T00:05:00Z" how="h-e">
+  <point lat="34.1" lon="-117.2" hae="0" ce="10" le="10"/>
+  <detail><contact callsign="VIPER-1-Alert"/></detail>
+</event>"#
+        )
+    }
+
+    #[test]
+    fn test_classify_cot_types() {
+        assert_eq!(
+            EmergencyKind::from_cot_type("b-a-o-tbl"),
+            Some(EmergencyKind::Alert911)
+        );
+        assert_eq!(
+            EmergencyKind::from_cot_type("a-u-emergency"),
+            Some(EmergencyKind::Distress)
+        );
+        assert_eq!(EmergencyKind::from_cot_type(CANCEL_TYPE), None);
+        assert_eq!(EmergencyKind::from_cot_type("a-f-G-U-C"), None);
+    }
+
+    #[tokio::test]
+    async fn test_beacon_lifecycle() {
+        let tracker = EmergencyTracker::new();
+        let mut events = tracker.subscribe();
+        let source = "tak-server-main".to_string();
+
+        tracker.inspect(
+            beacon_xml("ANDROID-1-9-1-1", "b-a-o-tbl").as_bytes(),
+            &source,
+        );
+        let raised = events.recv().await.unwrap();
+        assert_eq!(raised.state, EmergencyState::Active);
+        assert_eq!(raised.callsign.as_deref(), Some("VIPER-1-Alert"));
+        assert_eq!(tracker.active_count(), 1);
+
+        // Repeats don't re-raise
+        tracker.inspect(
+            beacon_xml("ANDROID-1-9-1-1", "b-a-o-tbl").as_bytes(),
+            &source,
+        );
+        assert!(events.try_recv().is_err());
+
+        let acked = tracker.acknowledge("ANDROID-1-9-1-1", "admin").unwrap();
+        assert_eq!(acked.state, EmergencyState::Acknowledged);
+        assert_eq!(acked.acknowledged_by.as_deref(), Some("admin"));
+        assert_eq!(tracker.active_count(), 0);
+
+        tracker.inspect(
+            beacon_xml("ANDROID-1-9-1-1", CANCEL_TYPE).as_bytes(),
+            &source,
+        );
+        assert_eq!(
+            events.recv().await.unwrap().state,
+            EmergencyState::Acknowledged
+        );
+        assert_eq!(
+            events.recv().await.unwrap().state,
+            EmergencyState::Cancelled
+        );
+        assert!(matches!(
+            tracker.clear("ANDROID-1-9-1-1", "admin"),
+            Err(EmergencyError::Closed(_))
+        ));
+
+        // Non-emergency traffic is ignored
+        tracker.inspect(beacon_xml("ANDROID-2", "a-f-G-U-C").as_bytes(), &source);
+        assert!(tracker.get("ANDROID-2").is_none());
+    }
+}
@@ -0,0 +1,120 @@
+//! Outbound Wire Formats
+//!
+//! Connections get CoT as it arrived, normally XML, unless they declare
+//! another format with [`ConnectionPool::set_wire_format`]. For protobuf
+//! connections the distributor converts each message into a TAK Protocol
+//! Version 1 streaming frame (`0xBF`, varint length, `TakMessage`), once per
+//! distinct payload, and the connection writes the frame as-is.
+//!
+//! Messages that cannot be converted are not sent to the connection; they
+//! are counted in `format_conversion_errors_total`.
+//!
+//! [`ConnectionPool::set_wire_format`]: crate::pool::ConnectionPool::set_wire_format
+
+use metrics::{counter, describe_counter};
+use omnitak_cot::parser::{detect_protocol, parse_any, Protocol};
+use omnitak_cot::{ParseError, ProtoError};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Format a connection expects CoT in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WireFormat {
+    /// CoT XML, for legacy servers and clients
+    #[default]
+    Xml,
+    /// TAK Protocol Version 1 streaming frames
+    Protobuf,
+}
+
+impl WireFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WireFormat::Xml => "xml",
+            WireFormat::Protobuf => "protobuf",
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum FormatError {
+    #[error("Unreadable message: {0}")]
+    Parse(#[from] ParseError),
+
+    #[error("Encoding failed: {0}")]
+    Encode(#[from] ProtoError),
+}
+
+/// Convert a message, in any format the parser reads, to `format`
+///
+/// XML messages converted to XML are returned unchanged.
+pub fn convert(data: &[u8], format: WireFormat) -> Result<Vec<u8>, FormatError> {
+    let result = match format {
+        WireFormat::Xml if matches!(detect_protocol(data), Ok(Protocol::Xml)) => {
+            return Ok(data.to_vec());
+        }
+        WireFormat::Xml => parse_any(data)
+            .map(|event| omnitak_cot::serialize_event(&event).into_bytes())
+            .map_err(FormatError::from),
+        WireFormat::Protobuf => parse_any(data)
+            .map_err(FormatError::from)
+            .and_then(|event| Ok(omnitak_cot::proto::encode_stream_frame(&event)?)),
+    };
+
+    match &result {
+        Ok(_) => counter!("format_conversions_total", "format" => format.as_str()).increment(1),
+        Err(_) => {
+            counter!("format_conversion_errors_total", "format" => format.as_str()).increment(1)
+        }
+    }
+    result
+}
+
+pub(crate) fn describe() {
+    describe_counter!(
+        "format_conversions_total",
+        "Messages converted to the wire format of their destination"
+    );
+    describe_counter!(
+        "format_conversion_errors_total",
+        "Messages not sent to a destination because they could not be converted to its wire format"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EVENT: &[u8] = b"<event version=\"2.0\" uid=\"U-1\" type=\"a-f-G\" how=\"m-g\" \
+        time=\"2024-01-15T10:30:00Z\" start=\"2024-01-15T10:30:00Z\" stale=\"2024-01-15T10:35:00Z\">\
+        <point lat=\"37.5\" lon=\"-122.25\" hae=\"10\" ce=\"5\" le=\"5\"/>\
+        <detail><contact callsign=\"Alpha-1\"/></detail></event>";
+
+    #[test]
+    fn test_protobuf_round_trip() {
+        let frame = convert(EVENT, WireFormat::Protobuf).unwrap();
+        assert_eq!(frame[0], 0xBF);
+
+        let event = omnitak_cot::parser::parse_stream(&frame[1..]).unwrap();
+        assert_eq!(event.uid, "U-1");
+        assert_eq!(event.point.lat, 37.5);
+
+        // Back to XML for a legacy destination
+        let xml = convert(&frame[1..], WireFormat::Xml).unwrap();
+        assert!(String::from_utf8(xml).unwrap().contains("uid=\"U-1\""));
+    }
+
+    #[test]
+    fn test_xml_unchanged() {
+        assert_eq!(convert(EVENT, WireFormat::Xml).unwrap(), EVENT);
+    }
+
+    #[test]
+    fn test_unreadable_message() {
+        assert!(matches!(
+            convert(b"<event uid=", WireFormat::Protobuf),
+            Err(FormatError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,495 @@
+//! Track Fusion
+//!
+//! When several upstream TAK servers report the same unit, each under its
+//! own UID or with slightly different timing, the common picture shows the
+//! unit several times. The fusion stage correlates position reports from
+//! different sources into one fused track:
+//!
+//! - **takv_serial**: same device serial in the `<takv>` detail
+//! - **callsign**: same callsign (case-insensitive)
+//! - **proximity**: same CoT type within `proximity_m` and `window_secs`
+//!
+//! Strategies are tried in the configured order. Correlated reports are
+//! re-issued under the UID first seen for the unit, reports no newer than
+//! the last one sent are dropped, and a `<_omnitak_fusion>` detail records
+//! which sources and UIDs contributed.
+//!
+//! Only XML atom (`a-*`) events are fused; everything else passes through.
+
+use omnitak_cot::geodesy::distance_m;
+use omnitak_cot::parse_cot_borrowed;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::pool::ConnectionId;
+
+/// How reports are matched to an existing fused track
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorrelationStrategy {
+    TakvSerial,
+    Callsign,
+    Proximity,
+}
+
+/// Track fusion settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FusionConfig {
+    /// Correlation strategies, tried in order
+    #[serde(default = "default_strategies")]
+    pub strategies: Vec<CorrelationStrategy>,
+    /// Maximum distance between reports for proximity correlation
+    #[serde(default = "default_proximity_m")]
+    pub proximity_m: f64,
+    /// Maximum time between reports for proximity correlation
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+    /// Forget fused tracks not updated for this long
+    #[serde(default = "default_track_ttl_secs")]
+    pub track_ttl_secs: u64,
+}
+
+fn default_strategies() -> Vec<CorrelationStrategy> {
+    vec![
+        CorrelationStrategy::TakvSerial,
+        CorrelationStrategy::Callsign,
+        CorrelationStrategy::Proximity,
+    ]
+}
+
+fn default_proximity_m() -> f64 {
+    25.0
+}
+
+fn default_window_secs() -> u64 {
+    30
+}
+
+fn default_track_ttl_secs() -> u64 {
+    300
+}
+
+impl Default for FusionConfig {
+    fn default() -> Self {
+        Self {
+            strategies: default_strategies(),
+            proximity_m: default_proximity_m(),
+            window_secs: default_window_secs(),
+            track_ttl_secs: default_track_ttl_secs(),
+        }
+    }
+}
+
+/// A source report contributing to a fused track
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackSource {
+    pub source: ConnectionId,
+    pub uid: String,
+    /// Strategy that matched this source; `None` for the first source
+    pub matched_by: Option<CorrelationStrategy>,
+    #[serde(skip)]
+    last_seen: Instant,
+}
+
+/// A unit as seen across all sources
+#[derive(Debug, Clone, Serialize)]
+pub struct FusedTrack {
+    /// UID the fused track is published under
+    pub uid: String,
+    pub cot_type: String,
+    pub callsign: Option<String>,
+    pub serial: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+    /// Event time of the newest report sent, in Unix milliseconds
+    pub time_ms: u64,
+    pub sources: Vec<TrackSource>,
+    #[serde(skip)]
+    updated: Instant,
+}
+
+/// What to do with a report after fusion
+#[derive(Debug, PartialEq)]
+pub enum FusionOutcome {
+    /// Forward the report unchanged
+    Unchanged,
+    /// Forward this rewritten report instead
+    Fused(Vec<u8>),
+    /// Drop the report; one at least as new was already sent for the unit
+    Stale,
+}
+
+#[derive(Default)]
+struct FusionState {
+    /// Fused tracks by fused UID
+    tracks: HashMap<String, FusedTrack>,
+    /// (source, source UID) to fused UID
+    index: HashMap<(ConnectionId, String), String>,
+}
+
+/// Correlates tracks reported by multiple sources
+pub struct TrackFusion {
+    config: FusionConfig,
+    state: Mutex<FusionState>,
+}
+
+impl TrackFusion {
+    pub fn new(config: FusionConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(FusionState::default()),
+        }
+    }
+
+    /// Fuse one inbound report
+    pub fn process(&self, data: &[u8], source: &ConnectionId) -> FusionOutcome {
+        if !data.starts_with(b"<") || !contains(data, b"type=\"a-") {
+            return FusionOutcome::Unchanged;
+        }
+        let Some(event) = std::str::from_utf8(data)
+            .ok()
+            .and_then(|xml| parse_cot_borrowed(xml).ok())
+        else {
+            return FusionOutcome::Unchanged;
+        };
+        if !event.event_type.starts_with("a-") {
+            return FusionOutcome::Unchanged;
+        }
+
+        let report = Report {
+            uid: event.uid.to_string(),
+            cot_type: event.event_type.to_string(),
+            callsign: event.callsign().map(str::to_string),
+            serial: takv_serial(data),
+            lat: event.point.lat,
+            lon: event.point.lon,
+            time_ms: event.time_millis(),
+        };
+
+        let mut guard = self.state.lock();
+        let state = &mut *guard;
+        let key = (source.clone(), report.uid.clone());
+        let fused_uid = match state.index.get(&key) {
+            Some(uid) if state.tracks.contains_key(uid) => uid.clone(),
+            _ => {
+                let (uid, matched_by) = match self.correlate(state, &report, source) {
+                    Some((uid, strategy)) => (uid, Some(strategy)),
+                    None => (report.uid.clone(), None),
+                };
+                let track = state
+                    .tracks
+                    .entry(uid.clone())
+                    .or_insert_with(|| FusedTrack {
+                        uid: uid.clone(),
+                        cot_type: report.cot_type.clone(),
+                        callsign: None,
+                        serial: None,
+                        lat: report.lat,
+                        lon: report.lon,
+                        time_ms: 0,
+                        sources: Vec::new(),
+                        updated: Instant::now(),
+                    });
+                track
+                    .sources
+                    .retain(|s| s.source != *source || s.uid != report.uid);
+                track.sources.push(TrackSource {
+                    source: source.clone(),
+                    uid: report.uid.clone(),
+                    matched_by,
+                    last_seen: Instant::now(),
+                });
+                state.index.insert(key, uid.clone());
+                uid
+            }
+        };
+
+        let Some(track) = state.tracks.get_mut(&fused_uid) else {
+            return FusionOutcome::Unchanged;
+        };
+        if let Some(member) = track
+            .sources
+            .iter_mut()
+            .find(|s| s.source == *source && s.uid == report.uid)
+        {
+            member.last_seen = Instant::now();
+        }
+        let fused = track.sources.len() > 1;
+        if fused && report.time_ms <= track.time_ms {
+            return FusionOutcome::Stale;
+        }
+
+        track.time_ms = track.time_ms.max(report.time_ms);
+        track.lat = report.lat;
+        track.lon = report.lon;
+        track.updated = Instant::now();
+        if report.callsign.is_some() {
+            track.callsign = report.callsign;
+        }
+        if report.serial.is_some() {
+            track.serial = report.serial;
+        }
+
+        if !fused {
+            return FusionOutcome::Unchanged;
+        }
+        FusionOutcome::Fused(rewrite(data, &report.uid, track))
+    }
+
+    /// Find the fused track a first-seen report belongs to
+    fn correlate(
+        &self,
+        state: &FusionState,
+        report: &Report,
+        source: &ConnectionId,
+    ) -> Option<(String, CorrelationStrategy)> {
+        let window = Duration::from_secs(self.config.window_secs);
+        // Only units already reported by other sources are candidates; two
+        // tracks on the same server are distinct by definition
+        let candidates = || {
+            state
+                .tracks
+                .values()
+                .filter(|t| !t.sources.iter().any(|s| s.source == *source))
+        };
+
+        for strategy in &self.config.strategies {
+            let found = match strategy {
+                CorrelationStrategy::TakvSerial => report
+                    .serial
+                    .as_ref()
+                    .and_then(|serial| candidates().find(|t| t.serial.as_ref() == Some(serial))),
+                CorrelationStrategy::Callsign => report.callsign.as_ref().and_then(|callsign| {
+                    candidates().find(|t| {
+                        t.callsign
+                            .as_ref()
+                            .is_some_and(|c| c.eq_ignore_ascii_case(callsign))
+                    })
+                }),
+                CorrelationStrategy::Proximity => candidates()
+                    .filter(|t| t.cot_type == report.cot_type && t.updated.elapsed() <= window)
+                    .filter(|t| report.time_ms.abs_diff(t.time_ms) <= window.as_millis() as u64)
+                    .map(|t| (t, distance_m(t.lat, t.lon, report.lat, report.lon)))
+                    .filter(|(_, d)| *d <= self.config.proximity_m)
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(t, _)| t),
+            };
+            if let Some(track) = found {
+                return Some((track.uid.clone(), *strategy));
+            }
+        }
+        None
+    }
+
+    /// Snapshot of tracks fused from more than one source
+    pub fn fused_tracks(&self) -> Vec<FusedTrack> {
+        self.state
+            .lock()
+            .tracks
+            .values()
+            .filter(|t| t.sources.len() > 1)
+            .cloned()
+            .collect()
+    }
+
+    /// Number of tracks being followed
+    pub fn track_count(&self) -> usize {
+        self.state.lock().tracks.len()
+    }
+
+    /// Forget tracks and sources that have not reported within the TTL
+    pub fn prune(&self) {
+        let ttl = Duration::from_secs(self.config.track_ttl_secs);
+        let mut state = self.state.lock();
+        state.tracks.retain(|_, track| {
+            track.sources.retain(|s| s.last_seen.elapsed() <= ttl);
+            !track.sources.is_empty()
+        });
+        let FusionState { tracks, index } = &mut *state;
+        index.retain(|(source, uid), fused| {
+            tracks.get(fused.as_str()).is_some_and(|t| {
+                t.sources
+                    .iter()
+                    .any(|s| s.source == *source && s.uid == *uid)
+            })
+        });
+    }
+}
+
+/// Fields used for correlation
+struct Report {
+    uid: String,
+    cot_type: String,
+    callsign: Option<String>,
+    serial: Option<String>,
+    lat: f64,
+    lon: f64,
+    time_ms: u64,
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+/// `serial` attribute of the `<takv>` element, if present
+fn takv_serial(data: &[u8]) -> Option<String> {
+    let xml = std::str::from_utf8(data).ok()?;
+    let start = xml.find("<takv")?;
+    let element = &xml[start..start + xml[start..].find('>')?];
+    let value_start = element.find(" serial=\"")? + " serial=\"".len();
+    let value_len = element[value_start..].find('"')?;
+    let serial = &element[value_start..value_start + value_len];
+    (!serial.is_empty()).then(|| serial.to_string())
+}
+
+pub(crate) fn escape_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+/// Re-issue a report under the fused UID with provenance detail
+fn rewrite(data: &[u8], source_uid: &str, track: &FusedTrack) -> Vec<u8> {
+    let xml = String::from_utf8_lossy(data);
+    let xml = xml.replacen(
+        &format!("uid=\"{}\"", source_uid),
+        &format!("uid=\"{}\"", track.uid),
+        1,
+    );
+
+    let sources = track
+        .sources
+        .iter()
+        .map(|s| format!("{}:{}", s.source, s.uid))
+        .collect::<Vec<_>>()
+        .join(",");
+    let provenance = format!(
+        "<_omnitak_fusion uid=\"{}\" source_uid=\"{}\" sources=\"{}\"/>",
+        escape_attr(&track.uid),
+        escape_attr(source_uid),
+        escape_attr(&sources)
+    );
+
+    let fused = if let Some(pos) = xml.rfind("</detail>") {
+        format!("{}{}{}", &xml[..pos], provenance, &xml[pos..])
+    } else if let Some(pos) = xml.rfind("<detail/>") {
+        format!(
+            "{}<detail>{}</detail>{}",
+            &xml[..pos],
+            provenance,
+            &xml[pos + "<detail/>".len()..]
+        )
+    } else if let Some(pos) = xml.rfind("</event>") {
+        format!(
+            "{}<detail>{}</detail>{}",
+            &xml[..pos],
+            provenance,
+            &xml[pos..]
+        )
+    } else {
+        xml
+    };
+    fused.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(uid: &str, callsign: &str, lat: f64, time: &str, serial: Option<&str>) -> Vec<u8> {
+        let takv = serial
+            .map(|s| {
+                format!(
+                    r#"<takv device="Pixel" platform="ATAK" os="34" version="5.2" serial="{}"/>"#,
+                    s
+                )
+            })
+            .unwrap_or_default();
+        format!(
+            r#"<event version="2.0" uid="{uid}" type="a-f-G-U-C" time="{time}" start="{time}" stale="2030-01-01T00:00:00Z" how="m-g"><point lat="{lat}" lon="-117.2" hae="0" ce="10" le="10"/><detail><contact callsign="{callsign}"/>{takv}</detail></event>"#
+        )
+        .into_bytes()
+    }
+
+    fn fused_xml(outcome: FusionOutcome) -> String {
+        match outcome {
+            FusionOutcome::Fused(data) => String::from_utf8(data).unwrap(),
+            other => panic!("expected fused report, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_callsign_correlation() {
+        let fusion = TrackFusion::new(FusionConfig::default());
+        let a = "server-a".to_string();
+        let b = "server-b".to_string();
+
+        let first = report("ANDROID-1", "VIPER-1", 34.1, "2026-01-01T00:00:00Z", None);
+        assert_eq!(fusion.process(&first, &a), FusionOutcome::Unchanged);
+
+        // Same unit relayed by another server under a different UID
+        let relayed = report("RELAY-77", "viper-1", 34.1001, "2026-01-01T00:00:01Z", None);
+        let xml = fused_xml(fusion.process(&relayed, &b));
+        assert!(xml.contains(r#"uid="ANDROID-1""#));
+        assert!(!xml.contains(r#"uid="RELAY-77" type"#));
+        assert!(xml.contains(r#"sources="server-a:ANDROID-1,server-b:RELAY-77""#));
+
+        // A late copy from the slower server is dropped
+        let late = report("ANDROID-1", "VIPER-1", 34.1, "2026-01-01T00:00:00Z", None);
+        assert_eq!(fusion.process(&late, &a), FusionOutcome::Stale);
+
+        let tracks = fusion.fused_tracks();
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(
+            tracks[0].sources[1].matched_by,
+            Some(CorrelationStrategy::Callsign)
+        );
+    }
+
+    #[test]
+    fn test_serial_and_proximity() {
+        let fusion = TrackFusion::new(FusionConfig {
+            strategies: vec![
+                CorrelationStrategy::TakvSerial,
+                CorrelationStrategy::Proximity,
+            ],
+            ..Default::default()
+        });
+        let a = "server-a".to_string();
+        let b = "server-b".to_string();
+        let c = "server-c".to_string();
+
+        let t = |s: u32| format!("2026-01-01T00:00:{:02}Z", s);
+        fusion.process(&report("U-1", "ALPHA", 34.1, &t(0), Some("SN-42")), &a);
+        let by_serial = fusion.process(&report("U-2", "BRAVO", 35.0, &t(1), Some("SN-42")), &b);
+        assert!(fused_xml(by_serial).contains(r#"uid="U-1""#));
+
+        // ~11 m away on a third server, no serial or matching callsign
+        let nearby = fusion.process(&report("U-3", "CHARLIE", 35.0001, &t(2), None), &c);
+        assert!(fused_xml(nearby).contains(r#"uid="U-1""#));
+
+        // Same time as the last fused report adds nothing
+        let repeat = fusion.process(&report("U-2", "BRAVO", 35.0, &t(2), Some("SN-42")), &b);
+        assert_eq!(repeat, FusionOutcome::Stale);
+
+        // Far away on the same server is a separate unit
+        let other = fusion.process(&report("U-4", "DELTA", 36.0, &t(2), None), &c);
+        assert_eq!(other, FusionOutcome::Unchanged);
+        assert_eq!(fusion.track_count(), 2);
+    }
+
+    #[test]
+    fn test_takv_serial() {
+        assert_eq!(
+            takv_serial(br#"<detail><takv device="x" serial="ABC123"/></detail>"#),
+            Some("ABC123".to_string())
+        );
+        assert_eq!(
+            takv_serial(br#"<detail><takv device="x"/><uid serial="no"/></detail>"#),
+            None
+        );
+    }
+}
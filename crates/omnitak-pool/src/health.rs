@@ -7,6 +7,7 @@ use anyhow::Result;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
 use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 
@@ -34,6 +35,17 @@ pub enum CircuitState {
     HalfOpen,
 }
 
+/// Circuit breaker state change for a connection
+#[derive(Debug, Clone)]
+pub struct CircuitEvent {
+    /// Connection whose circuit changed
+    pub connection_id: ConnectionId,
+    /// State before the change
+    pub previous: CircuitState,
+    /// State after the change
+    pub state: CircuitState,
+}
+
 /// Circuit breaker for a connection
 #[derive(Debug)]
 struct CircuitBreaker {
@@ -215,6 +227,8 @@ pub struct HealthMonitor {
     circuits: Arc<parking_lot::RwLock<HashMap<ConnectionId, CircuitBreaker>>>,
     /// Monitor task handle
     task: Arc<parking_lot::RwLock<Option<JoinHandle<()>>>>,
+    /// Circuit state change notifications
+    events: broadcast::Sender<CircuitEvent>,
 }
 
 impl HealthMonitor {
@@ -229,13 +243,20 @@ impl HealthMonitor {
             config,
             circuits: Arc::new(parking_lot::RwLock::new(HashMap::new())),
             task: Arc::new(parking_lot::RwLock::new(None)),
+            events: broadcast::channel(64).0,
         }
     }
 
+    /// Subscribe to circuit breaker state changes
+    pub fn subscribe(&self) -> broadcast::Receiver<CircuitEvent> {
+        self.events.subscribe()
+    }
+
     /// Start health monitoring
     pub fn start(&self, pool: Arc<ConnectionPool>) {
         let config = self.config.clone();
         let circuits = Arc::clone(&self.circuits);
+        let events = self.events.clone();
 
         let task = tokio::spawn(async move {
             info!("Health monitor started");
@@ -282,6 +303,7 @@ impl HealthMonitor {
                     // Update circuit breaker
                     let mut circuits_guard = circuits.write();
                     if let Some(circuit) = circuits_guard.get_mut(&connection.id) {
+                        let previous = circuit.state;
                         match check_result {
                             Ok(true) => {
                                 circuit.record_success();
@@ -301,6 +323,15 @@ impl HealthMonitor {
                                 }
                             }
                         }
+
+                        if circuit.state != previous {
+                            // No subscribers is fine
+                            let _ = events.send(CircuitEvent {
+                                connection_id: connection.id.clone(),
+                                previous,
+                                state: circuit.state,
+                            });
+                        }
                     }
                 }
             }
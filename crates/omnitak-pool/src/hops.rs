@@ -0,0 +1,312 @@
+//! Hop Limits
+//!
+//! Keeps messages from looping forever between chained aggregators that
+//! feed each other over ordinary TAK connections. (Federation links carry
+//! their own origin and path in the federation envelope.)
+//!
+//! At ingest the aggregator stamps each message with a
+//! `<_omnitak_hops origin=".." count=".."/>` detail. A message without a
+//! stamp gets this instance as its origin and a count of zero; a stamped
+//! message, which already passed through another aggregator, has its count
+//! raised by one. Before distribution the distributor drops messages that
+//! came back to the instance they started from, and messages that passed
+//! through more than `max_hops` aggregators.
+//!
+//! The stamp travels with the message to every destination. TAK clients
+//! and servers keep unknown detail elements, so the next aggregator along
+//! sees it. Deduplication ignores the stamp, so copies of an event that
+//! arrive over paths of different lengths are still duplicates.
+
+use metrics::{counter, describe_counter};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::fusion::escape_attr;
+
+/// Element carrying the stamp
+const STAMP_TAG: &str = "<_omnitak_hops ";
+
+/// Hop limit settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HopLimitConfig {
+    /// This instance's name in stamps; every aggregator in a chain needs a
+    /// different one. Defaults to a random ID per run.
+    #[serde(default)]
+    pub instance_id: Option<String>,
+    /// Drop messages that passed through more aggregators than this
+    #[serde(default = "default_max_hops")]
+    pub max_hops: u32,
+}
+
+fn default_max_hops() -> u32 {
+    8
+}
+
+impl Default for HopLimitConfig {
+    fn default() -> Self {
+        Self {
+            instance_id: None,
+            max_hops: default_max_hops(),
+        }
+    }
+}
+
+/// Origin and hop count stamped on a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HopStamp {
+    /// Instance that stamped the message first, as written in the stamp
+    pub origin: String,
+    /// Aggregators passed through after the origin
+    pub count: u32,
+}
+
+impl HopStamp {
+    /// Read the stamp of a message, if it has one
+    pub fn read(data: &[u8]) -> Option<Self> {
+        let xml = String::from_utf8_lossy(data);
+        let (_, element) = stamp_element(&xml)?;
+        Some(Self {
+            origin: attr(element, "origin")?.to_string(),
+            count: attr(element, "count")?.parse().ok()?,
+        })
+    }
+}
+
+/// Why a message was dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HopViolation {
+    /// The message came back to the instance it started from
+    Loop,
+    /// The message passed through more than `max_hops` aggregators
+    TooManyHops,
+}
+
+impl HopViolation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HopViolation::Loop => "loop",
+            HopViolation::TooManyHops => "max_hops",
+        }
+    }
+}
+
+/// Messages dropped by the hop limit since start
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct HopStats {
+    pub loops: u64,
+    pub max_hops_exceeded: u64,
+}
+
+/// Stamps messages at ingest and checks the stamps before distribution
+#[derive(Debug)]
+pub struct HopLimit {
+    /// Instance ID as written in stamps
+    origin: String,
+    max_hops: u32,
+    loops: AtomicU64,
+    exceeded: AtomicU64,
+}
+
+impl HopLimit {
+    pub fn new(config: HopLimitConfig) -> Self {
+        describe();
+
+        let instance_id = config
+            .instance_id
+            .unwrap_or_else(|| format!("omnitak-{}", uuid::Uuid::new_v4().simple()));
+        Self {
+            origin: escape_attr(&instance_id),
+            max_hops: config.max_hops,
+            loops: AtomicU64::new(0),
+            exceeded: AtomicU64::new(0),
+        }
+    }
+
+    /// This instance's name in stamps
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    pub fn max_hops(&self) -> u32 {
+        self.max_hops
+    }
+
+    /// Stamp a message as it enters this instance
+    ///
+    /// Messages that aren't CoT XML are returned unchanged.
+    pub fn stamp(&self, data: Vec<u8>) -> Vec<u8> {
+        match self.stamped(&data) {
+            Some(stamped) => stamped.into_bytes(),
+            None => data,
+        }
+    }
+
+    fn stamped(&self, data: &[u8]) -> Option<String> {
+        let xml = String::from_utf8_lossy(data);
+
+        if let Some((start, element)) = stamp_element(&xml) {
+            let origin = attr(element, "origin")?;
+            let count: u32 = attr(element, "count")?.parse().ok()?;
+            return Some(format!(
+                "{}{}{}",
+                &xml[..start],
+                stamp_xml(origin, count.saturating_add(1)),
+                &xml[start + element.len()..]
+            ));
+        }
+
+        let stamp = stamp_xml(&self.origin, 0);
+        if let Some(pos) = xml.rfind("</detail>") {
+            Some(format!("{}{}{}", &xml[..pos], stamp, &xml[pos..]))
+        } else if let Some(pos) = xml.rfind("<detail/>") {
+            Some(format!(
+                "{}<detail>{}</detail>{}",
+                &xml[..pos],
+                stamp,
+                &xml[pos + "<detail/>".len()..]
+            ))
+        } else {
+            let pos = xml.rfind("</event>")?;
+            Some(format!(
+                "{}<detail>{}</detail>{}",
+                &xml[..pos],
+                stamp,
+                &xml[pos..]
+            ))
+        }
+    }
+
+    /// Check a message before distribution, counting it if it must be
+    /// dropped
+    pub fn check(&self, data: &[u8]) -> Result<(), HopViolation> {
+        let Some(stamp) = HopStamp::read(data) else {
+            return Ok(());
+        };
+
+        let violation = if stamp.count > 0 && stamp.origin == self.origin {
+            self.loops.fetch_add(1, Ordering::Relaxed);
+            HopViolation::Loop
+        } else if stamp.count > self.max_hops {
+            self.exceeded.fetch_add(1, Ordering::Relaxed);
+            HopViolation::TooManyHops
+        } else {
+            return Ok(());
+        };
+        counter!("hop_limit_dropped_total", "reason" => violation.as_str()).increment(1);
+        Err(violation)
+    }
+
+    pub fn stats(&self) -> HopStats {
+        HopStats {
+            loops: self.loops.load(Ordering::Relaxed),
+            max_hops_exceeded: self.exceeded.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The message without its stamp
+pub(crate) fn unstamped(data: &[u8]) -> Cow<'_, [u8]> {
+    let tag = STAMP_TAG.as_bytes();
+    let Some(start) = data.windows(tag.len()).position(|w| w == tag) else {
+        return Cow::Borrowed(data);
+    };
+    match data[start..].windows(2).position(|w| w == b"/>") {
+        Some(len) => Cow::Owned([&data[..start], &data[start + len + 2..]].concat()),
+        None => Cow::Borrowed(data),
+    }
+}
+
+fn stamp_xml(origin: &str, count: u32) -> String {
+    format!("<_omnitak_hops origin=\"{}\" count=\"{}\"/>", origin, count)
+}
+
+/// Start offset and text of the stamp element
+fn stamp_element(xml: &str) -> Option<(usize, &str)> {
+    let start = xml.find(STAMP_TAG)?;
+    let end = xml[start..].find("/>")? + 2;
+    Some((start, &xml[start..start + end]))
+}
+
+fn attr<'a>(element: &'a str, name: &str) -> Option<&'a str> {
+    let key = format!(" {}=\"", name);
+    let start = element.find(&key)? + key.len();
+    let len = element[start..].find('"')?;
+    Some(&element[start..start + len])
+}
+
+pub(crate) fn describe() {
+    describe_counter!(
+        "hop_limit_dropped_total",
+        "Messages dropped for looping back or passing through too many aggregators"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EVENT: &[u8] =
+        b"<event uid=\"U-1\" type=\"a-f-G\"><point lat=\"1\" lon=\"2\"/><detail/></event>";
+
+    fn limit(instance_id: &str, max_hops: u32) -> HopLimit {
+        HopLimit::new(HopLimitConfig {
+            instance_id: Some(instance_id.to_string()),
+            max_hops,
+        })
+    }
+
+    #[test]
+    fn test_stamp_and_increment() {
+        let a = limit("site-a", 8);
+        let b = limit("site-b", 8);
+
+        let stamped = a.stamp(EVENT.to_vec());
+        let stamp = HopStamp::read(&stamped).unwrap();
+        assert_eq!(stamp.origin, "site-a");
+        assert_eq!(stamp.count, 0);
+        assert!(a.check(&stamped).is_ok());
+
+        let forwarded = b.stamp(stamped);
+        assert_eq!(HopStamp::read(&forwarded).unwrap().count, 1);
+        assert_eq!(
+            String::from_utf8_lossy(&forwarded)
+                .matches(STAMP_TAG)
+                .count(),
+            1
+        );
+        assert!(b.check(&forwarded).is_ok());
+
+        // Back at the origin
+        let returned = a.stamp(forwarded);
+        assert_eq!(a.check(&returned), Err(HopViolation::Loop));
+        assert_eq!(a.stats().loops, 1);
+    }
+
+    #[test]
+    fn test_max_hops() {
+        let limit = limit("site-z", 1);
+        let data = b"<event uid=\"U-1\"><detail><_omnitak_hops origin=\"site-a\" count=\"1\"/></detail></event>";
+
+        assert!(limit.check(data).is_ok());
+        let data = limit.stamp(data.to_vec());
+        assert_eq!(limit.check(&data), Err(HopViolation::TooManyHops));
+        assert_eq!(limit.stats().max_hops_exceeded, 1);
+    }
+
+    #[test]
+    fn test_unstamped_passes() {
+        let limit = limit("site-a", 0);
+        assert!(limit.check(EVENT).is_ok());
+        assert_eq!(limit.stamp(b"not xml".to_vec()), b"not xml");
+    }
+
+    #[test]
+    fn test_unstamped() {
+        let stamped = limit("site-a", 8).stamp(EVENT.to_vec());
+        let relayed = limit("site-b", 8).stamp(stamped.clone());
+        assert_eq!(unstamped(&stamped), unstamped(&relayed));
+        assert!(!String::from_utf8_lossy(&unstamped(&relayed)).contains(STAMP_TAG));
+        assert_eq!(unstamped(EVENT), EVENT);
+    }
+}
@@ -98,28 +98,60 @@
 //! ```
 
 pub mod aggregator;
+pub mod anomaly;
+pub mod coalescing;
 pub mod concurrency;
 pub mod distributor;
+pub mod emergency;
+pub mod format;
+pub mod fusion;
 pub mod health;
+pub mod hops;
 pub mod metrics;
 pub mod pool;
+pub mod quarantine;
+pub mod shedding;
+pub mod sink;
+pub mod smoothing;
+pub mod tap;
+pub mod transform;
 
 // Re-export commonly used types
-pub use aggregator::{AggregatorConfig, InboundMessage, MessageAggregator};
+pub use aggregator::{AggregatorConfig, InboundMessage, MessageAggregator, SourceDuplicates};
+pub use anomaly::{Anomaly, AnomalyConfig, AnomalyDetector, AnomalyKind};
+pub use coalescing::{CoalescingConfig, CoalescingStats, PositionCoalescer};
 pub use concurrency::{
     ConcurrencyConfig, ConcurrencyLimiter, ConnectionPermit, ConnectionRequest, Priority,
 };
 pub use distributor::{
     DistributionMessage, DistributionStrategy, DistributorConfig, FilterRule, MessageDistributor,
+    RoutePlugins,
 };
-pub use health::{CircuitState, HealthConfig, HealthMonitor, HealthStatus};
+pub use emergency::{Emergency, EmergencyError, EmergencyKind, EmergencyState, EmergencyTracker};
+pub use format::{FormatError, WireFormat};
+pub use fusion::{CorrelationStrategy, FusedTrack, FusionConfig, FusionOutcome, TrackFusion};
+pub use health::{CircuitEvent, CircuitState, HealthConfig, HealthMonitor, HealthStatus};
+pub use hops::{HopLimit, HopLimitConfig, HopStamp, HopStats, HopViolation};
 pub use metrics::{
     AggregatorMetrics, DistributorMetrics, MetricsConfig, MetricsExporter, MetricsRegistry,
-    MetricsSnapshot, PoolMetrics,
+    MetricsSnapshot, PoolMetrics, ProcessStats,
 };
 pub use pool::{
     Connection, ConnectionId, ConnectionPool, ConnectionState, PoolConfig, PoolMessage, PoolStats,
+    StatsSample, STATS_HISTORY_LEN, STATS_SAMPLE_INTERVAL,
 };
+pub use quarantine::{Quarantine, QuarantineConfig, QuarantinedMessage, SourceQuarantine};
+pub use shedding::{LoadShedder, ShedThresholds, SheddingConfig, SheddingStats, TrafficClass};
+pub use sink::{
+    connect_sink, EventSink, SinkBackend, SinkBatchConfig, SinkDefinition, SinkError, SinkRecord,
+    SinkStats,
+};
+pub use smoothing::{SmoothingConfig, TrackSmoother};
+pub use tap::{MessageTap, TapDestination, TapOutcome, TapRecord};
+pub use transform::{MessageTransformer, TransformConfig, TransformPipeline, TransformerStats};
+
+pub use omnitak_core::config::{DedupKey, DedupOverride};
+pub use omnitak_core::TraceId;
 
 /// Prelude module for convenient imports
 pub mod prelude {
@@ -183,6 +215,7 @@ mod integration_tests {
             data: b"<event uid=\"test-123\">".to_vec(),
             source: "test-source".to_string(),
             timestamp: std::time::Instant::now(),
+            trace_id: TraceId::new(),
         };
 
         aggregator.sender().send_async(msg).await.unwrap();
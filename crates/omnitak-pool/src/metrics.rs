@@ -11,9 +11,9 @@ use metrics::{counter, describe_counter, describe_gauge, describe_histogram, gau
 use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::{Duration, Instant};
-use tracing::info;
+use tracing::{info, warn};
 
 /// Pool metrics collector
 #[derive(Debug)]
@@ -26,7 +26,17 @@ pub struct PoolMetrics {
 
 impl PoolMetrics {
     pub fn new() -> Self {
-        // Describe metrics
+        Self::describe();
+
+        Self {
+            messages_sent: AtomicU64::new(0),
+            messages_received: AtomicU64::new(0),
+            connections_added: AtomicU64::new(0),
+            connections_removed: AtomicU64::new(0),
+        }
+    }
+
+    fn describe() {
         describe_counter!(
             "pool_messages_sent_total",
             "Total messages sent through pool"
@@ -40,13 +50,34 @@ impl PoolMetrics {
             "pool_connections_removed_total",
             "Total connections removed"
         );
-
-        Self {
-            messages_sent: AtomicU64::new(0),
-            messages_received: AtomicU64::new(0),
-            connections_added: AtomicU64::new(0),
-            connections_removed: AtomicU64::new(0),
-        }
+        describe_gauge!("pool_connections_total", "Connections in the pool");
+        describe_gauge!("pool_connections_active", "Connections currently active");
+        describe_gauge!(
+            "pool_connections_inactive",
+            "Connections currently inactive"
+        );
+        describe_counter!("pool_errors_total", "Total connection errors");
+        describe_gauge!(
+            "pool_connection_up",
+            "Whether a connection is active (1) or not (0)"
+        );
+        describe_counter!(
+            "pool_connection_messages_sent_total",
+            "Messages sent per connection"
+        );
+        describe_counter!(
+            "pool_connection_messages_received_total",
+            "Messages received per connection"
+        );
+        describe_counter!("pool_connection_errors_total", "Errors per connection");
+        describe_gauge!(
+            "pool_connection_queue_depth",
+            "Messages waiting in a connection channel"
+        );
+        describe_gauge!(
+            "pool_connection_queue_capacity",
+            "Capacity of a connection channel"
+        );
     }
 
     pub fn record_message_sent(&self) {
@@ -96,6 +127,18 @@ pub struct DistributorMetrics {
 
 impl DistributorMetrics {
     pub fn new() -> Self {
+        Self::describe();
+
+        Self {
+            messages_received: AtomicU64::new(0),
+            messages_sent: AtomicU64::new(0),
+            messages_dropped: AtomicU64::new(0),
+            batches_processed: AtomicU64::new(0),
+            start_time: Instant::now(),
+        }
+    }
+
+    fn describe() {
         describe_counter!(
             "distributor_messages_received_total",
             "Total messages received by distributor"
@@ -121,14 +164,6 @@ impl DistributorMetrics {
             "distributor_batch_duration_seconds",
             "Time to process each batch in seconds"
         );
-
-        Self {
-            messages_received: AtomicU64::new(0),
-            messages_sent: AtomicU64::new(0),
-            messages_dropped: AtomicU64::new(0),
-            batches_processed: AtomicU64::new(0),
-            start_time: Instant::now(),
-        }
     }
 
     pub fn record_message_received(&self) {
@@ -180,11 +215,25 @@ pub struct AggregatorMetrics {
     unique_messages: AtomicU64,
     duplicate_messages: AtomicU64,
     messages_no_uid: AtomicU64,
+    messages_filtered: AtomicU64,
     cache_cleanups: AtomicU64,
 }
 
 impl AggregatorMetrics {
     pub fn new() -> Self {
+        Self::describe();
+
+        Self {
+            messages_received: AtomicU64::new(0),
+            unique_messages: AtomicU64::new(0),
+            duplicate_messages: AtomicU64::new(0),
+            messages_no_uid: AtomicU64::new(0),
+            messages_filtered: AtomicU64::new(0),
+            cache_cleanups: AtomicU64::new(0),
+        }
+    }
+
+    fn describe() {
         describe_counter!(
             "aggregator_messages_received_total",
             "Total messages received by aggregator"
@@ -201,6 +250,10 @@ impl AggregatorMetrics {
             "aggregator_messages_no_uid_total",
             "Total messages without UID"
         );
+        describe_counter!(
+            "aggregator_messages_filtered_total",
+            "Total messages dropped by the inbound filters"
+        );
         describe_counter!(
             "aggregator_cache_cleanups_total",
             "Total cache cleanup operations"
@@ -209,14 +262,18 @@ impl AggregatorMetrics {
             "aggregator_dedup_ratio",
             "Deduplication ratio (duplicates / total)"
         );
-
-        Self {
-            messages_received: AtomicU64::new(0),
-            unique_messages: AtomicU64::new(0),
-            duplicate_messages: AtomicU64::new(0),
-            messages_no_uid: AtomicU64::new(0),
-            cache_cleanups: AtomicU64::new(0),
-        }
+        describe_histogram!(
+            "aggregator_cache_cleanup_entries",
+            "Entries removed by each cache cleanup"
+        );
+        describe_counter!(
+            "aggregator_source_messages_total",
+            "Messages per source that were new, first seen elsewhere (duplicate) or resent (repeat)"
+        );
+        describe_gauge!(
+            "aggregator_source_duplicate_ratio",
+            "Fraction of a source's messages another source delivered first"
+        );
     }
 
     pub fn record_message_received(&self) {
@@ -241,6 +298,11 @@ impl AggregatorMetrics {
         counter!("aggregator_messages_no_uid_total").increment(1);
     }
 
+    pub fn record_filtered(&self) {
+        self.messages_filtered.fetch_add(1, Ordering::Relaxed);
+        counter!("aggregator_messages_filtered_total").increment(1);
+    }
+
     pub fn record_cache_cleanup(&self, entries_removed: usize) {
         self.cache_cleanups.fetch_add(1, Ordering::Relaxed);
         counter!("aggregator_cache_cleanups_total").increment(1);
@@ -395,7 +457,11 @@ impl MetricsExporter {
 
     /// Get current metrics snapshot as string
     pub fn render(&self) -> Option<String> {
-        self.handle.as_ref().map(|h| h.render())
+        self.handle.as_ref().map(|h| {
+            // Nothing else drains histogram buckets when there is no HTTP listener
+            h.run_upkeep();
+            h.render()
+        })
     }
 }
 
@@ -405,6 +471,14 @@ pub struct MetricsRegistry {
     distributor: Arc<DistributorMetrics>,
     aggregator: Arc<AggregatorMetrics>,
     exporter: Arc<parking_lot::RwLock<MetricsExporter>>,
+    /// Worker busy time at the previous runtime update, for utilization
+    runtime_cursor: parking_lot::Mutex<Option<RuntimeCursor>>,
+}
+
+#[derive(Debug)]
+struct RuntimeCursor {
+    at: Instant,
+    busy: Vec<Duration>,
 }
 
 impl MetricsRegistry {
@@ -415,12 +489,43 @@ impl MetricsRegistry {
             distributor: Arc::new(DistributorMetrics::new()),
             aggregator: Arc::new(AggregatorMetrics::new()),
             exporter: Arc::new(parking_lot::RwLock::new(MetricsExporter::new(config))),
+            runtime_cursor: parking_lot::Mutex::new(None),
         }
     }
 
+    /// Process-wide registry with the Prometheus recorder installed
+    ///
+    /// The `metrics` recorder is global, so only one exporter can ever be
+    /// installed. Everything that renders metrics in-process shares this
+    /// registry; it never starts its own HTTP server.
+    pub fn global() -> Arc<MetricsRegistry> {
+        static GLOBAL: OnceLock<Arc<MetricsRegistry>> = OnceLock::new();
+
+        Arc::clone(GLOBAL.get_or_init(|| {
+            let registry = MetricsRegistry::new(MetricsConfig::default());
+            if let Err(e) = registry.init() {
+                warn!(error = %e, "Failed to install Prometheus recorder");
+            }
+            Arc::new(registry)
+        }))
+    }
+
     /// Initialize metrics exporter
     pub fn init(&self) -> anyhow::Result<()> {
-        self.exporter.write().init()
+        self.exporter.write().init()?;
+
+        // Descriptions given before the recorder was installed went nowhere
+        PoolMetrics::describe();
+        DistributorMetrics::describe();
+        AggregatorMetrics::describe();
+        crate::coalescing::describe();
+        crate::concurrency::describe();
+        crate::format::describe();
+        crate::hops::describe();
+        crate::quarantine::describe();
+        crate::shedding::describe();
+        describe_process();
+        Ok(())
     }
 
     /// Start metrics HTTP server
@@ -450,9 +555,148 @@ impl MetricsRegistry {
         gauge!("pool_connections_total").set(stats.total_connections as f64);
         gauge!("pool_connections_active").set(stats.active_connections as f64);
         gauge!("pool_connections_inactive").set(stats.inactive_connections as f64);
-        gauge!("pool_messages_sent_total").set(stats.total_messages_sent as f64);
-        gauge!("pool_messages_received_total").set(stats.total_messages_received as f64);
-        gauge!("pool_errors_total").set(stats.total_errors as f64);
+        counter!("pool_errors_total").absolute(stats.total_errors);
+
+        for connection in pool.get_connections_by_priority() {
+            let labels = [
+                ("connection", connection.id.clone()),
+                ("name", connection.name.clone()),
+            ];
+            let state = &connection.state;
+            gauge!("pool_connection_up", &labels).set(if state.is_active() { 1.0 } else { 0.0 });
+            counter!("pool_connection_messages_sent_total", &labels)
+                .absolute(state.messages_sent.load(Ordering::Relaxed));
+            counter!("pool_connection_messages_received_total", &labels)
+                .absolute(state.messages_received.load(Ordering::Relaxed));
+            counter!("pool_connection_errors_total", &labels)
+                .absolute(state.errors.load(Ordering::Relaxed));
+
+            for (direction, channel_len, capacity) in [
+                ("inbound", connection.tx.len(), connection.tx.capacity()),
+                ("outbound", connection.rx.len(), connection.rx.capacity()),
+            ] {
+                let labels = [
+                    ("connection", connection.id.clone()),
+                    ("direction", direction.to_string()),
+                ];
+                gauge!("pool_connection_queue_depth", &labels).set(channel_len as f64);
+                if let Some(capacity) = capacity {
+                    gauge!("pool_connection_queue_capacity", &labels).set(capacity as f64);
+                }
+            }
+        }
+    }
+
+    /// Update process resource gauges from a sample taken by the caller
+    pub fn update_process(&self, stats: &ProcessStats) {
+        gauge!("process_cpu_percent").set(stats.cpu_percent);
+        gauge!("process_resident_memory_bytes").set(stats.resident_memory_bytes as f64);
+        gauge!("process_virtual_memory_bytes").set(stats.virtual_memory_bytes as f64);
+        if let Some(fds) = stats.open_fds {
+            gauge!("process_open_fds").set(fds as f64);
+        }
+        if let Some(sockets) = stats.open_sockets {
+            gauge!("process_open_sockets").set(sockets as f64);
+        }
+        gauge!("process_uptime_seconds").set(stats.uptime.as_secs_f64());
+    }
+
+    /// Update tokio runtime gauges for the runtime the caller is on
+    pub fn update_runtime(&self) {
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let runtime = handle.metrics();
+
+        let workers = runtime.num_workers();
+        gauge!("tokio_workers").set(workers as f64);
+        gauge!("tokio_tasks_alive").set(runtime.num_alive_tasks() as f64);
+        gauge!("tokio_global_queue_depth").set(runtime.global_queue_depth() as f64);
+
+        let now = Instant::now();
+        let busy: Vec<Duration> = (0..workers)
+            .map(|worker| runtime.worker_total_busy_duration(worker))
+            .collect();
+        let mut cursor = self.runtime_cursor.lock();
+
+        // Utilization is busy time over wall time since the previous update
+        if let Some(previous) = cursor.as_ref().filter(|c| c.busy.len() == workers) {
+            let elapsed = now.duration_since(previous.at).as_secs_f64();
+            if elapsed > 0.0 {
+                let mut total = 0.0;
+                for (worker, (current, before)) in busy.iter().zip(&previous.busy).enumerate() {
+                    let ratio = (current.saturating_sub(*before).as_secs_f64() / elapsed).min(1.0);
+                    total += ratio;
+                    gauge!("tokio_worker_busy_ratio", "worker" => worker.to_string()).set(ratio);
+                }
+                gauge!("tokio_busy_ratio").set(total / workers.max(1) as f64);
+            }
+        }
+        for worker in 0..workers {
+            counter!("tokio_worker_park_total", "worker" => worker.to_string())
+                .absolute(runtime.worker_park_count(worker));
+        }
+
+        *cursor = Some(RuntimeCursor { at: now, busy });
+    }
+
+    /// Update queue occupancy gauges for the distributor and aggregator
+    /// channels
+    ///
+    /// Per-connection channels are covered by [`update_gauges`]. A depth
+    /// approaching capacity means messages are about to be dropped or
+    /// senders are about to block.
+    ///
+    /// [`update_gauges`]: MetricsRegistry::update_gauges
+    pub fn update_channels(
+        &self,
+        distributor: &crate::distributor::MessageDistributor,
+        aggregator: &crate::aggregator::MessageAggregator,
+    ) {
+        let channels = [
+            (
+                "distributor",
+                distributor.pending_count(),
+                distributor.queue_capacity(),
+            ),
+            (
+                "aggregator",
+                aggregator.pending_count(),
+                aggregator.queue_capacity(),
+            ),
+        ];
+        for (channel, depth, capacity) in channels {
+            gauge!("channel_queue_depth", "channel" => channel).set(depth as f64);
+            gauge!("channel_queue_capacity", "channel" => channel).set(capacity as f64);
+        }
+    }
+
+    /// Update per-source duplicate counters from the aggregator
+    pub fn update_duplicates(&self, aggregator: &crate::aggregator::MessageAggregator) {
+        for stats in aggregator.duplicate_stats() {
+            let outcomes = [
+                ("unique", stats.unique),
+                ("duplicate", stats.duplicates),
+                ("repeat", stats.repeats),
+            ];
+            for (outcome, count) in outcomes {
+                counter!(
+                    "aggregator_source_messages_total",
+                    "source" => stats.source.clone(),
+                    "outcome" => outcome
+                )
+                .absolute(count);
+            }
+            gauge!("aggregator_source_duplicate_ratio", "source" => stats.source.clone())
+                .set(stats.duplicate_ratio());
+        }
+    }
+
+    /// Render every metric family in Prometheus text format
+    ///
+    /// Returns `None` when the exporter is disabled or was never installed.
+    pub fn render(&self) -> Option<String> {
+        self.exporter.read().render()
     }
 
     /// Get metrics snapshot
@@ -466,6 +710,64 @@ impl MetricsRegistry {
     }
 }
 
+/// Resource usage of the running process, sampled by the caller
+#[derive(Debug, Clone, Default)]
+pub struct ProcessStats {
+    /// CPU usage since the previous sample; may exceed 100 on several cores
+    pub cpu_percent: f64,
+    pub resident_memory_bytes: u64,
+    pub virtual_memory_bytes: u64,
+    /// Open file descriptors, where the platform can count them
+    pub open_fds: Option<u64>,
+    /// Open sockets, where the platform can tell them apart
+    pub open_sockets: Option<u64>,
+    pub uptime: Duration,
+}
+
+fn describe_process() {
+    describe_gauge!(
+        "process_cpu_percent",
+        "Process CPU usage since the previous sample"
+    );
+    describe_gauge!(
+        "process_resident_memory_bytes",
+        "Resident memory size in bytes"
+    );
+    describe_gauge!(
+        "process_virtual_memory_bytes",
+        "Virtual memory size in bytes"
+    );
+    describe_gauge!("process_open_fds", "Open file descriptors");
+    describe_gauge!("process_open_sockets", "Open sockets");
+    describe_gauge!(
+        "process_uptime_seconds",
+        "Time since the process started serving"
+    );
+    describe_gauge!("tokio_workers", "Tokio worker threads");
+    describe_gauge!(
+        "tokio_worker_busy_ratio",
+        "Fraction of time each worker was busy since the previous scrape"
+    );
+    describe_gauge!(
+        "tokio_busy_ratio",
+        "Mean worker busy fraction since the previous scrape"
+    );
+    describe_counter!(
+        "tokio_worker_park_total",
+        "Times each worker parked for lack of work"
+    );
+    describe_gauge!(
+        "channel_queue_depth",
+        "Messages waiting in an internal channel"
+    );
+    describe_gauge!("channel_queue_capacity", "Capacity of an internal channel");
+    describe_gauge!("tokio_tasks_alive", "Tokio tasks alive");
+    describe_gauge!(
+        "tokio_global_queue_depth",
+        "Tasks waiting in the tokio global queue"
+    );
+}
+
 /// Metrics snapshot for reporting
 #[derive(Debug, Clone)]
 pub struct MetricsSnapshot {
@@ -519,6 +821,38 @@ mod tests {
         assert_eq!(metrics.get_dedup_ratio(), 0.5);
     }
 
+    #[tokio::test]
+    async fn test_global_render() {
+        let registry = MetricsRegistry::global();
+        registry.pool().record_message_sent();
+        registry.update_process(&ProcessStats {
+            resident_memory_bytes: 4096,
+            open_fds: Some(12),
+            ..Default::default()
+        });
+        registry.update_runtime();
+
+        let rendered = registry.render().unwrap();
+        assert!(rendered.contains("# TYPE pool_messages_sent_total counter"));
+        assert!(rendered.contains("process_resident_memory_bytes 4096"));
+        assert!(rendered.contains("process_open_fds 12"));
+        assert!(rendered.contains("tokio_workers"));
+        assert!(!rendered.contains("process_open_sockets"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_runtime_utilization() {
+        let registry = MetricsRegistry::global();
+        registry.update_runtime();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        registry.update_runtime();
+
+        let rendered = registry.render().unwrap();
+        assert!(rendered.contains("tokio_worker_busy_ratio{worker=\"1\"}"));
+        assert!(rendered.contains("tokio_busy_ratio"));
+        assert!(rendered.contains("# TYPE tokio_worker_park_total counter"));
+    }
+
     #[test]
     fn test_metrics_config_default() {
         let config = MetricsConfig::default();
@@ -6,19 +6,28 @@
 use anyhow::{Context, Result};
 use dashmap::DashMap;
 use flume::{Receiver, Sender};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 
+use crate::format::WireFormat;
 use crate::health::HealthMonitor;
 use crate::metrics::PoolMetrics;
 
 /// Unique identifier for a connection
 pub type ConnectionId = String;
 
+/// How often each connection rolls its counters into a stats sample
+pub const STATS_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Number of samples kept per connection (5 minutes at the default interval)
+pub const STATS_HISTORY_LEN: usize = 60;
+
 /// Message types that can flow through the pool
 #[derive(Debug, Clone)]
 pub enum PoolMessage {
@@ -68,6 +77,41 @@ pub struct ConnectionState {
     pub errors: AtomicU64,
     /// Last error message
     pub last_error: RwLock<Option<String>>,
+    /// Write latency accumulated since the last sample
+    write_latency: LatencyWindow,
+    /// Counters at the previous sample, used to derive rates
+    sample_cursor: Mutex<SampleCursor>,
+    /// Recent samples, oldest first
+    history: RwLock<VecDeque<StatsSample>>,
+}
+
+/// One point in a connection's rolling stats history
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsSample {
+    /// End of the sample window (epoch millis)
+    pub timestamp_ms: u64,
+    /// Messages handed to the connection per second
+    pub messages_sent_per_sec: f64,
+    /// Messages received from the connection per second
+    pub messages_received_per_sec: f64,
+    /// Mean write latency over the window, if anything was written
+    pub avg_write_latency_ms: Option<f64>,
+    /// Slowest write in the window
+    pub max_write_latency_ms: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+struct LatencyWindow {
+    total_us: AtomicU64,
+    count: AtomicU64,
+    max_us: AtomicU64,
+}
+
+#[derive(Debug)]
+struct SampleCursor {
+    at: Instant,
+    sent: u64,
+    received: u64,
 }
 
 impl ConnectionState {
@@ -79,9 +123,82 @@ impl ConnectionState {
             messages_received: AtomicU64::new(0),
             errors: AtomicU64::new(0),
             last_error: RwLock::new(None),
+            write_latency: LatencyWindow::default(),
+            sample_cursor: Mutex::new(SampleCursor {
+                at: Instant::now(),
+                sent: 0,
+                received: 0,
+            }),
+            history: RwLock::new(VecDeque::with_capacity(STATS_HISTORY_LEN)),
         }
     }
 
+    /// Record how long a single write to the remote end took
+    pub fn record_write_latency(&self, latency: Duration) {
+        let us = latency.as_micros() as u64;
+        self.write_latency.total_us.fetch_add(us, Ordering::Relaxed);
+        self.write_latency.count.fetch_add(1, Ordering::Relaxed);
+        self.write_latency.max_us.fetch_max(us, Ordering::Relaxed);
+    }
+
+    /// Close the current window and append it to the history
+    pub fn sample(&self) -> StatsSample {
+        let total_us = self.write_latency.total_us.swap(0, Ordering::Relaxed);
+        let count = self.write_latency.count.swap(0, Ordering::Relaxed);
+        let max_us = self.write_latency.max_us.swap(0, Ordering::Relaxed);
+
+        let sent = self.messages_sent.load(Ordering::Relaxed);
+        let received = self.messages_received.load(Ordering::Relaxed);
+        let now = Instant::now();
+
+        let (sent_delta, received_delta, elapsed) = {
+            let mut cursor = self.sample_cursor.lock();
+            let deltas = (
+                sent.saturating_sub(cursor.sent),
+                received.saturating_sub(cursor.received),
+                now.duration_since(cursor.at).as_secs_f64(),
+            );
+            *cursor = SampleCursor {
+                at: now,
+                sent,
+                received,
+            };
+            deltas
+        };
+
+        let per_sec = |delta: u64| {
+            if elapsed > 0.0 {
+                delta as f64 / elapsed
+            } else {
+                0.0
+            }
+        };
+
+        let sample = StatsSample {
+            timestamp_ms: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+            messages_sent_per_sec: per_sec(sent_delta),
+            messages_received_per_sec: per_sec(received_delta),
+            avg_write_latency_ms: (count > 0).then(|| total_us as f64 / count as f64 / 1000.0),
+            max_write_latency_ms: (count > 0).then(|| max_us as f64 / 1000.0),
+        };
+
+        let mut history = self.history.write();
+        if history.len() == STATS_HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(sample.clone());
+
+        sample
+    }
+
+    /// Recent stats samples, oldest first
+    pub fn history(&self) -> Vec<StatsSample> {
+        self.history.read().iter().cloned().collect()
+    }
+
     pub fn record_sent(&self) {
         self.messages_sent.fetch_add(1, Ordering::Relaxed);
         self.last_message.store(
@@ -151,6 +268,18 @@ impl Default for PoolConfig {
     }
 }
 
+impl From<&omnitak_core::config::PoolConfig> for PoolConfig {
+    fn from(config: &omnitak_core::config::PoolConfig) -> Self {
+        Self {
+            max_connections: config.max_connections,
+            channel_capacity: config.channel_capacity,
+            health_check_interval: config.health_check_interval(),
+            inactive_timeout: config.inactive_timeout(),
+            auto_reconnect: config.auto_reconnect,
+        }
+    }
+}
+
 /// Connection Pool Manager
 ///
 /// Thread-safe pool that manages thousands of concurrent connections
@@ -166,6 +295,11 @@ pub struct ConnectionPool {
     metrics: Arc<PoolMetrics>,
     /// Shutdown signal
     shutdown: Arc<AtomicBool>,
+    /// Tenant namespace per connection or message source; IDs without an
+    /// entry belong to the default namespace
+    tenants: DashMap<ConnectionId, String>,
+    /// Wire format per connection; connections without an entry get XML
+    formats: DashMap<ConnectionId, WireFormat>,
 }
 
 impl ConnectionPool {
@@ -178,6 +312,8 @@ impl ConnectionPool {
             health_monitor: Arc::new(HealthMonitor::new()),
             metrics,
             shutdown: Arc::new(AtomicBool::new(false)),
+            tenants: DashMap::new(),
+            formats: DashMap::new(),
         }
     }
 
@@ -186,6 +322,50 @@ impl ConnectionPool {
         self.connections.len()
     }
 
+    /// Place a connection or message source in a tenant namespace. Traffic
+    /// is only distributed between IDs in the same namespace.
+    pub fn set_tenant(&self, id: ConnectionId, tenant: Option<String>) {
+        match tenant {
+            Some(tenant) => {
+                self.tenants.insert(id, tenant);
+            }
+            None => {
+                self.tenants.remove(&id);
+            }
+        }
+    }
+
+    /// Tenant namespace of a connection or message source, `None` for the
+    /// default namespace
+    pub fn tenant_of(&self, id: &ConnectionId) -> Option<String> {
+        self.tenants.get(id).map(|tenant| tenant.clone())
+    }
+
+    /// Declare the format a connection wants CoT in. The distributor
+    /// converts messages for connections that don't take XML.
+    pub fn set_wire_format(&self, id: ConnectionId, format: WireFormat) {
+        match format {
+            WireFormat::Xml => {
+                self.formats.remove(&id);
+            }
+            format => {
+                self.formats.insert(id, format);
+            }
+        }
+    }
+
+    /// Wire format of a connection
+    pub fn wire_format_of(&self, id: &ConnectionId) -> WireFormat {
+        self.formats
+            .get(id)
+            .map_or(WireFormat::Xml, |format| *format)
+    }
+
+    /// Get the configured connection limit
+    pub fn max_connections(&self) -> usize {
+        self.config.max_connections
+    }
+
     /// Add a new connection to the pool
     ///
     /// Spawns a tokio task to manage the connection lifecycle.
@@ -228,6 +408,11 @@ impl ConnectionPool {
                 "Connection handler started"
             );
 
+            let mut stats_tick = tokio::time::interval_at(
+                tokio::time::Instant::now() + STATS_SAMPLE_INTERVAL,
+                STATS_SAMPLE_INTERVAL,
+            );
+
             loop {
                 if shutdown.load(Ordering::Relaxed) {
                     debug!(connection_id = %id_clone, "Shutdown signal received");
@@ -268,6 +453,11 @@ impl ConnectionPool {
                         }
                     }
 
+                    // Roll counters into the stats history
+                    _ = stats_tick.tick() => {
+                        state_clone.sample();
+                    }
+
                     // Timeout to check shutdown signal periodically
                     _ = tokio::time::sleep(Duration::from_millis(100)) => {
                         continue;
@@ -336,6 +526,8 @@ impl ConnectionPool {
         }
 
         self.metrics.record_connection_removed();
+        self.tenants.remove(id);
+        self.formats.remove(id);
 
         info!(
             connection_id = %id,
@@ -493,6 +685,33 @@ pub struct PoolStats {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_stats_sample_window() {
+        let state = ConnectionState::new();
+
+        let idle = state.sample();
+        assert_eq!(idle.avg_write_latency_ms, None);
+        assert_eq!(idle.messages_sent_per_sec, 0.0);
+
+        state.record_sent();
+        state.record_sent();
+        state.record_write_latency(Duration::from_millis(2));
+        state.record_write_latency(Duration::from_millis(6));
+
+        let busy = state.sample();
+        assert_eq!(busy.avg_write_latency_ms, Some(4.0));
+        assert_eq!(busy.max_write_latency_ms, Some(6.0));
+        assert!(busy.messages_sent_per_sec > 0.0);
+
+        // Latency accumulators reset with each window
+        assert_eq!(state.sample().avg_write_latency_ms, None);
+
+        for _ in 0..STATS_HISTORY_LEN {
+            state.sample();
+        }
+        assert_eq!(state.history().len(), STATS_HISTORY_LEN);
+    }
+
     #[tokio::test]
     async fn test_add_remove_connection() {
         let pool = ConnectionPool::new(PoolConfig::default());
@@ -0,0 +1,241 @@
+//! Strict Validation Quarantine
+//!
+//! Messages from sources in strict mode are parsed and checked against the
+//! CoT event schema (see [`omnitak_cot::validate_event_strict`]) before
+//! they are deduplicated. Messages that fail are not forwarded; they are
+//! kept, with the reason, in a bounded buffer per source where they can be
+//! inspected, and counted in `quarantined_messages_total`. Messages from
+//! other sources are not parsed.
+//!
+//! Sources are selected with [`Quarantine::set_strict`], usually from the
+//! configuration of the server or listener they come from.
+
+use chrono::{DateTime, Utc};
+use metrics::{counter, describe_counter};
+use omnitak_core::TraceId;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use tracing::debug;
+
+use crate::aggregator::MessageAggregator;
+use crate::pool::ConnectionId;
+
+/// Strict validation settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantineConfig {
+    /// Messages kept per source; the oldest are dropped first
+    #[serde(default = "default_capacity")]
+    pub capacity: usize,
+}
+
+fn default_capacity() -> usize {
+    100
+}
+
+impl Default for QuarantineConfig {
+    fn default() -> Self {
+        Self {
+            capacity: default_capacity(),
+        }
+    }
+}
+
+/// A message that failed strict validation
+#[derive(Debug, Clone)]
+pub struct QuarantinedMessage {
+    pub received_at: DateTime<Utc>,
+    pub trace_id: TraceId,
+    /// UID of the event, if one could be read
+    pub uid: Option<String>,
+    /// Why the message was rejected
+    pub reason: String,
+    pub data: Vec<u8>,
+}
+
+/// Quarantined messages of one source connection
+#[derive(Debug, Clone, Default)]
+pub struct SourceQuarantine {
+    pub source: ConnectionId,
+    /// Whether messages from the source are validated
+    pub strict: bool,
+    /// Messages quarantined since startup, including those no longer kept
+    pub total: u64,
+    /// Most recent quarantined messages, oldest first
+    pub messages: Vec<QuarantinedMessage>,
+}
+
+#[derive(Default)]
+struct SourceBuffer {
+    total: u64,
+    messages: VecDeque<QuarantinedMessage>,
+}
+
+/// Validates messages from strict sources and keeps those that fail
+pub struct Quarantine {
+    config: QuarantineConfig,
+    strict: RwLock<HashSet<ConnectionId>>,
+    buffers: Mutex<HashMap<ConnectionId, SourceBuffer>>,
+}
+
+impl Quarantine {
+    pub fn new(config: QuarantineConfig) -> Self {
+        Self {
+            config,
+            strict: RwLock::new(HashSet::new()),
+            buffers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Turn strict validation on or off for a source
+    pub fn set_strict(&self, source: ConnectionId, strict: bool) {
+        if strict {
+            self.strict.write().insert(source);
+        } else {
+            self.strict.write().remove(&source);
+        }
+    }
+
+    /// Whether messages from a source are validated
+    pub fn is_strict(&self, source: &ConnectionId) -> bool {
+        self.strict.read().contains(source)
+    }
+
+    /// Validate a message if its source is strict; returns `true` if it
+    /// was quarantined and must not be forwarded
+    pub fn inspect(&self, data: &[u8], source: &ConnectionId, trace_id: TraceId) -> bool {
+        if !self.is_strict(source) {
+            return false;
+        }
+
+        let (check, uid, reason) = match omnitak_cot::parser::parse_any(data) {
+            Err(e) => ("parse", MessageAggregator::extract_uid(data), e.to_string()),
+            Ok(event) => match omnitak_cot::validate_event_strict(&event) {
+                Ok(()) => return false,
+                Err(e) => ("schema", Some(event.uid), e.to_string()),
+            },
+        };
+
+        counter!("quarantined_messages_total", "check" => check).increment(1);
+        debug!(
+            trace_id = %trace_id,
+            source = %source,
+            uid = uid.as_deref().unwrap_or_default(),
+            reason = %reason,
+            "Message failed strict validation, quarantined"
+        );
+
+        let mut buffers = self.buffers.lock();
+        let buffer = buffers.entry(source.clone()).or_default();
+        buffer.total += 1;
+        buffer.messages.push_back(QuarantinedMessage {
+            received_at: Utc::now(),
+            trace_id,
+            uid,
+            reason,
+            data: data.to_vec(),
+        });
+        while buffer.messages.len() > self.config.capacity {
+            buffer.messages.pop_front();
+        }
+        true
+    }
+
+    /// Quarantined messages of every source that is strict or has any
+    pub fn sources(&self) -> Vec<SourceQuarantine> {
+        let strict = self.strict.read();
+        let buffers = self.buffers.lock();
+
+        let mut sources: Vec<SourceQuarantine> = buffers
+            .iter()
+            .map(|(source, buffer)| SourceQuarantine {
+                source: source.clone(),
+                strict: strict.contains(source),
+                total: buffer.total,
+                messages: buffer.messages.iter().cloned().collect(),
+            })
+            .collect();
+        sources.extend(
+            strict
+                .iter()
+                .filter(|source| !buffers.contains_key(*source))
+                .map(|source| SourceQuarantine {
+                    source: source.clone(),
+                    strict: true,
+                    ..Default::default()
+                }),
+        );
+        sources.sort_by(|a, b| a.source.cmp(&b.source));
+        sources
+    }
+
+    /// Drop the quarantined messages of a source; returns `false` if it had
+    /// none
+    pub fn clear(&self, source: &ConnectionId) -> bool {
+        self.buffers.lock().remove(source).is_some()
+    }
+}
+
+impl Default for Quarantine {
+    fn default() -> Self {
+        Self::new(QuarantineConfig::default())
+    }
+}
+
+pub(crate) fn describe() {
+    describe_counter!(
+        "quarantined_messages_total",
+        "Messages from strict sources quarantined because they failed to parse or validate"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(how: &str) -> Vec<u8> {
+        format!(
+            r#"<event version="2.0" uid="U-1" type="a-f-G" how="{how}" time="2024-01-15T10:30:00Z" start="2024-01-15T10:30:00Z" stale="2024-01-15T10:35:00Z"><point lat="37.5" lon="-122.25" hae="10" ce="5" le="5"/></event>"#
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_only_strict_sources_validated() {
+        let quarantine = Quarantine::default();
+        let source = "tak-server-a".to_string();
+
+        assert!(!quarantine.inspect(&event("machine"), &source, TraceId::new()));
+        assert!(quarantine.sources().is_empty());
+
+        quarantine.set_strict(source.clone(), true);
+        assert!(!quarantine.inspect(&event("m-g"), &source, TraceId::new()));
+        assert!(quarantine.inspect(&event("machine"), &source, TraceId::new()));
+        assert!(quarantine.inspect(b"<event uid=\"U-2\"", &source, TraceId::new()));
+
+        let sources = quarantine.sources();
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].total, 2);
+        assert_eq!(sources[0].messages[0].uid.as_deref(), Some("U-1"));
+        assert!(sources[0].messages[0].reason.contains("how"));
+        assert_eq!(sources[0].messages[1].uid.as_deref(), Some("U-2"));
+
+        assert!(quarantine.clear(&source));
+        assert_eq!(quarantine.sources()[0].total, 0);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let quarantine = Quarantine::new(QuarantineConfig { capacity: 2 });
+        let source = "atak-client-1".to_string();
+        quarantine.set_strict(source.clone(), true);
+
+        for _ in 0..5 {
+            quarantine.inspect(b"not cot", &source, TraceId::new());
+        }
+
+        let sources = quarantine.sources();
+        assert_eq!(sources[0].total, 5);
+        assert_eq!(sources[0].messages.len(), 2);
+    }
+}
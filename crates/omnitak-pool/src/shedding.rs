@@ -0,0 +1,400 @@
+//! Load Shedding
+//!
+//! Explicit overload behaviour for the aggregator. Without it, overload
+//! shows up as whatever happens to hit a full channel being dropped,
+//! emergencies included. With a [`LoadShedder`] the aggregator drops
+//! messages by traffic class instead, least valuable first:
+//!
+//! 1. position reports (`a-*`), which the next report replaces anyway
+//! 2. routine events: markers, tasking, file transfers and the rest
+//! 3. chat (`b-t-f`)
+//!
+//! Emergencies (`b-a-o-*`, or an `<emergency>` detail) are never shed.
+//!
+//! Each class has a queue threshold and a CPU threshold; it is shed while
+//! either pressure is at or above its threshold, and shedding a class
+//! always sheds the classes before it. Queue pressure is the fill of the
+//! fuller of the aggregator and distributor queues. CPU pressure is the
+//! fraction of time the tokio workers were busy, i.e. how much of the CPU
+//! the pipeline can get is in use. Both are sampled every
+//! `sample_interval_ms`.
+
+use metrics::{counter, describe_counter, describe_gauge, gauge};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Traffic classes, in the order they are shed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrafficClass {
+    /// Position reports (`a-*`)
+    Position,
+    /// Anything not in another class
+    Routine,
+    /// GeoChat (`b-t-f`)
+    Chat,
+    /// Emergency beacons and cancels, never shed
+    Emergency,
+}
+
+impl TrafficClass {
+    /// Classes that can be shed, in shedding order
+    pub const SHEDDABLE: [TrafficClass; 3] = [
+        TrafficClass::Position,
+        TrafficClass::Routine,
+        TrafficClass::Chat,
+    ];
+
+    /// Classify a CoT message without parsing it
+    pub fn of(data: &[u8]) -> Self {
+        let msg = String::from_utf8_lossy(data);
+        if msg.contains("<emergency") {
+            return TrafficClass::Emergency;
+        }
+
+        let cot_type = msg
+            .find("type=\"")
+            .map(|start| &msg[start + 6..])
+            .and_then(|rest| rest.find('"').map(|end| &rest[..end]))
+            .unwrap_or_default();
+        if cot_type.starts_with("b-a-o-") {
+            TrafficClass::Emergency
+        } else if cot_type.starts_with("b-t-f") {
+            TrafficClass::Chat
+        } else if cot_type.starts_with("a-") {
+            TrafficClass::Position
+        } else {
+            TrafficClass::Routine
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrafficClass::Position => "position",
+            TrafficClass::Routine => "routine",
+            TrafficClass::Chat => "chat",
+            TrafficClass::Emergency => "emergency",
+        }
+    }
+}
+
+/// Pressure (0-1) at which each class is shed; a class without a
+/// threshold is never shed on that signal
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShedThresholds {
+    #[serde(default)]
+    pub position: Option<f64>,
+    #[serde(default)]
+    pub routine: Option<f64>,
+    #[serde(default)]
+    pub chat: Option<f64>,
+}
+
+impl ShedThresholds {
+    fn get(&self, class: TrafficClass) -> Option<f64> {
+        match class {
+            TrafficClass::Position => self.position,
+            TrafficClass::Routine => self.routine,
+            TrafficClass::Chat => self.chat,
+            TrafficClass::Emergency => None,
+        }
+    }
+
+    /// Number of classes shed at `pressure`, counted from the first
+    fn level(&self, pressure: f64) -> usize {
+        TrafficClass::SHEDDABLE
+            .iter()
+            .rposition(|class| self.get(*class).is_some_and(|t| pressure >= t))
+            .map_or(0, |i| i + 1)
+    }
+}
+
+/// Load shedding settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SheddingConfig {
+    /// Queue fill at which each class is shed
+    #[serde(default = "default_queue_thresholds")]
+    pub queue: ShedThresholds,
+    /// Tokio worker busy fraction at which each class is shed
+    #[serde(default = "default_cpu_thresholds")]
+    pub cpu: ShedThresholds,
+    /// How often pressure is sampled
+    #[serde(default = "default_sample_interval_ms")]
+    pub sample_interval_ms: u64,
+}
+
+fn default_queue_thresholds() -> ShedThresholds {
+    ShedThresholds {
+        position: Some(0.5),
+        routine: Some(0.75),
+        chat: Some(0.95),
+    }
+}
+
+fn default_cpu_thresholds() -> ShedThresholds {
+    // Busy workers alone don't mean chat is at risk; leave that to the queues
+    ShedThresholds {
+        position: Some(0.85),
+        routine: Some(0.95),
+        chat: None,
+    }
+}
+
+fn default_sample_interval_ms() -> u64 {
+    250
+}
+
+impl Default for SheddingConfig {
+    fn default() -> Self {
+        Self {
+            queue: default_queue_thresholds(),
+            cpu: default_cpu_thresholds(),
+            sample_interval_ms: default_sample_interval_ms(),
+        }
+    }
+}
+
+impl SheddingConfig {
+    /// Returns the sampling interval as a Duration.
+    pub fn sample_interval(&self) -> Duration {
+        Duration::from_millis(self.sample_interval_ms.max(1))
+    }
+}
+
+/// Current shedding state and totals
+#[derive(Debug, Clone, Serialize)]
+pub struct SheddingStats {
+    /// Classes being shed right now
+    pub shedding: Vec<TrafficClass>,
+    pub queue_pressure: f64,
+    pub cpu_pressure: f64,
+    /// Messages shed per class since start
+    pub shed: BTreeMap<TrafficClass, u64>,
+}
+
+/// Decides which traffic classes to drop under overload
+#[derive(Debug)]
+pub struct LoadShedder {
+    config: SheddingConfig,
+    /// Number of classes currently shed, counted from the first
+    level: AtomicUsize,
+    queue_pressure: AtomicU64,
+    cpu_pressure: AtomicU64,
+    /// Messages shed, indexed like [`TrafficClass::SHEDDABLE`]
+    shed: [AtomicU64; 3],
+    /// Worker busy time at the previous CPU sample
+    cpu_cursor: Mutex<Option<(Instant, Duration)>>,
+}
+
+impl LoadShedder {
+    pub fn new(config: SheddingConfig) -> Self {
+        describe();
+
+        Self {
+            config,
+            level: AtomicUsize::new(0),
+            queue_pressure: AtomicU64::new(0f64.to_bits()),
+            cpu_pressure: AtomicU64::new(0f64.to_bits()),
+            shed: Default::default(),
+            cpu_cursor: Mutex::new(None),
+        }
+    }
+
+    pub fn config(&self) -> &SheddingConfig {
+        &self.config
+    }
+
+    /// Check a message against the current shedding level
+    ///
+    /// Returns the message's class if it should be dropped, and counts it.
+    /// Nothing is classified while no class is being shed.
+    pub fn shed(&self, data: &[u8]) -> Option<TrafficClass> {
+        let level = self.level.load(Ordering::Relaxed);
+        if level == 0 {
+            return None;
+        }
+
+        let class = TrafficClass::of(data);
+        let rank = TrafficClass::SHEDDABLE.iter().position(|c| *c == class)?;
+        if rank >= level {
+            return None;
+        }
+        self.shed[rank].fetch_add(1, Ordering::Relaxed);
+        counter!("shed_messages_total", "class" => class.as_str()).increment(1);
+        Some(class)
+    }
+
+    /// Recompute which classes are shed from the current pressures (0-1)
+    pub fn update(&self, queue_pressure: f64, cpu_pressure: f64) {
+        let level = self
+            .config
+            .queue
+            .level(queue_pressure)
+            .max(self.config.cpu.level(cpu_pressure));
+
+        self.queue_pressure
+            .store(queue_pressure.to_bits(), Ordering::Relaxed);
+        self.cpu_pressure
+            .store(cpu_pressure.to_bits(), Ordering::Relaxed);
+        gauge!("shed_queue_pressure").set(queue_pressure);
+        gauge!("shed_cpu_pressure").set(cpu_pressure);
+        gauge!("shed_level").set(level as f64);
+
+        let previous = self.level.swap(level, Ordering::Relaxed);
+        if level > previous {
+            warn!(
+                shedding = ?&TrafficClass::SHEDDABLE[..level],
+                queue_pressure,
+                cpu_pressure,
+                "Overloaded, shedding traffic"
+            );
+        } else if level < previous {
+            info!(
+                shedding = ?&TrafficClass::SHEDDABLE[..level],
+                queue_pressure,
+                cpu_pressure,
+                "Load easing, shedding less traffic"
+            );
+        }
+    }
+
+    /// Fraction of time the current runtime's workers were busy since the
+    /// previous call, 0-1
+    pub fn sample_cpu(&self) -> f64 {
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return 0.0;
+        };
+        let runtime = handle.metrics();
+        let workers = runtime.num_workers();
+        let busy: Duration = (0..workers)
+            .map(|worker| runtime.worker_total_busy_duration(worker))
+            .sum();
+        let now = Instant::now();
+
+        let previous = self.cpu_cursor.lock().replace((now, busy));
+        let Some((at, before)) = previous else {
+            return 0.0;
+        };
+        let elapsed = now.duration_since(at).as_secs_f64() * workers.max(1) as f64;
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        (busy.saturating_sub(before).as_secs_f64() / elapsed).min(1.0)
+    }
+
+    /// Current state and totals
+    pub fn stats(&self) -> SheddingStats {
+        let level = self.level.load(Ordering::Relaxed);
+        SheddingStats {
+            shedding: TrafficClass::SHEDDABLE[..level].to_vec(),
+            queue_pressure: f64::from_bits(self.queue_pressure.load(Ordering::Relaxed)),
+            cpu_pressure: f64::from_bits(self.cpu_pressure.load(Ordering::Relaxed)),
+            shed: TrafficClass::SHEDDABLE
+                .iter()
+                .zip(&self.shed)
+                .map(|(class, count)| (*class, count.load(Ordering::Relaxed)))
+                .collect(),
+        }
+    }
+}
+
+/// Fraction of `capacity` in use, 0-1
+pub(crate) fn fill(used: usize, capacity: usize) -> f64 {
+    if capacity == 0 {
+        return 1.0;
+    }
+    (used as f64 / capacity as f64).clamp(0.0, 1.0)
+}
+
+pub(crate) fn describe() {
+    describe_counter!(
+        "shed_messages_total",
+        "Messages dropped by load shedding, by traffic class"
+    );
+    describe_gauge!(
+        "shed_level",
+        "Traffic classes being shed (0 none, 3 everything but emergencies)"
+    );
+    describe_gauge!(
+        "shed_queue_pressure",
+        "Fill of the fuller of the aggregator and distributor queues"
+    );
+    describe_gauge!(
+        "shed_cpu_pressure",
+        "Tokio worker busy fraction seen by the load shedder"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(cot_type: &str) -> Vec<u8> {
+        format!(
+            r#"<event version="2.0" uid="U-1" type="{}" how="m-g"/>"#,
+            cot_type
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            TrafficClass::of(&event("a-f-G-U-C")),
+            TrafficClass::Position
+        );
+        assert_eq!(TrafficClass::of(&event("b-t-f")), TrafficClass::Chat);
+        assert_eq!(
+            TrafficClass::of(&event("b-a-o-tbl")),
+            TrafficClass::Emergency
+        );
+        assert_eq!(TrafficClass::of(&event("t-x-d-d")), TrafficClass::Routine);
+        assert_eq!(
+            TrafficClass::of(b"<event type=\"a-f-G\"><detail><emergency/></detail></event>"),
+            TrafficClass::Emergency
+        );
+    }
+
+    #[test]
+    fn test_sheds_lowest_classes_first() {
+        let shedder = LoadShedder::new(SheddingConfig::default());
+        assert_eq!(shedder.shed(&event("a-f-G")), None);
+
+        shedder.update(0.6, 0.0);
+        assert_eq!(shedder.shed(&event("a-f-G")), Some(TrafficClass::Position));
+        assert_eq!(shedder.shed(&event("t-x-d-d")), None);
+
+        // CPU alone sheds routine traffic but never chat by default
+        shedder.update(0.0, 1.0);
+        assert_eq!(shedder.shed(&event("t-x-d-d")), Some(TrafficClass::Routine));
+        assert_eq!(shedder.shed(&event("b-t-f")), None);
+
+        shedder.update(1.0, 1.0);
+        assert_eq!(shedder.shed(&event("b-t-f")), Some(TrafficClass::Chat));
+        assert_eq!(shedder.shed(&event("b-a-o-tbl")), None);
+
+        shedder.update(0.1, 0.1);
+        assert_eq!(shedder.shed(&event("a-f-G")), None);
+
+        let stats = shedder.stats();
+        assert!(stats.shedding.is_empty());
+        assert_eq!(stats.shed[&TrafficClass::Position], 1);
+        assert_eq!(stats.shed[&TrafficClass::Routine], 1);
+        assert_eq!(stats.shed[&TrafficClass::Chat], 1);
+    }
+
+    #[test]
+    fn test_unset_threshold_sheds_earlier_classes() {
+        let thresholds = ShedThresholds {
+            position: None,
+            routine: Some(0.5),
+            chat: None,
+        };
+        assert_eq!(thresholds.level(0.4), 0);
+        assert_eq!(thresholds.level(0.5), 2);
+    }
+}
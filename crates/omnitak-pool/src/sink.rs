@@ -0,0 +1,413 @@
+//! Output Sinks
+//!
+//! Ships every distributed CoT event to external data platforms (Kafka, NATS)
+//! for analytics pipelines. Each sink gets its own bounded queue and batching
+//! task, so a slow or unreachable platform never stalls distribution to TAK
+//! connections: when a sink queue is full, events for that sink are dropped
+//! and counted.
+//!
+//! Events are keyed by CoT UID so a platform can partition on it and keep
+//! each track's updates in order.
+
+use crate::pool::ConnectionId;
+use async_trait::async_trait;
+use flume::{Receiver, Sender};
+use metrics::{counter, describe_counter, describe_histogram, histogram};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "nats")]
+pub mod nats;
+
+/// Sink errors
+#[derive(Debug, Error)]
+pub enum SinkError {
+    #[error("Sink connection failed: {0}")]
+    Connect(String),
+
+    #[error("Delivery failed: {0}")]
+    Delivery(String),
+
+    #[error("{0} sink support not compiled in (enable the `{1}` feature)")]
+    Unsupported(&'static str, &'static str),
+}
+
+/// A single event handed to a sink
+#[derive(Debug, Clone)]
+pub struct SinkRecord {
+    /// Partition key (CoT UID, empty if the event has none)
+    pub key: String,
+    /// Raw CoT XML
+    pub payload: Vec<u8>,
+    /// Connection the event was received from, if any
+    pub source: Option<ConnectionId>,
+    /// When the event entered the distributor
+    pub timestamp: Instant,
+}
+
+/// Destination for batches of events
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    /// Short name used in logs and metrics labels
+    fn name(&self) -> &str;
+
+    /// Deliver a batch; the whole batch is counted as failed on error
+    async fn send_batch(&self, records: &[SinkRecord]) -> Result<(), SinkError>;
+}
+
+/// Batching settings shared by all sinks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SinkBatchConfig {
+    /// Maximum events per batch
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Maximum time an event waits for its batch to fill
+    #[serde(default = "default_linger_ms")]
+    pub linger_ms: u64,
+    /// Events queued per sink before new events are dropped
+    #[serde(default = "default_queue_capacity")]
+    pub queue_capacity: usize,
+}
+
+fn default_batch_size() -> usize {
+    500
+}
+
+fn default_linger_ms() -> u64 {
+    100
+}
+
+fn default_queue_capacity() -> usize {
+    50_000
+}
+
+impl Default for SinkBatchConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: default_batch_size(),
+            linger_ms: default_linger_ms(),
+            queue_capacity: default_queue_capacity(),
+        }
+    }
+}
+
+/// Kafka sink settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KafkaSinkConfig {
+    /// Bootstrap servers (`host:port,host:port`)
+    pub brokers: String,
+    pub topic: String,
+    /// Extra librdkafka properties (e.g. `security.protocol`, `compression.type`)
+    #[serde(default)]
+    pub properties: std::collections::HashMap<String, String>,
+}
+
+/// NATS sink settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NatsSinkConfig {
+    /// Server URL (`nats://host:4222`)
+    pub url: String,
+    /// Subject prefix; events go to `<subject>.<partition>`
+    pub subject: String,
+    /// Number of UID partitions; 0 publishes everything on `subject`
+    #[serde(default)]
+    pub partitions: u32,
+    #[serde(default)]
+    pub credentials_file: Option<String>,
+}
+
+/// Configured sink backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkBackend {
+    Kafka(KafkaSinkConfig),
+    Nats(NatsSinkConfig),
+}
+
+/// A sink definition as it appears in configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SinkDefinition {
+    #[serde(flatten)]
+    pub backend: SinkBackend,
+    #[serde(default)]
+    pub batch: SinkBatchConfig,
+}
+
+/// Connect the sink described by a backend configuration
+pub async fn connect_sink(backend: &SinkBackend) -> Result<Arc<dyn EventSink>, SinkError> {
+    match backend {
+        #[cfg(feature = "kafka")]
+        SinkBackend::Kafka(config) => Ok(Arc::new(kafka::KafkaSink::new(config)?)),
+        #[cfg(not(feature = "kafka"))]
+        SinkBackend::Kafka(_) => Err(SinkError::Unsupported("Kafka", "kafka")),
+        #[cfg(feature = "nats")]
+        SinkBackend::Nats(config) => Ok(Arc::new(nats::NatsSink::connect(config).await?)),
+        #[cfg(not(feature = "nats"))]
+        SinkBackend::Nats(_) => Err(SinkError::Unsupported("NATS", "nats")),
+    }
+}
+
+/// Stable partition for a key (FNV-1a, so it never changes across restarts)
+pub fn partition_for(key: &str, partitions: u32) -> u32 {
+    if partitions == 0 {
+        return 0;
+    }
+    let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    (hash % partitions as u64) as u32
+}
+
+/// Delivery counters for one sink
+#[derive(Debug, Default)]
+pub struct SinkMetrics {
+    records_sent: AtomicU64,
+    records_failed: AtomicU64,
+    records_dropped: AtomicU64,
+    batches_sent: AtomicU64,
+    batches_failed: AtomicU64,
+    last_error: parking_lot::Mutex<Option<String>>,
+}
+
+/// Point-in-time view of a sink's delivery metrics
+#[derive(Debug, Clone, Serialize)]
+pub struct SinkStats {
+    pub name: String,
+    pub records_sent: u64,
+    pub records_failed: u64,
+    pub records_dropped: u64,
+    pub batches_sent: u64,
+    pub batches_failed: u64,
+    pub queued: usize,
+    pub last_error: Option<String>,
+}
+
+/// A running sink: queue plus batching task
+pub(crate) struct SinkHandle {
+    name: String,
+    tx: Sender<SinkRecord>,
+    rx: Receiver<SinkRecord>,
+    metrics: Arc<SinkMetrics>,
+    task: JoinHandle<()>,
+}
+
+impl SinkHandle {
+    pub(crate) fn spawn(sink: Arc<dyn EventSink>, config: SinkBatchConfig) -> Self {
+        describe_counter!(
+            "sink_records_sent_total",
+            "Events delivered to output sinks"
+        );
+        describe_counter!(
+            "sink_records_failed_total",
+            "Events whose batch failed to deliver"
+        );
+        describe_counter!(
+            "sink_records_dropped_total",
+            "Events dropped because a sink queue was full"
+        );
+        describe_histogram!(
+            "sink_batch_duration_seconds",
+            "Time to deliver each sink batch in seconds"
+        );
+
+        let name = sink.name().to_string();
+        let (tx, rx) = flume::bounded(config.queue_capacity.max(1));
+        let metrics = Arc::new(SinkMetrics::default());
+        let task = tokio::spawn(Self::run(sink, config, rx.clone(), Arc::clone(&metrics)));
+
+        Self {
+            name,
+            tx,
+            rx,
+            metrics,
+            task,
+        }
+    }
+
+    /// Queue an event without waiting; counts a drop if the queue is full
+    pub(crate) fn offer(&self, record: SinkRecord) {
+        if self.tx.try_send(record).is_err() {
+            self.metrics.records_dropped.fetch_add(1, Ordering::Relaxed);
+            counter!("sink_records_dropped_total", "sink" => self.name.clone()).increment(1);
+        }
+    }
+
+    pub(crate) fn stats(&self) -> SinkStats {
+        let m = &self.metrics;
+        SinkStats {
+            name: self.name.clone(),
+            records_sent: m.records_sent.load(Ordering::Relaxed),
+            records_failed: m.records_failed.load(Ordering::Relaxed),
+            records_dropped: m.records_dropped.load(Ordering::Relaxed),
+            batches_sent: m.batches_sent.load(Ordering::Relaxed),
+            batches_failed: m.batches_failed.load(Ordering::Relaxed),
+            queued: self.rx.len(),
+            last_error: m.last_error.lock().clone(),
+        }
+    }
+
+    pub(crate) fn abort(&self) {
+        self.task.abort();
+    }
+
+    async fn run(
+        sink: Arc<dyn EventSink>,
+        config: SinkBatchConfig,
+        rx: Receiver<SinkRecord>,
+        metrics: Arc<SinkMetrics>,
+    ) {
+        let name = sink.name().to_string();
+        let linger = Duration::from_millis(config.linger_ms);
+        let mut batch = Vec::with_capacity(config.batch_size);
+
+        // Wait for the first event, then give the batch up to `linger` to fill
+        while let Ok(first) = rx.recv_async().await {
+            batch.push(first);
+            let deadline = tokio::time::Instant::now() + linger;
+            while batch.len() < config.batch_size {
+                match tokio::time::timeout_at(deadline, rx.recv_async()).await {
+                    Ok(Ok(record)) => batch.push(record),
+                    _ => break,
+                }
+            }
+
+            let started = Instant::now();
+            let count = batch.len() as u64;
+            match sink.send_batch(&batch).await {
+                Ok(()) => {
+                    metrics.records_sent.fetch_add(count, Ordering::Relaxed);
+                    metrics.batches_sent.fetch_add(1, Ordering::Relaxed);
+                    counter!("sink_records_sent_total", "sink" => name.clone()).increment(count);
+                    debug!(sink = %name, count, "Sink batch delivered");
+                }
+                Err(e) => {
+                    metrics.records_failed.fetch_add(count, Ordering::Relaxed);
+                    metrics.batches_failed.fetch_add(1, Ordering::Relaxed);
+                    *metrics.last_error.lock() = Some(e.to_string());
+                    counter!("sink_records_failed_total", "sink" => name.clone()).increment(count);
+                    warn!(sink = %name, count, error = %e, "Sink batch delivery failed");
+                }
+            }
+            histogram!("sink_batch_duration_seconds", "sink" => name.clone())
+                .record(started.elapsed().as_secs_f64());
+            batch.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MemorySink {
+        batches: parking_lot::Mutex<Vec<Vec<String>>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl EventSink for MemorySink {
+        fn name(&self) -> &str {
+            "memory"
+        }
+
+        async fn send_batch(&self, records: &[SinkRecord]) -> Result<(), SinkError> {
+            if self.fail {
+                return Err(SinkError::Delivery("broker down".to_string()));
+            }
+            self.batches
+                .lock()
+                .push(records.iter().map(|r| r.key.clone()).collect());
+            Ok(())
+        }
+    }
+
+    fn record(key: &str) -> SinkRecord {
+        SinkRecord {
+            key: key.to_string(),
+            payload: Vec::new(),
+            source: None,
+            timestamp: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn test_partition_for_is_stable() {
+        assert_eq!(partition_for("ANDROID-1", 0), 0);
+        let p = partition_for("ANDROID-1", 8);
+        assert!(p < 8);
+        assert_eq!(partition_for("ANDROID-1", 8), p);
+        // Keys spread across partitions
+        let used: std::collections::HashSet<u32> = (0..64)
+            .map(|i| partition_for(&format!("uid-{}", i), 8))
+            .collect();
+        assert!(used.len() > 4);
+    }
+
+    #[tokio::test]
+    async fn test_sink_batches_and_counts() {
+        let sink = Arc::new(MemorySink {
+            batches: parking_lot::Mutex::new(Vec::new()),
+            fail: false,
+        });
+        let config = SinkBatchConfig {
+            batch_size: 2,
+            linger_ms: 20,
+            queue_capacity: 10,
+        };
+        let handle = SinkHandle::spawn(Arc::clone(&sink) as Arc<dyn EventSink>, config);
+        for key in ["a", "b", "c"] {
+            handle.offer(record(key));
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let batches = sink.batches.lock().clone();
+        assert_eq!(batches, vec![vec!["a", "b"], vec!["c"]]);
+        let stats = handle.stats();
+        assert_eq!(stats.records_sent, 3);
+        assert_eq!(stats.batches_sent, 2);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_sink_failures_and_drops() {
+        let sink = Arc::new(MemorySink {
+            batches: parking_lot::Mutex::new(Vec::new()),
+            fail: true,
+        });
+        let config = SinkBatchConfig {
+            batch_size: 10,
+            linger_ms: 10,
+            queue_capacity: 1,
+        };
+        let handle = SinkHandle::spawn(sink, config);
+        handle.offer(record("a"));
+        handle.offer(record("b"));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let stats = handle.stats();
+        assert_eq!(stats.records_failed, 1);
+        assert_eq!(stats.records_dropped, 1);
+        assert_eq!(
+            stats.last_error.as_deref(),
+            Some("Delivery failed: broker down")
+        );
+        handle.abort();
+    }
+
+    #[test]
+    fn test_sink_definition_config() {
+        let def: SinkDefinition = serde_json::from_str(
+            r#"{"type": "nats", "url": "nats://localhost:4222", "subject": "cot", "partitions": 4}"#,
+        )
+        .unwrap();
+        assert!(matches!(def.backend, SinkBackend::Nats(ref n) if n.partitions == 4));
+        assert_eq!(def.batch.batch_size, 500);
+    }
+}
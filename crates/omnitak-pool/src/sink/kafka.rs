@@ -0,0 +1,80 @@
+//! Kafka output sink
+//!
+//! Produces each event keyed by CoT UID. The `murmur2_random` partitioner
+//! matches the Java client, so all updates for a track land on the same
+//! partition regardless of which producer wrote them.
+
+use async_trait::async_trait;
+use futures::future::join_all;
+use rdkafka::config::ClientConfig;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::util::Timeout;
+
+use super::{EventSink, KafkaSinkConfig, SinkError, SinkRecord};
+
+/// Kafka producer
+pub struct KafkaSink {
+    producer: FutureProducer,
+    topic: String,
+    name: String,
+}
+
+impl KafkaSink {
+    /// Create the producer; brokers are contacted lazily
+    pub fn new(config: &KafkaSinkConfig) -> Result<Self, SinkError> {
+        let mut client = ClientConfig::new();
+        client
+            .set("bootstrap.servers", &config.brokers)
+            .set("partitioner", "murmur2_random")
+            .set("linger.ms", "5");
+        for (key, value) in &config.properties {
+            client.set(key, value);
+        }
+        let producer = client
+            .create()
+            .map_err(|e| SinkError::Connect(e.to_string()))?;
+
+        Ok(Self {
+            producer,
+            topic: config.topic.clone(),
+            name: format!("kafka:{}", config.topic),
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for KafkaSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn send_batch(&self, records: &[SinkRecord]) -> Result<(), SinkError> {
+        // Enqueue the whole batch, then wait for every delivery report
+        let deliveries = records.iter().map(|record| {
+            let mut message = FutureRecord::to(&self.topic).payload(&record.payload);
+            if !record.key.is_empty() {
+                message = message.key(&record.key);
+            }
+            self.producer.send(message, Timeout::Never)
+        });
+
+        let mut failed = 0;
+        let mut last_error = None;
+        for result in join_all(deliveries).await {
+            if let Err((e, _)) = result {
+                failed += 1;
+                last_error = Some(e);
+            }
+        }
+
+        match last_error {
+            None => Ok(()),
+            Some(e) => Err(SinkError::Delivery(format!(
+                "{} of {} events failed: {}",
+                failed,
+                records.len(),
+                e
+            ))),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! NATS output sink
+//!
+//! Publishes each event on `<subject>.<partition>`, where the partition is
+//! derived from the CoT UID, so a queue group member per partition sees every
+//! update for its tracks in order.
+
+use async_trait::async_trait;
+
+use super::{partition_for, EventSink, NatsSinkConfig, SinkError, SinkRecord};
+
+/// Core NATS publisher
+pub struct NatsSink {
+    client: async_nats::Client,
+    subject: String,
+    partitions: u32,
+    name: String,
+}
+
+impl NatsSink {
+    /// Connect to the NATS server
+    pub async fn connect(config: &NatsSinkConfig) -> Result<Self, SinkError> {
+        let mut options = async_nats::ConnectOptions::new().name("omnitak");
+        if let Some(path) = &config.credentials_file {
+            options = options
+                .credentials_file(path)
+                .await
+                .map_err(|e| SinkError::Connect(format!("credentials {}: {}", path, e)))?;
+        }
+        let client = options
+            .connect(config.url.as_str())
+            .await
+            .map_err(|e| SinkError::Connect(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            subject: config.subject.clone(),
+            partitions: config.partitions,
+            name: format!("nats:{}", config.subject),
+        })
+    }
+
+    fn subject_for(&self, key: &str) -> String {
+        if self.partitions == 0 {
+            self.subject.clone()
+        } else {
+            format!("{}.{}", self.subject, partition_for(key, self.partitions))
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for NatsSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn send_batch(&self, records: &[SinkRecord]) -> Result<(), SinkError> {
+        for record in records {
+            self.client
+                .publish(self.subject_for(&record.key), record.payload.clone().into())
+                .await
+                .map_err(|e| SinkError::Delivery(e.to_string()))?;
+        }
+        // Publishes are buffered; flush so the batch is on the wire
+        self.client
+            .flush()
+            .await
+            .map_err(|e| SinkError::Delivery(e.to_string()))
+    }
+}
@@ -0,0 +1,432 @@
+//! Track Smoothing and Dead Reckoning
+//!
+//! Phone GPS and relayed tracks jitter by tens of meters between reports,
+//! and slow feeds leave map symbols frozen between updates. The smoothing
+//! stage runs an alpha-beta filter per track UID and, for the destinations
+//! of routes with `smoothing: true` (and any connections listed in
+//! [`SmoothingConfig::connections`]), replaces the reported position with
+//! the filtered one. Other connections keep receiving the raw reports, and
+//! tracks no smoothing destination receives are not filtered at all.
+//!
+//! With `extrapolate` enabled, tracks that have not reported for a while
+//! are projected forward along their filtered velocity and re-sent to the
+//! destinations their last report was smoothed for, up to
+//! `max_extrapolation_secs` after the last real report.
+//!
+//! Every position the stage changes carries an `<_omnitak_estimated>`
+//! detail naming the method and whether it was smoothed or extrapolated;
+//! extrapolated reports also get `how="m-p"` (machine predicted).
+//!
+//! Only XML atom (`a-*`) events are smoothed; everything else passes through.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use omnitak_cot::geodesy::{bearing_deg, destination, distance_m};
+use omnitak_cot::parse_cot_borrowed;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::pool::{ConnectionId, ConnectionPool, PoolMessage};
+
+/// Tracks slower than this are not extrapolated
+const MIN_EXTRAPOLATION_SPEED_MPS: f64 = 0.5;
+
+/// Track smoothing settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmoothingConfig {
+    /// Connections that receive smoothed and extrapolated positions for
+    /// every track, in addition to the destinations of smoothing routes
+    #[serde(default)]
+    pub connections: Vec<ConnectionId>,
+    /// Position gain (0-1); lower is smoother but lags more
+    #[serde(default = "default_alpha")]
+    pub alpha: f64,
+    /// Velocity gain (0-1)
+    #[serde(default = "default_beta")]
+    pub beta: f64,
+    /// Send dead-reckoned positions between reports
+    #[serde(default)]
+    pub extrapolate: bool,
+    /// How often extrapolated positions are sent
+    #[serde(default = "default_extrapolate_interval_ms")]
+    pub extrapolate_interval_ms: u64,
+    /// Stop extrapolating this long after the last real report
+    #[serde(default = "default_max_extrapolation_secs")]
+    pub max_extrapolation_secs: u64,
+    /// Forget tracks not updated for this long
+    #[serde(default = "default_track_ttl_secs")]
+    pub track_ttl_secs: u64,
+}
+
+fn default_alpha() -> f64 {
+    0.5
+}
+
+fn default_beta() -> f64 {
+    0.2
+}
+
+fn default_extrapolate_interval_ms() -> u64 {
+    1000
+}
+
+fn default_max_extrapolation_secs() -> u64 {
+    10
+}
+
+fn default_track_ttl_secs() -> u64 {
+    300
+}
+
+impl Default for SmoothingConfig {
+    fn default() -> Self {
+        Self {
+            connections: Vec::new(),
+            alpha: default_alpha(),
+            beta: default_beta(),
+            extrapolate: false,
+            extrapolate_interval_ms: default_extrapolate_interval_ms(),
+            max_extrapolation_secs: default_max_extrapolation_secs(),
+            track_ttl_secs: default_track_ttl_secs(),
+        }
+    }
+}
+
+/// Filter state for one track
+struct FilterState {
+    lat: f64,
+    lon: f64,
+    /// Velocity north and east in meters per second
+    v_north: f64,
+    v_east: f64,
+    /// Event time of the last report
+    time: DateTime<Utc>,
+    /// Last report as received, the template for extrapolated reports
+    report: Vec<u8>,
+    /// Route destinations (connection IDs or names) the last report was
+    /// smoothed for
+    destinations: Vec<String>,
+    /// When the last real report arrived
+    updated: Instant,
+    /// When the last extrapolated report was sent
+    extrapolated: Option<Instant>,
+}
+
+impl FilterState {
+    fn speed(&self) -> f64 {
+        self.v_north.hypot(self.v_east)
+    }
+
+    fn course(&self) -> f64 {
+        bearing_from(self.v_north, self.v_east)
+    }
+
+    /// Position after moving along the filtered velocity for `secs`
+    fn predict(&self, secs: f64) -> (f64, f64) {
+        let speed = self.speed();
+        if speed <= 0.0 || secs <= 0.0 {
+            return (self.lat, self.lon);
+        }
+        destination(self.lat, self.lon, self.course(), speed * secs)
+    }
+}
+
+/// Alpha-beta filter over the tracks sent to smoothing destinations
+pub struct TrackSmoother {
+    config: SmoothingConfig,
+    /// Filter state per track UID; sharded so distribution workers only
+    /// contend on the same tracks
+    tracks: DashMap<String, FilterState>,
+}
+
+impl TrackSmoother {
+    pub fn new(config: SmoothingConfig) -> Self {
+        Self {
+            config,
+            tracks: DashMap::new(),
+        }
+    }
+
+    /// Whether a connection receives smoothed positions for every track
+    pub fn is_selected(&self, connection_id: &ConnectionId) -> bool {
+        self.config.connections.contains(connection_id)
+    }
+
+    /// Whether any connection receives smoothed positions for every track
+    pub fn has_connections(&self) -> bool {
+        !self.config.connections.is_empty()
+    }
+
+    /// Update the filter with a report and return it with the smoothed
+    /// position, or `None` if the report is not a track position.
+    /// `destinations` are those of the smoothing routes it matched
+    pub fn process(&self, data: &[u8], destinations: &[String]) -> Option<Vec<u8>> {
+        if !data.starts_with(b"<") {
+            return None;
+        }
+        let event = parse_cot_borrowed(std::str::from_utf8(data).ok()?).ok()?;
+        if !event.event_type.starts_with("a-") {
+            return None;
+        }
+        let (lat, lon) = (event.point.lat, event.point.lon);
+
+        let ttl = Duration::from_secs(self.config.track_ttl_secs);
+        let Some(mut state) = self
+            .tracks
+            .get_mut(event.uid)
+            .filter(|state| state.updated.elapsed() <= ttl)
+        else {
+            self.tracks.insert(
+                event.uid.to_string(),
+                FilterState {
+                    lat,
+                    lon,
+                    v_north: 0.0,
+                    v_east: 0.0,
+                    time: event.time,
+                    report: data.to_vec(),
+                    destinations: destinations.to_vec(),
+                    updated: Instant::now(),
+                    extrapolated: None,
+                },
+            );
+            return None;
+        };
+
+        let dt = (event.time - state.time).num_milliseconds() as f64 / 1000.0;
+        if dt > 0.0 {
+            // Predict, then correct by a fraction of the residual
+            let (pred_lat, pred_lon) = state.predict(dt);
+            let residual = distance_m(pred_lat, pred_lon, lat, lon);
+            let bearing = bearing_deg(pred_lat, pred_lon, lat, lon);
+            let (r_north, r_east) = (
+                residual * bearing.to_radians().cos(),
+                residual * bearing.to_radians().sin(),
+            );
+            let (new_lat, new_lon) =
+                destination(pred_lat, pred_lon, bearing, self.config.alpha * residual);
+            state.lat = new_lat;
+            state.lon = new_lon;
+            state.v_north += self.config.beta * r_north / dt;
+            state.v_east += self.config.beta * r_east / dt;
+            state.time = event.time;
+        }
+        state.report = data.to_vec();
+        if state.destinations != destinations {
+            state.destinations = destinations.to_vec();
+        }
+        state.updated = Instant::now();
+        state.extrapolated = None;
+
+        Some(estimated(data, state.lat, state.lon, None, "smoothed"))
+    }
+
+    /// Dead-reckoned reports for tracks that are due one, at `now`, with
+    /// the route destinations each is for
+    pub fn extrapolate(&self, now: DateTime<Utc>) -> Vec<(Vec<u8>, Vec<String>)> {
+        let interval = Duration::from_millis(self.config.extrapolate_interval_ms);
+        let max_age = Duration::from_secs(self.config.max_extrapolation_secs);
+        self.tracks
+            .iter_mut()
+            .filter(|state| {
+                let age = state.updated.elapsed();
+                age >= interval
+                    && age <= max_age
+                    && state.extrapolated.is_none_or(|t| t.elapsed() >= interval)
+                    && state.speed() >= MIN_EXTRAPOLATION_SPEED_MPS
+            })
+            .map(|mut state| {
+                let secs = (now - state.time).num_milliseconds() as f64 / 1000.0;
+                let (lat, lon) = state.predict(secs);
+                state.extrapolated = Some(Instant::now());
+                let report = estimated(&state.report, lat, lon, Some(now), "extrapolated");
+                (report, state.destinations.clone())
+            })
+            .collect()
+    }
+
+    /// Number of tracks being filtered
+    pub fn track_count(&self) -> usize {
+        self.tracks.len()
+    }
+
+    /// Forget tracks that have not reported within the TTL
+    pub fn prune(&self) {
+        let ttl = Duration::from_secs(self.config.track_ttl_secs);
+        self.tracks
+            .retain(|_, state| state.updated.elapsed() <= ttl);
+    }
+
+    /// Start pruning, and extrapolating if enabled, in the background.
+    /// Extrapolated reports go straight to the connections they are for.
+    pub fn start(self: Arc<Self>, pool: Arc<ConnectionPool>) {
+        let period = Duration::from_millis(self.config.extrapolate_interval_ms.max(100));
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                self.prune();
+                if !self.config.extrapolate {
+                    continue;
+                }
+
+                let reports = self.extrapolate(Utc::now());
+                if reports.is_empty() {
+                    continue;
+                }
+                let connections = pool.get_active_connections();
+                for (report, destinations) in &reports {
+                    for connection in connections.iter().filter(|c| {
+                        self.is_selected(&c.id)
+                            || destinations.iter().any(|d| *d == c.id || *d == c.name)
+                    }) {
+                        if connection
+                            .tx
+                            .try_send(PoolMessage::Cot(report.clone()))
+                            .is_err()
+                        {
+                            debug!(connection_id = %connection.id, "Dropped extrapolated report");
+                        } else {
+                            connection.state.record_sent();
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Course in degrees for a north/east velocity
+fn bearing_from(v_north: f64, v_east: f64) -> f64 {
+    v_east.atan2(v_north).to_degrees().rem_euclid(360.0)
+}
+
+/// Replace the value of `attr` in the first `<element` tag
+fn set_attr(xml: &str, element: &str, attr: &str, value: &str) -> String {
+    let Some(start) = xml.find(&format!("<{}", element)) else {
+        return xml.to_string();
+    };
+    let end = start + xml[start..].find('>').unwrap_or(xml.len() - start);
+    let needle = format!(" {}=\"", attr);
+    let Some(offset) = xml[start..end].find(&needle) else {
+        return xml.to_string();
+    };
+    let value_start = start + offset + needle.len();
+    let Some(value_len) = xml[value_start..end].find('"') else {
+        return xml.to_string();
+    };
+    format!(
+        "{}{}{}",
+        &xml[..value_start],
+        value,
+        &xml[value_start + value_len..]
+    )
+}
+
+/// Rewrite a report with an estimated position and tag it
+fn estimated(data: &[u8], lat: f64, lon: f64, time: Option<DateTime<Utc>>, mode: &str) -> Vec<u8> {
+    let xml = String::from_utf8_lossy(data);
+    let mut xml = set_attr(&xml, "point", "lat", &format!("{:.7}", lat));
+    xml = set_attr(&xml, "point", "lon", &format!("{:.7}", lon));
+    if let Some(time) = time {
+        let time = time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        xml = set_attr(&xml, "event", "time", &time);
+        xml = set_attr(&xml, "event", "start", &time);
+        xml = set_attr(&xml, "event", "how", "m-p");
+    }
+
+    let tag = format!(
+        "<_omnitak_estimated method=\"alpha-beta\" mode=\"{}\"/>",
+        mode
+    );
+    let tagged = if let Some(pos) = xml.rfind("</detail>") {
+        format!("{}{}{}", &xml[..pos], tag, &xml[pos..])
+    } else if let Some(pos) = xml.rfind("<detail/>") {
+        format!(
+            "{}<detail>{}</detail>{}",
+            &xml[..pos],
+            tag,
+            &xml[pos + "<detail/>".len()..]
+        )
+    } else if let Some(pos) = xml.rfind("</event>") {
+        format!("{}<detail>{}</detail>{}", &xml[..pos], tag, &xml[pos..])
+    } else {
+        xml
+    };
+    tagged.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(lat: f64, lon: f64, time: &str) -> Vec<u8> {
+        format!(
+            r#"<event version="2.0" uid="UNIT-1" type="a-f-G-U-C" time="{time}" start="{time}" stale="2030-01-01T00:00:00Z" how="m-g"><point lat="{lat}" lon="{lon}" hae="0" ce="10" le="10"/><detail><contact callsign="UNIT-1"/></detail></event>"#
+        )
+        .into_bytes()
+    }
+
+    fn position(data: &[u8]) -> (f64, f64) {
+        let event = omnitak_cot::parser::parse_any(data).unwrap();
+        (event.point.lat, event.point.lon)
+    }
+
+    #[test]
+    fn test_smoothing_damps_jitter() {
+        let smoother = TrackSmoother::new(SmoothingConfig::default());
+        assert!(smoother
+            .process(&report(34.0, -117.0, "2026-01-01T00:00:00Z"), &[])
+            .is_none());
+
+        // A 100 m jump north is only partly followed
+        let smoothed = smoother
+            .process(&report(34.0009, -117.0, "2026-01-01T00:00:01Z"), &[])
+            .unwrap();
+        let (lat, lon) = position(&smoothed);
+        let moved = distance_m(34.0, -117.0, lat, lon);
+        assert!(moved > 40.0 && moved < 60.0, "moved {} m", moved);
+
+        let xml = String::from_utf8(smoothed).unwrap();
+        assert!(xml.contains(r#"<_omnitak_estimated method="alpha-beta" mode="smoothed"/>"#));
+        assert!(xml.contains(r#"how="m-g""#));
+
+        // Non-track events pass through
+        let chat = br#"<event version="2.0" uid="chat" type="b-t-f" time="2026-01-01T00:00:00Z" start="2026-01-01T00:00:00Z" stale="2026-01-01T00:01:00Z" how="h-g-i-g-o"><point lat="0" lon="0" hae="0" ce="0" le="0"/></event>"#;
+        assert!(smoother.process(chat, &[]).is_none());
+    }
+
+    #[test]
+    fn test_extrapolation() {
+        let smoother = TrackSmoother::new(SmoothingConfig {
+            alpha: 1.0,
+            beta: 1.0,
+            extrapolate_interval_ms: 0,
+            ..Default::default()
+        });
+        // Moving east at about 10 m/s
+        let destinations = ["web-map".to_string()];
+        smoother.process(&report(0.0, 0.0, "2026-01-01T00:00:00Z"), &destinations);
+        smoother.process(
+            &report(0.0, 0.0000898, "2026-01-01T00:00:01Z"),
+            &destinations,
+        );
+
+        let now = "2026-01-01T00:00:03Z".parse::<DateTime<Utc>>().unwrap();
+        let reports = smoother.extrapolate(now);
+        assert_eq!(reports.len(), 1);
+        // Sent where the last report was smoothed for
+        assert_eq!(reports[0].1, destinations);
+
+        let (lat, lon) = position(&reports[0].0);
+        let ahead = distance_m(0.0, 0.0000898, lat, lon);
+        assert!((ahead - 20.0).abs() < 1.0, "ahead {} m", ahead);
+
+        let xml = String::from_utf8(reports[0].0.clone()).unwrap();
+        assert!(xml.contains(r#"how="m-p""#));
+        assert!(xml.contains(r#"mode="extrapolated""#));
+        assert!(xml.contains(r#"time="2026-01-01T00:00:03.000Z""#));
+    }
+}
@@ -0,0 +1,116 @@
+//! Traffic Tap
+//!
+//! A live view of routing decisions for debugging. While anyone is
+//! subscribed, the distributor publishes a [`TapRecord`] for every message
+//! it handles: where the message came from, which filter rule or route
+//! matched for each connection, and whether the connection received it,
+//! had it filtered out, or dropped it on a full channel.
+//!
+//! Records are only built while the tap has subscribers, so an idle tap
+//! costs one atomic load per message. Subscribers that fall behind miss
+//! records rather than slowing the distributor down.
+
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+use crate::pool::ConnectionId;
+
+/// Records buffered per subscriber before the slowest starts missing them
+const TAP_CAPACITY: usize = 1024;
+
+/// What happened to a message at one connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TapOutcome {
+    /// Queued on the connection
+    Delivered,
+    /// No filter rule let it through
+    Filtered,
+    /// The connection's channel was full or closed, or the message could
+    /// not be converted to the connection's wire format
+    Dropped,
+}
+
+/// One connection the distributor considered for a message
+#[derive(Debug, Clone, Serialize)]
+pub struct TapDestination {
+    pub connection: ConnectionId,
+    pub outcome: TapOutcome,
+    /// Filter rule that let the message through, if not every message goes
+    pub matched_rule: Option<String>,
+    /// The connection got the smoothed position instead of the report
+    pub smoothed: bool,
+    /// Route plugins run on the message for this connection
+    pub plugins: Vec<String>,
+}
+
+/// A message as the distributor routed it
+#[derive(Debug, Clone, Serialize)]
+pub struct TapRecord {
+    /// Trace ID assigned at ingest, as found in the debug logs
+    pub trace_id: String,
+    /// When distribution finished (epoch millis)
+    pub timestamp_ms: u64,
+    pub source: Option<ConnectionId>,
+    pub uid: Option<String>,
+    pub cot_type: Option<String>,
+    /// Sent past the filters, e.g. by an admin injection
+    pub bypass_filters: bool,
+    /// Routes that matched and assigned plugins, from the route table
+    pub matched_routes: Vec<String>,
+    pub destinations: Vec<TapDestination>,
+    /// Time from ingest to the end of distribution
+    pub latency_us: u64,
+    /// The message as it arrived at the distributor
+    pub payload: String,
+}
+
+impl TapRecord {
+    /// Connections that received the message
+    pub fn delivered(&self) -> impl Iterator<Item = &ConnectionId> {
+        self.destinations
+            .iter()
+            .filter(|d| d.outcome == TapOutcome::Delivered)
+            .map(|d| &d.connection)
+    }
+}
+
+/// Fan-out point for tap records
+#[derive(Debug)]
+pub struct MessageTap {
+    tx: broadcast::Sender<Arc<TapRecord>>,
+}
+
+impl MessageTap {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(TAP_CAPACITY);
+        Self { tx }
+    }
+
+    /// Whether anyone is listening, i.e. records are worth building
+    pub fn is_active(&self) -> bool {
+        self.tx.receiver_count() > 0
+    }
+
+    /// Start receiving records
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<TapRecord>> {
+        self.tx.subscribe()
+    }
+
+    /// Number of subscribers
+    pub fn subscribers(&self) -> usize {
+        self.tx.receiver_count()
+    }
+
+    pub(crate) fn publish(&self, record: TapRecord) {
+        // Subscribers may all have left since the record was started
+        let _ = self.tx.send(Arc::new(record));
+    }
+}
+
+impl Default for MessageTap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
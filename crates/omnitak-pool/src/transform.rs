@@ -0,0 +1,443 @@
+//! Transformer Pipeline
+//!
+//! Runs message transformers (typically WASM plugins) on every unique
+//! message before it is distributed. Stages run in the configured order;
+//! stages not named in the order run after those that are, in the order
+//! they were added.
+//!
+//! A stage that fails, panics or exceeds the timeout is skipped for that
+//! message: the message continues through the remaining stages as it was
+//! before the failing one, so a broken plugin cannot stop traffic.
+//!
+//! Stages that routes assign to specific destinations are route-only: they
+//! are skipped on all traffic and run by the distributor, through
+//! [`TransformPipeline::apply_selected`], on the destinations they belong to.
+
+use arc_swap::ArcSwap;
+use async_trait::async_trait;
+use futures::FutureExt;
+use metrics::{counter, histogram};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Durations kept per stage for percentile estimates
+const DURATION_SAMPLES: usize = 256;
+
+/// A message transformation stage
+#[async_trait]
+pub trait MessageTransformer: Send + Sync {
+    /// Stable identifier, used for ordering and metrics
+    fn id(&self) -> &str;
+
+    /// Whether the stage applies to messages of this CoT type
+    fn handles(&self, _cot_type: &str) -> bool {
+        true
+    }
+
+    /// Transform a CoT message
+    async fn transform(&self, data: &[u8]) -> anyhow::Result<Vec<u8>>;
+}
+
+/// Transformer pipeline settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransformConfig {
+    /// Stage IDs in the order they run
+    #[serde(default)]
+    pub order: Vec<String>,
+    /// Longest a single stage may take on one message
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_timeout_ms() -> u64 {
+    50
+}
+
+impl Default for TransformConfig {
+    fn default() -> Self {
+        Self {
+            order: Vec::new(),
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+}
+
+/// Counters and timings for one stage
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TransformerStats {
+    pub id: String,
+    pub enabled: bool,
+    /// Messages the stage ran on
+    pub executions: u64,
+    /// Runs that returned an error or panicked
+    pub errors: u64,
+    /// Runs abandoned at the timeout
+    pub timeouts: u64,
+    pub avg_time_us: f64,
+    pub p50_time_us: u64,
+    pub p95_time_us: u64,
+    pub p99_time_us: u64,
+    pub last_execution: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_error: Option<String>,
+}
+
+struct Stage {
+    transformer: Arc<dyn MessageTransformer>,
+    enabled: bool,
+    stats: Mutex<StageStats>,
+}
+
+#[derive(Default)]
+struct StageStats {
+    executions: u64,
+    errors: u64,
+    timeouts: u64,
+    total_time: Duration,
+    recent: VecDeque<Duration>,
+    last_execution: Option<chrono::DateTime<chrono::Utc>>,
+    last_error: Option<String>,
+}
+
+impl StageStats {
+    fn record(&mut self, elapsed: Duration, error: Option<String>, timed_out: bool) {
+        self.executions += 1;
+        self.total_time += elapsed;
+        if self.recent.len() == DURATION_SAMPLES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(elapsed);
+        self.last_execution = Some(chrono::Utc::now());
+        if timed_out {
+            self.timeouts += 1;
+        }
+        if let Some(error) = error {
+            if !timed_out {
+                self.errors += 1;
+            }
+            self.last_error = Some(error);
+        }
+    }
+
+    fn snapshot(&self, id: &str, enabled: bool) -> TransformerStats {
+        let mut sorted: Vec<u64> = self.recent.iter().map(|d| d.as_micros() as u64).collect();
+        sorted.sort_unstable();
+        let percentile = |p: usize| {
+            if sorted.is_empty() {
+                0
+            } else {
+                sorted[(sorted.len() - 1) * p / 100]
+            }
+        };
+
+        TransformerStats {
+            id: id.to_string(),
+            enabled,
+            executions: self.executions,
+            errors: self.errors,
+            timeouts: self.timeouts,
+            avg_time_us: if self.executions == 0 {
+                0.0
+            } else {
+                self.total_time.as_micros() as f64 / self.executions as f64
+            },
+            p50_time_us: percentile(50),
+            p95_time_us: percentile(95),
+            p99_time_us: percentile(99),
+            last_execution: self.last_execution,
+            last_error: self.last_error.clone(),
+        }
+    }
+}
+
+/// Ordered set of transformer stages, changeable while traffic flows
+pub struct TransformPipeline {
+    config: TransformConfig,
+    stages: ArcSwap<Vec<Arc<Stage>>>,
+    /// Stages that only run where routes assign them
+    route_only: ArcSwap<HashSet<String>>,
+    /// Serializes changes to `stages`
+    update: Mutex<()>,
+}
+
+impl TransformPipeline {
+    pub fn new(config: TransformConfig) -> Self {
+        Self {
+            config,
+            stages: ArcSwap::from_pointee(Vec::new()),
+            route_only: ArcSwap::from_pointee(HashSet::new()),
+            update: Mutex::new(()),
+        }
+    }
+
+    /// Add a stage, replacing any stage with the same ID. A replaced stage
+    /// keeps its position and stays enabled or disabled as it was
+    pub fn add(&self, transformer: Arc<dyn MessageTransformer>) {
+        let _guard = self.update.lock();
+        let mut stages: Vec<Arc<Stage>> = self.stages.load().iter().cloned().collect();
+        let existing = stages
+            .iter()
+            .position(|s| s.transformer.id() == transformer.id());
+        let stage = Arc::new(Stage {
+            transformer,
+            enabled: existing.is_none_or(|i| stages[i].enabled),
+            stats: Mutex::new(StageStats::default()),
+        });
+        match existing {
+            Some(i) => stages[i] = stage,
+            None => stages.push(stage),
+        }
+
+        // Stable sort keeps unlisted stages in the order they were added
+        let position = |stage: &Arc<Stage>| {
+            self.config
+                .order
+                .iter()
+                .position(|id| id == stage.transformer.id())
+                .unwrap_or(usize::MAX)
+        };
+        stages.sort_by_key(position);
+        self.stages.store(Arc::new(stages));
+    }
+
+    /// Remove a stage, returning whether it was present
+    pub fn remove(&self, id: &str) -> bool {
+        let _guard = self.update.lock();
+        let stages = self.stages.load();
+        let remaining: Vec<Arc<Stage>> = stages
+            .iter()
+            .filter(|s| s.transformer.id() != id)
+            .cloned()
+            .collect();
+        let removed = remaining.len() != stages.len();
+        self.stages.store(Arc::new(remaining));
+        removed
+    }
+
+    /// Enable or disable a stage, returning whether it exists
+    pub fn set_enabled(&self, id: &str, enabled: bool) -> bool {
+        let _guard = self.update.lock();
+        let mut found = false;
+        let stages = self
+            .stages
+            .load()
+            .iter()
+            .map(|stage| {
+                if stage.transformer.id() != id {
+                    return Arc::clone(stage);
+                }
+                found = true;
+                let stats = std::mem::take(&mut *stage.stats.lock());
+                Arc::new(Stage {
+                    transformer: Arc::clone(&stage.transformer),
+                    enabled,
+                    stats: Mutex::new(stats),
+                })
+            })
+            .collect();
+        self.stages.store(Arc::new(stages));
+        found
+    }
+
+    /// Keep these stages off all traffic; they run only through
+    /// [`Self::apply_selected`]
+    pub fn set_route_only(&self, ids: HashSet<String>) {
+        self.route_only.store(Arc::new(ids));
+    }
+
+    /// Whether any stage would run on all traffic
+    pub fn is_empty(&self) -> bool {
+        let route_only = self.route_only.load();
+        !self
+            .stages
+            .load()
+            .iter()
+            .any(|s| s.enabled && !route_only.contains(s.transformer.id()))
+    }
+
+    /// Statistics for each stage, in pipeline order
+    pub fn stats(&self) -> Vec<TransformerStats> {
+        self.stages
+            .load()
+            .iter()
+            .map(|s| s.stats.lock().snapshot(s.transformer.id(), s.enabled))
+            .collect()
+    }
+
+    /// Statistics for one stage
+    pub fn stage_stats(&self, id: &str) -> Option<TransformerStats> {
+        self.stats().into_iter().find(|s| s.id == id)
+    }
+
+    /// Run a message through every enabled stage that handles its type,
+    /// except route-only stages
+    pub async fn apply(&self, data: Vec<u8>) -> Vec<u8> {
+        let route_only = self.route_only.load_full();
+        self.run(data, |id| !route_only.contains(id)).await
+    }
+
+    /// Run a message through the named stages, in pipeline order. Unknown
+    /// IDs, e.g. plugins that are not loaded, are ignored
+    pub async fn apply_selected(&self, ids: &[String], data: Vec<u8>) -> Vec<u8> {
+        self.run(data, |id| ids.iter().any(|s| s == id)).await
+    }
+
+    async fn run(&self, data: Vec<u8>, selected: impl Fn(&str) -> bool) -> Vec<u8> {
+        let stages = self.stages.load_full();
+        if stages.is_empty() {
+            return data;
+        }
+
+        let cot_type = extract_type(&data).unwrap_or_default();
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        let mut data = data;
+
+        for stage in stages.iter() {
+            let id = stage.transformer.id();
+            if !stage.enabled || !selected(id) || !stage.transformer.handles(&cot_type) {
+                continue;
+            }
+
+            let start = Instant::now();
+            let run = AssertUnwindSafe(stage.transformer.transform(&data)).catch_unwind();
+            let outcome = tokio::time::timeout(timeout, run).await;
+            let elapsed = start.elapsed();
+            histogram!("transformer_duration_seconds", "plugin" => id.to_string())
+                .record(elapsed.as_secs_f64());
+
+            let (error, timed_out) = match outcome {
+                Ok(Ok(Ok(transformed))) => {
+                    data = transformed;
+                    (None, false)
+                }
+                Ok(Ok(Err(e))) => (Some(e.to_string()), false),
+                Ok(Err(_)) => (Some("transformer panicked".to_string()), false),
+                Err(_) => (Some(format!("timed out after {:?}", timeout)), true),
+            };
+            if let Some(error) = &error {
+                counter!("transformer_failures_total", "plugin" => id.to_string()).increment(1);
+                warn!(plugin = id, error = %error, "Transformer failed, message passed on unchanged");
+            }
+            stage.stats.lock().record(elapsed, error, timed_out);
+        }
+
+        data
+    }
+}
+
+/// Extract the CoT type from a message
+pub(crate) fn extract_type(data: &[u8]) -> Option<String> {
+    let msg = String::from_utf8_lossy(data);
+    let start = msg.find(" type=\"")? + 7;
+    let end = msg[start..].find('"')?;
+    Some(msg[start..start + end].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Append(&'static str);
+
+    #[async_trait]
+    impl MessageTransformer for Append {
+        fn id(&self) -> &str {
+            self.0
+        }
+
+        fn handles(&self, cot_type: &str) -> bool {
+            cot_type.starts_with("a-f")
+        }
+
+        async fn transform(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+            let mut data = data.to_vec();
+            data.extend_from_slice(self.0.as_bytes());
+            Ok(data)
+        }
+    }
+
+    struct Broken;
+
+    #[async_trait]
+    impl MessageTransformer for Broken {
+        fn id(&self) -> &str {
+            "broken"
+        }
+
+        async fn transform(&self, _data: &[u8]) -> anyhow::Result<Vec<u8>> {
+            panic!("plugin bug")
+        }
+    }
+
+    const EVENT: &[u8] = b"<event uid=\"u\" type=\"a-f-G\"/>";
+
+    #[tokio::test]
+    async fn test_stages_run_in_configured_order() {
+        let pipeline = TransformPipeline::new(TransformConfig {
+            order: vec!["b".to_string(), "a".to_string()],
+            ..Default::default()
+        });
+        pipeline.add(Arc::new(Append("c")));
+        pipeline.add(Arc::new(Append("a")));
+        pipeline.add(Arc::new(Append("b")));
+
+        let out = pipeline.apply(EVENT.to_vec()).await;
+        assert!(out.ends_with(b"/>bac"));
+
+        // Other types are left alone
+        let hostile = b"<event uid=\"u\" type=\"a-h-G\"/>".to_vec();
+        assert_eq!(pipeline.apply(hostile.clone()).await, hostile);
+
+        assert!(pipeline.set_enabled("a", false));
+        assert!(pipeline.apply(EVENT.to_vec()).await.ends_with(b"/>bc"));
+        // A reloaded stage stays disabled
+        pipeline.add(Arc::new(Append("a")));
+        assert!(pipeline.apply(EVENT.to_vec()).await.ends_with(b"/>bc"));
+        assert!(pipeline.remove("b"));
+        assert_eq!(
+            pipeline
+                .stats()
+                .iter()
+                .map(|s| s.id.as_str())
+                .collect::<Vec<_>>(),
+            ["a", "c"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failing_stage_is_isolated() {
+        let pipeline = TransformPipeline::new(TransformConfig::default());
+        pipeline.add(Arc::new(Broken));
+        pipeline.add(Arc::new(Append("ok")));
+
+        let out = pipeline.apply(EVENT.to_vec()).await;
+        assert!(out.ends_with(b"/>ok"));
+
+        let broken = pipeline.stage_stats("broken").unwrap();
+        assert_eq!((broken.executions, broken.errors), (1, 1));
+        assert_eq!(broken.last_error.as_deref(), Some("transformer panicked"));
+        assert_eq!(pipeline.stage_stats("ok").unwrap().errors, 0);
+    }
+
+    #[tokio::test]
+    async fn test_route_only_stages() {
+        let pipeline = TransformPipeline::new(TransformConfig::default());
+        pipeline.add(Arc::new(Append("a")));
+        pipeline.add(Arc::new(Append("b")));
+        pipeline.set_route_only(HashSet::from(["b".to_string()]));
+
+        assert!(pipeline.apply(EVENT.to_vec()).await.ends_with(b"/>a"));
+        let selected = vec!["b".to_string(), "missing".to_string()];
+        assert!(pipeline
+            .apply_selected(&selected, EVENT.to_vec())
+            .await
+            .ends_with(b"/>b"));
+
+        pipeline.set_enabled("a", false);
+        assert!(pipeline.is_empty());
+    }
+}
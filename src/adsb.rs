@@ -0,0 +1,694 @@
+//! ADS-B Input Adapter
+//!
+//! Connects to a dump1090/readsb feed and turns aircraft tracks into CoT
+//! events injected into the aggregator, giving every connected client an air
+//! picture. Two feed formats are supported:
+//!
+//! - **SBS** (BaseStation CSV, usually port 30003): already decoded by the
+//!   receiver, one `MSG,...` line per update.
+//! - **Beast** (binary, usually port 30005): raw Mode S frames, decoded here
+//!   (DF17/18 identification, airborne position and velocity).
+//!
+//! ```text
+//!   dump1090 ──▶ reader task ──▶ aircraft table ──▶ publish task ──▶ MessageAggregator
+//!                (SBS / Beast)   (merge by ICAO)     (≤1 event per aircraft per update_interval)
+//! ```
+
+use anyhow::{Context, Result};
+use bytes::{Buf, BytesMut};
+use chrono::{Duration as ChronoDuration, Utc};
+use omnitak_cot::{Contact, Detail, Event, Point, Track};
+use omnitak_pool::{InboundMessage, MessageAggregator, TraceId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::net::TcpStream;
+use tracing::{debug, info, warn};
+
+/// Aggregator source ID for ADS-B events
+pub const ADSB_SOURCE: &str = "adsb";
+
+/// CoT "unknown" value for ce/le
+const UNKNOWN: f64 = 9_999_999.0;
+
+const FEET_TO_METERS: f64 = 0.3048;
+const KNOTS_TO_MPS: f64 = 0.514_444;
+
+/// Delay before reconnecting to a failed feed
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Maximum age difference between CPR even/odd frames for a global decode
+const CPR_MAX_PAIR_AGE: Duration = Duration::from_secs(10);
+
+/// ADS-B adapter configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdsbConfig {
+    /// Receiver address (host:port)
+    pub address: String,
+    /// Feed format
+    #[serde(default)]
+    pub format: AdsbFormat,
+    /// Minimum seconds between events for the same aircraft
+    #[serde(default = "default_update_interval_secs")]
+    pub update_interval_secs: u64,
+    /// Seconds without messages before an aircraft is dropped; also the CoT stale time
+    #[serde(default = "default_stale_secs")]
+    pub stale_secs: u64,
+    /// CoT type for aircraft; unknown affiliation by default since ADS-B
+    /// says nothing about friend or foe
+    #[serde(default = "default_cot_type")]
+    pub cot_type: String,
+    /// Prefix for event UIDs (followed by the ICAO hex address)
+    #[serde(default = "default_uid_prefix")]
+    pub uid_prefix: String,
+    /// Callsigns by ICAO hex address, overriding the broadcast flight ID
+    #[serde(default)]
+    pub callsigns: HashMap<String, String>,
+}
+
+/// ADS-B feed format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AdsbFormat {
+    /// BaseStation CSV
+    #[default]
+    Sbs,
+    /// Beast binary
+    Beast,
+}
+
+fn default_update_interval_secs() -> u64 {
+    5
+}
+
+fn default_stale_secs() -> u64 {
+    60
+}
+
+fn default_cot_type() -> String {
+    "a-u-A".to_string()
+}
+
+fn default_uid_prefix() -> String {
+    "ICAO-".to_string()
+}
+
+/// Start reading the feed and publishing aircraft
+pub fn spawn(config: AdsbConfig, aggregator: Arc<MessageAggregator>) {
+    let table = Arc::new(Mutex::new(AircraftTable::default()));
+
+    let reader_table = Arc::clone(&table);
+    let reader_config = config.clone();
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = read_feed(&reader_config, &reader_table).await {
+                warn!(error = %e, "ADS-B feed failed, retrying in {:?}", RECONNECT_DELAY);
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+
+    tokio::spawn(async move {
+        let sender = aggregator.sender();
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+
+        loop {
+            interval.tick().await;
+
+            let events = {
+                let mut table = table.lock().unwrap();
+                table.expire(Duration::from_secs(config.stale_secs), Instant::now());
+                table.due_events(&config, Instant::now())
+            };
+
+            for event in events {
+                let msg = InboundMessage {
+                    data: omnitak_cot::serialize_event(&event).into_bytes(),
+                    source: ADSB_SOURCE.to_string(),
+                    timestamp: Instant::now(),
+                    trace_id: TraceId::new(),
+                };
+                if let Err(e) = sender.send_async(msg).await {
+                    warn!(error = %e, "Failed to inject ADS-B event");
+                }
+            }
+        }
+    });
+}
+
+/// Connect to the receiver and feed the aircraft table until the feed fails
+async fn read_feed(config: &AdsbConfig, table: &Mutex<AircraftTable>) -> Result<()> {
+    let stream = TcpStream::connect(&config.address)
+        .await
+        .with_context(|| format!("Failed to connect to ADS-B feed at {}", config.address))?;
+    info!(address = %config.address, format = ?config.format, "Connected to ADS-B feed");
+
+    match config.format {
+        AdsbFormat::Sbs => {
+            let mut lines = BufReader::new(stream).lines();
+            while let Some(line) = lines.next_line().await.context("ADS-B feed read failed")? {
+                table.lock().unwrap().update_sbs(&line, Instant::now());
+            }
+        }
+        AdsbFormat::Beast => {
+            let mut stream = stream;
+            let mut buffer = BytesMut::with_capacity(4096);
+            loop {
+                if stream
+                    .read_buf(&mut buffer)
+                    .await
+                    .context("ADS-B feed read failed")?
+                    == 0
+                {
+                    break;
+                }
+                let mut table = table.lock().unwrap();
+                while let Some(frame) = next_beast_frame(&mut buffer) {
+                    table.update_mode_s(&frame, Instant::now());
+                }
+            }
+        }
+    }
+
+    anyhow::bail!("ADS-B feed closed the connection")
+}
+
+// ============================================================================
+// Aircraft Table
+// ============================================================================
+
+/// Latest known state of one aircraft
+#[derive(Debug, Default)]
+struct Aircraft {
+    flight: Option<String>,
+    position: Option<(f64, f64)>,
+    altitude_ft: Option<f64>,
+    speed_kt: Option<f64>,
+    track_deg: Option<f64>,
+    vertical_rate_fpm: Option<f64>,
+    squawk: Option<String>,
+    on_ground: bool,
+    /// Last CPR frames as (lat_cpr, lon_cpr, received)
+    cpr_even: Option<(u32, u32, Instant)>,
+    cpr_odd: Option<(u32, u32, Instant)>,
+    last_seen: Option<Instant>,
+    last_published: Option<Instant>,
+    updated: bool,
+}
+
+/// Aircraft keyed by 24-bit ICAO address
+#[derive(Debug, Default)]
+struct AircraftTable {
+    aircraft: HashMap<u32, Aircraft>,
+}
+
+impl AircraftTable {
+    fn entry(&mut self, icao: u32, now: Instant) -> &mut Aircraft {
+        let aircraft = self.aircraft.entry(icao).or_default();
+        aircraft.last_seen = Some(now);
+        aircraft
+    }
+
+    /// Apply one SBS `MSG` line
+    fn update_sbs(&mut self, line: &str, now: Instant) {
+        let fields: Vec<&str> = line.trim().split(',').collect();
+        if fields.len() < 11 || fields[0] != "MSG" {
+            return;
+        }
+        let Ok(icao) = u32::from_str_radix(fields[4], 16) else {
+            return;
+        };
+
+        let field = |i: usize| fields.get(i).map(|f| f.trim()).filter(|f| !f.is_empty());
+        let number = |i: usize| field(i).and_then(|f| f.parse::<f64>().ok());
+
+        let aircraft = self.entry(icao, now);
+        if let Some(flight) = field(10) {
+            aircraft.flight = Some(flight.to_string());
+        }
+        if let Some(altitude) = number(11) {
+            aircraft.altitude_ft = Some(altitude);
+        }
+        if let Some(speed) = number(12) {
+            aircraft.speed_kt = Some(speed);
+        }
+        if let Some(track) = number(13) {
+            aircraft.track_deg = Some(track);
+        }
+        if let (Some(lat), Some(lon)) = (number(14), number(15)) {
+            aircraft.position = Some((lat, lon));
+        }
+        if let Some(rate) = number(16) {
+            aircraft.vertical_rate_fpm = Some(rate);
+        }
+        if let Some(squawk) = field(17) {
+            aircraft.squawk = Some(squawk.to_string());
+        }
+        if let Some(ground) = field(21) {
+            aircraft.on_ground = ground != "0";
+        }
+        aircraft.updated = true;
+    }
+
+    /// Apply one Mode S frame (7 or 14 bytes)
+    fn update_mode_s(&mut self, msg: &[u8], now: Instant) {
+        if msg.len() != 14 {
+            return;
+        }
+        let df = msg[0] >> 3;
+        if df != 17 && df != 18 {
+            return;
+        }
+
+        let icao = bits(msg, 8, 24);
+        let tc = bits(msg, 32, 5);
+        let aircraft = self.entry(icao, now);
+
+        match tc {
+            1..=4 => {
+                aircraft.flight = Some(decode_callsign(msg)).filter(|c| !c.is_empty());
+            }
+            9..=18 => {
+                if let Some(altitude) = decode_altitude(msg) {
+                    aircraft.altitude_ft = Some(altitude);
+                }
+                let odd = bits(msg, 53, 1) == 1;
+                let frame = (bits(msg, 54, 17), bits(msg, 71, 17), now);
+                if odd {
+                    aircraft.cpr_odd = Some(frame);
+                } else {
+                    aircraft.cpr_even = Some(frame);
+                }
+                if let (Some(even), Some(odd_frame)) = (aircraft.cpr_even, aircraft.cpr_odd) {
+                    let age = if even.2 > odd_frame.2 {
+                        even.2 - odd_frame.2
+                    } else {
+                        odd_frame.2 - even.2
+                    };
+                    if age <= CPR_MAX_PAIR_AGE {
+                        if let Some(position) =
+                            cpr_global((even.0, even.1), (odd_frame.0, odd_frame.1), odd)
+                        {
+                            aircraft.position = Some(position);
+                        }
+                    }
+                }
+                aircraft.on_ground = false;
+            }
+            19 => {
+                if let Some((speed, track, rate)) = decode_velocity(msg) {
+                    aircraft.speed_kt = Some(speed);
+                    aircraft.track_deg = Some(track);
+                    aircraft.vertical_rate_fpm = rate;
+                }
+            }
+            _ => return,
+        }
+        aircraft.updated = true;
+    }
+
+    /// Drop aircraft not heard from within `max_age`
+    fn expire(&mut self, max_age: Duration, now: Instant) {
+        self.aircraft.retain(|icao, aircraft| {
+            let alive = aircraft
+                .last_seen
+                .is_some_and(|seen| now.duration_since(seen) <= max_age);
+            if !alive {
+                debug!(icao = format!("{:06X}", icao), "Aircraft expired");
+            }
+            alive
+        });
+    }
+
+    /// Events for positioned aircraft updated since they were last published
+    fn due_events(&mut self, config: &AdsbConfig, now: Instant) -> Vec<Event> {
+        let min_interval = Duration::from_secs(config.update_interval_secs);
+        let mut events = Vec::new();
+
+        for (icao, aircraft) in &mut self.aircraft {
+            if !aircraft.updated || aircraft.position.is_none() {
+                continue;
+            }
+            if let Some(last) = aircraft.last_published {
+                if now.duration_since(last) < min_interval {
+                    continue;
+                }
+            }
+            events.push(aircraft_event(config, *icao, aircraft));
+            aircraft.last_published = Some(now);
+            aircraft.updated = false;
+        }
+
+        events
+    }
+}
+
+/// Build the CoT event for an aircraft
+fn aircraft_event(config: &AdsbConfig, icao: u32, aircraft: &Aircraft) -> Event {
+    let now = Utc::now();
+    let hex = format!("{:06X}", icao);
+    let (lat, lon) = aircraft.position.unwrap_or_default();
+
+    let callsign = config
+        .callsigns
+        .get(&hex)
+        .cloned()
+        .or_else(|| aircraft.flight.clone())
+        .unwrap_or_else(|| hex.clone());
+
+    let track = aircraft.speed_kt.map(|speed| Track {
+        speed: speed * KNOTS_TO_MPS,
+        course: aircraft.track_deg.unwrap_or(0.0),
+    });
+
+    let mut remarks = format!("ICAO {}", hex);
+    if let Some(squawk) = &aircraft.squawk {
+        remarks.push_str(&format!(" squawk {}", squawk));
+    }
+    if let Some(rate) = aircraft.vertical_rate_fpm {
+        remarks.push_str(&format!(" vrate {:+.0} ft/min", rate));
+    }
+
+    Event {
+        version: "2.0".to_string(),
+        uid: format!("{}{}", config.uid_prefix, hex),
+        event_type: config.cot_type.clone(),
+        time: now,
+        start: now,
+        stale: now + ChronoDuration::seconds(config.stale_secs as i64),
+        how: "m-g".to_string(),
+        point: Point {
+            lat,
+            lon,
+            hae: aircraft
+                .altitude_ft
+                .filter(|_| !aircraft.on_ground)
+                .map(|ft| ft * FEET_TO_METERS)
+                .unwrap_or(0.0),
+            ce: UNKNOWN,
+            le: UNKNOWN,
+        },
+        detail: Some(Detail {
+            contact: Some(Contact {
+                endpoint: None,
+                callsign,
+            }),
+            track,
+            xml_detail: Some(format!("<remarks>{}</remarks>", remarks)),
+            ..Default::default()
+        }),
+    }
+}
+
+// ============================================================================
+// Beast framing
+// ============================================================================
+
+/// Beast escape / frame start byte
+const BEAST_ESCAPE: u8 = 0x1a;
+
+/// Extract the next Mode S frame from a Beast stream buffer
+///
+/// Mode A/C and unknown frames are skipped. Returns None when more data is
+/// needed; consumed bytes are removed from the buffer.
+fn next_beast_frame(buffer: &mut BytesMut) -> Option<Vec<u8>> {
+    'frames: loop {
+        let start = buffer.iter().position(|&b| b == BEAST_ESCAPE)?;
+        buffer.advance(start);
+        if buffer.len() < 2 {
+            return None;
+        }
+
+        let payload_len = match buffer[1] {
+            b'1' => 2,
+            b'2' => 7,
+            b'3' => 14,
+            _ => {
+                buffer.advance(1);
+                continue;
+            }
+        };
+        // 6-byte MLAT timestamp + 1-byte signal level + payload
+        let frame_len = 7 + payload_len;
+
+        let mut frame = Vec::with_capacity(frame_len);
+        let mut i = 2;
+        while frame.len() < frame_len {
+            let byte = *buffer.get(i)?;
+            if byte == BEAST_ESCAPE {
+                match buffer.get(i + 1)? {
+                    &BEAST_ESCAPE => {
+                        frame.push(BEAST_ESCAPE);
+                        i += 2;
+                    }
+                    // Unescaped frame start: this frame was truncated
+                    _ => {
+                        buffer.advance(i);
+                        continue 'frames;
+                    }
+                }
+            } else {
+                frame.push(byte);
+                i += 1;
+            }
+        }
+
+        buffer.advance(i);
+        if payload_len == 2 {
+            continue;
+        }
+        return Some(frame.split_off(7));
+    }
+}
+
+// ============================================================================
+// Mode S decoding
+// ============================================================================
+
+/// Read `len` bits starting at bit `start` (0 = MSB of the first byte)
+fn bits(msg: &[u8], start: usize, len: usize) -> u32 {
+    let mut value = 0u32;
+    for bit in start..start + len {
+        let byte = msg[bit / 8];
+        value = (value << 1) | ((byte >> (7 - bit % 8)) & 1) as u32;
+    }
+    value
+}
+
+/// Decode the flight ID from an identification message (TC 1-4)
+fn decode_callsign(msg: &[u8]) -> String {
+    const CHARSET: &[u8; 64] = b"#ABCDEFGHIJKLMNOPQRSTUVWXYZ##### ###############0123456789######";
+    (0..8)
+        .map(|i| CHARSET[bits(msg, 40 + i * 6, 6) as usize] as char)
+        .filter(|c| *c != '#')
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Decode barometric altitude in feet from an airborne position message
+fn decode_altitude(msg: &[u8]) -> Option<f64> {
+    let q = bits(msg, 47, 1);
+    if q == 0 {
+        // 100 ft Gillham-coded altitude is not supported
+        return None;
+    }
+    let n = (bits(msg, 40, 7) << 4) | bits(msg, 48, 4);
+    Some(n as f64 * 25.0 - 1000.0)
+}
+
+/// Decode ground speed (kt), track (deg) and vertical rate (ft/min) from an
+/// airborne velocity message (TC 19, subtypes 1-2)
+fn decode_velocity(msg: &[u8]) -> Option<(f64, f64, Option<f64>)> {
+    let subtype = bits(msg, 37, 3);
+    if subtype != 1 && subtype != 2 {
+        return None;
+    }
+    let scale = if subtype == 2 { 4.0 } else { 1.0 };
+
+    let v_ew = bits(msg, 46, 10);
+    let v_ns = bits(msg, 57, 10);
+    if v_ew == 0 || v_ns == 0 {
+        return None;
+    }
+    let mut vx = (v_ew - 1) as f64 * scale;
+    let mut vy = (v_ns - 1) as f64 * scale;
+    if bits(msg, 45, 1) == 1 {
+        vx = -vx;
+    }
+    if bits(msg, 56, 1) == 1 {
+        vy = -vy;
+    }
+
+    let speed = vx.hypot(vy);
+    let track = vx.atan2(vy).to_degrees().rem_euclid(360.0);
+
+    let vr = bits(msg, 69, 9);
+    let rate = (vr != 0).then(|| {
+        let rate = (vr - 1) as f64 * 64.0;
+        if bits(msg, 68, 1) == 1 {
+            -rate
+        } else {
+            rate
+        }
+    });
+
+    Some((speed, track, rate))
+}
+
+/// Number of longitude zones at a latitude
+fn cpr_nl(lat: f64) -> f64 {
+    const NZ: f64 = 15.0;
+    let lat = lat.abs();
+    if lat == 0.0 {
+        return 59.0;
+    }
+    if lat == 87.0 {
+        return 2.0;
+    }
+    if lat > 87.0 {
+        return 1.0;
+    }
+    let a = 1.0 - (std::f64::consts::PI / (2.0 * NZ)).cos();
+    let b = lat.to_radians().cos().powi(2);
+    (2.0 * std::f64::consts::PI / (1.0 - a / b).acos()).floor()
+}
+
+/// Globally unambiguous position from an even/odd CPR pair
+fn cpr_global(even: (u32, u32), odd: (u32, u32), odd_is_newest: bool) -> Option<(f64, f64)> {
+    const SCALE: f64 = 131_072.0;
+    let (lat_e, lon_e) = (even.0 as f64 / SCALE, even.1 as f64 / SCALE);
+    let (lat_o, lon_o) = (odd.0 as f64 / SCALE, odd.1 as f64 / SCALE);
+
+    let j = (59.0 * lat_e - 60.0 * lat_o + 0.5).floor();
+    let mut lat_even = 360.0 / 60.0 * (j.rem_euclid(60.0) + lat_e);
+    let mut lat_odd = 360.0 / 59.0 * (j.rem_euclid(59.0) + lat_o);
+    if lat_even >= 270.0 {
+        lat_even -= 360.0;
+    }
+    if lat_odd >= 270.0 {
+        lat_odd -= 360.0;
+    }
+    if cpr_nl(lat_even) != cpr_nl(lat_odd) {
+        // Frames straddle a zone boundary
+        return None;
+    }
+
+    let (lat, lon_cpr, nl_offset) = if odd_is_newest {
+        (lat_odd, lon_o, 1.0)
+    } else {
+        (lat_even, lon_e, 0.0)
+    };
+    let nl = cpr_nl(lat);
+    let ni = (nl - nl_offset).max(1.0);
+    let m = (lon_e * (nl - 1.0) - lon_o * nl + 0.5).floor();
+    let mut lon = 360.0 / ni * (m.rem_euclid(ni) + lon_cpr);
+    if lon >= 180.0 {
+        lon -= 360.0;
+    }
+
+    Some((lat, lon))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn config() -> AdsbConfig {
+        serde_yaml::from_str("address: 127.0.0.1:30003\ncallsigns:\n  4CA2D6: MEDEVAC-1\n").unwrap()
+    }
+
+    #[test]
+    fn test_decode_callsign() {
+        assert_eq!(
+            decode_callsign(&hex("8D4840D6202CC371C32CE0576098")),
+            "KLM1023"
+        );
+    }
+
+    #[test]
+    fn test_decode_velocity() {
+        let (speed, track, rate) = decode_velocity(&hex("8D485020994409940838175B284F")).unwrap();
+        assert!((speed - 159.2).abs() < 0.1);
+        assert!((track - 182.88).abs() < 0.01);
+        assert_eq!(rate, Some(-832.0));
+    }
+
+    #[test]
+    fn test_cpr_position_and_altitude() {
+        let mut table = AircraftTable::default();
+        let t0 = Instant::now();
+        table.update_mode_s(&hex("8D40621D58C386435CC412692AD6"), t0);
+        table.update_mode_s(
+            &hex("8D40621D58C382D690C8AC2863A7"),
+            t0 + Duration::from_secs(1),
+        );
+
+        let aircraft = &table.aircraft[&0x40621D];
+        let (lat, lon) = aircraft.position.unwrap();
+        assert!((lat - 52.2572).abs() < 1e-4);
+        assert!((lon - 3.9194).abs() < 1e-4);
+        assert_eq!(aircraft.altitude_ft, Some(38000.0));
+    }
+
+    #[test]
+    fn test_sbs_updates_and_publishing() {
+        let config = config();
+        let mut table = AircraftTable::default();
+        let t0 = Instant::now();
+
+        table.update_sbs("MSG,1,1,1,4CA2D6,1,2024/01/15,10:30:00.000,2024/01/15,10:30:00.000,RYR1234 ,,,,,,,,,,,0", t0);
+        // No position yet
+        assert!(table.due_events(&config, t0).is_empty());
+
+        table.update_sbs("MSG,3,1,1,4CA2D6,1,2024/01/15,10:30:00.000,2024/01/15,10:30:00.000,,37000,,,51.4700,-0.4543,,,0,0,0,0", t0);
+        table.update_sbs("MSG,4,1,1,4CA2D6,1,2024/01/15,10:30:00.000,2024/01/15,10:30:00.000,,,450,270,,,-64,,,,,0", t0);
+
+        let events = table.due_events(&config, t0);
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.uid, "ICAO-4CA2D6");
+        assert_eq!(event.point.lat, 51.47);
+        assert!((event.point.hae - 11277.6).abs() < 0.1);
+        let detail = event.detail.as_ref().unwrap();
+        assert_eq!(detail.contact.as_ref().unwrap().callsign, "MEDEVAC-1");
+        assert_eq!(detail.track.as_ref().unwrap().course, 270.0);
+
+        // Rate limited until the update interval passes
+        table.update_sbs("MSG,3,1,1,4CA2D6,1,2024/01/15,10:30:01.000,2024/01/15,10:30:01.000,,37000,,,51.4710,-0.4600,,,0,0,0,0", t0 + Duration::from_secs(1));
+        assert!(table
+            .due_events(&config, t0 + Duration::from_secs(1))
+            .is_empty());
+        assert_eq!(
+            table.due_events(&config, t0 + Duration::from_secs(5)).len(),
+            1
+        );
+
+        table.expire(Duration::from_secs(60), t0 + Duration::from_secs(120));
+        assert!(table.aircraft.is_empty());
+    }
+
+    #[test]
+    fn test_beast_framing() {
+        let payload = hex("8D4840D6202CC371C32CE0576098");
+        let mut stream = vec![0x00, 0x1a, b'1', 0, 0, 0, 0, 0, 0, 0, 0x12, 0x34];
+        stream.extend_from_slice(&[0x1a, b'3', 0, 0, 0x1a, 0x1a, 0, 0, 0, 0xff]);
+        stream.extend_from_slice(&payload);
+
+        let mut buffer = BytesMut::from(&stream[..stream.len() - 3]);
+        assert!(next_beast_frame(&mut buffer).is_none());
+
+        buffer.extend_from_slice(&stream[stream.len() - 3..]);
+        assert_eq!(next_beast_frame(&mut buffer).unwrap(), payload);
+        assert!(buffer.is_empty());
+    }
+}
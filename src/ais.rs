@@ -0,0 +1,587 @@
+//! AIS Input Adapter
+//!
+//! Receives AIS NMEA sentences (`!AIVDM`/`!AIVDO`) from rtl-ais, a shore
+//! station or a network feed, decodes vessel reports and injects them into
+//! the aggregator as maritime CoT, building a common operating picture of
+//! surface traffic. Event UIDs are derived from the vessel MMSI so repeated
+//! reports update the same track.
+//!
+//! Decoded message types:
+//!
+//! - 1/2/3: Class A position report
+//! - 5: Class A static and voyage data (name, callsign, ship type)
+//! - 18/19: Class B position report (19 also carries the name)
+//! - 24: Class B static data
+
+use crate::self_position::verify_checksum;
+use anyhow::{Context, Result};
+use chrono::{Duration as ChronoDuration, Utc};
+use omnitak_cot::{escape_xml, Contact, Detail, Event, Point, Track};
+use omnitak_pool::{InboundMessage, MessageAggregator, TraceId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::{TcpStream, UdpSocket};
+use tracing::{debug, info, warn};
+
+/// Aggregator source ID for AIS events
+pub const AIS_SOURCE: &str = "ais";
+
+/// CoT "unknown" value for le
+const UNKNOWN: f64 = 9_999_999.0;
+
+const KNOTS_TO_MPS: f64 = 0.514_444;
+
+/// Delay before reconnecting to a failed feed
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Time allowed for the remaining fragments of a multi-sentence message
+const FRAGMENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// AIS adapter configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AisConfig {
+    /// Where to read NMEA sentences from
+    pub source: AisSource,
+    /// Minimum seconds between events for the same vessel
+    #[serde(default = "default_update_interval_secs")]
+    pub update_interval_secs: u64,
+    /// Seconds without reports before a vessel is dropped; also the CoT stale time
+    #[serde(default = "default_stale_secs")]
+    pub stale_secs: u64,
+    /// CoT type for vessels
+    #[serde(default = "default_cot_type")]
+    pub cot_type: String,
+    /// Prefix for event UIDs (followed by the MMSI)
+    #[serde(default = "default_uid_prefix")]
+    pub uid_prefix: String,
+}
+
+/// AIS sentence source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AisSource {
+    /// Datagrams from rtl-ais, AIS-catcher or a receiver's UDP output
+    Udp {
+        #[serde(default = "default_udp_bind")]
+        bind: String,
+    },
+    /// Line-oriented TCP feed
+    Tcp { address: String },
+}
+
+fn default_update_interval_secs() -> u64 {
+    10
+}
+
+fn default_stale_secs() -> u64 {
+    600
+}
+
+fn default_cot_type() -> String {
+    "a-u-S".to_string()
+}
+
+fn default_uid_prefix() -> String {
+    "MMSI-".to_string()
+}
+
+fn default_udp_bind() -> String {
+    "0.0.0.0:10110".to_string()
+}
+
+/// Start reading the feed and publishing vessels
+pub fn spawn(config: AisConfig, aggregator: Arc<MessageAggregator>) {
+    let table = Arc::new(Mutex::new(VesselTable::default()));
+
+    let reader_table = Arc::clone(&table);
+    let reader_config = config.clone();
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = read_feed(&reader_config.source, &reader_table).await {
+                warn!(error = %e, "AIS feed failed, retrying in {:?}", RECONNECT_DELAY);
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+
+    tokio::spawn(async move {
+        let sender = aggregator.sender();
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+
+        loop {
+            interval.tick().await;
+
+            let events = {
+                let mut table = table.lock().unwrap();
+                table.expire(Duration::from_secs(config.stale_secs), Instant::now());
+                table.due_events(&config, Instant::now())
+            };
+
+            for event in events {
+                let msg = InboundMessage {
+                    data: omnitak_cot::serialize_event(&event).into_bytes(),
+                    source: AIS_SOURCE.to_string(),
+                    timestamp: Instant::now(),
+                    trace_id: TraceId::new(),
+                };
+                if let Err(e) = sender.send_async(msg).await {
+                    warn!(error = %e, "Failed to inject AIS event");
+                }
+            }
+        }
+    });
+}
+
+/// Read sentences into the vessel table until the source fails
+async fn read_feed(source: &AisSource, table: &Mutex<VesselTable>) -> Result<()> {
+    match source {
+        AisSource::Udp { bind } => {
+            let socket = UdpSocket::bind(bind)
+                .await
+                .with_context(|| format!("Failed to bind AIS UDP socket on {}", bind))?;
+            info!(bind = %bind, "Listening for AIS sentences");
+
+            let mut buf = vec![0u8; 8192];
+            loop {
+                let (len, _) = socket.recv_from(&mut buf).await?;
+                let text = String::from_utf8_lossy(&buf[..len]);
+                let mut table = table.lock().unwrap();
+                for line in text.lines() {
+                    table.update(line, Instant::now());
+                }
+            }
+        }
+        AisSource::Tcp { address } => {
+            let stream = TcpStream::connect(address)
+                .await
+                .with_context(|| format!("Failed to connect to AIS feed at {}", address))?;
+            info!(address = %address, "Connected to AIS feed");
+
+            let mut lines = BufReader::new(stream).lines();
+            while let Some(line) = lines.next_line().await.context("AIS feed read failed")? {
+                table.lock().unwrap().update(&line, Instant::now());
+            }
+            anyhow::bail!("AIS feed closed the connection")
+        }
+    }
+}
+
+// ============================================================================
+// Vessel Table
+// ============================================================================
+
+/// Latest known state of one vessel
+#[derive(Debug, Default)]
+struct Vessel {
+    name: Option<String>,
+    callsign: Option<String>,
+    ship_type: Option<u32>,
+    nav_status: Option<u32>,
+    position: Option<(f64, f64)>,
+    speed_kt: Option<f64>,
+    course_deg: Option<f64>,
+    heading_deg: Option<f64>,
+    accurate: bool,
+    last_seen: Option<Instant>,
+    last_published: Option<Instant>,
+    updated: bool,
+}
+
+/// Fragments of a multi-sentence message collected so far
+#[derive(Debug)]
+struct PendingMessage {
+    total: usize,
+    payload: String,
+    next: usize,
+    started: Instant,
+}
+
+/// Vessels keyed by MMSI
+#[derive(Debug, Default)]
+struct VesselTable {
+    vessels: HashMap<u32, Vessel>,
+    /// Incomplete multi-sentence messages keyed by (sequence ID, channel)
+    pending: HashMap<(String, String), PendingMessage>,
+}
+
+impl VesselTable {
+    /// Apply one NMEA sentence
+    fn update(&mut self, line: &str, now: Instant) {
+        let Some(body) = verify_checksum(line.trim()) else {
+            return;
+        };
+        let fields: Vec<&str> = body.split(',').collect();
+        if fields.len() < 7 || !(fields[0].ends_with("VDM") || fields[0].ends_with("VDO")) {
+            return;
+        }
+
+        let (Ok(total), Ok(number)) = (fields[1].parse::<usize>(), fields[2].parse::<usize>())
+        else {
+            return;
+        };
+
+        if total == 1 {
+            if let Some(bits) = decode_armor(fields[5]) {
+                self.apply(&bits, now);
+            }
+            return;
+        }
+
+        self.pending
+            .retain(|_, p| now.duration_since(p.started) <= FRAGMENT_TIMEOUT);
+        let key = (fields[3].to_string(), fields[4].to_string());
+        if number == 1 {
+            self.pending.insert(
+                key.clone(),
+                PendingMessage {
+                    total,
+                    payload: String::new(),
+                    next: 1,
+                    started: now,
+                },
+            );
+        }
+
+        let Some(pending) = self.pending.get_mut(&key) else {
+            return;
+        };
+        if pending.total != total || pending.next != number {
+            // Lost a fragment
+            self.pending.remove(&key);
+            return;
+        }
+        pending.payload.push_str(fields[5]);
+        pending.next += 1;
+
+        if number == total {
+            let pending = self.pending.remove(&key).unwrap();
+            if let Some(bits) = decode_armor(&pending.payload) {
+                self.apply(&bits, now);
+            }
+        }
+    }
+
+    /// Apply a decoded message
+    fn apply(&mut self, bits: &Bits, now: Instant) {
+        let msg_type = bits.uint(0, 6);
+        let mmsi = bits.uint(8, 30);
+        if mmsi == 0 {
+            return;
+        }
+
+        let vessel = match msg_type {
+            1..=3 | 5 | 18 | 19 | 24 => self.vessels.entry(mmsi).or_default(),
+            _ => return,
+        };
+
+        match msg_type {
+            1..=3 => {
+                vessel.nav_status = Some(bits.uint(38, 4));
+                vessel.apply_position(bits, 50);
+            }
+            18 | 19 => {
+                vessel.apply_position(bits, 46);
+                if msg_type == 19 {
+                    vessel.name = bits.text(143, 20).or(vessel.name.take());
+                    vessel.ship_type = Some(bits.uint(263, 8));
+                }
+            }
+            5 => {
+                vessel.callsign = bits.text(70, 7).or(vessel.callsign.take());
+                vessel.name = bits.text(112, 20).or(vessel.name.take());
+                vessel.ship_type = Some(bits.uint(232, 8));
+            }
+            24 => match bits.uint(38, 2) {
+                0 => vessel.name = bits.text(40, 20).or(vessel.name.take()),
+                1 => {
+                    vessel.ship_type = Some(bits.uint(40, 8));
+                    vessel.callsign = bits.text(90, 7).or(vessel.callsign.take());
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+
+        vessel.last_seen = Some(now);
+        vessel.updated = true;
+    }
+
+    /// Drop vessels not heard from within `max_age`
+    fn expire(&mut self, max_age: Duration, now: Instant) {
+        self.vessels.retain(|mmsi, vessel| {
+            let alive = vessel
+                .last_seen
+                .is_some_and(|seen| now.duration_since(seen) <= max_age);
+            if !alive {
+                debug!(mmsi, "Vessel expired");
+            }
+            alive
+        });
+    }
+
+    /// Events for positioned vessels updated since they were last published
+    fn due_events(&mut self, config: &AisConfig, now: Instant) -> Vec<Event> {
+        let min_interval = Duration::from_secs(config.update_interval_secs);
+        let mut events = Vec::new();
+
+        for (mmsi, vessel) in &mut self.vessels {
+            if !vessel.updated || vessel.position.is_none() {
+                continue;
+            }
+            if let Some(last) = vessel.last_published {
+                if now.duration_since(last) < min_interval {
+                    continue;
+                }
+            }
+            events.push(vessel_event(config, *mmsi, vessel));
+            vessel.last_published = Some(now);
+            vessel.updated = false;
+        }
+
+        events
+    }
+}
+
+impl Vessel {
+    /// Apply the kinematic fields of a position report. Class A and Class B
+    /// share the same layout from speed over ground onwards, starting at `sog`.
+    fn apply_position(&mut self, bits: &Bits, sog: usize) {
+        let lon = bits.int(sog + 11, 28) as f64 / 600_000.0;
+        let lat = bits.int(sog + 39, 27) as f64 / 600_000.0;
+        // 181°/91° mean "not available"
+        if lon.abs() <= 180.0 && lat.abs() <= 90.0 {
+            self.position = Some((lat, lon));
+        }
+
+        let speed = bits.uint(sog, 10);
+        self.speed_kt = (speed != 1023).then(|| speed as f64 / 10.0);
+        let course = bits.uint(sog + 66, 12);
+        self.course_deg = (course < 3600).then(|| course as f64 / 10.0);
+        let heading = bits.uint(sog + 78, 9);
+        self.heading_deg = (heading < 360).then_some(heading as f64);
+        self.accurate = bits.uint(sog + 10, 1) == 1;
+    }
+}
+
+/// Build the CoT event for a vessel
+fn vessel_event(config: &AisConfig, mmsi: u32, vessel: &Vessel) -> Event {
+    let now = Utc::now();
+    let (lat, lon) = vessel.position.unwrap_or_default();
+
+    // AIS text may contain XML markup characters
+    let callsign = vessel
+        .name
+        .as_deref()
+        .map_or_else(|| format!("MMSI {}", mmsi), escape_xml);
+
+    let track = vessel.speed_kt.map(|speed| Track {
+        speed: speed * KNOTS_TO_MPS,
+        course: vessel.course_deg.or(vessel.heading_deg).unwrap_or(0.0),
+    });
+
+    let mut remarks = format!("MMSI {}", mmsi);
+    if let Some(callsign) = &vessel.callsign {
+        remarks.push_str(&format!(" callsign {}", callsign));
+    }
+    if let Some(ship_type) = vessel.ship_type {
+        remarks.push_str(&format!(" type {}", ship_type));
+    }
+    if let Some(status) = vessel.nav_status.and_then(nav_status_name) {
+        remarks.push_str(&format!(" ({})", status));
+    }
+
+    Event {
+        version: "2.0".to_string(),
+        uid: format!("{}{}", config.uid_prefix, mmsi),
+        event_type: config.cot_type.clone(),
+        time: now,
+        start: now,
+        stale: now + ChronoDuration::seconds(config.stale_secs as i64),
+        how: "m-g".to_string(),
+        point: Point {
+            lat,
+            lon,
+            hae: 0.0,
+            // Position accuracy flag: better or worse than 10 m
+            ce: if vessel.accurate { 10.0 } else { 100.0 },
+            le: UNKNOWN,
+        },
+        detail: Some(Detail {
+            contact: Some(Contact {
+                endpoint: None,
+                callsign,
+            }),
+            track,
+            xml_detail: Some(format!("<remarks>{}</remarks>", escape_xml(&remarks))),
+            ..Default::default()
+        }),
+    }
+}
+
+/// Human-readable navigational status
+fn nav_status_name(status: u32) -> Option<&'static str> {
+    Some(match status {
+        0 => "under way using engine",
+        1 => "at anchor",
+        2 => "not under command",
+        3 => "restricted manoeuvrability",
+        4 => "constrained by draught",
+        5 => "moored",
+        6 => "aground",
+        7 => "engaged in fishing",
+        8 => "under way sailing",
+        14 => "AIS-SART active",
+        _ => return None,
+    })
+}
+
+// ============================================================================
+// Payload decoding
+// ============================================================================
+
+/// De-armored AIS payload bits
+struct Bits(Vec<u8>);
+
+impl Bits {
+    /// Unsigned field; bits past the end of the payload read as zero
+    fn uint(&self, start: usize, len: usize) -> u32 {
+        (start..start + len).fold(0, |acc, i| {
+            (acc << 1) | self.0.get(i).copied().unwrap_or(0) as u32
+        })
+    }
+
+    /// Two's complement signed field
+    fn int(&self, start: usize, len: usize) -> i32 {
+        let value = self.uint(start, len) as i32;
+        (value << (32 - len)) >> (32 - len)
+    }
+
+    /// Six-bit ASCII text field of `chars` characters, trimmed of padding
+    fn text(&self, start: usize, chars: usize) -> Option<String> {
+        const CHARSET: &[u8; 64] =
+            b"@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_ !\"#$%&'()*+,-./0123456789:;<=>?";
+        let text: String = (0..chars)
+            .map(|i| CHARSET[self.uint(start + i * 6, 6) as usize] as char)
+            .collect();
+        let text = text.trim_end_matches('@').trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+}
+
+/// Decode the six-bit ASCII armoring of a sentence payload into bits
+fn decode_armor(payload: &str) -> Option<Bits> {
+    let mut bits = Vec::with_capacity(payload.len() * 6);
+    for c in payload.bytes() {
+        let mut value = c.checked_sub(48)?;
+        if value > 40 {
+            value -= 8;
+        }
+        if value > 63 {
+            return None;
+        }
+        bits.extend((0..6).rev().map(|i| (value >> i) & 1));
+    }
+    Some(Bits(bits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AisConfig {
+        serde_yaml::from_str("source:\n  type: udp\n").unwrap()
+    }
+
+    #[test]
+    fn test_class_a_position() {
+        let mut table = VesselTable::default();
+        table.update(
+            "!AIVDM,1,1,,B,177KQJ5000G?tO`K>RA1wUbN0TKH,0*5C",
+            Instant::now(),
+        );
+
+        let vessel = &table.vessels[&477553000];
+        let (lat, lon) = vessel.position.unwrap();
+        assert!((lat - 47.582833).abs() < 1e-5);
+        assert!((lon - -122.345833).abs() < 1e-5);
+        assert_eq!(vessel.nav_status, Some(5));
+        assert_eq!(vessel.speed_kt, Some(0.0));
+        assert_eq!(vessel.course_deg, Some(51.0));
+        assert_eq!(vessel.heading_deg, Some(181.0));
+    }
+
+    #[test]
+    fn test_multipart_static_data() {
+        let mut table = VesselTable::default();
+        let now = Instant::now();
+        table.update(
+            "!AIVDM,2,1,1,A,55?MbV02;H;s<HtKR20EHE:0@T4@Dn2222222216L961O5Gf0NSQEp6ClRp8,0*1C",
+            now,
+        );
+        assert!(table.vessels.is_empty());
+        table.update("!AIVDM,2,2,1,A,88888888880,2*25", now);
+
+        let vessel = &table.vessels[&351759000];
+        assert_eq!(vessel.name.as_deref(), Some("EVER DIADEM"));
+        assert_eq!(vessel.callsign.as_deref(), Some("3FOF8"));
+        assert_eq!(vessel.ship_type, Some(70));
+        assert!(table.pending.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_bad_checksum_and_orphan_fragments() {
+        let mut table = VesselTable::default();
+        let now = Instant::now();
+        table.update("!AIVDM,1,1,,B,177KQJ5000G?tO`K>RA1wUbN0TKH,0*5D", now);
+        table.update("!AIVDM,2,2,1,A,88888888880,2*25", now);
+        assert!(table.vessels.is_empty());
+    }
+
+    #[test]
+    fn test_vessel_events() {
+        let config = config();
+        let mut table = VesselTable::default();
+        let now = Instant::now();
+
+        table.update("!AIVDM,1,1,,B,177KQJ5000G?tO`K>RA1wUbN0TKH,0*5C", now);
+        let events = table.due_events(&config, now);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].uid, "MMSI-477553000");
+        assert_eq!(events[0].event_type, "a-u-S");
+        let detail = events[0].detail.as_ref().unwrap();
+        assert_eq!(detail.contact.as_ref().unwrap().callsign, "MMSI 477553000");
+        assert!(detail.xml_detail.as_ref().unwrap().contains("moored"));
+
+        // Nothing new to publish
+        assert!(table
+            .due_events(&config, now + Duration::from_secs(60))
+            .is_empty());
+
+        table.expire(Duration::from_secs(600), now + Duration::from_secs(601));
+        assert!(table.vessels.is_empty());
+    }
+
+    #[test]
+    fn test_vessel_text_escaped() {
+        let vessel = Vessel {
+            name: Some("R&D <1>".to_string()),
+            callsign: Some("A\"B'C".to_string()),
+            ..Default::default()
+        };
+        let event = vessel_event(&config(), 477553000, &vessel);
+        let detail = event.detail.as_ref().unwrap();
+        assert_eq!(
+            detail.contact.as_ref().unwrap().callsign,
+            "R&amp;D &lt;1&gt;"
+        );
+        assert!(detail
+            .xml_detail
+            .as_ref()
+            .unwrap()
+            .contains("callsign A&quot;B&apos;C"));
+        assert!(omnitak_cot::parse_cot(&omnitak_cot::serialize_event(&event)).is_ok());
+    }
+}
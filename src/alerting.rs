@@ -0,0 +1,214 @@
+//! Operator Alerting
+//!
+//! Turns system events into alerts and hands them to the API's
+//! [`AlertManager`], which routes each alert to the email, Slack and Matrix
+//! channels whose severity threshold it meets.
+
+use crate::events::{EventBus, SystemEvent};
+use omnitak_api::alerts::{Alert, AlertSeverity};
+use omnitak_api::AlertManager;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::warn;
+
+/// Deliver alerts for bus events until the bus closes
+pub fn spawn(manager: Arc<AlertManager>, bus: &EventBus) {
+    let mut events = bus.subscribe();
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    // Deliver in the background so a slow channel can't hold
+                    // up alerts for later events
+                    let manager = Arc::clone(&manager);
+                    tokio::spawn(async move {
+                        manager.dispatch(&alert_for(&event)).await;
+                    });
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Alerting fell behind, dropped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Severity and wording for an event
+fn alert_for(event: &SystemEvent) -> Alert {
+    match event {
+        SystemEvent::ConnectionUp {
+            connection_id,
+            address,
+        } => Alert::new(
+            AlertSeverity::Info,
+            format!("Connection {} up", connection_id),
+            format!("Connected to {}", address),
+        ),
+        SystemEvent::ConnectionDown {
+            connection_id,
+            address,
+            reason,
+        } => Alert::new(
+            AlertSeverity::Warning,
+            format!("Connection {} down", connection_id),
+            match reason {
+                Some(reason) => format!("Lost connection to {}: {}", address, reason),
+                None => format!("Lost connection to {}", address),
+            },
+        ),
+        SystemEvent::CircuitBreaker {
+            connection_id,
+            previous,
+            state,
+        } => Alert::new(
+            if *state == "open" {
+                AlertSeverity::Warning
+            } else {
+                AlertSeverity::Info
+            },
+            format!("Circuit breaker {} for {}", state, connection_id),
+            format!("Circuit breaker changed from {} to {}", previous, state),
+        ),
+        SystemEvent::CertificateExpiry {
+            path,
+            subject,
+            not_after,
+            days_until_expiry,
+            expired,
+        } => {
+            if *expired {
+                Alert::new(
+                    AlertSeverity::Critical,
+                    format!("Certificate {} expired", subject),
+                    format!("{} expired on {}", path, not_after),
+                )
+            } else {
+                Alert::new(
+                    AlertSeverity::Warning,
+                    format!(
+                        "Certificate {} expires in {} days",
+                        subject, days_until_expiry
+                    ),
+                    format!("{} expires on {}", path, not_after),
+                )
+            }
+        }
+        SystemEvent::FilterMatch {
+            connection_id,
+            matches,
+            window_secs,
+        } => Alert::new(
+            AlertSeverity::Info,
+            format!("Filter matches on {}", connection_id),
+            format!("{} messages matched in the last {}s", matches, window_secs),
+        ),
+        SystemEvent::Emergency {
+            uid,
+            kind,
+            callsign,
+            connection_id,
+            lat,
+            lon,
+            state,
+            operator,
+        } => {
+            let who = callsign.as_deref().unwrap_or(uid);
+            if *state == "active" {
+                Alert::new(
+                    AlertSeverity::Critical,
+                    format!("EMERGENCY: {} from {}", kind, who),
+                    format!(
+                        "{} raised at {:.5}, {:.5} (received via {})",
+                        kind, lat, lon, connection_id
+                    ),
+                )
+            } else {
+                Alert::new(
+                    AlertSeverity::Info,
+                    format!("Emergency {} for {}", state, who),
+                    match operator {
+                        Some(operator) => format!("{} {} by {}", kind, state, operator),
+                        None => format!("{} {}", kind, state),
+                    },
+                )
+            }
+        }
+        SystemEvent::Anomaly {
+            uid,
+            kind,
+            callsign,
+            connection_id,
+            lat,
+            lon,
+            value,
+            limit,
+            quarantined,
+        } => Alert::new(
+            AlertSeverity::Warning,
+            format!(
+                "Track anomaly ({}) for {}",
+                kind,
+                callsign.as_deref().unwrap_or(uid)
+            ),
+            format!(
+                "{:.1} against a limit of {:.1} at {:.5}, {:.5} (received via {}){}",
+                value,
+                limit,
+                lat,
+                lon,
+                connection_id,
+                if *quarantined { "; report quarantined" } else { "" }
+            ),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_severities() {
+        let down = alert_for(&SystemEvent::ConnectionDown {
+            connection_id: "tak-server-main".to_string(),
+            address: "tak.example.com:8089".to_string(),
+            reason: Some("connection reset".to_string()),
+        });
+        assert_eq!(down.severity, AlertSeverity::Warning);
+        assert_eq!(
+            down.message,
+            "Lost connection to tak.example.com:8089: connection reset"
+        );
+
+        let recovered = alert_for(&SystemEvent::CircuitBreaker {
+            connection_id: "tak-server-main".to_string(),
+            previous: "open",
+            state: "closed",
+        });
+        assert_eq!(recovered.severity, AlertSeverity::Info);
+
+        let expired = alert_for(&SystemEvent::CertificateExpiry {
+            path: "certs/client.pem".to_string(),
+            subject: "omnitak".to_string(),
+            not_after: "2026-01-01".to_string(),
+            days_until_expiry: -3,
+            expired: true,
+        });
+        assert_eq!(expired.severity, AlertSeverity::Critical);
+        assert_eq!(expired.subject(), "[CRITICAL] Certificate omnitak expired");
+
+        let emergency = alert_for(&SystemEvent::Emergency {
+            uid: "ANDROID-1-9-1-1".to_string(),
+            kind: "911 Alert",
+            callsign: Some("VIPER-1".to_string()),
+            connection_id: "tak-server-main".to_string(),
+            lat: 34.1,
+            lon: -117.2,
+            state: "active",
+            operator: None,
+        });
+        assert_eq!(emergency.severity, AlertSeverity::Critical);
+        assert_eq!(emergency.title, "EMERGENCY: 911 Alert from VIPER-1");
+    }
+}
@@ -0,0 +1,424 @@
+//! Load generator (`omnitak bench`)
+//!
+//! Publishers send synthetic position reports over TCP at a fixed rate while
+//! subscribers connected to the same endpoint receive what is routed back.
+//! Each report carries its send time, so subscribers measure end-to-end
+//! latency. Without `--target` the endpoint is an in-process routing core
+//! (pool, aggregator and distributor with default settings) behind a
+//! loopback listener, which isolates OmniTAK's own routing; with `--target`
+//! the reports go to a running OmniTAK TCP listener or a TAK server.
+//!
+//! Reports sent during the warm-up are routed but left out of the results.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use hdrhistogram::Histogram;
+use omnitak_core::config;
+use omnitak_pool::{
+    AggregatorConfig, ConnectionPool, DistributorConfig, MessageAggregator, MessageDistributor,
+    PoolConfig,
+};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::server_listener::{ListenerConfig, TcpListener as ServerTcpListener};
+
+const FRAME_END: &[u8] = b"</event>";
+
+/// Time for subscribers to be registered before publishing starts
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// Time for in-flight reports to arrive after publishing stops
+const DRAIN_TIME: Duration = Duration::from_secs(2);
+
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// TAK TCP endpoint (host:port) to load instead of an in-process core
+    #[arg(long)]
+    target: Option<String>,
+
+    /// Publishing connections
+    #[arg(long, default_value = "10", value_parser = clap::value_parser!(u64).range(1..))]
+    publishers: u64,
+
+    /// Reports per second per publisher
+    #[arg(long, default_value = "100")]
+    rate: f64,
+
+    /// Receiving connections; every report should reach each of them
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u64).range(1..))]
+    subscribers: u64,
+
+    /// Measured seconds, after the warm-up
+    #[arg(long, default_value = "30", value_parser = clap::value_parser!(u64).range(1..))]
+    duration: u64,
+
+    /// Seconds of load before measuring starts
+    #[arg(long, default_value = "2")]
+    warmup: u64,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// Counters shared by publishers and subscribers
+struct Stats {
+    /// Reports sent after the warm-up
+    sent: AtomicU64,
+    /// Measured reports received, over all subscribers
+    delivered: AtomicU64,
+    /// Delivery latency in microseconds
+    latency: Mutex<Histogram<u64>>,
+    /// Reports sent before this time are warm-up
+    measure_from_us: AtomicU64,
+}
+
+impl Stats {
+    fn new() -> Self {
+        Self {
+            sent: AtomicU64::new(0),
+            delivered: AtomicU64::new(0),
+            latency: Mutex::new(Histogram::new_with_bounds(1, 3_600_000_000, 3).unwrap()),
+            measure_from_us: AtomicU64::new(u64::MAX),
+        }
+    }
+
+    fn measuring(&self, sent_us: u64) -> bool {
+        sent_us >= self.measure_from_us.load(Ordering::Relaxed)
+    }
+
+    /// Record a frame received by a subscriber if it is one of this run's
+    fn record_delivery(&self, frame: &[u8], run_id: &str) {
+        let Ok(frame) = std::str::from_utf8(frame) else {
+            return;
+        };
+        let Some(sent) = sent_time(frame, run_id) else {
+            return;
+        };
+        if !self.measuring(sent) {
+            return;
+        }
+        self.delivered.fetch_add(1, Ordering::Relaxed);
+        self.latency
+            .lock()
+            .unwrap()
+            .saturating_record(unix_micros().saturating_sub(sent));
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    target: String,
+    publishers: u64,
+    rate_per_publisher: f64,
+    subscribers: u64,
+    duration_secs: f64,
+    sent: u64,
+    sent_per_sec: f64,
+    expected: u64,
+    delivered: u64,
+    delivered_per_sec: f64,
+    loss_percent: f64,
+    latency_p50_ms: f64,
+    latency_p90_ms: f64,
+    latency_p99_ms: f64,
+    latency_p999_ms: f64,
+    latency_max_ms: f64,
+}
+
+/// Run the benchmark and print its report
+pub fn run(args: BenchArgs) -> Result<()> {
+    if !args.rate.is_finite() || args.rate <= 0.0 {
+        anyhow::bail!("--rate must be positive");
+    }
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("Failed to start runtime")?;
+    let report = runtime.block_on(bench(&args))?;
+    runtime.shutdown_timeout(Duration::from_secs(1));
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    Ok(())
+}
+
+async fn bench(args: &BenchArgs) -> Result<Report> {
+    // The listener is held until the end so the in-process core keeps running
+    let (target, local) = match &args.target {
+        Some(target) => (target.clone(), None),
+        None => {
+            let (listener, address) =
+                start_core((args.publishers + args.subscribers) as usize).await?;
+            (address, Some(listener))
+        }
+    };
+
+    let run_id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+    let stats = Arc::new(Stats::new());
+    let running = Arc::new(AtomicBool::new(true));
+
+    for _ in 0..args.subscribers {
+        let stream = connect(&target).await?;
+        tokio::spawn(subscribe(stream, run_id.clone(), Arc::clone(&stats)));
+    }
+    tokio::time::sleep(SETTLE_TIME).await;
+
+    for id in 0..args.publishers {
+        let stream = connect(&target).await?;
+        tokio::spawn(publish(
+            stream,
+            id,
+            args.rate,
+            run_id.clone(),
+            Arc::clone(&running),
+            Arc::clone(&stats),
+        ));
+    }
+
+    tokio::time::sleep(Duration::from_secs(args.warmup)).await;
+    stats
+        .measure_from_us
+        .store(unix_micros(), Ordering::Relaxed);
+    let started = Instant::now();
+    tokio::time::sleep(Duration::from_secs(args.duration)).await;
+    running.store(false, Ordering::Relaxed);
+    let elapsed = started.elapsed();
+    tokio::time::sleep(DRAIN_TIME).await;
+
+    let target = match &local {
+        Some(_) => format!("in-process core ({})", target),
+        None => target,
+    };
+    Ok(build_report(args, target, &stats, elapsed))
+}
+
+/// Routing core with the server's default settings, accepting TAK clients
+/// on a loopback port
+async fn start_core(connections: usize) -> Result<(ServerTcpListener, String)> {
+    let mut pool_config = PoolConfig::from(&config::PoolConfig::default());
+    pool_config.max_connections = pool_config.max_connections.max(connections);
+    let pool = Arc::new(ConnectionPool::new(pool_config));
+    let distributor = Arc::new(MessageDistributor::new(
+        Arc::clone(&pool),
+        DistributorConfig::from(&config::DistributorConfig::default()),
+    ));
+    distributor.start().await;
+    let aggregator = Arc::new(MessageAggregator::new(
+        Arc::clone(&distributor),
+        AggregatorConfig::from(&config::AggregatorConfig::default()),
+    ));
+    aggregator.start().await;
+
+    let socket = std::net::TcpListener::bind("127.0.0.1:0")
+        .context("Failed to bind benchmark listener")?;
+    let address = socket.local_addr()?.to_string();
+    let mut listener = ServerTcpListener::new(
+        ListenerConfig {
+            id: "bench".to_string(),
+            bind_addr: address.clone(),
+            max_connections: connections + 10,
+            ..ListenerConfig::default()
+        },
+        pool,
+        aggregator,
+    );
+    listener.set_inherited_socket(socket);
+    listener.start().await?;
+    Ok((listener, address))
+}
+
+async fn connect(target: &str) -> Result<TcpStream> {
+    let stream = TcpStream::connect(target)
+        .await
+        .with_context(|| format!("Failed to connect to {}", target))?;
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
+
+/// Send reports at `rate` per second, discarding what the endpoint routes
+/// to this connection
+async fn publish(
+    stream: TcpStream,
+    id: u64,
+    rate: f64,
+    run_id: String,
+    running: Arc<AtomicBool>,
+    stats: Arc<Stats>,
+) {
+    let (mut reader, mut writer) = stream.into_split();
+    let drain = tokio::spawn(async move {
+        let mut buf = vec![0u8; 64 * 1024];
+        while matches!(reader.read(&mut buf).await, Ok(n) if n > 0) {}
+    });
+
+    let period = Duration::from_secs_f64(1.0 / rate);
+    // Spread publishers over the first period
+    tokio::time::sleep(period.mul_f64(rand::random::<f64>())).await;
+    let mut ticks = tokio::time::interval(period);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Burst);
+    let mut seq = 0u64;
+    while running.load(Ordering::Relaxed) {
+        ticks.tick().await;
+        seq += 1;
+        let sent_us = unix_micros();
+        if writer
+            .write_all(&report(&run_id, id, seq, sent_us))
+            .await
+            .is_err()
+        {
+            break;
+        }
+        if stats.measuring(sent_us) {
+            stats.sent.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    drain.abort();
+}
+
+/// Record every report of this run that reaches the connection
+async fn subscribe(mut stream: TcpStream, run_id: String, stats: Arc<Stats>) {
+    let mut buffer = Vec::with_capacity(64 * 1024);
+    let mut chunk = vec![0u8; 64 * 1024];
+    loop {
+        let n = match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => n,
+        };
+        buffer.extend_from_slice(&chunk[..n]);
+        let mut start = 0;
+        while let Some(end) = find(&buffer[start..], FRAME_END) {
+            let frame_end = start + end + FRAME_END.len();
+            stats.record_delivery(&buffer[start..frame_end], &run_id);
+            start = frame_end;
+        }
+        buffer.drain(..start);
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// A position report. Every report has its own UID so deduplication cannot
+/// hide drops.
+fn report(run_id: &str, id: u64, seq: u64, sent_us: u64) -> Vec<u8> {
+    let now = chrono::Utc::now();
+    let stale = now + chrono::Duration::minutes(5);
+    let lat = 38.0 + (id % 1000) as f64 * 0.001;
+    let lon = -77.0 + (id / 1000) as f64 * 0.001;
+    format!(
+        "<event version=\"2.0\" uid=\"bench-{run_id}-{id}-{seq}\" type=\"a-f-G-U-C\" how=\"m-g\" \
+         time=\"{time}\" start=\"{time}\" stale=\"{stale}\">\
+         <point lat=\"{lat:.6}\" lon=\"{lon:.6}\" hae=\"0\" ce=\"10\" le=\"10\"/>\
+         <detail><contact callsign=\"BENCH-{id}\"/><bench run=\"{run_id}\" sent_us=\"{sent_us}\"/></detail>\
+         </event>",
+        time = now.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        stale = stale.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+    )
+    .into_bytes()
+}
+
+/// Send time of a report from run `run_id`
+fn sent_time(frame: &str, run_id: &str) -> Option<u64> {
+    let (_, rest) = frame.split_once("<bench run=\"")?;
+    let rest = rest.strip_prefix(run_id)?.strip_prefix("\" sent_us=\"")?;
+    rest.split('"').next()?.parse().ok()
+}
+
+fn build_report(args: &BenchArgs, target: String, stats: &Stats, elapsed: Duration) -> Report {
+    let sent = stats.sent.load(Ordering::Relaxed);
+    let delivered = stats.delivered.load(Ordering::Relaxed);
+    let expected = sent * args.subscribers;
+    let loss_percent = if expected == 0 {
+        0.0
+    } else {
+        expected.saturating_sub(delivered) as f64 * 100.0 / expected as f64
+    };
+    let secs = elapsed.as_secs_f64();
+    let latency = stats.latency.lock().unwrap();
+    let ms = |quantile: f64| latency.value_at_quantile(quantile) as f64 / 1000.0;
+
+    Report {
+        target,
+        publishers: args.publishers,
+        rate_per_publisher: args.rate,
+        subscribers: args.subscribers,
+        duration_secs: secs,
+        sent,
+        sent_per_sec: sent as f64 / secs,
+        expected,
+        delivered,
+        delivered_per_sec: delivered as f64 / secs,
+        loss_percent,
+        latency_p50_ms: ms(0.5),
+        latency_p90_ms: ms(0.9),
+        latency_p99_ms: ms(0.99),
+        latency_p999_ms: ms(0.999),
+        latency_max_ms: latency.max() as f64 / 1000.0,
+    }
+}
+
+fn print_report(report: &Report) {
+    println!("Target        {}", report.target);
+    println!(
+        "Load          {} publisher(s) x {} msg/s, {} subscriber(s), {:.1} s",
+        report.publishers, report.rate_per_publisher, report.subscribers, report.duration_secs
+    );
+    println!(
+        "Sent          {} ({:.0} msg/s)",
+        report.sent, report.sent_per_sec
+    );
+    println!(
+        "Delivered     {} of {} ({:.0} msg/s), {:.3}% lost",
+        report.delivered, report.expected, report.delivered_per_sec, report.loss_percent
+    );
+    println!(
+        "Latency (ms)  p50 {:.3}  p90 {:.3}  p99 {:.3}  p99.9 {:.3}  max {:.3}",
+        report.latency_p50_ms,
+        report.latency_p90_ms,
+        report.latency_p99_ms,
+        report.latency_p999_ms,
+        report.latency_max_ms
+    );
+}
+
+fn unix_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_micros() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sent_time_only_for_this_run() {
+        let frame = String::from_utf8(report("abcd1234", 3, 7, 1_700_000_000_000_000)).unwrap();
+        assert!(frame.contains("uid=\"bench-abcd1234-3-7\""));
+        assert!(frame.ends_with("</event>"));
+        assert_eq!(sent_time(&frame, "abcd1234"), Some(1_700_000_000_000_000));
+        assert_eq!(sent_time(&frame, "ffff0000"), None);
+    }
+
+    #[test]
+    fn test_warmup_reports_not_recorded() {
+        let stats = Stats::new();
+        stats.measure_from_us.store(1_000, Ordering::Relaxed);
+        stats.record_delivery(&report("run", 0, 1, 999), "run");
+        stats.record_delivery(&report("run", 0, 2, 1_000), "run");
+        stats.record_delivery(&report("other", 0, 3, 2_000), "run");
+        assert_eq!(stats.delivered.load(Ordering::Relaxed), 1);
+    }
+}
@@ -597,7 +597,6 @@ fn save_config(config: &OmniTakConfig, output: &Path) -> Result<()> {
     yaml.push_str("  bind_addr: \"127.0.0.1:9443\"\n");
     yaml.push_str("  enable_tls: false\n");
     yaml.push_str("  jwt_expiration: 86400\n");
-    yaml.push_str("  rate_limit_rps: 100\n");
     yaml.push_str("  enable_swagger: true\n");
     yaml.push_str("  enable_static_files: true\n\n");
 
@@ -0,0 +1,648 @@
+//! omnitak-soak - long-running load test with failure injection
+//!
+//! Runs OmniTAK's connection pool, aggregator and distributor in-process,
+//! connected over TCP to N mock TAK servers. M simulated clients, spread over
+//! the servers, publish position reports through their server; every report
+//! should reach every other server through OmniTAK. Servers can be restarted
+//! on a schedule and the links can drop a share of frames. At the end a
+//! report with delivery, latency and memory growth is printed (and written as
+//! JSON with --report), and the process exits non-zero if a --max-* limit was
+//! exceeded, so it can gate a release.
+//!
+//! Example (10,000 clients for four hours, a restart every ten minutes):
+//!
+//!   omnitak-soak --servers 8 --clients 10000 --duration 14400 \
+//!       --restart-every 600 --loss 0.1 --max-drop-percent 1 --report soak.json
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use hdrhistogram::Histogram;
+use omnitak_api::ResourceMonitor;
+use omnitak_client::{
+    tcp::{TcpClient, TcpClientConfig},
+    Bytes, CotMessage, TakClient,
+};
+use omnitak_pool::{
+    AggregatorConfig, ConnectionPool, DistributionStrategy, DistributorConfig, FilterRule,
+    InboundMessage, MessageAggregator, MessageDistributor, PoolConfig, PoolMessage, TraceId,
+};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, watch, Notify};
+use tokio_stream::StreamExt;
+use tracing::{info, warn};
+
+/// Soak test OmniTAK's routing core against mock TAK servers
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Number of mock TAK servers (at least 2)
+    #[arg(long, default_value = "4")]
+    servers: usize,
+
+    /// Number of simulated clients, spread over the servers
+    #[arg(long, default_value = "100")]
+    clients: usize,
+
+    /// Reports per second per client
+    #[arg(long, default_value = "1.0")]
+    rate: f64,
+
+    /// Test duration in seconds
+    #[arg(long, default_value = "3600")]
+    duration: u64,
+
+    /// Restart a random mock server every this many seconds
+    #[arg(long)]
+    restart_every: Option<u64>,
+
+    /// Seconds a restarted server stays down
+    #[arg(long, default_value = "5")]
+    restart_downtime: u64,
+
+    /// Percentage of frames each server drops instead of sending to OmniTAK
+    #[arg(long, default_value = "0.0")]
+    loss: f64,
+
+    /// Seconds between progress lines
+    #[arg(long, default_value = "60")]
+    report_interval: u64,
+
+    /// Write the final report as JSON to this file
+    #[arg(long)]
+    report: Option<PathBuf>,
+
+    /// Fail if more than this percentage of expected deliveries is missing
+    /// (reports routed while their destination was restarting count as
+    /// missing, so leave headroom when injecting restarts)
+    #[arg(long)]
+    max_drop_percent: Option<f64>,
+
+    /// Fail if any report took longer than this to be delivered
+    #[arg(long)]
+    max_latency_ms: Option<u64>,
+
+    /// Fail if resident memory grew more than this after the first interval
+    #[arg(long)]
+    max_memory_growth_mb: Option<f64>,
+
+    /// Log OmniTAK internals too
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+/// Counters shared by mock servers and clients
+struct Stats {
+    /// Reports written to OmniTAK by the mock servers
+    published: AtomicU64,
+    /// Reports dropped by injected loss
+    lost: AtomicU64,
+    /// Reports not sent because their server was down or had no peer
+    offline: AtomicU64,
+    /// Reports a server fell too far behind on and skipped
+    lagged: AtomicU64,
+    /// Reports received from OmniTAK by the mock servers
+    delivered: AtomicU64,
+    restarts: AtomicU64,
+    /// Delivery latency in microseconds
+    latency: Mutex<Histogram<u64>>,
+}
+
+impl Stats {
+    fn new() -> Self {
+        Self {
+            published: AtomicU64::new(0),
+            lost: AtomicU64::new(0),
+            offline: AtomicU64::new(0),
+            lagged: AtomicU64::new(0),
+            delivered: AtomicU64::new(0),
+            restarts: AtomicU64::new(0),
+            latency: Mutex::new(Histogram::new_with_bounds(1, 3_600_000_000, 3).unwrap()),
+        }
+    }
+
+    fn record_delivery(&self, frame: &str) {
+        let Some(sent) = frame
+            .split_once("sent_us=\"")
+            .and_then(|(_, rest)| rest.split('"').next())
+            .and_then(|s| s.parse::<u64>().ok())
+        else {
+            return;
+        };
+        self.delivered.fetch_add(1, Ordering::Relaxed);
+        let latency = unix_micros().saturating_sub(sent);
+        self.latency.lock().unwrap().saturating_record(latency);
+    }
+}
+
+/// A mock TAK server that can be restarted on the same port
+struct MockServer {
+    addr: SocketAddr,
+    /// Reports from this server's clients, fanned out to connected peers
+    outbound: broadcast::Sender<Arc<[u8]>>,
+    up: AtomicBool,
+    restart: Notify,
+}
+
+/// Final soak test report
+#[derive(Debug, Serialize)]
+struct Report {
+    servers: usize,
+    clients: usize,
+    duration_secs: u64,
+    restarts: u64,
+    published: u64,
+    injected_loss: u64,
+    offline: u64,
+    lagged: u64,
+    expected: u64,
+    delivered: u64,
+    drop_percent: f64,
+    latency_p50_ms: f64,
+    latency_p99_ms: f64,
+    latency_max_ms: f64,
+    memory_start_mb: f64,
+    memory_end_mb: f64,
+    memory_peak_mb: f64,
+    memory_growth_mb: f64,
+    failures: Vec<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    if args.servers < 2 {
+        anyhow::bail!("At least 2 servers are needed to route between");
+    }
+    if args.rate <= 0.0 {
+        anyhow::bail!("Rate must be positive");
+    }
+
+    let filter = if args.verbose {
+        "info"
+    } else {
+        "omnitak_soak=info,warn"
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| filter.into()),
+        )
+        .init();
+
+    let stats = Arc::new(Stats::new());
+
+    // OmniTAK's routing core, configured as in the main binary
+    let pool = Arc::new(ConnectionPool::new(PoolConfig {
+        max_connections: args.servers.max(1000),
+        channel_capacity: 1000,
+        health_check_interval: Duration::from_secs(30),
+        inactive_timeout: Duration::from_secs(300),
+        auto_reconnect: true,
+    }));
+    let distributor = Arc::new(MessageDistributor::new(
+        Arc::clone(&pool),
+        DistributorConfig {
+            channel_capacity: 10_000,
+            strategy: DistributionStrategy::DropOnFull,
+            max_workers: 16,
+            batch_size: 100,
+            flush_interval: Duration::from_millis(10),
+        },
+    ));
+    distributor.start().await;
+    let aggregator = Arc::new(MessageAggregator::new(
+        Arc::clone(&distributor),
+        AggregatorConfig {
+            dedup_window: Duration::from_secs(60),
+            max_cache_entries: 100_000,
+            cleanup_interval: Duration::from_secs(10),
+            channel_capacity: 10_000,
+            worker_count: 4,
+            ..Default::default()
+        },
+    ));
+    aggregator.start().await;
+
+    // Mock servers, each bridged into the pool like a configured TAK server
+    let mut servers = Vec::with_capacity(args.servers);
+    for index in 0..args.servers {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .context("Failed to bind mock server")?;
+        let server = Arc::new(MockServer {
+            addr: listener.local_addr()?,
+            outbound: broadcast::channel(4096).0,
+            up: AtomicBool::new(true),
+            restart: Notify::new(),
+        });
+        tokio::spawn(run_server(
+            Arc::clone(&server),
+            listener,
+            Arc::clone(&stats),
+            args.loss,
+            Duration::from_secs(args.restart_downtime),
+        ));
+        tokio::spawn(bridge(
+            index,
+            server.addr,
+            Arc::clone(&pool),
+            Arc::clone(&aggregator),
+            Arc::clone(&distributor),
+        ));
+        servers.push(server);
+    }
+
+    let running = Arc::new(AtomicBool::new(true));
+    for id in 0..args.clients {
+        tokio::spawn(run_client(
+            id,
+            Arc::clone(&servers[id % servers.len()]),
+            args.rate,
+            Arc::clone(&running),
+            Arc::clone(&stats),
+        ));
+    }
+
+    if let Some(every) = args.restart_every {
+        let servers = servers.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(Duration::from_secs(every));
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                let index = rand::random::<usize>() % servers.len();
+                warn!(server = index, "Restarting mock server");
+                servers[index].restart.notify_one();
+            }
+        });
+    }
+
+    info!(
+        servers = args.servers,
+        clients = args.clients,
+        rate = args.rate,
+        duration_secs = args.duration,
+        "Soak test started"
+    );
+
+    let monitor = ResourceMonitor::new();
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(args.duration);
+    let mut memory_start = None;
+    let mut memory_peak = 0;
+    let mut ticks = tokio::time::interval(Duration::from_secs(args.report_interval.max(1)));
+    ticks.tick().await;
+    while Instant::now() < deadline {
+        tokio::select! {
+            _ = ticks.tick() => {}
+            _ = tokio::time::sleep_until(deadline.into()) => break,
+        }
+        let rss = monitor.sample().memory_rss_bytes;
+        // Startup allocations settle during the first interval
+        let start = *memory_start.get_or_insert(rss);
+        memory_peak = memory_peak.max(rss);
+        let latency = stats.latency.lock().unwrap();
+        info!(
+            elapsed_secs = started.elapsed().as_secs(),
+            published = stats.published.load(Ordering::Relaxed),
+            delivered = stats.delivered.load(Ordering::Relaxed),
+            connections = pool.connection_count(),
+            p99_ms = latency.value_at_quantile(0.99) as f64 / 1000.0,
+            max_ms = latency.max() as f64 / 1000.0,
+            rss_mb = mb(rss),
+            growth_mb = mb(rss) - mb(start),
+            "Progress"
+        );
+    }
+
+    // Stop publishing and let in-flight reports arrive
+    running.store(false, Ordering::Relaxed);
+    let elapsed = started.elapsed();
+    tokio::time::sleep(Duration::from_secs(5)).await;
+
+    let rss = monitor.sample().memory_rss_bytes;
+    let memory_start = memory_start.unwrap_or(rss);
+    let report = build_report(
+        &args,
+        &stats,
+        memory_start,
+        rss,
+        memory_peak.max(rss),
+        elapsed,
+    );
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    if let Some(path) = &args.report {
+        std::fs::write(path, serde_json::to_vec_pretty(&report)?)
+            .with_context(|| format!("Failed to write report to {}", path.display()))?;
+    }
+    if !report.failures.is_empty() {
+        anyhow::bail!("Soak test failed: {}", report.failures.join("; "));
+    }
+    Ok(())
+}
+
+/// Accept OmniTAK's connections until told to restart, then go away for
+/// `downtime` and come back on the same port
+async fn run_server(
+    server: Arc<MockServer>,
+    mut listener: TcpListener,
+    stats: Arc<Stats>,
+    loss: f64,
+    downtime: Duration,
+) {
+    loop {
+        let (stop_tx, stop_rx) = watch::channel(false);
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((socket, _)) => {
+                        tokio::spawn(serve_peer(
+                            socket,
+                            server.outbound.subscribe(),
+                            stop_rx.clone(),
+                            Arc::clone(&stats),
+                            loss,
+                        ));
+                    }
+                    Err(e) => warn!(error = %e, "Mock server accept failed"),
+                },
+                _ = server.restart.notified() => break,
+            }
+        }
+
+        server.up.store(false, Ordering::Relaxed);
+        let _ = stop_tx.send(true);
+        drop(listener);
+        tokio::time::sleep(downtime).await;
+
+        listener = loop {
+            match TcpListener::bind(server.addr).await {
+                Ok(listener) => break listener,
+                Err(e) => {
+                    warn!(addr = %server.addr, error = %e, "Mock server rebind failed");
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        };
+        server.up.store(true, Ordering::Relaxed);
+        stats.restarts.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Send this server's reports to a connected peer and record what it
+/// routes back
+async fn serve_peer(
+    socket: TcpStream,
+    mut outbound: broadcast::Receiver<Arc<[u8]>>,
+    mut stop: watch::Receiver<bool>,
+    stats: Arc<Stats>,
+    loss: f64,
+) {
+    let (reader, mut writer) = socket.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    loop {
+        tokio::select! {
+            _ = stop.changed() => break,
+            line = lines.next_line() => match line {
+                Ok(Some(line)) => stats.record_delivery(&line),
+                _ => break,
+            },
+            frame = outbound.recv() => match frame {
+                Ok(frame) => {
+                    if loss > 0.0 && rand::random::<f64>() * 100.0 < loss {
+                        stats.lost.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+                    if writer.write_all(&frame).await.is_err() {
+                        break;
+                    }
+                    stats.published.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    stats.lagged.fetch_add(skipped, Ordering::Relaxed);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        }
+    }
+}
+
+/// Connect to a mock server and bridge it into the pool, reconnecting
+/// whenever the server goes away
+async fn bridge(
+    index: usize,
+    addr: SocketAddr,
+    pool: Arc<ConnectionPool>,
+    aggregator: Arc<MessageAggregator>,
+    distributor: Arc<MessageDistributor>,
+) {
+    let connection_id = format!("soak-server-{}", index);
+    loop {
+        let mut config = TcpClientConfig::default();
+        config.base.server_addr = addr.to_string();
+        config.base.reconnect.initial_backoff = Duration::from_millis(200);
+        config.base.reconnect.max_backoff = Duration::from_secs(2);
+        let mut client = TcpClient::new(config);
+        if let Err(e) = client.connect().await {
+            warn!(server = index, error = %e, "Failed to connect to mock server");
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            continue;
+        }
+
+        let connection = match pool
+            .add_connection(
+                connection_id.clone(),
+                connection_id.clone(),
+                addr.to_string(),
+                5,
+            )
+            .await
+            .map(|_| pool.get_connection(&connection_id))
+        {
+            Ok(Some(connection)) => connection,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!(server = index, error = %e, "Failed to register with pool");
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        distributor.add_filter(connection_id.clone(), FilterRule::AlwaysSend);
+
+        let mut incoming = client.receive_cot();
+        let outgoing = connection.rx.clone();
+        let sender = aggregator.sender();
+        loop {
+            tokio::select! {
+                message = incoming.next() => match message {
+                    Some(Ok(message)) => {
+                        let inbound = InboundMessage {
+                            data: message.data.to_vec(),
+                            source: connection_id.clone(),
+                            timestamp: Instant::now(),
+                            trace_id: TraceId::new(),
+                        };
+                        if sender.send_async(inbound).await.is_err() {
+                            break;
+                        }
+                    }
+                    _ => break,
+                },
+                message = outgoing.recv_async() => match message {
+                    Ok(PoolMessage::Cot(data)) => {
+                        let message = CotMessage {
+                            data: Bytes::from(data),
+                            metadata: None,
+                        };
+                        if client.send_cot(message).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(PoolMessage::Ping) => {}
+                    Ok(PoolMessage::Shutdown) | Err(_) => break,
+                },
+            }
+        }
+
+        let _ = client.disconnect().await;
+        let _ = pool.remove_connection(&connection_id).await;
+        info!(
+            server = index,
+            "Mock server connection closed, reconnecting"
+        );
+    }
+}
+
+/// Publish position reports through `server` at `rate` per second
+async fn run_client(
+    id: usize,
+    server: Arc<MockServer>,
+    rate: f64,
+    running: Arc<AtomicBool>,
+    stats: Arc<Stats>,
+) {
+    let period = Duration::from_secs_f64(1.0 / rate);
+    // Spread clients over the first period
+    tokio::time::sleep(period.mul_f64(rand::random::<f64>())).await;
+
+    let lat = 38.0 + (id % 1000) as f64 * 0.001;
+    let lon = -77.0 + (id / 1000) as f64 * 0.001;
+    let mut ticks = tokio::time::interval(period);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut seq = 0u64;
+    while running.load(Ordering::Relaxed) {
+        ticks.tick().await;
+        seq += 1;
+        if !server.up.load(Ordering::Relaxed)
+            || server.outbound.send(report(id, seq, lat, lon)).is_err()
+        {
+            stats.offline.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// A newline-terminated position report. Each report gets its own UID so
+/// the aggregator's deduplication doesn't hide drops.
+fn report(id: usize, seq: u64, lat: f64, lon: f64) -> Arc<[u8]> {
+    let now = chrono::Utc::now();
+    let stale = now + chrono::Duration::minutes(5);
+    format!(
+        "<event version=\"2.0\" uid=\"soak-{id}-{seq}\" type=\"a-f-G-U-C\" how=\"m-g\" \
+         time=\"{time}\" start=\"{time}\" stale=\"{stale}\">\
+         <point lat=\"{lat:.6}\" lon=\"{lon:.6}\" hae=\"0\" ce=\"10\" le=\"10\"/>\
+         <detail><contact callsign=\"SOAK-{id}\"/><soak sent_us=\"{sent}\"/></detail>\
+         </event>\n",
+        time = now.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        stale = stale.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        sent = unix_micros(),
+    )
+    .into_bytes()
+    .into()
+}
+
+fn build_report(
+    args: &Args,
+    stats: &Stats,
+    memory_start: u64,
+    memory_end: u64,
+    memory_peak: u64,
+    elapsed: Duration,
+) -> Report {
+    let published = stats.published.load(Ordering::Relaxed);
+    let delivered = stats.delivered.load(Ordering::Relaxed);
+    // Every published report should reach every other server
+    let expected = published * (args.servers as u64 - 1);
+    let drop_percent = if expected == 0 {
+        0.0
+    } else {
+        expected.saturating_sub(delivered) as f64 * 100.0 / expected as f64
+    };
+    let latency = stats.latency.lock().unwrap();
+    let latency_max_ms = latency.max() as f64 / 1000.0;
+    let memory_growth_mb = mb(memory_end) - mb(memory_start);
+
+    let mut failures = Vec::new();
+    if published == 0 {
+        failures.push("no reports were published".to_string());
+    }
+    if let Some(max) = args.max_drop_percent {
+        if drop_percent > max {
+            failures.push(format!("dropped {:.3}% (limit {}%)", drop_percent, max));
+        }
+    }
+    if let Some(max) = args.max_latency_ms {
+        if latency_max_ms > max as f64 {
+            failures.push(format!(
+                "max latency {:.1} ms (limit {} ms)",
+                latency_max_ms, max
+            ));
+        }
+    }
+    if let Some(max) = args.max_memory_growth_mb {
+        if memory_growth_mb > max {
+            failures.push(format!(
+                "memory grew {:.1} MB (limit {} MB)",
+                memory_growth_mb, max
+            ));
+        }
+    }
+
+    Report {
+        servers: args.servers,
+        clients: args.clients,
+        duration_secs: elapsed.as_secs(),
+        restarts: stats.restarts.load(Ordering::Relaxed),
+        published,
+        injected_loss: stats.lost.load(Ordering::Relaxed),
+        offline: stats.offline.load(Ordering::Relaxed),
+        lagged: stats.lagged.load(Ordering::Relaxed),
+        expected,
+        delivered,
+        drop_percent,
+        latency_p50_ms: latency.value_at_quantile(0.5) as f64 / 1000.0,
+        latency_p99_ms: latency.value_at_quantile(0.99) as f64 / 1000.0,
+        latency_max_ms,
+        memory_start_mb: mb(memory_start),
+        memory_end_mb: mb(memory_end),
+        memory_peak_mb: mb(memory_peak),
+        memory_growth_mb,
+        failures,
+    }
+}
+
+fn unix_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_micros() as u64)
+}
+
+fn mb(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
@@ -0,0 +1,948 @@
+//! Administration subcommands
+//!
+//! `omnitak` with no subcommand, or `omnitak serve`, runs the server. The
+//! other subcommands administer an installation without the GUI: they edit
+//! the configuration file, or talk to a running instance over the REST API
+//! when given `--url`.
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand, ValueEnum};
+use omnitak_api::backup::BackupContents;
+use omnitak_api::types::{
+    ConnectionList, ConnectionType, CreateConnectionRequest, CreateConnectionResponse,
+    DeleteConnectionResponse, RestoreReport, SendCotRequest, SendCotResponse, UserRole,
+};
+use serde::de::DeserializeOwned;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::{ServeArgs, TakServerDef, TlsConfigDef, UserConfig};
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the server (the default when no subcommand is given)
+    Serve(ServeArgs),
+
+    /// Check a configuration file without starting the server
+    ValidateConfig,
+
+    /// List, add or remove TAK server connections in the configuration
+    /// file, or on a running instance with --url
+    Connections {
+        #[command(flatten)]
+        remote: Remote,
+
+        #[command(subcommand)]
+        action: ConnectionsAction,
+    },
+
+    /// Send a CoT message through a running instance
+    SendCot(SendCotArgs),
+
+    /// Inspect certificates
+    #[command(subcommand)]
+    Cert(CertAction),
+
+    /// Manage API users in the configuration file
+    #[command(subcommand)]
+    User(UserAction),
+
+    /// Back up a running instance, restore a backup onto one, or check a
+    /// backup file
+    Backup {
+        // Without --url, the API address in the configuration file
+        #[command(flatten)]
+        remote: Remote,
+
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+
+    /// Terminal dashboard of a running instance, for headless servers
+    Tui(TuiArgs),
+
+    /// Measure routing throughput and latency with synthetic publishers
+    Bench(crate::bench::BenchArgs),
+
+    /// Register the server as a Windows service
+    #[command(subcommand)]
+    Service(ServiceAction),
+}
+
+/// Running instance to administer
+#[derive(Args, Debug)]
+pub struct Remote {
+    /// URL of a running instance, e.g. http://localhost:8443
+    #[arg(long, env = "OMNITAK_URL", global = true)]
+    url: Option<String>,
+
+    /// API key for the running instance
+    #[arg(long, env = "OMNITAK_API_KEY", hide_env_values = true, global = true)]
+    api_key: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConnectionsAction {
+    /// List connections
+    List,
+
+    /// Add a connection
+    Add {
+        /// Connection ID in the configuration file, or name on a running instance
+        id: String,
+
+        /// Server address (host:port)
+        address: String,
+
+        /// Transport
+        #[arg(long, value_enum, default_value = "tcp")]
+        protocol: ConnectionProtocol,
+
+        /// Client certificate (PEM) for TLS
+        #[arg(long, requires = "key")]
+        cert: Option<String>,
+
+        /// Client private key (PEM) for TLS
+        #[arg(long, requires = "cert")]
+        key: Option<String>,
+
+        /// CA certificate (PEM) the server is verified against; the system
+        /// roots when omitted
+        #[arg(long)]
+        ca: Option<String>,
+    },
+
+    /// Remove a connection
+    Remove {
+        /// Connection ID, or name on a running instance
+        id: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConnectionProtocol {
+    Tcp,
+    Tls,
+}
+
+#[derive(Args, Debug)]
+pub struct SendCotArgs {
+    /// File holding the CoT XML; read from stdin when omitted or "-"
+    file: Option<PathBuf>,
+
+    /// Only send to this connection (repeatable); all connections when omitted
+    #[arg(long = "connection")]
+    connections: Vec<Uuid>,
+
+    /// Send directly, bypassing the filter rules (admin only)
+    #[arg(long)]
+    no_filters: bool,
+
+    /// Routing priority (0-10)
+    #[arg(long, default_value = "0", value_parser = clap::value_parser!(u8).range(0..=10))]
+    priority: u8,
+
+    #[command(flatten)]
+    remote: Remote,
+}
+
+#[derive(Args, Debug)]
+pub struct TuiArgs {
+    /// Seconds between status refreshes
+    #[arg(long, default_value = "2", value_parser = clap::value_parser!(u64).range(1..))]
+    refresh: u64,
+
+    // Without --url, the API address in the configuration file
+    #[command(flatten)]
+    remote: Remote,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ServiceAction {
+    /// Register an automatically started service using this configuration
+    /// file (run as Administrator)
+    Install,
+
+    /// Stop and remove the service
+    Uninstall,
+
+    /// Entry point used by the Service Control Manager
+    #[command(hide = true)]
+    Run,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CertAction {
+    /// Show the subject, issuer, validity and fingerprint of a PEM or
+    /// PKCS#12 file
+    Info {
+        /// Certificate file (.pem, .crt, .cer, .p12, .pfx)
+        path: PathBuf,
+
+        /// PKCS#12 password
+        #[arg(long, env = "OMNITAK_CERT_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BackupAction {
+    /// Download a backup of configuration, users, API keys, connections,
+    /// certificate metadata and plugin settings
+    Create {
+        /// Archive to write; omnitak-backup-<time>.zip when omitted
+        output: Option<PathBuf>,
+    },
+
+    /// Restore a backup onto the instance
+    Restore {
+        /// Archive written by `backup create`
+        file: PathBuf,
+    },
+
+    /// Check a backup's format version and checksums without restoring it
+    Verify {
+        /// Archive written by `backup create`
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum UserAction {
+    /// Add a user; the password is stored as an Argon2 hash
+    Add {
+        username: String,
+
+        /// Role: admin, operator or readonly
+        #[arg(long, default_value = "readonly", value_parser = parse_role)]
+        role: UserRole,
+
+        /// Custom role from `api.roles`, replacing the permissions of --role
+        #[arg(long)]
+        custom_role: Option<String>,
+
+        /// Confine the user to a tenant namespace
+        #[arg(long)]
+        tenant: Option<String>,
+
+        /// Password; read from stdin when omitted
+        #[arg(long, env = "OMNITAK_USER_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+    },
+}
+
+fn parse_role(s: &str) -> Result<UserRole, String> {
+    serde_json::from_value(serde_json::Value::String(s.to_lowercase())).map_err(|_| {
+        format!(
+            "unknown role '{}' (expected admin, operator or readonly)",
+            s
+        )
+    })
+}
+
+/// Run an administration subcommand
+pub fn run(command: Command, config_path: &Path) -> Result<()> {
+    match command {
+        Command::Serve(_) | Command::Service(ServiceAction::Run) => {
+            unreachable!("serving is handled by main")
+        }
+        Command::ValidateConfig => validate_config(config_path),
+        Command::Connections { remote, action } => match remote.client()? {
+            Some(client) => block_on(remote_connections(&client, action)),
+            None => local_connections(config_path, action),
+        },
+        Command::SendCot(args) => {
+            let client = args
+                .remote
+                .client()?
+                .context("send-cot needs a running instance (--url or OMNITAK_URL)")?;
+            block_on(send_cot(&client, args))
+        }
+        Command::Cert(CertAction::Info { path, password }) => cert_info(&path, password.as_deref()),
+        Command::User(UserAction::Add {
+            username,
+            role,
+            custom_role,
+            tenant,
+            password,
+        }) => add_user(config_path, username, role, custom_role, tenant, password),
+        Command::Backup {
+            action: BackupAction::Verify { file },
+            ..
+        } => verify_backup(&file).map(|_| ()),
+        Command::Backup { remote, action } => {
+            let client = match remote.client()? {
+                Some(client) => client,
+                None => local_client(config_path, remote.api_key)?,
+            };
+            block_on(remote_backup(&client, action))
+        }
+        Command::Tui(args) => {
+            let client = match args.remote.client()? {
+                Some(client) => client,
+                None => local_client(config_path, args.remote.api_key)?,
+            };
+            crate::tui::run(client, std::time::Duration::from_secs(args.refresh))
+        }
+        Command::Bench(args) => crate::bench::run(args),
+        Command::Service(ServiceAction::Install) => {
+            crate::service::install(config_path)?;
+            println!(
+                "Installed service {} with {}",
+                crate::service::SERVICE_NAME,
+                config_path.display()
+            );
+            Ok(())
+        }
+        Command::Service(ServiceAction::Uninstall) => {
+            crate::service::uninstall()?;
+            println!("Removed service {}", crate::service::SERVICE_NAME);
+            Ok(())
+        }
+    }
+}
+
+fn block_on<F: std::future::Future<Output = Result<()>>>(future: F) -> Result<()> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to start runtime")?
+        .block_on(future)
+}
+
+// ============================================================================
+// Configuration file
+// ============================================================================
+
+fn validate_config(path: &Path) -> Result<()> {
+    let config = crate::load_config(path)?;
+    crate::validate_config(&config).with_context(|| format!("{} is not valid", path.display()))?;
+
+    println!(
+        "{} is valid: {} server(s), {} listener(s) ({} enabled), {} user(s)",
+        path.display(),
+        config.servers.len(),
+        config.listeners.len(),
+        config.listeners.iter().filter(|l| l.enabled).count(),
+        config.api.users.len() + config.api.tenant_users.len()
+    );
+    Ok(())
+}
+
+/// Configuration file as an untyped YAML document, so sections the edit
+/// doesn't touch are written back as they were (comments aside)
+struct ConfigDocument {
+    path: PathBuf,
+    root: serde_yaml::Mapping,
+}
+
+impl ConfigDocument {
+    fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {:?}", path))?;
+        let root = match serde_yaml::from_str(&content).context("Failed to parse config file")? {
+            serde_yaml::Value::Mapping(root) => root,
+            serde_yaml::Value::Null => serde_yaml::Mapping::new(),
+            _ => anyhow::bail!("{} is not a YAML mapping", path.display()),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            root,
+        })
+    }
+
+    /// Sequence at `key` inside `section`, or at the top level
+    fn list(&mut self, section: Option<&str>, key: &str) -> Result<&mut Vec<serde_yaml::Value>> {
+        let mut mapping = &mut self.root;
+        if let Some(section) = section {
+            mapping = mapping
+                .entry(section.into())
+                .or_insert_with(|| serde_yaml::Mapping::new().into())
+                .as_mapping_mut()
+                .with_context(|| format!("'{}' is not a mapping", section))?;
+        }
+        let value = mapping
+            .entry(key.into())
+            .or_insert_with(|| serde_yaml::Value::Sequence(Vec::new()));
+        if value.is_null() {
+            *value = serde_yaml::Value::Sequence(Vec::new());
+        }
+        value
+            .as_sequence_mut()
+            .with_context(|| format!("'{}' is not a list", key))
+    }
+
+    /// Write the document back once it still parses as a configuration
+    fn save(self) -> Result<()> {
+        let value = serde_yaml::Value::Mapping(self.root);
+        serde_yaml::from_value::<crate::Config>(value.clone())
+            .context("Edited configuration would not load")?;
+        fs::write(&self.path, serde_yaml::to_string(&value)?)
+            .with_context(|| format!("Failed to write config file: {:?}", self.path))
+    }
+}
+
+fn local_connections(path: &Path, action: ConnectionsAction) -> Result<()> {
+    match action {
+        ConnectionsAction::List => {
+            let config = crate::load_config(path)?;
+            println!("{:<24} {:<6} ADDRESS", "ID", "PROTO");
+            for server in &config.servers {
+                println!(
+                    "{:<24} {:<6} {}",
+                    server.id, server.protocol, server.address
+                );
+            }
+            Ok(())
+        }
+        ConnectionsAction::Add {
+            id,
+            address,
+            protocol,
+            cert,
+            key,
+            ca,
+        } => {
+            let tls = match protocol {
+                ConnectionProtocol::Tcp => None,
+                ConnectionProtocol::Tls => {
+                    let (Some(cert_path), Some(key_path)) = (cert, key) else {
+                        anyhow::bail!("TLS connections need --cert and --key");
+                    };
+                    Some(TlsConfigDef {
+                        cert_path,
+                        key_path,
+                        ca_path: ca,
+                        verify_server: true,
+                    })
+                }
+            };
+            split_address(&address)?;
+
+            let mut document = ConfigDocument::load(path)?;
+            let servers = document.list(None, "servers")?;
+            if servers
+                .iter()
+                .any(|s| s.get("id").and_then(|v| v.as_str()) == Some(id.as_str()))
+            {
+                anyhow::bail!("Connection '{}' already exists", id);
+            }
+            servers.push(serde_yaml::to_value(TakServerDef {
+                id: id.clone(),
+                address,
+                protocol: match protocol {
+                    ConnectionProtocol::Tcp => "tcp",
+                    ConnectionProtocol::Tls => "tls",
+                }
+                .to_string(),
+                tls,
+                compression: false,
+                format: Default::default(),
+                strict_validation: false,
+            })?);
+            document.save()?;
+
+            println!("Added connection '{}' to {}", id, path.display());
+            Ok(())
+        }
+        ConnectionsAction::Remove { id } => {
+            let mut document = ConfigDocument::load(path)?;
+            let servers = document.list(None, "servers")?;
+            let before = servers.len();
+            servers.retain(|s| s.get("id").and_then(|v| v.as_str()) != Some(id.as_str()));
+            if servers.len() == before {
+                anyhow::bail!("No connection '{}' in {}", id, path.display());
+            }
+            document.save()?;
+
+            println!("Removed connection '{}' from {}", id, path.display());
+            Ok(())
+        }
+    }
+}
+
+fn add_user(
+    path: &Path,
+    username: String,
+    role: UserRole,
+    custom_role: Option<String>,
+    tenant: Option<String>,
+    password: Option<String>,
+) -> Result<()> {
+    let password = match password {
+        Some(password) => password,
+        None => {
+            eprintln!("Password for {}:", username);
+            let mut line = String::new();
+            std::io::stdin()
+                .read_line(&mut line)
+                .context("Failed to read password")?;
+            line.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+    if password.is_empty() {
+        anyhow::bail!("Password must not be empty");
+    }
+
+    let mut document = ConfigDocument::load(path)?;
+    let exists = |list: &Vec<serde_yaml::Value>| {
+        list.iter()
+            .any(|u| u.get("username").and_then(|v| v.as_str()) == Some(username.as_str()))
+    };
+    if exists(document.list(Some("api"), "tenant_users")?)
+        || exists(document.list(Some("api"), "users")?)
+    {
+        anyhow::bail!("User '{}' already exists", username);
+    }
+    if let Some(role) = &custom_role {
+        let defined = document
+            .root
+            .get("api")
+            .and_then(|api| api.get("roles"))
+            .and_then(|roles| roles.as_sequence())
+            .is_some_and(|roles| {
+                roles
+                    .iter()
+                    .any(|r| r.get("name").and_then(|v| v.as_str()) == Some(role.as_str()))
+            });
+        if !defined {
+            anyhow::bail!("Role '{}' is not defined under api.roles", role);
+        }
+    }
+
+    let password_hash =
+        omnitak_api::auth::AuthService::new(Default::default()).hash_password(&password)?;
+    document
+        .list(Some("api"), "users")?
+        .push(serde_yaml::to_value(UserConfig {
+            username: username.clone(),
+            password_hash,
+            role,
+            tenant,
+            custom_role,
+        })?);
+    document.save()?;
+
+    println!("Added user '{}' to {}", username, path.display());
+    Ok(())
+}
+
+/// Split `host:port`, where the host may be a bracketed IPv6 address
+fn split_address(address: &str) -> Result<(String, u16)> {
+    let (host, port) = address
+        .rsplit_once(':')
+        .with_context(|| format!("Address '{}' must be host:port", address))?;
+    let port = port
+        .parse()
+        .with_context(|| format!("Invalid port in address '{}'", address))?;
+    let host = host.trim_matches(['[', ']']);
+    if host.is_empty() {
+        anyhow::bail!("Address '{}' has no host", address);
+    }
+    Ok((host.to_string(), port))
+}
+
+// ============================================================================
+// Running instance
+// ============================================================================
+
+impl Remote {
+    fn client(&self) -> Result<Option<ApiClient>> {
+        let Some(url) = &self.url else {
+            return Ok(None);
+        };
+        Ok(Some(ApiClient {
+            http: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(30))
+                .build()?,
+            base_url: url.trim_end_matches('/').to_string(),
+            api_key: self.api_key.clone(),
+        }))
+    }
+}
+
+/// Client for the instance run from `config_path`, reached on loopback when
+/// its API listens on all interfaces
+fn local_client(config_path: &Path, api_key: Option<String>) -> Result<ApiClient> {
+    let config = crate::load_config(config_path)?;
+    let mut addr: std::net::SocketAddr = config
+        .api
+        .bind_addr
+        .parse()
+        .with_context(|| format!("Invalid API bind address: {}", config.api.bind_addr))?;
+    if addr.ip().is_unspecified() {
+        addr.set_ip(match addr {
+            std::net::SocketAddr::V4(_) => std::net::Ipv4Addr::LOCALHOST.into(),
+            std::net::SocketAddr::V6(_) => std::net::Ipv6Addr::LOCALHOST.into(),
+        });
+    }
+    let scheme = if config.api.enable_tls { "https" } else { "http" };
+
+    let remote = Remote {
+        url: Some(format!("{}://{}", scheme, addr)),
+        api_key,
+    };
+    Ok(remote.client()?.expect("URL is set"))
+}
+
+/// REST client for a running instance
+#[derive(Clone)]
+pub(crate) struct ApiClient {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl ApiClient {
+    pub(crate) fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// WebSocket URL of `path` on the instance
+    pub(crate) fn ws_url(&self, path: &str) -> String {
+        let url = format!("{}{}", self.base_url, path);
+        match url.strip_prefix("http") {
+            Some(rest) => format!("ws{}", rest),
+            None => url,
+        }
+    }
+
+    /// WebSocket handshake for `path` on the instance, carrying the API key
+    pub(crate) fn ws_request(
+        &self,
+        path: &str,
+    ) -> Result<tokio_tungstenite::tungstenite::handshake::client::Request> {
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+        let mut request = self
+            .ws_url(path)
+            .into_client_request()
+            .context("Invalid WebSocket URL")?;
+        if let Some(key) = &self.api_key {
+            request
+                .headers_mut()
+                .insert("X-API-Key", key.parse().context("Invalid API key")?);
+        }
+        Ok(request)
+    }
+
+    pub(crate) async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.send(self.request(reqwest::Method::GET, path)).await
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}{}", self.base_url, path));
+        match &self.api_key {
+            Some(key) => request.header("X-API-Key", key),
+            None => request,
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T> {
+        self.send_raw(request)
+            .await?
+            .json()
+            .await
+            .context("Failed to parse response")
+    }
+
+    /// Response of a request, which must succeed
+    async fn send_raw(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {}", self.base_url))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Request failed ({}): {}", status, body);
+        }
+        Ok(response)
+    }
+
+    pub(crate) async fn connections(&self) -> Result<ConnectionList> {
+        self.get("/api/v1/connections").await
+    }
+}
+
+async fn remote_connections(client: &ApiClient, action: ConnectionsAction) -> Result<()> {
+    match action {
+        ConnectionsAction::List => {
+            let list = client.connections().await?;
+            println!(
+                "{:<36} {:<20} {:<10} {:<12} ADDRESS",
+                "ID", "NAME", "TYPE", "STATUS"
+            );
+            for c in &list.connections {
+                println!(
+                    "{:<36} {:<20} {:<10} {:<12} {}:{}",
+                    c.id,
+                    c.name,
+                    json_name(&c.connection_type),
+                    json_name(&c.status),
+                    c.address,
+                    c.port
+                );
+            }
+            Ok(())
+        }
+        ConnectionsAction::Add {
+            id,
+            address,
+            protocol,
+            cert,
+            key,
+            ca,
+        } => {
+            if ca.is_some() {
+                eprintln!("Note: --ca is ignored for running instances; import a certificate bundle instead");
+            }
+            let (host, port) = split_address(&address)?;
+            let request = CreateConnectionRequest {
+                name: id,
+                connection_type: match protocol {
+                    ConnectionProtocol::Tcp => ConnectionType::TcpClient,
+                    ConnectionProtocol::Tls => ConnectionType::TlsClient,
+                },
+                address: host,
+                port,
+                auto_reconnect: true,
+                tls_cert_path: cert,
+                tls_key_path: key,
+                certificate_id: None,
+                validate_certs: true,
+                reconnect: Default::default(),
+            };
+            let created: CreateConnectionResponse = client
+                .send(
+                    client
+                        .request(reqwest::Method::POST, "/api/v1/connections")
+                        .json(&request),
+                )
+                .await?;
+
+            println!("{} ({})", created.message, created.id);
+            Ok(())
+        }
+        ConnectionsAction::Remove { id } => {
+            let id = match Uuid::parse_str(&id) {
+                Ok(uuid) => uuid,
+                Err(_) => {
+                    let list = client.connections().await?;
+                    let mut matches = list.connections.iter().filter(|c| c.name == id);
+                    match (matches.next(), matches.next()) {
+                        (Some(c), None) => c.id,
+                        (Some(_), Some(_)) => {
+                            anyhow::bail!("Several connections are named '{}'; use the ID", id)
+                        }
+                        (None, _) => anyhow::bail!("No connection named '{}'", id),
+                    }
+                }
+            };
+            let deleted: DeleteConnectionResponse = client
+                .send(client.request(
+                    reqwest::Method::DELETE,
+                    &format!("/api/v1/connections/{}", id),
+                ))
+                .await?;
+
+            println!("{}", deleted.message);
+            Ok(())
+        }
+    }
+}
+
+async fn send_cot(client: &ApiClient, args: SendCotArgs) -> Result<()> {
+    let message = match args.file.as_deref() {
+        Some(path) if path != Path::new("-") => fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?,
+        _ => {
+            let mut message = String::new();
+            std::io::stdin()
+                .read_to_string(&mut message)
+                .context("Failed to read CoT from stdin")?;
+            message
+        }
+    };
+
+    let request = SendCotRequest {
+        message,
+        target_connections: (!args.connections.is_empty()).then_some(args.connections),
+        apply_filters: !args.no_filters,
+        priority: args.priority,
+    };
+    let sent: SendCotResponse = client
+        .send(
+            client
+                .request(reqwest::Method::POST, "/api/v1/cot/send")
+                .json(&request),
+        )
+        .await?;
+
+    println!(
+        "Sent message {} to {} connection(s)",
+        sent.message_id, sent.sent_to_count
+    );
+    for warning in &sent.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    Ok(())
+}
+
+async fn remote_backup(client: &ApiClient, action: BackupAction) -> Result<()> {
+    match action {
+        BackupAction::Create { output } => {
+            let data = client
+                .send_raw(client.request(reqwest::Method::GET, "/api/v1/backup"))
+                .await?
+                .bytes()
+                .await
+                .context("Failed to download backup")?;
+            let (_, contents) = BackupContents::from_archive(&data)
+                .context("The instance returned an invalid backup")?;
+
+            let path = output.unwrap_or_else(|| {
+                PathBuf::from(format!(
+                    "omnitak-backup-{}.zip",
+                    chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+                ))
+            });
+            fs::write(&path, &data)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+
+            println!(
+                "Wrote {} ({} users, {} API keys, {} connections, {} certificates, {} plugins)",
+                path.display(),
+                contents.users.len(),
+                contents.api_keys.len(),
+                contents.connections.len(),
+                contents.certificates.len(),
+                contents.plugins.len()
+            );
+            println!("The backup holds password and key hashes; store it securely");
+            Ok(())
+        }
+        BackupAction::Restore { file } => {
+            let data = verify_backup(&file)?;
+            let report: RestoreReport = client
+                .send(
+                    client
+                        .request(reqwest::Method::POST, "/api/v1/backup/restore")
+                        .header(reqwest::header::CONTENT_TYPE, "application/zip")
+                        .body(data),
+                )
+                .await?;
+
+            println!(
+                "Restored {} users, {} API keys, {} connections and {} plugins' settings",
+                report.users_restored,
+                report.api_keys_restored,
+                report.connections_restored,
+                report.plugins_restored
+            );
+            if report.config_restored {
+                println!("Replaced the configuration file; restart the server to apply it");
+            }
+            for warning in &report.warnings {
+                eprintln!("Warning: {}", warning);
+            }
+            Ok(())
+        }
+        BackupAction::Verify { .. } => unreachable!("verified without an instance"),
+    }
+}
+
+/// Read a backup and check it, returning its bytes
+fn verify_backup(path: &Path) -> Result<Vec<u8>> {
+    let data = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let (manifest, _) = BackupContents::from_archive(&data)
+        .with_context(|| format!("{} failed verification", path.display()))?;
+    println!(
+        "{}: format version {}, created {} by OmniTAK {}, {} files verified",
+        path.display(),
+        manifest.version,
+        manifest.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        manifest.server_version,
+        manifest.files.len()
+    );
+    Ok(data)
+}
+
+/// Name of an enum value as the API writes it
+pub(crate) fn json_name<T: serde::Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => "-".to_string(),
+    }
+}
+
+// ============================================================================
+// Certificates
+// ============================================================================
+
+fn cert_info(path: &Path, password: Option<&str>) -> Result<()> {
+    use omnitak_cert::{CertificateBundle, CertificateInfo};
+
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let certs = match ext.as_str() {
+        "p12" | "pfx" => {
+            let data =
+                fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            // Trust stores hold CA certificates without a private key
+            let ders: Vec<Vec<u8>> = match CertificateBundle::from_pkcs12(&data, password) {
+                Ok(bundle) => bundle
+                    .certs
+                    .iter()
+                    .chain(bundle.ca_certs.iter().flatten())
+                    .map(|c| c.to_vec())
+                    .collect(),
+                Err(_) => omnitak_cert::load_truststore(&data, password)?
+                    .into_iter()
+                    .map(|c| c.to_vec())
+                    .collect(),
+            };
+            ders.iter()
+                .map(|der| CertificateInfo::from_der(der))
+                .collect::<Result<Vec<_>>>()?
+        }
+        _ => CertificateInfo::from_pem_file(path)?,
+    };
+    if certs.is_empty() {
+        anyhow::bail!("No certificates found in {}", path.display());
+    }
+
+    for (i, cert) in certs.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("Subject:     {}", cert.subject_dn);
+        println!("Issuer:      {}", cert.issuer_dn);
+        println!("Serial:      {}", cert.serial_number);
+        println!("Not before:  {}", cert.not_before);
+        println!("Not after:   {}", cert.not_after);
+        let status = if cert.is_expired {
+            format!("expired {} day(s) ago", -cert.days_until_expiry)
+        } else if !cert.is_valid {
+            "not yet valid".to_string()
+        } else if cert.expiring_soon {
+            format!("expires in {} day(s)", cert.days_until_expiry)
+        } else {
+            format!("valid, {} day(s) left", cert.days_until_expiry)
+        };
+        println!("Status:      {}", status);
+        println!("CA:          {}", if cert.is_ca { "yes" } else { "no" });
+        if !cert.key_usage.is_empty() {
+            println!("Key usage:   {}", cert.key_usage.join(", "));
+        }
+        println!("SHA-256:     {}", cert.fingerprint);
+    }
+    Ok(())
+}
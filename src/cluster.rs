@@ -0,0 +1,699 @@
+//! High-Availability Clustering
+//!
+//! Runs two or more OmniTAK nodes with the same configuration so outbound
+//! TAK server connections survive the loss of a node:
+//!
+//! - **active/standby**: the leader (the longest-running live node) holds
+//!   every outbound connection; the other nodes take over when its lease
+//!   expires.
+//! - **active/active**: outbound connections are partitioned across the
+//!   live nodes by rendezvous hashing and move when nodes join or leave.
+//!
+//! ```text
+//!   node-a ──┐  heartbeat + held connections   ┌── node-b
+//!            ├──────▶ state_dir/node-a.json    │
+//!            │        state_dir/node-b.json ◀──┤
+//!            └─ reads all records, takes over connections of expired nodes
+//! ```
+//!
+//! Nodes share state through a directory on shared storage (NFS, SMB, a
+//! replicated volume). Each node rewrites its record every `heartbeat_secs`;
+//! a record older than `lease_secs` belongs to a dead node. A node that
+//! cannot write its record releases its connections, so a node cut off
+//! from the store does not keep connections another node is taking over.
+//!
+//! Records only decide which node should hold a connection. To hold it, a
+//! node claims the server's lease, `leases/<server>/<epoch>.json`, by
+//! hard linking the next epoch's file into place; linking fails if the file
+//! exists, so of nodes whose views of the cluster diverge only one wins
+//! each epoch. The shared storage must therefore support hard links.
+//! The epoch is a fencing number: a node checks that its epoch is still
+//! the latest before opening the connection and whenever it renews the
+//! lease, and releases the connection once a later epoch exists. A lease
+//! the holder has not renewed within `lease_secs` may be claimed by the
+//! next node.
+//!
+//! Leases compare timestamps written by different nodes, so node clocks
+//! must agree to well within `lease_secs` (see the `time_sync` check).
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use omnitak_pool::ConnectionPool;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Wait before reopening a held connection that closed
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Cluster configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterConfig {
+    /// Unique name of this node
+    pub node_id: String,
+    #[serde(default)]
+    pub mode: ClusterMode,
+    /// Directory on storage shared by all nodes
+    pub state_dir: PathBuf,
+    /// How often this node renews its record (seconds)
+    #[serde(default = "default_heartbeat_secs")]
+    pub heartbeat_secs: u64,
+    /// Age after which a node's record is considered dead (seconds)
+    #[serde(default = "default_lease_secs")]
+    pub lease_secs: u64,
+}
+
+fn default_heartbeat_secs() -> u64 {
+    2
+}
+
+fn default_lease_secs() -> u64 {
+    10
+}
+
+/// How outbound connections are spread over the nodes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClusterMode {
+    /// The leader holds every connection
+    #[default]
+    ActiveStandby,
+    /// Connections are partitioned across live nodes
+    ActiveActive,
+}
+
+/// A node's record in the state directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct NodeRecord {
+    node_id: String,
+    /// When the node joined; the earliest live node leads
+    joined_at: DateTime<Utc>,
+    heartbeat_at: DateTime<Utc>,
+    /// Server IDs of the connections the node holds
+    connections: Vec<String>,
+}
+
+/// A node's claim on a server connection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Lease {
+    node_id: String,
+    /// Fencing number; each claim on a server takes the next one
+    epoch: u64,
+    renewed_at: DateTime<Utc>,
+    /// Set when the holder gives the connection up
+    #[serde(default)]
+    released: bool,
+}
+
+impl Lease {
+    /// Whether the lease keeps other nodes from claiming the server
+    fn is_held(&self, now: DateTime<Utc>, lease: Duration) -> bool {
+        let lease = ChronoDuration::from_std(lease).unwrap_or(ChronoDuration::MAX);
+        !self.released && now - self.renewed_at <= lease
+    }
+}
+
+/// Node records kept as `<node_id>.json` files in a shared directory, and
+/// connection leases in its `leases` subdirectory
+///
+/// Shared storage can block for seconds when it is slow or unreachable, so
+/// every access runs on the blocking thread pool.
+#[derive(Clone)]
+struct DirectoryStore {
+    dir: Arc<PathBuf>,
+}
+
+impl DirectoryStore {
+    fn new(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create cluster state directory {:?}", dir))?;
+        Ok(Self {
+            dir: Arc::new(dir.to_path_buf()),
+        })
+    }
+
+    /// Replace this node's record
+    async fn write(&self, record: &NodeRecord) -> Result<()> {
+        let (store, record) = (self.clone(), record.clone());
+        tokio::task::spawn_blocking(move || store.write_blocking(&record)).await?
+    }
+
+    async fn read_all(&self) -> Result<Vec<NodeRecord>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || store.read_all_blocking()).await?
+    }
+
+    /// Claim the lease on a server unless another node holds it, returning
+    /// the new lease
+    async fn claim(
+        &self,
+        server_id: &str,
+        node_id: &str,
+        lease: Duration,
+    ) -> Result<Option<Lease>> {
+        let (store, server_id, node_id) =
+            (self.clone(), server_id.to_string(), node_id.to_string());
+        tokio::task::spawn_blocking(move || store.claim_blocking(&server_id, &node_id, lease))
+            .await?
+    }
+
+    /// Renew a lease this node holds; false once a later epoch exists
+    async fn renew(&self, server_id: &str, lease: &Lease) -> Result<bool> {
+        let (store, server_id, lease) = (self.clone(), server_id.to_string(), lease.clone());
+        tokio::task::spawn_blocking(move || store.renew_blocking(&server_id, &lease)).await?
+    }
+
+    /// Whether `lease` is still the latest claim on the server
+    async fn is_current(&self, server_id: &str, lease: &Lease) -> Result<bool> {
+        let (store, server_id, epoch) = (self.clone(), server_id.to_string(), lease.epoch);
+        tokio::task::spawn_blocking(move || -> Result<bool> {
+            Ok(store.latest_epoch(&store.lease_dir(&server_id))? == Some(epoch))
+        })
+        .await?
+    }
+
+    /// Write the record to a new temporary file and rename it over the
+    /// previous one, so readers never see a partial record
+    fn write_blocking(&self, record: &NodeRecord) -> Result<()> {
+        let path = self.dir.join(format!("{}.json", record.node_id));
+        write_file(&path, &serde_json::to_vec(record)?, false)
+            .with_context(|| format!("Failed to replace {:?}", path))
+    }
+
+    fn read_all_blocking(&self) -> Result<Vec<NodeRecord>> {
+        let mut records = Vec::new();
+        for entry in std::fs::read_dir(self.dir.as_path())
+            .with_context(|| format!("Failed to read cluster state directory {:?}", self.dir))?
+        {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            match std::fs::read(&path)
+                .ok()
+                .and_then(|data| serde_json::from_slice(&data).ok())
+            {
+                Some(record) => records.push(record),
+                None => debug!(path = ?path, "Skipping unreadable cluster record"),
+            }
+        }
+        Ok(records)
+    }
+
+    /// Lease directory of a server; the ID is hex encoded as server IDs may
+    /// contain characters file names cannot
+    fn lease_dir(&self, server_id: &str) -> PathBuf {
+        let name: String = server_id.bytes().map(|b| format!("{:02x}", b)).collect();
+        self.dir.join("leases").join(name)
+    }
+
+    /// Latest epoch claimed in a lease directory
+    fn latest_epoch(&self, dir: &Path) -> Result<Option<u64>> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", dir)),
+        };
+        let mut latest = None;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let epoch = path
+                .file_stem()
+                .and_then(|s| s.to_str()?.parse::<u64>().ok());
+            latest = latest.max(epoch);
+        }
+        Ok(latest)
+    }
+
+    fn read_lease(&self, dir: &Path, epoch: u64) -> Result<Lease> {
+        let path = dir.join(format!("{}.json", epoch));
+        let data = std::fs::read(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        serde_json::from_slice(&data).with_context(|| format!("Invalid lease {:?}", path))
+    }
+
+    /// Take the epoch after the latest, if its lease has lapsed or was
+    /// released. Creating the epoch's file fails when another node created
+    /// it first, so each epoch has a single holder.
+    fn claim_blocking(
+        &self,
+        server_id: &str,
+        node_id: &str,
+        lease: Duration,
+    ) -> Result<Option<Lease>> {
+        let dir = self.lease_dir(server_id);
+        std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {:?}", dir))?;
+        let now = Utc::now();
+        let latest = self.latest_epoch(&dir)?;
+        if let Some(epoch) = latest {
+            let current = self.read_lease(&dir, epoch)?;
+            if current.node_id != node_id && current.is_held(now, lease) {
+                return Ok(None);
+            }
+        }
+
+        let claimed = Lease {
+            node_id: node_id.to_string(),
+            epoch: latest.map_or(1, |epoch| epoch + 1),
+            renewed_at: now,
+            released: false,
+        };
+        let path = dir.join(format!("{}.json", claimed.epoch));
+        match write_file(&path, &serde_json::to_vec(&claimed)?, true) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to create {:?}", path)),
+        }
+
+        // The previous epoch is superseded
+        if let Some(epoch) = latest {
+            let _ = std::fs::remove_file(dir.join(format!("{}.json", epoch)));
+        }
+        Ok(Some(claimed))
+    }
+
+    fn renew_blocking(&self, server_id: &str, lease: &Lease) -> Result<bool> {
+        let dir = self.lease_dir(server_id);
+        if self.latest_epoch(&dir)? != Some(lease.epoch) {
+            return Ok(false);
+        }
+        let path = dir.join(format!("{}.json", lease.epoch));
+        write_file(&path, &serde_json::to_vec(lease)?, false)
+            .with_context(|| format!("Failed to renew {:?}", path))?;
+        Ok(true)
+    }
+}
+
+/// Write `data` to a new temporary file next to `path`, then move it into
+/// place, so readers never see a partial file. With `exclusive` the file is
+/// hard linked into place, which fails if `path` exists; otherwise it is
+/// renamed over any previous file. The temporary file is created
+/// exclusively under a unique name, so a leftover from an earlier attempt,
+/// or another process writing the same file, is never written through.
+fn write_file(path: &Path, data: &[u8], exclusive: bool) -> std::io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", name, uuid::Uuid::new_v4()));
+    let written = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmp)
+        .and_then(|mut file| {
+            file.write_all(data)?;
+            file.sync_all()
+        })
+        .and_then(|()| {
+            if exclusive {
+                std::fs::hard_link(&tmp, path)
+            } else {
+                std::fs::rename(&tmp, path)
+            }
+        });
+    if written.is_err() || exclusive {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    written
+}
+
+/// Records renewed within the lease
+fn live_members(records: Vec<NodeRecord>, now: DateTime<Utc>, lease: Duration) -> Vec<NodeRecord> {
+    let lease = ChronoDuration::from_std(lease).unwrap_or(ChronoDuration::MAX);
+    records
+        .into_iter()
+        .filter(|r| now - r.heartbeat_at <= lease)
+        .collect()
+}
+
+/// The live node that holds every connection in active/standby mode
+fn leader(live: &[NodeRecord]) -> Option<&str> {
+    live.iter()
+        .min_by(|a, b| (a.joined_at, &a.node_id).cmp(&(b.joined_at, &b.node_id)))
+        .map(|r| r.node_id.as_str())
+}
+
+/// Server IDs `node_id` should hold
+fn assigned<'a>(
+    mode: ClusterMode,
+    node_id: &str,
+    live: &[NodeRecord],
+    server_ids: &'a [String],
+) -> HashSet<&'a str> {
+    match mode {
+        ClusterMode::ActiveStandby => {
+            if leader(live) == Some(node_id) {
+                server_ids.iter().map(String::as_str).collect()
+            } else {
+                HashSet::new()
+            }
+        }
+        ClusterMode::ActiveActive => server_ids
+            .iter()
+            .filter(|server_id| {
+                live.iter()
+                    .max_by_key(|r| (rendezvous_score(&r.node_id, server_id), &r.node_id))
+                    .is_some_and(|r| r.node_id == node_id)
+            })
+            .map(String::as_str)
+            .collect(),
+    }
+}
+
+/// FNV-1a of node and server ID; stable across builds so nodes running
+/// different versions agree on the partition
+fn rendezvous_score(node_id: &str, server_id: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in node_id.bytes().chain([0]).chain(server_id.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// A connection this node holds, with its lease
+struct Held {
+    task: JoinHandle<()>,
+    lease: Lease,
+}
+
+/// Take part in the cluster, holding the connections assigned to this node
+///
+/// `start` opens the connection to a server; its task must end when the
+/// connection closes. Connections are released by removing `tak-server-<id>`
+/// from the pool and aborting the task.
+pub fn spawn<F>(
+    config: ClusterConfig,
+    server_ids: Vec<String>,
+    pool: Arc<ConnectionPool>,
+    start: F,
+) -> Result<JoinHandle<()>>
+where
+    F: Fn(&str) -> Option<JoinHandle<()>> + Send + 'static,
+{
+    let store = DirectoryStore::new(&config.state_dir)?;
+
+    Ok(tokio::spawn(async move {
+        let joined_at = Utc::now();
+        let lease = Duration::from_secs(config.lease_secs.max(1));
+        let mut interval = tokio::time::interval(Duration::from_secs(config.heartbeat_secs.max(1)));
+        let mut held: HashMap<String, Held> = HashMap::new();
+        let mut retry_after: HashMap<String, Instant> = HashMap::new();
+        let mut last_assigned: Option<usize> = None;
+
+        info!(
+            node_id = %config.node_id,
+            mode = ?config.mode,
+            state_dir = ?config.state_dir,
+            "Joined cluster"
+        );
+
+        loop {
+            interval.tick().await;
+
+            // Connections that closed on their own are retried later
+            let closed: Vec<String> = held
+                .iter()
+                .filter(|(_, h)| h.task.is_finished())
+                .map(|(id, _)| id.clone())
+                .collect();
+            for id in closed {
+                if let Some(h) = held.remove(&id) {
+                    give_up(&store, &id, h.lease).await;
+                }
+                retry_after.insert(id, Instant::now() + RETRY_DELAY);
+            }
+
+            let mut connections: Vec<String> = held.keys().cloned().collect();
+            connections.sort();
+            let record = NodeRecord {
+                node_id: config.node_id.clone(),
+                joined_at,
+                heartbeat_at: Utc::now(),
+                connections,
+            };
+            let members = match async {
+                store.write(&record).await?;
+                store.read_all().await
+            }
+            .await
+            {
+                Ok(members) => members,
+                Err(e) => {
+                    if !held.is_empty() {
+                        warn!(error = %e, "Lost cluster state, releasing {} connection(s)", held.len());
+                    } else {
+                        warn!(error = %e, "Cluster state unavailable");
+                    }
+                    for (id, h) in held.drain() {
+                        release(&pool, &id, h.task).await;
+                    }
+                    last_assigned = None;
+                    continue;
+                }
+            };
+
+            let live = live_members(members, Utc::now(), lease);
+            let assigned = assigned(config.mode, &config.node_id, &live, &server_ids);
+            if last_assigned != Some(assigned.len()) {
+                info!(
+                    node_id = %config.node_id,
+                    live_nodes = live.len(),
+                    leader = ?leader(&live),
+                    assigned = assigned.len(),
+                    "Cluster membership changed"
+                );
+                last_assigned = Some(assigned.len());
+            }
+
+            let released: Vec<String> = held
+                .keys()
+                .filter(|id| !assigned.contains(id.as_str()))
+                .cloned()
+                .collect();
+            for id in released {
+                if let Some(h) = held.remove(&id) {
+                    info!(server_id = %id, "Releasing connection to another node");
+                    release(&pool, &id, h.task).await;
+                    give_up(&store, &id, h.lease).await;
+                }
+            }
+
+            // Keep only the connections whose lease is still ours
+            let renewing: Vec<String> = held.keys().cloned().collect();
+            for id in renewing {
+                let Some(h) = held.get_mut(&id) else { continue };
+                h.lease.renewed_at = Utc::now();
+                match store.renew(&id, &h.lease).await {
+                    Ok(true) => continue,
+                    Ok(false) => {
+                        warn!(server_id = %id, "Connection claimed by another node, releasing")
+                    }
+                    Err(e) => {
+                        warn!(server_id = %id, error = %e, "Failed to renew lease, releasing")
+                    }
+                }
+                if let Some(h) = held.remove(&id) {
+                    release(&pool, &id, h.task).await;
+                }
+            }
+
+            for id in assigned {
+                if held.contains_key(id)
+                    || retry_after.get(id).is_some_and(|at| Instant::now() < *at)
+                {
+                    continue;
+                }
+                retry_after.remove(id);
+
+                // Another node may still hold the connection; wait for its
+                // lease to lapse or be released
+                let claimed = match store.claim(id, &config.node_id, lease).await {
+                    Ok(Some(claimed)) => claimed,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        warn!(server_id = %id, error = %e, "Failed to claim lease");
+                        continue;
+                    }
+                };
+                // Fencing: only open the connection while no later claim exists
+                if !store.is_current(id, &claimed).await.unwrap_or(false) {
+                    continue;
+                }
+                info!(server_id = %id, epoch = claimed.epoch, "Taking over connection");
+                match start(id) {
+                    Some(task) => {
+                        held.insert(
+                            id.to_string(),
+                            Held {
+                                task,
+                                lease: claimed,
+                            },
+                        );
+                    }
+                    None => give_up(&store, id, claimed).await,
+                }
+            }
+        }
+    }))
+}
+
+async fn release(pool: &ConnectionPool, server_id: &str, task: JoinHandle<()>) {
+    let _ = pool
+        .remove_connection(&format!("tak-server-{}", server_id))
+        .await;
+    task.abort();
+}
+
+/// Mark a lease released so another node can claim the server at once
+async fn give_up(store: &DirectoryStore, server_id: &str, mut lease: Lease) {
+    lease.released = true;
+    if let Err(e) = store.renew(server_id, &lease).await {
+        debug!(server_id = %server_id, error = %e, "Failed to release lease");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(node_id: &str, joined_secs_ago: i64, heartbeat_secs_ago: i64) -> NodeRecord {
+        let now = Utc::now();
+        NodeRecord {
+            node_id: node_id.to_string(),
+            joined_at: now - ChronoDuration::seconds(joined_secs_ago),
+            heartbeat_at: now - ChronoDuration::seconds(heartbeat_secs_ago),
+            connections: Vec::new(),
+        }
+    }
+
+    fn servers(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("server-{}", i)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_directory_store() {
+        let dir = std::env::temp_dir().join(format!("omnitak-cluster-{}", std::process::id()));
+        let store = DirectoryStore::new(&dir).unwrap();
+
+        // A temporary file left behind by an interrupted write is not read
+        std::fs::write(dir.join(".a.leftover.tmp"), "{").unwrap();
+        let mut a = record("a", 60, 1);
+        store.write(&a).await.unwrap();
+        a.connections = vec!["server-0".to_string()];
+        store.write(&a).await.unwrap();
+        store.write(&record("b", 30, 1)).await.unwrap();
+
+        let mut records = store.read_all().await.unwrap();
+        records.sort_by(|x, y| x.node_id.cmp(&y.node_id));
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].connections, vec!["server-0".to_string()]);
+
+        // Only the leftover remains besides the records
+        let files = std::fs::read_dir(&dir).unwrap().count();
+        assert_eq!(files, 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_lease_fencing() {
+        let dir = std::env::temp_dir().join(format!("omnitak-leases-{}", uuid::Uuid::new_v4()));
+        let store = DirectoryStore::new(&dir).unwrap();
+        let lease = Duration::from_secs(10);
+
+        // Nodes whose views diverge both try to take server-0: one holds it
+        let mut a = store.claim("server-0", "a", lease).await.unwrap().unwrap();
+        assert_eq!(a.epoch, 1);
+        assert!(store.claim("server-0", "b", lease).await.unwrap().is_none());
+
+        // Racing claims on the same epoch have a single winner
+        let claims: Vec<_> = (0..8)
+            .map(|i| {
+                let store = store.clone();
+                std::thread::spawn(move || {
+                    store
+                        .claim_blocking("server-1", &format!("node-{}", i), lease)
+                        .unwrap()
+                })
+            })
+            .collect();
+        let winners = claims
+            .into_iter()
+            .filter_map(|claim| claim.join().unwrap())
+            .count();
+        assert_eq!(winners, 1);
+
+        // a stops renewing; once b claims the next epoch, a is fenced off
+        a.renewed_at = Utc::now() - ChronoDuration::seconds(30);
+        assert!(store.renew("server-0", &a).await.unwrap());
+        let b = store.claim("server-0", "b", lease).await.unwrap().unwrap();
+        assert_eq!(b.epoch, 2);
+        assert!(!store.is_current("server-0", &a).await.unwrap());
+        assert!(!store.renew("server-0", &a).await.unwrap());
+        assert!(store.is_current("server-0", &b).await.unwrap());
+
+        // Released leases can be claimed at once
+        assert!(store.claim("server-0", "a", lease).await.unwrap().is_none());
+        give_up(&store, "server-0", b).await;
+        let a = store.claim("server-0", "a", lease).await.unwrap().unwrap();
+        assert_eq!(a.epoch, 3);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_active_standby_takeover() {
+        let servers = servers(3);
+        let lease = Duration::from_secs(10);
+
+        // The longest-running node leads, even if another has a lower ID
+        let records = vec![record("a", 60, 1), record("b", 600, 1)];
+        let live = live_members(records, Utc::now(), lease);
+        assert_eq!(leader(&live), Some("b"));
+        assert_eq!(
+            assigned(ClusterMode::ActiveStandby, "b", &live, &servers).len(),
+            3
+        );
+        assert!(assigned(ClusterMode::ActiveStandby, "a", &live, &servers).is_empty());
+
+        // b stops renewing its lease and a takes over
+        let records = vec![record("a", 60, 1), record("b", 600, 30)];
+        let live = live_members(records, Utc::now(), lease);
+        assert_eq!(leader(&live), Some("a"));
+        assert_eq!(
+            assigned(ClusterMode::ActiveStandby, "a", &live, &servers).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_active_active_partition() {
+        let servers = servers(20);
+        let live = vec![record("a", 60, 1), record("b", 60, 1), record("c", 60, 1)];
+
+        let parts: Vec<HashSet<&str>> = ["a", "b", "c"]
+            .iter()
+            .map(|node| assigned(ClusterMode::ActiveActive, node, &live, &servers))
+            .collect();
+        // Every server is held by exactly one node
+        assert_eq!(parts.iter().map(HashSet::len).sum::<usize>(), 20);
+        for server in &servers {
+            assert_eq!(
+                parts.iter().filter(|p| p.contains(server.as_str())).count(),
+                1
+            );
+        }
+
+        // When c leaves only its servers move
+        let remaining = &live[..2];
+        let a = assigned(ClusterMode::ActiveActive, "a", remaining, &servers);
+        let b = assigned(ClusterMode::ActiveActive, "b", remaining, &servers);
+        assert!(parts[0].is_subset(&a));
+        assert!(parts[1].is_subset(&b));
+        assert_eq!(a.len() + b.len(), 20);
+    }
+}
@@ -0,0 +1,377 @@
+//! System Event Bus
+//!
+//! Operational events are published on a broadcast channel that webhooks
+//! and alerting subscribe to independently:
+//!
+//! - **connection_up / connection_down**: TAK server connections established
+//!   or lost (including failed connection attempts).
+//! - **circuit_breaker**: a connection's health circuit opened or recovered.
+//! - **certificate_expiry**: a configured TLS certificate is expired or
+//!   within the warning window.
+//! - **filter_match**: messages matched a connection's content filters,
+//!   summarised per connection every interval.
+//! - **emergency**: an emergency beacon was raised, acknowledged, cleared or
+//!   cancelled.
+//! - **anomaly**: a track report implied impossible movement or altitude,
+//!   and whether it was quarantined.
+//!
+//! Publishing never blocks; a subscriber that falls too far behind skips
+//! the oldest events.
+
+use omnitak_cert::CertificateInfo;
+use omnitak_pool::{
+    Anomaly, AnomalyDetector, CircuitEvent, CircuitState, Emergency, EmergencyState,
+    EmergencyTracker, HealthMonitor, MessageDistributor,
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+/// Events buffered per subscriber before the oldest are skipped
+const EVENT_BUS_CAPACITY: usize = 1024;
+
+/// Settings for the built-in event producers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventsConfig {
+    /// Warn about certificates expiring within this many days
+    #[serde(default = "default_cert_warning_days")]
+    pub cert_warning_days: i64,
+    /// How often to check certificate expiry
+    #[serde(default = "default_cert_check_interval_hours")]
+    pub cert_check_interval_hours: u64,
+    /// Window over which filter matches are summarised
+    #[serde(default = "default_filter_match_interval_secs")]
+    pub filter_match_interval_secs: u64,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self {
+            cert_warning_days: default_cert_warning_days(),
+            cert_check_interval_hours: default_cert_check_interval_hours(),
+            filter_match_interval_secs: default_filter_match_interval_secs(),
+        }
+    }
+}
+
+fn default_cert_warning_days() -> i64 {
+    30
+}
+
+fn default_cert_check_interval_hours() -> u64 {
+    24
+}
+
+fn default_filter_match_interval_secs() -> u64 {
+    60
+}
+
+/// Event categories consumers can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    ConnectionUp,
+    ConnectionDown,
+    CircuitBreaker,
+    CertificateExpiry,
+    FilterMatch,
+    Emergency,
+    Anomaly,
+}
+
+/// An operational event
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SystemEvent {
+    ConnectionUp {
+        connection_id: String,
+        address: String,
+    },
+    ConnectionDown {
+        connection_id: String,
+        address: String,
+        reason: Option<String>,
+    },
+    CircuitBreaker {
+        connection_id: String,
+        previous: &'static str,
+        state: &'static str,
+    },
+    CertificateExpiry {
+        path: String,
+        subject: String,
+        not_after: String,
+        days_until_expiry: i64,
+        expired: bool,
+    },
+    FilterMatch {
+        connection_id: String,
+        matches: u64,
+        window_secs: u64,
+    },
+    Emergency {
+        uid: String,
+        kind: &'static str,
+        callsign: Option<String>,
+        connection_id: String,
+        lat: f64,
+        lon: f64,
+        state: &'static str,
+        operator: Option<String>,
+    },
+    Anomaly {
+        uid: String,
+        kind: &'static str,
+        callsign: Option<String>,
+        connection_id: String,
+        lat: f64,
+        lon: f64,
+        value: f64,
+        limit: f64,
+        quarantined: bool,
+    },
+}
+
+impl SystemEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Self::ConnectionUp { .. } => EventKind::ConnectionUp,
+            Self::ConnectionDown { .. } => EventKind::ConnectionDown,
+            Self::CircuitBreaker { .. } => EventKind::CircuitBreaker,
+            Self::CertificateExpiry { .. } => EventKind::CertificateExpiry,
+            Self::FilterMatch { .. } => EventKind::FilterMatch,
+            Self::Emergency { .. } => EventKind::Emergency,
+            Self::Anomaly { .. } => EventKind::Anomaly,
+        }
+    }
+}
+
+/// Broadcast channel for system events; cheap to clone
+#[derive(Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<SystemEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            tx: broadcast::channel(EVENT_BUS_CAPACITY).0,
+        }
+    }
+
+    /// Publish an event to all current subscribers
+    pub fn publish(&self, event: SystemEvent) {
+        // No subscribers is fine
+        let _ = self.tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SystemEvent> {
+        self.tx.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Start the circuit breaker, certificate, filter, emergency and anomaly
+/// watchers. `cert_paths` are the PEM certificates to check for expiry.
+pub fn spawn_watchers(
+    bus: &EventBus,
+    config: &EventsConfig,
+    cert_paths: Vec<PathBuf>,
+    health_monitor: &HealthMonitor,
+    distributor: Arc<MessageDistributor>,
+    emergencies: &EmergencyTracker,
+    anomalies: Option<&AnomalyDetector>,
+) {
+    tokio::spawn(watch_circuits(bus.clone(), health_monitor.subscribe()));
+    tokio::spawn(watch_emergencies(bus.clone(), emergencies.subscribe()));
+    if let Some(anomalies) = anomalies {
+        tokio::spawn(watch_anomalies(bus.clone(), anomalies.subscribe()));
+    }
+    tokio::spawn(watch_certificates(
+        bus.clone(),
+        cert_paths,
+        config.cert_warning_days,
+        Duration::from_secs(config.cert_check_interval_hours.max(1) * 3600),
+    ));
+    tokio::spawn(watch_filter_matches(
+        bus.clone(),
+        distributor,
+        config.filter_match_interval_secs.max(1),
+    ));
+}
+
+fn circuit_state_name(state: CircuitState) -> &'static str {
+    match state {
+        CircuitState::Closed => "closed",
+        CircuitState::Open => "open",
+        CircuitState::HalfOpen => "half_open",
+    }
+}
+
+async fn watch_circuits(bus: EventBus, mut events: broadcast::Receiver<CircuitEvent>) {
+    loop {
+        match events.recv().await {
+            Ok(event) => {
+                // Half-open is an internal probe state; report trips and recoveries
+                if event.state == CircuitState::HalfOpen {
+                    continue;
+                }
+                bus.publish(SystemEvent::CircuitBreaker {
+                    connection_id: event.connection_id,
+                    previous: circuit_state_name(event.previous),
+                    state: circuit_state_name(event.state),
+                });
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Missed {} circuit breaker events", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+fn emergency_event(emergency: Emergency) -> SystemEvent {
+    let (state, operator) = match emergency.state {
+        EmergencyState::Active => ("active", None),
+        EmergencyState::Acknowledged => ("acknowledged", emergency.acknowledged_by),
+        EmergencyState::Cleared => ("cleared", emergency.cleared_by),
+        EmergencyState::Cancelled => ("cancelled", None),
+    };
+    SystemEvent::Emergency {
+        uid: emergency.uid,
+        kind: emergency.kind.label(),
+        callsign: emergency.callsign,
+        connection_id: emergency.source,
+        lat: emergency.lat,
+        lon: emergency.lon,
+        state,
+        operator,
+    }
+}
+
+async fn watch_emergencies(bus: EventBus, mut events: broadcast::Receiver<Emergency>) {
+    loop {
+        match events.recv().await {
+            Ok(emergency) => bus.publish(emergency_event(emergency)),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Missed {} emergency events", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+fn anomaly_event(anomaly: Anomaly) -> SystemEvent {
+    SystemEvent::Anomaly {
+        uid: anomaly.uid,
+        kind: anomaly.kind.label(),
+        callsign: anomaly.callsign,
+        connection_id: anomaly.source,
+        lat: anomaly.lat,
+        lon: anomaly.lon,
+        value: anomaly.value,
+        limit: anomaly.limit,
+        quarantined: anomaly.quarantined,
+    }
+}
+
+async fn watch_anomalies(bus: EventBus, mut events: broadcast::Receiver<Anomaly>) {
+    loop {
+        match events.recv().await {
+            Ok(anomaly) => bus.publish(anomaly_event(anomaly)),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Missed {} anomaly events", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Certificates in `certs` that are expired or expire within `warning_days`
+fn expiring(certs: &[CertificateInfo], warning_days: i64) -> Vec<&CertificateInfo> {
+    certs
+        .iter()
+        .filter(|cert| cert.is_expired || cert.days_until_expiry <= warning_days)
+        .collect()
+}
+
+async fn watch_certificates(
+    bus: EventBus,
+    paths: Vec<PathBuf>,
+    warning_days: i64,
+    interval: Duration,
+) {
+    if paths.is_empty() {
+        return;
+    }
+
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        for path in &paths {
+            let certs = match CertificateInfo::from_pem_file(path) {
+                Ok(certs) => certs,
+                Err(e) => {
+                    debug!("Skipping expiry check for {}: {:#}", path.display(), e);
+                    continue;
+                }
+            };
+            for cert in expiring(&certs, warning_days) {
+                bus.publish(SystemEvent::CertificateExpiry {
+                    path: path.display().to_string(),
+                    subject: cert.subject_cn.clone(),
+                    not_after: cert.not_after.clone(),
+                    days_until_expiry: cert.days_until_expiry,
+                    expired: cert.is_expired,
+                });
+            }
+        }
+    }
+}
+
+async fn watch_filter_matches(
+    bus: EventBus,
+    distributor: Arc<MessageDistributor>,
+    window_secs: u64,
+) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(window_secs));
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        for (connection_id, matches) in distributor.take_filter_matches() {
+            bus.publish(SystemEvent::FilterMatch {
+                connection_id,
+                matches,
+                window_secs,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bus_fans_out() {
+        let bus = EventBus::new();
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+
+        bus.publish(SystemEvent::ConnectionUp {
+            connection_id: "tak-server-main".to_string(),
+            address: "tak.example.com:8089".to_string(),
+        });
+
+        assert_eq!(first.recv().await.unwrap().kind(), EventKind::ConnectionUp);
+        assert_eq!(second.recv().await.unwrap().kind(), EventKind::ConnectionUp);
+    }
+}
@@ -0,0 +1,574 @@
+//! Federation Between OmniTAK Instances
+//!
+//! Links two or more OmniTAK aggregators at different sites so they
+//! exchange selected traffic, similar to TAK Server federation:
+//!
+//! ```text
+//!   site A                                     site B
+//!   aggregator ─▶ distributor ─▶ federate:B ══ mutual TLS ══▶ reader ─▶ aggregator
+//!                                  (policy)    0xBF frames      (policy, loop check)
+//! ```
+//!
+//! - **Mutual TLS**: both ends present certificates signed by the
+//!   federation CA. Inbound federates are identified by the CN of their
+//!   certificate, which must equal the federate `name`; outbound links
+//!   verify the server certificate against the CA and the address host.
+//! - **Framing**: every event travels in a protobuf `FederatedMessage`
+//!   (see `omnitak_cot::proto::encode_federated`) with the original CoT
+//!   bytes as payload, so nothing is lost in XML/protobuf conversion.
+//! - **Loop prevention**: the envelope carries the origin instance, the
+//!   path of instances it passed through and a hop count. Events whose
+//!   path already contains the receiving instance, or that crossed more
+//!   than `max_hops` links, are dropped, and events are never sent to a
+//!   federate already on their path.
+//! - **Policy**: per federate, which CoT types may be sent and received,
+//!   and whether events learned from other federates are passed on.
+//!
+//! Each live link is a pool connection named `federate:<name>`, so it gets
+//! distributor output like any TAK server connection and shows up in the
+//! connection list.
+
+use anyhow::{anyhow, Context, Result};
+use bytes::BytesMut;
+use omnitak_cot::proto::{decode_federated, encode_federated, pb::FederatedMessage};
+use omnitak_pool::{ConnectionPool, InboundMessage, MessageAggregator, PoolMessage, TraceId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::rustls::{self, pki_types::ServerName};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+use tracing::{debug, error, info, warn};
+
+use crate::events::{EventBus, SystemEvent};
+
+/// Prefix of the pool connection ID of a federate link
+pub const FEDERATE_PREFIX: &str = "federate:";
+
+/// Largest federation frame accepted (10MB, as for TAK connections)
+const MAX_FRAME_SIZE: usize = 10 * 1024 * 1024;
+
+/// How long federation metadata of a received event is remembered
+const LEARNED_TTL: Duration = Duration::from_secs(120);
+
+/// Delay before reconnecting an outbound federate link
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// Federation configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FederationConfig {
+    /// This instance's federation ID; the CN of `cert_path` and the name
+    /// other instances list this one under
+    pub instance_id: String,
+    /// Accept inbound federates on this address (e.g. "0.0.0.0:9001")
+    #[serde(default)]
+    pub bind_addr: Option<String>,
+    /// This instance's certificate (PEM)
+    pub cert_path: String,
+    /// This instance's private key (PEM)
+    pub key_path: String,
+    /// CA that signs every federate certificate (PEM)
+    pub ca_path: String,
+    /// Drop events that crossed more federation links than this
+    #[serde(default = "default_max_hops")]
+    pub max_hops: u32,
+    #[serde(default)]
+    pub federates: Vec<FederateConfig>,
+}
+
+fn default_max_hops() -> u32 {
+    3
+}
+
+/// A peer instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FederateConfig {
+    /// The peer's instance ID (the CN of its certificate)
+    pub name: String,
+    /// Connect out to this address; omit for federates that connect in
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub policy: FederatePolicy,
+}
+
+/// What a federate may send and receive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FederatePolicy {
+    /// CoT type prefixes sent to the federate; empty sends everything
+    #[serde(default)]
+    pub send_types: Vec<String>,
+    /// CoT type prefixes accepted from the federate; empty accepts everything
+    #[serde(default)]
+    pub receive_types: Vec<String>,
+    /// Pass on events learned from other federates
+    #[serde(default = "default_transit")]
+    pub transit: bool,
+}
+
+fn default_transit() -> bool {
+    true
+}
+
+impl Default for FederatePolicy {
+    fn default() -> Self {
+        Self {
+            send_types: Vec::new(),
+            receive_types: Vec::new(),
+            transit: default_transit(),
+        }
+    }
+}
+
+fn type_allowed(prefixes: &[String], cot_type: &str) -> bool {
+    prefixes.is_empty() || prefixes.iter().any(|p| cot_type.starts_with(p.as_str()))
+}
+
+/// Federation metadata of an event received from a federate
+struct Learned {
+    origin: String,
+    path: Vec<String>,
+    hops: u32,
+    at: Instant,
+}
+
+/// Loop prevention and policy decisions, shared by every link
+struct Router {
+    instance_id: String,
+    max_hops: u32,
+    /// By event UID
+    learned: Mutex<HashMap<String, Learned>>,
+}
+
+impl Router {
+    fn new(config: &FederationConfig) -> Self {
+        Self {
+            instance_id: config.instance_id.clone(),
+            max_hops: config.max_hops,
+            learned: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Envelope for sending an event to `federate`, or `None` if it must
+    /// not be sent there
+    fn outbound(&self, federate: &FederateConfig, data: &[u8]) -> Option<FederatedMessage> {
+        let event = omnitak_cot::parse_cot_bytes(data).ok()?;
+        if !type_allowed(&federate.policy.send_types, &event.event_type) {
+            return None;
+        }
+
+        let learned = self.learned.lock().unwrap();
+        let (origin, mut path, hops) = match learned.get(&event.uid) {
+            Some(l) if l.at.elapsed() <= LEARNED_TTL => {
+                if !federate.policy.transit || l.path.contains(&federate.name) {
+                    return None;
+                }
+                (l.origin.clone(), l.path.clone(), l.hops + 1)
+            }
+            _ => (self.instance_id.clone(), Vec::new(), 1),
+        };
+        drop(learned);
+        if hops > self.max_hops {
+            return None;
+        }
+        path.push(self.instance_id.clone());
+
+        Some(FederatedMessage {
+            origin,
+            path,
+            hops,
+            payload: data.to_vec(),
+        })
+    }
+
+    /// Check an envelope received from `federate`; returns why it was
+    /// rejected, if it was
+    fn inbound(
+        &self,
+        federate: &FederateConfig,
+        message: &FederatedMessage,
+    ) -> Result<(), &'static str> {
+        if message.origin == self.instance_id || message.path.contains(&self.instance_id) {
+            return Err("loop");
+        }
+        if message.hops > self.max_hops {
+            return Err("too many hops");
+        }
+        let event = omnitak_cot::parse_cot_bytes(&message.payload).map_err(|_| "invalid CoT")?;
+        if !type_allowed(&federate.policy.receive_types, &event.event_type) {
+            return Err("type not accepted");
+        }
+
+        let mut learned = self.learned.lock().unwrap();
+        learned.retain(|_, l| l.at.elapsed() <= LEARNED_TTL);
+        learned.insert(
+            event.uid,
+            Learned {
+                origin: message.origin.clone(),
+                path: message.path.clone(),
+                hops: message.hops,
+                at: Instant::now(),
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Everything a link needs
+struct Federation {
+    config: FederationConfig,
+    router: Router,
+    pool: Arc<ConnectionPool>,
+    aggregator: Arc<MessageAggregator>,
+    events: EventBus,
+}
+
+/// Start accepting inbound federates and connecting to outbound ones
+pub async fn spawn(
+    config: FederationConfig,
+    pool: Arc<ConnectionPool>,
+    aggregator: Arc<MessageAggregator>,
+    events: EventBus,
+) -> Result<()> {
+    let certs = load_certs(&config.cert_path)?;
+    let key = rustls_pemfile::private_key(&mut std::io::BufReader::new(
+        std::fs::File::open(&config.key_path).context("Failed to open federation key")?,
+    ))
+    .context("Failed to read federation key")?
+    .ok_or_else(|| anyhow!("No private key found in {}", config.key_path))?;
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in load_certs(&config.ca_path)? {
+        roots.add(cert).context("Failed to add federation CA")?;
+    }
+    let roots = Arc::new(roots);
+
+    let verifier = rustls::server::WebPkiClientVerifier::builder(Arc::clone(&roots))
+        .build()
+        .context("Failed to build federate verifier")?;
+    let server_config = rustls::ServerConfig::builder()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(certs.clone(), key.clone_key())
+        .context("Invalid federation certificate or key")?;
+    let client_config = rustls::ClientConfig::builder()
+        .with_root_certificates(Arc::clone(&roots))
+        .with_client_auth_cert(certs, key)
+        .context("Invalid federation certificate or key")?;
+
+    let federation = Arc::new(Federation {
+        router: Router::new(&config),
+        config,
+        pool,
+        aggregator,
+        events,
+    });
+
+    if let Some(bind_addr) = &federation.config.bind_addr {
+        let listener = TcpListener::bind(bind_addr)
+            .await
+            .with_context(|| format!("Failed to bind federation listener to {}", bind_addr))?;
+        info!(bind_addr = %bind_addr, "Accepting federates");
+        tokio::spawn(accept_loop(
+            listener,
+            TlsAcceptor::from(Arc::new(server_config)),
+            Arc::clone(&federation),
+        ));
+    }
+
+    let connector = TlsConnector::from(Arc::new(client_config));
+    for federate in &federation.config.federates {
+        if let Some(address) = &federate.address {
+            tokio::spawn(connect_loop(
+                federate.clone(),
+                address.clone(),
+                connector.clone(),
+                Arc::clone(&federation),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn load_certs(path: &str) -> Result<Vec<rustls::pki_types::CertificateDer<'static>>> {
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path))?;
+    rustls_pemfile::certs(&mut std::io::BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to parse certificates in {}", path))
+}
+
+async fn accept_loop(listener: TcpListener, acceptor: TlsAcceptor, federation: Arc<Federation>) {
+    loop {
+        let (stream, peer_addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                error!(error = %e, "Federation accept failed");
+                continue;
+            }
+        };
+
+        let acceptor = acceptor.clone();
+        let federation = Arc::clone(&federation);
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!(peer = %peer_addr, error = %e, "Federate TLS handshake failed");
+                    return;
+                }
+            };
+
+            // The verified client certificate names the federate
+            let name = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(|cert| omnitak_cert::CertificateInfo::from_der(cert).ok())
+                .map(|info| info.subject_cn);
+            let Some(federate) = name.as_ref().and_then(|name| {
+                federation
+                    .config
+                    .federates
+                    .iter()
+                    .find(|f| f.name == *name)
+                    .cloned()
+            }) else {
+                warn!(peer = %peer_addr, name = ?name, "Rejected unknown federate");
+                return;
+            };
+
+            if let Err(e) = run_link(&federation, &federate, &peer_addr.to_string(), stream).await {
+                warn!(federate = %federate.name, error = %e, "Federate link closed");
+            }
+        });
+    }
+}
+
+async fn connect_loop(
+    federate: FederateConfig,
+    address: String,
+    connector: TlsConnector,
+    federation: Arc<Federation>,
+) {
+    let host = address
+        .rsplit_once(':')
+        .map_or(address.as_str(), |(host, _)| host)
+        .to_string();
+    loop {
+        let result: Result<()> = async {
+            let server_name = ServerName::try_from(host.clone())
+                .with_context(|| format!("Invalid federate host {}", host))?;
+            let tcp = TcpStream::connect(&address)
+                .await
+                .with_context(|| format!("Failed to connect to {}", address))?;
+            let stream = connector
+                .connect(server_name, tcp)
+                .await
+                .context("TLS handshake failed")?;
+            run_link(&federation, &federate, &address, stream).await
+        }
+        .await;
+
+        if let Err(e) = result {
+            warn!(federate = %federate.name, error = %e, "Federate link down, retrying in {:?}", RECONNECT_DELAY);
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Exchange traffic with a federate until the link fails
+async fn run_link<S>(
+    federation: &Federation,
+    federate: &FederateConfig,
+    address: &str,
+    stream: S,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let connection_id = format!("{}{}", FEDERATE_PREFIX, federate.name);
+    federation
+        .pool
+        .add_connection(
+            connection_id.clone(),
+            format!("Federate {}", federate.name),
+            address.to_string(),
+            5,
+        )
+        .await
+        .with_context(|| format!("Federate {} is already linked", federate.name))?;
+    let connection = federation
+        .pool
+        .get_connection(&connection_id)
+        .context("Federate connection missing from pool")?;
+
+    info!(federate = %federate.name, address = %address, "Federate linked");
+    federation.events.publish(SystemEvent::ConnectionUp {
+        connection_id: connection_id.clone(),
+        address: address.to_string(),
+    });
+
+    let (mut reader, mut writer) = tokio::io::split(stream);
+
+    let writer_task = {
+        let federate = federate.clone();
+        let connection = Arc::clone(&connection);
+        let router = &federation.router;
+        async move {
+            while let Ok(msg) = connection.rx.recv_async().await {
+                let PoolMessage::Cot(data) = msg else {
+                    continue;
+                };
+                let Some(envelope) = router.outbound(&federate, &data) else {
+                    continue;
+                };
+                let frame = encode_federated(&envelope)?;
+                writer.write_all(&frame).await?;
+            }
+            Ok::<(), anyhow::Error>(())
+        }
+    };
+
+    let reader_task = async {
+        let mut buffer = BytesMut::with_capacity(8192);
+        loop {
+            while let Some((message, used)) = decode_federated(&buffer, MAX_FRAME_SIZE)? {
+                let _ = buffer.split_to(used);
+                if let Err(reason) = federation.router.inbound(federate, &message) {
+                    debug!(federate = %federate.name, origin = %message.origin, reason, "Dropped federated event");
+                    continue;
+                }
+                connection.state.record_received();
+                let inbound = InboundMessage {
+                    data: message.payload,
+                    source: connection_id.clone(),
+                    timestamp: Instant::now(),
+                    trace_id: TraceId::new(),
+                };
+                if federation
+                    .aggregator
+                    .sender()
+                    .send_async(inbound)
+                    .await
+                    .is_err()
+                {
+                    return Err(anyhow!("Aggregator stopped"));
+                }
+            }
+            if reader.read_buf(&mut buffer).await? == 0 {
+                return Err(anyhow!("Federate closed the connection"));
+            }
+        }
+    };
+
+    let result = tokio::select! {
+        result = writer_task => result,
+        result = reader_task => result,
+    };
+
+    let _ = federation.pool.remove_connection(&connection_id).await;
+    federation.events.publish(SystemEvent::ConnectionDown {
+        connection_id,
+        address: address.to_string(),
+        reason: result.as_ref().err().map(|e| e.to_string()),
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(instance_id: &str) -> FederationConfig {
+        serde_yaml::from_str(&format!(
+            "instance_id: {}\ncert_path: c.pem\nkey_path: k.pem\nca_path: ca.pem\nmax_hops: 2\n",
+            instance_id
+        ))
+        .unwrap()
+    }
+
+    fn federate(name: &str, policy: FederatePolicy) -> FederateConfig {
+        FederateConfig {
+            name: name.to_string(),
+            address: None,
+            policy,
+        }
+    }
+
+    fn cot(uid: &str, cot_type: &str) -> Vec<u8> {
+        format!(
+            r#"<event version="2.0" uid="{uid}" type="{cot_type}" time="2026-01-01T00:00:00Z" start="2026-01-01T00:00:00Z" stale="2030-01-01T00:00:00Z" how="m-g"><point lat="34.0" lon="-117.2" hae="0" ce="10" le="10"/><detail/></event>"#
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_loop_prevention() {
+        let a = Router::new(&config("site-a"));
+        let b = Router::new(&config("site-b"));
+        let c = Router::new(&config("site-c"));
+        let open = FederatePolicy::default();
+
+        // A originates, B receives and passes it to C
+        let to_b = a
+            .outbound(&federate("site-b", open.clone()), &cot("U1", "a-f-G"))
+            .unwrap();
+        assert_eq!((to_b.origin.as_str(), to_b.hops), ("site-a", 1));
+        b.inbound(&federate("site-a", open.clone()), &to_b).unwrap();
+
+        // Never back to an instance already on the path
+        assert!(b
+            .outbound(&federate("site-a", open.clone()), &to_b.payload)
+            .is_none());
+        let to_c = b
+            .outbound(&federate("site-c", open.clone()), &to_b.payload)
+            .unwrap();
+        assert_eq!(to_c.path, vec!["site-a".to_string(), "site-b".to_string()]);
+        assert_eq!(to_c.hops, 2);
+
+        // C has reached max_hops and may not pass it on
+        c.inbound(&federate("site-b", open.clone()), &to_c).unwrap();
+        assert!(c
+            .outbound(&federate("site-d", open.clone()), &to_c.payload)
+            .is_none());
+
+        // An event coming back to its origin is dropped
+        assert_eq!(a.inbound(&federate("site-c", open), &to_c), Err("loop"));
+    }
+
+    #[test]
+    fn test_policy() {
+        let a = Router::new(&config("site-a"));
+        let b = Router::new(&config("site-b"));
+        let friendly_only = FederatePolicy {
+            send_types: vec!["a-f-".to_string()],
+            receive_types: vec!["a-f-".to_string()],
+            transit: false,
+        };
+
+        let peer = federate("site-b", friendly_only.clone());
+        assert!(a.outbound(&peer, &cot("U1", "a-f-G")).is_some());
+        assert!(a.outbound(&peer, &cot("U2", "a-h-G")).is_none());
+
+        let hostile = a
+            .outbound(
+                &federate("site-b", FederatePolicy::default()),
+                &cot("U2", "a-h-G"),
+            )
+            .unwrap();
+        assert_eq!(
+            b.inbound(&federate("site-a", friendly_only.clone()), &hostile),
+            Err("type not accepted")
+        );
+
+        // Without transit, learned events stay here
+        let friendly = a.outbound(&peer, &cot("U1", "a-f-G")).unwrap();
+        b.inbound(&federate("site-a", friendly_only.clone()), &friendly)
+            .unwrap();
+        assert!(b
+            .outbound(&federate("site-c", friendly_only), &friendly.payload)
+            .is_none());
+    }
+}
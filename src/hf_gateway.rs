@@ -0,0 +1,460 @@
+//! Winlink / Email-over-HF Gateway
+//!
+//! For austere links where a live TAK stream is not possible, the exporter
+//! keeps the latest position of selected tracks and periodically packs those
+//! updated since the previous report into a compact text report suitable
+//! for Winlink or any other email-over-HF transport: one line per track,
+//! zstd-compressed and base64 armoured, typically well under 1 KB for
+//! dozens of tracks. Reports are written to an outbox directory (for Pat,
+//! Winlink Express or manual sending) and/or mailed through an SMTP relay.
+//!
+//! The importer watches an inbox directory for received reports, whether
+//! saved messages or raw bodies, and re-injects the tracks into the
+//! aggregator as CoT so the receiving aggregator shows the same picture.
+//! Reports carry plain text, escaped again when the CoT is built.
+//!
+//! Report body:
+//!
+//! ```text
+//! OMNITAK-HF/1 <report id> <track count>
+//! <base64, 76 columns>
+//! ```
+
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use omnitak_api::alerts::EmailChannel;
+use omnitak_cot::{escape_xml, unescape_xml, Contact, Detail, Event, Point};
+use omnitak_pool::{ConnectionPool, InboundMessage, MessageAggregator, PoolMessage, TraceId};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Pool connection and aggregator source ID for the gateway
+pub const HF_CONNECTION_ID: &str = "hf-gateway";
+
+/// First token of every report
+const REPORT_HEADER: &str = "OMNITAK-HF/1";
+
+/// Base64 line width, safe for every mail transport
+const LINE_WIDTH: usize = 76;
+
+const ZSTD_LEVEL: i32 = 19;
+
+/// Gateway configuration; export and import are independent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HfGatewayConfig {
+    #[serde(default)]
+    pub export: Option<HfExportConfig>,
+    #[serde(default)]
+    pub import: Option<HfImportConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HfExportConfig {
+    /// Seconds between reports
+    #[serde(default = "default_export_interval_secs")]
+    pub interval_secs: u64,
+    /// CoT type prefixes to include
+    #[serde(default = "default_types")]
+    pub types: Vec<String>,
+    /// Most recently updated tracks kept per report
+    #[serde(default = "default_max_tracks")]
+    pub max_tracks: usize,
+    /// Write each report to this directory
+    #[serde(default)]
+    pub outbox_dir: Option<PathBuf>,
+    /// Mail each report through this SMTP relay (e.g. Pat or a Winlink RMS gateway)
+    #[serde(default)]
+    pub email: Option<EmailChannel>,
+    #[serde(default = "default_subject")]
+    pub subject: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HfImportConfig {
+    /// Directory to scan for received reports; imported reports are
+    /// removed, other files are left in place
+    pub inbox_dir: PathBuf,
+    #[serde(default = "default_import_interval_secs")]
+    pub poll_interval_secs: u64,
+}
+
+fn default_export_interval_secs() -> u64 {
+    900
+}
+
+fn default_types() -> Vec<String> {
+    vec!["a-f-".to_string()]
+}
+
+fn default_max_tracks() -> usize {
+    100
+}
+
+fn default_subject() -> String {
+    "OmniTAK position report".to_string()
+}
+
+fn default_import_interval_secs() -> u64 {
+    60
+}
+
+/// One track as carried in a report, with plain-text strings
+#[derive(Debug, Clone, PartialEq)]
+struct TrackRecord {
+    uid: String,
+    cot_type: String,
+    how: String,
+    lat: f64,
+    lon: f64,
+    hae: f64,
+    /// Unix seconds
+    time: i64,
+    /// Seconds from `time` until stale
+    stale_secs: i64,
+    callsign: Option<String>,
+}
+
+impl TrackRecord {
+    fn from_event(event: &Event) -> Self {
+        Self {
+            uid: unescape_xml(&event.uid).into_owned(),
+            cot_type: unescape_xml(&event.event_type).into_owned(),
+            how: unescape_xml(&event.how).into_owned(),
+            lat: event.point.lat,
+            lon: event.point.lon,
+            hae: event.point.hae,
+            time: event.time.timestamp(),
+            stale_secs: (event.stale - event.time).num_seconds().max(0),
+            callsign: event.callsign().map(|c| unescape_xml(c).into_owned()),
+        }
+    }
+
+    /// Tab-separated line; 5 decimal places is about 1 m
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{:.5}\t{:.5}\t{:.0}\t{}\t{}\t{}",
+            field(&self.uid),
+            field(&self.cot_type),
+            field(&self.how),
+            self.lat,
+            self.lon,
+            self.hae,
+            self.time,
+            self.stale_secs,
+            self.callsign.as_deref().map(field).unwrap_or_default()
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let mut next = || fields.next();
+        let record = Self {
+            uid: next()?.to_string(),
+            cot_type: next()?.to_string(),
+            how: next()?.to_string(),
+            lat: next()?.parse().ok()?,
+            lon: next()?.parse().ok()?,
+            hae: next()?.parse().ok()?,
+            time: next()?.parse().ok()?,
+            stale_secs: next()?.parse().ok()?,
+            callsign: next().filter(|c| !c.is_empty()).map(str::to_string),
+        };
+        (!record.uid.is_empty()).then_some(record)
+    }
+
+    fn to_event(&self) -> Option<Event> {
+        let time = DateTime::<Utc>::from_timestamp(self.time, 0)?;
+        Some(Event {
+            version: "2.0".to_string(),
+            uid: escape_xml(&self.uid),
+            event_type: escape_xml(&self.cot_type),
+            time,
+            start: time,
+            stale: time + ChronoDuration::seconds(self.stale_secs),
+            how: escape_xml(&self.how),
+            point: Point::new(self.lat, self.lon, self.hae),
+            detail: Some(Detail {
+                contact: self.callsign.as_deref().map(|callsign| Contact {
+                    endpoint: None,
+                    callsign: escape_xml(callsign),
+                }),
+                xml_detail: Some("<remarks>Relayed over HF</remarks>".to_string()),
+                ..Default::default()
+            }),
+        })
+    }
+}
+
+/// Strip separators so a value fits in one field
+fn field(value: &str) -> String {
+    value.replace(['\t', '\r', '\n'], " ")
+}
+
+/// Build a report body from track records
+fn encode_report(id: &str, records: &[TrackRecord]) -> Result<String> {
+    let lines: Vec<String> = records.iter().map(TrackRecord::to_line).collect();
+    let compressed = zstd::encode_all(lines.join("\n").as_bytes(), ZSTD_LEVEL)
+        .context("Failed to compress report")?;
+    let armoured = base64::engine::general_purpose::STANDARD.encode(compressed);
+
+    let mut body = format!("{} {} {}\n", REPORT_HEADER, id, records.len());
+    for chunk in armoured.as_bytes().chunks(LINE_WIDTH) {
+        body.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+        body.push('\n');
+    }
+    Ok(body)
+}
+
+/// Find and decode a report anywhere in a message (headers, quoting and
+/// signatures around it are ignored)
+fn decode_report(text: &str) -> Result<Vec<TrackRecord>> {
+    let mut lines = text.lines().map(str::trim);
+    if !lines.by_ref().any(|line| line.starts_with(REPORT_HEADER)) {
+        bail!("No {} report found", REPORT_HEADER);
+    }
+    let armoured: String = lines
+        .take_while(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .concat();
+    let compressed = base64::engine::general_purpose::STANDARD
+        .decode(armoured)
+        .context("Report is not valid base64")?;
+    let plain = zstd::decode_all(compressed.as_slice()).context("Failed to decompress report")?;
+    let plain = String::from_utf8(plain).context("Report is not UTF-8")?;
+    Ok(plain.lines().filter_map(TrackRecord::from_line).collect())
+}
+
+/// Start the exporter and/or importer
+pub async fn spawn(
+    config: HfGatewayConfig,
+    pool: Arc<ConnectionPool>,
+    aggregator: Arc<MessageAggregator>,
+) -> Result<()> {
+    if let Some(export) = config.export {
+        spawn_exporter(export, &pool).await?;
+    }
+    if let Some(import) = config.import {
+        tokio::spawn(run_importer(import, aggregator));
+    }
+    Ok(())
+}
+
+/// Register with the pool to see routed traffic and send periodic reports
+async fn spawn_exporter(config: HfExportConfig, pool: &ConnectionPool) -> Result<()> {
+    pool.add_connection(
+        HF_CONNECTION_ID.to_string(),
+        "Winlink/HF gateway".to_string(),
+        HF_CONNECTION_ID.to_string(),
+        1,
+    )
+    .await
+    .context("Failed to register HF gateway with the pool")?;
+    let connection = pool
+        .get_connection(&HF_CONNECTION_ID.to_string())
+        .context("HF gateway connection missing from pool")?;
+
+    // Latest position per UID, with when it was received
+    let tracks: Arc<Mutex<HashMap<String, (Instant, TrackRecord)>>> = Arc::default();
+
+    let collector = Arc::clone(&tracks);
+    let types = config.types.clone();
+    tokio::spawn(async move {
+        while let Ok(msg) = connection.rx.recv_async().await {
+            let PoolMessage::Cot(data) = msg else {
+                continue;
+            };
+            let Ok(event) = omnitak_cot::parse_cot_bytes(&data) else {
+                continue;
+            };
+            if !types.iter().any(|t| event.event_type.starts_with(t)) {
+                continue;
+            }
+            collector.lock().unwrap().insert(
+                event.uid.clone(),
+                (Instant::now(), TrackRecord::from_event(&event)),
+            );
+        }
+    });
+
+    tokio::spawn(async move {
+        let period = Duration::from_secs(config.interval_secs.max(60));
+        let mut interval = tokio::time::interval(period);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+
+            let records = {
+                let mut tracks = tracks.lock().unwrap();
+                let now = Utc::now().timestamp();
+                tracks.retain(|_, (_, r)| r.time + r.stale_secs > now);
+                let mut latest: Vec<_> = tracks.values().cloned().collect();
+                latest.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+                latest.truncate(config.max_tracks);
+                tracks.clear();
+                latest.into_iter().map(|(_, r)| r).collect::<Vec<_>>()
+            };
+            if records.is_empty() {
+                continue;
+            }
+            if let Err(e) = send_report(&config, &records).await {
+                warn!(error = %e, "Failed to send HF report");
+            }
+        }
+    });
+
+    Ok(())
+}
+
+async fn send_report(config: &HfExportConfig, records: &[TrackRecord]) -> Result<()> {
+    let id = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let body = encode_report(&id, records)?;
+    info!(
+        tracks = records.len(),
+        bytes = body.len(),
+        "Sending HF report {}",
+        id
+    );
+
+    if let Some(dir) = &config.outbox_dir {
+        tokio::fs::create_dir_all(dir).await?;
+        let path = dir.join(format!("omnitak-{}.txt", id));
+        tokio::fs::write(&path, &body)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    if let Some(email) = &config.email {
+        let subject = format!("{} {}", config.subject, id);
+        omnitak_api::alerts::send_email(email, subject, body).await?;
+    }
+    Ok(())
+}
+
+async fn run_importer(config: HfImportConfig, aggregator: Arc<MessageAggregator>) {
+    let sender = aggregator.sender();
+    let mut interval = tokio::time::interval(Duration::from_secs(config.poll_interval_secs.max(1)));
+    // Files that are not reports, left in the inbox and not read again
+    let mut skipped = HashSet::new();
+    loop {
+        interval.tick().await;
+
+        let files = match inbox_files(&config.inbox_dir).await {
+            Ok(files) => files,
+            Err(e) => {
+                debug!(error = %e, "Cannot read HF inbox {}", config.inbox_dir.display());
+                continue;
+            }
+        };
+        skipped.retain(|path| files.contains(path));
+        for path in files {
+            if skipped.contains(&path) {
+                continue;
+            }
+            let text = match tokio::fs::read_to_string(&path).await {
+                Ok(text) => text,
+                Err(e) => {
+                    // Possibly still being written; try again next poll
+                    debug!("Cannot read {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            match decode_report(&text) {
+                Ok(records) => {
+                    info!(
+                        tracks = records.len(),
+                        "Imported HF report {}",
+                        path.display()
+                    );
+                    for event in records.iter().filter_map(TrackRecord::to_event) {
+                        let msg = InboundMessage {
+                            data: omnitak_cot::serialize_event(&event).into_bytes(),
+                            source: HF_CONNECTION_ID.to_string(),
+                            timestamp: Instant::now(),
+                            trace_id: TraceId::new(),
+                        };
+                        if let Err(e) = sender.send_async(msg).await {
+                            warn!(error = %e, "Failed to inject HF track");
+                        }
+                    }
+                    if let Err(e) = tokio::fs::remove_file(&path).await {
+                        warn!("Failed to remove {}: {}", path.display(), e);
+                    }
+                }
+                Err(e) => {
+                    debug!("Skipping {}: {:#}", path.display(), e);
+                    skipped.insert(path);
+                }
+            }
+        }
+    }
+}
+
+async fn inbox_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_type().await?.is_file() {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(uid: &str, callsign: Option<&str>) -> TrackRecord {
+        TrackRecord {
+            uid: uid.to_string(),
+            cot_type: "a-f-G-U-C".to_string(),
+            how: "m-g".to_string(),
+            lat: 34.12345,
+            lon: -117.54321,
+            hae: 120.0,
+            time: 1_767_225_600,
+            stale_secs: 300,
+            callsign: callsign.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_report_round_trip() {
+        let records = vec![
+            record("ANDROID-1", Some("VIPER\t1")),
+            record("ANDROID-2", None),
+        ];
+        let body = encode_report("20260101T000000Z", &records).unwrap();
+        assert!(body.starts_with("OMNITAK-HF/1 20260101T000000Z 2\n"));
+        assert!(body.lines().all(|l| l.len() <= LINE_WIDTH));
+
+        // Received with mail headers and a signature around it
+        let received = format!("From: N0CALL\nSubject: report\n\n{}\n--\n73", body);
+        let decoded = decode_report(&received).unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].callsign.as_deref(), Some("VIPER 1"));
+        assert_eq!(decoded[1], records[1]);
+
+        let event = decoded[0].to_event().unwrap();
+        assert_eq!(event.uid, "ANDROID-1");
+        assert_eq!((event.stale - event.time).num_seconds(), 300);
+
+        assert!(decode_report("no report here").is_err());
+    }
+
+    #[test]
+    fn test_markup_in_strings_escaped() {
+        let original = record("ANDROID-3", Some(r#"Tom & "Jerry" <1>"#));
+        let xml = omnitak_cot::serialize_event(&original.to_event().unwrap());
+        assert!(xml.contains(r#"callsign="Tom &amp; &quot;Jerry&quot; &lt;1&gt;""#));
+
+        // Carried as plain text and escaped once on the other side
+        let parsed = omnitak_cot::parse_cot(&xml).unwrap();
+        assert_eq!(TrackRecord::from_event(&parsed), original);
+    }
+}
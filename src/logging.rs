@@ -0,0 +1,390 @@
+//! Log output
+//!
+//! Logs go to stdout and optionally to a file, each as text or JSON lines.
+//! Files are rotated when they reach a size limit or when the hour or day
+//! changes (UTC), keeping the newest `max_files` as `omnitak.log.1`,
+//! `omnitak.log.2`, ... Levels can be set per subsystem by tracing target,
+//! and are swapped at runtime through the admin API.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use omnitak_api::types::LogLevels;
+use omnitak_api::LogLevelControl;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoggingConfig {
+    /// Level for everything without a more specific entry
+    #[serde(default = "default_level")]
+    pub level: String,
+    /// Levels by tracing target, e.g. `omnitak_pool: debug`
+    #[serde(default)]
+    pub levels: BTreeMap<String, String>,
+    /// Stdout format
+    #[serde(default)]
+    pub format: LogFormat,
+    /// Also log to stdout when logging to a file
+    #[serde(default = "default_true")]
+    pub stdout: bool,
+    #[serde(default)]
+    pub file: Option<FileLogConfig>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: default_level(),
+            levels: BTreeMap::new(),
+            format: LogFormat::default(),
+            stdout: true,
+            file: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileLogConfig {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub format: LogFormat,
+    /// Start a new file every hour or day
+    #[serde(default)]
+    pub rotation: Rotation,
+    /// Start a new file when the current one reaches this size (0 = no limit)
+    #[serde(default = "default_max_size_mb")]
+    pub max_size_mb: u64,
+    /// Rotated files to keep
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Rotation {
+    #[default]
+    Never,
+    Hourly,
+    Daily,
+}
+
+impl Rotation {
+    /// Identifies the period a timestamp falls in
+    fn period(self, at: DateTime<Utc>) -> Option<String> {
+        match self {
+            Rotation::Never => None,
+            Rotation::Hourly => Some(at.format("%Y%m%d%H").to_string()),
+            Rotation::Daily => Some(at.format("%Y%m%d").to_string()),
+        }
+    }
+}
+
+fn default_level() -> String {
+    "info".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_size_mb() -> u64 {
+    100
+}
+
+fn default_max_files() -> usize {
+    10
+}
+
+impl LoggingConfig {
+    pub fn log_levels(&self) -> LogLevels {
+        LogLevels {
+            level: self.level.clone(),
+            levels: self.levels.clone(),
+        }
+    }
+}
+
+/// Install the global subscriber. `RUST_LOG`, when set, takes precedence
+/// over the configured levels until they are changed through the API.
+pub fn init(config: &LoggingConfig) -> Result<LogLevelControl> {
+    let levels = config.log_levels();
+    levels
+        .validate_levels()
+        .map_err(anyhow::Error::msg)
+        .context("Invalid logging configuration")?;
+
+    let filter = match std::env::var("RUST_LOG") {
+        Ok(env) if !env.is_empty() => EnvFilter::try_new(&env).context("Invalid RUST_LOG")?,
+        _ => EnvFilter::try_new(levels.directives())?,
+    };
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let mut layers: Vec<Box<dyn Layer<Filtered> + Send + Sync>> = Vec::new();
+    if config.stdout || config.file.is_none() {
+        layers.push(fmt_layer(config.format, io::stdout));
+    }
+    if let Some(file) = &config.file {
+        let writer = RollingFile::open(file)
+            .with_context(|| format!("Failed to open log file {}", file.path.display()))?;
+        layers.push(fmt_layer(file.format, writer));
+    }
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(layers)
+        .try_init()
+        .context("Logging is already initialized")?;
+
+    Ok(LogLevelControl::new(levels, move |levels: &LogLevels| {
+        let filter = EnvFilter::try_new(levels.directives()).map_err(|e| e.to_string())?;
+        handle.reload(filter).map_err(|e| e.to_string())
+    }))
+}
+
+type Filtered = tracing_subscriber::layer::Layered<reload::Layer<EnvFilter, Registry>, Registry>;
+
+fn fmt_layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+/// Log file that rotates by size and time
+pub struct RollingFile {
+    path: PathBuf,
+    rotation: Rotation,
+    max_size: u64,
+    max_files: usize,
+    state: Mutex<FileState>,
+}
+
+struct FileState {
+    file: File,
+    size: u64,
+    period: Option<String>,
+}
+
+impl RollingFile {
+    pub fn open(config: &FileLogConfig) -> io::Result<Self> {
+        if let Some(dir) = config.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let state = open_file(&config.path, config.rotation)?;
+        Ok(Self {
+            path: config.path.clone(),
+            rotation: config.rotation,
+            max_size: config.max_size_mb.saturating_mul(1024 * 1024),
+            max_files: config.max_files,
+            state: Mutex::new(state),
+        })
+    }
+
+    fn write_record(&self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let period = self.rotation.period(Utc::now());
+        let too_big =
+            self.max_size > 0 && state.size > 0 && state.size + buf.len() as u64 > self.max_size;
+        if too_big || period != state.period {
+            *state = self.rotate()?;
+        }
+        let written = state.file.write(buf)?;
+        state.size += written as u64;
+        Ok(written)
+    }
+
+    /// Shift `path.N` to `path.N+1`, dropping those beyond `max_files`, and
+    /// start a new file
+    fn rotate(&self) -> io::Result<FileState> {
+        if self.max_files == 0 {
+            fs::remove_file(&self.path).or_else(ignore_not_found)?;
+        } else {
+            fs::remove_file(numbered(&self.path, self.max_files)).or_else(ignore_not_found)?;
+            for n in (1..self.max_files).rev() {
+                fs::rename(numbered(&self.path, n), numbered(&self.path, n + 1))
+                    .or_else(ignore_not_found)?;
+            }
+            fs::rename(&self.path, numbered(&self.path, 1)).or_else(ignore_not_found)?;
+        }
+        open_file(&self.path, self.rotation)
+    }
+}
+
+fn open_file(path: &Path, rotation: Rotation) -> io::Result<FileState> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let metadata = file.metadata()?;
+    // An existing file belongs to the period it was last written in
+    let modified = metadata.modified().unwrap_or_else(|_| SystemTime::now());
+    Ok(FileState {
+        file,
+        size: metadata.len(),
+        period: rotation.period(modified.into()),
+    })
+}
+
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+fn ignore_not_found(e: io::Error) -> io::Result<()> {
+    if e.kind() == io::ErrorKind::NotFound {
+        Ok(())
+    } else {
+        Err(e)
+    }
+}
+
+impl Write for &RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_record(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .file
+            .flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for RollingFile {
+    type Writer = &'a RollingFile;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("omnitak-logging-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn file_config(path: PathBuf, max_size_mb: u64, max_files: usize) -> FileLogConfig {
+        FileLogConfig {
+            path,
+            format: LogFormat::Text,
+            rotation: Rotation::Never,
+            max_size_mb,
+            max_files,
+        }
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config: LoggingConfig = serde_yaml::from_str(
+            r#"
+level: warn
+levels:
+  omnitak_pool: debug
+file:
+  path: /var/log/omnitak/omnitak.log
+  format: json
+  rotation: daily
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.log_levels().directives(), "warn,omnitak_pool=debug");
+        assert!(config.stdout);
+        let file = config.file.unwrap();
+        assert_eq!(file.format, LogFormat::Json);
+        assert_eq!(file.rotation, Rotation::Daily);
+        assert_eq!(file.max_size_mb, 100);
+        assert_eq!(file.max_files, 10);
+    }
+
+    #[test]
+    fn test_rotates_by_size_and_prunes() {
+        let dir = temp_dir("size");
+        let path = dir.join("omnitak.log");
+        let log = RollingFile::open(&file_config(path.clone(), 0, 2)).unwrap();
+        // Force a 10-byte limit to keep the test small
+        let log = RollingFile {
+            max_size: 10,
+            ..log
+        };
+
+        for line in ["aaaaaaaa\n", "bbbbbbbb\n", "cccccccc\n", "dddddddd\n"] {
+            (&log).write_all(line.as_bytes()).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "dddddddd\n");
+        assert_eq!(
+            fs::read_to_string(numbered(&path, 1)).unwrap(),
+            "cccccccc\n"
+        );
+        assert_eq!(
+            fs::read_to_string(numbered(&path, 2)).unwrap(),
+            "bbbbbbbb\n"
+        );
+        assert!(!numbered(&path, 3).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_rotates_on_new_period() {
+        let dir = temp_dir("period");
+        let path = dir.join("omnitak.log");
+        let mut config = file_config(path.clone(), 0, 3);
+        config.rotation = Rotation::Daily;
+        let log = RollingFile::open(&config).unwrap();
+        (&log).write_all(b"today\n").unwrap();
+
+        // Pretend the current file was opened yesterday
+        log.state.lock().unwrap().period = Some("19700101".to_string());
+        (&log).write_all(b"tomorrow\n").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "tomorrow\n");
+        assert_eq!(fs::read_to_string(numbered(&path, 1)).unwrap(), "today\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_appends_to_existing_file() {
+        let dir = temp_dir("append");
+        let path = dir.join("omnitak.log");
+        let config = file_config(path.clone(), 1, 3);
+        (&RollingFile::open(&config).unwrap())
+            .write_all(b"one\n")
+            .unwrap();
+        (&RollingFile::open(&config).unwrap())
+            .write_all(b"two\n")
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -93,7 +93,7 @@ async fn main() -> Result<()> {
         tls_cert_path: None,
         tls_key_path: None,
         auth_config: Default::default(),
-        rate_limit_rps: 100,
+        rate_limit: Default::default(),
         enable_swagger: true,
         enable_static_files: true,
     };
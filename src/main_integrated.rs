@@ -1,42 +1,72 @@
+mod adsb;
+mod ais;
+mod alerting;
+mod bench;
+mod cli;
+mod cluster;
+mod events;
+mod federation;
+mod hf_gateway;
+mod logging;
+mod mqtt_bridge;
+mod runtime;
+mod self_position;
 mod server_listener;
+mod service;
+mod tui;
+mod upgrade;
+mod webhooks;
 
 use anyhow::{Context, Result};
 use clap::Parser;
+use omnitak_api::types::UserRole;
 use omnitak_api::{ServerBuilder, ServerConfig};
+use omnitak_core::TimeSyncConfig;
 use omnitak_client::{
     tcp::{TcpClient, TcpClientConfig},
     tls::{TlsClient, TlsClientConfig},
-    Bytes, CotMessage, TakClient,
+    Bytes, Compression, CotMessage, TakClient, TcpFramingMode, TlsFramingMode,
 };
 use omnitak_pool::{
     AggregatorConfig, ConnectionPool, DistributorConfig, FilterRule, HealthMonitor, InboundMessage,
-    MessageAggregator, MessageDistributor, PoolConfig, PoolMessage,
+    MessageAggregator, MessageDistributor, PoolConfig, PoolMessage, SinkDefinition, TraceId,
+    WireFormat,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use server_listener::{
     TcpListener as ServerTcpListener, TlsListener as ServerTlsListener,
     ListenerConfig as ServerListenerConfig, ListenerProtocol as ServerListenerProtocol,
     TlsListenerConfig as ServerTlsListenerConfig, ClientAuthConfig as ServerClientAuthConfig,
+    EndpointPolicy, VirtualHostConfig as ServerVirtualHostConfig,
 };
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::signal;
 use tokio_stream::StreamExt;
 use tracing::{debug, error, info, warn};
 
 /// OmniTAK - High-performance TAK aggregator and message broker
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-struct Args {
+struct Cli {
     /// Path to configuration file
-    #[arg(short, long, default_value = "config/config.yaml")]
+    #[arg(short, long, default_value = "config/config.yaml", global = true)]
     config: PathBuf,
 
+    #[command(subcommand)]
+    command: Option<cli::Command>,
+
+    #[command(flatten)]
+    serve: ServeArgs,
+}
+
+/// Options for running the server
+#[derive(clap::Args, Debug)]
+struct ServeArgs {
     /// Override bind address
     #[arg(short, long)]
     bind: Option<SocketAddr>,
@@ -48,16 +78,106 @@ struct Args {
     /// Admin password for initial setup
     #[arg(long, env = "OMNITAK_ADMIN_PASSWORD", default_value = "changeme")]
     admin_password: String,
+
+    /// Write the process ID here, refusing to start if another live
+    /// instance holds it
+    #[arg(long, env = "OMNITAK_PIDFILE")]
+    pidfile: Option<PathBuf>,
 }
 
 #[derive(Debug, Deserialize)]
 struct Config {
     #[serde(default)]
     api: ApiConfig,
+    /// Log levels, format and file output
+    #[serde(default)]
+    logging: logging::LoggingConfig,
     #[serde(default)]
     servers: Vec<TakServerDef>,
     #[serde(default)]
     listeners: Vec<ListenerConfig>,
+    /// Connection pool sizing and health checks
+    #[serde(default)]
+    pool: omnitak_core::config::PoolConfig,
+    /// Fan-out of messages to connections
+    #[serde(default)]
+    distributor: omnitak_core::config::DistributorConfig,
+    /// Deduplication of inbound messages
+    #[serde(default)]
+    aggregator: omnitak_core::config::AggregatorConfig,
+    /// Periodic statistics report and the Prometheus endpoint
+    #[serde(default)]
+    metrics: omnitak_core::config::MetricsConfig,
+    /// Field rules every inbound message must pass
+    #[serde(default)]
+    filters: omnitak_core::config::FilterConfig,
+    #[serde(default)]
+    upgrade: UpgradeConfig,
+    #[serde(default)]
+    time_sync: TimeSyncConfig,
+    #[serde(default)]
+    self_position: Option<self_position::SelfPositionConfig>,
+    #[serde(default)]
+    adsb: Option<adsb::AdsbConfig>,
+    #[serde(default)]
+    ais: Option<ais::AisConfig>,
+    #[serde(default)]
+    mqtt: Option<mqtt_bridge::MqttBridgeConfig>,
+    #[serde(default)]
+    hf_gateway: Option<hf_gateway::HfGatewayConfig>,
+    #[serde(default)]
+    sinks: Vec<SinkDefinition>,
+    #[serde(default)]
+    events: events::EventsConfig,
+    #[serde(default)]
+    webhooks: webhooks::WebhooksConfig,
+    #[serde(default)]
+    alerts: omnitak_api::AlertsConfig,
+    /// Authorize TLS listener clients by their certificate
+    #[serde(default)]
+    client_access: omnitak_api::ClientAccessConfig,
+    /// Forward audit and authentication events to a SIEM over syslog or CEF
+    #[serde(default)]
+    siem: Option<omnitak_api::SiemConfig>,
+    #[serde(default)]
+    fts: omnitak_api::FtsConfig,
+    #[serde(default)]
+    fusion: Option<omnitak_pool::FusionConfig>,
+    #[serde(default)]
+    smoothing: Option<omnitak_pool::SmoothingConfig>,
+    #[serde(default)]
+    anomalies: Option<omnitak_pool::AnomalyConfig>,
+    /// Buffer for messages from strict sources that failed validation
+    #[serde(default)]
+    quarantine: omnitak_pool::QuarantineConfig,
+    /// Drop messages looping between chained aggregators
+    #[serde(default)]
+    hop_limit: Option<omnitak_pool::HopLimitConfig>,
+    /// Drop low-priority traffic by class when queues or CPU are overloaded
+    #[serde(default)]
+    shedding: Option<omnitak_pool::SheddingConfig>,
+    /// Forward only the newest position report per UID within a short window
+    #[serde(default)]
+    coalescing: Option<omnitak_pool::CoalescingConfig>,
+    #[serde(default)]
+    transformers: omnitak_pool::TransformConfig,
+    /// Routes whose transformer plugins only run on their destinations
+    #[serde(default)]
+    routing: Option<omnitak_filter::RoutingConfig>,
+    #[serde(default)]
+    federation: Option<federation::FederationConfig>,
+    #[serde(default)]
+    cluster: Option<cluster::ClusterConfig>,
+    #[serde(default)]
+    runtime: runtime::RuntimeConfig,
+    #[serde(default)]
+    marti: Option<omnitak_api::MartiConfig>,
+    /// mDNS discovery and announcement of this instance
+    #[serde(default)]
+    discovery: Option<omnitak_core::config::DiscoveryConfig>,
+    /// Watch for Android devices attached over ADB
+    #[serde(default)]
+    adb_monitor: Option<omnitak_api::AdbMonitorConfig>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -66,21 +186,94 @@ struct ApiConfig {
     bind_addr: String,
     #[serde(default = "default_enable_tls")]
     enable_tls: bool,
+    /// Answer HAProxy agent checks with load-based weights on this address
+    #[serde(default)]
+    lb_agent_addr: Option<String>,
+    /// API users with Argon2-hashed passwords, as added by `omnitak user add`
+    #[serde(default)]
+    users: Vec<UserConfig>,
+    /// API users confined to a tenant namespace
+    #[serde(default)]
+    tenant_users: Vec<TenantUserConfig>,
+    /// WASM plugins: directory, hot reload, storage and sandbox
+    #[serde(default)]
+    plugins: omnitak_plugin_api::PluginManagerConfig,
+    /// Per-client request rates for reads and for mutating requests
+    #[serde(default)]
+    rate_limit: omnitak_api::RateLimitConfig,
+    /// Access token lifetime in seconds
+    #[serde(default = "default_jwt_expiration")]
+    jwt_expiration: i64,
+    /// Refresh token lifetime in seconds
+    #[serde(default = "default_refresh_expiration")]
+    refresh_expiration: i64,
+    /// Custom roles: named permission lists users and API keys can be given
+    #[serde(default)]
+    roles: Vec<omnitak_api::RoleDefinition>,
+    /// Audit log database and retention
+    #[serde(default)]
+    audit: omnitak_api::AuditConfig,
+    /// Database keeping token revocations and refresh tokens across restarts
+    #[serde(default)]
+    token_store: Option<PathBuf>,
+    /// Reverse proxies (addresses or CIDR ranges) trusted to report client
+    /// addresses in Forwarded / X-Forwarded-For
+    #[serde(default)]
+    trusted_proxies: Vec<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// API user that only sees and feeds one tenant's connections
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct TenantUserConfig {
+    username: String,
+    /// Argon2 PHC string, as written by `omnitak user add`
+    password_hash: String,
+    role: UserRole,
+    tenant: String,
+    /// Custom role replacing the permissions of `role`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    custom_role: Option<String>,
+}
+
+/// API user whose password is stored as an Argon2 PHC string
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct UserConfig {
+    username: String,
+    password_hash: String,
+    role: UserRole,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tenant: Option<String>,
+    /// Custom role replacing the permissions of `role`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    custom_role: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 struct TakServerDef {
     id: String,
     address: String,
     protocol: String,
     tls: Option<TlsConfigDef>,
+    /// zstd-compress frames (the remote must be an OmniTAK listener with compression enabled)
+    #[serde(default)]
+    compression: bool,
+    /// Format CoT is sent in: `xml`, or `protobuf` for servers speaking TAK
+    /// Protocol Version 1
+    #[serde(default)]
+    format: WireFormat,
+    /// Quarantine messages that fail CoT schema validation instead of
+    /// forwarding them
+    #[serde(default)]
+    strict_validation: bool,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 struct TlsConfigDef {
     cert_path: String,
     key_path: String,
-    ca_path: String,
+    /// Trust roots for the server certificate; the system roots when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ca_path: Option<String>,
     #[serde(default = "default_verify_server")]
     verify_server: bool,
 }
@@ -112,6 +305,28 @@ struct ListenerTlsConfig {
     key_path: String,
     #[serde(default)]
     client_auth: Option<ClientAuthConfig>,
+    /// Policy for clients not routed to a virtual host
+    #[serde(default)]
+    policy: EndpointPolicy,
+    /// Further endpoints on the same port, chosen by the server name (SNI)
+    #[serde(default)]
+    virtual_hosts: Vec<VirtualHostConfig>,
+    /// Refuse clients whose server name matches no virtual host
+    #[serde(default)]
+    strict_sni: bool,
+}
+
+/// Endpoint on a TLS listener with its own certificate and policy
+#[derive(Debug, Deserialize, Clone)]
+struct VirtualHostConfig {
+    id: String,
+    server_names: Vec<String>,
+    cert_path: String,
+    key_path: String,
+    #[serde(default)]
+    client_auth: Option<ClientAuthConfig>,
+    #[serde(default)]
+    policy: EndpointPolicy,
 }
 
 /// Listener configuration for incoming TAK connections
@@ -126,8 +341,65 @@ struct ListenerConfig {
     max_connections: usize,
     #[serde(default)]
     tls: Option<ListenerTlsConfig>,
+    /// Expect zstd-compressed frames from other OmniTAK instances
+    #[serde(default)]
+    compression: bool,
+    /// Quarantine messages from clients that fail CoT schema validation
+    #[serde(default)]
+    strict_validation: bool,
+}
+
+/// In-place upgrade (socket handover) configuration
+#[derive(Debug, Deserialize)]
+struct UpgradeConfig {
+    /// Re-execute the binary on SIGUSR2, handing over listening sockets
+    #[serde(default = "default_upgrade_enabled")]
+    enabled: bool,
+    /// How long to wait for the new process to report ready
+    #[serde(default = "default_upgrade_ready_timeout_secs")]
+    ready_timeout_secs: u64,
+    /// How long to let existing client connections finish before exiting
+    #[serde(default = "default_upgrade_drain_timeout_secs")]
+    drain_timeout_secs: u64,
+}
+
+impl Default for UpgradeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_upgrade_enabled(),
+            ready_timeout_secs: default_upgrade_ready_timeout_secs(),
+            drain_timeout_secs: default_upgrade_drain_timeout_secs(),
+        }
+    }
+}
+
+fn default_upgrade_enabled() -> bool {
+    true
+}
+
+fn default_upgrade_ready_timeout_secs() -> u64 {
+    30
+}
+
+fn default_upgrade_drain_timeout_secs() -> u64 {
+    60
+}
+
+/// State passed from an upgrading process to its successor
+#[derive(Debug, Serialize, Deserialize)]
+struct HandoffState {
+    servers: Vec<TakServerDef>,
+    /// Connections created through the API
+    #[serde(default)]
+    api_connections: Vec<omnitak_api::HandoffConnection>,
 }
 
+/// Socket ID used for the API listener during handover
+const API_LISTENER_ID: &str = "omnitak-api";
+
+/// Socket ID used for the load balancer agent during handover
+const LB_AGENT_LISTENER_ID: &str = "omnitak-lb-agent";
+
 fn default_listener_enabled() -> bool {
     true
 }
@@ -141,6 +413,17 @@ impl Default for ApiConfig {
         Self {
             bind_addr: default_bind_addr(),
             enable_tls: default_enable_tls(),
+            lb_agent_addr: None,
+            users: Vec::new(),
+            tenant_users: Vec::new(),
+            plugins: Default::default(),
+            rate_limit: Default::default(),
+            jwt_expiration: default_jwt_expiration(),
+            refresh_expiration: default_refresh_expiration(),
+            roles: Vec::new(),
+            audit: Default::default(),
+            token_store: None,
+            trusted_proxies: Vec::new(),
         }
     }
 }
@@ -153,6 +436,35 @@ fn default_enable_tls() -> bool {
     false
 }
 
+fn default_jwt_expiration() -> i64 {
+    86400
+}
+
+fn default_refresh_expiration() -> i64 {
+    7 * 86400
+}
+
+/// Enabled listeners, as offered for onboarding data packages
+fn listener_endpoints(listeners: &[ListenerConfig]) -> Vec<omnitak_api::ListenerEndpoint> {
+    listeners
+        .iter()
+        .filter(|l| l.enabled)
+        .filter_map(|l| {
+            let port = l.bind_addr.parse::<SocketAddr>().ok()?.port();
+            let tls = l.tls.as_ref().filter(|_| l.protocol == ListenerProtocol::Tls);
+            Some(omnitak_api::ListenerEndpoint {
+                id: l.id.clone(),
+                port,
+                tls: tls.is_some(),
+                client_auth: tls
+                    .and_then(|t| t.client_auth.as_ref())
+                    .is_some_and(|a| a.required),
+                cert_path: tls.map(|t| PathBuf::from(&t.cert_path)),
+            })
+        })
+        .collect()
+}
+
 /// Validates listener configuration
 fn validate_listeners(listeners: &[ListenerConfig]) -> Result<()> {
     // Check if no listeners are enabled
@@ -204,17 +516,27 @@ fn validate_listeners(listeners: &[ListenerConfig]) -> Result<()> {
             validate_file_exists(&tls_config.key_path, &format!("TLS key for listener '{}'", listener.id))?;
 
             // Validate client auth CA if required
-            if let Some(ref client_auth) = tls_config.client_auth {
-                if client_auth.required {
-                    if let Some(ref ca_path) = client_auth.ca_path {
-                        validate_file_exists(ca_path, &format!("Client CA certificate for listener '{}'", listener.id))?;
-                    } else {
-                        anyhow::bail!(
-                            "Listener '{}' requires client authentication but 'ca_path' is not specified",
-                            listener.id
-                        );
+            validate_client_auth(tls_config.client_auth.as_ref(), &format!("listener '{}'", listener.id))?;
+
+            // Validate virtual hosts
+            let mut seen_hosts = HashSet::new();
+            let mut seen_names = HashSet::new();
+            for host in &tls_config.virtual_hosts {
+                let what = format!("virtual host '{}' of listener '{}'", host.id, listener.id);
+                if !seen_hosts.insert(&host.id) {
+                    anyhow::bail!("Duplicate {}", what);
+                }
+                if host.server_names.is_empty() {
+                    anyhow::bail!("No server_names for {}", what);
+                }
+                for name in &host.server_names {
+                    if !seen_names.insert(name.to_ascii_lowercase()) {
+                        anyhow::bail!("Server name '{}' of {} is already used by another virtual host", name, what);
                     }
                 }
+                validate_file_exists(&host.cert_path, &format!("TLS certificate for {}", what))?;
+                validate_file_exists(&host.key_path, &format!("TLS key for {}", what))?;
+                validate_client_auth(host.client_auth.as_ref(), &what)?;
             }
         } else if listener.tls.is_some() {
             warn!(
@@ -241,6 +563,20 @@ fn validate_listeners(listeners: &[ListenerConfig]) -> Result<()> {
     Ok(())
 }
 
+/// Validates the CA of a TLS endpoint that requires client certificates
+fn validate_client_auth(client_auth: Option<&ClientAuthConfig>, what: &str) -> Result<()> {
+    let Some(client_auth) = client_auth.filter(|a| a.required) else {
+        return Ok(());
+    };
+    match &client_auth.ca_path {
+        Some(ca_path) => validate_file_exists(ca_path, &format!("Client CA certificate for {}", what)),
+        None => anyhow::bail!(
+            "{} requires client authentication but 'ca_path' is not specified",
+            what
+        ),
+    }
+}
+
 /// Helper function to validate that a file exists
 fn validate_file_exists(path: &str, description: &str) -> Result<()> {
     let path_buf = PathBuf::from(path);
@@ -250,6 +586,135 @@ fn validate_file_exists(path: &str, description: &str) -> Result<()> {
     Ok(())
 }
 
+/// Reads the configuration file, resolving `${VAR}` and `secret://` references
+fn load_config(path: &Path) -> Result<Config> {
+    let config_content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {:?}", path))?;
+
+    let value = omnitak_core::secrets::resolve_yaml(&config_content)
+        .context("Failed to load config file")?;
+    serde_yaml::from_value(value).context("Failed to parse config file")
+}
+
+/// Checks a configuration beyond what parsing catches, without starting anything
+fn validate_config(config: &Config) -> Result<()> {
+    config
+        .api
+        .bind_addr
+        .parse::<SocketAddr>()
+        .with_context(|| format!("Invalid API bind address: {}", config.api.bind_addr))?;
+
+    validate_listeners(&config.listeners)?;
+    config
+        .client_access
+        .validate()
+        .context("Invalid client_access rule")?;
+    config.pool.validate()?;
+    config.distributor.validate()?;
+    config.aggregator.validate()?;
+    config.metrics.validate()?;
+    config.filters.validate()?;
+    omnitak_filter::FieldRuleFilter::new(&config.filters)?;
+    config.runtime.validate()?;
+
+    let mut ids = HashSet::new();
+    for server in &config.servers {
+        if !ids.insert(server.id.as_str()) {
+            anyhow::bail!("Duplicate server ID: {}", server.id);
+        }
+        let (host, port) = server
+            .address
+            .rsplit_once(':')
+            .with_context(|| format!("Server '{}' address must be host:port", server.id))?;
+        if host.is_empty() || port.parse::<u16>().is_err() {
+            anyhow::bail!("Server '{}' has an invalid address: {}", server.id, server.address);
+        }
+        match server.protocol.to_lowercase().as_str() {
+            "tcp" => {}
+            "tls" => {
+                let tls = server.tls.as_ref().with_context(|| {
+                    format!("Server '{}' uses TLS but has no tls section", server.id)
+                })?;
+                validate_file_exists(&tls.cert_path, "Certificate")?;
+                validate_file_exists(&tls.key_path, "Private key")?;
+                if let Some(ref ca_path) = tls.ca_path {
+                    validate_file_exists(ca_path, "CA certificate")?;
+                }
+            }
+            other => anyhow::bail!("Server '{}' has unsupported protocol: {}", server.id, other),
+        }
+        if server.compression && server.format == WireFormat::Protobuf {
+            anyhow::bail!(
+                "Server '{}' cannot combine compression with the protobuf format",
+                server.id
+            );
+        }
+    }
+
+    let mut usernames = HashSet::new();
+    let all_users = config
+        .api
+        .users
+        .iter()
+        .map(|u| &u.username)
+        .chain(config.api.tenant_users.iter().map(|u| &u.username));
+    for username in all_users {
+        if !usernames.insert(username) {
+            anyhow::bail!("Duplicate API user: {}", username);
+        }
+    }
+
+    omnitak_api::rbac::validate_roles(&config.api.roles).map_err(anyhow::Error::msg)?;
+    omnitak_api::middleware::TrustedProxies::parse(&config.api.trusted_proxies)
+        .map_err(anyhow::Error::msg)?;
+    let custom_roles = config
+        .api
+        .users
+        .iter()
+        .map(|u| (&u.username, &u.custom_role))
+        .chain(
+            config
+                .api
+                .tenant_users
+                .iter()
+                .map(|u| (&u.username, &u.custom_role)),
+        );
+    for (username, custom_role) in custom_roles {
+        if let Some(role) = custom_role {
+            if !config.api.roles.iter().any(|r| &r.name == role) {
+                anyhow::bail!("API user '{}' has undefined role '{}'", username, role);
+            }
+        }
+    }
+
+    if let Some(routing) = &config.routing {
+        routing.validate().context("Invalid routing configuration")?;
+    }
+
+    if let Some(siem) = &config.siem {
+        siem.validate().context("Invalid SIEM configuration")?;
+        let files = [
+            (&siem.ca_path, "SIEM CA certificate"),
+            (&siem.cert_path, "SIEM client certificate"),
+            (&siem.key_path, "SIEM client key"),
+        ];
+        for (path, description) in files {
+            if let Some(path) = path {
+                validate_file_exists(&path.to_string_lossy(), description)?;
+            }
+        }
+    }
+
+    config
+        .logging
+        .log_levels()
+        .validate_levels()
+        .map_err(anyhow::Error::msg)
+        .context("Invalid logging configuration")?;
+
+    Ok(())
+}
+
 /// Convert config listener to server_listener format
 fn convert_listener_config(config: &ListenerConfig) -> ServerListenerConfig {
     ServerListenerConfig {
@@ -264,11 +729,31 @@ fn convert_listener_config(config: &ListenerConfig) -> ServerListenerConfig {
         tls: config.tls.as_ref().map(|tls| ServerTlsListenerConfig {
             cert_path: tls.cert_path.clone(),
             key_path: tls.key_path.clone(),
-            client_auth: tls.client_auth.as_ref().map(|ca| ServerClientAuthConfig {
-                required: ca.required,
-                ca_path: ca.ca_path.clone().unwrap_or_default(),
-            }),
+            client_auth: tls.client_auth.as_ref().map(convert_client_auth),
+            policy: tls.policy,
+            virtual_hosts: tls
+                .virtual_hosts
+                .iter()
+                .map(|host| ServerVirtualHostConfig {
+                    id: host.id.clone(),
+                    server_names: host.server_names.clone(),
+                    cert_path: host.cert_path.clone(),
+                    key_path: host.key_path.clone(),
+                    client_auth: host.client_auth.as_ref().map(convert_client_auth),
+                    policy: host.policy,
+                })
+                .collect(),
+            strict_sni: tls.strict_sni,
         }),
+        compression: config.compression,
+        strict_validation: config.strict_validation,
+    }
+}
+
+fn convert_client_auth(config: &ClientAuthConfig) -> ServerClientAuthConfig {
+    ServerClientAuthConfig {
+        required: config.required,
+        ca_path: config.ca_path.clone().unwrap_or_default(),
     }
 }
 
@@ -308,26 +793,543 @@ impl ConnectionMetrics {
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize tracing (ignore if already initialized)
-    let _ = tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .try_init();
+/// Shared handles for outbound TAK server connections
+#[derive(Clone)]
+struct ConnectionContext {
+    pool: Arc<ConnectionPool>,
+    aggregator: Arc<MessageAggregator>,
+    distributor: Arc<MessageDistributor>,
+    events: events::EventBus,
+    metrics: Arc<ConnectionMetrics>,
+}
+
+/// Connect to a TAK server and bridge it into the pool
+///
+/// The returned task ends when the connection closes, or when it is
+/// removed from the pool.
+fn spawn_server_connection(
+    server_def: &TakServerDef,
+    ctx: &ConnectionContext,
+) -> Option<tokio::task::JoinHandle<()>> {
+    info!(
+        "Connecting to TAK server: {} at {}",
+        server_def.id, server_def.address
+    );
+
+    if server_def.protocol.to_lowercase() == "tcp" {
+        // Create TCP client
+        let mut client_config = TcpClientConfig::default();
+        client_config.base.server_addr = server_def.address.clone();
+        if server_def.compression {
+            client_config.base.compression = Compression::zstd();
+            client_config.framing = TcpFramingMode::LengthPrefixed;
+        }
+        if server_def.format == WireFormat::Protobuf {
+            // The distributor sends complete protobuf frames; write them as-is
+            client_config.framing = TcpFramingMode::Xml;
+        }
+
+        // Clone for the async task
+        let address = server_def.address.clone();
+        let server_id = server_def.id.clone();
+        let format = server_def.format;
+        let strict_validation = server_def.strict_validation;
+        let metrics = ctx.metrics.clone();
+        let pool_clone = Arc::clone(&ctx.pool);
+        let aggregator_clone = Arc::clone(&ctx.aggregator);
+        let distributor_clone = Arc::clone(&ctx.distributor);
+        let events = ctx.events.clone();
+
+        let mut client = TcpClient::new(client_config);
+        Some(tokio::spawn(async move {
+            info!("Connecting TCP client to {} ({})", address, server_id);
+            if let Err(e) = client.connect().await {
+                error!("Failed to connect to TAK server {}: {}", server_id, e);
+                events.publish(events::SystemEvent::ConnectionDown {
+                    connection_id: format!("tak-server-{}", server_id),
+                    address: address.clone(),
+                    reason: Some(e.to_string()),
+                });
+            } else {
+                info!("Successfully connected to TAK server: {}", server_id);
+
+                // ═══════════════════════════════════════════════════════════════
+                // CONNECTION POOL INTEGRATION POINT
+                // ═══════════════════════════════════════════════════════════════
+
+                // Register this connection with the pool
+                let connection_id = format!("tak-server-{}", server_id);
+                match pool_clone
+                    .add_connection(
+                        connection_id.clone(),
+                        server_id.clone(),
+                        address.clone(),
+                        5, // Default priority
+                    )
+                    .await
+                {
+                    Ok(_) => {
+                        info!("[{}] Registered with connection pool", server_id);
+                        pool_clone.set_wire_format(connection_id.clone(), format);
+                        aggregator_clone
+                            .quarantine()
+                            .set_strict(connection_id.clone(), strict_validation);
+                        events.publish(events::SystemEvent::ConnectionUp {
+                            connection_id: connection_id.clone(),
+                            address: address.clone(),
+                        });
+
+                        // Set filter to broadcast to all connections (default behavior)
+                        distributor_clone.add_filter(
+                            connection_id.clone(),
+                            FilterRule::AlwaysSend,
+                        );
+                    }
+                    Err(e) => {
+                        error!("[{}] Failed to register with pool: {}", server_id, e);
+                        return;
+                    }
+                }
+
+                // Get the connection's rx channel for receiving distributed messages
+                let connection = match pool_clone.get_connection(&connection_id) {
+                    Some(conn) => conn,
+                    None => {
+                        error!("[{}] Failed to get connection from pool", server_id);
+                        return;
+                    }
+                };
+
+                // ═══════════════════════════════════════════════════════════════
+                // BIDIRECTIONAL MESSAGE FLOW
+                // ═══════════════════════════════════════════════════════════════
+
+                // Task 1: Receive messages FROM TAK server → Aggregator
+                let mut rx_from_server = client.receive_cot();
+                let aggregator_sender = aggregator_clone.sender();
+                let metrics_clone = metrics.clone();
+                let server_id_clone = server_id.clone();
+                let connection_id_recv = connection_id.clone();
+
+                let recv_task = tokio::spawn(async move {
+                    while let Some(result) = rx_from_server.next().await {
+                        match result {
+                            Ok(msg) => {
+                                metrics_clone.record_message(msg.data.len());
+                                debug!(
+                                    "[{}] Received {} bytes from TAK server",
+                                    server_id_clone,
+                                    msg.data.len()
+                                );
+
+                                // Feed into aggregator for deduplication
+                                // Convert Bytes to Vec<u8>
+                                let inbound_msg = InboundMessage {
+                                    data: msg.data.to_vec(),
+                                    source: connection_id_recv.clone(),
+                                    timestamp: Instant::now(),
+                                    trace_id: TraceId::new(),
+                                };
 
+                                if let Err(e) = aggregator_sender.send_async(inbound_msg).await {
+                                    error!(
+                                        "[{}] Failed to send to aggregator: {}",
+                                        server_id_clone, e
+                                    );
+                                    break;
+                                }
+                            }
+                            Err(e) => {
+                                metrics_clone.record_error();
+                                warn!(
+                                    "[{}] Error receiving message: {}",
+                                    server_id_clone, e
+                                );
+                                break;
+                            }
+                        }
+                    }
+                    warn!("[{}] Receive task ended", server_id_clone);
+                });
+
+                // Task 2: Receive messages FROM pool → TAK server
+                let rx_from_pool = connection.rx.clone();
+                let write_state = Arc::clone(&connection.state);
+                let server_id_clone = server_id.clone();
+
+                let send_task = tokio::spawn(async move {
+                    while let Ok(pool_msg) = rx_from_pool.recv_async().await {
+                        match pool_msg {
+                            PoolMessage::Cot(data) => {
+                                debug!(
+                                    "[{}] Sending {} bytes to TAK server",
+                                    server_id_clone,
+                                    data.len()
+                                );
+
+                                // Send to TAK server - convert Vec<u8> back to CotMessage
+                                let cot_msg = CotMessage {
+                                    data: Bytes::from(data),
+                                    metadata: None,
+                                };
+                                let started = std::time::Instant::now();
+                                if let Err(e) = client.send_cot(cot_msg).await {
+                                    error!(
+                                        "[{}] Failed to send to TAK server: {}",
+                                        server_id_clone, e
+                                    );
+                                    break;
+                                }
+                                write_state.record_write_latency(started.elapsed());
+                            }
+                            PoolMessage::Ping => {
+                                debug!("[{}] Received ping", server_id_clone);
+                                // Health check - no action needed
+                            }
+                            PoolMessage::Shutdown => {
+                                info!("[{}] Received shutdown signal", server_id_clone);
+                                break;
+                            }
+                        }
+                    }
+                    warn!("[{}] Send task ended", server_id_clone);
+                    let _ = client.disconnect().await;
+                });
+
+                // Wait for either task to complete
+                tokio::select! {
+                    _ = recv_task => {
+                        info!("[{}] Connection receive task completed", server_id);
+                    }
+                    _ = send_task => {
+                        info!("[{}] Connection send task completed", server_id);
+                    }
+                }
+
+                // Clean up: remove from pool
+                if let Err(e) = pool_clone.remove_connection(&connection_id).await {
+                    warn!("[{}] Failed to remove from pool: {}", server_id, e);
+                }
+
+                warn!("Connection closed to TAK server: {}", server_id);
+                events.publish(events::SystemEvent::ConnectionDown {
+                    connection_id,
+                    address,
+                    reason: None,
+                });
+            }
+        }))
+    } else if server_def.protocol.to_lowercase() == "tls" {
+        if let Some(ref tls_config) = server_def.tls {
+            // Create TLS client
+            let cert_path = PathBuf::from(&tls_config.cert_path);
+            let key_path = PathBuf::from(&tls_config.key_path);
+
+            let mut client_config = TlsClientConfig::new(cert_path, key_path);
+            if let Some(ref ca_path) = tls_config.ca_path {
+                client_config = client_config.with_ca_cert(PathBuf::from(ca_path));
+            }
+            client_config.base.server_addr = server_def.address.clone();
+            client_config.verify_server = tls_config.verify_server;
+            if server_def.compression {
+                client_config.base.compression = Compression::zstd();
+                client_config.framing = TlsFramingMode::LengthPrefixed;
+            }
+
+            // Clone for the async task
+            let address = server_def.address.clone();
+            let server_id = server_def.id.clone();
+            let format = server_def.format;
+            let strict_validation = server_def.strict_validation;
+            let metrics = ctx.metrics.clone();
+            let pool_clone = Arc::clone(&ctx.pool);
+            let aggregator_clone = Arc::clone(&ctx.aggregator);
+            let distributor_clone = Arc::clone(&ctx.distributor);
+            let events = ctx.events.clone();
+
+            match TlsClient::new(client_config) {
+                Ok(mut client) => {
+                    Some(tokio::spawn(async move {
+                        info!("Connecting TLS client to {} ({})", address, server_id);
+                        if let Err(e) = client.connect().await {
+                            error!("Failed to connect to TAK server {}: {}", server_id, e);
+                            events.publish(events::SystemEvent::ConnectionDown {
+                                connection_id: format!("tak-server-{}", server_id),
+                                address: address.clone(),
+                                reason: Some(e.to_string()),
+                            });
+                        } else {
+                            info!("Successfully connected to TAK server: {}", server_id);
+
+                            // ═══════════════════════════════════════════════════════════════
+                            // CONNECTION POOL INTEGRATION POINT (TLS)
+                            // ═══════════════════════════════════════════════════════════════
+
+                            // Register this connection with the pool
+                            let connection_id = format!("tak-server-{}", server_id);
+                            match pool_clone
+                                .add_connection(
+                                    connection_id.clone(),
+                                    server_id.clone(),
+                                    address.clone(),
+                                    5, // Default priority
+                                )
+                                .await
+                            {
+                                Ok(_) => {
+                                    info!("[{}] Registered with connection pool", server_id);
+                                    pool_clone.set_wire_format(connection_id.clone(), format);
+                                    aggregator_clone
+                                        .quarantine()
+                                        .set_strict(connection_id.clone(), strict_validation);
+                                    events.publish(events::SystemEvent::ConnectionUp {
+                                        connection_id: connection_id.clone(),
+                                        address: address.clone(),
+                                    });
+
+                                    // Set filter to broadcast to all connections
+                                    distributor_clone.add_filter(
+                                        connection_id.clone(),
+                                        FilterRule::AlwaysSend,
+                                    );
+                                }
+                                Err(e) => {
+                                    error!("[{}] Failed to register with pool: {}", server_id, e);
+                                    return;
+                                }
+                            }
+
+                            // Get the connection's rx channel
+                            let connection = match pool_clone.get_connection(&connection_id) {
+                                Some(conn) => conn,
+                                None => {
+                                    error!("[{}] Failed to get connection from pool", server_id);
+                                    return;
+                                }
+                            };
+
+                            // ═══════════════════════════════════════════════════════════════
+                            // BIDIRECTIONAL MESSAGE FLOW (TLS)
+                            // ═══════════════════════════════════════════════════════════════
+
+                            // Task 1: Receive messages FROM TAK server → Aggregator
+                            let mut rx_from_server = client.receive_cot();
+                            let aggregator_sender = aggregator_clone.sender();
+                            let metrics_clone = metrics.clone();
+                            let server_id_clone = server_id.clone();
+                            let connection_id_recv = connection_id.clone();
+
+                            let recv_task = tokio::spawn(async move {
+                                while let Some(result) = rx_from_server.next().await {
+                                    match result {
+                                        Ok(msg) => {
+                                            metrics_clone.record_message(msg.data.len());
+                                            debug!(
+                                                "[{}] Received {} bytes from TAK server",
+                                                server_id_clone,
+                                                msg.data.len()
+                                            );
+
+                                            // Feed into aggregator for deduplication
+                                            // Convert Bytes to Vec<u8>
+                                            let inbound_msg = InboundMessage {
+                                                data: msg.data.to_vec(),
+                                                source: connection_id_recv.clone(),
+                                                timestamp: Instant::now(),
+                                                trace_id: TraceId::new(),
+                                            };
+
+                                            if let Err(e) = aggregator_sender.send_async(inbound_msg).await {
+                                                error!(
+                                                    "[{}] Failed to send to aggregator: {}",
+                                                    server_id_clone, e
+                                                );
+                                                break;
+                                            }
+                                        }
+                                        Err(e) => {
+                                            metrics_clone.record_error();
+                                            warn!(
+                                                "[{}] Error receiving message: {}",
+                                                server_id_clone, e
+                                            );
+                                            break;
+                                        }
+                                    }
+                                }
+                                warn!("[{}] Receive task ended", server_id_clone);
+                            });
+
+                            // Task 2: Receive messages FROM pool → TAK server
+                            let rx_from_pool = connection.rx.clone();
+                            let write_state = Arc::clone(&connection.state);
+                            let server_id_clone = server_id.clone();
+
+                            let send_task = tokio::spawn(async move {
+                                while let Ok(pool_msg) = rx_from_pool.recv_async().await {
+                                    match pool_msg {
+                                        PoolMessage::Cot(data) => {
+                                            debug!(
+                                                "[{}] Sending {} bytes to TAK server",
+                                                server_id_clone,
+                                                data.len()
+                                            );
+
+                                            // Send to TAK server - convert Vec<u8> back to CotMessage
+                                            let cot_msg = CotMessage {
+                                                data: Bytes::from(data),
+                                                metadata: None,
+                                            };
+                                            let started = std::time::Instant::now();
+                                            if let Err(e) = client.send_cot(cot_msg).await {
+                                                error!(
+                                                    "[{}] Failed to send to TAK server: {}",
+                                                    server_id_clone, e
+                                                );
+                                                break;
+                                            }
+                                            write_state.record_write_latency(started.elapsed());
+                                        }
+                                        PoolMessage::Ping => {
+                                            debug!("[{}] Received ping", server_id_clone);
+                                            // Health check - no action needed
+                                        }
+                                        PoolMessage::Shutdown => {
+                                            info!("[{}] Received shutdown signal", server_id_clone);
+                                            break;
+                                        }
+                                    }
+                                }
+                                warn!("[{}] Send task ended", server_id_clone);
+                                let _ = client.disconnect().await;
+                            });
+
+                            // Wait for either task to complete
+                            tokio::select! {
+                                _ = recv_task => {
+                                    info!("[{}] Connection receive task completed", server_id);
+                                }
+                                _ = send_task => {
+                                    info!("[{}] Connection send task completed", server_id);
+                                }
+                            }
+
+                            // Clean up: remove from pool
+                            if let Err(e) = pool_clone.remove_connection(&connection_id).await {
+                                warn!("[{}] Failed to remove from pool: {}", server_id, e);
+                            }
+
+                            warn!("Connection closed to TAK server: {}", server_id);
+                            events.publish(events::SystemEvent::ConnectionDown {
+                                connection_id,
+                                address,
+                                reason: None,
+                            });
+                        }
+                    }))
+                }
+                Err(e) => {
+                    error!("Failed to create TLS client for {}: {}", server_id, e);
+                    None
+                }
+            }
+        } else {
+            error!("TAK server {} uses TLS but has no tls settings", server_def.id);
+            None
+        }
+    } else {
+        error!(
+            "Unknown protocol '{}' for TAK server {}",
+            server_def.protocol, server_def.id
+        );
+        None
+    }
+}
+
+fn main() -> Result<()> {
     // Parse command line arguments
-    let args = Args::parse();
+    let cli = Cli::parse();
+    match cli.command {
+        None => serve(cli.serve, cli.config),
+        Some(cli::Command::Serve(args)) => serve(args, cli.config),
+        // Started by the Windows Service Control Manager
+        Some(cli::Command::Service(cli::ServiceAction::Run)) => {
+            service::run_as_service(move || serve(cli.serve, cli.config))
+        }
+        Some(command) => {
+            let _ = tracing_subscriber::fmt()
+                .with_max_level(tracing::Level::WARN)
+                .with_writer(std::io::stderr)
+                .try_init();
+            cli::run(command, &cli.config)
+        }
+    }
+}
 
+/// Run the server until it is shut down
+fn serve(args: ServeArgs, config_path: PathBuf) -> Result<()> {
     // Load configuration file
-    let config_content = fs::read_to_string(&args.config)
-        .with_context(|| format!("Failed to read config file: {:?}", args.config))?;
+    let config = load_config(&config_path)?;
+
+    // Log to stdout and/or a rotating file at the configured levels
+    let log_control = logging::init(&config.logging)?;
+
+    config
+        .runtime
+        .validate()
+        .context("Invalid runtime configuration")?;
+    let pipeline_runtime =
+        runtime::pipeline(&config.runtime).context("Failed to start pipeline runtime")?;
+    let api_runtime = runtime::api(&config.runtime).context("Failed to start API runtime")?;
+    let api_handle = api_runtime.as_ref().map(|rt| rt.handle().clone());
+
+    let result =
+        pipeline_runtime.block_on(run(args, config_path, config, log_control, api_handle));
+
+    // Runtimes can't be dropped from async context, so the API runtime
+    // outlives the pipeline's block_on
+    if let Some(api_runtime) = api_runtime {
+        api_runtime.shutdown_timeout(Duration::from_secs(5));
+    }
+    result
+}
 
-    let config: Config =
-        serde_yaml::from_str(&config_content).context("Failed to parse config file")?;
+async fn run(
+    args: ServeArgs,
+    config_path: PathBuf,
+    config: Config,
+    log_control: omnitak_api::LogLevelControl,
+    api_runtime: Option<tokio::runtime::Handle>,
+) -> Result<()> {
+    // Tasks serving the API go to its own runtime when configured
+    let api_runtime = api_runtime.unwrap_or_else(tokio::runtime::Handle::current);
+
+    // Pick up sockets handed over by a previous process (in-place upgrade)
+    let mut inherited = upgrade::Inherited::from_env();
+
+    // Held until shutdown; an upgrade successor takes over its predecessor's
+    let _pidfile = args
+        .pidfile
+        .as_deref()
+        .map(|path| service::Pidfile::create(path, inherited.is_upgrade()))
+        .transpose()?;
 
     // Validate listener configuration
     validate_listeners(&config.listeners)?;
 
+    // On upgrade, continue with the connection definitions the previous process was running
+    let (mut servers, restored_connections) = match inherited.load_state::<HandoffState>()? {
+        Some(state) => {
+            info!(
+                "Loaded {} server definition(s) and {} API connection(s) from upgrade handoff",
+                state.servers.len(),
+                state.api_connections.len()
+            );
+            (state.servers, state.api_connections)
+        }
+        None => (config.servers.clone(), Vec::new()),
+    };
+
     // Log listener configuration
     let enabled_listeners: Vec<_> = config.listeners.iter().filter(|l| l.enabled).collect();
     if !enabled_listeners.is_empty() {
@@ -362,14 +1364,23 @@ async fn main() -> Result<()> {
         enable_tls: config.api.enable_tls,
         tls_cert_path: None,
         tls_key_path: None,
-        auth_config: Default::default(),
-        rate_limit_rps: 100,
+        auth_config: omnitak_api::auth::AuthConfig {
+            jwt_expiration: chrono::Duration::seconds(config.api.jwt_expiration),
+            refresh_expiration: chrono::Duration::seconds(config.api.refresh_expiration),
+            roles: config.api.roles.clone(),
+            token_store: config.api.token_store.clone(),
+            ..Default::default()
+        },
+        rate_limit: config.api.rate_limit.clone(),
+        trusted_proxies: config.api.trusted_proxies.clone(),
         enable_swagger: true,
         enable_static_files: true,
         enable_enrollment: true,
         enrollment_ca_cert_path: None,
         enrollment_ca_key_path: None,
         enrollment_server_config: None,
+        time_sync: config.time_sync.clone(),
+        marti: config.marti.clone(),
     };
 
     // ═══════════════════════════════════════════════════════════════════════════
@@ -378,51 +1389,283 @@ async fn main() -> Result<()> {
     info!("Initializing connection pool infrastructure...");
 
     // Create connection pool
-    let pool_config = PoolConfig {
-        max_connections: 1000,
-        channel_capacity: 1000,
-        health_check_interval: Duration::from_secs(30),
-        inactive_timeout: Duration::from_secs(300),
-        auto_reconnect: true,
-    };
-    let pool = Arc::new(ConnectionPool::new(pool_config));
-    info!("Connection pool initialized (max: 1000 connections)");
-
-    // Create message distributor with 16 worker threads
-    let distributor_config = DistributorConfig {
-        channel_capacity: 10_000,
-        strategy: omnitak_pool::DistributionStrategy::DropOnFull,
-        max_workers: 16,
-        batch_size: 100,
-        flush_interval: Duration::from_millis(10),
-    };
-    let distributor = Arc::new(MessageDistributor::new(
-        Arc::clone(&pool),
-        distributor_config,
+    let pool = Arc::new(ConnectionPool::new(PoolConfig::from(&config.pool)));
+    info!(
+        "Connection pool initialized (max: {} connections)",
+        config.pool.max_connections
+    );
+
+    // Create message distributor
+    let distributor_config = DistributorConfig::from(&config.distributor);
+    let mut distributor = MessageDistributor::new(Arc::clone(&pool), distributor_config);
+    // Transformer plugins loaded through the API run on all traffic, unless
+    // a route assigns them to its destinations
+    let transformers = Arc::new(omnitak_pool::TransformPipeline::new(
+        config.transformers.clone(),
     ));
+    if let Some(routing) = config.routing.clone() {
+        routing.validate().context("Invalid routing configuration")?;
+        let route_plugins = routing.route_plugins();
+        info!(
+            "Routing enabled ({} routes, plugins: {:?})",
+            routing.routes.len(),
+            route_plugins
+        );
+        transformers.set_route_only(route_plugins);
+        let routes = routing
+            .into_route_table()
+            .context("Invalid routing configuration")?;
+        distributor = distributor.with_route_plugins(omnitak_pool::RoutePlugins::new(
+            Arc::new(routes),
+            Arc::clone(&transformers),
+        ));
+    }
+    // Routes can ask for smoothing without tuning it
+    let smoothing_routes = config.routing.as_ref().is_some_and(|routing| {
+        routing
+            .routes
+            .iter()
+            .any(|route| route.enabled && route.smoothing)
+    });
+    let smoothing = config
+        .smoothing
+        .clone()
+        .or_else(|| smoothing_routes.then(omnitak_pool::SmoothingConfig::default));
+    if let Some(smoothing_config) = smoothing {
+        info!(
+            "Track smoothing enabled for smoothing routes and {:?} (extrapolate: {})",
+            smoothing_config.connections, smoothing_config.extrapolate
+        );
+        let smoother = Arc::new(omnitak_pool::TrackSmoother::new(smoothing_config));
+        smoother.clone().start(Arc::clone(&pool));
+        distributor = distributor.with_smoothing(smoother);
+    }
+    if let Some(hop_config) = config.hop_limit.clone() {
+        let hop_limit = Arc::new(omnitak_pool::HopLimit::new(hop_config));
+        info!(
+            "Hop limit enabled (instance '{}', max {} hops)",
+            hop_limit.origin(),
+            hop_limit.max_hops()
+        );
+        distributor = distributor.with_hop_limit(hop_limit);
+    }
+    let distributor = Arc::new(distributor);
     distributor.start().await;
-    info!("Message distributor started (16 workers)");
+    info!(
+        "Message distributor started ({} workers, {:?} when full)",
+        config.distributor.max_workers, config.distributor.strategy
+    );
+
+    // Ship every distributed event to configured data platforms
+    for sink_def in &config.sinks {
+        match omnitak_pool::connect_sink(&sink_def.backend).await {
+            Ok(sink) => distributor.add_sink(sink, sink_def.batch.clone()),
+            Err(e) => error!("Failed to start output sink {:?}: {}", sink_def.backend, e),
+        }
+    }
+
+    // Keep the track table behind the web map delta stream
+    let track_store = Arc::new(omnitak_api::TrackStore::new());
+    distributor.add_sink(track_store.clone(), omnitak_pool::SinkBatchConfig::default());
+    track_store.clone().start();
 
     // Create message aggregator with deduplication
-    let aggregator_config = AggregatorConfig {
-        dedup_window: Duration::from_secs(60),
-        max_cache_entries: 100_000,
-        cleanup_interval: Duration::from_secs(10),
-        channel_capacity: 10_000,
-        worker_count: 4,
-    };
-    let aggregator = Arc::new(MessageAggregator::new(
+    let aggregator_config = AggregatorConfig::from(&config.aggregator);
+    let mut aggregator = MessageAggregator::new(Arc::clone(&distributor), aggregator_config)
+        .with_quarantine(Arc::new(omnitak_pool::Quarantine::new(config.quarantine.clone())));
+    if let Some(fusion_config) = config.fusion.clone() {
+        info!(
+            "Track fusion enabled ({:?}, {} m proximity)",
+            fusion_config.strategies, fusion_config.proximity_m
+        );
+        aggregator = aggregator.with_fusion(Arc::new(omnitak_pool::TrackFusion::new(fusion_config)));
+    }
+    if let Some(anomaly_config) = config.anomalies.clone() {
+        info!(
+            "Anomaly detection enabled (max {} m/s, quarantine: {})",
+            anomaly_config.max_speed_mps, anomaly_config.quarantine
+        );
+        aggregator = aggregator
+            .with_anomaly_detector(Arc::new(omnitak_pool::AnomalyDetector::new(anomaly_config)));
+    }
+    if let Some(shedding_config) = config.shedding.clone() {
+        info!(
+            "Load shedding enabled (queue {:?}, cpu {:?})",
+            shedding_config.queue, shedding_config.cpu
+        );
+        aggregator = aggregator
+            .with_load_shedder(Arc::new(omnitak_pool::LoadShedder::new(shedding_config)));
+    }
+    if let Some(coalescing_config) = config.coalescing.clone() {
+        info!(
+            "Position coalescing enabled ({} ms window)",
+            coalescing_config.window_ms
+        );
+        aggregator = aggregator
+            .with_coalescing(Arc::new(omnitak_pool::PositionCoalescer::new(coalescing_config)));
+    }
+    let filter = omnitak_filter::FieldRuleFilter::new(&config.filters)?;
+    if !filter.passes_everything() {
+        info!(
+            "Inbound filters enabled ({:?}, {} rules)",
+            config.filters.mode,
+            config.filters.rules.len()
+        );
+        aggregator = aggregator.with_filter(Arc::new(filter));
+    }
+    aggregator = aggregator.with_transformers(Arc::clone(&transformers));
+    let aggregator = Arc::new(aggregator);
+    aggregator.start().await;
+    info!(
+        "Message aggregator started ({}s dedup window, {} workers)",
+        config.aggregator.dedup_window_secs, config.aggregator.worker_count
+    );
+
+    // Report load to load balancers in front of several nodes
+    let load_monitor = Arc::new(omnitak_api::LoadMonitor::new(
+        Arc::clone(&pool),
+        Arc::clone(&aggregator),
         Arc::clone(&distributor),
-        aggregator_config,
     ));
-    aggregator.start().await;
-    info!("Message aggregator started (60s dedup window, 4 workers)");
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    let lb_agent_handover = match &config.api.lb_agent_addr {
+        Some(agent_addr) => {
+            let listener = match inherited.take_listener(LB_AGENT_LISTENER_ID) {
+                Some(socket) => socket,
+                None => std::net::TcpListener::bind(agent_addr).with_context(|| {
+                    format!("Failed to bind load balancer agent to {}", agent_addr)
+                })?,
+            };
+            let handover = listener.try_clone()?;
+            listener.set_nonblocking(true)?;
+            let listener = {
+                let _runtime = api_runtime.enter();
+                tokio::net::TcpListener::from_std(listener)?
+            };
+            api_runtime.spawn(Arc::clone(&load_monitor).serve_agent(listener));
+            Some(handover)
+        }
+        None => None,
+    };
+
+    // Convert aircraft from a dump1090/readsb feed into CoT
+    if let Some(adsb_config) = config.adsb.clone() {
+        info!(
+            "Starting ADS-B input from {} ({:?})",
+            adsb_config.address, adsb_config.format
+        );
+        adsb::spawn(adsb_config, Arc::clone(&aggregator));
+    }
+
+    // Convert vessels from an AIS NMEA feed into CoT
+    if let Some(ais_config) = config.ais.clone() {
+        info!("Starting AIS input ({:?})", ais_config.source);
+        ais::spawn(ais_config, Arc::clone(&aggregator));
+    }
+
+    // Bridge CoT to and from an MQTT broker
+    if let Some(mqtt_config) = config.mqtt.clone() {
+        info!(
+            "Starting MQTT bridge to {}:{}",
+            mqtt_config.host, mqtt_config.port
+        );
+        if let Err(e) =
+            mqtt_bridge::spawn(mqtt_config, Arc::clone(&pool), Arc::clone(&aggregator)).await
+        {
+            error!("Failed to start MQTT bridge: {:#}", e);
+        }
+    }
+
+    // Batch selected tracks into Winlink/HF email reports and import received ones
+    if let Some(hf_config) = config.hf_gateway.clone() {
+        info!("Starting Winlink/HF gateway");
+        if let Err(e) =
+            hf_gateway::spawn(hf_config, Arc::clone(&pool), Arc::clone(&aggregator)).await
+        {
+            error!("Failed to start HF gateway: {:#}", e);
+        }
+    }
 
     // Create health monitor
     let health_monitor = Arc::new(HealthMonitor::new());
     health_monitor.start(Arc::clone(&pool));
     info!("Health monitor started");
 
+    // System events (connections, circuits, certificates, filter matches)
+    // feed webhooks and alerting
+    let event_bus = events::EventBus::new();
+    let cert_paths = servers
+        .iter()
+        .filter_map(|server| server.tls.as_ref())
+        .flat_map(|tls| std::iter::once(&tls.cert_path).chain(tls.ca_path.as_ref()))
+        .chain(
+            config
+                .listeners
+                .iter()
+                .filter_map(|listener| listener.tls.as_ref())
+                .map(|tls| &tls.cert_path),
+        )
+        .map(PathBuf::from)
+        .collect();
+    let mut events_config = config.events.clone();
+    for key in config.webhooks.migrate_event_settings(&mut events_config) {
+        warn!("webhooks.{} is deprecated, set events.{} instead", key, key);
+    }
+    events::spawn_watchers(
+        &event_bus,
+        &events_config,
+        cert_paths,
+        &health_monitor,
+        Arc::clone(&distributor),
+        &aggregator.emergencies(),
+        aggregator.anomalies().as_deref(),
+    );
+    webhooks::spawn(config.webhooks.clone(), &event_bus);
+    let alert_manager = Arc::new(omnitak_api::AlertManager::new(config.alerts.clone()));
+    alerting::spawn(Arc::clone(&alert_manager), &event_bus);
+
+    // Poll FreeTAKServer REST APIs for connections configured as FTS
+    let fts_manager = Arc::new(omnitak_api::FtsManager::new(config.fts.clone()));
+    if !config.fts.servers.is_empty() {
+        info!("Polling {} FreeTAKServer REST API(s)", config.fts.servers.len());
+        Arc::clone(&fts_manager).start();
+    }
+
+    // Publish our own position (self-SA) if a position source is configured;
+    // GPS fix times also back the clock check
+    let gps_clock = Arc::new(omnitak_core::GpsClock::new());
+    if let Some(self_position_config) = config.self_position.clone() {
+        info!(
+            "Starting self-position source ({:?}) as '{}'",
+            self_position_config.source, self_position_config.callsign
+        );
+        self_position::spawn(
+            self_position_config,
+            Arc::clone(&distributor),
+            Arc::clone(&pool),
+            Arc::clone(&gps_clock),
+        );
+    }
+
+    // Exchange traffic with other OmniTAK instances
+    if let Some(federation_config) = config.federation.clone() {
+        info!(
+            "Starting federation as '{}' with {} federate(s)",
+            federation_config.instance_id,
+            federation_config.federates.len()
+        );
+        if let Err(e) = federation::spawn(
+            federation_config,
+            Arc::clone(&pool),
+            Arc::clone(&aggregator),
+            event_bus.clone(),
+        )
+        .await
+        {
+            error!("Failed to start federation: {:#}", e);
+        }
+    }
+
     // Set default filter rule: broadcast all messages to all connections
     // (The distributor already has source filtering to prevent loops)
     info!("Message distribution configured with loop prevention");
@@ -432,6 +1675,9 @@ async fn main() -> Result<()> {
     // ═══════════════════════════════════════════════════════════════════════════
     let mut tcp_listeners: Vec<ServerTcpListener> = Vec::new();
     let mut tls_listeners: Vec<ServerTlsListener> = Vec::new();
+    let client_access = Arc::new(omnitak_api::ClientAccessPolicy::new(
+        config.client_access.clone(),
+    ));
 
     for listener_config in &config.listeners {
         if !listener_config.enabled {
@@ -453,6 +1699,9 @@ async fn main() -> Result<()> {
                     Arc::clone(&pool),
                     Arc::clone(&aggregator),
                 );
+                if let Some(socket) = inherited.take_listener(&listener_config.id) {
+                    tcp_listener.set_inherited_socket(socket);
+                }
 
                 match tcp_listener.start().await {
                     Ok(_) => {
@@ -480,6 +1729,10 @@ async fn main() -> Result<()> {
                     Arc::clone(&aggregator),
                 ) {
                     Ok(mut tls_listener) => {
+                        if let Some(socket) = inherited.take_listener(&listener_config.id) {
+                            tls_listener.set_inherited_socket(socket);
+                        }
+                        tls_listener.set_client_access_policy(Arc::clone(&client_access));
                         match tls_listener.start().await {
                             Ok(_) => {
                                 info!("TLS listener '{}' started successfully", listener_config.id);
@@ -521,500 +1774,439 @@ async fn main() -> Result<()> {
     let metrics_clone = global_metrics.clone();
     let pool_clone = Arc::clone(&pool);
     let aggregator_clone = Arc::clone(&aggregator);
+    let stats_distributor = Arc::clone(&distributor);
+    let stats_interval = config.metrics.interval();
 
     // Create vectors to hold listener references for stats
     let tcp_listener_count = tcp_listeners.len();
     let tls_listener_count = tls_listeners.len();
 
-    tokio::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_secs(30));
-        let mut last_messages = 0u64;
-        let mut last_bytes = 0u64;
-
-        loop {
-            interval.tick().await;
-            let (messages, bytes, errors) = metrics_clone.snapshot();
-            let msg_delta = messages.saturating_sub(last_messages);
-            let bytes_delta = bytes.saturating_sub(last_bytes);
+    if config.metrics.enabled {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(stats_interval);
+            let mut last_messages = 0u64;
+            let mut last_bytes = 0u64;
+
+            loop {
+                interval.tick().await;
+                let (messages, bytes, errors) = metrics_clone.snapshot();
+                let msg_delta = messages.saturating_sub(last_messages);
+                let bytes_delta = bytes.saturating_sub(last_bytes);
+
+                // Get pool statistics
+                let pool_stats = pool_clone.stats();
+                let (dedup_entries, _) = aggregator_clone.cache_stats();
+
+                if msg_delta > 0 || errors > 0 {
+                    info!(
+                        "Stats: {} msgs (+{}, {:.1} msg/s), {:.2} KB (+{:.2} KB), {} errors | Pool: {} conns ({} active) | Listeners: {} active ({} TCP, {} TLS) | Dedup: {} cached",
+                        messages,
+                        msg_delta,
+                        msg_delta as f64 / stats_interval.as_secs_f64(),
+                        bytes as f64 / 1024.0,
+                        bytes_delta as f64 / 1024.0,
+                        errors,
+                        pool_stats.total_connections,
+                        pool_stats.active_connections,
+                        tcp_listener_count + tls_listener_count,
+                        tcp_listener_count,
+                        tls_listener_count,
+                        dedup_entries
+                    );
+                } else {
+                    info!(
+                        "Stats: {} msgs total, {:.2} KB total, {} errors | No activity in last {}s | Pool: {} conns | Listeners: {} | Dedup: {} cached",
+                        messages,
+                        bytes as f64 / 1024.0,
+                        errors,
+                        stats_interval.as_secs(),
+                        pool_stats.total_connections,
+                        tcp_listener_count + tls_listener_count,
+                        dedup_entries
+                    );
+                }
 
-            // Get pool statistics
-            let pool_stats = pool_clone.stats();
-            let (dedup_entries, _) = aggregator_clone.cache_stats();
+                for sink in stats_distributor.sink_stats() {
+                    info!(
+                        "Sink {}: {} sent, {} failed, {} dropped, {} queued",
+                        sink.name,
+                        sink.records_sent,
+                        sink.records_failed,
+                        sink.records_dropped,
+                        sink.queued
+                    );
+                }
 
-            if msg_delta > 0 || errors > 0 {
-                info!(
-                    "Stats: {} msgs (+{}, {:.1} msg/s), {:.2} KB (+{:.2} KB), {} errors | Pool: {} conns ({} active) | Listeners: {} active ({} TCP, {} TLS) | Dedup: {} cached",
-                    messages,
-                    msg_delta,
-                    msg_delta as f64 / 30.0,
-                    bytes as f64 / 1024.0,
-                    bytes_delta as f64 / 1024.0,
-                    errors,
-                    pool_stats.total_connections,
-                    pool_stats.active_connections,
-                    tcp_listener_count + tls_listener_count,
-                    tcp_listener_count,
-                    tls_listener_count,
-                    dedup_entries
-                );
-            } else {
-                info!(
-                    "Stats: {} msgs total, {:.2} KB total, {} errors | No activity in last 30s | Pool: {} conns | Listeners: {} | Dedup: {} cached",
-                    messages,
-                    bytes as f64 / 1024.0,
-                    errors,
-                    pool_stats.total_connections,
-                    tcp_listener_count + tls_listener_count,
-                    dedup_entries
-                );
+                last_messages = messages;
+                last_bytes = bytes;
             }
+        });
+    }
 
-            last_messages = messages;
-            last_bytes = bytes;
+    let connection_ctx = ConnectionContext {
+        pool: Arc::clone(&pool),
+        aggregator: Arc::clone(&aggregator),
+        distributor: Arc::clone(&distributor),
+        events: event_bus.clone(),
+        metrics: global_metrics.clone(),
+    };
+    // Connection tasks by server ID, for applying server changes on reload;
+    // in cluster mode the cluster decides which servers this node holds
+    let mut server_tasks: Option<ServerTasks> = match config.cluster.clone() {
+        // Only connect to the servers this node holds in the cluster
+        Some(cluster_config) => {
+            info!(
+                "Clustering as '{}' ({:?}) through {:?}",
+                cluster_config.node_id, cluster_config.mode, cluster_config.state_dir
+            );
+            let server_ids = servers.iter().map(|s| s.id.clone()).collect();
+            let server_defs: HashMap<String, TakServerDef> =
+                servers.iter().map(|s| (s.id.clone(), s.clone())).collect();
+            let ctx = connection_ctx.clone();
+            cluster::spawn(cluster_config, server_ids, Arc::clone(&pool), move |id| {
+                spawn_server_connection(server_defs.get(id)?, &ctx)
+            })?;
+            None
         }
-    });
+        None => Some(
+            servers
+                .iter()
+                .map(|server_def| {
+                    (
+                        server_def.id.clone(),
+                        spawn_server_connection(server_def, &connection_ctx),
+                    )
+                })
+                .collect(),
+        ),
+    };
 
-    for server_def in &config.servers {
-        info!(
-            "Connecting to TAK server: {} at {}",
-            server_def.id, server_def.address
-        );
+    // Build and run API server
+    info!("Starting OmniTAK API server");
+    info!("Configuration loaded from {:?}", config_path);
+    info!("Bind address: {}", bind_addr);
+    info!("TLS enabled: {}", server_config.enable_tls);
 
-        if server_def.protocol.to_lowercase() == "tcp" {
-            // Create TCP client
-            let mut client_config = TcpClientConfig::default();
-            client_config.base.server_addr = server_def.address.clone();
+    // Bind the API socket here so it can be handed over on upgrade
+    let api_listener = match inherited.take_listener(API_LISTENER_ID) {
+        Some(socket) => socket,
+        None => std::net::TcpListener::bind(bind_addr)
+            .with_context(|| format!("Failed to bind API server to {}", bind_addr))?,
+    };
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    let api_handover = api_listener.try_clone()?;
+
+    let mut builder =
+        ServerBuilder::new(server_config).with_default_user(&args.admin_user, &args.admin_password);
+    for user in &config.api.users {
+        builder = builder.with_hashed_user(
+            &user.username,
+            &user.password_hash,
+            user.role,
+            user.tenant.as_deref(),
+        );
+        if let Some(role) = &user.custom_role {
+            builder = builder.with_custom_role(&user.username, role);
+        }
+    }
+    for user in &config.api.tenant_users {
+        builder = builder.with_hashed_user(
+            &user.username,
+            &user.password_hash,
+            user.role,
+            Some(&user.tenant),
+        );
+        if let Some(role) = &user.custom_role {
+            builder = builder.with_custom_role(&user.username, role);
+        }
+    }
+    // The API's own pipeline, for traffic injected through it, is sized
+    // and filtered like the main one
+    builder = builder.with_app_config(omnitak_core::AppConfig {
+        pool: config.pool.clone(),
+        distributor: config.distributor.clone(),
+        aggregator: config.aggregator.clone(),
+        filters: config.filters.clone(),
+        metrics: config.metrics.clone(),
+        discovery: config.discovery.clone().unwrap_or_else(|| {
+            omnitak_core::config::DiscoveryConfig {
+                enabled: false,
+                ..Default::default()
+            }
+        }),
+        ..Default::default()
+    });
+    if let Some(adb_monitor) = &config.adb_monitor {
+        builder = builder.with_adb_monitor(adb_monitor.clone());
+    }
+    builder = builder.with_audit_log(config.api.audit.clone());
+    if let Some(siem) = &config.siem {
+        builder = builder.with_siem_export(siem.clone());
+    }
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    let api_connections = builder.connections();
+    let server = builder
+        .with_pipeline_runtime(tokio::runtime::Handle::current())
+        .with_restored_connections(restored_connections)
+        .with_listener(api_listener)
+        .with_alert_manager(alert_manager)
+        .with_client_access_policy(client_access)
+        .with_emergency_tracker(aggregator.emergencies())
+        .with_fts_manager(fts_manager)
+        .with_track_store(track_store)
+        .with_traffic_tap(distributor.tap())
+        .with_traffic_pool(Arc::clone(&pool))
+        .with_gps_clock(gps_clock)
+        .with_load_monitor(Arc::clone(&load_monitor))
+        .with_transform_pipeline(transformers)
+        .with_plugin_config(config.api.plugins.clone())
+        .with_listener_endpoints(listener_endpoints(&config.listeners))
+        .with_log_control(Arc::new(log_control))
+        .with_config_file(config_path.clone())
+        .build()?;
 
-            // Clone for the async task
-            let address = server_def.address.clone();
-            let server_id = server_def.id.clone();
-            let metrics = global_metrics.clone();
-            let pool_clone = Arc::clone(&pool);
-            let aggregator_clone = Arc::clone(&aggregator);
-            let distributor_clone = Arc::clone(&distributor);
-
-            let mut client = TcpClient::new(client_config);
-            tokio::spawn(async move {
-                info!("Connecting TCP client to {} ({})", address, server_id);
-                if let Err(e) = client.connect().await {
-                    error!("Failed to connect to TAK server {}: {}", server_id, e);
-                } else {
-                    info!("Successfully connected to TAK server: {}", server_id);
+    // Everything is serving; let an upgrading parent start draining
+    inherited.notify_ready();
+    service::notify_ready();
+    service::spawn_watchdog();
 
-                    // ═══════════════════════════════════════════════════════════════
-                    // CONNECTION POOL INTEGRATION POINT
-                    // ═══════════════════════════════════════════════════════════════
+    let mut shutdown_signal = service::ShutdownSignal::new()?;
+    let mut reload_signal = service::ReloadSignal::new()?;
+    let mut upgrade_signal = upgrade::UpgradeSignal::new()?;
+    let server_task = api_runtime.spawn(server.run());
+    let server_task = async { server_task.await.context("API server task failed")? };
+    tokio::pin!(server_task);
 
-                    // Register this connection with the pool
-                    let connection_id = format!("tak-server-{}", server_id);
-                    match pool_clone
-                        .add_connection(
-                            connection_id.clone(),
-                            server_id.clone(),
-                            address.clone(),
-                            5, // Default priority
-                        )
-                        .await
+    // Run server with graceful shutdown
+    loop {
+        tokio::select! {
+            result = &mut server_task => {
+                if let Err(e) = result {
+                    error!("Server error: {}", e);
+
+                    shutdown_infrastructure(
+                        &mut tcp_listeners,
+                        &mut tls_listeners,
+                        None,
+                        &health_monitor,
+                        &aggregator,
+                        &distributor,
+                        &pool,
+                    )
+                    .await;
+
+                    return Err(e);
+                }
+                break;
+            }
+            _ = shutdown_signal.recv() => {
+                info!("Received shutdown signal, stopping server...");
+                service::notify_stopping();
+                load_monitor.set_draining(true);
+
+                shutdown_infrastructure(
+                    &mut tcp_listeners,
+                    &mut tls_listeners,
+                    None,
+                    &health_monitor,
+                    &aggregator,
+                    &distributor,
+                    &pool,
+                )
+                .await;
+                break;
+            }
+            _ = reload_signal.recv() => {
+                info!("Received reload signal, re-reading {:?}", config_path);
+                service::notify_reloading();
+
+                match &mut server_tasks {
+                    Some(server_tasks) => match reload_servers(
+                        &config_path,
+                        &servers,
+                        server_tasks,
+                        &connection_ctx,
+                    )
+                    .await
                     {
-                        Ok(_) => {
-                            info!("[{}] Registered with connection pool", server_id);
-
-                            // Set filter to broadcast to all connections (default behavior)
-                            distributor_clone.add_filter(
-                                connection_id.clone(),
-                                FilterRule::AlwaysSend,
-                            );
-                        }
-                        Err(e) => {
-                            error!("[{}] Failed to register with pool: {}", server_id, e);
-                            return;
-                        }
-                    }
-
-                    // Get the connection's rx channel for receiving distributed messages
-                    let connection = match pool_clone.get_connection(&connection_id) {
-                        Some(conn) => conn,
-                        None => {
-                            error!("[{}] Failed to get connection from pool", server_id);
-                            return;
-                        }
+                        Ok(reloaded) => servers = reloaded,
+                        Err(e) => error!("Reload failed, keeping current configuration: {:#}", e),
+                    },
+                    None => warn!("Server changes are not applied on reload in cluster mode; restart instead"),
+                }
+                service::notify_ready();
+            }
+            _ = upgrade_signal.recv(), if config.upgrade.enabled => {
+                info!("Received upgrade signal, handing over to new binary...");
+
+                #[cfg(unix)]
+                {
+                    use std::os::unix::io::AsRawFd;
+
+                    let mut sockets = vec![(API_LISTENER_ID.to_string(), api_handover.as_raw_fd())];
+                    sockets.extend(
+                        lb_agent_handover
+                            .as_ref()
+                            .map(|l| (LB_AGENT_LISTENER_ID.to_string(), l.as_raw_fd())),
+                    );
+                    sockets.extend(
+                        tcp_listeners
+                            .iter()
+                            .filter_map(|l| l.handover_fd().map(|fd| (l.id().to_string(), fd))),
+                    );
+                    sockets.extend(
+                        tls_listeners
+                            .iter()
+                            .filter_map(|l| l.handover_fd().map(|fd| (l.id().to_string(), fd))),
+                    );
+
+                    let state = HandoffState {
+                        servers: servers.clone(),
+                        api_connections: api_connections.snapshot().await,
                     };
+                    let ready_timeout = Duration::from_secs(config.upgrade.ready_timeout_secs);
 
-                    // ═══════════════════════════════════════════════════════════════
-                    // BIDIRECTIONAL MESSAGE FLOW
-                    // ═══════════════════════════════════════════════════════════════
-
-                    // Task 1: Receive messages FROM TAK server → Aggregator
-                    let mut rx_from_server = client.receive_cot();
-                    let aggregator_sender = aggregator_clone.sender();
-                    let metrics_clone = metrics.clone();
-                    let server_id_clone = server_id.clone();
-                    let connection_id_recv = connection_id.clone();
-
-                    let recv_task = tokio::spawn(async move {
-                        while let Some(result) = rx_from_server.next().await {
-                            match result {
-                                Ok(msg) => {
-                                    metrics_clone.record_message(msg.data.len());
-                                    debug!(
-                                        "[{}] Received {} bytes from TAK server",
-                                        server_id_clone,
-                                        msg.data.len()
-                                    );
-
-                                    // Feed into aggregator for deduplication
-                                    // Convert Bytes to Vec<u8>
-                                    let inbound_msg = InboundMessage {
-                                        data: msg.data.to_vec(),
-                                        source: connection_id_recv.clone(),
-                                        timestamp: Instant::now(),
-                                    };
-
-                                    if let Err(e) = aggregator_sender.send_async(inbound_msg).await {
-                                        error!(
-                                            "[{}] Failed to send to aggregator: {}",
-                                            server_id_clone, e
-                                        );
-                                        break;
-                                    }
-                                }
-                                Err(e) => {
-                                    metrics_clone.record_error();
-                                    warn!(
-                                        "[{}] Error receiving message: {}",
-                                        server_id_clone, e
-                                    );
-                                    break;
-                                }
-                            }
-                        }
-                        warn!("[{}] Receive task ended", server_id_clone);
-                    });
-
-                    // Task 2: Receive messages FROM pool → TAK server
-                    let rx_from_pool = connection.rx.clone();
-                    let server_id_clone = server_id.clone();
-
-                    let send_task = tokio::spawn(async move {
-                        while let Ok(pool_msg) = rx_from_pool.recv_async().await {
-                            match pool_msg {
-                                PoolMessage::Cot(data) => {
-                                    debug!(
-                                        "[{}] Sending {} bytes to TAK server",
-                                        server_id_clone,
-                                        data.len()
-                                    );
-
-                                    // Send to TAK server - convert Vec<u8> back to CotMessage
-                                    let cot_msg = CotMessage {
-                                        data: Bytes::from(data),
-                                        metadata: None,
-                                    };
-                                    if let Err(e) = client.send_cot(cot_msg).await {
-                                        error!(
-                                            "[{}] Failed to send to TAK server: {}",
-                                            server_id_clone, e
-                                        );
-                                        break;
-                                    }
-                                }
-                                PoolMessage::Ping => {
-                                    debug!("[{}] Received ping", server_id_clone);
-                                    // Health check - no action needed
-                                }
-                                PoolMessage::Shutdown => {
-                                    info!("[{}] Received shutdown signal", server_id_clone);
-                                    break;
-                                }
-                            }
-                        }
-                        warn!("[{}] Send task ended", server_id_clone);
-                    });
+                    let handover = match upgrade::spawn_successor(&sockets, &state) {
+                        Ok(successor) => successor.wait_ready(ready_timeout).await,
+                        Err(e) => Err(e),
+                    };
 
-                    // Wait for either task to complete
-                    tokio::select! {
-                        _ = recv_task => {
-                            info!("[{}] Connection receive task completed", server_id);
+                    match handover {
+                        Ok(pid) => {
+                            info!("Successor {} is serving, draining this process", pid);
+
+                            shutdown_infrastructure(
+                                &mut tcp_listeners,
+                                &mut tls_listeners,
+                                Some(Duration::from_secs(config.upgrade.drain_timeout_secs)),
+                                &health_monitor,
+                                &aggregator,
+                                &distributor,
+                                &pool,
+                            )
+                            .await;
+                            break;
                         }
-                        _ = send_task => {
-                            info!("[{}] Connection send task completed", server_id);
+                        Err(e) => {
+                            error!("Upgrade failed, continuing with current binary: {:#}", e);
                         }
                     }
-
-                    // Clean up: remove from pool
-                    if let Err(e) = pool_clone.remove_connection(&connection_id).await {
-                        warn!("[{}] Failed to remove from pool: {}", server_id, e);
-                    }
-
-                    warn!("Connection closed to TAK server: {}", server_id);
-                }
-            });
-        } else if server_def.protocol.to_lowercase() == "tls" {
-            if let Some(ref tls_config) = server_def.tls {
-                // Create TLS client
-                let cert_path = PathBuf::from(&tls_config.cert_path);
-                let key_path = PathBuf::from(&tls_config.key_path);
-                let ca_path = PathBuf::from(&tls_config.ca_path);
-
-                let mut client_config =
-                    TlsClientConfig::new(cert_path, key_path).with_ca_cert(ca_path);
-                client_config.base.server_addr = server_def.address.clone();
-                client_config.verify_server = tls_config.verify_server;
-
-                // Clone for the async task
-                let address = server_def.address.clone();
-                let server_id = server_def.id.clone();
-                let metrics = global_metrics.clone();
-                let pool_clone = Arc::clone(&pool);
-                let aggregator_clone = Arc::clone(&aggregator);
-                let distributor_clone = Arc::clone(&distributor);
-
-                match TlsClient::new(client_config) {
-                    Ok(mut client) => {
-                        tokio::spawn(async move {
-                            info!("Connecting TLS client to {} ({})", address, server_id);
-                            if let Err(e) = client.connect().await {
-                                error!("Failed to connect to TAK server {}: {}", server_id, e);
-                            } else {
-                                info!("Successfully connected to TAK server: {}", server_id);
-
-                                // ═══════════════════════════════════════════════════════════════
-                                // CONNECTION POOL INTEGRATION POINT (TLS)
-                                // ═══════════════════════════════════════════════════════════════
-
-                                // Register this connection with the pool
-                                let connection_id = format!("tak-server-{}", server_id);
-                                match pool_clone
-                                    .add_connection(
-                                        connection_id.clone(),
-                                        server_id.clone(),
-                                        address.clone(),
-                                        5, // Default priority
-                                    )
-                                    .await
-                                {
-                                    Ok(_) => {
-                                        info!("[{}] Registered with connection pool", server_id);
-
-                                        // Set filter to broadcast to all connections
-                                        distributor_clone.add_filter(
-                                            connection_id.clone(),
-                                            FilterRule::AlwaysSend,
-                                        );
-                                    }
-                                    Err(e) => {
-                                        error!("[{}] Failed to register with pool: {}", server_id, e);
-                                        return;
-                                    }
-                                }
-
-                                // Get the connection's rx channel
-                                let connection = match pool_clone.get_connection(&connection_id) {
-                                    Some(conn) => conn,
-                                    None => {
-                                        error!("[{}] Failed to get connection from pool", server_id);
-                                        return;
-                                    }
-                                };
-
-                                // ═══════════════════════════════════════════════════════════════
-                                // BIDIRECTIONAL MESSAGE FLOW (TLS)
-                                // ═══════════════════════════════════════════════════════════════
-
-                                // Task 1: Receive messages FROM TAK server → Aggregator
-                                let mut rx_from_server = client.receive_cot();
-                                let aggregator_sender = aggregator_clone.sender();
-                                let metrics_clone = metrics.clone();
-                                let server_id_clone = server_id.clone();
-                                let connection_id_recv = connection_id.clone();
-
-                                let recv_task = tokio::spawn(async move {
-                                    while let Some(result) = rx_from_server.next().await {
-                                        match result {
-                                            Ok(msg) => {
-                                                metrics_clone.record_message(msg.data.len());
-                                                debug!(
-                                                    "[{}] Received {} bytes from TAK server",
-                                                    server_id_clone,
-                                                    msg.data.len()
-                                                );
-
-                                                // Feed into aggregator for deduplication
-                                                // Convert Bytes to Vec<u8>
-                                                let inbound_msg = InboundMessage {
-                                                    data: msg.data.to_vec(),
-                                                    source: connection_id_recv.clone(),
-                                                    timestamp: Instant::now(),
-                                                };
-
-                                                if let Err(e) = aggregator_sender.send_async(inbound_msg).await {
-                                                    error!(
-                                                        "[{}] Failed to send to aggregator: {}",
-                                                        server_id_clone, e
-                                                    );
-                                                    break;
-                                                }
-                                            }
-                                            Err(e) => {
-                                                metrics_clone.record_error();
-                                                warn!(
-                                                    "[{}] Error receiving message: {}",
-                                                    server_id_clone, e
-                                                );
-                                                break;
-                                            }
-                                        }
-                                    }
-                                    warn!("[{}] Receive task ended", server_id_clone);
-                                });
-
-                                // Task 2: Receive messages FROM pool → TAK server
-                                let rx_from_pool = connection.rx.clone();
-                                let server_id_clone = server_id.clone();
-
-                                let send_task = tokio::spawn(async move {
-                                    while let Ok(pool_msg) = rx_from_pool.recv_async().await {
-                                        match pool_msg {
-                                            PoolMessage::Cot(data) => {
-                                                debug!(
-                                                    "[{}] Sending {} bytes to TAK server",
-                                                    server_id_clone,
-                                                    data.len()
-                                                );
-
-                                                // Send to TAK server - convert Vec<u8> back to CotMessage
-                                                let cot_msg = CotMessage {
-                                                    data: Bytes::from(data),
-                                                    metadata: None,
-                                                };
-                                                if let Err(e) = client.send_cot(cot_msg).await {
-                                                    error!(
-                                                        "[{}] Failed to send to TAK server: {}",
-                                                        server_id_clone, e
-                                                    );
-                                                    break;
-                                                }
-                                            }
-                                            PoolMessage::Ping => {
-                                                debug!("[{}] Received ping", server_id_clone);
-                                                // Health check - no action needed
-                                            }
-                                            PoolMessage::Shutdown => {
-                                                info!("[{}] Received shutdown signal", server_id_clone);
-                                                break;
-                                            }
-                                        }
-                                    }
-                                    warn!("[{}] Send task ended", server_id_clone);
-                                });
-
-                                // Wait for either task to complete
-                                tokio::select! {
-                                    _ = recv_task => {
-                                        info!("[{}] Connection receive task completed", server_id);
-                                    }
-                                    _ = send_task => {
-                                        info!("[{}] Connection send task completed", server_id);
-                                    }
-                                }
-
-                                // Clean up: remove from pool
-                                if let Err(e) = pool_clone.remove_connection(&connection_id).await {
-                                    warn!("[{}] Failed to remove from pool: {}", server_id, e);
-                                }
-
-                                warn!("Connection closed to TAK server: {}", server_id);
-                            }
-                        });
-                    }
-                    Err(e) => {
-                        error!("Failed to create TLS client for {}: {}", server_id, e);
-                    }
                 }
             }
         }
     }
 
-    // Build and run API server
-    info!("Starting OmniTAK API server");
-    info!("Configuration loaded from {:?}", args.config);
-    info!("Bind address: {}", bind_addr);
-    info!("TLS enabled: {}", server_config.enable_tls);
-
-    let server = ServerBuilder::new(server_config)
-        .with_default_user(&args.admin_user, &args.admin_password)
-        .build()?;
-
-    // Run server with graceful shutdown
-    tokio::select! {
-        result = server.run() => {
-            if let Err(e) = result {
-                error!("Server error: {}", e);
-
-                // Graceful shutdown in proper order:
-                // 1. Stop listeners (no new connections)
-                // 2. Stop health monitor
-                // 3. Stop aggregator
-                // 4. Stop distributor
-                // 5. Shutdown pool (closes all connections)
-                info!("Shutting down infrastructure...");
-
-                // Stop all listeners
-                info!("Stopping {} TCP listener(s)...", tcp_listeners.len());
-                for listener in &mut tcp_listeners {
-                    if let Err(e) = listener.stop().await {
-                        error!("Error stopping TCP listener: {}", e);
-                    }
-                }
-
-                info!("Stopping {} TLS listener(s)...", tls_listeners.len());
-                for listener in &mut tls_listeners {
-                    if let Err(e) = listener.stop().await {
-                        error!("Error stopping TLS listener: {}", e);
-                    }
-                }
-
-                info!("Shutting down connection pool infrastructure...");
-                health_monitor.stop().await;
-                aggregator.stop().await;
-                distributor.stop().await;
-                if let Err(e) = pool.shutdown().await {
-                    error!("Error during pool shutdown: {}", e);
-                }
+    Ok(())
+}
 
-                return Err(e);
-            }
+/// Outbound connection tasks by server ID (none if the protocol is unknown)
+type ServerTasks = HashMap<String, Option<tokio::task::JoinHandle<()>>>;
+
+/// Apply the `servers` of a re-read configuration: connect added servers,
+/// disconnect removed ones and reconnect changed ones. Other sections only
+/// take effect on restart.
+async fn reload_servers(
+    config_path: &Path,
+    current: &[TakServerDef],
+    tasks: &mut ServerTasks,
+    ctx: &ConnectionContext,
+) -> Result<Vec<TakServerDef>> {
+    let config = load_config(config_path)?;
+    validate_config(&config)?;
+
+    let wanted: HashMap<&str, &TakServerDef> =
+        config.servers.iter().map(|s| (s.id.as_str(), s)).collect();
+    let (mut added, mut removed, mut changed) = (0, 0, 0);
+
+    for server in current {
+        match wanted.get(server.id.as_str()) {
+            Some(def) if *def == server => continue,
+            Some(_) => changed += 1,
+            None => removed += 1,
+        }
+        info!("Disconnecting TAK server {} for reload", server.id);
+        let _ = ctx
+            .pool
+            .remove_connection(&format!("tak-server-{}", server.id))
+            .await;
+        if let Some(Some(task)) = tasks.remove(&server.id) {
+            task.abort();
         }
-        _ = signal::ctrl_c() => {
-            info!("Received shutdown signal, stopping server...");
+    }
+    for server in &config.servers {
+        if tasks.contains_key(&server.id) {
+            continue;
+        }
+        if !current.iter().any(|s| s.id == server.id) {
+            added += 1;
+        }
+        tasks.insert(server.id.clone(), spawn_server_connection(server, ctx));
+    }
 
-            // Graceful shutdown in proper order
-            info!("Shutting down infrastructure...");
+    info!(
+        "Reloaded servers: {} added, {} removed, {} changed",
+        added, removed, changed
+    );
+    Ok(config.servers)
+}
 
-            // Stop all listeners
-            info!("Stopping {} TCP listener(s)...", tcp_listeners.len());
-            for listener in &mut tcp_listeners {
-                if let Err(e) = listener.stop().await {
-                    error!("Error stopping TCP listener: {}", e);
-                }
-            }
+/// Graceful shutdown in proper order:
+/// 1. Stop listeners (no new connections)
+/// 2. Optionally wait for existing client connections to drain
+/// 3. Stop health monitor
+/// 4. Stop aggregator
+/// 5. Stop distributor
+/// 6. Shutdown pool (closes all connections)
+async fn shutdown_infrastructure(
+    tcp_listeners: &mut [ServerTcpListener],
+    tls_listeners: &mut [ServerTlsListener],
+    drain_timeout: Option<Duration>,
+    health_monitor: &HealthMonitor,
+    aggregator: &MessageAggregator,
+    distributor: &MessageDistributor,
+    pool: &ConnectionPool,
+) {
+    info!("Shutting down infrastructure...");
+
+    // Stop all listeners
+    info!("Stopping {} TCP listener(s)...", tcp_listeners.len());
+    for listener in tcp_listeners.iter_mut() {
+        if let Err(e) = listener.stop().await {
+            error!("Error stopping TCP listener: {}", e);
+        }
+    }
 
-            info!("Stopping {} TLS listener(s)...", tls_listeners.len());
-            for listener in &mut tls_listeners {
-                if let Err(e) = listener.stop().await {
-                    error!("Error stopping TLS listener: {}", e);
-                }
-            }
+    info!("Stopping {} TLS listener(s)...", tls_listeners.len());
+    for listener in tls_listeners.iter_mut() {
+        if let Err(e) = listener.stop().await {
+            error!("Error stopping TLS listener: {}", e);
+        }
+    }
 
-            info!("Shutting down connection pool infrastructure...");
-            health_monitor.stop().await;
-            aggregator.stop().await;
-            distributor.stop().await;
-            if let Err(e) = pool.shutdown().await {
-                error!("Error during pool shutdown: {}", e);
-            }
+    if let Some(drain_timeout) = drain_timeout {
+        let active = || {
+            tcp_listeners
+                .iter()
+                .map(|l| l.stats().active_connections)
+                .chain(tls_listeners.iter().map(|l| l.stats().active_connections))
+                .sum::<usize>()
+        };
+
+        info!("Draining {} client connection(s) (up to {:?})...", active(), drain_timeout);
+        let deadline = Instant::now() + drain_timeout;
+        while active() > 0 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        if active() > 0 {
+            warn!("Drain timeout reached with {} client connection(s) still open", active());
         }
     }
 
-    Ok(())
+    info!("Shutting down connection pool infrastructure...");
+    health_monitor.stop().await;
+    aggregator.stop().await;
+    distributor.stop().await;
+    if let Err(e) = pool.shutdown().await {
+        error!("Error during pool shutdown: {}", e);
+    }
 }
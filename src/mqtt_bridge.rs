@@ -0,0 +1,311 @@
+//! MQTT Bridge
+//!
+//! Bidirectional bridge between the message pool and an MQTT broker:
+//!
+//! - **Outbound**: every aggregated CoT event is published to a topic built
+//!   from a template (e.g. `omnitak/cot/{type}/{uid}`), as XML or TAK protobuf.
+//! - **Inbound**: messages on the subscribed topics are decoded (XML or
+//!   protobuf, detected per message) and injected into the aggregator.
+//!
+//! The bridge registers as a regular pool connection, so events it injects
+//! are never echoed back to the broker.
+
+use anyhow::{Context, Result};
+use omnitak_pool::{ConnectionPool, InboundMessage, MessageAggregator, PoolMessage, TraceId};
+use rumqttc::{AsyncClient, Event as MqttEvent, MqttOptions, Packet, QoS, Transport};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Pool connection ID of the bridge
+pub const MQTT_CONNECTION_ID: &str = "mqtt-bridge";
+
+/// Delay before reconnecting after a broker error
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Largest MQTT packet accepted or sent
+const MAX_PACKET_SIZE: usize = 256 * 1024;
+
+/// MQTT bridge configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MqttBridgeConfig {
+    /// Broker hostname
+    pub host: String,
+    /// Broker port
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Connect with TLS (system trust roots)
+    #[serde(default)]
+    pub tls: bool,
+    /// MQTT client ID
+    #[serde(default = "default_client_id")]
+    pub client_id: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Topic template for outbound events; `{uid}`, `{type}` and `{callsign}`
+    /// are substituted. Unset to disable publishing.
+    #[serde(default = "default_publish_topic")]
+    pub publish_topic: Option<String>,
+    /// Outbound payload encoding
+    #[serde(default)]
+    pub payload: PayloadFormat,
+    /// Topic filters to subscribe to for inbound CoT
+    #[serde(default)]
+    pub subscribe: Vec<String>,
+    /// Quality of service for publish and subscribe (0-2)
+    #[serde(default)]
+    pub qos: u8,
+    /// Publish with the retain flag
+    #[serde(default)]
+    pub retain: bool,
+    #[serde(default = "default_keep_alive_secs")]
+    pub keep_alive_secs: u64,
+}
+
+/// MQTT payload encoding
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadFormat {
+    /// CoT XML
+    #[default]
+    Xml,
+    /// TAK protocol v1 protobuf (`TakMessage`, no framing)
+    Protobuf,
+}
+
+fn default_port() -> u16 {
+    1883
+}
+
+fn default_client_id() -> String {
+    "omnitak".to_string()
+}
+
+fn default_publish_topic() -> Option<String> {
+    Some("omnitak/cot/{type}/{uid}".to_string())
+}
+
+fn default_keep_alive_secs() -> u64 {
+    30
+}
+
+impl MqttBridgeConfig {
+    fn qos(&self) -> QoS {
+        match self.qos {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            _ => QoS::ExactlyOnce,
+        }
+    }
+
+    fn options(&self) -> MqttOptions {
+        let mut options = MqttOptions::new(&self.client_id, &self.host, self.port);
+        options.set_keep_alive(Duration::from_secs(self.keep_alive_secs));
+        options.set_max_packet_size(MAX_PACKET_SIZE, MAX_PACKET_SIZE);
+        if let Some(username) = &self.username {
+            options.set_credentials(username, self.password.clone().unwrap_or_default());
+        }
+        if self.tls {
+            options.set_transport(Transport::tls_with_default_config());
+        }
+        options
+    }
+}
+
+/// Register the bridge with the pool and start it
+pub async fn spawn(
+    config: MqttBridgeConfig,
+    pool: Arc<ConnectionPool>,
+    aggregator: Arc<MessageAggregator>,
+) -> Result<()> {
+    let broker = format!("{}:{}", config.host, config.port);
+    pool.add_connection(
+        MQTT_CONNECTION_ID.to_string(),
+        format!("MQTT bridge {}", broker),
+        broker.clone(),
+        5,
+    )
+    .await
+    .context("Failed to register MQTT bridge with the pool")?;
+    let connection = pool
+        .get_connection(&MQTT_CONNECTION_ID.to_string())
+        .context("MQTT bridge connection missing from pool")?;
+
+    let (client, mut eventloop) = AsyncClient::new(config.options(), 100);
+    let qos = config.qos();
+
+    // Broker event loop: (re)subscribe on every connect, inject inbound CoT
+    let subscribe_client = client.clone();
+    let subscriptions = config.subscribe.clone();
+    tokio::spawn(async move {
+        let sender = aggregator.sender();
+        loop {
+            match eventloop.poll().await {
+                Ok(MqttEvent::Incoming(Packet::ConnAck(_))) => {
+                    info!(broker = %broker, "Connected to MQTT broker");
+                    for topic in &subscriptions {
+                        if let Err(e) = subscribe_client.subscribe(topic, qos).await {
+                            warn!(topic = %topic, error = %e, "MQTT subscribe failed");
+                        }
+                    }
+                }
+                Ok(MqttEvent::Incoming(Packet::Publish(publish))) => {
+                    let Some(data) = decode_payload(&publish.payload) else {
+                        debug!(topic = %publish.topic, "Ignoring non-CoT MQTT message");
+                        continue;
+                    };
+                    let msg = InboundMessage {
+                        data,
+                        source: MQTT_CONNECTION_ID.to_string(),
+                        timestamp: Instant::now(),
+                        trace_id: TraceId::new(),
+                    };
+                    if let Err(e) = sender.send_async(msg).await {
+                        warn!(error = %e, "Failed to inject MQTT message");
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!(broker = %broker, error = %e, "MQTT connection error, retrying in {:?}", RECONNECT_DELAY);
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        }
+    });
+
+    // Outbound: publish everything the distributor routes to the bridge
+    if let Some(template) = config.publish_topic.clone() {
+        let format = config.payload;
+        let retain = config.retain;
+        tokio::spawn(async move {
+            while let Ok(msg) = connection.rx.recv_async().await {
+                let PoolMessage::Cot(data) = msg else {
+                    continue;
+                };
+                let Some((topic, payload)) = encode_message(&template, format, &data) else {
+                    continue;
+                };
+                if let Err(e) = client.publish(topic, qos, retain, payload).await {
+                    warn!(error = %e, "MQTT publish failed");
+                }
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Build the topic and payload for an outbound CoT message
+fn encode_message(template: &str, format: PayloadFormat, data: &[u8]) -> Option<(String, Vec<u8>)> {
+    let event = match omnitak_cot::parse_cot_bytes(data) {
+        Ok(event) => event,
+        Err(e) => {
+            debug!(error = %e, "Not publishing unparseable CoT to MQTT");
+            return None;
+        }
+    };
+
+    let callsign = event
+        .detail
+        .as_ref()
+        .and_then(|d| d.contact.as_ref())
+        .map(|c| c.callsign.as_str())
+        .unwrap_or("");
+    let topic = template
+        .replace("{uid}", &topic_level(&event.uid))
+        .replace("{type}", &topic_level(&event.event_type))
+        .replace("{callsign}", &topic_level(callsign));
+
+    let payload = match format {
+        PayloadFormat::Xml => data.to_vec(),
+        PayloadFormat::Protobuf => match omnitak_cot::encode_event(&event) {
+            Ok(payload) => payload,
+            Err(e) => {
+                debug!(error = %e, "Failed to encode CoT as protobuf");
+                return None;
+            }
+        },
+    };
+
+    Some((topic, payload))
+}
+
+/// Make a value safe to use as a single topic level
+fn topic_level(value: &str) -> String {
+    let level: String = value
+        .chars()
+        .map(|c| if matches!(c, '/' | '+' | '#') { '_' } else { c })
+        .collect();
+    if level.is_empty() {
+        "_".to_string()
+    } else {
+        level
+    }
+}
+
+/// Decode an inbound payload (CoT XML or TAK protobuf) into CoT XML
+fn decode_payload(payload: &[u8]) -> Option<Vec<u8>> {
+    let trimmed = payload.trim_ascii_start();
+    if trimmed.starts_with(b"<") {
+        omnitak_cot::parse_cot_bytes(trimmed).ok()?;
+        return Some(trimmed.to_vec());
+    }
+    let event = omnitak_cot::decode_event(payload).ok()?;
+    Some(omnitak_cot::serialize_event(&event).into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COT: &str = r#"<?xml version="1.0" encoding="UTF-8"?><event version="2.0" uid="SENSOR/1" type="a-f-G-E-S" time="2024-01-15T10:30:00Z" start="2024-01-15T10:30:00Z" stale="2024-01-15T10:35:00Z" how="m-g"><point lat="37.7" lon="-122.4" hae="10" ce="5" le="5"/><detail><contact callsign="Gate #3"/></detail></event>"#;
+
+    #[test]
+    fn test_config_defaults() {
+        let config: MqttBridgeConfig = serde_yaml::from_str("host: broker.local").unwrap();
+        assert_eq!(config.port, 1883);
+        assert_eq!(config.payload, PayloadFormat::Xml);
+        assert_eq!(config.qos(), QoS::AtMostOnce);
+        assert_eq!(
+            config.publish_topic.as_deref(),
+            Some("omnitak/cot/{type}/{uid}")
+        );
+    }
+
+    #[test]
+    fn test_encode_topic_and_payload() {
+        let (topic, payload) = encode_message(
+            "cot/{type}/{callsign}/{uid}",
+            PayloadFormat::Xml,
+            COT.as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(topic, "cot/a-f-G-E-S/Gate _3/SENSOR_1");
+        assert_eq!(payload, COT.as_bytes());
+
+        assert!(encode_message("cot", PayloadFormat::Xml, b"not cot").is_none());
+    }
+
+    #[test]
+    fn test_protobuf_round_trip() {
+        let (_, payload) =
+            encode_message("cot/{uid}", PayloadFormat::Protobuf, COT.as_bytes()).unwrap();
+        assert_ne!(payload.first(), Some(&b'<'));
+
+        let xml = decode_payload(&payload).unwrap();
+        let event = omnitak_cot::parse_cot_bytes(&xml).unwrap();
+        assert_eq!(event.uid, "SENSOR/1");
+        assert_eq!(event.event_type, "a-f-G-E-S");
+    }
+
+    #[test]
+    fn test_decode_xml_payload() {
+        let padded = format!("\n  {}", COT);
+        assert_eq!(decode_payload(padded.as_bytes()).unwrap(), COT.as_bytes());
+        assert!(decode_payload(b"<not-cot/>").is_none());
+        assert!(decode_payload(b"{\"temp\": 21.5}").is_none());
+    }
+}
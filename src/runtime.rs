@@ -0,0 +1,189 @@
+//! Tokio runtimes
+//!
+//! Message routing runs on the main "pipeline" runtime. The REST API,
+//! Swagger UI and static files can be moved to a runtime of their own so a
+//! burst of HTTP traffic cannot hold up the workers routing CoT, and the
+//! workers of either runtime can be pinned to a set of CPU cores (Linux).
+
+use serde::Deserialize;
+use std::io;
+use tokio::runtime::{Builder, Runtime};
+use tracing::warn;
+
+#[derive(Debug, Default, Deserialize, Clone)]
+pub struct RuntimeConfig {
+    /// Pipeline worker threads (default: one per core)
+    #[serde(default)]
+    pub pipeline_worker_threads: Option<usize>,
+    /// CPU cores to pin pipeline threads to
+    #[serde(default)]
+    pub pipeline_cores: Vec<usize>,
+    /// Serve the API on a separate runtime with this many worker threads
+    #[serde(default)]
+    pub api_worker_threads: Option<usize>,
+    /// CPU cores to pin API threads to
+    #[serde(default)]
+    pub api_cores: Vec<usize>,
+}
+
+/// Cores past this can't be pinned to
+#[cfg(target_os = "linux")]
+const MAX_CORES: usize = libc::CPU_SETSIZE as usize;
+#[cfg(not(target_os = "linux"))]
+const MAX_CORES: usize = usize::MAX;
+
+impl RuntimeConfig {
+    /// Check that every core threads are pinned to can be
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (key, cores) in [
+            ("pipeline_cores", &self.pipeline_cores),
+            ("api_cores", &self.api_cores),
+        ] {
+            if let Some(core) = cores.iter().find(|&&core| core >= MAX_CORES) {
+                anyhow::bail!(
+                    "runtime.{} includes core {}; cores are numbered below {}",
+                    key,
+                    core,
+                    MAX_CORES
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runtime for connections, listeners and the message pipeline
+pub fn pipeline(config: &RuntimeConfig) -> io::Result<Runtime> {
+    build(
+        "omnitak-pipeline",
+        config.pipeline_worker_threads,
+        &config.pipeline_cores,
+    )
+}
+
+/// Separate runtime for the API, if configured
+pub fn api(config: &RuntimeConfig) -> io::Result<Option<Runtime>> {
+    if config.api_worker_threads.is_none() && !config.api_cores.is_empty() {
+        warn!("runtime.api_cores has no effect without runtime.api_worker_threads");
+    }
+    config
+        .api_worker_threads
+        .map(|threads| build("omnitak-api", Some(threads), &config.api_cores))
+        .transpose()
+}
+
+fn build(name: &str, worker_threads: Option<usize>, cores: &[usize]) -> io::Result<Runtime> {
+    let mut builder = Builder::new_multi_thread();
+    builder.enable_all().thread_name(name);
+    if let Some(threads) = worker_threads {
+        builder.worker_threads(threads);
+    }
+    if !cores.is_empty() {
+        if cfg!(not(target_os = "linux")) {
+            warn!(runtime = name, "CPU pinning is only supported on Linux");
+        }
+        let cores = cores.to_vec();
+        builder.on_thread_start(move || pin_current_thread(&cores));
+    }
+    builder.build()
+}
+
+#[cfg(target_os = "linux")]
+fn pin_current_thread(cores: &[usize]) {
+    // SAFETY: cpu_set_t is plain data, and pid 0 applies the mask to the
+    // calling thread only
+    let result = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        // Out-of-range cores are rejected when the config is validated;
+        // CPU_SET would panic on them
+        for &core in cores.iter().filter(|&&core| core < MAX_CORES) {
+            libc::CPU_SET(core, &mut set);
+        }
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    if result != 0 {
+        warn!(
+            cores = ?cores,
+            error = %io::Error::last_os_error(),
+            "Failed to pin runtime thread"
+        );
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_current_thread(_cores: &[usize]) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A core this process may run on
+    #[cfg(target_os = "linux")]
+    fn allowed_core() -> usize {
+        // SAFETY: as in pin_current_thread
+        let set = unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set);
+            set
+        };
+        (0..MAX_CORES)
+            .find(|&core| unsafe { libc::CPU_ISSET(core, &set) })
+            .unwrap()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn allowed_core() -> usize {
+        0
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(RuntimeConfig::default().validate().is_ok());
+
+        #[cfg(target_os = "linux")]
+        {
+            let config = RuntimeConfig {
+                pipeline_cores: vec![0, MAX_CORES],
+                ..Default::default()
+            };
+            assert!(config.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_api_runtime() {
+        assert!(api(&RuntimeConfig::default()).unwrap().is_none());
+
+        let config = RuntimeConfig {
+            api_worker_threads: Some(2),
+            api_cores: vec![allowed_core()],
+            ..Default::default()
+        };
+        let runtime = api(&config).unwrap().unwrap();
+        assert_eq!(runtime.metrics().num_workers(), 2);
+
+        let name = runtime
+            .block_on(runtime.spawn(async { std::thread::current().name().map(str::to_string) }))
+            .unwrap();
+        assert_eq!(name.as_deref(), Some("omnitak-api"));
+
+        #[cfg(target_os = "linux")]
+        {
+            let cpus = runtime
+                .block_on(runtime.spawn(async {
+                    // SAFETY: as in pin_current_thread
+                    unsafe {
+                        let mut set: libc::cpu_set_t = std::mem::zeroed();
+                        libc::sched_getaffinity(
+                            0,
+                            std::mem::size_of::<libc::cpu_set_t>(),
+                            &mut set,
+                        );
+                        libc::CPU_COUNT(&set)
+                    }
+                }))
+                .unwrap();
+            assert_eq!(cpus, 1);
+        }
+    }
+}
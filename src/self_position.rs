@@ -0,0 +1,673 @@
+//! Self-Position Source (Own SA)
+//!
+//! Reads the aggregator's own position from gpsd or an NMEA 0183 feed (serial
+//! device or TCP), or uses a fixed configured position, and periodically
+//! publishes a self-SA CoT event, so the aggregator appears on connected maps
+//! like any other node.
+//!
+//! ```text
+//!   gpsd (JSON TPV) ─┐
+//!   serial NMEA ─────┤
+//!   TCP NMEA ────────┼─▶ reader task ─▶ latest fix ─▶ publish task ─▶ MessageDistributor
+//!   static ──────────┘                                (every interval_secs)   or selected
+//!                                                                             connections
+//! ```
+//!
+//! With `connections` set, the beacon goes only to those connections, so
+//! it can be shown on bridged networks without appearing everywhere. Each
+//! instance can set a `uid_namespace` (e.g. its site name) so several
+//! aggregators bridged together keep distinct UIDs.
+//!
+//! No event is published until a fix is available, and publishing pauses if
+//! the source stops delivering fixes.
+//!
+//! The UTC time of GPS fixes is recorded in a [`GpsClock`], which the clock
+//! monitor uses to sanity-check the system time on gateways that move
+//! without network access to NTP.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDate, NaiveTime, Utc};
+use omnitak_core::GpsClock;
+use omnitak_cot::{Contact, Detail, Event, Group, Point, PrecisionLocation, Takv, Track};
+use omnitak_pool::{
+    ConnectionPool, DistributionMessage, MessageDistributor, PoolMessage, TraceId,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Distributor source ID for self-SA events
+pub const SELF_SA_SOURCE: &str = "self-sa";
+
+/// CoT "unknown" value for ce/le/hae
+const UNKNOWN: f64 = 9_999_999.0;
+
+/// Typical user-equivalent range error used to turn HDOP into meters
+const UERE_M: f64 = 5.0;
+
+/// Knots to meters per second
+const KNOTS_TO_MPS: f64 = 0.514_444;
+
+/// Delay before reconnecting to a failed position source
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Self-position configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfPositionConfig {
+    /// Where positions come from
+    pub source: PositionSource,
+    /// UID of the published self-SA event
+    #[serde(default = "default_uid")]
+    pub uid: String,
+    /// Prefix for `uid`, e.g. a site name, so several aggregators bridged
+    /// together publish distinct UIDs
+    #[serde(default)]
+    pub uid_namespace: Option<String>,
+    /// Callsign shown on maps
+    #[serde(default = "default_callsign")]
+    pub callsign: String,
+    /// CoT type of the self-SA event
+    #[serde(default = "default_cot_type")]
+    pub cot_type: String,
+    /// Team color (e.g. "Cyan")
+    #[serde(default)]
+    pub team: Option<String>,
+    /// Team role (e.g. "HQ")
+    #[serde(default)]
+    pub role: Option<String>,
+    /// How often to publish (seconds)
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// Stale time of published events (seconds)
+    #[serde(default = "default_stale_secs")]
+    pub stale_secs: u64,
+    /// Only send to these connections; empty sends to all through the
+    /// distributor
+    #[serde(default)]
+    pub connections: Vec<String>,
+}
+
+impl SelfPositionConfig {
+    /// UID the self-SA event is published under
+    pub fn effective_uid(&self) -> String {
+        match &self.uid_namespace {
+            Some(namespace) => format!("{}-{}", namespace, self.uid),
+            None => self.uid.clone(),
+        }
+    }
+}
+
+/// Position source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PositionSource {
+    /// gpsd JSON protocol
+    Gpsd {
+        #[serde(default = "default_gpsd_address")]
+        address: String,
+    },
+    /// NMEA 0183 from a serial device
+    Serial {
+        device: String,
+        /// Line speed; applied with `stty` on Linux when set
+        #[serde(default)]
+        baud: Option<u32>,
+    },
+    /// NMEA 0183 from a TCP stream (e.g. a GPS-to-network bridge)
+    NmeaTcp { address: String },
+    /// Fixed position, for aggregators without a GPS
+    Static {
+        lat: f64,
+        lon: f64,
+        /// Height above ellipsoid in meters
+        #[serde(default)]
+        hae: Option<f64>,
+    },
+}
+
+fn default_uid() -> String {
+    "OMNITAK-SELF".to_string()
+}
+
+fn default_callsign() -> String {
+    "OmniTAK".to_string()
+}
+
+fn default_cot_type() -> String {
+    "a-f-G-U-C".to_string()
+}
+
+fn default_interval_secs() -> u64 {
+    10
+}
+
+fn default_stale_secs() -> u64 {
+    120
+}
+
+fn default_gpsd_address() -> String {
+    "127.0.0.1:2947".to_string()
+}
+
+/// A position fix; optional fields are filled in as sentences arrive
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Fix {
+    pub lat: f64,
+    pub lon: f64,
+    /// Height above ellipsoid in meters
+    pub hae: Option<f64>,
+    /// Horizontal error in meters
+    pub ce: Option<f64>,
+    /// Vertical error in meters
+    pub le: Option<f64>,
+    /// Speed over ground in meters per second
+    pub speed: Option<f64>,
+    /// Course over ground in degrees
+    pub course: Option<f64>,
+    /// UTC time of the fix as reported by the receiver
+    pub time: Option<DateTime<Utc>>,
+}
+
+/// Start reading positions and publishing self-SA events; fix times are
+/// recorded in `gps_clock`
+pub fn spawn(
+    config: SelfPositionConfig,
+    distributor: Arc<MessageDistributor>,
+    pool: Arc<ConnectionPool>,
+    gps_clock: Arc<GpsClock>,
+) -> (JoinHandle<()>, JoinHandle<()>) {
+    let (tx, rx) = watch::channel(None);
+
+    let source = config.source.clone();
+    let reader = tokio::spawn(async move {
+        loop {
+            if let Err(e) = read_source(&source, &tx, &gps_clock).await {
+                warn!(error = %e, "Self-position source failed, retrying in {:?}", RECONNECT_DELAY);
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+
+    let publisher = tokio::spawn(publish_loop(config, rx, distributor, pool));
+
+    (reader, publisher)
+}
+
+/// Publish the latest fix at the configured interval
+async fn publish_loop(
+    config: SelfPositionConfig,
+    rx: watch::Receiver<Option<(Fix, Instant)>>,
+    distributor: Arc<MessageDistributor>,
+    pool: Arc<ConnectionPool>,
+) {
+    let interval_duration = Duration::from_secs(config.interval_secs.max(1));
+    let max_fix_age = interval_duration * 3;
+    let mut interval = tokio::time::interval(interval_duration);
+    let mut have_fix = false;
+
+    info!(
+        uid = %config.effective_uid(),
+        callsign = %config.callsign,
+        interval_secs = config.interval_secs,
+        "Self-position publisher started"
+    );
+
+    loop {
+        interval.tick().await;
+
+        let latest = *rx.borrow();
+        let fix = match latest {
+            Some((fix, at)) if at.elapsed() <= max_fix_age => fix,
+            _ => {
+                if have_fix {
+                    warn!("No recent position fix, pausing self-SA");
+                    have_fix = false;
+                }
+                continue;
+            }
+        };
+        if !have_fix {
+            info!(
+                lat = fix.lat,
+                lon = fix.lon,
+                "Position fix acquired, publishing self-SA"
+            );
+            have_fix = true;
+        }
+
+        let data = omnitak_cot::serialize_event(&build_event(&config, &fix)).into_bytes();
+        if config.connections.is_empty() {
+            let msg = DistributionMessage {
+                data,
+                source: Some(SELF_SA_SOURCE.to_string()),
+                timestamp: Instant::now(),
+                bypass_filters: false,
+                trace_id: TraceId::new(),
+            };
+            if let Err(e) = distributor.sender().send_async(msg).await {
+                warn!(error = %e, "Failed to publish self-SA");
+            }
+            continue;
+        }
+
+        for connection_id in &config.connections {
+            let Some(connection) = pool.get_connection(connection_id) else {
+                debug!(connection_id = %connection_id, "Self-SA connection not active");
+                continue;
+            };
+            if connection
+                .tx
+                .try_send(PoolMessage::Cot(data.clone()))
+                .is_ok()
+            {
+                connection.state.record_sent();
+            } else {
+                warn!(connection_id = %connection_id, "Failed to publish self-SA");
+            }
+        }
+    }
+}
+
+/// Connect to a source and feed fixes into the channel until it fails
+async fn read_source(
+    source: &PositionSource,
+    tx: &watch::Sender<Option<(Fix, Instant)>>,
+    gps_clock: &GpsClock,
+) -> Result<()> {
+    let (reader, gpsd): (Box<dyn AsyncBufRead + Unpin + Send>, bool) = match source {
+        PositionSource::Gpsd { address } => {
+            let mut stream = TcpStream::connect(address)
+                .await
+                .with_context(|| format!("Failed to connect to gpsd at {}", address))?;
+            stream
+                .write_all(b"?WATCH={\"enable\":true,\"json\":true};\n")
+                .await
+                .context("Failed to enable gpsd watch mode")?;
+            info!(address = %address, "Connected to gpsd");
+            (Box::new(BufReader::new(stream)), true)
+        }
+        PositionSource::NmeaTcp { address } => {
+            let stream = TcpStream::connect(address)
+                .await
+                .with_context(|| format!("Failed to connect to NMEA source at {}", address))?;
+            info!(address = %address, "Connected to NMEA source");
+            (Box::new(BufReader::new(stream)), false)
+        }
+        PositionSource::Static { lat, lon, hae } => {
+            let fix = Fix {
+                lat: *lat,
+                lon: *lon,
+                hae: *hae,
+                ..Default::default()
+            };
+            // Keep the fix fresh; the publisher pauses on old fixes
+            loop {
+                let _ = tx.send(Some((fix, Instant::now())));
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+        PositionSource::Serial { device, baud } => {
+            #[cfg(target_os = "linux")]
+            if let Some(baud) = baud {
+                let status = tokio::process::Command::new("stty")
+                    .args(["-F", device, &baud.to_string(), "raw", "-echo"])
+                    .status()
+                    .await;
+                if !matches!(status, Ok(s) if s.success()) {
+                    warn!(device = %device, baud, "Failed to set serial line speed");
+                }
+            }
+            #[cfg(not(target_os = "linux"))]
+            let _ = baud;
+
+            let file = tokio::fs::File::open(device)
+                .await
+                .with_context(|| format!("Failed to open serial device {}", device))?;
+            info!(device = %device, "Reading NMEA from serial device");
+            (Box::new(BufReader::new(file)), false)
+        }
+    };
+
+    let mut lines = reader.lines();
+    let mut nmea = NmeaState::default();
+
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .context("Position source read failed")?
+    {
+        let fix = if gpsd {
+            parse_gpsd_tpv(&line)
+        } else {
+            nmea.update(&line)
+        };
+        if let Some(fix) = fix {
+            debug!(lat = fix.lat, lon = fix.lon, time = ?fix.time, "Position fix");
+            if let Some(time) = fix.time {
+                gps_clock.record(time);
+            }
+            let _ = tx.send(Some((fix, Instant::now())));
+        }
+    }
+
+    anyhow::bail!("Position source closed the connection")
+}
+
+/// Build the self-SA event for a fix
+pub fn build_event(config: &SelfPositionConfig, fix: &Fix) -> Event {
+    let now = Utc::now();
+    let is_static = matches!(config.source, PositionSource::Static { .. });
+
+    let group = config.team.as_ref().map(|team| Group {
+        name: team.clone(),
+        role: config
+            .role
+            .clone()
+            .unwrap_or_else(|| "Team Member".to_string()),
+    });
+    let track = match (fix.speed, fix.course) {
+        (None, None) => None,
+        (speed, course) => Some(Track {
+            speed: speed.unwrap_or(0.0),
+            course: course.unwrap_or(0.0),
+        }),
+    };
+
+    Event {
+        version: "2.0".to_string(),
+        uid: config.effective_uid(),
+        event_type: config.cot_type.clone(),
+        time: now,
+        start: now,
+        stale: now + ChronoDuration::seconds(config.stale_secs as i64),
+        how: "m-g".to_string(),
+        point: Point {
+            lat: fix.lat,
+            lon: fix.lon,
+            hae: fix.hae.unwrap_or(UNKNOWN),
+            ce: fix.ce.unwrap_or(UNKNOWN),
+            le: fix.le.unwrap_or(UNKNOWN),
+        },
+        detail: Some(Detail {
+            contact: Some(Contact {
+                endpoint: None,
+                callsign: config.callsign.clone(),
+            }),
+            group,
+            track,
+            precision_location: Some(if is_static {
+                PrecisionLocation {
+                    geopointsrc: "USER".to_string(),
+                    altsrc: if fix.hae.is_some() { "USER" } else { "???" }.to_string(),
+                }
+            } else {
+                PrecisionLocation {
+                    geopointsrc: "GPS".to_string(),
+                    altsrc: if fix.hae.is_some() { "GPS" } else { "???" }.to_string(),
+                }
+            }),
+            takv: Some(Takv {
+                device: "OmniTAK Aggregator".to_string(),
+                platform: "OmniTAK".to_string(),
+                os: std::env::consts::OS.to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            }),
+            ..Default::default()
+        }),
+    }
+}
+
+// ============================================================================
+// NMEA 0183
+// ============================================================================
+
+/// Merges GGA (position, altitude, HDOP, time) and RMC (speed, course,
+/// date) sentences
+#[derive(Debug, Default)]
+struct NmeaState {
+    speed: Option<f64>,
+    course: Option<f64>,
+    date: Option<NaiveDate>,
+}
+
+impl NmeaState {
+    /// Process one sentence, returning a fix when a position was reported
+    fn update(&mut self, line: &str) -> Option<Fix> {
+        let body = verify_checksum(line.trim())?;
+        let fields: Vec<&str> = body.split(',').collect();
+        // Talker ID (GP, GN, GL, ...) is ignored
+        let kind = fields.first()?.get(2..)?;
+
+        match kind {
+            "RMC" => {
+                if fields.get(2) != Some(&"A") {
+                    return None;
+                }
+                self.speed = fields
+                    .get(7)
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .map(|knots| knots * KNOTS_TO_MPS);
+                self.course = fields.get(8).and_then(|s| s.parse().ok());
+                self.date = fields
+                    .get(9)
+                    .and_then(|s| NaiveDate::parse_from_str(s, "%d%m%y").ok());
+                // Position comes from GGA, which also carries altitude
+                None
+            }
+            "GGA" => {
+                let quality: u32 = fields.get(6)?.parse().ok()?;
+                if quality == 0 {
+                    return None;
+                }
+                let lat = parse_coord(fields.get(2)?, fields.get(3)?)?;
+                let lon = parse_coord(fields.get(4)?, fields.get(5)?)?;
+                let hdop: Option<f64> = fields.get(8).and_then(|s| s.parse().ok());
+                let msl: Option<f64> = fields.get(9).and_then(|s| s.parse().ok());
+                let geoid: f64 = fields.get(11).and_then(|s| s.parse().ok()).unwrap_or(0.0);
+                // GGA carries only the time of day; the date comes from RMC
+                let time = self
+                    .date
+                    .zip(fields.get(1).and_then(|s| parse_time(s)))
+                    .map(|(date, time)| {
+                        DateTime::from_naive_utc_and_offset(date.and_time(time), Utc)
+                    });
+
+                Some(Fix {
+                    lat,
+                    lon,
+                    hae: msl.map(|msl| msl + geoid),
+                    ce: hdop.map(|hdop| hdop * UERE_M),
+                    le: None,
+                    speed: self.speed,
+                    course: self.course,
+                    time,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Validate `$...*HH` (or AIS `!...*HH`) framing and checksum, returning the sentence body
+pub(crate) fn verify_checksum(sentence: &str) -> Option<&str> {
+    let sentence = sentence.strip_prefix(['$', '!'])?;
+    let (body, checksum) = sentence.rsplit_once('*')?;
+    let expected = u8::from_str_radix(checksum.get(..2)?, 16).ok()?;
+    let actual = body.bytes().fold(0u8, |acc, b| acc ^ b);
+    (actual == expected).then_some(body)
+}
+
+/// Parse an `hhmmss.ss` UTC time
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H%M%S%.f").ok()
+}
+
+/// Parse `ddmm.mmmm`/`dddmm.mmmm` with a hemisphere letter into degrees
+fn parse_coord(value: &str, hemisphere: &str) -> Option<f64> {
+    let dot = value.find('.').unwrap_or(value.len());
+    if dot < 3 {
+        return None;
+    }
+    let degrees: f64 = value[..dot - 2].parse().ok()?;
+    let minutes: f64 = value[dot - 2..].parse().ok()?;
+    let decimal = degrees + minutes / 60.0;
+    match hemisphere {
+        "N" | "E" => Some(decimal),
+        "S" | "W" => Some(-decimal),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// gpsd
+// ============================================================================
+
+/// Parse a gpsd TPV report with at least a 2D fix
+fn parse_gpsd_tpv(line: &str) -> Option<Fix> {
+    let report: serde_json::Value = serde_json::from_str(line).ok()?;
+    if report.get("class")?.as_str()? != "TPV" || report.get("mode")?.as_u64()? < 2 {
+        return None;
+    }
+
+    let number = |key: &str| report.get(key).and_then(|v| v.as_f64());
+    let ce = number("eph").or_else(|| match (number("epx"), number("epy")) {
+        (Some(x), Some(y)) => Some(x.max(y)),
+        _ => None,
+    });
+    let hae = number("altHAE").or_else(|| {
+        number("altMSL")
+            .or_else(|| number("alt"))
+            .map(|alt| alt + number("geoidSep").unwrap_or(0.0))
+    });
+
+    Some(Fix {
+        lat: number("lat")?,
+        lon: number("lon")?,
+        hae,
+        ce,
+        le: number("epv"),
+        speed: number("speed"),
+        course: number("track"),
+        time: report
+            .get("time")
+            .and_then(|v| v.as_str())
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|t| t.with_timezone(&Utc)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RMC: &str = "$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A";
+    const GGA: &str = "$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47";
+
+    #[test]
+    fn test_nmea_checksum() {
+        assert!(verify_checksum(GGA).is_some());
+        assert!(verify_checksum(&GGA.replace("4807", "4808")).is_none());
+        assert!(verify_checksum("GPGGA,no,dollar*00").is_none());
+    }
+
+    #[test]
+    fn test_parse_coord() {
+        assert!((parse_coord("4807.038", "N").unwrap() - 48.1173).abs() < 1e-9);
+        assert!((parse_coord("01131.000", "W").unwrap() + 11.516_666_666).abs() < 1e-6);
+        assert!(parse_coord("4807.038", "X").is_none());
+        assert!(parse_coord("", "N").is_none());
+    }
+
+    #[test]
+    fn test_nmea_rmc_then_gga() {
+        let mut state = NmeaState::default();
+        assert!(state.update(RMC).is_none());
+
+        let fix = state.update(GGA).unwrap();
+        assert!((fix.lat - 48.1173).abs() < 1e-9);
+        assert!((fix.lon - 11.516_666_666).abs() < 1e-6);
+        assert!((fix.hae.unwrap() - 592.3).abs() < 1e-9);
+        assert!((fix.ce.unwrap() - 4.5).abs() < 1e-9);
+        assert!((fix.speed.unwrap() - 22.4 * KNOTS_TO_MPS).abs() < 1e-9);
+        assert_eq!(fix.course, Some(84.4));
+        assert_eq!(
+            fix.time,
+            Some("1994-03-23T12:35:19Z".parse::<DateTime<Utc>>().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_nmea_no_fix() {
+        let mut state = NmeaState::default();
+        let gga = "$GPGGA,123519,,,,,0,00,,,M,,M,,*6B";
+        assert!(state.update(gga).is_none());
+    }
+
+    #[test]
+    fn test_parse_gpsd_tpv() {
+        let fix = parse_gpsd_tpv(
+            r#"{"class":"TPV","mode":3,"lat":37.7749,"lon":-122.4194,"altHAE":12.5,"eph":4.0,"epv":6.0,"speed":1.5,"track":270.0,"time":"2026-01-01T12:00:00.000Z"}"#,
+        )
+        .unwrap();
+        assert_eq!(fix.lat, 37.7749);
+        assert_eq!(fix.hae, Some(12.5));
+        assert_eq!(fix.ce, Some(4.0));
+        assert_eq!(fix.course, Some(270.0));
+        assert_eq!(
+            fix.time,
+            Some("2026-01-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap())
+        );
+
+        assert!(parse_gpsd_tpv(r#"{"class":"TPV","mode":1}"#).is_none());
+        assert!(parse_gpsd_tpv(r#"{"class":"SKY","mode":3}"#).is_none());
+        assert!(parse_gpsd_tpv("not json").is_none());
+    }
+
+    #[test]
+    fn test_build_event() {
+        let config: SelfPositionConfig =
+            serde_yaml::from_str("source:\n  type: gpsd\ncallsign: HQ-1\nteam: Cyan\n").unwrap();
+        let fix = Fix {
+            lat: 37.7749,
+            lon: -122.4194,
+            ..Default::default()
+        };
+
+        let event = build_event(&config, &fix);
+        assert_eq!(event.uid, "OMNITAK-SELF");
+        assert_eq!(event.point.hae, UNKNOWN);
+        assert_eq!(event.stale - event.time, ChronoDuration::seconds(120));
+
+        let detail = event.detail.unwrap();
+        assert_eq!(detail.contact.unwrap().callsign, "HQ-1");
+        assert_eq!(detail.group.unwrap().name, "Cyan");
+        assert!(detail.track.is_none());
+
+        let xml = omnitak_cot::serialize_event(&build_event(&config, &fix));
+        assert!(omnitak_cot::parse_cot(&xml).is_ok());
+    }
+
+    #[test]
+    fn test_static_source_and_namespace() {
+        let config: SelfPositionConfig = serde_yaml::from_str(
+            "source:\n  type: static\n  lat: 34.05\n  lon: -117.2\nuid_namespace: SITE-A\nconnections: [bridge]\n",
+        )
+        .unwrap();
+        assert_eq!(config.connections, vec!["bridge".to_string()]);
+
+        let fix = Fix {
+            lat: 34.05,
+            lon: -117.2,
+            ..Default::default()
+        };
+        let event = build_event(&config, &fix);
+        assert_eq!(event.uid, "SITE-A-OMNITAK-SELF");
+        let precision = event.detail.unwrap().precision_location.unwrap();
+        assert_eq!(precision.geopointsrc, "USER");
+    }
+}
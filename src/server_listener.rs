@@ -39,13 +39,34 @@
 //!         ▼            ▼            ▼
 //!    Write Task   Write Task   Write Task  ← Send to clients
 //! ```
+//!
+//! # Virtual Hosts
+//!
+//! A TLS listener can serve several endpoints on one port, told apart by
+//! the server name (SNI) clients ask for, e.g. `streaming.example.com` and
+//! `partners.example.com`. Each virtual host has its own certificate,
+//! client certificate CA and [`EndpointPolicy`]. Clients asking for no or
+//! an unknown name get the listener's own certificate and policy, unless
+//! `strict_sni` is set.
+//!
+//! # Client Access
+//!
+//! With a [`ClientAccessPolicy`] set, clients presenting a certificate are
+//! authorized by its CN, OUs and issuing CA after the handshake: denied
+//! clients are disconnected, read-only clients get the feed but the CoT
+//! they send is dropped. Clients without a certificate get the policy's
+//! default access.
 
 use anyhow::{Context, Result, anyhow};
 use bytes::BytesMut;
-use omnitak_pool::{ConnectionPool, MessageAggregator, InboundMessage};
+use omnitak_api::client_access::{
+    ClientAccess, ClientAccessPolicy, ClientAuthorization, ClientIdentity,
+};
+use omnitak_client::Compression;
+use omnitak_pool::{ConnectionPool, MessageAggregator, InboundMessage, TraceId};
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
@@ -53,7 +74,7 @@ use tokio::net::{TcpListener as TokioTcpListener, TcpStream};
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 use tokio_rustls::rustls::{self, ServerConfig as TlsServerConfig};
-use tokio_rustls::TlsAcceptor;
+use tokio_rustls::LazyConfigAcceptor;
 use tracing::{debug, error, info, warn};
 
 /// Maximum frame size for CoT messages (10MB)
@@ -84,6 +105,54 @@ pub struct ClientAuthConfig {
     pub ca_path: String,
 }
 
+/// What clients of a TLS endpoint may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointPolicy {
+    /// Accept CoT from clients
+    #[serde(default = "default_true")]
+    pub receive: bool,
+    /// Send aggregated CoT to clients
+    #[serde(default = "default_true")]
+    pub send: bool,
+    /// Connection limit for this endpoint, within the listener's
+    /// `max_connections`
+    #[serde(default)]
+    pub max_connections: Option<usize>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for EndpointPolicy {
+    fn default() -> Self {
+        Self {
+            receive: true,
+            send: true,
+            max_connections: None,
+        }
+    }
+}
+
+/// Endpoint on a TLS listener chosen by the server name clients ask for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirtualHostConfig {
+    /// Endpoint name in logs and connection names
+    pub id: String,
+    /// Server names served, e.g. `streaming.example.com`; `*.example.com`
+    /// matches any single label in front of `example.com`
+    pub server_names: Vec<String>,
+    /// Path to the endpoint's certificate (PEM format)
+    pub cert_path: String,
+    /// Path to the endpoint's private key (PEM format)
+    pub key_path: String,
+    /// Optional client authentication
+    #[serde(default)]
+    pub client_auth: Option<ClientAuthConfig>,
+    #[serde(default)]
+    pub policy: EndpointPolicy,
+}
+
 /// TLS listener configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TlsListenerConfig {
@@ -93,6 +162,16 @@ pub struct TlsListenerConfig {
     pub key_path: String,
     /// Optional client authentication
     pub client_auth: Option<ClientAuthConfig>,
+    /// Policy for clients not routed to a virtual host
+    #[serde(default)]
+    pub policy: EndpointPolicy,
+    /// Further endpoints on the same port, chosen by SNI
+    #[serde(default)]
+    pub virtual_hosts: Vec<VirtualHostConfig>,
+    /// Refuse clients whose server name matches no virtual host instead of
+    /// serving them the certificate and policy above
+    #[serde(default)]
+    pub strict_sni: bool,
 }
 
 /// Listener configuration
@@ -110,6 +189,12 @@ pub struct ListenerConfig {
     pub max_connections: usize,
     /// TLS configuration (required if protocol is TLS)
     pub tls: Option<TlsListenerConfig>,
+    /// Expect length-prefixed zstd frames (links from other OmniTAK instances)
+    #[serde(default)]
+    pub compression: bool,
+    /// Quarantine messages from clients that fail CoT schema validation
+    #[serde(default)]
+    pub strict_validation: bool,
 }
 
 impl Default for ListenerConfig {
@@ -121,6 +206,19 @@ impl Default for ListenerConfig {
             protocol: ListenerProtocol::Tcp,
             max_connections: 1000,
             tls: None,
+            compression: false,
+            strict_validation: false,
+        }
+    }
+}
+
+impl ListenerConfig {
+    /// Frame compression mode for accepted connections
+    fn compression(&self) -> Compression {
+        if self.compression {
+            Compression::zstd()
+        } else {
+            Compression::None
         }
     }
 }
@@ -184,6 +282,99 @@ impl ListenerState {
     }
 }
 
+/// Read the next frame: `</event>`-delimited XML, or length-prefixed zstd
+/// when compression is enabled
+async fn read_frame<R>(
+    stream: &mut R,
+    buffer: &mut BytesMut,
+    compression: Compression,
+) -> Result<Option<bytes::Bytes>>
+where
+    R: AsyncReadExt + Unpin,
+{
+    if !compression.is_enabled() {
+        return TcpListener::read_xml_frame(stream, buffer).await;
+    }
+
+    loop {
+        if buffer.len() >= 4 {
+            let frame_len = u32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+            if frame_len > MAX_FRAME_SIZE {
+                return Err(anyhow!("Frame length {} exceeds maximum", frame_len));
+            }
+            if buffer.len() >= 4 + frame_len {
+                let _ = buffer.split_to(4);
+                let frame = buffer.split_to(frame_len).freeze();
+                return compression.decompress(frame).map(Some);
+            }
+        }
+
+        let n = stream.read_buf(buffer).await?;
+        if n == 0 {
+            if buffer.is_empty() {
+                return Ok(None);
+            } else {
+                return Err(anyhow!("Connection closed with incomplete frame"));
+            }
+        }
+    }
+}
+
+/// Encode an outbound frame, compressing and length-prefixing it if enabled
+fn encode_frame(data: Vec<u8>, compression: Compression) -> Result<Vec<u8>> {
+    if !compression.is_enabled() {
+        return Ok(data);
+    }
+
+    let compressed = compression.compress(&data)?;
+    let mut frame = Vec::with_capacity(4 + compressed.len());
+    frame.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&compressed);
+    Ok(frame)
+}
+
+/// Bind a listening socket, or adopt one inherited from a previous process
+///
+/// Returns the async listener plus a duplicate handle kept for handing the
+/// socket over to a successor process during an in-place upgrade.
+fn open_listener(
+    bind_addr: SocketAddr,
+    inherited: Option<std::net::TcpListener>,
+) -> Result<(TokioTcpListener, std::net::TcpListener)> {
+    let socket = match inherited {
+        Some(socket) => {
+            info!(bind_addr = %bind_addr, "Adopting inherited listening socket");
+            socket
+        }
+        None => {
+            let socket = std::net::TcpListener::bind(bind_addr)?;
+
+            // Enable SO_REUSEPORT for load balancing across multiple instances
+            #[cfg(unix)]
+            {
+                use std::os::unix::io::AsRawFd;
+                let fd = socket.as_raw_fd();
+                unsafe {
+                    let optval: libc::c_int = 1;
+                    libc::setsockopt(
+                        fd,
+                        libc::SOL_SOCKET,
+                        libc::SO_REUSEPORT,
+                        &optval as *const _ as *const libc::c_void,
+                        std::mem::size_of_val(&optval) as libc::socklen_t,
+                    );
+                }
+            }
+
+            socket
+        }
+    };
+
+    socket.set_nonblocking(true)?;
+    let handover = socket.try_clone()?;
+    Ok((TokioTcpListener::from_std(socket)?, handover))
+}
+
 /// TCP Listener for accepting ATAK client connections
 pub struct TcpListener {
     config: ListenerConfig,
@@ -191,6 +382,8 @@ pub struct TcpListener {
     aggregator: Arc<MessageAggregator>,
     state: Arc<ListenerState>,
     accept_task: Option<JoinHandle<()>>,
+    inherited: Option<std::net::TcpListener>,
+    handover: Option<std::net::TcpListener>,
 }
 
 impl TcpListener {
@@ -206,9 +399,28 @@ impl TcpListener {
             aggregator,
             state: Arc::new(ListenerState::new()),
             accept_task: None,
+            inherited: None,
+            handover: None,
         }
     }
 
+    /// Use a socket inherited from a previous process instead of binding
+    pub fn set_inherited_socket(&mut self, socket: std::net::TcpListener) {
+        self.inherited = Some(socket);
+    }
+
+    /// Raw fd of the listening socket, for handing over during an upgrade
+    #[cfg(unix)]
+    pub fn handover_fd(&self) -> Option<std::os::unix::io::RawFd> {
+        use std::os::unix::io::AsRawFd;
+        self.handover.as_ref().map(|s| s.as_raw_fd())
+    }
+
+    /// Listener identifier
+    pub fn id(&self) -> &str {
+        &self.config.id
+    }
+
     /// Start the listener
     pub async fn start(&mut self) -> Result<()> {
         if !self.config.enabled {
@@ -232,31 +444,17 @@ impl TcpListener {
             "Starting TCP listener"
         );
 
-        // Create TCP listener with SO_REUSEPORT
-        let listener = TokioTcpListener::bind(bind_addr).await?;
-
-        // Enable SO_REUSEPORT for load balancing across multiple instances
-        #[cfg(unix)]
-        {
-            use std::os::unix::io::AsRawFd;
-            let fd = listener.as_raw_fd();
-            unsafe {
-                let optval: libc::c_int = 1;
-                libc::setsockopt(
-                    fd,
-                    libc::SOL_SOCKET,
-                    libc::SO_REUSEPORT,
-                    &optval as *const _ as *const libc::c_void,
-                    std::mem::size_of_val(&optval) as libc::socklen_t,
-                );
-            }
-        }
+        // Create TCP listener with SO_REUSEPORT (or adopt one handed over on upgrade)
+        let (listener, handover) = open_listener(bind_addr, self.inherited.take())?;
+        self.handover = Some(handover);
 
         let pool = Arc::clone(&self.pool);
         let aggregator = Arc::clone(&self.aggregator);
         let state = Arc::clone(&self.state);
         let max_connections = self.config.max_connections;
         let listener_id = self.config.id.clone();
+        let compression = self.config.compression();
+        let strict_validation = self.config.strict_validation;
 
         // Spawn accept loop
         let accept_task = tokio::spawn(async move {
@@ -314,6 +512,8 @@ impl TcpListener {
                                 aggregator_clone,
                                 state_clone,
                                 listener_id_clone,
+                                compression,
+                                strict_validation,
                             )
                             .await
                             {
@@ -351,6 +551,7 @@ impl TcpListener {
     }
 
     /// Handle an accepted TCP connection
+    #[allow(clippy::too_many_arguments)]
     async fn handle_connection(
         mut stream: TcpStream,
         remote_addr: SocketAddr,
@@ -358,6 +559,8 @@ impl TcpListener {
         aggregator: Arc<MessageAggregator>,
         state: Arc<ListenerState>,
         listener_id: String,
+        compression: Compression,
+        strict_validation: bool,
     ) -> Result<()> {
         // Generate unique connection ID
         let connection_id = format!("atak-client-{}", remote_addr);
@@ -380,6 +583,9 @@ impl TcpListener {
             5, // Default priority
         )
         .await?;
+        aggregator
+            .quarantine()
+            .set_strict(connection_id.clone(), strict_validation);
 
         // Get the connection from pool to access its channels
         let connection = pool
@@ -401,7 +607,7 @@ impl TcpListener {
             let mut buffer = BytesMut::with_capacity(INITIAL_BUFFER_CAPACITY);
 
             loop {
-                match Self::read_xml_frame(&mut read_half, &mut buffer).await {
+                match read_frame(&mut read_half, &mut buffer, compression).await {
                     Ok(Some(frame)) => {
                         let frame_len = frame.len();
                         state_read.bytes_received.fetch_add(frame_len as u64, Ordering::Relaxed);
@@ -418,6 +624,7 @@ impl TcpListener {
                             data: frame.to_vec(),
                             source: connection_id_read.clone(),
                             timestamp: Instant::now(),
+                            trace_id: TraceId::new(),
                         };
 
                         if let Err(e) = aggregator_sender.send_async(inbound_msg).await {
@@ -460,6 +667,17 @@ impl TcpListener {
                         match msg {
                             Ok(pool_msg) => {
                                 if let omnitak_pool::PoolMessage::Cot(data) = pool_msg {
+                                    let data = match encode_frame(data, compression) {
+                                        Ok(data) => data,
+                                        Err(e) => {
+                                            error!(
+                                                connection_id = %connection_id_write,
+                                                error = %e,
+                                                "Failed to compress frame"
+                                            );
+                                            continue;
+                                        }
+                                    };
                                     match write_half.write_all(&data).await {
                                         Ok(_) => {
                                             if let Err(e) = write_half.flush().await {
@@ -533,6 +751,7 @@ impl TcpListener {
 
         // Clean up
         state.active.fetch_sub(1, Ordering::Relaxed);
+        aggregator.quarantine().set_strict(connection_id.clone(), false);
         if let Err(e) = pool.remove_connection(&connection_id).await {
             warn!(
                 connection_id = %connection_id,
@@ -608,6 +827,7 @@ impl TcpListener {
             task.abort();
             let _ = task.await;
         }
+        self.handover = None;
 
         info!(listener_id = %self.config.id, "TCP listener stopped");
         Ok(())
@@ -619,14 +839,215 @@ impl TcpListener {
     }
 }
 
+/// Certificate, client verification and policy of one TLS endpoint
+struct Endpoint {
+    /// Virtual host ID; `None` for the listener's own endpoint
+    virtual_host: Option<String>,
+    /// Lowercase server names
+    server_names: Vec<String>,
+    server_config: Arc<TlsServerConfig>,
+    policy: EndpointPolicy,
+    active: AtomicUsize,
+}
+
+impl Endpoint {
+    fn new(
+        virtual_host: Option<String>,
+        server_names: &[String],
+        server_config: Arc<TlsServerConfig>,
+        policy: EndpointPolicy,
+    ) -> Self {
+        Self {
+            virtual_host,
+            server_names: server_names
+                .iter()
+                .map(|n| n.to_ascii_lowercase())
+                .collect(),
+            server_config,
+            policy,
+            active: AtomicUsize::new(0),
+        }
+    }
+}
+
+/// Picks the endpoint for a connection from the server name in its
+/// ClientHello
+struct SniRouter {
+    default: Endpoint,
+    virtual_hosts: Vec<Endpoint>,
+    strict: bool,
+}
+
+impl SniRouter {
+    fn build(config: &ListenerConfig) -> Result<Self> {
+        let tls_config = config
+            .tls
+            .as_ref()
+            .ok_or_else(|| anyhow!("TLS configuration required for TLS listener"))?;
+
+        let default = Endpoint::new(
+            None,
+            &[],
+            build_server_config(
+                &config.id,
+                &tls_config.cert_path,
+                &tls_config.key_path,
+                tls_config.client_auth.as_ref(),
+            )?,
+            tls_config.policy,
+        );
+
+        let mut seen = std::collections::HashSet::new();
+        let mut virtual_hosts = Vec::with_capacity(tls_config.virtual_hosts.len());
+        for host in &tls_config.virtual_hosts {
+            if host.server_names.is_empty() {
+                return Err(anyhow!("Virtual host '{}' has no server names", host.id));
+            }
+            let endpoint = Endpoint::new(
+                Some(host.id.clone()),
+                &host.server_names,
+                build_server_config(
+                    &format!("{}/{}", config.id, host.id),
+                    &host.cert_path,
+                    &host.key_path,
+                    host.client_auth.as_ref(),
+                )
+                .with_context(|| format!("Virtual host '{}'", host.id))?,
+                host.policy,
+            );
+            for name in &endpoint.server_names {
+                if !seen.insert(name.clone()) {
+                    return Err(anyhow!(
+                        "Server name '{}' is used by more than one virtual host",
+                        name
+                    ));
+                }
+            }
+            virtual_hosts.push(endpoint);
+        }
+
+        Ok(Self {
+            default,
+            virtual_hosts,
+            strict: tls_config.strict_sni,
+        })
+    }
+
+    /// Endpoint for a requested server name, or `None` to refuse the client
+    ///
+    /// Exact names win over wildcards.
+    fn route(&self, server_name: Option<&str>) -> Option<&Endpoint> {
+        if let Some(name) = server_name.map(str::to_ascii_lowercase) {
+            let find = |matches: fn(&str, &str) -> bool| {
+                self.virtual_hosts
+                    .iter()
+                    .find(|e| e.server_names.iter().any(|n| matches(n, &name)))
+            };
+            if let Some(endpoint) = find(|n, name| n == name).or_else(|| find(server_name_matches))
+            {
+                return Some(endpoint);
+            }
+        }
+        (!self.strict).then_some(&self.default)
+    }
+}
+
+/// Whether a server name matches a configured name or `*.` wildcard, which
+/// stands for exactly one label
+fn server_name_matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => name
+            .split_once('.')
+            .is_some_and(|(label, rest)| !label.is_empty() && rest == suffix),
+        None => pattern == name,
+    }
+}
+
+/// Build the TLS server configuration of one endpoint
+fn build_server_config(
+    endpoint_id: &str,
+    cert_path: &str,
+    key_path: &str,
+    client_auth: Option<&ClientAuthConfig>,
+) -> Result<Arc<TlsServerConfig>> {
+    info!(
+        endpoint = %endpoint_id,
+        cert_path = %cert_path,
+        key_path = %key_path,
+        "Building TLS server configuration"
+    );
+
+    // Load server certificate and key
+    let cert_file = std::fs::File::open(cert_path).context("Failed to open server certificate")?;
+    let mut cert_reader = std::io::BufReader::new(cert_file);
+
+    let key_file = std::fs::File::open(key_path).context("Failed to open server private key")?;
+    let mut key_reader = std::io::BufReader::new(key_file);
+
+    let certs = rustls_pemfile::certs(&mut cert_reader)
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to parse server certificate")?;
+
+    let key = rustls_pemfile::private_key(&mut key_reader)
+        .context("Failed to read private key")?
+        .ok_or_else(|| anyhow!("No private key found in key file"))?;
+
+    // Configure client authentication if specified
+    let builder = TlsServerConfig::builder();
+    let mut server_config = match client_auth.filter(|a| a.required) {
+        Some(client_auth) => {
+            info!(
+                endpoint = %endpoint_id,
+                ca_path = %client_auth.ca_path,
+                "Configuring mutual TLS with client certificate verification"
+            );
+
+            let ca_file = std::fs::File::open(&client_auth.ca_path)
+                .context("Failed to open CA certificate")?;
+            let mut ca_reader = std::io::BufReader::new(ca_file);
+
+            let mut root_cert_store = rustls::RootCertStore::empty();
+            let ca_certs = rustls_pemfile::certs(&mut ca_reader)
+                .collect::<Result<Vec<_>, _>>()
+                .context("Failed to parse CA certificate")?;
+
+            for cert in ca_certs {
+                root_cert_store.add(cert).context("Failed to add CA certificate")?;
+            }
+
+            let client_verifier =
+                rustls::server::WebPkiClientVerifier::builder(Arc::new(root_cert_store))
+                    .build()
+                    .context("Failed to build client verifier")?;
+
+            builder
+                .with_client_cert_verifier(client_verifier)
+                .with_single_cert(certs, key)
+                .context("Failed to configure mutual TLS")?
+        }
+        None => builder
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .context("Invalid certificate or key")?,
+    };
+
+    // Set protocol versions (TLS 1.2 and 1.3)
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    Ok(Arc::new(server_config))
+}
+
 /// TLS Listener for accepting secure ATAK client connections
 pub struct TlsListener {
     config: ListenerConfig,
     pool: Arc<ConnectionPool>,
     aggregator: Arc<MessageAggregator>,
     state: Arc<ListenerState>,
-    tls_acceptor: Option<TlsAcceptor>,
+    router: Option<Arc<SniRouter>>,
+    client_access: Option<Arc<ClientAccessPolicy>>,
     accept_task: Option<JoinHandle<()>>,
+    inherited: Option<std::net::TcpListener>,
+    handover: Option<std::net::TcpListener>,
 }
 
 impl TlsListener {
@@ -636,9 +1057,9 @@ impl TlsListener {
         pool: Arc<ConnectionPool>,
         aggregator: Arc<MessageAggregator>,
     ) -> Result<Self> {
-        // Build TLS configuration
-        let tls_acceptor = if config.enabled {
-            Some(Self::build_tls_acceptor(&config)?)
+        // Build TLS configuration for every endpoint
+        let router = if config.enabled {
+            Some(Arc::new(SniRouter::build(&config)?))
         } else {
             None
         };
@@ -648,97 +1069,34 @@ impl TlsListener {
             pool,
             aggregator,
             state: Arc::new(ListenerState::new()),
-            tls_acceptor,
+            router,
+            client_access: None,
             accept_task: None,
+            inherited: None,
+            handover: None,
         })
     }
 
-    /// Build TLS acceptor from configuration
-    fn build_tls_acceptor(config: &ListenerConfig) -> Result<TlsAcceptor> {
-        let tls_config = config
-            .tls
-            .as_ref()
-            .ok_or_else(|| anyhow!("TLS configuration required for TLS listener"))?;
-
-        info!(
-            listener_id = %config.id,
-            cert_path = %tls_config.cert_path,
-            key_path = %tls_config.key_path,
-            "Building TLS server configuration"
-        );
-
-        // Load server certificate and key
-        let cert_file = std::fs::File::open(&tls_config.cert_path)
-            .context("Failed to open server certificate")?;
-        let mut cert_reader = std::io::BufReader::new(cert_file);
-
-        let key_file = std::fs::File::open(&tls_config.key_path)
-            .context("Failed to open server private key")?;
-        let mut key_reader = std::io::BufReader::new(key_file);
-
-        let certs = rustls_pemfile::certs(&mut cert_reader)
-            .collect::<Result<Vec<_>, _>>()
-            .context("Failed to parse server certificate")?;
-
-        let key = rustls_pemfile::private_key(&mut key_reader)
-            .context("Failed to read private key")?
-            .ok_or_else(|| anyhow!("No private key found in key file"))?;
-
-        // Build server config
-        let mut server_config = TlsServerConfig::builder()
-            .with_no_client_auth()
-            .with_single_cert(certs, key)
-            .context("Invalid certificate or key")?;
-
-        // Configure client authentication if specified
-        if let Some(client_auth) = &tls_config.client_auth {
-            if client_auth.required {
-                info!(
-                    listener_id = %config.id,
-                    ca_path = %client_auth.ca_path,
-                    "Configuring mutual TLS with client certificate verification"
-                );
-
-                let ca_file = std::fs::File::open(&client_auth.ca_path)
-                    .context("Failed to open CA certificate")?;
-                let mut ca_reader = std::io::BufReader::new(ca_file);
-
-                let mut root_cert_store = rustls::RootCertStore::empty();
-                let ca_certs = rustls_pemfile::certs(&mut ca_reader)
-                    .collect::<Result<Vec<_>, _>>()
-                    .context("Failed to parse CA certificate")?;
-
-                for cert in ca_certs {
-                    root_cert_store.add(cert).context("Failed to add CA certificate")?;
-                }
+    /// Use a socket inherited from a previous process instead of binding
+    pub fn set_inherited_socket(&mut self, socket: std::net::TcpListener) {
+        self.inherited = Some(socket);
+    }
 
-                let client_verifier = rustls::server::WebPkiClientVerifier::builder(
-                    Arc::new(root_cert_store)
-                )
-                .build()
-                .context("Failed to build client verifier")?;
-
-                // Rebuild with client auth
-                server_config = TlsServerConfig::builder()
-                    .with_client_cert_verifier(client_verifier)
-                    .with_single_cert(
-                        rustls_pemfile::certs(&mut std::io::BufReader::new(
-                            std::fs::File::open(&tls_config.cert_path)?
-                        ))
-                        .collect::<Result<Vec<_>, _>>()?,
-                        rustls_pemfile::private_key(&mut std::io::BufReader::new(
-                            std::fs::File::open(&tls_config.key_path)?
-                        ))?
-                        .ok_or_else(|| anyhow!("No private key found"))?,
-                    )
-                    .context("Failed to configure mutual TLS")?;
-            }
-        }
+    /// Authorize clients by their certificate
+    pub fn set_client_access_policy(&mut self, policy: Arc<ClientAccessPolicy>) {
+        self.client_access = Some(policy);
+    }
 
-        // Set protocol versions (TLS 1.2 and 1.3)
-        server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    /// Raw fd of the listening socket, for handing over during an upgrade
+    #[cfg(unix)]
+    pub fn handover_fd(&self) -> Option<std::os::unix::io::RawFd> {
+        use std::os::unix::io::AsRawFd;
+        self.handover.as_ref().map(|s| s.as_raw_fd())
+    }
 
-        Ok(TlsAcceptor::from(Arc::new(server_config)))
+    /// Listener identifier
+    pub fn id(&self) -> &str {
+        &self.config.id
     }
 
     /// Start the listener
@@ -751,10 +1109,10 @@ impl TlsListener {
             return Ok(());
         }
 
-        let tls_acceptor = self
-            .tls_acceptor
+        let router = self
+            .router
             .clone()
-            .ok_or_else(|| anyhow!("TLS acceptor not initialized"))?;
+            .ok_or_else(|| anyhow!("TLS configuration not initialized"))?;
 
         let bind_addr: SocketAddr = self
             .config
@@ -769,30 +1127,17 @@ impl TlsListener {
             "Starting TLS listener"
         );
 
-        let listener = TokioTcpListener::bind(bind_addr).await?;
-
-        // Enable SO_REUSEPORT
-        #[cfg(unix)]
-        {
-            use std::os::unix::io::AsRawFd;
-            let fd = listener.as_raw_fd();
-            unsafe {
-                let optval: libc::c_int = 1;
-                libc::setsockopt(
-                    fd,
-                    libc::SOL_SOCKET,
-                    libc::SO_REUSEPORT,
-                    &optval as *const _ as *const libc::c_void,
-                    std::mem::size_of_val(&optval) as libc::socklen_t,
-                );
-            }
-        }
+        let (listener, handover) = open_listener(bind_addr, self.inherited.take())?;
+        self.handover = Some(handover);
 
         let pool = Arc::clone(&self.pool);
         let aggregator = Arc::clone(&self.aggregator);
         let state = Arc::clone(&self.state);
+        let client_access = self.client_access.clone();
         let max_connections = self.config.max_connections;
         let listener_id = self.config.id.clone();
+        let compression = self.config.compression();
+        let strict_validation = self.config.strict_validation;
 
         // Spawn accept loop
         let accept_task = tokio::spawn(async move {
@@ -821,22 +1166,88 @@ impl TlsListener {
                             continue;
                         }
 
-                        // Perform TLS handshake
-                        let tls_acceptor_clone = tls_acceptor.clone();
+                        // Read the ClientHello, then handshake with the
+                        // endpoint its server name selects
+                        let router_clone = Arc::clone(&router);
+                        let client_access_clone = client_access.clone();
                         let pool_clone = Arc::clone(&pool);
                         let aggregator_clone = Arc::clone(&aggregator);
                         let state_clone = Arc::clone(&state);
                         let listener_id_clone = listener_id.clone();
 
                         tokio::spawn(async move {
-                            match tls_acceptor_clone.accept(stream).await {
+                            let acceptor = LazyConfigAcceptor::new(
+                                rustls::server::Acceptor::default(),
+                                stream,
+                            );
+                            let start = match acceptor.await {
+                                Ok(start) => start,
+                                Err(e) => {
+                                    error!(
+                                        listener_id = %listener_id_clone,
+                                        remote_addr = %remote_addr,
+                                        error = %e,
+                                        "TLS handshake failed"
+                                    );
+                                    state_clone.rejected.fetch_add(1, Ordering::Relaxed);
+                                    return;
+                                }
+                            };
+
+                            let server_name =
+                                start.client_hello().server_name().map(str::to_string);
+                            let Some(endpoint) = router_clone.route(server_name.as_deref()) else {
+                                warn!(
+                                    listener_id = %listener_id_clone,
+                                    remote_addr = %remote_addr,
+                                    server_name = ?server_name,
+                                    "No virtual host for server name, rejecting"
+                                );
+                                state_clone.rejected.fetch_add(1, Ordering::Relaxed);
+                                return;
+                            };
+                            if let Some(max) = endpoint.policy.max_connections {
+                                if endpoint.active.load(Ordering::Relaxed) >= max {
+                                    warn!(
+                                        listener_id = %listener_id_clone,
+                                        remote_addr = %remote_addr,
+                                        virtual_host = ?endpoint.virtual_host,
+                                        max,
+                                        "Endpoint connection limit reached, rejecting"
+                                    );
+                                    state_clone.rejected.fetch_add(1, Ordering::Relaxed);
+                                    return;
+                                }
+                            }
+
+                            match start.into_stream(Arc::clone(&endpoint.server_config)).await {
                                 Ok(tls_stream) => {
+                                    let authorization = client_access_clone
+                                        .as_deref()
+                                        .map(|policy| Self::authorize(policy, &tls_stream));
+                                    if let Some(auth) = &authorization {
+                                        if auth.access == ClientAccess::Deny {
+                                            warn!(
+                                                listener_id = %listener_id_clone,
+                                                remote_addr = %remote_addr,
+                                                rule = ?auth.rule,
+                                                "Client not authorized, rejecting"
+                                            );
+                                            state_clone.rejected.fetch_add(1, Ordering::Relaxed);
+                                            return;
+                                        }
+                                    }
+
                                     state_clone.accepted.fetch_add(1, Ordering::Relaxed);
                                     state_clone.active.fetch_add(1, Ordering::Relaxed);
+                                    endpoint.active.fetch_add(1, Ordering::Relaxed);
 
                                     info!(
                                         listener_id = %listener_id_clone,
                                         remote_addr = %remote_addr,
+                                        virtual_host = ?endpoint.virtual_host,
+                                        access = ?authorization.as_ref().map(|a| a.access),
+                                        group = ?authorization.as_ref().and_then(|a| a.group.as_deref()),
                                         active = current_active + 1,
                                         "Accepted TLS connection"
                                     );
@@ -848,6 +1259,10 @@ impl TlsListener {
                                         aggregator_clone,
                                         state_clone,
                                         listener_id_clone,
+                                        compression,
+                                        strict_validation,
+                                        endpoint,
+                                        authorization,
                                     )
                                     .await
                                     {
@@ -857,11 +1272,13 @@ impl TlsListener {
                                             "TLS connection handler error"
                                         );
                                     }
+                                    endpoint.active.fetch_sub(1, Ordering::Relaxed);
                                 }
                                 Err(e) => {
                                     error!(
                                         listener_id = %listener_id_clone,
                                         remote_addr = %remote_addr,
+                                        virtual_host = ?endpoint.virtual_host,
                                         error = %e,
                                         "TLS handshake failed"
                                     );
@@ -891,7 +1308,48 @@ impl TlsListener {
         Ok(())
     }
 
+    /// Authorize a client by the certificate it presented, or with the
+    /// default access if it presented none
+    fn authorize(
+        policy: &ClientAccessPolicy,
+        stream: &tokio_rustls::server::TlsStream<TcpStream>,
+    ) -> ClientAuthorization {
+        let Some(cert) = stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .and_then(|certs| certs.first())
+        else {
+            let authorization = policy.authorize_anonymous();
+            debug!(
+                access = authorization.access.as_str(),
+                "Authorized client without certificate"
+            );
+            return authorization;
+        };
+        let identity = match ClientIdentity::from_der(cert) {
+            Ok(identity) => identity,
+            Err(e) => {
+                // The certificate passed verification, so this is unexpected;
+                // treat the client like one no rule matches
+                warn!(error = %e, "Failed to read client certificate");
+                ClientIdentity::default()
+            }
+        };
+
+        let authorization = policy.authorize(&identity);
+        debug!(
+            cn = %identity.cn,
+            issuer = %identity.issuer,
+            rule = ?authorization.rule,
+            access = authorization.access.as_str(),
+            "Authorized client certificate"
+        );
+        authorization
+    }
+
     /// Handle an accepted TLS connection
+    #[allow(clippy::too_many_arguments)]
     async fn handle_tls_connection(
         stream: tokio_rustls::server::TlsStream<TcpStream>,
         remote_addr: SocketAddr,
@@ -899,13 +1357,23 @@ impl TlsListener {
         aggregator: Arc<MessageAggregator>,
         state: Arc<ListenerState>,
         listener_id: String,
+        compression: Compression,
+        strict_validation: bool,
+        endpoint: &Endpoint,
+        authorization: Option<ClientAuthorization>,
     ) -> Result<()> {
         let connection_id = format!("atak-client-tls-{}", remote_addr);
+        let policy = endpoint.policy;
+        // Read-only clients get the feed but may not publish
+        let publish = authorization
+            .as_ref()
+            .is_none_or(|auth| auth.access.can_publish());
 
         debug!(
             listener_id = %listener_id,
             connection_id = %connection_id,
             remote_addr = %remote_addr,
+            virtual_host = ?endpoint.virtual_host,
             "Setting up TLS connection handler"
         );
 
@@ -913,13 +1381,21 @@ impl TlsListener {
         let (_tx, mut rx) = mpsc::channel::<Vec<u8>>(1000);
 
         // Register with pool
-        pool.add_connection(
-            connection_id.clone(),
-            format!("ATAK Client TLS {}", remote_addr),
-            remote_addr.to_string(),
-            5,
-        )
-        .await?;
+        let mut name = match &endpoint.virtual_host {
+            Some(host) => format!("ATAK Client TLS {} ({})", remote_addr, host),
+            None => format!("ATAK Client TLS {}", remote_addr),
+        };
+        if let Some(group) = authorization
+            .as_ref()
+            .and_then(|auth| auth.group.as_deref())
+        {
+            name = format!("{} [{}]", name, group);
+        }
+        pool.add_connection(connection_id.clone(), name, remote_addr.to_string(), 5)
+            .await?;
+        aggregator
+            .quarantine()
+            .set_strict(connection_id.clone(), strict_validation);
 
         let connection = pool
             .get_connection(&connection_id)
@@ -939,7 +1415,7 @@ impl TlsListener {
             let mut buffer = BytesMut::with_capacity(INITIAL_BUFFER_CAPACITY);
 
             loop {
-                match Self::read_xml_frame(&mut read_half, &mut buffer).await {
+                match read_frame(&mut read_half, &mut buffer, compression).await {
                     Ok(Some(frame)) => {
                         let frame_len = frame.len();
                         state_read.bytes_received.fetch_add(frame_len as u64, Ordering::Relaxed);
@@ -951,10 +1427,17 @@ impl TlsListener {
                             "Received CoT message from TLS client"
                         );
 
+                        // The endpoint doesn't take CoT from its clients, or
+                        // this client may not publish
+                        if !policy.receive || !publish {
+                            continue;
+                        }
+
                         let inbound_msg = InboundMessage {
                             data: frame.to_vec(),
                             source: connection_id_read.clone(),
                             timestamp: Instant::now(),
+                            trace_id: TraceId::new(),
                         };
 
                         if let Err(e) = aggregator_sender.send_async(inbound_msg).await {
@@ -987,6 +1470,21 @@ impl TlsListener {
                         match msg {
                             Ok(pool_msg) => {
                                 if let omnitak_pool::PoolMessage::Cot(data) = pool_msg {
+                                    // The endpoint doesn't send CoT to its clients
+                                    if !policy.send {
+                                        continue;
+                                    }
+                                    let data = match encode_frame(data, compression) {
+                                        Ok(data) => data,
+                                        Err(e) => {
+                                            error!(
+                                                connection_id = %connection_id_write,
+                                                error = %e,
+                                                "Failed to compress frame"
+                                            );
+                                            continue;
+                                        }
+                                    };
                                     if let Err(e) = write_half.write_all(&data).await {
                                         error!(
                                             connection_id = %connection_id_write,
@@ -1031,6 +1529,7 @@ impl TlsListener {
         let _ = tokio::join!(read_task, write_task);
 
         state.active.fetch_sub(1, Ordering::Relaxed);
+        aggregator.quarantine().set_strict(connection_id.clone(), false);
         if let Err(e) = pool.remove_connection(&connection_id).await {
             warn!(connection_id = %connection_id, error = %e, "Failed to remove connection");
         }
@@ -1091,6 +1590,7 @@ impl TlsListener {
             task.abort();
             let _ = task.await;
         }
+        self.handover = None;
 
         info!(listener_id = %self.config.id, "TLS listener stopped");
         Ok(())
@@ -1130,13 +1630,71 @@ mod tests {
                     required: true,
                     ca_path: "/path/to/ca.pem".to_string(),
                 }),
+                policy: EndpointPolicy::default(),
+                virtual_hosts: Vec::new(),
+                strict_sni: false,
             }),
+            compression: false,
+            strict_validation: false,
         };
 
         assert_eq!(config.protocol, ListenerProtocol::Tls);
         assert!(config.tls.is_some());
     }
 
+    #[test]
+    fn test_virtual_hosts_config() {
+        let yaml = r#"
+cert_path: /certs/default.pem
+key_path: /certs/default-key.pem
+client_auth: null
+virtual_hosts:
+  - id: partners
+    server_names: [partners.example.com]
+    cert_path: /certs/partners.pem
+    key_path: /certs/partners-key.pem
+    policy:
+      send: false
+      max_connections: 20
+"#;
+        let config: TlsListenerConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.policy, EndpointPolicy::default());
+        assert!(!config.strict_sni);
+
+        let host = &config.virtual_hosts[0];
+        assert!(host.client_auth.is_none());
+        assert!(host.policy.receive);
+        assert!(!host.policy.send);
+        assert_eq!(host.policy.max_connections, Some(20));
+    }
+
+    #[test]
+    fn test_server_name_matches() {
+        assert!(server_name_matches("tak.example.com", "tak.example.com"));
+        assert!(!server_name_matches("tak.example.com", "example.com"));
+        assert!(server_name_matches("*.example.com", "tak.example.com"));
+        assert!(!server_name_matches("*.example.com", "example.com"));
+        assert!(!server_name_matches("*.example.com", "a.tak.example.com"));
+        assert!(!server_name_matches("*.example.com", ".example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_compressed_frame_round_trip() {
+        let compression = Compression::zstd();
+        let xml = b"<event uid=\"a\"><point lat=\"1\" lon=\"2\"/></event>".to_vec();
+
+        let mut wire = encode_frame(xml.clone(), compression).unwrap();
+        wire.extend(encode_frame(xml.clone(), compression).unwrap());
+
+        let mut reader = &wire[..];
+        let mut buffer = BytesMut::new();
+        for _ in 0..2 {
+            let frame = read_frame(&mut reader, &mut buffer, compression).await.unwrap();
+            assert_eq!(frame.as_deref(), Some(&xml[..]));
+        }
+        assert!(read_frame(&mut reader, &mut buffer, compression).await.unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn test_tcp_listener_creation() {
         let pool = Arc::new(ConnectionPool::new(PoolConfig::default()));
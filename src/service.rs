@@ -0,0 +1,476 @@
+//! Running as a Managed Service
+//!
+//! Integration with the service manager the server runs under:
+//!
+//! - systemd (`Type=notify`): readiness, reload and stop notifications over
+//!   `$NOTIFY_SOCKET`, and watchdog keep-alives when `WatchdogSec=` is set.
+//!   After an in-place upgrade the successor reports itself as the main
+//!   PID, which needs `NotifyAccess=all` in the unit.
+//! - Windows: registering with the Service Control Manager, and running
+//!   under it with stop requests turned into a graceful shutdown.
+//! - A pidfile that refuses to start a second instance over a live one.
+//! - Shutdown on `SIGTERM` as well as Ctrl-C, and config reload on `SIGHUP`.
+//!
+//! Notifications are no-ops when the process isn't run by systemd.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+// ============================================================================
+// systemd notifications
+// ============================================================================
+
+/// Startup (or a reload) finished and the server is accepting traffic
+pub fn notify_ready() {
+    #[cfg(unix)]
+    notify(&[
+        sd_notify::NotifyState::Ready,
+        sd_notify::NotifyState::MainPid(std::process::id()),
+    ]);
+}
+
+/// A reload started; followed by [`notify_ready`] once it is applied
+pub fn notify_reloading() {
+    #[cfg(unix)]
+    match sd_notify::NotifyState::monotonic_usec_now() {
+        Ok(now) => notify(&[sd_notify::NotifyState::Reloading, now]),
+        Err(e) => tracing::debug!("Failed to read monotonic clock: {}", e),
+    }
+}
+
+/// Graceful shutdown started
+pub fn notify_stopping() {
+    #[cfg(unix)]
+    notify(&[sd_notify::NotifyState::Stopping]);
+}
+
+#[cfg(unix)]
+fn notify(states: &[sd_notify::NotifyState]) {
+    // Keep NOTIFY_SOCKET set: an upgrade successor notifies through it too
+    if let Err(e) = sd_notify::notify(false, states) {
+        tracing::debug!("Failed to notify service manager: {}", e);
+    }
+}
+
+/// Send watchdog keep-alives at half the interval systemd expects them, if
+/// the unit has a watchdog
+pub fn spawn_watchdog() {
+    #[cfg(unix)]
+    {
+        let mut usec = 0;
+        if !sd_notify::watchdog_enabled(false, &mut usec) || usec == 0 {
+            return;
+        }
+        let interval = std::time::Duration::from_micros(usec / 2);
+        info!("systemd watchdog enabled, pinging every {:?}", interval);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                notify(&[sd_notify::NotifyState::Watchdog]);
+            }
+        });
+    }
+}
+
+// ============================================================================
+// Signals
+// ============================================================================
+
+/// Shutdown trigger: Ctrl-C, `SIGTERM` on Unix, or a stop request from the
+/// Windows Service Control Manager
+pub struct ShutdownSignal {
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+}
+
+impl ShutdownSignal {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            terminate: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                .context("Failed to install SIGTERM handler")?,
+        })
+    }
+
+    /// Wait for the next shutdown request
+    pub async fn recv(&mut self) {
+        #[cfg(unix)]
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = self.terminate.recv() => {}
+        }
+
+        #[cfg(windows)]
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = windows::stop_requested() => {}
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Config reload trigger (`SIGHUP` on Unix, never fires elsewhere)
+pub struct ReloadSignal {
+    #[cfg(unix)]
+    signal: tokio::signal::unix::Signal,
+}
+
+impl ReloadSignal {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            signal: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+                .context("Failed to install SIGHUP handler")?,
+        })
+    }
+
+    /// Wait for the next reload request
+    pub async fn recv(&mut self) {
+        #[cfg(unix)]
+        self.signal.recv().await;
+
+        #[cfg(not(unix))]
+        std::future::pending::<()>().await;
+    }
+}
+
+// ============================================================================
+// Pidfile
+// ============================================================================
+
+/// Pidfile holding this process's ID, removed on drop
+///
+/// Removal is skipped when the file no longer holds our ID, which is the
+/// case once an upgrade successor has taken it over.
+#[derive(Debug)]
+pub struct Pidfile {
+    path: PathBuf,
+    pid: u32,
+}
+
+impl Pidfile {
+    /// Write the pidfile, refusing when it names another live process.
+    /// With `takeover` (an upgrade successor) the previous process's ID is
+    /// overwritten.
+    pub fn create(path: &Path, takeover: bool) -> Result<Self> {
+        let pid = std::process::id();
+
+        if let Some(existing) = read_pid(path) {
+            if existing != pid && !takeover && process_alive(existing) {
+                anyhow::bail!(
+                    "Another instance (PID {}) holds the pidfile {}",
+                    existing,
+                    path.display()
+                );
+            }
+            if existing != pid && !takeover {
+                warn!("Replacing stale pidfile {} (PID {})", path.display(), existing);
+            }
+        }
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        // Write then rename so readers never see a partial file
+        let tmp = path.with_extension(format!("tmp.{}", pid));
+        std::fs::write(&tmp, format!("{}\n", pid))
+            .with_context(|| format!("Failed to write pidfile {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to write pidfile {}", path.display()))?;
+
+        info!("Wrote pidfile {} (PID {})", path.display(), pid);
+        Ok(Self {
+            path: path.to_path_buf(),
+            pid,
+        })
+    }
+}
+
+impl Drop for Pidfile {
+    fn drop(&mut self) {
+        if read_pid(&self.path) == Some(self.pid) {
+            if let Err(e) = std::fs::remove_file(&self.path) {
+                warn!("Failed to remove pidfile {}: {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Whether a process with this ID exists
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: signal 0 only checks whether the process can be signalled
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    // Exists, but belongs to another user
+    std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Without a cheap liveness check, an existing pidfile is assumed stale
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    false
+}
+
+// ============================================================================
+// Windows service
+// ============================================================================
+
+/// Name the service is registered under
+pub const SERVICE_NAME: &str = "OmniTAK";
+
+/// Register the server as an automatically started Windows service that
+/// runs with `config_path`
+pub fn install(config_path: &Path) -> Result<()> {
+    #[cfg(windows)]
+    return windows::install(config_path);
+
+    #[cfg(not(windows))]
+    {
+        let _ = config_path;
+        anyhow::bail!(
+            "Service registration is only available on Windows; on Linux install the \
+             systemd unit from deploy/systemd/omnitak.service"
+        )
+    }
+}
+
+/// Stop and remove the Windows service
+pub fn uninstall() -> Result<()> {
+    #[cfg(windows)]
+    return windows::uninstall();
+
+    #[cfg(not(windows))]
+    anyhow::bail!("Service registration is only available on Windows");
+}
+
+/// Run `serve` under the Windows Service Control Manager, reporting its
+/// state until it returns. Only the SCM starts the binary this way.
+pub fn run_as_service<F>(serve: F) -> Result<()>
+where
+    F: FnOnce() -> Result<()> + Send + 'static,
+{
+    #[cfg(windows)]
+    return windows::run(serve);
+
+    #[cfg(not(windows))]
+    {
+        let _ = serve;
+        anyhow::bail!("Running under the Service Control Manager is only available on Windows")
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::SERVICE_NAME;
+    use anyhow::{Context, Result};
+    use std::ffi::OsString;
+    use std::path::Path;
+    use std::sync::{Mutex, OnceLock};
+    use std::time::Duration;
+    use tokio::sync::Notify;
+    use tracing::{error, info};
+    use windows_service::service::{
+        ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl,
+        ServiceExitCode, ServiceInfo, ServiceStartType, ServiceState, ServiceStatus,
+        ServiceType,
+    };
+    use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+    use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+    use windows_service::{define_windows_service, service_dispatcher};
+
+    type Serve = Box<dyn FnOnce() -> Result<()> + Send>;
+
+    /// Server entry point handed from `run` to the SCM callback
+    static SERVE: Mutex<Option<Serve>> = Mutex::new(None);
+
+    /// Raised by the control handler on a stop or system shutdown
+    fn stop_notify() -> &'static Notify {
+        static STOP: OnceLock<Notify> = OnceLock::new();
+        STOP.get_or_init(Notify::new)
+    }
+
+    pub async fn stop_requested() {
+        stop_notify().notified().await
+    }
+
+    pub fn install(config_path: &Path) -> Result<()> {
+        let manager = ServiceManager::local_computer(
+            None::<&str>,
+            ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+        )
+        .context("Failed to connect to the Service Control Manager (run as Administrator)")?;
+
+        let config_path = std::fs::canonicalize(config_path)
+            .with_context(|| format!("Config file not found: {}", config_path.display()))?;
+        let info = ServiceInfo {
+            name: OsString::from(SERVICE_NAME),
+            display_name: OsString::from("OmniTAK TAK Aggregator"),
+            service_type: ServiceType::OWN_PROCESS,
+            start_type: ServiceStartType::AutoStart,
+            error_control: ServiceErrorControl::Normal,
+            executable_path: std::env::current_exe()?,
+            launch_arguments: vec![
+                OsString::from("--config"),
+                config_path.into_os_string(),
+                OsString::from("service"),
+                OsString::from("run"),
+            ],
+            dependencies: vec![],
+            account_name: None,
+            account_password: None,
+        };
+        let service = manager
+            .create_service(&info, ServiceAccess::CHANGE_CONFIG)
+            .context("Failed to create service")?;
+        service.set_description("High-performance TAK aggregator and message broker")?;
+
+        info!("Installed service {}", SERVICE_NAME);
+        Ok(())
+    }
+
+    pub fn uninstall() -> Result<()> {
+        let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+            .context("Failed to connect to the Service Control Manager (run as Administrator)")?;
+        let service = manager
+            .open_service(
+                SERVICE_NAME,
+                ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+            )
+            .context("Failed to open service")?;
+
+        if service.query_status()?.current_state != ServiceState::Stopped {
+            service.stop().context("Failed to stop service")?;
+        }
+        service.delete().context("Failed to delete service")?;
+
+        info!("Removed service {}", SERVICE_NAME);
+        Ok(())
+    }
+
+    pub fn run<F>(serve: F) -> Result<()>
+    where
+        F: FnOnce() -> Result<()> + Send + 'static,
+    {
+        *SERVE.lock().unwrap() = Some(Box::new(serve));
+        service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+            .context("Failed to start service dispatcher (not started by the SCM?)")
+    }
+
+    define_windows_service!(ffi_service_main, service_main);
+
+    fn service_main(_arguments: Vec<OsString>) {
+        if let Err(e) = run_service() {
+            error!("Service failed: {:#}", e);
+        }
+    }
+
+    fn run_service() -> Result<()> {
+        let status_handle = service_control_handler::register(SERVICE_NAME, |control| {
+            match control {
+                ServiceControl::Stop | ServiceControl::Shutdown => {
+                    // Stored if the shutdown loop isn't waiting yet
+                    stop_notify().notify_one();
+                    ServiceControlHandlerResult::NoError
+                }
+                ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+                _ => ServiceControlHandlerResult::NotImplemented,
+            }
+        })?;
+
+        let status = |state, controls_accepted, exit_code| ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted,
+            exit_code,
+            checkpoint: 0,
+            wait_hint: Duration::default(),
+            process_id: None,
+        };
+
+        status_handle.set_service_status(status(
+            ServiceState::Running,
+            ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+            ServiceExitCode::Win32(0),
+        ))?;
+
+        let serve = SERVE.lock().unwrap().take().context("Service started twice")?;
+        let result = serve();
+
+        let exit_code = match &result {
+            Ok(()) => ServiceExitCode::Win32(0),
+            Err(_) => ServiceExitCode::ServiceSpecific(1),
+        };
+        status_handle.set_service_status(status(
+            ServiceState::Stopped,
+            ServiceControlAccept::empty(),
+            exit_code,
+        ))?;
+        result
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("omnitak-{}-{}.pid", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_pidfile_removed_on_drop() {
+        let path = temp_path("pidfile-drop");
+
+        let pidfile = Pidfile::create(&path, false).unwrap();
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+
+        drop(pidfile);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_pidfile_refuses_live_process() {
+        let path = temp_path("pidfile-live");
+
+        // PID 1 is always alive
+        std::fs::write(&path, "1\n").unwrap();
+        assert!(Pidfile::create(&path, false).is_err());
+
+        // An upgrade successor takes it over, and the old process leaves
+        // it alone on exit
+        let pidfile = Pidfile::create(&path, true).unwrap();
+        drop(Pidfile {
+            path: path.clone(),
+            pid: 1,
+        });
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+
+        drop(pidfile);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_stale_pidfile_replaced() {
+        let path = temp_path("pidfile-stale");
+
+        std::fs::write(&path, format!("{}\n", i32::MAX)).unwrap();
+        let pidfile = Pidfile::create(&path, false).unwrap();
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+        drop(pidfile);
+    }
+}
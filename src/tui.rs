@@ -0,0 +1,595 @@
+//! Terminal dashboard
+//!
+//! `omnitak tui` watches a running instance from a terminal, for operators
+//! on headless servers where the egui GUI can't run. Status and connections
+//! are polled from the REST API; recent messages come from the track delta
+//! socket, which reports every position the distributor sends out.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use futures::StreamExt;
+use omnitak_api::types::{
+    ConnectionInfo, ConnectionList, ConnectionStatus, SystemStatus, TrackFeature,
+    WsServerMessage,
+};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Cell, List, ListItem, Paragraph, Row, Sparkline, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+use uuid::Uuid;
+
+use crate::cli::{json_name, ApiClient};
+
+/// Recent messages kept for the messages pane
+const MAX_RECENT: usize = 500;
+
+/// Throughput samples kept for the sparkline
+const MAX_HISTORY: usize = 240;
+
+/// How long to wait for a key press before redrawing
+const FRAME_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Delay before reconnecting after the track socket drops
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Data arriving from the background tasks
+enum Update {
+    Status(SystemStatus),
+    Connections(Vec<ConnectionInfo>),
+    PollFailed(String),
+    Message(RecentMessage),
+    /// Whether the track socket is connected
+    Stream(bool),
+}
+
+/// A track update shown in the messages pane
+#[derive(Debug, Clone)]
+struct RecentMessage {
+    time: DateTime<Utc>,
+    uid: String,
+    cot_type: String,
+    callsign: Option<String>,
+    removed: bool,
+}
+
+impl RecentMessage {
+    fn from_feature(feature: TrackFeature) -> Self {
+        Self {
+            time: feature.properties.time,
+            uid: feature.id,
+            cot_type: feature.properties.cot_type,
+            callsign: feature.properties.callsign,
+            removed: false,
+        }
+    }
+
+    fn removed(uid: String) -> Self {
+        Self {
+            time: Utc::now(),
+            uid,
+            cot_type: String::new(),
+            callsign: None,
+            removed: true,
+        }
+    }
+}
+
+/// A connection with the rates measured between the last two polls
+struct ConnectionRow {
+    info: ConnectionInfo,
+    rx_rate: f64,
+    tx_rate: f64,
+}
+
+/// Everything the dashboard shows
+#[derive(Default)]
+struct Dashboard {
+    status: Option<SystemStatus>,
+    connections: Vec<ConnectionRow>,
+    polled_at: Option<Instant>,
+    /// Messages per second, oldest first
+    history: VecDeque<u64>,
+    /// Newest first
+    recent: VecDeque<RecentMessage>,
+    paused: bool,
+    poll_error: Option<String>,
+    stream_connected: bool,
+}
+
+impl Dashboard {
+    fn apply(&mut self, update: Update, now: Instant) {
+        match update {
+            Update::Status(status) => {
+                if self.history.len() == MAX_HISTORY {
+                    self.history.pop_front();
+                }
+                self.history.push_back(status.messages_per_second.round() as u64);
+                self.status = Some(status);
+                self.poll_error = None;
+            }
+            Update::Connections(connections) => self.update_connections(connections, now),
+            Update::PollFailed(error) => self.poll_error = Some(error),
+            Update::Message(message) => {
+                if self.paused {
+                    return;
+                }
+                if self.recent.len() == MAX_RECENT {
+                    self.recent.pop_back();
+                }
+                self.recent.push_front(message);
+            }
+            Update::Stream(connected) => self.stream_connected = connected,
+        }
+    }
+
+    /// Replace the connection table, deriving rates from the previous poll
+    fn update_connections(&mut self, connections: Vec<ConnectionInfo>, now: Instant) {
+        let elapsed = self
+            .polled_at
+            .map(|at| now.duration_since(at).as_secs_f64())
+            .filter(|secs| *secs > 0.0);
+        let previous: HashMap<Uuid, (u64, u64)> = self
+            .connections
+            .iter()
+            .map(|row| {
+                (
+                    row.info.id,
+                    (row.info.messages_received, row.info.messages_sent),
+                )
+            })
+            .collect();
+
+        self.connections = connections
+            .into_iter()
+            .map(|info| {
+                let (rx_rate, tx_rate) = match (elapsed, previous.get(&info.id)) {
+                    (Some(secs), Some(&(rx, tx))) => (
+                        info.messages_received.saturating_sub(rx) as f64 / secs,
+                        info.messages_sent.saturating_sub(tx) as f64 / secs,
+                    ),
+                    _ => (0.0, 0.0),
+                };
+                ConnectionRow {
+                    info,
+                    rx_rate,
+                    tx_rate,
+                }
+            })
+            .collect();
+        self.polled_at = Some(now);
+    }
+
+    fn count(&self, status: ConnectionStatus) -> usize {
+        self.connections
+            .iter()
+            .filter(|row| row.info.status == status)
+            .count()
+    }
+}
+
+/// Run the dashboard until the operator quits
+pub fn run(client: ApiClient, refresh: Duration) -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()
+        .context("Failed to start runtime")?;
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    runtime.spawn(poll(client.clone(), refresh, tx.clone()));
+    runtime.spawn(follow_tracks(client.clone(), tx));
+
+    let mut terminal = ratatui::init();
+    let result = draw_loop(&mut terminal, client.base_url(), &mut rx);
+    ratatui::restore();
+
+    runtime.shutdown_background();
+    result
+}
+
+fn draw_loop(
+    terminal: &mut DefaultTerminal,
+    url: &str,
+    rx: &mut mpsc::UnboundedReceiver<Update>,
+) -> Result<()> {
+    let mut dashboard = Dashboard::default();
+    loop {
+        while let Ok(update) = rx.try_recv() {
+            dashboard.apply(update, Instant::now());
+        }
+        terminal.draw(|frame| draw(frame, &dashboard, url))?;
+
+        if !event::poll(FRAME_INTERVAL)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::Char('p') => dashboard.paused = !dashboard.paused,
+            KeyCode::Char('c') => dashboard.recent.clear(),
+            _ => {}
+        }
+    }
+}
+
+// ============================================================================
+// Background tasks
+// ============================================================================
+
+/// Poll status and connections every `refresh`
+async fn poll(client: ApiClient, refresh: Duration, tx: mpsc::UnboundedSender<Update>) {
+    let mut interval = tokio::time::interval(refresh);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let (status, connections) = tokio::join!(
+            client.get::<SystemStatus>("/api/v1/status"),
+            client.get::<ConnectionList>("/api/v1/connections?limit=1000"),
+        );
+        let updates = match (status, connections) {
+            (Ok(status), Ok(list)) => vec![
+                Update::Status(status),
+                Update::Connections(list.connections),
+            ],
+            (Err(e), _) | (_, Err(e)) => vec![Update::PollFailed(format!("{:#}", e))],
+        };
+        for update in updates {
+            if tx.send(update).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Follow the track delta socket, reconnecting when it drops
+async fn follow_tracks(client: ApiClient, tx: mpsc::UnboundedSender<Update>) {
+    loop {
+        let socket = match client.ws_request("/api/v1/tracks/stream") {
+            Ok(request) => tokio_tungstenite::connect_async(request).await.ok(),
+            Err(_) => None,
+        };
+        if let Some((mut socket, _)) = socket {
+            if tx.send(Update::Stream(true)).is_err() {
+                return;
+            }
+            while let Some(Ok(message)) = socket.next().await {
+                let Message::Text(text) = message else {
+                    continue;
+                };
+                // The snapshot sent on connect is current state, not news
+                let Ok(WsServerMessage::TrackDelta {
+                    added,
+                    updated,
+                    removed,
+                    ..
+                }) = serde_json::from_str(&text)
+                else {
+                    continue;
+                };
+                let messages = added
+                    .into_iter()
+                    .chain(updated)
+                    .map(RecentMessage::from_feature)
+                    .chain(removed.into_iter().map(RecentMessage::removed));
+                for message in messages {
+                    if tx.send(Update::Message(message)).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+        if tx.send(Update::Stream(false)).is_err() {
+            return;
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+// ============================================================================
+// Rendering
+// ============================================================================
+
+fn draw(frame: &mut Frame, dashboard: &Dashboard, url: &str) {
+    let [header, overview, connections, messages, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(6),
+        Constraint::Min(6),
+        Constraint::Min(6),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    draw_header(frame, header, dashboard, url);
+    draw_overview(frame, overview, dashboard);
+    draw_connections(frame, connections, dashboard);
+    draw_messages(frame, messages, dashboard);
+
+    frame.render_widget(
+        Line::from(vec![
+            " q ".reversed(),
+            " quit  ".into(),
+            " p ".reversed(),
+            if dashboard.paused {
+                " resume  ".into()
+            } else {
+                " pause  ".into()
+            },
+            " c ".reversed(),
+            " clear messages".into(),
+        ]),
+        footer,
+    );
+}
+
+fn draw_header(frame: &mut Frame, area: Rect, dashboard: &Dashboard, url: &str) {
+    let mut spans = vec![Span::styled("OmniTAK", Style::new().bold())];
+    if let Some(status) = &dashboard.status {
+        spans.push(Span::raw(format!(" v{}", status.version)));
+    }
+    spans.push(Span::raw(format!("  {}  ", url)));
+    spans.push(match (&dashboard.status, &dashboard.poll_error) {
+        (_, Some(error)) => Span::styled(format!("● unreachable: {}", error), Color::Red),
+        (Some(status), None) => Span::styled(
+            format!("● up {}", format_duration(status.uptime_seconds)),
+            Color::Green,
+        ),
+        (None, None) => Span::styled("● connecting…", Color::Yellow),
+    });
+    frame.render_widget(Line::from(spans), area);
+}
+
+fn draw_overview(frame: &mut Frame, area: Rect, dashboard: &Dashboard) {
+    let [summary, throughput] =
+        Layout::horizontal([Constraint::Length(44), Constraint::Min(20)]).areas(area);
+
+    let lines = match &dashboard.status {
+        Some(status) => vec![
+            Line::from(vec![
+                Span::raw(format!("Connections  {} ", dashboard.connections.len())),
+                Span::styled(
+                    format!("{} up ", dashboard.count(ConnectionStatus::Connected)),
+                    Color::Green,
+                ),
+                Span::styled(
+                    format!("{} connecting ", dashboard.count(ConnectionStatus::Connecting)),
+                    Color::Yellow,
+                ),
+                Span::styled(
+                    format!("{} error", dashboard.count(ConnectionStatus::Error)),
+                    Color::Red,
+                ),
+            ]),
+            Line::raw(format!(
+                "Messages     {} ({:.1}/s)",
+                status.messages_processed, status.messages_per_second
+            )),
+            Line::raw(format!(
+                "Memory       {}",
+                format_bytes(status.memory_usage_bytes)
+            )),
+            Line::raw(format!("Filters      {}", status.active_filters)),
+        ],
+        None => vec![Line::raw("Waiting for status…")],
+    };
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Overview ")),
+        summary,
+    );
+
+    let data: Vec<u64> = dashboard.history.iter().copied().collect();
+    // Newest samples on the right edge
+    let width = throughput.width.saturating_sub(2) as usize;
+    let visible = &data[data.len().saturating_sub(width)..];
+    let peak = visible.iter().copied().max().unwrap_or(0);
+    frame.render_widget(
+        Sparkline::default()
+            .block(Block::bordered().title(format!(" Throughput (msg/s, peak {}) ", peak)))
+            .data(visible)
+            .style(Color::Cyan),
+        throughput,
+    );
+}
+
+fn draw_connections(frame: &mut Frame, area: Rect, dashboard: &Dashboard) {
+    let header = Row::new([
+        "NAME", "TYPE", "STATUS", "ADDRESS", "RX", "TX", "RX/S", "TX/S", "ACTIVITY",
+    ])
+    .style(Style::new().add_modifier(Modifier::BOLD));
+
+    let now = Utc::now();
+    let rows = dashboard.connections.iter().map(|row| {
+        let info = &row.info;
+        let color = match info.status {
+            ConnectionStatus::Connected => Color::Green,
+            ConnectionStatus::Connecting => Color::Yellow,
+            ConnectionStatus::Disconnected => Color::DarkGray,
+            ConnectionStatus::Error => Color::Red,
+        };
+        let activity = match (&info.error, info.last_activity) {
+            (Some(error), _) => Cell::from(error.as_str()).style(Color::Red),
+            (None, Some(at)) => Cell::from(format!(
+                "{} ago",
+                format_duration((now - at).num_seconds().max(0) as u64)
+            )),
+            (None, None) => Cell::from("-"),
+        };
+        Row::new([
+            Cell::from(info.name.as_str()),
+            Cell::from(json_name(&info.connection_type)),
+            Cell::from(json_name(&info.status)).style(color),
+            Cell::from(format!("{}:{}", info.address, info.port)),
+            Cell::from(info.messages_received.to_string()),
+            Cell::from(info.messages_sent.to_string()),
+            Cell::from(format!("{:.1}", row.rx_rate)),
+            Cell::from(format!("{:.1}", row.tx_rate)),
+            activity,
+        ])
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Min(12),
+            Constraint::Length(10),
+            Constraint::Length(12),
+            Constraint::Min(16),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Min(10),
+        ],
+    )
+    .header(header)
+    .block(Block::bordered().title(format!(
+        " Connections ({}) ",
+        dashboard.connections.len()
+    )));
+    frame.render_widget(table, area);
+}
+
+fn draw_messages(frame: &mut Frame, area: Rect, dashboard: &Dashboard) {
+    let mut title = vec![Span::raw(format!(
+        " Recent messages ({}) ",
+        dashboard.recent.len()
+    ))];
+    if dashboard.paused {
+        title.push(Span::styled("[paused] ", Color::Yellow));
+    }
+    if !dashboard.stream_connected {
+        title.push(Span::styled("[stream disconnected] ", Color::Red));
+    }
+
+    let items = dashboard
+        .recent
+        .iter()
+        .take(area.height as usize)
+        .map(|message| {
+            let time = message.time.with_timezone(&Local).format("%H:%M:%S");
+            let line = if message.removed {
+                Line::from(vec![
+                    Span::raw(format!("{}  ", time)),
+                    Span::styled("removed", Color::DarkGray),
+                    Span::raw(format!("  {}", message.uid)),
+                ])
+            } else {
+                Line::from(vec![
+                    Span::raw(format!("{}  ", time)),
+                    Span::styled(format!("{:<16}", message.cot_type), Color::Cyan),
+                    Span::styled(
+                        format!("  {:<20}", message.callsign.as_deref().unwrap_or("-")),
+                        Style::new().bold(),
+                    ),
+                    Span::raw(format!("  {}", message.uid)),
+                ])
+            };
+            ListItem::new(line)
+        });
+
+    frame.render_widget(
+        List::new(items).block(Block::bordered().title(Line::from(title))),
+        area,
+    );
+}
+
+/// `90061` → `1d 01h 01m`
+fn format_duration(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86_400, secs / 3_600 % 24, secs / 60 % 60);
+    if days > 0 {
+        format!("{}d {:02}h {:02}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const MIB: f64 = 1024.0 * 1024.0;
+    if bytes as f64 >= 1024.0 * MIB {
+        format!("{:.1} GiB", bytes as f64 / (1024.0 * MIB))
+    } else {
+        format!("{:.1} MiB", bytes as f64 / MIB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use omnitak_api::types::ConnectionType;
+
+    fn connection(id: Uuid, received: u64, sent: u64) -> ConnectionInfo {
+        ConnectionInfo {
+            id,
+            name: "tak".to_string(),
+            connection_type: ConnectionType::TlsClient,
+            status: ConnectionStatus::Connected,
+            address: "tak.example.com".to_string(),
+            port: 8089,
+            messages_received: received,
+            messages_sent: sent,
+            bytes_received: 0,
+            bytes_sent: 0,
+            connected_at: None,
+            last_activity: None,
+            error: None,
+            tenant: None,
+        }
+    }
+
+    #[test]
+    fn test_connection_rates_from_previous_poll() {
+        let id = Uuid::new_v4();
+        let start = Instant::now();
+        let mut dashboard = Dashboard::default();
+
+        dashboard.apply(Update::Connections(vec![connection(id, 100, 10)]), start);
+        assert_eq!(dashboard.connections[0].rx_rate, 0.0);
+
+        dashboard.apply(
+            Update::Connections(vec![connection(id, 140, 30), connection(Uuid::new_v4(), 5, 5)]),
+            start + Duration::from_secs(2),
+        );
+        assert_eq!(dashboard.connections[0].rx_rate, 20.0);
+        assert_eq!(dashboard.connections[0].tx_rate, 10.0);
+        // New connections have no previous sample
+        assert_eq!(dashboard.connections[1].rx_rate, 0.0);
+    }
+
+    #[test]
+    fn test_recent_messages_capped_and_paused() {
+        let mut dashboard = Dashboard::default();
+        let now = Instant::now();
+        for i in 0..MAX_RECENT + 10 {
+            dashboard.apply(Update::Message(RecentMessage::removed(i.to_string())), now);
+        }
+        assert_eq!(dashboard.recent.len(), MAX_RECENT);
+        assert_eq!(dashboard.recent[0].uid, (MAX_RECENT + 9).to_string());
+
+        dashboard.paused = true;
+        dashboard.apply(Update::Message(RecentMessage::removed("late".into())), now);
+        assert_eq!(dashboard.recent[0].uid, (MAX_RECENT + 9).to_string());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(42), "42s");
+        assert_eq!(format_duration(125), "2m 05s");
+        assert_eq!(format_duration(3_660), "1h 01m");
+        assert_eq!(format_duration(90_061), "1d 01h 01m");
+    }
+}
@@ -0,0 +1,343 @@
+//! In-place Binary Upgrade (Socket Handover)
+//!
+//! Lets a running OmniTAK replace itself with a new binary without dropping
+//! its listening sockets. On `SIGUSR2` the old process re-executes its own
+//! executable path (which may have been replaced on disk) with the same
+//! arguments, passing the listening sockets as inherited file descriptors and
+//! a state file with the outbound TAK server definitions. The new process
+//! adopts the sockets instead of binding them and reports readiness over a
+//! pipe; only then does the old process stop accepting, drain its existing
+//! client connections, and exit. If the new process never becomes ready, it
+//! is killed and the old process keeps serving.
+//!
+//! ```text
+//!    old process                          new process
+//!    ───────────                          ───────────
+//!    SIGUSR2
+//!    clear FD_CLOEXEC on listeners
+//!    spawn current_exe ─────────────────▶ adopt inherited listeners
+//!                                         load handoff state
+//!    wait for ready byte ◀─────────────── notify_ready()
+//!    stop listeners, drain, exit
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+
+/// Listener ID → fd pairs inherited from the previous process (`id=fd,id=fd`)
+pub const LISTENERS_ENV: &str = "OMNITAK_UPGRADE_LISTENERS";
+
+/// Path of the handoff state file written by the previous process
+pub const STATE_ENV: &str = "OMNITAK_UPGRADE_STATE";
+
+/// Write end of the readiness pipe
+pub const READY_FD_ENV: &str = "OMNITAK_UPGRADE_READY_FD";
+
+/// Sockets and state handed over by a previous process
+///
+/// Empty on a normal (non-upgrade) start.
+#[derive(Debug, Default)]
+pub struct Inherited {
+    listeners: HashMap<String, std::net::TcpListener>,
+    state_path: Option<PathBuf>,
+    #[cfg(unix)]
+    ready_fd: Option<RawFd>,
+}
+
+impl Inherited {
+    /// Collect anything passed by a parent process and clear the environment
+    /// so it isn't passed on to unrelated children.
+    pub fn from_env() -> Self {
+        let mut inherited = Self::default();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::FromRawFd;
+
+            if let Ok(spec) = std::env::var(LISTENERS_ENV) {
+                for (id, fd) in parse_listener_spec(&spec) {
+                    // SAFETY: the parent passed this fd for us to own
+                    let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+                    inherited.listeners.insert(id, listener);
+                }
+            }
+
+            inherited.ready_fd = std::env::var(READY_FD_ENV)
+                .ok()
+                .and_then(|fd| fd.parse().ok());
+        }
+
+        inherited.state_path = std::env::var_os(STATE_ENV).map(PathBuf::from);
+
+        std::env::remove_var(LISTENERS_ENV);
+        std::env::remove_var(STATE_ENV);
+        std::env::remove_var(READY_FD_ENV);
+
+        if inherited.is_upgrade() {
+            info!(
+                listeners = inherited.listeners.len(),
+                "Started as upgrade successor, adopting inherited sockets"
+            );
+        }
+
+        inherited
+    }
+
+    /// Whether this process was started by an upgrading parent
+    pub fn is_upgrade(&self) -> bool {
+        !self.listeners.is_empty() || self.state_path.is_some()
+    }
+
+    /// Take the inherited socket for a listener, if one was passed
+    pub fn take_listener(&mut self, id: &str) -> Option<std::net::TcpListener> {
+        self.listeners.remove(id)
+    }
+
+    /// Load and remove the handoff state file, if one was passed
+    pub fn load_state<T: DeserializeOwned>(&mut self) -> Result<Option<T>> {
+        let Some(path) = self.state_path.take() else {
+            return Ok(None);
+        };
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read upgrade state: {:?}", path))?;
+        let _ = std::fs::remove_file(&path);
+
+        let state = serde_yaml::from_str(&content).context("Failed to parse upgrade state")?;
+        Ok(Some(state))
+    }
+
+    /// Tell the parent process we are serving so it can start draining
+    ///
+    /// Any inherited sockets that were not adopted are closed.
+    pub fn notify_ready(&mut self) {
+        for (id, _) in self.listeners.drain() {
+            warn!(listener_id = %id, "Inherited listener no longer configured, closing");
+        }
+
+        #[cfg(unix)]
+        if let Some(fd) = self.ready_fd.take() {
+            use std::io::Write;
+            use std::os::unix::io::FromRawFd;
+
+            // SAFETY: the parent passed this fd for us to own
+            let mut pipe = unsafe { std::fs::File::from_raw_fd(fd) };
+            if let Err(e) = pipe.write_all(&[1]) {
+                warn!(error = %e, "Failed to notify parent process of readiness");
+            }
+        }
+    }
+}
+
+/// Parse `id=fd,id=fd` into pairs, skipping malformed entries
+#[cfg(unix)]
+fn parse_listener_spec(spec: &str) -> Vec<(String, RawFd)> {
+    spec.split(',')
+        .filter_map(|entry| {
+            let (id, fd) = entry.rsplit_once('=')?;
+            Some((id.to_string(), fd.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// A freshly spawned successor process that has not yet reported readiness
+#[cfg(unix)]
+pub struct Successor {
+    child: std::process::Child,
+    ready: std::fs::File,
+    state_path: PathBuf,
+}
+
+/// Re-execute the current binary, handing over listening sockets and state
+#[cfg(unix)]
+pub fn spawn_successor<S: Serialize>(
+    listeners: &[(String, RawFd)],
+    state: &S,
+) -> Result<Successor> {
+    use std::os::unix::io::FromRawFd;
+
+    let exe = std::env::current_exe().context("Failed to resolve current executable")?;
+    let state_path =
+        std::env::temp_dir().join(format!("omnitak-upgrade-{}.yaml", std::process::id()));
+    std::fs::write(&state_path, serde_yaml::to_string(state)?)
+        .with_context(|| format!("Failed to write upgrade state: {:?}", state_path))?;
+
+    let mut fds = [0 as RawFd; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error()).context("Failed to create readiness pipe");
+    }
+    let [read_fd, write_fd] = fds;
+    set_cloexec(read_fd, true)?;
+
+    // Only the listeners and the pipe's write end cross the exec boundary
+    for (_, fd) in listeners {
+        set_cloexec(*fd, false)?;
+    }
+
+    let spec = listeners
+        .iter()
+        .map(|(id, fd)| format!("{}={}", id, fd))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    info!(exe = ?exe, listeners = listeners.len(), "Spawning upgrade successor");
+
+    let spawned = std::process::Command::new(&exe)
+        .args(std::env::args_os().skip(1))
+        .env(LISTENERS_ENV, spec)
+        .env(STATE_ENV, &state_path)
+        .env(READY_FD_ENV, write_fd.to_string())
+        .spawn();
+
+    for (_, fd) in listeners {
+        let _ = set_cloexec(*fd, true);
+    }
+    unsafe { libc::close(write_fd) };
+
+    // SAFETY: read_fd is a fresh pipe end owned by nothing else
+    let ready = unsafe { std::fs::File::from_raw_fd(read_fd) };
+
+    let child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            let _ = std::fs::remove_file(&state_path);
+            return Err(e).with_context(|| format!("Failed to spawn {:?}", exe));
+        }
+    };
+
+    Ok(Successor {
+        child,
+        ready,
+        state_path,
+    })
+}
+
+#[cfg(unix)]
+impl Successor {
+    /// Wait for the successor to report readiness, returning its PID
+    ///
+    /// The successor is killed if it exits or times out before becoming ready.
+    pub async fn wait_ready(mut self, timeout: std::time::Duration) -> Result<u32> {
+        use std::io::Read;
+
+        let pid = self.child.id();
+        let mut ready = self.ready;
+        let read = tokio::task::spawn_blocking(move || {
+            let mut byte = [0u8; 1];
+            ready.read(&mut byte).map(|n| n == 1)
+        });
+
+        match tokio::time::timeout(timeout, read).await {
+            Ok(Ok(Ok(true))) => {
+                info!(pid, "Upgrade successor is ready");
+                Ok(pid)
+            }
+            outcome => {
+                let _ = self.child.kill();
+                let _ = self.child.wait();
+                let _ = std::fs::remove_file(&self.state_path);
+                match outcome {
+                    Err(_) => anyhow::bail!("Successor {} not ready after {:?}", pid, timeout),
+                    _ => anyhow::bail!("Successor {} exited before becoming ready", pid),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn set_cloexec(fd: RawFd, cloexec: bool) -> Result<()> {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        if flags < 0 {
+            return Err(std::io::Error::last_os_error()).context("fcntl(F_GETFD) failed");
+        }
+        let flags = if cloexec {
+            flags | libc::FD_CLOEXEC
+        } else {
+            flags & !libc::FD_CLOEXEC
+        };
+        if libc::fcntl(fd, libc::F_SETFD, flags) < 0 {
+            return Err(std::io::Error::last_os_error()).context("fcntl(F_SETFD) failed");
+        }
+    }
+    Ok(())
+}
+
+/// Upgrade trigger (`SIGUSR2` on Unix, never fires elsewhere)
+pub struct UpgradeSignal {
+    #[cfg(unix)]
+    signal: tokio::signal::unix::Signal,
+}
+
+impl UpgradeSignal {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            signal: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::user_defined2())
+                .context("Failed to install SIGUSR2 handler")?,
+        })
+    }
+
+    /// Wait for the next upgrade request
+    pub async fn recv(&mut self) {
+        #[cfg(unix)]
+        self.signal.recv().await;
+
+        #[cfg(not(unix))]
+        std::future::pending::<()>().await;
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_listener_spec() {
+        let parsed = parse_listener_spec("omnitak-api=5,tcp-8087=6,bogus,tls=x");
+        assert_eq!(
+            parsed,
+            vec![
+                ("omnitak-api".to_string(), 5),
+                ("tcp-8087".to_string(), 6),
+            ]
+        );
+        assert!(parse_listener_spec("").is_empty());
+    }
+
+    #[test]
+    fn test_set_cloexec_round_trip() {
+        use std::os::unix::io::AsRawFd;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let fd = listener.as_raw_fd();
+
+        set_cloexec(fd, false).unwrap();
+        assert_eq!(unsafe { libc::fcntl(fd, libc::F_GETFD) } & libc::FD_CLOEXEC, 0);
+
+        set_cloexec(fd, true).unwrap();
+        assert_ne!(unsafe { libc::fcntl(fd, libc::F_GETFD) } & libc::FD_CLOEXEC, 0);
+    }
+
+    #[test]
+    fn test_load_state_removes_file() {
+        let path = std::env::temp_dir().join(format!("omnitak-upgrade-test-{}.yaml", std::process::id()));
+        std::fs::write(&path, "- alpha\n- bravo\n").unwrap();
+
+        let mut inherited = Inherited {
+            state_path: Some(path.clone()),
+            ..Default::default()
+        };
+        let state: Vec<String> = inherited.load_state().unwrap().unwrap();
+        assert_eq!(state, vec!["alpha", "bravo"]);
+        assert!(!path.exists());
+        assert!(inherited.load_state::<Vec<String>>().unwrap().is_none());
+    }
+}
@@ -0,0 +1,362 @@
+//! Webhook Notifications
+//!
+//! Posts events from the system event bus (see [`crate::events`]) to
+//! configured HTTP endpoints. Each endpoint can subscribe to a subset of
+//! event kinds.
+//!
+//! Failed deliveries (network errors, 5xx, 429) are retried with exponential
+//! backoff. Every delivery outcome is logged under the `webhook_audit` target
+//! and optionally appended to a JSON-lines audit file.
+
+use crate::events::{EventBus, EventKind, EventsConfig, SystemEvent};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Webhook configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebhooksConfig {
+    #[serde(default)]
+    pub endpoints: Vec<WebhookEndpoint>,
+    /// Append a JSON line per delivery attempt sequence to this file
+    #[serde(default)]
+    pub audit_file: Option<PathBuf>,
+    /// Deprecated, use `events.cert_warning_days`
+    #[serde(default, skip_serializing)]
+    pub cert_warning_days: Option<i64>,
+    /// Deprecated, use `events.cert_check_interval_hours`
+    #[serde(default, skip_serializing)]
+    pub cert_check_interval_hours: Option<u64>,
+    /// Deprecated, use `events.filter_match_interval_secs`
+    #[serde(default, skip_serializing)]
+    pub filter_match_interval_secs: Option<u64>,
+}
+
+impl WebhooksConfig {
+    /// Apply the event producer settings still configured here, where they
+    /// lived before the event bus, to `events`. Returns the keys applied.
+    pub fn migrate_event_settings(&self, events: &mut EventsConfig) -> Vec<&'static str> {
+        let mut moved = Vec::new();
+        if let Some(days) = self.cert_warning_days {
+            events.cert_warning_days = days;
+            moved.push("cert_warning_days");
+        }
+        if let Some(hours) = self.cert_check_interval_hours {
+            events.cert_check_interval_hours = hours;
+            moved.push("cert_check_interval_hours");
+        }
+        if let Some(secs) = self.filter_match_interval_secs {
+            events.filter_match_interval_secs = secs;
+            moved.push("filter_match_interval_secs");
+        }
+        moved
+    }
+}
+
+/// A single webhook receiver
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    /// Value for the auth header, e.g. `Bearer <token>`
+    #[serde(default)]
+    pub auth_header: Option<String>,
+    #[serde(default = "default_auth_header_name")]
+    pub auth_header_name: String,
+    /// Events to deliver; empty means all
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    /// Attempts per event, including the first
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[serde(default = "default_max_backoff_secs")]
+    pub max_backoff_secs: u64,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl WebhookEndpoint {
+    fn wants(&self, kind: EventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+
+    /// Delay before retry number `attempt` (1 = first retry)
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        Duration::from_millis(self.initial_backoff_ms.saturating_mul(factor))
+            .min(Duration::from_secs(self.max_backoff_secs))
+    }
+}
+
+fn default_auth_header_name() -> String {
+    "Authorization".to_string()
+}
+
+fn default_max_attempts() -> u32 {
+    5
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_max_backoff_secs() -> u64 {
+    60
+}
+
+fn default_timeout_secs() -> u64 {
+    10
+}
+
+/// JSON body posted to each endpoint
+#[derive(Debug, Serialize)]
+struct WebhookPayload<'a> {
+    id: Uuid,
+    timestamp: DateTime<Utc>,
+    source: &'static str,
+    #[serde(flatten)]
+    event: &'a SystemEvent,
+}
+
+/// Audit record for one event delivered (or not) to one endpoint
+#[derive(Debug, Serialize)]
+struct DeliveryRecord<'a> {
+    timestamp: DateTime<Utc>,
+    event_id: Uuid,
+    event: EventKind,
+    url: &'a str,
+    attempts: u32,
+    delivered: bool,
+    status: Option<u16>,
+    error: Option<String>,
+}
+
+/// Deliver bus events to the configured endpoints. Does nothing when no
+/// endpoints are configured.
+pub fn spawn(config: WebhooksConfig, bus: &EventBus) {
+    if config.endpoints.is_empty() {
+        return;
+    }
+
+    info!(
+        "Webhook notifications enabled for {} endpoint(s)",
+        config.endpoints.len()
+    );
+    tokio::spawn(dispatch(Arc::new(config), bus.subscribe()));
+}
+
+async fn dispatch(config: Arc<WebhooksConfig>, mut rx: broadcast::Receiver<SystemEvent>) {
+    let client = reqwest::Client::new();
+
+    loop {
+        let event = match rx.recv().await {
+            Ok(event) => Arc::new(event),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Webhook dispatcher fell behind, dropped {} events", skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        for (index, endpoint) in config.endpoints.iter().enumerate() {
+            if !endpoint.wants(event.kind()) {
+                continue;
+            }
+            let client = client.clone();
+            let config = Arc::clone(&config);
+            let event = Arc::clone(&event);
+            // Deliver concurrently so a slow endpoint can't hold up the others
+            tokio::spawn(async move {
+                deliver(
+                    &client,
+                    &config.endpoints[index],
+                    &event,
+                    config.audit_file.as_ref(),
+                )
+                .await;
+            });
+        }
+    }
+}
+
+async fn deliver(
+    client: &reqwest::Client,
+    endpoint: &WebhookEndpoint,
+    event: &SystemEvent,
+    audit_file: Option<&PathBuf>,
+) {
+    let payload = WebhookPayload {
+        id: Uuid::new_v4(),
+        timestamp: Utc::now(),
+        source: "omnitak",
+        event,
+    };
+
+    let max_attempts = endpoint.max_attempts.max(1);
+    let mut attempts = 0;
+    let (delivered, status, error) = loop {
+        attempts += 1;
+
+        let mut request = client
+            .post(&endpoint.url)
+            .timeout(Duration::from_secs(endpoint.timeout_secs))
+            .header("X-OmniTAK-Delivery", payload.id.to_string())
+            .json(&payload);
+        if let Some(value) = &endpoint.auth_header {
+            request = request.header(endpoint.auth_header_name.as_str(), value);
+        }
+
+        let (retryable, outcome) = match request.send().await {
+            Ok(response) if response.status().is_success() => {
+                break (true, Some(response.status().as_u16()), None);
+            }
+            Ok(response) => {
+                let status = response.status();
+                let retryable = status.is_server_error() || status.as_u16() == 429;
+                (
+                    retryable,
+                    (Some(status.as_u16()), format!("HTTP {}", status)),
+                )
+            }
+            Err(e) => (true, (None, e.to_string())),
+        };
+
+        if !retryable || attempts >= max_attempts {
+            break (false, outcome.0, Some(outcome.1));
+        }
+
+        let delay = endpoint.backoff(attempts);
+        debug!(
+            "Webhook {} attempt {} failed ({}), retrying in {:?}",
+            endpoint.url, attempts, outcome.1, delay
+        );
+        tokio::time::sleep(delay).await;
+    };
+
+    let record = DeliveryRecord {
+        timestamp: Utc::now(),
+        event_id: payload.id,
+        event: event.kind(),
+        url: &endpoint.url,
+        attempts,
+        delivered,
+        status,
+        error,
+    };
+
+    if delivered {
+        info!(target: "webhook_audit", "Delivered {:?} to {} ({} attempt(s))", record.event, record.url, attempts);
+    } else {
+        warn!(
+            target: "webhook_audit",
+            "Failed to deliver {:?} to {} after {} attempt(s): {}",
+            record.event,
+            record.url,
+            attempts,
+            record.error.as_deref().unwrap_or("unknown error")
+        );
+    }
+
+    if let Some(path) = audit_file {
+        if let Err(e) = append_audit(path, &record).await {
+            warn!("Failed to write webhook audit to {}: {}", path.display(), e);
+        }
+    }
+}
+
+async fn append_audit(path: &PathBuf, record: &DeliveryRecord<'_>) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(record)?;
+    line.push(b'\n');
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(&line).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(yaml: &str) -> WebhookEndpoint {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_endpoint_defaults_and_event_filter() {
+        let all = endpoint("url: http://localhost/hook");
+        assert_eq!(all.max_attempts, 5);
+        assert_eq!(all.auth_header_name, "Authorization");
+        assert!(all.wants(EventKind::FilterMatch));
+
+        let some =
+            endpoint("url: http://localhost/hook\nevents: [connection_down, certificate_expiry]");
+        assert!(some.wants(EventKind::ConnectionDown));
+        assert!(!some.wants(EventKind::ConnectionUp));
+    }
+
+    #[test]
+    fn test_backoff_schedule() {
+        let endpoint =
+            endpoint("url: http://localhost/hook\ninitial_backoff_ms: 500\nmax_backoff_secs: 3");
+        let delays: Vec<_> = (1..=5).map(|attempt| endpoint.backoff(attempt)).collect();
+        assert_eq!(
+            delays,
+            vec![
+                Duration::from_millis(500),
+                Duration::from_millis(1000),
+                Duration::from_millis(2000),
+                Duration::from_secs(3),
+                Duration::from_secs(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_migrate_event_settings() {
+        let webhooks: WebhooksConfig =
+            serde_yaml::from_str("cert_warning_days: 14\nfilter_match_interval_secs: 5").unwrap();
+        let mut events = EventsConfig::default();
+        assert_eq!(
+            webhooks.migrate_event_settings(&mut events),
+            vec!["cert_warning_days", "filter_match_interval_secs"]
+        );
+        assert_eq!(events.cert_warning_days, 14);
+        assert_eq!(events.cert_check_interval_hours, 24);
+        assert_eq!(events.filter_match_interval_secs, 5);
+
+        let mut events = EventsConfig::default();
+        assert!(WebhooksConfig::default()
+            .migrate_event_settings(&mut events)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_payload_shape() {
+        let event = SystemEvent::ConnectionDown {
+            connection_id: "tak-server-main".to_string(),
+            address: "tak.example.com:8089".to_string(),
+            reason: Some("connection reset".to_string()),
+        };
+        assert_eq!(event.kind(), EventKind::ConnectionDown);
+
+        let payload = WebhookPayload {
+            id: Uuid::nil(),
+            timestamp: Utc::now(),
+            source: "omnitak",
+            event: &event,
+        };
+        let json = serde_json::to_value(&payload).unwrap();
+        assert_eq!(json["event"], "connection_down");
+        assert_eq!(json["connection_id"], "tak-server-main");
+        assert_eq!(json["reason"], "connection reset");
+        assert_eq!(json["source"], "omnitak");
+    }
+}
@@ -4,7 +4,7 @@ use std::sync::Arc;
 use std::time::Duration;
 use omnitak_pool::{
     AggregatorConfig, ConnectionPool, DistributorConfig, FilterRule,
-    InboundMessage, MessageAggregator, MessageDistributor, PoolConfig,
+    InboundMessage, MessageAggregator, MessageDistributor, PoolConfig, TraceId,
 };
 
 #[tokio::test(flavor = "multi_thread")]
@@ -57,6 +57,7 @@ async fn test_basic_pool_initialization() {
         source: conn_id.clone(),
         data: cot_message.to_vec(),
         timestamp: std::time::Instant::now(),
+        trace_id: TraceId::new(),
     };
 
     let sender = aggregator.sender();
@@ -122,6 +123,7 @@ async fn test_deduplication() {
             source: conn_id.clone(),
             data: cot_message.to_vec(),
             timestamp: std::time::Instant::now(),
+            trace_id: TraceId::new(),
         })
         .await
         .expect("Failed to send first message");
@@ -132,6 +134,7 @@ async fn test_deduplication() {
             source: conn_id.clone(),
             data: cot_message.to_vec(),
             timestamp: std::time::Instant::now(),
+            trace_id: TraceId::new(),
         })
         .await
         .expect("Failed to send duplicate message");
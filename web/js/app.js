@@ -2,6 +2,7 @@
 // Handles TAK server connections, certificate management, and real-time monitoring
 
 const API_BASE = 'http://localhost:8080/api/v1';
+const WS_BASE = API_BASE.replace(/^http/, 'ws');
 
 // Application State
 const state = {
@@ -19,7 +20,12 @@ const state = {
         clientKey: null,
         caCert: null
     },
-    systemStatus: 'offline'
+    systemStatus: 'offline',
+    token: sessionStorage.getItem('omnitak-token'),
+    // Tracks by UID, kept current from the track stream
+    tracks: new Map(),
+    trackSeq: null,
+    trackSocket: null
 };
 
 // Initialize Application
@@ -27,7 +33,9 @@ document.addEventListener('DOMContentLoaded', () => {
     console.log('OmniTAK Web Interface Loaded');
     initializeEventListeners();
     checkSystemStatus();
-    loadConnections();
+    if (state.token) {
+        signedIn();
+    }
     startStatusPolling();
 });
 
@@ -53,6 +61,61 @@ function initializeEventListeners() {
     // Clear messages button
     const clearMessagesBtn = document.getElementById('clear-messages');
     clearMessagesBtn.addEventListener('click', clearMessages);
+
+    // Sign in form
+    const signInForm = document.getElementById('sign-in-form');
+    signInForm.addEventListener('submit', handleSignIn);
+}
+
+// Authorization header for API requests
+function authHeaders() {
+    return state.token ? { 'Authorization': `Bearer ${state.token}` } : {};
+}
+
+// Handle Sign In
+async function handleSignIn(event) {
+    event.preventDefault();
+
+    try {
+        const response = await fetch(`${API_BASE}/auth/login`, {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json'
+            },
+            body: JSON.stringify({
+                username: document.getElementById('username').value,
+                password: document.getElementById('password').value
+            })
+        });
+
+        if (!response.ok) {
+            throw new Error('Invalid username or password');
+        }
+        const login = await response.json();
+        state.token = login.access_token;
+        sessionStorage.setItem('omnitak-token', state.token);
+        event.target.reset();
+        signedIn();
+    } catch (error) {
+        showToast(`Sign in failed: ${error.message}`, 'error');
+    }
+}
+
+// Start everything that needs credentials
+function signedIn() {
+    document.getElementById('sign-in-card').style.display = 'none';
+    loadConnections();
+    connectTrackStream();
+}
+
+// Forget an expired or revoked token and ask to sign in again
+function signedOut() {
+    state.token = null;
+    sessionStorage.removeItem('omnitak-token');
+    if (state.trackSocket) {
+        state.trackSocket.close();
+    }
+    document.getElementById('sign-in-card').style.display = '';
 }
 
 // Protocol Selection Handler
@@ -146,7 +209,8 @@ async function handleAddConnection(event) {
         const response = await fetch(`${API_BASE}/connections`, {
             method: 'POST',
             headers: {
-                'Content-Type': 'application/json'
+                'Content-Type': 'application/json',
+                ...authHeaders()
             },
             body: JSON.stringify(connectionData)
         });
@@ -203,7 +267,8 @@ async function handleTestConnection() {
         const response = await fetch(`${API_BASE}/test-connection`, {
             method: 'POST',
             headers: {
-                'Content-Type': 'application/json'
+                'Content-Type': 'application/json',
+                ...authHeaders()
             },
             body: JSON.stringify({ address, protocol })
         });
@@ -289,7 +354,8 @@ async function reconnectConnection(connectionId) {
 
     try {
         const response = await fetch(`${API_BASE}/connections/${connectionId}/reconnect`, {
-            method: 'POST'
+            method: 'POST',
+            headers: authHeaders()
         });
 
         if (response.ok) {
@@ -312,7 +378,8 @@ async function removeConnection(connectionId) {
 
     try {
         const response = await fetch(`${API_BASE}/connections/${connectionId}`, {
-            method: 'DELETE'
+            method: 'DELETE',
+            headers: authHeaders()
         });
 
         if (response.ok) {
@@ -335,8 +402,12 @@ async function removeConnection(connectionId) {
 // Load Connections from API
 async function loadConnections() {
     try {
-        const response = await fetch(`${API_BASE}/connections`);
-        if (response.ok) {
+        const response = await fetch(`${API_BASE}/connections`, {
+            headers: authHeaders()
+        });
+        if (response.status === 401) {
+            signedOut();
+        } else if (response.ok) {
             const connections = await response.json();
             state.connections = connections;
             renderConnections();
@@ -386,10 +457,100 @@ function updateStats(stats) {
 function startStatusPolling() {
     setInterval(() => {
         checkSystemStatus();
-        loadConnections();
+        if (state.token) {
+            loadConnections();
+        }
     }, 5000); // Poll every 5 seconds
 }
 
+// Track Stream: a snapshot, then deltas applied in sequence
+function connectTrackStream() {
+    if (state.trackSocket) {
+        return;
+    }
+    const ws = new WebSocket(
+        `${WS_BASE}/tracks/stream?access_token=${encodeURIComponent(state.token)}`
+    );
+    state.trackSocket = ws;
+
+    ws.onmessage = (event) => {
+        try {
+            const data = JSON.parse(event.data);
+
+            if (data.type === 'track_snapshot') {
+                state.tracks = new Map(data.features.map(f => [f.id, f]));
+                state.trackSeq = data.seq;
+                scheduleTrackRender();
+            } else if (data.type === 'track_delta') {
+                // A gap means missed changes; start over from a snapshot
+                if (state.trackSeq === null || data.seq !== state.trackSeq + 1) {
+                    state.trackSeq = null;
+                    ws.send(JSON.stringify({ type: 'resync' }));
+                    return;
+                }
+                for (const feature of [...data.added, ...data.updated]) {
+                    state.tracks.set(feature.id, feature);
+                }
+                for (const uid of data.removed) {
+                    state.tracks.delete(uid);
+                }
+                state.trackSeq = data.seq;
+                scheduleTrackRender();
+            }
+        } catch (error) {
+            console.error('Track stream message error:', error);
+        }
+    };
+
+    ws.onclose = () => {
+        state.trackSocket = null;
+        state.trackSeq = null;
+        if (state.token) {
+            setTimeout(connectTrackStream, 5000);
+        }
+    };
+}
+
+// Render at most once per frame however fast deltas arrive
+let trackRenderPending = false;
+function scheduleTrackRender() {
+    if (!trackRenderPending) {
+        trackRenderPending = true;
+        requestAnimationFrame(() => {
+            trackRenderPending = false;
+            renderTracks();
+        });
+    }
+}
+
+// Render Tracks
+function renderTracks() {
+    const body = document.getElementById('tracks-body');
+    const tracks = [...state.tracks.values()].sort((a, b) =>
+        (a.properties.callsign || a.id).localeCompare(b.properties.callsign || b.id)
+    );
+    document.getElementById('track-count').textContent = tracks.length;
+
+    // Callsigns come from the network, so text is set, never parsed as HTML
+    body.replaceChildren(...tracks.map(track => {
+        const [lon, lat] = track.geometry.coordinates;
+        const speed = track.properties.speed;
+        const row = document.createElement('tr');
+        for (const text of [
+            track.properties.callsign || track.id,
+            track.properties.cot_type,
+            `${lat.toFixed(5)}, ${lon.toFixed(5)}`,
+            speed === undefined ? '' : `${speed.toFixed(1)} m/s`,
+            new Date(track.properties.time).toLocaleTimeString()
+        ]) {
+            const cell = document.createElement('td');
+            cell.textContent = text;
+            row.appendChild(cell);
+        }
+        return row;
+    }));
+}
+
 // Add Message to Log
 function addMessageToLog(message) {
     const messagesLog = document.getElementById('messages-log');